@@ -0,0 +1,103 @@
+//! Permission checks against the seeded dataset. Needs PERF_DATABASE_URL;
+//! see src/perf for the dataset and its size settings.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use template_repo_backend::config::PermissionsConfig;
+
+mod common;
+
+fn has_permission(c: &mut Criterion) {
+    let Some(db) = common::BenchDb::setup() else {
+        return;
+    };
+    let dataset = &db.dataset;
+    let checkers =
+        [("joined", false), ("denormalized", true)].map(|(name, denormalized_grants)| {
+            // No result cache, so every check reaches the database
+            let rebac = db
+                .rebac_service()
+                .with_permissions_config(&PermissionsConfig {
+                    denormalized_grants,
+                    check_cache_capacity: 0,
+                    ..Default::default()
+                });
+            (name, rebac)
+        });
+
+    let mut group = c.benchmark_group("has_permission");
+    for (name, rebac) in &checkers {
+        // A different user and asset each time, all granted through a
+        // scoped role
+        let next = AtomicUsize::new(0);
+        group.bench_function(format!("{}/granted", name), |b| {
+            b.to_async(&db.runtime).iter(|| {
+                let n = next.fetch_add(1, Ordering::Relaxed);
+                let user = n % dataset.users.len();
+                let assets = dataset.assets_of(user);
+                let asset = assets[(n / dataset.users.len()) % assets.len()];
+                async move {
+                    let allowed = rebac
+                        .has_permission(dataset.users[user].id, asset, "perf.read", None)
+                        .await
+                        .unwrap();
+                    assert!(allowed);
+                }
+            })
+        });
+
+        // Assets of the next folder over, which nobody holding this user's
+        // role scope can see
+        let next = AtomicUsize::new(0);
+        group.bench_function(format!("{}/denied", name), |b| {
+            b.to_async(&db.runtime).iter(|| {
+                let n = next.fetch_add(1, Ordering::Relaxed);
+                let user = n % dataset.users.len();
+                let assets = dataset.assets_of(user + 1);
+                let asset = assets[(n / dataset.users.len()) % assets.len()];
+                async move {
+                    rebac
+                        .has_permission(dataset.users[user].id, asset, "perf.update", None)
+                        .await
+                        .unwrap()
+                }
+            })
+        });
+    }
+
+    // The same check repeated, answered from the result cache
+    let cached = &db.rebac_service();
+    let (user, asset) = (dataset.users[0].id, dataset.assets_of(0)[0]);
+    group.bench_function("cached", |b| {
+        b.to_async(&db.runtime).iter(|| async move {
+            cached
+                .has_permission(user, asset, "perf.read", None)
+                .await
+                .unwrap()
+        })
+    });
+    group.finish();
+}
+
+fn get_accessible_entities(c: &mut Criterion) {
+    let Some(db) = common::BenchDb::setup() else {
+        return;
+    };
+    let dataset = &db.dataset;
+    let rebac = &db.rebac_service();
+    let next = AtomicUsize::new(0);
+    c.bench_function("get_accessible_entities", |b| {
+        b.to_async(&db.runtime).iter(|| {
+            let user = dataset.users[next.fetch_add(1, Ordering::Relaxed) % dataset.users.len()].id;
+            async move {
+                rebac
+                    .get_accessible_entities(user, "perf.read")
+                    .await
+                    .unwrap()
+            }
+        })
+    });
+}
+
+criterion_group!(benches, has_permission, get_accessible_entities);
+criterion_main!(benches);
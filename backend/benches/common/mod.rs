@@ -0,0 +1,57 @@
+use sqlx::PgPool;
+use template_repo_backend::features::ontology::OntologyService;
+use template_repo_backend::features::rebac::RebacService;
+use template_repo_backend::features::system::AuditService;
+use template_repo_backend::perf::{self, Dataset, DatasetSize};
+use tokio::runtime::Runtime;
+
+/// A runtime, a pool on PERF_DATABASE_URL and the seeded dataset
+pub struct BenchDb {
+    pub runtime: Runtime,
+    pub pool: PgPool,
+    pub dataset: Dataset,
+}
+
+impl BenchDb {
+    /// None when PERF_DATABASE_URL isn't set, so `cargo bench` without a
+    /// database still runs the benchmarks that don't need one
+    pub fn setup() -> Option<Self> {
+        template_repo_backend::config::init();
+        let Ok(url) = std::env::var(perf::DATABASE_URL_ENV) else {
+            eprintln!(
+                "{} is not set; skipping database benchmarks",
+                perf::DATABASE_URL_ENV
+            );
+            return None;
+        };
+        let size = DatasetSize::from_env().expect("Invalid dataset size");
+        let runtime = Runtime::new().expect("Failed to start the runtime");
+        let (pool, dataset) = runtime.block_on(async {
+            let pool = perf::connect(&url, 20)
+                .await
+                .expect("No benchmark database");
+            let dataset = Dataset::ensure(&pool, &size)
+                .await
+                .expect("Failed to seed the dataset");
+            (pool, dataset)
+        });
+        Some(Self {
+            runtime,
+            pool,
+            dataset,
+        })
+    }
+
+    pub fn ontology_service(&self) -> OntologyService {
+        OntologyService::new(self.pool.clone(), AuditService::new(self.pool.clone()))
+    }
+
+    #[allow(dead_code)]
+    pub fn rebac_service(&self) -> RebacService {
+        RebacService::new(
+            self.pool.clone(),
+            self.ontology_service(),
+            AuditService::new(self.pool.clone()),
+        )
+    }
+}
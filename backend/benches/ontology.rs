@@ -0,0 +1,86 @@
+//! Entity writes and reads against the seeded dataset, including attribute
+//! validation against the PerfAsset class's properties. Needs
+//! PERF_DATABASE_URL; see src/perf.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use template_repo_backend::features::ontology::models::CreateEntityInput;
+use template_repo_backend::features::ontology::service::OntologyError;
+use uuid::Uuid;
+
+mod common;
+
+fn entities(c: &mut Criterion) {
+    let Some(db) = common::BenchDb::setup() else {
+        return;
+    };
+    let dataset = &db.dataset;
+    let ontology = &db.ontology_service();
+    // Entities created here are soft-deleted afterwards
+    let run = Uuid::new_v4().to_string();
+    let run = run.as_str();
+    let input = |n: usize, attributes: serde_json::Value| CreateEntityInput {
+        class_id: dataset.asset_class_id,
+        display_name: format!("bench-{}-{}", run, n),
+        parent_entity_id: Some(dataset.folders[n % dataset.folders.len()]),
+        attributes: Some(attributes),
+    };
+
+    let mut group = c.benchmark_group("create_entity");
+    let next = AtomicUsize::new(0);
+    group.bench_function("valid", |b| {
+        b.to_async(&db.runtime).iter(|| {
+            let n = next.fetch_add(1, Ordering::Relaxed);
+            let input = input(
+                n,
+                serde_json::json!({
+                    "serial": format!("{:012X}", n),
+                    "weight_kg": n % 1000,
+                    "status": "active",
+                    "bench_run": run,
+                }),
+            );
+            async move { ontology.create_entity(input, None, None).await.unwrap() }
+        })
+    });
+    // Rejected by the status options rule
+    group.bench_function("invalid", |b| {
+        b.to_async(&db.runtime).iter(|| {
+            let input = input(
+                0,
+                serde_json::json!({
+                    "serial": "000000000000",
+                    "weight_kg": 10,
+                    "status": "lost",
+                }),
+            );
+            async move {
+                let result = ontology.create_entity(input, None, None).await;
+                assert!(matches!(result, Err(OntologyError::InvalidInput(_))));
+            }
+        })
+    });
+    group.finish();
+
+    let next = AtomicUsize::new(0);
+    c.bench_function("get_entity", |b| {
+        b.to_async(&db.runtime).iter(|| {
+            let id = dataset.assets[next.fetch_add(1, Ordering::Relaxed) % dataset.assets.len()];
+            async move { ontology.get_entity(id).await.unwrap() }
+        })
+    });
+
+    db.runtime.block_on(async {
+        sqlx::query(
+            "UPDATE entities SET deleted_at = NOW() WHERE class_id = $1 AND attributes->>'bench_run' = $2",
+        )
+        .bind(dataset.asset_class_id)
+        .bind(run)
+        .execute(&db.pool)
+        .await
+        .expect("Failed to remove benchmark entities");
+    });
+}
+
+criterion_group!(benches, entities);
+criterion_main!(benches);
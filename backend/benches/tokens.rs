@@ -0,0 +1,78 @@
+//! Signing and verifying access and refresh tokens with the configured
+//! RS256 keys. Runs from the backend directory so config/ and keys/ are
+//! found; no database needed.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use template_repo_backend::config::Config;
+use template_repo_backend::features::auth::jwt::{
+    create_jwt, create_refresh_token, validate_jwt, UserRoleClaim,
+};
+use template_repo_backend::utils::jwt_keys;
+
+/// The configuration the server would run with, keys loaded the same way
+fn config() -> Config {
+    template_repo_backend::config::init();
+    let mut config = Config::from_env().expect("Failed to load config");
+    if config.jwt_private_key.trim().is_empty() || config.jwt_public_key.trim().is_empty() {
+        let (private_pem, public_pem) = jwt_keys::load_keys(&config).expect("No JWT keys in keys/");
+        config.jwt_private_key = private_pem;
+        config.jwt_public_key = public_pem;
+    }
+    config
+}
+
+/// Claims the size of a user with a few scoped roles
+fn claims() -> (Vec<UserRoleClaim>, Vec<String>) {
+    let roles = (0..4)
+        .map(|n| UserRoleClaim {
+            role_name: format!("role_{}", n),
+            resource_id: Some(uuid::Uuid::new_v4().to_string()),
+        })
+        .collect();
+    let permissions = ["read", "update", "create", "delete", "export"]
+        .iter()
+        .map(|p| p.to_string())
+        .collect();
+    (roles, permissions)
+}
+
+fn tokens(c: &mut Criterion) {
+    let config = config();
+    let (roles, permissions) = claims();
+    let user_id = uuid::Uuid::new_v4().to_string();
+    let issue = || {
+        create_jwt(
+            &user_id,
+            "bench.user",
+            "bench.user@perf.example",
+            roles.clone(),
+            permissions.clone(),
+            &config,
+        )
+        .unwrap()
+    };
+
+    let mut group = c.benchmark_group("jwt");
+    group.bench_function("issue_access_token", |b| b.iter(issue));
+    group.bench_function("issue_refresh_token", |b| {
+        b.iter(|| {
+            create_refresh_token(
+                &user_id,
+                "bench.user",
+                "bench.user@perf.example",
+                roles.clone(),
+                permissions.clone(),
+                &config,
+            )
+            .unwrap()
+        })
+    });
+    let token = issue();
+    group.bench_function("validate_access_token", |b| {
+        b.iter(|| validate_jwt(&token, &config).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, tokens);
+criterion_main!(benches);
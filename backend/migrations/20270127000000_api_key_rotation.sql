@@ -0,0 +1,21 @@
+-- Migration: API Key Rotation and Expiry
+-- Description: Tracks key ownership, rotation lineage and expiry notifications
+
+ALTER TABLE api_keys
+    ADD COLUMN IF NOT EXISTS created_by UUID REFERENCES entities(id) ON DELETE SET NULL,
+    ADD COLUMN IF NOT EXISTS rotated_from_id UUID REFERENCES api_keys(id) ON DELETE SET NULL,
+    ADD COLUMN IF NOT EXISTS rotated_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS expiry_notified_at TIMESTAMPTZ;
+
+-- Keys created before expiry was enforced get the default 90 day lifetime
+UPDATE api_keys
+SET expires_at = created_at + INTERVAL '90 days'
+WHERE expires_at IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_api_keys_prefix ON api_keys(prefix);
+CREATE INDEX IF NOT EXISTS idx_api_keys_expiring ON api_keys(expires_at)
+    WHERE status = 'active' AND expiry_notified_at IS NULL;
+
+COMMENT ON COLUMN api_keys.rotated_from_id IS 'Key that this key replaced during rotation';
+COMMENT ON COLUMN api_keys.rotated_at IS 'When this key was superseded; it stays valid until expires_at (grace window)';
+COMMENT ON COLUMN api_keys.expiry_notified_at IS 'When the owner was notified about the upcoming expiry';
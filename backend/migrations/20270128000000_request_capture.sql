@@ -0,0 +1,37 @@
+-- Migration: Request Capture
+-- Description: Opt-in capture of sanitized request/response pairs for debugging and replay
+
+CREATE TABLE IF NOT EXISTS capture_rules (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    api_key_id UUID REFERENCES api_keys(id) ON DELETE CASCADE,
+    route_prefix TEXT,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_by UUID REFERENCES entities(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT capture_rule_target CHECK (api_key_id IS NOT NULL OR route_prefix IS NOT NULL),
+    CONSTRAINT capture_rule_expiry CHECK (expires_at > created_at)
+);
+
+CREATE TABLE IF NOT EXISTS captured_requests (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    rule_id UUID REFERENCES capture_rules(id) ON DELETE SET NULL,
+    method TEXT NOT NULL,
+    path TEXT NOT NULL,
+    query TEXT,
+    request_headers JSONB NOT NULL DEFAULT '{}',
+    request_body TEXT,
+    response_status INTEGER NOT NULL,
+    response_headers JSONB NOT NULL DEFAULT '{}',
+    response_body TEXT,
+    duration_ms BIGINT NOT NULL,
+    captured_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_capture_rules_expires ON capture_rules(expires_at);
+CREATE INDEX IF NOT EXISTS idx_captured_requests_rule ON captured_requests(rule_id, captured_at DESC);
+CREATE INDEX IF NOT EXISTS idx_captured_requests_expires ON captured_requests(expires_at);
+
+COMMENT ON TABLE capture_rules IS 'Time-boxed opt-in rules selecting which requests are captured (by API key and/or route prefix)';
+COMMENT ON TABLE captured_requests IS 'Sanitized request/response pairs; credentials are stripped before storage and rows are purged after expires_at';
@@ -0,0 +1,21 @@
+-- Migration: Entity updated_at maintenance
+-- Description: Bump entities.updated_at on every change so it can back ETags / conditional GETs
+
+CREATE OR REPLACE FUNCTION touch_entity_updated_at()
+RETURNS TRIGGER AS $$
+BEGIN
+    -- Respect an explicit value from the caller; otherwise bump on any real change
+    IF NEW.updated_at IS NOT DISTINCT FROM OLD.updated_at AND NEW IS DISTINCT FROM OLD THEN
+        NEW.updated_at := NOW();
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trigger_touch_entity_updated_at ON entities;
+CREATE TRIGGER trigger_touch_entity_updated_at
+    BEFORE UPDATE ON entities
+    FOR EACH ROW
+    EXECUTE FUNCTION touch_entity_updated_at();
+
+COMMENT ON FUNCTION touch_entity_updated_at() IS 'Keeps entities.updated_at current for updates that do not set it explicitly';
@@ -0,0 +1,26 @@
+-- Migration: IP Access Rules
+-- Description: CIDR allow/deny rules evaluated before authentication, scoped globally, per tenant or per API key
+
+CREATE TABLE IF NOT EXISTS ip_access_rules (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    cidr TEXT NOT NULL,
+    action VARCHAR(10) NOT NULL CHECK (action IN ('allow', 'deny')),
+    scope VARCHAR(20) NOT NULL CHECK (scope IN ('global', 'tenant', 'api_key')),
+    tenant_id UUID,
+    api_key_id UUID REFERENCES api_keys(id) ON DELETE CASCADE,
+    route_prefix TEXT,
+    description TEXT,
+    created_by UUID REFERENCES entities(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT ip_access_rule_scope CHECK (
+        (scope = 'global' AND tenant_id IS NULL AND api_key_id IS NULL)
+        OR (scope = 'tenant' AND tenant_id IS NOT NULL AND api_key_id IS NULL)
+        OR (scope = 'api_key' AND api_key_id IS NOT NULL AND tenant_id IS NULL)
+    )
+);
+
+CREATE INDEX IF NOT EXISTS idx_ip_access_rules_tenant ON ip_access_rules(tenant_id);
+CREATE INDEX IF NOT EXISTS idx_ip_access_rules_api_key ON ip_access_rules(api_key_id);
+
+COMMENT ON TABLE ip_access_rules IS 'IP allow/deny rules; a matching deny blocks, and once allow rules apply to a scope the client must match one of them';
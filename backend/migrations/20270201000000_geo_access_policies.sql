@@ -0,0 +1,20 @@
+-- Migration: Geo Access Policies
+-- Description: Per-tenant country restrictions for logins and API calls (export-control compliance)
+
+CREATE TABLE IF NOT EXISTS geo_access_policies (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id UUID NOT NULL UNIQUE,
+    mode VARCHAR(10) NOT NULL CHECK (mode IN ('allow', 'deny')),
+    -- ISO 3166-1 alpha-2 codes, upper case
+    countries TEXT[] NOT NULL DEFAULT '{}',
+    restrict_login BOOLEAN NOT NULL DEFAULT TRUE,
+    restrict_api BOOLEAN NOT NULL DEFAULT TRUE,
+    -- Whether clients whose country cannot be determined are blocked
+    block_unknown BOOLEAN NOT NULL DEFAULT FALSE,
+    description TEXT,
+    created_by UUID REFERENCES entities(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+COMMENT ON TABLE geo_access_policies IS 'Per-tenant country allow/deny lists; active firefighter sessions bypass them and every decision is recorded in security_events';
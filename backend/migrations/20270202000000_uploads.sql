@@ -0,0 +1,23 @@
+-- Migration: Uploads
+-- Description: Files streamed to the storage backend, either in one multipart request or resumed across chunked requests
+
+CREATE TABLE IF NOT EXISTS uploads (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    owner_id UUID NOT NULL REFERENCES entities(id) ON DELETE CASCADE,
+    purpose VARCHAR(20) NOT NULL CHECK (purpose IN ('attachment', 'avatar', 'import')),
+    filename TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    size_bytes BIGINT NOT NULL CHECK (size_bytes >= 0),
+    received_bytes BIGINT NOT NULL DEFAULT 0 CHECK (received_bytes >= 0 AND received_bytes <= size_bytes),
+    -- Hex SHA-256; the client's expected value until completion, then the verified digest
+    sha256 VARCHAR(64),
+    status VARCHAR(10) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'complete')),
+    storage_key TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_uploads_owner ON uploads(owner_id);
+CREATE INDEX IF NOT EXISTS idx_uploads_pending ON uploads(created_at) WHERE status = 'pending';
+
+COMMENT ON TABLE uploads IS 'Uploaded files; pending rows are resumable sessions that expire after uploads.session_ttl_hours';
@@ -0,0 +1,28 @@
+-- Migration: Entity Embeddings
+-- Description: pgvector embeddings of entity names/attributes for semantic search, maintained by a background indexer
+
+-- pgvector ships with the database image; without it semantic search stays disabled (development mode)
+DO $$
+BEGIN
+    IF EXISTS (
+        SELECT 1 FROM pg_available_extensions WHERE name = 'vector'
+    ) THEN
+        CREATE EXTENSION IF NOT EXISTS vector;
+
+        CREATE TABLE IF NOT EXISTS entity_embeddings (
+            entity_id UUID PRIMARY KEY REFERENCES entities(id) ON DELETE CASCADE,
+            -- Embedding model; vectors from different models are never compared
+            model VARCHAR(255) NOT NULL,
+            -- Unconstrained dimensions so the model can change without a migration
+            embedding vector NOT NULL,
+            updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_entity_embeddings_model ON entity_embeddings(model);
+
+        COMMENT ON TABLE entity_embeddings IS 'Entity embeddings for /ai/semantic-search; rows older than entities.updated_at are re-indexed';
+        RAISE NOTICE 'vector extension enabled';
+    ELSE
+        RAISE NOTICE 'vector extension not available - semantic search disabled (development mode)';
+    END IF;
+END $$;
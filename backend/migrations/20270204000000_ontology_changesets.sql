@@ -0,0 +1,23 @@
+-- Migration: Ontology Changesets
+-- Description: Proposed schema changes (e.g. AI modeling suggestions) held for review before being applied to a DRAFT version
+
+CREATE TABLE IF NOT EXISTS ontology_changesets (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    -- Where the proposal came from, e.g. 'ai'
+    source VARCHAR(20) NOT NULL,
+    status VARCHAR(10) NOT NULL DEFAULT 'draft' CHECK (status IN ('draft', 'applied', 'discarded')),
+    title TEXT NOT NULL,
+    rationale TEXT,
+    -- {"classes": [...], "relationship_types": [...]}
+    changes JSONB NOT NULL,
+    applied_version_id UUID REFERENCES ontology_versions(id) ON DELETE SET NULL,
+    created_by UUID REFERENCES entities(id) ON DELETE SET NULL,
+    applied_by UUID REFERENCES entities(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    applied_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_ontology_changesets_status ON ontology_changesets(status, created_at DESC);
+
+COMMENT ON TABLE ontology_changesets IS 'Reviewable schema changesets; only drafts can be edited or applied, and applying targets a DRAFT ontology version';
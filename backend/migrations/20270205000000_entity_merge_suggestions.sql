@@ -0,0 +1,28 @@
+-- Migration: Entity Merge Suggestions
+-- Description: Likely duplicate entities found by the background detection job, queued for human review
+
+CREATE TABLE IF NOT EXISTS entity_merge_suggestions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    class_id UUID NOT NULL REFERENCES classes(id) ON DELETE CASCADE,
+    -- The entity that is kept; the duplicate is folded into it on approval
+    primary_entity_id UUID NOT NULL REFERENCES entities(id) ON DELETE CASCADE,
+    duplicate_entity_id UUID NOT NULL REFERENCES entities(id) ON DELETE CASCADE,
+    -- 0-1, higher is more likely a duplicate
+    score DOUBLE PRECISION NOT NULL,
+    -- e.g. ["name similarity 0.94", "3 of 4 shared attributes match"]
+    reasons JSONB NOT NULL DEFAULT '[]',
+    status VARCHAR(10) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'merged', 'rejected')),
+    reviewed_by UUID REFERENCES entities(id) ON DELETE SET NULL,
+    reviewed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (primary_entity_id <> duplicate_entity_id)
+);
+
+-- One suggestion per pair regardless of direction, so rejected pairs are never suggested again
+CREATE UNIQUE INDEX IF NOT EXISTS idx_entity_merge_suggestions_pair ON entity_merge_suggestions(
+    LEAST(primary_entity_id, duplicate_entity_id),
+    GREATEST(primary_entity_id, duplicate_entity_id)
+);
+CREATE INDEX IF NOT EXISTS idx_entity_merge_suggestions_status ON entity_merge_suggestions(status, score DESC);
+
+COMMENT ON TABLE entity_merge_suggestions IS 'Duplicate entity candidates; approving one re-points the duplicate''s relationships and children to the primary and soft-deletes the duplicate';
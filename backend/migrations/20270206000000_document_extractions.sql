@@ -0,0 +1,25 @@
+-- Migration: Document Extractions
+-- Description: Entities and relationships the AI found in an uploaded document, held until the user accepts them into the graph
+
+ALTER TABLE uploads DROP CONSTRAINT IF EXISTS uploads_purpose_check;
+ALTER TABLE uploads ADD CONSTRAINT uploads_purpose_check
+    CHECK (purpose IN ('attachment', 'avatar', 'import', 'extraction'));
+
+CREATE TABLE IF NOT EXISTS document_extractions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    upload_id UUID REFERENCES uploads(id) ON DELETE SET NULL,
+    status VARCHAR(10) NOT NULL DEFAULT 'proposed' CHECK (status IN ('proposed', 'accepted', 'discarded')),
+    -- {"entities": [...], "relationships": [...]} mapped onto existing classes
+    proposal JSONB NOT NULL,
+    -- Proposed items that were dropped, e.g. unknown classes
+    warnings JSONB NOT NULL DEFAULT '[]',
+    -- Entities created on accept, all PENDING approval
+    created_entity_ids UUID[] NOT NULL DEFAULT '{}',
+    created_by UUID NOT NULL REFERENCES entities(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    reviewed_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_document_extractions_created_by ON document_extractions(created_by, created_at DESC);
+
+COMMENT ON TABLE document_extractions IS 'AI extraction proposals from uploaded documents; accepting one creates its entities as PENDING for approval';
@@ -0,0 +1,19 @@
+-- Migration: AI Provider Fallback
+-- Description: Priority and API key settings for AiProvider entities. Providers are tried in priority order, a tenant's own providers first.
+
+DO $$
+DECLARE
+    v_system_version_id UUID;
+    v_ai_provider_class_id UUID;
+BEGIN
+    SELECT id INTO v_system_version_id FROM ontology_versions WHERE is_system = TRUE LIMIT 1;
+    SELECT id INTO v_ai_provider_class_id FROM classes WHERE name = 'AiProvider' AND version_id = v_system_version_id LIMIT 1;
+
+    IF v_ai_provider_class_id IS NOT NULL THEN
+        INSERT INTO properties (name, description, class_id, data_type, is_required, version_id) VALUES
+            ('priority', 'Order in which providers are tried, lowest first', v_ai_provider_class_id, 'integer', FALSE, v_system_version_id),
+            ('api_key_env', 'Name of the environment variable holding the API key', v_ai_provider_class_id, 'string', FALSE, v_system_version_id),
+            ('status', 'Result of the last health check (Healthy, Unhealthy)', v_ai_provider_class_id, 'string', FALSE, v_system_version_id)
+        ON CONFLICT (name, class_id) DO NOTHING;
+    END IF;
+END $$;
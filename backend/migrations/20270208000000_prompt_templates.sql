@@ -0,0 +1,31 @@
+-- Migration: Prompt Templates
+-- Description: Named, versioned prompt templates with {{variable}} placeholders, so AI prompts can be tuned without redeploying
+
+CREATE TABLE IF NOT EXISTS prompt_templates (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(100) NOT NULL UNIQUE,
+    description TEXT,
+    -- Built-in templates are used by backend features and can't be deleted
+    is_builtin BOOLEAN NOT NULL DEFAULT FALSE,
+    active_version INT NOT NULL DEFAULT 1,
+    created_by UUID REFERENCES entities(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS prompt_template_versions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    template_id UUID NOT NULL REFERENCES prompt_templates(id) ON DELETE CASCADE,
+    version INT NOT NULL,
+    body TEXT NOT NULL,
+    -- [{"name": "...", "description": "..."}]; every placeholder in body must be declared
+    variables JSONB NOT NULL DEFAULT '[]',
+    -- Why this version was made
+    note TEXT,
+    created_by UUID REFERENCES entities(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (template_id, version)
+);
+
+COMMENT ON TABLE prompt_templates IS 'Named AI prompt templates; active_version selects the prompt_template_versions row in use';
+COMMENT ON TABLE prompt_template_versions IS 'Immutable history of prompt template bodies; activating an older version rolls back';
@@ -0,0 +1,30 @@
+-- Migration: AI Conversations
+-- Description: Stored AI chat sessions and their messages, per user and optionally per project
+
+CREATE TABLE IF NOT EXISTS ai_conversations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES entities(id) ON DELETE CASCADE,
+    -- Project entity the chat is about, added to the model's context
+    project_id UUID REFERENCES entities(id) ON DELETE SET NULL,
+    title VARCHAR(200) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- Bumped by every message; retention counts from here
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_ai_conversations_user ON ai_conversations(user_id, updated_at DESC);
+CREATE INDEX IF NOT EXISTS idx_ai_conversations_updated ON ai_conversations(updated_at);
+
+CREATE TABLE IF NOT EXISTS ai_messages (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    conversation_id UUID NOT NULL REFERENCES ai_conversations(id) ON DELETE CASCADE,
+    role VARCHAR(10) NOT NULL CHECK (role IN ('user', 'assistant')),
+    content TEXT NOT NULL,
+    -- clock_timestamp keeps a question and its reply, saved in one transaction, in order
+    created_at TIMESTAMPTZ NOT NULL DEFAULT clock_timestamp()
+);
+
+CREATE INDEX IF NOT EXISTS idx_ai_messages_conversation ON ai_messages(conversation_id, created_at);
+
+COMMENT ON TABLE ai_conversations IS 'AI chat sessions; deleted after the configured retention once idle';
+COMMENT ON TABLE ai_messages IS 'Messages of an AI chat session, in order';
@@ -0,0 +1,27 @@
+-- Migration: AI Policy Drafts
+-- Description: Cron schedules on ABAC policies, and AI-drafted policies held until an admin approves them
+
+-- Policies with a schedule only apply while it is active (same semantics as scoped role schedules)
+ALTER TABLE policies ADD COLUMN IF NOT EXISTS schedule_cron TEXT;
+
+CREATE TABLE IF NOT EXISTS ai_policy_drafts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    -- The natural-language access requirement the draft was made from
+    requirement TEXT NOT NULL,
+    status VARCHAR(10) NOT NULL DEFAULT 'draft' CHECK (status IN ('draft', 'approved', 'rejected')),
+    -- Validated policy in the PolicyService create format, plus the target class name
+    proposal JSONB NOT NULL,
+    -- Things the reviewer should check, e.g. attributes the engine never sets
+    warnings JSONB NOT NULL DEFAULT '[]',
+    -- Policy created on approval
+    policy_id UUID REFERENCES policies(id) ON DELETE SET NULL,
+    created_by UUID NOT NULL REFERENCES entities(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    reviewed_by UUID REFERENCES entities(id) ON DELETE SET NULL,
+    reviewed_at TIMESTAMPTZ,
+    review_note TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_ai_policy_drafts_status ON ai_policy_drafts(status, created_at DESC);
+
+COMMENT ON TABLE ai_policy_drafts IS 'ABAC policies drafted by the AI from natural-language requirements; nothing is enforced until an admin approves the draft';
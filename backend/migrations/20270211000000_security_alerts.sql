@@ -0,0 +1,44 @@
+-- Migration: Security Alerts
+-- Description: A record of every alert raised by an alert rule, with the events that triggered it and an optional AI explanation
+
+CREATE TABLE IF NOT EXISTS security_alerts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    -- alert_rules row (or the AlertRule entity it was ported to)
+    rule_id UUID NOT NULL,
+    rule_name VARCHAR(100) NOT NULL,
+    event_count BIGINT NOT NULL,
+    -- Correlated security_events, most recent first
+    event_ids UUID[] NOT NULL DEFAULT '{}',
+    -- Message sent to the alert channel
+    message TEXT NOT NULL,
+    raised_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- Machine-generated: {"machine_generated": true, "notice", "summary", "next_steps", "generated_at"}
+    ai_explanation JSONB,
+    -- Why the explanation could not be generated, if it failed
+    ai_error TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_security_alerts_raised ON security_alerts(raised_at DESC);
+CREATE INDEX IF NOT EXISTS idx_security_alerts_rule ON security_alerts(rule_id, raised_at DESC);
+
+COMMENT ON TABLE security_alerts IS 'Alerts raised by alert rules; ai_explanation is machine-generated and must be verified before acting on it';
+
+-- Function: alert_rule_event_ids
+-- The events a rule currently counts, using the same criteria as check_alert_rules()
+CREATE OR REPLACE FUNCTION alert_rule_event_ids(p_rule_id UUID, p_limit INTEGER DEFAULT 50)
+RETURNS UUID[] AS $$
+    SELECT COALESCE(array_agg(id), '{}') FROM (
+        SELECT se.id
+        FROM alert_rules ar
+        JOIN security_events se ON
+            (ar.event_type IS NULL OR se.event_type = ar.event_type)
+            AND se.detected_at > NOW() - (ar.threshold_window_minutes || ' minutes')::INTERVAL
+            AND (ar.min_severity IS NULL OR
+                 (ar.min_severity = 'info') OR
+                 (ar.min_severity = 'warning' AND se.severity IN ('warning', 'critical')) OR
+                 (ar.min_severity = 'critical' AND se.severity = 'critical'))
+        WHERE ar.id = p_rule_id
+        ORDER BY se.detected_at DESC
+        LIMIT p_limit
+    ) events;
+$$ LANGUAGE sql STABLE;
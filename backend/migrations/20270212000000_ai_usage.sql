@@ -0,0 +1,49 @@
+-- Migration: AI Usage Accounting
+-- Description: Token counts and cost per AI call, optional monthly budgets per tenant, and per-1000-token prices on AiProvider entities.
+
+CREATE TABLE IF NOT EXISTS ai_usage (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID REFERENCES entities(id) ON DELETE SET NULL,
+    tenant_id UUID,
+    provider_id UUID,
+    provider_name TEXT NOT NULL,
+    model TEXT NOT NULL,
+    operation TEXT NOT NULL CHECK (operation IN ('generate', 'stream', 'embed')),
+    prompt_tokens BIGINT NOT NULL DEFAULT 0,
+    completion_tokens BIGINT NOT NULL DEFAULT 0,
+    estimated BOOLEAN NOT NULL DEFAULT FALSE,
+    cost DOUBLE PRECISION NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_ai_usage_tenant ON ai_usage(tenant_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_ai_usage_user ON ai_usage(user_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_ai_usage_created ON ai_usage(created_at);
+
+COMMENT ON TABLE ai_usage IS 'Tokens and cost of every AI provider call, estimated from text length when the provider reports none';
+
+CREATE TABLE IF NOT EXISTS ai_budgets (
+    tenant_id UUID PRIMARY KEY,
+    monthly_token_limit BIGINT CHECK (monthly_token_limit >= 0),
+    monthly_cost_limit DOUBLE PRECISION CHECK (monthly_cost_limit >= 0),
+    updated_by UUID REFERENCES entities(id) ON DELETE SET NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+COMMENT ON TABLE ai_budgets IS 'Optional monthly AI token and cost limits per tenant; calls are refused once a limit is reached';
+
+DO $$
+DECLARE
+    v_system_version_id UUID;
+    v_ai_provider_class_id UUID;
+BEGIN
+    SELECT id INTO v_system_version_id FROM ontology_versions WHERE is_system = TRUE LIMIT 1;
+    SELECT id INTO v_ai_provider_class_id FROM classes WHERE name = 'AiProvider' AND version_id = v_system_version_id LIMIT 1;
+
+    IF v_ai_provider_class_id IS NOT NULL THEN
+        INSERT INTO properties (name, description, class_id, data_type, is_required, version_id) VALUES
+            ('input_cost_per_1k', 'Price in USD per 1000 prompt tokens', v_ai_provider_class_id, 'float', FALSE, v_system_version_id),
+            ('output_cost_per_1k', 'Price in USD per 1000 completion tokens', v_ai_provider_class_id, 'float', FALSE, v_system_version_id)
+        ON CONFLICT (name, class_id) DO NOTHING;
+    END IF;
+END $$;
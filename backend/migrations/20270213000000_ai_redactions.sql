@@ -0,0 +1,19 @@
+-- Migration: AI Redaction Audit
+-- Description: What was pseudonymized from each prompt sent to an external AI provider. Only kinds, counts and property names are kept, never the values.
+
+CREATE TABLE IF NOT EXISTS ai_redactions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID REFERENCES entities(id) ON DELETE SET NULL,
+    tenant_id UUID,
+    provider_id UUID,
+    provider_name TEXT NOT NULL,
+    operation TEXT NOT NULL CHECK (operation IN ('generate', 'stream', 'embed')),
+    counts JSONB NOT NULL DEFAULT '{}',
+    fields TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_ai_redactions_created ON ai_redactions(created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_ai_redactions_user ON ai_redactions(user_id, created_at);
+
+COMMENT ON TABLE ai_redactions IS 'Audit of personal data replaced with placeholders before AI provider calls; values are never stored';
@@ -0,0 +1,99 @@
+-- Migration: Ontology Change Events
+-- Description: Change feed of entities, classes and properties, consumed by the background index worker, and the worker's progress.
+
+CREATE TABLE IF NOT EXISTS ontology_change_events (
+    seq BIGSERIAL PRIMARY KEY,
+    object_type TEXT NOT NULL CHECK (object_type IN ('entity', 'class', 'property')),
+    object_id UUID NOT NULL,
+    -- The entity's class, the class itself, or the property's class
+    class_id UUID,
+    operation TEXT NOT NULL CHECK (operation IN ('INSERT', 'UPDATE', 'DELETE')),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_ontology_change_events_created ON ontology_change_events(created_at);
+
+COMMENT ON TABLE ontology_change_events IS 'Change feed for background indexers; consumers wake on NOTIFY ontology_changes and track their position by seq';
+
+CREATE OR REPLACE FUNCTION record_ontology_change() RETURNS TRIGGER AS $$
+DECLARE
+    v_row RECORD;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        v_row := OLD;
+    ELSE
+        v_row := NEW;
+    END IF;
+
+    IF TG_TABLE_NAME = 'entities' THEN
+        -- Only changes to what gets indexed, not bookkeeping columns
+        IF TG_OP = 'UPDATE'
+           AND NEW.display_name IS NOT DISTINCT FROM OLD.display_name
+           AND NEW.attributes IS NOT DISTINCT FROM OLD.attributes
+           AND NEW.class_id IS NOT DISTINCT FROM OLD.class_id
+           AND NEW.deleted_at IS NOT DISTINCT FROM OLD.deleted_at THEN
+            RETURN NULL;
+        END IF;
+        INSERT INTO ontology_change_events (object_type, object_id, class_id, operation)
+        VALUES ('entity', v_row.id, v_row.class_id, TG_OP);
+    ELSIF TG_TABLE_NAME = 'classes' THEN
+        IF TG_OP = 'UPDATE' AND NEW.name IS NOT DISTINCT FROM OLD.name THEN
+            RETURN NULL;
+        END IF;
+        INSERT INTO ontology_change_events (object_type, object_id, class_id, operation)
+        VALUES ('class', v_row.id, v_row.id, TG_OP);
+    ELSE
+        IF TG_OP = 'UPDATE'
+           AND NEW.name IS NOT DISTINCT FROM OLD.name
+           AND NEW.is_sensitive IS NOT DISTINCT FROM OLD.is_sensitive THEN
+            RETURN NULL;
+        END IF;
+        INSERT INTO ontology_change_events (object_type, object_id, class_id, operation)
+        VALUES ('property', v_row.id, v_row.class_id, TG_OP);
+    END IF;
+
+    -- Identical payloads are delivered once per transaction, so bulk changes wake consumers once
+    PERFORM pg_notify('ontology_changes', '');
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_entities_change_events ON entities;
+CREATE TRIGGER trg_entities_change_events
+    AFTER INSERT OR UPDATE OR DELETE ON entities
+    FOR EACH ROW EXECUTE FUNCTION record_ontology_change();
+
+DROP TRIGGER IF EXISTS trg_classes_change_events ON classes;
+CREATE TRIGGER trg_classes_change_events
+    AFTER INSERT OR UPDATE OR DELETE ON classes
+    FOR EACH ROW EXECUTE FUNCTION record_ontology_change();
+
+DROP TRIGGER IF EXISTS trg_properties_change_events ON properties;
+CREATE TRIGGER trg_properties_change_events
+    AFTER INSERT OR UPDATE OR DELETE ON properties
+    FOR EACH ROW EXECUTE FUNCTION record_ontology_change();
+
+CREATE TABLE IF NOT EXISTS ai_index_state (
+    worker TEXT PRIMARY KEY,
+    -- Last change event handled
+    last_seq BIGINT NOT NULL DEFAULT 0,
+    events_processed BIGINT NOT NULL DEFAULT 0,
+    entities_indexed BIGINT NOT NULL DEFAULT 0,
+    -- False when the index can't be built here, e.g. without pgvector
+    available BOOLEAN NOT NULL DEFAULT TRUE,
+    heartbeat_at TIMESTAMPTZ,
+    last_indexed_at TIMESTAMPTZ,
+    last_error TEXT,
+    last_error_at TIMESTAMPTZ,
+    backfill_started_at TIMESTAMPTZ,
+    backfill_total BIGINT,
+    backfill_done BIGINT NOT NULL DEFAULT 0,
+    backfill_finished_at TIMESTAMPTZ
+);
+
+COMMENT ON TABLE ai_index_state IS 'Progress and health of background index workers, shown in /api/system/info';
+
+-- Start from the current end of the feed; existing entities are picked up by the catch-up pass
+INSERT INTO ai_index_state (worker, last_seq)
+SELECT 'entity_embeddings', COALESCE(MAX(seq), 0) FROM ontology_change_events
+ON CONFLICT (worker) DO NOTHING;
@@ -0,0 +1,17 @@
+-- Migration: Service Capabilities
+-- Description: Capabilities declared by services registered through discovery (webhook consumer, authz cache, sync agent), stored on their Service entities.
+
+DO $$
+DECLARE
+    v_system_version_id UUID;
+    v_service_class_id UUID;
+BEGIN
+    SELECT id INTO v_system_version_id FROM ontology_versions WHERE is_system = TRUE LIMIT 1;
+    SELECT id INTO v_service_class_id FROM classes WHERE name = 'Service' AND version_id = v_system_version_id LIMIT 1;
+
+    IF v_service_class_id IS NOT NULL THEN
+        INSERT INTO properties (name, description, class_id, data_type, is_required, version_id) VALUES
+            ('capabilities', 'Declared capabilities: kind (webhook_consumer, authz_cache, sync_agent, other), scope and description', v_service_class_id, 'json', FALSE, v_system_version_id)
+        ON CONFLICT (name, class_id) DO NOTHING;
+    END IF;
+END $$;
@@ -0,0 +1,32 @@
+-- Migration: Customizable Navigation
+-- Description: Admin-defined menu trees per tenant and/or role, and per-user pinned and hidden items. ABAC filtering still applies to every item.
+
+CREATE TABLE IF NOT EXISTS navigation_menus (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id UUID,
+    role_name TEXT,
+    -- Breaks ties when a user holds several roles with their own menu
+    priority INT NOT NULL DEFAULT 0,
+    sections JSONB NOT NULL,
+    updated_by UUID REFERENCES entities(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_navigation_menus_scope ON navigation_menus (
+    (COALESCE(tenant_id, '00000000-0000-0000-0000-000000000000'::uuid)),
+    (COALESCE(role_name, ''))
+);
+
+COMMENT ON TABLE navigation_menus IS 'Menu trees replacing the built-in navigation; the most specific match (tenant, then role) wins';
+
+CREATE TABLE IF NOT EXISTS navigation_preferences (
+    user_id UUID NOT NULL REFERENCES entities(id) ON DELETE CASCADE,
+    item_id TEXT NOT NULL,
+    pinned BOOLEAN NOT NULL DEFAULT FALSE,
+    hidden BOOLEAN NOT NULL DEFAULT FALSE,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, item_id)
+);
+
+COMMENT ON TABLE navigation_preferences IS 'Navigation items a user pinned to the top of their section or hid';
@@ -0,0 +1,24 @@
+-- Migration: Fix Bulk Permission Check
+-- Description: check_multiple_entities_permission read check_entity_permission's result as a composite and failed on every call. Select its columns directly.
+
+CREATE OR REPLACE FUNCTION check_multiple_entities_permission(
+    p_user_id UUID,
+    p_entity_ids UUID[],
+    p_permission_name VARCHAR(100),
+    p_tenant_id UUID DEFAULT NULL
+)
+RETURNS TABLE (
+    entity_id UUID,
+    has_permission BOOLEAN,
+    is_denied BOOLEAN
+) AS $$
+BEGIN
+    RETURN QUERY
+    SELECT
+        ids.id,
+        COALESCE(res.has_permission, FALSE),
+        COALESCE(res.is_denied, FALSE)
+    FROM unnest(p_entity_ids) AS ids(id)
+    LEFT JOIN LATERAL check_entity_permission(p_user_id, ids.id, p_permission_name, p_tenant_id) AS res ON TRUE;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,19 @@
+-- Migration: Quick Search Index
+-- Description: Trigram index on entity display names so /navigation/quick-search substring matches stay fast. Without pg_trgm quick-search still works, scanning instead.
+
+DO $$
+BEGIN
+    IF EXISTS (
+        SELECT 1 FROM pg_available_extensions WHERE name = 'pg_trgm'
+    ) THEN
+        CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+        CREATE INDEX IF NOT EXISTS idx_entities_display_name_trgm
+            ON entities USING gin (lower(display_name) gin_trgm_ops)
+            WHERE deleted_at IS NULL;
+
+        RAISE NOTICE 'pg_trgm extension enabled';
+    ELSE
+        RAISE NOTICE 'pg_trgm extension not available - quick search falls back to sequential scans';
+    END IF;
+END $$;
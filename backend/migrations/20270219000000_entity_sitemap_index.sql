@@ -0,0 +1,6 @@
+-- Migration: Entity Sitemap Index
+-- Description: Index live entities in change order, so /discovery/sitemap pages and "changed since" polls read a range instead of sorting the table.
+
+CREATE INDEX IF NOT EXISTS idx_entities_updated_at_id
+    ON entities (updated_at, id)
+    WHERE deleted_at IS NULL;
@@ -0,0 +1,43 @@
+-- Migration: Firefighter Two-Person Approval
+-- Description: Per-tenant policies requiring a second designated approver before firefighter access is granted, and the pending requests awaiting their decision.
+
+CREATE TABLE IF NOT EXISTS firefighter_approval_policies (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    -- NULL is the global policy, used for tenants without their own
+    tenant_id UUID,
+    requires_approval BOOLEAN NOT NULL DEFAULT TRUE,
+    approver_ids UUID[] NOT NULL DEFAULT '{}',
+    -- How long a request waits for a decision before it lapses
+    request_ttl_minutes INT NOT NULL DEFAULT 60 CHECK (request_ttl_minutes > 0),
+    updated_by UUID REFERENCES entities(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_firefighter_approval_policies_scope
+    ON firefighter_approval_policies ((COALESCE(tenant_id, '00000000-0000-0000-0000-000000000000'::uuid)));
+
+COMMENT ON TABLE firefighter_approval_policies IS 'Whether firefighter access needs a second approver, and who may approve; the tenant policy wins over the global one';
+
+CREATE TABLE IF NOT EXISTS firefighter_requests (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES entities(id) ON DELETE CASCADE,
+    justification TEXT NOT NULL,
+    duration_minutes INT NOT NULL,
+    -- Approvers designated by the policy when the request was made
+    approver_ids UUID[] NOT NULL,
+    status TEXT NOT NULL DEFAULT 'PENDING'
+        CHECK (status IN ('PENDING', 'APPROVED', 'REJECTED', 'EXPIRED')),
+    decided_by UUID REFERENCES entities(id) ON DELETE SET NULL,
+    decided_at TIMESTAMPTZ,
+    decision_reason TEXT,
+    session_id UUID REFERENCES firefighter_sessions(id) ON DELETE SET NULL,
+    ip_address TEXT,
+    user_agent TEXT,
+    requested_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_firefighter_requests_pending
+    ON firefighter_requests (user_id, expires_at)
+    WHERE status = 'PENDING';
@@ -0,0 +1,9 @@
+-- Migration: Firefighter Session Expiry
+-- Description: Track which firefighter sessions have been warned about their end, so the expiry sweep warns each holder once before revoking the session.
+
+ALTER TABLE firefighter_sessions ADD COLUMN IF NOT EXISTS expiry_warned_at TIMESTAMPTZ;
+
+-- The sweep only looks at sessions that haven't been ended yet
+CREATE INDEX IF NOT EXISTS idx_firefighter_sessions_open
+    ON firefighter_sessions (expires_at)
+    WHERE deactivated_at IS NULL;
@@ -0,0 +1,26 @@
+-- Migration: Firefighter Audit Tagging
+-- Description: Expose the firefighter session an audit event was recorded under, so everything done with emergency access can be listed for post-incident review.
+
+CREATE OR REPLACE VIEW unified_audit_logs AS
+SELECT 
+    e.id, 
+    COALESCE(r_init.target_entity_id, '00000000-0000-0000-0000-000000000000'::uuid) as user_id, 
+    e.attributes->>'action' as action, 
+    e.attributes->>'target_type' as target_type,
+    r_target.target_entity_id as target_id,
+    e.attributes->'before_state' as before_state,
+    e.attributes->'after_state' as after_state,
+    e.attributes->'details' as metadata,
+    e.created_at,
+    (e.attributes->>'firefighter_session_id')::uuid as firefighter_session_id
+FROM entities e
+JOIN classes c ON e.class_id = c.id
+LEFT JOIN relationships r_init ON e.id = r_init.source_entity_id 
+    AND r_init.relationship_type_id = (SELECT id FROM relationship_types WHERE name = 'initiated_by' LIMIT 1)
+LEFT JOIN relationships r_target ON e.id = r_target.source_entity_id 
+    AND r_target.relationship_type_id = (SELECT id FROM relationship_types WHERE name = 'affected_target' LIMIT 1)
+WHERE c.name = 'SecurityEvent';
+
+CREATE INDEX IF NOT EXISTS idx_entities_firefighter_session
+    ON entities (((attributes->>'firefighter_session_id')::uuid))
+    WHERE attributes->>'firefighter_session_id' IS NOT NULL;
@@ -0,0 +1,11 @@
+-- Migration: Firefighter Post-Incident Reviews
+-- Description: Reviewer sign-off closing out a firefighter session once its report has been checked.
+
+CREATE TABLE IF NOT EXISTS firefighter_reviews (
+    session_id UUID PRIMARY KEY REFERENCES firefighter_sessions(id) ON DELETE CASCADE,
+    reviewer_id UUID NOT NULL REFERENCES entities(id),
+    notes TEXT NOT NULL,
+    signed_off_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+COMMENT ON TABLE firefighter_reviews IS 'Sign-off on the post-incident review of a firefighter session; a session without one is still open for review';
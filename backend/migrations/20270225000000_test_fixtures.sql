@@ -0,0 +1,65 @@
+-- Migration: Test Fixtures
+-- Description: Record fixtures loaded during test mode sessions so the classes, entities and relationships they created can be removed together. Also fixes mark_as_test_data, which wrote to a relationships column that doesn't exist, and drops the session audit trigger, which wrote to the retired audit_logs table (the service audits sessions now).
+
+CREATE TABLE IF NOT EXISTS test_fixtures (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    session_id UUID NOT NULL REFERENCES test_mode_sessions(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    loaded_by UUID REFERENCES entities(id) ON DELETE SET NULL,
+    class_ids UUID[] NOT NULL DEFAULT '{}',
+    entity_ids UUID[] NOT NULL DEFAULT '{}',
+    relationship_ids UUID[] NOT NULL DEFAULT '{}',
+    loaded_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    removed_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_test_fixtures_session ON test_fixtures(session_id);
+
+COMMENT ON TABLE test_fixtures IS 'Fixtures loaded in test mode, with everything they created for cleanup';
+
+CREATE OR REPLACE FUNCTION mark_as_test_data(
+    p_entity_id UUID,
+    p_test_suite VARCHAR DEFAULT 'e2e',
+    p_test_name VARCHAR DEFAULT NULL
+) RETURNS VOID AS $$
+DECLARE
+    v_marker_id UUID;
+    v_rt_id UUID;
+BEGIN
+    -- Get test marker entity
+    SELECT id INTO v_marker_id 
+    FROM entities 
+    WHERE class_id = (SELECT id FROM classes WHERE name = 'TestMarker')
+    LIMIT 1;
+    
+    -- Get relationship type
+    SELECT id INTO v_rt_id 
+    FROM relationship_types 
+    WHERE name = 'marked_as_test';
+    
+    IF v_marker_id IS NULL OR v_rt_id IS NULL THEN
+        RAISE EXCEPTION 'Test marker infrastructure not found';
+    END IF;
+    
+    -- Create relationship
+    INSERT INTO relationships (id, relationship_type_id, source_entity_id, target_entity_id, metadata)
+    VALUES (
+        gen_random_uuid(),
+        v_rt_id,
+        p_entity_id,
+        v_marker_id,
+        jsonb_build_object(
+            'test_suite', p_test_suite,
+            'test_name', p_test_name,
+            'marked_at', NOW()
+        )
+    )
+    ON CONFLICT DO NOTHING;
+    
+    RAISE NOTICE 'Entity % marked as test data', p_entity_id;
+END;
+$$ LANGUAGE plpgsql;
+
+-- audit_logs was retired for ontology-backed audit events
+DROP TRIGGER IF EXISTS trigger_audit_test_mode_session ON test_mode_sessions;
+DROP FUNCTION IF EXISTS audit_test_mode_session();
@@ -0,0 +1,27 @@
+-- Migration: Test Environment Snapshots
+-- Description: Logical snapshots of every table, stored as JSON rows, so QA environments can be reset to a named state without re-running migrations. These tables are left out of snapshots and restores themselves.
+
+CREATE TABLE IF NOT EXISTS test_snapshots (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(255) NOT NULL UNIQUE,
+    description TEXT,
+    tags TEXT[] NOT NULL DEFAULT '{}',
+    -- No foreign key: the creator may not exist after a restore
+    created_by UUID,
+    table_count INTEGER NOT NULL DEFAULT 0,
+    row_count BIGINT NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    restored_at TIMESTAMPTZ
+);
+
+CREATE TABLE IF NOT EXISTS test_snapshot_tables (
+    snapshot_id UUID NOT NULL REFERENCES test_snapshots(id) ON DELETE CASCADE,
+    table_name TEXT NOT NULL,
+    rows JSONB NOT NULL,
+    row_count BIGINT NOT NULL,
+    PRIMARY KEY (snapshot_id, table_name)
+);
+
+CREATE INDEX IF NOT EXISTS idx_test_snapshots_tags ON test_snapshots USING GIN (tags);
+
+COMMENT ON TABLE test_snapshots IS 'Named logical snapshots of the database for resetting test environments';
@@ -0,0 +1,42 @@
+-- Migration: Email Delivery
+-- Description: Outgoing email queue and delivery log. Messages are rendered when queued and sent by a background worker, which retries failed attempts with backoff. Bodies are cleared once a message is sent, since they can carry single-use links. Built-in templates can be overridden per deployment.
+
+CREATE TABLE IF NOT EXISTS email_deliveries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    template VARCHAR(100) NOT NULL,
+    recipient VARCHAR(320) NOT NULL,
+    subject TEXT NOT NULL,
+    text_body TEXT,
+    html_body TEXT,
+    -- pending: waiting for its next attempt; sent; failed: out of attempts or rejected
+    status VARCHAR(20) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'sent', 'failed')),
+    -- Provider of the latest attempt
+    provider VARCHAR(50),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL,
+    -- Also pushed forward while a worker holds the message, so a crashed
+    -- worker's claim lapses and another instance picks it up
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_error TEXT,
+    provider_message_id TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    sent_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_email_deliveries_due
+    ON email_deliveries (next_attempt_at) WHERE status = 'pending';
+CREATE INDEX IF NOT EXISTS idx_email_deliveries_created ON email_deliveries (created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_email_deliveries_recipient ON email_deliveries (recipient);
+
+CREATE TABLE IF NOT EXISTS email_templates (
+    name VARCHAR(100) PRIMARY KEY,
+    subject TEXT NOT NULL,
+    text_body TEXT NOT NULL,
+    html_body TEXT,
+    updated_by UUID,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+COMMENT ON TABLE email_deliveries IS 'Outgoing email queue and delivery log, drained by the email worker';
+COMMENT ON TABLE email_templates IS 'Per-deployment overrides of the built-in email templates';
@@ -0,0 +1,13 @@
+-- Migration: Notification Digests
+-- Description: Tracks when each user was last sent their notification digest. Low-priority notifications waiting for a digest carry a digest_pending attribute until the digest job emails them.
+
+CREATE TABLE IF NOT EXISTS notification_digests (
+    user_id UUID PRIMARY KEY,
+    last_sent_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_entities_notification_digest_pending
+    ON entities ((attributes->>'user_id'))
+    WHERE attributes->>'digest_pending' = 'true';
+
+COMMENT ON TABLE notification_digests IS 'When each user last received a notification digest email';
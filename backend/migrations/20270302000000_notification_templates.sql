@@ -0,0 +1,13 @@
+-- Migration: Notification Templates
+-- Description: Per-locale wording for built-in notifications (new device sign-in, password changes, expiring access). The built-in English text is used for any key and locale without a row here.
+
+CREATE TABLE IF NOT EXISTS notification_templates (
+    key VARCHAR(100) NOT NULL,
+    locale VARCHAR(20) NOT NULL,
+    message TEXT NOT NULL,
+    updated_by UUID,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (key, locale)
+);
+
+COMMENT ON TABLE notification_templates IS 'Admin-edited, per-locale variants of built-in notification messages';
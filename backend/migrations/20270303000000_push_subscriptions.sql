@@ -0,0 +1,19 @@
+-- Migration: Web Push Subscriptions
+-- Description: Browser push subscriptions (endpoint and encryption keys) per user, with delivery health so dead subscriptions can be dropped.
+
+CREATE TABLE IF NOT EXISTS push_subscriptions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL,
+    endpoint TEXT NOT NULL UNIQUE,
+    p256dh TEXT NOT NULL,
+    auth TEXT NOT NULL,
+    user_agent TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_success_at TIMESTAMPTZ,
+    failure_count INT NOT NULL DEFAULT 0,
+    last_error TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_push_subscriptions_user ON push_subscriptions(user_id);
+
+COMMENT ON TABLE push_subscriptions IS 'Web Push subscriptions that receive notifications while the app is closed';
@@ -0,0 +1,88 @@
+-- Migration: Graph Database Sync
+-- Description: Adds relationships to the ontology change feed, with their endpoints so a deleted edge can still be found, and tracks the graph sync connector's progress.
+
+ALTER TABLE ontology_change_events DROP CONSTRAINT IF EXISTS ontology_change_events_object_type_check;
+ALTER TABLE ontology_change_events ADD CONSTRAINT ontology_change_events_object_type_check
+    CHECK (object_type IN ('entity', 'class', 'property', 'relationship'));
+
+-- Only set for relationship events
+ALTER TABLE ontology_change_events ADD COLUMN IF NOT EXISTS source_entity_id UUID;
+ALTER TABLE ontology_change_events ADD COLUMN IF NOT EXISTS target_entity_id UUID;
+
+CREATE OR REPLACE FUNCTION record_ontology_change() RETURNS TRIGGER AS $$
+DECLARE
+    v_row RECORD;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        v_row := OLD;
+    ELSE
+        v_row := NEW;
+    END IF;
+
+    IF TG_TABLE_NAME = 'entities' THEN
+        -- Only changes to what gets indexed, not bookkeeping columns
+        IF TG_OP = 'UPDATE'
+           AND NEW.display_name IS NOT DISTINCT FROM OLD.display_name
+           AND NEW.attributes IS NOT DISTINCT FROM OLD.attributes
+           AND NEW.class_id IS NOT DISTINCT FROM OLD.class_id
+           AND NEW.parent_entity_id IS NOT DISTINCT FROM OLD.parent_entity_id
+           AND NEW.deleted_at IS NOT DISTINCT FROM OLD.deleted_at THEN
+            RETURN NULL;
+        END IF;
+        INSERT INTO ontology_change_events (object_type, object_id, class_id, operation)
+        VALUES ('entity', v_row.id, v_row.class_id, TG_OP);
+    ELSIF TG_TABLE_NAME = 'classes' THEN
+        IF TG_OP = 'UPDATE' AND NEW.name IS NOT DISTINCT FROM OLD.name THEN
+            RETURN NULL;
+        END IF;
+        INSERT INTO ontology_change_events (object_type, object_id, class_id, operation)
+        VALUES ('class', v_row.id, v_row.id, TG_OP);
+    ELSIF TG_TABLE_NAME = 'relationships' THEN
+        IF TG_OP = 'UPDATE'
+           AND NEW.source_entity_id IS NOT DISTINCT FROM OLD.source_entity_id
+           AND NEW.target_entity_id IS NOT DISTINCT FROM OLD.target_entity_id
+           AND NEW.relationship_type_id IS NOT DISTINCT FROM OLD.relationship_type_id
+           AND NEW.metadata IS NOT DISTINCT FROM OLD.metadata THEN
+            RETURN NULL;
+        END IF;
+        -- An edge that moved is removed from its old endpoints first
+        IF TG_OP = 'UPDATE'
+           AND (NEW.source_entity_id IS DISTINCT FROM OLD.source_entity_id
+                OR NEW.target_entity_id IS DISTINCT FROM OLD.target_entity_id) THEN
+            INSERT INTO ontology_change_events
+                (object_type, object_id, operation, source_entity_id, target_entity_id)
+            VALUES ('relationship', OLD.id, 'DELETE', OLD.source_entity_id, OLD.target_entity_id);
+        END IF;
+        INSERT INTO ontology_change_events
+            (object_type, object_id, operation, source_entity_id, target_entity_id)
+        VALUES ('relationship', v_row.id, TG_OP, v_row.source_entity_id, v_row.target_entity_id);
+    ELSE
+        IF TG_OP = 'UPDATE'
+           AND NEW.name IS NOT DISTINCT FROM OLD.name
+           AND NEW.is_sensitive IS NOT DISTINCT FROM OLD.is_sensitive THEN
+            RETURN NULL;
+        END IF;
+        INSERT INTO ontology_change_events (object_type, object_id, class_id, operation)
+        VALUES ('property', v_row.id, v_row.class_id, TG_OP);
+    END IF;
+
+    -- Identical payloads are delivered once per transaction, so bulk changes wake consumers once
+    PERFORM pg_notify('ontology_changes', '');
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_relationships_change_events ON relationships;
+CREATE TRIGGER trg_relationships_change_events
+    AFTER INSERT OR UPDATE OR DELETE ON relationships
+    FOR EACH ROW EXECUTE FUNCTION record_ontology_change();
+
+-- Where a backfill that pages by id has got to
+ALTER TABLE ai_index_state ADD COLUMN IF NOT EXISTS backfill_cursor TEXT;
+
+-- Unavailable until the connector is enabled, so it doesn't hold back pruning of the feed
+INSERT INTO ai_index_state (worker, last_seq, available)
+SELECT 'graph_sync', COALESCE(MAX(seq), 0), FALSE FROM ontology_change_events
+ON CONFLICT (worker) DO NOTHING;
+
+COMMENT ON TABLE ai_index_state IS 'Progress and health of background index workers (embeddings, graph sync), shown in /api/system/info';
@@ -0,0 +1,9 @@
+-- Migration: External Search Index
+-- Description: Tracks the Elasticsearch/OpenSearch indexer's progress through the ontology change feed.
+
+-- Unavailable until the indexer is enabled, so it doesn't hold back pruning of the feed
+INSERT INTO ai_index_state (worker, last_seq, available)
+SELECT 'search_index', COALESCE(MAX(seq), 0), FALSE FROM ontology_change_events
+ON CONFLICT (worker) DO NOTHING;
+
+COMMENT ON TABLE ai_index_state IS 'Progress and health of background index workers (embeddings, graph sync, search index), shown in /api/system/info';
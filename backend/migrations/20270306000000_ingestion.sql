@@ -0,0 +1,47 @@
+-- Migration: Inbound Entity Ingestion
+-- Description: Links entities to their ids in upstream systems of record and records handled events, so redelivered or out-of-date upserts are skipped.
+
+-- The entity an upstream record maps to. External ids are only unique within their source.
+CREATE TABLE IF NOT EXISTS ingestion_links (
+    source VARCHAR(100) NOT NULL,
+    external_id VARCHAR(255) NOT NULL,
+    entity_id UUID NOT NULL REFERENCES entities(id) ON DELETE CASCADE,
+    -- Highest upstream sequence applied; older upserts are skipped
+    last_sequence BIGINT,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (source, external_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_ingestion_links_entity ON ingestion_links(entity_id);
+
+-- One row per handled event. A failed event may be retried by sending it again.
+CREATE TABLE IF NOT EXISTS ingestion_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    source VARCHAR(100) NOT NULL,
+    -- Missing when the message couldn't be parsed
+    event_id VARCHAR(255),
+    external_id VARCHAR(255),
+    status VARCHAR(20) NOT NULL
+        CHECK (status IN ('created', 'updated', 'deleted', 'stale', 'ignored', 'failed')),
+    entity_id UUID,
+    error TEXT,
+    -- Kept for failed events, to see what was sent
+    payload JSONB,
+    received_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (source, event_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_ingestion_events_status
+    ON ingestion_events(source, status, received_at DESC);
+
+-- Per-source consumer position and health
+CREATE TABLE IF NOT EXISTS ingestion_sources (
+    name VARCHAR(100) PRIMARY KEY,
+    -- Where a polled HTTP source resumes; NATS tracks its own position
+    cursor TEXT,
+    events_received BIGINT NOT NULL DEFAULT 0,
+    events_failed BIGINT NOT NULL DEFAULT 0,
+    last_polled_at TIMESTAMPTZ,
+    last_error TEXT,
+    last_error_at TIMESTAMPTZ
+);
@@ -0,0 +1,326 @@
+-- Migration: Permission Hot Path
+-- Description: check_entity_permission looked up the has_role and grants_permission relationship types, the Permission class and the requested level on every call, in plpgsql. The check now takes those from the caller, which keeps them in memory, as a plain SQL function whose plan is cached with the caller's prepared statement. An optional denormalized table of each user's grants, kept current by triggers, lets checks skip the role -> permission joins.
+
+-- The check itself. p_requested_level may be NULL for permission types the
+-- caller doesn't know yet; it's looked up then.
+CREATE OR REPLACE FUNCTION check_entity_permission_resolved(
+    p_user_id uuid,
+    p_entity_id uuid,
+    p_permission_name character varying,
+    p_tenant_id uuid,
+    p_has_role_type_id uuid,
+    p_grants_perm_type_id uuid,
+    p_permission_class_id uuid,
+    p_requested_level integer
+)
+ RETURNS TABLE(has_permission boolean, granted_via_entity_id uuid, granted_via_role character varying, is_inherited boolean, is_denied boolean)
+ LANGUAGE sql
+ STABLE
+AS $function$
+    WITH RECURSIVE params AS (
+        SELECT
+            effective_now(p_user_id) AS now,
+            (SELECT class_id FROM entities WHERE id = p_entity_id) AS entity_class_id,
+            COALESCE(
+                p_requested_level,
+                (SELECT (attributes->>'level')::integer FROM entities
+                 WHERE display_name = p_permission_name AND class_id = p_permission_class_id LIMIT 1),
+                0
+            ) AS requested_level
+    ),
+    graph_path AS (
+        SELECT id, parent_entity_id, 0 as depth FROM entities
+        WHERE id = p_entity_id AND deleted_at IS NULL AND (p_tenant_id IS NULL OR tenant_id = p_tenant_id)
+        UNION ALL
+        SELECT e.id, e.parent_entity_id, gp.depth + 1 FROM entities e
+        JOIN graph_path gp ON e.id = gp.parent_entity_id
+        WHERE e.deleted_at IS NULL AND (p_tenant_id IS NULL OR e.tenant_id = p_tenant_id)
+    ),
+    applicable_roles AS (
+        SELECT
+            r.target_entity_id as role_id,
+            r.metadata->>'scope_entity_id' as scope_id_str,
+            COALESCE((r.metadata->>'is_deny')::boolean, FALSE) as is_deny,
+            e_role.display_name as role_name,
+            gp.depth,
+            CASE WHEN r.metadata->>'scope_entity_id' IS NULL THEN 1000 ELSE gp.depth END as specificity
+        FROM relationships r
+        CROSS JOIN params p
+        JOIN entities e_role ON r.target_entity_id = e_role.id
+        LEFT JOIN graph_path gp ON (r.metadata->>'scope_entity_id')::uuid = gp.id
+        WHERE r.source_entity_id = p_user_id
+          AND r.relationship_type_id = p_has_role_type_id
+          AND (r.metadata->>'valid_from' IS NULL OR (r.metadata->>'valid_from')::timestamp with time zone <= p.now)
+          AND (r.metadata->>'valid_until' IS NULL OR (r.metadata->>'valid_until')::timestamp with time zone > p.now)
+          AND (r.metadata->>'scope_entity_id' IS NULL OR (r.metadata->>'scope_entity_id')::uuid IN (SELECT id FROM graph_path))
+          AND (r.metadata->'scope_class_ids' IS NULL OR r.metadata->'scope_class_ids' ? p.entity_class_id::text)
+    ),
+    roles_with_permission AS (
+        -- ReBAC part
+        SELECT ar.* FROM applicable_roles ar
+        CROSS JOIN params p
+        JOIN relationships rel_grant ON ar.role_id = rel_grant.source_entity_id
+        JOIN entities e_perm ON rel_grant.target_entity_id = e_perm.id
+        WHERE rel_grant.relationship_type_id = p_grants_perm_type_id
+          AND (e_perm.display_name = p_permission_name OR COALESCE((e_perm.attributes->>'level')::integer, 0) >= p.requested_level OR e_perm.display_name = 'admin')
+        UNION ALL
+        -- ABAC part (Attribute filters to role)
+        SELECT ar.* FROM applicable_roles ar
+        JOIN entities e_role ON ar.role_id = e_role.id
+        WHERE (e_role.attributes->'permissions' @> jsonb_build_array(p_permission_name))
+           OR (e_role.attributes->>'is_admin')::boolean = TRUE
+    )
+    SELECT
+        COALESCE(CASE
+            WHEN EXISTS (SELECT 1 FROM roles_with_permission WHERE is_deny = TRUE) THEN FALSE
+            WHEN EXISTS (SELECT 1 FROM roles_with_permission WHERE is_deny = FALSE) THEN TRUE
+            ELSE FALSE
+        END, FALSE),
+        (SELECT (scope_id_str)::uuid FROM roles_with_permission WHERE is_deny = FALSE ORDER BY specificity ASC LIMIT 1),
+        (SELECT role_name FROM roles_with_permission WHERE is_deny = FALSE ORDER BY specificity ASC LIMIT 1),
+        (SELECT (scope_id_str)::uuid IS DISTINCT FROM p_entity_id FROM roles_with_permission WHERE is_deny = FALSE ORDER BY specificity ASC LIMIT 1),
+        EXISTS (SELECT 1 FROM roles_with_permission WHERE is_deny = TRUE);
+$function$;
+
+-- Kept for SQL callers; resolves the ids and runs the same check
+CREATE OR REPLACE FUNCTION public.check_entity_permission(p_user_id uuid, p_entity_id uuid, p_permission_name character varying, p_tenant_id uuid DEFAULT NULL::uuid)
+ RETURNS TABLE(has_permission boolean, granted_via_entity_id uuid, granted_via_role character varying, is_inherited boolean, is_denied boolean)
+ LANGUAGE sql
+ STABLE
+AS $function$
+    SELECT * FROM check_entity_permission_resolved(
+        p_user_id,
+        p_entity_id,
+        p_permission_name,
+        p_tenant_id,
+        (SELECT id FROM relationship_types WHERE name = 'has_role' LIMIT 1),
+        (SELECT id FROM relationship_types WHERE name = 'grants_permission' LIMIT 1),
+        (SELECT id FROM classes WHERE name = 'Permission' LIMIT 1),
+        NULL
+    );
+$function$;
+
+-- Each role assignment joined with what the role grants: one row per granted
+-- permission type, per permission named in the role's attributes, and one for
+-- admin roles. Read by check_entity_permission_denormalized.
+CREATE TABLE IF NOT EXISTS permission_grants (
+    id BIGSERIAL PRIMARY KEY,
+    -- The has_role relationship
+    assignment_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    role_id UUID NOT NULL,
+    role_name TEXT NOT NULL,
+    scope_entity_id UUID,
+    scope_class_ids JSONB,
+    is_deny BOOLEAN NOT NULL DEFAULT FALSE,
+    valid_from TIMESTAMPTZ,
+    valid_until TIMESTAMPTZ,
+    -- NULL on admin rows
+    permission_name TEXT,
+    permission_level INTEGER,
+    -- Named in the role's `permissions` attribute, so matched by name only
+    via_attribute BOOLEAN NOT NULL DEFAULT FALSE,
+    -- The admin permission type, or a role with is_admin
+    grants_all BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX IF NOT EXISTS idx_permission_grants_user ON permission_grants (user_id);
+
+COMMENT ON TABLE permission_grants IS 'Denormalized role assignments and the permissions they grant, maintained by triggers on relationships and entities';
+
+-- Rebuild the grant rows of these users
+CREATE OR REPLACE FUNCTION refresh_permission_grants(p_user_ids uuid[])
+RETURNS void AS $$
+DECLARE
+    v_has_role_type_id uuid;
+    v_grants_perm_type_id uuid;
+BEGIN
+    SELECT id INTO v_has_role_type_id FROM relationship_types WHERE name = 'has_role' LIMIT 1;
+    SELECT id INTO v_grants_perm_type_id FROM relationship_types WHERE name = 'grants_permission' LIMIT 1;
+
+    DELETE FROM permission_grants WHERE user_id = ANY(p_user_ids);
+
+    WITH assignments AS (
+        SELECT
+            r.id AS assignment_id,
+            r.source_entity_id AS user_id,
+            r.target_entity_id AS role_id,
+            e_role.display_name AS role_name,
+            e_role.attributes AS role_attributes,
+            (r.metadata->>'scope_entity_id')::uuid AS scope_entity_id,
+            r.metadata->'scope_class_ids' AS scope_class_ids,
+            COALESCE((r.metadata->>'is_deny')::boolean, FALSE) AS is_deny,
+            (r.metadata->>'valid_from')::timestamptz AS valid_from,
+            (r.metadata->>'valid_until')::timestamptz AS valid_until
+        FROM relationships r
+        JOIN entities e_role ON e_role.id = r.target_entity_id
+        WHERE r.relationship_type_id = v_has_role_type_id
+          AND r.source_entity_id = ANY(p_user_ids)
+    )
+    INSERT INTO permission_grants (
+        assignment_id, user_id, role_id, role_name, scope_entity_id, scope_class_ids,
+        is_deny, valid_from, valid_until, permission_name, permission_level, via_attribute, grants_all
+    )
+    SELECT a.assignment_id, a.user_id, a.role_id, a.role_name, a.scope_entity_id, a.scope_class_ids,
+           a.is_deny, a.valid_from, a.valid_until,
+           e_perm.display_name, COALESCE((e_perm.attributes->>'level')::integer, 0), FALSE,
+           e_perm.display_name = 'admin'
+    FROM assignments a
+    JOIN relationships rel_grant ON rel_grant.source_entity_id = a.role_id
+        AND rel_grant.relationship_type_id = v_grants_perm_type_id
+    JOIN entities e_perm ON e_perm.id = rel_grant.target_entity_id
+    UNION ALL
+    SELECT a.assignment_id, a.user_id, a.role_id, a.role_name, a.scope_entity_id, a.scope_class_ids,
+           a.is_deny, a.valid_from, a.valid_until, perm.name, NULL, TRUE, FALSE
+    FROM assignments a
+    CROSS JOIN LATERAL jsonb_array_elements_text(
+        CASE WHEN jsonb_typeof(a.role_attributes->'permissions') = 'array'
+             THEN a.role_attributes->'permissions' ELSE '[]'::jsonb END
+    ) AS perm(name)
+    UNION ALL
+    SELECT a.assignment_id, a.user_id, a.role_id, a.role_name, a.scope_entity_id, a.scope_class_ids,
+           a.is_deny, a.valid_from, a.valid_until, NULL, NULL, TRUE, TRUE
+    FROM assignments a
+    WHERE (a.role_attributes->>'is_admin')::boolean = TRUE;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Users whose grants depend on a relationship of this type from this source
+CREATE OR REPLACE FUNCTION permission_grants_affected_users(p_type_id uuid, p_source_id uuid)
+RETURNS uuid[] AS $$
+    SELECT CASE (SELECT name FROM relationship_types WHERE id = p_type_id)
+        WHEN 'has_role' THEN ARRAY[p_source_id]
+        -- Everyone holding the role
+        WHEN 'grants_permission' THEN ARRAY(
+            SELECT r.source_entity_id FROM relationships r
+            JOIN relationship_types t ON t.id = r.relationship_type_id AND t.name = 'has_role'
+            WHERE r.target_entity_id = p_source_id
+        )
+        ELSE ARRAY[]::uuid[]
+    END;
+$$ LANGUAGE sql STABLE;
+
+CREATE OR REPLACE FUNCTION permission_grants_relationship_changed()
+RETURNS TRIGGER AS $$
+DECLARE
+    v_users uuid[] := ARRAY[]::uuid[];
+BEGIN
+    IF TG_OP IN ('UPDATE', 'DELETE') THEN
+        v_users := v_users || permission_grants_affected_users(OLD.relationship_type_id, OLD.source_entity_id);
+    END IF;
+    IF TG_OP IN ('INSERT', 'UPDATE') THEN
+        v_users := v_users || permission_grants_affected_users(NEW.relationship_type_id, NEW.source_entity_id);
+    END IF;
+    IF cardinality(v_users) > 0 THEN
+        PERFORM refresh_permission_grants(v_users);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_permission_grants_relationships ON relationships;
+CREATE TRIGGER trg_permission_grants_relationships
+    AFTER INSERT OR UPDATE OR DELETE ON relationships
+    FOR EACH ROW EXECUTE FUNCTION permission_grants_relationship_changed();
+
+-- Renamed or re-levelled permission types, and roles whose name or
+-- attribute permissions changed
+CREATE OR REPLACE FUNCTION permission_grants_entity_changed()
+RETURNS TRIGGER AS $$
+DECLARE
+    v_users uuid[];
+BEGIN
+    v_users := ARRAY(
+        -- Holders of this role
+        SELECT r.source_entity_id FROM relationships r
+        JOIN relationship_types t ON t.id = r.relationship_type_id AND t.name = 'has_role'
+        WHERE r.target_entity_id = NEW.id
+        UNION
+        -- Holders of roles granting this permission type
+        SELECT r.source_entity_id FROM relationships g
+        JOIN relationship_types tg ON tg.id = g.relationship_type_id AND tg.name = 'grants_permission'
+        JOIN relationships r ON r.target_entity_id = g.source_entity_id
+        JOIN relationship_types t ON t.id = r.relationship_type_id AND t.name = 'has_role'
+        WHERE g.target_entity_id = NEW.id
+    );
+    IF cardinality(v_users) > 0 THEN
+        PERFORM refresh_permission_grants(v_users);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_permission_grants_entities ON entities;
+CREATE TRIGGER trg_permission_grants_entities
+    AFTER UPDATE OF display_name, attributes ON entities
+    FOR EACH ROW
+    WHEN (OLD.display_name IS DISTINCT FROM NEW.display_name OR OLD.attributes IS DISTINCT FROM NEW.attributes)
+    EXECUTE FUNCTION permission_grants_entity_changed();
+
+-- Same answers as check_entity_permission_resolved, from permission_grants
+CREATE OR REPLACE FUNCTION check_entity_permission_denormalized(
+    p_user_id uuid,
+    p_entity_id uuid,
+    p_permission_name character varying,
+    p_tenant_id uuid,
+    p_permission_class_id uuid,
+    p_requested_level integer
+)
+ RETURNS TABLE(has_permission boolean, granted_via_entity_id uuid, granted_via_role character varying, is_inherited boolean, is_denied boolean)
+ LANGUAGE sql
+ STABLE
+AS $function$
+    WITH RECURSIVE params AS (
+        SELECT
+            effective_now(p_user_id) AS now,
+            (SELECT class_id FROM entities WHERE id = p_entity_id) AS entity_class_id,
+            COALESCE(
+                p_requested_level,
+                (SELECT (attributes->>'level')::integer FROM entities
+                 WHERE display_name = p_permission_name AND class_id = p_permission_class_id LIMIT 1),
+                0
+            ) AS requested_level
+    ),
+    graph_path AS (
+        SELECT id, parent_entity_id, 0 as depth FROM entities
+        WHERE id = p_entity_id AND deleted_at IS NULL AND (p_tenant_id IS NULL OR tenant_id = p_tenant_id)
+        UNION ALL
+        SELECT e.id, e.parent_entity_id, gp.depth + 1 FROM entities e
+        JOIN graph_path gp ON e.id = gp.parent_entity_id
+        WHERE e.deleted_at IS NULL AND (p_tenant_id IS NULL OR e.tenant_id = p_tenant_id)
+    ),
+    roles_with_permission AS (
+        SELECT
+            g.scope_entity_id,
+            g.is_deny,
+            g.role_name::varchar AS role_name,
+            CASE WHEN g.scope_entity_id IS NULL THEN 1000 ELSE gp.depth END AS specificity
+        FROM permission_grants g
+        CROSS JOIN params p
+        LEFT JOIN graph_path gp ON g.scope_entity_id = gp.id
+        WHERE g.user_id = p_user_id
+          AND (g.valid_from IS NULL OR g.valid_from <= p.now)
+          AND (g.valid_until IS NULL OR g.valid_until > p.now)
+          AND (g.scope_entity_id IS NULL OR gp.id IS NOT NULL)
+          AND (g.scope_class_ids IS NULL OR g.scope_class_ids ? p.entity_class_id::text)
+          AND (g.grants_all
+               OR g.permission_name = p_permission_name
+               OR (NOT g.via_attribute AND g.permission_level >= p.requested_level))
+    )
+    SELECT
+        COALESCE(CASE
+            WHEN EXISTS (SELECT 1 FROM roles_with_permission WHERE is_deny = TRUE) THEN FALSE
+            WHEN EXISTS (SELECT 1 FROM roles_with_permission WHERE is_deny = FALSE) THEN TRUE
+            ELSE FALSE
+        END, FALSE),
+        (SELECT scope_entity_id FROM roles_with_permission WHERE is_deny = FALSE ORDER BY specificity ASC LIMIT 1),
+        (SELECT role_name FROM roles_with_permission WHERE is_deny = FALSE ORDER BY specificity ASC LIMIT 1),
+        (SELECT scope_entity_id IS DISTINCT FROM p_entity_id FROM roles_with_permission WHERE is_deny = FALSE ORDER BY specificity ASC LIMIT 1),
+        EXISTS (SELECT 1 FROM roles_with_permission WHERE is_deny = TRUE);
+$function$;
+
+-- Existing assignments
+SELECT refresh_permission_grants(ARRAY(
+    SELECT DISTINCT r.source_entity_id FROM relationships r
+    JOIN relationship_types t ON t.id = r.relationship_type_id AND t.name = 'has_role'
+));
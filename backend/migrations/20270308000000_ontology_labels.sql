@@ -0,0 +1,6 @@
+-- Migration: Ontology Labels
+-- Description: Per-locale display names and descriptions for classes, properties and entities, for deployments that show the ontology in more than one language. Keyed by language tag, e.g. {"nb": {"display_name": "Oppdrag", "description": "..."}}. The existing name and description columns stay the untranslated text that any locale without a label falls back to.
+
+ALTER TABLE classes ADD COLUMN IF NOT EXISTS labels JSONB NOT NULL DEFAULT '{}'::jsonb;
+ALTER TABLE properties ADD COLUMN IF NOT EXISTS labels JSONB NOT NULL DEFAULT '{}'::jsonb;
+ALTER TABLE entities ADD COLUMN IF NOT EXISTS labels JSONB NOT NULL DEFAULT '{}'::jsonb;
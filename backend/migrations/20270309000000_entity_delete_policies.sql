@@ -0,0 +1,6 @@
+-- Migration: Entity Delete Policies
+-- Description: Soft-deleting an entity used to leave its children pointing at a deleted parent and its relationships pointing at a deleted endpoint. Each class now says what deleting one of its entities does to the entity's children: 'cascade' soft-deletes all descendants with it, 'detach' clears the children's parent, and 'block' refuses the delete while live children exist. Relationships touching a deleted entity are removed in every case. Entities deleted before this migration are left as they are.
+
+ALTER TABLE classes ADD COLUMN IF NOT EXISTS delete_policy TEXT NOT NULL DEFAULT 'detach'
+    CHECK (delete_policy IN ('cascade', 'detach', 'block'));
+
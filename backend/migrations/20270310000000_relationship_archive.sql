@@ -0,0 +1,20 @@
+-- Migration: Relationship Archive
+-- Description: Deleting an entity used to hard-delete every relationship touching the entities it removed, including role grants, with no way back. Those relationships are now moved here, keyed by the entity whose delete removed them, and restoring that entity moves them back. The relationships triggers see an ordinary delete and insert, so permission_grants and the change feed stay in step.
+
+CREATE TABLE IF NOT EXISTS archived_relationships (
+    id UUID PRIMARY KEY,
+    source_entity_id UUID NOT NULL REFERENCES entities(id) ON DELETE CASCADE,
+    target_entity_id UUID NOT NULL REFERENCES entities(id) ON DELETE CASCADE,
+    relationship_type_id UUID NOT NULL REFERENCES relationship_types(id) ON DELETE CASCADE,
+    metadata JSONB DEFAULT '{}',
+    tenant_id UUID,
+    created_by UUID REFERENCES entities(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    -- The entity passed to the delete, which may be an ancestor of an endpoint
+    deleted_with_entity_id UUID NOT NULL REFERENCES entities(id) ON DELETE CASCADE,
+    archived_by UUID REFERENCES entities(id) ON DELETE SET NULL,
+    archived_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_archived_relationships_deleted_with
+    ON archived_relationships(deleted_with_entity_id);
@@ -0,0 +1,6 @@
+-- Migration: Idempotency Replayable
+-- Description: Responses too large to store used to fail with a 500 after the write had happened, and released the key so a retry ran it again. Such responses are now sent as they are and the key is marked not replayable, so retries are refused instead of repeated.
+
+ALTER TABLE idempotency_keys ADD COLUMN IF NOT EXISTS replayable BOOLEAN NOT NULL DEFAULT TRUE;
+
+COMMENT ON COLUMN idempotency_keys.replayable IS 'FALSE when the request completed but its response was too large to store';
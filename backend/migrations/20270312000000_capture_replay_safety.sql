@@ -0,0 +1,6 @@
+-- Migration: Capture Replay Safety
+-- Description: Captured request bodies are stored redacted and truncated, and replay used to send them as they were stored, so a replayed write could save "[REDACTED]" as a password or half a document. Captures now record whether their body was changed for storage, and replay refuses those unless explicitly overridden. Existing captures are treated as changed.
+
+ALTER TABLE captured_requests ADD COLUMN IF NOT EXISTS request_body_altered BOOLEAN NOT NULL DEFAULT TRUE;
+
+COMMENT ON COLUMN captured_requests.request_body_altered IS 'TRUE when request_body was redacted, truncated or lossily decoded for storage and differs from what was sent';
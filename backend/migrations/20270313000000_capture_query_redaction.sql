@@ -0,0 +1,6 @@
+-- Migration: Capture Query Redaction
+-- Description: Captured query strings were stored as sent, tokens and passwords included. They are now redacted like bodies, and a redacted query makes a replay send a different request just as a redacted body does, so the altered flag covers the whole request and is renamed to match.
+
+ALTER TABLE captured_requests RENAME COLUMN request_body_altered TO request_altered;
+
+COMMENT ON COLUMN captured_requests.request_altered IS 'TRUE when the query or request_body was redacted, truncated or lossily decoded for storage and differs from what was sent';
@@ -0,0 +1,6 @@
+-- Migration: Capture Response Truncated
+-- Description: Responses too large to buffer, or that failed part way, made the capture middleware answer 500 in place of the real response. They are now passed through uncaptured, and the capture records that its response body is missing.
+
+ALTER TABLE captured_requests ADD COLUMN IF NOT EXISTS response_truncated BOOLEAN NOT NULL DEFAULT FALSE;
+
+COMMENT ON COLUMN captured_requests.response_truncated IS 'TRUE when the response was passed through without its body being captured';
@@ -0,0 +1,34 @@
+use template_repo_backend::cli::{Admin, Command, USAGE};
+use template_repo_backend::config;
+
+/// Operator commands against the database, for when the HTTP API can't or
+/// shouldn't be used. Run from the server's working directory so the same
+/// config/ and keys/ are picked up.
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = match Command::parse(&args) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if command == Command::Help {
+        print!("{}", USAGE);
+        return;
+    }
+
+    config::init();
+    let result = match config::Config::from_env() {
+        Ok(config) => match Admin::connect(config).await {
+            Ok(admin) => admin.run(command).await,
+            Err(e) => Err(e),
+        },
+        Err(e) => Err(format!("Failed to load config: {}", e)),
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
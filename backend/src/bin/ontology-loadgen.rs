@@ -0,0 +1,46 @@
+use template_repo_backend::perf::load::{self, LoadConfig, USAGE};
+
+/// Mixed HTTP traffic against a running instance, for capacity planning.
+/// Prints latency percentiles per operation when the run ends.
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args
+        .iter()
+        .any(|a| matches!(a.as_str(), "help" | "--help" | "-h"))
+    {
+        print!("{}", USAGE);
+        return;
+    }
+    let config = match LoadConfig::parse(&args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    eprintln!(
+        "Running {} workers against {} for {}s",
+        config.concurrency,
+        config.base_url,
+        config.duration.as_secs()
+    );
+    let report = match load::run(&config).await {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    println!("{}", report);
+    if let Some(path) = &config.json_output {
+        let written = serde_json::to_string_pretty(&report)
+            .map_err(|e| e.to_string())
+            .and_then(|json| std::fs::write(path, json).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            eprintln!("Failed to write {}: {}", path, e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,548 @@
+use super::{Command, RetentionJob, USAGE};
+use crate::config::Config;
+use crate::features::abac::models::AssignRoleInput;
+use crate::features::abac::AbacService;
+use crate::features::ai::conversations::ConversationService;
+use crate::features::ai::prompts::PromptService;
+use crate::features::ai::service::AiService;
+use crate::features::auth::mfa::MfaService;
+use crate::features::auth::models::RegisterUser;
+use crate::features::auth::service::AuthService;
+use crate::features::environment::models::{EnvironmentBundle, ImportOptions, ImportReport};
+use crate::features::environment::EnvironmentService;
+use crate::features::firefighter::models::FirefighterSession;
+use crate::features::firefighter::service::FirefighterService;
+use crate::features::ontology::models::{
+    DesiredState, DesiredStatePlan, OntologyExport, OntologyImportSummary,
+};
+use crate::features::ontology::OntologyService;
+use crate::features::rebac::RebacService;
+use crate::features::request_capture::RequestCaptureService;
+use crate::features::system::schema::{self, MigrationStatus, SchemaReport, MIGRATOR};
+use crate::features::system::AuditService;
+use crate::features::uploads::UploadService;
+use crate::features::users::service::UserService;
+use crate::middleware::idempotency::IdempotencyStore;
+use crate::utils;
+use rand::distributions::{Alphanumeric, DistString};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Written to the session's user agent so break-glass grants can be told
+/// apart from self-service activations
+const BREAK_GLASS_AGENT: &str = "ontology-admin";
+
+/// The services the operator commands work through, connected straight to
+/// the database
+pub struct Admin {
+    pub(super) pool: PgPool,
+    config: Config,
+    ontology_service: OntologyService,
+    abac_service: AbacService,
+    rebac_service: RebacService,
+    auth_service: AuthService,
+    firefighter_service: FirefighterService,
+    environment_service: EnvironmentService,
+}
+
+impl Admin {
+    /// Connect with the server's configuration, loading the JWT keys from
+    /// keys/ when the config doesn't hold them
+    pub async fn connect(mut config: Config) -> Result<Self, String> {
+        if (config.jwt_private_key.trim().is_empty() || config.jwt_public_key.trim().is_empty())
+            && utils::jwt_keys::check_keys_exist()
+        {
+            let (private_pem, public_pem) =
+                utils::jwt_keys::load_keys(&config).map_err(|e| e.to_string())?;
+            config.jwt_private_key = private_pem;
+            config.jwt_public_key = public_pem;
+        }
+        let pool = PgPoolOptions::new()
+            .max_connections(2)
+            .acquire_timeout(std::time::Duration::from_secs(10))
+            .connect(&config.database_url)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+        Ok(Self::new(pool, config))
+    }
+
+    pub fn new(pool: PgPool, config: Config) -> Self {
+        let audit_service = AuditService::new(pool.clone());
+        let ontology_service = OntologyService::new(pool.clone(), audit_service.clone());
+        let rebac_service = RebacService::new(
+            pool.clone(),
+            ontology_service.clone(),
+            audit_service.clone(),
+        );
+        let abac_service = AbacService::new(
+            pool.clone(),
+            rebac_service.clone(),
+            ontology_service.clone(),
+        );
+        let user_service = UserService::new(
+            pool.clone(),
+            audit_service.clone(),
+            ontology_service.clone(),
+        );
+        let auth_service = AuthService::new(
+            pool.clone(),
+            config.clone(),
+            abac_service.clone(),
+            user_service,
+            audit_service.clone(),
+            ontology_service.clone(),
+            MfaService::new(pool.clone(), "OntologyManager".to_string()),
+        );
+        let firefighter_service = FirefighterService::new(
+            pool.clone(),
+            audit_service.clone(),
+            ontology_service.clone(),
+            config.firefighter.clone(),
+        );
+        let environment_service =
+            EnvironmentService::new(pool.clone(), ontology_service.clone(), audit_service);
+        Self {
+            pool,
+            config,
+            ontology_service,
+            abac_service,
+            rebac_service,
+            auth_service,
+            firefighter_service,
+            environment_service,
+        }
+    }
+
+    /// Run a command, printing its result
+    pub async fn run(&self, command: Command) -> Result<(), String> {
+        if !matches!(
+            command,
+            Command::Help
+                | Command::RotateJwtKeys
+                | Command::Migrations
+                | Command::Migrate
+                | Command::VerifySchema
+        ) {
+            let pending = self.migration_status().await?;
+            if pending.iter().any(|m| !m.modified) {
+                return Err(
+                    "The database has pending migrations; run `ontology-admin migrate` first"
+                        .to_string(),
+                );
+            }
+        }
+
+        match command {
+            Command::Help => print!("{}", USAGE),
+            Command::CreateSuperadmin { email, username } => {
+                let password = std::env::var("ADMIN_PASSWORD").ok();
+                let generated = password.is_none();
+                let password = password
+                    .unwrap_or_else(|| Alphanumeric.sample_string(&mut rand::thread_rng(), 24));
+                let (user_id, created) =
+                    self.create_superadmin(&email, &username, &password).await?;
+                if created {
+                    println!("Created superadmin {} ({})", email, user_id);
+                    if generated {
+                        println!("Password: {}", password);
+                    }
+                } else {
+                    println!(
+                        "Granted superadmin to existing user {} ({})",
+                        email, user_id
+                    );
+                }
+            }
+            Command::RotateJwtKeys => {
+                utils::key_rotation::rotate_keys().map_err(|e| e.to_string())?;
+                println!("Restart the server to sign with the new key.");
+            }
+            Command::ExportOntology { output } => {
+                let export = self.export_ontology().await?;
+                let json = serde_json::to_string_pretty(&export).map_err(|e| e.to_string())?;
+                match output {
+                    Some(path) => {
+                        std::fs::write(&path, json)
+                            .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+                        println!("Exported {} classes to {}", export.classes.len(), path);
+                    }
+                    None => println!("{}", json),
+                }
+            }
+            Command::ImportOntology {
+                input,
+                version,
+                publish,
+            } => {
+                let json = std::fs::read_to_string(&input)
+                    .map_err(|e| format!("Failed to read {}: {}", input, e))?;
+                let export: OntologyExport = serde_json::from_str(&json)
+                    .map_err(|e| format!("{} is not an ontology export: {}", input, e))?;
+                let summary = self.import_ontology(&export, &version, publish).await?;
+                println!(
+                    "Imported {} classes and {} properties as version {} ({:?})",
+                    summary.classes, summary.properties, version, summary.version.status
+                );
+            }
+            Command::PlanOntology { input, prune } => {
+                let desired = read_desired_state(&input)?;
+                let plan = self
+                    .ontology_service
+                    .plan_desired_state(&desired, prune)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                print_plan(&plan);
+            }
+            Command::ApplyOntology {
+                input,
+                prune,
+                fingerprint,
+            } => {
+                let desired = read_desired_state(&input)?;
+                let plan = self
+                    .ontology_service
+                    .apply_desired_state(&desired, prune, fingerprint.as_deref(), None)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                print_plan(&plan);
+            }
+            Command::ExportBundle { tenant, output } => {
+                let bundle = self.export_bundle(tenant).await?;
+                let json = serde_json::to_string_pretty(&bundle).map_err(|e| e.to_string())?;
+                match output {
+                    Some(path) => {
+                        std::fs::write(&path, json)
+                            .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+                        println!(
+                            "Exported {} entities, {} relationships and {} policies to {}",
+                            bundle.entities.len(),
+                            bundle.relationships.len(),
+                            bundle.policies.len(),
+                            path
+                        );
+                    }
+                    None => println!("{}", json),
+                }
+            }
+            Command::ImportBundle {
+                input,
+                tenant,
+                dry_run,
+            } => {
+                let json = std::fs::read_to_string(&input)
+                    .map_err(|e| format!("Failed to read {}: {}", input, e))?;
+                let bundle: EnvironmentBundle = serde_json::from_str(&json)
+                    .map_err(|e| format!("{} is not an environment bundle: {}", input, e))?;
+                let report = self.import_bundle(&bundle, tenant, dry_run).await?;
+                println!(
+                    "{} {} entities ({} updated, {} with new ids, {} matched by name), \
+                     {} relationships ({} skipped), {} policies and {} settings",
+                    if dry_run { "Would import" } else { "Imported" },
+                    report.entities_created + report.entities_updated,
+                    report.entities_updated,
+                    report.entities_remapped,
+                    report.entities_matched,
+                    report.relationships,
+                    report.relationships_skipped,
+                    report.policies,
+                    report.settings
+                );
+            }
+            Command::RunRetention { jobs } => {
+                for job in jobs {
+                    let removed = self.run_retention(job).await?;
+                    println!("{}: removed {}", job.name(), removed);
+                }
+            }
+            Command::Migrations => {
+                let status = self.migration_status().await?;
+                if status.is_empty() {
+                    println!("All migrations are applied.");
+                }
+                for migration in status {
+                    println!(
+                        "{} {} {}",
+                        if migration.modified {
+                            "modified"
+                        } else {
+                            "pending "
+                        },
+                        migration.version,
+                        migration.description
+                    );
+                }
+            }
+            Command::VerifySchema => {
+                let report = self.verify_schema().await?;
+                if report.has_drift() {
+                    for problem in report.problems() {
+                        println!("{}", problem);
+                    }
+                    return Err("The database schema doesn't match this build".to_string());
+                }
+                println!("The database schema matches this build.");
+            }
+            Command::Migrate => {
+                MIGRATOR
+                    .run(&self.pool)
+                    .await
+                    .map_err(|e| format!("Failed to run migrations: {}", e))?;
+                println!("All migrations are applied.");
+            }
+            Command::Seed { force } => {
+                let password = std::env::var("DEMO_PASSWORD").ok();
+                let generated = password.is_none();
+                let password = password
+                    .unwrap_or_else(|| Alphanumeric.sample_string(&mut rand::thread_rng(), 16));
+                let summary = self.seed_demo(&password, force).await?;
+                println!(
+                    "Seeded {} classes, {} projects and {} entities. Demo users:",
+                    summary.classes, summary.projects, summary.entities
+                );
+                for (email, role) in &summary.users {
+                    println!("  {} ({})", email, role);
+                }
+                if generated {
+                    println!("Password: {}", password);
+                }
+            }
+            Command::GrantBreakGlass {
+                user,
+                justification,
+                minutes,
+            } => {
+                let session = self
+                    .grant_break_glass(&user, justification, minutes)
+                    .await?;
+                println!(
+                    "Firefighter session {} active for {} until {}",
+                    session.id, user, session.expires_at
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the user's id and whether the account was created; an
+    /// existing user with the email only gets the role
+    pub async fn create_superadmin(
+        &self,
+        email: &str,
+        username: &str,
+        password: &str,
+    ) -> Result<(Uuid, bool), String> {
+        let existing = self.find_user(email).await?;
+        let user_id = match existing {
+            Some(user_id) => user_id,
+            None => {
+                self.auth_service
+                    .register(RegisterUser {
+                        username: username.to_string(),
+                        email: email.to_string(),
+                        password: password.to_string(),
+                    })
+                    .await
+                    .map_err(|e| format!("Failed to create user: {}", e))?
+                    .user_id
+            }
+        };
+        self.abac_service
+            .assign_role(
+                AssignRoleInput {
+                    user_id: user_id.to_string(),
+                    role_name: "superadmin".to_string(),
+                    resource_id: None,
+                },
+                None,
+            )
+            .await
+            .map_err(|e| format!("Failed to grant superadmin: {}", e))?;
+        Ok((user_id, existing.is_none()))
+    }
+
+    pub async fn export_ontology(&self) -> Result<OntologyExport, String> {
+        self.ontology_service
+            .export_ontology()
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn import_ontology(
+        &self,
+        export: &OntologyExport,
+        version: &str,
+        publish: bool,
+    ) -> Result<OntologyImportSummary, String> {
+        let mut summary = self
+            .ontology_service
+            .import_ontology(export, version, None)
+            .await
+            .map_err(|e| e.to_string())?;
+        if publish {
+            summary.version = self
+                .ontology_service
+                .publish_version(summary.version.id, None)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(summary)
+    }
+
+    pub async fn export_bundle(&self, tenant: Option<Uuid>) -> Result<EnvironmentBundle, String> {
+        self.environment_service
+            .export_bundle(tenant, None)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn import_bundle(
+        &self,
+        bundle: &EnvironmentBundle,
+        tenant: Option<Uuid>,
+        dry_run: bool,
+    ) -> Result<ImportReport, String> {
+        self.environment_service
+            .import_bundle(
+                bundle,
+                ImportOptions {
+                    tenant_id: tenant,
+                    dry_run,
+                },
+                None,
+            )
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// Returns how many records the job removed
+    pub async fn run_retention(&self, job: RetentionJob) -> Result<u64, String> {
+        let removed = match job {
+            RetentionJob::Idempotency => IdempotencyStore::new(self.pool.clone())
+                .purge_expired()
+                .await
+                .map_err(|e| e.to_string())?,
+            RetentionJob::Captures => RequestCaptureService::new(self.pool.clone(), String::new())
+                .purge_expired()
+                .await
+                .map_err(|e| e.to_string())?,
+            RetentionJob::Uploads => {
+                UploadService::new(self.pool.clone(), self.config.uploads.clone())
+                    .cleanup_expired()
+                    .await
+                    .map_err(|e| e.to_string())?
+            }
+            RetentionJob::Conversations => {
+                let prompts = PromptService::new(self.pool.clone());
+                let ai = AiService::new(self.pool.clone(), String::new(), String::new());
+                ConversationService::new(
+                    self.pool.clone(),
+                    ai,
+                    self.rebac_service.clone(),
+                    prompts,
+                    self.config.ai_conversations.clone(),
+                )
+                .purge_expired()
+                .await
+                .map_err(|e| e.to_string())?
+            }
+            RetentionJob::Firefighter => self
+                .firefighter_service
+                .revoke_expired_sessions()
+                .await
+                .map_err(|e| e.to_string())? as u64,
+        };
+        Ok(removed)
+    }
+
+    /// Migrations not yet applied, and applied ones whose checksum no longer
+    /// matches the file
+    pub async fn migration_status(&self) -> Result<Vec<MigrationStatus>, String> {
+        schema::migration_status(&self.pool)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// Migrations and SQL functions that differ from what this build expects
+    pub async fn verify_schema(&self) -> Result<SchemaReport, String> {
+        schema::check_schema(&self.pool)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// `user` is an id or an email address
+    pub async fn grant_break_glass(
+        &self,
+        user: &str,
+        justification: String,
+        minutes: Option<i32>,
+    ) -> Result<FirefighterSession, String> {
+        let user_id = match Uuid::parse_str(user) {
+            Ok(id) => id,
+            Err(_) => self
+                .find_user(user)
+                .await?
+                .ok_or_else(|| format!("No user with email {}", user))?,
+        };
+        self.firefighter_service
+            .grant_break_glass(
+                user_id,
+                justification,
+                minutes,
+                Some(BREAK_GLASS_AGENT.to_string()),
+            )
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    async fn find_user(&self, email: &str) -> Result<Option<Uuid>, String> {
+        sqlx::query_scalar::<_, Uuid>("SELECT id FROM unified_users WHERE email = $1")
+            .bind(email)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+fn read_desired_state(path: &str) -> Result<DesiredState, String> {
+    let json =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    serde_json::from_str(&json)
+        .map_err(|e| format!("{} is not a desired-state document: {}", path, e))
+}
+
+fn print_plan(plan: &DesiredStatePlan) {
+    for change in &plan.changes {
+        let action = serde_json::to_value(change.action).unwrap_or_default();
+        let object = serde_json::to_value(change.object).unwrap_or_default();
+        let name = match &change.owner {
+            Some(owner) => format!("{}.{}", owner, change.name),
+            None => change.name.clone(),
+        };
+        println!(
+            "  {} {} {}",
+            action.as_str().unwrap_or_default(),
+            object.as_str().unwrap_or_default(),
+            name
+        );
+        for field in &change.fields {
+            println!("      {}: {} -> {}", field.field, field.from, field.to);
+        }
+    }
+    if plan.applied {
+        println!(
+            "Applied {} changes to version {}",
+            plan.changes.len(),
+            plan.version
+        );
+    } else if plan.changes.is_empty() {
+        println!("Version {} already matches", plan.version);
+    } else {
+        println!(
+            "{} changes to version {}; fingerprint {}",
+            plan.changes.len(),
+            plan.version,
+            plan.fingerprint
+        );
+    }
+}
@@ -0,0 +1,427 @@
+pub mod commands;
+pub mod seed;
+
+pub use crate::features::system::schema::MigrationStatus;
+pub use commands::Admin;
+pub use seed::SeedSummary;
+
+use uuid::Uuid;
+
+pub const USAGE: &str = "\
+Usage: ontology-admin <command> [options]
+
+Commands:
+  create-superadmin --email <email> --username <name>
+      Create a user with the superadmin role, or grant the role to an existing
+      user with that email. The password is read from ADMIN_PASSWORD, or
+      generated and printed once.
+  rotate-jwt-keys
+      Write a new JWT signing key pair to keys/. Restart the server to use it;
+      tokens signed with the old key stop being accepted.
+  export-ontology [--output <file>]
+      Write the current published ontology as JSON (to stdout by default).
+  import-ontology --input <file> --version <version> [--publish]
+      Create a draft version from an export, and optionally publish it.
+  plan-ontology --input <file> [--prune]
+      Show what applying a desired-state document would change in the
+      current version. --prune also deprecates classes and properties the
+      document doesn't mention.
+  apply-ontology --input <file> [--prune] [--fingerprint <hash>]
+      Apply a desired-state document in one transaction. With the
+      fingerprint of a reviewed plan, nothing changes unless the plan is
+      still the same.
+  export-bundle [--tenant <id>] [--output <file>]
+      Write the ontology, entities, relationships, roles, policies and
+      settings of one tenant, or of the whole instance, as one JSON bundle.
+  import-bundle --input <file> [--tenant <id>] [--dry-run]
+      Apply a bundle, remapping ids that are taken here. A tenant bundle goes
+      to its own tenant unless --tenant is given. Nothing is written if the
+      bundle uses classes, properties or relationship types missing here.
+  run-retention [idempotency|captures|uploads|conversations|firefighter ...]
+      Run the given retention jobs now, or all of them.
+  migrations
+      List migrations that haven't been applied, and applied ones whose file
+      has changed since.
+  migrate
+      Apply pending migrations.
+  verify-schema
+      Check that the applied migrations are exactly the ones in this build
+      and that the SQL functions the server calls exist. Exits non-zero and
+      lists the differences if not.
+  grant-break-glass --user <id|email> --justification <text> [--minutes <n>]
+      Activate firefighter mode for a user without a password or approval.
+  seed [--force]
+      Fill a fresh database with demo tenants, users, classes, entities and
+      projects. The users' password is read from DEMO_PASSWORD, or generated
+      and printed. --force seeds a database that already has users.
+  help
+      Show this message.
+";
+
+/// Scheduled cleanups the server runs in the background
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetentionJob {
+    Idempotency,
+    Captures,
+    Uploads,
+    Conversations,
+    Firefighter,
+}
+
+impl RetentionJob {
+    pub const ALL: [RetentionJob; 5] = [
+        RetentionJob::Idempotency,
+        RetentionJob::Captures,
+        RetentionJob::Uploads,
+        RetentionJob::Conversations,
+        RetentionJob::Firefighter,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RetentionJob::Idempotency => "idempotency",
+            RetentionJob::Captures => "captures",
+            RetentionJob::Uploads => "uploads",
+            RetentionJob::Conversations => "conversations",
+            RetentionJob::Firefighter => "firefighter",
+        }
+    }
+
+    fn parse(name: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|job| job.name() == name)
+            .ok_or_else(|| format!("Unknown retention job '{}'", name))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    CreateSuperadmin {
+        email: String,
+        username: String,
+    },
+    RotateJwtKeys,
+    ExportOntology {
+        output: Option<String>,
+    },
+    ImportOntology {
+        input: String,
+        version: String,
+        publish: bool,
+    },
+    PlanOntology {
+        input: String,
+        prune: bool,
+    },
+    ApplyOntology {
+        input: String,
+        prune: bool,
+        fingerprint: Option<String>,
+    },
+    ExportBundle {
+        tenant: Option<Uuid>,
+        output: Option<String>,
+    },
+    ImportBundle {
+        input: String,
+        tenant: Option<Uuid>,
+        dry_run: bool,
+    },
+    RunRetention {
+        jobs: Vec<RetentionJob>,
+    },
+    Migrations,
+    Migrate,
+    VerifySchema,
+    GrantBreakGlass {
+        user: String,
+        justification: String,
+        minutes: Option<i32>,
+    },
+    Seed {
+        force: bool,
+    },
+    Help,
+}
+
+impl Command {
+    /// Parse the arguments after the program name
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let Some((name, rest)) = args.split_first() else {
+            return Ok(Command::Help);
+        };
+        let mut options = Options::parse(rest)?;
+        let command = match name.as_str() {
+            "create-superadmin" => Command::CreateSuperadmin {
+                email: options.required("email")?,
+                username: options.required("username")?,
+            },
+            "rotate-jwt-keys" => Command::RotateJwtKeys,
+            "export-ontology" => Command::ExportOntology {
+                output: options.take("output")?,
+            },
+            "import-ontology" => Command::ImportOntology {
+                input: options.required("input")?,
+                version: options.required("version")?,
+                publish: options.flag("publish"),
+            },
+            "plan-ontology" => Command::PlanOntology {
+                input: options.required("input")?,
+                prune: options.flag("prune"),
+            },
+            "apply-ontology" => Command::ApplyOntology {
+                input: options.required("input")?,
+                prune: options.flag("prune"),
+                fingerprint: options.take("fingerprint")?,
+            },
+            "export-bundle" => Command::ExportBundle {
+                tenant: options.uuid("tenant")?,
+                output: options.take("output")?,
+            },
+            "import-bundle" => Command::ImportBundle {
+                input: options.required("input")?,
+                tenant: options.uuid("tenant")?,
+                dry_run: options.flag("dry-run"),
+            },
+            "run-retention" => {
+                let jobs = if options.positional.is_empty() {
+                    RetentionJob::ALL.to_vec()
+                } else {
+                    std::mem::take(&mut options.positional)
+                        .iter()
+                        .map(|name| RetentionJob::parse(name))
+                        .collect::<Result<_, _>>()?
+                };
+                Command::RunRetention { jobs }
+            }
+            "migrations" => Command::Migrations,
+            "migrate" => Command::Migrate,
+            "verify-schema" => Command::VerifySchema,
+            "grant-break-glass" => Command::GrantBreakGlass {
+                user: options.required("user")?,
+                justification: options.required("justification")?,
+                minutes: options
+                    .take("minutes")?
+                    .map(|m| {
+                        m.parse()
+                            .map_err(|_| "--minutes must be a whole number".to_string())
+                    })
+                    .transpose()?,
+            },
+            "seed" => Command::Seed {
+                force: options.flag("force"),
+            },
+            "help" | "--help" | "-h" => Command::Help,
+            other => return Err(format!("Unknown command '{}'", other)),
+        };
+        options.finish()?;
+        Ok(command)
+    }
+}
+
+/// `--name value` pairs, `--flag`s and positional arguments
+pub(crate) struct Options {
+    values: Vec<(String, Option<String>)>,
+    positional: Vec<String>,
+}
+
+impl Options {
+    pub(crate) fn parse(args: &[String]) -> Result<Self, String> {
+        let mut values = Vec::new();
+        let mut positional = Vec::new();
+        let mut args = args.iter().peekable();
+        while let Some(arg) = args.next() {
+            match arg.strip_prefix("--") {
+                Some("") => {
+                    return Err("Unexpected '--'".to_string());
+                }
+                Some(name) => {
+                    let value = match name.split_once('=') {
+                        Some((name, value)) => {
+                            values.push((name.to_string(), Some(value.to_string())));
+                            continue;
+                        }
+                        None => args.next_if(|next| !next.starts_with("--")).cloned(),
+                    };
+                    values.push((name.to_string(), value));
+                }
+                None => positional.push(arg.clone()),
+            }
+        }
+        Ok(Self { values, positional })
+    }
+
+    pub(crate) fn take(&mut self, name: &str) -> Result<Option<String>, String> {
+        match self.values.iter().position(|(n, _)| n == name) {
+            Some(index) => match self.values.remove(index).1 {
+                Some(value) => Ok(Some(value)),
+                None => Err(format!("--{} needs a value", name)),
+            },
+            None => Ok(None),
+        }
+    }
+
+    pub(crate) fn required(&mut self, name: &str) -> Result<String, String> {
+        self.take(name)?
+            .filter(|v| !v.trim().is_empty())
+            .ok_or_else(|| format!("--{} is required", name))
+    }
+
+    pub(crate) fn uuid(&mut self, name: &str) -> Result<Option<Uuid>, String> {
+        self.take(name)?
+            .map(|v| Uuid::parse_str(&v).map_err(|_| format!("--{} must be a UUID", name)))
+            .transpose()
+    }
+
+    pub(crate) fn flag(&mut self, name: &str) -> bool {
+        match self.values.iter().position(|(n, _)| n == name) {
+            Some(index) => {
+                let (_, value) = self.values.remove(index);
+                // A flag swallows a following positional argument; give it back
+                self.positional.extend(value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Reject anything the command didn't use
+    pub(crate) fn finish(self) -> Result<(), String> {
+        if let Some((name, _)) = self.values.first() {
+            return Err(format!("Unknown option --{}", name));
+        }
+        if let Some(arg) = self.positional.first() {
+            return Err(format!("Unexpected argument '{}'", arg));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        Command::parse(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_commands_are_parsed() {
+        assert_eq!(parse(&[]).unwrap(), Command::Help);
+        assert_eq!(
+            parse(&[
+                "create-superadmin",
+                "--email",
+                "ops@example.com",
+                "--username=ops"
+            ])
+            .unwrap(),
+            Command::CreateSuperadmin {
+                email: "ops@example.com".to_string(),
+                username: "ops".to_string(),
+            }
+        );
+        assert_eq!(
+            parse(&[
+                "import-ontology",
+                "--publish",
+                "--input",
+                "o.json",
+                "--version",
+                "2.0"
+            ])
+            .unwrap(),
+            Command::ImportOntology {
+                input: "o.json".to_string(),
+                version: "2.0".to_string(),
+                publish: true,
+            }
+        );
+        assert_eq!(
+            parse(&[
+                "apply-ontology",
+                "--input",
+                "desired.json",
+                "--prune",
+                "--fingerprint=ab12"
+            ])
+            .unwrap(),
+            Command::ApplyOntology {
+                input: "desired.json".to_string(),
+                prune: true,
+                fingerprint: Some("ab12".to_string()),
+            }
+        );
+        assert_eq!(parse(&["verify-schema"]).unwrap(), Command::VerifySchema);
+        assert_eq!(
+            parse(&["run-retention"]).unwrap(),
+            Command::RunRetention {
+                jobs: RetentionJob::ALL.to_vec()
+            }
+        );
+        assert_eq!(
+            parse(&["run-retention", "uploads", "firefighter"]).unwrap(),
+            Command::RunRetention {
+                jobs: vec![RetentionJob::Uploads, RetentionJob::Firefighter]
+            }
+        );
+        assert_eq!(
+            parse(&[
+                "grant-break-glass",
+                "--user",
+                "ops@example.com",
+                "--justification",
+                "Restore access",
+                "--minutes",
+                "30"
+            ])
+            .unwrap(),
+            Command::GrantBreakGlass {
+                user: "ops@example.com".to_string(),
+                justification: "Restore access".to_string(),
+                minutes: Some(30),
+            }
+        );
+        let tenant = Uuid::new_v4();
+        assert_eq!(
+            parse(&[
+                "import-bundle",
+                "--dry-run",
+                "--input",
+                "b.json",
+                "--tenant",
+                &tenant.to_string()
+            ])
+            .unwrap(),
+            Command::ImportBundle {
+                input: "b.json".to_string(),
+                tenant: Some(tenant),
+                dry_run: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_bad_arguments_are_rejected() {
+        assert!(parse(&["create-superadmin", "--email", "ops@example.com"]).is_err());
+        assert!(parse(&["export-ontology", "--output"]).is_err());
+        assert!(parse(&["migrate", "--force"]).is_err());
+        assert!(parse(&["migrate", "now"]).is_err());
+        assert!(parse(&["run-retention", "audit"]).is_err());
+        assert!(parse(&[
+            "grant-break-glass",
+            "--user",
+            "a",
+            "--justification",
+            "b",
+            "--minutes",
+            "x"
+        ])
+        .is_err());
+        assert!(parse(&["seed", "tenants"]).is_err());
+        assert!(parse(&["export-bundle", "--tenant", "acme"]).is_err());
+        assert!(parse(&["import-bundle", "--dry-run"]).is_err());
+        assert!(parse(&["drop-database"]).is_err());
+    }
+}
@@ -0,0 +1,454 @@
+use super::Admin;
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
+    Argon2,
+};
+use serde_json::{json, Value};
+use sqlx::{PgConnection, Postgres, Transaction};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Demo tenants; fixed ids so a second run can tell the data is there
+pub const DEMO_TENANTS: [(Uuid, &str, &str); 2] = [
+    (
+        Uuid::from_u128(0x0d3e_0000_0000_4000_8000_0000_0000_0001),
+        "Northwind Logistics",
+        "northwind",
+    ),
+    (
+        Uuid::from_u128(0x0d3e_0000_0000_4000_8000_0000_0000_0002),
+        "Contoso Relief",
+        "contoso",
+    ),
+];
+
+/// Every tenant gets one user per role
+const DEMO_ROLES: [&str; 3] = ["admin", "editor", "viewer"];
+
+/// What the seed created; the password is shared by every demo user
+#[derive(Debug, Clone)]
+pub struct SeedSummary {
+    /// (email, role) of each demo user
+    pub users: Vec<(String, String)>,
+    pub classes: usize,
+    pub projects: usize,
+    pub entities: usize,
+}
+
+struct DemoClass {
+    name: &'static str,
+    description: &'static str,
+    /// (name, data type, required, class a reference points at)
+    properties: &'static [(&'static str, &'static str, bool, Option<&'static str>)],
+}
+
+const DEMO_CLASSES: [DemoClass; 3] = [
+    DemoClass {
+        name: "Site",
+        description: "A warehouse, depot or field location",
+        properties: &[
+            ("address", "string", true, None),
+            ("capacity", "integer", false, None),
+        ],
+    },
+    DemoClass {
+        name: "Vehicle",
+        description: "A truck or van stationed at a site",
+        properties: &[
+            ("registration", "string", true, None),
+            ("payload_kg", "number", false, None),
+            ("home_site", "uuid", false, Some("Site")),
+        ],
+    },
+    DemoClass {
+        name: "Shipment",
+        description: "Goods moving between two sites",
+        properties: &[
+            ("status", "string", true, None),
+            ("origin", "uuid", false, Some("Site")),
+            ("destination", "uuid", false, Some("Site")),
+            ("vehicle", "uuid", false, Some("Vehicle")),
+        ],
+    },
+];
+
+impl Admin {
+    /// Populate the database with two tenants, their users, example classes,
+    /// entities and projects, all in one transaction. Refuses to run twice,
+    /// and on a database that already has users unless `force` is set.
+    pub async fn seed_demo(&self, password: &str, force: bool) -> Result<SeedSummary, String> {
+        let mut tx = self.pool.begin().await.map_err(|e| e.to_string())?;
+        let seeded = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM entities WHERE tenant_id = $1)",
+        )
+        .bind(DEMO_TENANTS[0].0)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+        if seeded {
+            return Err("The demo data is already there".to_string());
+        }
+        let users = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM unified_users")
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+        if users > 0 && !force {
+            return Err(
+                "The database already has users; pass --force to add the demo data anyway"
+                    .to_string(),
+            );
+        }
+
+        let mut seed = Seed::load(&mut tx, password)
+            .await
+            .map_err(|e| e.to_string())?;
+        seed.run(&mut tx).await.map_err(|e| e.to_string())?;
+        tx.commit().await.map_err(|e| e.to_string())?;
+        Ok(seed.summary)
+    }
+}
+
+/// Ids the seed looks up or creates as it goes
+struct Seed {
+    password_hash: String,
+    system_classes: HashMap<&'static str, Uuid>,
+    relationship_types: HashMap<String, Uuid>,
+    roles: HashMap<String, Uuid>,
+    demo_classes: HashMap<&'static str, Uuid>,
+    summary: SeedSummary,
+}
+
+impl Seed {
+    async fn load(tx: &mut Transaction<'_, Postgres>, password: &str) -> Result<Self, sqlx::Error> {
+        let password_hash = Argon2::default()
+            .hash_password(password.as_bytes(), &SaltString::generate(&mut OsRng))
+            .map_err(|e| sqlx::Error::Protocol(e.to_string()))?
+            .to_string();
+
+        let mut system_classes = HashMap::new();
+        for name in ["User", "Role", "Project", "Task"] {
+            let id = sqlx::query_scalar::<_, Uuid>(
+                r#"
+                SELECT c.id FROM classes c
+                JOIN ontology_versions ov ON ov.id = c.version_id
+                WHERE ov.is_system AND c.name = $1
+                "#,
+            )
+            .bind(name)
+            .fetch_one(&mut **tx)
+            .await?;
+            system_classes.insert(name, id);
+        }
+        let relationship_types =
+            sqlx::query_as::<_, (String, Uuid)>("SELECT name, id FROM relationship_types")
+                .fetch_all(&mut **tx)
+                .await?
+                .into_iter()
+                .collect();
+        let roles = sqlx::query_as::<_, (String, Uuid)>(
+            "SELECT display_name, id FROM entities WHERE class_id = $1 AND deleted_at IS NULL",
+        )
+        .bind(system_classes["Role"])
+        .fetch_all(&mut **tx)
+        .await?
+        .into_iter()
+        .collect();
+
+        Ok(Self {
+            password_hash,
+            system_classes,
+            relationship_types,
+            roles,
+            demo_classes: HashMap::new(),
+            summary: SeedSummary {
+                users: Vec::new(),
+                classes: 0,
+                projects: 0,
+                entities: 0,
+            },
+        })
+    }
+
+    async fn run(&mut self, tx: &mut Transaction<'_, Postgres>) -> Result<(), sqlx::Error> {
+        self.create_classes(tx).await?;
+        for (tenant_id, name, slug) in DEMO_TENANTS {
+            self.seed_tenant(tx, tenant_id, name, slug).await?;
+        }
+        Ok(())
+    }
+
+    /// Shared by both tenants, in the current published version
+    async fn create_classes(
+        &mut self,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<(), sqlx::Error> {
+        let version_id = sqlx::query_scalar::<_, Uuid>(
+            "SELECT id FROM ontology_versions WHERE is_current = TRUE",
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+
+        for class in &DEMO_CLASSES {
+            let existing = sqlx::query_scalar::<_, Uuid>(
+                "SELECT id FROM classes WHERE name = $1 AND version_id = $2 AND tenant_id IS NULL",
+            )
+            .bind(class.name)
+            .bind(version_id)
+            .fetch_optional(&mut **tx)
+            .await?;
+            let class_id = match existing {
+                Some(id) => id,
+                None => {
+                    sqlx::query_scalar::<_, Uuid>(
+                        r#"
+                        INSERT INTO classes (name, description, version_id)
+                        VALUES ($1, $2, $3)
+                        RETURNING id
+                        "#,
+                    )
+                    .bind(class.name)
+                    .bind(class.description)
+                    .bind(version_id)
+                    .fetch_one(&mut **tx)
+                    .await?
+                }
+            };
+            self.demo_classes.insert(class.name, class_id);
+
+            for (name, data_type, required, reference) in class.properties {
+                sqlx::query(
+                    r#"
+                    INSERT INTO properties (name, class_id, data_type, reference_class_id,
+                                            is_required, version_id)
+                    SELECT $1, $2, $3, $4, $5, $6
+                    WHERE NOT EXISTS (SELECT 1 FROM properties WHERE class_id = $2 AND name = $1)
+                    "#,
+                )
+                .bind(name)
+                .bind(class_id)
+                .bind(data_type)
+                .bind(reference.map(|r| self.demo_classes[r]))
+                .bind(required)
+                .bind(version_id)
+                .execute(&mut **tx)
+                .await?;
+            }
+            self.summary.classes += 1;
+        }
+        Ok(())
+    }
+
+    async fn seed_tenant(
+        &mut self,
+        tx: &mut Transaction<'_, Postgres>,
+        tenant_id: Uuid,
+        tenant_name: &str,
+        slug: &str,
+    ) -> Result<(), sqlx::Error> {
+        // Users, one per role
+        let mut users = HashMap::new();
+        for role in DEMO_ROLES {
+            let username = format!("{}.{}", slug, role);
+            let email = format!("{}@{}.example", role, slug);
+            let user_id = self
+                .entity(
+                    tx,
+                    self.system_classes["User"],
+                    &username,
+                    json!({
+                        "email": email,
+                        "username": username,
+                        "password_hash": self.password_hash,
+                        "custom_attributes": {},
+                    }),
+                    tenant_id,
+                    None,
+                )
+                .await?;
+            self.grant_role(tx, user_id, role).await?;
+            users.insert(role, user_id);
+            self.summary.users.push((email, role.to_string()));
+        }
+
+        // Sites, vehicles and a shipment between them
+        let hub = self
+            .entity(
+                tx,
+                self.demo_classes["Site"],
+                &format!("{} Central Hub", tenant_name),
+                json!({ "address": "1 Harbour Road", "capacity": 1200 }),
+                tenant_id,
+                None,
+            )
+            .await?;
+        let depot = self
+            .entity(
+                tx,
+                self.demo_classes["Site"],
+                &format!("{} North Depot", tenant_name),
+                json!({ "address": "48 Ridge Street", "capacity": 300 }),
+                tenant_id,
+                None,
+            )
+            .await?;
+        let mut vehicles = Vec::new();
+        for (n, payload) in [(1, 3500), (2, 7500)] {
+            vehicles.push(
+                self.entity(
+                    tx,
+                    self.demo_classes["Vehicle"],
+                    &format!("{}-TRK-{:02}", slug.to_uppercase(), n),
+                    json!({
+                        "registration": format!("{}-{:04}", &slug[..2].to_uppercase(), 1000 + n),
+                        "payload_kg": payload,
+                        "home_site": hub,
+                    }),
+                    tenant_id,
+                    Some(hub),
+                )
+                .await?,
+            );
+        }
+        let shipment = self
+            .entity(
+                tx,
+                self.demo_classes["Shipment"],
+                &format!("{} weekly resupply", tenant_name),
+                json!({
+                    "status": "in_transit",
+                    "origin": hub,
+                    "destination": depot,
+                    "vehicle": vehicles[0],
+                }),
+                tenant_id,
+                None,
+            )
+            .await?;
+        self.relate(tx, "contains", hub, vehicles[0]).await?;
+        self.relate(tx, "contains", hub, vehicles[1]).await?;
+        self.relate(tx, "depends_on", shipment, vehicles[0]).await?;
+
+        // A project owned by the tenant admin with the others as members
+        let project = self
+            .entity(
+                tx,
+                self.system_classes["Project"],
+                &format!("{} depot expansion", tenant_name),
+                json!({
+                    "description": "Double the capacity of the north depot",
+                    "status": "active",
+                    "start_date": "2026-09-01",
+                    "end_date": "2027-03-31",
+                }),
+                tenant_id,
+                None,
+            )
+            .await?;
+        self.summary.projects += 1;
+        self.relate(tx, "owns_project", users["admin"], project)
+            .await?;
+        self.relate(tx, "member_of_project", users["editor"], project)
+            .await?;
+        self.relate(tx, "member_of_project", users["viewer"], project)
+            .await?;
+        for (title, status, assignee) in [
+            ("Survey the site", "done", "admin"),
+            ("Order racking", "in_progress", "editor"),
+            ("Hire forklift drivers", "todo", "editor"),
+        ] {
+            let task = self
+                .entity(
+                    tx,
+                    self.system_classes["Task"],
+                    title,
+                    json!({ "status": status, "priority": "medium" }),
+                    tenant_id,
+                    Some(project),
+                )
+                .await?;
+            self.relate(tx, "has_task", project, task).await?;
+            self.relate(tx, "assigned_to", task, users[assignee])
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn entity(
+        &mut self,
+        conn: &mut PgConnection,
+        class_id: Uuid,
+        display_name: &str,
+        attributes: Value,
+        tenant_id: Uuid,
+        parent_entity_id: Option<Uuid>,
+    ) -> Result<Uuid, sqlx::Error> {
+        let id = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            INSERT INTO entities (class_id, display_name, attributes, tenant_id,
+                                  parent_entity_id, approval_status)
+            VALUES ($1, $2, $3, $4, $5, 'APPROVED')
+            RETURNING id
+            "#,
+        )
+        .bind(class_id)
+        .bind(display_name)
+        .bind(attributes)
+        .bind(tenant_id)
+        .bind(parent_entity_id)
+        .fetch_one(conn)
+        .await?;
+        self.summary.entities += 1;
+        Ok(id)
+    }
+
+    async fn relate(
+        &self,
+        conn: &mut PgConnection,
+        relationship_type: &str,
+        source: Uuid,
+        target: Uuid,
+    ) -> Result<(), sqlx::Error> {
+        let Some(type_id) = self.relationship_types.get(relationship_type) else {
+            return Err(sqlx::Error::Protocol(format!(
+                "Relationship type '{}' not found",
+                relationship_type
+            )));
+        };
+        sqlx::query(
+            r#"
+            INSERT INTO relationships (source_entity_id, target_entity_id, relationship_type_id)
+            VALUES ($1, $2, $3)
+            "#,
+        )
+        .bind(source)
+        .bind(target)
+        .bind(type_id)
+        .execute(conn)
+        .await?;
+        Ok(())
+    }
+
+    async fn grant_role(
+        &self,
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        role: &str,
+    ) -> Result<(), sqlx::Error> {
+        let Some(role_id) = self.roles.get(role) else {
+            return Err(sqlx::Error::Protocol(format!("Role '{}' not found", role)));
+        };
+        sqlx::query(
+            r#"
+            INSERT INTO relationships (source_entity_id, target_entity_id, relationship_type_id, metadata)
+            VALUES ($1, $2, $3, $4)
+            "#,
+        )
+        .bind(user_id)
+        .bind(role_id)
+        .bind(self.relationship_types["has_role"])
+        .bind(json!({ "scope_entity_id": null, "is_deny": false, "granted_by": null }))
+        .execute(conn)
+        .await?;
+        Ok(())
+    }
+}
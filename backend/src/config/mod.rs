@@ -1,5 +1,5 @@
 use dotenv::dotenv;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
 use std::env;
 
 #[derive(Debug, Deserialize, Clone)]
@@ -10,13 +10,1103 @@ pub struct Config {
     pub refresh_token_expiry: i64,
     pub jwt_private_key: String,
     pub jwt_public_key: String,
+    #[serde(default)]
+    pub security_headers: SecurityHeadersConfig,
+    #[serde(default)]
+    pub cors: CorsConfig,
+    #[serde(default)]
+    pub body_limits: BodyLimitsConfig,
+    #[serde(default)]
+    pub ip_access: IpAccessConfig,
+    #[serde(default)]
+    pub geoip: GeoIpConfig,
+    #[serde(default)]
+    pub shutdown: ShutdownConfig,
+    #[serde(default)]
+    pub tls: TlsConfig,
+    #[serde(default)]
+    pub route_limits: RouteLimitsConfig,
+    #[serde(default)]
+    pub ai_circuit_breaker: CircuitBreakerConfig,
+    #[serde(default)]
+    pub outbound_http: OutboundHttpConfig,
+    #[serde(default)]
+    pub csrf: CsrfConfig,
+    #[serde(default)]
+    pub uploads: UploadsConfig,
+    #[serde(default)]
+    pub semantic_search: SemanticSearchConfig,
+    #[serde(default)]
+    pub duplicate_detection: DuplicateDetectionConfig,
+    #[serde(default)]
+    pub ai_conversations: AiConversationsConfig,
+    #[serde(default)]
+    pub ai_alert_explanations: AiAlertExplanationsConfig,
+    #[serde(default)]
+    pub ai_redaction: AiRedactionConfig,
+    #[serde(default)]
+    pub ai_providers: AiProvidersConfig,
+    #[serde(default)]
+    pub api: ApiConfig,
+    #[serde(default)]
+    pub firefighter: FirefighterConfig,
+    #[serde(default)]
+    pub test_mode: TestModeConfig,
+    #[serde(default)]
+    pub email: EmailConfig,
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+    #[serde(default)]
+    pub web_push: WebPushConfig,
+    #[serde(default)]
+    pub graph_sync: GraphSyncConfig,
+    #[serde(default)]
+    pub search_index: SearchIndexConfig,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default)]
+    pub storage: StorageConfig,
+    #[serde(default)]
+    pub ingestion: IngestionConfig,
+    #[serde(default)]
+    pub decision_log: DecisionLogConfig,
+    #[serde(default)]
+    pub permissions: PermissionsConfig,
+    #[serde(default)]
+    pub schema: SchemaConfig,
+}
+
+/// Entity embeddings for semantic search and the indexer that maintains them.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct SemanticSearchConfig {
+    /// Model passed to the AI provider's embeddings endpoint
+    pub embedding_model: String,
+    pub index_interval_secs: u64,
+    /// Entities embedded per provider call
+    pub batch_size: i64,
+    /// Results less similar than this (cosine, 0-1) are dropped
+    pub min_similarity: f64,
+    /// Handled ontology change events are deleted after this long
+    pub event_retention_hours: i64,
+}
+
+impl Default for SemanticSearchConfig {
+    fn default() -> Self {
+        Self {
+            embedding_model: "nomic-embed-text".to_string(),
+            index_interval_secs: 300,
+            batch_size: 64,
+            min_similarity: 0.3,
+            event_retention_hours: 72,
+        }
+    }
+}
+
+/// Background job that queues likely duplicate entities for merge review.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct DuplicateDetectionConfig {
+    pub enabled: bool,
+    pub interval_secs: u64,
+    /// Pairs scoring below this (0-1) are not suggested
+    pub min_score: f64,
+}
+
+impl Default for DuplicateDetectionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_secs: 3600,
+            min_score: 0.85,
+        }
+    }
+}
+
+/// Stored AI chat conversations and how long they are kept.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct AiConversationsConfig {
+    /// Conversations idle longer than this are deleted; 0 keeps them forever
+    pub retention_days: u32,
+    pub cleanup_interval_secs: u64,
+    /// Most recent messages sent to the model as context for a new reply
+    pub context_messages: i64,
+    pub max_message_chars: usize,
+}
+
+impl Default for AiConversationsConfig {
+    fn default() -> Self {
+        Self {
+            retention_days: 90,
+            cleanup_interval_secs: 3600,
+            context_messages: 20,
+            max_message_chars: 8000,
+        }
+    }
+}
+
+/// AI summaries attached to raised security alerts.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct AiAlertExplanationsConfig {
+    /// Explain every alert as it is raised; on-demand explanations work either way
+    pub enabled: bool,
+    /// Most recent correlated events included in the prompt
+    pub max_events: usize,
+}
+
+impl Default for AiAlertExplanationsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_events: 25,
+        }
+    }
+}
+
+/// Pseudonymization of personal data in prompts sent to AI providers.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct AiRedactionConfig {
+    pub enabled: bool,
+    /// Also redact for providers on this machine, which are trusted by default
+    pub redact_local_providers: bool,
+    /// Put the original values back into responses
+    pub restore_responses: bool,
+    /// Classes whose entities' display names are treated as personal names
+    pub name_classes: Vec<String>,
+}
+
+impl Default for AiRedactionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            redact_local_providers: false,
+            restore_responses: true,
+            name_classes: vec!["User".to_string(), "Person".to_string()],
+        }
+    }
+}
+
+/// Which `AiProvider` entities may be given an API key.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct AiProvidersConfig {
+    /// Hosts a provider's `api_base` must be on (over https) for its key to
+    /// be sent; subdomains match too. Empty sends keys nowhere.
+    pub key_hosts: Vec<String>,
+}
+
+impl Default for AiProvidersConfig {
+    fn default() -> Self {
+        Self {
+            key_hosts: vec![
+                "api.openai.com".to_string(),
+                "api.anthropic.com".to_string(),
+            ],
+        }
+    }
+}
+
+/// File uploads and the local storage backend they are streamed to.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct UploadsConfig {
+    /// Directory resumable uploads are assembled in; complete files go to `[storage]`
+    pub storage_dir: String,
+    pub max_file_bytes: u64,
+    /// Largest single chunk of a resumable upload
+    pub max_chunk_bytes: u64,
+    /// Unfinished resumable uploads are discarded after this long
+    pub session_ttl_hours: i64,
+}
+
+impl Default for UploadsConfig {
+    fn default() -> Self {
+        Self {
+            storage_dir: "data/uploads".to_string(),
+            max_file_bytes: 100 * 1024 * 1024,
+            max_chunk_bytes: 8 * 1024 * 1024,
+            session_ttl_hours: 24,
+        }
+    }
+}
+
+/// CSRF cookie attributes and which requests skip the double-submit check.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct CsrfConfig {
+    /// "strict", "lax" or "none" ("none" always sets Secure)
+    pub same_site: String,
+    pub secure: bool,
+    pub max_age_secs: i64,
+    /// Requests carrying one of these headers, and no session cookie, authenticate
+    /// without cookies (API clients). `authorization` must hold a valid bearer
+    /// token and `x-api-key` an active API key.
+    #[serde(deserialize_with = "string_list")]
+    pub exempt_headers: Vec<String>,
+    /// Path prefixes that never need a token, e.g. signed webhook receivers
+    #[serde(deserialize_with = "string_list")]
+    pub exempt_paths: Vec<String>,
+}
+
+impl Default for CsrfConfig {
+    fn default() -> Self {
+        Self {
+            same_site: "lax".to_string(),
+            secure: cfg!(not(debug_assertions)),
+            max_age_secs: 3600,
+            exempt_headers: vec!["authorization".to_string(), "x-api-key".to_string()],
+            exempt_paths: Vec::new(),
+        }
+    }
+}
+
+/// Retries and timeout budgets for calls to other services (webhooks, AI providers).
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct OutboundHttpConfig {
+    /// Retries after the first attempt; 0 disables retrying
+    pub max_retries: u32,
+    /// First backoff ceiling, doubled per retry up to `max_backoff_ms`
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    pub attempt_timeout_secs: u64,
+    /// Budget for all attempts and backoff together
+    pub total_timeout_secs: u64,
+    /// AI generation is slow, so it gets its own limits (keep them below the `/api/ai` route timeout)
+    pub ai_attempt_timeout_secs: u64,
+    pub ai_total_timeout_secs: u64,
+}
+
+impl Default for OutboundHttpConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff_ms: 200,
+            max_backoff_ms: 2000,
+            attempt_timeout_secs: 10,
+            total_timeout_secs: 30,
+            ai_attempt_timeout_secs: 90,
+            ai_total_timeout_secs: 110,
+        }
+    }
+}
+
+/// When to stop calling a failing dependency and for how long.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failures that open the breaker
+    pub failure_threshold: u32,
+    /// How long calls fail fast before a single probe is let through
+    pub open_secs: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            open_secs: 30,
+        }
+    }
+}
+
+/// API versions clients may ask for and the deprecation timeline of old routes,
+/// advertised at /api/discovery/capabilities.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct ApiConfig {
+    /// Version served under /api, sent in the `API-Version` response header
+    pub current_version: String,
+    /// Versions accepted in a request's `Accept-Version` header; must include the current one
+    #[serde(deserialize_with = "string_list")]
+    pub supported_versions: Vec<String>,
+    pub deprecations: Vec<ApiDeprecation>,
+}
+
+/// A deprecated route group. The longest matching prefix wins.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ApiDeprecation {
+    pub prefix: String,
+    /// Only requests with this method; every method when unset
+    #[serde(default)]
+    pub method: Option<String>,
+    pub deprecated_at: chrono::NaiveDate,
+    /// Requests are refused with 410 Gone from this date
+    #[serde(default)]
+    pub sunset_at: Option<chrono::NaiveDate>,
+    /// Where clients should move to, sent as a successor-version link
+    #[serde(default)]
+    pub replacement: Option<String>,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            current_version: "1".to_string(),
+            supported_versions: vec!["1".to_string()],
+            deprecations: Vec::new(),
+        }
+    }
+}
+
+/// Handler timeouts and concurrency caps per route group. The longest matching
+/// prefix wins; everything else gets the defaults. 0 disables either limit.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct RouteLimitsConfig {
+    pub default_timeout_secs: u64,
+    pub default_max_concurrent: usize,
+    pub routes: Vec<RouteLimit>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct RouteLimit {
+    pub prefix: String,
+    #[serde(default)]
+    pub timeout_secs: u64,
+    #[serde(default)]
+    pub max_concurrent: usize,
+}
+
+impl Default for RouteLimitsConfig {
+    fn default() -> Self {
+        Self {
+            default_timeout_secs: 30,
+            default_max_concurrent: 0,
+            routes: vec![
+                RouteLimit {
+                    prefix: "/api/auth".to_string(),
+                    timeout_secs: 10,
+                    max_concurrent: 0,
+                },
+                RouteLimit {
+                    prefix: "/api/ai".to_string(),
+                    timeout_secs: 120,
+                    max_concurrent: 8,
+                },
+                RouteLimit {
+                    prefix: "/api/environment".to_string(),
+                    timeout_secs: 300,
+                    max_concurrent: 1,
+                },
+            ],
+        }
+    }
+}
+
+/// Optional HTTPS termination for deployments without a reverse proxy.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct TlsConfig {
+    pub enabled: bool,
+    /// PEM certificate chain and private key; unused when `acme_domains` is set
+    pub cert_path: String,
+    pub key_path: String,
+    /// How often the certificate files are checked for rotation
+    pub reload_interval_secs: u64,
+    /// Obtain certificates from Let's Encrypt (TLS-ALPN-01, so the listener must
+    /// be reachable on port 443) instead of reading them from disk
+    #[serde(deserialize_with = "string_list")]
+    pub acme_domains: Vec<String>,
+    /// Contact email addresses for the ACME account
+    #[serde(deserialize_with = "string_list")]
+    pub acme_contacts: Vec<String>,
+    /// Where issued certificates and the account key are kept between restarts
+    pub acme_cache_dir: String,
+    /// Use the production directory; the staging one is rate-limit friendly for testing
+    pub acme_production: bool,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cert_path: String::new(),
+            key_path: String::new(),
+            reload_interval_secs: 300,
+            acme_domains: Vec::new(),
+            acme_contacts: Vec::new(),
+            acme_cache_dir: "acme-cache".to_string(),
+            acme_production: false,
+        }
+    }
+}
+
+/// Deadlines for graceful shutdown. Keep their sum below the orchestrator's
+/// grace period (30s by default in Kubernetes) so the process exits cleanly.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct ShutdownConfig {
+    /// How long in-flight requests may run after SIGTERM
+    pub drain_timeout_secs: u64,
+    /// How long running background jobs may take to finish afterwards
+    pub task_timeout_secs: u64,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            drain_timeout_secs: 20,
+            task_timeout_secs: 5,
+        }
+    }
+}
+
+/// Country lookups for geo access policies.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct GeoIpConfig {
+    /// Path to a MaxMind-format country database (e.g. GeoLite2-Country.mmdb);
+    /// empty disables lookups, leaving every client's country unknown
+    pub database_path: String,
+}
+
+/// Limits on firefighter (break-glass) sessions and the job that ends them.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct FirefighterConfig {
+    /// Used when a request doesn't say how long it needs
+    pub default_duration_minutes: i32,
+    pub min_duration_minutes: i32,
+    /// Hard ceiling; sessions older than this are revoked even if they were
+    /// granted for longer under a previous setting
+    pub max_duration_minutes: i32,
+    /// Holders are notified this long before their session ends
+    pub expiry_warning_minutes: i32,
+    pub sweep_interval_secs: u64,
+}
+
+impl Default for FirefighterConfig {
+    fn default() -> Self {
+        Self {
+            default_duration_minutes: 60,
+            min_duration_minutes: 15,
+            max_duration_minutes: 480,
+            expiry_warning_minutes: 10,
+            sweep_interval_secs: 60,
+        }
+    }
+}
+
+/// Outgoing email: which provider sends it and how the delivery worker
+/// retries. Secrets are read from the environment variables named here.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct EmailConfig {
+    /// "log" (append to `log_path`, for development), "smtp", "ses" or "sendgrid"
+    pub provider: String,
+    pub from_address: String,
+    pub from_name: String,
+    /// Frontend base URL that links in messages point to
+    pub app_url: String,
+    pub log_path: String,
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    /// "starttls", "tls" (implicit TLS, usually port 465) or "none"
+    pub smtp_security: String,
+    /// Leave empty for relays that don't authenticate
+    pub smtp_username: String,
+    pub smtp_password_env: String,
+    pub ses_region: String,
+    pub ses_access_key_id_env: String,
+    pub ses_secret_access_key_env: String,
+    /// Only needed with temporary credentials
+    pub ses_session_token_env: String,
+    pub sendgrid_api_key_env: String,
+    pub sendgrid_api_base: String,
+    pub worker_interval_secs: u64,
+    /// Messages sent per worker pass
+    pub batch_size: i64,
+    pub max_attempts: i32,
+    /// Wait before the first retry; doubles with each attempt up to `retry_max_secs`
+    pub retry_base_secs: i64,
+    pub retry_max_secs: i64,
+}
+
+impl Default for EmailConfig {
+    fn default() -> Self {
+        Self {
+            provider: "log".to_string(),
+            from_address: "no-reply@localhost".to_string(),
+            from_name: "Ontology Manager".to_string(),
+            app_url: "http://localhost:5373".to_string(),
+            log_path: "data/emails.log".to_string(),
+            smtp_host: "localhost".to_string(),
+            smtp_port: 587,
+            smtp_security: "starttls".to_string(),
+            smtp_username: String::new(),
+            smtp_password_env: "SMTP_PASSWORD".to_string(),
+            ses_region: "us-east-1".to_string(),
+            ses_access_key_id_env: "AWS_ACCESS_KEY_ID".to_string(),
+            ses_secret_access_key_env: "AWS_SECRET_ACCESS_KEY".to_string(),
+            ses_session_token_env: "AWS_SESSION_TOKEN".to_string(),
+            sendgrid_api_key_env: "SENDGRID_API_KEY".to_string(),
+            sendgrid_api_base: "https://api.sendgrid.com".to_string(),
+            worker_interval_secs: 10,
+            batch_size: 50,
+            max_attempts: 5,
+            retry_base_secs: 30,
+            retry_max_secs: 3600,
+        }
+    }
+}
+
+/// The job that emails daily and weekly notification digests.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct NotificationsConfig {
+    /// How often to look for users whose digest is due
+    pub digest_check_interval_secs: u64,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            digest_check_interval_secs: 900,
+        }
+    }
+}
+
+/// Web Push (VAPID) delivery of high-priority notifications to browsers.
+/// Disabled until a key pair is configured.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct WebPushConfig {
+    /// Uncompressed P-256 public key, base64url; browsers subscribe with it
+    pub vapid_public_key: String,
+    /// Environment variable holding the matching 32-byte private key, base64url
+    pub vapid_private_key_env: String,
+    /// Contact for push services, a mailto: or https: URL
+    pub subject: String,
+    /// How long push services keep a message for an offline browser
+    pub ttl_secs: u32,
+    /// Push service hosts subscriptions may point at; subdomains match too.
+    /// Empty allows any host.
+    pub allowed_hosts: Vec<String>,
+    /// Accept plain-HTTP endpoints, for local push service emulators
+    pub allow_insecure_endpoints: bool,
+    pub max_subscriptions_per_user: i64,
+    /// Consecutive failed sends before a subscription is dropped
+    pub max_failures: i32,
+}
+
+impl Default for WebPushConfig {
+    fn default() -> Self {
+        Self {
+            vapid_public_key: String::new(),
+            vapid_private_key_env: "VAPID_PRIVATE_KEY".to_string(),
+            subject: "mailto:admin@example.com".to_string(),
+            ttl_secs: 86400,
+            allowed_hosts: vec![
+                "fcm.googleapis.com".to_string(),
+                "push.services.mozilla.com".to_string(),
+                "notify.windows.com".to_string(),
+                "push.apple.com".to_string(),
+            ],
+            allow_insecure_endpoints: false,
+            max_subscriptions_per_user: 10,
+            max_failures: 5,
+        }
+    }
+}
+
+/// Mirror of entities and relationships in a Neo4j (Bolt) graph database,
+/// kept current from the ontology change feed.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct GraphSyncConfig {
+    pub enabled: bool,
+    /// Bolt URI, e.g. bolt://localhost:7687 or neo4j+s://graph.example.com
+    pub uri: String,
+    pub username: String,
+    /// Environment variable holding the password
+    pub password_env: String,
+    /// Target database; empty uses the server's default
+    pub database: String,
+    pub sync_interval_secs: u64,
+    /// Entities or relationships written per graph transaction
+    pub batch_size: i64,
+    /// Also copy attributes of sensitive properties
+    pub include_sensitive: bool,
+}
+
+impl Default for GraphSyncConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            uri: "bolt://localhost:7687".to_string(),
+            username: "neo4j".to_string(),
+            password_env: "NEO4J_PASSWORD".to_string(),
+            database: String::new(),
+            sync_interval_secs: 60,
+            batch_size: 500,
+            include_sensitive: false,
+        }
+    }
+}
+
+/// Elasticsearch or OpenSearch indexes of entities and audit events, kept
+/// current from the ontology change feed.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct SearchIndexConfig {
+    pub enabled: bool,
+    /// Cluster base URL, e.g. http://localhost:9200
+    pub url: String,
+    /// Basic auth user; leave empty to use an API key or no authentication
+    pub username: String,
+    pub password_env: String,
+    /// Environment variable holding an Elasticsearch API key (base64 id:key)
+    pub api_key_env: String,
+    /// Indexes are named `<prefix>-entities` and `<prefix>-audit`
+    pub index_prefix: String,
+    pub sync_interval_secs: u64,
+    /// Documents sent per bulk request
+    pub batch_size: i64,
+    /// Quick search and audit log search query the index instead of Postgres
+    pub delegate_search: bool,
+    /// Also index attributes of sensitive properties
+    pub include_sensitive: bool,
+}
+
+impl Default for SearchIndexConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: "http://localhost:9200".to_string(),
+            username: String::new(),
+            password_env: "SEARCH_INDEX_PASSWORD".to_string(),
+            api_key_env: "SEARCH_INDEX_API_KEY".to_string(),
+            index_prefix: "ontology".to_string(),
+            sync_interval_secs: 60,
+            batch_size: 500,
+            delegate_search: true,
+            include_sensitive: false,
+        }
+    }
+}
+
+/// Cache for hot reads: system classes, relationship types and navigation
+/// and dashboard aggregates. Shared through Redis when `redis_url` is set,
+/// otherwise kept in each process.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct CacheConfig {
+    pub enabled: bool,
+    /// e.g. redis://localhost:6379/0; empty keeps the cache in process
+    pub redis_url: String,
+    /// Prepended to every Redis key, so instances can share a database
+    pub key_prefix: String,
+    /// Upper bound on staleness of ontology lookups changed outside this service
+    pub ontology_ttl_secs: u64,
+    /// How long counts for the sidebar and dashboards are reused
+    pub aggregate_ttl_secs: u64,
+    /// Redis calls taking longer than this are skipped and the value is loaded
+    pub timeout_ms: u64,
+    /// Entries kept by the in-process cache
+    pub local_capacity: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            redis_url: String::new(),
+            key_prefix: "ontology-manager".to_string(),
+            ontology_ttl_secs: 300,
+            aggregate_ttl_secs: 15,
+            timeout_ms: 250,
+            local_capacity: 10_000,
+        }
+    }
+}
+
+/// Consumers that apply entity upserts from upstream systems of record,
+/// read from NATS JetStream or polled from an HTTP endpoint.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct IngestionConfig {
+    pub enabled: bool,
+    /// Records of handled events, which make redelivery harmless, are kept this long
+    pub event_retention_days: i64,
+    pub sources: Vec<IngestionSourceConfig>,
+}
+
+impl Default for IngestionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            event_retention_days: 30,
+            sources: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct IngestionSourceConfig {
+    /// Unique; external ids are scoped to their source
+    pub name: String,
+    /// "nats" or "http"
+    pub kind: String,
+    /// NATS server URL, or the endpoint polled with `?cursor=&limit=`
+    pub url: String,
+    /// JetStream stream to consume from (nats)
+    #[serde(default)]
+    pub stream: String,
+    /// Only messages on this subject, e.g. "cmdb.entities.>"; empty for all (nats)
+    #[serde(default)]
+    pub subject: String,
+    /// Durable consumer name, so delivery resumes after a restart (nats);
+    /// defaults to `ontology-<name>`
+    #[serde(default)]
+    pub durable_name: String,
+    /// Environment variable holding a bearer token (http) or credentials token (nats)
+    #[serde(default)]
+    pub token_env: String,
+    /// Entities are created in this tenant; unset for the shared ontology
+    #[serde(default)]
+    pub tenant_id: Option<uuid::Uuid>,
+    #[serde(default = "default_ingestion_poll_interval")]
+    pub poll_interval_secs: u64,
+    #[serde(default = "default_ingestion_batch_size")]
+    pub batch_size: usize,
+}
+
+fn default_ingestion_poll_interval() -> u64 {
+    10
+}
+
+fn default_ingestion_batch_size() -> usize {
+    100
+}
+
+/// Structured records of permission decisions, sent to a sink separate from
+/// the audit log so they can be kept and analyzed at volume.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct DecisionLogConfig {
+    pub enabled: bool,
+    /// "log" (tracing events on the `authz_decision` target), "file" (JSON
+    /// lines appended to `path`) or "http" (batches POSTed to `url`)
+    pub sink: String,
+    pub path: String,
+    pub url: String,
+    /// Environment variable holding a bearer token for the http sink
+    pub token_env: String,
+    /// Share of allowed decisions recorded, from 0.0 to 1.0
+    pub sample_rate: f64,
+    /// Share of denied decisions recorded
+    pub deny_sample_rate: f64,
+    /// Decisions waiting for the sink; more are dropped rather than slowing checks down
+    pub buffer_size: usize,
+    /// Decisions written to the sink at a time
+    pub batch_size: usize,
+    pub flush_interval_ms: u64,
+}
+
+impl Default for DecisionLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sink: "log".to_string(),
+            path: "logs/decisions.jsonl".to_string(),
+            url: String::new(),
+            token_env: String::new(),
+            sample_rate: 1.0,
+            deny_sample_rate: 1.0,
+            buffer_size: 10_000,
+            batch_size: 500,
+            flush_interval_ms: 1000,
+        }
+    }
+}
+
+/// Permission checks: how long resolved lookups are kept, and whether checks
+/// read the trigger-maintained permission_grants table.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct PermissionsConfig {
+    /// Check against permission_grants instead of joining roles to permissions per call
+    pub denormalized_grants: bool,
+    /// How long the has_role/grants_permission ids and permission levels are kept
+    pub kernel_cache_ttl_secs: u64,
+    /// How long a check result is reused for the same user, entity and permission
+    pub check_cache_ttl_secs: u64,
+    pub check_cache_capacity: u64,
+}
+
+impl Default for PermissionsConfig {
+    fn default() -> Self {
+        Self {
+            denormalized_grants: false,
+            kernel_cache_ttl_secs: 60,
+            check_cache_ttl_secs: 30,
+            check_cache_capacity: 10_000,
+        }
+    }
+}
+
+/// What startup does when the database schema differs from this build.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct SchemaConfig {
+    /// Refuse to start on pending, modified or unknown migrations or missing
+    /// SQL functions; when off the problems are only logged
+    pub fail_on_drift: bool,
+}
+
+impl Default for SchemaConfig {
+    fn default() -> Self {
+        Self {
+            fail_on_drift: true,
+        }
+    }
+}
+
+/// Where uploaded files and export bundles are kept: on local disk or in an
+/// S3-compatible bucket. Secrets are read from the environment variables named here.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct StorageConfig {
+    /// "local" or "s3"
+    pub backend: String,
+    /// Root directory of the local backend; keys are prefixed by use (uploads/, exports/)
+    pub local_dir: String,
+    /// This server's /api/storage as clients reach it, for presigned local download URLs
+    pub public_url: String,
+    /// Key for signing local download URLs; unset uses a random key per process,
+    /// so URLs stop working on restart and aren't valid on other instances
+    pub url_signing_key_env: String,
+    /// How long presigned download URLs stay valid
+    pub presign_ttl_secs: u64,
+    pub s3_bucket: String,
+    pub s3_region: String,
+    /// Leave empty for AWS; e.g. http://localhost:9000 for MinIO
+    pub s3_endpoint: String,
+    /// Address the bucket as endpoint/bucket instead of bucket.endpoint, as most
+    /// self-hosted stores expect
+    pub s3_path_style: bool,
+    pub s3_access_key_id_env: String,
+    pub s3_secret_access_key_env: String,
+    /// Only needed with temporary credentials
+    pub s3_session_token_env: String,
+    /// Objects larger than this are sent as a multipart upload in parts of this size
+    pub s3_part_size_bytes: u64,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            backend: "local".to_string(),
+            local_dir: "data/uploads".to_string(),
+            public_url: "http://localhost:5300/api/storage".to_string(),
+            url_signing_key_env: "STORAGE_URL_SIGNING_KEY".to_string(),
+            presign_ttl_secs: 900,
+            s3_bucket: String::new(),
+            s3_region: "us-east-1".to_string(),
+            s3_endpoint: String::new(),
+            s3_path_style: false,
+            s3_access_key_id_env: "AWS_ACCESS_KEY_ID".to_string(),
+            s3_secret_access_key_env: "AWS_SECRET_ACCESS_KEY".to_string(),
+            s3_session_token_env: "AWS_SESSION_TOKEN".to_string(),
+            s3_part_size_bytes: 8 * 1024 * 1024,
+        }
+    }
+}
+
+/// Test environment tooling.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct TestModeConfig {
+    /// Database snapshots and restores. Restoring replaces every table, so
+    /// only turn this on for QA environments.
+    pub snapshots_enabled: bool,
+    /// Loading fixtures. A fixture creates users, roles and relationships
+    /// from whatever it is given, so only turn this on for test environments.
+    pub fixtures_enabled: bool,
+    /// Per-session clock overrides for the temporal permission checks. An
+    /// override brings future or expired grants into effect for its user,
+    /// so only turn this on for test environments.
+    pub mock_clock_enabled: bool,
+}
+
+/// Settings for IP allow/deny enforcement (the rules themselves live in the database).
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct IpAccessConfig {
+    /// Peers whose X-Forwarded-For / X-Real-IP headers are believed, in CIDR notation
+    #[serde(deserialize_with = "string_list")]
+    pub trusted_proxies: Vec<String>,
+}
+
+impl Default for IpAccessConfig {
+    fn default() -> Self {
+        Self {
+            trusted_proxies: vec!["127.0.0.1/32".to_string(), "::1/128".to_string()],
+        }
+    }
+}
+
+/// Request body size limits. The longest matching route prefix wins;
+/// everything else gets `default_bytes`.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct BodyLimitsConfig {
+    pub default_bytes: usize,
+    pub routes: Vec<RouteBodyLimit>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct RouteBodyLimit {
+    pub prefix: String,
+    pub max_bytes: usize,
+}
+
+impl Default for BodyLimitsConfig {
+    fn default() -> Self {
+        Self {
+            default_bytes: 1024 * 1024,
+            routes: vec![
+                RouteBodyLimit {
+                    prefix: "/api/auth".to_string(),
+                    max_bytes: 16 * 1024,
+                },
+                // Multipart overhead on top of uploads.max_file_bytes
+                RouteBodyLimit {
+                    prefix: "/api/uploads".to_string(),
+                    max_bytes: 101 * 1024 * 1024,
+                },
+                // Environment bundles carry every entity of a tenant or instance
+                RouteBodyLimit {
+                    prefix: "/api/environment".to_string(),
+                    max_bytes: 64 * 1024 * 1024,
+                },
+            ],
+        }
+    }
+}
+
+/// Cross-origin settings. Lists accept TOML arrays or comma-separated env values,
+/// e.g. `APP_CORS__ALLOWED_ORIGINS=https://app.example.com,https://admin.example.com`.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct CorsConfig {
+    /// Use `*` to allow any origin (only valid when credentials are disabled)
+    #[serde(deserialize_with = "string_list")]
+    pub allowed_origins: Vec<String>,
+    #[serde(deserialize_with = "string_list")]
+    pub allowed_methods: Vec<String>,
+    #[serde(deserialize_with = "string_list")]
+    pub allowed_headers: Vec<String>,
+    #[serde(deserialize_with = "string_list")]
+    pub exposed_headers: Vec<String>,
+    pub allow_credentials: bool,
+    /// Preflight cache lifetime in seconds; 0 leaves it to the browser
+    pub max_age_secs: u64,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        let list = |items: &[&str]| items.iter().map(|s| s.to_string()).collect();
+        Self {
+            allowed_origins: list(&[
+                "http://localhost:5373",
+                "http://localhost:3000",
+                "http://127.0.0.1:5373",
+                "http://127.0.0.1:3000",
+            ]),
+            allowed_methods: list(&["GET", "POST", "PUT", "DELETE", "OPTIONS"]),
+            allowed_headers: list(&[
+                "content-type",
+                "authorization",
+                "cookie",
+                "set-cookie",
+                "accept",
+                "x-csrf-token",
+                "idempotency-key",
+                "if-none-match",
+                "accept-version",
+            ]),
+            exposed_headers: list(&[
+                "etag",
+                "idempotent-replayed",
+                "api-version",
+                "deprecation",
+                "sunset",
+                "link",
+            ]),
+            allow_credentials: true,
+            max_age_secs: 0,
+        }
+    }
+}
+
+/// Accept either a list or a single comma-separated string
+fn string_list<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringList {
+        List(Vec<String>),
+        Csv(String),
+    }
+
+    let items = match StringList::deserialize(deserializer)? {
+        StringList::List(items) => items,
+        StringList::Csv(s) => s.split(',').map(|p| p.to_string()).collect(),
+    };
+    Ok(items
+        .into_iter()
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect())
+}
+
+/// Browser security headers added to every response.
+/// Override per deployment, e.g. `APP_SECURITY_HEADERS__HSTS_MAX_AGE=0` behind plain HTTP.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct SecurityHeadersConfig {
+    pub enabled: bool,
+    /// The API only serves JSON, so nothing needs to load by default
+    pub content_security_policy: String,
+    /// Appended to the CSP unless it already contains a frame-ancestors directive
+    pub frame_ancestors: String,
+    /// Strict-Transport-Security max-age in seconds; 0 disables the header
+    pub hsts_max_age: u64,
+    pub hsts_include_subdomains: bool,
+    pub referrer_policy: String,
+}
+
+impl Default for SecurityHeadersConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            content_security_policy: "default-src 'none'; base-uri 'none'".to_string(),
+            frame_ancestors: "'none'".to_string(),
+            hsts_max_age: 31_536_000,
+            hsts_include_subdomains: false,
+            referrer_policy: "no-referrer".to_string(),
+        }
+    }
 }
 
 impl Config {
     pub fn from_env() -> Result<Self, config::ConfigError> {
         let mut builder = config::Config::builder()
             .add_source(config::File::with_name("config/default"))
-            .add_source(config::Environment::with_prefix("APP"));
+            .add_source(
+                config::Environment::with_prefix("APP")
+                    .prefix_separator("_")
+                    .separator("__"),
+            );
 
         if let Ok(env) = env::var("RUN_MODE") {
             builder = builder
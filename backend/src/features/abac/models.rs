@@ -2,6 +2,9 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
+use validator::Validate;
+
+use crate::utils::validation::not_blank;
 
 /// Represents an area/scope within the application (e.g., a project, team, module)
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -74,8 +77,10 @@ pub struct AssignRoleInput {
 }
 
 /// Input for creating a new resource
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreateResourceInput {
+    #[validate(custom = "not_blank", length(max = 255))]
     pub name: String,
+    #[validate(custom = "not_blank")]
     pub resource_type: String,
 }
@@ -3,6 +3,10 @@ use super::models::{
 };
 use super::service::AbacService;
 use crate::features::auth::jwt::Claims;
+use crate::middleware::abac::{
+    enforce_route_permissions, route_permission_report, RoutePermission, RoutePermissionEntry,
+};
+use crate::utils::validation::{not_blank, ValidatedJson};
 use axum::{
     extract::{Path, State},
     http::StatusCode,
@@ -11,18 +15,36 @@ use axum::{
 };
 use serde::Deserialize;
 use uuid::Uuid;
+use validator::Validate;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Validate)]
 pub struct CreateRoleInput {
+    #[validate(custom = "not_blank", length(max = 255))]
     pub name: String,
     pub description: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Validate)]
 pub struct AddPermissionInput {
+    #[validate(custom = "not_blank")]
     pub action: String,
 }
 
+/// Permissions enforced by `enforce_route_permissions` for the routes below
+pub const ROUTE_PERMISSIONS: &[RoutePermission] = &[
+    RoutePermission::new("GET", "/roles", "read"),
+    RoutePermission::new("POST", "/roles", "manage_users"),
+    RoutePermission::new("GET", "/resources", "read"),
+    RoutePermission::new("POST", "/resources", "manage_users"),
+    RoutePermission::new("GET", "/users/:user_id/roles", "read"),
+    RoutePermission::new("POST", "/users/:user_id/roles", "manage_users"),
+    RoutePermission::new("DELETE", "/users/roles/:id", "manage_users"),
+    RoutePermission::new("GET", "/permissions/:role_id", "read"),
+    RoutePermission::new("POST", "/permissions/:role_id", "manage_users"),
+    RoutePermission::new("DELETE", "/permissions/delete/:id", "manage_users"),
+    RoutePermission::new("GET", "/route-permissions", "read"),
+];
+
 pub fn abac_routes() -> Router<AbacService> {
     Router::new()
         .route("/roles", get(list_roles).post(create_role))
@@ -37,6 +59,16 @@ pub fn abac_routes() -> Router<AbacService> {
             get(get_role_permissions).post(add_permission),
         )
         .route("/permissions/delete/:id", delete(remove_permission))
+        .route("/route-permissions", get(list_route_permissions))
+        .route_layer(axum::middleware::from_fn_with_state(
+            ROUTE_PERMISSIONS,
+            enforce_route_permissions,
+        ))
+}
+
+/// Which permission each declared route requires
+async fn list_route_permissions() -> Json<Vec<RoutePermissionEntry>> {
+    Json(route_permission_report())
 }
 
 async fn list_roles(State(abac): State<AbacService>) -> Result<Json<Vec<Role>>, StatusCode> {
@@ -48,7 +80,7 @@ async fn list_roles(State(abac): State<AbacService>) -> Result<Json<Vec<Role>>,
 
 async fn create_role(
     State(abac): State<AbacService>,
-    Json(input): Json<CreateRoleInput>,
+    ValidatedJson(input): ValidatedJson<CreateRoleInput>,
 ) -> Result<Json<Role>, StatusCode> {
     abac.create_role(&input.name, input.description.as_deref())
         .await
@@ -67,7 +99,7 @@ async fn list_resources(
 
 async fn create_resource(
     State(abac): State<AbacService>,
-    Json(input): Json<CreateResourceInput>,
+    ValidatedJson(input): ValidatedJson<CreateResourceInput>,
 ) -> Result<Json<Resource>, StatusCode> {
     abac.create_resource(input)
         .await
@@ -134,7 +166,7 @@ async fn get_role_permissions(
 async fn add_permission(
     State(abac): State<AbacService>,
     Path(role_id): Path<String>,
-    Json(input): Json<AddPermissionInput>,
+    ValidatedJson(input): ValidatedJson<AddPermissionInput>,
 ) -> Result<Json<Permission>, StatusCode> {
     abac.add_permission(&role_id, &input.action)
         .await
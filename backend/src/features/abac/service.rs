@@ -1,6 +1,9 @@
 use super::models::{
     AssignRoleInput, CreateResourceInput, Permission, Resource, Role, UserRole, UserRoleAssignment,
 };
+use crate::utils::api_error::ApiError;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
 use chrono::Utc;
 use sqlx::{Pool, Postgres};
 use uuid::Uuid;
@@ -30,6 +33,23 @@ impl From<sqlx::Error> for AbacError {
     }
 }
 
+impl From<AbacError> for ApiError {
+    fn from(err: AbacError) -> Self {
+        let (status, code) = match &err {
+            AbacError::NotFound(_) => (StatusCode::NOT_FOUND, "abac.not_found"),
+            AbacError::InvalidInput(_) => (StatusCode::BAD_REQUEST, "abac.invalid_input"),
+            AbacError::DatabaseError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+        };
+        ApiError::new(status, code, err.to_string())
+    }
+}
+
+impl IntoResponse for AbacError {
+    fn into_response(self) -> Response {
+        ApiError::from(self).into_response()
+    }
+}
+
 use crate::features::ontology::OntologyService;
 use crate::features::rebac::RebacService;
 
@@ -345,18 +365,26 @@ impl AbacService {
         .fetch_all(&self.pool)
         .await?;
 
+        let ids: Vec<Uuid> = rels.iter().map(|rel| rel.target_entity_id).collect();
+        let perm_entities = self
+            .ontology_service
+            .get_entities(&ids)
+            .await
+            .map_err(|e| AbacError::DatabaseError(e.to_string()))?;
+
         let mut results = Vec::new();
         for rel in rels {
-            let perm_entity = self
-                .ontology_service
-                .get_entity(rel.target_entity_id)
-                .await
-                .map_err(|e| AbacError::DatabaseError(e.to_string()))?;
+            let perm_entity = perm_entities.get(&rel.target_entity_id).ok_or_else(|| {
+                AbacError::DatabaseError(format!(
+                    "Not found: Entity {} not found",
+                    rel.target_entity_id
+                ))
+            })?;
 
             results.push(Permission {
                 id: rel.id,
                 role_id: role_uuid,
-                action: perm_entity.display_name,
+                action: perm_entity.display_name.clone(),
                 created_at: rel.created_at,
             });
         }
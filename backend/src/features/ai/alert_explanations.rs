@@ -0,0 +1,340 @@
+use super::audit_qa::REDACTED_KEYS;
+use super::prompts::{BuiltinPrompt, PromptService};
+use super::service::{AiError, AiService, GenerateRequest};
+use crate::config::AiAlertExplanationsConfig;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::{FromRow, Pool, Postgres};
+use uuid::Uuid;
+
+/// Shown with every explanation, wherever the alert is displayed
+pub const MACHINE_GENERATED_NOTICE: &str =
+    "Generated by an AI model from the correlated events. Verify before acting on it.";
+
+/// Longest event details included in the prompt, per event
+const MAX_DETAILS_CHARS: usize = 500;
+
+pub const ALERT_EXPLANATION_PROMPT: BuiltinPrompt = BuiltinPrompt {
+    name: "alert_explanation",
+    description: "Explains a raised security alert and suggests next steps",
+    body: r#"You assist a security analyst with an alert raised by a monitoring rule.
+
+Alert: {{alert}}
+
+Correlated security events, most recent first:
+{{events}}
+
+Using ONLY these events, explain what they suggest is happening and how serious it looks.
+Then suggest concrete next steps for the analyst.
+
+Respond ONLY with a JSON object:
+{ "summary": "...", "next_steps": ["...", "..."] }"#,
+    variables: &[
+        ("alert", "Rule name, description and event count"),
+        (
+            "events",
+            "One line per event: time, type, severity, outcome and details",
+        ),
+    ],
+};
+
+/// Machine-generated explanation stored on an alert. The marker and notice
+/// travel with it so no consumer can present it as analyst-written.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertExplanation {
+    pub machine_generated: bool,
+    pub notice: String,
+    pub summary: String,
+    pub next_steps: Vec<String>,
+    pub generated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct SecurityAlert {
+    pub id: Uuid,
+    pub rule_id: Uuid,
+    pub rule_name: String,
+    pub event_count: i64,
+    pub event_ids: Vec<Uuid>,
+    pub message: String,
+    pub raised_at: DateTime<Utc>,
+    pub ai_explanation: Option<Json<AlertExplanation>>,
+    pub ai_error: Option<String>,
+}
+
+/// A correlated event as shown to the model
+#[derive(Debug, FromRow)]
+struct AlertEvent {
+    event_type: String,
+    severity: String,
+    outcome: String,
+    user_id: Option<Uuid>,
+    ip_address: Option<String>,
+    resource: Option<String>,
+    action: Option<String>,
+    details: Option<serde_json::Value>,
+    detected_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawExplanation {
+    summary: String,
+    #[serde(default)]
+    next_steps: Vec<String>,
+}
+
+/// Extract the model's JSON, which may be wrapped in prose or code fences
+fn parse_explanation(text: &str) -> Result<RawExplanation, AiError> {
+    let json = match (text.find('{'), text.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &text[start..=end],
+        _ => {
+            return Err(AiError::InvalidSuggestion(
+                "response contains no explanation".to_string(),
+            ))
+        }
+    };
+    let raw: RawExplanation = serde_json::from_str(json)
+        .map_err(|e| AiError::InvalidSuggestion(format!("malformed explanation: {}", e)))?;
+    if raw.summary.trim().is_empty() {
+        return Err(AiError::InvalidSuggestion(
+            "explanation has no summary".to_string(),
+        ));
+    }
+    Ok(raw)
+}
+
+/// Event details with secret-looking keys removed, cut to a prompt-sized length
+fn redacted_details(details: &serde_json::Value) -> String {
+    let mut details = details.clone();
+    if let Some(obj) = details.as_object_mut() {
+        obj.retain(|key, _| {
+            let lower = key.to_lowercase();
+            !REDACTED_KEYS.iter().any(|r| lower.contains(r))
+        });
+        if obj.is_empty() {
+            return String::new();
+        }
+    }
+    let text = details.to_string();
+    if text.chars().count() > MAX_DETAILS_CHARS {
+        format!(
+            "{}…",
+            text.chars().take(MAX_DETAILS_CHARS).collect::<String>()
+        )
+    } else {
+        text
+    }
+}
+
+fn format_events(events: &[AlertEvent]) -> String {
+    if events.is_empty() {
+        return "none recorded".to_string();
+    }
+    events
+        .iter()
+        .map(|e| {
+            let mut line = format!(
+                "- {} {} ({}, {})",
+                e.detected_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                e.event_type,
+                e.severity,
+                e.outcome
+            );
+            if let Some(user_id) = e.user_id {
+                line.push_str(&format!(" user={}", user_id));
+            }
+            if let Some(ip) = &e.ip_address {
+                line.push_str(&format!(" ip={}", ip));
+            }
+            if let Some(resource) = &e.resource {
+                line.push_str(&format!(" resource={}", resource));
+            }
+            if let Some(action) = &e.action {
+                line.push_str(&format!(" action={}", action));
+            }
+            let details = e.details.as_ref().map(redacted_details).unwrap_or_default();
+            if !details.is_empty() {
+                line.push_str(&format!(" details={}", details));
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Attaches AI explanations and suggested next steps to raised security alerts.
+#[derive(Clone)]
+pub struct AlertExplanationService {
+    pool: Pool<Postgres>,
+    ai: AiService,
+    prompts: PromptService,
+    config: AiAlertExplanationsConfig,
+}
+
+impl AlertExplanationService {
+    pub fn new(
+        pool: Pool<Postgres>,
+        ai: AiService,
+        prompts: PromptService,
+        config: AiAlertExplanationsConfig,
+    ) -> Self {
+        Self {
+            pool,
+            ai,
+            prompts,
+            config,
+        }
+    }
+
+    pub async fn get(&self, alert_id: Uuid) -> Result<SecurityAlert, AiError> {
+        sqlx::query_as::<_, SecurityAlert>("SELECT * FROM security_alerts WHERE id = $1")
+            .bind(alert_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| AiError::Failed(e.to_string()))?
+            .ok_or_else(|| AiError::NotFound("Alert not found".to_string()))
+    }
+
+    /// Explain a newly raised alert without delaying its notification, if
+    /// automatic explanations are enabled
+    pub fn explain_in_background(&self, alert_id: Uuid) {
+        if !self.config.enabled {
+            return;
+        }
+        let svc = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = svc.explain(alert_id).await {
+                tracing::warn!("Could not explain alert {}: {}", alert_id, e);
+            }
+        });
+    }
+
+    /// Summarize the alert's correlated events and store the result on the
+    /// alert, replacing any earlier explanation. Failures are stored too.
+    pub async fn explain(&self, alert_id: Uuid) -> Result<SecurityAlert, AiError> {
+        let alert = self.get(alert_id).await?;
+        let events = sqlx::query_as::<_, AlertEvent>(
+            r#"
+            SELECT event_type, severity, outcome, user_id, host(ip_address) AS ip_address,
+                   resource, action, details, detected_at
+            FROM security_events
+            WHERE id = ANY($1)
+            ORDER BY detected_at DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(&alert.event_ids)
+        .bind(self.config.max_events as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))?;
+
+        let description: Option<String> =
+            sqlx::query_scalar("SELECT description FROM alert_rules WHERE id = $1")
+                .bind(alert.rule_id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| AiError::Failed(e.to_string()))?
+                .flatten();
+        let summary_line = format!(
+            "{}{} ({} events)",
+            alert.rule_name,
+            description.map(|d| format!(": {}", d)).unwrap_or_default(),
+            alert.event_count
+        );
+
+        let prompt = self
+            .prompts
+            .render(
+                &ALERT_EXPLANATION_PROMPT,
+                &[("alert", summary_line), ("events", format_events(&events))],
+            )
+            .await;
+        let generated = self
+            .ai
+            .generate_text(GenerateRequest {
+                prompt,
+                temperature: Some(0.2),
+                max_tokens: Some(800),
+            })
+            .await
+            .and_then(|response| parse_explanation(&response.text));
+
+        let raw = match generated {
+            Ok(raw) => raw,
+            Err(e) => {
+                let _ = sqlx::query("UPDATE security_alerts SET ai_error = $2 WHERE id = $1")
+                    .bind(alert_id)
+                    .bind(e.to_string())
+                    .execute(&self.pool)
+                    .await;
+                return Err(e);
+            }
+        };
+        let explanation = AlertExplanation {
+            machine_generated: true,
+            notice: MACHINE_GENERATED_NOTICE.to_string(),
+            summary: raw.summary.trim().to_string(),
+            next_steps: raw
+                .next_steps
+                .into_iter()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            generated_at: Utc::now(),
+        };
+
+        sqlx::query_as::<_, SecurityAlert>(
+            r#"
+            UPDATE security_alerts SET ai_explanation = $2, ai_error = NULL
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(alert_id)
+        .bind(Json(&explanation))
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_events_redacts_secret_details() {
+        let events = vec![AlertEvent {
+            event_type: "privilege_escalation".to_string(),
+            severity: "critical".to_string(),
+            outcome: "success".to_string(),
+            user_id: None,
+            ip_address: Some("10.0.0.7".to_string()),
+            resource: Some("roles".to_string()),
+            action: None,
+            details: Some(serde_json::json!({ "role": "superadmin", "session_token": "abc" })),
+            detected_at: Utc::now(),
+        }];
+
+        let text = format_events(&events);
+        assert!(text.contains("privilege_escalation (critical, success) ip=10.0.0.7"));
+        assert!(text.contains("superadmin"));
+        assert!(!text.contains("abc"));
+        assert_eq!(format_events(&[]), "none recorded");
+    }
+
+    #[test]
+    fn test_parse_explanation_requires_a_summary() {
+        let raw = parse_explanation(
+            "Here you go:\n```json\n{ \"summary\": \"Likely brute force\", \"next_steps\": [\"Block the IP\"] }\n```",
+        )
+        .unwrap();
+        assert_eq!(raw.summary, "Likely brute force");
+        assert_eq!(raw.next_steps, vec!["Block the IP"]);
+
+        assert!(parse_explanation("{ \"summary\": \" \" }").is_err());
+        assert!(parse_explanation("no idea").is_err());
+    }
+}
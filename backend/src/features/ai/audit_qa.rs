@@ -0,0 +1,616 @@
+use super::prompts::{BuiltinPrompt, PromptService};
+use super::service::{AiError, AiService, GenerateRequest};
+use crate::features::system::AuditService;
+use crate::utils::validation::not_blank;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, Pool, Postgres};
+use std::collections::HashSet;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Window searched when neither the request nor the question gives one
+const DEFAULT_WINDOW_DAYS: i64 = 30;
+const DEFAULT_EVENTS: i64 = 40;
+const MAX_EVENTS: i64 = 100;
+
+/// Longest name, in words, looked up from the question
+const MAX_NAME_WORDS: usize = 4;
+
+/// State keys never shown to the model
+pub(super) const REDACTED_KEYS: &[&str] = &["password", "secret", "token", "hash", "mfa"];
+
+/// Words that never identify an entity or action, space-separated
+const STOPWORDS: &str = "a about all an and any are at by did do does for from has have how in is last me my of on or past show that the this to was were what when where which who whom why with week month today yesterday day days weeks months hours";
+
+fn is_stopword(word: &str) -> bool {
+    STOPWORDS.split_whitespace().any(|s| s == word)
+}
+
+/// Question words and the audit actions they refer to
+const ACTION_SYNONYMS: &[(&str, &[&str])] = &[
+    ("changed", &["update", "change"]),
+    ("modified", &["update", "change"]),
+    ("updated", &["update"]),
+    ("edited", &["update"]),
+    ("created", &["create"]),
+    ("added", &["create", "add", "assign"]),
+    ("deleted", &["delete"]),
+    ("removed", &["delete", "remove", "revoke"]),
+    ("granted", &["grant", "assign"]),
+    ("assigned", &["assign"]),
+    ("revoked", &["revoke"]),
+    ("logged", &["login"]),
+    ("permissions", &["permission", "role"]),
+    ("roles", &["role"]),
+    ("merged", &["merge"]),
+];
+
+pub const AUDIT_QA_PROMPT: BuiltinPrompt = BuiltinPrompt {
+    name: "audit_qa",
+    description: "Answers security questions from retrieved audit events",
+    body: r#"You answer questions about an application's audit log.
+Use ONLY the audit events listed below; do not rely on anything else you know.
+Cite every event your answer relies on by its reference, e.g. [E2].
+If the events don't answer the question, say that the audit log doesn't show it.
+
+Events ({{window}}):
+{{events}}
+
+Question: {{question}}
+
+Answer concisely."#,
+    variables: &[
+        ("window", "The time range that was searched"),
+        (
+            "events",
+            "One line per retrieved event, starting with its [E#] reference",
+        ),
+        ("question", "The admin's question"),
+    ],
+};
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct AuditQuestionRequest {
+    #[validate(custom = "not_blank")]
+    pub question: String,
+    /// Overrides any time range in the question
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// Events given to the model, most relevant first
+    pub limit: Option<i64>,
+}
+
+/// An audit event the answer cites
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditCitation {
+    /// "E1", "E2", ... as used in the answer
+    pub reference: String,
+    pub event_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub actor_id: Uuid,
+    pub actor_name: Option<String>,
+    pub action: Option<String>,
+    pub target_type: Option<String>,
+    pub target_id: Option<Uuid>,
+    pub target_name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditAnswer {
+    pub question: String,
+    pub answer: String,
+    /// Events the answer cites, in order of first citation
+    pub citations: Vec<AuditCitation>,
+    /// False when the answer cites no event, so it can't be checked against the log
+    pub grounded: bool,
+    pub events_considered: usize,
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+struct RetrievedEvent {
+    id: Uuid,
+    user_id: Uuid,
+    actor_name: Option<String>,
+    action: Option<String>,
+    target_type: Option<String>,
+    target_id: Option<Uuid>,
+    target_name: Option<String>,
+    before_state: Option<serde_json::Value>,
+    after_state: Option<serde_json::Value>,
+    metadata: Option<serde_json::Value>,
+    created_at: DateTime<Utc>,
+}
+
+impl RetrievedEvent {
+    fn citation(&self, reference: String) -> AuditCitation {
+        AuditCitation {
+            reference,
+            event_id: self.id,
+            created_at: self.created_at,
+            actor_id: self.user_id,
+            actor_name: self.actor_name.clone(),
+            action: self.action.clone(),
+            target_type: self.target_type.clone(),
+            target_id: self.target_id,
+            target_name: self.target_name.clone(),
+        }
+    }
+}
+
+/// Time range named in the question, e.g. "last week" or "past 3 days"
+fn window_from_question(
+    question: &str,
+    now: DateTime<Utc>,
+) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let text = question.to_lowercase();
+    let words: Vec<&str> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+    let start_of_today = now.date_naive().and_hms_opt(0, 0, 0)?.and_utc();
+
+    if words.contains(&"today") {
+        return Some((start_of_today, now));
+    }
+    if words.contains(&"yesterday") {
+        return Some((start_of_today - Duration::days(1), start_of_today));
+    }
+    for pair in words.windows(3) {
+        if let ("last" | "past", Ok(n)) = (pair[0], pair[1].parse::<i64>()) {
+            let span = match pair[2].trim_end_matches('s') {
+                "hour" => Duration::hours(n),
+                "day" => Duration::days(n),
+                "week" => Duration::weeks(n),
+                "month" => Duration::days(30 * n),
+                _ => continue,
+            };
+            return Some((now - span, now));
+        }
+    }
+    for pair in words.windows(2) {
+        if let ("last" | "past" | "this", unit) = (pair[0], pair[1]) {
+            let span = match unit {
+                "hour" => Duration::hours(1),
+                "day" => Duration::days(1),
+                "week" => Duration::weeks(1),
+                "month" => Duration::days(30),
+                "year" => Duration::days(365),
+                _ => continue,
+            };
+            return Some((now - span, now));
+        }
+    }
+    None
+}
+
+/// Word sequences that may name an entity or user, lowercased
+fn name_candidates(question: &str) -> Vec<String> {
+    let words: Vec<String> = question
+        .split(|c: char| c.is_whitespace() || matches!(c, '?' | ',' | '!' | '"' | '\'' | '(' | ')'))
+        .map(|w| w.trim_matches('.').to_lowercase())
+        .filter(|w| !w.is_empty())
+        .collect();
+    let mut names = HashSet::new();
+    for len in 1..=MAX_NAME_WORDS {
+        for window in words.windows(len) {
+            // A lone stopword or very short word is never a useful name
+            if len == 1 && (window[0].len() < 3 || is_stopword(&window[0])) {
+                continue;
+            }
+            names.insert(window.join(" "));
+        }
+    }
+    let mut names: Vec<_> = names.into_iter().collect();
+    names.sort();
+    names
+}
+
+/// Substrings of actions or target types the question asks about
+fn action_terms(question: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    for word in question
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|w| w.len() >= 4 && !is_stopword(w))
+    {
+        match ACTION_SYNONYMS.iter().find(|(w, _)| *w == word) {
+            Some((_, actions)) => terms.extend(actions.iter().map(|a| a.to_string())),
+            None => terms.push(word.trim_end_matches('s').to_string()),
+        }
+    }
+    terms.sort();
+    terms.dedup();
+    terms
+}
+
+fn redact(value: &serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => map
+            .iter()
+            .map(|(k, v)| {
+                let lower = k.to_lowercase();
+                if REDACTED_KEYS.iter().any(|r| lower.contains(r)) {
+                    (
+                        k.clone(),
+                        serde_json::Value::String("[redacted]".to_string()),
+                    )
+                } else {
+                    (k.clone(), redact(v))
+                }
+            })
+            .collect(),
+        serde_json::Value::Array(items) => items.iter().map(redact).collect(),
+        other => other.clone(),
+    }
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        text.to_string()
+    } else {
+        format!("{}…", text.chars().take(max).collect::<String>())
+    }
+}
+
+/// Fields that differ between the before and after states, with secret
+/// values redacted but still reported as changed
+fn changed_fields(event: &RetrievedEvent) -> String {
+    let empty = serde_json::Map::new();
+    let before = event
+        .before_state
+        .as_ref()
+        .and_then(|b| b.as_object())
+        .unwrap_or(&empty);
+    let Some(after) = event.after_state.as_ref().and_then(|a| a.as_object()) else {
+        return String::new();
+    };
+    let shown = |key: &str, value: &serde_json::Value| {
+        let lower = key.to_lowercase();
+        if REDACTED_KEYS.iter().any(|r| lower.contains(r)) {
+            "[redacted]".to_string()
+        } else {
+            truncate(&redact(value).to_string(), 60)
+        }
+    };
+    let changes: Vec<String> = after
+        .iter()
+        .filter(|(k, v)| before.get(*k) != Some(*v))
+        .take(8)
+        .map(|(k, v)| match before.get(k) {
+            Some(old) => format!("{}: {} -> {}", k, shown(k, old), shown(k, v)),
+            None => format!("{}: {}", k, shown(k, v)),
+        })
+        .collect();
+    changes.join("; ")
+}
+
+/// One line per event, numbered from E1, for the prompt
+fn format_events(events: &[RetrievedEvent]) -> String {
+    events
+        .iter()
+        .enumerate()
+        .map(|(i, e)| {
+            let mut line = format!(
+                "[E{}] {} | actor: {} ({}) | action: {} | target: {} {}",
+                i + 1,
+                e.created_at.format("%Y-%m-%d %H:%M UTC"),
+                e.actor_name.as_deref().unwrap_or("unknown"),
+                e.user_id,
+                e.action.as_deref().unwrap_or("unknown"),
+                e.target_type.as_deref().unwrap_or("-"),
+                match (&e.target_name, e.target_id) {
+                    (Some(name), Some(id)) => format!("\"{}\" ({})", name, id),
+                    (None, Some(id)) => id.to_string(),
+                    _ => String::new(),
+                }
+            );
+            let changes = changed_fields(e);
+            if !changes.is_empty() {
+                line.push_str(&format!(" | changes: {}", changes));
+            }
+            if let Some(details) = e
+                .metadata
+                .as_ref()
+                .filter(|m| m.as_object().is_some_and(|o| !o.is_empty()))
+            {
+                line.push_str(&format!(
+                    " | details: {}",
+                    truncate(&redact(details).to_string(), 200)
+                ));
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Event indexes (0-based) cited as [E#] in the answer, in order of first
+/// citation; references to events that weren't given are ignored
+fn cited_events(answer: &str, event_count: usize) -> Vec<usize> {
+    let mut cited = Vec::new();
+    let mut rest = answer;
+    while let Some(start) = rest.find("[E") {
+        rest = &rest[start + 2..];
+        let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+        if let Ok(n) = digits.parse::<usize>() {
+            if (1..=event_count).contains(&n) && !cited.contains(&(n - 1)) {
+                cited.push(n - 1);
+            }
+        }
+    }
+    cited
+}
+
+/// Answers admins' questions about the audit log. Relevant events are
+/// retrieved first and the model answers from those alone, citing them.
+#[derive(Clone)]
+pub struct AuditQaService {
+    pool: Pool<Postgres>,
+    ai: AiService,
+    prompts: PromptService,
+    audit: AuditService,
+}
+
+impl AuditQaService {
+    pub fn new(
+        pool: Pool<Postgres>,
+        ai: AiService,
+        prompts: PromptService,
+        audit: AuditService,
+    ) -> Self {
+        Self {
+            pool,
+            ai,
+            prompts,
+            audit,
+        }
+    }
+
+    pub async fn ask(
+        &self,
+        user_id: Uuid,
+        request: AuditQuestionRequest,
+    ) -> Result<AuditAnswer, AiError> {
+        let question = request.question.trim().to_string();
+        if question.is_empty() {
+            return Err(AiError::InvalidInput("question is required".to_string()));
+        }
+        let now = Utc::now();
+        let (since, until) = match (request.since, request.until) {
+            (None, None) => window_from_question(&question, now)
+                .unwrap_or((now - Duration::days(DEFAULT_WINDOW_DAYS), now)),
+            (since, until) => (
+                since.unwrap_or(now - Duration::days(DEFAULT_WINDOW_DAYS)),
+                until.unwrap_or(now),
+            ),
+        };
+        if since >= until {
+            return Err(AiError::InvalidInput(
+                "since must be before until".to_string(),
+            ));
+        }
+        let limit = request.limit.unwrap_or(DEFAULT_EVENTS).clamp(1, MAX_EVENTS);
+
+        let events = self.retrieve(&question, since, until, limit).await?;
+
+        // Reading the audit log through the assistant is itself audited
+        let _ = self
+            .audit
+            .log(
+                user_id,
+                "ai.audit_question",
+                "audit_log",
+                None,
+                None,
+                None,
+                Some(serde_json::json!({
+                    "question": question,
+                    "since": since,
+                    "until": until,
+                    "events": events.iter().map(|e| e.id).collect::<Vec<_>>(),
+                })),
+            )
+            .await;
+
+        // Without events there is nothing to ground an answer in
+        if events.is_empty() {
+            return Ok(AuditAnswer {
+                question,
+                answer: "No audit events in this time range match the question.".to_string(),
+                citations: Vec::new(),
+                grounded: false,
+                events_considered: 0,
+                since,
+                until,
+            });
+        }
+
+        let window = format!(
+            "{} to {}",
+            since.format("%Y-%m-%d %H:%M UTC"),
+            until.format("%Y-%m-%d %H:%M UTC")
+        );
+        let prompt = self
+            .prompts
+            .render(
+                &AUDIT_QA_PROMPT,
+                &[
+                    ("window", window),
+                    ("events", format_events(&events)),
+                    ("question", question.clone()),
+                ],
+            )
+            .await;
+        let response = self
+            .ai
+            .for_user(user_id)
+            .await
+            .generate_text(GenerateRequest {
+                prompt,
+                temperature: Some(0.0),
+                max_tokens: Some(600),
+            })
+            .await?;
+
+        let answer = response.text.trim().to_string();
+        let citations: Vec<AuditCitation> = cited_events(&answer, events.len())
+            .into_iter()
+            .map(|i| events[i].citation(format!("E{}", i + 1)))
+            .collect();
+
+        Ok(AuditAnswer {
+            question,
+            answer,
+            grounded: !citations.is_empty(),
+            citations,
+            events_considered: events.len(),
+            since,
+            until,
+        })
+    }
+
+    /// Events in the window about entities or users named in the question, or
+    /// with matching actions; those matching both rank first, then the newest.
+    /// With nothing to match on, the newest events in the window.
+    async fn retrieve(
+        &self,
+        question: &str,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<RetrievedEvent>, AiError> {
+        let names = name_candidates(question);
+        let named_ids = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            SELECT e.id FROM entities e
+            JOIN classes c ON e.class_id = c.id
+            WHERE c.name <> 'SecurityEvent'
+              AND e.deleted_at IS NULL
+              AND (LOWER(e.display_name) = ANY($1)
+                   OR LOWER(e.attributes->>'username') = ANY($1)
+                   OR LOWER(e.attributes->>'email') = ANY($1))
+            LIMIT 200
+            "#,
+        )
+        .bind(&names)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))?;
+
+        let patterns: Vec<String> = action_terms(question)
+            .into_iter()
+            .map(|t| format!("%{}%", t.replace('%', "").replace('_', "\\_")))
+            .collect();
+
+        sqlx::query_as::<_, RetrievedEvent>(
+            r#"
+            SELECT u.id, u.user_id, actor.display_name AS actor_name, u.action, u.target_type,
+                   u.target_id, target.display_name AS target_name,
+                   u.before_state, u.after_state, u.metadata, u.created_at
+            FROM (
+                SELECT u.*,
+                       (CASE WHEN u.target_id = ANY($3) OR u.user_id = ANY($3) THEN 2 ELSE 0 END
+                        + CASE WHEN u.action ILIKE ANY($4) OR u.target_type ILIKE ANY($4) THEN 1 ELSE 0 END) AS score
+                FROM unified_audit_logs u
+                WHERE u.created_at >= $1 AND u.created_at < $2
+            ) u
+            LEFT JOIN entities actor ON actor.id = u.user_id
+            LEFT JOIN entities target ON target.id = u.target_id
+            WHERE u.score > 0 OR (cardinality($3) = 0 AND cardinality($4) = 0)
+            ORDER BY u.score DESC, u.created_at DESC
+            LIMIT $5
+            "#,
+        )
+        .bind(since)
+        .bind(until)
+        .bind(&named_ids)
+        .bind(&patterns)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn event(before: serde_json::Value, after: serde_json::Value) -> RetrievedEvent {
+        RetrievedEvent {
+            id: Uuid::nil(),
+            user_id: Uuid::nil(),
+            actor_name: Some("alice".to_string()),
+            action: Some("role.update".to_string()),
+            target_type: Some("role".to_string()),
+            target_id: None,
+            target_name: None,
+            before_state: Some(before),
+            after_state: Some(after),
+            metadata: None,
+            created_at: Utc.with_ymd_and_hms(2026, 10, 1, 12, 0, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_window_from_question() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 15, 9, 30, 0).unwrap();
+        assert_eq!(
+            window_from_question("Who changed permissions on Project X last week?", now),
+            Some((now - Duration::weeks(1), now))
+        );
+        assert_eq!(
+            window_from_question("logins in the past 3 days", now),
+            Some((now - Duration::days(3), now))
+        );
+        let midnight = Utc.with_ymd_and_hms(2026, 10, 15, 0, 0, 0).unwrap();
+        assert_eq!(
+            window_from_question("What happened yesterday?", now),
+            Some((midnight - Duration::days(1), midnight))
+        );
+        assert_eq!(window_from_question("Who deleted Project X?", now), None);
+    }
+
+    #[test]
+    fn test_name_candidates_and_action_terms() {
+        let names = name_candidates("Who changed permissions on Project X last week?");
+        assert!(names.contains(&"project x".to_string()));
+        assert!(!names.contains(&"who".to_string()));
+        assert!(!names.contains(&"x".to_string()));
+
+        assert_eq!(
+            action_terms("Who changed permissions on Project X?"),
+            vec!["change", "permission", "project", "role", "update"]
+        );
+    }
+
+    #[test]
+    fn test_changed_fields_redacts_secrets() {
+        let e = event(
+            json!({ "name": "Editor", "password_hash": "abc" }),
+            json!({ "name": "Admin", "password_hash": "def", "level": 3 }),
+        );
+        assert_eq!(
+            changed_fields(&e),
+            "level: 3; name: \"Editor\" -> \"Admin\"; password_hash: [redacted] -> [redacted]"
+        );
+        assert!(format_events(&[e]).starts_with("[E1] 2026-10-01 12:00 UTC | actor: alice"));
+    }
+
+    #[test]
+    fn test_cited_events() {
+        assert_eq!(
+            cited_events(
+                "Alice did it [E2], then again [E2] and [E1]; see [E9] and [Ex]",
+                3
+            ),
+            vec![1, 0]
+        );
+        assert!(cited_events("No idea", 3).is_empty());
+    }
+}
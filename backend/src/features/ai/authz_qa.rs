@@ -0,0 +1,563 @@
+use super::prompts::{BuiltinPrompt, PromptService};
+use super::service::{AiError, AiService, GenerateRequest};
+use crate::features::rebac::RebacService;
+use crate::features::system::AuditService;
+use crate::utils::validation::not_blank;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Postgres};
+use uuid::Uuid;
+use validator::Validate;
+
+/// Users checked when asking who holds a permission; only users with at
+/// least one role assignment can hold one
+const MAX_CANDIDATES: i64 = 200;
+
+pub const AUTHZ_QUERY_PROMPT: BuiltinPrompt = BuiltinPrompt {
+    name: "authz_query",
+    description: "Translates an authorization question into a permission lookup",
+    body: r#"Translate the question about access rights into a JSON lookup.
+
+Permissions: {{permissions}}
+
+Lookup kinds:
+- "who_can": which users hold a permission on an entity. Needs "permission" and "entity".
+- "can_user": whether a user holds a permission on an entity. Needs "user", "permission" and "entity".
+- "user_permissions": every permission a user holds on an entity. Needs "user" and "entity".
+
+Format: { "kind": "<kind>", "permission": "<permission>", "entity": "<entity name>", "user": "<username, email or name>" }
+Leave out fields the kind doesn't need. Use names exactly as they appear in the question.
+
+Question: {{question}}
+
+Respond ONLY with the JSON object."#,
+    variables: &[
+        ("permissions", "Comma-separated permission names"),
+        ("question", "The admin's question"),
+    ],
+};
+
+pub const AUTHZ_ANSWER_PROMPT: BuiltinPrompt = BuiltinPrompt {
+    name: "authz_answer",
+    description: "Answers an authorization question from permission check results",
+    body: r#"You answer questions about who can do what in an application.
+Use ONLY the permission check results below; do not rely on anything else you know.
+Cite every result your answer relies on by its reference, e.g. [F2].
+
+Results:
+{{findings}}
+
+Question: {{question}}
+
+Answer concisely."#,
+    variables: &[
+        (
+            "findings",
+            "One line per permission check result, starting with its [F#] reference",
+        ),
+        ("question", "The admin's question"),
+    ],
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthzQueryKind {
+    WhoCan,
+    CanUser,
+    UserPermissions,
+}
+
+/// The lookup the model translates the question into
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthzQuery {
+    pub kind: AuthzQueryKind,
+    pub permission: Option<String>,
+    pub entity: String,
+    pub user: Option<String>,
+}
+
+impl AuthzQuery {
+    fn validate(&self, permissions: &[String]) -> Result<(), String> {
+        if self.entity.trim().is_empty() {
+            return Err("lookup names no entity".to_string());
+        }
+        let needs_user = self.kind != AuthzQueryKind::WhoCan;
+        if needs_user && self.user.as_deref().is_none_or(|u| u.trim().is_empty()) {
+            return Err("lookup names no user".to_string());
+        }
+        match (&self.permission, self.kind) {
+            (_, AuthzQueryKind::UserPermissions) => Ok(()),
+            (None, _) => Err("lookup names no permission".to_string()),
+            (Some(p), _) if !permissions.contains(p) => Err(format!("unknown permission '{}'", p)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct AuthzQuestionRequest {
+    #[validate(custom = "not_blank")]
+    pub question: String,
+}
+
+/// One permission check, as returned by ReBAC. Each can be re-run with
+/// `GET /rebac/check` to verify the answer.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuthzFinding {
+    /// "F1", "F2", ... as used in the answer
+    pub reference: String,
+    pub user_id: Uuid,
+    pub user_name: String,
+    pub entity_id: Uuid,
+    pub entity_name: String,
+    pub permission: String,
+    pub allowed: bool,
+    pub denied: bool,
+    pub granted_via_role: Option<String>,
+    /// The entity the grant is scoped to; an ancestor when inherited
+    pub granted_via_entity_id: Option<Uuid>,
+    pub is_inherited: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuthzAnswer {
+    pub question: String,
+    /// The lookup the model generated, returned so callers can see what was run
+    pub query: AuthzQuery,
+    pub answer: String,
+    pub findings: Vec<AuthzFinding>,
+    /// Reference of each finding the answer cites
+    pub cited: Vec<String>,
+    /// More users hold roles than were checked
+    pub truncated: bool,
+}
+
+/// Extract the lookup from a model response, which may wrap the JSON in prose or code fences
+pub fn parse_authz_query(text: &str, permissions: &[String]) -> Result<AuthzQuery, AiError> {
+    let json = match (text.find('{'), text.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &text[start..=end],
+        _ => {
+            return Err(AiError::InvalidQuery(
+                "response contains no lookup".to_string(),
+            ))
+        }
+    };
+    let query: AuthzQuery = serde_json::from_str(json)
+        .map_err(|e| AiError::InvalidQuery(format!("malformed lookup: {}", e)))?;
+    query.validate(permissions).map_err(AiError::InvalidQuery)?;
+    Ok(query)
+}
+
+fn format_findings(findings: &[AuthzFinding]) -> String {
+    findings
+        .iter()
+        .map(|f| {
+            let outcome = match (f.allowed, f.denied) {
+                (_, true) => "explicitly denied",
+                (true, _) => "allowed",
+                _ => "not allowed",
+            };
+            let mut line = format!(
+                "[{}] {} '{}' on {}: {}",
+                f.reference, f.user_name, f.permission, f.entity_name, outcome
+            );
+            if let Some(role) = &f.granted_via_role {
+                line.push_str(&format!(" via role {}", role));
+                if f.is_inherited {
+                    line.push_str(" (inherited from a parent entity)");
+                }
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// References cited as [F#] in the answer, in order of first citation;
+/// references to results that weren't given are ignored
+fn cited_findings(answer: &str, findings: &[AuthzFinding]) -> Vec<String> {
+    let mut cited: Vec<String> = Vec::new();
+    let mut rest = answer;
+    while let Some(start) = rest.find("[F") {
+        rest = &rest[start + 2..];
+        let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+        let reference = format!("F{}", digits);
+        if findings.iter().any(|f| f.reference == reference) && !cited.contains(&reference) {
+            cited.push(reference);
+        }
+    }
+    cited
+}
+
+/// Answers admins' questions about access rights. The model only picks the
+/// lookup and words the answer; who holds what comes from ReBAC checks,
+/// returned alongside so the answer can be verified.
+#[derive(Clone)]
+pub struct AuthzQaService {
+    pool: Pool<Postgres>,
+    ai: AiService,
+    rebac: RebacService,
+    prompts: PromptService,
+    audit: AuditService,
+}
+
+impl AuthzQaService {
+    pub fn new(
+        pool: Pool<Postgres>,
+        ai: AiService,
+        rebac: RebacService,
+        prompts: PromptService,
+        audit: AuditService,
+    ) -> Self {
+        Self {
+            pool,
+            ai,
+            rebac,
+            prompts,
+            audit,
+        }
+    }
+
+    pub async fn ask(
+        &self,
+        user_id: Uuid,
+        request: AuthzQuestionRequest,
+    ) -> Result<AuthzAnswer, AiError> {
+        let question = request.question.trim().to_string();
+        if question.is_empty() {
+            return Err(AiError::InvalidInput("question is required".to_string()));
+        }
+        let ai = self.ai.for_user(user_id).await;
+
+        let permissions = self.permission_names().await?;
+        let prompt = self
+            .prompts
+            .render(
+                &AUTHZ_QUERY_PROMPT,
+                &[
+                    ("permissions", permissions.join(", ")),
+                    ("question", question.clone()),
+                ],
+            )
+            .await;
+        let response = ai
+            .generate_text(GenerateRequest {
+                prompt,
+                temperature: Some(0.0),
+                max_tokens: Some(200),
+            })
+            .await?;
+        let query = parse_authz_query(&response.text, &permissions)?;
+
+        let (findings, truncated) = self.run(&query).await?;
+
+        // Looking up other users' access is itself audited
+        let _ = self
+            .audit
+            .log(
+                user_id,
+                "ai.authz_question",
+                "entity",
+                findings.first().map(|f| f.entity_id),
+                None,
+                None,
+                Some(serde_json::json!({ "question": question, "query": query })),
+            )
+            .await;
+
+        if findings.is_empty() {
+            let answer = match query.kind {
+                AuthzQueryKind::UserPermissions => "The user holds no permissions on this entity.",
+                _ => "No user holds that permission on this entity.",
+            };
+            return Ok(AuthzAnswer {
+                question,
+                query,
+                answer: answer.to_string(),
+                findings,
+                cited: Vec::new(),
+                truncated,
+            });
+        }
+
+        let prompt = self
+            .prompts
+            .render(
+                &AUTHZ_ANSWER_PROMPT,
+                &[
+                    ("findings", format_findings(&findings)),
+                    ("question", question.clone()),
+                ],
+            )
+            .await;
+        let response = ai
+            .generate_text(GenerateRequest {
+                prompt,
+                temperature: Some(0.0),
+                max_tokens: Some(600),
+            })
+            .await?;
+        let answer = response.text.trim().to_string();
+
+        Ok(AuthzAnswer {
+            question,
+            query,
+            cited: cited_findings(&answer, &findings),
+            answer,
+            findings,
+            truncated,
+        })
+    }
+
+    /// Names of the system Permission entities
+    async fn permission_names(&self) -> Result<Vec<String>, AiError> {
+        sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT e.display_name FROM entities e
+            JOIN classes c ON c.id = e.class_id
+            WHERE c.name = 'Permission' AND e.deleted_at IS NULL
+            ORDER BY e.display_name
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))
+    }
+
+    async fn run(&self, query: &AuthzQuery) -> Result<(Vec<AuthzFinding>, bool), AiError> {
+        let (entity_id, entity_name) = self.resolve_entity(&query.entity).await?;
+        let rebac_failed = |e: crate::features::rebac::RebacError| AiError::Failed(e.to_string());
+
+        let mut findings = Vec::new();
+        let mut truncated = false;
+        match query.kind {
+            AuthzQueryKind::WhoCan => {
+                let permission = query.permission.as_deref().unwrap_or_default();
+                let mut candidates = self.role_holders().await?;
+                truncated = candidates.len() as i64 > MAX_CANDIDATES;
+                candidates.truncate(MAX_CANDIDATES as usize);
+                for (user_id, user_name) in candidates {
+                    let check = self
+                        .rebac
+                        .check_permission(user_id, entity_id, permission, None, None)
+                        .await
+                        .map_err(rebac_failed)?;
+                    if check.has_permission && !check.is_denied.unwrap_or(false) {
+                        findings.push(AuthzFinding {
+                            reference: String::new(),
+                            user_id,
+                            user_name,
+                            entity_id,
+                            entity_name: entity_name.clone(),
+                            permission: permission.to_string(),
+                            allowed: true,
+                            denied: false,
+                            granted_via_role: check.granted_via_role,
+                            granted_via_entity_id: check.granted_via_entity_id,
+                            is_inherited: check.is_inherited.unwrap_or(false),
+                        });
+                    }
+                }
+            }
+            AuthzQueryKind::CanUser => {
+                let (user_id, user_name) = self
+                    .resolve_user(query.user.as_deref().unwrap_or_default())
+                    .await?;
+                let permission = query.permission.as_deref().unwrap_or_default();
+                let check = self
+                    .rebac
+                    .check_permission(user_id, entity_id, permission, None, None)
+                    .await
+                    .map_err(rebac_failed)?;
+                let denied = check.is_denied.unwrap_or(false);
+                findings.push(AuthzFinding {
+                    reference: String::new(),
+                    user_id,
+                    user_name,
+                    entity_id,
+                    entity_name,
+                    permission: permission.to_string(),
+                    allowed: check.has_permission && !denied,
+                    denied,
+                    granted_via_role: check.granted_via_role,
+                    granted_via_entity_id: check.granted_via_entity_id,
+                    is_inherited: check.is_inherited.unwrap_or(false),
+                });
+            }
+            AuthzQueryKind::UserPermissions => {
+                let (user_id, user_name) = self
+                    .resolve_user(query.user.as_deref().unwrap_or_default())
+                    .await?;
+                let permissions = self
+                    .rebac
+                    .get_user_entity_permissions(user_id, entity_id)
+                    .await
+                    .map_err(rebac_failed)?;
+                for permission in permissions {
+                    let denied = permission.is_denied.unwrap_or(false);
+                    let allowed = permission.has_permission.unwrap_or(false) && !denied;
+                    if !allowed && !denied {
+                        continue;
+                    }
+                    findings.push(AuthzFinding {
+                        reference: String::new(),
+                        user_id,
+                        user_name: user_name.clone(),
+                        entity_id,
+                        entity_name: entity_name.clone(),
+                        permission: permission.permission_name,
+                        allowed,
+                        denied,
+                        granted_via_role: None,
+                        granted_via_entity_id: None,
+                        is_inherited: false,
+                    });
+                }
+            }
+        }
+
+        for (i, finding) in findings.iter_mut().enumerate() {
+            finding.reference = format!("F{}", i + 1);
+        }
+        Ok((findings, truncated))
+    }
+
+    /// The one live entity with this name; users are looked up separately
+    async fn resolve_entity(&self, name: &str) -> Result<(Uuid, String), AiError> {
+        let matches = sqlx::query_as::<_, (Uuid, String)>(
+            r#"
+            SELECT e.id, e.display_name FROM entities e
+            JOIN classes c ON c.id = e.class_id
+            WHERE e.deleted_at IS NULL AND c.name <> 'User'
+              AND LOWER(e.display_name) = LOWER($1)
+            LIMIT 2
+            "#,
+        )
+        .bind(name.trim())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))?;
+        match matches.as_slice() {
+            [entity] => Ok(entity.clone()),
+            [] => Err(AiError::NotFound(format!("No entity named '{}'", name))),
+            _ => Err(AiError::InvalidInput(format!(
+                "More than one entity is named '{}'",
+                name
+            ))),
+        }
+    }
+
+    async fn resolve_user(&self, name: &str) -> Result<(Uuid, String), AiError> {
+        let matches = sqlx::query_as::<_, (Uuid, String)>(
+            r#"
+            SELECT e.id, e.display_name FROM entities e
+            JOIN classes c ON c.id = e.class_id
+            WHERE e.deleted_at IS NULL AND c.name = 'User'
+              AND (LOWER(e.display_name) = LOWER($1)
+                   OR LOWER(e.attributes->>'username') = LOWER($1)
+                   OR LOWER(e.attributes->>'email') = LOWER($1))
+            LIMIT 2
+            "#,
+        )
+        .bind(name.trim())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))?;
+        match matches.as_slice() {
+            [user] => Ok(user.clone()),
+            [] => Err(AiError::NotFound(format!("No user named '{}'", name))),
+            _ => Err(AiError::InvalidInput(format!(
+                "More than one user matches '{}'",
+                name
+            ))),
+        }
+    }
+
+    /// Users with a role assignment, one more than are checked
+    async fn role_holders(&self) -> Result<Vec<(Uuid, String)>, AiError> {
+        sqlx::query_as::<_, (Uuid, String)>(
+            r#"
+            SELECT DISTINCT u.id, u.display_name FROM entities u
+            JOIN classes c ON c.id = u.class_id AND c.name = 'User'
+            JOIN relationships r ON r.source_entity_id = u.id
+            JOIN relationship_types rt ON rt.id = r.relationship_type_id AND rt.name = 'has_role'
+            WHERE u.deleted_at IS NULL
+            ORDER BY u.display_name, u.id
+            LIMIT $1
+            "#,
+        )
+        .bind(MAX_CANDIDATES + 1)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn permissions() -> Vec<String> {
+        vec!["read".to_string(), "approve".to_string()]
+    }
+
+    fn finding(reference: &str) -> AuthzFinding {
+        AuthzFinding {
+            reference: reference.to_string(),
+            user_id: Uuid::new_v4(),
+            user_name: "alice".to_string(),
+            entity_id: Uuid::new_v4(),
+            entity_name: "Project Alpha".to_string(),
+            permission: "approve".to_string(),
+            allowed: true,
+            denied: false,
+            granted_via_role: Some("Approver".to_string()),
+            granted_via_entity_id: None,
+            is_inherited: true,
+        }
+    }
+
+    #[test]
+    fn test_parse_authz_query() {
+        let query = parse_authz_query(
+            "```json\n{\"kind\": \"who_can\", \"permission\": \"approve\", \"entity\": \"Project Alpha\"}\n```",
+            &permissions(),
+        )
+        .unwrap();
+        assert_eq!(query.kind, AuthzQueryKind::WhoCan);
+        assert_eq!(query.entity, "Project Alpha");
+
+        for text in [
+            "no idea",
+            "{\"kind\": \"who_can\", \"permission\": \"launch\", \"entity\": \"X\"}",
+            "{\"kind\": \"can_user\", \"permission\": \"read\", \"entity\": \"X\"}",
+            "{\"kind\": \"who_can\", \"entity\": \"X\"}",
+            "{\"kind\": \"everything\", \"entity\": \"X\"}",
+        ] {
+            assert!(
+                matches!(
+                    parse_authz_query(text, &permissions()),
+                    Err(AiError::InvalidQuery(_))
+                ),
+                "{text}"
+            );
+        }
+        assert!(parse_authz_query(
+            "{\"kind\": \"user_permissions\", \"entity\": \"X\", \"user\": \"bob\"}",
+            &permissions()
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_format_and_cite_findings() {
+        let findings = vec![finding("F1"), finding("F2")];
+        let formatted = format_findings(&findings);
+        assert!(formatted.starts_with(
+            "[F1] alice 'approve' on Project Alpha: allowed via role Approver (inherited"
+        ));
+        assert_eq!(
+            cited_findings("alice can [F2], see also [F1] and [F9] [F2]", &findings),
+            vec!["F2", "F1"]
+        );
+    }
+}
@@ -0,0 +1,475 @@
+use super::prompts::{BuiltinPrompt, PromptService};
+use super::service::{AiError, AiService, GenerateRequest};
+use crate::config::AiConversationsConfig;
+use crate::features::rebac::RebacService;
+use crate::utils::shutdown::Shutdown;
+use crate::utils::validation::not_blank;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, Pool, Postgres};
+use uuid::Uuid;
+use validator::Validate;
+
+/// Title until the first message names the conversation
+const DEFAULT_TITLE: &str = "New conversation";
+const TITLE_CHARS: usize = 60;
+
+pub const CONVERSATION_PROMPT: BuiltinPrompt = BuiltinPrompt {
+    name: "conversation",
+    description: "Replies in an AI chat conversation",
+    body: r#"You are an assistant in an ontology and access management application.
+{{context}}
+Continue the conversation below. Reply to the last user message only.
+
+{{transcript}}
+Assistant:"#,
+    variables: &[
+        (
+            "context",
+            "The project the conversation is about, if any, on its own line",
+        ),
+        (
+            "transcript",
+            "Earlier messages as \"User: ...\" / \"Assistant: ...\" lines",
+        ),
+    ],
+};
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct AiConversation {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub project_id: Option<Uuid>,
+    pub title: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct AiMessage {
+    pub id: Uuid,
+    pub conversation_id: Uuid,
+    /// "user" or "assistant"
+    pub role: String,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConversationWithMessages {
+    #[serde(flatten)]
+    pub conversation: AiConversation,
+    pub messages: Vec<AiMessage>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct CreateConversationInput {
+    pub title: Option<String>,
+    /// Project entity the conversation is about; the caller needs read access
+    pub project_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListConversationsQuery {
+    pub project_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct SendMessageInput {
+    #[validate(custom = "not_blank")]
+    pub content: String,
+    #[validate(range(min = 0.0, max = 2.0))]
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u16>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SendMessageResponse {
+    pub message: AiMessage,
+    pub reply: AiMessage,
+}
+
+/// Earlier messages as the transcript the model continues
+fn transcript(history: &[AiMessage], content: &str) -> String {
+    let mut text = String::new();
+    for message in history {
+        let speaker = if message.role == "assistant" {
+            "Assistant"
+        } else {
+            "User"
+        };
+        text.push_str(&format!("{}: {}\n", speaker, message.content.trim()));
+    }
+    text.push_str(&format!("User: {}\n", content.trim()));
+    text
+}
+
+/// First line of the first message, shortened
+fn title_from(content: &str) -> String {
+    let line = content.trim().lines().next().unwrap_or_default().trim();
+    if line.chars().count() <= TITLE_CHARS {
+        line.to_string()
+    } else {
+        let cut: String = line.chars().take(TITLE_CHARS - 1).collect();
+        format!("{}…", cut.trim_end())
+    }
+}
+
+fn db_error(e: sqlx::Error) -> AiError {
+    AiError::Failed(e.to_string())
+}
+
+/// Stored AI chats, so conversations survive page reloads and can be resumed.
+/// Every conversation belongs to one user; others can't see it.
+#[derive(Clone)]
+pub struct ConversationService {
+    pool: Pool<Postgres>,
+    ai: AiService,
+    rebac: RebacService,
+    prompts: PromptService,
+    config: AiConversationsConfig,
+}
+
+impl ConversationService {
+    pub fn new(
+        pool: Pool<Postgres>,
+        ai: AiService,
+        rebac: RebacService,
+        prompts: PromptService,
+        config: AiConversationsConfig,
+    ) -> Self {
+        Self {
+            pool,
+            ai,
+            rebac,
+            prompts,
+            config,
+        }
+    }
+
+    pub async fn create(
+        &self,
+        user_id: Uuid,
+        input: CreateConversationInput,
+    ) -> Result<AiConversation, AiError> {
+        if let Some(project_id) = input.project_id {
+            self.project_context(user_id, project_id).await?;
+        }
+        let title = input
+            .title
+            .as_deref()
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .map(title_from)
+            .unwrap_or_else(|| DEFAULT_TITLE.to_string());
+
+        sqlx::query_as::<_, AiConversation>(
+            r#"
+            INSERT INTO ai_conversations (user_id, project_id, title)
+            VALUES ($1, $2, $3)
+            RETURNING *
+            "#,
+        )
+        .bind(user_id)
+        .bind(input.project_id)
+        .bind(title)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(db_error)
+    }
+
+    /// Most recently active first
+    pub async fn list(
+        &self,
+        user_id: Uuid,
+        query: ListConversationsQuery,
+    ) -> Result<Vec<AiConversation>, AiError> {
+        sqlx::query_as::<_, AiConversation>(
+            r#"
+            SELECT * FROM ai_conversations
+            WHERE user_id = $1 AND ($2::uuid IS NULL OR project_id = $2)
+            ORDER BY updated_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .bind(query.project_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_error)
+    }
+
+    async fn find(&self, user_id: Uuid, id: Uuid) -> Result<AiConversation, AiError> {
+        sqlx::query_as::<_, AiConversation>(
+            "SELECT * FROM ai_conversations WHERE id = $1 AND user_id = $2",
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| AiError::NotFound("Conversation not found".to_string()))
+    }
+
+    async fn messages(&self, conversation_id: Uuid) -> Result<Vec<AiMessage>, AiError> {
+        sqlx::query_as::<_, AiMessage>(
+            "SELECT * FROM ai_messages WHERE conversation_id = $1 ORDER BY created_at",
+        )
+        .bind(conversation_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_error)
+    }
+
+    /// The conversation with all its messages, to resume it
+    pub async fn get(&self, user_id: Uuid, id: Uuid) -> Result<ConversationWithMessages, AiError> {
+        let conversation = self.find(user_id, id).await?;
+        let messages = self.messages(id).await?;
+        Ok(ConversationWithMessages {
+            conversation,
+            messages,
+        })
+    }
+
+    pub async fn delete(&self, user_id: Uuid, id: Uuid) -> Result<(), AiError> {
+        let deleted = sqlx::query("DELETE FROM ai_conversations WHERE id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(db_error)?
+            .rows_affected();
+        if deleted == 0 {
+            return Err(AiError::NotFound("Conversation not found".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Delete all of the user's conversations, or only those about a project
+    pub async fn delete_all(
+        &self,
+        user_id: Uuid,
+        query: ListConversationsQuery,
+    ) -> Result<u64, AiError> {
+        sqlx::query(
+            "DELETE FROM ai_conversations WHERE user_id = $1 AND ($2::uuid IS NULL OR project_id = $2)",
+        )
+        .bind(user_id)
+        .bind(query.project_id)
+        .execute(&self.pool)
+        .await
+        .map(|r| r.rows_affected())
+        .map_err(db_error)
+    }
+
+    /// Ask the model to reply, then save the message and the reply together.
+    /// Nothing is saved if the model fails, so the message can be resent.
+    pub async fn send(
+        &self,
+        user_id: Uuid,
+        id: Uuid,
+        input: SendMessageInput,
+    ) -> Result<SendMessageResponse, AiError> {
+        let content = input.content.trim();
+        if content.is_empty() {
+            return Err(AiError::InvalidInput("content is required".to_string()));
+        }
+        if content.chars().count() > self.config.max_message_chars {
+            return Err(AiError::InvalidInput(format!(
+                "message is longer than {} characters",
+                self.config.max_message_chars
+            )));
+        }
+        let conversation = self.find(user_id, id).await?;
+
+        let mut history = sqlx::query_as::<_, AiMessage>(
+            r#"
+            SELECT * FROM ai_messages WHERE conversation_id = $1
+            ORDER BY created_at DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(id)
+        .bind((self.config.context_messages - 1).max(0))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_error)?;
+        history.reverse();
+
+        let context = match conversation.project_id {
+            Some(project_id) => self
+                .project_context(user_id, project_id)
+                .await
+                .unwrap_or_default(),
+            None => String::new(),
+        };
+        let prompt = self
+            .prompts
+            .render(
+                &CONVERSATION_PROMPT,
+                &[
+                    ("context", context),
+                    ("transcript", transcript(&history, content)),
+                ],
+            )
+            .await;
+
+        let response = self
+            .ai
+            .for_user(user_id)
+            .await
+            .generate_text(GenerateRequest {
+                prompt,
+                temperature: input.temperature,
+                max_tokens: input.max_tokens,
+            })
+            .await?;
+
+        let mut tx = self.pool.begin().await.map_err(db_error)?;
+        let insert = r#"
+            INSERT INTO ai_messages (conversation_id, role, content)
+            VALUES ($1, $2, $3)
+            RETURNING *
+        "#;
+        let message = sqlx::query_as::<_, AiMessage>(insert)
+            .bind(id)
+            .bind("user")
+            .bind(content)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(db_error)?;
+        let reply = sqlx::query_as::<_, AiMessage>(insert)
+            .bind(id)
+            .bind("assistant")
+            .bind(response.text.trim())
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(db_error)?;
+
+        // An untitled conversation is named after its first message
+        sqlx::query(
+            r#"
+            UPDATE ai_conversations
+            SET updated_at = NOW(),
+                title = CASE WHEN title = $2 AND $3 THEN $4 ELSE title END
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(DEFAULT_TITLE)
+        .bind(history.is_empty())
+        .bind(title_from(content))
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+        tx.commit().await.map_err(db_error)?;
+
+        Ok(SendMessageResponse { message, reply })
+    }
+
+    /// A line describing the project, after checking the user can read it
+    async fn project_context(&self, user_id: Uuid, project_id: Uuid) -> Result<String, AiError> {
+        let project = sqlx::query_as::<_, (String, Option<String>)>(
+            r#"
+            SELECT e.display_name, e.attributes->>'description'
+            FROM entities e
+            JOIN classes c ON e.class_id = c.id
+            WHERE e.id = $1 AND c.name = 'Project' AND e.deleted_at IS NULL
+            "#,
+        )
+        .bind(project_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| AiError::NotFound("Project not found".to_string()))?;
+
+        let can_read = self
+            .rebac
+            .has_permission(user_id, project_id, "read", None)
+            .await
+            .map_err(|e| AiError::Failed(e.to_string()))?;
+        if !can_read {
+            return Err(AiError::Forbidden(
+                "No read access to this project".to_string(),
+            ));
+        }
+
+        Ok(match project.1.filter(|d| !d.trim().is_empty()) {
+            Some(description) => format!(
+                "The conversation is about the project \"{}\": {}\n",
+                project.0,
+                description.trim()
+            ),
+            None => format!("The conversation is about the project \"{}\".\n", project.0),
+        })
+    }
+
+    /// Delete conversations idle for longer than the retention period
+    pub async fn purge_expired(&self) -> Result<u64, AiError> {
+        if self.config.retention_days == 0 {
+            return Ok(0);
+        }
+        sqlx::query(
+            "DELETE FROM ai_conversations WHERE updated_at < NOW() - make_interval(days => $1)",
+        )
+        .bind(self.config.retention_days as i32)
+        .execute(&self.pool)
+        .await
+        .map(|r| r.rows_affected())
+        .map_err(db_error)
+    }
+
+    pub async fn start_retention_task(self, shutdown: Shutdown) {
+        shutdown.clone().spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
+                self.config.cleanup_interval_secs.max(60),
+            ));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = shutdown.cancelled() => break,
+                }
+                match self.purge_expired().await {
+                    Ok(0) => {}
+                    Ok(n) => tracing::info!("Deleted {} expired AI conversations", n),
+                    Err(e) => tracing::error!("Failed to delete expired AI conversations: {}", e),
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: &str, content: &str) -> AiMessage {
+        AiMessage {
+            id: Uuid::new_v4(),
+            conversation_id: Uuid::nil(),
+            role: role.to_string(),
+            content: content.to_string(),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_transcript() {
+        let history = [message("user", "Hi "), message("assistant", "Hello!")];
+        assert_eq!(
+            transcript(&history, " What is a class?"),
+            "User: Hi\nAssistant: Hello!\nUser: What is a class?\n"
+        );
+    }
+
+    #[test]
+    fn test_title_from() {
+        assert_eq!(
+            title_from("  Short question\nmore detail"),
+            "Short question"
+        );
+        let long = "x".repeat(100);
+        let title = title_from(&long);
+        assert_eq!(title.chars().count(), TITLE_CHARS);
+        assert!(title.ends_with('…'));
+    }
+}
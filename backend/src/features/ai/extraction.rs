@@ -0,0 +1,736 @@
+use super::prompts::{BuiltinPrompt, PromptService};
+use super::service::{AiError, AiService, GenerateRequest};
+use crate::features::ontology::models::{
+    CreateEntityInput, Entity, GraphImport, ImportRelationshipInput, Relationship, RelationshipType,
+};
+use crate::features::ontology::service::OntologyError;
+use crate::features::ontology::OntologyService;
+use crate::features::uploads::service::UploadError;
+use crate::features::uploads::UploadService;
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::{FromRow, Pool, Postgres};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+use validator::Validate;
+
+/// Uploads must have this purpose to be extracted from
+pub const EXTRACTION_PURPOSE: &str = "extraction";
+
+const MAX_DOCUMENT_BYTES: u64 = 1024 * 1024;
+
+/// Text beyond this is left out of the prompt to stay within model context
+const MAX_DOCUMENT_CHARS: usize = 24_000;
+
+pub const EXTRACTION_PROMPT: BuiltinPrompt = BuiltinPrompt {
+    name: "document_extraction",
+    description: "Finds entities and relationships in an uploaded document",
+    body: r#"Extract the entities and relationships described in the document below.
+Only use these classes (with their attributes):
+{{schema}}
+
+Relationship types (source class -> target class):
+{{relationship_types}}
+{{instructions}}
+Document:
+"""
+{{document}}
+"""
+
+Respond ONLY with a JSON object:
+{ "entities": [{ "key": "e1", "class": "...", "display_name": "...", "attributes": {} }],
+  "relationships": [{ "source": "e1", "target": "e2", "relationship_type": "..." }] }
+Relationships refer to entities by key. Leave out anything that does not fit the classes."#,
+    variables: &[
+        ("schema", "One line per class: \"- Class: attribute, ...\""),
+        ("relationship_types", "One line per type: \"- name (Source -> Target)\""),
+        ("instructions", "The uploader's extra guidance, if any, on its own line"),
+        ("document", "The document text"),
+    ],
+};
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ExtractRequest {
+    /// A completed upload with purpose "extraction"
+    pub upload_id: Uuid,
+    /// Extra guidance for the model, e.g. which kinds of things to look for
+    pub instructions: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct AcceptExtractionRequest {
+    /// Keys of the proposed entities to create; all of them if omitted.
+    /// Relationships are kept only when both ends are accepted.
+    pub keys: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProposedEntity {
+    /// Identifies the entity within the proposal
+    pub key: String,
+    pub class_id: Uuid,
+    pub class_name: String,
+    pub display_name: String,
+    pub attributes: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProposedRelationship {
+    /// Entity keys
+    pub source: String,
+    pub target: String,
+    pub relationship_type: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExtractionProposal {
+    pub entities: Vec<ProposedEntity>,
+    pub relationships: Vec<ProposedRelationship>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct DocumentExtraction {
+    pub id: Uuid,
+    pub upload_id: Option<Uuid>,
+    /// "proposed", "accepted" or "discarded"
+    pub status: String,
+    pub proposal: Json<ExtractionProposal>,
+    pub warnings: Json<Vec<String>>,
+    pub created_entity_ids: Vec<Uuid>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AcceptExtractionResponse {
+    pub extraction: DocumentExtraction,
+    pub entities: Vec<Entity>,
+    pub relationships: Vec<Relationship>,
+}
+
+/// What the model returns, before it is mapped onto the ontology
+#[derive(Debug, Default, Deserialize)]
+struct RawExtraction {
+    #[serde(default)]
+    entities: Vec<RawEntity>,
+    #[serde(default)]
+    relationships: Vec<ProposedRelationship>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawEntity {
+    key: String,
+    class: String,
+    display_name: String,
+    #[serde(default)]
+    attributes: serde_json::Value,
+}
+
+/// A class entities can be created in, with the attribute names it accepts
+#[derive(Debug, Clone, FromRow)]
+struct TargetClass {
+    id: Uuid,
+    name: String,
+    properties: Vec<String>,
+}
+
+/// Extract the model's JSON, which may be wrapped in prose or code fences
+fn parse_extraction(text: &str) -> Result<RawExtraction, AiError> {
+    let json = match (text.find('{'), text.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &text[start..=end],
+        _ => {
+            return Err(AiError::InvalidSuggestion(
+                "response contains no extraction".to_string(),
+            ))
+        }
+    };
+    serde_json::from_str(json)
+        .map_err(|e| AiError::InvalidSuggestion(format!("malformed extraction: {}", e)))
+}
+
+/// Keep what fits the ontology: known classes and their attributes, and
+/// relationship types whose class constraints the endpoints satisfy. Anything
+/// dropped is described in the returned warnings.
+fn map_to_ontology(
+    raw: RawExtraction,
+    classes: &[TargetClass],
+    relationship_types: &[RelationshipType],
+) -> (ExtractionProposal, Vec<String>) {
+    let by_name: HashMap<String, &TargetClass> =
+        classes.iter().map(|c| (c.name.to_lowercase(), c)).collect();
+    let mut warnings = Vec::new();
+    let mut proposal = ExtractionProposal::default();
+    let mut keys = HashSet::new();
+
+    for entity in raw.entities {
+        let key = entity.key.trim().to_string();
+        let display_name = entity.display_name.trim().to_string();
+        if key.is_empty() || display_name.is_empty() {
+            warnings.push("dropped an entity without a key or name".to_string());
+            continue;
+        }
+        let Some(class) = by_name.get(&entity.class.trim().to_lowercase()) else {
+            warnings.push(format!(
+                "dropped '{}': unknown class '{}'",
+                display_name, entity.class
+            ));
+            continue;
+        };
+        if !keys.insert(key.clone()) {
+            warnings.push(format!(
+                "dropped '{}': duplicate key '{}'",
+                display_name, key
+            ));
+            continue;
+        }
+
+        let mut attributes = serde_json::Map::new();
+        if let serde_json::Value::Object(obj) = entity.attributes {
+            for (name, value) in obj {
+                if class.properties.contains(&name) {
+                    attributes.insert(name, value);
+                } else {
+                    warnings.push(format!(
+                        "dropped attribute '{}' of '{}': not a property of {}",
+                        name, display_name, class.name
+                    ));
+                }
+            }
+        }
+
+        proposal.entities.push(ProposedEntity {
+            key,
+            class_id: class.id,
+            class_name: class.name.clone(),
+            display_name,
+            attributes: serde_json::Value::Object(attributes),
+        });
+    }
+
+    let class_of: HashMap<&str, Uuid> = proposal
+        .entities
+        .iter()
+        .map(|e| (e.key.as_str(), e.class_id))
+        .collect();
+    let mut seen = HashSet::new();
+    for rel in raw.relationships {
+        let (Some(&source_class), Some(&target_class)) = (
+            class_of.get(rel.source.trim()),
+            class_of.get(rel.target.trim()),
+        ) else {
+            warnings.push(format!(
+                "dropped '{}' relationship: unknown entity '{}' or '{}'",
+                rel.relationship_type, rel.source, rel.target
+            ));
+            continue;
+        };
+        let Some(rel_type) = relationship_types
+            .iter()
+            .find(|t| t.name.eq_ignore_ascii_case(rel.relationship_type.trim()))
+        else {
+            warnings.push(format!(
+                "dropped relationship {} -> {}: unknown type '{}'",
+                rel.source, rel.target, rel.relationship_type
+            ));
+            continue;
+        };
+        if rel_type
+            .allowed_source_class_id
+            .is_some_and(|id| id != source_class)
+            || rel_type
+                .allowed_target_class_id
+                .is_some_and(|id| id != target_class)
+        {
+            warnings.push(format!(
+                "dropped '{}' relationship {} -> {}: classes not allowed for this type",
+                rel_type.name, rel.source, rel.target
+            ));
+            continue;
+        }
+        let rel = ProposedRelationship {
+            source: rel.source.trim().to_string(),
+            target: rel.target.trim().to_string(),
+            relationship_type: rel_type.name.clone(),
+        };
+        if seen.insert((
+            rel.source.clone(),
+            rel.target.clone(),
+            rel.relationship_type.clone(),
+        )) {
+            proposal.relationships.push(rel);
+        }
+    }
+
+    (proposal, warnings)
+}
+
+/// The accepted part of a proposal as an ontology import
+fn to_import(proposal: &ExtractionProposal, keys: Option<&[String]>) -> GraphImport {
+    let accepted: Vec<&ProposedEntity> = proposal
+        .entities
+        .iter()
+        .filter(|e| keys.is_none_or(|keys| keys.contains(&e.key)))
+        .collect();
+    let index: HashMap<&str, usize> = accepted
+        .iter()
+        .enumerate()
+        .map(|(i, e)| (e.key.as_str(), i))
+        .collect();
+
+    GraphImport {
+        entities: accepted
+            .iter()
+            .map(|e| CreateEntityInput {
+                class_id: e.class_id,
+                display_name: e.display_name.clone(),
+                parent_entity_id: None,
+                attributes: Some(e.attributes.clone()),
+            })
+            .collect(),
+        relationships: proposal
+            .relationships
+            .iter()
+            .filter_map(|r| {
+                Some(ImportRelationshipInput {
+                    source: *index.get(r.source.as_str())?,
+                    target: *index.get(r.target.as_str())?,
+                    relationship_type: r.relationship_type.clone(),
+                })
+            })
+            .collect(),
+    }
+}
+
+/// Proposes entities and relationships found in uploaded documents, which the
+/// uploader can accept into the graph as PENDING entities.
+#[derive(Clone)]
+pub struct ExtractionService {
+    pool: Pool<Postgres>,
+    ai: AiService,
+    uploads: UploadService,
+    ontology: OntologyService,
+    prompts: PromptService,
+}
+
+impl ExtractionService {
+    pub fn new(
+        pool: Pool<Postgres>,
+        ai: AiService,
+        uploads: UploadService,
+        ontology: OntologyService,
+        prompts: PromptService,
+    ) -> Self {
+        Self {
+            pool,
+            ai,
+            uploads,
+            ontology,
+            prompts,
+        }
+    }
+
+    pub async fn extract(
+        &self,
+        user_id: Uuid,
+        request: ExtractRequest,
+    ) -> Result<DocumentExtraction, AiError> {
+        let (text, truncated) = self.read_document(user_id, request.upload_id).await?;
+
+        let classes = self.target_classes().await?;
+        if classes.is_empty() {
+            return Err(AiError::Conflict(
+                "The current ontology version has no classes to extract into".to_string(),
+            ));
+        }
+        let relationship_types =
+            sqlx::query_as::<_, RelationshipType>("SELECT * FROM relationship_types ORDER BY name")
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| AiError::Failed(e.to_string()))?;
+
+        let prompt = self
+            .prompts
+            .render(
+                &EXTRACTION_PROMPT,
+                &prompt_variables(
+                    &text,
+                    &classes,
+                    &relationship_types,
+                    request.instructions.as_deref(),
+                ),
+            )
+            .await;
+        let response = self
+            .ai
+            .for_user(user_id)
+            .await
+            .generate_text(GenerateRequest {
+                prompt,
+                temperature: Some(0.0),
+                max_tokens: Some(3000),
+            })
+            .await?;
+
+        let (proposal, mut warnings) = map_to_ontology(
+            parse_extraction(&response.text)?,
+            &classes,
+            &relationship_types,
+        );
+        if truncated {
+            warnings.insert(
+                0,
+                format!(
+                    "only the first {} characters of the document were read",
+                    MAX_DOCUMENT_CHARS
+                ),
+            );
+        }
+
+        sqlx::query_as::<_, DocumentExtraction>(
+            r#"
+            INSERT INTO document_extractions (upload_id, proposal, warnings, created_by)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *
+            "#,
+        )
+        .bind(request.upload_id)
+        .bind(Json(&proposal))
+        .bind(Json(&warnings))
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))
+    }
+
+    pub async fn get(&self, user_id: Uuid, id: Uuid) -> Result<DocumentExtraction, AiError> {
+        sqlx::query_as::<_, DocumentExtraction>(
+            "SELECT * FROM document_extractions WHERE id = $1 AND created_by = $2",
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))?
+        .ok_or_else(|| AiError::NotFound("Extraction not found".to_string()))
+    }
+
+    /// Create the accepted entities (PENDING approval) and the relationships between them
+    pub async fn accept(
+        &self,
+        user_id: Uuid,
+        id: Uuid,
+        request: AcceptExtractionRequest,
+    ) -> Result<AcceptExtractionResponse, AiError> {
+        // Claim the proposal first so concurrent accepts can't both create entities
+        let extraction = self.transition(user_id, id, "accepted").await?;
+
+        let import = to_import(&extraction.proposal, request.keys.as_deref());
+        let imported = match self
+            .ontology
+            .import_pending_graph(import, Some(user_id))
+            .await
+        {
+            Ok(imported) => imported,
+            Err(e) => {
+                let _ = sqlx::query(
+                    "UPDATE document_extractions SET status = 'proposed', reviewed_at = NULL WHERE id = $1",
+                )
+                .bind(id)
+                .execute(&self.pool)
+                .await;
+                return Err(match e {
+                    OntologyError::InvalidInput(msg) => AiError::InvalidInput(msg),
+                    e => AiError::Failed(e.to_string()),
+                });
+            }
+        };
+
+        let entity_ids: Vec<Uuid> = imported.entities.iter().map(|e| e.id).collect();
+        let extraction = sqlx::query_as::<_, DocumentExtraction>(
+            "UPDATE document_extractions SET created_entity_ids = $2 WHERE id = $1 RETURNING *",
+        )
+        .bind(id)
+        .bind(&entity_ids)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))?;
+
+        Ok(AcceptExtractionResponse {
+            extraction,
+            entities: imported.entities,
+            relationships: imported.relationships,
+        })
+    }
+
+    pub async fn discard(&self, user_id: Uuid, id: Uuid) -> Result<DocumentExtraction, AiError> {
+        self.transition(user_id, id, "discarded").await
+    }
+
+    /// Move a proposed extraction to its final status
+    async fn transition(
+        &self,
+        user_id: Uuid,
+        id: Uuid,
+        status: &str,
+    ) -> Result<DocumentExtraction, AiError> {
+        let extraction = sqlx::query_as::<_, DocumentExtraction>(
+            r#"
+            UPDATE document_extractions SET status = $3, reviewed_at = NOW()
+            WHERE id = $1 AND created_by = $2 AND status = 'proposed'
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(status)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))?;
+
+        match extraction {
+            Some(extraction) => Ok(extraction),
+            None => {
+                let existing = self.get(user_id, id).await?;
+                Err(AiError::Conflict(format!(
+                    "Extraction is already {}",
+                    existing.status
+                )))
+            }
+        }
+    }
+
+    /// The document's text, and whether it was cut short for the prompt
+    async fn read_document(
+        &self,
+        user_id: Uuid,
+        upload_id: Uuid,
+    ) -> Result<(String, bool), AiError> {
+        let (upload, mut content) = self
+            .uploads
+            .open(user_id, upload_id)
+            .await
+            .map_err(|e| match e {
+                UploadError::NotFound => AiError::NotFound("Upload not found".to_string()),
+                UploadError::Conflict(msg) => AiError::Conflict(msg),
+                e => AiError::Failed(e.to_string()),
+            })?;
+        if upload.purpose != EXTRACTION_PURPOSE {
+            return Err(AiError::InvalidInput(format!(
+                "upload purpose must be '{}'",
+                EXTRACTION_PURPOSE
+            )));
+        }
+        if upload.size_bytes as u64 > MAX_DOCUMENT_BYTES {
+            return Err(AiError::InvalidInput(format!(
+                "documents larger than {} bytes are not supported",
+                MAX_DOCUMENT_BYTES
+            )));
+        }
+
+        let mut bytes = Vec::new();
+        while let Some(chunk) = content.next().await {
+            let chunk = chunk.map_err(|e| AiError::Failed(e.to_string()))?;
+            bytes.extend_from_slice(&chunk);
+            if bytes.len() as u64 >= MAX_DOCUMENT_BYTES {
+                bytes.truncate(MAX_DOCUMENT_BYTES as usize);
+                break;
+            }
+        }
+        let text = String::from_utf8(bytes).map_err(|_| {
+            AiError::InvalidInput("only UTF-8 text documents are supported".to_string())
+        })?;
+        if text.trim().is_empty() {
+            return Err(AiError::InvalidInput("document is empty".to_string()));
+        }
+
+        match text.char_indices().nth(MAX_DOCUMENT_CHARS) {
+            Some((end, _)) => Ok((text[..end].to_string(), true)),
+            None => Ok((text, false)),
+        }
+    }
+
+    /// Concrete classes of the current version with their attribute names
+    async fn target_classes(&self) -> Result<Vec<TargetClass>, AiError> {
+        sqlx::query_as::<_, TargetClass>(
+            r#"
+            SELECT c.id, c.name,
+                   COALESCE(array_agg(p.name ORDER BY p.name) FILTER (WHERE p.id IS NOT NULL), '{}') AS properties
+            FROM classes c
+            JOIN ontology_versions v ON v.id = c.version_id AND v.is_current = TRUE
+            LEFT JOIN properties p ON p.class_id = c.id AND p.is_deprecated = FALSE
+            WHERE c.is_deprecated = FALSE AND c.is_abstract = FALSE AND c.tenant_id IS NULL
+            GROUP BY c.id, c.name
+            ORDER BY c.name
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))
+    }
+}
+
+/// Values for `EXTRACTION_PROMPT`
+fn prompt_variables(
+    text: &str,
+    classes: &[TargetClass],
+    relationship_types: &[RelationshipType],
+    instructions: Option<&str>,
+) -> [(&'static str, String); 4] {
+    let class_names: HashMap<Uuid, &str> =
+        classes.iter().map(|c| (c.id, c.name.as_str())).collect();
+    let schema = classes
+        .iter()
+        .map(|c| format!("- {}: {}", c.name, c.properties.join(", ")))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let types = relationship_types
+        .iter()
+        .map(|t| {
+            let endpoint = |id: Option<Uuid>| {
+                id.and_then(|id| class_names.get(&id).copied())
+                    .unwrap_or("any")
+            };
+            format!(
+                "- {} ({} -> {})",
+                t.name,
+                endpoint(t.allowed_source_class_id),
+                endpoint(t.allowed_target_class_id)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    [
+        ("schema", schema),
+        (
+            "relationship_types",
+            if types.is_empty() { "none".to_string() } else { types },
+        ),
+        (
+            "instructions",
+            instructions
+                .filter(|i| !i.trim().is_empty())
+                .map(|i| format!("\nInstructions: {}\n", i.trim()))
+                .unwrap_or_default(),
+        ),
+        ("document", text.to_string()),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn class(name: &str, properties: &[&str]) -> TargetClass {
+        TargetClass {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            properties: properties.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    fn relationship_type(
+        name: &str,
+        source: Option<Uuid>,
+        target: Option<Uuid>,
+    ) -> RelationshipType {
+        RelationshipType {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            description: None,
+            source_cardinality: None,
+            target_cardinality: None,
+            allowed_source_class_id: source,
+            allowed_target_class_id: target,
+            grants_permission_inheritance: false,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_map_to_ontology_drops_what_does_not_fit() {
+        let person = class("Person", &["rank"]);
+        let unit = class("Unit", &[]);
+        let types = vec![
+            relationship_type("member_of", Some(person.id), Some(unit.id)),
+            relationship_type("contains", None, None),
+        ];
+        let raw = parse_extraction(
+            r#"```json
+            {"entities": [
+                {"key": "p1", "class": "person", "display_name": "Kari Nordmann", "attributes": {"rank": "Major", "shoe_size": 41}},
+                {"key": "u1", "class": "Unit", "display_name": "2nd Battalion"},
+                {"key": "v1", "class": "Vehicle", "display_name": "Truck 7"},
+                {"key": "p1", "class": "Person", "display_name": "Duplicate"}
+            ],
+            "relationships": [
+                {"source": "p1", "target": "u1", "relationship_type": "member_of"},
+                {"source": "u1", "target": "p1", "relationship_type": "member_of"},
+                {"source": "u1", "target": "p1", "relationship_type": "contains"},
+                {"source": "u1", "target": "p1", "relationship_type": "contains"},
+                {"source": "p1", "target": "v1", "relationship_type": "contains"},
+                {"source": "p1", "target": "u1", "relationship_type": "commands"}
+            ]}
+            ```"#,
+        )
+        .unwrap();
+
+        let (proposal, warnings) = map_to_ontology(raw, &[person.clone(), unit], &types);
+        assert_eq!(proposal.entities.len(), 2);
+        assert_eq!(proposal.entities[0].class_name, "Person");
+        assert_eq!(
+            proposal.entities[0].attributes,
+            serde_json::json!({ "rank": "Major" })
+        );
+        assert_eq!(proposal.relationships.len(), 2);
+        assert_eq!(proposal.relationships[0].relationship_type, "member_of");
+        assert_eq!(proposal.relationships[1].relationship_type, "contains");
+        // shoe_size, Truck 7, duplicate key, reversed member_of, dangling and unknown type
+        assert_eq!(warnings.len(), 6);
+    }
+
+    #[test]
+    fn test_to_import_keeps_relationships_between_accepted_entities() {
+        let proposal = ExtractionProposal {
+            entities: ["a", "b", "c"]
+                .iter()
+                .map(|key| ProposedEntity {
+                    key: key.to_string(),
+                    class_id: Uuid::nil(),
+                    class_name: "Thing".to_string(),
+                    display_name: key.to_uppercase(),
+                    attributes: serde_json::json!({}),
+                })
+                .collect(),
+            relationships: vec![
+                ProposedRelationship {
+                    source: "a".to_string(),
+                    target: "c".to_string(),
+                    relationship_type: "contains".to_string(),
+                },
+                ProposedRelationship {
+                    source: "a".to_string(),
+                    target: "b".to_string(),
+                    relationship_type: "contains".to_string(),
+                },
+            ],
+        };
+
+        let import = to_import(&proposal, Some(&["a".to_string(), "c".to_string()]));
+        assert_eq!(import.entities.len(), 2);
+        assert_eq!(import.entities[1].display_name, "C");
+        assert_eq!(import.relationships.len(), 1);
+        assert_eq!(
+            (
+                import.relationships[0].source,
+                import.relationships[0].target
+            ),
+            (0, 1)
+        );
+
+        assert_eq!(to_import(&proposal, None).relationships.len(), 2);
+    }
+}
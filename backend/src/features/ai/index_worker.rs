@@ -0,0 +1,422 @@
+use super::semantic_search::SemanticSearchService;
+use super::service::AiError;
+use crate::utils::shutdown::Shutdown;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::postgres::PgListener;
+use sqlx::{FromRow, Pool, Postgres};
+use std::collections::BTreeSet;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Channel the change event triggers notify
+pub const CHANGES_CHANNEL: &str = "ontology_changes";
+
+/// Row in `ai_index_state` for the entity embeddings index
+const WORKER: &str = "entity_embeddings";
+
+/// Change events handled per pass
+const EVENT_BATCH: i64 = 1000;
+
+/// Delete change events older than `retention_hours` that every running
+/// consumer of the feed (the workers in `ai_index_state`) has handled
+pub(crate) async fn prune_change_events(
+    pool: &Pool<Postgres>,
+    retention_hours: i64,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM ontology_change_events
+        WHERE seq <= (SELECT MIN(last_seq) FROM ai_index_state WHERE available)
+          AND created_at < NOW() - make_interval(hours => $1)
+        "#,
+    )
+    .bind(retention_hours.max(0) as i32)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+#[derive(Debug, FromRow)]
+struct ChangeEvent {
+    seq: i64,
+    object_type: String,
+    object_id: Uuid,
+    class_id: Option<Uuid>,
+}
+
+/// Stored progress of the worker
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct IndexWorkerState {
+    pub worker: String,
+    pub last_seq: i64,
+    pub events_processed: i64,
+    pub entities_indexed: i64,
+    pub available: bool,
+    pub heartbeat_at: Option<DateTime<Utc>>,
+    pub last_indexed_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+    pub backfill_started_at: Option<DateTime<Utc>>,
+    pub backfill_total: Option<i64>,
+    pub backfill_done: i64,
+    pub backfill_finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexWorkerStatus {
+    #[serde(flatten)]
+    pub state: IndexWorkerState,
+    /// Change events not yet handled
+    pub pending_events: i64,
+    pub backfilling: bool,
+    /// Running, with a recent heartbeat and no error since its last success
+    pub healthy: bool,
+}
+
+/// Keeps the semantic search index current: re-embeds entities as ontology
+/// change events arrive, runs requested backfills, and periodically catches
+/// up on anything the events missed.
+#[derive(Clone)]
+pub struct IndexWorker {
+    pool: Pool<Postgres>,
+    search: SemanticSearchService,
+}
+
+impl IndexWorker {
+    pub fn new(pool: Pool<Postgres>, search: SemanticSearchService) -> Self {
+        Self { pool, search }
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(self.search.config().index_interval_secs.max(1))
+    }
+
+    pub async fn start(self, shutdown: Shutdown) {
+        shutdown.clone().spawn(async move {
+            let available = match self.search.is_available().await {
+                Ok(available) => available,
+                Err(e) => {
+                    tracing::error!("Failed to check for entity embeddings table: {}", e);
+                    return;
+                }
+            };
+            if let Err(e) = self.set_available(available).await {
+                tracing::warn!("Failed to record index worker state: {}", e);
+            }
+            if !available {
+                tracing::info!("pgvector not available, entity embedding indexer disabled");
+                return;
+            }
+
+            let mut listener = match PgListener::connect_with(&self.pool).await {
+                Ok(mut listener) => match listener.listen(CHANGES_CHANNEL).await {
+                    Ok(()) => Some(listener),
+                    Err(e) => {
+                        tracing::warn!("Index worker can't listen for changes, polling: {}", e);
+                        None
+                    }
+                },
+                Err(e) => {
+                    tracing::warn!("Index worker can't listen for changes, polling: {}", e);
+                    None
+                }
+            };
+
+            let mut interval = tokio::time::interval(self.interval());
+            loop {
+                let catch_up = tokio::select! {
+                    _ = interval.tick() => true,
+                    notification = async {
+                        match listener.as_mut() {
+                            Some(listener) => listener.recv().await,
+                            None => std::future::pending().await,
+                        }
+                    } => {
+                        if let Err(e) = notification {
+                            tracing::warn!("Index worker lost its change listener, polling: {}", e);
+                            listener = None;
+                        }
+                        false
+                    }
+                    _ = shutdown.cancelled() => break,
+                };
+                self.run_once(catch_up, &shutdown).await;
+            }
+        });
+    }
+
+    /// One pass: handle pending change events, continue any backfill, and on
+    /// `catch_up` embed whatever else is stale. Errors are recorded in the
+    /// worker state and retried on the next pass.
+    pub async fn run_once(&self, catch_up: bool, shutdown: &Shutdown) {
+        let result = async {
+            self.heartbeat().await?;
+            self.process_events(shutdown).await?;
+            self.continue_backfill(shutdown).await?;
+            if catch_up {
+                self.catch_up(shutdown).await?;
+                self.prune_events().await?;
+            }
+            Ok::<_, AiError>(())
+        }
+        .await;
+
+        if let Err(e) = result {
+            tracing::warn!("Entity embedding indexer failed: {}", e);
+            let _ = sqlx::query(
+                "UPDATE ai_index_state SET last_error = $2, last_error_at = NOW() WHERE worker = $1",
+            )
+            .bind(WORKER)
+            .bind(e.to_string())
+            .execute(&self.pool)
+            .await;
+        }
+    }
+
+    /// Re-embed the entities touched by change events, in order. The
+    /// position only advances past events whose entities were embedded.
+    async fn process_events(&self, shutdown: &Shutdown) -> Result<(), AiError> {
+        loop {
+            let last_seq = self.state().await?.last_seq;
+            let events = sqlx::query_as::<_, ChangeEvent>(
+                r#"
+                SELECT seq, object_type, object_id, class_id
+                FROM ontology_change_events
+                WHERE seq > $1
+                ORDER BY seq
+                LIMIT $2
+                "#,
+            )
+            .bind(last_seq)
+            .bind(EVENT_BATCH)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| AiError::Failed(e.to_string()))?;
+            let Some(last) = events.last().map(|e| e.seq) else {
+                return Ok(());
+            };
+
+            let entity_ids = self.affected_entities(&events).await?;
+            let indexed = self.search.reindex(&entity_ids).await?;
+            self.record_progress(last, events.len(), indexed).await?;
+
+            if (events.len() as i64) < EVENT_BATCH || shutdown.is_triggered() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Changed entities, plus every entity of a class whose name or
+    /// properties changed, since those change the embedded text too
+    async fn affected_entities(&self, events: &[ChangeEvent]) -> Result<Vec<Uuid>, AiError> {
+        let mut entity_ids = BTreeSet::new();
+        let mut class_ids = BTreeSet::new();
+        for event in events {
+            match (event.object_type.as_str(), event.class_id) {
+                ("entity", _) => {
+                    entity_ids.insert(event.object_id);
+                }
+                (_, Some(class_id)) => {
+                    class_ids.insert(class_id);
+                }
+                _ => {}
+            }
+        }
+        if !class_ids.is_empty() {
+            let class_ids: Vec<Uuid> = class_ids.into_iter().collect();
+            let members: Vec<Uuid> = sqlx::query_scalar(
+                "SELECT id FROM entities WHERE class_id = ANY($1) AND deleted_at IS NULL",
+            )
+            .bind(&class_ids)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| AiError::Failed(e.to_string()))?;
+            entity_ids.extend(members);
+        }
+        Ok(entity_ids.into_iter().collect())
+    }
+
+    async fn continue_backfill(&self, shutdown: &Shutdown) -> Result<(), AiError> {
+        let state = self.state().await?;
+        let Some(started_at) = state
+            .backfill_started_at
+            .filter(|_| state.backfill_finished_at.is_none())
+        else {
+            return Ok(());
+        };
+
+        let batch_size = self.search.config().batch_size.max(1);
+        loop {
+            let indexed = self.search.index_backfill_batch(started_at).await?;
+            sqlx::query(
+                r#"
+                UPDATE ai_index_state SET
+                    backfill_done = backfill_done + $2,
+                    entities_indexed = entities_indexed + $2,
+                    last_indexed_at = CASE WHEN $2 > 0 THEN NOW() ELSE last_indexed_at END,
+                    backfill_finished_at = CASE WHEN $3 THEN NOW() ELSE NULL END
+                WHERE worker = $1
+                "#,
+            )
+            .bind(WORKER)
+            .bind(indexed as i64)
+            .bind((indexed as i64) < batch_size)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| AiError::Failed(e.to_string()))?;
+
+            if (indexed as i64) < batch_size || shutdown.is_triggered() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Embed anything still stale, such as changes whose events were
+    /// committed out of order or made while the worker was down
+    async fn catch_up(&self, shutdown: &Shutdown) -> Result<(), AiError> {
+        let batch_size = self.search.config().batch_size.max(1);
+        loop {
+            let indexed = self.search.index_batch().await?;
+            if indexed > 0 {
+                let last_seq = self.state().await?.last_seq;
+                self.record_progress(last_seq, 0, indexed).await?;
+            }
+            if (indexed as i64) < batch_size || shutdown.is_triggered() {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn prune_events(&self) -> Result<(), AiError> {
+        prune_change_events(&self.pool, self.search.config().event_retention_hours)
+            .await
+            .map_err(|e| AiError::Failed(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn record_progress(
+        &self,
+        last_seq: i64,
+        events: usize,
+        indexed: usize,
+    ) -> Result<(), AiError> {
+        sqlx::query(
+            r#"
+            UPDATE ai_index_state SET
+                last_seq = $2,
+                events_processed = events_processed + $3,
+                entities_indexed = entities_indexed + $4,
+                last_indexed_at = NOW()
+            WHERE worker = $1
+            "#,
+        )
+        .bind(WORKER)
+        .bind(last_seq)
+        .bind(events as i64)
+        .bind(indexed as i64)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn heartbeat(&self) -> Result<(), AiError> {
+        sqlx::query("UPDATE ai_index_state SET heartbeat_at = NOW() WHERE worker = $1")
+            .bind(WORKER)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| AiError::Failed(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn set_available(&self, available: bool) -> Result<(), AiError> {
+        sqlx::query(
+            r#"
+            UPDATE ai_index_state SET
+                available = $2,
+                heartbeat_at = NOW(),
+                last_error = CASE WHEN $2 THEN last_error ELSE 'pgvector extension not available' END
+            WHERE worker = $1
+            "#,
+        )
+        .bind(WORKER)
+        .bind(available)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn state(&self) -> Result<IndexWorkerState, AiError> {
+        sqlx::query_as::<_, IndexWorkerState>("SELECT * FROM ai_index_state WHERE worker = $1")
+            .bind(WORKER)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| AiError::Failed(e.to_string()))?
+            .ok_or_else(|| AiError::NotFound("Index worker state not found".to_string()))
+    }
+
+    pub async fn status(&self) -> Result<IndexWorkerStatus, AiError> {
+        let state = self.state().await?;
+        let pending_events: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM ontology_change_events WHERE seq > $1")
+                .bind(state.last_seq)
+                .fetch_one(&self.pool)
+                .await
+                .map_err(|e| AiError::Failed(e.to_string()))?;
+
+        // Missing two passes in a row means the worker has stalled
+        let stale_after = chrono::Duration::from_std(self.interval() * 2)
+            .unwrap_or_else(|_| chrono::Duration::hours(1));
+        let alive = state
+            .heartbeat_at
+            .is_some_and(|at| Utc::now() - at < stale_after);
+        let failing = match (state.last_error_at, state.last_indexed_at) {
+            (Some(error_at), Some(indexed_at)) => error_at > indexed_at,
+            (Some(_), None) => true,
+            _ => false,
+        };
+
+        Ok(IndexWorkerStatus {
+            backfilling: state.backfill_started_at.is_some()
+                && state.backfill_finished_at.is_none(),
+            healthy: state.available && alive && !failing,
+            pending_events,
+            state,
+        })
+    }
+
+    /// Re-embed every entity, oldest embeddings first, while search keeps
+    /// using the current ones. Restarts a backfill already in progress.
+    pub async fn request_backfill(&self) -> Result<IndexWorkerStatus, AiError> {
+        if !self.search.is_available().await? {
+            return Err(AiError::FeatureDisabled(
+                "Semantic search requires the pgvector extension".to_string(),
+            ));
+        }
+        sqlx::query(
+            r#"
+            UPDATE ai_index_state SET
+                backfill_started_at = NOW(),
+                backfill_total = (SELECT COUNT(*) FROM entities WHERE deleted_at IS NULL),
+                backfill_done = 0,
+                backfill_finished_at = NULL
+            WHERE worker = $1
+            "#,
+        )
+        .bind(WORKER)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))?;
+
+        // Wake the worker rather than waiting for its next pass
+        sqlx::query("SELECT pg_notify($1, '')")
+            .bind(CHANGES_CHANNEL)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| AiError::Failed(e.to_string()))?;
+
+        self.status().await
+    }
+}
@@ -1,2 +1,16 @@
+pub mod alert_explanations;
+pub mod audit_qa;
+pub mod authz_qa;
+pub mod conversations;
+pub mod extraction;
+pub mod index_worker;
+pub mod modeling;
+pub mod nl_query;
+pub mod policy_drafting;
+pub mod prompts;
+pub mod providers;
+pub mod redaction;
 pub mod routes;
+pub mod semantic_search;
 pub mod service;
+pub mod usage;
@@ -0,0 +1,190 @@
+use super::service::{AiError, AiService, GenerateRequest};
+use crate::features::ontology::changesets::validate_changes;
+use crate::features::ontology::models::{OntologyChangeset, SchemaChanges, PROPERTY_DATA_TYPES};
+use crate::features::ontology::service::OntologyError;
+use crate::features::ontology::OntologyService;
+use serde::Deserialize;
+use uuid::Uuid;
+use validator::Validate;
+
+const MAX_SAMPLES: usize = 50;
+
+/// Samples beyond this much JSON are dropped to keep the prompt within model context
+const MAX_SAMPLE_CHARS: usize = 20_000;
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct SuggestModelRequest {
+    /// Example records, e.g. rows from a spreadsheet or API export
+    #[validate(length(min = 1))]
+    pub samples: Vec<serde_json::Value>,
+    /// What the records are about, to steer naming
+    pub domain: Option<String>,
+}
+
+/// The model's answer: a changeset plus a short title and rationale for reviewers
+#[derive(Debug, Deserialize)]
+struct ModelSuggestion {
+    title: Option<String>,
+    rationale: Option<String>,
+    #[serde(flatten)]
+    changes: SchemaChanges,
+}
+
+/// Extract the suggestion from a model response, which may wrap the JSON in prose or code fences
+fn parse_suggestion(text: &str) -> Result<ModelSuggestion, AiError> {
+    let json = match (text.find('{'), text.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &text[start..=end],
+        _ => {
+            return Err(AiError::InvalidSuggestion(
+                "response contains no changeset".to_string(),
+            ))
+        }
+    };
+    let suggestion: ModelSuggestion = serde_json::from_str(json)
+        .map_err(|e| AiError::InvalidSuggestion(format!("malformed changeset: {}", e)))?;
+    validate_changes(&suggestion.changes).map_err(|e| AiError::InvalidSuggestion(e.to_string()))?;
+    Ok(suggestion)
+}
+
+/// Samples as JSON lines, stopping before the prompt budget is exceeded
+fn format_samples(samples: &[serde_json::Value]) -> String {
+    let mut text = String::new();
+    for sample in samples.iter().take(MAX_SAMPLES) {
+        let line = sample.to_string();
+        if !text.is_empty() && text.len() + line.len() > MAX_SAMPLE_CHARS {
+            break;
+        }
+        text.push_str(&line);
+        text.push('\n');
+    }
+    text
+}
+
+/// Turns sample records into a draft ontology changeset for an admin to review and apply.
+#[derive(Clone)]
+pub struct ModelingService {
+    ai: AiService,
+    ontology: OntologyService,
+}
+
+impl ModelingService {
+    pub fn new(ai: AiService, ontology: OntologyService) -> Self {
+        Self { ai, ontology }
+    }
+
+    pub async fn suggest(
+        &self,
+        user_id: Uuid,
+        request: SuggestModelRequest,
+    ) -> Result<OntologyChangeset, AiError> {
+        if request.samples.is_empty() {
+            return Err(AiError::InvalidSuggestion(
+                "at least one sample record is required".to_string(),
+            ));
+        }
+
+        // Existing classes are listed so the model extends them instead of inventing near-duplicates
+        let existing = self
+            .ontology
+            .list_classes(None)
+            .await
+            .map_err(|e| AiError::Failed(e.to_string()))?
+            .into_iter()
+            .filter(|c| !c.is_deprecated)
+            .map(|c| c.name)
+            .collect::<Vec<_>>();
+
+        let prompt = format!(
+            "Propose ontology changes that model the sample records below.\n\
+            Domain: {}\n\
+            Existing classes (reuse these names to extend them): {}\n\
+            \n\
+            Sample records (one JSON object per line):\n\
+            {}\n\
+            Suggest classes with typed properties, and relationship types between classes.\n\
+            Property data types: {}. Use \"reference\" with \"reference_class\" for properties that point to another class.\n\
+            Relationship cardinalities are \"one\" or \"many\".\n\
+            \n\
+            Respond ONLY with a JSON object:\n\
+            {{ \"title\": \"...\", \"rationale\": \"...\",\n\
+              \"classes\": [{{ \"name\": \"...\", \"description\": \"...\", \"parent_class\": null,\n\
+                \"properties\": [{{ \"name\": \"...\", \"data_type\": \"...\", \"description\": \"...\", \"is_required\": false, \"reference_class\": null }}] }}],\n\
+              \"relationship_types\": [{{ \"name\": \"...\", \"description\": \"...\", \"source_class\": \"...\", \"target_class\": \"...\", \"source_cardinality\": \"many\", \"target_cardinality\": \"one\" }}] }}",
+            request.domain.as_deref().unwrap_or("not specified"),
+            if existing.is_empty() {
+                "none".to_string()
+            } else {
+                existing.join(", ")
+            },
+            format_samples(&request.samples),
+            PROPERTY_DATA_TYPES.join(", "),
+        );
+
+        let response = self
+            .ai
+            .for_user(user_id)
+            .await
+            .generate_text(GenerateRequest {
+                prompt,
+                temperature: Some(0.2),
+                max_tokens: Some(2000),
+            })
+            .await?;
+        let suggestion = parse_suggestion(&response.text)?;
+
+        let title = suggestion
+            .title
+            .filter(|t| !t.trim().is_empty())
+            .unwrap_or_else(|| "AI modeling suggestion".to_string());
+        self.ontology
+            .create_changeset(
+                "ai",
+                &title,
+                suggestion.rationale,
+                suggestion.changes,
+                Some(user_id),
+            )
+            .await
+            .map_err(|e| match e {
+                OntologyError::InvalidInput(msg) => AiError::InvalidSuggestion(msg),
+                e => AiError::Failed(e.to_string()),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_suggestion() {
+        let text = "```json\n{\"title\": \"Missions\", \"classes\": [{\"name\": \"Mission\", \"properties\": [{\"name\": \"code\", \"data_type\": \"string\"}]}]}\n```";
+        let suggestion = parse_suggestion(text).unwrap();
+        assert_eq!(suggestion.title.as_deref(), Some("Missions"));
+        assert_eq!(suggestion.changes.classes[0].properties[0].name, "code");
+        assert!(suggestion.changes.relationship_types.is_empty());
+
+        assert!(matches!(
+            parse_suggestion("{\"classes\": [{\"name\": \"M\", \"properties\": [{\"name\": \"x\", \"data_type\": \"blob\"}]}]}"),
+            Err(AiError::InvalidSuggestion(_))
+        ));
+        assert!(matches!(
+            parse_suggestion("no idea"),
+            Err(AiError::InvalidSuggestion(_))
+        ));
+    }
+
+    #[test]
+    fn test_format_samples_respects_budget() {
+        let big = json!({ "notes": "x".repeat(MAX_SAMPLE_CHARS) });
+        let text = format_samples(&[json!({ "a": 1 }), big.clone(), json!({ "b": 2 })]);
+        assert_eq!(text, "{\"a\":1}\n");
+
+        // A single oversized sample is still sent rather than nothing
+        assert_eq!(
+            format_samples(std::slice::from_ref(&big)).len(),
+            big.to_string().len() + 1
+        );
+    }
+}
@@ -0,0 +1,402 @@
+use super::prompts::{BuiltinPrompt, PromptService};
+use super::service::{AiError, AiService, GenerateRequest};
+use crate::features::ontology::models::EntityWithDetails;
+use crate::features::rebac::condition_evaluator::evaluate_condition_group;
+use crate::features::rebac::policy_models::{ConditionGroup, EvaluationContext};
+use crate::features::rebac::RebacService;
+use crate::utils::validation::not_blank;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Postgres};
+use uuid::Uuid;
+use validator::Validate;
+
+/// Operators understood by `rebac::condition_evaluator`
+const FILTER_OPERATORS: &[&str] = &[
+    "==",
+    "!=",
+    ">",
+    ">=",
+    "<",
+    "<=",
+    "in",
+    "not_in",
+    "contains",
+    "matches",
+    "exists",
+    "not_exists",
+];
+
+/// Fields every entity has besides its class attributes
+const ENTITY_FIELDS: &[&str] = &["id", "display_name", "approval_status", "parent_entity_id"];
+
+/// Permission the caller needs on an entity for it to show up in results
+const READ_PERMISSION: &str = "read";
+
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 500;
+
+pub const NL_QUERY_PROMPT: BuiltinPrompt = BuiltinPrompt {
+    name: "nl_query",
+    description: "Translates a natural-language question into an entity query",
+    body: r#"Translate the question into a JSON query over an ontology of entities.
+
+Classes and their attributes:
+{{schema}}
+
+Every entity also has: {{entity_fields}}
+
+Query format:
+{ "class": "<class name>", "filter": { "all": [<condition>], "any": [<condition>] }, "limit": <optional number> }
+Each condition is { "attribute": "entity.<attribute>", "operator": "<operator>", "value": <JSON value> }.
+Operators: {{operators}}
+Every `all` condition must match, and at least one `any` condition when there are any.
+Use "not_exists" for attributes that are missing or unset.
+
+Question: {{question}}
+
+Respond ONLY with the JSON object."#,
+    variables: &[
+        ("schema", "One line per class: \"- Class: attribute, ...\""),
+        ("entity_fields", "Fields every entity has"),
+        ("operators", "Filter operators the query may use"),
+        ("question", "The user's question"),
+    ],
+};
+
+/// Entity filter the model translates questions into: a class plus the same
+/// condition DSL used by ReBAC policies, with attributes addressed as `entity.<name>`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityQuery {
+    pub class: String,
+    #[serde(default)]
+    pub filter: ConditionGroup,
+    pub limit: Option<usize>,
+}
+
+impl EntityQuery {
+    fn validate(&self) -> Result<(), String> {
+        if self.class.trim().is_empty() {
+            return Err("query has no class".to_string());
+        }
+        for condition in self.filter.all.iter().chain(self.filter.any.iter()) {
+            if condition
+                .attribute
+                .strip_prefix("entity.")
+                .is_none_or(str::is_empty)
+            {
+                return Err(format!(
+                    "attribute '{}' must be of the form entity.<name>",
+                    condition.attribute
+                ));
+            }
+            if !FILTER_OPERATORS.contains(&condition.operator.as_str()) {
+                return Err(format!("unknown operator '{}'", condition.operator));
+            }
+        }
+        Ok(())
+    }
+
+    fn attributes(&self) -> impl Iterator<Item = &str> {
+        self.filter
+            .all
+            .iter()
+            .chain(self.filter.any.iter())
+            .filter_map(|c| c.attribute.strip_prefix("entity."))
+    }
+
+    pub fn matches(&self, entity: &EntityWithDetails) -> bool {
+        let mut context = EvaluationContext::new();
+        if let Some(obj) = entity.attributes.as_object() {
+            for (k, v) in obj {
+                context.entity.insert(k.clone(), v.clone());
+            }
+        }
+        context.entity.insert(
+            "id".to_string(),
+            serde_json::Value::String(entity.id.to_string()),
+        );
+        context.entity.insert(
+            "display_name".to_string(),
+            serde_json::Value::String(entity.display_name.clone()),
+        );
+        context.entity.insert(
+            "approval_status".to_string(),
+            serde_json::to_value(&entity.approval_status).unwrap_or_default(),
+        );
+        if let Some(parent_id) = entity.parent_entity_id {
+            context.entity.insert(
+                "parent_entity_id".to_string(),
+                serde_json::Value::String(parent_id.to_string()),
+            );
+        }
+
+        evaluate_condition_group(&self.filter, &context)
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct NlQueryRequest {
+    #[validate(custom = "not_blank")]
+    pub question: String,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NlQueryResponse {
+    pub question: String,
+    /// The query the model generated, returned so callers can see what was run
+    pub query: EntityQuery,
+    pub results: Vec<EntityWithDetails>,
+    /// More entities matched than were returned
+    pub truncated: bool,
+}
+
+/// Extract the query from a model response, which may wrap the JSON in prose or code fences
+pub fn parse_entity_query(text: &str) -> Result<EntityQuery, AiError> {
+    let json = match (text.find('{'), text.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &text[start..=end],
+        _ => {
+            return Err(AiError::InvalidQuery(
+                "response contains no query".to_string(),
+            ))
+        }
+    };
+    let query: EntityQuery = serde_json::from_str(json)
+        .map_err(|e| AiError::InvalidQuery(format!("malformed query: {}", e)))?;
+    query.validate().map_err(AiError::InvalidQuery)?;
+    Ok(query)
+}
+
+/// Answers questions about the ontology by having the model write an entity
+/// query, then running it with the caller's read permissions.
+#[derive(Clone)]
+pub struct NlQueryService {
+    pool: Pool<Postgres>,
+    ai: AiService,
+    rebac: RebacService,
+    prompts: PromptService,
+}
+
+impl NlQueryService {
+    pub fn new(
+        pool: Pool<Postgres>,
+        ai: AiService,
+        rebac: RebacService,
+        prompts: PromptService,
+    ) -> Self {
+        Self {
+            pool,
+            ai,
+            rebac,
+            prompts,
+        }
+    }
+
+    pub async fn ask(
+        &self,
+        user_id: Uuid,
+        request: NlQueryRequest,
+    ) -> Result<NlQueryResponse, AiError> {
+        let question = request.question.trim().to_string();
+        if question.is_empty() {
+            return Err(AiError::InvalidQuery("question is required".to_string()));
+        }
+
+        let prompt = self.build_prompt(&question).await?;
+        let response = self
+            .ai
+            .for_user(user_id)
+            .await
+            .generate_text(GenerateRequest {
+                prompt,
+                temperature: Some(0.0),
+                max_tokens: Some(400),
+            })
+            .await?;
+        let query = parse_entity_query(&response.text)?;
+
+        let limit = request
+            .limit
+            .or(query.limit)
+            .unwrap_or(DEFAULT_LIMIT)
+            .clamp(1, MAX_LIMIT);
+        let (results, truncated) = self.execute(user_id, &query, limit).await?;
+
+        Ok(NlQueryResponse {
+            question,
+            query,
+            results,
+            truncated,
+        })
+    }
+
+    /// Classes and their non-sensitive attributes, so the model can map the
+    /// question's wording onto real names
+    async fn build_prompt(&self, question: &str) -> Result<String, AiError> {
+        let classes = sqlx::query_as::<_, (String, Vec<String>)>(
+            r#"
+            SELECT c.name,
+                   COALESCE(array_agg(DISTINCT p.name) FILTER (WHERE p.id IS NOT NULL), '{}')
+            FROM classes c
+            LEFT JOIN properties p
+              ON p.class_id = c.id AND p.is_deprecated = FALSE AND p.is_sensitive = FALSE
+            WHERE c.is_deprecated = FALSE
+            GROUP BY c.name
+            ORDER BY c.name
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))?;
+
+        let schema = classes
+            .iter()
+            .map(|(name, properties)| format!("- {}: {}", name, properties.join(", ")))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Ok(self
+            .prompts
+            .render(
+                &NL_QUERY_PROMPT,
+                &[
+                    ("schema", schema),
+                    ("entity_fields", ENTITY_FIELDS.join(", ")),
+                    ("operators", FILTER_OPERATORS.join(", ")),
+                    ("question", question.to_string()),
+                ],
+            )
+            .await)
+    }
+
+    async fn execute(
+        &self,
+        user_id: Uuid,
+        query: &EntityQuery,
+        limit: usize,
+    ) -> Result<(Vec<EntityWithDetails>, bool), AiError> {
+        // Filtering on a sensitive attribute would reveal its value through the results
+        let sensitive = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT DISTINCT p.name FROM properties p
+            JOIN classes c ON p.class_id = c.id
+            WHERE LOWER(c.name) = LOWER($1) AND p.is_sensitive = TRUE
+            "#,
+        )
+        .bind(&query.class)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))?;
+        if let Some(attribute) = query
+            .attributes()
+            .find(|a| sensitive.iter().any(|s| s == a))
+        {
+            return Err(AiError::InvalidQuery(format!(
+                "attribute '{}' cannot be queried",
+                attribute
+            )));
+        }
+
+        let accessible_ids: Vec<Uuid> = self
+            .rebac
+            .get_accessible_entities(user_id, READ_PERMISSION)
+            .await
+            .map_err(|e| AiError::Failed(e.to_string()))?
+            .into_iter()
+            .map(|e| e.entity_id)
+            .collect();
+
+        let entities = sqlx::query_as::<_, EntityWithDetails>(
+            r#"
+            SELECT e.id, e.class_id, c.name as class_name, e.display_name,
+                   e.parent_entity_id, p.display_name as parent_entity_name,
+                   e.tenant_id,
+                   e.attributes, e.approval_status, e.created_at, e.updated_at
+            FROM entities e
+            JOIN classes c ON e.class_id = c.id
+            LEFT JOIN entities p ON e.parent_entity_id = p.id
+            WHERE e.deleted_at IS NULL
+              AND LOWER(c.name) = LOWER($1)
+              AND e.id = ANY($2)
+            ORDER BY e.display_name
+            "#,
+        )
+        .bind(&query.class)
+        .bind(&accessible_ids)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))?;
+
+        let mut results: Vec<_> = entities.into_iter().filter(|e| query.matches(e)).collect();
+        let truncated = results.len() > limit;
+        results.truncate(limit);
+        Ok((results, truncated))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::ontology::models::ApprovalStatus;
+    use serde_json::json;
+
+    fn entity(attributes: serde_json::Value) -> EntityWithDetails {
+        EntityWithDetails {
+            id: Uuid::new_v4(),
+            class_id: Uuid::new_v4(),
+            class_name: "Mission".to_string(),
+            display_name: "Mission Alpha".to_string(),
+            parent_entity_id: None,
+            parent_entity_name: None,
+            tenant_id: None,
+            attributes,
+            approval_status: ApprovalStatus::APPROVED,
+            labels: Default::default(),
+            localized: None,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_parse_query_from_fenced_response() {
+        let text = "Here is the query:\n```json\n{\"class\": \"Mission\", \"filter\": {\"all\": [{\"attribute\": \"entity.owner\", \"operator\": \"not_exists\", \"value\": null}]}}\n```";
+        let query = parse_entity_query(text).unwrap();
+        assert_eq!(query.class, "Mission");
+        assert_eq!(query.filter.all.len(), 1);
+        assert!(query.filter.any.is_empty());
+
+        assert!(query.matches(&entity(json!({ "status": "active" }))));
+        assert!(!query.matches(&entity(json!({ "owner": "alice" }))));
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_queries() {
+        for text in [
+            "I don't know",
+            "{\"filter\": {}}",
+            "{\"class\": \"Mission\", \"filter\": {\"all\": [{\"attribute\": \"user.id\", \"operator\": \"==\", \"value\": 1}]}}",
+            "{\"class\": \"Mission\", \"filter\": {\"all\": [{\"attribute\": \"entity.x\", \"operator\": \"LIKE\", \"value\": 1}]}}",
+        ] {
+            assert!(
+                matches!(parse_entity_query(text), Err(AiError::InvalidQuery(_))),
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_builtin_fields_are_filterable() {
+        let query = parse_entity_query(
+            "{\"class\": \"Mission\", \"filter\": {\"any\": [{\"attribute\": \"entity.display_name\", \"operator\": \"contains\", \"value\": \"Alpha\"}, {\"attribute\": \"entity.priority\", \"operator\": \">\", \"value\": 3}]}}",
+        )
+        .unwrap();
+        assert!(query.matches(&entity(json!({ "priority": 1 }))));
+
+        let mut other = entity(json!({ "priority": 5 }));
+        other.display_name = "Mission Bravo".to_string();
+        assert!(query.matches(&other));
+        other.attributes = json!({ "priority": 2 });
+        assert!(!query.matches(&other));
+    }
+}
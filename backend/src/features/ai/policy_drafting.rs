@@ -0,0 +1,634 @@
+use super::prompts::{BuiltinPrompt, PromptService};
+use super::service::{AiError, AiService, GenerateRequest};
+use crate::features::rebac::policy_models::{ConditionGroup, CreatePolicyInput, Policy};
+use crate::features::rebac::policy_service::PolicyError;
+use crate::features::rebac::{PolicyService, RebacError, RebacService};
+use crate::utils::validation::not_blank;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::{FromRow, Pool, Postgres};
+use std::collections::HashSet;
+use uuid::Uuid;
+use validator::Validate;
+
+const MAX_REQUIREMENT_CHARS: usize = 2000;
+
+/// Operators the condition evaluator understands
+const OPERATORS: &[&str] = &[
+    "==",
+    "!=",
+    ">",
+    ">=",
+    "<",
+    "<=",
+    "in",
+    "not_in",
+    "contains",
+    "matches",
+    "exists",
+    "not_exists",
+];
+
+/// Context attributes the policy engine sets besides the entity's own attributes
+const USER_ATTRIBUTES: &[&str] = &["id", "email"];
+const ENV_ATTRIBUTES: &[&str] = &["now"];
+const REQUEST_ATTRIBUTES: &[&str] = &["permission"];
+
+pub const POLICY_DRAFT_PROMPT: BuiltinPrompt = BuiltinPrompt {
+    name: "policy_draft",
+    description: "Drafts an ABAC policy and schedule from an access requirement",
+    body: r#"You draft attribute-based access control policies from access requirements.
+Classes (with their attributes):
+{{classes}}
+
+Attributes conditions can test:
+{{attributes}}
+Operators: ==, !=, >, >=, <, <=, in, not_in, contains, matches, exists, not_exists
+Condition values are literals; a condition cannot compare two attributes.
+
+Time restrictions go in "schedule_cron", not in conditions. It is a UTC cron
+expression with seconds first ("sec min hour day-of-month month day-of-week"), and
+the policy applies during the minutes it fires, so a window must fire every minute:
+weekdays 09:00-16:59 is "0 * 9-16 * * Mon-Fri". Use null for no time restriction.
+
+Requirement: {{requirement}}
+
+Respond ONLY with a JSON object:
+{ "name": "...", "description": "...", "effect": "ALLOW" or "DENY", "priority": 0,
+  "target_class": "<class name, or null for every class>",
+  "target_permissions": ["read"],
+  "conditions": { "all": [{ "attribute": "entity.status", "operator": "==", "value": "Active" }], "any": [] },
+  "schedule_cron": null,
+  "rationale": "How the policy meets the requirement, and any part of it that could not be expressed" }"#,
+    variables: &[
+        ("classes", "One line per class: \"- Class: attribute, ...\""),
+        (
+            "attributes",
+            "One line per attribute the policy engine sets",
+        ),
+        (
+            "requirement",
+            "The access requirement, in the requester's words",
+        ),
+    ],
+};
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct DraftPolicyRequest {
+    /// e.g. "contractors can read documents in their own project during business hours"
+    #[validate(custom = "not_blank")]
+    pub requirement: String,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct ListPolicyDraftsQuery {
+    /// "draft", "approved" or "rejected"
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct ReviewPolicyDraftRequest {
+    pub note: Option<String>,
+}
+
+/// A validated policy, ready for `PolicyService::create_policy` once approved
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyProposal {
+    pub name: String,
+    pub description: Option<String>,
+    pub effect: String,
+    pub priority: i32,
+    pub target_class_id: Option<Uuid>,
+    pub target_class_name: Option<String>,
+    pub target_permissions: Vec<String>,
+    pub conditions: ConditionGroup,
+    pub schedule_cron: Option<String>,
+    /// The model's explanation, for the reviewer
+    pub rationale: Option<String>,
+}
+
+impl PolicyProposal {
+    fn to_input(&self) -> CreatePolicyInput {
+        CreatePolicyInput {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            effect: self.effect.clone(),
+            priority: Some(self.priority),
+            target_class_id: self.target_class_id,
+            target_permissions: self.target_permissions.clone(),
+            conditions: serde_json::to_value(&self.conditions).unwrap_or_default(),
+            scope_entity_id: None,
+            is_active: Some(true),
+            valid_from: None,
+            valid_until: None,
+            schedule_cron: self.schedule_cron.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct PolicyDraft {
+    pub id: Uuid,
+    pub requirement: String,
+    /// "draft", "approved" or "rejected"
+    pub status: String,
+    pub proposal: Json<PolicyProposal>,
+    pub warnings: Json<Vec<String>>,
+    pub policy_id: Option<Uuid>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub reviewed_by: Option<Uuid>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub review_note: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApprovePolicyDraftResponse {
+    pub draft: PolicyDraft,
+    pub policy: Policy,
+}
+
+/// What the model returns, before it is checked against the ontology and engine
+#[derive(Debug, Deserialize)]
+struct RawPolicyDraft {
+    name: String,
+    #[serde(default)]
+    description: Option<String>,
+    effect: String,
+    #[serde(default)]
+    priority: Option<i32>,
+    #[serde(default)]
+    target_class: Option<String>,
+    #[serde(default)]
+    target_permissions: Vec<String>,
+    #[serde(default)]
+    conditions: ConditionGroup,
+    #[serde(default)]
+    schedule_cron: Option<String>,
+    #[serde(default)]
+    rationale: Option<String>,
+}
+
+/// A class a policy can target, with its attribute names
+#[derive(Debug, Clone, FromRow)]
+struct TargetClass {
+    id: Uuid,
+    name: String,
+    properties: Vec<String>,
+}
+
+/// Extract the model's JSON, which may be wrapped in prose or code fences
+fn parse_policy_draft(text: &str) -> Result<RawPolicyDraft, AiError> {
+    let json = match (text.find('{'), text.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &text[start..=end],
+        _ => {
+            return Err(AiError::InvalidSuggestion(
+                "response contains no policy".to_string(),
+            ))
+        }
+    };
+    serde_json::from_str(json)
+        .map_err(|e| AiError::InvalidSuggestion(format!("malformed policy: {}", e)))
+}
+
+/// Check the draft against the policy engine. Anything that would make the
+/// policy wrong rather than merely ineffective (an unknown class, operator or
+/// schedule) rejects the draft, since dropping it would broaden the policy;
+/// the returned warnings point the reviewer at everything else.
+fn validate_draft(
+    raw: RawPolicyDraft,
+    classes: &[TargetClass],
+) -> Result<(PolicyProposal, Vec<String>), AiError> {
+    let mut warnings = Vec::new();
+
+    let name = raw.name.trim().to_string();
+    if name.is_empty() {
+        return Err(AiError::InvalidSuggestion("policy has no name".to_string()));
+    }
+    let effect = raw.effect.trim().to_uppercase();
+    if effect != "ALLOW" && effect != "DENY" {
+        return Err(AiError::InvalidSuggestion(format!(
+            "effect must be ALLOW or DENY, not {:?}",
+            raw.effect
+        )));
+    }
+
+    let target_class = match raw.target_class.as_deref().map(str::trim) {
+        None | Some("") | Some("*") => {
+            warnings.push("applies to entities of every class".to_string());
+            None
+        }
+        Some(class_name) => Some(
+            classes
+                .iter()
+                .find(|c| c.name.eq_ignore_ascii_case(class_name))
+                .ok_or_else(|| {
+                    AiError::InvalidSuggestion(format!("unknown class {:?}", class_name))
+                })?,
+        ),
+    };
+
+    let mut seen = HashSet::new();
+    let target_permissions: Vec<String> = raw
+        .target_permissions
+        .iter()
+        .map(|p| p.trim().to_lowercase())
+        .filter(|p| !p.is_empty() && seen.insert(p.clone()))
+        .collect();
+    if target_permissions.is_empty() {
+        warnings.push("applies to every permission".to_string());
+    }
+
+    for condition in raw.conditions.all.iter().chain(&raw.conditions.any) {
+        if !OPERATORS.contains(&condition.operator.as_str()) {
+            return Err(AiError::InvalidSuggestion(format!(
+                "unknown operator {:?}",
+                condition.operator
+            )));
+        }
+        let (source, key) = condition
+            .attribute
+            .split_once('.')
+            .filter(|(_, key)| !key.is_empty())
+            .ok_or_else(|| {
+                AiError::InvalidSuggestion(format!(
+                    "attribute {:?} must start with entity., user., env. or request.",
+                    condition.attribute
+                ))
+            })?;
+        let known = match source {
+            "entity" => {
+                key == "id"
+                    || key == "display_name"
+                    || target_class.is_none_or(|c| c.properties.iter().any(|p| p == key))
+            }
+            "user" => USER_ATTRIBUTES.contains(&key),
+            "env" => ENV_ATTRIBUTES.contains(&key),
+            "request" => REQUEST_ATTRIBUTES.contains(&key),
+            _ => {
+                return Err(AiError::InvalidSuggestion(format!(
+                    "attribute {:?} must start with entity., user., env. or request.",
+                    condition.attribute
+                )))
+            }
+        };
+        if !known {
+            warnings.push(format!(
+                "{} is not set by the policy engine, so conditions on it only match when it is missing",
+                condition.attribute
+            ));
+        }
+    }
+
+    let schedule_cron = raw
+        .schedule_cron
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty());
+    if let Some(cron) = &schedule_cron {
+        RebacService::validate_cron(cron).map_err(|e| match e {
+            RebacError::InvalidInput(msg) => AiError::InvalidSuggestion(msg),
+            e => AiError::InvalidSuggestion(e.to_string()),
+        })?;
+    }
+
+    let conditions = raw.conditions;
+    if effect == "ALLOW"
+        && conditions.all.is_empty()
+        && conditions.any.is_empty()
+        && schedule_cron.is_none()
+    {
+        warnings.push("allows access unconditionally".to_string());
+    }
+
+    Ok((
+        PolicyProposal {
+            name,
+            description: raw
+                .description
+                .map(|d| d.trim().to_string())
+                .filter(|d| !d.is_empty()),
+            effect,
+            priority: raw.priority.unwrap_or(0),
+            target_class_id: target_class.map(|c| c.id),
+            target_class_name: target_class.map(|c| c.name.clone()),
+            target_permissions,
+            conditions,
+            schedule_cron,
+            rationale: raw
+                .rationale
+                .map(|r| r.trim().to_string())
+                .filter(|r| !r.is_empty()),
+        },
+        warnings,
+    ))
+}
+
+/// Values for `POLICY_DRAFT_PROMPT`
+fn prompt_variables(requirement: &str, classes: &[TargetClass]) -> [(&'static str, String); 3] {
+    let schema = classes
+        .iter()
+        .map(|c| format!("- {}: {}", c.name, c.properties.join(", ")))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let attributes = [
+        "- entity.id, entity.display_name: the entity being accessed",
+        "- entity.<attribute>: any attribute of the entity's class",
+        "- user.id, user.email: the user requesting access",
+        "- env.now: the current time, RFC 3339",
+        "- request.permission: the permission being checked",
+    ]
+    .join("\n");
+
+    [
+        ("classes", schema),
+        ("attributes", attributes),
+        ("requirement", requirement.to_string()),
+    ]
+}
+
+/// Turns natural-language access requirements into ABAC policy drafts. Drafts
+/// are never enforced; an admin must approve one before its policy is created.
+#[derive(Clone)]
+pub struct PolicyDraftingService {
+    pool: Pool<Postgres>,
+    ai: AiService,
+    prompts: PromptService,
+    policies: PolicyService,
+}
+
+impl PolicyDraftingService {
+    pub fn new(
+        pool: Pool<Postgres>,
+        ai: AiService,
+        prompts: PromptService,
+        policies: PolicyService,
+    ) -> Self {
+        Self {
+            pool,
+            ai,
+            prompts,
+            policies,
+        }
+    }
+
+    pub async fn draft(
+        &self,
+        user_id: Uuid,
+        request: DraftPolicyRequest,
+    ) -> Result<PolicyDraft, AiError> {
+        let requirement = request.requirement.trim().to_string();
+        if requirement.is_empty() {
+            return Err(AiError::InvalidInput("requirement is required".to_string()));
+        }
+        if requirement.chars().count() > MAX_REQUIREMENT_CHARS {
+            return Err(AiError::InvalidInput(format!(
+                "requirement is longer than {} characters",
+                MAX_REQUIREMENT_CHARS
+            )));
+        }
+
+        let classes = self.target_classes().await?;
+        let prompt = self
+            .prompts
+            .render(
+                &POLICY_DRAFT_PROMPT,
+                &prompt_variables(&requirement, &classes),
+            )
+            .await;
+        let response = self
+            .ai
+            .for_user(user_id)
+            .await
+            .generate_text(GenerateRequest {
+                prompt,
+                temperature: Some(0.0),
+                max_tokens: Some(1000),
+            })
+            .await?;
+
+        let (proposal, warnings) = validate_draft(parse_policy_draft(&response.text)?, &classes)?;
+
+        sqlx::query_as::<_, PolicyDraft>(
+            r#"
+            INSERT INTO ai_policy_drafts (requirement, proposal, warnings, created_by)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *
+            "#,
+        )
+        .bind(&requirement)
+        .bind(Json(&proposal))
+        .bind(Json(&warnings))
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))
+    }
+
+    pub async fn list(&self, query: ListPolicyDraftsQuery) -> Result<Vec<PolicyDraft>, AiError> {
+        sqlx::query_as::<_, PolicyDraft>(
+            r#"
+            SELECT * FROM ai_policy_drafts
+            WHERE ($1::TEXT IS NULL OR status = $1)
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(query.status)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))
+    }
+
+    pub async fn get(&self, id: Uuid) -> Result<PolicyDraft, AiError> {
+        sqlx::query_as::<_, PolicyDraft>("SELECT * FROM ai_policy_drafts WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| AiError::Failed(e.to_string()))?
+            .ok_or_else(|| AiError::NotFound("Policy draft not found".to_string()))
+    }
+
+    /// Create and activate the drafted policy
+    pub async fn approve(
+        &self,
+        user_id: Uuid,
+        id: Uuid,
+        request: ReviewPolicyDraftRequest,
+    ) -> Result<ApprovePolicyDraftResponse, AiError> {
+        // Claim the draft first so concurrent approvals can't both create a policy
+        let draft = self
+            .transition(user_id, id, "approved", request.note)
+            .await?;
+
+        let policy = match self
+            .policies
+            .create_policy(draft.proposal.to_input(), Some(user_id))
+            .await
+        {
+            Ok(policy) => policy,
+            Err(e) => {
+                let _ = sqlx::query(
+                    r#"
+                    UPDATE ai_policy_drafts
+                    SET status = 'draft', reviewed_by = NULL, reviewed_at = NULL, review_note = NULL
+                    WHERE id = $1
+                    "#,
+                )
+                .bind(id)
+                .execute(&self.pool)
+                .await;
+                return Err(match e {
+                    PolicyError::InvalidInput(msg) => AiError::InvalidInput(msg),
+                    e => AiError::Failed(e.to_string()),
+                });
+            }
+        };
+
+        let draft = sqlx::query_as::<_, PolicyDraft>(
+            "UPDATE ai_policy_drafts SET policy_id = $2 WHERE id = $1 RETURNING *",
+        )
+        .bind(id)
+        .bind(policy.id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))?;
+
+        Ok(ApprovePolicyDraftResponse { draft, policy })
+    }
+
+    pub async fn reject(
+        &self,
+        user_id: Uuid,
+        id: Uuid,
+        request: ReviewPolicyDraftRequest,
+    ) -> Result<PolicyDraft, AiError> {
+        self.transition(user_id, id, "rejected", request.note).await
+    }
+
+    /// Move a pending draft to its final status
+    async fn transition(
+        &self,
+        user_id: Uuid,
+        id: Uuid,
+        status: &str,
+        note: Option<String>,
+    ) -> Result<PolicyDraft, AiError> {
+        let draft = sqlx::query_as::<_, PolicyDraft>(
+            r#"
+            UPDATE ai_policy_drafts
+            SET status = $3, reviewed_by = $2, reviewed_at = NOW(), review_note = $4
+            WHERE id = $1 AND status = 'draft'
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(status)
+        .bind(note)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))?;
+
+        match draft {
+            Some(draft) => Ok(draft),
+            None => {
+                let existing = self.get(id).await?;
+                Err(AiError::Conflict(format!(
+                    "Policy draft is already {}",
+                    existing.status
+                )))
+            }
+        }
+    }
+
+    /// Concrete global classes with their attribute names. Policies match
+    /// entities by class id, so classes of earlier ontology versions count too;
+    /// on a name clash the current version's class wins.
+    async fn target_classes(&self) -> Result<Vec<TargetClass>, AiError> {
+        sqlx::query_as::<_, TargetClass>(
+            r#"
+            SELECT DISTINCT ON (c.name) c.id, c.name,
+                   COALESCE(array_agg(p.name ORDER BY p.name) FILTER (WHERE p.id IS NOT NULL), '{}') AS properties
+            FROM classes c
+            LEFT JOIN ontology_versions v ON v.id = c.version_id
+            LEFT JOIN properties p ON p.class_id = c.id AND p.is_deprecated = FALSE
+            WHERE c.is_deprecated = FALSE AND c.is_abstract = FALSE AND c.tenant_id IS NULL
+            GROUP BY c.id, c.name, v.is_current
+            ORDER BY c.name, v.is_current DESC NULLS LAST
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classes() -> Vec<TargetClass> {
+        vec![TargetClass {
+            id: Uuid::new_v4(),
+            name: "Document".to_string(),
+            properties: vec!["project_id".to_string(), "status".to_string()],
+        }]
+    }
+
+    #[test]
+    fn test_validate_draft_maps_class_and_flags_unset_attributes() {
+        let raw = parse_policy_draft(
+            r#"```json
+            { "name": "Contractor document access", "effect": "allow",
+              "target_class": "document", "target_permissions": ["Read", "read"],
+              "conditions": { "all": [
+                { "attribute": "entity.project_id", "operator": "exists" },
+                { "attribute": "user.role", "operator": "==", "value": "contractor" }
+              ] },
+              "schedule_cron": "0 * 9-16 * * Mon-Fri" }
+            ```"#,
+        )
+        .unwrap();
+        let classes = classes();
+        let (proposal, warnings) = validate_draft(raw, &classes).unwrap();
+
+        assert_eq!(proposal.effect, "ALLOW");
+        assert_eq!(proposal.target_class_id, Some(classes[0].id));
+        assert_eq!(proposal.target_permissions, vec!["read"]);
+        assert_eq!(
+            proposal.schedule_cron.as_deref(),
+            Some("0 * 9-16 * * Mon-Fri")
+        );
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("user.role"));
+    }
+
+    #[test]
+    fn test_validate_draft_rejects_what_would_change_its_meaning() {
+        let draft = |fields: &str| {
+            parse_policy_draft(&format!(
+                r#"{{ "name": "p", "effect": "ALLOW", {} }}"#,
+                fields
+            ))
+            .unwrap()
+        };
+        for fields in [
+            r#""target_class": "Spreadsheet""#,
+            r#""conditions": { "all": [{ "attribute": "entity.status", "operator": "like", "value": "x" }] }"#,
+            r#""conditions": { "any": [{ "attribute": "status", "operator": "==", "value": "x" }] }"#,
+            r#""schedule_cron": "business hours""#,
+        ] {
+            assert!(
+                matches!(
+                    validate_draft(draft(fields), &classes()),
+                    Err(AiError::InvalidSuggestion(_))
+                ),
+                "{} should be rejected",
+                fields
+            );
+        }
+
+        let (_, warnings) = validate_draft(draft(r#""target_class": null"#), &classes()).unwrap();
+        assert!(warnings.contains(&"allows access unconditionally".to_string()));
+    }
+}
@@ -0,0 +1,601 @@
+use super::alert_explanations::ALERT_EXPLANATION_PROMPT;
+use super::audit_qa::AUDIT_QA_PROMPT;
+use super::authz_qa::{AUTHZ_ANSWER_PROMPT, AUTHZ_QUERY_PROMPT};
+use super::conversations::CONVERSATION_PROMPT;
+use super::extraction::EXTRACTION_PROMPT;
+use super::nl_query::NL_QUERY_PROMPT;
+use super::policy_drafting::POLICY_DRAFT_PROMPT;
+use super::service::AiError;
+use crate::utils::validation::not_blank;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::{FromRow, Pool, Postgres};
+use std::collections::HashSet;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Prompts the backend itself uses. Each is seeded as version 1 of a template
+/// with the same name, and its body is the fallback when the stored template
+/// is missing or can't be rendered.
+pub const BUILTIN_PROMPTS: &[&BuiltinPrompt] =
+    &[
+        &NL_QUERY_PROMPT,
+        &EXTRACTION_PROMPT,
+        &CONVERSATION_PROMPT,
+        &AUDIT_QA_PROMPT,
+        &POLICY_DRAFT_PROMPT,
+        &ALERT_EXPLANATION_PROMPT,
+        &AUTHZ_QUERY_PROMPT,
+        &AUTHZ_ANSWER_PROMPT,
+    ];
+
+/// A prompt defined in code, with the variables the calling feature supplies
+pub struct BuiltinPrompt {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub body: &'static str,
+    /// (name, description)
+    pub variables: &'static [(&'static str, &'static str)],
+}
+
+impl BuiltinPrompt {
+    fn declared_variables(&self) -> Vec<PromptVariable> {
+        self.variables
+            .iter()
+            .map(|(name, description)| PromptVariable {
+                name: name.to_string(),
+                description: Some(description.to_string()),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PromptVariable {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct PromptTemplate {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub is_builtin: bool,
+    pub active_version: i32,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct PromptTemplateVersion {
+    pub id: Uuid,
+    pub template_id: Uuid,
+    pub version: i32,
+    pub body: String,
+    pub variables: Json<Vec<PromptVariable>>,
+    pub note: Option<String>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A template with the body and variables of its active version
+#[derive(Debug, Serialize)]
+pub struct PromptTemplateDetail {
+    #[serde(flatten)]
+    pub template: PromptTemplate,
+    pub body: String,
+    pub variables: Vec<PromptVariable>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreatePromptTemplateInput {
+    #[validate(custom = "not_blank", length(max = 100))]
+    pub name: String,
+    pub description: Option<String>,
+    #[validate(custom = "not_blank")]
+    pub body: String,
+    #[serde(default)]
+    pub variables: Vec<PromptVariable>,
+    pub note: Option<String>,
+}
+
+/// Saved as a new version, which becomes active
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdatePromptTemplateInput {
+    pub description: Option<String>,
+    #[validate(custom = "not_blank")]
+    pub body: String,
+    /// Defaults to the active version's variables
+    pub variables: Option<Vec<PromptVariable>>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct RenderPromptInput {
+    #[serde(default)]
+    pub variables: std::collections::HashMap<String, String>,
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Split a body into literal text and `{{name}}` placeholders. Braces around
+/// anything that isn't an identifier are literal, so JSON examples are safe.
+fn segments(body: &str) -> Vec<(&str, bool)> {
+    let mut parts = Vec::new();
+    let mut rest = body;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) if is_identifier(after[..end].trim()) => {
+                parts.push((&rest[..start], false));
+                parts.push((after[..end].trim(), true));
+                rest = &after[end + 2..];
+            }
+            _ => {
+                parts.push((&rest[..start + 2], false));
+                rest = after;
+            }
+        }
+    }
+    parts.push((rest, false));
+    parts
+}
+
+/// Distinct placeholder names in the order they first appear
+pub fn placeholders(body: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    segments(body)
+        .into_iter()
+        .filter(|(text, is_placeholder)| *is_placeholder && seen.insert(*text))
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+/// Substitute `values` into the body. Substituted text is not scanned again,
+/// so values containing `{{...}}` come through unchanged.
+pub fn render_template<V: AsRef<str>>(body: &str, values: &[(&str, V)]) -> Result<String, String> {
+    let mut out = String::with_capacity(body.len());
+    for (text, is_placeholder) in segments(body) {
+        if is_placeholder {
+            let (_, value) = values
+                .iter()
+                .find(|(name, _)| *name == text)
+                .ok_or_else(|| format!("no value for variable '{}'", text))?;
+            out.push_str(value.as_ref());
+        } else {
+            out.push_str(text);
+        }
+    }
+    Ok(out)
+}
+
+fn validate_template(body: &str, variables: &[PromptVariable]) -> Result<(), AiError> {
+    if body.trim().is_empty() {
+        return Err(AiError::InvalidInput("body is required".to_string()));
+    }
+    let mut declared = HashSet::new();
+    for variable in variables {
+        if !is_identifier(&variable.name) {
+            return Err(AiError::InvalidInput(format!(
+                "invalid variable name '{}'",
+                variable.name
+            )));
+        }
+        if !declared.insert(variable.name.as_str()) {
+            return Err(AiError::InvalidInput(format!(
+                "variable '{}' is declared twice",
+                variable.name
+            )));
+        }
+    }
+    if let Some(undeclared) = placeholders(body)
+        .into_iter()
+        .find(|p| !declared.contains(p.as_str()))
+    {
+        return Err(AiError::InvalidInput(format!(
+            "placeholder '{{{{{}}}}}' is not a declared variable",
+            undeclared
+        )));
+    }
+    Ok(())
+}
+
+fn builtin(name: &str) -> Option<&'static BuiltinPrompt> {
+    BUILTIN_PROMPTS.iter().copied().find(|p| p.name == name)
+}
+
+fn db_error(e: sqlx::Error) -> AiError {
+    AiError::Failed(e.to_string())
+}
+
+/// Stores prompt templates and renders them for AI features.
+#[derive(Clone)]
+pub struct PromptService {
+    pool: Pool<Postgres>,
+}
+
+impl PromptService {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+
+    /// Create version 1 of each built-in prompt that isn't stored yet
+    pub async fn seed_builtins(&self) -> Result<(), AiError> {
+        for prompt in BUILTIN_PROMPTS {
+            let mut tx = self.pool.begin().await.map_err(db_error)?;
+            let inserted = sqlx::query_scalar::<_, Uuid>(
+                r#"
+                INSERT INTO prompt_templates (name, description, is_builtin)
+                VALUES ($1, $2, TRUE)
+                ON CONFLICT (name) DO NOTHING
+                RETURNING id
+                "#,
+            )
+            .bind(prompt.name)
+            .bind(prompt.description)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(db_error)?;
+
+            if let Some(template_id) = inserted {
+                sqlx::query(
+                    r#"
+                    INSERT INTO prompt_template_versions (template_id, version, body, variables, note)
+                    VALUES ($1, 1, $2, $3, 'Built-in default')
+                    "#,
+                )
+                .bind(template_id)
+                .bind(prompt.body)
+                .bind(Json(prompt.declared_variables()))
+                .execute(&mut *tx)
+                .await
+                .map_err(db_error)?;
+            }
+            tx.commit().await.map_err(db_error)?;
+        }
+        Ok(())
+    }
+
+    /// Render the stored template for a built-in prompt, falling back to the
+    /// code default if it's missing or needs variables the feature doesn't supply
+    pub async fn render<V: AsRef<str>>(
+        &self,
+        prompt: &BuiltinPrompt,
+        values: &[(&str, V)],
+    ) -> String {
+        let stored = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT v.body FROM prompt_templates t
+            JOIN prompt_template_versions v ON v.template_id = t.id AND v.version = t.active_version
+            WHERE t.name = $1
+            "#,
+        )
+        .bind(prompt.name)
+        .fetch_optional(&self.pool)
+        .await;
+
+        match stored {
+            Ok(Some(body)) => match render_template(&body, values) {
+                Ok(text) => return text,
+                Err(e) => tracing::warn!(
+                    "Prompt template '{}' can't be rendered, using the built-in default: {}",
+                    prompt.name,
+                    e
+                ),
+            },
+            Ok(None) => {}
+            Err(e) => tracing::warn!(
+                "Failed to load prompt template '{}', using the built-in default: {}",
+                prompt.name,
+                e
+            ),
+        }
+        render_template(prompt.body, values).unwrap_or_else(|_| prompt.body.to_string())
+    }
+
+    pub async fn list(&self) -> Result<Vec<PromptTemplate>, AiError> {
+        sqlx::query_as::<_, PromptTemplate>("SELECT * FROM prompt_templates ORDER BY name")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(db_error)
+    }
+
+    async fn find(&self, name: &str) -> Result<PromptTemplate, AiError> {
+        sqlx::query_as::<_, PromptTemplate>("SELECT * FROM prompt_templates WHERE name = $1")
+            .bind(name)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(db_error)?
+            .ok_or_else(|| AiError::NotFound(format!("Prompt template '{}' not found", name)))
+    }
+
+    async fn version(
+        &self,
+        template_id: Uuid,
+        version: i32,
+    ) -> Result<Option<PromptTemplateVersion>, AiError> {
+        sqlx::query_as::<_, PromptTemplateVersion>(
+            "SELECT * FROM prompt_template_versions WHERE template_id = $1 AND version = $2",
+        )
+        .bind(template_id)
+        .bind(version)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(db_error)
+    }
+
+    pub async fn get(&self, name: &str) -> Result<PromptTemplateDetail, AiError> {
+        let template = self.find(name).await?;
+        let active = self
+            .version(template.id, template.active_version)
+            .await?
+            .ok_or_else(|| {
+                AiError::Failed(format!("Prompt template '{}' has no active version", name))
+            })?;
+        Ok(PromptTemplateDetail {
+            template,
+            body: active.body,
+            variables: active.variables.0,
+        })
+    }
+
+    pub async fn list_versions(&self, name: &str) -> Result<Vec<PromptTemplateVersion>, AiError> {
+        let template = self.find(name).await?;
+        sqlx::query_as::<_, PromptTemplateVersion>(
+            "SELECT * FROM prompt_template_versions WHERE template_id = $1 ORDER BY version DESC",
+        )
+        .bind(template.id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(db_error)
+    }
+
+    pub async fn create(
+        &self,
+        input: CreatePromptTemplateInput,
+        user_id: Uuid,
+    ) -> Result<PromptTemplateDetail, AiError> {
+        let name = input.name.trim();
+        if name.is_empty() || name.len() > 100 {
+            return Err(AiError::InvalidInput(
+                "name must be 1-100 characters".to_string(),
+            ));
+        }
+        validate_template(&input.body, &input.variables)?;
+
+        let mut tx = self.pool.begin().await.map_err(db_error)?;
+        let template = sqlx::query_as::<_, PromptTemplate>(
+            r#"
+            INSERT INTO prompt_templates (name, description, created_by)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (name) DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(name)
+        .bind(&input.description)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(db_error)?
+        .ok_or_else(|| AiError::Conflict(format!("Prompt template '{}' already exists", name)))?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO prompt_template_versions (template_id, version, body, variables, note, created_by)
+            VALUES ($1, 1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(template.id)
+        .bind(&input.body)
+        .bind(Json(&input.variables))
+        .bind(&input.note)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+        tx.commit().await.map_err(db_error)?;
+
+        Ok(PromptTemplateDetail {
+            template,
+            body: input.body,
+            variables: input.variables,
+        })
+    }
+
+    /// Save a new version and make it active
+    pub async fn update(
+        &self,
+        name: &str,
+        input: UpdatePromptTemplateInput,
+        user_id: Uuid,
+    ) -> Result<PromptTemplateDetail, AiError> {
+        let current = self.get(name).await?;
+        let variables = input.variables.unwrap_or(current.variables);
+        validate_template(&input.body, &variables)?;
+
+        // The feature using a built-in prompt only supplies its own variables
+        if let Some(prompt) = builtin(name).filter(|_| current.template.is_builtin) {
+            if let Some(unknown) = variables
+                .iter()
+                .find(|v| !prompt.variables.iter().any(|(name, _)| *name == v.name))
+            {
+                return Err(AiError::InvalidInput(format!(
+                    "'{}' only supplies the variables {}; '{}' would never be filled in",
+                    name,
+                    prompt
+                        .variables
+                        .iter()
+                        .map(|(name, _)| *name)
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    unknown.name
+                )));
+            }
+        }
+
+        let mut tx = self.pool.begin().await.map_err(db_error)?;
+        // Locking the template row serializes concurrent edits
+        let template = sqlx::query_as::<_, PromptTemplate>(
+            r#"
+            UPDATE prompt_templates
+            SET active_version = (SELECT MAX(version) + 1 FROM prompt_template_versions WHERE template_id = $1),
+                description = COALESCE($2, description),
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(current.template.id)
+        .bind(&input.description)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(db_error)?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO prompt_template_versions (template_id, version, body, variables, note, created_by)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(template.id)
+        .bind(template.active_version)
+        .bind(&input.body)
+        .bind(Json(&variables))
+        .bind(&input.note)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+        tx.commit().await.map_err(db_error)?;
+
+        Ok(PromptTemplateDetail {
+            template,
+            body: input.body,
+            variables,
+        })
+    }
+
+    /// Make an earlier (or later) version active again
+    pub async fn activate_version(
+        &self,
+        name: &str,
+        version: i32,
+    ) -> Result<PromptTemplateDetail, AiError> {
+        let template = self.find(name).await?;
+        let selected = self.version(template.id, version).await?.ok_or_else(|| {
+            AiError::NotFound(format!(
+                "Prompt template '{}' has no version {}",
+                name, version
+            ))
+        })?;
+
+        let template = sqlx::query_as::<_, PromptTemplate>(
+            "UPDATE prompt_templates SET active_version = $2, updated_at = NOW() WHERE id = $1 RETURNING *",
+        )
+        .bind(template.id)
+        .bind(version)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(db_error)?;
+
+        Ok(PromptTemplateDetail {
+            template,
+            body: selected.body,
+            variables: selected.variables.0,
+        })
+    }
+
+    pub async fn delete(&self, name: &str) -> Result<(), AiError> {
+        let template = self.find(name).await?;
+        if template.is_builtin {
+            return Err(AiError::Conflict(format!(
+                "'{}' is used by the backend and can't be deleted; activate version 1 to restore the default",
+                name
+            )));
+        }
+        sqlx::query("DELETE FROM prompt_templates WHERE id = $1")
+            .bind(template.id)
+            .execute(&self.pool)
+            .await
+            .map_err(db_error)?;
+        Ok(())
+    }
+
+    /// Preview the active version with the given values
+    pub async fn preview(&self, name: &str, input: RenderPromptInput) -> Result<String, AiError> {
+        let template = self.get(name).await?;
+        let values: Vec<(&str, &str)> = input
+            .variables
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        render_template(&template.body, &values).map_err(AiError::InvalidInput)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn var(name: &str) -> PromptVariable {
+        PromptVariable {
+            name: name.to_string(),
+            description: None,
+        }
+    }
+
+    #[test]
+    fn test_placeholders_ignore_json_braces() {
+        let body = "Question: {{ question }}\nRespond with {\"a\": {{}}, \"b\": [{\"c\": 1}]} about {{question}} and {{schema}}";
+        assert_eq!(placeholders(body), vec!["question", "schema"]);
+    }
+
+    #[test]
+    fn test_render_template() {
+        let body = "Hello {{name}}, {\"x\": {{}}} {{name}}";
+        assert_eq!(
+            render_template(body, &[("name", "{{name}}")]).unwrap(),
+            "Hello {{name}}, {\"x\": {{}}} {{name}}"
+        );
+        assert!(render_template("{{missing}}", &[("name", "x")]).is_err());
+    }
+
+    #[test]
+    fn test_validate_template() {
+        assert!(validate_template("Hi {{name}}", &[var("name")]).is_ok());
+        assert!(matches!(
+            validate_template("Hi {{name}}", &[]),
+            Err(AiError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            validate_template("Hi", &[var("name"), var("name")]),
+            Err(AiError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            validate_template("Hi", &[var("bad name")]),
+            Err(AiError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            validate_template("  ", &[]),
+            Err(AiError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_builtin_prompts_declare_their_placeholders() {
+        for prompt in BUILTIN_PROMPTS {
+            validate_template(prompt.body, &prompt.declared_variables())
+                .unwrap_or_else(|e| panic!("{}: {}", prompt.name, e));
+        }
+    }
+}
@@ -0,0 +1,913 @@
+use super::service::{AiError, GenerateRequest, TextStream};
+use super::usage::TokenUsage;
+use crate::config::AiProvidersConfig;
+use crate::utils::http_client::{OutboundClient, RetryPolicy};
+use async_openai::{
+    config::OpenAIConfig,
+    error::OpenAIError,
+    types::{
+        ChatCompletionRequestMessage, ChatCompletionRequestUserMessage,
+        ChatCompletionRequestUserMessageContent, CreateChatCompletionRequest,
+        CreateChatCompletionRequestArgs, CreateEmbeddingRequestArgs,
+    },
+    Client,
+};
+use axum::async_trait;
+use futures::{Stream, StreamExt};
+use std::sync::Arc;
+use uuid::Uuid;
+
+const ANTHROPIC_DEFAULT_BASE: &str = "https://api.anthropic.com";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Environment variables an `AiProvider` entity's `api_key_env` may name, so
+/// whoever can edit entities can't point it at the server's other secrets
+pub const API_KEY_ENV_PREFIX: &str = "AI_PROVIDER_KEY_";
+
+const DEFAULT_TEMPERATURE: f32 = 0.7;
+const DEFAULT_MAX_TOKENS: u16 = 1024;
+
+/// Provider API flavours, from the `provider_type` attribute of an `AiProvider` entity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    Ollama,
+    /// OpenAI itself or any server exposing its API (vLLM, LM Studio, LiteLLM, ...)
+    OpenAi,
+    Anthropic,
+}
+
+impl ProviderKind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value
+            .trim()
+            .to_ascii_lowercase()
+            .replace(['-', '_', ' '], "")
+            .as_str()
+        {
+            "ollama" => Some(Self::Ollama),
+            "openai" | "openaicompatible" => Some(Self::OpenAi),
+            "anthropic" => Some(Self::Anthropic),
+            _ => None,
+        }
+    }
+}
+
+/// The key in `var` for a provider at `api_base`; None unless `var` is an
+/// `AI_PROVIDER_KEY_*` variable and `api_base` is an https URL on one of
+/// `config.key_hosts`
+fn resolve_api_key(var: &str, api_base: &str, config: &AiProvidersConfig) -> Option<String> {
+    if !var.starts_with(API_KEY_ENV_PREFIX) {
+        tracing::warn!(
+            "Ignoring api_key_env {}: provider keys must be in {}* variables",
+            var,
+            API_KEY_ENV_PREFIX
+        );
+        return None;
+    }
+    let host = reqwest::Url::parse(api_base)
+        .ok()
+        .filter(|url| url.scheme() == "https")
+        .and_then(|url| url.host_str().map(str::to_ascii_lowercase));
+    let trusted = host.as_deref().is_some_and(|host| {
+        config.key_hosts.iter().any(|allowed| {
+            let allowed = allowed.to_ascii_lowercase();
+            host == allowed || host.ends_with(&format!(".{}", allowed))
+        })
+    });
+    if !trusted {
+        tracing::warn!(
+            "Not sending {} to {}: not an https URL on [ai_providers] key_hosts",
+            var,
+            api_base
+        );
+        return None;
+    }
+    std::env::var(var).ok()
+}
+
+/// A configured provider, in the order it should be tried
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ProviderConfig {
+    /// The `AiProvider` entity, or None for the environment fallback
+    pub id: Option<Uuid>,
+    pub name: String,
+    pub kind: ProviderKind,
+    pub api_base: String,
+    pub model: String,
+    /// Key read from the environment variable named by `api_key_env`, so
+    /// secrets stay out of entity attributes; see `resolve_api_key`
+    #[serde(skip)]
+    pub api_key: Option<String>,
+    /// Lower is tried first
+    pub priority: i64,
+    /// Providers scoped to a tenant take precedence over global ones for its users
+    pub tenant_id: Option<Uuid>,
+    /// False when the last background health check failed
+    pub healthy: bool,
+    /// USD per 1000 prompt tokens, for usage accounting; 0 when not set
+    pub input_cost_per_1k: f64,
+    /// USD per 1000 completion tokens
+    pub output_cost_per_1k: f64,
+}
+
+impl ProviderConfig {
+    /// Read an `AiProvider` entity's attributes; None if it can't be used
+    pub fn from_entity(
+        id: Uuid,
+        name: String,
+        tenant_id: Option<Uuid>,
+        attributes: &serde_json::Value,
+        config: &AiProvidersConfig,
+    ) -> Option<Self> {
+        let text = |key: &str| {
+            attributes
+                .get(key)
+                .and_then(|v| v.as_str())
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+        };
+        let price = |key: &str| {
+            attributes
+                .get(key)
+                .and_then(|v| v.as_f64())
+                .filter(|p| *p >= 0.0)
+                .unwrap_or(0.0)
+        };
+        // Providers predating provider_type were all Ollama
+        let kind = match text("provider_type") {
+            Some(value) => ProviderKind::parse(value)?,
+            None => ProviderKind::Ollama,
+        };
+        let api_base = match (text("api_base"), kind) {
+            (Some(base), _) => base.to_string(),
+            (None, ProviderKind::Anthropic) => ANTHROPIC_DEFAULT_BASE.to_string(),
+            (None, _) => return None,
+        };
+        let api_key = text("api_key_env").and_then(|var| resolve_api_key(var, &api_base, config));
+
+        Some(Self {
+            id: Some(id),
+            name,
+            kind,
+            api_base,
+            model: text("model_name")?.to_string(),
+            api_key,
+            priority: attributes
+                .get("priority")
+                .and_then(|v| v.as_i64())
+                .unwrap_or(0),
+            tenant_id,
+            healthy: text("status") != Some("Unhealthy"),
+            input_cost_per_1k: price("input_cost_per_1k"),
+            output_cost_per_1k: price("output_cost_per_1k"),
+        })
+    }
+
+    /// Whether the provider runs on this machine, so prompts never leave it
+    pub fn is_local(&self) -> bool {
+        let host = reqwest::Url::parse(&self.api_base)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string));
+        match host.as_deref() {
+            Some("localhost") => true,
+            Some(host) => host
+                .trim_matches(['[', ']'])
+                .parse::<std::net::IpAddr>()
+                .is_ok_and(|ip| ip.is_loopback()),
+            None => false,
+        }
+    }
+
+    /// Key for per-provider state such as circuit breakers
+    pub fn key(&self) -> String {
+        match self.id {
+            Some(id) => id.to_string(),
+            None => format!("env:{}", self.api_base),
+        }
+    }
+}
+
+/// Try order: the tenant's own providers, then global ones, each by priority.
+/// Providers whose health check failed go last, so they are only used when
+/// nothing healthy is left.
+pub fn fallback_order(mut providers: Vec<ProviderConfig>) -> Vec<ProviderConfig> {
+    providers.sort_by_key(|p| (!p.healthy, p.tenant_id.is_none(), p.priority));
+    providers
+}
+
+/// Generated text, with the token counts the provider reported, if any
+#[derive(Debug, Clone)]
+pub struct Generation {
+    pub text: String,
+    pub usage: Option<TokenUsage>,
+}
+
+/// One AI backend. Errors are provider messages; `AiService` maps them to
+/// `AiError` through its circuit breakers.
+#[async_trait]
+pub trait AiProvider: Send + Sync {
+    async fn health_check(&self) -> Result<(), String>;
+
+    async fn list_models(&self) -> Result<Vec<String>, String>;
+
+    async fn generate(&self, model: &str, req: &GenerateRequest) -> Result<Generation, String>;
+
+    /// Resolves once the first piece of text has arrived, so failures to
+    /// start are reported here rather than inside the stream
+    async fn generate_stream(
+        &self,
+        model: &str,
+        req: &GenerateRequest,
+    ) -> Result<TextStream, String>;
+
+    fn supports_embeddings(&self) -> bool {
+        true
+    }
+
+    async fn embed(&self, model: &str, inputs: Vec<String>) -> Result<Vec<Vec<f32>>, String>;
+}
+
+pub fn build_provider(
+    config: &ProviderConfig,
+    http: &OutboundClient,
+    retry: &RetryPolicy,
+) -> Arc<dyn AiProvider> {
+    match config.kind {
+        ProviderKind::Ollama => Arc::new(OllamaProvider {
+            root: config
+                .api_base
+                .trim_end_matches('/')
+                .trim_end_matches("/v1")
+                .to_string(),
+            openai: OpenAiCompatibleProvider::new(config, http, retry),
+            http: http.clone(),
+        }),
+        ProviderKind::OpenAi => Arc::new(OpenAiCompatibleProvider::new(config, http, retry)),
+        ProviderKind::Anthropic => Arc::new(AnthropicProvider {
+            api_base: normalize_api_base(&config.api_base),
+            api_key: config.api_key.clone().unwrap_or_default(),
+            http: http.clone(),
+            completion_http: OutboundClient::new(retry.clone()),
+        }),
+    }
+}
+
+/// OpenAI-style bases end in `/v1`
+pub fn normalize_api_base(api_base: &str) -> String {
+    let base = api_base.trim_end_matches('/');
+    if base.ends_with("/v1") {
+        base.to_string()
+    } else {
+        format!("{}/v1", base)
+    }
+}
+
+/// Hold back the stream until its first item, so a provider that fails to
+/// start generating is reported as a failed call
+async fn wait_for_first(mut stream: TextStream) -> Result<TextStream, String> {
+    match stream.next().await {
+        Some(Ok(first)) => Ok(Box::pin(
+            futures::stream::once(async { Ok(first) }).chain(stream),
+        )),
+        Some(Err(e)) => Err(e.to_string()),
+        None => Ok(Box::pin(futures::stream::empty())),
+    }
+}
+
+// ============================================================================
+// OPENAI-COMPATIBLE
+// ============================================================================
+
+pub struct OpenAiCompatibleProvider {
+    client: Client<OpenAIConfig>,
+    api_base: String,
+    api_key: Option<String>,
+    http: OutboundClient,
+    retry: RetryPolicy,
+}
+
+impl OpenAiCompatibleProvider {
+    fn new(config: &ProviderConfig, http: &OutboundClient, retry: &RetryPolicy) -> Self {
+        let api_base = normalize_api_base(&config.api_base);
+        let client = Client::with_config(
+            OpenAIConfig::new()
+                .with_api_base(&api_base)
+                .with_api_key(config.api_key.as_deref().unwrap_or("dummy")),
+        );
+        Self {
+            client,
+            api_base,
+            api_key: config.api_key.clone(),
+            http: http.clone(),
+            retry: retry.clone(),
+        }
+    }
+
+    fn chat_request(
+        model: &str,
+        req: &GenerateRequest,
+    ) -> Result<CreateChatCompletionRequest, String> {
+        CreateChatCompletionRequestArgs::default()
+            .model(model)
+            .messages([ChatCompletionRequestMessage::User(
+                ChatCompletionRequestUserMessage {
+                    content: ChatCompletionRequestUserMessageContent::Text(req.prompt.clone()),
+                    name: None,
+                },
+            )])
+            .temperature(req.temperature.unwrap_or(DEFAULT_TEMPERATURE))
+            .max_tokens(req.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS))
+            .build()
+            .map_err(|e| format!("Failed to build request: {}", e))
+    }
+}
+
+#[async_trait]
+impl AiProvider for OpenAiCompatibleProvider {
+    async fn health_check(&self) -> Result<(), String> {
+        self.list_models().await.map(|_| ())
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>, String> {
+        let url = format!("{}/models", self.api_base);
+        let res = self
+            .http
+            .send(|client| {
+                let request = client.get(&url);
+                match &self.api_key {
+                    Some(key) => request.bearer_auth(key),
+                    None => request,
+                }
+            })
+            .await
+            .map_err(|e| format!("AI Provider Unreachable: {}", e))?;
+        if !res.status().is_success() {
+            return Err(format!("AI Provider Degraded: HTTP {}", res.status()));
+        }
+        let body: serde_json::Value = res
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse models JSON: {}", e))?;
+        Ok(body
+            .get("data")
+            .and_then(|d| d.as_array())
+            .map(|models| {
+                models
+                    .iter()
+                    .filter_map(|m| m.get("id").and_then(|id| id.as_str()))
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn generate(&self, model: &str, req: &GenerateRequest) -> Result<Generation, String> {
+        let request = Self::chat_request(model, req)?;
+        // Only transport errors are retried; API errors would fail again
+        let response = self
+            .retry
+            .run(
+                || {
+                    let request = request.clone();
+                    async move { self.client.chat().create(request).await }
+                },
+                |e| matches!(e, OpenAIError::Reqwest(_)),
+            )
+            .await
+            .map_err(|e| format!("AI Service Error: {}", e))?;
+
+        Ok(Generation {
+            text: response
+                .choices
+                .first()
+                .and_then(|c| c.message.content.clone())
+                .unwrap_or_default(),
+            usage: response.usage.map(|u| TokenUsage {
+                prompt_tokens: u.prompt_tokens,
+                completion_tokens: u.completion_tokens,
+                estimated: false,
+            }),
+        })
+    }
+
+    async fn generate_stream(
+        &self,
+        model: &str,
+        req: &GenerateRequest,
+    ) -> Result<TextStream, String> {
+        let request = Self::chat_request(model, req)?;
+        let stream = self
+            .client
+            .chat()
+            .create_stream(request)
+            .await
+            .map_err(|e| format!("AI Service Error: {}", e))?;
+
+        let text = stream.filter_map(|chunk| async move {
+            match chunk {
+                Ok(chunk) => chunk
+                    .choices
+                    .into_iter()
+                    .next()
+                    .and_then(|c| c.delta.content)
+                    .filter(|text| !text.is_empty())
+                    .map(Ok),
+                Err(e) => Some(Err(AiError::Failed(format!("AI Service Error: {}", e)))),
+            }
+        });
+        wait_for_first(Box::pin(text)).await
+    }
+
+    async fn embed(&self, model: &str, inputs: Vec<String>) -> Result<Vec<Vec<f32>>, String> {
+        let request = CreateEmbeddingRequestArgs::default()
+            .model(model)
+            .input(inputs)
+            .build()
+            .map_err(|e| format!("Failed to build request: {}", e))?;
+
+        let response = self
+            .retry
+            .run(
+                || {
+                    let request = request.clone();
+                    async move { self.client.embeddings().create(request).await }
+                },
+                |e| matches!(e, OpenAIError::Reqwest(_)),
+            )
+            .await
+            .map_err(|e| format!("AI Embedding Error: {}", e))?;
+
+        let mut data = response.data;
+        data.sort_by_key(|e| e.index);
+        Ok(data.into_iter().map(|e| e.embedding).collect())
+    }
+}
+
+// ============================================================================
+// OLLAMA
+// ============================================================================
+
+/// Ollama serves the OpenAI API under `/v1`; health and model listing use
+/// its native `/api/tags`, which also works on versions without `/v1/models`
+pub struct OllamaProvider {
+    root: String,
+    openai: OpenAiCompatibleProvider,
+    http: OutboundClient,
+}
+
+#[async_trait]
+impl AiProvider for OllamaProvider {
+    async fn health_check(&self) -> Result<(), String> {
+        let res = self
+            .http
+            .get(&format!("{}/api/tags", self.root))
+            .await
+            .map_err(|e| format!("AI Provider Unreachable: {}", e))?;
+        if res.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("AI Provider Degraded: HTTP {}", res.status()))
+        }
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>, String> {
+        let res = self
+            .http
+            .get(&format!("{}/api/tags", self.root))
+            .await
+            .map_err(|e| format!("Failed to fetch models: {}", e))?;
+        if !res.status().is_success() {
+            return Err(format!("Failed to fetch models: HTTP {}", res.status()));
+        }
+        let body: serde_json::Value = res
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse models JSON: {}", e))?;
+
+        let mut models = Vec::new();
+        if let Some(models_array) = body.get("models").and_then(|m| m.as_array()) {
+            for m in models_array {
+                if let Some(name) = m.get("name").and_then(|n| n.as_str()) {
+                    models.push(name.to_string());
+                }
+            }
+        }
+        Ok(models)
+    }
+
+    async fn generate(&self, model: &str, req: &GenerateRequest) -> Result<Generation, String> {
+        self.openai.generate(model, req).await
+    }
+
+    async fn generate_stream(
+        &self,
+        model: &str,
+        req: &GenerateRequest,
+    ) -> Result<TextStream, String> {
+        self.openai.generate_stream(model, req).await
+    }
+
+    async fn embed(&self, model: &str, inputs: Vec<String>) -> Result<Vec<Vec<f32>>, String> {
+        self.openai.embed(model, inputs).await
+    }
+}
+
+// ============================================================================
+// ANTHROPIC
+// ============================================================================
+
+/// Anthropic's Messages API
+pub struct AnthropicProvider {
+    api_base: String,
+    api_key: String,
+    /// For quick calls such as listing models
+    http: OutboundClient,
+    /// With the longer timeouts used for inference
+    completion_http: OutboundClient,
+}
+
+impl AnthropicProvider {
+    fn message_body(model: &str, req: &GenerateRequest, stream: bool) -> serde_json::Value {
+        serde_json::json!({
+            "model": model,
+            "max_tokens": req.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            // Anthropic accepts 0-1 where OpenAI-style APIs accept 0-2
+            "temperature": req.temperature.unwrap_or(DEFAULT_TEMPERATURE).clamp(0.0, 1.0),
+            "messages": [{ "role": "user", "content": req.prompt }],
+            "stream": stream,
+        })
+    }
+
+    async fn post_message(&self, body: serde_json::Value) -> Result<reqwest::Response, String> {
+        let url = format!("{}/messages", self.api_base);
+        let res = self
+            .completion_http
+            .send(|client| {
+                client
+                    .post(&url)
+                    .header("x-api-key", &self.api_key)
+                    .header("anthropic-version", ANTHROPIC_VERSION)
+                    .json(&body)
+            })
+            .await
+            .map_err(|e| format!("AI Service Error: {}", e))?;
+        if res.status().is_success() {
+            return Ok(res);
+        }
+        let status = res.status();
+        let detail = res
+            .json::<serde_json::Value>()
+            .await
+            .ok()
+            .and_then(|b| b["error"]["message"].as_str().map(str::to_string))
+            .unwrap_or_default();
+        Err(format!("AI Service Error: HTTP {} {}", status, detail))
+    }
+}
+
+/// Text from a Messages API response
+fn anthropic_text(body: &serde_json::Value) -> String {
+    body.get("content")
+        .and_then(|c| c.as_array())
+        .map(|blocks| {
+            blocks
+                .iter()
+                .filter(|b| b["type"] == "text")
+                .filter_map(|b| b["text"].as_str())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Token counts from a Messages API response
+fn anthropic_usage(body: &serde_json::Value) -> Option<TokenUsage> {
+    let usage = body.get("usage")?;
+    Some(TokenUsage {
+        prompt_tokens: usage["input_tokens"].as_u64()? as u32,
+        completion_tokens: usage["output_tokens"].as_u64().unwrap_or(0) as u32,
+        estimated: false,
+    })
+}
+
+/// What a Messages API stream event contributes: text, the end, or an error
+enum AnthropicEvent {
+    Text(String),
+    Stop,
+    Error(String),
+    Other,
+}
+
+fn parse_anthropic_event(data: &str) -> AnthropicEvent {
+    let Ok(event) = serde_json::from_str::<serde_json::Value>(data) else {
+        return AnthropicEvent::Other;
+    };
+    match event["type"].as_str() {
+        Some("content_block_delta") if event["delta"]["type"] == "text_delta" => {
+            AnthropicEvent::Text(event["delta"]["text"].as_str().unwrap_or("").to_string())
+        }
+        Some("message_stop") => AnthropicEvent::Stop,
+        Some("error") => AnthropicEvent::Error(
+            event["error"]["message"]
+                .as_str()
+                .unwrap_or("stream error")
+                .to_string(),
+        ),
+        _ => AnthropicEvent::Other,
+    }
+}
+
+/// The `data:` payloads of a server-sent event stream
+fn sse_data<S>(bytes: S) -> impl Stream<Item = Result<String, String>>
+where
+    S: Stream<Item = reqwest::Result<axum::body::Bytes>> + Unpin,
+{
+    futures::stream::unfold(
+        (bytes, String::new(), false),
+        |(mut bytes, mut buffer, done)| async move {
+            if done {
+                return None;
+            }
+            loop {
+                if let Some(end) = buffer.find("\n\n") {
+                    let event: String = buffer.drain(..end + 2).collect();
+                    let data = event
+                        .lines()
+                        .filter_map(|line| line.strip_prefix("data:"))
+                        .map(str::trim_start)
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    if data.is_empty() {
+                        continue;
+                    }
+                    return Some((Ok(data), (bytes, buffer, false)));
+                }
+                match bytes.next().await {
+                    Some(Ok(chunk)) => {
+                        buffer.push_str(&String::from_utf8_lossy(&chunk).replace("\r\n", "\n"))
+                    }
+                    Some(Err(e)) => {
+                        return Some((
+                            Err(format!("AI Service Error: {}", e)),
+                            (bytes, buffer, true),
+                        ))
+                    }
+                    None => return None,
+                }
+            }
+        },
+    )
+}
+
+#[async_trait]
+impl AiProvider for AnthropicProvider {
+    async fn health_check(&self) -> Result<(), String> {
+        self.list_models().await.map(|_| ())
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>, String> {
+        let url = format!("{}/models", self.api_base);
+        let res = self
+            .http
+            .send(|client| {
+                client
+                    .get(&url)
+                    .header("x-api-key", &self.api_key)
+                    .header("anthropic-version", ANTHROPIC_VERSION)
+            })
+            .await
+            .map_err(|e| format!("AI Provider Unreachable: {}", e))?;
+        if !res.status().is_success() {
+            return Err(format!("AI Provider Degraded: HTTP {}", res.status()));
+        }
+        let body: serde_json::Value = res
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse models JSON: {}", e))?;
+        Ok(body["data"]
+            .as_array()
+            .map(|models| {
+                models
+                    .iter()
+                    .filter_map(|m| m["id"].as_str())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn generate(&self, model: &str, req: &GenerateRequest) -> Result<Generation, String> {
+        let res = self
+            .post_message(Self::message_body(model, req, false))
+            .await?;
+        let body: serde_json::Value = res
+            .json()
+            .await
+            .map_err(|e| format!("AI Service Error: {}", e))?;
+        Ok(Generation {
+            text: anthropic_text(&body),
+            usage: anthropic_usage(&body),
+        })
+    }
+
+    async fn generate_stream(
+        &self,
+        model: &str,
+        req: &GenerateRequest,
+    ) -> Result<TextStream, String> {
+        let res = self
+            .post_message(Self::message_body(model, req, true))
+            .await?;
+
+        let text = sse_data(Box::pin(res.bytes_stream()))
+            .map(|data| match data {
+                Ok(data) => parse_anthropic_event(&data),
+                Err(e) => AnthropicEvent::Error(e),
+            })
+            .take_while(|event| futures::future::ready(!matches!(event, AnthropicEvent::Stop)))
+            .filter_map(|event| async move {
+                match event {
+                    AnthropicEvent::Text(text) if !text.is_empty() => Some(Ok(text)),
+                    AnthropicEvent::Error(e) => Some(Err(AiError::Failed(e))),
+                    _ => None,
+                }
+            });
+        wait_for_first(Box::pin(text)).await
+    }
+
+    fn supports_embeddings(&self) -> bool {
+        false
+    }
+
+    async fn embed(&self, _model: &str, _inputs: Vec<String>) -> Result<Vec<Vec<f32>>, String> {
+        Err("Anthropic does not provide an embeddings API".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn provider(priority: i64, tenant_id: Option<Uuid>, healthy: bool) -> ProviderConfig {
+        ProviderConfig {
+            id: Some(Uuid::new_v4()),
+            name: format!("p{}", priority),
+            kind: ProviderKind::Ollama,
+            api_base: "http://localhost:11434".to_string(),
+            model: "llama3".to_string(),
+            api_key: None,
+            priority,
+            tenant_id,
+            healthy,
+            input_cost_per_1k: 0.0,
+            output_cost_per_1k: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_provider_kind_parse() {
+        assert_eq!(ProviderKind::parse("Ollama"), Some(ProviderKind::Ollama));
+        assert_eq!(ProviderKind::parse("OpenAI"), Some(ProviderKind::OpenAi));
+        assert_eq!(
+            ProviderKind::parse("openai-compatible"),
+            Some(ProviderKind::OpenAi)
+        );
+        assert_eq!(
+            ProviderKind::parse("Anthropic"),
+            Some(ProviderKind::Anthropic)
+        );
+        assert_eq!(ProviderKind::parse("gemini"), None);
+    }
+
+    #[test]
+    fn test_from_entity() {
+        let config = ProviderConfig::from_entity(
+            Uuid::new_v4(),
+            "Claude".to_string(),
+            None,
+            &json!({ "provider_type": "Anthropic", "model_name": "claude-sonnet", "priority": 2, "status": "Unhealthy" }),
+            &AiProvidersConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(config.kind, ProviderKind::Anthropic);
+        assert_eq!(config.api_base, ANTHROPIC_DEFAULT_BASE);
+        assert_eq!(config.priority, 2);
+        assert!(!config.healthy);
+
+        // Legacy providers have no provider_type
+        let legacy = ProviderConfig::from_entity(
+            Uuid::new_v4(),
+            "Local".to_string(),
+            None,
+            &json!({ "api_base": "http://localhost:11434", "model_name": "llama3" }),
+            &AiProvidersConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(legacy.kind, ProviderKind::Ollama);
+        assert!(legacy.healthy);
+        assert!(legacy.is_local());
+        assert!(!config.is_local());
+
+        assert!(ProviderConfig::from_entity(
+            Uuid::new_v4(),
+            "Broken".to_string(),
+            None,
+            &json!({ "provider_type": "OpenAI", "model_name": "gpt" }),
+            &AiProvidersConfig::default(),
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_api_key_needs_prefix_and_trusted_host() {
+        std::env::set_var("AI_PROVIDER_KEY_TEST_RESOLVE", "secret");
+        std::env::set_var("TEST_RESOLVE_DATABASE_PASSWORD", "hunter2");
+        let config = AiProvidersConfig::default();
+        let key = |var: &str, base: &str| resolve_api_key(var, base, &config);
+
+        assert_eq!(
+            key("AI_PROVIDER_KEY_TEST_RESOLVE", "https://api.openai.com/v1").as_deref(),
+            Some("secret")
+        );
+        assert_eq!(
+            key(
+                "AI_PROVIDER_KEY_TEST_RESOLVE",
+                "https://eu.api.anthropic.com"
+            )
+            .as_deref(),
+            Some("secret")
+        );
+        assert_eq!(
+            key("TEST_RESOLVE_DATABASE_PASSWORD", "https://api.openai.com"),
+            None
+        );
+        assert_eq!(
+            key("AI_PROVIDER_KEY_TEST_RESOLVE", "https://attacker.example"),
+            None
+        );
+        assert_eq!(
+            key("AI_PROVIDER_KEY_TEST_RESOLVE", "http://api.openai.com"),
+            None
+        );
+        assert_eq!(
+            key(
+                "AI_PROVIDER_KEY_TEST_RESOLVE",
+                "https://api.openai.com.evil.io"
+            ),
+            None
+        );
+        assert_eq!(
+            resolve_api_key(
+                "AI_PROVIDER_KEY_TEST_RESOLVE",
+                "https://api.openai.com",
+                &AiProvidersConfig {
+                    key_hosts: Vec::new()
+                }
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_fallback_order() {
+        let tenant = Some(Uuid::new_v4());
+        let order = fallback_order(vec![
+            provider(1, None, true),
+            provider(5, tenant, true),
+            provider(0, None, false),
+            provider(0, None, true),
+        ]);
+        let names: Vec<_> = order.iter().map(|p| (p.name.as_str(), p.healthy)).collect();
+        assert_eq!(
+            names,
+            vec![("p5", true), ("p0", true), ("p1", true), ("p0", false)]
+        );
+    }
+
+    #[test]
+    fn test_parse_anthropic_events() {
+        assert!(matches!(
+            parse_anthropic_event(r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hi"}}"#),
+            AnthropicEvent::Text(t) if t == "Hi"
+        ));
+        assert!(matches!(
+            parse_anthropic_event(r#"{"type":"message_stop"}"#),
+            AnthropicEvent::Stop
+        ));
+        assert!(matches!(
+            parse_anthropic_event(r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#),
+            AnthropicEvent::Error(e) if e == "Overloaded"
+        ));
+        assert!(matches!(
+            parse_anthropic_event(r#"{"type":"ping"}"#),
+            AnthropicEvent::Other
+        ));
+    }
+
+    #[test]
+    fn test_anthropic_text() {
+        let body = json!({ "content": [{ "type": "text", "text": "Hello" }, { "type": "tool_use" }, { "type": "text", "text": " there" }] });
+        assert_eq!(anthropic_text(&body), "Hello there");
+    }
+}
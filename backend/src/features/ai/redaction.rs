@@ -0,0 +1,421 @@
+use super::providers::ProviderConfig;
+use super::service::{AiError, TextStream};
+use crate::config::AiRedactionConfig;
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::{FromRow, Pool, Postgres};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Arc, OnceLock};
+use uuid::Uuid;
+
+/// Shorter names and values match too much ordinary text to be worth replacing
+const MIN_TERM_CHARS: i32 = 3;
+
+/// Longest placeholder, so a streamed one split across pieces can be held back
+const MAX_PLACEHOLDER_CHARS: usize = 24;
+
+const DEFAULT_LIMIT: i64 = 100;
+const MAX_LIMIT: i64 = 1000;
+
+fn email_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}").unwrap()
+    })
+}
+
+/// What was replaced in one call, without the values themselves
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedactionSummary {
+    /// Occurrences replaced, by kind ("email", "name", "sensitive")
+    pub counts: BTreeMap<String, usize>,
+    /// Sensitive properties whose values were replaced
+    pub fields: BTreeSet<String>,
+}
+
+impl RedactionSummary {
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+}
+
+/// Replaces personal data with placeholders such as `[EMAIL_1]`, the same
+/// value always getting the same placeholder, and can put the values back
+#[derive(Debug, Default)]
+pub struct Pseudonymizer {
+    /// Sensitive property names, whose values are replaced wherever they
+    /// appear as a JSON field
+    sensitive_fields: Vec<String>,
+    /// Stored sensitive values found in the text, with their property
+    sensitive_values: Vec<(String, String)>,
+    /// Personal names found in the text
+    names: Vec<String>,
+    /// Lowercased original -> placeholder, per kind
+    assigned: HashMap<(&'static str, String), String>,
+    /// Placeholder -> original, for restoring
+    originals: Vec<(String, String)>,
+    summary: RedactionSummary,
+}
+
+impl Pseudonymizer {
+    fn new(
+        sensitive_fields: Vec<String>,
+        sensitive_values: Vec<(String, String)>,
+        names: Vec<String>,
+    ) -> Self {
+        let mut sensitive_values = sensitive_values;
+        let mut names = names;
+        // Longest first, so a value containing another is replaced whole
+        sensitive_values.sort_by_key(|(_, value)| std::cmp::Reverse(value.len()));
+        names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+        Self {
+            sensitive_fields,
+            sensitive_values,
+            names,
+            ..Default::default()
+        }
+    }
+
+    fn placeholder(&mut self, kind: &'static str, original: &str) -> String {
+        *self.summary.counts.entry(kind.to_string()).or_default() += 1;
+        let key = (kind, original.to_lowercase());
+        if let Some(existing) = self.assigned.get(&key) {
+            return existing.clone();
+        }
+        let number = self.assigned.keys().filter(|(k, _)| *k == kind).count() + 1;
+        let placeholder = format!("[{}_{}]", kind.to_uppercase(), number);
+        self.assigned.insert(key, placeholder.clone());
+        self.originals
+            .push((placeholder.clone(), original.to_string()));
+        placeholder
+    }
+
+    /// `text` with sensitive field values, stored sensitive values, personal
+    /// names and email addresses replaced, in that order
+    pub fn redact(&mut self, text: &str) -> String {
+        let mut text = text.to_string();
+
+        for field in self.sensitive_fields.clone() {
+            let pattern = format!(r#"("{}"\s*:\s*")((?:[^"\\]|\\.)*)""#, regex::escape(&field));
+            let Ok(re) = Regex::new(&pattern) else {
+                continue;
+            };
+            let mut replaced = false;
+            text = re
+                .replace_all(&text, |caps: &regex::Captures| {
+                    if caps[2].is_empty() {
+                        return caps[0].to_string();
+                    }
+                    replaced = true;
+                    let placeholder = self.placeholder("sensitive", &caps[2]);
+                    format!("{}{}\"", &caps[1], placeholder)
+                })
+                .into_owned();
+            if replaced {
+                self.summary.fields.insert(field);
+            }
+        }
+
+        for (field, value) in self.sensitive_values.clone() {
+            let occurrences = text.matches(value.as_str()).count();
+            if occurrences == 0 {
+                continue;
+            }
+            let placeholder = self.placeholder("sensitive", &value);
+            // `placeholder` counted one occurrence
+            *self
+                .summary
+                .counts
+                .entry("sensitive".to_string())
+                .or_default() += occurrences - 1;
+            text = text.replace(value.as_str(), &placeholder);
+            self.summary.fields.insert(field);
+        }
+
+        if !self.names.is_empty() {
+            let alternatives = self
+                .names
+                .iter()
+                .map(|name| regex::escape(name))
+                .collect::<Vec<_>>()
+                .join("|");
+            if let Ok(re) = Regex::new(&format!(r"(?i)\b(?:{})\b", alternatives)) {
+                text = re
+                    .replace_all(&text, |caps: &regex::Captures| {
+                        self.placeholder("name", &caps[0])
+                    })
+                    .into_owned();
+            }
+        }
+
+        email_pattern()
+            .replace_all(&text, |caps: &regex::Captures| {
+                self.placeholder("email", &caps[0])
+            })
+            .into_owned()
+    }
+
+    /// `text` with every placeholder replaced by the value it stands for
+    pub fn restore(&self, text: &str) -> String {
+        if self.originals.is_empty() || !text.contains('[') {
+            return text.to_string();
+        }
+        self.originals
+            .iter()
+            .fold(text.to_string(), |text, (placeholder, original)| {
+                text.replace(placeholder.as_str(), original)
+            })
+    }
+
+    /// Restore a streamed response, holding back a trailing `[` until it is
+    /// clear whether a placeholder was split across pieces
+    pub fn restore_stream(self: Arc<Self>, stream: TextStream) -> TextStream {
+        Box::pin(futures::stream::unfold(
+            (stream, String::new(), false),
+            move |(mut stream, mut pending, done)| {
+                let this = self.clone();
+                async move {
+                    if done {
+                        return None;
+                    }
+                    loop {
+                        match stream.next().await {
+                            Some(Ok(piece)) => {
+                                pending.push_str(&piece);
+                                let ready = held_back_from(&pending);
+                                if ready > 0 {
+                                    let rest = pending.split_off(ready);
+                                    return Some((
+                                        Ok(this.restore(&pending)),
+                                        (stream, rest, false),
+                                    ));
+                                }
+                            }
+                            Some(Err(e)) => return Some((Err(e), (stream, pending, true))),
+                            None if pending.is_empty() => return None,
+                            None => {
+                                let text = this.restore(&pending);
+                                return Some((Ok(text), (stream, String::new(), true)));
+                            }
+                        }
+                    }
+                }
+            },
+        ))
+    }
+
+    pub fn summary(&self) -> &RedactionSummary {
+        &self.summary
+    }
+}
+
+/// Byte offset up to which `pending` can be restored and sent
+fn held_back_from(pending: &str) -> usize {
+    match pending.rfind('[') {
+        Some(start)
+            if !pending[start..].contains(']')
+                && pending[start..].chars().count() <= MAX_PLACEHOLDER_CHARS =>
+        {
+            start
+        }
+        _ => pending.len(),
+    }
+}
+
+/// One call's redactions, as recorded for audit
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct RedactionAudit {
+    pub id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub tenant_id: Option<Uuid>,
+    pub provider_id: Option<Uuid>,
+    pub provider_name: String,
+    pub operation: String,
+    pub counts: Json<BTreeMap<String, usize>>,
+    pub fields: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListRedactionsQuery {
+    pub user_id: Option<Uuid>,
+    pub tenant_id: Option<Uuid>,
+    pub limit: Option<i64>,
+}
+
+/// Strips personal data from what is sent to external AI providers and
+/// records what was removed
+#[derive(Clone)]
+pub struct RedactionService {
+    pool: Pool<Postgres>,
+    config: AiRedactionConfig,
+}
+
+impl RedactionService {
+    pub fn new(pool: Pool<Postgres>, config: AiRedactionConfig) -> Self {
+        Self { pool, config }
+    }
+
+    /// Whether prompts for `provider` must be redacted. Providers on this
+    /// machine are trusted unless configured otherwise.
+    pub fn applies_to(&self, provider: &ProviderConfig) -> bool {
+        self.config.enabled && (self.config.redact_local_providers || !provider.is_local())
+    }
+
+    pub fn restores_responses(&self) -> bool {
+        self.config.restore_responses
+    }
+
+    /// A pseudonymizer primed with the stored names and sensitive values that
+    /// occur in `texts`. None when redaction is disabled.
+    pub async fn prepare(&self, texts: &[&str]) -> Result<Option<Pseudonymizer>, AiError> {
+        if !self.config.enabled {
+            return Ok(None);
+        }
+        let haystack = texts.join("\n");
+
+        let sensitive_fields: Vec<String> = sqlx::query_scalar(
+            "SELECT DISTINCT name FROM properties WHERE is_sensitive = TRUE ORDER BY name",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))?;
+
+        let sensitive_values: Vec<(String, String)> = sqlx::query_as(
+            r#"
+            SELECT DISTINCT p.name, e.attributes ->> p.name
+            FROM properties p
+            JOIN entities e ON e.class_id = p.class_id
+            WHERE p.is_sensitive = TRUE
+              AND e.deleted_at IS NULL
+              AND LENGTH(e.attributes ->> p.name) >= $2
+              AND STRPOS($1, e.attributes ->> p.name) > 0
+            "#,
+        )
+        .bind(&haystack)
+        .bind(MIN_TERM_CHARS)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))?;
+
+        let names: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT DISTINCT e.display_name
+            FROM entities e
+            JOIN classes c ON c.id = e.class_id
+            WHERE c.name = ANY($2)
+              AND e.deleted_at IS NULL
+              AND LENGTH(e.display_name) >= $3
+              AND STRPOS(LOWER($1), LOWER(e.display_name)) > 0
+            "#,
+        )
+        .bind(&haystack)
+        .bind(&self.config.name_classes)
+        .bind(MIN_TERM_CHARS)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))?;
+
+        Ok(Some(Pseudonymizer::new(
+            sensitive_fields,
+            sensitive_values,
+            names,
+        )))
+    }
+
+    /// Record what was redacted from a call sent to `provider`. Values are
+    /// never stored, only their kinds and fields.
+    pub async fn audit(
+        &self,
+        provider: &ProviderConfig,
+        user_id: Option<Uuid>,
+        tenant_id: Option<Uuid>,
+        operation: &str,
+        summary: &RedactionSummary,
+    ) {
+        if summary.is_empty() {
+            return;
+        }
+        let result = sqlx::query(
+            r#"
+            INSERT INTO ai_redactions
+                (user_id, tenant_id, provider_id, provider_name, operation, counts, fields)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(user_id)
+        .bind(tenant_id)
+        .bind(provider.id)
+        .bind(&provider.name)
+        .bind(operation)
+        .bind(Json(&summary.counts))
+        .bind(summary.fields.iter().cloned().collect::<Vec<_>>())
+        .execute(&self.pool)
+        .await;
+        if let Err(e) = result {
+            tracing::error!("Failed to record AI redaction audit: {}", e);
+        }
+    }
+
+    pub async fn list(&self, query: ListRedactionsQuery) -> Result<Vec<RedactionAudit>, AiError> {
+        sqlx::query_as::<_, RedactionAudit>(
+            r#"
+            SELECT * FROM ai_redactions
+            WHERE ($1::UUID IS NULL OR user_id = $1)
+              AND ($2::UUID IS NULL OR tenant_id = $2)
+            ORDER BY created_at DESC
+            LIMIT $3
+            "#,
+        )
+        .bind(query.user_id)
+        .bind(query.tenant_id)
+        .bind(query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_replaces_consistently_and_restores() {
+        let mut p = Pseudonymizer::new(
+            vec!["ssn".to_string()],
+            vec![("phone".to_string(), "555-0100".to_string())],
+            vec!["Ada Lovelace".to_string()],
+        );
+        let redacted = p.redact(
+            r#"ada lovelace (ada@example.com) called 555-0100. {"ssn": "123-45-6789"} Mail ada@example.com"#,
+        );
+
+        assert_eq!(
+            redacted,
+            r#"[NAME_1] ([EMAIL_1]) called [SENSITIVE_2]. {"ssn": "[SENSITIVE_1]"} Mail [EMAIL_1]"#
+        );
+        assert_eq!(p.summary().counts["email"], 2);
+        assert_eq!(p.summary().counts["sensitive"], 2);
+        assert_eq!(
+            p.summary().fields.iter().collect::<Vec<_>>(),
+            vec!["phone", "ssn"]
+        );
+        assert_eq!(
+            p.restore("Contact [NAME_1] at [EMAIL_1]"),
+            "Contact ada lovelace at ada@example.com"
+        );
+    }
+
+    #[test]
+    fn test_held_back_from_waits_for_split_placeholders() {
+        assert_eq!(held_back_from("Hello [EMA"), 6);
+        assert_eq!(held_back_from("Hello [EMAIL_1]"), 15);
+        assert_eq!(held_back_from("no brackets"), 11);
+        let long = format!("[{}", "x".repeat(MAX_PLACEHOLDER_CHARS));
+        assert_eq!(held_back_from(&long), long.len());
+    }
+}
@@ -1,9 +1,55 @@
-use super::service::{AiService, GenerateRequest, GenerateResponse};
-use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+use super::alert_explanations::{AlertExplanationService, SecurityAlert};
+use super::audit_qa::{AuditAnswer, AuditQaService, AuditQuestionRequest};
+use super::authz_qa::{AuthzAnswer, AuthzQaService, AuthzQuestionRequest};
+use super::conversations::{
+    AiConversation, ConversationService, ConversationWithMessages, CreateConversationInput,
+    ListConversationsQuery, SendMessageInput, SendMessageResponse,
+};
+use super::extraction::{
+    AcceptExtractionRequest, AcceptExtractionResponse, DocumentExtraction, ExtractRequest,
+    ExtractionService,
+};
+use super::modeling::{ModelingService, SuggestModelRequest};
+use super::nl_query::{NlQueryRequest, NlQueryResponse, NlQueryService};
+use super::policy_drafting::{
+    ApprovePolicyDraftResponse, DraftPolicyRequest, ListPolicyDraftsQuery, PolicyDraft,
+    PolicyDraftingService, ReviewPolicyDraftRequest,
+};
+use super::prompts::{
+    CreatePromptTemplateInput, PromptService, PromptTemplate, PromptTemplateDetail,
+    PromptTemplateVersion, RenderPromptInput, UpdatePromptTemplateInput,
+};
+use super::semantic_search::{SemanticSearchRequest, SemanticSearchResult, SemanticSearchService};
+use super::index_worker::{IndexWorker, IndexWorkerStatus};
+use super::redaction::{ListRedactionsQuery, RedactionAudit, RedactionService};
+use super::service::{AiError, AiService, GenerateRequest, GenerateResponse};
+use super::usage::{
+    AiBudget, BudgetStatus, SetBudgetInput, UsageReport, UsageReportQuery, UsageService,
+};
+use crate::features::auth::jwt::Claims;
+use crate::features::ontology::models::OntologyChangeset;
+use crate::middleware::abac::{enforce_route_permissions, RoutePermission};
+use crate::utils::api_error::ApiError;
+use crate::utils::validation::{not_blank, ValidatedJson};
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{get, post, put},
+    Extension, Json, Router,
+};
+use futures::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use uuid::Uuid;
+use validator::Validate;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct GenerateClassDescriptionRequest {
+    #[validate(custom = "not_blank")]
     pub name: String,
     pub properties: Option<Vec<String>>,
 }
@@ -13,14 +59,48 @@ pub struct GenerateClassDescriptionResponse {
     pub description: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct SuggestRequest {
+    #[validate(custom = "not_blank")]
     pub context: String,
 }
 
+/// Permissions enforced by `enforce_route_permissions` for the administrative
+/// routes below, shared by the routers merged under `/ai`
+pub const ROUTE_PERMISSIONS: &[RoutePermission] = &[
+    RoutePermission::new("POST", "/suggest-model", "manage_system"),
+    RoutePermission::new("GET", "/usage", "manage_system"),
+    RoutePermission::new("GET", "/budgets", "manage_system"),
+    RoutePermission::new("PUT", "/budgets/:tenant_id", "manage_system"),
+    RoutePermission::new("DELETE", "/budgets/:tenant_id", "manage_system"),
+    RoutePermission::new("POST", "/index/backfill", "manage_system"),
+    RoutePermission::new("GET", "/redactions", "manage_system"),
+    RoutePermission::new("POST", "/alerts/:id/explain", "manage_system"),
+    RoutePermission::new("POST", "/audit-qa", "manage_system"),
+    RoutePermission::new("POST", "/authz-qa", "manage_system"),
+    RoutePermission::new("GET", "/policy-drafts", "manage_system"),
+    RoutePermission::new("POST", "/policy-drafts", "manage_system"),
+    RoutePermission::new("GET", "/policy-drafts/:id", "manage_system"),
+    RoutePermission::new("POST", "/policy-drafts/:id/approve", "manage_system"),
+    RoutePermission::new("POST", "/policy-drafts/:id/reject", "manage_system"),
+    RoutePermission::new("GET", "/prompts", "manage_system"),
+    RoutePermission::new("POST", "/prompts", "manage_system"),
+    RoutePermission::new("GET", "/prompts/:name", "manage_system"),
+    RoutePermission::new("PUT", "/prompts/:name", "manage_system"),
+    RoutePermission::new("DELETE", "/prompts/:name", "manage_system"),
+    RoutePermission::new("GET", "/prompts/:name/versions", "manage_system"),
+    RoutePermission::new(
+        "POST",
+        "/prompts/:name/versions/:version/activate",
+        "manage_system",
+    ),
+    RoutePermission::new("POST", "/prompts/:name/render", "manage_system"),
+];
+
 pub fn ai_routes() -> Router<AiService> {
     Router::new()
         .route("/generate", post(generate_text))
+        .route("/generate/stream", post(generate_text_stream))
         .route(
             "/generate-class-description",
             post(generate_class_description),
@@ -32,10 +112,540 @@ pub fn ai_routes() -> Router<AiService> {
         .route("/models", post(get_models).get(get_models))
 }
 
+/// The AI service preferring the caller's tenant providers
+async fn ai_for(svc: &AiService, claims: &Claims) -> AiService {
+    match Uuid::parse_str(&claims.sub) {
+        Ok(user_id) => svc.for_user(user_id).await,
+        Err(_) => svc.clone(),
+    }
+}
+
+/// Separate state from `ai_routes` because answering runs queries under the caller's permissions
+pub fn nl_query_routes() -> Router<NlQueryService> {
+    Router::new().route("/query", post(nl_query))
+}
+
+pub fn modeling_routes() -> Router<ModelingService> {
+    Router::new()
+        .route("/suggest-model", post(suggest_model))
+        .route_layer(axum::middleware::from_fn_with_state(
+            ROUTE_PERMISSIONS,
+            enforce_route_permissions,
+        ))
+}
+
+/// Proposes a draft changeset from sample records; review and apply it via `/ontology/changesets`
+async fn suggest_model(
+    State(svc): State<ModelingService>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(payload): ValidatedJson<SuggestModelRequest>,
+) -> Result<(StatusCode, Json<OntologyChangeset>), AiError> {
+    let user_id = Uuid::parse_str(&claims.sub)
+        .map_err(|_| AiError::Forbidden("Invalid user ID".to_string()))?;
+
+    svc.suggest(user_id, payload)
+        .await
+        .map(|changeset| (StatusCode::CREATED, Json(changeset)))
+        .inspect_err(|e| tracing::error!("Modeling suggestion failed: {}", e))
+}
+
+pub fn semantic_search_routes() -> Router<SemanticSearchService> {
+    Router::new().route("/semantic-search", post(semantic_search))
+}
+
+async fn semantic_search(
+    State(svc): State<SemanticSearchService>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(payload): ValidatedJson<SemanticSearchRequest>,
+) -> Result<Json<Vec<SemanticSearchResult>>, AiError> {
+    let user_id = Uuid::parse_str(&claims.sub)
+        .map_err(|_| AiError::InvalidQuery("Invalid user ID".to_string()))?;
+
+    svc.search(user_id, payload)
+        .await
+        .map(Json)
+        .inspect_err(|e| tracing::error!("Semantic search failed: {}", e))
+}
+
+/// Usage reports and budgets are admin-only, except the caller's own usage
+pub fn usage_routes() -> Router<UsageService> {
+    Router::new()
+        .route("/usage", get(usage_report))
+        .route("/usage/me", get(my_usage))
+        .route("/budgets", get(list_budgets))
+        .route("/budgets/:tenant_id", put(set_budget).delete(delete_budget))
+        .route_layer(axum::middleware::from_fn_with_state(
+            ROUTE_PERMISSIONS,
+            enforce_route_permissions,
+        ))
+}
+
+fn usage_user(claims: &Claims) -> Result<Uuid, AiError> {
+    Uuid::parse_str(&claims.sub).map_err(|_| AiError::Forbidden("Invalid user ID".to_string()))
+}
+
+async fn usage_report(
+    State(svc): State<UsageService>,
+    Query(query): Query<UsageReportQuery>,
+) -> Result<Json<UsageReport>, AiError> {
+    svc.report(query).await.map(Json)
+}
+
+async fn my_usage(
+    State(svc): State<UsageService>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<UsageReportQuery>,
+) -> Result<Json<UsageReport>, AiError> {
+    let user_id = Uuid::parse_str(&claims.sub)
+        .map_err(|_| AiError::Forbidden("Invalid user ID".to_string()))?;
+    svc.report(UsageReportQuery {
+        user_id: Some(user_id),
+        tenant_id: None,
+        ..query
+    })
+    .await
+    .map(Json)
+}
+
+async fn list_budgets(State(svc): State<UsageService>) -> Result<Json<Vec<BudgetStatus>>, AiError> {
+    svc.list_budgets().await.map(Json)
+}
+
+async fn set_budget(
+    State(svc): State<UsageService>,
+    Extension(claims): Extension<Claims>,
+    Path(tenant_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<SetBudgetInput>,
+) -> Result<Json<AiBudget>, AiError> {
+    let user_id = usage_user(&claims)?;
+    svc.set_budget(tenant_id, payload, user_id).await.map(Json)
+}
+
+async fn delete_budget(
+    State(svc): State<UsageService>,
+    Path(tenant_id): Path<Uuid>,
+) -> Result<StatusCode, AiError> {
+    svc.delete_budget(tenant_id)
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+}
+
+/// Progress of the semantic index worker, and admin-only backfills
+pub fn index_routes() -> Router<IndexWorker> {
+    Router::new()
+        .route("/index/status", get(index_status))
+        .route("/index/backfill", post(request_backfill))
+        .route_layer(axum::middleware::from_fn_with_state(
+            ROUTE_PERMISSIONS,
+            enforce_route_permissions,
+        ))
+}
+
+async fn index_status(
+    State(worker): State<IndexWorker>,
+) -> Result<Json<IndexWorkerStatus>, AiError> {
+    worker.status().await.map(Json)
+}
+
+async fn request_backfill(
+    State(worker): State<IndexWorker>,
+) -> Result<(StatusCode, Json<IndexWorkerStatus>), AiError> {
+    let status = worker.request_backfill().await?;
+    Ok((StatusCode::ACCEPTED, Json(status)))
+}
+
+/// Admin-only audit of what was redacted from prompts sent to AI providers
+pub fn redaction_routes() -> Router<RedactionService> {
+    Router::new()
+        .route("/redactions", get(list_redactions))
+        .route_layer(axum::middleware::from_fn_with_state(
+            ROUTE_PERMISSIONS,
+            enforce_route_permissions,
+        ))
+}
+
+async fn list_redactions(
+    State(svc): State<RedactionService>,
+    Query(query): Query<ListRedactionsQuery>,
+) -> Result<Json<Vec<RedactionAudit>>, AiError> {
+    svc.list(query).await.map(Json)
+}
+
+/// Admin-only; regenerates the machine-generated explanation on an alert
+pub fn alert_explanation_routes() -> Router<AlertExplanationService> {
+    Router::new()
+        .route("/alerts/:id/explain", post(explain_alert))
+        .route_layer(axum::middleware::from_fn_with_state(
+            ROUTE_PERMISSIONS,
+            enforce_route_permissions,
+        ))
+}
+
+async fn explain_alert(
+    State(svc): State<AlertExplanationService>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<SecurityAlert>, AiError> {
+    svc.explain(id)
+        .await
+        .map(Json)
+        .inspect_err(|e| tracing::error!("Alert explanation failed: {}", e))
+}
+
+/// Admin-only, since answers reveal audit log contents
+pub fn audit_qa_routes() -> Router<AuditQaService> {
+    Router::new()
+        .route("/audit-qa", post(ask_audit_log))
+        .route_layer(axum::middleware::from_fn_with_state(
+            ROUTE_PERMISSIONS,
+            enforce_route_permissions,
+        ))
+}
+
+async fn ask_audit_log(
+    State(svc): State<AuditQaService>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(payload): ValidatedJson<AuditQuestionRequest>,
+) -> Result<Json<AuditAnswer>, AiError> {
+    let user_id = Uuid::parse_str(&claims.sub)
+        .map_err(|_| AiError::Forbidden("Invalid user ID".to_string()))?;
+
+    svc.ask(user_id, payload)
+        .await
+        .map(Json)
+        .inspect_err(|e| tracing::error!("Audit log question failed: {}", e))
+}
+
+/// Admin-only, since answers reveal other users' access
+pub fn authz_qa_routes() -> Router<AuthzQaService> {
+    Router::new()
+        .route("/authz-qa", post(ask_authorization))
+        .route_layer(axum::middleware::from_fn_with_state(
+            ROUTE_PERMISSIONS,
+            enforce_route_permissions,
+        ))
+}
+
+async fn ask_authorization(
+    State(svc): State<AuthzQaService>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(payload): ValidatedJson<AuthzQuestionRequest>,
+) -> Result<Json<AuthzAnswer>, AiError> {
+    let user_id = Uuid::parse_str(&claims.sub)
+        .map_err(|_| AiError::Forbidden("Invalid user ID".to_string()))?;
+
+    svc.ask(user_id, payload)
+        .await
+        .map(Json)
+        .inspect_err(|e| tracing::error!("Authorization question failed: {}", e))
+}
+
+/// Admin-only; drafted policies are enforced only after an admin approves them
+pub fn policy_draft_routes() -> Router<PolicyDraftingService> {
+    Router::new()
+        .route("/policy-drafts", get(list_policy_drafts).post(draft_policy))
+        .route("/policy-drafts/:id", get(get_policy_draft))
+        .route("/policy-drafts/:id/approve", post(approve_policy_draft))
+        .route("/policy-drafts/:id/reject", post(reject_policy_draft))
+        .route_layer(axum::middleware::from_fn_with_state(
+            ROUTE_PERMISSIONS,
+            enforce_route_permissions,
+        ))
+}
+
+fn policy_user(claims: &Claims) -> Result<Uuid, AiError> {
+    Uuid::parse_str(&claims.sub).map_err(|_| AiError::Forbidden("Invalid user ID".to_string()))
+}
+
+async fn draft_policy(
+    State(svc): State<PolicyDraftingService>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(payload): ValidatedJson<DraftPolicyRequest>,
+) -> Result<(StatusCode, Json<PolicyDraft>), AiError> {
+    svc.draft(policy_user(&claims)?, payload)
+        .await
+        .map(|draft| (StatusCode::CREATED, Json(draft)))
+        .inspect_err(|e| tracing::error!("Policy drafting failed: {}", e))
+}
+
+async fn list_policy_drafts(
+    State(svc): State<PolicyDraftingService>,
+    Query(query): Query<ListPolicyDraftsQuery>,
+) -> Result<Json<Vec<PolicyDraft>>, AiError> {
+    svc.list(query).await.map(Json)
+}
+
+async fn get_policy_draft(
+    State(svc): State<PolicyDraftingService>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<PolicyDraft>, AiError> {
+    svc.get(id).await.map(Json)
+}
+
+async fn approve_policy_draft(
+    State(svc): State<PolicyDraftingService>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+    payload: Option<Json<ReviewPolicyDraftRequest>>,
+) -> Result<Json<ApprovePolicyDraftResponse>, AiError> {
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+    svc.approve(policy_user(&claims)?, id, payload)
+        .await
+        .map(Json)
+}
+
+async fn reject_policy_draft(
+    State(svc): State<PolicyDraftingService>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+    payload: Option<Json<ReviewPolicyDraftRequest>>,
+) -> Result<Json<PolicyDraft>, AiError> {
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+    svc.reject(policy_user(&claims)?, id, payload)
+        .await
+        .map(Json)
+}
+
+pub fn conversation_routes() -> Router<ConversationService> {
+    Router::new()
+        .route(
+            "/conversations",
+            get(list_conversations)
+                .post(create_conversation)
+                .delete(delete_conversations),
+        )
+        .route(
+            "/conversations/:id",
+            get(get_conversation).delete(delete_conversation),
+        )
+        .route("/conversations/:id/messages", post(send_message))
+}
+
+fn conversation_user(claims: &Claims) -> Result<Uuid, AiError> {
+    Uuid::parse_str(&claims.sub).map_err(|_| AiError::Forbidden("Invalid user ID".to_string()))
+}
+
+async fn list_conversations(
+    State(svc): State<ConversationService>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<ListConversationsQuery>,
+) -> Result<Json<Vec<AiConversation>>, AiError> {
+    svc.list(conversation_user(&claims)?, query).await.map(Json)
+}
+
+async fn create_conversation(
+    State(svc): State<ConversationService>,
+    Extension(claims): Extension<Claims>,
+    payload: Option<Json<CreateConversationInput>>,
+) -> Result<(StatusCode, Json<AiConversation>), AiError> {
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+    svc.create(conversation_user(&claims)?, payload)
+        .await
+        .map(|conversation| (StatusCode::CREATED, Json(conversation)))
+}
+
+/// Clears the caller's history, optionally only for `?project_id=`
+async fn delete_conversations(
+    State(svc): State<ConversationService>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<ListConversationsQuery>,
+) -> Result<Json<serde_json::Value>, AiError> {
+    let deleted = svc.delete_all(conversation_user(&claims)?, query).await?;
+    Ok(Json(serde_json::json!({ "deleted": deleted })))
+}
+
+async fn get_conversation(
+    State(svc): State<ConversationService>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ConversationWithMessages>, AiError> {
+    svc.get(conversation_user(&claims)?, id).await.map(Json)
+}
+
+async fn delete_conversation(
+    State(svc): State<ConversationService>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, AiError> {
+    svc.delete(conversation_user(&claims)?, id)
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+}
+
+async fn send_message(
+    State(svc): State<ConversationService>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<SendMessageInput>,
+) -> Result<(StatusCode, Json<SendMessageResponse>), AiError> {
+    svc.send(conversation_user(&claims)?, id, payload)
+        .await
+        .map(|sent| (StatusCode::CREATED, Json(sent)))
+        .inspect_err(|e| tracing::error!("AI conversation reply failed: {}", e))
+}
+
+/// Admin-only; changes take effect on the next AI request
+pub fn prompt_routes() -> Router<PromptService> {
+    Router::new()
+        .route("/prompts", get(list_prompts).post(create_prompt))
+        .route(
+            "/prompts/:name",
+            get(get_prompt).put(update_prompt).delete(delete_prompt),
+        )
+        .route("/prompts/:name/versions", get(list_prompt_versions))
+        .route(
+            "/prompts/:name/versions/:version/activate",
+            post(activate_prompt_version),
+        )
+        .route("/prompts/:name/render", post(render_prompt))
+        .route_layer(axum::middleware::from_fn_with_state(
+            ROUTE_PERMISSIONS,
+            enforce_route_permissions,
+        ))
+}
+
+fn prompt_user(claims: &Claims) -> Result<Uuid, AiError> {
+    Uuid::parse_str(&claims.sub).map_err(|_| AiError::Forbidden("Invalid user ID".to_string()))
+}
+
+async fn list_prompts(
+    State(svc): State<PromptService>,
+) -> Result<Json<Vec<PromptTemplate>>, AiError> {
+    svc.list().await.map(Json)
+}
+
+async fn create_prompt(
+    State(svc): State<PromptService>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(payload): ValidatedJson<CreatePromptTemplateInput>,
+) -> Result<(StatusCode, Json<PromptTemplateDetail>), AiError> {
+    let user_id = prompt_user(&claims)?;
+    svc.create(payload, user_id)
+        .await
+        .map(|template| (StatusCode::CREATED, Json(template)))
+}
+
+async fn get_prompt(
+    State(svc): State<PromptService>,
+    Path(name): Path<String>,
+) -> Result<Json<PromptTemplateDetail>, AiError> {
+    svc.get(&name).await.map(Json)
+}
+
+/// Saves a new version and makes it active
+async fn update_prompt(
+    State(svc): State<PromptService>,
+    Extension(claims): Extension<Claims>,
+    Path(name): Path<String>,
+    ValidatedJson(payload): ValidatedJson<UpdatePromptTemplateInput>,
+) -> Result<Json<PromptTemplateDetail>, AiError> {
+    let user_id = prompt_user(&claims)?;
+    svc.update(&name, payload, user_id).await.map(Json)
+}
+
+async fn delete_prompt(
+    State(svc): State<PromptService>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, AiError> {
+    svc.delete(&name).await.map(|_| StatusCode::NO_CONTENT)
+}
+
+async fn list_prompt_versions(
+    State(svc): State<PromptService>,
+    Path(name): Path<String>,
+) -> Result<Json<Vec<PromptTemplateVersion>>, AiError> {
+    svc.list_versions(&name).await.map(Json)
+}
+
+async fn activate_prompt_version(
+    State(svc): State<PromptService>,
+    Path((name, version)): Path<(String, i32)>,
+) -> Result<Json<PromptTemplateDetail>, AiError> {
+    svc.activate_version(&name, version).await.map(Json)
+}
+
+/// Preview the active version with sample values
+async fn render_prompt(
+    State(svc): State<PromptService>,
+    Path(name): Path<String>,
+    ValidatedJson(payload): ValidatedJson<RenderPromptInput>,
+) -> Result<Json<serde_json::Value>, AiError> {
+    let prompt = svc.preview(&name, payload).await?;
+    Ok(Json(serde_json::json!({ "prompt": prompt })))
+}
+
+/// Upload the document first via `/uploads` with purpose "extraction"
+pub fn extraction_routes() -> Router<ExtractionService> {
+    Router::new()
+        .route("/extract", post(extract))
+        .route("/extract/:id", get(get_extraction).delete(discard_extraction))
+        .route("/extract/:id/accept", post(accept_extraction))
+}
+
+fn extraction_user(claims: &Claims) -> Result<Uuid, AiError> {
+    Uuid::parse_str(&claims.sub).map_err(|_| AiError::Forbidden("Invalid user ID".to_string()))
+}
+
+async fn extract(
+    State(svc): State<ExtractionService>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(payload): ValidatedJson<ExtractRequest>,
+) -> Result<(StatusCode, Json<DocumentExtraction>), AiError> {
+    svc.extract(extraction_user(&claims)?, payload)
+        .await
+        .map(|extraction| (StatusCode::CREATED, Json(extraction)))
+        .inspect_err(|e| tracing::error!("Document extraction failed: {}", e))
+}
+
+async fn get_extraction(
+    State(svc): State<ExtractionService>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<DocumentExtraction>, AiError> {
+    svc.get(extraction_user(&claims)?, id).await.map(Json)
+}
+
+async fn discard_extraction(
+    State(svc): State<ExtractionService>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<DocumentExtraction>, AiError> {
+    svc.discard(extraction_user(&claims)?, id).await.map(Json)
+}
+
+/// Create the accepted entities as PENDING, optionally only those listed in `keys`
+async fn accept_extraction(
+    State(svc): State<ExtractionService>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+    payload: Option<Json<AcceptExtractionRequest>>,
+) -> Result<(StatusCode, Json<AcceptExtractionResponse>), AiError> {
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+    svc.accept(extraction_user(&claims)?, id, payload)
+        .await
+        .map(|accepted| (StatusCode::CREATED, Json(accepted)))
+        .inspect_err(|e| tracing::error!("Accepting document extraction failed: {}", e))
+}
+
+async fn nl_query(
+    State(svc): State<NlQueryService>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(payload): ValidatedJson<NlQueryRequest>,
+) -> Result<Json<NlQueryResponse>, AiError> {
+    let user_id = Uuid::parse_str(&claims.sub)
+        .map_err(|_| AiError::InvalidQuery("Invalid user ID".to_string()))?;
+
+    svc.ask(user_id, payload)
+        .await
+        .map(Json)
+        .inspect_err(|e| tracing::error!("Natural-language query failed: {}", e))
+}
+
 async fn suggest_roles(
     State(svc): State<AiService>,
-    Json(payload): Json<SuggestRequest>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(payload): ValidatedJson<SuggestRequest>,
+) -> Result<Json<serde_json::Value>, AiError> {
+    let svc = ai_for(&svc, &claims).await;
     // 1. Fetch existing permissions and roles for context
     let permissions = sqlx::query_scalar::<_, String>(
         r#"
@@ -63,14 +673,11 @@ async fn suggest_roles(
     let result = svc
         .generate_role_suggestions(&payload.context, &permissions, &roles)
         .await
-        .map_err(|e| {
-            tracing::error!("Role suggestion failed: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+        .inspect_err(|e| tracing::error!("Role suggestion failed: {}", e))?;
 
     let json: serde_json::Value = serde_json::from_str(&result).map_err(|e| {
         tracing::error!("Failed to parse role suggestion JSON: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
+        AiError::Failed(e.to_string())
     })?;
 
     Ok(Json(json))
@@ -78,78 +685,190 @@ async fn suggest_roles(
 
 async fn suggest_ontology(
     State(svc): State<AiService>,
-    Json(payload): Json<SuggestRequest>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    let result = svc
-        .generate_ontology_suggestions(&payload.context)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(payload): ValidatedJson<SuggestRequest>,
+) -> Result<Json<serde_json::Value>, AiError> {
+    let svc = ai_for(&svc, &claims).await;
+    let result = svc.generate_ontology_suggestions(&payload.context).await?;
 
     let json: serde_json::Value =
-        serde_json::from_str(&result).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        serde_json::from_str(&result).map_err(|e| AiError::Failed(e.to_string()))?;
 
     Ok(Json(json))
 }
 
 async fn suggest_contexts(
     State(svc): State<AiService>,
-    Json(payload): Json<SuggestRequest>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(payload): ValidatedJson<SuggestRequest>,
+) -> Result<Json<serde_json::Value>, AiError> {
+    let svc = ai_for(&svc, &claims).await;
     let result = svc
         .generate_context_suggestions(&payload.context)
         .await
-        .map_err(|e| {
-            tracing::error!("Context suggestion failed: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+        .inspect_err(|e| tracing::error!("Context suggestion failed: {}", e))?;
 
     let json: serde_json::Value = serde_json::from_str(&result).map_err(|e| {
         tracing::error!("Failed to parse context suggestion JSON: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
+        AiError::Failed(e.to_string())
     })?;
 
     Ok(Json(json))
 }
 
-async fn get_status(State(svc): State<AiService>) -> Result<Json<serde_json::Value>, StatusCode> {
+async fn get_status(
+    State(svc): State<AiService>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let svc = ai_for(&svc, &claims).await;
+    let breaker = svc.breaker_status();
     match svc.check_health().await {
-        Ok(status) => Ok(Json(status)),
+        Ok(mut status) => {
+            status["circuit_breaker"] = serde_json::json!(breaker);
+            Ok(Json(status))
+        }
         Err(e) => {
             tracing::warn!("AI Status check failed: {}", e);
             Ok(Json(serde_json::json!({
                 "status": "Unhealthy",
-                "message": e
+                "message": e,
+                "circuit_breaker": breaker
             })))
         }
     }
 }
 
-async fn get_models(State(svc): State<AiService>) -> Result<Json<Vec<String>>, StatusCode> {
-    svc.list_models().await.map(Json).map_err(|e| {
-        tracing::error!("Failed to list models: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })
+async fn get_models(
+    State(svc): State<AiService>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Vec<String>>, AiError> {
+    let svc = ai_for(&svc, &claims).await;
+    svc.list_models()
+        .await
+        .map(Json)
+        .inspect_err(|e| tracing::error!("Failed to list models: {}", e))
 }
 
 async fn generate_text(
     State(svc): State<AiService>,
-    Json(payload): Json<GenerateRequest>,
-) -> Result<Json<GenerateResponse>, StatusCode> {
-    svc.generate_text(payload).await.map(Json).map_err(|e| {
-        tracing::error!("AI generation failed: {}", e);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(payload): ValidatedJson<GenerateRequest>,
+) -> Result<Json<GenerateResponse>, AiError> {
+    let svc = ai_for(&svc, &claims).await;
+    svc.generate_text(payload)
+        .await
+        .map(Json)
+        .inspect_err(|e| tracing::error!("AI generation failed: {}", e))
+}
+
+/// Server-sent events: `delta` events carry `{"text": ...}` pieces in order,
+/// then `done`, or `error` if the provider fails mid-stream. Closing the
+/// connection cancels the generation.
+async fn generate_text_stream(
+    State(svc): State<AiService>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(payload): ValidatedJson<GenerateRequest>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AiError> {
+    let svc = ai_for(&svc, &claims).await;
+    let text = svc
+        .generate_text_stream(payload)
+        .await
+        .inspect_err(|e| tracing::error!("AI generation failed: {}", e))?;
+
+    // Ends after `done` or the first `error`
+    let events = futures::stream::unfold(Some(text), |text| async move {
+        let mut text = text?;
+        let (event, rest) = match text.next().await {
+            Some(Ok(piece)) => (
+                Event::default()
+                    .event("delta")
+                    .json_data(serde_json::json!({ "text": piece }))
+                    .unwrap_or_default(),
+                Some(text),
+            ),
+            Some(Err(e)) => {
+                tracing::error!("AI generation stream failed: {}", e);
+                (
+                    Event::default()
+                        .event("error")
+                        .data(serde_json::json!({ "error": "AI request failed" }).to_string()),
+                    None,
+                )
+            }
+            None => (Event::default().event("done").data("{}"), None),
+        };
+        Some((Ok(event), rest))
+    });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
 }
 
 async fn generate_class_description(
     State(svc): State<AiService>,
-    Json(payload): Json<GenerateClassDescriptionRequest>,
-) -> Result<Json<GenerateClassDescriptionResponse>, StatusCode> {
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(payload): ValidatedJson<GenerateClassDescriptionRequest>,
+) -> Result<Json<GenerateClassDescriptionResponse>, AiError> {
+    let svc = ai_for(&svc, &claims).await;
     svc.generate_class_description(&payload.name, payload.properties)
         .await
         .map(|description| Json(GenerateClassDescriptionResponse { description }))
-        .map_err(|e| {
-            tracing::error!("Class description generation failed: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })
+        .inspect_err(|e| tracing::error!("Class description generation failed: {}", e))
+}
+
+impl IntoResponse for AiError {
+    fn into_response(self) -> Response {
+        match self {
+            // Fail fast while the provider is down so AI features can degrade gracefully
+            AiError::Unavailable { retry_after_secs } => (
+                [(header::RETRY_AFTER, retry_after_secs.to_string())],
+                ApiError::new(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "ai.unavailable",
+                    "AI provider is temporarily unavailable",
+                )
+                .with_detail("retry_after_secs", retry_after_secs),
+            )
+                .into_response(),
+            // The reason is safe to return and lets users rephrase the question
+            AiError::InvalidQuery(reason) => ApiError::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "ai.invalid_query",
+                "Could not translate question into a query",
+            )
+            .with_detail("reason", reason)
+            .into_response(),
+            AiError::InvalidSuggestion(reason) => ApiError::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "ai.invalid_suggestion",
+                "AI suggestion was not usable",
+            )
+            .with_detail("reason", reason)
+            .into_response(),
+            AiError::Forbidden(message) => {
+                ApiError::new(StatusCode::FORBIDDEN, "ai.forbidden", message).into_response()
+            }
+            AiError::InvalidInput(message) => {
+                ApiError::new(StatusCode::BAD_REQUEST, "ai.invalid_input", message).into_response()
+            }
+            AiError::NotFound(message) => {
+                ApiError::new(StatusCode::NOT_FOUND, "ai.not_found", message).into_response()
+            }
+            AiError::Conflict(message) => {
+                ApiError::new(StatusCode::CONFLICT, "ai.conflict", message).into_response()
+            }
+            AiError::FeatureDisabled(message) => {
+                ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "ai.feature_disabled", message)
+                    .into_response()
+            }
+            AiError::BudgetExceeded(message) => {
+                ApiError::new(StatusCode::TOO_MANY_REQUESTS, "ai.budget_exceeded", message)
+                    .into_response()
+            }
+            // Provider details are logged by the handlers, not returned
+            AiError::Failed(_) => {
+                ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "ai.failed", "AI request failed")
+                    .into_response()
+            }
+        }
+    }
 }
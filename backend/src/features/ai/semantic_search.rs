@@ -0,0 +1,332 @@
+use super::service::{AiError, AiService};
+use crate::config::SemanticSearchConfig;
+use crate::features::ontology::models::EntityWithDetails;
+use crate::features::rebac::RebacService;
+use crate::utils::validation::not_blank;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, Pool, Postgres};
+use uuid::Uuid;
+use validator::Validate;
+
+/// Permission the caller needs on an entity for it to show up in results
+const READ_PERMISSION: &str = "read";
+
+const DEFAULT_LIMIT: i64 = 20;
+const MAX_LIMIT: i64 = 100;
+
+/// Share of the score that comes from vector similarity; the rest rewards
+/// names containing the query verbatim, so exact hits still rank first
+const VECTOR_WEIGHT: f64 = 0.8;
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct SemanticSearchRequest {
+    #[validate(custom = "not_blank")]
+    pub query: String,
+    /// Only search entities of this class
+    pub class: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct SemanticSearchResult {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub entity: EntityWithDetails,
+    /// Cosine similarity to the query, 0-1
+    pub similarity: f64,
+    /// Similarity blended with keyword matching; results are ordered by this
+    pub score: f64,
+}
+
+#[derive(Debug, FromRow)]
+struct PendingEntity {
+    id: Uuid,
+    class_name: String,
+    display_name: String,
+    attributes: serde_json::Value,
+}
+
+/// Text embedded for an entity: class, name and scalar attributes
+pub fn embedding_text(
+    class_name: &str,
+    display_name: &str,
+    attributes: &serde_json::Value,
+) -> String {
+    let mut text = format!("{}: {}", class_name, display_name);
+    if let Some(obj) = attributes.as_object() {
+        for (key, value) in obj {
+            let value = match value {
+                serde_json::Value::String(s) if !s.is_empty() => s.clone(),
+                serde_json::Value::Number(n) => n.to_string(),
+                serde_json::Value::Bool(b) => b.to_string(),
+                _ => continue,
+            };
+            text.push_str(&format!("\n{}: {}", key, value));
+        }
+    }
+    text
+}
+
+/// pgvector's text representation, bound as text and cast with `::vector`
+fn vector_literal(embedding: &[f32]) -> String {
+    let values: Vec<String> = embedding.iter().map(|v| v.to_string()).collect();
+    format!("[{}]", values.join(","))
+}
+
+/// Semantic entity search over pgvector embeddings, kept current by the index worker.
+#[derive(Clone)]
+pub struct SemanticSearchService {
+    pool: Pool<Postgres>,
+    ai: AiService,
+    rebac: RebacService,
+    config: SemanticSearchConfig,
+}
+
+impl SemanticSearchService {
+    pub fn new(
+        pool: Pool<Postgres>,
+        ai: AiService,
+        rebac: RebacService,
+        config: SemanticSearchConfig,
+    ) -> Self {
+        Self {
+            pool,
+            ai,
+            rebac,
+            config,
+        }
+    }
+
+    /// The embeddings table only exists where the database has pgvector
+    pub async fn is_available(&self) -> Result<bool, AiError> {
+        sqlx::query_scalar::<_, bool>("SELECT to_regclass('entity_embeddings') IS NOT NULL")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| AiError::Failed(e.to_string()))
+    }
+
+    pub async fn search(
+        &self,
+        user_id: Uuid,
+        request: SemanticSearchRequest,
+    ) -> Result<Vec<SemanticSearchResult>, AiError> {
+        let query = request.query.trim();
+        if query.is_empty() {
+            return Err(AiError::InvalidQuery("query is required".to_string()));
+        }
+        if !self.is_available().await? {
+            return Err(AiError::FeatureDisabled(
+                "Semantic search requires the pgvector extension".to_string(),
+            ));
+        }
+
+        let embedding = self
+            .ai
+            .embed(&self.config.embedding_model, vec![query.to_string()])
+            .await?
+            .pop()
+            .ok_or_else(|| AiError::Failed("Provider returned no embedding".to_string()))?;
+
+        let accessible_ids: Vec<Uuid> = self
+            .rebac
+            .get_accessible_entities(user_id, READ_PERMISSION)
+            .await
+            .map_err(|e| AiError::Failed(e.to_string()))?
+            .into_iter()
+            .map(|e| e.entity_id)
+            .collect();
+
+        let results = sqlx::query_as::<_, SemanticSearchResult>(
+            r#"
+            WITH scored AS (
+                SELECT e.id, e.class_id, c.name as class_name, e.display_name,
+                       e.parent_entity_id, p.display_name as parent_entity_name,
+                       e.tenant_id,
+                       e.attributes, e.approval_status, e.created_at, e.updated_at,
+                       (1 - (ee.embedding <=> $1::vector))::float8 AS similarity,
+                       CASE WHEN strpos(LOWER(e.display_name), LOWER($2)) > 0 THEN 1.0 ELSE 0.0 END AS keyword
+                FROM entity_embeddings ee
+                JOIN entities e ON e.id = ee.entity_id
+                JOIN classes c ON e.class_id = c.id
+                LEFT JOIN entities p ON e.parent_entity_id = p.id
+                WHERE e.deleted_at IS NULL
+                  AND ee.model = $3
+                  AND vector_dims(ee.embedding) = $4
+                  AND e.id = ANY($5)
+                  AND ($6::text IS NULL OR LOWER(c.name) = LOWER($6))
+            )
+            SELECT id, class_id, class_name, display_name, parent_entity_id, parent_entity_name,
+                   tenant_id, attributes, approval_status, created_at, updated_at,
+                   similarity,
+                   (similarity * $7 + keyword * (1 - $7))::float8 AS score
+            FROM scored
+            WHERE similarity >= $8 OR keyword > 0
+            ORDER BY score DESC
+            LIMIT $9
+            "#,
+        )
+        .bind(vector_literal(&embedding))
+        .bind(query)
+        .bind(&self.config.embedding_model)
+        .bind(embedding.len() as i32)
+        .bind(&accessible_ids)
+        .bind(request.class.as_deref())
+        .bind(VECTOR_WEIGHT)
+        .bind(self.config.min_similarity)
+        .bind(request.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))?;
+
+        Ok(results)
+    }
+
+    /// Embed up to one batch of entities that are new, changed since they were
+    /// last embedded, or embedded with a different model. Returns how many.
+    pub async fn index_batch(&self) -> Result<usize, AiError> {
+        self.index_selected(Selection::Stale).await
+    }
+
+    /// Like `index_batch`, also counting entities embedded before `before` as
+    /// stale, so a backfill re-embeds everything once
+    pub async fn index_backfill_batch(&self, before: DateTime<Utc>) -> Result<usize, AiError> {
+        self.index_selected(Selection::EmbeddedBefore(before)).await
+    }
+
+    /// Re-embed the given entities whether or not they look stale, and drop
+    /// the embeddings of any that were deleted. Returns how many were embedded.
+    pub async fn reindex(&self, entity_ids: &[Uuid]) -> Result<usize, AiError> {
+        sqlx::query(
+            r#"
+            DELETE FROM entity_embeddings
+            WHERE entity_id IN (SELECT id FROM entities WHERE id = ANY($1) AND deleted_at IS NOT NULL)
+            "#,
+        )
+        .bind(entity_ids)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))?;
+
+        let mut indexed = 0;
+        for chunk in entity_ids.chunks(self.config.batch_size.max(1) as usize) {
+            indexed += self.index_selected(Selection::Entities(chunk)).await?;
+        }
+        Ok(indexed)
+    }
+
+    async fn index_selected(&self, selection: Selection<'_>) -> Result<usize, AiError> {
+        let condition = match selection {
+            Selection::Stale => {
+                "(ee.entity_id IS NULL OR ee.model <> $1 OR ee.updated_at < e.updated_at)"
+            }
+            Selection::EmbeddedBefore(_) => {
+                "(ee.entity_id IS NULL OR ee.model <> $1 OR ee.updated_at < e.updated_at OR ee.updated_at < $3)"
+            }
+            Selection::Entities(_) => "e.id = ANY($3)",
+        };
+        // Sensitive attribute values are left out of the embedded text
+        let sql = format!(
+            r#"
+            SELECT e.id, c.name as class_name, e.display_name,
+                   e.attributes - COALESCE(
+                       (SELECT array_agg(p.name) FROM properties p
+                        WHERE p.class_id = e.class_id AND p.is_sensitive = TRUE),
+                       '{{}}'
+                   ) AS attributes
+            FROM entities e
+            JOIN classes c ON e.class_id = c.id
+            LEFT JOIN entity_embeddings ee ON ee.entity_id = e.id
+            WHERE e.deleted_at IS NULL
+              AND {}
+            ORDER BY e.updated_at
+            LIMIT $2
+            "#,
+            condition
+        );
+        let query = sqlx::query_as::<_, PendingEntity>(&sql)
+            .bind(&self.config.embedding_model)
+            .bind(self.config.batch_size.max(1));
+        let query = match selection {
+            Selection::Stale => query,
+            Selection::EmbeddedBefore(before) => query.bind(before),
+            Selection::Entities(ids) => query.bind(ids),
+        };
+        let pending = query
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| AiError::Failed(e.to_string()))?;
+
+        if pending.is_empty() {
+            return Ok(0);
+        }
+
+        let texts = pending
+            .iter()
+            .map(|e| embedding_text(&e.class_name, &e.display_name, &e.attributes))
+            .collect();
+        let embeddings = self.ai.embed(&self.config.embedding_model, texts).await?;
+        if embeddings.len() != pending.len() {
+            return Err(AiError::Failed(format!(
+                "Provider returned {} embeddings for {} inputs",
+                embeddings.len(),
+                pending.len()
+            )));
+        }
+
+        for (entity, embedding) in pending.iter().zip(&embeddings) {
+            sqlx::query(
+                r#"
+                INSERT INTO entity_embeddings (entity_id, model, embedding, updated_at)
+                VALUES ($1, $2, $3::vector, NOW())
+                ON CONFLICT (entity_id) DO UPDATE
+                SET model = EXCLUDED.model, embedding = EXCLUDED.embedding, updated_at = NOW()
+                "#,
+            )
+            .bind(entity.id)
+            .bind(&self.config.embedding_model)
+            .bind(vector_literal(embedding))
+            .execute(&self.pool)
+            .await
+            .map_err(|e| AiError::Failed(e.to_string()))?;
+        }
+
+        Ok(pending.len())
+    }
+
+    pub fn config(&self) -> &SemanticSearchConfig {
+        &self.config
+    }
+}
+
+/// Which entities an indexing pass embeds
+#[derive(Clone, Copy)]
+enum Selection<'a> {
+    Stale,
+    EmbeddedBefore(DateTime<Utc>),
+    Entities(&'a [Uuid]),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_embedding_text_includes_scalar_attributes() {
+        let text = embedding_text(
+            "Mission",
+            "Operation Alpha",
+            &json!({ "status": "active", "priority": 3, "tags": ["a"], "notes": "" }),
+        );
+        assert_eq!(
+            text,
+            "Mission: Operation Alpha\npriority: 3\nstatus: active"
+        );
+    }
+
+    #[test]
+    fn test_vector_literal() {
+        assert_eq!(vector_literal(&[0.5, -1.0, 0.25]), "[0.5,-1,0.25]");
+    }
+}
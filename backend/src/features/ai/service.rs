@@ -1,25 +1,93 @@
-use async_openai::{
-    config::OpenAIConfig,
-    types::{
-        ChatCompletionRequestMessage, ChatCompletionRequestUserMessage,
-        ChatCompletionRequestUserMessageContent, CreateChatCompletionRequestArgs,
-    },
-    Client,
-};
+use futures::Stream;
 use serde::{Deserialize, Serialize};
-
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use thiserror::Error;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::providers::{build_provider, fallback_order, normalize_api_base, AiProvider, ProviderConfig, ProviderKind};
+use super::redaction::{Pseudonymizer, RedactionService};
+use super::usage::{TokenUsage, UsageRecord, UsageService};
 use sqlx::{Pool, Postgres, Row};
+use crate::config::{AiProvidersConfig, AiRedactionConfig, CircuitBreakerConfig, OutboundHttpConfig};
+use crate::utils::circuit_breaker::{BreakerError, BreakerStatus, CircuitBreaker};
+use crate::utils::http_client::{OutboundClient, RetryPolicy};
+use crate::utils::shutdown::Shutdown;
+use crate::utils::validation::not_blank;
 
 #[derive(Clone)]
 pub struct AiService {
     pool: Pool<Postgres>,
     fallback_url: String,
     fallback_model: String,
+    /// Whose providers to prefer; None uses only global providers
+    tenant_id: Option<Uuid>,
+    /// Who usage is accounted to, when called on a user's behalf
+    user_id: Option<Uuid>,
+    /// Token accounting and per-tenant budgets
+    usage: UsageService,
+    /// Personal data stripped from prompts before they reach external providers
+    redaction: RedactionService,
+    /// Settings template for the per-provider breakers
+    breaker: CircuitBreaker,
+    /// One breaker per provider, so a dead primary doesn't block its fallbacks
+    breakers: Arc<Mutex<HashMap<String, CircuitBreaker>>>,
+    /// Provider of the most recent call, whose breaker `breaker_status` reports
+    last_provider: Arc<Mutex<Option<String>>>,
+    http: OutboundClient,
+    /// Retries for chat completions, which go through the async-openai client
+    completion_retry: RetryPolicy,
+    /// Which providers may be given API keys
+    providers_config: AiProvidersConfig,
+}
+#[derive(Error, Debug)]
+pub enum AiError {
+    #[error("AI provider is unavailable, retry in {retry_after_secs}s")]
+    Unavailable { retry_after_secs: u64 },
+    #[error("{0}")]
+    Failed(String),
+    #[error("Could not translate question into a query: {0}")]
+    InvalidQuery(String),
+    #[error("{0}")]
+    FeatureDisabled(String),
+    #[error("AI suggestion was not usable: {0}")]
+    InvalidSuggestion(String),
+    #[error("{0}")]
+    Forbidden(String),
+    #[error("{0}")]
+    InvalidInput(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    Conflict(String),
+    #[error("{0}")]
+    BudgetExceeded(String),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl From<BreakerError<String>> for AiError {
+    fn from(e: BreakerError<String>) -> Self {
+        match e {
+            BreakerError::Open { retry_after } => AiError::Unavailable {
+                retry_after_secs: retry_after.as_secs().max(1),
+            },
+            BreakerError::Inner(msg) => AiError::Failed(msg),
+        }
+    }
+}
+
+/// Pieces of generated text, in order
+pub type TextStream = Pin<Box<dyn Stream<Item = Result<String, AiError>> + Send>>;
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct GenerateRequest {
+    #[validate(custom = "not_blank")]
     pub prompt: String,
+    #[validate(range(min = 0.0, max = 2.0))]
     pub temperature: Option<f32>,
     pub max_tokens: Option<u16>,
 }
@@ -27,14 +95,188 @@ pub struct GenerateRequest {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GenerateResponse {
     pub text: String,
+    #[serde(default)]
+    pub usage: TokenUsage,
+}
+
+/// Counts the text a stream yields, and reports it once the stream ends or
+/// is dropped, so cancelled streams are accounted for what they produced
+struct MeteredStream {
+    inner: TextStream,
+    completion_chars: usize,
+    on_end: Option<Box<dyn FnOnce(usize) + Send>>,
+}
+
+impl MeteredStream {
+    fn finish(&mut self) {
+        if let Some(on_end) = self.on_end.take() {
+            on_end(self.completion_chars);
+        }
+    }
+}
+
+impl Stream for MeteredStream {
+    type Item = Result<String, AiError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let poll = self.inner.as_mut().poll_next(cx);
+        match &poll {
+            Poll::Ready(Some(Ok(text))) => self.completion_chars += text.chars().count(),
+            Poll::Ready(None) => self.finish(),
+            _ => {}
+        }
+        poll
+    }
+}
+
+impl Drop for MeteredStream {
+    fn drop(&mut self) {
+        self.finish();
+    }
 }
 
 impl AiService {
     pub fn new(pool: Pool<Postgres>, fallback_url: String, fallback_model: String) -> Self {
         Self {
+            usage: UsageService::new(pool.clone()),
+            redaction: RedactionService::new(pool.clone(), AiRedactionConfig::default()),
             pool,
             fallback_url,
             fallback_model,
+            tenant_id: None,
+            user_id: None,
+            breaker: CircuitBreaker::from_config(&CircuitBreakerConfig::default()),
+            breakers: Arc::new(Mutex::new(HashMap::new())),
+            last_provider: Arc::new(Mutex::new(None)),
+            http: OutboundClient::default(),
+            completion_retry: Self::completion_retry(&OutboundHttpConfig::default()),
+            providers_config: AiProvidersConfig::default(),
+        }
+    }
+
+    pub fn with_providers_config(mut self, config: &AiProvidersConfig) -> Self {
+        self.providers_config = config.clone();
+        self
+    }
+
+    pub fn with_http_config(mut self, config: &OutboundHttpConfig) -> Self {
+        self.http = OutboundClient::from_config(config);
+        self.completion_retry = Self::completion_retry(config);
+        self
+    }
+
+    fn completion_retry(config: &OutboundHttpConfig) -> RetryPolicy {
+        RetryPolicy::from_config(config).with_timeouts(
+            Duration::from_secs(config.ai_attempt_timeout_secs),
+            Duration::from_secs(config.ai_total_timeout_secs),
+        )
+    }
+
+    pub fn with_redaction_config(mut self, config: &AiRedactionConfig) -> Self {
+        self.redaction = RedactionService::new(self.pool.clone(), config.clone());
+        self
+    }
+
+    /// `prompt` with personal data replaced, and the pseudonymizer that did
+    /// it; None when redaction is disabled
+    async fn redact_request(
+        &self,
+        req: &GenerateRequest,
+    ) -> Result<Option<(GenerateRequest, Arc<Pseudonymizer>)>, AiError> {
+        let Some(mut pseudonymizer) = self.redaction.prepare(&[&req.prompt]).await? else {
+            return Ok(None);
+        };
+        let redacted = GenerateRequest {
+            prompt: pseudonymizer.redact(&req.prompt),
+            temperature: req.temperature,
+            max_tokens: req.max_tokens,
+        };
+        Ok(Some((redacted, Arc::new(pseudonymizer))))
+    }
+
+    /// The request to send to `config`'s provider
+    fn request_for<'a>(
+        &self,
+        config: &ProviderConfig,
+        req: &'a GenerateRequest,
+        redacted: &'a Option<(GenerateRequest, Arc<Pseudonymizer>)>,
+    ) -> &'a GenerateRequest {
+        match redacted {
+            Some((redacted, _)) if self.redaction.applies_to(config) => redacted,
+            _ => req,
+        }
+    }
+
+    /// The pseudonymizer whose output `config`'s provider was sent, after
+    /// recording what it redacted
+    async fn audit_redaction<'a>(
+        &self,
+        config: &ProviderConfig,
+        operation: &str,
+        pseudonymizer: Option<&'a Pseudonymizer>,
+    ) -> Option<&'a Pseudonymizer> {
+        let pseudonymizer = pseudonymizer.filter(|_| self.redaction.applies_to(config))?;
+        self.redaction
+            .audit(
+                config,
+                self.user_id,
+                self.tenant_id,
+                operation,
+                pseudonymizer.summary(),
+            )
+            .await;
+        Some(pseudonymizer)
+    }
+
+    /// Use `breaker`'s settings for every provider, starting closed
+    pub fn with_circuit_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.breaker = breaker;
+        self.breakers = Arc::new(Mutex::new(HashMap::new()));
+        self.last_provider = Arc::new(Mutex::new(None));
+        self
+    }
+
+    /// The same service, preferring `tenant_id`'s own providers. Breakers are
+    /// shared, so provider outages are tracked across tenants.
+    pub fn for_tenant(&self, tenant_id: Option<Uuid>) -> Self {
+        Self {
+            tenant_id,
+            ..self.clone()
+        }
+    }
+
+    /// `for_tenant` with the tenant of the user's entity; usage is accounted
+    /// to the user and counts against the tenant's budget
+    pub async fn for_user(&self, user_id: Uuid) -> Self {
+        let tenant_id = sqlx::query_scalar::<_, Option<Uuid>>(
+            "SELECT tenant_id FROM entities WHERE id = $1",
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .ok()
+        .flatten()
+        .flatten();
+        Self {
+            user_id: Some(user_id),
+            ..self.for_tenant(tenant_id)
+        }
+    }
+
+    fn usage_record(&self, operation: &'static str, usage: TokenUsage) -> UsageRecord {
+        UsageRecord {
+            user_id: self.user_id,
+            tenant_id: self.tenant_id,
+            operation,
+            usage,
+        }
+    }
+
+    pub fn breaker_status(&self) -> BreakerStatus {
+        let last = self.last_provider.lock().unwrap().clone();
+        match last {
+            Some(key) => self.breaker_for(&key).status(),
+            None => self.breaker.status(),
         }
     }
 
@@ -42,102 +284,172 @@ impl AiService {
         &self.pool
     }
 
-    fn normalize_api_base(api_base: &str) -> String {
-        let base = api_base.trim_end_matches('/');
-        if base.ends_with("/v1") {
-            base.to_string()
-        } else {
-            format!("{}/v1", base)
+    fn breaker_for(&self, key: &str) -> CircuitBreaker {
+        self.breakers
+            .lock()
+            .unwrap()
+            .entry(key.to_string())
+            .or_insert_with(|| self.breaker.new_like())
+            .clone()
+    }
+
+    fn env_provider(&self) -> ProviderConfig {
+        ProviderConfig {
+            id: None,
+            name: "Environment".to_string(),
+            kind: ProviderKind::Ollama,
+            api_base: self.fallback_url.clone(),
+            model: self.fallback_model.clone(),
+            api_key: None,
+            priority: 0,
+            tenant_id: None,
+            healthy: true,
+            input_cost_per_1k: 0.0,
+            output_cost_per_1k: 0.0,
         }
     }
 
-    pub async fn get_config(&self) -> (String, String) {
+    /// Active providers in the order to try them, see `fallback_order`.
+    /// The environment provider is used when none are configured.
+    pub async fn providers(&self) -> Vec<ProviderConfig> {
         // Optional env override for containerized deployments.
         if std::env::var("AI_PREFER_ENV")
             .map(|v| v == "true")
             .unwrap_or(false)
         {
-            return (
-                Self::normalize_api_base(&self.fallback_url),
-                self.fallback_model.clone(),
-            );
+            return vec![self.env_provider()];
         }
 
-        // Find active AiProvider entity
-        let result = sqlx::query(
+        let rows = sqlx::query(
             r#"
-            SELECT e.attributes 
+            SELECT e.id, e.display_name, e.tenant_id, e.attributes
             FROM entities e
             JOIN classes c ON e.class_id = c.id
-            WHERE c.name = 'AiProvider' 
+            WHERE c.name = 'AiProvider'
               AND (e.attributes->>'is_active')::boolean = true
               AND e.deleted_at IS NULL
-            LIMIT 1
+              AND (e.tenant_id IS NULL OR e.tenant_id = $1)
+            ORDER BY e.created_at
             "#,
         )
-        .fetch_optional(&self.pool)
-        .await;
+        .bind(self.tenant_id)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!("Failed to load AI providers: {}", e);
+            Vec::new()
+        });
 
-        match result {
-            Ok(Some(row)) => {
+        let providers: Vec<ProviderConfig> = rows
+            .iter()
+            .filter_map(|row| {
                 let attrs: serde_json::Value = row.get("attributes");
-                let url = attrs
-                    .get("api_base")
-                    .and_then(|v| v.as_str())
-                    .map(Self::normalize_api_base)
-                    .unwrap_or_else(|| Self::normalize_api_base(&self.fallback_url));
-                let model = attrs
-                    .get("model_name")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or(&self.fallback_model)
-                    .to_string();
-                (url, model)
-            }
-            _ => (
-                Self::normalize_api_base(&self.fallback_url),
-                self.fallback_model.clone(),
-            ),
+                ProviderConfig::from_entity(
+                    row.get("id"),
+                    row.get("display_name"),
+                    row.get("tenant_id"),
+                    &attrs,
+                    &self.providers_config,
+                )
+            })
+            .collect();
+
+        if providers.is_empty() {
+            vec![self.env_provider()]
+        } else {
+            fallback_order(providers)
         }
     }
 
-    fn get_client(&self, api_base: String) -> Client<OpenAIConfig> {
-        let config = OpenAIConfig::new()
-            .with_api_base(api_base)
-            .with_api_key("dummy");
-
-        Client::with_config(config)
+    /// Primary provider's API base and model
+    pub async fn get_config(&self) -> (String, String) {
+        let primary = self
+            .providers()
+            .await
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| self.env_provider());
+        (normalize_api_base(&primary.api_base), primary.model)
     }
 
-    pub async fn check_health(&self) -> Result<serde_json::Value, String> {
-        let (api_base, model) = self.get_config().await;
-        self.check_provider_health(&api_base, &model).await
+    fn build(&self, config: &ProviderConfig) -> Arc<dyn AiProvider> {
+        build_provider(config, &self.http, &self.completion_retry)
     }
 
-    async fn check_provider_health(
+    /// Run `call` against each provider in turn until one succeeds, skipping
+    /// those whose breaker is open. Fails with the last provider error, or
+    /// `Unavailable` if every breaker was open.
+    async fn with_fallback<T, F, Fut>(
         &self,
-        api_base: &str,
-        model: &str,
-    ) -> Result<serde_json::Value, String> {
-        // Simple check to see if we can reach the base URL
-        let health_url = format!("{}/api/tags", api_base.trim_end_matches("/v1"));
-        let res = reqwest::get(&health_url).await;
-
-        match res {
-            Ok(resp) if resp.status().is_success() => Ok(serde_json::json!({
-                "status": "Healthy",
-                "model": model,
-                "provider_url": api_base
-            })),
-            Ok(resp) => Err(format!("AI Provider Degraded: HTTP {}", resp.status())),
-            Err(e) => Err(format!("AI Provider Unreachable: {}", e)),
+        usable: impl Fn(&dyn AiProvider) -> bool,
+        call: F,
+    ) -> Result<T, AiError>
+    where
+        F: Fn(Arc<dyn AiProvider>, ProviderConfig) -> Fut,
+        Fut: Future<Output = Result<T, String>>,
+    {
+        let mut last_error = None;
+        let mut retry_after: Option<Duration> = None;
+
+        for config in self.providers().await {
+            let provider = self.build(&config);
+            if !usable(provider.as_ref()) {
+                continue;
+            }
+            let key = config.key();
+            let name = config.name.clone();
+            *self.last_provider.lock().unwrap() = Some(key.clone());
+
+            match self.breaker_for(&key).call(call(provider, config)).await {
+                Ok(value) => return Ok(value),
+                Err(BreakerError::Open { retry_after: wait }) => {
+                    retry_after = Some(retry_after.map_or(wait, |r| r.min(wait)));
+                }
+                Err(BreakerError::Inner(e)) => {
+                    tracing::warn!("AI provider '{}' failed, trying next: {}", name, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        match (last_error, retry_after) {
+            (Some(e), _) => Err(AiError::Failed(e)),
+            (None, Some(wait)) => Err(BreakerError::<String>::Open { retry_after: wait }.into()),
+            (None, None) => Err(AiError::Failed(
+                "No AI provider supports this operation".to_string(),
+            )),
+        }
+    }
+
+    /// Health of the first provider that responds, noting when it is a fallback
+    pub async fn check_health(&self) -> Result<serde_json::Value, String> {
+        let mut last_error = "No AI provider configured".to_string();
+        for (index, config) in self.providers().await.into_iter().enumerate() {
+            match self.build(&config).health_check().await {
+                Ok(()) => {
+                    return Ok(serde_json::json!({
+                        "status": "Healthy",
+                        "model": config.model,
+                        "provider_url": normalize_api_base(&config.api_base),
+                        "provider": config.name,
+                        "provider_kind": config.kind,
+                        "fallback": index > 0
+                    }))
+                }
+                Err(e) => last_error = e,
+            }
         }
+        Err(last_error)
     }
 
-    pub async fn start_background_health_check(self) {
-        tokio::spawn(async move {
+    pub async fn start_background_health_check(self, shutdown: Shutdown) {
+        shutdown.clone().spawn(async move {
             let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(60));
             loop {
-                interval.tick().await;
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = shutdown.cancelled() => break,
+                }
                 if let Err(e) = self.run_health_checks().await {
                     tracing::error!("Failed to run background AI health checks: {}", e);
                 }
@@ -145,11 +457,13 @@ impl AiService {
         });
     }
 
+    /// Record each provider's health in its `status` attribute; unhealthy
+    /// providers are tried after healthy ones
     async fn run_health_checks(&self) -> Result<(), String> {
         // Fetch all AiProvider entities
         let providers = sqlx::query(
             r#"
-            SELECT e.id, e.attributes 
+            SELECT e.id, e.display_name, e.tenant_id, e.attributes
             FROM entities e
             JOIN classes c ON e.class_id = c.id
             WHERE c.name = 'AiProvider' 
@@ -164,22 +478,17 @@ impl AiService {
             let id: uuid::Uuid = row.get("id");
             let mut attrs: serde_json::Value = row.get("attributes");
 
-            let api_base = attrs
-                .get("api_base")
-                .and_then(|v| v.as_str())
-                .unwrap_or("")
-                .to_string();
-            let model = attrs
-                .get("model_name")
-                .and_then(|v| v.as_str())
-                .unwrap_or("")
-                .to_string();
-
-            if api_base.is_empty() {
+            let Some(config) = ProviderConfig::from_entity(
+                id,
+                row.get("display_name"),
+                row.get("tenant_id"),
+                &attrs,
+                &self.providers_config,
+            ) else {
                 continue;
-            }
+            };
 
-            let status = match self.check_provider_health(&api_base, &model).await {
+            let status = match self.build(&config).health_check().await {
                 Ok(_) => "Healthy",
                 Err(_) => "Unhealthy",
             };
@@ -207,72 +516,135 @@ impl AiService {
         Ok(())
     }
 
-    pub async fn list_models(&self) -> Result<Vec<String>, String> {
-        let (api_base, _) = self.get_config().await;
-        let health_url = format!("{}/api/tags", api_base.trim_end_matches("/v1"));
-
-        let res = reqwest::get(&health_url)
-            .await
-            .map_err(|e| format!("Failed to fetch models: {}", e))?;
+    pub async fn list_models(&self) -> Result<Vec<String>, AiError> {
+        self.with_fallback(
+            |_| true,
+            |provider, _| async move { provider.list_models().await },
+        )
+        .await
+    }
 
-        if !res.status().is_success() {
-            return Err(format!("Failed to fetch models: HTTP {}", res.status()));
-        }
+    pub async fn generate_text(&self, req: GenerateRequest) -> Result<GenerateResponse, AiError> {
+        self.usage.check_budget(self.tenant_id).await?;
+        let redacted = self.redact_request(&req).await?;
+        let (req, redacted) = (&req, &redacted);
+        let (generation, config) = self
+            .with_fallback(
+                |_| true,
+                |provider, config| async move {
+                    provider
+                        .generate(&config.model, self.request_for(&config, req, redacted))
+                        .await
+                        .map(|generation| (generation, config))
+                },
+            )
+            .await?;
 
-        let body: serde_json::Value = res
-            .json()
-            .await
-            .map_err(|e| format!("Failed to parse models JSON: {}", e))?;
+        let usage = generation.usage.unwrap_or_else(|| {
+            TokenUsage::estimate(req.prompt.chars().count(), generation.text.chars().count())
+        });
+        self.usage
+            .record(&config, self.usage_record("generate", usage))
+            .await;
+
+        let pseudonymizer = redacted.as_ref().map(|(_, p)| p.as_ref());
+        let text = match self.audit_redaction(&config, "generate", pseudonymizer).await {
+            Some(p) if self.redaction.restores_responses() => p.restore(&generation.text),
+            _ => generation.text,
+        };
+        Ok(GenerateResponse { text, usage })
+    }
 
-        let mut models = Vec::new();
-        if let Some(models_array) = body.get("models").and_then(|m| m.as_array()) {
-            for m in models_array {
-                if let Some(name) = m.get("name").and_then(|n| n.as_str()) {
-                    models.push(name.to_string());
-                }
+    /// Like `generate_text`, but yields the response text in pieces as the
+    /// provider produces it. Dropping the stream cancels the provider request.
+    ///
+    /// The breaker sees the call succeed or fail once the first piece arrives;
+    /// errors after that end the stream with an `Err` item. Streams are not
+    /// retried, since part of the response may already have been shown.
+    /// Streamed usage is estimated from the text length.
+    pub async fn generate_text_stream(&self, req: GenerateRequest) -> Result<TextStream, AiError> {
+        self.usage.check_budget(self.tenant_id).await?;
+        let prompt_chars = req.prompt.chars().count();
+        let redacted = self.redact_request(&req).await?;
+        let (req, redacted) = (&req, &redacted);
+        let (stream, config) = self
+            .with_fallback(
+                |_| true,
+                |provider, config| async move {
+                    provider
+                        .generate_stream(&config.model, self.request_for(&config, req, redacted))
+                        .await
+                        .map(|stream| (stream, config))
+                },
+            )
+            .await?;
+
+        let pseudonymizer = redacted.as_ref().map(|(_, p)| p.as_ref());
+        let stream = match self.audit_redaction(&config, "stream", pseudonymizer).await {
+            Some(_) if self.redaction.restores_responses() => {
+                let (_, pseudonymizer) = redacted.as_ref().unwrap();
+                pseudonymizer.clone().restore_stream(stream)
             }
-        }
+            _ => stream,
+        };
 
-        Ok(models)
+        let usage = self.usage.clone();
+        let record = self.usage_record("stream", TokenUsage::default());
+        let on_end = move |completion_chars: usize| {
+            let record = UsageRecord {
+                usage: TokenUsage::estimate(prompt_chars, completion_chars),
+                ..record
+            };
+            if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                runtime.spawn(async move { usage.record(&config, record).await });
+            }
+        };
+        Ok(Box::pin(MeteredStream {
+            inner: stream,
+            completion_chars: 0,
+            on_end: Some(Box::new(on_end)),
+        }))
     }
 
-    pub async fn generate_text(&self, req: GenerateRequest) -> Result<GenerateResponse, String> {
-        let (api_base, model) = self.get_config().await;
-        let client = self.get_client(api_base);
-
-        let request = CreateChatCompletionRequestArgs::default()
-            .model(&model)
-            .messages([ChatCompletionRequestMessage::User(
-                ChatCompletionRequestUserMessage {
-                    content: ChatCompletionRequestUserMessageContent::Text(req.prompt),
-                    name: None,
+    /// Embed each input with `model`, returning vectors in input order.
+    /// Providers without an embeddings API are skipped.
+    pub async fn embed(&self, model: &str, inputs: Vec<String>) -> Result<Vec<Vec<f32>>, AiError> {
+        self.usage.check_budget(self.tenant_id).await?;
+        let input_chars = inputs.iter().map(|i| i.chars().count()).sum();
+        let texts: Vec<&str> = inputs.iter().map(String::as_str).collect();
+        let redacted = self.redaction.prepare(&texts).await?.map(|mut p| {
+            let redacted: Vec<String> = inputs.iter().map(|i| p.redact(i)).collect();
+            (redacted, p)
+        });
+        let (inputs, redacted) = (&inputs, &redacted);
+        let (vectors, config) = self
+            .with_fallback(
+                |provider| provider.supports_embeddings(),
+                |provider, config| async move {
+                    let inputs = match redacted {
+                        Some((redacted, _)) if self.redaction.applies_to(&config) => redacted,
+                        _ => inputs,
+                    };
+                    provider
+                        .embed(model, inputs.clone())
+                        .await
+                        .map(|vectors| (vectors, config))
                 },
-            )])
-            .temperature(req.temperature.unwrap_or(0.7))
-            .max_tokens(req.max_tokens.unwrap_or(1024))
-            .build()
-            .map_err(|e| format!("Failed to build request: {}", e))?;
-
-        let response = client
-            .chat()
-            .create(request)
-            .await
-            .map_err(|e| format!("AI Service Error: {}", e))?;
-
-        let text = response
-            .choices
-            .first()
-            .and_then(|c| c.message.content.clone())
-            .unwrap_or_default();
-
-        Ok(GenerateResponse { text })
+            )
+            .await?;
+        self.audit_redaction(&config, "embed", redacted.as_ref().map(|(_, p)| p))
+            .await;
+
+        let usage = TokenUsage::estimate(input_chars, 0);
+        self.usage.record(&config, self.usage_record("embed", usage)).await;
+        Ok(vectors)
     }
 
     pub async fn generate_class_description(
         &self,
         class_name: &str,
         properties: Option<Vec<String>>,
-    ) -> Result<String, String> {
+    ) -> Result<String, AiError> {
         let properties_text = match properties {
             Some(props) if !props.is_empty() => format!("\nProperties: {}", props.join(", ")),
             _ => String::new(),
@@ -308,7 +680,7 @@ impl AiService {
         context: &str,
         permissions: &[String],
         existing_roles: &[String],
-    ) -> Result<String, String> {
+    ) -> Result<String, AiError> {
         let permissions_list = if permissions.is_empty() {
             "None defined yet".to_string()
         } else {
@@ -343,7 +715,7 @@ impl AiService {
         Ok(response.text.trim().to_string())
     }
 
-    pub async fn generate_ontology_suggestions(&self, domain: &str) -> Result<String, String> {
+    pub async fn generate_ontology_suggestions(&self, domain: &str) -> Result<String, AiError> {
         let prompt = format!(
             "Suggest a basic ontology structure for the domain: {}\n\n\
             Provide 3-5 core classes. For each class, provide:\n\
@@ -364,7 +736,7 @@ impl AiService {
         Ok(response.text.trim().to_string())
     }
 
-    pub async fn generate_context_suggestions(&self, scenario: &str) -> Result<String, String> {
+    pub async fn generate_context_suggestions(&self, scenario: &str) -> Result<String, AiError> {
         let prompt = format!(
             "Based on the following scenario, suggest 3-5 specific 'Context' entities to track.\n\
             Scenario: {}\n\n\
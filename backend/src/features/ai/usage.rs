@@ -0,0 +1,363 @@
+use super::providers::ProviderConfig;
+use super::service::AiError;
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, Pool, Postgres};
+use uuid::Uuid;
+use validator::Validate;
+
+/// Rough characters per token, for providers that don't report usage
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Tokens consumed by one provider call
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    /// True when counted from text length because the provider reported nothing
+    pub estimated: bool,
+}
+
+impl TokenUsage {
+    pub fn estimate(prompt_chars: usize, completion_chars: usize) -> Self {
+        Self {
+            prompt_tokens: estimate_tokens(prompt_chars),
+            completion_tokens: estimate_tokens(completion_chars),
+            estimated: true,
+        }
+    }
+
+    pub fn total(&self) -> u32 {
+        self.prompt_tokens + self.completion_tokens
+    }
+
+    /// Cost in USD at the provider's per-1000-token prices
+    pub fn cost(&self, provider: &ProviderConfig) -> f64 {
+        (self.prompt_tokens as f64 * provider.input_cost_per_1k
+            + self.completion_tokens as f64 * provider.output_cost_per_1k)
+            / 1000.0
+    }
+}
+
+fn estimate_tokens(chars: usize) -> u32 {
+    chars.div_ceil(CHARS_PER_TOKEN) as u32
+}
+
+/// Who a call is accounted to, and what it was
+#[derive(Debug, Clone)]
+pub struct UsageRecord {
+    pub user_id: Option<Uuid>,
+    pub tenant_id: Option<Uuid>,
+    /// "generate", "stream" or "embed"
+    pub operation: &'static str,
+    pub usage: TokenUsage,
+}
+
+/// Monthly limits for one tenant; either may be left unlimited
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct AiBudget {
+    pub tenant_id: Uuid,
+    pub monthly_token_limit: Option<i64>,
+    pub monthly_cost_limit: Option<f64>,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct SetBudgetInput {
+    #[validate(range(min = 0))]
+    pub monthly_token_limit: Option<i64>,
+    #[validate(range(min = 0.0))]
+    pub monthly_cost_limit: Option<f64>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct UsageReportQuery {
+    /// "user", "tenant", "model", "provider" or "day" (default)
+    pub group_by: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub tenant_id: Option<Uuid>,
+    pub user_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct UsageReportRow {
+    /// The user, tenant, model, provider or day; None for calls without one
+    pub key: Option<String>,
+    pub requests: i64,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub cost: f64,
+    /// Requests whose tokens were estimated rather than reported
+    pub estimated_requests: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UsageReport {
+    pub group_by: String,
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    pub rows: Vec<UsageReportRow>,
+}
+
+#[derive(Debug, Serialize, FromRow)]
+pub struct BudgetStatus {
+    pub tenant_id: Uuid,
+    pub monthly_token_limit: Option<i64>,
+    pub monthly_cost_limit: Option<f64>,
+    pub tokens_used: i64,
+    pub cost_used: f64,
+}
+
+fn month_start(now: DateTime<Utc>) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+        .single()
+        .unwrap_or(now)
+}
+
+/// Records what every AI call consumed and enforces per-tenant monthly budgets
+#[derive(Clone)]
+pub struct UsageService {
+    pool: Pool<Postgres>,
+}
+
+impl UsageService {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+
+    /// Store a call's usage. Accounting failures are logged rather than
+    /// failing a call that already succeeded.
+    pub async fn record(&self, provider: &ProviderConfig, record: UsageRecord) {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO ai_usage
+                (user_id, tenant_id, provider_id, provider_name, model, operation,
+                 prompt_tokens, completion_tokens, estimated, cost)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            "#,
+        )
+        .bind(record.user_id)
+        .bind(record.tenant_id)
+        .bind(provider.id)
+        .bind(&provider.name)
+        .bind(&provider.model)
+        .bind(record.operation)
+        .bind(record.usage.prompt_tokens as i64)
+        .bind(record.usage.completion_tokens as i64)
+        .bind(record.usage.estimated)
+        .bind(record.usage.cost(provider))
+        .execute(&self.pool)
+        .await;
+        if let Err(e) = result {
+            tracing::error!("Failed to record AI usage: {}", e);
+        }
+    }
+
+    /// Fails with `BudgetExceeded` once the tenant has used its monthly
+    /// allowance. Tenants without a budget, and calls without a tenant, are
+    /// never limited.
+    pub async fn check_budget(&self, tenant_id: Option<Uuid>) -> Result<(), AiError> {
+        let Some(tenant_id) = tenant_id else {
+            return Ok(());
+        };
+        let Some(status) = self.budget_status(tenant_id).await? else {
+            return Ok(());
+        };
+        if status
+            .monthly_token_limit
+            .is_some_and(|limit| status.tokens_used >= limit)
+        {
+            return Err(AiError::BudgetExceeded(
+                "The monthly AI token budget has been used up".to_string(),
+            ));
+        }
+        if status
+            .monthly_cost_limit
+            .is_some_and(|limit| status.cost_used >= limit)
+        {
+            return Err(AiError::BudgetExceeded(
+                "The monthly AI cost budget has been used up".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The tenant's budget and what it has used this calendar month (UTC)
+    pub async fn budget_status(&self, tenant_id: Uuid) -> Result<Option<BudgetStatus>, AiError> {
+        sqlx::query_as::<_, BudgetStatus>(
+            r#"
+            SELECT b.tenant_id, b.monthly_token_limit, b.monthly_cost_limit,
+                   COALESCE(SUM(u.prompt_tokens + u.completion_tokens), 0)::BIGINT AS tokens_used,
+                   COALESCE(SUM(u.cost), 0)::DOUBLE PRECISION AS cost_used
+            FROM ai_budgets b
+            LEFT JOIN ai_usage u ON u.tenant_id = b.tenant_id AND u.created_at >= $2
+            WHERE b.tenant_id = $1
+            GROUP BY b.tenant_id, b.monthly_token_limit, b.monthly_cost_limit
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(month_start(Utc::now()))
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))
+    }
+
+    pub async fn list_budgets(&self) -> Result<Vec<BudgetStatus>, AiError> {
+        sqlx::query_as::<_, BudgetStatus>(
+            r#"
+            SELECT b.tenant_id, b.monthly_token_limit, b.monthly_cost_limit,
+                   COALESCE(SUM(u.prompt_tokens + u.completion_tokens), 0)::BIGINT AS tokens_used,
+                   COALESCE(SUM(u.cost), 0)::DOUBLE PRECISION AS cost_used
+            FROM ai_budgets b
+            LEFT JOIN ai_usage u ON u.tenant_id = b.tenant_id AND u.created_at >= $1
+            GROUP BY b.tenant_id, b.monthly_token_limit, b.monthly_cost_limit
+            ORDER BY b.tenant_id
+            "#,
+        )
+        .bind(month_start(Utc::now()))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))
+    }
+
+    pub async fn set_budget(
+        &self,
+        tenant_id: Uuid,
+        input: SetBudgetInput,
+        updated_by: Uuid,
+    ) -> Result<AiBudget, AiError> {
+        if input.monthly_token_limit.is_some_and(|l| l < 0)
+            || input.monthly_cost_limit.is_some_and(|l| l < 0.0)
+        {
+            return Err(AiError::InvalidInput(
+                "Budget limits can't be negative".to_string(),
+            ));
+        }
+        sqlx::query_as::<_, AiBudget>(
+            r#"
+            INSERT INTO ai_budgets (tenant_id, monthly_token_limit, monthly_cost_limit, updated_by)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (tenant_id) DO UPDATE SET
+                monthly_token_limit = EXCLUDED.monthly_token_limit,
+                monthly_cost_limit = EXCLUDED.monthly_cost_limit,
+                updated_by = EXCLUDED.updated_by,
+                updated_at = NOW()
+            RETURNING *
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(input.monthly_token_limit)
+        .bind(input.monthly_cost_limit)
+        .bind(updated_by)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))
+    }
+
+    pub async fn delete_budget(&self, tenant_id: Uuid) -> Result<(), AiError> {
+        let result = sqlx::query("DELETE FROM ai_budgets WHERE tenant_id = $1")
+            .bind(tenant_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| AiError::Failed(e.to_string()))?;
+        if result.rows_affected() == 0 {
+            return Err(AiError::NotFound("Budget not found".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Usage totals grouped by user, tenant, model, provider or day, for the
+    /// last 30 days unless a range is given
+    pub async fn report(&self, query: UsageReportQuery) -> Result<UsageReport, AiError> {
+        let group_by = query.group_by.unwrap_or_else(|| "day".to_string());
+        let key = match group_by.as_str() {
+            "user" => "user_id::TEXT",
+            "tenant" => "tenant_id::TEXT",
+            "model" => "model",
+            "provider" => "provider_name",
+            "day" => "to_char(date_trunc('day', created_at), 'YYYY-MM-DD')",
+            other => {
+                return Err(AiError::InvalidInput(format!(
+                    "Can't group usage by {:?}; use user, tenant, model, provider or day",
+                    other
+                )))
+            }
+        };
+        let until = query.until.unwrap_or_else(Utc::now);
+        let since = query
+            .since
+            .unwrap_or_else(|| until - chrono::Duration::days(30));
+
+        // `key` is one of the fixed expressions above, never user input
+        let rows = sqlx::query_as::<_, UsageReportRow>(&format!(
+            r#"
+            SELECT {key} AS key,
+                   COUNT(*) AS requests,
+                   COALESCE(SUM(prompt_tokens), 0)::BIGINT AS prompt_tokens,
+                   COALESCE(SUM(completion_tokens), 0)::BIGINT AS completion_tokens,
+                   COALESCE(SUM(cost), 0)::DOUBLE PRECISION AS cost,
+                   COUNT(*) FILTER (WHERE estimated) AS estimated_requests
+            FROM ai_usage
+            WHERE created_at >= $1 AND created_at < $2
+              AND ($3::UUID IS NULL OR tenant_id = $3)
+              AND ($4::UUID IS NULL OR user_id = $4)
+            GROUP BY 1
+            ORDER BY 1
+            "#
+        ))
+        .bind(since)
+        .bind(until)
+        .bind(query.tenant_id)
+        .bind(query.user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AiError::Failed(e.to_string()))?;
+
+        Ok(UsageReport {
+            group_by,
+            since,
+            until,
+            rows,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::ai::providers::ProviderKind;
+
+    #[test]
+    fn test_cost_uses_per_1k_prices() {
+        let provider = ProviderConfig {
+            id: None,
+            name: "priced".to_string(),
+            kind: ProviderKind::OpenAi,
+            api_base: "http://localhost".to_string(),
+            model: "m".to_string(),
+            api_key: None,
+            priority: 0,
+            tenant_id: None,
+            healthy: true,
+            input_cost_per_1k: 0.5,
+            output_cost_per_1k: 1.5,
+        };
+        let usage = TokenUsage {
+            prompt_tokens: 2000,
+            completion_tokens: 1000,
+            estimated: false,
+        };
+        assert!((usage.cost(&provider) - 2.5).abs() < 1e-9);
+        assert_eq!(usage.total(), 3000);
+    }
+
+    #[test]
+    fn test_estimate_rounds_up() {
+        let usage = TokenUsage::estimate(9, 0);
+        assert_eq!(usage.prompt_tokens, 3);
+        assert_eq!(usage.completion_tokens, 0);
+        assert!(usage.estimated);
+    }
+}
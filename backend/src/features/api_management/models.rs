@@ -2,6 +2,9 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
+use validator::Validate;
+
+use crate::utils::validation::not_blank;
 
 #[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
 pub struct ApiKey {
@@ -15,12 +18,26 @@ pub struct ApiKey {
     pub last_used_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub expires_at: Option<DateTime<Utc>>,
+    pub created_by: Option<Uuid>,
+    pub rotated_from_id: Option<Uuid>,
+    pub rotated_at: Option<DateTime<Utc>>,
+    pub expiry_notified_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct CreateApiKeyRequest {
+    #[validate(custom = "not_blank")]
     pub name: String,
     pub scopes: Option<Vec<String>>,
+    #[validate(range(min = 1))]
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RotateApiKeyRequest {
+    /// How long the old key keeps working after rotation
+    pub grace_period_hours: Option<i64>,
+    pub expires_in_days: Option<i64>,
 }
 
 #[derive(Debug, Serialize)]
@@ -31,6 +48,8 @@ pub struct CreateApiKeyResponse {
     pub secret: String, // Only returned once
     pub scopes: Vec<String>,
     pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub rotated_from_id: Option<Uuid>,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
@@ -1,55 +1,130 @@
-use super::models::{ApiKey, CreateApiKeyRequest, CreateApiKeyResponse, WebhookEndpoint};
-use super::service::ApiManagementService;
+use super::models::{
+    ApiKey, CreateApiKeyRequest, CreateApiKeyResponse, RotateApiKeyRequest, WebhookEndpoint,
+};
+use super::service::{ApiManagementError, ApiManagementService};
+use crate::features::abac::AbacService;
+use crate::features::auth::jwt::Claims;
+use crate::middleware::abac::{
+    check_user_permission, enforce_route_permissions, PermissionError, RoutePermission,
+};
+use crate::utils::api_error::ApiError;
+use crate::utils::validation::ValidatedJson;
 use axum::{
     extract::{Path, State},
     http::StatusCode,
-    routing::{delete, get},
-    Json, Router,
+    response::IntoResponse,
+    routing::{delete, get, post},
+    Extension, Json, Router,
 };
 use uuid::Uuid;
 
+/// Permissions enforced by `enforce_route_permissions` for the routes below.
+/// Keys are checked in the handlers, since everyone manages their own.
+pub const ROUTE_PERMISSIONS: &[RoutePermission] =
+    &[RoutePermission::new("GET", "/webhooks", "manage_system")];
+
 pub fn api_management_routes() -> Router<ApiManagementService> {
     Router::new()
         .route("/keys", get(list_api_keys).post(create_api_key))
         .route("/keys/:id", delete(revoke_api_key))
+        .route("/keys/:id/rotate", post(rotate_api_key))
         .route("/webhooks", get(list_webhooks))
+        .route_layer(axum::middleware::from_fn_with_state(
+            ROUTE_PERMISSIONS,
+            enforce_route_permissions,
+        ))
+}
+
+impl IntoResponse for ApiManagementError {
+    fn into_response(self) -> axum::response::Response {
+        let (status, code) = match self {
+            ApiManagementError::DatabaseError(_) | ApiManagementError::Internal(_) => {
+                (StatusCode::INTERNAL_SERVER_ERROR, "internal")
+            }
+            ApiManagementError::InvalidInput(_) => {
+                (StatusCode::BAD_REQUEST, "api_management.invalid_input")
+            }
+            ApiManagementError::KeyNotFound => {
+                (StatusCode::NOT_FOUND, "api_management.key_not_found")
+            }
+        };
+        ApiError::new(status, code, self.to_string()).into_response()
+    }
+}
+
+/// Owner to scope key operations to: the caller, or None when they have
+/// manage_system and may act on every key
+async fn key_owner(
+    abac: &AbacService,
+    claims: &Claims,
+) -> Result<Option<Uuid>, ApiManagementError> {
+    let user_id = Uuid::parse_str(&claims.sub)
+        .map_err(|_| ApiManagementError::Internal("Invalid user id in token".to_string()))?;
+    let manages_system =
+        check_user_permission(abac, &claims.sub, "manage_system", None, None, None)
+            .await
+            .map_err(|e| match e {
+                PermissionError::InternalError(msg) => ApiManagementError::Internal(msg),
+                _ => ApiManagementError::Internal("Permission check failed".to_string()),
+            })?;
+    Ok((!manages_system).then_some(user_id))
 }
 
 async fn list_api_keys(
     State(service): State<ApiManagementService>,
-) -> Result<Json<Vec<ApiKey>>, (StatusCode, String)> {
-    match service.list_keys().await {
-        Ok(keys) => Ok(Json(keys)),
-        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e)),
-    }
+    Extension(abac): Extension<AbacService>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Vec<ApiKey>>, ApiManagementError> {
+    let owner = key_owner(&abac, &claims).await?;
+    Ok(Json(service.list_keys(owner).await?))
 }
 
 async fn create_api_key(
     State(service): State<ApiManagementService>,
-    Json(payload): Json<CreateApiKeyRequest>,
-) -> Result<Json<CreateApiKeyResponse>, (StatusCode, String)> {
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(payload): ValidatedJson<CreateApiKeyRequest>,
+) -> Result<Json<CreateApiKeyResponse>, ApiManagementError> {
     let scopes = payload.scopes.unwrap_or_else(|| vec!["read:*".to_string()]);
-    match service.create_key(payload.name, scopes).await {
-        Ok(response) => Ok(Json(response)),
-        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e)),
-    }
+    let created_by = Uuid::parse_str(&claims.sub).ok();
+    let response = service
+        .create_key(payload.name, scopes, payload.expires_in_days, created_by)
+        .await?;
+    Ok(Json(response))
+}
+
+async fn rotate_api_key(
+    State(service): State<ApiManagementService>,
+    Extension(abac): Extension<AbacService>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+    payload: Option<Json<RotateApiKeyRequest>>,
+) -> Result<Json<CreateApiKeyResponse>, ApiManagementError> {
+    let Json(payload) = payload.unwrap_or_default();
+    let owner = key_owner(&abac, &claims).await?;
+    service
+        .rotate_key(
+            id,
+            owner,
+            payload.grace_period_hours,
+            payload.expires_in_days,
+        )
+        .await?
+        .map(Json)
+        .ok_or(ApiManagementError::KeyNotFound)
 }
 
 async fn revoke_api_key(
     State(service): State<ApiManagementService>,
+    Extension(abac): Extension<AbacService>,
+    Extension(claims): Extension<Claims>,
     Path(id): Path<Uuid>,
-) -> Result<(), (StatusCode, String)> {
-    match service.revoke_key(id).await {
-        Ok(_) => Ok(()),
-        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e)),
-    }
+) -> Result<(), ApiManagementError> {
+    let owner = key_owner(&abac, &claims).await?;
+    service.revoke_key(id, owner).await
 }
 
 async fn list_webhooks(
     State(service): State<ApiManagementService>,
-) -> Result<Json<Vec<WebhookEndpoint>>, (StatusCode, String)> {
-    match service.list_webhooks().await {
-        Ok(webhooks) => Ok(Json(webhooks)),
-        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e)),
-    }
+) -> Result<Json<Vec<WebhookEndpoint>>, ApiManagementError> {
+    Ok(Json(service.list_webhooks().await?))
 }
@@ -1,24 +1,69 @@
+use chrono::{DateTime, Duration, Utc};
 use rand::Rng;
+use sha2::{Digest, Sha256};
 use sqlx::{PgPool, Row};
+use thiserror::Error;
 use uuid::Uuid;
 
 use super::models::{ApiKey, CreateApiKeyResponse, WebhookEndpoint};
+use crate::features::notifications::models::NotificationCategory;
+use crate::features::notifications::NotificationService;
+use crate::utils::shutdown::Shutdown;
+
+/// Lifetime applied to keys created without an explicit expiry
+pub const DEFAULT_KEY_LIFETIME_DAYS: i64 = 90;
+/// How long a rotated key keeps working alongside its replacement
+pub const DEFAULT_ROTATION_GRACE_HOURS: i64 = 24;
+/// Owners are notified this many days before a key expires
+pub const EXPIRY_WARNING_DAYS: i64 = 7;
+
+#[derive(Error, Debug)]
+pub enum ApiManagementError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    #[error("API key not found or no longer active")]
+    KeyNotFound,
+
+    #[error("Internal error: {0}")]
+    Internal(String),
+}
 
 #[derive(Clone)]
 pub struct ApiManagementService {
     pool: PgPool,
+    notifications: NotificationService,
 }
 
 impl ApiManagementService {
     pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+        Self {
+            notifications: NotificationService::new(pool.clone()),
+            pool,
+        }
+    }
+
+    /// Deliver expiry warnings with the owners' channel preferences applied
+    pub fn with_notifications(mut self, notifications: NotificationService) -> Self {
+        self.notifications = notifications;
+        self
     }
 
-    pub async fn list_keys(&self) -> Result<Vec<ApiKey>, String> {
-        let keys = sqlx::query_as::<_, ApiKey>("SELECT * FROM api_keys ORDER BY created_at DESC")
-            .fetch_all(&self.pool)
-            .await
-            .map_err(|e| e.to_string())?;
+    /// Keys created by `owner`, or every key when `owner` is None
+    pub async fn list_keys(&self, owner: Option<Uuid>) -> Result<Vec<ApiKey>, ApiManagementError> {
+        let keys = sqlx::query_as::<_, ApiKey>(
+            r#"
+            SELECT * FROM api_keys
+            WHERE $1::uuid IS NULL OR created_by = $1
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(owner)
+        .fetch_all(&self.pool)
+        .await?;
 
         Ok(keys)
     }
@@ -27,16 +72,16 @@ impl ApiManagementService {
         &self,
         name: String,
         scopes: Vec<String>,
-    ) -> Result<CreateApiKeyResponse, String> {
-        let prefix = format!("pk_live_{}", self.generate_random_string(8));
-        let secret_part = self.generate_random_string(32);
-        let secret = format!("{}{}", prefix, secret_part);
-        let hash = format!("hashed_{}", secret_part); // Placeholder hashing
+        expires_in_days: Option<i64>,
+        created_by: Option<Uuid>,
+    ) -> Result<CreateApiKeyResponse, ApiManagementError> {
+        let (prefix, secret, hash) = self.generate_secret();
+        let expires_at = Self::expiry_from_days(expires_in_days)?;
 
         let record = sqlx::query(
             r#"
-            INSERT INTO api_keys (name, prefix, hash, scopes, status)
-            VALUES ($1, $2, $3, $4, 'active')
+            INSERT INTO api_keys (name, prefix, hash, scopes, status, expires_at, created_by)
+            VALUES ($1, $2, $3, $4, 'active', $5, $6)
             RETURNING id, created_at
             "#,
         )
@@ -44,9 +89,10 @@ impl ApiManagementService {
         .bind(&prefix)
         .bind(&hash)
         .bind(&scopes)
+        .bind(expires_at)
+        .bind(created_by)
         .fetch_one(&self.pool)
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
 
         Ok(CreateApiKeyResponse {
             id: record.get("id"),
@@ -55,29 +101,253 @@ impl ApiManagementService {
             secret,
             scopes,
             created_at: record.get("created_at"),
+            expires_at: Some(expires_at),
+            rotated_from_id: None,
         })
     }
 
-    pub async fn revoke_key(&self, id: Uuid) -> Result<(), String> {
-        sqlx::query("UPDATE api_keys SET status = 'revoked' WHERE id = $1")
-            .bind(id)
-            .execute(&self.pool)
-            .await
-            .map_err(|e| e.to_string())?;
+    /// Issue a replacement for an active key. The old key stays valid until the
+    /// grace window closes so clients can roll over without downtime.
+    /// Only keys created by `owner` are rotated, unless `owner` is None.
+    /// Returns `Ok(None)` if the key does not exist or is no longer usable.
+    pub async fn rotate_key(
+        &self,
+        id: Uuid,
+        owner: Option<Uuid>,
+        grace_period_hours: Option<i64>,
+        expires_in_days: Option<i64>,
+    ) -> Result<Option<CreateApiKeyResponse>, ApiManagementError> {
+        let grace_hours = grace_period_hours.unwrap_or(DEFAULT_ROTATION_GRACE_HOURS);
+        if !(0..=24 * 30).contains(&grace_hours) {
+            return Err(ApiManagementError::InvalidInput(
+                "Grace period must be between 0 and 720 hours".to_string(),
+            ));
+        }
+        let expires_at = Self::expiry_from_days(expires_in_days)?;
+
+        let mut tx = self.pool.begin().await?;
+
+        let old = sqlx::query_as::<_, ApiKey>(
+            r#"
+            SELECT * FROM api_keys
+            WHERE id = $1
+              AND ($2::uuid IS NULL OR created_by = $2)
+              AND status = 'active'
+              AND rotated_at IS NULL
+              AND (expires_at IS NULL OR expires_at > NOW())
+            FOR UPDATE
+            "#,
+        )
+        .bind(id)
+        .bind(owner)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let old = match old {
+            Some(key) => key,
+            None => return Ok(None),
+        };
+
+        let (prefix, secret, hash) = self.generate_secret();
+        let record = sqlx::query(
+            r#"
+            INSERT INTO api_keys (name, prefix, hash, scopes, status, expires_at, created_by, rotated_from_id)
+            VALUES ($1, $2, $3, $4, 'active', $5, $6, $7)
+            RETURNING id, created_at
+            "#,
+        )
+        .bind(&old.name)
+        .bind(&prefix)
+        .bind(&hash)
+        .bind(&old.scopes)
+        .bind(expires_at)
+        .bind(old.created_by)
+        .bind(old.id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        // Shorten the old key's lifetime to the grace window (never extend it)
+        let grace_ends_at = Utc::now() + Duration::hours(grace_hours);
+        sqlx::query(
+            r#"
+            UPDATE api_keys
+            SET rotated_at = NOW(),
+                expires_at = LEAST(COALESCE(expires_at, $2), $2)
+            WHERE id = $1
+            "#,
+        )
+        .bind(old.id)
+        .bind(grace_ends_at)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(Some(CreateApiKeyResponse {
+            id: record.get("id"),
+            name: old.name,
+            prefix,
+            secret,
+            scopes: old.scopes,
+            created_at: record.get("created_at"),
+            expires_at: Some(expires_at),
+            rotated_from_id: Some(old.id),
+        }))
+    }
+
+    /// Resolve a presented secret to its key if it is active and not expired
+    pub async fn verify_key(&self, secret: &str) -> Result<Option<ApiKey>, ApiManagementError> {
+        let key = sqlx::query_as::<_, ApiKey>(
+            r#"
+            UPDATE api_keys SET last_used_at = NOW()
+            WHERE hash = $1
+              AND status = 'active'
+              AND (expires_at IS NULL OR expires_at > NOW())
+            RETURNING *
+            "#,
+        )
+        .bind(Self::hash_secret(secret))
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(key)
+    }
+
+    /// Owner of an active, unexpired key, without recording the key as used
+    pub async fn peek_key_owner(&self, secret: &str) -> Result<Option<Uuid>, ApiManagementError> {
+        let owner = sqlx::query_scalar(
+            r#"
+            SELECT created_by FROM api_keys
+            WHERE hash = $1
+              AND status = 'active'
+              AND (expires_at IS NULL OR expires_at > NOW())
+            "#,
+        )
+        .bind(Self::hash_secret(secret))
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(owner.flatten())
+    }
+
+    /// Notify key owners about keys expiring within `within_days`.
+    /// Each key is marked once its owner has been notified, so a failed
+    /// delivery is retried on the next run. Returns the number of keys notified.
+    pub async fn notify_expiring_keys(
+        &self,
+        within_days: i64,
+    ) -> Result<usize, ApiManagementError> {
+        let keys = sqlx::query_as::<_, ApiKey>(
+            r#"
+            SELECT * FROM api_keys
+            WHERE status = 'active'
+              AND expiry_notified_at IS NULL
+              AND expires_at IS NOT NULL
+              AND expires_at > NOW()
+              AND expires_at <= NOW() + make_interval(days => $1::int)
+            "#,
+        )
+        .bind(within_days)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut notified = 0;
+        for key in &keys {
+            let expires_at = key.expires_at.map(|t| t.to_rfc3339()).unwrap_or_default();
+            tracing::warn!(key_id = %key.id, prefix = %key.prefix, %expires_at, "API key is about to expire");
+
+            if let Some(owner) = key.created_by {
+                let message = format!(
+                    "API key '{}' ({}) expires at {}. Rotate it to avoid service interruption.",
+                    key.name, key.prefix, expires_at
+                );
+                if let Err(e) = self
+                    .notifications
+                    .notify(owner, NotificationCategory::ApiKeys, &message)
+                    .await
+                {
+                    tracing::error!(key_id = %key.id, "Failed to send API key expiry notification: {}", e);
+                    continue;
+                }
+            }
+
+            sqlx::query("UPDATE api_keys SET expiry_notified_at = NOW() WHERE id = $1")
+                .bind(key.id)
+                .execute(&self.pool)
+                .await?;
+            notified += 1;
+        }
+
+        Ok(notified)
+    }
+
+    pub async fn start_expiry_notifier(self, shutdown: Shutdown) {
+        shutdown.clone().spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(3600));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = shutdown.cancelled() => break,
+                }
+                if let Err(e) = self.notify_expiring_keys(EXPIRY_WARNING_DAYS).await {
+                    tracing::error!("Failed to send API key expiry notifications: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Only keys created by `owner` are revoked, unless `owner` is None
+    pub async fn revoke_key(
+        &self,
+        id: Uuid,
+        owner: Option<Uuid>,
+    ) -> Result<(), ApiManagementError> {
+        let result = sqlx::query(
+            "UPDATE api_keys SET status = 'revoked' WHERE id = $1 AND ($2::uuid IS NULL OR created_by = $2)",
+        )
+        .bind(id)
+        .bind(owner)
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(ApiManagementError::KeyNotFound);
+        }
         Ok(())
     }
 
-    pub async fn list_webhooks(&self) -> Result<Vec<WebhookEndpoint>, String> {
+    pub async fn list_webhooks(&self) -> Result<Vec<WebhookEndpoint>, ApiManagementError> {
         let webhooks =
             sqlx::query_as::<_, WebhookEndpoint>("SELECT * FROM webhooks ORDER BY created_at DESC")
                 .fetch_all(&self.pool)
-                .await
-                .map_err(|e| e.to_string())?;
+                .await?;
 
         Ok(webhooks)
     }
 
-    // Helper
+    // Helpers
+    fn generate_secret(&self) -> (String, String, String) {
+        let prefix = format!("pk_live_{}", self.generate_random_string(8));
+        let secret = format!("{}{}", prefix, self.generate_random_string(32));
+        let hash = Self::hash_secret(&secret);
+        (prefix, secret, hash)
+    }
+
+    fn hash_secret(secret: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(secret.as_bytes());
+        hex::encode(hasher.finalize())
+    }
+
+    fn expiry_from_days(expires_in_days: Option<i64>) -> Result<DateTime<Utc>, ApiManagementError> {
+        let days = expires_in_days.unwrap_or(DEFAULT_KEY_LIFETIME_DAYS);
+        if !(1..=365).contains(&days) {
+            return Err(ApiManagementError::InvalidInput(
+                "Key lifetime must be between 1 and 365 days".to_string(),
+            ));
+        }
+        Ok(Utc::now() + Duration::days(days))
+    }
+
     fn generate_random_string(&self, len: usize) -> String {
         const CHARSET: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
         let mut rng = rand::thread_rng();
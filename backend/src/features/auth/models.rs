@@ -1,3 +1,4 @@
+use crate::utils::pagination::SortOrder;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
@@ -36,6 +37,51 @@ pub struct AuditLog {
     pub after_state: Option<JsonValue>,
     pub metadata: Option<JsonValue>,
     pub created_at: DateTime<Utc>,
+    /// Firefighter session the user had active when this was recorded
+    pub firefighter_session_id: Option<Uuid>,
+}
+
+/// Filters, sorting and paging for audit log listings
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditLogQuery {
+    pub user_id: Option<Uuid>,
+    pub target_id: Option<Uuid>,
+    /// An action (`user.login`) or the prefix of a group of them (`user`)
+    pub action: Option<String>,
+    pub target_type: Option<String>,
+    /// Client IP recorded with the entry
+    pub ip: Option<String>,
+    /// Recorded at or after
+    pub from: Option<DateTime<Utc>>,
+    /// Recorded before
+    pub to: Option<DateTime<Utc>>,
+    /// Matched against action, target type and metadata
+    pub q: Option<String>,
+    /// `created_at` (default), `action`, `target_type` or `user_id`
+    pub sort: Option<String>,
+    pub order: Option<SortOrder>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    /// `next_cursor` of the previous page, in place of `offset`
+    pub cursor: Option<String>,
+}
+
+impl AuditLogQuery {
+    /// Only a text search, in the default order, from the first page
+    pub fn is_text_search(&self) -> bool {
+        self.q.as_deref().is_some_and(|q| !q.trim().is_empty())
+            && self.user_id.is_none()
+            && self.target_id.is_none()
+            && self.action.is_none()
+            && self.target_type.is_none()
+            && self.ip.is_none()
+            && self.from.is_none()
+            && self.to.is_none()
+            && self.sort.is_none()
+            && self.order.is_none()
+            && self.offset.unwrap_or(0) == 0
+            && self.cursor.is_none()
+    }
 }
 
 #[derive(Debug, FromRow, Serialize, Deserialize, Clone)]
@@ -74,27 +120,45 @@ pub struct AdminSessionResponse {
     pub ip_address: Option<String>,
 }
 
+/// Filters, sorting and paging for the admin session listing
+#[derive(Debug, Default, Deserialize)]
+pub struct SessionListQuery {
+    pub user_id: Option<Uuid>,
+    /// IP the session was opened from
+    pub ip: Option<String>,
+    /// Opened at or after
+    pub from: Option<DateTime<Utc>>,
+    /// Opened before
+    pub to: Option<DateTime<Utc>>,
+    /// Matched against username and email
+    pub q: Option<String>,
+    /// `created_at` (default), `expires_at`, `username`, `email` or `ip_address`
+    pub sort: Option<String>,
+    pub order: Option<SortOrder>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    /// `next_cursor` of the previous page, in place of `offset`
+    pub cursor: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Validate, Clone)]
 pub struct RegisterUser {
-    #[validate(length(min = 3, message = "Username must be at least 3 characters"))]
+    #[validate(length(min = 3, max = 50))]
     pub username: String,
 
-    #[validate(email(message = "Email must be valid"))]
+    #[validate(email)]
     pub email: String,
 
-    #[validate(length(min = 8, message = "Password must be at least 8 characters"))]
+    #[validate(length(min = 8, max = 128))]
     pub password: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Validate, Clone)]
 pub struct LoginUser {
-    #[validate(length(
-        min = 3,
-        message = "Identifier (email or username) must be at least 3 characters"
-    ))]
+    #[validate(length(min = 3))]
     pub identifier: String,
 
-    #[validate(length(min = 8, message = "Password must be at least 8 characters"))]
+    #[validate(length(min = 8))]
     pub password: String,
 
     pub remember_me: Option<bool>,
@@ -1,25 +1,31 @@
+use crate::config::{Config, CsrfConfig};
 use crate::features::auth::service::AuthError;
 use crate::features::auth::{AuthResponse, AuthService, LoginUser, RegisterUser, User};
 use axum::{
-    extract::{Path, State},
+    extract::{ConnectInfo, Path, Query, State},
     http::StatusCode,
-    response::{sse::{Event, Sse}, IntoResponse},
+    response::IntoResponse,
     routing::{delete, get, post},
     Extension, Json, Router,
 };
-use futures::stream::{self, Stream};
 use serde::{Deserialize, Serialize};
-use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
 use tower_cookies::{Cookie, Cookies};
 use uuid::Uuid;
 use validator::Validate;
 
-use crate::middleware::csrf::{set_csrf_cookie, CSRF_COOKIE_NAME};
+use crate::middleware::abac::{enforce_route_permissions, RoutePermission};
+use crate::middleware::csrf::{csrf_token, rotate_csrf_token, set_csrf_cookie, CSRF_COOKIE_NAME};
+use crate::utils::api_error::ApiError;
+use crate::utils::ip::{client_ip, parse_cidrs};
+use crate::utils::pagination::Page;
+use crate::utils::validation::{not_blank, ValidatedJson};
 
 const ACCESS_TOKEN_COOKIE: &str = "access_token";
 const REFRESH_TOKEN_COOKIE: &str = "refresh_token";
 
-pub(crate) fn set_auth_cookies(cookies: &Cookies, auth: &AuthResponse) {
+pub(crate) fn set_auth_cookies(cookies: &Cookies, auth: &AuthResponse, csrf: &CsrfConfig) {
     if let Some(access_token) = &auth.access_token {
         let access_cookie = Cookie::build((ACCESS_TOKEN_COOKIE, access_token.clone()))
             .http_only(true)
@@ -46,8 +52,8 @@ pub(crate) fn set_auth_cookies(cookies: &Cookies, auth: &AuthResponse) {
         cookies.add(refresh_builder.build());
     }
 
-    // Also set CSRF cookie
-    set_csrf_cookie(cookies);
+    // Also set CSRF cookie; every new session gets a fresh token
+    set_csrf_cookie(cookies, csrf);
 }
 
 fn clear_auth_cookies(cookies: &Cookies) {
@@ -80,20 +86,18 @@ pub fn public_auth_routes() -> Router<AuthService> {
         // CVE-005 Fix: Test endpoints removed for security
 }
 
+/// Permissions enforced by `enforce_route_permissions` for the routes below
+pub const ROUTE_PERMISSIONS: &[RoutePermission] = &[
+    RoutePermission::new("GET", "/sessions/all", "manage_system"),
+    RoutePermission::new("DELETE", "/sessions/admin/:id", "manage_system"),
+    RoutePermission::new("GET", "/audit-logs", "manage_system"),
+];
+
 pub fn protected_auth_routes() -> Router<AuthService> {
     tracing::info!("Initializing protected_auth_routes");
     Router::new()
         .route("/change-password", post(change_password_handler))
-        .route("/notifications", get(notifications_handler))
-        .route(
-            "/notifications/read-all",
-            post(mark_all_notifications_read_handler),
-        )
-        .route("/notifications/stream", get(notifications_stream_handler))
-        .route(
-            "/notifications/:id/read",
-            post(mark_notification_read_handler),
-        )
+        .route("/csrf-token", get(csrf_token_handler))
         .route("/sessions", get(list_sessions_handler))
         .route("/sessions/all", get(list_all_sessions_handler))
         .route("/sessions/:id", delete(revoke_session_handler))
@@ -104,23 +108,22 @@ pub fn protected_auth_routes() -> Router<AuthService> {
         .route("/profile", axum::routing::put(profile_update_handler))
         .route("/debug", get(debug_handler))
         // CVE-005 Fix: Test cleanup endpoint removed
+        .route_layer(axum::middleware::from_fn_with_state(
+            ROUTE_PERMISSIONS,
+            enforce_route_permissions,
+        ))
 }
 
 #[axum::debug_handler]
 async fn register_handler(
     State(auth_service): State<AuthService>,
     cookies: Cookies,
-    Json(user): Json<RegisterUser>,
+    ValidatedJson(user): ValidatedJson<RegisterUser>,
 ) -> Result<(StatusCode, Json<AuthResponse>), AuthError> {
-    if let Err(e) = user.validate() {
-        tracing::warn!(email = %user.email, "Registration validation failed: {}", e);
-        return Err(AuthError::ValidationError(e.to_string()));
-    }
-
     match auth_service.register(user.clone()).await {
         Ok(response) => {
             tracing::info!(email = %user.email, "User registered successfully");
-            set_auth_cookies(&cookies, &response);
+            set_auth_cookies(&cookies, &response, auth_service.csrf_config());
             Ok((StatusCode::OK, Json(response)))
         }
         Err(e) => {
@@ -134,18 +137,27 @@ async fn register_handler(
 async fn login_handler(
     State(auth_service): State<AuthService>,
     headers: axum::http::HeaderMap,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    config: Option<Extension<Arc<Config>>>,
     cookies: Cookies,
-    Json(user): Json<LoginUser>,
+    ValidatedJson(user): ValidatedJson<LoginUser>,
 ) -> Result<(StatusCode, Json<AuthResponse>), AuthError> {
-    if let Err(e) = user.validate() {
-        tracing::warn!(identifier = %user.identifier, "Login validation failed: {}", e);
-        return Err(AuthError::ValidationError(e.to_string()));
-    }
-    // Extract client IP and user-agent (prefer X-Forwarded-For if present)
-    let ip = headers
-        .get("x-forwarded-for")
-        .and_then(|v| v.to_str().ok())
-        .map(|s| s.split(',').next().unwrap_or("").trim().to_string())
+    // Extract client IP and user-agent. Geo policies rely on the IP, so forwarding
+    // headers are only honoured from trusted proxies when the peer address is known.
+    let peer_ip = match (&connect_info, &config) {
+        (Some(ci), Some(Extension(config))) => parse_cidrs(&config.ip_access.trusted_proxies)
+            .ok()
+            .and_then(|proxies| client_ip(&headers, Some(ci), &proxies)),
+        _ => None,
+    };
+    let ip = peer_ip
+        .map(|ip| ip.to_string())
+        .or_else(|| {
+            headers
+                .get("x-forwarded-for")
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.split(',').next().unwrap_or("").trim().to_string())
+        })
         .or_else(|| {
             headers
                 .get("x-real-ip")
@@ -165,7 +177,7 @@ async fn login_handler(
     {
         Ok(response) => {
             tracing::info!(identifier = %user.identifier, ip = %ip, "User logged in successfully");
-            set_auth_cookies(&cookies, &response);
+            set_auth_cookies(&cookies, &response, auth_service.csrf_config());
             
             let status = if response.mfa_required {
                 StatusCode::ACCEPTED
@@ -203,7 +215,7 @@ async fn refresh_token_handler(
     match auth_service.refresh_token(refresh_token).await {
         Ok(response) => {
             tracing::debug!("Token refreshed successfully");
-            set_auth_cookies(&cookies, &response);
+            set_auth_cookies(&cookies, &response, auth_service.csrf_config());
             Ok((StatusCode::OK, Json(response)))
         }
         Err(e) => {
@@ -213,64 +225,74 @@ async fn refresh_token_handler(
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct ChangePasswordRequest {
+    #[validate(email)]
     pub email: String,
     pub current_password: String,
+    #[validate(length(min = 8, max = 128))]
     pub new_password: String,
 }
 
 #[axum::debug_handler]
 async fn change_password_handler(
     State(auth_service): State<AuthService>,
-    Json(req): Json<ChangePasswordRequest>,
+    cookies: Cookies,
+    ValidatedJson(req): ValidatedJson<ChangePasswordRequest>,
 ) -> Result<Json<serde_json::Value>, AuthError> {
-    // Basic validation
-    if req.new_password.len() < 8 {
-        return Err(AuthError::ValidationError(
-            "new password must be at least 8 characters".to_string(),
-        ));
-    }
-
     auth_service
         .change_password(&req.email, &req.current_password, &req.new_password)
         .await
         .map(|_| {
+            rotate_csrf_token(&cookies, auth_service.csrf_config());
             Json(serde_json::json!({
                 "message": "Password changed successfully. Notification sent."
             }))
         })
 }
 
+/// Issue the session's CSRF token (reusing the current one) for clients that
+/// can't read the cookie, e.g. after a page reload on another subdomain
+#[axum::debug_handler]
+async fn csrf_token_handler(
+    State(auth_service): State<AuthService>,
+    cookies: Cookies,
+) -> Json<serde_json::Value> {
+    let token = csrf_token(&cookies, auth_service.csrf_config());
+    Json(serde_json::json!({
+        "token": token,
+        "header": crate::middleware::csrf::CSRF_HEADER_NAME,
+    }))
+}
+
 #[derive(Deserialize, Validate)]
 pub struct ForgotPasswordRequest {
     #[validate(email)]
     pub email: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Validate)]
 pub struct MfaChallengeRequest {
+    #[validate(custom = "not_blank")]
     pub mfa_token: String,
+    #[validate(custom = "not_blank")]
     pub code: String,
     pub remember_me: Option<bool>,
 }
 
 #[derive(Deserialize, Validate)]
 pub struct ResetPasswordRequest {
+    #[validate(custom = "not_blank")]
     pub token: String,
-    #[validate(length(min = 8))]
+    #[validate(length(min = 8, max = 128))]
     pub new_password: String,
 }
 
 #[axum::debug_handler]
 async fn forgot_password_handler(
     State(auth_service): State<AuthService>,
-    Json(req): Json<ForgotPasswordRequest>,
+    ValidatedJson(req): ValidatedJson<ForgotPasswordRequest>,
 ) -> Result<Json<serde_json::Value>, AuthError> {
-    if let Err(e) = req.validate() {
-        return Err(AuthError::ValidationError(e.to_string()));
-    }
-
     // Always return success to prevent email enumeration
     let _ = auth_service.request_password_reset(&req.email).await;
     
@@ -291,12 +313,8 @@ async fn verify_reset_token_handler(
 #[axum::debug_handler]
 async fn reset_password_handler(
     State(auth_service): State<AuthService>,
-    Json(req): Json<ResetPasswordRequest>,
+    ValidatedJson(req): ValidatedJson<ResetPasswordRequest>,
 ) -> Result<Json<serde_json::Value>, AuthError> {
-    if let Err(e) = req.validate() {
-        return Err(AuthError::ValidationError(e.to_string()));
-    }
-
     auth_service.reset_password(&req.token, &req.new_password).await?;
 
     Ok(Json(serde_json::json!({
@@ -308,7 +326,7 @@ async fn reset_password_handler(
 async fn mfa_challenge_handler(
     State(auth_service): State<AuthService>,
     cookies: Cookies,
-    Json(req): Json<MfaChallengeRequest>,
+    ValidatedJson(req): ValidatedJson<MfaChallengeRequest>,
 ) -> Result<impl IntoResponse, AuthError> {
     // This handler processes MFA code submission after login
     auth_service.verify_mfa_and_login(
@@ -319,34 +337,6 @@ async fn mfa_challenge_handler(
     ).await
 }
 
-#[axum::debug_handler]
-async fn notifications_handler(
-    State(auth_service): State<AuthService>,
-    axum::extract::Query(query): axum::extract::Query<std::collections::HashMap<String, String>>,
-) -> Result<Json<serde_json::Value>, AuthError> {
-    let user_id = query
-        .get("user_id")
-        .ok_or(AuthError::ValidationError("missing user_id".to_string()))?;
-    let notifs = auth_service.get_notifications(user_id).await?;
-    let json: Vec<_> = notifs.into_iter().map(|(id, message, read, created_at)| {
-        serde_json::json!({ "id": id, "message": message, "read": read, "created_at": created_at })
-    }).collect();
-    Ok(Json(serde_json::json!({ "notifications": json })))
-}
-
-#[axum::debug_handler]
-async fn mark_notification_read_handler(
-    State(auth_service): State<AuthService>,
-    axum::extract::Path(id): axum::extract::Path<i64>,
-    axum::extract::Query(query): axum::extract::Query<std::collections::HashMap<String, String>>,
-) -> Result<Json<serde_json::Value>, AuthError> {
-    let user_id = query
-        .get("user_id")
-        .ok_or(AuthError::ValidationError("missing user_id".to_string()))?;
-    auth_service.mark_notification_read(id, user_id).await?;
-    Ok(Json(serde_json::json!({ "ok": true })))
-}
-
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RefreshTokenRequest {
     pub refresh_token: String,
@@ -424,14 +414,9 @@ async fn list_sessions_handler(
 
 async fn list_all_sessions_handler(
     State(auth_service): State<AuthService>,
-    Extension(claims): Extension<crate::features::auth::jwt::Claims>,
-) -> Result<Json<Vec<crate::features::auth::models::AdminSessionResponse>>, AuthError> {
-    // CVE-001 Fix: Check for superadmin role
-    if !claims.roles.iter().any(|r| r.role_name == "superadmin") {
-        return Err(AuthError::PermissionDenied);
-    }
-    
-    let sessions = auth_service.list_all_sessions(100).await?;
+    Query(query): Query<crate::features::auth::models::SessionListQuery>,
+) -> Result<Json<Page<crate::features::auth::models::AdminSessionResponse>>, AuthError> {
+    let sessions = auth_service.list_all_sessions(&query).await?;
     Ok(Json(sessions))
 }
 
@@ -450,11 +435,6 @@ async fn revoke_any_session_handler(
     Extension(claims): Extension<crate::features::auth::jwt::Claims>,
     Path(id): Path<String>,
 ) -> Result<StatusCode, AuthError> {
-    // CVE-001 Fix: Check for superadmin role
-    if !claims.roles.iter().any(|r| r.role_name == "superadmin") {
-        return Err(AuthError::PermissionDenied);
-    }
-    
     let admin_id = uuid::Uuid::parse_str(&claims.sub).map_err(|_| AuthError::UserNotFound)?;
     auth_service.revoke_any_session(&id, admin_id).await?;
     Ok(StatusCode::NO_CONTENT)
@@ -462,14 +442,9 @@ async fn revoke_any_session_handler(
 
 async fn get_audit_logs_handler(
     State(auth_service): State<AuthService>,
-    Extension(claims): Extension<crate::features::auth::jwt::Claims>,
-) -> Result<Json<Vec<crate::features::auth::models::AuditLog>>, AuthError> {
-    // CVE-001 Fix: Check for superadmin role
-    if !claims.roles.iter().any(|r| r.role_name == "superadmin") {
-        return Err(AuthError::PermissionDenied);
-    }
-    
-    let logs = auth_service.audit_service.get_logs().await?;
+    Query(query): Query<crate::features::auth::models::AuditLogQuery>,
+) -> Result<Json<Page<crate::features::auth::models::AuditLog>>, AuthError> {
+    let logs = auth_service.audit_service.list_logs(&query).await?;
     Ok(Json(logs))
 }
 
@@ -497,8 +472,9 @@ async fn debug_handler(
     }))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Validate)]
 pub struct UpdateProfileRequest {
+    #[validate(length(min = 3, max = 50))]
     pub username: Option<String>,
 }
 
@@ -506,7 +482,7 @@ pub struct UpdateProfileRequest {
 async fn profile_update_handler(
     State(auth_service): State<AuthService>,
     Extension(claims): Extension<crate::features::auth::jwt::Claims>,
-    Json(req): Json<UpdateProfileRequest>,
+    ValidatedJson(req): ValidatedJson<UpdateProfileRequest>,
 ) -> Result<Json<User>, AuthError> {
     let user_id = uuid::Uuid::parse_str(&claims.sub).ok();
     let user = auth_service
@@ -514,50 +490,19 @@ async fn profile_update_handler(
         .update(&claims.sub, req.username, None, user_id)
         .await?;
 
-    auth_service
-        .create_notification(&claims.sub, "Your profile was updated.")
-        .await?;
+    if let Some(user_id) = user_id {
+        auth_service
+            .send_notification(
+                user_id,
+                &crate::features::notifications::templates::PROFILE_UPDATED,
+                &[],
+            )
+            .await?;
+    }
 
     Ok(Json(user))
 }
 
-#[axum::debug_handler]
-async fn mark_all_notifications_read_handler(
-    State(auth_service): State<AuthService>,
-    Extension(claims): Extension<crate::features::auth::jwt::Claims>,
-) -> Result<Json<serde_json::Value>, AuthError> {
-    auth_service
-        .mark_all_notifications_read(&claims.sub)
-        .await?;
-    Ok(Json(serde_json::json!({ "success": true })))
-}
-
-async fn notifications_stream_handler(
-    State(auth_service): State<AuthService>,
-    Extension(claims): Extension<crate::features::auth::jwt::Claims>,
-) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
-    let rx = auth_service.subscribe_notifications();
-    let user_id = claims.sub.clone();
-
-    let stream = stream::unfold((rx, user_id), move |(mut rx, user_id)| async move {
-        loop {
-            match rx.recv().await {
-                Ok(event) => {
-                    if event.user_id == user_id {
-                        let event = Event::default()
-                            .data(serde_json::to_string(&event).unwrap_or_default());
-                        return Some((Ok(event), (rx, user_id)));
-                    }
-                }
-                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
-                Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
-            }
-        }
-    });
-
-    Sse::new(stream).keep_alive(axum::response::sse::KeepAlive::default())
-}
-
 // =====================================================================
 // MFA ROUTES
 // =====================================================================
@@ -580,20 +525,22 @@ pub fn mfa_routes() -> Router<MfaState> {
         .route("/backup-codes/regenerate", post(mfa_regenerate_backup_codes_handler))
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 struct MfaSetupRequest {
     user_id: Uuid,
+    #[validate(email)]
     email: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 struct MfaVerifyRequest {
     user_id: Uuid,
+    #[validate(custom = "not_blank")]
     code: String,
     remember_me: Option<bool>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 struct MfaDisableRequest {
     user_id: Uuid,
 }
@@ -603,19 +550,31 @@ struct MfaStatusRequest {
     user_id: Uuid,
 }
 
+impl From<MfaError> for ApiError {
+    fn from(err: MfaError) -> Self {
+        let code = match &err {
+            MfaError::MfaNotFound => "mfa.not_found",
+            MfaError::AlreadyEnabled => "mfa.already_enabled",
+            MfaError::NotEnabled => "mfa.not_enabled",
+            MfaError::InvalidCode => "mfa.invalid_code",
+            MfaError::InvalidBackupCode => "mfa.invalid_backup_code",
+            MfaError::NoBackupCodes => "mfa.no_backup_codes",
+            MfaError::NotVerified => "mfa.not_verified",
+            MfaError::DatabaseError(_) | MfaError::TotpError(_) => "internal",
+        };
+        ApiError::new(err.to_status_code(), code, err.to_string())
+    }
+}
+
 impl axum::response::IntoResponse for MfaError {
     fn into_response(self) -> axum::response::Response {
-        let status = self.to_status_code();
-        let body = Json(serde_json::json!({
-            "error": self.to_string()
-        }));
-        (status, body).into_response()
+        ApiError::from(self).into_response()
     }
 }
 
 async fn mfa_setup_handler(
     State(state): State<MfaState>,
-    Json(req): Json<MfaSetupRequest>,
+    ValidatedJson(req): ValidatedJson<MfaSetupRequest>,
 ) -> Result<Json<MfaSetupResponse>, MfaError> {
     let result = state.mfa_service.setup_mfa(req.user_id, &req.email).await?;
     Ok(Json(result))
@@ -623,7 +582,7 @@ async fn mfa_setup_handler(
 
 async fn mfa_verify_setup_handler(
     State(state): State<MfaState>,
-    Json(req): Json<MfaVerifyRequest>,
+    ValidatedJson(req): ValidatedJson<MfaVerifyRequest>,
 ) -> Result<Json<serde_json::Value>, MfaError> {
     state.mfa_service.verify_setup(req.user_id, &req.code).await?;
     Ok(Json(serde_json::json!({ "success": true, "message": "MFA enabled successfully" })))
@@ -633,7 +592,7 @@ async fn mfa_verify_handler(
     State(state): State<MfaState>,
     headers: axum::http::HeaderMap,
     cookies: Cookies,
-    Json(req): Json<MfaVerifyRequest>,
+    ValidatedJson(req): ValidatedJson<MfaVerifyRequest>,
 ) -> Result<(StatusCode, Json<AuthResponse>), AuthError> {
     // 1. Verify code (TOTP or Backup)
     let totp_result = state.mfa_service.verify_code(req.user_id, &req.code).await;
@@ -683,7 +642,7 @@ async fn mfa_verify_handler(
     ).await?;
 
     // 5. Set Cookies
-    set_auth_cookies(&cookies, &response);
+    set_auth_cookies(&cookies, &response, state.auth_service.csrf_config());
 
     // 6. Return
     Ok((StatusCode::OK, Json(response)))
@@ -691,7 +650,7 @@ async fn mfa_verify_handler(
 
 async fn mfa_disable_handler(
     State(state): State<MfaState>,
-    Json(req): Json<MfaDisableRequest>,
+    ValidatedJson(req): ValidatedJson<MfaDisableRequest>,
 ) -> Result<Json<serde_json::Value>, MfaError> {
     state.mfa_service.disable_mfa(req.user_id).await?;
     Ok(Json(serde_json::json!({ "success": true, "message": "MFA disabled" })))
@@ -707,7 +666,7 @@ async fn mfa_status_handler(
 
 async fn mfa_regenerate_backup_codes_handler(
     State(state): State<MfaState>,
-    Json(req): Json<MfaDisableRequest>,
+    ValidatedJson(req): ValidatedJson<MfaDisableRequest>,
 ) -> Result<Json<serde_json::Value>, MfaError> {
     let new_codes = state.mfa_service.regenerate_backup_codes(req.user_id).await?;
     Ok(Json(serde_json::json!({ 
@@ -7,6 +7,15 @@ use crate::features::abac::AbacService;
 use crate::features::auth::jwt::{create_jwt, create_refresh_token, UserRoleClaim};
 use crate::features::users::service::UserService;
 use crate::features::auth::mfa::MfaService;
+use crate::features::email::templates::{PASSWORD_CHANGED_EMAIL, PASSWORD_RESET_EMAIL};
+use crate::features::email::EmailService;
+use crate::features::geo_access::models::{AccessKind, GeoDecision};
+use crate::features::geo_access::GeoAccessService;
+use crate::features::notifications::models::NotificationCategory;
+use crate::features::notifications::templates::{
+    BuiltinNotification, NEW_DEVICE_LOGIN, PASSWORD_CHANGED, PASSWORD_RESET,
+};
+use crate::features::notifications::NotificationService;
 use argon2::{
     password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
     Argon2,
@@ -14,20 +23,30 @@ use argon2::{
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
 use chrono::{Duration, Utc};
-use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256}; // Add sha2 dependency for token hashing
 use rand::{distributions::Alphanumeric, Rng}; // Add rand for token generation
 
 use thiserror::Error;
-use tokio::sync::broadcast;
-
-#[derive(Clone, Serialize, Deserialize, Debug)]
-pub struct NotificationEvent {
-    pub user_id: String,
-    pub message: String,
-    pub id: i64,
-    pub created_at: String,
-}
+use crate::utils::api_error::ApiError;
+use crate::utils::pagination::{contains_pattern, cursor_timestamp, Page, PageRequest, SortField};
+
+static SESSION_SORT_FIELDS: &[SortField] = &[
+    SortField { name: "created_at", sql: "rt.created_at", sql_type: "timestamptz" },
+    SortField { name: "expires_at", sql: "rt.expires_at", sql_type: "timestamptz" },
+    SortField { name: "username", sql: "u.username", sql_type: "text" },
+    SortField { name: "email", sql: "COALESCE(u.email, '')", sql_type: "text" },
+    SortField { name: "ip_address", sql: "COALESCE(rt.ip_address, '')", sql_type: "text" },
+];
+
+/// Filters of `list_all_sessions`, bound at $1..$5
+const SESSION_FILTERS: &str = r#"
+    rt.expires_at > NOW()
+    AND ($1::uuid IS NULL OR rt.user_id = $1)
+    AND ($2::text IS NULL OR rt.ip_address = $2)
+    AND ($3::timestamptz IS NULL OR rt.created_at >= $3)
+    AND ($4::timestamptz IS NULL OR rt.created_at < $4)
+    AND ($5::text IS NULL OR u.username ILIKE $5 OR u.email ILIKE $5)
+"#;
 
 #[derive(Error, Debug)]
 pub enum AuthError {
@@ -66,6 +85,9 @@ pub enum AuthError {
 
     #[error("ABAC error: {0}")]
     AbacError(#[from] crate::features::abac::service::AbacError),
+
+    #[error("Access from your location is not permitted")]
+    GeoRestricted,
 }
 
 impl AuthError {
@@ -78,6 +100,7 @@ impl AuthError {
             Self::InvalidToken => StatusCode::UNAUTHORIZED,
             Self::UserNotFound => StatusCode::NOT_FOUND,
             Self::PermissionDenied => StatusCode::FORBIDDEN,
+            Self::GeoRestricted => StatusCode::FORBIDDEN,
             Self::ValidationError(_) => StatusCode::BAD_REQUEST,
             Self::DatabaseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::JwtError(_) => StatusCode::INTERNAL_SERVER_ERROR,
@@ -96,7 +119,9 @@ pub struct AuthService {
     pub audit_service: crate::features::system::AuditService,
     ontology_service: crate::features::ontology::OntologyService,
     mfa_service: MfaService,
-    notification_tx: broadcast::Sender<NotificationEvent>,
+    geo_access: Option<GeoAccessService>,
+    email: Option<EmailService>,
+    notifications: NotificationService,
 }
 
 impl AuthService {
@@ -109,8 +134,8 @@ impl AuthService {
         ontology_service: crate::features::ontology::OntologyService,
         mfa_service: MfaService,
     ) -> Self {
-        let (notification_tx, _) = broadcast::channel(100);
         Self {
+            notifications: NotificationService::new(pool.clone()),
             pool,
             config,
             abac_service,
@@ -118,14 +143,37 @@ impl AuthService {
             audit_service,
             ontology_service,
             mfa_service,
-            notification_tx,
+            geo_access: None,
+            email: None,
         }
     }
 
+    /// Enforce tenant country policies on login
+    pub fn with_geo_access(mut self, geo_access: GeoAccessService) -> Self {
+        self.geo_access = Some(geo_access);
+        self
+    }
+
+    /// Queue password reset links and change notices for delivery
+    pub fn with_email(mut self, email: EmailService) -> Self {
+        self.email = Some(email);
+        self
+    }
+
+    /// Deliver notifications with the users' channel preferences applied
+    pub fn with_notifications(mut self, notifications: NotificationService) -> Self {
+        self.notifications = notifications;
+        self
+    }
+
     pub fn get_user_service(&self) -> &UserService {
         &self.user_service
     }
 
+    pub fn csrf_config(&self) -> &crate::config::CsrfConfig {
+        &self.config.csrf
+    }
+
     pub fn get_abac_service(&self) -> &AbacService {
         &self.abac_service
     }
@@ -272,6 +320,19 @@ impl AuthService {
             return Err(AuthError::InvalidCredentials);
         }
 
+        // Tenant country restrictions, checked after the password so they reveal nothing to guessers
+        if let Some(geo) = &self.geo_access {
+            let client_ip = ip.as_deref().and_then(|s| s.parse().ok());
+            match geo.check(user.id, client_ip, AccessKind::Login).await {
+                Ok(GeoDecision::Blocked) => return Err(AuthError::GeoRestricted),
+                Ok(_) => {}
+                Err(e) => {
+                    tracing::error!("Geo access check failed during login: {}", e);
+                    return Err(AuthError::GeoRestricted);
+                }
+            }
+        }
+
         // Check MFA
         if self.mfa_service.is_mfa_required(user.id).await.unwrap_or(false) {
              // Generate a temporary MFA token (e.g. valid for 5 mins) to identify the user session during challenge
@@ -324,12 +385,16 @@ impl AuthService {
         // If new device/IP detected, create an in-app notification and (optionally) email
         if is_new_device {
             tracing::info!("New device detected for user {}", user.id);
-            let msg = format!(
-                "New sign-in detected from IP {} and device/agent {}",
-                ip.clone().unwrap_or_default(),
-                user_agent.clone().unwrap_or_default()
-            );
-            match self.create_notification(&user.id.to_string(), &msg).await {
+            let ip = ip.clone().unwrap_or_default();
+            let agent = user_agent.clone().unwrap_or_default();
+            match self
+                .send_notification(
+                    user.id,
+                    &NEW_DEVICE_LOGIN,
+                    &[("ip", &ip), ("user_agent", &agent)],
+                )
+                .await
+            {
                 Ok(_) => tracing::info!("Notification created successfully"),
                 Err(e) => tracing::error!("Failed to create notification: {}", e),
             }
@@ -435,11 +500,17 @@ impl AuthService {
             .execute(&self.pool)
             .await?;
 
-        self.create_notification(
-            &user.id.to_string(),
-            "Your password was successfully changed.",
-        )
-        .await?;
+        self.send_notification(user.id, &PASSWORD_CHANGED, &[]).await?;
+
+        if let Some(email_service) = &self.email {
+            if let Err(e) = email_service.queue(&PASSWORD_CHANGED_EMAIL, email, &[]).await {
+                tracing::error!(
+                    "Failed to queue password change email for user {}: {}",
+                    user.id,
+                    e
+                );
+            }
+        }
 
         // Log password change
         let _ = self
@@ -511,8 +582,26 @@ impl AuthService {
         .execute(&self.pool)
         .await?;
 
-        // 5. Send email
-        let _ = crate::utils::email::send_password_reset_email(email, &token);
+        // 5. Queue the email; the delivery worker sends and retries it
+        match &self.email {
+            Some(email_service) => {
+                let reset_link = email_service.link(&format!("reset-password/{}", token));
+                if let Err(e) = email_service
+                    .queue(&PASSWORD_RESET_EMAIL, email, &[("reset_link", &reset_link)])
+                    .await
+                {
+                    tracing::error!(
+                        "Failed to queue password reset email for user {}: {}",
+                        user.id,
+                        e
+                    );
+                }
+            }
+            None => tracing::warn!(
+                "Email is not configured; password reset email for user {} not sent",
+                user.id
+            ),
+        }
 
         // CVE-003 Fix: Add timing jitter before returning
         Self::add_timing_jitter().await;
@@ -604,7 +693,7 @@ impl AuthService {
             None
         ).await;
 
-        self.create_notification(&user_id.to_string(), "Your password has been successfully reset.").await?;
+        self.send_notification(user_id, &PASSWORD_RESET, &[]).await?;
 
         Ok(())
     }
@@ -660,7 +749,7 @@ impl AuthService {
         ).await?;
         
         // 6. Set cookies
-        crate::features::auth::routes::set_auth_cookies(&cookies, &auth_response);
+        crate::features::auth::routes::set_auth_cookies(&cookies, &auth_response, &self.config.csrf);
         
         Ok(axum::Json(auth_response))
     }
@@ -810,104 +899,36 @@ impl AuthService {
     }
 
     // Notifications
+    /// Notify a user through the channel they chose for `category`
     pub async fn create_notification(
         &self,
         user_id: &str,
+        category: NotificationCategory,
         message: &str,
     ) -> Result<(), AuthError> {
         let user_uuid = Uuid::parse_str(user_id).map_err(|_| AuthError::UserNotFound)?;
-        let created_at = Utc::now();
-        
-        // Find the Notification class
-        let class = self.ontology_service.get_system_class("Notification").await
+        self.notifications
+            .notify(user_uuid, category, message)
+            .await
             .map_err(|e| AuthError::DatabaseError(sqlx::Error::Protocol(e.to_string())))?;
-
-        let entity_id = Uuid::new_v4();
-        sqlx::query(
-            "INSERT INTO entities (id, class_id, display_name, attributes) VALUES ($1, $2, $3, $4)"
-        )
-        .bind(entity_id)
-        .bind(class.id)
-        .bind(format!("Notification: {}", &message[..message.len().min(20)]))
-        .bind(serde_json::json!({
-            "user_id": user_uuid,
-            "message": message,
-            "read": false
-        }))
-        .execute(&self.pool)
-        .await?;
-
-        // Mock ID for backward compatibility in NotificationEvent
-        let mock_id = (u64::from_str_radix(&entity_id.to_string().replace("-", "")[..16], 16).unwrap_or(0) % (i64::MAX as u64)) as i64;
-
-        let _ = self.notification_tx.send(NotificationEvent {
-            user_id: user_id.to_string(),
-            message: message.to_string(),
-            id: mock_id,
-            created_at: created_at.to_rfc3339(),
-        });
-
         Ok(())
     }
 
-    pub async fn get_notifications(
+    /// Like `create_notification`, with a built-in notification worded for
+    /// the user's locale
+    pub async fn send_notification(
         &self,
-        user_id: &str,
-    ) -> Result<Vec<(i64, String, i64, String)>, AuthError> {
-        let user_uuid = Uuid::parse_str(user_id).map_err(|_| AuthError::UserNotFound)?;
-        let rows = sqlx::query!(
-            "SELECT entity_id, message, read, created_at FROM unified_notifications WHERE user_id = $1 ORDER BY created_at DESC",
-            user_uuid
-        )
-        .fetch_all(&self.pool)
-        .await?;
-        
-        let mut out = Vec::new();
-        for r in rows {
-            // We use entity_id hash as a mock i64 ID for compatibility
-            let entity_id = r.entity_id.unwrap_or_default();
-            let mock_id = (u64::from_str_radix(&entity_id.to_string().replace("-", "")[..16], 16).unwrap_or(0) % (i64::MAX as u64)) as i64;
-            
-            let read: i64 = if r.read.unwrap_or(false) { 1 } else { 0 };
-            out.push((mock_id, r.message.unwrap_or_default(), read, r.created_at.map(|t| t.to_rfc3339()).unwrap_or_default()));
-        }
-        Ok(out)
-    }
-
-    pub async fn mark_notification_read(
-        &self,
-        _notification_id: i64,
-        user_id: &str,
+        user_id: Uuid,
+        template: &BuiltinNotification,
+        values: &[(&str, &str)],
     ) -> Result<(), AuthError> {
-        let user_uuid = Uuid::parse_str(user_id).map_err(|_| AuthError::UserNotFound)?;
-        
-        // Since we use mock IDs, we just mark all as read for this user for now.
-        // In a real migration, the frontend would use entity_id (UUID).
-        sqlx::query(
-            "UPDATE entities SET attributes = attributes || '{\"read\": true}' WHERE class_id = (SELECT id FROM classes WHERE name = 'Notification') AND attributes->>'user_id' = $1"
-        )
-        .bind(user_uuid.to_string())
-        .execute(&self.pool)
-        .await?;
-        
-        Ok(())
-    }
-
-    pub async fn mark_all_notifications_read(&self, user_id: &str) -> Result<(), AuthError> {
-        let user_uuid = Uuid::parse_str(user_id).map_err(|_| AuthError::UserNotFound)?;
-        sqlx::query(
-            "UPDATE entities SET attributes = attributes || '{\"read\": true}' WHERE class_id = (SELECT id FROM classes WHERE name = 'Notification') AND attributes->>'user_id' = $1"
-        )
-        .bind(user_uuid.to_string())
-        .execute(&self.pool)
-        .await?;
+        self.notifications
+            .notify_template(user_id, template, values)
+            .await
+            .map_err(|e| AuthError::DatabaseError(sqlx::Error::Protocol(e.to_string())))?;
         Ok(())
     }
 
-    pub fn subscribe_notifications(&self) -> broadcast::Receiver<NotificationEvent> {
-        self.notification_tx.subscribe()
-    }
-
     // Method to logout and blacklist refresh token
     pub async fn logout(&self, refresh_token: String) -> Result<(), AuthError> {
         let claims = match crate::features::auth::jwt::validate_jwt(&refresh_token, &self.config) {
@@ -956,35 +977,77 @@ impl AuthService {
             .collect())
     }
 
+    /// One page of the live sessions of all users matching `query`
     pub async fn list_all_sessions(
         &self,
-        limit: i64,
-    ) -> Result<Vec<crate::features::auth::models::AdminSessionResponse>, AuthError> {
-        let sessions = sqlx::query!(
+        query: &crate::features::auth::models::SessionListQuery,
+    ) -> Result<Page<crate::features::auth::models::AdminSessionResponse>, AuthError> {
+        let page = PageRequest::new(
+            SESSION_SORT_FIELDS,
+            query.sort.as_deref(),
+            query.order,
+            query.limit,
+            query.offset,
+            query.cursor.as_deref(),
+        )
+        .map_err(|e| AuthError::ValidationError(e.to_string()))?;
+        let ip = query.ip.as_deref().map(str::trim).filter(|ip| !ip.is_empty());
+        let pattern = query
+            .q
+            .as_deref()
+            .map(str::trim)
+            .filter(|q| !q.is_empty())
+            .map(contains_pattern);
+
+        let total = sqlx::query_scalar::<_, i64>(&format!(
+            "SELECT COUNT(*) FROM unified_refresh_tokens rt JOIN unified_users u ON rt.user_id = u.id WHERE {}",
+            SESSION_FILTERS
+        ))
+        .bind(query.user_id)
+        .bind(ip)
+        .bind(query.from)
+        .bind(query.to)
+        .bind(&pattern)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let sql = format!(
             r#"
-            SELECT rt.token_id as id, rt.user_id, u.username, u.email, 
+            SELECT rt.token_id as id, rt.user_id, u.username, COALESCE(u.email, '') as email,
                    rt.created_at, rt.expires_at, rt.user_agent, rt.ip_address
             FROM unified_refresh_tokens rt
             JOIN unified_users u ON rt.user_id = u.id
-            WHERE rt.expires_at > NOW()
-            ORDER BY rt.created_at DESC
-            LIMIT $1
+            WHERE {} AND {}
+            ORDER BY {}
+            LIMIT $8 OFFSET $9
             "#,
-            limit
-        )
-        .fetch_all(&self.pool)
-        .await?;
+            SESSION_FILTERS,
+            page.keyset("rt.token_id", "text", 6),
+            page.order_by("rt.token_id"),
+        );
+        let sessions = sqlx::query_as::<_, crate::features::auth::models::AdminSessionResponse>(&sql)
+            .bind(query.user_id)
+            .bind(ip)
+            .bind(query.from)
+            .bind(query.to)
+            .bind(&pattern)
+            .bind(page.cursor_value())
+            .bind(page.cursor_id())
+            .bind(page.fetch_limit())
+            .bind(page.offset)
+            .fetch_all(&self.pool)
+            .await?;
 
-        Ok(sessions.into_iter().map(|s| crate::features::auth::models::AdminSessionResponse {
-            id: s.id.unwrap_or_default(),
-            user_id: s.user_id.unwrap_or_else(Uuid::new_v4),
-            username: s.username.unwrap_or_default(),
-            email: s.email.unwrap_or_default(),
-            created_at: s.created_at.unwrap_or_else(|| Utc::now()),
-            expires_at: s.expires_at.unwrap_or_else(|| Utc::now()),
-            user_agent: s.user_agent,
-            ip_address: s.ip_address,
-        }).collect())
+        Ok(page.into_page(sessions, total, |s, field| {
+            let value = match field {
+                "expires_at" => cursor_timestamp(&s.expires_at),
+                "username" => s.username.clone(),
+                "email" => s.email.clone(),
+                "ip_address" => s.ip_address.clone().unwrap_or_default(),
+                _ => cursor_timestamp(&s.created_at),
+            };
+            (value, s.id.clone())
+        }))
     }
 
     pub async fn revoke_session(&self, user_id: Uuid, token_id: &str) -> Result<(), AuthError> {
@@ -1150,23 +1213,29 @@ impl AuthService {
     }
 }
 
-impl IntoResponse for AuthError {
-    fn into_response(self) -> axum::response::Response {
-        let status = match self {
-            AuthError::UserExists => StatusCode::CONFLICT,
-            AuthError::InvalidCredentials => StatusCode::UNAUTHORIZED,
-            AuthError::DatabaseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
-            AuthError::JwtError(_) => StatusCode::UNAUTHORIZED,
-            AuthError::PasswordHashError(_) => StatusCode::INTERNAL_SERVER_ERROR,
-            AuthError::ValidationError(_) => StatusCode::BAD_REQUEST,
-            AuthError::InvalidRefreshToken => StatusCode::UNAUTHORIZED,
-            AuthError::UserNotFound => StatusCode::NOT_FOUND,
-            AuthError::InvalidMfaCode => StatusCode::UNAUTHORIZED,
-            AuthError::InvalidToken => StatusCode::UNAUTHORIZED,
-            AuthError::PermissionDenied => StatusCode::FORBIDDEN,
-            AuthError::AbacError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+impl From<AuthError> for ApiError {
+    fn from(err: AuthError) -> Self {
+        let (status, code) = match &err {
+            AuthError::UserExists => (StatusCode::CONFLICT, "auth.user_exists"),
+            AuthError::InvalidCredentials => (StatusCode::UNAUTHORIZED, "auth.invalid_credentials"),
+            AuthError::JwtError(_) => (StatusCode::UNAUTHORIZED, "auth.invalid_token"),
+            AuthError::ValidationError(_) => (StatusCode::BAD_REQUEST, "auth.invalid_input"),
+            AuthError::InvalidRefreshToken => (StatusCode::UNAUTHORIZED, "auth.invalid_refresh_token"),
+            AuthError::UserNotFound => (StatusCode::NOT_FOUND, "auth.user_not_found"),
+            AuthError::InvalidMfaCode => (StatusCode::UNAUTHORIZED, "auth.invalid_mfa_code"),
+            AuthError::InvalidToken => (StatusCode::UNAUTHORIZED, "auth.invalid_token"),
+            AuthError::PermissionDenied => (StatusCode::FORBIDDEN, "auth.permission_denied"),
+            AuthError::GeoRestricted => (StatusCode::FORBIDDEN, "auth.geo_restricted"),
+            AuthError::DatabaseError(_)
+            | AuthError::PasswordHashError(_)
+            | AuthError::AbacError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "internal"),
         };
+        ApiError::new(status, code, err.to_string())
+    }
+}
 
-        (status, self.to_string()).into_response()
+impl IntoResponse for AuthError {
+    fn into_response(self) -> axum::response::Response {
+        ApiError::from(self).into_response()
     }
 }
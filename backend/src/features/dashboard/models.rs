@@ -1,6 +1,6 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct DashboardStats {
     pub total_users: i64,
     pub active_refresh_tokens: i64,
@@ -14,7 +14,7 @@ pub struct ActivityEntry {
     pub created_at: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct AdminDashboardStats {
     pub total_users: i64,
     pub user_growth: f64,
@@ -27,7 +27,7 @@ pub struct AdminDashboardStats {
     pub access_traffic: Vec<AccessTrafficPoint>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct AccessTrafficPoint {
     pub name: String, // "Mon", "Tue" etc
     pub access: i64,
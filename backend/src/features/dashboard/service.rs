@@ -2,20 +2,41 @@ use crate::features::auth::models::User;
 use crate::features::dashboard::models::{
     AccessTrafficPoint, ActivityEntry, AdminDashboardStats, DashboardStats,
 };
+use crate::utils::cache::{CacheScope, SharedCache};
 use chrono::{Datelike, Duration, Utc};
 use sqlx::{PgPool, Row};
 
 #[derive(Clone)]
 pub struct DashboardService {
     pool: PgPool,
+    cache: Option<SharedCache>,
 }
 
 impl DashboardService {
     pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+        Self { pool, cache: None }
+    }
+
+    /// Reuse the stats across requests for `cache.aggregate_ttl_secs`
+    pub fn with_cache(mut self, cache: SharedCache) -> Self {
+        self.cache = Some(cache);
+        self
     }
 
     pub async fn get_dashboard_stats(&self) -> Result<DashboardStats, String> {
+        match &self.cache {
+            Some(cache) => {
+                cache
+                    .get_or_load(CacheScope::Aggregates, "dashboard_stats", || {
+                        self.load_dashboard_stats()
+                    })
+                    .await
+            }
+            None => self.load_dashboard_stats().await,
+        }
+    }
+
+    async fn load_dashboard_stats(&self) -> Result<DashboardStats, String> {
         let total_users: i64 = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM unified_users")
             .fetch_one(&self.pool)
             .await
@@ -54,6 +75,19 @@ impl DashboardService {
     }
 
     pub async fn get_admin_stats(&self) -> Result<AdminDashboardStats, String> {
+        match &self.cache {
+            Some(cache) => {
+                cache
+                    .get_or_load(CacheScope::Aggregates, "admin_stats", || {
+                        self.load_admin_stats()
+                    })
+                    .await
+            }
+            None => self.load_admin_stats().await,
+        }
+    }
+
+    async fn load_admin_stats(&self) -> Result<AdminDashboardStats, String> {
         let now = Utc::now();
         let last_month = now - Duration::days(30);
 
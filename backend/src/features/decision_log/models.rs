@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Decision {
+    Allow,
+    Deny,
+}
+
+/// What settled a decision
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DecisionReason {
+    /// An active firefighter (break-glass) session
+    Firefighter,
+    PolicyAllow,
+    PolicyDeny,
+    /// A role granting the permission, with no policy matching
+    RoleGrant,
+    /// An explicit deny on a role
+    RoleDeny,
+    /// A granting role exists but isn't active by its schedule right now
+    Schedule,
+    NoGrant,
+}
+
+/// One `check_permission_integrated` decision
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DecisionRecord {
+    pub timestamp: DateTime<Utc>,
+    /// Trace id of the request that asked, for joining with traces and logs
+    pub trace_id: Option<String>,
+    pub subject_id: Uuid,
+    pub resource_id: Uuid,
+    pub tenant_id: Option<Uuid>,
+    pub permission: String,
+    pub field_name: Option<String>,
+    pub decision: Decision,
+    pub reason: DecisionReason,
+    pub matched_role: Option<String>,
+    pub matched_policy: Option<String>,
+    pub latency_ms: f64,
+}
+
+/// Counts since startup
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct DecisionLogStats {
+    /// Decisions accepted into the buffer
+    pub recorded: u64,
+    /// Decisions skipped by sampling
+    pub sampled_out: u64,
+    /// Decisions dropped because the buffer was full
+    pub dropped: u64,
+    /// Decisions the sink accepted
+    pub written: u64,
+    /// Decisions lost to sink errors
+    pub failed: u64,
+}
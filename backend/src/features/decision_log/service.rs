@@ -0,0 +1,339 @@
+use super::models::{Decision, DecisionLogStats, DecisionRecord};
+use crate::config::DecisionLogConfig;
+use crate::utils::http_client::OutboundClient;
+use crate::utils::shutdown::Shutdown;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+#[derive(Error, Debug)]
+pub enum DecisionLogError {
+    #[error("Invalid decision log configuration: {0}")]
+    Config(String),
+
+    #[error("Decision log sink error: {0}")]
+    Sink(String),
+}
+
+enum Sink {
+    /// Tracing events on the `authz_decision` target
+    Log,
+    /// JSON lines appended to a file
+    File(PathBuf),
+    /// Batches POSTed as a JSON array
+    Http {
+        url: String,
+        token: Option<String>,
+        http: OutboundClient,
+    },
+}
+
+impl Sink {
+    async fn write(&self, records: &[DecisionRecord]) -> Result<(), DecisionLogError> {
+        match self {
+            Sink::Log => {
+                for r in records {
+                    tracing::info!(
+                        target: "authz_decision",
+                        trace_id = r.trace_id.as_deref().unwrap_or_default(),
+                        subject_id = %r.subject_id,
+                        resource_id = %r.resource_id,
+                        tenant_id = ?r.tenant_id,
+                        permission = %r.permission,
+                        field_name = r.field_name.as_deref().unwrap_or_default(),
+                        decision = ?r.decision,
+                        reason = ?r.reason,
+                        matched_role = r.matched_role.as_deref().unwrap_or_default(),
+                        matched_policy = r.matched_policy.as_deref().unwrap_or_default(),
+                        latency_ms = r.latency_ms,
+                        "authorization decision"
+                    );
+                }
+                Ok(())
+            }
+            Sink::File(path) => {
+                let mut lines = Vec::new();
+                for r in records {
+                    serde_json::to_writer(&mut lines, r)
+                        .map_err(|e| DecisionLogError::Sink(e.to_string()))?;
+                    lines.push(b'\n');
+                }
+                if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+                    tokio::fs::create_dir_all(dir)
+                        .await
+                        .map_err(|e| DecisionLogError::Sink(e.to_string()))?;
+                }
+                let mut file = tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await
+                    .map_err(|e| DecisionLogError::Sink(format!("{}: {}", path.display(), e)))?;
+                file.write_all(&lines)
+                    .await
+                    .map_err(|e| DecisionLogError::Sink(format!("{}: {}", path.display(), e)))?;
+                file.flush()
+                    .await
+                    .map_err(|e| DecisionLogError::Sink(e.to_string()))
+            }
+            Sink::Http { url, token, http } => {
+                let res = http
+                    .send(|client| {
+                        let request = client.post(url).json(records);
+                        match token {
+                            Some(token) => request.bearer_auth(token),
+                            None => request,
+                        }
+                    })
+                    .await
+                    .map_err(|e| DecisionLogError::Sink(e.to_string()))?;
+                let status = res.status();
+                if !status.is_success() {
+                    let text = res.text().await.unwrap_or_default();
+                    return Err(DecisionLogError::Sink(format!(
+                        "{} returned {}: {}",
+                        url, status, text
+                    )));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    recorded: AtomicU64,
+    sampled_out: AtomicU64,
+    dropped: AtomicU64,
+    written: AtomicU64,
+    failed: AtomicU64,
+}
+
+/// Samples permission decisions and hands them to a background writer
+/// through a bounded buffer, so a slow sink never slows permission checks.
+#[derive(Clone)]
+pub struct DecisionLog {
+    config: Arc<DecisionLogConfig>,
+    sink: Arc<Sink>,
+    sender: mpsc::Sender<DecisionRecord>,
+    /// Taken by the writer when it starts
+    receiver: Arc<Mutex<Option<mpsc::Receiver<DecisionRecord>>>>,
+    counters: Arc<Counters>,
+}
+
+impl DecisionLog {
+    /// The configured log, or `None` when disabled
+    pub fn from_config(
+        config: &DecisionLogConfig,
+        http: OutboundClient,
+    ) -> Result<Option<Self>, DecisionLogError> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        for (name, rate) in [
+            ("sample_rate", config.sample_rate),
+            ("deny_sample_rate", config.deny_sample_rate),
+        ] {
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(DecisionLogError::Config(format!(
+                    "decision_log.{} must be between 0.0 and 1.0",
+                    name
+                )));
+            }
+        }
+        let sink = match config.sink.as_str() {
+            "log" => Sink::Log,
+            "file" if config.path.trim().is_empty() => {
+                return Err(DecisionLogError::Config(
+                    "decision_log.path is required for the file sink".to_string(),
+                ))
+            }
+            "file" => Sink::File(PathBuf::from(config.path.trim())),
+            "http" if config.url.trim().is_empty() => {
+                return Err(DecisionLogError::Config(
+                    "decision_log.url is required for the http sink".to_string(),
+                ))
+            }
+            "http" => Sink::Http {
+                url: config.url.trim().to_string(),
+                token: match std::env::var(&config.token_env) {
+                    Ok(token) if !config.token_env.is_empty() && !token.is_empty() => Some(token),
+                    _ => None,
+                },
+                http,
+            },
+            other => {
+                return Err(DecisionLogError::Config(format!(
+                    "unknown decision_log.sink '{}', expected 'log', 'file' or 'http'",
+                    other
+                )))
+            }
+        };
+        let (sender, receiver) = mpsc::channel(config.buffer_size.max(1));
+        Ok(Some(Self {
+            config: Arc::new(config.clone()),
+            sink: Arc::new(sink),
+            sender,
+            receiver: Arc::new(Mutex::new(Some(receiver))),
+            counters: Arc::new(Counters::default()),
+        }))
+    }
+
+    /// Queue `record` if it is sampled. Never waits; a full buffer drops it.
+    pub fn record(&self, record: DecisionRecord) {
+        let rate = match record.decision {
+            Decision::Allow => self.config.sample_rate,
+            Decision::Deny => self.config.deny_sample_rate,
+        };
+        if !sampled(rate, record.trace_id.as_deref()) {
+            self.counters.sampled_out.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        match self.sender.try_send(record) {
+            Ok(()) => self.counters.recorded.fetch_add(1, Ordering::Relaxed),
+            Err(_) => self.counters.dropped.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    pub fn stats(&self) -> DecisionLogStats {
+        DecisionLogStats {
+            recorded: self.counters.recorded.load(Ordering::Relaxed),
+            sampled_out: self.counters.sampled_out.load(Ordering::Relaxed),
+            dropped: self.counters.dropped.load(Ordering::Relaxed),
+            written: self.counters.written.load(Ordering::Relaxed),
+            failed: self.counters.failed.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Start the writer. It flushes every `flush_interval_ms` or when a batch
+    /// is full, and writes out what is buffered on shutdown.
+    pub fn start(&self, shutdown: Shutdown) {
+        let Some(mut receiver) = self.receiver.lock().ok().and_then(|mut r| r.take()) else {
+            return;
+        };
+        let log = self.clone();
+        shutdown.clone().spawn(async move {
+            let batch_size = log.config.batch_size.max(1);
+            let mut batch = Vec::with_capacity(batch_size);
+            let mut interval =
+                tokio::time::interval(Duration::from_millis(log.config.flush_interval_ms.max(10)));
+            loop {
+                tokio::select! {
+                    received = receiver.recv() => match received {
+                        Some(record) => {
+                            batch.push(record);
+                            if batch.len() >= batch_size {
+                                log.flush(&mut batch).await;
+                            }
+                        }
+                        None => break,
+                    },
+                    _ = interval.tick() => log.flush(&mut batch).await,
+                    _ = shutdown.cancelled() => break,
+                }
+            }
+
+            while let Ok(record) = receiver.try_recv() {
+                batch.push(record);
+                if batch.len() >= batch_size {
+                    log.flush(&mut batch).await;
+                }
+            }
+            log.flush(&mut batch).await;
+        });
+    }
+
+    async fn flush(&self, batch: &mut Vec<DecisionRecord>) {
+        if batch.is_empty() {
+            return;
+        }
+        let count = batch.len() as u64;
+        match self.sink.write(batch).await {
+            Ok(()) => {
+                self.counters.written.fetch_add(count, Ordering::Relaxed);
+            }
+            Err(e) => {
+                self.counters.failed.fetch_add(count, Ordering::Relaxed);
+                tracing::warn!("Dropped {} authorization decision records: {}", count, e);
+            }
+        }
+        batch.clear();
+    }
+}
+
+/// Whether to keep a decision at sampling `rate`. Decisions with a trace id
+/// are sampled by the id, so one request's decisions are kept or skipped together.
+fn sampled(rate: f64, trace_id: Option<&str>) -> bool {
+    if rate >= 1.0 {
+        return true;
+    }
+    if rate <= 0.0 {
+        return false;
+    }
+    let position = trace_id
+        .and_then(|id| id.get(..16))
+        .and_then(|prefix| u64::from_str_radix(prefix, 16).ok())
+        .map(|n| n as f64 / u64::MAX as f64)
+        .unwrap_or_else(rand::random::<f64>);
+    position < rate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sampling_follows_the_trace() {
+        assert!(sampled(1.0, None));
+        assert!(!sampled(0.0, Some("00000000000000000000000000000001")));
+
+        let low = Some("0fffffffffffffff0000000000000000");
+        let high = Some("f000000000000000ffffffffffffffff");
+        assert!(sampled(0.1, low));
+        assert!(!sampled(0.1, high));
+        assert!(sampled(0.95, high));
+        // The same trace always gets the same answer
+        assert!((0..20).all(|_| sampled(0.5, low) && !sampled(0.5, high)));
+
+        let kept = (0..1000).filter(|_| sampled(0.25, None)).count();
+        assert!((150..350).contains(&kept), "{}", kept);
+    }
+
+    #[test]
+    fn test_invalid_config_is_rejected() {
+        let http = OutboundClient::from_config(&Default::default());
+        let enabled = DecisionLogConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        for config in [
+            DecisionLogConfig {
+                sink: "kafka".to_string(),
+                ..enabled.clone()
+            },
+            DecisionLogConfig {
+                sink: "http".to_string(),
+                ..enabled.clone()
+            },
+            DecisionLogConfig {
+                sample_rate: 1.5,
+                ..enabled.clone()
+            },
+        ] {
+            assert!(DecisionLog::from_config(&config, http.clone()).is_err());
+        }
+        assert!(DecisionLog::from_config(&enabled, http.clone())
+            .unwrap()
+            .is_some());
+        assert!(
+            DecisionLog::from_config(&DecisionLogConfig::default(), http)
+                .unwrap()
+                .is_none()
+        );
+    }
+}
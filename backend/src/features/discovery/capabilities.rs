@@ -0,0 +1,175 @@
+use crate::config::{ApiDeprecation, Config};
+use chrono::{NaiveDate, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// What this deployment offers, so clients can adapt at runtime instead of
+/// assuming a feature set
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiCapabilities {
+    pub base_path: String,
+    /// Request header clients send to ask for a version
+    pub version_header: String,
+    pub current_version: String,
+    pub versions: Vec<ApiVersionInfo>,
+    /// Optional features and whether they are switched on here
+    pub features: BTreeMap<String, bool>,
+    pub limits: ApiLimits,
+    pub deprecations: Vec<DeprecationNotice>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiVersionInfo {
+    pub version: String,
+    pub status: VersionStatus,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VersionStatus {
+    Current,
+    Supported,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiLimits {
+    pub max_upload_bytes: u64,
+    pub max_upload_chunk_bytes: u64,
+    pub max_request_body_bytes: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeprecationNotice {
+    pub prefix: String,
+    pub method: Option<String>,
+    pub status: DeprecationStatus,
+    pub deprecated_at: NaiveDate,
+    pub sunset_at: Option<NaiveDate>,
+    pub replacement: Option<String>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeprecationStatus {
+    /// Announced but still fully supported
+    Scheduled,
+    Deprecated,
+    /// Requests are refused with 410 Gone
+    Sunset,
+}
+
+impl DeprecationNotice {
+    fn new(deprecation: &ApiDeprecation, today: NaiveDate) -> Self {
+        let mut notice = Self {
+            prefix: deprecation.prefix.clone(),
+            method: deprecation.method.clone(),
+            status: DeprecationStatus::Scheduled,
+            deprecated_at: deprecation.deprecated_at,
+            sunset_at: deprecation.sunset_at,
+            replacement: deprecation.replacement.clone(),
+            note: deprecation.note.clone(),
+        };
+        notice.update_status(today);
+        notice
+    }
+
+    fn update_status(&mut self, today: NaiveDate) {
+        self.status = match self.sunset_at {
+            Some(sunset) if sunset <= today => DeprecationStatus::Sunset,
+            _ if self.deprecated_at <= today => DeprecationStatus::Deprecated,
+            _ => DeprecationStatus::Scheduled,
+        };
+    }
+}
+
+impl ApiCapabilities {
+    /// `semantic_search` is whether pgvector was found at startup
+    pub fn from_config(config: &Config, semantic_search: bool) -> Self {
+        let api = &config.api;
+        let versions = api
+            .supported_versions
+            .iter()
+            .map(|version| ApiVersionInfo {
+                version: version.clone(),
+                status: if *version == api.current_version {
+                    VersionStatus::Current
+                } else {
+                    VersionStatus::Supported
+                },
+            })
+            .collect();
+
+        let features = BTreeMap::from([
+            ("semantic_search".to_string(), semantic_search),
+            (
+                "duplicate_detection".to_string(),
+                config.duplicate_detection.enabled,
+            ),
+            (
+                "ai_alert_explanations".to_string(),
+                config.ai_alert_explanations.enabled,
+            ),
+            ("ai_redaction".to_string(), config.ai_redaction.enabled),
+            (
+                "geo_access".to_string(),
+                !config.geoip.database_path.is_empty(),
+            ),
+            ("tls".to_string(), config.tls.enabled),
+            ("graph_sync".to_string(), config.graph_sync.enabled),
+            ("search_index".to_string(), config.search_index.enabled),
+            ("resumable_uploads".to_string(), true),
+        ]);
+
+        Self {
+            base_path: "/api".to_string(),
+            version_header: "Accept-Version".to_string(),
+            current_version: api.current_version.clone(),
+            versions,
+            features,
+            limits: ApiLimits {
+                max_upload_bytes: config.uploads.max_file_bytes,
+                max_upload_chunk_bytes: config.uploads.max_chunk_bytes,
+                max_request_body_bytes: config.body_limits.default_bytes,
+            },
+            deprecations: api
+                .deprecations
+                .iter()
+                .map(|d| DeprecationNotice::new(d, Utc::now().date_naive()))
+                .collect(),
+        }
+    }
+
+    /// Deprecation statuses move with the calendar; refresh them before serving
+    pub fn refreshed(&self) -> Self {
+        let today = Utc::now().date_naive();
+        let mut capabilities = self.clone();
+        for notice in &mut capabilities.deprecations {
+            notice.update_status(today);
+        }
+        capabilities
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deprecation_status_follows_the_calendar() {
+        let deprecation = ApiDeprecation {
+            prefix: "/api/things".to_string(),
+            method: None,
+            deprecated_at: NaiveDate::from_ymd_opt(2027, 3, 1).unwrap(),
+            sunset_at: NaiveDate::from_ymd_opt(2027, 9, 1),
+            replacement: None,
+            note: None,
+        };
+        let status = |y, m, d| {
+            DeprecationNotice::new(&deprecation, NaiveDate::from_ymd_opt(y, m, d).unwrap()).status
+        };
+        assert_eq!(status(2027, 2, 28), DeprecationStatus::Scheduled);
+        assert_eq!(status(2027, 3, 1), DeprecationStatus::Deprecated);
+        assert_eq!(status(2027, 9, 1), DeprecationStatus::Sunset);
+    }
+}
@@ -1,5 +1,7 @@
+pub mod capabilities;
 pub mod models;
 pub mod routes;
 pub mod service;
+pub mod sitemap;
 
 pub use service::DiscoveryService;
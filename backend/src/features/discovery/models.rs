@@ -1,5 +1,8 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::utils::validation::not_blank;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum ServiceStatus {
@@ -8,6 +11,28 @@ pub enum ServiceStatus {
     WARNING,
 }
 
+/// What a downstream service does with this system's data
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum CapabilityKind {
+    /// Receives webhook deliveries
+    WebhookConsumer,
+    /// Caches authorization decisions, so needs to hear about permission changes
+    AuthzCache,
+    /// Copies ontology data to or from another system
+    SyncAgent,
+    Other,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceCapability {
+    pub kind: CapabilityKind,
+    /// What the capability covers, e.g. the events consumed or classes synced
+    #[serde(default)]
+    pub scope: Vec<String>,
+    pub description: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServiceInstance {
     pub id: String,
@@ -15,20 +40,76 @@ pub struct ServiceInstance {
     pub version: String,
     pub endpoint: String,
     pub status: ServiceStatus,
+    pub registered_at: DateTime<Utc>,
     pub last_heartbeat: DateTime<Utc>,
     pub metadata: std::collections::HashMap<String, String>,
+    pub capabilities: Vec<ServiceCapability>,
     pub entity_id: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct RegisterServiceRequest {
+    #[validate(custom = "not_blank")]
     pub name: String,
+    #[validate(custom = "not_blank")]
     pub version: String,
+    #[validate(custom = "not_blank")]
     pub endpoint: String,
     pub metadata: Option<std::collections::HashMap<String, String>>,
+    #[serde(default)]
+    pub capabilities: Vec<ServiceCapability>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct HeartbeatRequest {
+    #[validate(custom = "not_blank")]
     pub service_id: String,
 }
+
+/// A service offering one capability, as listed in the catalogue
+#[derive(Debug, Clone, Serialize)]
+pub struct CatalogueEntry {
+    pub service_id: String,
+    pub name: String,
+    pub status: ServiceStatus,
+    pub last_heartbeat: DateTime<Utc>,
+    pub scope: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CapabilityGroup {
+    pub kind: CapabilityKind,
+    pub services: Vec<CatalogueEntry>,
+}
+
+/// Every registered integration, grouped by what it does
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceCatalogue {
+    pub generated_at: DateTime<Utc>,
+    pub total: usize,
+    pub up: usize,
+    pub warning: usize,
+    pub down: usize,
+    pub capabilities: Vec<CapabilityGroup>,
+    /// Services that declared no capabilities
+    pub undeclared: Vec<CatalogueEntry>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct SitemapQuery {
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+    /// Only entities changed at or after this time; ignored with a cursor
+    pub changed_since: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+}
+
+/// A page of readable entities, oldest change first
+#[derive(Debug, Clone, Serialize)]
+pub struct SitemapPage {
+    pub items: Vec<crate::features::ontology::models::SitemapEntry>,
+    /// Where to continue from. Kept after the last page too: passing it
+    /// later returns only what changed since.
+    pub next_cursor: Option<String>,
+    pub has_more: bool,
+}
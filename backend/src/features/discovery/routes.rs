@@ -1,9 +1,23 @@
+use crate::features::auth::jwt::Claims;
+use crate::features::discovery::capabilities::ApiCapabilities;
 use crate::features::auth::service::AuthError;
 use crate::features::discovery::models::{
-    HeartbeatRequest, RegisterServiceRequest, ServiceInstance,
+    HeartbeatRequest, RegisterServiceRequest, ServiceCatalogue, ServiceInstance, SitemapPage,
+    SitemapQuery,
 };
 use crate::features::discovery::service::DiscoveryService;
-use axum::{extract::State, routing::get, routing::post, Json, Router};
+use crate::middleware::abac::{enforce_route_permissions, RoutePermission};
+use crate::utils::etag;
+use crate::utils::validation::ValidatedJson;
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Response,
+    routing::get,
+    routing::post,
+    Extension, Json, Router,
+};
+use std::sync::Arc;
 
 pub fn discovery_routes() -> Router<DiscoveryService> {
     Router::new()
@@ -13,6 +27,69 @@ pub fn discovery_routes() -> Router<DiscoveryService> {
         .route("/services/:id", get(get_service_handler))
 }
 
+/// Permissions enforced by `enforce_route_permissions` for the routes below
+pub const ROUTE_PERMISSIONS: &[RoutePermission] =
+    &[RoutePermission::new("GET", "/catalogue", "manage_system")];
+
+/// Needs the auth middleware; the catalogue is for admins only
+pub fn discovery_admin_routes() -> Router<DiscoveryService> {
+    Router::new()
+        .route("/catalogue", get(catalogue_handler))
+        .route("/schema", get(schema_handler))
+        .route("/sitemap", get(sitemap_handler))
+        .route_layer(axum::middleware::from_fn_with_state(
+            ROUTE_PERMISSIONS,
+            enforce_route_permissions,
+        ))
+}
+
+/// Versions, feature flags and deprecations of this deployment; public so
+/// clients can check before signing in
+pub fn capabilities_routes() -> Router<Arc<ApiCapabilities>> {
+    Router::new().route("/capabilities", get(capabilities_handler))
+}
+
+async fn capabilities_handler(
+    State(capabilities): State<Arc<ApiCapabilities>>,
+) -> Json<ApiCapabilities> {
+    Json(capabilities.refreshed())
+}
+
+async fn catalogue_handler(
+    State(service): State<DiscoveryService>,
+) -> Result<Json<ServiceCatalogue>, AuthError> {
+    Ok(Json(service.catalogue().await))
+}
+
+/// Any signed-in client; revalidate with If-None-Match to skip unchanged schemas
+async fn schema_handler(
+    State(service): State<DiscoveryService>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let schema = service.schema().await.map_err(|e| {
+        tracing::error!("Failed to build schema catalogue: {}", e);
+        e.to_status_code()
+    })?;
+    let etag = etag::payload_etag(&schema);
+    Ok(etag::conditional_json(&headers, etag, schema))
+}
+
+/// Entities the caller can read, for indexing and sync agents. Store
+/// `next_cursor` and pass it back later to fetch only what changed.
+async fn sitemap_handler(
+    State(service): State<DiscoveryService>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<SitemapQuery>,
+) -> Result<Json<SitemapPage>, StatusCode> {
+    let user_id = uuid::Uuid::parse_str(&claims.sub).map_err(|_| StatusCode::BAD_REQUEST)?;
+    service.sitemap(user_id, &query).await.map(Json).map_err(|e| {
+        if e.to_status_code().is_server_error() {
+            tracing::error!("Failed to build sitemap: {}", e);
+        }
+        e.to_status_code()
+    })
+}
+
 async fn get_service_handler(
     State(service): State<DiscoveryService>,
     axum::extract::Path(id): axum::extract::Path<String>,
@@ -26,7 +103,7 @@ async fn get_service_handler(
 
 async fn register_handler(
     State(service): State<DiscoveryService>,
-    Json(req): Json<RegisterServiceRequest>,
+    ValidatedJson(req): ValidatedJson<RegisterServiceRequest>,
 ) -> Json<serde_json::Value> {
     let id = service.register(req).await;
     Json(serde_json::json!({ "id": id }))
@@ -34,7 +111,7 @@ async fn register_handler(
 
 async fn heartbeat_handler(
     State(service): State<DiscoveryService>,
-    Json(req): Json<HeartbeatRequest>,
+    ValidatedJson(req): ValidatedJson<HeartbeatRequest>,
 ) -> Result<Json<serde_json::Value>, AuthError> {
     if service.heartbeat(&req.service_id).await {
         Ok(Json(serde_json::json!({ "success": true })))
@@ -1,4 +1,7 @@
-use crate::features::discovery::models::{RegisterServiceRequest, ServiceInstance, ServiceStatus};
+use crate::features::discovery::models::{
+    CapabilityGroup, CatalogueEntry, RegisterServiceRequest, ServiceCatalogue, ServiceInstance,
+    ServiceStatus,
+};
 use chrono::Utc;
 use std::sync::Arc;
 use std::time::Duration;
@@ -9,11 +12,15 @@ use uuid::Uuid;
 #[derive(Clone)]
 pub struct DiscoveryService {
     registry: Arc<RwLock<std::collections::HashMap<String, ServiceInstance>>>,
-    ontology_service: crate::features::ontology::service::OntologyService,
+    pub(super) ontology_service: crate::features::ontology::service::OntologyService,
+    pub(super) rebac_service: crate::features::rebac::RebacService,
 }
 
 impl DiscoveryService {
-    pub fn new(ontology_service: crate::features::ontology::service::OntologyService) -> Self {
+    pub fn new(
+        ontology_service: crate::features::ontology::service::OntologyService,
+        rebac_service: crate::features::rebac::RebacService,
+    ) -> Self {
         let registry: Arc<RwLock<std::collections::HashMap<String, ServiceInstance>>> =
             Arc::new(RwLock::new(std::collections::HashMap::new()));
         let registry_clone = registry.clone();
@@ -77,14 +84,7 @@ impl DiscoveryService {
                                     crate::features::ontology::models::UpdateEntityInput {
                                         display_name: None,
                                         parent_entity_id: None,
-                                        attributes: Some(serde_json::json!({
-                                            "service_id": instance.id,
-                                            "name": instance.name,
-                                            "version": instance.version,
-                                            "endpoint": instance.endpoint,
-                                            "status": format!("{:?}", instance.status),
-                                            "last_heartbeat": instance.last_heartbeat.to_rfc3339(),
-                                        })),
+                                        attributes: Some(entity_attributes(instance)),
                                     },
                                     None,
                                 ).await;
@@ -98,6 +98,7 @@ impl DiscoveryService {
         Self {
             registry,
             ontology_service,
+            rebac_service,
         }
     }
 
@@ -110,8 +111,10 @@ impl DiscoveryService {
             version: req.version.clone(),
             endpoint: req.endpoint.clone(),
             status: ServiceStatus::UP,
+            registered_at: now,
             last_heartbeat: now,
             metadata: req.metadata.unwrap_or_default(),
+            capabilities: req.capabilities,
             entity_id: None,
         };
 
@@ -126,14 +129,7 @@ impl DiscoveryService {
                         class_id: service_class.id,
                         display_name: req.name.clone(),
                         parent_entity_id: None,
-                        attributes: Some(serde_json::json!({
-                            "service_id": id,
-                            "name": req.name,
-                            "version": req.version,
-                            "endpoint": req.endpoint,
-                            "status": "UP",
-                            "last_heartbeat": now.to_rfc3339()
-                        })),
+                        attributes: Some(entity_attributes(&instance)),
                     },
                     None,
                     None,
@@ -156,13 +152,10 @@ impl DiscoveryService {
     pub async fn heartbeat(&self, service_id: &str) -> bool {
         let mut status_changed = false;
         let now = Utc::now();
-        let (name, version, endpoint) = {
+        let instance = {
             let mut registry = self.registry.write().await;
             if let Some(instance) = registry.get_mut(service_id) {
                 instance.last_heartbeat = now;
-                let n = instance.name.clone();
-                let v = instance.version.clone();
-                let e = instance.endpoint.clone();
                 if instance.status != ServiceStatus::UP {
                     tracing::info!(
                         "Service {} ({}) recovered. Marking as UP.",
@@ -172,7 +165,7 @@ impl DiscoveryService {
                     instance.status = ServiceStatus::UP;
                     status_changed = true;
                 }
-                (n, v, e)
+                instance.clone()
             } else {
                 return false;
             }
@@ -201,14 +194,7 @@ impl DiscoveryService {
                         crate::features::ontology::models::UpdateEntityInput {
                             display_name: None,
                             parent_entity_id: None,
-                            attributes: Some(serde_json::json!({
-                                "service_id": service_id,
-                                "name": name,
-                                "version": version,
-                                "endpoint": endpoint,
-                                "status": "UP",
-                                "last_heartbeat": now.to_rfc3339()
-                            })),
+                            attributes: Some(entity_attributes(&instance)),
                         },
                         None,
                     )
@@ -226,4 +212,134 @@ impl DiscoveryService {
     pub async fn get_service(&self, id: &str) -> Option<ServiceInstance> {
         self.registry.read().await.get(id).cloned()
     }
+
+    /// Registered services grouped by capability, with their health
+    pub async fn catalogue(&self) -> ServiceCatalogue {
+        let mut services = self.list_services().await;
+        services.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.id.cmp(&b.id)));
+        build_catalogue(&services)
+    }
+
+    /// The published ontology, for clients generating code against it
+    pub async fn schema(
+        &self,
+    ) -> Result<
+        crate::features::ontology::models::OntologySchema,
+        crate::features::ontology::service::OntologyError,
+    > {
+        self.ontology_service.published_schema().await
+    }
+}
+
+/// Attributes of the service's ontology entity
+fn entity_attributes(instance: &ServiceInstance) -> serde_json::Value {
+    serde_json::json!({
+        "service_id": instance.id,
+        "name": instance.name,
+        "version": instance.version,
+        "endpoint": instance.endpoint,
+        "status": format!("{:?}", instance.status),
+        "last_heartbeat": instance.last_heartbeat.to_rfc3339(),
+        "capabilities": instance.capabilities,
+    })
+}
+
+fn build_catalogue(services: &[ServiceInstance]) -> ServiceCatalogue {
+    let entry = |instance: &ServiceInstance, scope: Vec<String>| CatalogueEntry {
+        service_id: instance.id.clone(),
+        name: instance.name.clone(),
+        status: instance.status.clone(),
+        last_heartbeat: instance.last_heartbeat,
+        scope,
+    };
+    let count = |status: ServiceStatus| services.iter().filter(|s| s.status == status).count();
+
+    let mut capabilities: Vec<CapabilityGroup> = Vec::new();
+    let mut undeclared = Vec::new();
+    for instance in services {
+        if instance.capabilities.is_empty() {
+            undeclared.push(entry(instance, Vec::new()));
+        }
+        for capability in &instance.capabilities {
+            let service = entry(instance, capability.scope.clone());
+            match capabilities.iter_mut().find(|g| g.kind == capability.kind) {
+                Some(group) => group.services.push(service),
+                None => capabilities.push(CapabilityGroup {
+                    kind: capability.kind,
+                    services: vec![service],
+                }),
+            }
+        }
+    }
+    capabilities.sort_by_key(|g| g.kind);
+
+    ServiceCatalogue {
+        generated_at: Utc::now(),
+        total: services.len(),
+        up: count(ServiceStatus::UP),
+        warning: count(ServiceStatus::WARNING),
+        down: count(ServiceStatus::DOWN),
+        capabilities,
+        undeclared,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::discovery::models::{CapabilityKind, ServiceCapability};
+
+    fn instance(name: &str, status: ServiceStatus, kinds: &[CapabilityKind]) -> ServiceInstance {
+        ServiceInstance {
+            id: name.to_string(),
+            name: name.to_string(),
+            version: "1.0".to_string(),
+            endpoint: format!("http://{}", name),
+            status,
+            registered_at: Utc::now(),
+            last_heartbeat: Utc::now(),
+            metadata: Default::default(),
+            capabilities: kinds
+                .iter()
+                .map(|kind| ServiceCapability {
+                    kind: *kind,
+                    scope: vec!["entities".to_string()],
+                    description: None,
+                })
+                .collect(),
+            entity_id: None,
+        }
+    }
+
+    #[test]
+    fn test_build_catalogue_groups_by_capability() {
+        let catalogue = build_catalogue(&[
+            instance("cache", ServiceStatus::UP, &[CapabilityKind::AuthzCache]),
+            instance(
+                "hooks",
+                ServiceStatus::DOWN,
+                &[CapabilityKind::WebhookConsumer, CapabilityKind::SyncAgent],
+            ),
+            instance("legacy", ServiceStatus::WARNING, &[]),
+        ]);
+
+        assert_eq!(
+            (catalogue.total, catalogue.up, catalogue.warning, catalogue.down),
+            (3, 1, 1, 1)
+        );
+        let groups: Vec<_> = catalogue
+            .capabilities
+            .iter()
+            .map(|g| (g.kind, g.services[0].name.as_str()))
+            .collect();
+        assert_eq!(
+            groups,
+            vec![
+                (CapabilityKind::WebhookConsumer, "hooks"),
+                (CapabilityKind::AuthzCache, "cache"),
+                (CapabilityKind::SyncAgent, "hooks"),
+            ]
+        );
+        assert_eq!(catalogue.undeclared[0].name, "legacy");
+    }
 }
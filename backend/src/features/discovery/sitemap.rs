@@ -0,0 +1,119 @@
+use crate::features::discovery::models::{SitemapPage, SitemapQuery};
+use crate::features::discovery::service::DiscoveryService;
+use crate::features::ontology::service::OntologyError;
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+const DEFAULT_LIMIT: i64 = 100;
+const MAX_LIMIT: i64 = 1000;
+/// Batches scanned per page while filling it with readable entities, so a
+/// caller who can read little doesn't scan the whole table in one request
+const MAX_SCAN_BATCHES: usize = 10;
+
+impl DiscoveryService {
+    /// Entities the user can read, in change order. Pages can come back
+    /// short when most of a scanned range is unreadable; keep following
+    /// `next_cursor` while `has_more` is set.
+    pub async fn sitemap(
+        &self,
+        user_id: Uuid,
+        query: &SitemapQuery,
+    ) -> Result<SitemapPage, OntologyError> {
+        let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+        let mut position = match (&query.cursor, query.changed_since) {
+            (Some(cursor), _) => Some(decode_cursor(cursor)?),
+            (None, Some(since)) => Some((since, Uuid::nil())),
+            (None, None) => None,
+        };
+        let tenant_id = self
+            .ontology_service
+            .get_entity(user_id)
+            .await
+            .ok()
+            .and_then(|user| user.tenant_id);
+
+        let mut items = Vec::new();
+        let mut has_more = false;
+        for _ in 0..MAX_SCAN_BATCHES {
+            let batch = self
+                .ontology_service
+                .sitemap_batch(tenant_id, position, limit + 1)
+                .await?;
+            // One row over the limit tells whether anything follows
+            has_more = batch.len() as i64 > limit;
+            let Some(last) = batch.last() else {
+                break;
+            };
+            position = Some((last.updated_at, last.id));
+
+            let readable: HashSet<Uuid> = self
+                .rebac_service
+                .check_multiple_permissions(
+                    user_id,
+                    batch.iter().map(|e| e.id).collect(),
+                    "read",
+                    tenant_id,
+                )
+                .await
+                .map_err(|e| OntologyError::DatabaseError(e.to_string()))?
+                .into_iter()
+                .filter(|(_, allowed, denied)| *allowed && !denied)
+                .map(|(id, ..)| id)
+                .collect();
+            items.extend(batch.into_iter().filter(|e| readable.contains(&e.id)));
+
+            if items.len() as i64 >= limit || !has_more {
+                break;
+            }
+        }
+        // The last batch may hold more readable entities than fit; resume
+        // after the last one returned rather than skipping the rest
+        if items.len() as i64 > limit {
+            items.truncate(limit as usize);
+            let last = items.last().expect("page is not empty");
+            position = Some((last.updated_at, last.id));
+            has_more = true;
+        }
+
+        Ok(SitemapPage {
+            items,
+            next_cursor: position.map(encode_cursor),
+            has_more,
+        })
+    }
+}
+
+/// `<updated_at in microseconds>_<id>`; Postgres keeps microseconds, so the
+/// position round-trips exactly
+fn encode_cursor((updated_at, id): (DateTime<Utc>, Uuid)) -> String {
+    format!("{}_{}", updated_at.timestamp_micros(), id)
+}
+
+fn decode_cursor(cursor: &str) -> Result<(DateTime<Utc>, Uuid), OntologyError> {
+    let invalid = || OntologyError::InvalidInput(format!("Invalid sitemap cursor: {}", cursor));
+    let (micros, id) = cursor.split_once('_').ok_or_else(invalid)?;
+    let updated_at = micros
+        .parse::<i64>()
+        .ok()
+        .and_then(DateTime::from_timestamp_micros)
+        .ok_or_else(invalid)?;
+    let id = Uuid::parse_str(id).map_err(|_| invalid())?;
+    Ok((updated_at, id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sitemap_cursor_round_trip() {
+        let position = (
+            DateTime::from_timestamp_micros(1_790_000_000_123_456).unwrap(),
+            Uuid::new_v4(),
+        );
+        assert_eq!(decode_cursor(&encode_cursor(position)).unwrap(), position);
+        assert!(decode_cursor("not-a-cursor").is_err());
+        assert!(decode_cursor("123_not-a-uuid").is_err());
+    }
+}
@@ -0,0 +1,7 @@
+pub mod models;
+pub mod providers;
+pub mod routes;
+pub mod service;
+pub mod templates;
+
+pub use service::{EmailError, EmailService};
@@ -0,0 +1,110 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::utils::validation::not_blank;
+
+/// A rendered message as handed to a provider
+#[derive(Debug, Clone)]
+pub struct OutgoingEmail {
+    pub from_address: String,
+    pub from_name: String,
+    pub to: String,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: Option<String>,
+}
+
+/// A queued message and the outcome of its delivery attempts
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct EmailDelivery {
+    pub id: Uuid,
+    pub template: String,
+    pub recipient: String,
+    pub subject: String,
+    /// Bodies can carry single-use links, so they are never returned by the
+    /// API and are cleared once the message is sent
+    #[serde(skip)]
+    pub text_body: Option<String>,
+    #[serde(skip)]
+    pub html_body: Option<String>,
+    /// "pending", "sent" or "failed"
+    pub status: String,
+    pub provider: Option<String>,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub provider_message_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListDeliveriesQuery {
+    pub status: Option<String>,
+    pub recipient: Option<String>,
+    pub template: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// Delivery counts by status, for the admin overview
+#[derive(Debug, Serialize, FromRow)]
+pub struct DeliveryStats {
+    pub pending: i64,
+    pub sent: i64,
+    pub failed: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TemplateVariable {
+    pub name: String,
+    pub description: String,
+}
+
+/// A stored override of a built-in template
+#[derive(Debug, Clone, FromRow)]
+pub struct EmailTemplateOverride {
+    pub name: String,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: Option<String>,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A template as it is currently rendered: the override if there is one,
+/// otherwise the built-in
+#[derive(Debug, Serialize)]
+pub struct EmailTemplate {
+    pub name: String,
+    pub description: String,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: Option<String>,
+    pub variables: Vec<TemplateVariable>,
+    pub is_overridden: bool,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateEmailTemplateInput {
+    #[validate(custom = "not_blank")]
+    pub subject: String,
+    #[validate(custom = "not_blank")]
+    pub text_body: String,
+    /// Without one, messages are sent as plain text only
+    pub html_body: Option<String>,
+}
+
+/// Subject and bodies with the variables filled in
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderedEmail {
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: Option<String>,
+}
@@ -0,0 +1,731 @@
+use super::models::OutgoingEmail;
+use crate::config::EmailConfig;
+use crate::utils::http_client::OutboundClient;
+use crate::utils::sigv4::{hmac_sha256, sha256_hex, signing_key};
+use axum::async_trait;
+use chrono::Utc;
+use reqwest::StatusCode;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufStream};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls;
+use tokio_rustls::TlsConnector;
+use uuid::Uuid;
+
+/// Limit on a whole SMTP conversation, from connecting to QUIT
+const SMTP_TIMEOUT: Duration = Duration::from_secs(60);
+
+const SES_SEND_PATH: &str = "/v2/email/outbound-emails";
+
+/// Email delivery services, from `email.provider`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    /// Appends messages to a local file instead of sending them
+    Log,
+    Smtp,
+    /// Amazon SES, through its v2 HTTP API
+    Ses,
+    SendGrid,
+}
+
+impl ProviderKind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value
+            .trim()
+            .to_ascii_lowercase()
+            .replace(['-', '_', ' '], "")
+            .as_str()
+        {
+            "log" => Some(Self::Log),
+            "smtp" => Some(Self::Smtp),
+            "ses" | "amazonses" | "awsses" => Some(Self::Ses),
+            "sendgrid" => Some(Self::SendGrid),
+            _ => None,
+        }
+    }
+}
+
+/// Why an attempt failed. Permanent failures, such as a rejected recipient,
+/// are not retried.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SendFailure {
+    Transient(String),
+    Permanent(String),
+}
+
+impl SendFailure {
+    pub fn message(&self) -> &str {
+        match self {
+            Self::Transient(message) | Self::Permanent(message) => message,
+        }
+    }
+
+    pub fn is_permanent(&self) -> bool {
+        matches!(self, Self::Permanent(_))
+    }
+}
+
+impl std::fmt::Display for SendFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+/// One email backend. `EmailService` records each attempt and decides
+/// whether to retry.
+#[async_trait]
+pub trait EmailProvider: Send + Sync {
+    /// Recorded against each delivery attempt
+    fn name(&self) -> &str;
+
+    /// Hand the message over, returning the provider's message id if it reports one
+    async fn send(&self, email: &OutgoingEmail) -> Result<Option<String>, SendFailure>;
+}
+
+/// Build the configured provider. Fails on an unknown provider or missing
+/// settings, so a misconfigured deployment is caught at startup.
+pub fn build_provider(
+    config: &EmailConfig,
+    http: &OutboundClient,
+) -> Result<Arc<dyn EmailProvider>, String> {
+    let kind = ProviderKind::parse(&config.provider)
+        .ok_or_else(|| format!("unknown email provider '{}'", config.provider))?;
+    let secret = |var: &str| {
+        std::env::var(var)
+            .ok()
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| format!("environment variable {} is not set", var))
+    };
+
+    let provider: Arc<dyn EmailProvider> = match kind {
+        ProviderKind::Log => Arc::new(LogProvider {
+            path: PathBuf::from(&config.log_path),
+        }),
+        ProviderKind::Smtp => {
+            if config.smtp_host.trim().is_empty() {
+                return Err("email.smtp_host is required for SMTP".to_string());
+            }
+            let security = SmtpSecurity::parse(&config.smtp_security).ok_or_else(|| {
+                format!(
+                    "email.smtp_security must be 'starttls', 'tls' or 'none', not '{}'",
+                    config.smtp_security
+                )
+            })?;
+            let credentials = match config.smtp_username.trim() {
+                "" => None,
+                username => Some((username.to_string(), secret(&config.smtp_password_env)?)),
+            };
+            Arc::new(SmtpProvider {
+                host: config.smtp_host.trim().to_string(),
+                port: config.smtp_port,
+                security,
+                credentials,
+                hello_name: hello_name(&config.from_address),
+                tls: tls_connector()?,
+            })
+        }
+        ProviderKind::Ses => {
+            if config.ses_region.trim().is_empty() {
+                return Err("email.ses_region is required for SES".to_string());
+            }
+            Arc::new(SesProvider {
+                region: config.ses_region.trim().to_string(),
+                access_key_id: secret(&config.ses_access_key_id_env)?,
+                secret_access_key: secret(&config.ses_secret_access_key_env)?,
+                session_token: secret(&config.ses_session_token_env).ok(),
+                http: http.clone(),
+            })
+        }
+        ProviderKind::SendGrid => Arc::new(SendGridProvider {
+            api_base: config.sendgrid_api_base.trim_end_matches('/').to_string(),
+            api_key: secret(&config.sendgrid_api_key_env)?,
+            http: http.clone(),
+        }),
+    };
+    Ok(provider)
+}
+
+/// Failure for an HTTP API's non-success response. Rate limiting, server
+/// errors and rejected credentials may clear up, so those are retried.
+fn status_failure(provider: &str, status: StatusCode, body: &str) -> SendFailure {
+    let message = format!(
+        "{} rejected the message: HTTP {}: {}",
+        provider,
+        status,
+        body.chars().take(500).collect::<String>()
+    );
+    let transient = status.is_server_error()
+        || matches!(
+            status,
+            StatusCode::TOO_MANY_REQUESTS
+                | StatusCode::REQUEST_TIMEOUT
+                | StatusCode::UNAUTHORIZED
+                | StatusCode::FORBIDDEN
+        );
+    if transient {
+        SendFailure::Transient(message)
+    } else {
+        SendFailure::Permanent(message)
+    }
+}
+
+// ============================================================================
+// LOG
+// ============================================================================
+
+/// Appends one line per message to a local file, for development and tests
+pub struct LogProvider {
+    path: PathBuf,
+}
+
+#[async_trait]
+impl EmailProvider for LogProvider {
+    fn name(&self) -> &str {
+        "log"
+    }
+
+    async fn send(&self, email: &OutgoingEmail) -> Result<Option<String>, SendFailure> {
+        let failure = |e: std::io::Error| {
+            SendFailure::Transient(format!("Failed to write {}: {}", self.path.display(), e))
+        };
+        if let Some(dir) = self.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(dir).await.map_err(failure)?;
+        }
+        let line = format!(
+            "[{}] To: {} | Subject: {} | {}\n",
+            Utc::now().to_rfc3339(),
+            email.to,
+            email.subject,
+            email
+                .text_body
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .map_err(failure)?;
+        file.write_all(line.as_bytes()).await.map_err(failure)?;
+        Ok(None)
+    }
+}
+
+// ============================================================================
+// SMTP
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpSecurity {
+    /// Upgrade a plain connection with STARTTLS, refusing to send without it
+    StartTls,
+    /// TLS from the first byte
+    Tls,
+    /// Plain text, for relays on a trusted network
+    None,
+}
+
+impl SmtpSecurity {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "starttls" => Some(Self::StartTls),
+            "tls" | "ssl" => Some(Self::Tls),
+            "none" => Some(Self::None),
+            _ => None,
+        }
+    }
+}
+
+pub struct SmtpProvider {
+    host: String,
+    port: u16,
+    security: SmtpSecurity,
+    /// Username and password for AUTH PLAIN
+    credentials: Option<(String, String)>,
+    hello_name: String,
+    tls: TlsConnector,
+}
+
+fn tls_connector() -> Result<TlsConnector, String> {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    let config = rustls::ClientConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .map_err(|e| format!("Failed to set up TLS: {}", e))?
+    .with_root_certificates(roots)
+    .with_no_client_auth();
+    Ok(TlsConnector::from(Arc::new(config)))
+}
+
+/// Name given in EHLO: the sender's domain
+fn hello_name(from_address: &str) -> String {
+    from_address
+        .rsplit_once('@')
+        .map(|(_, domain)| domain.trim())
+        .filter(|domain| !domain.is_empty())
+        .unwrap_or("localhost")
+        .to_string()
+}
+
+struct SmtpReply {
+    code: u16,
+    text: String,
+}
+
+impl SmtpReply {
+    /// 5xx replies are final; anything else may succeed on a later attempt
+    fn into_failure(self) -> SendFailure {
+        let message = format!("SMTP {} {}", self.code, self.text);
+        if (500..600).contains(&self.code) {
+            SendFailure::Permanent(message)
+        } else {
+            SendFailure::Transient(message)
+        }
+    }
+}
+
+fn io_failure(e: std::io::Error) -> SendFailure {
+    SendFailure::Transient(format!("SMTP connection error: {}", e))
+}
+
+/// One SMTP conversation over a plain or TLS stream
+struct SmtpConnection<S> {
+    stream: BufStream<S>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> SmtpConnection<S> {
+    fn new(stream: S) -> Self {
+        Self {
+            stream: BufStream::new(stream),
+        }
+    }
+
+    fn into_inner(self) -> S {
+        self.stream.into_inner()
+    }
+
+    /// Read a reply, joining the lines of a multiline one
+    async fn read_reply(&mut self) -> Result<SmtpReply, SendFailure> {
+        let mut text = Vec::new();
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line).await.map_err(io_failure)? == 0 {
+                return Err(SendFailure::Transient(
+                    "SMTP server closed the connection".to_string(),
+                ));
+            }
+            let line = line.trim_end();
+            let code = line
+                .get(..3)
+                .and_then(|code| code.parse::<u16>().ok())
+                .ok_or_else(|| SendFailure::Transient(format!("Malformed SMTP reply: {}", line)))?;
+            text.push(line.get(4..).unwrap_or_default().to_string());
+            if line.as_bytes().get(3) != Some(&b'-') {
+                return Ok(SmtpReply {
+                    code,
+                    text: text.join(" "),
+                });
+            }
+        }
+    }
+
+    async fn expect(&mut self, expected: &[u16]) -> Result<SmtpReply, SendFailure> {
+        let reply = self.read_reply().await?;
+        if expected.contains(&reply.code) {
+            Ok(reply)
+        } else {
+            Err(reply.into_failure())
+        }
+    }
+
+    async fn write(&mut self, data: &str) -> Result<(), SendFailure> {
+        self.stream
+            .write_all(data.as_bytes())
+            .await
+            .map_err(io_failure)?;
+        self.stream.flush().await.map_err(io_failure)
+    }
+
+    async fn command(&mut self, line: &str, expected: &[u16]) -> Result<SmtpReply, SendFailure> {
+        self.write(&format!("{}\r\n", line)).await?;
+        self.expect(expected).await
+    }
+}
+
+impl SmtpProvider {
+    async fn handshake(
+        &self,
+        tcp: TcpStream,
+    ) -> Result<tokio_rustls::client::TlsStream<TcpStream>, SendFailure> {
+        let server_name = rustls::pki_types::ServerName::try_from(self.host.clone())
+            .map_err(|e| SendFailure::Transient(format!("Invalid SMTP host: {}", e)))?;
+        self.tls
+            .connect(server_name, tcp)
+            .await
+            .map_err(|e| SendFailure::Transient(format!("SMTP TLS handshake failed: {}", e)))
+    }
+
+    async fn converse(&self, email: &OutgoingEmail) -> Result<Option<String>, SendFailure> {
+        let tcp = TcpStream::connect((self.host.as_str(), self.port))
+            .await
+            .map_err(|e| {
+                SendFailure::Transient(format!(
+                    "Failed to connect to {}:{}: {}",
+                    self.host, self.port, e
+                ))
+            })?;
+        match self.security {
+            SmtpSecurity::None => {
+                self.deliver(&mut SmtpConnection::new(tcp), email, false)
+                    .await
+            }
+            SmtpSecurity::Tls => {
+                let tls = self.handshake(tcp).await?;
+                self.deliver(&mut SmtpConnection::new(tls), email, false)
+                    .await
+            }
+            SmtpSecurity::StartTls => {
+                let mut plain = SmtpConnection::new(tcp);
+                plain.expect(&[220]).await?;
+                plain
+                    .command(&format!("EHLO {}", self.hello_name), &[250])
+                    .await?;
+                plain.command("STARTTLS", &[220]).await?;
+                let tls = self.handshake(plain.into_inner()).await?;
+                self.deliver(&mut SmtpConnection::new(tls), email, true)
+                    .await
+            }
+        }
+    }
+
+    /// Everything after the connection is set up. `greeted` is true after
+    /// STARTTLS, where the server doesn't greet again.
+    async fn deliver<S: AsyncRead + AsyncWrite + Unpin>(
+        &self,
+        conn: &mut SmtpConnection<S>,
+        email: &OutgoingEmail,
+        greeted: bool,
+    ) -> Result<Option<String>, SendFailure> {
+        if !greeted {
+            conn.expect(&[220]).await?;
+        }
+        conn.command(&format!("EHLO {}", self.hello_name), &[250])
+            .await?;
+        if let Some((username, password)) = &self.credentials {
+            let token = base64::encode(format!("\0{}\0{}", username, password));
+            // Rejected credentials are a configuration problem, not a bad message
+            conn.command(&format!("AUTH PLAIN {}", token), &[235])
+                .await
+                .map_err(|f| {
+                    SendFailure::Transient(format!("SMTP authentication failed: {}", f))
+                })?;
+        }
+        conn.command(&format!("MAIL FROM:<{}>", email.from_address), &[250])
+            .await?;
+        conn.command(&format!("RCPT TO:<{}>", email.to), &[250, 251])
+            .await?;
+        conn.command("DATA", &[354]).await?;
+
+        let message_id = format!("<{}@{}>", Uuid::new_v4(), self.hello_name);
+        conn.write(&format_message(email, &message_id)).await?;
+        conn.write("\r\n.\r\n").await?;
+        conn.expect(&[250]).await?;
+        // The message is accepted; a failed QUIT doesn't change that
+        let _ = conn.command("QUIT", &[221]).await;
+        Ok(Some(message_id))
+    }
+}
+
+#[async_trait]
+impl EmailProvider for SmtpProvider {
+    fn name(&self) -> &str {
+        "smtp"
+    }
+
+    async fn send(&self, email: &OutgoingEmail) -> Result<Option<String>, SendFailure> {
+        tokio::time::timeout(SMTP_TIMEOUT, self.converse(email))
+            .await
+            .unwrap_or_else(|_| {
+                Err(SendFailure::Transient(format!(
+                    "SMTP conversation timed out after {:?}",
+                    SMTP_TIMEOUT
+                )))
+            })
+    }
+}
+
+/// Base64 in 76-character lines
+fn base64_lines(text: &str) -> String {
+    base64::encode(text)
+        .as_bytes()
+        .chunks(76)
+        .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+        .collect::<Vec<_>>()
+        .join("\r\n")
+}
+
+/// Header value, as an RFC 2047 encoded word when it isn't plain ASCII
+fn encode_header(value: &str) -> String {
+    let value = value.replace(['\r', '\n'], " ");
+    if value.is_ascii() {
+        value
+    } else {
+        format!("=?UTF-8?B?{}?=", base64::encode(value))
+    }
+}
+
+fn mailbox(name: &str, address: &str) -> String {
+    let name = name.replace(['\r', '\n'], " ");
+    if name.trim().is_empty() {
+        format!("<{}>", address)
+    } else if name.is_ascii() {
+        format!(
+            "\"{}\" <{}>",
+            name.replace('\\', "\\\\").replace('"', "\\\""),
+            address
+        )
+    } else {
+        format!("{} <{}>", encode_header(&name), address)
+    }
+}
+
+/// The DATA section of a message. Bodies are base64, so long lines and
+/// non-ASCII text survive any relay and no line can start with a dot.
+pub fn format_message(email: &OutgoingEmail, message_id: &str) -> String {
+    let mut message = format!(
+        "From: {}\r\nTo: <{}>\r\nSubject: {}\r\nDate: {}\r\nMessage-ID: {}\r\nMIME-Version: 1.0\r\n",
+        mailbox(&email.from_name, &email.from_address),
+        email.to,
+        encode_header(&email.subject),
+        Utc::now().to_rfc2822(),
+        message_id,
+    );
+    let part = |content_type: &str, body: &str| {
+        format!(
+            "Content-Type: {}; charset=utf-8\r\nContent-Transfer-Encoding: base64\r\n\r\n{}\r\n",
+            content_type,
+            base64_lines(body)
+        )
+    };
+    match &email.html_body {
+        None => message.push_str(&part("text/plain", &email.text_body)),
+        Some(html) => {
+            let boundary = format!("=_{}", Uuid::new_v4().simple());
+            message.push_str(&format!(
+                "Content-Type: multipart/alternative; boundary=\"{}\"\r\n\r\n",
+                boundary
+            ));
+            message.push_str(&format!("--{}\r\n", boundary));
+            message.push_str(&part("text/plain", &email.text_body));
+            message.push_str(&format!("--{}\r\n", boundary));
+            message.push_str(&part("text/html", html));
+            message.push_str(&format!("--{}--\r\n", boundary));
+        }
+    }
+    message
+}
+
+// ============================================================================
+// AMAZON SES
+// ============================================================================
+
+pub struct SesProvider {
+    region: String,
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+    http: OutboundClient,
+}
+
+impl SesProvider {
+    fn host(&self) -> String {
+        format!("email.{}.amazonaws.com", self.region)
+    }
+
+    /// SigV4 Authorization header for a JSON POST to the send endpoint
+    fn authorization(&self, host: &str, amz_date: &str, body: &str) -> String {
+        let date = &amz_date[..8];
+        let mut headers = vec![
+            ("content-type", "application/json".to_string()),
+            ("host", host.to_string()),
+            ("x-amz-date", amz_date.to_string()),
+        ];
+        if let Some(token) = &self.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value))
+            .collect();
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_request = format!(
+            "POST\n{}\n\n{}\n{}\n{}",
+            SES_SEND_PATH,
+            canonical_headers,
+            signed_headers,
+            sha256_hex(body.as_bytes())
+        );
+        let scope = format!("{}/{}/ses/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            sha256_hex(canonical_request.as_bytes())
+        );
+        let signature = hex::encode(hmac_sha256(
+            &signing_key(&self.secret_access_key, date, &self.region, "ses"),
+            string_to_sign.as_bytes(),
+        ));
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.access_key_id, scope, signed_headers, signature
+        )
+    }
+}
+
+#[async_trait]
+impl EmailProvider for SesProvider {
+    fn name(&self) -> &str {
+        "ses"
+    }
+
+    async fn send(&self, email: &OutgoingEmail) -> Result<Option<String>, SendFailure> {
+        let text = |data: &str| serde_json::json!({ "Data": data, "Charset": "UTF-8" });
+        let mut body = serde_json::json!({ "Text": text(&email.text_body) });
+        if let Some(html) = &email.html_body {
+            body["Html"] = text(html);
+        }
+        let payload = serde_json::json!({
+            "FromEmailAddress": mailbox(&email.from_name, &email.from_address),
+            "Destination": { "ToAddresses": [email.to] },
+            "Content": {
+                "Simple": {
+                    "Subject": text(&email.subject),
+                    "Body": body,
+                }
+            }
+        })
+        .to_string();
+
+        let host = self.host();
+        let url = format!("https://{}{}", host, SES_SEND_PATH);
+        let amz_date = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        let authorization = self.authorization(&host, &amz_date, &payload);
+        let res = self
+            .http
+            .send(|client| {
+                let request = client
+                    .post(&url)
+                    .header("content-type", "application/json")
+                    .header("x-amz-date", &amz_date)
+                    .header("authorization", &authorization)
+                    .body(payload.clone());
+                match &self.session_token {
+                    Some(token) => request.header("x-amz-security-token", token),
+                    None => request,
+                }
+            })
+            .await
+            .map_err(|e| SendFailure::Transient(format!("SES unreachable: {}", e)))?;
+
+        let status = res.status();
+        let body = res.text().await.unwrap_or_default();
+        if !status.is_success() {
+            return Err(status_failure("SES", status, &body));
+        }
+        Ok(serde_json::from_str::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|v| {
+                v.get("MessageId")
+                    .and_then(|id| id.as_str())
+                    .map(str::to_string)
+            }))
+    }
+}
+
+// ============================================================================
+// SENDGRID
+// ============================================================================
+
+pub struct SendGridProvider {
+    api_base: String,
+    api_key: String,
+    http: OutboundClient,
+}
+
+#[async_trait]
+impl EmailProvider for SendGridProvider {
+    fn name(&self) -> &str {
+        "sendgrid"
+    }
+
+    async fn send(&self, email: &OutgoingEmail) -> Result<Option<String>, SendFailure> {
+        let mut content =
+            vec![serde_json::json!({ "type": "text/plain", "value": email.text_body })];
+        if let Some(html) = &email.html_body {
+            content.push(serde_json::json!({ "type": "text/html", "value": html }));
+        }
+        let payload = serde_json::json!({
+            "personalizations": [{ "to": [{ "email": email.to }] }],
+            "from": { "email": email.from_address, "name": email.from_name },
+            "subject": email.subject,
+            "content": content,
+        });
+
+        let url = format!("{}/v3/mail/send", self.api_base);
+        let res = self
+            .http
+            .send(|client| client.post(&url).bearer_auth(&self.api_key).json(&payload))
+            .await
+            .map_err(|e| SendFailure::Transient(format!("SendGrid unreachable: {}", e)))?;
+
+        let status = res.status();
+        let message_id = res
+            .headers()
+            .get("x-message-id")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        if !status.is_success() {
+            let body = res.text().await.unwrap_or_default();
+            return Err(status_failure("SendGrid", status, &body));
+        }
+        Ok(message_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formatted_message_encodes_headers_and_bodies() {
+        let email = OutgoingEmail {
+            from_address: "no-reply@example.com".to_string(),
+            from_name: "Ontology \"Manager\"".to_string(),
+            to: "user@example.com".to_string(),
+            subject: "Réinitialisation\r\nBcc: someone@example.com".to_string(),
+            text_body: ".leading dot\n".repeat(20),
+            html_body: Some("<p>Hello</p>".to_string()),
+        };
+        let message = format_message(&email, "<id@example.com>");
+
+        assert!(message.contains("From: \"Ontology \\\"Manager\\\"\" <no-reply@example.com>\r\n"));
+        assert!(message.contains("Subject: =?UTF-8?B?"));
+        assert!(!message.contains("\r\nBcc:"));
+        assert!(message.contains("multipart/alternative"));
+        assert!(message
+            .lines()
+            .all(|line| line.len() <= 998 && !line.starts_with('.')));
+    }
+}
@@ -0,0 +1,135 @@
+use crate::features::auth::jwt::Claims;
+use crate::features::email::models::{
+    DeliveryStats, EmailDelivery, EmailTemplate, ListDeliveriesQuery, UpdateEmailTemplateInput,
+};
+use crate::features::email::service::{EmailError, EmailService};
+use crate::middleware::abac::{enforce_route_permissions, RoutePermission};
+use crate::utils::api_error::ApiError;
+use crate::utils::validation::ValidatedJson;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use uuid::Uuid;
+
+/// Permissions enforced by `enforce_route_permissions` for the routes below
+pub const ROUTE_PERMISSIONS: &[RoutePermission] = &[
+    RoutePermission::new("GET", "/deliveries", "manage_system"),
+    RoutePermission::new("GET", "/deliveries/stats", "manage_system"),
+    RoutePermission::new("GET", "/deliveries/:id", "manage_system"),
+    RoutePermission::new("POST", "/deliveries/:id/retry", "manage_system"),
+    RoutePermission::new("GET", "/templates", "manage_system"),
+    RoutePermission::new("GET", "/templates/:name", "manage_system"),
+    RoutePermission::new("PUT", "/templates/:name", "manage_system"),
+    RoutePermission::new("DELETE", "/templates/:name", "manage_system"),
+];
+
+pub fn email_routes() -> Router<EmailService> {
+    Router::new()
+        .route("/deliveries", get(list_deliveries_handler))
+        .route("/deliveries/stats", get(delivery_stats_handler))
+        .route("/deliveries/:id", get(get_delivery_handler))
+        .route("/deliveries/:id/retry", post(retry_delivery_handler))
+        .route("/templates", get(list_templates_handler))
+        .route(
+            "/templates/:name",
+            get(get_template_handler)
+                .put(update_template_handler)
+                .delete(reset_template_handler),
+        )
+        .route_layer(axum::middleware::from_fn_with_state(
+            ROUTE_PERMISSIONS,
+            enforce_route_permissions,
+        ))
+}
+
+fn user_id(claims: &Claims) -> Result<Uuid, EmailError> {
+    Uuid::parse_str(&claims.sub)
+        .map_err(|_| EmailError::Forbidden("Invalid user id in token".to_string()))
+}
+
+#[axum::debug_handler]
+async fn list_deliveries_handler(
+    State(service): State<EmailService>,
+    Query(query): Query<ListDeliveriesQuery>,
+) -> Result<Json<Vec<EmailDelivery>>, EmailError> {
+    Ok(Json(service.list_deliveries(query).await?))
+}
+
+#[axum::debug_handler]
+async fn delivery_stats_handler(
+    State(service): State<EmailService>,
+) -> Result<Json<DeliveryStats>, EmailError> {
+    Ok(Json(service.delivery_stats().await?))
+}
+
+#[axum::debug_handler]
+async fn get_delivery_handler(
+    State(service): State<EmailService>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<EmailDelivery>, EmailError> {
+    Ok(Json(service.get_delivery(id).await?))
+}
+
+#[axum::debug_handler]
+async fn retry_delivery_handler(
+    State(service): State<EmailService>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<EmailDelivery>, EmailError> {
+    let user_id = user_id(&claims)?;
+    Ok(Json(service.retry_delivery(id, user_id).await?))
+}
+
+#[axum::debug_handler]
+async fn list_templates_handler(
+    State(service): State<EmailService>,
+) -> Result<Json<Vec<EmailTemplate>>, EmailError> {
+    Ok(Json(service.list_templates().await?))
+}
+
+#[axum::debug_handler]
+async fn get_template_handler(
+    State(service): State<EmailService>,
+    Path(name): Path<String>,
+) -> Result<Json<EmailTemplate>, EmailError> {
+    Ok(Json(service.get_template(&name).await?))
+}
+
+#[axum::debug_handler]
+async fn update_template_handler(
+    State(service): State<EmailService>,
+    Extension(claims): Extension<Claims>,
+    Path(name): Path<String>,
+    ValidatedJson(input): ValidatedJson<UpdateEmailTemplateInput>,
+) -> Result<Json<EmailTemplate>, EmailError> {
+    let user_id = user_id(&claims)?;
+    Ok(Json(service.update_template(&name, input, user_id).await?))
+}
+
+#[axum::debug_handler]
+async fn reset_template_handler(
+    State(service): State<EmailService>,
+    Extension(claims): Extension<Claims>,
+    Path(name): Path<String>,
+) -> Result<Json<EmailTemplate>, EmailError> {
+    let user_id = user_id(&claims)?;
+    Ok(Json(service.reset_template(&name, user_id).await?))
+}
+
+impl axum::response::IntoResponse for EmailError {
+    fn into_response(self) -> axum::response::Response {
+        let (status, code) = match &self {
+            EmailError::DatabaseError(_) | EmailError::Config(_) | EmailError::Template(_) => {
+                (StatusCode::INTERNAL_SERVER_ERROR, "internal")
+            }
+            EmailError::InvalidInput(_) => (StatusCode::BAD_REQUEST, "email.invalid_input"),
+            EmailError::Forbidden(_) => (StatusCode::FORBIDDEN, "email.forbidden"),
+            EmailError::NotFound => (StatusCode::NOT_FOUND, "email.not_found"),
+        };
+
+        ApiError::new(status, code, self.to_string()).into_response()
+    }
+}
@@ -0,0 +1,374 @@
+use super::models::{DeliveryStats, EmailDelivery, ListDeliveriesQuery, OutgoingEmail};
+use super::providers::{build_provider, EmailProvider};
+use super::templates::BuiltinEmail;
+use crate::config::EmailConfig;
+use crate::features::system::AuditService;
+use crate::utils::http_client::OutboundClient;
+use crate::utils::shutdown::Shutdown;
+use sqlx::PgPool;
+use std::sync::Arc;
+use thiserror::Error;
+use uuid::Uuid;
+
+/// How long a worker holds a claimed message. If it dies mid-send the claim
+/// lapses and the message is picked up again.
+const CLAIM_LEASE_SECS: i64 = 300;
+
+#[derive(Error, Debug)]
+pub enum EmailError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+
+    #[error("Invalid email configuration: {0}")]
+    Config(String),
+
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("Template error: {0}")]
+    Template(String),
+
+    #[error("Not found")]
+    NotFound,
+}
+
+/// Wait in seconds before the retry that follows attempt number `attempt`,
+/// doubling from `base_secs` up to `max_secs`
+pub fn retry_delay(attempt: i32, base_secs: i64, max_secs: i64) -> i64 {
+    let doublings = (attempt - 1).clamp(0, 30) as u32;
+    base_secs
+        .max(1)
+        .saturating_mul(2i64.saturating_pow(doublings))
+        .min(max_secs.max(1))
+}
+
+/// Plain `local@domain` addresses only, so nothing can be smuggled into
+/// SMTP commands or headers
+fn is_valid_address(address: &str) -> bool {
+    let Some((local, domain)) = address.rsplit_once('@') else {
+        return false;
+    };
+    address.len() <= 320
+        && !local.is_empty()
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && !address
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || "<>,;\"()[]\\".contains(c))
+}
+
+/// Queues templated messages and sends them through the configured provider.
+/// `email_deliveries` is both the queue and the delivery log.
+#[derive(Clone)]
+pub struct EmailService {
+    pub(super) pool: PgPool,
+    pub(super) audit_service: AuditService,
+    pub(super) config: EmailConfig,
+    provider: Arc<dyn EmailProvider>,
+}
+
+impl EmailService {
+    pub fn new(
+        pool: PgPool,
+        audit_service: AuditService,
+        config: EmailConfig,
+        http: OutboundClient,
+    ) -> Result<Self, EmailError> {
+        let provider = build_provider(&config, &http).map_err(EmailError::Config)?;
+        Ok(Self {
+            pool,
+            audit_service,
+            config,
+            provider,
+        })
+    }
+
+    /// Send through `provider` instead of the configured one
+    pub fn with_provider(mut self, provider: Arc<dyn EmailProvider>) -> Self {
+        self.provider = provider;
+        self
+    }
+
+    pub fn provider_name(&self) -> &str {
+        self.provider.name()
+    }
+
+    /// Absolute frontend URL for `path`, for links in messages
+    pub fn link(&self, path: &str) -> String {
+        format!(
+            "{}/{}",
+            self.config.app_url.trim_end_matches('/'),
+            path.trim_start_matches('/')
+        )
+    }
+
+    // ========================================================================
+    // QUEUE
+    // ========================================================================
+
+    /// Render `template` for `to` and queue it for the delivery worker.
+    /// `values` are the template's own variables; the common ones are added here.
+    pub async fn queue(
+        &self,
+        template: &BuiltinEmail,
+        to: &str,
+        values: &[(&str, &str)],
+    ) -> Result<Uuid, EmailError> {
+        let to = to.trim();
+        if !is_valid_address(to) {
+            return Err(EmailError::InvalidInput(format!(
+                "'{}' is not a valid email address",
+                to
+            )));
+        }
+        let rendered = self.render_for(template, to, values).await?;
+
+        let id = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            INSERT INTO email_deliveries (template, recipient, subject, text_body, html_body, max_attempts)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id
+            "#,
+        )
+        .bind(template.name)
+        .bind(to)
+        .bind(&rendered.subject)
+        .bind(&rendered.text_body)
+        .bind(&rendered.html_body)
+        .bind(self.config.max_attempts.max(1))
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(id)
+    }
+
+    // ========================================================================
+    // DELIVERY
+    // ========================================================================
+
+    /// Claim the messages that are due and attempt each once. Returns how
+    /// many were attempted.
+    pub async fn deliver_due(&self) -> Result<usize, EmailError> {
+        let claimed = sqlx::query_as::<_, EmailDelivery>(
+            r#"
+            UPDATE email_deliveries
+            SET attempts = attempts + 1,
+                next_attempt_at = NOW() + make_interval(secs => $2),
+                updated_at = NOW()
+            WHERE id IN (
+                SELECT id FROM email_deliveries
+                WHERE status = 'pending' AND next_attempt_at <= NOW()
+                ORDER BY next_attempt_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(self.config.batch_size.max(1))
+        .bind(CLAIM_LEASE_SECS as f64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        for delivery in &claimed {
+            self.attempt(delivery).await?;
+        }
+        Ok(claimed.len())
+    }
+
+    async fn attempt(&self, delivery: &EmailDelivery) -> Result<(), EmailError> {
+        let email = OutgoingEmail {
+            from_address: self.config.from_address.clone(),
+            from_name: self.config.from_name.clone(),
+            to: delivery.recipient.clone(),
+            subject: delivery.subject.clone(),
+            text_body: delivery.text_body.clone().unwrap_or_default(),
+            html_body: delivery.html_body.clone(),
+        };
+
+        let failure = match self.provider.send(&email).await {
+            Ok(message_id) => {
+                sqlx::query(
+                    r#"
+                    UPDATE email_deliveries
+                    SET status = 'sent', provider = $2, provider_message_id = $3,
+                        last_error = NULL, text_body = NULL, html_body = NULL,
+                        sent_at = NOW(), updated_at = NOW()
+                    WHERE id = $1
+                    "#,
+                )
+                .bind(delivery.id)
+                .bind(self.provider.name())
+                .bind(message_id)
+                .execute(&self.pool)
+                .await?;
+                return Ok(());
+            }
+            Err(failure) => failure,
+        };
+
+        if failure.is_permanent() || delivery.attempts >= delivery.max_attempts {
+            tracing::error!(
+                "Giving up on email {} after {} attempt(s): {}",
+                delivery.id,
+                delivery.attempts,
+                failure
+            );
+            sqlx::query(
+                r#"
+                UPDATE email_deliveries
+                SET status = 'failed', provider = $2, last_error = $3, updated_at = NOW()
+                WHERE id = $1
+                "#,
+            )
+            .bind(delivery.id)
+            .bind(self.provider.name())
+            .bind(failure.message())
+            .execute(&self.pool)
+            .await?;
+        } else {
+            let delay = retry_delay(
+                delivery.attempts,
+                self.config.retry_base_secs,
+                self.config.retry_max_secs,
+            );
+            tracing::warn!(
+                "Email {} failed (attempt {}), retrying in {}s: {}",
+                delivery.id,
+                delivery.attempts,
+                delay,
+                failure
+            );
+            sqlx::query(
+                r#"
+                UPDATE email_deliveries
+                SET provider = $2, last_error = $3,
+                    next_attempt_at = NOW() + make_interval(secs => $4), updated_at = NOW()
+                WHERE id = $1
+                "#,
+            )
+            .bind(delivery.id)
+            .bind(self.provider.name())
+            .bind(failure.message())
+            .bind(delay as f64)
+            .execute(&self.pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    pub async fn start_delivery_task(self, shutdown: Shutdown) {
+        shutdown.clone().spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
+                self.config.worker_interval_secs.max(1),
+            ));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = shutdown.cancelled() => break,
+                }
+                if let Err(e) = self.deliver_due().await {
+                    tracing::error!("Failed to deliver queued email: {}", e);
+                }
+            }
+        });
+    }
+
+    // ========================================================================
+    // DELIVERY LOG
+    // ========================================================================
+
+    pub async fn list_deliveries(
+        &self,
+        query: ListDeliveriesQuery,
+    ) -> Result<Vec<EmailDelivery>, EmailError> {
+        Ok(sqlx::query_as::<_, EmailDelivery>(
+            r#"
+            SELECT * FROM email_deliveries
+            WHERE ($1::text IS NULL OR status = $1)
+              AND ($2::text IS NULL OR recipient = $2)
+              AND ($3::text IS NULL OR template = $3)
+            ORDER BY created_at DESC
+            LIMIT $4
+            "#,
+        )
+        .bind(query.status)
+        .bind(query.recipient)
+        .bind(query.template)
+        .bind(query.limit.unwrap_or(100).clamp(1, 500))
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    pub async fn get_delivery(&self, id: Uuid) -> Result<EmailDelivery, EmailError> {
+        sqlx::query_as::<_, EmailDelivery>("SELECT * FROM email_deliveries WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or(EmailError::NotFound)
+    }
+
+    pub async fn delivery_stats(&self) -> Result<DeliveryStats, EmailError> {
+        Ok(sqlx::query_as::<_, DeliveryStats>(
+            r#"
+            SELECT COUNT(*) FILTER (WHERE status = 'pending') AS pending,
+                   COUNT(*) FILTER (WHERE status = 'sent') AS sent,
+                   COUNT(*) FILTER (WHERE status = 'failed') AS failed
+            FROM email_deliveries
+            "#,
+        )
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    /// Send a failed message again with a fresh set of attempts, or a pending
+    /// one right away
+    pub async fn retry_delivery(
+        &self,
+        id: Uuid,
+        user_id: Uuid,
+    ) -> Result<EmailDelivery, EmailError> {
+        let delivery = sqlx::query_as::<_, EmailDelivery>(
+            r#"
+            UPDATE email_deliveries
+            SET status = 'pending', next_attempt_at = NOW(), updated_at = NOW(),
+                attempts = CASE WHEN status = 'failed' THEN 0 ELSE attempts END
+            WHERE id = $1 AND status IN ('pending', 'failed')
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(delivery) = delivery else {
+            // Either missing or already sent
+            let existing = self.get_delivery(id).await?;
+            return Err(EmailError::InvalidInput(format!(
+                "email {} was already sent",
+                existing.id
+            )));
+        };
+
+        let _ = self
+            .audit_service
+            .log(
+                user_id,
+                "email.delivery_retried",
+                "email_delivery",
+                None,
+                None,
+                Some(serde_json::json!({
+                    "delivery_id": delivery.id,
+                    "template": delivery.template,
+                    "recipient": delivery.recipient,
+                })),
+                None,
+            )
+            .await;
+        Ok(delivery)
+    }
+}
@@ -0,0 +1,421 @@
+use super::models::{
+    EmailTemplate, EmailTemplateOverride, RenderedEmail, TemplateVariable, UpdateEmailTemplateInput,
+};
+use super::service::{EmailError, EmailService};
+use crate::features::ai::prompts::{placeholders, render_template};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Filled in by `EmailService` for every template
+pub const COMMON_VARIABLES: &[(&str, &str)] = &[
+    ("app_name", "Sender name, from email.from_name"),
+    ("app_url", "Frontend base URL, from email.app_url"),
+    ("recipient", "Address the message is sent to"),
+];
+
+/// A message the backend sends, with the variables its caller supplies.
+/// Deployments can override the wording; the built-in text is the fallback
+/// when an override is missing or can't be rendered.
+pub struct BuiltinEmail {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub subject: &'static str,
+    pub text_body: &'static str,
+    pub html_body: Option<&'static str>,
+    /// (name, description)
+    pub variables: &'static [(&'static str, &'static str)],
+}
+
+pub const PASSWORD_RESET_EMAIL: BuiltinEmail = BuiltinEmail {
+    name: "password_reset",
+    description: "Link for choosing a new password, sent on request",
+    subject: "Reset your {{app_name}} password",
+    text_body: "Someone asked to reset the password for your {{app_name}} account ({{recipient}}).
+
+Open this link within the next hour to choose a new password:
+
+{{reset_link}}
+
+If you didn't ask for this, you can ignore this email and your password won't change.
+",
+    html_body: Some(
+        "<p>Someone asked to reset the password for your {{app_name}} account ({{recipient}}).</p>
+<p><a href=\"{{reset_link}}\">Choose a new password</a> within the next hour.</p>
+<p>If you didn't ask for this, you can ignore this email and your password won't change.</p>
+",
+    ),
+    variables: &[("reset_link", "Single-use link to the reset page")],
+};
+
+pub const PASSWORD_CHANGED_EMAIL: BuiltinEmail = BuiltinEmail {
+    name: "password_changed",
+    description: "Notice that an account's password was changed",
+    subject: "Your {{app_name}} password was changed",
+    text_body: "The password for your {{app_name}} account ({{recipient}}) was just changed.
+
+If you didn't do this, reset your password at {{app_url}} and tell your administrator.
+",
+    html_body: Some(
+        "<p>The password for your {{app_name}} account ({{recipient}}) was just changed.</p>
+<p>If you didn't do this, <a href=\"{{app_url}}\">reset your password</a> and tell your administrator.</p>
+",
+    ),
+    variables: &[],
+};
+
+pub const NOTIFICATION_EMAIL: BuiltinEmail = BuiltinEmail {
+    name: "notification",
+    description: "A notification, for users who chose email delivery for its category",
+    subject: "{{app_name}}: {{message}}",
+    text_body: "{{message}}
+
+You get these emails because of your {{category}} notification settings at {{app_url}}.
+",
+    html_body: Some(
+        "<p>{{message}}</p>
+<p>You get these emails because of your {{category}} notification settings in <a href=\"{{app_url}}\">{{app_name}}</a>.</p>
+",
+    ),
+    variables: &[
+        ("message", "The notification text"),
+        ("category", "Notification category, e.g. account"),
+    ],
+};
+
+pub const NOTIFICATION_DIGEST_EMAIL: BuiltinEmail = BuiltinEmail {
+    name: "notification_digest",
+    description: "Low-priority notifications batched into a daily or weekly email",
+    subject: "Your {{period}} {{app_name}} digest: {{count}} notification(s)",
+    text_body: "Here is what happened in your {{app_name}} account:
+
+{{notifications}}
+
+Change how often you get this digest at {{app_url}}.
+",
+    html_body: Some(
+        "<p>Here is what happened in your {{app_name}} account:</p>
+<pre style=\"white-space: pre-wrap; font-family: inherit\">{{notifications}}</pre>
+<p>Change how often you get this digest in <a href=\"{{app_url}}\">{{app_name}}</a>.</p>
+",
+    ),
+    variables: &[
+        ("period", "\"daily\" or \"weekly\""),
+        ("count", "Number of notifications in the digest"),
+        ("notifications", "One line per notification, oldest first"),
+    ],
+};
+
+pub const BUILTIN_EMAILS: &[&BuiltinEmail] = &[
+    &PASSWORD_RESET_EMAIL,
+    &PASSWORD_CHANGED_EMAIL,
+    &NOTIFICATION_EMAIL,
+    &NOTIFICATION_DIGEST_EMAIL,
+];
+
+impl BuiltinEmail {
+    fn declared_variables(&self) -> Vec<TemplateVariable> {
+        self.variables
+            .iter()
+            .chain(COMMON_VARIABLES)
+            .map(|(name, description)| TemplateVariable {
+                name: name.to_string(),
+                description: description.to_string(),
+            })
+            .collect()
+    }
+
+    fn render(&self, values: &[(&str, &str)]) -> Result<RenderedEmail, String> {
+        render(self.subject, self.text_body, self.html_body, values)
+    }
+}
+
+pub fn builtin(name: &str) -> Option<&'static BuiltinEmail> {
+    BUILTIN_EMAILS.iter().copied().find(|t| t.name == name)
+}
+
+fn escape_html(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Fill in a template. Values are escaped in the HTML body, and line breaks
+/// are removed from the subject so a value can't add headers.
+pub fn render(
+    subject: &str,
+    text_body: &str,
+    html_body: Option<&str>,
+    values: &[(&str, &str)],
+) -> Result<RenderedEmail, String> {
+    let escaped: Vec<(&str, String)> = values
+        .iter()
+        .map(|(name, value)| (*name, escape_html(value)))
+        .collect();
+    Ok(RenderedEmail {
+        subject: render_template(subject, values)?
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" "),
+        text_body: render_template(text_body, values)?,
+        html_body: html_body
+            .map(|body| render_template(body, &escaped))
+            .transpose()?,
+    })
+}
+
+fn validate_override(
+    template: &BuiltinEmail,
+    input: &UpdateEmailTemplateInput,
+) -> Result<(), EmailError> {
+    if input.subject.trim().is_empty() {
+        return Err(EmailError::InvalidInput("subject is required".to_string()));
+    }
+    if input.text_body.trim().is_empty() {
+        return Err(EmailError::InvalidInput(
+            "text_body is required".to_string(),
+        ));
+    }
+    let declared: HashSet<&str> = template
+        .variables
+        .iter()
+        .chain(COMMON_VARIABLES)
+        .map(|(name, _)| *name)
+        .collect();
+    let bodies = [
+        Some(input.subject.as_str()),
+        Some(input.text_body.as_str()),
+        input.html_body.as_deref(),
+    ];
+    if let Some(undeclared) = bodies
+        .into_iter()
+        .flatten()
+        .flat_map(placeholders)
+        .find(|p| !declared.contains(p.as_str()))
+    {
+        return Err(EmailError::InvalidInput(format!(
+            "placeholder '{{{{{}}}}}' is not a variable of the '{}' template",
+            undeclared, template.name
+        )));
+    }
+    Ok(())
+}
+
+fn describe(template: &BuiltinEmail, stored: Option<EmailTemplateOverride>) -> EmailTemplate {
+    let variables = template.declared_variables();
+    match stored {
+        Some(stored) => EmailTemplate {
+            name: template.name.to_string(),
+            description: template.description.to_string(),
+            subject: stored.subject,
+            text_body: stored.text_body,
+            html_body: stored.html_body,
+            variables,
+            is_overridden: true,
+            updated_by: stored.updated_by,
+            updated_at: Some(stored.updated_at),
+        },
+        None => EmailTemplate {
+            name: template.name.to_string(),
+            description: template.description.to_string(),
+            subject: template.subject.to_string(),
+            text_body: template.text_body.to_string(),
+            html_body: template.html_body.map(str::to_string),
+            variables,
+            is_overridden: false,
+            updated_by: None,
+            updated_at: None,
+        },
+    }
+}
+
+impl EmailService {
+    // ========================================================================
+    // TEMPLATES
+    // ========================================================================
+
+    async fn stored_template(
+        &self,
+        name: &str,
+    ) -> Result<Option<EmailTemplateOverride>, EmailError> {
+        Ok(sqlx::query_as::<_, EmailTemplateOverride>(
+            "SELECT * FROM email_templates WHERE name = $1",
+        )
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    /// Render `template` for `recipient`, preferring the deployment's override
+    pub(super) async fn render_for(
+        &self,
+        template: &BuiltinEmail,
+        recipient: &str,
+        values: &[(&str, &str)],
+    ) -> Result<RenderedEmail, EmailError> {
+        let mut all_values = vec![
+            ("app_name", self.config.from_name.as_str()),
+            ("app_url", self.config.app_url.as_str()),
+            ("recipient", recipient),
+        ];
+        all_values.extend_from_slice(values);
+
+        if let Some(stored) = self.stored_template(template.name).await? {
+            match render(
+                &stored.subject,
+                &stored.text_body,
+                stored.html_body.as_deref(),
+                &all_values,
+            ) {
+                Ok(rendered) => return Ok(rendered),
+                Err(e) => tracing::warn!(
+                    "Email template '{}' could not be rendered, using the built-in: {}",
+                    template.name,
+                    e
+                ),
+            }
+        }
+        template.render(&all_values).map_err(EmailError::Template)
+    }
+
+    pub async fn list_templates(&self) -> Result<Vec<EmailTemplate>, EmailError> {
+        let stored = sqlx::query_as::<_, EmailTemplateOverride>("SELECT * FROM email_templates")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(BUILTIN_EMAILS
+            .iter()
+            .map(|template| {
+                let stored = stored.iter().find(|s| s.name == template.name).cloned();
+                describe(template, stored)
+            })
+            .collect())
+    }
+
+    pub async fn get_template(&self, name: &str) -> Result<EmailTemplate, EmailError> {
+        let template = builtin(name).ok_or(EmailError::NotFound)?;
+        Ok(describe(template, self.stored_template(name).await?))
+    }
+
+    /// Replace the wording of a built-in template for this deployment
+    pub async fn update_template(
+        &self,
+        name: &str,
+        input: UpdateEmailTemplateInput,
+        user_id: Uuid,
+    ) -> Result<EmailTemplate, EmailError> {
+        let template = builtin(name).ok_or(EmailError::NotFound)?;
+        validate_override(template, &input)?;
+
+        let stored = sqlx::query_as::<_, EmailTemplateOverride>(
+            r#"
+            INSERT INTO email_templates (name, subject, text_body, html_body, updated_by)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (name) DO UPDATE SET
+                subject = EXCLUDED.subject,
+                text_body = EXCLUDED.text_body,
+                html_body = EXCLUDED.html_body,
+                updated_by = EXCLUDED.updated_by,
+                updated_at = NOW()
+            RETURNING *
+            "#,
+        )
+        .bind(name)
+        .bind(&input.subject)
+        .bind(&input.text_body)
+        .bind(&input.html_body)
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let _ = self
+            .audit_service
+            .log(
+                user_id,
+                "email.template_updated",
+                "email_template",
+                None,
+                None,
+                Some(serde_json::json!({ "name": name, "subject": input.subject })),
+                None,
+            )
+            .await;
+        Ok(describe(template, Some(stored)))
+    }
+
+    /// Drop the override, going back to the built-in wording
+    pub async fn reset_template(
+        &self,
+        name: &str,
+        user_id: Uuid,
+    ) -> Result<EmailTemplate, EmailError> {
+        let template = builtin(name).ok_or(EmailError::NotFound)?;
+        let removed = sqlx::query("DELETE FROM email_templates WHERE name = $1")
+            .bind(name)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+
+        if removed > 0 {
+            let _ = self
+                .audit_service
+                .log(
+                    user_id,
+                    "email.template_reset",
+                    "email_template",
+                    None,
+                    None,
+                    Some(serde_json::json!({ "name": name })),
+                    None,
+                )
+                .await;
+        }
+        Ok(describe(template, None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn html_values_are_escaped_and_subject_stays_on_one_line() {
+        let rendered = render(
+            "Hello {{name}}",
+            "Hi {{name}}",
+            Some("<p>Hi {{name}}</p>"),
+            &[("name", "<b>Eve</b>\r\nBcc: x@example.com")],
+        )
+        .unwrap();
+
+        assert_eq!(rendered.subject, "Hello <b>Eve</b> Bcc: x@example.com");
+        assert_eq!(rendered.text_body, "Hi <b>Eve</b>\r\nBcc: x@example.com");
+        assert_eq!(
+            rendered.html_body.as_deref(),
+            Some("<p>Hi &lt;b&gt;Eve&lt;/b&gt;\r\nBcc: x@example.com</p>")
+        );
+    }
+
+    #[test]
+    fn builtins_only_use_declared_variables() {
+        for template in BUILTIN_EMAILS {
+            let input = UpdateEmailTemplateInput {
+                subject: template.subject.to_string(),
+                text_body: template.text_body.to_string(),
+                html_body: template.html_body.map(str::to_string),
+            };
+            assert!(
+                validate_override(template, &input).is_ok(),
+                "{}",
+                template.name
+            );
+        }
+    }
+}
@@ -0,0 +1,581 @@
+use super::models::*;
+use super::service::{EnvironmentError, EnvironmentService, CATALOG_CLASSES};
+use sqlx::{Postgres, Transaction};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/// Target-side ids for the names and ids a bundle refers to
+struct Resolved {
+    classes: HashMap<String, Uuid>,
+    relationship_types: HashMap<String, Uuid>,
+}
+
+impl EnvironmentService {
+    // ========================================================================
+    // IMPORT
+    // ========================================================================
+
+    /// Apply a bundle in one transaction, or check and roll back when
+    /// `dry_run` is set. The ontology isn't imported; every class, property
+    /// and relationship type the bundle uses must already exist here, which
+    /// `import-ontology` takes care of. Entities keep their ids unless those
+    /// are taken by something else, roles and permissions are matched by
+    /// name, and a tenant bundle can be moved to another tenant.
+    pub async fn import_bundle(
+        &self,
+        bundle: &EnvironmentBundle,
+        options: ImportOptions,
+        user_id: Option<Uuid>,
+    ) -> Result<ImportReport, EnvironmentError> {
+        if bundle.tenant_id.is_none() && options.tenant_id.is_some() {
+            return Err(EnvironmentError::InvalidInput(
+                "An instance bundle can't be imported into a tenant".to_string(),
+            ));
+        }
+        let tenant_id = options.tenant_id.or(bundle.tenant_id);
+        let resolved = self.check_compatibility(bundle, tenant_id).await?;
+        let remap_tenant = |t: Option<Uuid>| {
+            if t.is_some() && t == bundle.tenant_id {
+                tenant_id
+            } else {
+                t
+            }
+        };
+
+        let mut report = ImportReport {
+            dry_run: options.dry_run,
+            ..Default::default()
+        };
+        let mut tx = self.pool.begin().await?;
+
+        let ids =
+            import_entities(&mut tx, bundle, &resolved, tenant_id, user_id, &mut report).await?;
+
+        for relationship in &bundle.relationships {
+            let (Some(source), Some(target)) = (
+                ids.get(&relationship.source_id),
+                ids.get(&relationship.target_id),
+            ) else {
+                report.relationships_skipped += 1;
+                continue;
+            };
+            sqlx::query(
+                r#"
+                INSERT INTO relationships (source_entity_id, target_entity_id, relationship_type_id,
+                                           metadata, tenant_id, created_by)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT ON CONSTRAINT unique_relationship
+                DO UPDATE SET metadata = EXCLUDED.metadata
+                "#,
+            )
+            .bind(source)
+            .bind(target)
+            .bind(resolved.relationship_types[&relationship.relationship_type])
+            .bind(&relationship.metadata)
+            .bind(remap_tenant(relationship.tenant_id))
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+            report.relationships += 1;
+        }
+
+        let policy_ids: Vec<Uuid> = bundle.policies.iter().map(|p| p.id).collect();
+        let existing_policies: HashMap<Uuid, Option<Uuid>> =
+            sqlx::query_as::<_, (Uuid, Option<Uuid>)>(
+                "SELECT id, tenant_id FROM policies WHERE id = ANY($1)",
+            )
+            .bind(&policy_ids)
+            .fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .collect();
+        for policy in &bundle.policies {
+            let policy_tenant = remap_tenant(policy.tenant_id);
+            // Another tenant's policy with the same id is left alone
+            let id = match existing_policies.get(&policy.id) {
+                Some(existing) if *existing != policy_tenant => Uuid::new_v4(),
+                _ => policy.id,
+            };
+            sqlx::query(
+                r#"
+                INSERT INTO policies (id, name, description, effect, priority, target_class_id,
+                                      target_permissions, conditions, scope_entity_id, tenant_id,
+                                      is_active, valid_from, valid_until, schedule_cron, created_by)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+                ON CONFLICT (id) DO UPDATE SET
+                    name = EXCLUDED.name,
+                    description = EXCLUDED.description,
+                    effect = EXCLUDED.effect,
+                    priority = EXCLUDED.priority,
+                    target_class_id = EXCLUDED.target_class_id,
+                    target_permissions = EXCLUDED.target_permissions,
+                    conditions = EXCLUDED.conditions,
+                    scope_entity_id = EXCLUDED.scope_entity_id,
+                    is_active = EXCLUDED.is_active,
+                    valid_from = EXCLUDED.valid_from,
+                    valid_until = EXCLUDED.valid_until,
+                    schedule_cron = EXCLUDED.schedule_cron,
+                    updated_at = NOW(),
+                    updated_by = EXCLUDED.created_by
+                "#,
+            )
+            .bind(id)
+            .bind(&policy.name)
+            .bind(&policy.description)
+            .bind(&policy.effect)
+            .bind(policy.priority)
+            .bind(policy.target_class.as_ref().map(|c| resolved.classes[c]))
+            .bind(&policy.target_permissions)
+            .bind(&policy.conditions)
+            .bind(
+                policy
+                    .scope_entity_id
+                    .map(|s| ids.get(&s).copied().unwrap_or(s)),
+            )
+            .bind(policy_tenant)
+            .bind(policy.is_active)
+            .bind(policy.valid_from)
+            .bind(policy.valid_until)
+            .bind(&policy.schedule_cron)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+            report.policies += 1;
+        }
+
+        report.settings =
+            import_settings(&mut tx, &bundle.settings, &remap_tenant, user_id).await?;
+
+        if options.dry_run {
+            tx.rollback().await?;
+            return Ok(report);
+        }
+        tx.commit().await?;
+
+        if let Some(uid) = user_id {
+            let _ = self
+                .audit_service
+                .log(
+                    uid,
+                    "environment.import",
+                    "environment",
+                    tenant_id,
+                    None,
+                    serde_json::to_value(&report).ok(),
+                    Some(serde_json::json!({
+                        "source_tenant_id": bundle.tenant_id,
+                        "exported_at": bundle.exported_at,
+                        "schema_version": bundle.schema_version,
+                    })),
+                )
+                .await;
+        }
+
+        Ok(report)
+    }
+
+    /// Collect every reason the bundle can't be applied here, so they can
+    /// all be fixed in one go
+    async fn check_compatibility(
+        &self,
+        bundle: &EnvironmentBundle,
+        tenant_id: Option<Uuid>,
+    ) -> Result<Resolved, EnvironmentError> {
+        if bundle.format_version != BUNDLE_FORMAT_VERSION {
+            return Err(EnvironmentError::Incompatible(vec![format!(
+                "Bundle format {} is not supported; expected {}",
+                bundle.format_version, BUNDLE_FORMAT_VERSION
+            )]));
+        }
+
+        let mut problems = Vec::new();
+        let schema_version = self.schema_version().await?;
+        if bundle.schema_version > schema_version {
+            problems.push(format!(
+                "Bundle was exported at migration {}, but this database is at {}",
+                bundle.schema_version, schema_version
+            ));
+        }
+
+        let mut class_names: HashSet<&str> = HashSet::new();
+        class_names.extend(bundle.ontology.classes.iter().map(|c| c.name.as_str()));
+        class_names.extend(bundle.entities.iter().map(|e| e.class.as_str()));
+        class_names.extend(
+            bundle
+                .policies
+                .iter()
+                .filter_map(|p| p.target_class.as_deref()),
+        );
+        let class_names: Vec<&str> = class_names.into_iter().collect();
+        // A tenant's own class wins over the current version, which wins over
+        // the system ontology
+        let classes: HashMap<String, Uuid> = sqlx::query_as::<_, (String, Uuid)>(
+            r#"
+            SELECT DISTINCT ON (c.name) c.name, c.id
+            FROM classes c
+            JOIN ontology_versions ov ON ov.id = c.version_id
+            WHERE c.name = ANY($1)
+              AND (c.tenant_id = $2 OR (c.tenant_id IS NULL AND (ov.is_current OR ov.is_system)))
+            ORDER BY c.name, c.tenant_id IS NULL, ov.is_system, c.created_at DESC
+            "#,
+        )
+        .bind(&class_names)
+        .bind(tenant_id)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .collect();
+        let mut missing: Vec<&str> = class_names
+            .iter()
+            .copied()
+            .filter(|name| !classes.contains_key(*name))
+            .collect();
+        missing.sort_unstable();
+        problems.extend(
+            missing
+                .into_iter()
+                .map(|name| format!("Class '{}' does not exist here", name)),
+        );
+
+        let class_ids: Vec<Uuid> = classes.values().copied().collect();
+        let properties: HashMap<(Uuid, String), String> =
+            sqlx::query_as::<_, (Uuid, String, String)>(
+                "SELECT class_id, name, data_type FROM properties WHERE class_id = ANY($1) AND NOT is_deprecated",
+            )
+            .bind(&class_ids)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|(class_id, name, data_type)| ((class_id, name), data_type))
+            .collect();
+        for class in &bundle.ontology.classes {
+            let Some(class_id) = classes.get(&class.name) else {
+                continue;
+            };
+            for property in &class.properties {
+                match properties.get(&(*class_id, property.name.clone())) {
+                    None => problems.push(format!(
+                        "Property '{}.{}' does not exist here",
+                        class.name, property.name
+                    )),
+                    Some(data_type) if !data_type.eq_ignore_ascii_case(&property.data_type) => {
+                        problems.push(format!(
+                            "Property '{}.{}' is {} here but {} in the bundle",
+                            class.name, property.name, data_type, property.data_type
+                        ))
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+
+        let relationship_types: HashMap<String, Uuid> =
+            sqlx::query_as::<_, (String, Uuid)>("SELECT name, id FROM relationship_types")
+                .fetch_all(&self.pool)
+                .await?
+                .into_iter()
+                .collect();
+        let mut missing: Vec<&str> = bundle
+            .relationships
+            .iter()
+            .map(|r| r.relationship_type.as_str())
+            .filter(|name| !relationship_types.contains_key(*name))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        missing.sort_unstable();
+        problems.extend(
+            missing
+                .into_iter()
+                .map(|name| format!("Relationship type '{}' does not exist here", name)),
+        );
+
+        // Policies may be scoped to entities outside the bundle, as long as
+        // they exist here too
+        let bundled: HashSet<Uuid> = bundle.entities.iter().map(|e| e.id).collect();
+        let outside: Vec<Uuid> = bundle
+            .policies
+            .iter()
+            .filter_map(|p| p.scope_entity_id)
+            .filter(|id| !bundled.contains(id))
+            .collect();
+        if !outside.is_empty() {
+            let found: HashSet<Uuid> = sqlx::query_scalar::<_, Uuid>(
+                "SELECT id FROM entities WHERE id = ANY($1) AND deleted_at IS NULL",
+            )
+            .bind(&outside)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .collect();
+            for policy in &bundle.policies {
+                if let Some(scope) = policy
+                    .scope_entity_id
+                    .filter(|id| !bundled.contains(id) && !found.contains(id))
+                {
+                    problems.push(format!(
+                        "Policy '{}' is scoped to entity {}, which is neither in the bundle nor here",
+                        policy.name, scope
+                    ));
+                }
+            }
+        }
+
+        if !problems.is_empty() {
+            return Err(EnvironmentError::Incompatible(problems));
+        }
+        Ok(Resolved {
+            classes,
+            relationship_types,
+        })
+    }
+}
+
+/// Write the bundle's entities and return where each bundle id ended up
+async fn import_entities(
+    tx: &mut Transaction<'_, Postgres>,
+    bundle: &EnvironmentBundle,
+    resolved: &Resolved,
+    tenant_id: Option<Uuid>,
+    user_id: Option<Uuid>,
+    report: &mut ImportReport,
+) -> Result<HashMap<Uuid, Uuid>, EnvironmentError> {
+    let catalog_class_ids: Vec<Uuid> = CATALOG_CLASSES
+        .iter()
+        .filter_map(|name| resolved.classes.get(*name).copied())
+        .collect();
+    let catalog: HashMap<(Uuid, String), Uuid> = sqlx::query_as::<_, (Uuid, String, Uuid)>(
+        r#"
+        SELECT class_id, display_name, id FROM entities
+        WHERE class_id = ANY($1) AND deleted_at IS NULL
+          AND (tenant_id IS NULL OR tenant_id = $2)
+        "#,
+    )
+    .bind(&catalog_class_ids)
+    .bind(tenant_id)
+    .fetch_all(&mut **tx)
+    .await?
+    .into_iter()
+    .map(|(class_id, name, id)| ((class_id, name), id))
+    .collect();
+
+    let bundle_ids: Vec<Uuid> = bundle.entities.iter().map(|e| e.id).collect();
+    let existing: HashMap<Uuid, (Uuid, Option<Uuid>)> =
+        sqlx::query_as::<_, (Uuid, Uuid, Option<Uuid>)>(
+            "SELECT id, class_id, tenant_id FROM entities WHERE id = ANY($1)",
+        )
+        .bind(&bundle_ids)
+        .fetch_all(&mut **tx)
+        .await?
+        .into_iter()
+        .map(|(id, class_id, tenant)| (id, (class_id, tenant)))
+        .collect();
+
+    let mut ids = HashMap::new();
+    let mut written = Vec::new();
+    for entity in &bundle.entities {
+        let class_id = resolved.classes[&entity.class];
+        let entity_tenant = if entity.tenant_id.is_some() && entity.tenant_id == bundle.tenant_id {
+            tenant_id
+        } else {
+            entity.tenant_id
+        };
+
+        if CATALOG_CLASSES.contains(&entity.class.as_str()) {
+            if let Some(id) = catalog.get(&(class_id, entity.display_name.clone())) {
+                ids.insert(entity.id, *id);
+                report.entities_matched += 1;
+                continue;
+            }
+        }
+
+        let id = match existing.get(&entity.id) {
+            Some(&(existing_class, existing_tenant))
+                if existing_class == class_id && existing_tenant == entity_tenant =>
+            {
+                sqlx::query(
+                    r#"
+                    UPDATE entities
+                    SET display_name = $2, attributes = $3, approval_status = $4,
+                        parent_entity_id = NULL, deleted_at = NULL, deleted_by = NULL,
+                        updated_by = $5
+                    WHERE id = $1
+                    "#,
+                )
+                .bind(entity.id)
+                .bind(&entity.display_name)
+                .bind(&entity.attributes)
+                .bind(&entity.approval_status)
+                .bind(user_id)
+                .execute(&mut **tx)
+                .await?;
+                report.entities_updated += 1;
+                entity.id
+            }
+            taken => {
+                let id = if taken.is_some() {
+                    report.entities_remapped += 1;
+                    Uuid::new_v4()
+                } else {
+                    entity.id
+                };
+                sqlx::query(
+                    r#"
+                    INSERT INTO entities (id, class_id, display_name, tenant_id, attributes,
+                                          approval_status, created_by)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7)
+                    "#,
+                )
+                .bind(id)
+                .bind(class_id)
+                .bind(&entity.display_name)
+                .bind(entity_tenant)
+                .bind(&entity.attributes)
+                .bind(&entity.approval_status)
+                .bind(user_id)
+                .execute(&mut **tx)
+                .await?;
+                report.entities_created += 1;
+                id
+            }
+        };
+        ids.insert(entity.id, id);
+        written.push((entity, id));
+    }
+
+    // Parents go in once every entity exists
+    for (entity, id) in written {
+        if let Some(parent) = entity.parent_id.and_then(|p| ids.get(&p)) {
+            sqlx::query("UPDATE entities SET parent_entity_id = $2 WHERE id = $1")
+                .bind(id)
+                .bind(parent)
+                .execute(&mut **tx)
+                .await?;
+        }
+    }
+
+    Ok(ids)
+}
+
+/// Returns how many settings were written
+async fn import_settings(
+    tx: &mut Transaction<'_, Postgres>,
+    settings: &BundleSettings,
+    remap_tenant: &impl Fn(Option<Uuid>) -> Option<Uuid>,
+    user_id: Option<Uuid>,
+) -> Result<usize, EnvironmentError> {
+    let mut written = 0;
+
+    // Identical rules aren't added twice
+    for rule in &settings.ip_access_rules {
+        written += sqlx::query(
+            r#"
+            INSERT INTO ip_access_rules (cidr, action, scope, tenant_id, route_prefix, description, created_by)
+            SELECT $1, $2, $3, $4, $5, $6, $7
+            WHERE NOT EXISTS (
+                SELECT 1 FROM ip_access_rules
+                WHERE cidr = $1 AND action = $2 AND scope = $3
+                  AND tenant_id IS NOT DISTINCT FROM $4
+                  AND route_prefix IS NOT DISTINCT FROM $5
+                  AND api_key_id IS NULL
+            )
+            "#,
+        )
+        .bind(&rule.cidr)
+        .bind(&rule.action)
+        .bind(&rule.scope)
+        .bind(remap_tenant(rule.tenant_id))
+        .bind(&rule.route_prefix)
+        .bind(&rule.description)
+        .bind(user_id)
+        .execute(&mut **tx)
+        .await?
+        .rows_affected() as usize;
+    }
+
+    for policy in &settings.geo_access_policies {
+        sqlx::query(
+            r#"
+            INSERT INTO geo_access_policies (tenant_id, mode, countries, restrict_login,
+                                             restrict_api, block_unknown, description, created_by)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (tenant_id) DO UPDATE SET
+                mode = EXCLUDED.mode,
+                countries = EXCLUDED.countries,
+                restrict_login = EXCLUDED.restrict_login,
+                restrict_api = EXCLUDED.restrict_api,
+                block_unknown = EXCLUDED.block_unknown,
+                description = EXCLUDED.description,
+                updated_at = NOW()
+            "#,
+        )
+        .bind(remap_tenant(Some(policy.tenant_id)))
+        .bind(&policy.mode)
+        .bind(&policy.countries)
+        .bind(policy.restrict_login)
+        .bind(policy.restrict_api)
+        .bind(policy.block_unknown)
+        .bind(&policy.description)
+        .bind(user_id)
+        .execute(&mut **tx)
+        .await?;
+        written += 1;
+    }
+
+    for template in &settings.notification_templates {
+        sqlx::query(
+            r#"
+            INSERT INTO notification_templates (key, locale, message, updated_by)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (key, locale) DO UPDATE SET
+                message = EXCLUDED.message,
+                updated_by = EXCLUDED.updated_by,
+                updated_at = NOW()
+            "#,
+        )
+        .bind(&template.key)
+        .bind(&template.locale)
+        .bind(&template.message)
+        .bind(user_id)
+        .execute(&mut **tx)
+        .await?;
+        written += 1;
+    }
+
+    for rule in &settings.alert_rules {
+        sqlx::query(
+            r#"
+            INSERT INTO alert_rules (rule_name, description, enabled, event_type, min_severity,
+                                     threshold_count, threshold_window_minutes, group_by,
+                                     alert_channel, alert_cooldown_minutes)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ON CONFLICT (rule_name) DO UPDATE SET
+                description = EXCLUDED.description,
+                enabled = EXCLUDED.enabled,
+                event_type = EXCLUDED.event_type,
+                min_severity = EXCLUDED.min_severity,
+                threshold_count = EXCLUDED.threshold_count,
+                threshold_window_minutes = EXCLUDED.threshold_window_minutes,
+                group_by = EXCLUDED.group_by,
+                alert_channel = EXCLUDED.alert_channel,
+                alert_cooldown_minutes = EXCLUDED.alert_cooldown_minutes,
+                updated_at = NOW()
+            "#,
+        )
+        .bind(&rule.rule_name)
+        .bind(&rule.description)
+        .bind(rule.enabled)
+        .bind(&rule.event_type)
+        .bind(&rule.min_severity)
+        .bind(rule.threshold_count)
+        .bind(rule.threshold_window_minutes)
+        .bind(&rule.group_by)
+        .bind(&rule.alert_channel)
+        .bind(rule.alert_cooldown_minutes)
+        .execute(&mut **tx)
+        .await?;
+        written += 1;
+    }
+
+    Ok(written)
+}
@@ -0,0 +1,6 @@
+pub mod import;
+pub mod models;
+pub mod routes;
+pub mod service;
+
+pub use service::EnvironmentService;
@@ -0,0 +1,177 @@
+use crate::features::ontology::models::{ApprovalStatus, OntologyExport};
+use crate::utils::storage::PresignedUrl;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Bumped when the bundle layout changes incompatibly
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+/// Configuration and data of one tenant or the whole instance in a single
+/// document, for promoting from one environment to another. Classes and
+/// relationship types are referred to by name, since their ids differ
+/// between databases.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvironmentBundle {
+    pub format_version: u32,
+    pub exported_at: DateTime<Utc>,
+    /// Newest migration applied to the source database
+    pub schema_version: i64,
+    /// The tenant exported, or None for the whole instance
+    pub tenant_id: Option<Uuid>,
+    pub ontology: OntologyExport,
+    pub entities: Vec<BundleEntity>,
+    pub relationships: Vec<BundleRelationship>,
+    pub policies: Vec<BundlePolicy>,
+    pub settings: BundleSettings,
+}
+
+impl EnvironmentBundle {
+    /// e.g. environment-instance-20270301120000.json
+    pub fn file_name(&self) -> String {
+        let scope = self
+            .tenant_id
+            .map(|t| t.to_string())
+            .unwrap_or_else(|| "instance".to_string());
+        format!(
+            "environment-{}-{}.json",
+            scope,
+            self.exported_at.format("%Y%m%d%H%M%S")
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct BundleEntity {
+    pub id: Uuid,
+    pub class: String,
+    pub display_name: String,
+    pub parent_id: Option<Uuid>,
+    pub tenant_id: Option<Uuid>,
+    pub attributes: serde_json::Value,
+    pub approval_status: ApprovalStatus,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct BundleRelationship {
+    pub source_id: Uuid,
+    pub target_id: Uuid,
+    pub relationship_type: String,
+    pub metadata: Option<serde_json::Value>,
+    pub tenant_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct BundlePolicy {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub effect: String,
+    pub priority: i32,
+    pub target_class: Option<String>,
+    pub target_permissions: Vec<String>,
+    pub conditions: serde_json::Value,
+    pub scope_entity_id: Option<Uuid>,
+    pub tenant_id: Option<Uuid>,
+    pub is_active: bool,
+    pub valid_from: Option<DateTime<Utc>>,
+    pub valid_until: Option<DateTime<Utc>>,
+    pub schedule_cron: Option<String>,
+}
+
+/// Settings stored outside the ontology. Notification templates and alert
+/// rules are instance-wide, so tenant bundles leave them out.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BundleSettings {
+    #[serde(default)]
+    pub ip_access_rules: Vec<BundleIpRule>,
+    #[serde(default)]
+    pub geo_access_policies: Vec<BundleGeoPolicy>,
+    #[serde(default)]
+    pub notification_templates: Vec<BundleNotificationTemplate>,
+    #[serde(default)]
+    pub alert_rules: Vec<BundleAlertRule>,
+}
+
+/// Rules bound to an API key stay behind with the key
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct BundleIpRule {
+    pub cidr: String,
+    pub action: String,
+    pub scope: String,
+    pub tenant_id: Option<Uuid>,
+    pub route_prefix: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct BundleGeoPolicy {
+    pub tenant_id: Uuid,
+    pub mode: String,
+    pub countries: Vec<String>,
+    pub restrict_login: bool,
+    pub restrict_api: bool,
+    pub block_unknown: bool,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct BundleNotificationTemplate {
+    pub key: String,
+    pub locale: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct BundleAlertRule {
+    pub rule_name: String,
+    pub description: Option<String>,
+    pub enabled: Option<bool>,
+    pub event_type: Option<String>,
+    pub min_severity: Option<String>,
+    pub threshold_count: Option<i32>,
+    pub threshold_window_minutes: Option<i32>,
+    pub group_by: Option<String>,
+    pub alert_channel: String,
+    pub alert_cooldown_minutes: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    pub tenant_id: Option<Uuid>,
+}
+
+/// A bundle kept in object storage, with a link to download it
+#[derive(Debug, Serialize)]
+pub struct StoredExport {
+    pub key: String,
+    pub size_bytes: u64,
+    pub sha256: String,
+    pub download: PresignedUrl,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ImportOptions {
+    /// Where a tenant bundle lands; defaults to the tenant it came from
+    pub tenant_id: Option<Uuid>,
+    /// Check and apply everything, then roll back
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub entities_created: usize,
+    pub entities_updated: usize,
+    /// Bundle ids given a new id because the original is taken here
+    pub entities_remapped: usize,
+    /// Roles and permissions matched by name to existing ones
+    pub entities_matched: usize,
+    pub relationships: usize,
+    /// Relationships to entities neither in the bundle nor in this database
+    pub relationships_skipped: usize,
+    pub policies: usize,
+    pub settings: usize,
+}
@@ -0,0 +1,101 @@
+use crate::features::auth::jwt::Claims;
+use crate::features::environment::models::{
+    EnvironmentBundle, ExportQuery, ImportOptions, ImportReport, StoredExport,
+};
+use crate::features::environment::service::{EnvironmentError, EnvironmentService};
+use crate::middleware::abac::{enforce_route_permissions, RoutePermission};
+use crate::utils::api_error::ApiError;
+use crate::utils::storage::content_disposition;
+use axum::{
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use uuid::Uuid;
+
+/// Permissions enforced by `enforce_route_permissions` for the routes below
+pub const ROUTE_PERMISSIONS: &[RoutePermission] = &[
+    RoutePermission::new("GET", "/export", "manage_system"),
+    RoutePermission::new("POST", "/exports", "manage_system"),
+    RoutePermission::new("POST", "/import", "manage_system"),
+];
+
+pub fn environment_routes() -> Router<EnvironmentService> {
+    Router::new()
+        .route("/export", get(export_handler))
+        .route("/exports", post(store_export_handler))
+        .route("/import", post(import_handler))
+        .route_layer(axum::middleware::from_fn_with_state(
+            ROUTE_PERMISSIONS,
+            enforce_route_permissions,
+        ))
+}
+
+/// `?tenant_id=` for one tenant; the whole instance otherwise
+#[axum::debug_handler]
+async fn export_handler(
+    State(service): State<EnvironmentService>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response, EnvironmentError> {
+    let user_id = Uuid::parse_str(&claims.sub).ok();
+    let bundle = service.export_bundle(query.tenant_id, user_id).await?;
+    Ok((
+        [(
+            header::CONTENT_DISPOSITION,
+            content_disposition(&bundle.file_name()),
+        )],
+        Json(bundle),
+    )
+        .into_response())
+}
+
+/// Like `/export`, but kept in object storage; responds with a download link
+#[axum::debug_handler]
+async fn store_export_handler(
+    State(service): State<EnvironmentService>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<ExportQuery>,
+) -> Result<(StatusCode, Json<StoredExport>), EnvironmentError> {
+    let user_id = Uuid::parse_str(&claims.sub).ok();
+    let stored = service.store_bundle(query.tenant_id, user_id).await?;
+    Ok((StatusCode::CREATED, Json(stored)))
+}
+
+/// `?dry_run=true` reports what would change without writing anything
+#[axum::debug_handler]
+async fn import_handler(
+    State(service): State<EnvironmentService>,
+    Extension(claims): Extension<Claims>,
+    Query(options): Query<ImportOptions>,
+    Json(bundle): Json<EnvironmentBundle>,
+) -> Result<Json<ImportReport>, EnvironmentError> {
+    let user_id = Uuid::parse_str(&claims.sub).ok();
+    Ok(Json(
+        service.import_bundle(&bundle, options, user_id).await?,
+    ))
+}
+
+impl IntoResponse for EnvironmentError {
+    fn into_response(self) -> Response {
+        let (status, code) = match &self {
+            EnvironmentError::DatabaseError(_)
+            | EnvironmentError::OntologyError(_)
+            | EnvironmentError::Storage(_) => (StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+            EnvironmentError::Forbidden(_) => (StatusCode::FORBIDDEN, "environment.forbidden"),
+            EnvironmentError::InvalidInput(_) => {
+                (StatusCode::BAD_REQUEST, "environment.invalid_input")
+            }
+            EnvironmentError::Incompatible(_) => (StatusCode::CONFLICT, "environment.incompatible"),
+        };
+
+        let mut error = ApiError::new(status, code, self.to_string());
+        if let EnvironmentError::Incompatible(problems) = &self {
+            error = error.with_detail("problems", problems);
+        }
+
+        error.into_response()
+    }
+}
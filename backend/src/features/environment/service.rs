@@ -0,0 +1,298 @@
+use super::models::*;
+use crate::features::ontology::OntologyService;
+use crate::features::system::AuditService;
+use crate::utils::storage::{bytes_stream, Download, ObjectStorage, StorageError};
+use sqlx::PgPool;
+use std::collections::HashSet;
+use std::sync::Arc;
+use thiserror::Error;
+use uuid::Uuid;
+
+/// Classes whose entities belong to one installation: accounts, credentials,
+/// secrets and event history. Bundles never carry them.
+pub(super) const LOCAL_CLASSES: [&str; 17] = [
+    "User",
+    "Identity",
+    "RefreshToken",
+    "PasswordResetToken",
+    "Notification",
+    "ApiKey",
+    "ServiceAccount",
+    "AiProvider",
+    "Webhook",
+    "APIRequestEvent",
+    "AuditEvent",
+    "DataAccessEvent",
+    "PermissionChangeEvent",
+    "SecurityEvent",
+    "SessionEvent",
+    "SystemEvent",
+    "TestMarker",
+];
+
+/// Shared catalog classes. Tenant bundles include them, and imports match
+/// them to existing entities by name instead of creating copies.
+pub(super) const CATALOG_CLASSES: [&str; 2] = ["Role", "Permission"];
+
+#[derive(Error, Debug)]
+pub enum EnvironmentError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+
+    #[error("Ontology error: {0}")]
+    OntologyError(String),
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    #[error("Bundle is not compatible with this environment: {}", .0.join("; "))]
+    Incompatible(Vec<String>),
+
+    #[error("Storage error: {0}")]
+    Storage(#[from] StorageError),
+}
+
+#[derive(Clone)]
+pub struct EnvironmentService {
+    pub(super) pool: PgPool,
+    pub(super) ontology_service: OntologyService,
+    pub(super) audit_service: AuditService,
+    storage: Option<Arc<dyn ObjectStorage>>,
+}
+
+impl EnvironmentService {
+    pub fn new(
+        pool: PgPool,
+        ontology_service: OntologyService,
+        audit_service: AuditService,
+    ) -> Self {
+        Self {
+            pool,
+            ontology_service,
+            audit_service,
+            storage: None,
+        }
+    }
+
+    /// Where [`EnvironmentService::store_bundle`] keeps bundles
+    pub fn with_storage(mut self, storage: Arc<dyn ObjectStorage>) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    /// Newest migration applied to this database
+    pub(super) async fn schema_version(&self) -> Result<i64, EnvironmentError> {
+        Ok(sqlx::query_scalar::<_, i64>(
+            "SELECT COALESCE(MAX(version), 0) FROM _sqlx_migrations WHERE success",
+        )
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    // ========================================================================
+    // EXPORT
+    // ========================================================================
+
+    /// Bundle one tenant, or the whole instance when `tenant_id` is None.
+    /// Relationships are included only when both ends are in the bundle.
+    pub async fn export_bundle(
+        &self,
+        tenant_id: Option<Uuid>,
+        user_id: Option<Uuid>,
+    ) -> Result<EnvironmentBundle, EnvironmentError> {
+        let ontology = self
+            .ontology_service
+            .export_ontology()
+            .await
+            .map_err(|e| EnvironmentError::OntologyError(e.to_string()))?;
+
+        let mut entities = sqlx::query_as::<_, BundleEntity>(
+            r#"
+            SELECT e.id, c.name AS class, e.display_name, e.parent_entity_id AS parent_id,
+                   e.tenant_id, e.attributes, e.approval_status
+            FROM entities e
+            JOIN classes c ON c.id = e.class_id
+            WHERE e.deleted_at IS NULL
+              AND NOT (c.name = ANY($1))
+              AND ($2::uuid IS NULL OR e.tenant_id = $2
+                   OR (e.tenant_id IS NULL AND c.name = ANY($3)))
+            ORDER BY e.created_at, e.id
+            "#,
+        )
+        .bind(&LOCAL_CLASSES[..])
+        .bind(tenant_id)
+        .bind(&CATALOG_CLASSES[..])
+        .fetch_all(&self.pool)
+        .await?;
+
+        let ids: HashSet<Uuid> = entities.iter().map(|e| e.id).collect();
+        for entity in &mut entities {
+            if entity.parent_id.is_some_and(|p| !ids.contains(&p)) {
+                entity.parent_id = None;
+            }
+        }
+        let ids: Vec<Uuid> = ids.into_iter().collect();
+
+        let relationships = sqlx::query_as::<_, BundleRelationship>(
+            r#"
+            SELECT r.source_entity_id AS source_id, r.target_entity_id AS target_id,
+                   rt.name AS relationship_type, r.metadata, r.tenant_id
+            FROM relationships r
+            JOIN relationship_types rt ON rt.id = r.relationship_type_id
+            WHERE r.source_entity_id = ANY($1) AND r.target_entity_id = ANY($1)
+            ORDER BY r.created_at, r.id
+            "#,
+        )
+        .bind(&ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let policies = sqlx::query_as::<_, BundlePolicy>(
+            r#"
+            SELECT p.id, p.name, p.description, p.effect, p.priority, c.name AS target_class,
+                   p.target_permissions, p.conditions, p.scope_entity_id, p.tenant_id,
+                   p.is_active, p.valid_from, p.valid_until, p.schedule_cron
+            FROM policies p
+            LEFT JOIN classes c ON c.id = p.target_class_id
+            WHERE $1::uuid IS NULL OR p.tenant_id = $1
+            ORDER BY p.priority DESC, p.name, p.id
+            "#,
+        )
+        .bind(tenant_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let settings = self.export_settings(tenant_id).await?;
+
+        let bundle = EnvironmentBundle {
+            format_version: BUNDLE_FORMAT_VERSION,
+            exported_at: chrono::Utc::now(),
+            schema_version: self.schema_version().await?,
+            tenant_id,
+            ontology,
+            entities,
+            relationships,
+            policies,
+            settings,
+        };
+
+        if let Some(uid) = user_id {
+            let _ = self
+                .audit_service
+                .log(
+                    uid,
+                    "environment.export",
+                    "environment",
+                    tenant_id,
+                    None,
+                    None,
+                    Some(serde_json::json!({
+                        "entities": bundle.entities.len(),
+                        "relationships": bundle.relationships.len(),
+                        "policies": bundle.policies.len(),
+                    })),
+                )
+                .await;
+        }
+
+        Ok(bundle)
+    }
+
+    /// Export a bundle into object storage under exports/, returning a
+    /// presigned link to it instead of the bundle itself
+    pub async fn store_bundle(
+        &self,
+        tenant_id: Option<Uuid>,
+        user_id: Option<Uuid>,
+    ) -> Result<StoredExport, EnvironmentError> {
+        let storage = self.storage.as_ref().ok_or_else(|| {
+            EnvironmentError::InvalidInput("Object storage is not configured".to_string())
+        })?;
+        let bundle = self.export_bundle(tenant_id, user_id).await?;
+        let file_name = bundle.file_name();
+        let content = serde_json::to_vec(&bundle)
+            .map_err(|e| EnvironmentError::InvalidInput(e.to_string()))?;
+
+        let key = format!("exports/{}", file_name);
+        let stored = storage.put(&key, bytes_stream(content), u64::MAX).await?;
+        let download = storage
+            .presigned_url(
+                &key,
+                Download {
+                    filename: &file_name,
+                    content_type: "application/json",
+                },
+            )
+            .await?;
+        Ok(StoredExport {
+            key,
+            size_bytes: stored.size,
+            sha256: stored.sha256,
+            download,
+        })
+    }
+
+    async fn export_settings(
+        &self,
+        tenant_id: Option<Uuid>,
+    ) -> Result<BundleSettings, EnvironmentError> {
+        let ip_access_rules = sqlx::query_as::<_, BundleIpRule>(
+            r#"
+            SELECT cidr, action, scope, tenant_id, route_prefix, description
+            FROM ip_access_rules
+            WHERE api_key_id IS NULL AND ($1::uuid IS NULL OR tenant_id = $1)
+            ORDER BY created_at, id
+            "#,
+        )
+        .bind(tenant_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let geo_access_policies = sqlx::query_as::<_, BundleGeoPolicy>(
+            r#"
+            SELECT tenant_id, mode, countries, restrict_login, restrict_api, block_unknown, description
+            FROM geo_access_policies
+            WHERE $1::uuid IS NULL OR tenant_id = $1
+            ORDER BY created_at, id
+            "#,
+        )
+        .bind(tenant_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        if tenant_id.is_some() {
+            return Ok(BundleSettings {
+                ip_access_rules,
+                geo_access_policies,
+                ..Default::default()
+            });
+        }
+
+        let notification_templates = sqlx::query_as::<_, BundleNotificationTemplate>(
+            "SELECT key, locale, message FROM notification_templates ORDER BY key, locale",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let alert_rules = sqlx::query_as::<_, BundleAlertRule>(
+            r#"
+            SELECT rule_name, description, enabled, event_type, min_severity, threshold_count,
+                   threshold_window_minutes, group_by, alert_channel, alert_cooldown_minutes
+            FROM alert_rules
+            ORDER BY rule_name
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(BundleSettings {
+            ip_access_rules,
+            geo_access_policies,
+            notification_templates,
+            alert_rules,
+        })
+    }
+}
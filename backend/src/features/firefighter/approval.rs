@@ -0,0 +1,409 @@
+use super::models::{
+    FirefighterApprovalPolicy, FirefighterRequest, FirefighterScope, FirefighterSession,
+    SaveApprovalPolicyInput,
+};
+use super::service::{FirefighterError, FirefighterService};
+use sqlx::types::Json;
+use uuid::Uuid;
+
+const DEFAULT_REQUEST_TTL_MINUTES: i32 = 60;
+const MAX_REQUEST_TTL_MINUTES: i32 = 1440;
+
+impl FirefighterService {
+    // ========================================================================
+    // TWO-PERSON APPROVAL
+    // ========================================================================
+
+    /// The requester's tenant policy, or the global one when their tenant has none
+    pub(super) async fn effective_approval_policy(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Option<FirefighterApprovalPolicy>, FirefighterError> {
+        let policy = sqlx::query_as::<_, FirefighterApprovalPolicy>(
+            r#"
+            SELECT * FROM firefighter_approval_policies
+            WHERE tenant_id IS NULL
+               OR tenant_id = (SELECT tenant_id FROM entities WHERE id = $1)
+            ORDER BY tenant_id NULLS LAST
+            LIMIT 1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(policy)
+    }
+
+    /// Record a pending request and notify the approvers. A user with a
+    /// request already waiting gets that one back, so retries don't page
+    /// the approvers again.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) async fn file_request(
+        &self,
+        user_id: Uuid,
+        policy: &FirefighterApprovalPolicy,
+        justification: String,
+        duration: i32,
+        scope: Option<FirefighterScope>,
+        ip: Option<String>,
+        user_agent: Option<String>,
+    ) -> Result<FirefighterRequest, FirefighterError> {
+        if let Some(pending) = self.get_pending_request(user_id).await? {
+            return Ok(pending);
+        }
+
+        let approvers: Vec<Uuid> = policy
+            .approver_ids
+            .iter()
+            .copied()
+            .filter(|id| *id != user_id)
+            .collect();
+        if approvers.is_empty() {
+            return Err(FirefighterError::Forbidden(
+                "No approver other than the requester is designated for firefighter access"
+                    .to_string(),
+            ));
+        }
+
+        let request = sqlx::query_as::<_, FirefighterRequest>(
+            r#"
+            INSERT INTO firefighter_requests
+                (user_id, justification, duration_minutes, approver_ids, ip_address, user_agent, scope, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, NOW() + make_interval(mins => $8))
+            RETURNING *
+            "#,
+        )
+        .bind(user_id)
+        .bind(&justification)
+        .bind(duration)
+        .bind(&approvers)
+        .bind(ip)
+        .bind(user_agent)
+        .bind(scope.map(Json))
+        .bind(policy.request_ttl_minutes)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let _ = self
+            .audit_service
+            .log(
+                user_id,
+                "firefighter.requested",
+                "firefighter_request",
+                None,
+                None,
+                Some(serde_json::json!({
+                    "request_id": request.id,
+                    "justification": justification,
+                    "duration_minutes": duration,
+                    "approver_ids": approvers,
+                    "scope": request.scope,
+                })),
+                None,
+            )
+            .await;
+
+        let requester = self.display_name(user_id).await;
+        let reach = if request.scope.is_some() {
+            "scoped"
+        } else {
+            "full"
+        };
+        self.notify(
+            &approvers,
+            &format!(
+                "{} requests {} firefighter access for {} minutes: {}",
+                requester, reach, duration, justification
+            ),
+        )
+        .await;
+
+        Ok(request)
+    }
+
+    /// The user's request still waiting for a decision, if any
+    pub async fn get_pending_request(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Option<FirefighterRequest>, FirefighterError> {
+        let request = sqlx::query_as::<_, FirefighterRequest>(
+            r#"
+            SELECT * FROM firefighter_requests
+            WHERE user_id = $1 AND status = 'PENDING' AND expires_at > NOW()
+            ORDER BY requested_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(request)
+    }
+
+    /// Requests waiting on this approver, oldest first
+    pub async fn list_pending_approvals(
+        &self,
+        approver_id: Uuid,
+    ) -> Result<Vec<FirefighterRequest>, FirefighterError> {
+        self.expire_lapsed_requests().await?;
+        let requests = sqlx::query_as::<_, FirefighterRequest>(
+            r#"
+            SELECT * FROM firefighter_requests
+            WHERE status = 'PENDING' AND $1 = ANY(approver_ids) AND user_id <> $1
+            ORDER BY requested_at
+            "#,
+        )
+        .bind(approver_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(requests)
+    }
+
+    /// Approve a pending request and activate the requester's session in
+    /// the same transaction
+    pub async fn approve_request(
+        &self,
+        approver_id: Uuid,
+        request_id: Uuid,
+        reason: Option<String>,
+    ) -> Result<FirefighterSession, FirefighterError> {
+        let mut tx = self.pool.begin().await?;
+        let Some(mut request) = self
+            .decide(
+                &mut tx,
+                approver_id,
+                request_id,
+                "APPROVED",
+                reason.as_deref(),
+            )
+            .await?
+        else {
+            return Err(self.decision_error(approver_id, request_id).await);
+        };
+
+        let session = self
+            .activate(
+                &mut tx,
+                request.user_id,
+                &request.justification,
+                request.duration_minutes,
+                request.scope.as_deref(),
+                request.ip_address.clone(),
+                request.user_agent.clone(),
+            )
+            .await?;
+        sqlx::query("UPDATE firefighter_requests SET session_id = $1 WHERE id = $2")
+            .bind(session.id)
+            .bind(request.id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        request.session_id = Some(session.id);
+
+        self.log_decision(approver_id, &request, "firefighter.approved")
+            .await;
+        self.log_activation(&session, request.duration_minutes, Some(approver_id))
+            .await;
+        self.notify(
+            &[request.user_id],
+            "Your firefighter access request was approved; elevated access is now active.",
+        )
+        .await;
+
+        Ok(session)
+    }
+
+    pub async fn reject_request(
+        &self,
+        approver_id: Uuid,
+        request_id: Uuid,
+        reason: Option<String>,
+    ) -> Result<FirefighterRequest, FirefighterError> {
+        let mut conn = self.pool.acquire().await?;
+        let Some(request) = self
+            .decide(
+                &mut conn,
+                approver_id,
+                request_id,
+                "REJECTED",
+                reason.as_deref(),
+            )
+            .await?
+        else {
+            return Err(self.decision_error(approver_id, request_id).await);
+        };
+
+        self.log_decision(approver_id, &request, "firefighter.rejected")
+            .await;
+        let message = match &request.decision_reason {
+            Some(reason) => format!("Your firefighter access request was rejected: {}", reason),
+            None => "Your firefighter access request was rejected.".to_string(),
+        };
+        self.notify(&[request.user_id], &message).await;
+
+        Ok(request)
+    }
+
+    /// Settle a request the approver may decide on; None when it isn't
+    /// pending or isn't theirs to decide
+    async fn decide(
+        &self,
+        conn: &mut sqlx::PgConnection,
+        approver_id: Uuid,
+        request_id: Uuid,
+        status: &str,
+        reason: Option<&str>,
+    ) -> Result<Option<FirefighterRequest>, FirefighterError> {
+        let request = sqlx::query_as::<_, FirefighterRequest>(
+            r#"
+            UPDATE firefighter_requests
+            SET status = $3, decided_by = $2, decided_at = NOW(), decision_reason = $4
+            WHERE id = $1 AND status = 'PENDING' AND expires_at > NOW()
+              AND $2 = ANY(approver_ids) AND user_id <> $2
+            RETURNING *
+            "#,
+        )
+        .bind(request_id)
+        .bind(approver_id)
+        .bind(status)
+        .bind(reason)
+        .fetch_optional(&mut *conn)
+        .await?;
+        Ok(request)
+    }
+
+    /// Why `decide` found nothing to settle
+    async fn decision_error(&self, approver_id: Uuid, request_id: Uuid) -> FirefighterError {
+        let request = sqlx::query_as::<_, FirefighterRequest>(
+            "SELECT * FROM firefighter_requests WHERE id = $1",
+        )
+        .bind(request_id)
+        .fetch_optional(&self.pool)
+        .await;
+        match request {
+            Err(e) => e.into(),
+            Ok(None) => FirefighterError::RequestNotFound,
+            Ok(Some(r)) if r.user_id == approver_id => FirefighterError::Forbidden(
+                "Requesters cannot approve their own firefighter access".to_string(),
+            ),
+            Ok(Some(r)) if !r.approver_ids.contains(&approver_id) => FirefighterError::Forbidden(
+                "Not a designated approver for this request".to_string(),
+            ),
+            Ok(Some(r)) if r.status == "PENDING" => {
+                FirefighterError::Conflict("Request has expired".to_string())
+            }
+            Ok(Some(r)) => FirefighterError::Conflict(format!(
+                "Request is already {}",
+                r.status.to_lowercase()
+            )),
+        }
+    }
+
+    async fn expire_lapsed_requests(&self) -> Result<(), FirefighterError> {
+        sqlx::query(
+            "UPDATE firefighter_requests SET status = 'EXPIRED' WHERE status = 'PENDING' AND expires_at <= NOW()",
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn log_decision(&self, approver_id: Uuid, request: &FirefighterRequest, action: &str) {
+        let _ = self
+            .audit_service
+            .log(
+                approver_id,
+                action,
+                "firefighter_request",
+                None,
+                None,
+                Some(serde_json::json!({
+                    "request_id": request.id,
+                    "requester_id": request.user_id,
+                    "reason": request.decision_reason,
+                    "session_id": request.session_id,
+                })),
+                None,
+            )
+            .await;
+    }
+
+    // ========================================================================
+    // APPROVAL POLICIES
+    // ========================================================================
+
+    /// The policy set for exactly this scope; None means the global policy
+    pub async fn get_approval_policy(
+        &self,
+        tenant_id: Option<Uuid>,
+    ) -> Result<Option<FirefighterApprovalPolicy>, FirefighterError> {
+        let policy = sqlx::query_as::<_, FirefighterApprovalPolicy>(
+            "SELECT * FROM firefighter_approval_policies WHERE tenant_id IS NOT DISTINCT FROM $1",
+        )
+        .bind(tenant_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(policy)
+    }
+
+    /// Create or replace the policy for the input's tenant scope
+    pub async fn save_approval_policy(
+        &self,
+        input: SaveApprovalPolicyInput,
+        updated_by: Uuid,
+    ) -> Result<FirefighterApprovalPolicy, FirefighterError> {
+        let ttl = input
+            .request_ttl_minutes
+            .unwrap_or(DEFAULT_REQUEST_TTL_MINUTES);
+        if !(1..=MAX_REQUEST_TTL_MINUTES).contains(&ttl) {
+            return Err(FirefighterError::InvalidInput(format!(
+                "request_ttl_minutes must be between 1 and {}",
+                MAX_REQUEST_TTL_MINUTES
+            )));
+        }
+        let mut approvers = input.approver_ids;
+        approvers.sort();
+        approvers.dedup();
+        if input.requires_approval && approvers.is_empty() {
+            return Err(FirefighterError::InvalidInput(
+                "At least one approver is required when approval is required".to_string(),
+            ));
+        }
+
+        let policy = sqlx::query_as::<_, FirefighterApprovalPolicy>(
+            r#"
+            INSERT INTO firefighter_approval_policies
+                (tenant_id, requires_approval, approver_ids, request_ttl_minutes, updated_by)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT ((COALESCE(tenant_id, '00000000-0000-0000-0000-000000000000'::uuid)))
+            DO UPDATE SET requires_approval = EXCLUDED.requires_approval,
+                          approver_ids = EXCLUDED.approver_ids,
+                          request_ttl_minutes = EXCLUDED.request_ttl_minutes,
+                          updated_by = EXCLUDED.updated_by, updated_at = NOW()
+            RETURNING *
+            "#,
+        )
+        .bind(input.tenant_id)
+        .bind(input.requires_approval)
+        .bind(&approvers)
+        .bind(ttl)
+        .bind(updated_by)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let _ = self
+            .audit_service
+            .log(
+                updated_by,
+                "firefighter.approval_policy_updated",
+                "firefighter_approval_policy",
+                None,
+                None,
+                Some(serde_json::to_value(&policy).unwrap_or_default()),
+                None,
+            )
+            .await;
+
+        Ok(policy)
+    }
+}
@@ -0,0 +1,122 @@
+use super::models::FirefighterSession;
+use super::service::{FirefighterError, FirefighterService};
+use crate::features::notifications::templates::GRANT_EXPIRING;
+use crate::utils::shutdown::Shutdown;
+
+impl FirefighterService {
+    // ========================================================================
+    // EXPIRY
+    // ========================================================================
+
+    /// Notify holders whose session ends within the warning window. Each
+    /// session is warned once.
+    pub async fn warn_expiring_sessions(&self) -> Result<usize, FirefighterError> {
+        let sessions = sqlx::query_as::<_, FirefighterSession>(
+            r#"
+            UPDATE firefighter_sessions SET expiry_warned_at = NOW()
+            WHERE deactivated_at IS NULL AND expiry_warned_at IS NULL
+              AND LEAST(expires_at, activated_at + make_interval(mins => $1))
+                  BETWEEN NOW() AND NOW() + make_interval(mins => $2)
+            RETURNING *
+            "#,
+        )
+        .bind(self.config.max_duration_minutes)
+        .bind(self.config.expiry_warning_minutes)
+        .fetch_all(&self.pool)
+        .await?;
+
+        for session in &sessions {
+            let ends_at = session.expires_at.min(
+                session.activated_at
+                    + chrono::Duration::minutes(self.config.max_duration_minutes as i64),
+            );
+            self.notify_template(
+                &[session.user_id],
+                &GRANT_EXPIRING,
+                &[("ends_at", &ends_at.to_rfc3339())],
+            )
+            .await;
+        }
+        Ok(sessions.len())
+    }
+
+    /// End sessions that are past their expiry or the configured maximum
+    /// duration, recording when access actually ran out
+    pub async fn revoke_expired_sessions(&self) -> Result<usize, FirefighterError> {
+        let sessions = sqlx::query_as::<_, FirefighterSession>(
+            r#"
+            UPDATE firefighter_sessions
+            SET deactivated_at = LEAST(expires_at, activated_at + make_interval(mins => $1)),
+                deactivation_reason = CASE WHEN expires_at <= activated_at + make_interval(mins => $1)
+                    THEN 'Expired' ELSE 'Maximum duration reached' END
+            WHERE deactivated_at IS NULL
+              AND LEAST(expires_at, activated_at + make_interval(mins => $1)) <= NOW()
+            RETURNING *
+            "#,
+        )
+        .bind(self.config.max_duration_minutes)
+        .fetch_all(&self.pool)
+        .await?;
+
+        for session in &sessions {
+            // Grants lapse at the scheduled expiry on their own, but not
+            // when the maximum duration cuts a session short
+            if session.scope.is_some() {
+                if let Err(e) = self.end_scoped_grants(session.id).await {
+                    tracing::error!(
+                        "Failed to end scoped grants for firefighter session {}: {}",
+                        session.id,
+                        e
+                    );
+                }
+            }
+            let _ = self
+                .audit_service
+                .log(
+                    session.user_id,
+                    "firefighter.expired",
+                    "firefighter_session",
+                    None,
+                    None,
+                    Some(serde_json::json!({
+                        "session_id": session.id,
+                        "reason": session.deactivation_reason,
+                        "activated_at": session.activated_at,
+                        "deactivated_at": session.deactivated_at,
+                    })),
+                    None,
+                )
+                .await;
+            self.notify(
+                &[session.user_id],
+                "Your firefighter access has ended and elevated permissions were revoked.",
+            )
+            .await;
+        }
+        Ok(sessions.len())
+    }
+
+    pub async fn start_expiry_task(self, shutdown: Shutdown) {
+        shutdown.clone().spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
+                self.config.sweep_interval_secs.max(10),
+            ));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = shutdown.cancelled() => break,
+                }
+                if let Err(e) = self.warn_expiring_sessions().await {
+                    tracing::error!("Failed to warn about expiring firefighter sessions: {}", e);
+                }
+                match self.revoke_expired_sessions().await {
+                    Ok(0) => {}
+                    Ok(n) => tracing::info!("Revoked {} expired firefighter sessions", n),
+                    Err(e) => {
+                        tracing::error!("Failed to revoke expired firefighter sessions: {}", e)
+                    }
+                }
+            }
+        });
+    }
+}
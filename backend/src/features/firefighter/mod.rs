@@ -1,5 +1,9 @@
+pub mod approval;
+pub mod expiry;
 pub mod models;
+pub mod review;
 pub mod routes;
+pub mod scope;
 pub mod service;
 
 pub use service::FirefighterService;
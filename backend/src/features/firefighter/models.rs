@@ -1,7 +1,11 @@
+use crate::features::auth::models::AuditLog;
+use crate::utils::validation::not_blank;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
 use sqlx::FromRow;
 use uuid::Uuid;
+use validator::Validate;
 
 #[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
 pub struct FirefighterSession {
@@ -16,16 +20,34 @@ pub struct FirefighterSession {
     pub deactivation_reason: Option<String>,
     pub ip_address: Option<String>,
     pub user_agent: Option<String>,
+    /// When the holder was told the session is about to end
+    pub expiry_warned_at: Option<DateTime<Utc>>,
+    /// None for broad elevation
+    pub scope: Option<Json<FirefighterScope>>,
 }
 
-#[derive(Debug, Deserialize)]
+/// Limits a session to parts of the graph. Access covers the subtrees under
+/// `entity_ids` (everything, when empty), only entities of `class_ids` when
+/// any are given, and never the subtrees under `exclude_entity_ids`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct FirefighterScope {
+    pub entity_ids: Vec<Uuid>,
+    pub class_ids: Vec<Uuid>,
+    pub exclude_entity_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
 pub struct RequestElevationInput {
     pub password: String,
+    #[validate(custom = "not_blank")]
     pub justification: String,
     pub duration_minutes: Option<i32>,
+    /// Omit for broad elevation
+    pub scope: Option<FirefighterScope>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct DeactivateInput {
     pub reason: Option<String>,
 }
@@ -34,4 +56,137 @@ pub struct DeactivateInput {
 pub struct FirefighterStatus {
     pub is_active: bool,
     pub session: Option<FirefighterSession>,
+    /// Request still waiting for a second approver
+    pub pending_request: Option<FirefighterRequest>,
+}
+
+/// A session with the audit events recorded under it, for post-incident review
+#[derive(Debug, Serialize)]
+pub struct FirefighterSessionActions {
+    pub session: FirefighterSession,
+    pub actions: Vec<AuditLog>,
+}
+
+// ============================================================================
+// POST-INCIDENT REVIEW
+// ============================================================================
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct FirefighterReview {
+    pub session_id: Uuid,
+    pub reviewer_id: Uuid,
+    pub notes: String,
+    pub signed_off_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, FromRow, Clone)]
+pub struct TouchedEntity {
+    pub id: Uuid,
+    pub display_name: String,
+    pub class_name: String,
+    /// Audit actions recorded against the entity during the session
+    #[sqlx(skip)]
+    pub actions: Vec<String>,
+}
+
+/// Everything a reviewer needs to close out a firefighter session
+#[derive(Debug, Serialize)]
+pub struct FirefighterReviewReport {
+    pub session: FirefighterSession,
+    pub holder_name: String,
+    /// Role granted for the session
+    pub elevated_role: String,
+    /// None while the session is still active
+    pub ended_at: Option<DateTime<Utc>>,
+    /// The two-person approval, when the session needed one
+    pub approval: Option<FirefighterRequest>,
+    pub approved_by_name: Option<String>,
+    pub actions: Vec<AuditLog>,
+    pub entities_touched: Vec<TouchedEntity>,
+    pub review: Option<FirefighterReview>,
+    pub generated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReviewReportQuery {
+    /// "json" (default) or "pdf"
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct SignOffReviewInput {
+    #[validate(custom = "not_blank")]
+    pub notes: String,
+}
+
+// ============================================================================
+// TWO-PERSON APPROVAL
+// ============================================================================
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct FirefighterRequest {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub justification: String,
+    pub duration_minutes: i32,
+    pub approver_ids: Vec<Uuid>,
+    /// PENDING, APPROVED, REJECTED or EXPIRED
+    pub status: String,
+    pub decided_by: Option<Uuid>,
+    pub decided_at: Option<DateTime<Utc>>,
+    pub decision_reason: Option<String>,
+    pub session_id: Option<Uuid>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub scope: Option<Json<FirefighterScope>>,
+    pub requested_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// What a request for elevation led to
+#[derive(Debug, Clone)]
+pub enum ElevationOutcome {
+    Activated(FirefighterSession),
+    PendingApproval(FirefighterRequest),
+}
+
+impl ElevationOutcome {
+    pub fn into_session(self) -> Option<FirefighterSession> {
+        match self {
+            ElevationOutcome::Activated(session) => Some(session),
+            ElevationOutcome::PendingApproval(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct FirefighterApprovalPolicy {
+    pub id: Uuid,
+    /// None for the global policy
+    pub tenant_id: Option<Uuid>,
+    pub requires_approval: bool,
+    pub approver_ids: Vec<Uuid>,
+    pub request_ttl_minutes: i32,
+    pub updated_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct SaveApprovalPolicyInput {
+    pub tenant_id: Option<Uuid>,
+    pub requires_approval: bool,
+    #[serde(default)]
+    pub approver_ids: Vec<Uuid>,
+    pub request_ttl_minutes: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApprovalPolicyQuery {
+    pub tenant_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct DecideRequestInput {
+    pub reason: Option<String>,
 }
@@ -0,0 +1,291 @@
+use super::models::{
+    FirefighterRequest, FirefighterReview, FirefighterReviewReport, FirefighterSession,
+    TouchedEntity,
+};
+use super::service::{FirefighterError, FirefighterService};
+use crate::utils::pdf::TextPdf;
+use chrono::Utc;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+impl FirefighterService {
+    // ========================================================================
+    // POST-INCIDENT REVIEW
+    // ========================================================================
+
+    /// Who held the session, when, with what scope and approval, and what
+    /// they did with it
+    pub async fn review_report(
+        &self,
+        session_id: Uuid,
+    ) -> Result<FirefighterReviewReport, FirefighterError> {
+        let recorded = self.session_actions(session_id).await?;
+        let session = recorded.session;
+        let actions = recorded.actions;
+
+        let approval = sqlx::query_as::<_, FirefighterRequest>(
+            "SELECT * FROM firefighter_requests WHERE session_id = $1",
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        let review = sqlx::query_as::<_, FirefighterReview>(
+            "SELECT * FROM firefighter_reviews WHERE session_id = $1",
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        // Entities in the order they were first touched, with what was done to each
+        let mut touched_ids: Vec<Uuid> = Vec::new();
+        let mut actions_by_entity: HashMap<Uuid, Vec<String>> = HashMap::new();
+        for action in &actions {
+            if let Some(target_id) = action.target_id {
+                let entry = actions_by_entity.entry(target_id).or_insert_with(|| {
+                    touched_ids.push(target_id);
+                    Vec::new()
+                });
+                if !entry.contains(&action.action) {
+                    entry.push(action.action.clone());
+                }
+            }
+        }
+        let mut entities: HashMap<Uuid, TouchedEntity> = sqlx::query_as::<_, TouchedEntity>(
+            r#"
+            SELECT e.id, e.display_name, c.name AS class_name
+            FROM entities e
+            JOIN classes c ON c.id = e.class_id
+            WHERE e.id = ANY($1)
+            "#,
+        )
+        .bind(&touched_ids)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|e| (e.id, e))
+        .collect();
+        let entities_touched = touched_ids
+            .iter()
+            .filter_map(|id| {
+                let mut entity = entities.remove(id)?;
+                entity.actions = actions_by_entity.remove(id).unwrap_or_default();
+                Some(entity)
+            })
+            .collect();
+
+        let approved_by_name = match approval.as_ref().and_then(|a| a.decided_by) {
+            Some(approver_id) => Some(self.display_name(approver_id).await),
+            None => None,
+        };
+
+        Ok(FirefighterReviewReport {
+            holder_name: self.display_name(session.user_id).await,
+            elevated_role: self.display_name(session.elevated_role_id).await,
+            ended_at: ended_at(&session),
+            approval,
+            approved_by_name,
+            actions,
+            entities_touched,
+            review,
+            generated_at: Utc::now(),
+            session,
+        })
+    }
+
+    /// Close out an ended session. Holders can't review their own session,
+    /// and a session is signed off once.
+    pub async fn sign_off_review(
+        &self,
+        reviewer_id: Uuid,
+        session_id: Uuid,
+        notes: &str,
+    ) -> Result<FirefighterReview, FirefighterError> {
+        let notes = notes.trim();
+        if notes.is_empty() {
+            return Err(FirefighterError::InvalidInput(
+                "Review notes are required".to_string(),
+            ));
+        }
+        let session = sqlx::query_as::<_, FirefighterSession>(
+            "SELECT * FROM firefighter_sessions WHERE id = $1",
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(FirefighterError::NotFound)?;
+
+        if session.user_id == reviewer_id {
+            return Err(FirefighterError::Forbidden(
+                "Session holders cannot review their own session".to_string(),
+            ));
+        }
+        if ended_at(&session).is_none() {
+            return Err(FirefighterError::Conflict(
+                "Session is still active; end it before signing off".to_string(),
+            ));
+        }
+
+        let review = sqlx::query_as::<_, FirefighterReview>(
+            r#"
+            INSERT INTO firefighter_reviews (session_id, reviewer_id, notes)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (session_id) DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(session_id)
+        .bind(reviewer_id)
+        .bind(notes)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| {
+            FirefighterError::Conflict("Session has already been signed off".to_string())
+        })?;
+
+        let _ = self
+            .audit_service
+            .log(
+                reviewer_id,
+                "firefighter.reviewed",
+                "firefighter_session",
+                None,
+                None,
+                Some(serde_json::json!({
+                    "session_id": session_id,
+                    "holder_id": session.user_id,
+                    "notes": notes,
+                })),
+                None,
+            )
+            .await;
+
+        Ok(review)
+    }
+}
+
+fn ended_at(session: &FirefighterSession) -> Option<chrono::DateTime<Utc>> {
+    session
+        .deactivated_at
+        .or_else(|| (session.expires_at <= Utc::now()).then_some(session.expires_at))
+}
+
+/// The report as a printable document for the incident file
+pub fn render_review_pdf(report: &FirefighterReviewReport) -> Vec<u8> {
+    let session = &report.session;
+    let time = |t: chrono::DateTime<Utc>| t.format("%Y-%m-%d %H:%M:%S UTC").to_string();
+
+    let mut pdf = TextPdf::new("Firefighter Session Review");
+    pdf.line(&format!("Session {}", session.id))
+        .line(&format!("Generated {}", time(report.generated_at)));
+
+    pdf.heading("Who")
+        .line(&format!("{} ({})", report.holder_name, session.user_id))
+        .line(&format!(
+            "From {} / {}",
+            session.ip_address.as_deref().unwrap_or("unknown address"),
+            session.user_agent.as_deref().unwrap_or("unknown client")
+        ));
+
+    pdf.heading("When")
+        .line(&format!("Activated {}", time(session.activated_at)))
+        .line(&format!("Scheduled to expire {}", time(session.expires_at)));
+    match report.ended_at {
+        Some(ended) => pdf.line(&format!(
+            "Ended {}{}",
+            time(ended),
+            session
+                .deactivation_reason
+                .as_deref()
+                .map(|r| format!(" ({})", r))
+                .unwrap_or_default()
+        )),
+        None => pdf.line("Still active"),
+    };
+
+    pdf.heading("Scope")
+        .line(&format!("Elevated role: {}", report.elevated_role))
+        .line(&format!("Justification: {}", session.justification));
+    match &session.scope {
+        Some(scope) => {
+            let ids = |ids: &[Uuid]| match ids {
+                [] => "none".to_string(),
+                ids => ids
+                    .iter()
+                    .map(Uuid::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            };
+            pdf.line(&format!("Limited to subtrees: {}", ids(&scope.entity_ids)))
+                .line(&format!("Limited to classes: {}", ids(&scope.class_ids)))
+                .line(&format!(
+                    "Excluded subtrees: {}",
+                    ids(&scope.exclude_entity_ids)
+                ))
+        }
+        None => pdf.line("Unrestricted elevation"),
+    };
+
+    pdf.heading("Approval");
+    match &report.approval {
+        Some(approval) => pdf
+            .line(&format!(
+                "Requested {}, approved by {} at {}",
+                time(approval.requested_at),
+                report.approved_by_name.as_deref().unwrap_or("unknown"),
+                approval.decided_at.map(time).unwrap_or_default()
+            ))
+            .line(&format!(
+                "Approval note: {}",
+                approval.decision_reason.as_deref().unwrap_or("none")
+            )),
+        None => pdf.line("Activated without a second approver"),
+    };
+
+    pdf.heading(&format!("Actions taken ({})", report.actions.len()));
+    if report.actions.is_empty() {
+        pdf.line("None recorded");
+    }
+    for action in &report.actions {
+        pdf.line(&format!(
+            "{}  {} on {}{}",
+            time(action.created_at),
+            action.action,
+            action.target_type,
+            action
+                .target_id
+                .map(|id| format!(" {}", id))
+                .unwrap_or_default()
+        ));
+    }
+
+    pdf.heading(&format!(
+        "Entities touched ({})",
+        report.entities_touched.len()
+    ));
+    if report.entities_touched.is_empty() {
+        pdf.line("None");
+    }
+    for entity in &report.entities_touched {
+        pdf.line(&format!(
+            "{} [{}] {}: {}",
+            entity.display_name,
+            entity.class_name,
+            entity.id,
+            entity.actions.join(", ")
+        ));
+    }
+
+    pdf.heading("Review sign-off");
+    match &report.review {
+        Some(review) => pdf
+            .line(&format!(
+                "Signed off by {} at {}",
+                review.reviewer_id,
+                time(review.signed_off_at)
+            ))
+            .line(&review.notes),
+        None => pdf.line("Awaiting reviewer sign-off"),
+    };
+
+    pdf.render()
+}
@@ -1,22 +1,65 @@
+use crate::config::Config;
 use crate::features::auth::jwt::Claims;
 use crate::features::firefighter::models::{
-    DeactivateInput, FirefighterSession, FirefighterStatus, RequestElevationInput,
+    ApprovalPolicyQuery, DeactivateInput, DecideRequestInput, ElevationOutcome,
+    FirefighterApprovalPolicy, FirefighterRequest, FirefighterReview, FirefighterSession,
+    FirefighterSessionActions, FirefighterStatus, RequestElevationInput, ReviewReportQuery,
+    SaveApprovalPolicyInput, SignOffReviewInput,
 };
+use crate::features::firefighter::review::render_review_pdf;
 use crate::features::firefighter::service::{FirefighterError, FirefighterService};
+use crate::middleware::abac::{enforce_route_permissions, RoutePermission};
+use crate::utils::api_error::ApiError;
+use crate::utils::validation::ValidatedJson;
 use axum::{
-    extract::State,
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
     routing::{get, post},
     Extension, Json, Router,
 };
+use std::sync::Arc;
+use tower_cookies::Cookies;
 use uuid::Uuid;
 
+/// Permissions enforced by `enforce_route_permissions` for the routes below
+pub const ROUTE_PERMISSIONS: &[RoutePermission] = &[
+    RoutePermission::new("GET", "/sessions", "manage_system"),
+    RoutePermission::new("GET", "/sessions/:id/actions", "manage_system"),
+    RoutePermission::new("GET", "/sessions/:id/report", "manage_system"),
+    RoutePermission::new("POST", "/sessions/:id/review", "manage_system"),
+    RoutePermission::new("GET", "/approval-policy", "manage_system"),
+    RoutePermission::new("PUT", "/approval-policy", "manage_system"),
+];
+
 pub fn firefighter_routes() -> Router<FirefighterService> {
     Router::new()
         .route("/request", post(request_elevation_handler))
         .route("/status", get(get_status_handler))
         .route("/deactivate", post(deactivate_handler))
         .route("/sessions", get(list_sessions_handler))
+        .route("/sessions/:id/actions", get(session_actions_handler))
+        .route("/sessions/:id/report", get(review_report_handler))
+        .route("/sessions/:id/review", post(sign_off_review_handler))
+        .route("/requests", get(list_pending_approvals_handler))
+        .route("/requests/:id/approve", post(approve_request_handler))
+        .route("/requests/:id/reject", post(reject_request_handler))
+        .route(
+            "/approval-policy",
+            get(get_approval_policy_handler).put(save_approval_policy_handler),
+        )
+        .route_layer(axum::middleware::from_fn_with_state(
+            ROUTE_PERMISSIONS,
+            enforce_route_permissions,
+        ))
+}
+
+/// Elevation changes what the session may do, so it gets a new CSRF token
+fn rotate_csrf(cookies: &Cookies, config: Option<Extension<Arc<Config>>>) {
+    let csrf = config
+        .map(|Extension(c)| c.csrf.clone())
+        .unwrap_or_default();
+    crate::middleware::csrf::rotate_csrf_token(cookies, &csrf);
 }
 
 #[axum::debug_handler]
@@ -24,8 +67,10 @@ async fn request_elevation_handler(
     State(service): State<FirefighterService>,
     Extension(claims): Extension<Claims>,
     headers: axum::http::HeaderMap,
-    Json(input): Json<RequestElevationInput>,
-) -> Result<Json<FirefighterSession>, FirefighterError> {
+    config: Option<Extension<Arc<Config>>>,
+    cookies: Cookies,
+    ValidatedJson(input): ValidatedJson<RequestElevationInput>,
+) -> Result<Response, FirefighterError> {
     let user_id = Uuid::parse_str(&claims.sub).map_err(|_| FirefighterError::NotFound)?;
 
     let ip = headers
@@ -45,18 +90,28 @@ async fn request_elevation_handler(
         .and_then(|v| v.to_str().ok())
         .map(|s| s.to_string());
 
-    let session = service
-        .request_elevation(
+    let outcome = service
+        .request_scoped_elevation(
             user_id,
             &input.password,
             input.justification,
             input.duration_minutes,
+            input.scope,
             Some(ip),
             user_agent,
         )
         .await?;
 
-    Ok(Json(session))
+    match outcome {
+        ElevationOutcome::Activated(session) => {
+            rotate_csrf(&cookies, config);
+            Ok(Json(session).into_response())
+        }
+        // Nothing is elevated until a second approver signs off
+        ElevationOutcome::PendingApproval(request) => {
+            Ok((StatusCode::ACCEPTED, Json(request)).into_response())
+        }
+    }
 }
 
 #[axum::debug_handler]
@@ -73,47 +128,168 @@ async fn get_status_handler(
 async fn deactivate_handler(
     State(service): State<FirefighterService>,
     Extension(claims): Extension<Claims>,
-    Json(input): Json<DeactivateInput>,
+    config: Option<Extension<Arc<Config>>>,
+    cookies: Cookies,
+    ValidatedJson(input): ValidatedJson<DeactivateInput>,
 ) -> Result<StatusCode, FirefighterError> {
     let user_id = Uuid::parse_str(&claims.sub).map_err(|_| FirefighterError::NotFound)?;
     service.deactivate(user_id, input.reason).await?;
+    rotate_csrf(&cookies, config);
     Ok(StatusCode::OK)
 }
 
 #[axum::debug_handler]
 async fn list_sessions_handler(
     State(service): State<FirefighterService>,
-    Extension(claims): Extension<Claims>,
 ) -> Result<Json<Vec<FirefighterSession>>, FirefighterError> {
-    // Check for superadmin permission in claims
-    if !claims.permissions.iter().any(|p| p == "*" || p == "admin") {
-        return Err(FirefighterError::Forbidden(
-            "Only admins can view all sessions".to_string(),
-        ));
-    }
-
     let sessions = service.list_sessions(None, false, 50).await?;
     Ok(Json(sessions))
 }
 
-impl axum::response::IntoResponse for FirefighterError {
-    fn into_response(self) -> axum::response::Response {
-        let (status, error_message) = match self {
+/// Everything done under one session, for the post-incident review
+#[axum::debug_handler]
+async fn session_actions_handler(
+    State(service): State<FirefighterService>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<FirefighterSessionActions>, FirefighterError> {
+    let actions = service.session_actions(id).await?;
+    Ok(Json(actions))
+}
+
+/// `?format=pdf` for a printable copy; JSON otherwise
+#[axum::debug_handler]
+async fn review_report_handler(
+    State(service): State<FirefighterService>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<ReviewReportQuery>,
+) -> Result<Response, FirefighterError> {
+    let report = service.review_report(id).await?;
+    match query.format.as_deref().unwrap_or("json") {
+        "json" => Ok(Json(report).into_response()),
+        "pdf" => Ok((
+            [
+                (header::CONTENT_TYPE, "application/pdf".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"firefighter-session-{}.pdf\"", id),
+                ),
+            ],
+            render_review_pdf(&report),
+        )
+            .into_response()),
+        other => Err(FirefighterError::InvalidInput(format!(
+            "Unsupported report format '{}'; use json or pdf",
+            other
+        ))),
+    }
+}
+
+/// Close out an ended session after reviewing its report
+#[axum::debug_handler]
+async fn sign_off_review_handler(
+    State(service): State<FirefighterService>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+    ValidatedJson(input): ValidatedJson<SignOffReviewInput>,
+) -> Result<Json<FirefighterReview>, FirefighterError> {
+    let user_id = Uuid::parse_str(&claims.sub).map_err(|_| FirefighterError::NotFound)?;
+    let review = service.sign_off_review(user_id, id, &input.notes).await?;
+    Ok(Json(review))
+}
+
+// ============================================================================
+// TWO-PERSON APPROVAL
+// ============================================================================
+
+/// Requests the caller is a designated approver for
+#[axum::debug_handler]
+async fn list_pending_approvals_handler(
+    State(service): State<FirefighterService>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Vec<FirefighterRequest>>, FirefighterError> {
+    let user_id = Uuid::parse_str(&claims.sub).map_err(|_| FirefighterError::NotFound)?;
+    let requests = service.list_pending_approvals(user_id).await?;
+    Ok(Json(requests))
+}
+
+#[axum::debug_handler]
+async fn approve_request_handler(
+    State(service): State<FirefighterService>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+    ValidatedJson(input): ValidatedJson<DecideRequestInput>,
+) -> Result<Json<FirefighterSession>, FirefighterError> {
+    let user_id = Uuid::parse_str(&claims.sub).map_err(|_| FirefighterError::NotFound)?;
+    let session = service.approve_request(user_id, id, input.reason).await?;
+    Ok(Json(session))
+}
+
+#[axum::debug_handler]
+async fn reject_request_handler(
+    State(service): State<FirefighterService>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+    ValidatedJson(input): ValidatedJson<DecideRequestInput>,
+) -> Result<Json<FirefighterRequest>, FirefighterError> {
+    let user_id = Uuid::parse_str(&claims.sub).map_err(|_| FirefighterError::NotFound)?;
+    let request = service.reject_request(user_id, id, input.reason).await?;
+    Ok(Json(request))
+}
+
+/// The policy for one scope; leave out `tenant_id` for the global policy
+#[axum::debug_handler]
+async fn get_approval_policy_handler(
+    State(service): State<FirefighterService>,
+    Query(query): Query<ApprovalPolicyQuery>,
+) -> Result<Json<Option<FirefighterApprovalPolicy>>, FirefighterError> {
+    let policy = service.get_approval_policy(query.tenant_id).await?;
+    Ok(Json(policy))
+}
+
+#[axum::debug_handler]
+async fn save_approval_policy_handler(
+    State(service): State<FirefighterService>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(input): ValidatedJson<SaveApprovalPolicyInput>,
+) -> Result<Json<FirefighterApprovalPolicy>, FirefighterError> {
+    let user_id = Uuid::parse_str(&claims.sub).map_err(|_| FirefighterError::NotFound)?;
+    let policy = service.save_approval_policy(input, user_id).await?;
+    Ok(Json(policy))
+}
+
+impl IntoResponse for FirefighterError {
+    fn into_response(self) -> Response {
+        let (status, code, error_message) = match self {
             FirefighterError::DatabaseError(e) => {
-                (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+                (StatusCode::INTERNAL_SERVER_ERROR, "internal", e.to_string())
             }
-            FirefighterError::AuthError(e) => (StatusCode::UNAUTHORIZED, e.to_string()),
-            FirefighterError::InvalidCredentials => {
-                (StatusCode::UNAUTHORIZED, "Invalid credentials".to_string())
+            FirefighterError::AuthError(e) => (
+                StatusCode::UNAUTHORIZED,
+                "firefighter.auth_failed",
+                e.to_string(),
+            ),
+            FirefighterError::InvalidCredentials => (
+                StatusCode::UNAUTHORIZED,
+                "firefighter.invalid_credentials",
+                "Invalid credentials".to_string(),
+            ),
+            FirefighterError::Forbidden(e) => (StatusCode::FORBIDDEN, "firefighter.forbidden", e),
+            FirefighterError::NotFound => (
+                StatusCode::NOT_FOUND,
+                "firefighter.session_not_found",
+                "Session not found".to_string(),
+            ),
+            FirefighterError::RequestNotFound => (
+                StatusCode::NOT_FOUND,
+                "firefighter.request_not_found",
+                "Approval request not found".to_string(),
+            ),
+            FirefighterError::InvalidInput(e) => {
+                (StatusCode::BAD_REQUEST, "firefighter.invalid_input", e)
             }
-            FirefighterError::Forbidden(e) => (StatusCode::FORBIDDEN, e),
-            FirefighterError::NotFound => (StatusCode::NOT_FOUND, "Session not found".to_string()),
+            FirefighterError::Conflict(e) => (StatusCode::CONFLICT, "firefighter.conflict", e),
         };
 
-        let body = Json(serde_json::json!({
-            "error": error_message,
-        }));
-
-        (status, body).into_response()
+        ApiError::new(status, code, error_message).into_response()
     }
 }
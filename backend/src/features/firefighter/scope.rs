@@ -0,0 +1,200 @@
+use super::models::FirefighterScope;
+use super::service::{FirefighterError, FirefighterService};
+use chrono::{DateTime, Utc};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+/// Entities and classes a single scope may name, all lists combined
+const MAX_SCOPE_ITEMS: usize = 50;
+
+impl FirefighterService {
+    // ========================================================================
+    // SCOPED ACCESS
+    // ========================================================================
+
+    /// Dedupe the scope and check it names live entities and classes
+    pub(super) async fn validate_scope(
+        &self,
+        mut scope: FirefighterScope,
+    ) -> Result<FirefighterScope, FirefighterError> {
+        for ids in [
+            &mut scope.entity_ids,
+            &mut scope.class_ids,
+            &mut scope.exclude_entity_ids,
+        ] {
+            ids.sort();
+            ids.dedup();
+        }
+        if scope.entity_ids.is_empty() && scope.class_ids.is_empty() {
+            return Err(FirefighterError::InvalidInput(
+                "A scope needs at least one entity or class".to_string(),
+            ));
+        }
+        if scope.entity_ids.len() + scope.class_ids.len() + scope.exclude_entity_ids.len()
+            > MAX_SCOPE_ITEMS
+        {
+            return Err(FirefighterError::InvalidInput(format!(
+                "A scope may name at most {} entities and classes",
+                MAX_SCOPE_ITEMS
+            )));
+        }
+        if let Some(id) = scope
+            .exclude_entity_ids
+            .iter()
+            .find(|id| scope.entity_ids.contains(id))
+        {
+            return Err(FirefighterError::InvalidInput(format!(
+                "Entity {} is both granted and excluded",
+                id
+            )));
+        }
+
+        let entity_ids: Vec<Uuid> = scope
+            .entity_ids
+            .iter()
+            .chain(&scope.exclude_entity_ids)
+            .copied()
+            .collect();
+        let found: Vec<Uuid> =
+            sqlx::query_scalar("SELECT id FROM entities WHERE id = ANY($1) AND deleted_at IS NULL")
+                .bind(&entity_ids)
+                .fetch_all(&self.pool)
+                .await?;
+        if let Some(missing) = entity_ids.iter().find(|id| !found.contains(id)) {
+            return Err(FirefighterError::InvalidInput(format!(
+                "Entity {} not found",
+                missing
+            )));
+        }
+
+        let found: Vec<Uuid> = sqlx::query_scalar("SELECT id FROM classes WHERE id = ANY($1)")
+            .bind(&scope.class_ids)
+            .fetch_all(&self.pool)
+            .await?;
+        if let Some(missing) = scope.class_ids.iter().find(|id| !found.contains(id)) {
+            return Err(FirefighterError::InvalidInput(format!(
+                "Class {} not found",
+                missing
+            )));
+        }
+
+        Ok(scope)
+    }
+
+    /// Carry out a scope as temporary has_role assignments: an admin grant
+    /// per subtree (or one graph-wide grant limited to the classes) and a
+    /// deny per excluded subtree, all lapsing when the session expires.
+    /// Role assignments are unique per user and role, so each one gets its
+    /// own throwaway role. Returns the role of the first grant.
+    pub(super) async fn grant_scope(
+        &self,
+        conn: &mut PgConnection,
+        session_id: Uuid,
+        user_id: Uuid,
+        role_class_id: Uuid,
+        scope: &FirefighterScope,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Uuid, FirefighterError> {
+        let has_role_id: Uuid =
+            sqlx::query_scalar("SELECT id FROM relationship_types WHERE name = 'has_role' LIMIT 1")
+                .fetch_one(&mut *conn)
+                .await?;
+
+        let class_ids = (!scope.class_ids.is_empty()).then_some(&scope.class_ids);
+        let grants: Vec<(Option<Uuid>, bool)> = if scope.entity_ids.is_empty() {
+            vec![(None, false)]
+        } else {
+            scope
+                .entity_ids
+                .iter()
+                .map(|id| (Some(*id), false))
+                .collect()
+        };
+        let denies = scope.exclude_entity_ids.iter().map(|id| (Some(*id), true));
+
+        let mut first_role = None;
+        for (n, (scope_entity_id, is_deny)) in grants.into_iter().chain(denies).enumerate() {
+            let role_id: Uuid = sqlx::query_scalar(
+                r#"
+                INSERT INTO entities (class_id, display_name, attributes, created_by)
+                VALUES ($1, $2, $3, $4)
+                RETURNING id
+                "#,
+            )
+            .bind(role_class_id)
+            .bind(format!("firefighter-{}-{}", session_id, n + 1))
+            .bind(serde_json::json!({
+                "name": "firefighter",
+                "description": "Temporary role for a scoped firefighter session",
+                "level": 100,
+                "is_admin": true,
+                "firefighter_session_id": session_id,
+            }))
+            .bind(user_id)
+            .fetch_one(&mut *conn)
+            .await?;
+
+            let mut metadata = serde_json::json!({
+                "valid_from": Utc::now(),
+                "valid_until": expires_at,
+                "is_deny": is_deny,
+                "granted_by": user_id,
+                "firefighter_session_id": session_id,
+            });
+            if let Some(scope_entity_id) = scope_entity_id {
+                metadata["scope_entity_id"] = serde_json::json!(scope_entity_id);
+            }
+            // A deny shuts the whole excluded subtree, whatever its class
+            if let (Some(class_ids), false) = (class_ids, is_deny) {
+                metadata["scope_class_ids"] = serde_json::json!(class_ids);
+            }
+            sqlx::query(
+                r#"
+                INSERT INTO relationships (source_entity_id, target_entity_id, relationship_type_id, metadata, created_by)
+                VALUES ($1, $2, $3, $4, $1)
+                "#,
+            )
+            .bind(user_id)
+            .bind(role_id)
+            .bind(has_role_id)
+            .bind(metadata)
+            .execute(&mut *conn)
+            .await?;
+
+            first_role.get_or_insert(role_id);
+        }
+
+        first_role.ok_or_else(|| {
+            FirefighterError::InvalidInput("A scope needs at least one entity or class".to_string())
+        })
+    }
+
+    /// End a scoped session's assignments now rather than at their
+    /// scheduled expiry, and retire its throwaway roles
+    pub(super) async fn end_scoped_grants(&self, session_id: Uuid) -> Result<(), FirefighterError> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            r#"
+            UPDATE relationships
+            SET metadata = metadata || jsonb_build_object('valid_until', NOW(), 'revoked_at', NOW())
+            WHERE metadata->>'firefighter_session_id' = $1::text
+            "#,
+        )
+        .bind(session_id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            UPDATE entities SET deleted_at = NOW()
+            WHERE attributes->>'firefighter_session_id' = $1::text
+              AND class_id = (SELECT id FROM classes WHERE name = 'Role' LIMIT 1)
+              AND deleted_at IS NULL
+            "#,
+        )
+        .bind(session_id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+}
@@ -1,11 +1,19 @@
-use super::models::{FirefighterSession, FirefighterStatus};
+use super::models::{
+    ElevationOutcome, FirefighterScope, FirefighterSession, FirefighterSessionActions,
+    FirefighterStatus,
+};
+use crate::config::FirefighterConfig;
 use crate::features::auth::models::User;
 use crate::features::auth::service::AuthError;
+use crate::features::notifications::models::NotificationCategory;
+use crate::features::notifications::templates::BuiltinNotification;
+use crate::features::notifications::NotificationService;
 use crate::features::ontology::service::OntologyService;
 use crate::features::system::AuditService;
 use argon2::{password_hash::PasswordHash, Argon2, PasswordVerifier};
 use chrono::{Duration, Utc};
-use sqlx::PgPool;
+use sqlx::types::Json;
+use sqlx::{PgConnection, PgPool};
 use thiserror::Error;
 use uuid::Uuid;
 
@@ -25,13 +33,24 @@ pub enum FirefighterError {
 
     #[error("Session not found")]
     NotFound,
+
+    #[error("Approval request not found")]
+    RequestNotFound,
+
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
 }
 
 #[derive(Clone)]
 pub struct FirefighterService {
-    pool: PgPool,
-    audit_service: AuditService,
-    ontology_service: OntologyService,
+    pub(super) pool: PgPool,
+    pub(super) audit_service: AuditService,
+    pub(super) ontology_service: OntologyService,
+    pub(super) config: FirefighterConfig,
+    notifications: NotificationService,
 }
 
 impl FirefighterService {
@@ -39,15 +58,26 @@ impl FirefighterService {
         pool: PgPool,
         audit_service: AuditService,
         ontology_service: OntologyService,
+        config: FirefighterConfig,
     ) -> Self {
         Self {
+            notifications: NotificationService::new(pool.clone()),
             pool,
             audit_service,
             ontology_service,
+            config,
         }
     }
 
-    /// Request firefighter mode (with password verification)
+    /// Deliver notifications with the users' channel preferences applied
+    pub fn with_notifications(mut self, notifications: NotificationService) -> Self {
+        self.notifications = notifications;
+        self
+    }
+
+    /// Request firefighter mode (with password verification). When the
+    /// requester's approval policy asks for a second person, this files a
+    /// pending request for the designated approvers instead of activating.
     pub async fn request_elevation(
         &self,
         user_id: Uuid,
@@ -56,7 +86,32 @@ impl FirefighterService {
         duration_minutes: Option<i32>,
         ip: Option<String>,
         user_agent: Option<String>,
-    ) -> Result<FirefighterSession, FirefighterError> {
+    ) -> Result<ElevationOutcome, FirefighterError> {
+        self.request_scoped_elevation(
+            user_id,
+            password,
+            justification,
+            duration_minutes,
+            None,
+            ip,
+            user_agent,
+        )
+        .await
+    }
+
+    /// Like `request_elevation`, but a scope limits the session to those
+    /// subtrees and classes instead of elevating everywhere
+    #[allow(clippy::too_many_arguments)]
+    pub async fn request_scoped_elevation(
+        &self,
+        user_id: Uuid,
+        password: &str,
+        justification: String,
+        duration_minutes: Option<i32>,
+        scope: Option<FirefighterScope>,
+        ip: Option<String>,
+        user_agent: Option<String>,
+    ) -> Result<ElevationOutcome, FirefighterError> {
         // 1. Find user and verify password
         let user = sqlx::query_as::<_, User>("SELECT * FROM unified_users WHERE id = $1")
             .bind(user_id)
@@ -74,7 +129,113 @@ impl FirefighterService {
             return Err(FirefighterError::InvalidCredentials);
         }
 
-        // 2. Find superadmin role entity ID
+        let duration = session_duration(&self.config, duration_minutes);
+        let scope = match scope {
+            Some(scope) => Some(self.validate_scope(scope).await?),
+            None => None,
+        };
+
+        // 2. Hold for a second approver if the policy says so
+        if let Some(policy) = self.effective_approval_policy(user_id).await? {
+            if policy.requires_approval {
+                let request = self
+                    .file_request(
+                        user_id,
+                        &policy,
+                        justification,
+                        duration,
+                        scope,
+                        ip,
+                        user_agent,
+                    )
+                    .await?;
+                return Ok(ElevationOutcome::PendingApproval(request));
+            }
+        }
+
+        // 3. Create session
+        let mut tx = self.pool.begin().await?;
+        let session = self
+            .activate(
+                &mut tx,
+                user_id,
+                &justification,
+                duration,
+                scope.as_ref(),
+                ip,
+                user_agent,
+            )
+            .await?;
+        tx.commit().await?;
+        self.log_activation(&session, duration, None).await;
+
+        Ok(ElevationOutcome::Activated(session))
+    }
+
+    /// Activate firefighter mode for a user without a password or approval,
+    /// for operators working directly on the deployment. The session is
+    /// bounded, audited and revoked like any other.
+    pub async fn grant_break_glass(
+        &self,
+        user_id: Uuid,
+        justification: String,
+        duration_minutes: Option<i32>,
+        user_agent: Option<String>,
+    ) -> Result<FirefighterSession, FirefighterError> {
+        if justification.trim().is_empty() {
+            return Err(FirefighterError::InvalidInput(
+                "A justification is required".to_string(),
+            ));
+        }
+        sqlx::query_scalar::<_, Uuid>("SELECT id FROM unified_users WHERE id = $1")
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or(AuthError::UserNotFound)?;
+        if self.get_active_session(user_id).await?.is_some() {
+            return Err(FirefighterError::Conflict(
+                "User already has an active firefighter session".to_string(),
+            ));
+        }
+
+        let duration = session_duration(&self.config, duration_minutes);
+        let mut tx = self.pool.begin().await?;
+        let session = self
+            .activate(
+                &mut tx,
+                user_id,
+                &justification,
+                duration,
+                None,
+                None,
+                user_agent,
+            )
+            .await?;
+        tx.commit().await?;
+        self.log_activation(&session, duration, None).await;
+        self.notify(
+            &[user_id],
+            "An operator granted you firefighter access; elevated access is now active.",
+        )
+        .await;
+
+        Ok(session)
+    }
+
+    /// Grant the superadmin role for `duration` minutes, or with a scope,
+    /// admin rights over just that part of the graph
+    #[allow(clippy::too_many_arguments)]
+    pub(super) async fn activate(
+        &self,
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        justification: &str,
+        duration: i32,
+        scope: Option<&FirefighterScope>,
+        ip: Option<String>,
+        user_agent: Option<String>,
+    ) -> Result<FirefighterSession, FirefighterError> {
+        // Find superadmin role entity ID
         let role_class = self
             .ontology_service
             .get_system_class("Role")
@@ -85,53 +246,119 @@ impl FirefighterService {
             "SELECT * FROM entities WHERE display_name = 'superadmin' AND class_id = $1",
         )
         .bind(role_class.id)
-        .fetch_optional(&self.pool)
+        .fetch_optional(&mut *conn)
         .await?
         .ok_or_else(|| FirefighterError::Forbidden("Superadmin role not found".to_string()))?;
 
-        // 3. Create session
         let activated_at = Utc::now();
-        let duration = duration_minutes.unwrap_or(60).clamp(15, 480);
         let expires_at = activated_at + Duration::minutes(duration as i64);
 
+        let session_id = Uuid::new_v4();
+        let elevated_role_id = match scope {
+            Some(scope) => {
+                self.grant_scope(conn, session_id, user_id, role_class.id, scope, expires_at)
+                    .await?
+            }
+            None => superadmin_role.id,
+        };
+
         let session = sqlx::query_as::<_, FirefighterSession>(
             r#"
             INSERT INTO firefighter_sessions 
-                (user_id, elevated_role_id, justification, activated_at, expires_at, ip_address, user_agent)
-            VALUES ($1, $2, $3, $4, $5, $6, $7)
+                (id, user_id, elevated_role_id, justification, activated_at, expires_at, ip_address, user_agent, scope)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
             RETURNING *
             "#
         )
+        .bind(session_id)
         .bind(user_id)
-        .bind(superadmin_role.id)
-        .bind(justification.clone())
+        .bind(elevated_role_id)
+        .bind(justification)
         .bind(activated_at)
         .bind(expires_at)
-        .bind(ip.clone())
-        .bind(user_agent.clone())
-        .fetch_one(&self.pool)
+        .bind(ip)
+        .bind(user_agent)
+        .bind(scope.map(Json))
+        .fetch_one(&mut *conn)
         .await?;
 
-        // 4. Audit Log
+        Ok(session)
+    }
+
+    pub(super) async fn log_activation(
+        &self,
+        session: &FirefighterSession,
+        duration: i32,
+        approved_by: Option<Uuid>,
+    ) {
         let _ = self
             .audit_service
             .log(
-                user_id,
+                session.user_id,
                 "firefighter.activated",
                 "firefighter_session",
-                Some(session.id),
+                None,
                 None,
                 Some(serde_json::json!({
-                    "justification": justification,
+                    "session_id": session.id,
+                    "justification": session.justification,
                     "duration_minutes": duration,
-                    "ip": ip,
-                    "user_agent": user_agent,
+                    "ip": session.ip_address,
+                    "user_agent": session.user_agent,
+                    "approved_by": approved_by,
+                    "scope": session.scope,
                 })),
                 None,
             )
             .await;
+    }
 
-        Ok(session)
+    pub(super) async fn display_name(&self, user_id: Uuid) -> String {
+        self.ontology_service
+            .get_entity(user_id)
+            .await
+            .map(|e| e.display_name)
+            .unwrap_or_else(|_| user_id.to_string())
+    }
+
+    /// Best effort: a failed notification must not block emergency access
+    pub(super) async fn notify(&self, user_ids: &[Uuid], message: &str) {
+        for user_id in user_ids {
+            if let Err(e) = self
+                .notifications
+                .notify(*user_id, NotificationCategory::Access, message)
+                .await
+            {
+                tracing::warn!(
+                    "Failed to notify {} about firefighter access: {}",
+                    user_id,
+                    e
+                );
+            }
+        }
+    }
+
+    /// Best effort, like `notify`, with a built-in notification worded for
+    /// each user's locale
+    pub(super) async fn notify_template(
+        &self,
+        user_ids: &[Uuid],
+        template: &BuiltinNotification,
+        values: &[(&str, &str)],
+    ) {
+        for user_id in user_ids {
+            if let Err(e) = self
+                .notifications
+                .notify_template(*user_id, template, values)
+                .await
+            {
+                tracing::warn!(
+                    "Failed to notify {} about firefighter access: {}",
+                    user_id,
+                    e
+                );
+            }
+        }
     }
 
     /// Check if user has active firefighter session
@@ -159,9 +386,11 @@ impl FirefighterService {
     /// Get current status
     pub async fn get_status(&self, user_id: Uuid) -> Result<FirefighterStatus, FirefighterError> {
         let session = self.get_active_session(user_id).await?;
+        let pending_request = self.get_pending_request(user_id).await?;
         Ok(FirefighterStatus {
             is_active: session.is_some(),
             session,
+            pending_request,
         })
     }
 
@@ -185,6 +414,9 @@ impl FirefighterService {
         .bind(session.id)
         .execute(&self.pool)
         .await?;
+        if session.scope.is_some() {
+            self.end_scoped_grants(session.id).await?;
+        }
 
         // Audit Log
         let _ = self
@@ -193,9 +425,9 @@ impl FirefighterService {
                 user_id,
                 "firefighter.deactivated",
                 "firefighter_session",
-                Some(session.id),
                 None,
-                Some(serde_json::json!({ "reason": reason })),
+                None,
+                Some(serde_json::json!({ "session_id": session.id, "reason": reason })),
                 None,
             )
             .await;
@@ -203,6 +435,31 @@ impl FirefighterService {
         Ok(())
     }
 
+    /// A session and everything its holder did while it was active
+    pub async fn session_actions(
+        &self,
+        session_id: Uuid,
+    ) -> Result<FirefighterSessionActions, FirefighterError> {
+        let session = sqlx::query_as::<_, FirefighterSession>(
+            "SELECT * FROM firefighter_sessions WHERE id = $1",
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(FirefighterError::NotFound)?;
+
+        let actions = self
+            .audit_service
+            .get_logs_for_firefighter_session(session_id)
+            .await
+            .map_err(|e| match e {
+                AuthError::DatabaseError(e) => FirefighterError::DatabaseError(e),
+                e => FirefighterError::AuthError(e),
+            })?;
+
+        Ok(FirefighterSessionActions { session, actions })
+    }
+
     /// List sessions (admin/audit view)
     pub async fn list_sessions(
         &self,
@@ -227,3 +484,30 @@ impl FirefighterService {
         Ok(sessions)
     }
 }
+
+/// Requested minutes, or the default, within the configured bounds
+pub(super) fn session_duration(config: &FirefighterConfig, requested: Option<i32>) -> i32 {
+    let max = config.max_duration_minutes.max(config.min_duration_minutes);
+    requested
+        .unwrap_or(config.default_duration_minutes)
+        .clamp(config.min_duration_minutes, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_duration_is_bounded() {
+        let config = FirefighterConfig::default();
+        assert_eq!(session_duration(&config, None), 60);
+        assert_eq!(session_duration(&config, Some(5)), 15);
+        assert_eq!(session_duration(&config, Some(10_000)), 480);
+
+        let config = FirefighterConfig {
+            max_duration_minutes: 30,
+            ..FirefighterConfig::default()
+        };
+        assert_eq!(session_duration(&config, None), 30);
+    }
+}
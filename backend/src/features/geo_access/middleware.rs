@@ -0,0 +1,56 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+use std::net::SocketAddr;
+
+use crate::features::geo_access::models::{AccessKind, GeoDecision};
+use crate::features::geo_access::service::GeoAccessService;
+use crate::middleware::auth::peek_user_id;
+use crate::utils::ip::client_ip;
+
+/// Logins are checked by `AuthService::login` once the user is known; the rest
+/// of the auth API stays reachable so blocked users can still log out.
+const EXCLUDED_PREFIX: &str = "/api/auth";
+
+/// Applies tenant country policies to authenticated API calls. Requests without
+/// a valid token are left to `auth_middleware` to reject.
+pub async fn geo_access_middleware(
+    State(service): State<GeoAccessService>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !service.has_policies() || request.uri().path().starts_with(EXCLUDED_PREFIX) {
+        return next.run(request).await;
+    }
+    let Some(user_id) = peek_user_id(request.headers(), request.extensions()).await else {
+        return next.run(request).await;
+    };
+
+    let ip = client_ip(
+        request.headers(),
+        request.extensions().get::<ConnectInfo<SocketAddr>>(),
+        service.trusted_proxies(),
+    );
+    match service.check(user_id, ip, AccessKind::Api).await {
+        Ok(GeoDecision::Allowed) | Ok(GeoDecision::Overridden) => next.run(request).await,
+        Ok(GeoDecision::Blocked) => (
+            StatusCode::FORBIDDEN,
+            Json(json!({ "error": "Access from your location is not permitted" })),
+        )
+            .into_response(),
+        Err(e) => {
+            // Fail closed: the policy exists for compliance reasons
+            tracing::error!("Geo access check failed: {}", e);
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(json!({ "error": "Unable to verify access location" })),
+            )
+                .into_response()
+        }
+    }
+}
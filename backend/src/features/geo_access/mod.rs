@@ -0,0 +1,6 @@
+pub mod middleware;
+pub mod models;
+pub mod routes;
+pub mod service;
+
+pub use service::GeoAccessService;
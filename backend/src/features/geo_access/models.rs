@@ -0,0 +1,83 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct GeoAccessPolicy {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    /// "allow" (only the listed countries) or "deny" (everything but the listed countries)
+    pub mode: String,
+    pub countries: Vec<String>,
+    pub restrict_login: bool,
+    pub restrict_api: bool,
+    pub block_unknown: bool,
+    pub description: Option<String>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl GeoAccessPolicy {
+    pub fn applies_to(&self, kind: AccessKind) -> bool {
+        match kind {
+            AccessKind::Login => self.restrict_login,
+            AccessKind::Api => self.restrict_api,
+        }
+    }
+
+    /// Whether a client in `country` passes this policy
+    pub fn permits(&self, country: Option<&str>) -> bool {
+        let Some(country) = country else {
+            return !self.block_unknown;
+        };
+        let listed = self
+            .countries
+            .iter()
+            .any(|c| c.eq_ignore_ascii_case(country));
+        match self.mode.as_str() {
+            "allow" => listed,
+            _ => !listed,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpsertGeoPolicyRequest {
+    pub mode: String,
+    pub countries: Vec<String>,
+    pub restrict_login: Option<bool>,
+    pub restrict_api: Option<bool>,
+    pub block_unknown: Option<bool>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessKind {
+    Login,
+    Api,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GeoDecision {
+    /// No policy applies, or the country passes it
+    Allowed,
+    /// The policy would block, but the user holds an active firefighter session
+    Overridden,
+    Blocked,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LookupQuery {
+    pub ip: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LookupResult {
+    pub ip: String,
+    pub country: Option<String>,
+    pub database_loaded: bool,
+}
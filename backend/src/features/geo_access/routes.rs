@@ -0,0 +1,98 @@
+use crate::features::auth::jwt::Claims;
+use crate::features::geo_access::models::{
+    GeoAccessPolicy, LookupQuery, LookupResult, UpsertGeoPolicyRequest,
+};
+use crate::features::geo_access::service::{GeoAccessError, GeoAccessService};
+use crate::middleware::abac::{enforce_route_permissions, RoutePermission};
+use crate::utils::api_error::ApiError;
+use crate::utils::validation::ValidatedJson;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::{get, put},
+    Extension, Json, Router,
+};
+use std::net::IpAddr;
+use uuid::Uuid;
+
+/// Permissions enforced by `enforce_route_permissions` for the routes below
+pub const ROUTE_PERMISSIONS: &[RoutePermission] = &[
+    RoutePermission::new("GET", "/policies", "manage_system"),
+    RoutePermission::new("PUT", "/policies/:tenant_id", "manage_system"),
+    RoutePermission::new("DELETE", "/policies/:tenant_id", "manage_system"),
+    RoutePermission::new("GET", "/lookup", "manage_system"),
+];
+
+pub fn geo_access_routes() -> Router<GeoAccessService> {
+    Router::new()
+        .route("/policies", get(list_policies_handler))
+        .route(
+            "/policies/:tenant_id",
+            put(upsert_policy_handler).delete(delete_policy_handler),
+        )
+        .route("/lookup", get(lookup_handler))
+        .route_layer(axum::middleware::from_fn_with_state(
+            ROUTE_PERMISSIONS,
+            enforce_route_permissions,
+        ))
+}
+
+#[axum::debug_handler]
+async fn list_policies_handler(
+    State(service): State<GeoAccessService>,
+) -> Result<Json<Vec<GeoAccessPolicy>>, GeoAccessError> {
+    Ok(Json(service.list_policies().await?))
+}
+
+#[axum::debug_handler]
+async fn upsert_policy_handler(
+    State(service): State<GeoAccessService>,
+    Extension(claims): Extension<Claims>,
+    Path(tenant_id): Path<Uuid>,
+    ValidatedJson(input): ValidatedJson<UpsertGeoPolicyRequest>,
+) -> Result<Json<GeoAccessPolicy>, GeoAccessError> {
+    let updated_by = Uuid::parse_str(&claims.sub).ok();
+    let policy = service.upsert_policy(tenant_id, input, updated_by).await?;
+    Ok(Json(policy))
+}
+
+#[axum::debug_handler]
+async fn delete_policy_handler(
+    State(service): State<GeoAccessService>,
+    Path(tenant_id): Path<Uuid>,
+) -> Result<StatusCode, GeoAccessError> {
+    service.delete_policy(tenant_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Which country an address resolves to, for checking policies before applying them
+#[axum::debug_handler]
+async fn lookup_handler(
+    State(service): State<GeoAccessService>,
+    Query(query): Query<LookupQuery>,
+) -> Result<Json<LookupResult>, GeoAccessError> {
+    let ip: IpAddr =
+        query.ip.trim().parse().map_err(|_| {
+            GeoAccessError::InvalidInput(format!("Invalid IP address: {}", query.ip))
+        })?;
+    Ok(Json(LookupResult {
+        ip: ip.to_string(),
+        country: service.country_for(ip),
+        database_loaded: service.database_loaded(),
+    }))
+}
+
+impl axum::response::IntoResponse for GeoAccessError {
+    fn into_response(self) -> axum::response::Response {
+        let (status, code) = match &self {
+            GeoAccessError::DatabaseError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+            GeoAccessError::Forbidden(_) => (StatusCode::FORBIDDEN, "geo_access.forbidden"),
+            GeoAccessError::InvalidInput(_) => {
+                (StatusCode::BAD_REQUEST, "geo_access.invalid_input")
+            }
+            GeoAccessError::NotFound => (StatusCode::NOT_FOUND, "geo_access.not_found"),
+        };
+
+        ApiError::new(status, code, self.to_string()).into_response()
+    }
+}
@@ -0,0 +1,323 @@
+use super::models::{AccessKind, GeoAccessPolicy, GeoDecision, UpsertGeoPolicyRequest};
+use crate::features::firefighter::service::FirefighterService;
+use crate::utils::ip::Cidr;
+use crate::utils::shutdown::Shutdown;
+use maxminddb::{geoip2, Reader};
+use moka::future::Cache;
+use serde_json::json;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use thiserror::Error;
+use uuid::Uuid;
+
+/// Other instances pick up policy changes within this interval
+const POLICY_REFRESH_SECS: u64 = 60;
+
+#[derive(Error, Debug)]
+pub enum GeoAccessError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    #[error("Policy not found")]
+    NotFound,
+}
+
+#[derive(Clone)]
+pub struct GeoAccessService {
+    pool: PgPool,
+    reader: Option<Arc<Reader<Vec<u8>>>>,
+    trusted_proxies: Arc<Vec<Cidr>>,
+    firefighter_service: FirefighterService,
+    policies: Arc<RwLock<HashMap<Uuid, GeoAccessPolicy>>>,
+    // user entity id -> tenant id
+    tenant_cache: Cache<Uuid, Option<Uuid>>,
+}
+
+impl GeoAccessService {
+    /// `database_path` points at a MaxMind-format country database (e.g.
+    /// GeoLite2-Country.mmdb). An empty path disables lookups, so every
+    /// client's country is unknown.
+    pub fn new(
+        pool: PgPool,
+        database_path: &str,
+        trusted_proxies: Vec<Cidr>,
+        firefighter_service: FirefighterService,
+    ) -> Result<Self, String> {
+        let reader = if database_path.trim().is_empty() {
+            None
+        } else {
+            let reader = Reader::open_readfile(database_path.trim())
+                .map_err(|e| format!("Failed to open GeoIP database {}: {}", database_path, e))?;
+            tracing::info!(
+                "Loaded GeoIP database {} ({})",
+                database_path,
+                reader.metadata.database_type
+            );
+            Some(Arc::new(reader))
+        };
+
+        Ok(Self {
+            pool,
+            reader,
+            trusted_proxies: Arc::new(trusted_proxies),
+            firefighter_service,
+            policies: Arc::new(RwLock::new(HashMap::new())),
+            tenant_cache: Cache::builder()
+                .max_capacity(10_000)
+                .time_to_live(Duration::from_secs(300))
+                .build(),
+        })
+    }
+
+    pub fn trusted_proxies(&self) -> &[Cidr] {
+        &self.trusted_proxies
+    }
+
+    pub fn database_loaded(&self) -> bool {
+        self.reader.is_some()
+    }
+
+    /// ISO country code for an address, if the database knows it
+    pub fn country_for(&self, ip: IpAddr) -> Option<String> {
+        let reader = self.reader.as_ref()?;
+        let record: geoip2::Country = reader.lookup(ip).ok()?;
+        record
+            .country
+            .and_then(|c| c.iso_code)
+            .map(|code| code.to_ascii_uppercase())
+    }
+
+    // ========================================================================
+    // POLICIES
+    // ========================================================================
+
+    pub async fn upsert_policy(
+        &self,
+        tenant_id: Uuid,
+        req: UpsertGeoPolicyRequest,
+        updated_by: Option<Uuid>,
+    ) -> Result<GeoAccessPolicy, GeoAccessError> {
+        if !["allow", "deny"].contains(&req.mode.as_str()) {
+            return Err(GeoAccessError::InvalidInput(
+                "mode must be 'allow' or 'deny'".to_string(),
+            ));
+        }
+        let mut countries = Vec::with_capacity(req.countries.len());
+        for code in &req.countries {
+            let code = code.trim().to_ascii_uppercase();
+            if code.len() != 2 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+                return Err(GeoAccessError::InvalidInput(format!(
+                    "Invalid ISO 3166-1 alpha-2 country code: {}",
+                    code
+                )));
+            }
+            if !countries.contains(&code) {
+                countries.push(code);
+            }
+        }
+        if req.mode == "allow" && countries.is_empty() {
+            return Err(GeoAccessError::InvalidInput(
+                "An allow policy needs at least one country".to_string(),
+            ));
+        }
+
+        let policy = sqlx::query_as::<_, GeoAccessPolicy>(
+            r#"
+            INSERT INTO geo_access_policies
+                (tenant_id, mode, countries, restrict_login, restrict_api, block_unknown, description, created_by)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (tenant_id) DO UPDATE SET
+                mode = EXCLUDED.mode,
+                countries = EXCLUDED.countries,
+                restrict_login = EXCLUDED.restrict_login,
+                restrict_api = EXCLUDED.restrict_api,
+                block_unknown = EXCLUDED.block_unknown,
+                description = EXCLUDED.description,
+                updated_at = NOW()
+            RETURNING *
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(&req.mode)
+        .bind(&countries)
+        .bind(req.restrict_login.unwrap_or(true))
+        .bind(req.restrict_api.unwrap_or(true))
+        .bind(req.block_unknown.unwrap_or(false))
+        .bind(req.description)
+        .bind(updated_by)
+        .fetch_one(&self.pool)
+        .await?;
+
+        self.refresh_policies().await?;
+        Ok(policy)
+    }
+
+    pub async fn list_policies(&self) -> Result<Vec<GeoAccessPolicy>, GeoAccessError> {
+        let policies = sqlx::query_as::<_, GeoAccessPolicy>(
+            "SELECT * FROM geo_access_policies ORDER BY created_at DESC",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(policies)
+    }
+
+    pub async fn delete_policy(&self, tenant_id: Uuid) -> Result<(), GeoAccessError> {
+        let result = sqlx::query("DELETE FROM geo_access_policies WHERE tenant_id = $1")
+            .bind(tenant_id)
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(GeoAccessError::NotFound);
+        }
+        self.refresh_policies().await?;
+        Ok(())
+    }
+
+    /// Reload the policies used on the request path
+    pub async fn refresh_policies(&self) -> Result<(), GeoAccessError> {
+        let policies = self
+            .list_policies()
+            .await?
+            .into_iter()
+            .map(|p| (p.tenant_id, p))
+            .collect();
+        *self.policies.write().unwrap_or_else(|e| e.into_inner()) = policies;
+        Ok(())
+    }
+
+    pub async fn start_refresh_task(self, shutdown: Shutdown) {
+        if let Err(e) = self.refresh_policies().await {
+            tracing::error!("Failed to load geo access policies: {}", e);
+        }
+        shutdown.clone().spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(POLICY_REFRESH_SECS));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = shutdown.cancelled() => break,
+                }
+                if let Err(e) = self.refresh_policies().await {
+                    tracing::error!("Failed to refresh geo access policies: {}", e);
+                }
+            }
+        });
+    }
+
+    // ========================================================================
+    // ENFORCEMENT
+    // ========================================================================
+
+    pub fn has_policies(&self) -> bool {
+        !self
+            .policies
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_empty()
+    }
+
+    /// Check a user's request from `ip` against their tenant's policy
+    pub async fn check(
+        &self,
+        user_id: Uuid,
+        ip: Option<IpAddr>,
+        kind: AccessKind,
+    ) -> Result<GeoDecision, GeoAccessError> {
+        let country = ip.and_then(|ip| self.country_for(ip));
+        self.check_country(user_id, ip, country.as_deref(), kind)
+            .await
+    }
+
+    /// As `check`, with the country already resolved.
+    ///
+    /// Blocks and firefighter overrides are both recorded in security_events so
+    /// they show up in monitoring.
+    pub async fn check_country(
+        &self,
+        user_id: Uuid,
+        ip: Option<IpAddr>,
+        country: Option<&str>,
+        kind: AccessKind,
+    ) -> Result<GeoDecision, GeoAccessError> {
+        if !self.has_policies() {
+            return Ok(GeoDecision::Allowed);
+        }
+        let Some(tenant_id) = self.resolve_tenant(user_id).await? else {
+            return Ok(GeoDecision::Allowed);
+        };
+        let policy = {
+            let guard = self.policies.read().unwrap_or_else(|e| e.into_inner());
+            match guard.get(&tenant_id) {
+                Some(policy) if policy.applies_to(kind) => policy.clone(),
+                _ => return Ok(GeoDecision::Allowed),
+            }
+        };
+        if policy.permits(country) {
+            return Ok(GeoDecision::Allowed);
+        }
+
+        let overridden = self
+            .firefighter_service
+            .get_active_session(user_id)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to check firefighter session: {}", e);
+            })
+            .ok()
+            .flatten()
+            .is_some();
+
+        let (event_type, outcome, decision) = if overridden {
+            ("geo_override", "success", GeoDecision::Overridden)
+        } else {
+            ("geo_blocked", "blocked", GeoDecision::Blocked)
+        };
+        let details = json!({
+            "country": country,
+            "tenant_id": tenant_id,
+            "policy_id": policy.id,
+            "kind": kind,
+        });
+        if let Err(e) = sqlx::query(
+            "SELECT log_security_event($1, 'warning', $2, $3::inet, 'geo_access', $4, $5, $6)",
+        )
+        .bind(event_type)
+        .bind(user_id)
+        .bind(ip.map(|ip| ip.to_string()))
+        .bind(match kind {
+            AccessKind::Login => "login",
+            AccessKind::Api => "api",
+        })
+        .bind(outcome)
+        .bind(details)
+        .execute(&self.pool)
+        .await
+        {
+            tracing::error!("Failed to record geo access event: {}", e);
+        }
+
+        Ok(decision)
+    }
+
+    async fn resolve_tenant(&self, user_id: Uuid) -> Result<Option<Uuid>, GeoAccessError> {
+        if let Some(tenant_id) = self.tenant_cache.get(&user_id).await {
+            return Ok(tenant_id);
+        }
+        let tenant_id =
+            sqlx::query_scalar::<_, Option<Uuid>>("SELECT tenant_id FROM entities WHERE id = $1")
+                .bind(user_id)
+                .fetch_optional(&self.pool)
+                .await?
+                .flatten();
+        self.tenant_cache.insert(user_id, tenant_id).await;
+        Ok(tenant_id)
+    }
+}
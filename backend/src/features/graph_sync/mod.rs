@@ -0,0 +1,6 @@
+pub mod models;
+pub mod neo4j;
+pub mod routes;
+pub mod service;
+
+pub use service::{GraphSync, GraphSyncError};
@@ -0,0 +1,80 @@
+use crate::features::ai::index_worker::IndexWorkerState;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// A live entity as written to the graph, as an `:Entity` node
+#[derive(Debug, Clone, PartialEq, FromRow)]
+pub struct GraphNode {
+    pub id: Uuid,
+    pub class_id: Uuid,
+    pub class_name: String,
+    pub display_name: String,
+    pub tenant_id: Option<Uuid>,
+    /// Written as a `CHILD_OF` relationship to the parent's node
+    pub parent_entity_id: Option<Uuid>,
+    /// Without sensitive properties unless `graph_sync.include_sensitive` is set
+    pub attributes: serde_json::Value,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A relationship between two live entities, typed by its relationship type's name
+#[derive(Debug, Clone, PartialEq, FromRow)]
+pub struct GraphEdge {
+    pub id: Uuid,
+    pub source_entity_id: Uuid,
+    pub target_entity_id: Uuid,
+    pub relationship_type: String,
+    pub tenant_id: Option<Uuid>,
+    pub metadata: Option<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A relationship to remove. The source lets the graph find it through the
+/// node index instead of scanning every relationship.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct EdgeRef {
+    pub id: Uuid,
+    pub source_entity_id: Uuid,
+}
+
+/// Changes written to the graph in one transaction. Removals are applied
+/// before upserts, nodes before the relationships between them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GraphBatch {
+    pub delete_edges: Vec<EdgeRef>,
+    pub delete_nodes: Vec<Uuid>,
+    pub upsert_nodes: Vec<GraphNode>,
+    pub upsert_edges: Vec<GraphEdge>,
+}
+
+impl GraphBatch {
+    pub fn is_empty(&self) -> bool {
+        self.delete_edges.is_empty()
+            && self.delete_nodes.is_empty()
+            && self.upsert_nodes.is_empty()
+            && self.upsert_edges.is_empty()
+    }
+
+    /// Nodes and relationships written or removed
+    pub fn len(&self) -> usize {
+        self.delete_edges.len()
+            + self.delete_nodes.len()
+            + self.upsert_nodes.len()
+            + self.upsert_edges.len()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphSyncStatus {
+    /// `entities_indexed` counts nodes and relationships written
+    #[serde(flatten)]
+    pub state: IndexWorkerState,
+    pub enabled: bool,
+    /// Change events not yet mirrored
+    pub pending_events: i64,
+    pub backfilling: bool,
+    /// Running, with a recent heartbeat and no error since its last success
+    pub healthy: bool,
+}
@@ -0,0 +1,192 @@
+use super::models::GraphBatch;
+use super::service::GraphSyncError;
+use crate::config::GraphSyncConfig;
+use axum::async_trait;
+use neo4rs::{query, ConfigBuilder, Graph, Query};
+
+/// Where the mirror is written. `GraphSync` decides what changed; a sink
+/// only applies it.
+#[async_trait]
+pub trait GraphSink: Send + Sync {
+    /// Create the constraints and indexes the writes rely on
+    async fn prepare(&self) -> Result<(), GraphSyncError>;
+
+    /// Apply a batch atomically
+    async fn apply(&self, batch: &GraphBatch) -> Result<(), GraphSyncError>;
+}
+
+/// Neo4j, or any store speaking Bolt and Cypher (Memgraph, Amazon Neptune's
+/// Bolt endpoint)
+pub struct Neo4jSink {
+    graph: Graph,
+}
+
+impl Neo4jSink {
+    pub async fn connect(config: &GraphSyncConfig) -> Result<Self, GraphSyncError> {
+        let password = std::env::var(&config.password_env).unwrap_or_default();
+        let mut builder = ConfigBuilder::default()
+            .uri(config.uri.as_str())
+            .user(config.username.as_str())
+            .password(password.as_str())
+            .max_connections(4);
+        if !config.database.trim().is_empty() {
+            builder = builder.db(config.database.trim());
+        }
+        let neo4j_config = builder
+            .build()
+            .map_err(|e| GraphSyncError::Graph(e.to_string()))?;
+        let graph = Graph::connect(neo4j_config)
+            .await
+            .map_err(|e| GraphSyncError::Graph(e.to_string()))?;
+        Ok(Self { graph })
+    }
+}
+
+#[async_trait]
+impl GraphSink for Neo4jSink {
+    async fn prepare(&self) -> Result<(), GraphSyncError> {
+        self.graph
+            .run(query(
+                "CREATE CONSTRAINT entity_id IF NOT EXISTS FOR (n:Entity) REQUIRE n.id IS UNIQUE",
+            ))
+            .await
+            .map_err(|e| GraphSyncError::Graph(e.to_string()))
+    }
+
+    async fn apply(&self, batch: &GraphBatch) -> Result<(), GraphSyncError> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let mut txn = self
+            .graph
+            .start_txn()
+            .await
+            .map_err(|e| GraphSyncError::Graph(e.to_string()))?;
+        txn.run_queries(batch_queries(batch))
+            .await
+            .map_err(|e| GraphSyncError::Graph(e.to_string()))?;
+        txn.commit()
+            .await
+            .map_err(|e| GraphSyncError::Graph(e.to_string()))
+    }
+}
+
+/// Cypher for a batch, in the order it must run
+fn batch_queries(batch: &GraphBatch) -> Vec<Query> {
+    let mut queries = Vec::with_capacity(batch.len() * 2);
+
+    for edge in &batch.delete_edges {
+        queries.push(
+            query("MATCH (:Entity {id: $source})-[r {id: $id}]->() DELETE r")
+                .param("source", edge.source_entity_id.to_string())
+                .param("id", edge.id.to_string()),
+        );
+    }
+
+    for id in &batch.delete_nodes {
+        queries.push(query("MATCH (n:Entity {id: $id}) DETACH DELETE n").param("id", id.to_string()));
+    }
+
+    for node in &batch.upsert_nodes {
+        queries.push(
+            query(
+                r#"
+                MERGE (n:Entity {id: $id})
+                SET n.class_id = $class_id,
+                    n.class_name = $class_name,
+                    n.display_name = $display_name,
+                    n.tenant_id = CASE $tenant_id WHEN '' THEN null ELSE $tenant_id END,
+                    n.attributes = $attributes,
+                    n.updated_at = $updated_at
+                "#,
+            )
+            .param("id", node.id.to_string())
+            .param("class_id", node.class_id.to_string())
+            .param("class_name", node.class_name.clone())
+            .param("display_name", node.display_name.clone())
+            .param("tenant_id", optional_id(node.tenant_id))
+            .param("attributes", node.attributes.to_string())
+            .param("updated_at", node.updated_at.to_rfc3339()),
+        );
+        queries.push(
+            query("MATCH (:Entity {id: $id})-[r:CHILD_OF]->() DELETE r")
+                .param("id", node.id.to_string()),
+        );
+        if let Some(parent_id) = node.parent_entity_id {
+            // The parent may not be mirrored yet; its properties follow when it is
+            queries.push(
+                query(
+                    "MATCH (n:Entity {id: $id}) MERGE (p:Entity {id: $parent}) MERGE (n)-[:CHILD_OF]->(p)",
+                )
+                .param("id", node.id.to_string())
+                .param("parent", parent_id.to_string()),
+            );
+        }
+    }
+
+    for edge in &batch.upsert_edges {
+        // Recreated rather than updated, since the type may have changed
+        queries.push(
+            query("MATCH (:Entity {id: $source})-[r {id: $id}]->() DELETE r")
+                .param("source", edge.source_entity_id.to_string())
+                .param("id", edge.id.to_string()),
+        );
+        let cypher = format!(
+            r#"
+            MERGE (s:Entity {{id: $source}})
+            MERGE (t:Entity {{id: $target}})
+            CREATE (s)-[r:{} {{id: $id}}]->(t)
+            SET r.tenant_id = CASE $tenant_id WHEN '' THEN null ELSE $tenant_id END,
+                r.metadata = $metadata,
+                r.created_at = $created_at
+            "#,
+            escape_identifier(&edge.relationship_type)
+        );
+        queries.push(
+            query(&cypher)
+                .param("source", edge.source_entity_id.to_string())
+                .param("target", edge.target_entity_id.to_string())
+                .param("id", edge.id.to_string())
+                .param("tenant_id", optional_id(edge.tenant_id))
+                .param(
+                    "metadata",
+                    edge.metadata
+                        .clone()
+                        .unwrap_or_else(|| serde_json::json!({}))
+                        .to_string(),
+                )
+                .param("created_at", edge.created_at.to_rfc3339()),
+        );
+    }
+
+    queries
+}
+
+fn optional_id(id: Option<uuid::Uuid>) -> String {
+    id.map(|id| id.to_string()).unwrap_or_default()
+}
+
+/// Quote a relationship type for Cypher, which can't take it as a parameter
+pub fn escape_identifier(name: &str) -> String {
+    format!("`{}`", name.replace('`', "``"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identifiers_are_quoted() {
+        assert_eq!(escape_identifier("contains"), "`contains`");
+        assert_eq!(escape_identifier("has role"), "`has role`");
+        assert_eq!(
+            escape_identifier("x`]->() DETACH DELETE n //"),
+            "`x``]->() DETACH DELETE n //`"
+        );
+    }
+
+    #[test]
+    fn empty_batch_has_no_queries() {
+        assert!(batch_queries(&GraphBatch::default()).is_empty());
+    }
+}
@@ -0,0 +1,52 @@
+use super::models::GraphSyncStatus;
+use super::service::{GraphSync, GraphSyncError};
+use crate::middleware::abac::{enforce_route_permissions, RoutePermission};
+use crate::utils::api_error::ApiError;
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+
+/// Permissions enforced by `enforce_route_permissions` for the routes below
+pub const ROUTE_PERMISSIONS: &[RoutePermission] =
+    &[RoutePermission::new("POST", "/backfill", "manage_system")];
+
+/// Progress of the graph database mirror, and admin-only backfills
+pub fn graph_sync_routes() -> Router<GraphSync> {
+    Router::new()
+        .route("/status", get(status_handler))
+        .route("/backfill", post(backfill_handler))
+        .route_layer(axum::middleware::from_fn_with_state(
+            ROUTE_PERMISSIONS,
+            enforce_route_permissions,
+        ))
+}
+
+async fn status_handler(
+    State(sync): State<GraphSync>,
+) -> Result<Json<GraphSyncStatus>, GraphSyncError> {
+    sync.status().await.map(Json)
+}
+
+async fn backfill_handler(
+    State(sync): State<GraphSync>,
+) -> Result<(StatusCode, Json<GraphSyncStatus>), GraphSyncError> {
+    let status = sync.request_backfill().await?;
+    Ok((StatusCode::ACCEPTED, Json(status)))
+}
+
+impl IntoResponse for GraphSyncError {
+    fn into_response(self) -> Response {
+        let (status, code) = match &self {
+            GraphSyncError::DatabaseError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+            GraphSyncError::Graph(_) => (StatusCode::BAD_GATEWAY, "graph_sync.graph_failed"),
+            GraphSyncError::Disabled => (StatusCode::NOT_FOUND, "graph_sync.disabled"),
+            GraphSyncError::Forbidden(_) => (StatusCode::FORBIDDEN, "graph_sync.forbidden"),
+        };
+
+        ApiError::new(status, code, self.to_string()).into_response()
+    }
+}
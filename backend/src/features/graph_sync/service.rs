@@ -0,0 +1,564 @@
+use super::models::{EdgeRef, GraphBatch, GraphEdge, GraphNode, GraphSyncStatus};
+use super::neo4j::{GraphSink, Neo4jSink};
+use crate::config::GraphSyncConfig;
+use crate::features::ai::index_worker::{prune_change_events, IndexWorkerState, CHANGES_CHANNEL};
+use crate::utils::shutdown::Shutdown;
+use chrono::Utc;
+use sqlx::postgres::PgListener;
+use sqlx::{FromRow, PgPool};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use uuid::Uuid;
+
+/// Row in `ai_index_state` for this connector
+const WORKER: &str = "graph_sync";
+
+/// Handled change events are kept this long, like the embedding indexer's default
+const EVENT_RETENTION_HOURS: i64 = 72;
+
+#[derive(Error, Debug)]
+pub enum GraphSyncError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+
+    #[error("Graph database error: {0}")]
+    Graph(String),
+
+    #[error("Graph sync is not enabled")]
+    Disabled,
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+}
+
+#[derive(Debug, FromRow)]
+struct ChangeEvent {
+    seq: i64,
+    object_type: String,
+    object_id: Uuid,
+    class_id: Option<Uuid>,
+    source_entity_id: Option<Uuid>,
+}
+
+/// Mirrors entities and relationships into a graph database. Change events
+/// are applied in order as they arrive; a requested backfill rewrites
+/// everything, e.g. after pointing the connector at an empty database.
+#[derive(Clone)]
+pub struct GraphSync {
+    pool: PgPool,
+    sink: Arc<dyn GraphSink>,
+    config: GraphSyncConfig,
+}
+
+impl GraphSync {
+    pub fn new(pool: PgPool, sink: Arc<dyn GraphSink>, config: GraphSyncConfig) -> Self {
+        Self { pool, sink, config }
+    }
+
+    /// Connect to the configured graph database. When the connector is
+    /// disabled it is recorded as stopped, so the change feed can be pruned
+    /// without it, and `None` is returned.
+    pub async fn from_config(
+        pool: PgPool,
+        config: &GraphSyncConfig,
+    ) -> Result<Option<Self>, GraphSyncError> {
+        if !config.enabled {
+            sqlx::query("UPDATE ai_index_state SET available = FALSE WHERE worker = $1")
+                .bind(WORKER)
+                .execute(&pool)
+                .await?;
+            return Ok(None);
+        }
+        let sink = Neo4jSink::connect(config).await?;
+        Ok(Some(Self::new(pool, Arc::new(sink), config.clone())))
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(self.config.sync_interval_secs.max(1))
+    }
+
+    fn batch_size(&self) -> i64 {
+        self.config.batch_size.max(1)
+    }
+
+    pub async fn start(self, shutdown: Shutdown) {
+        shutdown.clone().spawn(async move {
+            if let Err(e) = self.enable().await {
+                tracing::error!("Failed to start graph sync: {}", e);
+                return;
+            }
+
+            let mut listener = match PgListener::connect_with(&self.pool).await {
+                Ok(mut listener) => match listener.listen(CHANGES_CHANNEL).await {
+                    Ok(()) => Some(listener),
+                    Err(e) => {
+                        tracing::warn!("Graph sync can't listen for changes, polling: {}", e);
+                        None
+                    }
+                },
+                Err(e) => {
+                    tracing::warn!("Graph sync can't listen for changes, polling: {}", e);
+                    None
+                }
+            };
+
+            let mut interval = tokio::time::interval(self.interval());
+            loop {
+                let periodic = tokio::select! {
+                    _ = interval.tick() => true,
+                    notification = async {
+                        match listener.as_mut() {
+                            Some(listener) => listener.recv().await,
+                            None => std::future::pending().await,
+                        }
+                    } => {
+                        if let Err(e) = notification {
+                            tracing::warn!("Graph sync lost its change listener, polling: {}", e);
+                            listener = None;
+                        }
+                        false
+                    }
+                    _ = shutdown.cancelled() => break,
+                };
+                self.run_once(periodic, &shutdown).await;
+            }
+        });
+    }
+
+    /// Mark the connector as running and create the graph's constraints.
+    /// Changes made while it was stopped may have been pruned from the feed,
+    /// so a connector that wasn't running starts from the end of the feed
+    /// with a full backfill, which is also how a new graph gets its data.
+    async fn enable(&self) -> Result<(), GraphSyncError> {
+        self.sink.prepare().await?;
+        let running: Option<bool> =
+            sqlx::query_scalar("SELECT available FROM ai_index_state WHERE worker = $1")
+                .bind(WORKER)
+                .fetch_optional(&self.pool)
+                .await?;
+        if running == Some(true) {
+            return Ok(());
+        }
+        sqlx::query(
+            r#"
+            INSERT INTO ai_index_state (worker, last_seq, available, heartbeat_at)
+            SELECT $1, COALESCE(MAX(seq), 0), TRUE, NOW() FROM ontology_change_events
+            ON CONFLICT (worker) DO UPDATE SET
+                last_seq = EXCLUDED.last_seq,
+                available = TRUE,
+                heartbeat_at = NOW()
+            "#,
+        )
+        .bind(WORKER)
+        .execute(&self.pool)
+        .await?;
+        self.request_backfill().await?;
+        Ok(())
+    }
+
+    /// One pass: apply pending change events and continue any backfill. The
+    /// `periodic` pass also re-applies recent events, in case some were
+    /// committed after later ones had already been handled, and prunes the
+    /// feed. Errors are recorded in the connector state and retried on the
+    /// next pass.
+    pub async fn run_once(&self, periodic: bool, shutdown: &Shutdown) {
+        let result = async {
+            sqlx::query("UPDATE ai_index_state SET heartbeat_at = NOW() WHERE worker = $1")
+                .bind(WORKER)
+                .execute(&self.pool)
+                .await?;
+            self.process_events(shutdown).await?;
+            self.continue_backfill(shutdown).await?;
+            if periodic {
+                self.recheck_recent().await?;
+                prune_change_events(&self.pool, EVENT_RETENTION_HOURS).await?;
+            }
+            Ok::<_, GraphSyncError>(())
+        }
+        .await;
+
+        if let Err(e) = result {
+            tracing::warn!("Graph sync failed: {}", e);
+            let _ = sqlx::query(
+                "UPDATE ai_index_state SET last_error = $2, last_error_at = NOW() WHERE worker = $1",
+            )
+            .bind(WORKER)
+            .bind(e.to_string())
+            .execute(&self.pool)
+            .await;
+        }
+    }
+
+    /// Apply change events in order. The position only advances past events
+    /// whose changes were written.
+    async fn process_events(&self, shutdown: &Shutdown) -> Result<(), GraphSyncError> {
+        loop {
+            let last_seq = self.state().await?.last_seq;
+            let events = sqlx::query_as::<_, ChangeEvent>(
+                r#"
+                SELECT seq, object_type, object_id, class_id, source_entity_id
+                FROM ontology_change_events
+                WHERE seq > $1
+                ORDER BY seq
+                LIMIT $2
+                "#,
+            )
+            .bind(last_seq)
+            .bind(self.batch_size())
+            .fetch_all(&self.pool)
+            .await?;
+            let Some(last) = events.last().map(|e| e.seq) else {
+                return Ok(());
+            };
+
+            let written = self.apply_events(&events).await?;
+            self.record_progress(last, events.len(), written).await?;
+
+            if (events.len() as i64) < self.batch_size() || shutdown.is_triggered() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Re-apply the events of the last two intervals. Writes read the current
+    /// rows, so repeating them is harmless.
+    async fn recheck_recent(&self) -> Result<(), GraphSyncError> {
+        let window = chrono::Duration::from_std(self.interval() * 2)
+            .unwrap_or_else(|_| chrono::Duration::hours(1));
+        let last_seq = self.state().await?.last_seq;
+        let events = sqlx::query_as::<_, ChangeEvent>(
+            r#"
+            SELECT seq, object_type, object_id, class_id, source_entity_id
+            FROM ontology_change_events
+            WHERE seq <= $1 AND created_at > $2
+            ORDER BY seq
+            "#,
+        )
+        .bind(last_seq)
+        .bind(Utc::now() - window)
+        .fetch_all(&self.pool)
+        .await?;
+        for chunk in events.chunks(self.batch_size() as usize) {
+            self.apply_events(chunk).await?;
+        }
+        Ok(())
+    }
+
+    /// Write what the events touched as it is now. Entities of a renamed
+    /// class are rewritten too, since their nodes carry the class name.
+    async fn apply_events(&self, events: &[ChangeEvent]) -> Result<usize, GraphSyncError> {
+        let mut entity_ids = BTreeSet::new();
+        let mut class_ids = BTreeSet::new();
+        let mut edges = BTreeMap::new();
+        for event in events {
+            match event.object_type.as_str() {
+                "entity" => {
+                    entity_ids.insert(event.object_id);
+                }
+                "class" => {
+                    class_ids.extend(event.class_id);
+                }
+                "relationship" => {
+                    if let Some(source_entity_id) = event.source_entity_id {
+                        edges.insert(event.object_id, source_entity_id);
+                    }
+                }
+                // Attributes are copied whole, so property changes don't matter
+                _ => {}
+            }
+        }
+        if !class_ids.is_empty() {
+            let class_ids: Vec<Uuid> = class_ids.into_iter().collect();
+            let members: Vec<Uuid> = sqlx::query_scalar(
+                "SELECT id FROM entities WHERE class_id = ANY($1) AND deleted_at IS NULL",
+            )
+            .bind(&class_ids)
+            .fetch_all(&self.pool)
+            .await?;
+            entity_ids.extend(members);
+        }
+
+        let entity_ids: Vec<Uuid> = entity_ids.into_iter().collect();
+        let nodes = self.load_nodes(&entity_ids).await?;
+        let live: BTreeSet<Uuid> = nodes.iter().map(|n| n.id).collect();
+
+        let edge_ids: Vec<Uuid> = edges.keys().copied().collect();
+        let upsert_edges = self.load_edges(&edge_ids).await?;
+        let current: BTreeSet<Uuid> = upsert_edges.iter().map(|e| e.id).collect();
+
+        // An event's endpoint is where the edge was when the event was
+        // recorded; a moved edge also has a delete event for its old endpoint
+        let batch = GraphBatch {
+            delete_edges: edges
+                .into_iter()
+                .filter(|(id, _)| !current.contains(id))
+                .map(|(id, source_entity_id)| EdgeRef {
+                    id,
+                    source_entity_id,
+                })
+                .collect(),
+            delete_nodes: entity_ids
+                .into_iter()
+                .filter(|id| !live.contains(id))
+                .collect(),
+            upsert_nodes: nodes,
+            upsert_edges,
+        };
+        self.sink.apply(&batch).await?;
+        Ok(batch.len())
+    }
+
+    /// Live entities among `ids`; deleted ones are left out
+    async fn load_nodes(&self, ids: &[Uuid]) -> Result<Vec<GraphNode>, GraphSyncError> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let nodes = sqlx::query_as::<_, GraphNode>(
+            r#"
+            SELECT e.id, e.class_id, c.name AS class_name, e.display_name, e.tenant_id,
+                   e.parent_entity_id, e.updated_at,
+                   CASE WHEN $2 THEN e.attributes ELSE e.attributes - COALESCE(
+                       (SELECT array_agg(p.name) FROM properties p
+                        WHERE p.class_id = e.class_id AND p.is_sensitive = TRUE),
+                       '{}'
+                   ) END AS attributes
+            FROM entities e
+            JOIN classes c ON c.id = e.class_id
+            WHERE e.id = ANY($1) AND e.deleted_at IS NULL
+            ORDER BY e.id
+            "#,
+        )
+        .bind(ids)
+        .bind(self.config.include_sensitive)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(nodes)
+    }
+
+    /// Relationships among `ids` whose endpoints are both live
+    async fn load_edges(&self, ids: &[Uuid]) -> Result<Vec<GraphEdge>, GraphSyncError> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let edges = sqlx::query_as::<_, GraphEdge>(
+            r#"
+            SELECT r.id, r.source_entity_id, r.target_entity_id, rt.name AS relationship_type,
+                   r.tenant_id, r.metadata, r.created_at
+            FROM relationships r
+            JOIN relationship_types rt ON rt.id = r.relationship_type_id
+            JOIN entities s ON s.id = r.source_entity_id AND s.deleted_at IS NULL
+            JOIN entities t ON t.id = r.target_entity_id AND t.deleted_at IS NULL
+            WHERE r.id = ANY($1)
+            ORDER BY r.id
+            "#,
+        )
+        .bind(ids)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(edges)
+    }
+
+    /// Write the next page of a backfill: entities first, then relationships,
+    /// each in id order, resuming after `backfill_cursor`.
+    async fn continue_backfill(&self, shutdown: &Shutdown) -> Result<(), GraphSyncError> {
+        loop {
+            let state = self.state().await?;
+            if state.backfill_started_at.is_none() || state.backfill_finished_at.is_some() {
+                return Ok(());
+            }
+            let cursor: Option<String> =
+                sqlx::query_scalar("SELECT backfill_cursor FROM ai_index_state WHERE worker = $1")
+                    .bind(WORKER)
+                    .fetch_one(&self.pool)
+                    .await?;
+            let (phase, after) = parse_cursor(cursor.as_deref());
+
+            let (written, next_cursor, finished) = match phase {
+                BackfillPhase::Entities => {
+                    let ids: Vec<Uuid> = sqlx::query_scalar(
+                        r#"
+                        SELECT id FROM entities
+                        WHERE deleted_at IS NULL AND ($1::uuid IS NULL OR id > $1)
+                        ORDER BY id
+                        LIMIT $2
+                        "#,
+                    )
+                    .bind(after)
+                    .bind(self.batch_size())
+                    .fetch_all(&self.pool)
+                    .await?;
+                    let nodes = self.load_nodes(&ids).await?;
+                    let batch = GraphBatch {
+                        upsert_nodes: nodes,
+                        ..Default::default()
+                    };
+                    self.sink.apply(&batch).await?;
+                    let next = match ids.last() {
+                        Some(id) if (ids.len() as i64) == self.batch_size() => {
+                            format!("entity:{}", id)
+                        }
+                        _ => "relationship:".to_string(),
+                    };
+                    (batch.len(), next, false)
+                }
+                BackfillPhase::Relationships => {
+                    let ids: Vec<Uuid> = sqlx::query_scalar(
+                        r#"
+                        SELECT id FROM relationships
+                        WHERE ($1::uuid IS NULL OR id > $1)
+                        ORDER BY id
+                        LIMIT $2
+                        "#,
+                    )
+                    .bind(after)
+                    .bind(self.batch_size())
+                    .fetch_all(&self.pool)
+                    .await?;
+                    let edges = self.load_edges(&ids).await?;
+                    let batch = GraphBatch {
+                        upsert_edges: edges,
+                        ..Default::default()
+                    };
+                    self.sink.apply(&batch).await?;
+                    let finished = (ids.len() as i64) < self.batch_size();
+                    let next = match ids.last() {
+                        Some(id) => format!("relationship:{}", id),
+                        None => cursor.unwrap_or_default(),
+                    };
+                    (batch.len(), next, finished)
+                }
+            };
+
+            sqlx::query(
+                r#"
+                UPDATE ai_index_state SET
+                    backfill_cursor = $2,
+                    backfill_done = backfill_done + $3,
+                    entities_indexed = entities_indexed + $3,
+                    last_indexed_at = NOW(),
+                    backfill_finished_at = CASE WHEN $4 THEN NOW() ELSE NULL END
+                WHERE worker = $1
+                "#,
+            )
+            .bind(WORKER)
+            .bind(next_cursor)
+            .bind(written as i64)
+            .bind(finished)
+            .execute(&self.pool)
+            .await?;
+
+            if finished || shutdown.is_triggered() {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn record_progress(
+        &self,
+        last_seq: i64,
+        events: usize,
+        written: usize,
+    ) -> Result<(), GraphSyncError> {
+        sqlx::query(
+            r#"
+            UPDATE ai_index_state SET
+                last_seq = $2,
+                events_processed = events_processed + $3,
+                entities_indexed = entities_indexed + $4,
+                last_indexed_at = NOW()
+            WHERE worker = $1
+            "#,
+        )
+        .bind(WORKER)
+        .bind(last_seq)
+        .bind(events as i64)
+        .bind(written as i64)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn state(&self) -> Result<IndexWorkerState, GraphSyncError> {
+        sqlx::query_as::<_, IndexWorkerState>("SELECT * FROM ai_index_state WHERE worker = $1")
+            .bind(WORKER)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or(GraphSyncError::Disabled)
+    }
+
+    pub async fn status(&self) -> Result<GraphSyncStatus, GraphSyncError> {
+        let state = self.state().await?;
+        let pending_events: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM ontology_change_events WHERE seq > $1")
+                .bind(state.last_seq)
+                .fetch_one(&self.pool)
+                .await?;
+
+        // Missing two passes in a row means the connector has stalled
+        let stale_after = chrono::Duration::from_std(self.interval() * 2)
+            .unwrap_or_else(|_| chrono::Duration::hours(1));
+        let alive = state
+            .heartbeat_at
+            .is_some_and(|at| Utc::now() - at < stale_after);
+        let failing = match (state.last_error_at, state.last_indexed_at) {
+            (Some(error_at), Some(indexed_at)) => error_at > indexed_at,
+            (Some(_), None) => true,
+            _ => false,
+        };
+
+        Ok(GraphSyncStatus {
+            enabled: self.config.enabled,
+            backfilling: state.backfill_started_at.is_some()
+                && state.backfill_finished_at.is_none(),
+            healthy: state.available && alive && !failing,
+            pending_events,
+            state,
+        })
+    }
+
+    /// Rewrite every entity and relationship, e.g. into a new graph database.
+    /// Restarts a backfill already in progress.
+    pub async fn request_backfill(&self) -> Result<GraphSyncStatus, GraphSyncError> {
+        sqlx::query(
+            r#"
+            UPDATE ai_index_state SET
+                backfill_started_at = NOW(),
+                backfill_total = (SELECT COUNT(*) FROM entities WHERE deleted_at IS NULL)
+                               + (SELECT COUNT(*) FROM relationships),
+                backfill_done = 0,
+                backfill_cursor = NULL,
+                backfill_finished_at = NULL
+            WHERE worker = $1
+            "#,
+        )
+        .bind(WORKER)
+        .execute(&self.pool)
+        .await?;
+
+        // Wake the connector rather than waiting for its next pass
+        sqlx::query("SELECT pg_notify($1, '')")
+            .bind(CHANGES_CHANNEL)
+            .execute(&self.pool)
+            .await?;
+
+        self.status().await
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BackfillPhase {
+    Entities,
+    Relationships,
+}
+
+/// `entity:<id>` or `relationship:<id>`, the last id written; no cursor
+/// means the backfill hasn't written anything yet
+fn parse_cursor(cursor: Option<&str>) -> (BackfillPhase, Option<Uuid>) {
+    match cursor.and_then(|c| c.split_once(':')) {
+        Some(("relationship", id)) => (BackfillPhase::Relationships, Uuid::parse_str(id).ok()),
+        Some((_, id)) => (BackfillPhase::Entities, Uuid::parse_str(id).ok()),
+        None => (BackfillPhase::Entities, None),
+    }
+}
@@ -0,0 +1,6 @@
+pub mod models;
+pub mod routes;
+pub mod service;
+pub mod sources;
+
+pub use service::{IngestionError, IngestionService};
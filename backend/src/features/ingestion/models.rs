@@ -0,0 +1,105 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// An upstream record to create, update or delete, as sent by a source
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityUpsert {
+    /// Unique per source; an event seen before is not applied again
+    pub event_id: String,
+    /// The record's id in the upstream system
+    pub external_id: String,
+    /// Class name, resolved in the source's tenant
+    pub class: String,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    /// External id of the parent record, which must have been ingested first
+    #[serde(default)]
+    pub parent_external_id: Option<String>,
+    /// Replaces the entity's attributes; omitted keeps them on update
+    #[serde(default)]
+    pub attributes: Option<serde_json::Value>,
+    #[serde(default)]
+    pub deleted: bool,
+    /// Upstream version of the record; upserts older than the last applied one are skipped
+    #[serde(default)]
+    pub sequence: Option<i64>,
+}
+
+/// What applying an upsert did
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", content = "entity_id", rename_all = "lowercase")]
+pub enum ApplyOutcome {
+    Created(Uuid),
+    Updated(Uuid),
+    Deleted(Uuid),
+    /// The event was already applied
+    Duplicate,
+    /// A newer version of the record was already applied
+    Stale,
+    /// A delete for a record that was never ingested
+    Ignored,
+}
+
+impl ApplyOutcome {
+    /// Status recorded in `ingestion_events`; duplicates aren't recorded again
+    pub fn status(&self) -> Option<&'static str> {
+        match self {
+            Self::Created(_) => Some("created"),
+            Self::Updated(_) => Some("updated"),
+            Self::Deleted(_) => Some("deleted"),
+            Self::Stale => Some("stale"),
+            Self::Ignored => Some("ignored"),
+            Self::Duplicate => None,
+        }
+    }
+
+    pub fn entity_id(&self) -> Option<Uuid> {
+        match self {
+            Self::Created(id) | Self::Updated(id) | Self::Deleted(id) => Some(*id),
+            _ => None,
+        }
+    }
+}
+
+/// Body returned by a polled HTTP source
+#[derive(Debug, Deserialize)]
+pub struct PolledEvents {
+    #[serde(default)]
+    pub events: Vec<serde_json::Value>,
+    /// Passed back on the next poll; unchanged when omitted
+    #[serde(default)]
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct IngestionEvent {
+    pub id: Uuid,
+    pub source: String,
+    pub event_id: Option<String>,
+    pub external_id: Option<String>,
+    pub status: String,
+    pub entity_id: Option<Uuid>,
+    pub error: Option<String>,
+    pub payload: Option<serde_json::Value>,
+    pub received_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IngestionEventsQuery {
+    pub source: Option<String>,
+    pub status: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct IngestionSourceStatus {
+    pub name: String,
+    pub cursor: Option<String>,
+    pub events_received: i64,
+    pub events_failed: i64,
+    pub last_polled_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+}
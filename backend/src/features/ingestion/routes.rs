@@ -0,0 +1,56 @@
+use super::models::{IngestionEvent, IngestionEventsQuery, IngestionSourceStatus};
+use super::service::{IngestionError, IngestionService};
+use crate::middleware::abac::{enforce_route_permissions, RoutePermission};
+use crate::utils::api_error::ApiError;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+
+/// Permissions enforced by `enforce_route_permissions` for the routes below
+pub const ROUTE_PERMISSIONS: &[RoutePermission] = &[
+    RoutePermission::new("GET", "/sources", "manage_system"),
+    RoutePermission::new("GET", "/events", "manage_system"),
+];
+
+/// Admin-only consumer status and handled events, e.g. `?status=failed`
+pub fn ingestion_routes() -> Router<IngestionService> {
+    Router::new()
+        .route("/sources", get(sources_handler))
+        .route("/events", get(events_handler))
+        .route_layer(axum::middleware::from_fn_with_state(
+            ROUTE_PERMISSIONS,
+            enforce_route_permissions,
+        ))
+}
+
+async fn sources_handler(
+    State(service): State<IngestionService>,
+) -> Result<Json<Vec<IngestionSourceStatus>>, IngestionError> {
+    service.sources().await.map(Json)
+}
+
+async fn events_handler(
+    State(service): State<IngestionService>,
+    Query(query): Query<IngestionEventsQuery>,
+) -> Result<Json<Vec<IngestionEvent>>, IngestionError> {
+    service.list_events(&query).await.map(Json)
+}
+
+impl IntoResponse for IngestionError {
+    fn into_response(self) -> Response {
+        let (status, code) = match &self {
+            IngestionError::DatabaseError(_) | IngestionError::Ontology(_) => {
+                (StatusCode::INTERNAL_SERVER_ERROR, "internal")
+            }
+            IngestionError::Rejected(_) => (StatusCode::UNPROCESSABLE_ENTITY, "ingestion.rejected"),
+            IngestionError::Source(_) => (StatusCode::BAD_GATEWAY, "ingestion.source_failed"),
+            IngestionError::Forbidden(_) => (StatusCode::FORBIDDEN, "ingestion.forbidden"),
+        };
+
+        ApiError::new(status, code, self.to_string()).into_response()
+    }
+}
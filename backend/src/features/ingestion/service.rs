@@ -0,0 +1,588 @@
+use super::models::{
+    ApplyOutcome, EntityUpsert, IngestionEvent, IngestionEventsQuery, IngestionSourceStatus,
+};
+use super::sources::{build_source, EventSource};
+use crate::config::{IngestionConfig, IngestionSourceConfig};
+use crate::features::ontology::models::{CreateEntityInput, UpdateEntityInput};
+use crate::features::ontology::service::OntologyError;
+use crate::features::ontology::OntologyService;
+use crate::utils::http_client::OutboundClient;
+use crate::utils::shutdown::Shutdown;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::collections::HashSet;
+use std::time::Duration;
+use thiserror::Error;
+use uuid::Uuid;
+
+#[derive(Error, Debug)]
+pub enum IngestionError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+
+    /// The event can't be applied as sent; it is recorded as failed and skipped
+    #[error("Rejected: {0}")]
+    Rejected(String),
+
+    #[error("Ontology error: {0}")]
+    Ontology(String),
+
+    #[error("Source error: {0}")]
+    Source(String),
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+}
+
+impl From<OntologyError> for IngestionError {
+    fn from(err: OntologyError) -> Self {
+        match err {
+            OntologyError::DatabaseError(msg) => IngestionError::Ontology(msg),
+            other => IngestionError::Rejected(other.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct Link {
+    entity_id: Uuid,
+    last_sequence: Option<i64>,
+    class_id: Option<Uuid>,
+}
+
+/// Applies entity upserts from upstream systems of record through
+/// `OntologyService`, so they get the same validation as edits made in the
+/// app. Each source's records are linked to entities by external id;
+/// redelivered events and out-of-date versions are skipped.
+#[derive(Clone)]
+pub struct IngestionService {
+    pool: PgPool,
+    ontology: OntologyService,
+    config: IngestionConfig,
+}
+
+impl IngestionService {
+    pub fn new(pool: PgPool, ontology: OntologyService, config: IngestionConfig) -> Self {
+        Self {
+            pool,
+            ontology,
+            config,
+        }
+    }
+
+    /// Start a consumer per configured source, and the pruning of old event
+    /// records. Fails on a misconfigured source.
+    pub async fn start(self, http: OutboundClient, shutdown: Shutdown) -> Result<(), String> {
+        let mut names = HashSet::new();
+        let mut consumers = Vec::new();
+        for config in &self.config.sources {
+            if !names.insert(config.name.as_str()) {
+                return Err(format!("duplicate ingestion source '{}'", config.name));
+            }
+            consumers.push((config.clone(), build_source(config, &http)?));
+        }
+
+        for (config, source) in consumers {
+            let service = self.clone();
+            let shutdown_for_task = shutdown.clone();
+            shutdown.spawn(async move {
+                service.consume(config, source, shutdown_for_task).await;
+            });
+        }
+
+        let service = self.clone();
+        let shutdown_for_task = shutdown.clone();
+        shutdown.spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(3600));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = shutdown_for_task.cancelled() => break,
+                }
+                if let Err(e) = service.prune_events().await {
+                    tracing::warn!("Failed to prune ingestion events: {}", e);
+                }
+            }
+        });
+        Ok(())
+    }
+
+    async fn consume(
+        &self,
+        config: IngestionSourceConfig,
+        mut source: Box<dyn EventSource>,
+        shutdown: Shutdown,
+    ) {
+        let retry = Duration::from_secs(config.poll_interval_secs.max(1));
+        while !shutdown.is_triggered() {
+            if let Err(e) = self.consume_once(&config, source.as_mut(), &shutdown).await {
+                tracing::warn!("Ingestion from '{}' failed: {}", config.name, e);
+                let _ = sqlx::query(
+                    r#"
+                    INSERT INTO ingestion_sources (name, last_error, last_error_at)
+                    VALUES ($1, $2, NOW())
+                    ON CONFLICT (name) DO UPDATE SET
+                        last_error = EXCLUDED.last_error,
+                        last_error_at = EXCLUDED.last_error_at
+                    "#,
+                )
+                .bind(&config.name)
+                .bind(e.to_string())
+                .execute(&self.pool)
+                .await;
+                tokio::select! {
+                    _ = tokio::time::sleep(retry) => {}
+                    _ = shutdown.cancelled() => break,
+                }
+            }
+        }
+    }
+
+    /// Poll `source` once and handle what it returned: each message is
+    /// applied or recorded as failed, then the source's position advances.
+    /// Only waiting for messages is interrupted by shutdown. On a database or
+    /// source error nothing is committed, so the batch is delivered again.
+    /// Returns the number of messages handled.
+    pub async fn consume_once(
+        &self,
+        config: &IngestionSourceConfig,
+        source: &mut dyn EventSource,
+        shutdown: &Shutdown,
+    ) -> Result<usize, IngestionError> {
+        let cursor: Option<String> =
+            sqlx::query_scalar("SELECT cursor FROM ingestion_sources WHERE name = $1")
+                .bind(&config.name)
+                .fetch_optional(&self.pool)
+                .await?
+                .flatten();
+        let polled = tokio::select! {
+            polled = source.poll(cursor.as_deref(), config.batch_size.max(1)) => polled?,
+            _ = shutdown.cancelled() => return Ok(0),
+        };
+
+        let received = polled.messages.len();
+        let mut failed = 0;
+        for message in polled.messages {
+            if !self.handle_message(config, message).await? {
+                failed += 1;
+            }
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO ingestion_sources (name, cursor, events_received, events_failed, last_polled_at)
+            VALUES ($1, $2, $3, $4, NOW())
+            ON CONFLICT (name) DO UPDATE SET
+                cursor = COALESCE(EXCLUDED.cursor, ingestion_sources.cursor),
+                events_received = ingestion_sources.events_received + EXCLUDED.events_received,
+                events_failed = ingestion_sources.events_failed + EXCLUDED.events_failed,
+                last_polled_at = EXCLUDED.last_polled_at,
+                last_error = NULL
+            "#,
+        )
+        .bind(&config.name)
+        .bind(&polled.next_cursor)
+        .bind(received as i64)
+        .bind(failed as i64)
+        .execute(&self.pool)
+        .await?;
+        source.commit().await?;
+        Ok(received)
+    }
+
+    /// Apply one message. Returns false if it was recorded as failed.
+    async fn handle_message(
+        &self,
+        config: &IngestionSourceConfig,
+        message: Result<serde_json::Value, String>,
+    ) -> Result<bool, IngestionError> {
+        let payload = match message {
+            Ok(payload) => payload,
+            Err(error) => {
+                self.record_failure(&config.name, None, None, &error, None)
+                    .await?;
+                return Ok(false);
+            }
+        };
+        let upsert = match serde_json::from_value::<EntityUpsert>(payload.clone()) {
+            Ok(upsert) => upsert,
+            Err(e) => {
+                let event_id = payload.get("event_id").and_then(|v| v.as_str());
+                let external_id = payload.get("external_id").and_then(|v| v.as_str());
+                let error = format!("invalid event: {}", e);
+                self.record_failure(&config.name, event_id, external_id, &error, Some(&payload))
+                    .await?;
+                return Ok(false);
+            }
+        };
+
+        match self.apply(&config.name, config.tenant_id, &upsert).await {
+            Ok(_) => Ok(true),
+            Err(IngestionError::Rejected(error)) => {
+                tracing::debug!(
+                    "Ingestion event {} from '{}' rejected: {}",
+                    upsert.event_id,
+                    config.name,
+                    error
+                );
+                self.record_failure(
+                    &config.name,
+                    Some(&upsert.event_id),
+                    Some(&upsert.external_id),
+                    &error,
+                    Some(&payload),
+                )
+                .await?;
+                Ok(false)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Apply an upsert from `source`, creating entities in `tenant_id`.
+    /// Events already applied are reported as duplicates without changing
+    /// anything. Returns `Rejected` for events that can't be applied as sent,
+    /// which are not recorded; the consumer records them as failed.
+    pub async fn apply(
+        &self,
+        source: &str,
+        tenant_id: Option<Uuid>,
+        upsert: &EntityUpsert,
+    ) -> Result<ApplyOutcome, IngestionError> {
+        if upsert.event_id.trim().is_empty() || upsert.external_id.trim().is_empty() {
+            return Err(IngestionError::Rejected(
+                "event_id and external_id are required".to_string(),
+            ));
+        }
+
+        // Held until the outcome is recorded, so upserts of the same record
+        // from several instances are applied one at a time
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1 || '/' || $2))")
+            .bind(source)
+            .bind(&upsert.external_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let seen: Option<bool> = sqlx::query_scalar(
+            "SELECT TRUE FROM ingestion_events WHERE source = $1 AND event_id = $2 AND status <> 'failed'",
+        )
+        .bind(source)
+        .bind(&upsert.event_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if seen.is_some() {
+            return Ok(ApplyOutcome::Duplicate);
+        }
+
+        let link = sqlx::query_as::<_, Link>(
+            r#"
+            SELECT l.entity_id, l.last_sequence, e.class_id
+            FROM ingestion_links l
+            LEFT JOIN entities e ON e.id = l.entity_id AND e.deleted_at IS NULL
+            WHERE l.source = $1 AND l.external_id = $2
+            "#,
+        )
+        .bind(source)
+        .bind(&upsert.external_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let stale = matches!(
+            (&link, upsert.sequence),
+            (Some(Link { last_sequence: Some(last), .. }), Some(sequence)) if sequence <= *last
+        );
+        let outcome = if stale {
+            ApplyOutcome::Stale
+        } else if upsert.deleted {
+            self.apply_delete(&mut tx, source, upsert, link).await?
+        } else {
+            self.apply_upsert(&mut tx, source, tenant_id, upsert, link)
+                .await?
+        };
+
+        if let Some(status) = outcome.status() {
+            sqlx::query(
+                r#"
+                INSERT INTO ingestion_events (source, event_id, external_id, status, entity_id)
+                VALUES ($1, $2, $3, $4, $5)
+                ON CONFLICT (source, event_id) DO UPDATE SET
+                    status = EXCLUDED.status,
+                    entity_id = EXCLUDED.entity_id,
+                    error = NULL,
+                    payload = NULL,
+                    received_at = NOW()
+                "#,
+            )
+            .bind(source)
+            .bind(&upsert.event_id)
+            .bind(&upsert.external_id)
+            .bind(status)
+            .bind(outcome.entity_id())
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(outcome)
+    }
+
+    async fn apply_upsert(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        source: &str,
+        tenant_id: Option<Uuid>,
+        upsert: &EntityUpsert,
+        link: Option<Link>,
+    ) -> Result<ApplyOutcome, IngestionError> {
+        let class_id = self.resolve_class(tx, &upsert.class, tenant_id).await?;
+        let parent_entity_id = match &upsert.parent_external_id {
+            Some(parent) => Some(self.resolve_parent(tx, source, parent).await?),
+            None => None,
+        };
+
+        let outcome = match link {
+            // Linked to a live entity
+            Some(Link {
+                entity_id,
+                class_id: Some(current_class),
+                ..
+            }) => {
+                if current_class != class_id {
+                    return Err(IngestionError::Rejected(format!(
+                        "{} was ingested as another class than {}",
+                        upsert.external_id, upsert.class
+                    )));
+                }
+                self.ontology
+                    .update_entity(
+                        entity_id,
+                        UpdateEntityInput {
+                            display_name: upsert.display_name.clone(),
+                            parent_entity_id,
+                            attributes: upsert.attributes.clone(),
+                        },
+                        None,
+                    )
+                    .await?;
+                ApplyOutcome::Updated(entity_id)
+            }
+            // New, or its entity was deleted in the app since; it is recreated
+            _ => {
+                let display_name = upsert
+                    .display_name
+                    .clone()
+                    .filter(|name| !name.trim().is_empty())
+                    .ok_or_else(|| {
+                        IngestionError::Rejected("display_name is required".to_string())
+                    })?;
+                let entity = self
+                    .ontology
+                    .create_entity(
+                        CreateEntityInput {
+                            class_id,
+                            display_name,
+                            parent_entity_id,
+                            attributes: upsert.attributes.clone(),
+                        },
+                        None,
+                        tenant_id,
+                    )
+                    .await?;
+                ApplyOutcome::Created(entity.id)
+            }
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO ingestion_links (source, external_id, entity_id, last_sequence)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (source, external_id) DO UPDATE SET
+                entity_id = EXCLUDED.entity_id,
+                last_sequence = COALESCE(EXCLUDED.last_sequence, ingestion_links.last_sequence),
+                updated_at = NOW()
+            "#,
+        )
+        .bind(source)
+        .bind(&upsert.external_id)
+        .bind(outcome.entity_id())
+        .bind(upsert.sequence)
+        .execute(&mut **tx)
+        .await?;
+        Ok(outcome)
+    }
+
+    /// Delete the linked entity. The link is kept with the delete's sequence,
+    /// so an older upsert arriving late doesn't bring the entity back.
+    async fn apply_delete(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        source: &str,
+        upsert: &EntityUpsert,
+        link: Option<Link>,
+    ) -> Result<ApplyOutcome, IngestionError> {
+        let Some(link) = link else {
+            return Ok(ApplyOutcome::Ignored);
+        };
+        if link.class_id.is_some() {
+            match self.ontology.delete_entity(link.entity_id, None).await {
+                Ok(_) | Err(OntologyError::NotFound(_)) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        sqlx::query(
+            r#"
+            UPDATE ingestion_links SET
+                last_sequence = COALESCE($3, last_sequence),
+                updated_at = NOW()
+            WHERE source = $1 AND external_id = $2
+            "#,
+        )
+        .bind(source)
+        .bind(&upsert.external_id)
+        .bind(upsert.sequence)
+        .execute(&mut **tx)
+        .await?;
+        Ok(ApplyOutcome::Deleted(link.entity_id))
+    }
+
+    /// The tenant's own class of that name, else the current shared one
+    async fn resolve_class(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        name: &str,
+        tenant_id: Option<Uuid>,
+    ) -> Result<Uuid, IngestionError> {
+        sqlx::query_scalar(
+            r#"
+            SELECT c.id
+            FROM classes c
+            JOIN ontology_versions ov ON ov.id = c.version_id
+            WHERE c.name = $1
+              AND (c.tenant_id = $2 OR (c.tenant_id IS NULL AND (ov.is_current OR ov.is_system)))
+            ORDER BY c.tenant_id IS NULL, ov.is_system, c.created_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(name)
+        .bind(tenant_id)
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or_else(|| IngestionError::Rejected(format!("unknown class {}", name)))
+    }
+
+    async fn resolve_parent(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        source: &str,
+        parent_external_id: &str,
+    ) -> Result<Uuid, IngestionError> {
+        sqlx::query_scalar(
+            r#"
+            SELECT l.entity_id
+            FROM ingestion_links l
+            JOIN entities e ON e.id = l.entity_id AND e.deleted_at IS NULL
+            WHERE l.source = $1 AND l.external_id = $2
+            "#,
+        )
+        .bind(source)
+        .bind(parent_external_id)
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or_else(|| {
+            IngestionError::Rejected(format!(
+                "parent {} has not been ingested",
+                parent_external_id
+            ))
+        })
+    }
+
+    async fn record_failure(
+        &self,
+        source: &str,
+        event_id: Option<&str>,
+        external_id: Option<&str>,
+        error: &str,
+        payload: Option<&serde_json::Value>,
+    ) -> Result<(), IngestionError> {
+        sqlx::query(
+            r#"
+            INSERT INTO ingestion_events (source, event_id, external_id, status, error, payload)
+            VALUES ($1, $2, $3, 'failed', $4, $5)
+            ON CONFLICT (source, event_id) DO UPDATE SET
+                status = 'failed',
+                error = EXCLUDED.error,
+                payload = EXCLUDED.payload,
+                received_at = NOW()
+            "#,
+        )
+        .bind(source)
+        .bind(event_id)
+        .bind(external_id)
+        .bind(error)
+        .bind(payload)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Delete event records past the retention period. Redelivery of an
+    /// event older than that is still caught by the record's sequence.
+    pub async fn prune_events(&self) -> Result<u64, IngestionError> {
+        let result = sqlx::query(
+            "DELETE FROM ingestion_events WHERE received_at < NOW() - make_interval(days => $1::int)",
+        )
+        .bind(self.config.event_retention_days.max(1) as i32)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn sources(&self) -> Result<Vec<IngestionSourceStatus>, IngestionError> {
+        let mut statuses = sqlx::query_as::<_, IngestionSourceStatus>(
+            r#"
+            SELECT name, cursor, events_received, events_failed, last_polled_at,
+                   last_error, last_error_at
+            FROM ingestion_sources
+            ORDER BY name
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        // Configured sources that haven't polled yet
+        for config in &self.config.sources {
+            if !statuses.iter().any(|s| s.name == config.name) {
+                statuses.push(IngestionSourceStatus {
+                    name: config.name.clone(),
+                    cursor: None,
+                    events_received: 0,
+                    events_failed: 0,
+                    last_polled_at: None,
+                    last_error: None,
+                    last_error_at: None,
+                });
+            }
+        }
+        Ok(statuses)
+    }
+
+    pub async fn list_events(
+        &self,
+        query: &IngestionEventsQuery,
+    ) -> Result<Vec<IngestionEvent>, IngestionError> {
+        let events = sqlx::query_as::<_, IngestionEvent>(
+            r#"
+            SELECT id, source, event_id, external_id, status, entity_id, error, payload, received_at
+            FROM ingestion_events
+            WHERE ($1::text IS NULL OR source = $1)
+              AND ($2::text IS NULL OR status = $2)
+            ORDER BY received_at DESC
+            LIMIT $3
+            "#,
+        )
+        .bind(&query.source)
+        .bind(&query.status)
+        .bind(query.limit.unwrap_or(100).clamp(1, 1000))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(events)
+    }
+}
@@ -0,0 +1,227 @@
+use super::models::PolledEvents;
+use super::service::IngestionError;
+use crate::config::IngestionSourceConfig;
+use crate::utils::http_client::OutboundClient;
+use async_nats::jetstream::{self, consumer::pull, consumer::PullConsumer};
+use axum::async_trait;
+use futures::StreamExt;
+use std::time::Duration;
+
+/// Messages read from a source. Each is the decoded JSON, or why it couldn't be
+/// decoded; either way it is handled before the batch is committed.
+#[derive(Debug, Default)]
+pub struct Polled {
+    pub messages: Vec<Result<serde_json::Value, String>>,
+    /// Position to store once the messages are handled (polled sources)
+    pub next_cursor: Option<String>,
+}
+
+/// Where upserts come from. A source waits for messages itself, so the
+/// consumer loop can call `poll` back to back.
+#[async_trait]
+pub trait EventSource: Send {
+    /// Up to `limit` messages after `cursor`. Sources that track their own
+    /// position ignore the cursor.
+    async fn poll(&mut self, cursor: Option<&str>, limit: usize) -> Result<Polled, IngestionError>;
+
+    /// The messages of the last poll were handled and needn't be delivered again
+    async fn commit(&mut self) -> Result<(), IngestionError>;
+}
+
+/// Build the configured source. Fails on an unknown kind or missing
+/// settings, so a misconfigured deployment is caught at startup; connection
+/// problems are retried by the consumer instead.
+pub fn build_source(
+    config: &IngestionSourceConfig,
+    http: &OutboundClient,
+) -> Result<Box<dyn EventSource>, String> {
+    if config.url.trim().is_empty() {
+        return Err(format!("ingestion source '{}' has no url", config.name));
+    }
+    let token = match std::env::var(&config.token_env) {
+        Ok(token) if !config.token_env.is_empty() && !token.is_empty() => Some(token),
+        _ => None,
+    };
+    let wait = Duration::from_secs(config.poll_interval_secs.max(1));
+    match config.kind.as_str() {
+        "nats" => {
+            if config.stream.trim().is_empty() {
+                return Err(format!("ingestion source '{}' has no stream", config.name));
+            }
+            Ok(Box::new(NatsSource {
+                config: config.clone(),
+                token,
+                wait,
+                consumer: None,
+                pending: Vec::new(),
+            }))
+        }
+        "http" => Ok(Box::new(HttpSource {
+            url: config.url.clone(),
+            token,
+            http: http.clone(),
+            wait,
+            caught_up: false,
+        })),
+        other => Err(format!(
+            "ingestion source '{}' has unknown kind '{}', expected 'nats' or 'http'",
+            config.name, other
+        )),
+    }
+}
+
+/// A durable pull consumer on a JetStream stream. Messages are acknowledged
+/// once handled; unacknowledged ones are redelivered by the server.
+pub struct NatsSource {
+    config: IngestionSourceConfig,
+    token: Option<String>,
+    wait: Duration,
+    consumer: Option<PullConsumer>,
+    pending: Vec<jetstream::Message>,
+}
+
+impl NatsSource {
+    fn durable_name(&self) -> String {
+        if self.config.durable_name.trim().is_empty() {
+            format!("ontology-{}", self.config.name)
+        } else {
+            self.config.durable_name.trim().to_string()
+        }
+    }
+
+    async fn connect(&self) -> Result<PullConsumer, IngestionError> {
+        let mut options = async_nats::ConnectOptions::new();
+        if let Some(token) = &self.token {
+            options = options.token(token.clone());
+        }
+        let client = options
+            .connect(self.config.url.as_str())
+            .await
+            .map_err(|e| IngestionError::Source(e.to_string()))?;
+        let stream = jetstream::new(client)
+            .get_stream(self.config.stream.as_str())
+            .await
+            .map_err(|e| IngestionError::Source(e.to_string()))?;
+        let durable_name = self.durable_name();
+        stream
+            .get_or_create_consumer(
+                &durable_name,
+                pull::Config {
+                    durable_name: Some(durable_name.clone()),
+                    filter_subject: self.config.subject.trim().to_string(),
+                    ..Default::default()
+                },
+            )
+            .await
+            .map_err(|e| IngestionError::Source(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl EventSource for NatsSource {
+    async fn poll(
+        &mut self,
+        _cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<Polled, IngestionError> {
+        if self.consumer.is_none() {
+            self.consumer = Some(self.connect().await?);
+        }
+        let consumer = self.consumer.as_ref().expect("connected above");
+        let batch = consumer
+            .batch()
+            .max_messages(limit.max(1))
+            .expires(self.wait)
+            .messages()
+            .await;
+        let mut batch = match batch {
+            Ok(batch) => batch,
+            Err(e) => {
+                // Reconnect on the next poll
+                self.consumer = None;
+                return Err(IngestionError::Source(e.to_string()));
+            }
+        };
+
+        self.pending.clear();
+        let mut polled = Polled::default();
+        while let Some(message) = batch.next().await {
+            let message = message.map_err(|e| IngestionError::Source(e.to_string()))?;
+            polled.messages.push(
+                serde_json::from_slice(&message.payload)
+                    .map_err(|e| format!("invalid JSON: {}", e)),
+            );
+            self.pending.push(message);
+        }
+        Ok(polled)
+    }
+
+    async fn commit(&mut self) -> Result<(), IngestionError> {
+        for message in self.pending.drain(..) {
+            message
+                .ack()
+                .await
+                .map_err(|e| IngestionError::Source(e.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+/// An endpoint answering `GET url?cursor=&limit=` with [`PolledEvents`].
+/// Once it returns a short page it is polled again after the poll interval.
+pub struct HttpSource {
+    url: String,
+    token: Option<String>,
+    http: OutboundClient,
+    wait: Duration,
+    caught_up: bool,
+}
+
+#[async_trait]
+impl EventSource for HttpSource {
+    async fn poll(&mut self, cursor: Option<&str>, limit: usize) -> Result<Polled, IngestionError> {
+        if self.caught_up {
+            tokio::time::sleep(self.wait).await;
+        }
+
+        let limit = limit.max(1);
+        let limit_param = limit.to_string();
+        let mut query = vec![("limit", limit_param.as_str())];
+        if let Some(cursor) = cursor {
+            query.push(("cursor", cursor));
+        }
+        let res = self
+            .http
+            .send(|client| {
+                let request = client.get(&self.url).query(&query);
+                match &self.token {
+                    Some(token) => request.bearer_auth(token),
+                    None => request,
+                }
+            })
+            .await
+            .map_err(|e| IngestionError::Source(e.to_string()))?;
+        let status = res.status();
+        if !status.is_success() {
+            let text = res.text().await.unwrap_or_default();
+            return Err(IngestionError::Source(format!(
+                "polling {} returned {}: {}",
+                self.url, status, text
+            )));
+        }
+        let body: PolledEvents = res
+            .json()
+            .await
+            .map_err(|e| IngestionError::Source(format!("invalid response: {}", e)))?;
+
+        self.caught_up = body.events.len() < limit;
+        Ok(Polled {
+            messages: body.events.into_iter().map(Ok).collect(),
+            next_cursor: body.next_cursor,
+        })
+    }
+
+    async fn commit(&mut self) -> Result<(), IngestionError> {
+        Ok(())
+    }
+}
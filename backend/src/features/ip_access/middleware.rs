@@ -0,0 +1,72 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+use std::net::SocketAddr;
+
+use crate::features::ip_access::models::{AccessDecision, RequestOrigin};
+use crate::features::ip_access::service::IpAccessService;
+use crate::middleware::auth::peek_user_id;
+use crate::utils::ip::client_ip;
+
+/// Runs before authentication, so the caller's identity is read here only as a
+/// hint for scoping rules; an invalid token simply leaves the request unscoped.
+pub async fn ip_access_middleware(
+    State(service): State<IpAccessService>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !service.has_rules() {
+        return next.run(request).await;
+    }
+
+    let ip = client_ip(
+        request.headers(),
+        request.extensions().get::<ConnectInfo<SocketAddr>>(),
+        service.trusted_proxies(),
+    );
+    let user_id = peek_user_id(request.headers(), request.extensions()).await;
+    let tenant_id = match user_id {
+        Some(user_id) if service.has_tenant_rules() => service.resolve_tenant(user_id).await,
+        _ => None,
+    };
+    let origin = RequestOrigin {
+        api_key: request
+            .headers()
+            .get("x-api-key")
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_string()),
+        tenant_id,
+    };
+
+    let path = request.uri().path().to_string();
+    let decision = service.evaluate(ip, &path, &origin);
+    if decision == AccessDecision::Allowed {
+        return next.run(request).await;
+    }
+
+    tracing::warn!(
+        ip = ?ip,
+        path = %path,
+        "Request blocked by IP access rules"
+    );
+    let method = request.method().to_string();
+    tokio::spawn(async move {
+        if let Err(e) = service
+            .record_blocked(ip, &method, &path, user_id, &decision)
+            .await
+        {
+            tracing::error!("Failed to record blocked IP attempt: {}", e);
+        }
+    });
+
+    (
+        StatusCode::FORBIDDEN,
+        Json(json!({ "error": "Access from this IP address is not allowed" })),
+    )
+        .into_response()
+}
@@ -0,0 +1,82 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::utils::ip::Cidr;
+use crate::utils::validation::not_blank;
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct IpAccessRule {
+    pub id: Uuid,
+    pub cidr: String,
+    /// "allow" or "deny"
+    pub action: String,
+    /// "global", "tenant" or "api_key"
+    pub scope: String,
+    pub tenant_id: Option<Uuid>,
+    pub api_key_id: Option<Uuid>,
+    pub route_prefix: Option<String>,
+    pub description: Option<String>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Row shape loaded for the middleware, joined with the API key prefix
+#[derive(Debug, FromRow)]
+pub struct ActiveIpRuleRow {
+    pub id: Uuid,
+    pub cidr: String,
+    pub action: String,
+    pub scope: String,
+    pub tenant_id: Option<Uuid>,
+    pub api_key_prefix: Option<String>,
+    pub route_prefix: Option<String>,
+}
+
+/// Which requests a rule applies to
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RuleScope {
+    Global,
+    Tenant(Uuid),
+    ApiKey(String),
+}
+
+/// Parsed rule held in memory so requests are checked without a database round trip
+#[derive(Debug, Clone)]
+pub struct ActiveIpRule {
+    pub id: Uuid,
+    pub network: Cidr,
+    pub allow: bool,
+    pub scope: RuleScope,
+    pub route_prefix: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateIpRuleRequest {
+    #[validate(custom = "not_blank")]
+    pub cidr: String,
+    pub action: String,
+    pub scope: String,
+    pub tenant_id: Option<Uuid>,
+    pub api_key_id: Option<Uuid>,
+    pub route_prefix: Option<String>,
+    pub description: Option<String>,
+}
+
+/// Who is making a request, as far as can be told before authentication
+#[derive(Debug, Default, Clone)]
+pub struct RequestOrigin {
+    pub api_key: Option<String>,
+    pub tenant_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccessDecision {
+    Allowed,
+    /// Matched a deny rule
+    Denied(Uuid),
+    /// Allow rules apply to this request but none matched
+    NotAllowed,
+}
@@ -0,0 +1,70 @@
+use crate::features::auth::jwt::Claims;
+use crate::features::ip_access::models::{CreateIpRuleRequest, IpAccessRule};
+use crate::features::ip_access::service::{IpAccessError, IpAccessService};
+use crate::middleware::abac::{enforce_route_permissions, RoutePermission};
+use crate::utils::api_error::ApiError;
+use crate::utils::validation::ValidatedJson;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{delete, get},
+    Extension, Json, Router,
+};
+use uuid::Uuid;
+
+/// Permissions enforced by `enforce_route_permissions` for the routes below
+pub const ROUTE_PERMISSIONS: &[RoutePermission] = &[
+    RoutePermission::new("GET", "/rules", "manage_system"),
+    RoutePermission::new("POST", "/rules", "manage_system"),
+    RoutePermission::new("DELETE", "/rules/:id", "manage_system"),
+];
+
+pub fn ip_access_routes() -> Router<IpAccessService> {
+    Router::new()
+        .route("/rules", get(list_rules_handler).post(create_rule_handler))
+        .route("/rules/:id", delete(delete_rule_handler))
+        .route_layer(axum::middleware::from_fn_with_state(
+            ROUTE_PERMISSIONS,
+            enforce_route_permissions,
+        ))
+}
+
+#[axum::debug_handler]
+async fn list_rules_handler(
+    State(service): State<IpAccessService>,
+) -> Result<Json<Vec<IpAccessRule>>, IpAccessError> {
+    Ok(Json(service.list_rules().await?))
+}
+
+#[axum::debug_handler]
+async fn create_rule_handler(
+    State(service): State<IpAccessService>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(input): ValidatedJson<CreateIpRuleRequest>,
+) -> Result<(StatusCode, Json<IpAccessRule>), IpAccessError> {
+    let created_by = Uuid::parse_str(&claims.sub).ok();
+    let rule = service.create_rule(input, created_by).await?;
+    Ok((StatusCode::CREATED, Json(rule)))
+}
+
+#[axum::debug_handler]
+async fn delete_rule_handler(
+    State(service): State<IpAccessService>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, IpAccessError> {
+    service.delete_rule(id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+impl axum::response::IntoResponse for IpAccessError {
+    fn into_response(self) -> axum::response::Response {
+        let (status, code) = match &self {
+            IpAccessError::DatabaseError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+            IpAccessError::Forbidden(_) => (StatusCode::FORBIDDEN, "ip_access.forbidden"),
+            IpAccessError::InvalidInput(_) => (StatusCode::BAD_REQUEST, "ip_access.invalid_input"),
+            IpAccessError::NotFound => (StatusCode::NOT_FOUND, "ip_access.not_found"),
+        };
+
+        ApiError::new(status, code, self.to_string()).into_response()
+    }
+}
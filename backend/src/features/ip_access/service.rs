@@ -0,0 +1,333 @@
+use super::models::{
+    AccessDecision, ActiveIpRule, ActiveIpRuleRow, CreateIpRuleRequest, IpAccessRule,
+    RequestOrigin, RuleScope,
+};
+use crate::utils::ip::Cidr;
+use crate::utils::shutdown::Shutdown;
+use moka::future::Cache;
+use serde_json::json;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use thiserror::Error;
+use uuid::Uuid;
+
+/// Other instances pick up rule changes within this interval
+const RULE_REFRESH_SECS: u64 = 60;
+
+const RULE_COLUMNS: &str = "id, cidr, action, scope, tenant_id, api_key_id, route_prefix, description, created_by, created_at";
+
+#[derive(Error, Debug)]
+pub enum IpAccessError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    #[error("Rule not found")]
+    NotFound,
+}
+
+#[derive(Clone)]
+pub struct IpAccessService {
+    pool: PgPool,
+    trusted_proxies: Arc<Vec<Cidr>>,
+    active_rules: Arc<RwLock<Vec<ActiveIpRule>>>,
+    // user entity id -> tenant id, so tenant rules don't cost a query per request
+    tenant_cache: Cache<Uuid, Option<Uuid>>,
+}
+
+impl IpAccessService {
+    pub fn new(pool: PgPool, trusted_proxies: Vec<Cidr>) -> Self {
+        Self {
+            pool,
+            trusted_proxies: Arc::new(trusted_proxies),
+            active_rules: Arc::new(RwLock::new(Vec::new())),
+            tenant_cache: Cache::builder()
+                .max_capacity(10_000)
+                .time_to_live(Duration::from_secs(300))
+                .build(),
+        }
+    }
+
+    pub fn trusted_proxies(&self) -> &[Cidr] {
+        &self.trusted_proxies
+    }
+
+    // ========================================================================
+    // RULES
+    // ========================================================================
+
+    pub async fn create_rule(
+        &self,
+        req: CreateIpRuleRequest,
+        created_by: Option<Uuid>,
+    ) -> Result<IpAccessRule, IpAccessError> {
+        let network: Cidr = req.cidr.parse().map_err(IpAccessError::InvalidInput)?;
+
+        if !["allow", "deny"].contains(&req.action.as_str()) {
+            return Err(IpAccessError::InvalidInput(
+                "action must be 'allow' or 'deny'".to_string(),
+            ));
+        }
+        let (tenant_id, api_key_id) = match req.scope.as_str() {
+            "global" => (None, None),
+            "tenant" => (
+                Some(req.tenant_id.ok_or_else(|| {
+                    IpAccessError::InvalidInput("tenant scope requires tenant_id".to_string())
+                })?),
+                None,
+            ),
+            "api_key" => (
+                None,
+                Some(req.api_key_id.ok_or_else(|| {
+                    IpAccessError::InvalidInput("api_key scope requires api_key_id".to_string())
+                })?),
+            ),
+            _ => {
+                return Err(IpAccessError::InvalidInput(
+                    "scope must be 'global', 'tenant' or 'api_key'".to_string(),
+                ))
+            }
+        };
+
+        let route_prefix = req
+            .route_prefix
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty());
+        if route_prefix.as_ref().is_some_and(|p| !p.starts_with('/')) {
+            return Err(IpAccessError::InvalidInput(
+                "route_prefix must start with '/'".to_string(),
+            ));
+        }
+
+        let rule = sqlx::query_as::<_, IpAccessRule>(&format!(
+            r#"
+            INSERT INTO ip_access_rules
+                (cidr, action, scope, tenant_id, api_key_id, route_prefix, description, created_by)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING {}
+            "#,
+            RULE_COLUMNS
+        ))
+        .bind(network.to_string())
+        .bind(&req.action)
+        .bind(&req.scope)
+        .bind(tenant_id)
+        .bind(api_key_id)
+        .bind(route_prefix)
+        .bind(req.description)
+        .bind(created_by)
+        .fetch_one(&self.pool)
+        .await?;
+
+        self.refresh_rules().await?;
+        Ok(rule)
+    }
+
+    pub async fn list_rules(&self) -> Result<Vec<IpAccessRule>, IpAccessError> {
+        let rules = sqlx::query_as::<_, IpAccessRule>(&format!(
+            "SELECT {} FROM ip_access_rules ORDER BY created_at DESC",
+            RULE_COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rules)
+    }
+
+    pub async fn delete_rule(&self, id: Uuid) -> Result<(), IpAccessError> {
+        let result = sqlx::query("DELETE FROM ip_access_rules WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(IpAccessError::NotFound);
+        }
+        self.refresh_rules().await?;
+        Ok(())
+    }
+
+    /// Reload the rule set used by the middleware
+    pub async fn refresh_rules(&self) -> Result<(), IpAccessError> {
+        let rows = sqlx::query_as::<_, ActiveIpRuleRow>(
+            r#"
+            SELECT r.id, r.cidr, r.action, r.scope, r.tenant_id,
+                   k.prefix AS api_key_prefix, r.route_prefix
+            FROM ip_access_rules r
+            LEFT JOIN api_keys k ON k.id = r.api_key_id
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let rules = rows
+            .into_iter()
+            .filter_map(|row| {
+                let network = match row.cidr.parse() {
+                    Ok(network) => network,
+                    Err(e) => {
+                        tracing::warn!("Skipping IP rule {} with invalid CIDR: {}", row.id, e);
+                        return None;
+                    }
+                };
+                let scope = match row.scope.as_str() {
+                    "tenant" => RuleScope::Tenant(row.tenant_id?),
+                    "api_key" => RuleScope::ApiKey(row.api_key_prefix?),
+                    _ => RuleScope::Global,
+                };
+                Some(ActiveIpRule {
+                    id: row.id,
+                    network,
+                    allow: row.action == "allow",
+                    scope,
+                    route_prefix: row.route_prefix,
+                })
+            })
+            .collect();
+
+        *self.active_rules.write().unwrap_or_else(|e| e.into_inner()) = rules;
+        Ok(())
+    }
+
+    pub async fn start_refresh_task(self, shutdown: Shutdown) {
+        if let Err(e) = self.refresh_rules().await {
+            tracing::error!("Failed to load IP access rules: {}", e);
+        }
+        shutdown.clone().spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(RULE_REFRESH_SECS));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = shutdown.cancelled() => break,
+                }
+                if let Err(e) = self.refresh_rules().await {
+                    tracing::error!("Failed to refresh IP access rules: {}", e);
+                }
+            }
+        });
+    }
+
+    // ========================================================================
+    // EVALUATION
+    // ========================================================================
+
+    pub fn has_rules(&self) -> bool {
+        !self
+            .active_rules
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_empty()
+    }
+
+    pub fn has_tenant_rules(&self) -> bool {
+        self.active_rules
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .any(|r| matches!(r.scope, RuleScope::Tenant(_)))
+    }
+
+    /// Decide whether `ip` may reach `path`.
+    ///
+    /// Rules apply when their scope matches the request origin and their route
+    /// prefix (if any) matches the path. Any matching deny rule blocks. Allow
+    /// rules form an allowlist per scope: if a scope has applicable allow rules,
+    /// the client must match one of them, so global and tenant allowlists both
+    /// have to be satisfied. An unknown client address matches nothing.
+    pub fn evaluate(
+        &self,
+        ip: Option<IpAddr>,
+        path: &str,
+        origin: &RequestOrigin,
+    ) -> AccessDecision {
+        let guard = self.active_rules.read().unwrap_or_else(|e| e.into_inner());
+        let hit = |rule: &ActiveIpRule| ip.is_some_and(|ip| rule.network.contains(ip));
+
+        let mut allowlists: HashMap<&RuleScope, bool> = HashMap::new();
+        for rule in guard.iter() {
+            let route_ok = rule
+                .route_prefix
+                .as_deref()
+                .is_none_or(|prefix| path.starts_with(prefix));
+            let scope_ok = match &rule.scope {
+                RuleScope::Global => true,
+                RuleScope::Tenant(tenant_id) => origin.tenant_id == Some(*tenant_id),
+                RuleScope::ApiKey(prefix) => origin
+                    .api_key
+                    .as_deref()
+                    .is_some_and(|k| k.starts_with(prefix.as_str())),
+            };
+            if !route_ok || !scope_ok {
+                continue;
+            }
+
+            if rule.allow {
+                *allowlists.entry(&rule.scope).or_default() |= hit(rule);
+            } else if hit(rule) {
+                return AccessDecision::Denied(rule.id);
+            }
+        }
+
+        if allowlists.values().all(|matched| *matched) {
+            AccessDecision::Allowed
+        } else {
+            AccessDecision::NotAllowed
+        }
+    }
+
+    /// Tenant of a user entity, cached briefly
+    pub async fn resolve_tenant(&self, user_id: Uuid) -> Option<Uuid> {
+        if let Some(tenant_id) = self.tenant_cache.get(&user_id).await {
+            return tenant_id;
+        }
+
+        let tenant_id = match sqlx::query_scalar::<_, Option<Uuid>>(
+            "SELECT tenant_id FROM entities WHERE id = $1",
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        {
+            Ok(row) => row.flatten(),
+            Err(e) => {
+                tracing::error!("Failed to resolve tenant for IP rules: {}", e);
+                return None;
+            }
+        };
+        self.tenant_cache.insert(user_id, tenant_id).await;
+        tenant_id
+    }
+
+    /// Record a blocked request in security_events
+    pub async fn record_blocked(
+        &self,
+        ip: Option<IpAddr>,
+        method: &str,
+        path: &str,
+        user_id: Option<Uuid>,
+        decision: &AccessDecision,
+    ) -> Result<(), IpAccessError> {
+        let details = match decision {
+            AccessDecision::Denied(rule_id) => json!({ "reason": "deny_rule", "rule_id": rule_id }),
+            _ => json!({ "reason": "not_in_allowlist" }),
+        };
+
+        sqlx::query(
+            "SELECT log_security_event('ip_blocked', 'warning', $1, $2::inet, $3, $4, 'blocked', $5)",
+        )
+        .bind(user_id)
+        .bind(ip.map(|ip| ip.to_string()))
+        .bind(path)
+        .bind(method)
+        .bind(details)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
@@ -3,16 +3,28 @@ pub mod ai;
 pub mod api_management;
 pub mod auth;
 pub mod dashboard;
+pub mod decision_log;
 pub mod discovery;
+pub mod email;
+pub mod environment;
 pub mod firefighter;
+pub mod geo_access;
+pub mod graph_sync;
+pub mod ingestion;
+pub mod ip_access;
 pub mod navigation;
+pub mod notifications;
 pub mod ontology;
 pub mod projects;
 pub mod rate_limit;
 pub mod rebac;
+pub mod request_capture;
+pub mod search_index;
+pub mod storage;
 pub mod system;
 pub mod users;
 // Temporarily disabled due to compilation issues
 // pub mod monitoring;
 pub mod test_marker;
 pub mod test_mode;
+pub mod uploads;
@@ -1,29 +1,72 @@
 use super::models::AlertRule;
-use reqwest::Client;
+use crate::features::ai::alert_explanations::AlertExplanationService;
+use crate::utils::http_client::OutboundClient;
 use serde_json::json;
+use sqlx::PgPool;
 use std::env;
 use tracing;
+use uuid::Uuid;
 
 /// Alert System
 /// Sends security alerts to configured channels (Slack, Discord, Email, etc.)
 #[derive(Clone)]
 pub struct AlertSystem {
-    client: Client,
+    /// Retries transient webhook failures with backoff
+    client: OutboundClient,
     slack_webhook: Option<String>,
     discord_webhook: Option<String>,
     pagerduty_key: Option<String>,
+    /// Attaches AI explanations to recorded alerts, when configured
+    explanations: Option<AlertExplanationService>,
 }
 
 impl AlertSystem {
     pub fn new() -> Self {
         Self {
-            client: Client::new(),
+            client: OutboundClient::default(),
             slack_webhook: env::var("SLACK_WEBHOOK_URL").ok(),
             discord_webhook: env::var("DISCORD_WEBHOOK_URL").ok(),
             pagerduty_key: env::var("PAGERDUTY_INTEGRATION_KEY").ok(),
+            explanations: None,
         }
     }
 
+    pub fn with_explanations(mut self, explanations: AlertExplanationService) -> Self {
+        self.explanations = Some(explanations);
+        self
+    }
+
+    /// Record the alert with the events that triggered it, send it, then
+    /// explain it in the background so the notification isn't delayed
+    pub async fn raise(
+        &self,
+        db: &PgPool,
+        rule: &AlertRule,
+        event_count: i64,
+    ) -> Result<Uuid, Box<dyn std::error::Error>> {
+        let message = self.format_alert_message(rule, event_count);
+        let alert_id: Uuid = sqlx::query_scalar(
+            r#"
+            INSERT INTO security_alerts (rule_id, rule_name, event_count, event_ids, message)
+            VALUES ($1, $2, $3, alert_rule_event_ids($1), $4)
+            RETURNING id
+            "#,
+        )
+        .bind(rule.id)
+        .bind(&rule.rule_name)
+        .bind(event_count)
+        .bind(&message)
+        .fetch_one(db)
+        .await?;
+
+        self.send_alert(rule, event_count).await.ok();
+
+        if let Some(explanations) = &self.explanations {
+            explanations.explain_in_background(alert_id);
+        }
+        Ok(alert_id)
+    }
+
     /// Send alert based on rule configuration
     pub async fn send_alert(&self, rule: &AlertRule, event_count: i64) -> Result<(), Box<dyn std::error::Error>> {
         let message = self.format_alert_message(rule, event_count);
@@ -78,10 +121,9 @@ impl AlertSystem {
                 "icon_emoji": ":shield:"
             });
 
-            let response = self.client
-                .post(webhook_url)
-                .json(&payload)
-                .send()
+            let response = self
+                .client
+                .send(|client| client.post(webhook_url).json(&payload))
                 .await?;
 
             if !response.status().is_success() {
@@ -105,10 +147,9 @@ impl AlertSystem {
                 "avatar_url": "https://cdn-icons-png.flaticon.com/512/2913/2913133.png"
             });
 
-            let response = self.client
-                .post(webhook_url)
-                .json(&payload)
-                .send()
+            let response = self
+                .client
+                .send(|client| client.post(webhook_url).json(&payload))
                 .await?;
 
             if !response.status().is_success() {
@@ -145,10 +186,9 @@ impl AlertSystem {
                 }
             });
 
-            let response = self.client
-                .post("https://events.pagerduty.com/v2/enqueue")
-                .json(&payload)
-                .send()
+            let response = self
+                .client
+                .send(|client| client.post("https://events.pagerduty.com/v2/enqueue").json(&payload))
                 .await?;
 
             if !response.status().is_success() {
@@ -172,10 +212,9 @@ impl AlertSystem {
                 "timestamp": chrono::Utc::now().to_rfc3339()
             });
 
-            let response = self.client
-                .post(&webhook_url)
-                .json(&payload)
-                .send()
+            let response = self
+                .client
+                .send(|client| client.post(&webhook_url).json(&payload))
                 .await?;
 
             if !response.status().is_success() {
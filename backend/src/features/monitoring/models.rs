@@ -2,6 +2,7 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
+use validator::Validate;
 
 /// Failed authentication attempt record
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -140,7 +141,7 @@ pub struct SecurityEventSummary {
 }
 
 /// Create failed auth attempt request
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
 pub struct CreateFailedAuthAttempt {
     pub attempted_identifier: String,
     pub user_id: Option<Uuid>,
@@ -152,7 +153,7 @@ pub struct CreateFailedAuthAttempt {
 }
 
 /// Create security event request
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
 pub struct CreateSecurityEvent {
     pub event_type: String,
     pub severity: Severity,
@@ -251,9 +251,9 @@ impl MonitoringService {
                 .fetch_one(&self.db)
                 .await?;
 
-                // Send alert
+                // Record, send and (optionally) explain the alert
                 self.alert_system
-                    .send_alert(&rule, trigger.event_count)
+                    .raise(&self.db, &rule, trigger.event_count)
                     .await
                     .ok();
 
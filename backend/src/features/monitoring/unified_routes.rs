@@ -13,6 +13,7 @@ use tracing;
 
 use super::unified_service::UnifiedMonitoringService;
 use crate::features::auth::jwt::Claims;
+use crate::utils::validation::ValidatedJson;
 
 /// Query parameters for listing
 #[derive(Debug, Deserialize)]
@@ -154,7 +155,7 @@ pub async fn get_monitoring_entity(
 /// Create failed auth attempt in ontology
 pub async fn create_failed_auth_ontology(
     State(service): State<Arc<UnifiedMonitoringService>>,
-    Json(request): Json<super::models::CreateFailedAuthAttempt>,
+    ValidatedJson(request): ValidatedJson<super::models::CreateFailedAuthAttempt>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
     let entity_id = service
         .log_failed_auth_ontology(request)
@@ -174,7 +175,7 @@ pub async fn create_failed_auth_ontology(
 /// Create security event in ontology
 pub async fn create_security_event_ontology(
     State(service): State<Arc<UnifiedMonitoringService>>,
-    Json(request): Json<super::models::CreateSecurityEvent>,
+    ValidatedJson(request): ValidatedJson<super::models::CreateSecurityEvent>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
     let entity_id = service
         .log_security_event_ontology(request)
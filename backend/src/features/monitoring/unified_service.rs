@@ -522,9 +522,9 @@ impl UnifiedMonitoringService {
                     updated_at: chrono::Utc::now(),
                 };
 
-                // Send alert
+                // Record, send and (optionally) explain the alert
                 self.alert_system
-                    .send_alert(&alert_rule, trigger.event_count.unwrap_or(0))
+                    .raise(&self.db, &alert_rule, trigger.event_count.unwrap_or(0))
                     .await
                     .ok();
 
@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use super::models::NavBadges;
+use super::service::{NavigationError, NavigationService};
+use crate::utils::cache::CacheScope;
+
+/// Role grants ending within this many days count as expiring
+const EXPIRING_GRANT_DAYS: i32 = 7;
+
+/// Navigation item that shows each admin count, and the permission needed to open it
+const PENDING_APPROVALS: (&str, &str) = ("admin.ontology.designer", "ui.view.ontology");
+const EXPIRING_GRANTS: (&str, &str) = ("admin.schedules", "ui.view.schedules");
+const OPEN_FIREFIGHTER_SESSIONS: (&str, &str) = ("admin.firefighter", "ui.view.firefighter");
+
+/// Admin counts, the same for every user of a tenant
+#[derive(FromRow, Serialize, Deserialize)]
+struct AdminBadgeCounts {
+    pending_approvals: i64,
+    expiring_grants: i64,
+    open_firefighter_sessions: i64,
+}
+
+impl NavigationService {
+    // ========================================================================
+    // BADGE COUNTS
+    // ========================================================================
+
+    /// Counts for the sidebar. Admin counts are only returned when the user
+    /// may open the item showing them; pending approvals are limited to the
+    /// user's tenant when they have one.
+    pub async fn badge_counts(&self, user_id: Uuid) -> Result<NavBadges, NavigationError> {
+        let (approvals, grants, firefighter, tenant_id) = tokio::try_join!(
+            self.can(user_id, PENDING_APPROVALS.1),
+            self.can(user_id, EXPIRING_GRANTS.1),
+            self.can(user_id, OPEN_FIREFIGHTER_SESSIONS.1),
+            self.user_tenant(user_id),
+        )?;
+
+        let unread_notifications: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM unified_notifications
+            WHERE user_id = $1 AND NOT COALESCE(read, FALSE)
+            "#,
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let mut badges = NavBadges {
+            unread_notifications,
+            ..Default::default()
+        };
+        if !(approvals || grants || firefighter) {
+            return Ok(badges);
+        }
+
+        let counts = match &self.cache {
+            Some(cache) => {
+                let key = format!(
+                    "badges:{}",
+                    tenant_id.map_or_else(|| "all".to_string(), |t| t.to_string())
+                );
+                cache
+                    .get_or_load(CacheScope::Aggregates, &key, || {
+                        self.admin_badge_counts(tenant_id)
+                    })
+                    .await?
+            }
+            None => self.admin_badge_counts(tenant_id).await?,
+        };
+        badges.items = [
+            (PENDING_APPROVALS.0, approvals, counts.pending_approvals),
+            (EXPIRING_GRANTS.0, grants, counts.expiring_grants),
+            (
+                OPEN_FIREFIGHTER_SESSIONS.0,
+                firefighter,
+                counts.open_firefighter_sessions,
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, allowed, _)| *allowed)
+        .map(|(item_id, _, count)| (item_id.to_string(), count))
+        .collect();
+        Ok(badges)
+    }
+
+    async fn admin_badge_counts(
+        &self,
+        tenant_id: Option<Uuid>,
+    ) -> Result<AdminBadgeCounts, NavigationError> {
+        let counts = sqlx::query_as::<_, AdminBadgeCounts>(
+            r#"
+            SELECT
+                (
+                    SELECT COUNT(*) FROM entities
+                    WHERE approval_status = 'PENDING' AND deleted_at IS NULL
+                      AND ($1::uuid IS NULL OR tenant_id = $1)
+                ) AS pending_approvals,
+                (
+                    SELECT COUNT(*) FROM relationships r
+                    JOIN relationship_types rt ON rt.id = r.relationship_type_id
+                    WHERE rt.name = 'has_role'
+                      AND (r.metadata->>'valid_until')::timestamptz
+                          BETWEEN NOW() AND NOW() + make_interval(days => $2)
+                ) AS expiring_grants,
+                (
+                    SELECT COUNT(*) FROM firefighter_sessions
+                    WHERE deactivated_at IS NULL AND expires_at > NOW()
+                ) AS open_firefighter_sessions
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(EXPIRING_GRANT_DAYS)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(counts)
+    }
+}
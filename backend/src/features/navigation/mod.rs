@@ -1,4 +1,6 @@
+pub mod badges;
 pub mod models;
+pub mod quick_search;
 pub mod routes;
 pub mod service;
 
@@ -1,5 +1,9 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use sqlx::FromRow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use uuid::Uuid;
+use validator::Validate;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct NavItemDefinition {
@@ -32,6 +36,12 @@ pub struct NavItemVisibility {
     pub missing_permissions: Vec<String>,
     #[serde(default)]
     pub reasons: Vec<String>,
+    /// Pinned by the user; pinned items come first in their section
+    #[serde(default)]
+    pub pinned: bool,
+    /// Hidden by the user, so not visible whatever their permissions
+    #[serde(default)]
+    pub hidden: bool,
     #[serde(default)]
     pub children: Vec<NavItemVisibility>,
 }
@@ -54,6 +64,79 @@ pub struct NavItemSummary {
     pub section_label: String,
 }
 
+/// A menu tree that replaces the built-in navigation for a tenant, a role,
+/// or a role within a tenant. Both unset is the deployment-wide default.
+#[derive(Clone, Debug, Serialize, FromRow)]
+pub struct NavigationMenu {
+    pub id: Uuid,
+    pub tenant_id: Option<Uuid>,
+    pub role_name: Option<String>,
+    pub priority: i32,
+    pub sections: sqlx::types::Json<Vec<NavSectionDefinition>>,
+    pub updated_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct SaveNavigationMenu {
+    pub tenant_id: Option<Uuid>,
+    pub role_name: Option<String>,
+    #[serde(default)]
+    pub priority: i32,
+    pub sections: Vec<NavSectionDefinition>,
+}
+
+#[derive(Clone, Debug, Serialize, FromRow)]
+pub struct NavPreference {
+    pub item_id: String,
+    pub pinned: bool,
+    pub hidden: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateNavPreference {
+    pub pinned: Option<bool>,
+    pub hidden: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuickSearchQuery {
+    pub q: String,
+    /// Results per group
+    pub limit: Option<i64>,
+}
+
+#[derive(Clone, Debug, Serialize, FromRow)]
+pub struct QuickSearchHit {
+    pub id: Uuid,
+    pub label: String,
+    /// Class name, project status, email or class description
+    pub detail: Option<String>,
+    /// 1.0 exact, 0.8 prefix, 0.6 word prefix, 0.4 anywhere in the name;
+    /// entities found through the search index score relative to the best hit
+    pub score: f64,
+}
+
+/// Quick-search matches grouped by kind, best first within each group
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct QuickSearchResults {
+    pub query: String,
+    pub entities: Vec<QuickSearchHit>,
+    pub projects: Vec<QuickSearchHit>,
+    pub users: Vec<QuickSearchHit>,
+    pub classes: Vec<QuickSearchHit>,
+}
+
+/// Sidebar badge counts. `items` is keyed by navigation item id and only
+/// holds items the user may open.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct NavBadges {
+    pub items: BTreeMap<String, i64>,
+    pub unread_notifications: i64,
+}
+
 pub fn default_navigation() -> Vec<NavSectionDefinition> {
     vec![
         NavSectionDefinition {
@@ -225,6 +308,88 @@ pub fn evaluate_navigation(
         .collect()
 }
 
+/// Every permission any item in the tree requires
+pub fn required_permissions(sections: &[NavSectionDefinition]) -> HashSet<String> {
+    fn collect(item: &NavItemDefinition, permissions: &mut HashSet<String>) {
+        permissions.extend(item.required_permissions.iter().cloned());
+        for child in &item.children {
+            collect(child, permissions);
+        }
+    }
+
+    let mut permissions = HashSet::new();
+    for item in sections.iter().flat_map(|s| s.items.iter()) {
+        collect(item, &mut permissions);
+    }
+    permissions
+}
+
+/// Check an admin-defined menu: ids unique across the tree, labels set and
+/// links relative to the app
+pub fn validate_sections(sections: &[NavSectionDefinition]) -> Result<(), String> {
+    fn check_item(item: &NavItemDefinition, ids: &mut HashSet<String>) -> Result<(), String> {
+        if item.id.trim().is_empty() || item.label.trim().is_empty() {
+            return Err("Navigation items need an id and a label".to_string());
+        }
+        if !item.href.starts_with('/') {
+            return Err(format!("Link of {} must start with '/'", item.id));
+        }
+        if !ids.insert(item.id.clone()) {
+            return Err(format!("Duplicate navigation id: {}", item.id));
+        }
+        item.children.iter().try_for_each(|c| check_item(c, ids))
+    }
+
+    if sections.is_empty() {
+        return Err("A menu needs at least one section".to_string());
+    }
+    let mut ids = HashSet::new();
+    for section in sections {
+        if section.id.trim().is_empty() || section.label.trim().is_empty() {
+            return Err("Navigation sections need an id and a label".to_string());
+        }
+        if !ids.insert(section.id.clone()) {
+            return Err(format!("Duplicate navigation id: {}", section.id));
+        }
+        section
+            .items
+            .iter()
+            .try_for_each(|item| check_item(item, &mut ids))?;
+    }
+    Ok(())
+}
+
+/// Mark the user's pinned and hidden items, hide what they hid and move
+/// pinned items to the top of their section or parent. Runs after ABAC
+/// filtering, so pinning never reveals an item.
+pub fn apply_preferences(sections: &mut [NavSectionVisibility], preferences: &[NavPreference]) {
+    fn apply(items: &mut [NavItemVisibility], preferences: &HashMap<&str, &NavPreference>) {
+        for item in items.iter_mut() {
+            apply(&mut item.children, preferences);
+            if let Some(preference) = preferences.get(item.id.as_str()) {
+                item.pinned = preference.pinned;
+                if preference.hidden {
+                    item.hidden = true;
+                    if item.visible {
+                        item.visible = false;
+                        item.reasons.push("Hidden by user".to_string());
+                    }
+                }
+            }
+        }
+        items.sort_by_key(|item| !item.pinned);
+    }
+
+    let preferences: HashMap<&str, &NavPreference> = preferences
+        .iter()
+        .map(|p| (p.item_id.as_str(), p))
+        .collect();
+    for section in sections.iter_mut() {
+        apply(&mut section.items, &preferences);
+        section.visible = section.items.iter().any(|item| item.visible);
+    }
+}
+
 pub fn flatten_visible_items(sections: &[NavSectionVisibility]) -> Vec<NavItemSummary> {
     let mut items = Vec::new();
     for section in sections {
@@ -279,6 +444,8 @@ fn evaluate_item(item: &NavItemDefinition, permissions: &HashSet<String>) -> Nav
         visible: final_visible,
         missing_permissions,
         reasons,
+        pinned: false,
+        hidden: false,
         children,
     }
 }
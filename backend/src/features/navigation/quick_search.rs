@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use super::models::{QuickSearchHit, QuickSearchResults};
+use super::service::{NavigationError, NavigationService};
+use crate::features::search_index::models::IndexHit;
+
+/// Shorter queries match too much to be useful while typing
+const MIN_QUERY_CHARS: usize = 2;
+const DEFAULT_LIMIT: i64 = 5;
+const MAX_LIMIT: i64 = 20;
+/// Candidates fetched per result slot before permission filtering
+const OVERFETCH: i64 = 4;
+
+/// Search terms for the SQL below: $1 the lowercased query, $2 the same with
+/// LIKE wildcards escaped
+struct Terms {
+    lower: String,
+    like: String,
+}
+
+/// Score a match on `column` the way `QuickSearchHit::score` describes
+fn rank(column: &str) -> String {
+    format!(
+        r#"CASE WHEN lower({c}) = $1 THEN 1.0
+                WHEN lower({c}) LIKE $2 || '%' ESCAPE '\' THEN 0.8
+                WHEN lower({c}) LIKE '% ' || $2 || '%' ESCAPE '\' THEN 0.6
+                ELSE 0.4 END::float8"#,
+        c = column
+    )
+}
+
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+impl NavigationService {
+    /// Type-ahead search over entities, projects, users and classes. Each
+    /// group is limited separately and only holds what the user may see:
+    /// entities and projects by ReBAC, users and classes by the ABAC
+    /// permissions that guard their admin pages.
+    pub async fn quick_search(
+        &self,
+        user_id: Uuid,
+        query: &str,
+        limit: Option<i64>,
+    ) -> Result<QuickSearchResults, NavigationError> {
+        let query = query.trim();
+        let mut results = QuickSearchResults {
+            query: query.to_string(),
+            ..Default::default()
+        };
+        if query.chars().count() < MIN_QUERY_CHARS {
+            return Ok(results);
+        }
+
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+        let lower = query.to_lowercase();
+        let terms = Terms {
+            like: escape_like(&lower),
+            lower,
+        };
+        let tenant_id = self.user_tenant(user_id).await?;
+
+        let (entities, projects, users, classes) = tokio::try_join!(
+            self.search_entities(user_id, tenant_id, &terms, limit),
+            self.search_projects(user_id, tenant_id, &terms, limit),
+            self.search_users(user_id, tenant_id, &terms, limit),
+            self.search_classes(user_id, tenant_id, &terms, limit),
+        )?;
+        results.entities = entities;
+        results.projects = projects;
+        results.users = users;
+        results.classes = classes;
+        Ok(results)
+    }
+
+    /// Entities of user-defined classes; users and projects have their own
+    /// groups. Answered from the search index when it delegates, falling
+    /// back to the database if the index can't be reached.
+    async fn search_entities(
+        &self,
+        user_id: Uuid,
+        tenant_id: Option<Uuid>,
+        terms: &Terms,
+        limit: i64,
+    ) -> Result<Vec<QuickSearchHit>, NavigationError> {
+        if let Some(index) = self.search_index.as_ref().filter(|i| i.delegates_search()) {
+            match index
+                .search_entities(&terms.lower, tenant_id, limit * OVERFETCH)
+                .await
+            {
+                Ok(hits) => {
+                    let candidates = self.indexed_entities(hits).await?;
+                    return self
+                        .readable(user_id, tenant_id, candidates, "read", limit)
+                        .await;
+                }
+                Err(e) => tracing::warn!("Search index unavailable for quick search: {}", e),
+            }
+        }
+
+        let candidates = sqlx::query_as::<_, QuickSearchHit>(&format!(
+            r#"
+            SELECT e.id, e.display_name AS label, c.name AS detail, {rank} AS score
+            FROM entities e
+            JOIN classes c ON c.id = e.class_id
+            JOIN ontology_versions ov ON ov.id = c.version_id
+            WHERE e.deleted_at IS NULL AND NOT ov.is_system
+              AND (e.tenant_id IS NULL OR e.tenant_id = $3)
+              AND lower(e.display_name) LIKE '%' || $2 || '%' ESCAPE '\'
+            ORDER BY score DESC, length(e.display_name), e.display_name
+            LIMIT $4
+            "#,
+            rank = rank("e.display_name")
+        ))
+        .bind(&terms.lower)
+        .bind(&terms.like)
+        .bind(tenant_id)
+        .bind(limit * OVERFETCH)
+        .fetch_all(&self.pool)
+        .await?;
+
+        self.readable(user_id, tenant_id, candidates, "read", limit)
+            .await
+    }
+
+    /// Index hits as quick search results, in hit order, with labels from the
+    /// database. Hits for entities deleted since they were indexed are dropped.
+    async fn indexed_entities(
+        &self,
+        hits: Vec<IndexHit>,
+    ) -> Result<Vec<QuickSearchHit>, NavigationError> {
+        if hits.is_empty() {
+            return Ok(Vec::new());
+        }
+        let ids: Vec<Uuid> = hits.iter().map(|hit| hit.id).collect();
+        let rows: HashMap<Uuid, QuickSearchHit> = sqlx::query_as::<_, QuickSearchHit>(
+            r#"
+            SELECT e.id, e.display_name AS label, c.name AS detail, 0.0::float8 AS score
+            FROM entities e
+            JOIN classes c ON c.id = e.class_id
+            WHERE e.id = ANY($1) AND e.deleted_at IS NULL
+            "#,
+        )
+        .bind(&ids)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|hit| (hit.id, hit))
+        .collect();
+
+        Ok(hits
+            .into_iter()
+            .filter_map(|hit| {
+                rows.get(&hit.id).cloned().map(|mut row| {
+                    row.score = hit.score;
+                    row
+                })
+            })
+            .collect())
+    }
+
+    async fn search_projects(
+        &self,
+        user_id: Uuid,
+        tenant_id: Option<Uuid>,
+        terms: &Terms,
+        limit: i64,
+    ) -> Result<Vec<QuickSearchHit>, NavigationError> {
+        let candidates = sqlx::query_as::<_, QuickSearchHit>(&format!(
+            r#"
+            SELECT p.id, p.name AS label, p.status AS detail, {rank} AS score
+            FROM unified_projects p
+            WHERE (p.tenant_id IS NULL OR p.tenant_id = $3)
+              AND lower(p.name) LIKE '%' || $2 || '%' ESCAPE '\'
+              AND NOT is_test_data(p.id)
+            ORDER BY score DESC, length(p.name), p.name
+            LIMIT $4
+            "#,
+            rank = rank("p.name")
+        ))
+        .bind(&terms.lower)
+        .bind(&terms.like)
+        .bind(tenant_id)
+        .bind(limit * OVERFETCH)
+        .fetch_all(&self.pool)
+        .await?;
+
+        self.readable(user_id, tenant_id, candidates, "project.read", limit)
+            .await
+    }
+
+    async fn search_users(
+        &self,
+        user_id: Uuid,
+        tenant_id: Option<Uuid>,
+        terms: &Terms,
+        limit: i64,
+    ) -> Result<Vec<QuickSearchHit>, NavigationError> {
+        if !self.can(user_id, "ui.view.users").await? {
+            return Ok(Vec::new());
+        }
+        let users = sqlx::query_as::<_, QuickSearchHit>(&format!(
+            r#"
+            SELECT u.id, u.username AS label, u.email AS detail,
+                   GREATEST({username}, {email}) AS score
+            FROM unified_users u
+            WHERE (u.tenant_id IS NULL OR u.tenant_id = $3)
+              AND (lower(u.username) LIKE '%' || $2 || '%' ESCAPE '\'
+                   OR lower(u.email) LIKE '%' || $2 || '%' ESCAPE '\')
+            ORDER BY score DESC, length(u.username), u.username
+            LIMIT $4
+            "#,
+            username = rank("u.username"),
+            email = rank("u.email")
+        ))
+        .bind(&terms.lower)
+        .bind(&terms.like)
+        .bind(tenant_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(users)
+    }
+
+    /// Classes of the system ontology and the current published version
+    async fn search_classes(
+        &self,
+        user_id: Uuid,
+        tenant_id: Option<Uuid>,
+        terms: &Terms,
+        limit: i64,
+    ) -> Result<Vec<QuickSearchHit>, NavigationError> {
+        if !self.can(user_id, "ui.view.ontology").await? {
+            return Ok(Vec::new());
+        }
+        let classes = sqlx::query_as::<_, QuickSearchHit>(&format!(
+            r#"
+            SELECT c.id, c.name AS label, c.description AS detail, {rank} AS score
+            FROM classes c
+            JOIN ontology_versions ov ON ov.id = c.version_id
+            WHERE (ov.is_current OR ov.is_system)
+              AND (c.tenant_id IS NULL OR c.tenant_id = $3)
+              AND lower(c.name) LIKE '%' || $2 || '%' ESCAPE '\'
+            ORDER BY score DESC, length(c.name), c.name
+            LIMIT $4
+            "#,
+            rank = rank("c.name")
+        ))
+        .bind(&terms.lower)
+        .bind(&terms.like)
+        .bind(tenant_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(classes)
+    }
+
+    /// The candidates the user holds `permission` on, in order, up to `limit`
+    async fn readable(
+        &self,
+        user_id: Uuid,
+        tenant_id: Option<Uuid>,
+        candidates: Vec<QuickSearchHit>,
+        permission: &str,
+        limit: i64,
+    ) -> Result<Vec<QuickSearchHit>, NavigationError> {
+        if candidates.is_empty() {
+            return Ok(candidates);
+        }
+        let ids = candidates.iter().map(|hit| hit.id).collect();
+        let allowed: std::collections::HashSet<Uuid> = self
+            .rebac_service
+            .check_multiple_permissions(user_id, ids, permission, tenant_id)
+            .await
+            .map_err(|e| NavigationError::PermissionCheck(e.to_string()))?
+            .into_iter()
+            .filter(|(_, allowed, denied)| *allowed && !denied)
+            .map(|(id, ..)| id)
+            .collect();
+
+        Ok(candidates
+            .into_iter()
+            .filter(|hit| allowed.contains(&hit.id))
+            .take(limit as usize)
+            .collect())
+    }
+
+    pub(super) async fn can(
+        &self,
+        user_id: Uuid,
+        permission: &str,
+    ) -> Result<bool, NavigationError> {
+        self.abac_service
+            .check_permission(user_id, permission, None, None, None)
+            .await
+            .map_err(|e| NavigationError::PermissionCheck(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_like() {
+        assert_eq!(escape_like("50%_off\\"), "50\\%\\_off\\\\");
+        assert_eq!(escape_like("plain"), "plain");
+    }
+}
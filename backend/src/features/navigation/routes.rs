@@ -1,19 +1,29 @@
-use axum::{extract::State, http::StatusCode, routing::post, Extension, Json, Router};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::{delete, get, post, put},
+    Extension, Json, Router,
+};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
 
 use super::models::{
-    default_navigation, evaluate_navigation, flatten_visible_items, NavItemSummary,
-    NavSectionVisibility,
+    default_navigation, evaluate_navigation, flatten_visible_items, NavBadges, NavItemSummary,
+    NavPreference, NavSectionDefinition, NavSectionVisibility, NavigationMenu, QuickSearchQuery,
+    QuickSearchResults, SaveNavigationMenu, UpdateNavPreference,
 };
 use super::service::{NavigationError, NavigationService};
 use crate::features::auth::jwt::Claims;
+use crate::middleware::abac::{enforce_route_permissions, RoutePermission};
+use crate::utils::validation::ValidatedJson;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct EvaluateRequest {
     pub permissions: Option<Vec<String>>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct SimulateRequest {
     pub baseline_permissions: Vec<String>,
     pub proposed_permissions: Vec<String>,
@@ -34,23 +44,46 @@ pub struct SimulationResponse {
     pub summary: SimulationSummary,
 }
 
+/// Permissions enforced by `enforce_route_permissions` for the routes below
+pub const ROUTE_PERMISSIONS: &[RoutePermission] = &[
+    RoutePermission::new("GET", "/menus", "manage_system"),
+    RoutePermission::new("PUT", "/menus", "manage_system"),
+    RoutePermission::new("GET", "/menus/default", "manage_system"),
+    RoutePermission::new("DELETE", "/menus/:id", "manage_system"),
+];
+
 pub fn navigation_routes() -> Router<NavigationService> {
     Router::new()
         .route("/evaluate", post(evaluate_navigation_handler))
         .route("/simulate", post(simulate_navigation_handler))
+        .route("/quick-search", get(quick_search_handler))
+        .route("/badges", get(badges_handler))
+        .route("/menus", get(list_menus_handler).put(save_menu_handler))
+        .route("/menus/default", get(default_menu_handler))
+        .route("/menus/:id", delete(delete_menu_handler))
+        .route("/preferences", get(list_preferences_handler))
+        .route(
+            "/preferences/:item_id",
+            put(set_preference_handler).delete(clear_preference_handler),
+        )
+        .route_layer(axum::middleware::from_fn_with_state(
+            ROUTE_PERMISSIONS,
+            enforce_route_permissions,
+        ))
 }
 
 async fn evaluate_navigation_handler(
     State(service): State<NavigationService>,
     Extension(claims): Extension<Claims>,
-    Json(payload): Json<EvaluateRequest>,
+    ValidatedJson(payload): ValidatedJson<EvaluateRequest>,
 ) -> Result<Json<Vec<NavSectionVisibility>>, StatusCode> {
     if let Some(permissions) = payload.permissions {
         return Ok(Json(service.evaluate_with_permissions(&permissions)));
     }
 
+    let roles: Vec<String> = claims.roles.iter().map(|r| r.role_name.clone()).collect();
     service
-        .evaluate_for_user(&claims.sub)
+        .evaluate_for_user(&claims.sub, &roles)
         .await
         .map(Json)
         .map_err(map_nav_error)
@@ -58,7 +91,7 @@ async fn evaluate_navigation_handler(
 
 async fn simulate_navigation_handler(
     Extension(claims): Extension<Claims>,
-    Json(payload): Json<SimulateRequest>,
+    ValidatedJson(payload): ValidatedJson<SimulateRequest>,
 ) -> Result<Json<SimulationResponse>, StatusCode> {
     if !has_permission(&claims.permissions, "ui.view.roles") && !cfg!(debug_assertions) {
         return Err(StatusCode::FORBIDDEN);
@@ -112,6 +145,105 @@ async fn simulate_navigation_handler(
     }))
 }
 
+async fn quick_search_handler(
+    State(service): State<NavigationService>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<QuickSearchQuery>,
+) -> Result<Json<QuickSearchResults>, StatusCode> {
+    service
+        .quick_search(user_id(&claims)?, &query.q, query.limit)
+        .await
+        .map(Json)
+        .map_err(map_nav_error)
+}
+
+async fn badges_handler(
+    State(service): State<NavigationService>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<NavBadges>, StatusCode> {
+    service
+        .badge_counts(user_id(&claims)?)
+        .await
+        .map(Json)
+        .map_err(map_nav_error)
+}
+
+// ============================================================================
+// MENUS (admin) AND PREFERENCES (per user)
+// ============================================================================
+
+async fn list_menus_handler(
+    State(service): State<NavigationService>,
+) -> Result<Json<Vec<NavigationMenu>>, StatusCode> {
+    service.list_menus().await.map(Json).map_err(map_nav_error)
+}
+
+/// The built-in navigation, as a starting point for a custom menu
+async fn default_menu_handler() -> Result<Json<Vec<NavSectionDefinition>>, StatusCode> {
+    Ok(Json(default_navigation()))
+}
+
+async fn save_menu_handler(
+    State(service): State<NavigationService>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(payload): ValidatedJson<SaveNavigationMenu>,
+) -> Result<Json<NavigationMenu>, StatusCode> {
+    let user_id = user_id(&claims)?;
+    service
+        .save_menu(payload, Some(user_id))
+        .await
+        .map(Json)
+        .map_err(map_nav_error)
+}
+
+async fn delete_menu_handler(
+    State(service): State<NavigationService>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, StatusCode> {
+    service.delete_menu(id).await.map_err(map_nav_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_preferences_handler(
+    State(service): State<NavigationService>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Vec<NavPreference>>, StatusCode> {
+    service
+        .list_preferences(user_id(&claims)?)
+        .await
+        .map(Json)
+        .map_err(map_nav_error)
+}
+
+async fn set_preference_handler(
+    State(service): State<NavigationService>,
+    Extension(claims): Extension<Claims>,
+    Path(item_id): Path<String>,
+    ValidatedJson(payload): ValidatedJson<UpdateNavPreference>,
+) -> Result<Json<NavPreference>, StatusCode> {
+    service
+        .set_preference(user_id(&claims)?, &item_id, payload)
+        .await
+        .map(Json)
+        .map_err(map_nav_error)
+}
+
+async fn clear_preference_handler(
+    State(service): State<NavigationService>,
+    Extension(claims): Extension<Claims>,
+    Path(item_id): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    service
+        .clear_preference(user_id(&claims)?, &item_id)
+        .await
+        .map_err(map_nav_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn user_id(claims: &Claims) -> Result<Uuid, StatusCode> {
+    Uuid::parse_str(&claims.sub).map_err(|_| StatusCode::BAD_REQUEST)
+}
+
 fn has_permission(permissions: &[String], required: &str) -> bool {
     permissions
         .iter()
@@ -121,6 +253,11 @@ fn has_permission(permissions: &[String], required: &str) -> bool {
 fn map_nav_error(error: NavigationError) -> StatusCode {
     match error {
         NavigationError::InvalidUserId(_) => StatusCode::BAD_REQUEST,
-        NavigationError::PermissionCheck(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        NavigationError::InvalidMenu(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        NavigationError::NotFound(_) => StatusCode::NOT_FOUND,
+        NavigationError::PermissionCheck(e) | NavigationError::Database(e) => {
+            tracing::error!("Navigation error: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
     }
 }
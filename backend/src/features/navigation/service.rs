@@ -1,23 +1,62 @@
+use sqlx::PgPool;
 use std::collections::HashSet;
 use uuid::Uuid;
 
-use super::models::{default_navigation, evaluate_navigation, NavSectionVisibility};
+use super::models::{
+    apply_preferences, default_navigation, evaluate_navigation, required_permissions,
+    validate_sections, NavPreference, NavSectionDefinition, NavSectionVisibility, NavigationMenu,
+    SaveNavigationMenu, UpdateNavPreference,
+};
 use crate::features::abac::AbacService;
+use crate::features::rebac::RebacService;
+use crate::features::search_index::SearchIndex;
+use crate::utils::cache::SharedCache;
 
 #[derive(Clone)]
 pub struct NavigationService {
-    abac_service: AbacService,
+    pub(super) pool: PgPool,
+    pub(super) abac_service: AbacService,
+    pub(super) rebac_service: RebacService,
+    pub(super) search_index: Option<SearchIndex>,
+    pub(super) cache: Option<SharedCache>,
 }
 
 #[derive(Debug)]
 pub enum NavigationError {
     InvalidUserId(String),
     PermissionCheck(String),
+    InvalidMenu(String),
+    NotFound(String),
+    Database(String),
+}
+
+impl From<sqlx::Error> for NavigationError {
+    fn from(err: sqlx::Error) -> Self {
+        NavigationError::Database(err.to_string())
+    }
 }
 
 impl NavigationService {
-    pub fn new(abac_service: AbacService) -> Self {
-        Self { abac_service }
+    pub fn new(pool: PgPool, abac_service: AbacService, rebac_service: RebacService) -> Self {
+        Self {
+            pool,
+            abac_service,
+            rebac_service,
+            search_index: None,
+            cache: None,
+        }
+    }
+
+    /// Reuse admin badge counts across users of a tenant
+    pub fn with_cache(mut self, cache: SharedCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Answer entity quick search from the search index when it delegates
+    pub fn with_search_index(mut self, search_index: SearchIndex) -> Self {
+        self.search_index = Some(search_index);
+        self
     }
 
     pub fn evaluate_with_permissions(&self, permissions: &[String]) -> Vec<NavSectionVisibility> {
@@ -26,35 +65,184 @@ impl NavigationService {
         evaluate_navigation(&definitions, &permissions)
     }
 
+    /// The user's menu (see `menu_for`) filtered by their ABAC permissions,
+    /// with their pinned and hidden items applied
     pub async fn evaluate_for_user(
         &self,
         user_id: &str,
+        roles: &[String],
     ) -> Result<Vec<NavSectionVisibility>, NavigationError> {
         let user_uuid =
             Uuid::parse_str(user_id).map_err(|e| NavigationError::InvalidUserId(e.to_string()))?;
 
-        let definitions = default_navigation();
+        let definitions = self.menu_for(user_uuid, roles).await?;
         let mut permission_set: HashSet<String> = HashSet::new();
 
-        for section in &definitions {
-            for item in &section.items {
-                for permission in &item.required_permissions {
-                    if permission_set.contains(permission) {
-                        continue;
-                    }
-                    let allowed = self
-                        .abac_service
-                        .check_permission(user_uuid, permission, None, None, None)
-                        .await
-                        .map_err(|e| NavigationError::PermissionCheck(e.to_string()))?;
-                    if allowed {
-                        permission_set.insert(permission.clone());
-                    }
-                }
+        for permission in required_permissions(&definitions) {
+            let allowed = self
+                .abac_service
+                .check_permission(user_uuid, &permission, None, None, None)
+                .await
+                .map_err(|e| NavigationError::PermissionCheck(e.to_string()))?;
+            if allowed {
+                permission_set.insert(permission);
             }
         }
 
-        Ok(evaluate_navigation(&definitions, &permission_set))
+        let mut sections = evaluate_navigation(&definitions, &permission_set);
+        let preferences = self.list_preferences(user_uuid).await?;
+        apply_preferences(&mut sections, &preferences);
+        Ok(sections)
+    }
+
+    /// The most specific stored menu for the user's tenant and roles:
+    /// tenant and role, then tenant, then role, then the deployment default.
+    /// Falls back to the built-in navigation when none is stored.
+    pub async fn menu_for(
+        &self,
+        user_id: Uuid,
+        roles: &[String],
+    ) -> Result<Vec<NavSectionDefinition>, NavigationError> {
+        let tenant_id = self.user_tenant(user_id).await?;
+        let menu = sqlx::query_as::<_, NavigationMenu>(
+            r#"
+            SELECT * FROM navigation_menus
+            WHERE (tenant_id IS NULL OR tenant_id = $1)
+              AND (role_name IS NULL OR role_name = ANY($2))
+            ORDER BY (tenant_id IS NOT NULL) DESC, (role_name IS NOT NULL) DESC,
+                     priority DESC, role_name
+            LIMIT 1
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(roles)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(menu
+            .map(|m| m.sections.0)
+            .unwrap_or_else(default_navigation))
+    }
+
+    pub(super) async fn user_tenant(&self, user_id: Uuid) -> Result<Option<Uuid>, NavigationError> {
+        let tenant_id =
+            sqlx::query_scalar::<_, Option<Uuid>>("SELECT tenant_id FROM entities WHERE id = $1")
+                .bind(user_id)
+                .fetch_optional(&self.pool)
+                .await?
+                .flatten();
+        Ok(tenant_id)
+    }
+
+    pub async fn list_menus(&self) -> Result<Vec<NavigationMenu>, NavigationError> {
+        let menus = sqlx::query_as::<_, NavigationMenu>(
+            "SELECT * FROM navigation_menus ORDER BY tenant_id NULLS FIRST, role_name NULLS FIRST",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(menus)
+    }
+
+    /// Create or replace the menu for the request's tenant/role scope
+    pub async fn save_menu(
+        &self,
+        input: SaveNavigationMenu,
+        updated_by: Option<Uuid>,
+    ) -> Result<NavigationMenu, NavigationError> {
+        validate_sections(&input.sections).map_err(NavigationError::InvalidMenu)?;
+        let role_name = input
+            .role_name
+            .map(|r| r.trim().to_string())
+            .filter(|r| !r.is_empty());
+
+        let menu = sqlx::query_as::<_, NavigationMenu>(
+            r#"
+            INSERT INTO navigation_menus (tenant_id, role_name, priority, sections, updated_by)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT ((COALESCE(tenant_id, '00000000-0000-0000-0000-000000000000'::uuid)),
+                         (COALESCE(role_name, '')))
+            DO UPDATE SET priority = EXCLUDED.priority, sections = EXCLUDED.sections,
+                          updated_by = EXCLUDED.updated_by, updated_at = NOW()
+            RETURNING *
+            "#,
+        )
+        .bind(input.tenant_id)
+        .bind(role_name)
+        .bind(input.priority)
+        .bind(sqlx::types::Json(&input.sections))
+        .bind(updated_by)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(menu)
+    }
+
+    pub async fn delete_menu(&self, id: Uuid) -> Result<(), NavigationError> {
+        let result = sqlx::query("DELETE FROM navigation_menus WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(NavigationError::NotFound(format!("Menu {} not found", id)));
+        }
+        Ok(())
+    }
+
+    pub async fn list_preferences(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Vec<NavPreference>, NavigationError> {
+        let preferences = sqlx::query_as::<_, NavPreference>(
+            "SELECT item_id, pinned, hidden, updated_at FROM navigation_preferences WHERE user_id = $1 ORDER BY item_id",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(preferences)
+    }
+
+    /// Pin or hide an item; fields left out keep their current value
+    pub async fn set_preference(
+        &self,
+        user_id: Uuid,
+        item_id: &str,
+        input: UpdateNavPreference,
+    ) -> Result<NavPreference, NavigationError> {
+        if item_id.trim().is_empty() {
+            return Err(NavigationError::InvalidMenu(
+                "Navigation item id is required".to_string(),
+            ));
+        }
+        let preference = sqlx::query_as::<_, NavPreference>(
+            r#"
+            INSERT INTO navigation_preferences (user_id, item_id, pinned, hidden)
+            VALUES ($1, $2, COALESCE($3, FALSE), COALESCE($4, FALSE))
+            ON CONFLICT (user_id, item_id) DO UPDATE SET
+                pinned = COALESCE($3, navigation_preferences.pinned),
+                hidden = COALESCE($4, navigation_preferences.hidden),
+                updated_at = NOW()
+            RETURNING item_id, pinned, hidden, updated_at
+            "#,
+        )
+        .bind(user_id)
+        .bind(item_id)
+        .bind(input.pinned)
+        .bind(input.hidden)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(preference)
+    }
+
+    pub async fn clear_preference(
+        &self,
+        user_id: Uuid,
+        item_id: &str,
+    ) -> Result<(), NavigationError> {
+        sqlx::query("DELETE FROM navigation_preferences WHERE user_id = $1 AND item_id = $2")
+            .bind(user_id)
+            .bind(item_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
     }
 }
 
@@ -0,0 +1,150 @@
+use super::models::{DigestFrequency, NotificationPreferences};
+use super::service::{NotificationError, NotificationService};
+use crate::config::NotificationsConfig;
+use crate::features::email::templates::NOTIFICATION_DIGEST_EMAIL;
+use crate::utils::shutdown::Shutdown;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// A user with notifications waiting for their digest
+#[derive(sqlx::FromRow)]
+struct PendingDigest {
+    user_id: Uuid,
+    email: Option<String>,
+    notification_preferences: Option<serde_json::Value>,
+    first_pending_at: DateTime<Utc>,
+    last_sent_at: Option<DateTime<Utc>>,
+}
+
+impl PendingDigest {
+    /// A digest goes out at most once per period. Users who switched the
+    /// digest off since get what was already waiting right away.
+    fn is_due(&self, digest: DigestFrequency, now: DateTime<Utc>) -> bool {
+        match digest.period() {
+            None => true,
+            Some(period) => self.last_sent_at.unwrap_or(self.first_pending_at) + period <= now,
+        }
+    }
+}
+
+impl NotificationService {
+    // ========================================================================
+    // DIGESTS
+    // ========================================================================
+
+    /// Email each user whose digest is due the notifications waiting for it.
+    /// Returns how many digests were queued.
+    pub async fn send_due_digests(&self) -> Result<usize, NotificationError> {
+        let Some(email_service) = &self.email else {
+            return Ok(0);
+        };
+
+        let pending = sqlx::query_as::<_, PendingDigest>(
+            r#"
+            SELECT u.id AS user_id, u.email, u.notification_preferences,
+                   MIN(n.created_at) AS first_pending_at, d.last_sent_at
+            FROM entities n
+            JOIN classes c ON c.id = n.class_id AND c.name = 'Notification'
+            JOIN unified_users u ON u.id = (n.attributes->>'user_id')::uuid
+            LEFT JOIN notification_digests d ON d.user_id = u.id
+            WHERE n.attributes->>'digest_pending' = 'true' AND n.deleted_at IS NULL
+            GROUP BY u.id, u.email, u.notification_preferences, d.last_sent_at
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let now = Utc::now();
+        let mut sent = 0;
+        for user in pending {
+            let prefs = NotificationPreferences::from_value(user.notification_preferences.clone());
+            if !user.is_due(prefs.digest, now) {
+                continue;
+            }
+
+            let mut tx = self.pool.begin().await?;
+            let mut items = sqlx::query_as::<_, (String, DateTime<Utc>)>(
+                r#"
+                UPDATE entities
+                SET attributes = (attributes - 'digest_pending')
+                        || jsonb_build_object('digested_at', NOW()),
+                    updated_at = NOW()
+                WHERE id IN (
+                    SELECT n.id FROM entities n
+                    WHERE n.attributes->>'user_id' = $1::text
+                      AND n.attributes->>'digest_pending' = 'true'
+                      AND n.deleted_at IS NULL
+                    FOR UPDATE SKIP LOCKED
+                )
+                RETURNING attributes->>'message', created_at
+                "#,
+            )
+            .bind(user.user_id)
+            .fetch_all(&mut *tx)
+            .await?;
+            if items.is_empty() {
+                continue;
+            }
+            items.sort_by_key(|(_, created_at)| *created_at);
+
+            if let Some(address) = &user.email {
+                let lines: Vec<String> = items
+                    .iter()
+                    .map(|(message, created_at)| {
+                        format!("- {}: {}", created_at.format("%Y-%m-%d %H:%M UTC"), message)
+                    })
+                    .collect();
+                let period = match prefs.digest {
+                    DigestFrequency::Weekly => "weekly",
+                    _ => "daily",
+                };
+                if let Err(e) = email_service
+                    .queue(
+                        &NOTIFICATION_DIGEST_EMAIL,
+                        address,
+                        &[
+                            ("period", period),
+                            ("count", &items.len().to_string()),
+                            ("notifications", &lines.join("\n")),
+                        ],
+                    )
+                    .await
+                {
+                    // Leave the notifications pending for the next run
+                    tracing::error!("Failed to queue digest for {}: {}", user.user_id, e);
+                    continue;
+                }
+                sent += 1;
+            }
+
+            sqlx::query(
+                r#"
+                INSERT INTO notification_digests (user_id, last_sent_at) VALUES ($1, NOW())
+                ON CONFLICT (user_id) DO UPDATE SET last_sent_at = EXCLUDED.last_sent_at
+                "#,
+            )
+            .bind(user.user_id)
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+        }
+        Ok(sent)
+    }
+
+    pub async fn start_digest_task(self, config: NotificationsConfig, shutdown: Shutdown) {
+        shutdown.clone().spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
+                config.digest_check_interval_secs.max(1),
+            ));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = shutdown.cancelled() => break,
+                }
+                if let Err(e) = self.send_due_digests().await {
+                    tracing::error!("Failed to send notification digests: {}", e);
+                }
+            }
+        });
+    }
+}
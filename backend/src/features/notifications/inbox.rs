@@ -0,0 +1,153 @@
+use super::models::{ListNotificationsQuery, Notification, NotificationPage};
+use super::service::{NotificationError, NotificationService};
+use uuid::Uuid;
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
+
+/// A user's live notifications; `$1` is the user id
+pub(super) const INBOX: &str = r#"
+    FROM entities e
+    JOIN classes c ON c.id = e.class_id
+    WHERE c.name = 'Notification'
+      AND e.deleted_at IS NULL
+      AND e.attributes->>'user_id' = $1::text
+"#;
+
+pub(super) const COLUMNS: &str = r#"
+    SELECT e.id,
+           COALESCE(e.attributes->>'message', '') AS message,
+           e.attributes->>'category' AS category,
+           COALESCE((e.attributes->>'read')::boolean, false) AS read,
+           e.created_at
+"#;
+
+impl NotificationService {
+    // ========================================================================
+    // INBOX
+    // ========================================================================
+
+    pub async fn list_notifications(
+        &self,
+        user_id: Uuid,
+        query: ListNotificationsQuery,
+    ) -> Result<NotificationPage, NotificationError> {
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE);
+        let offset = query.offset.unwrap_or(0).max(0);
+        let unread_filter = if query.unread_only {
+            "AND NOT COALESCE((e.attributes->>'read')::boolean, false)"
+        } else {
+            ""
+        };
+
+        let notifications = sqlx::query_as::<_, Notification>(&format!(
+            "{} {} {} ORDER BY e.created_at DESC, e.id LIMIT $2 OFFSET $3",
+            COLUMNS, INBOX, unread_filter
+        ))
+        .bind(user_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let (total, unread) = sqlx::query_as::<_, (i64, i64)>(&format!(
+            r#"
+            SELECT COUNT(*),
+                   COUNT(*) FILTER (WHERE NOT COALESCE((e.attributes->>'read')::boolean, false))
+            {}
+            "#,
+            INBOX
+        ))
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(NotificationPage {
+            notifications,
+            total: if query.unread_only { unread } else { total },
+            unread,
+            limit,
+            offset,
+        })
+    }
+
+    pub async fn unread_count(&self, user_id: Uuid) -> Result<i64, NotificationError> {
+        Ok(sqlx::query_scalar::<_, i64>(&format!(
+            "SELECT COUNT(*) {} AND NOT COALESCE((e.attributes->>'read')::boolean, false)",
+            INBOX
+        ))
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    /// Mark one of the user's notifications read. Other users' notifications
+    /// are reported as not found.
+    pub async fn mark_read(
+        &self,
+        user_id: Uuid,
+        notification_id: Uuid,
+    ) -> Result<Notification, NotificationError> {
+        sqlx::query_as::<_, Notification>(&format!(
+            r#"
+            WITH updated AS (
+                UPDATE entities e
+                SET attributes = e.attributes || '{{"read": true}}', updated_at = NOW()
+                WHERE e.id = $2 AND e.id IN (SELECT e.id {})
+                RETURNING e.*
+            )
+            {} FROM updated e
+            "#,
+            INBOX, COLUMNS
+        ))
+        .bind(user_id)
+        .bind(notification_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| NotificationError::NotFound("Notification".to_string()))
+    }
+
+    /// Returns how many were unread
+    pub async fn mark_all_read(&self, user_id: Uuid) -> Result<u64, NotificationError> {
+        Ok(sqlx::query(&format!(
+            r#"
+            UPDATE entities
+            SET attributes = attributes || '{{"read": true}}', updated_at = NOW()
+            WHERE id IN (
+                SELECT e.id {} AND NOT COALESCE((e.attributes->>'read')::boolean, false)
+            )
+            "#,
+            INBOX
+        ))
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?
+        .rows_affected())
+    }
+
+    pub async fn delete_notification(
+        &self,
+        user_id: Uuid,
+        notification_id: Uuid,
+    ) -> Result<(), NotificationError> {
+        let deleted = sqlx::query(&format!(
+            r#"
+            UPDATE entities SET deleted_at = NOW()
+            WHERE id = $2 AND id IN (SELECT e.id {})
+            "#,
+            INBOX
+        ))
+        .bind(user_id)
+        .bind(notification_id)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        if deleted == 0 {
+            return Err(NotificationError::NotFound("Notification".to_string()));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,12 @@
+pub mod digest;
+pub mod inbox;
+pub mod models;
+pub mod push;
+pub mod routes;
+pub mod service;
+pub mod stream;
+pub mod templates;
+pub mod webpush;
+
+pub use push::WebPush;
+pub use service::{NotificationError, NotificationService};
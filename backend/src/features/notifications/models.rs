@@ -0,0 +1,346 @@
+use crate::features::email::models::TemplateVariable;
+use crate::utils::validation::not_blank;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+use validator::Validate;
+
+/// What a notification is about. Users choose a delivery channel per category.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, sqlx::Type,
+)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationCategory {
+    /// Sign-ins from new devices, password changes and resets
+    Security,
+    /// Firefighter (break-glass) requests, approvals and expiry
+    Access,
+    /// Profile changes
+    Account,
+    /// API keys that are about to expire
+    ApiKeys,
+}
+
+impl NotificationCategory {
+    pub const ALL: [NotificationCategory; 4] = [
+        NotificationCategory::Security,
+        NotificationCategory::Access,
+        NotificationCategory::Account,
+        NotificationCategory::ApiKeys,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationCategory::Security => "security",
+            NotificationCategory::Access => "access",
+            NotificationCategory::Account => "account",
+            NotificationCategory::ApiKeys => "api_keys",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            NotificationCategory::Security => "New sign-ins and password changes",
+            NotificationCategory::Access => "Emergency access requests, approvals and expiry",
+            NotificationCategory::Account => "Changes to your profile",
+            NotificationCategory::ApiKeys => "API keys that are about to expire",
+        }
+    }
+
+    /// Low-priority categories can wait for the digest; the rest are emailed
+    /// as they happen
+    pub fn priority(&self) -> NotificationPriority {
+        match self {
+            NotificationCategory::Security | NotificationCategory::Access => {
+                NotificationPriority::High
+            }
+            NotificationCategory::Account | NotificationCategory::ApiKeys => {
+                NotificationPriority::Low
+            }
+        }
+    }
+
+    /// Security notices always reach the user somewhere
+    pub fn can_disable(&self) -> bool {
+        !matches!(self, NotificationCategory::Security)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationPriority {
+    High,
+    Low,
+}
+
+/// Where notifications of a category go
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryChannel {
+    /// Listed in the app only
+    #[default]
+    InApp,
+    /// Listed in the app and emailed
+    Email,
+    /// Not delivered at all
+    None,
+}
+
+/// How often low-priority email notifications are batched into one message
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DigestFrequency {
+    /// Email each notification as it happens
+    #[default]
+    Off,
+    Daily,
+    Weekly,
+}
+
+impl DigestFrequency {
+    pub fn period(&self) -> Option<chrono::Duration> {
+        match self {
+            DigestFrequency::Off => None,
+            DigestFrequency::Daily => Some(chrono::Duration::days(1)),
+            DigestFrequency::Weekly => Some(chrono::Duration::weeks(1)),
+        }
+    }
+}
+
+/// Which notifications are pushed to the user's subscribed browsers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PushScope {
+    /// Security and access notifications
+    #[default]
+    HighPriority,
+    All,
+    Off,
+}
+
+impl PushScope {
+    pub fn includes(&self, category: NotificationCategory) -> bool {
+        match self {
+            PushScope::HighPriority => category.priority() == NotificationPriority::High,
+            PushScope::All => true,
+            PushScope::Off => false,
+        }
+    }
+}
+
+/// Stored on the user entity as the `notification_preferences` attribute.
+/// Categories without an entry use the in-app channel.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationPreferences {
+    pub channels: BTreeMap<NotificationCategory, DeliveryChannel>,
+    pub digest: DigestFrequency,
+    /// e.g. "nb-NO"; notifications are in English without one
+    pub locale: Option<String>,
+    pub push: PushScope,
+}
+
+impl NotificationPreferences {
+    /// Unreadable or legacy values fall back to the defaults rather than
+    /// dropping notifications
+    pub fn from_value(value: Option<serde_json::Value>) -> Self {
+        value
+            .and_then(|v| serde_json::from_value(v).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn channel(&self, category: NotificationCategory) -> DeliveryChannel {
+        self.channels.get(&category).copied().unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdatePreferencesInput {
+    /// Only the listed categories change
+    #[serde(default)]
+    pub channels: BTreeMap<NotificationCategory, DeliveryChannel>,
+    pub digest: Option<DigestFrequency>,
+    /// An empty string clears the locale
+    pub locale: Option<String>,
+    pub push: Option<PushScope>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CategoryPreference {
+    pub category: NotificationCategory,
+    pub description: &'static str,
+    pub priority: NotificationPriority,
+    pub can_disable: bool,
+    pub channel: DeliveryChannel,
+}
+
+/// A user's preferences with every category spelled out, for the settings page
+#[derive(Debug, Serialize)]
+pub struct PreferencesView {
+    pub categories: Vec<CategoryPreference>,
+    pub digest: DigestFrequency,
+    pub locale: Option<String>,
+    pub push: PushScope,
+}
+
+impl From<&NotificationPreferences> for PreferencesView {
+    fn from(prefs: &NotificationPreferences) -> Self {
+        Self {
+            categories: NotificationCategory::ALL
+                .iter()
+                .map(|category| CategoryPreference {
+                    category: *category,
+                    description: category.description(),
+                    priority: category.priority(),
+                    can_disable: category.can_disable(),
+                    channel: prefs.channel(*category),
+                })
+                .collect(),
+            digest: prefs.digest,
+            locale: prefs.locale.clone(),
+            push: prefs.push,
+        }
+    }
+}
+
+/// A notification as it was created for its recipient
+#[derive(Debug, Clone)]
+pub struct SentNotification {
+    pub id: Uuid,
+    pub message: String,
+    pub category: NotificationCategory,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A notification in a user's inbox
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Notification {
+    pub id: Uuid,
+    pub message: String,
+    /// None for notifications created before categories existed
+    pub category: Option<NotificationCategory>,
+    pub read: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A notification just created, as broadcast to open streams
+#[derive(Debug, Clone)]
+pub struct NotificationEvent {
+    pub user_id: Uuid,
+    pub notification: Notification,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ListNotificationsQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    #[serde(default)]
+    pub unread_only: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct NotificationStreamQuery {
+    /// For clients that can't send the `Last-Event-ID` header
+    pub last_event_id: Option<Uuid>,
+}
+
+/// A page of the inbox, newest first
+#[derive(Debug, Serialize)]
+pub struct NotificationPage {
+    pub notifications: Vec<Notification>,
+    /// Matching the query, across all pages
+    pub total: i64,
+    pub unread: i64,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnreadCount {
+    pub unread: i64,
+}
+
+/// Admin-edited wording of a built-in notification for one locale
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct NotificationTemplateVariant {
+    pub key: String,
+    pub locale: String,
+    pub message: String,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A built-in notification with its stored variants
+#[derive(Debug, Serialize)]
+pub struct NotificationTemplate {
+    pub key: String,
+    pub category: NotificationCategory,
+    pub description: String,
+    /// English text used when no variant matches the recipient's locale
+    pub default_message: String,
+    pub variables: Vec<TemplateVariable>,
+    pub variants: Vec<NotificationTemplateVariant>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateNotificationTemplateInput {
+    #[validate(custom = "not_blank")]
+    pub message: String,
+}
+
+/// A browser's push subscription
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct PushSubscription {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub endpoint: String,
+    /// Encryption keys are only needed to send, never returned
+    #[serde(skip)]
+    pub p256dh: String,
+    #[serde(skip)]
+    pub auth: String,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub failure_count: i32,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PushSubscriptionKeys {
+    pub p256dh: String,
+    pub auth: String,
+}
+
+/// The browser's `PushSubscription.toJSON()`
+#[derive(Debug, Deserialize, Validate)]
+pub struct SubscribeInput {
+    #[validate(url)]
+    pub endpoint: String,
+    pub keys: PushSubscriptionKeys,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UnsubscribeInput {
+    #[validate(url)]
+    pub endpoint: String,
+}
+
+/// What the service worker receives
+#[derive(Debug, Clone, Serialize)]
+pub struct PushPayload {
+    pub id: Uuid,
+    pub category: NotificationCategory,
+    pub message: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Whether push is available, and the key browsers subscribe with
+#[derive(Debug, Serialize)]
+pub struct PushConfigView {
+    pub enabled: bool,
+    pub public_key: Option<String>,
+}
@@ -0,0 +1,336 @@
+use super::models::{
+    NotificationPriority, PushConfigView, PushPayload, PushSubscription, SubscribeInput,
+};
+use super::service::{NotificationError, NotificationService};
+use super::webpush::{self, VapidKeys};
+use crate::config::WebPushConfig;
+use crate::utils::http_client::OutboundClient;
+use reqwest::StatusCode;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Sends Web Push messages signed with the server's VAPID keys
+#[derive(Clone)]
+pub struct WebPush {
+    config: WebPushConfig,
+    keys: Arc<VapidKeys>,
+    http: OutboundClient,
+}
+
+impl WebPush {
+    /// Endpoints come from browsers, so redirects are never followed; a
+    /// redirect could otherwise lead past the host check
+    pub fn new(config: WebPushConfig, keys: VapidKeys, http: OutboundClient) -> Self {
+        Self {
+            config,
+            keys: Arc::new(keys),
+            http: http.without_redirects(),
+        }
+    }
+
+    /// None when no public key is configured. The private key is read from
+    /// the environment variable the config names.
+    pub fn from_config(
+        config: &WebPushConfig,
+        http: OutboundClient,
+    ) -> Result<Option<Self>, String> {
+        if config.vapid_public_key.trim().is_empty() {
+            return Ok(None);
+        }
+        let private_key = std::env::var(&config.vapid_private_key_env).map_err(|_| {
+            format!(
+                "web_push.vapid_public_key is set but {} is not",
+                config.vapid_private_key_env
+            )
+        })?;
+        let keys = VapidKeys::from_base64(&config.vapid_public_key, &private_key)?;
+        Ok(Some(Self::new(config.clone(), keys, http)))
+    }
+
+    pub fn public_key(&self) -> &str {
+        self.keys.public_key()
+    }
+
+    fn check_endpoint(&self, endpoint: &str) -> Result<(), NotificationError> {
+        let invalid =
+            |reason: &str| NotificationError::InvalidInput(format!("endpoint {}", reason));
+        let url = reqwest::Url::parse(endpoint).map_err(|_| invalid("is not a valid URL"))?;
+        match url.scheme() {
+            "https" => {}
+            "http" if self.config.allow_insecure_endpoints => {}
+            _ => return Err(invalid("must use https")),
+        }
+        let host = url
+            .host_str()
+            .ok_or_else(|| invalid("has no host"))?
+            .to_ascii_lowercase();
+        let allowed = self.config.allowed_hosts.is_empty()
+            || self.config.allowed_hosts.iter().any(|allowed| {
+                let allowed = allowed.to_ascii_lowercase();
+                host == allowed || host.ends_with(&format!(".{}", allowed))
+            });
+        if !allowed {
+            return Err(invalid("is not a known push service"));
+        }
+        Ok(())
+    }
+}
+
+/// Decode a subscription key, checking it has the expected shape
+fn decode_key(value: &str, name: &str, len: usize) -> Result<Vec<u8>, NotificationError> {
+    let bytes = webpush::b64url_decode(value)
+        .map_err(|e| NotificationError::InvalidInput(format!("keys.{}: {}", name, e)))?;
+    if bytes.len() != len {
+        return Err(NotificationError::InvalidInput(format!(
+            "keys.{} must be {} bytes",
+            name, len
+        )));
+    }
+    Ok(bytes)
+}
+
+enum PushOutcome {
+    Delivered,
+    /// The push service says the subscription no longer exists
+    Gone,
+    Failed(String),
+}
+
+impl NotificationService {
+    // ========================================================================
+    // WEB PUSH
+    // ========================================================================
+
+    fn web_push(&self) -> Result<&WebPush, NotificationError> {
+        self.push
+            .as_ref()
+            .ok_or_else(|| NotificationError::InvalidInput("Web Push is not enabled".to_string()))
+    }
+
+    pub fn push_config(&self) -> PushConfigView {
+        PushConfigView {
+            enabled: self.push.is_some(),
+            public_key: self.push.as_ref().map(|p| p.public_key().to_string()),
+        }
+    }
+
+    /// Register (or refresh) a browser's subscription for `user_id`. Past the
+    /// per-user limit the least recently created subscription is replaced.
+    pub async fn subscribe_push(
+        &self,
+        user_id: Uuid,
+        input: SubscribeInput,
+        user_agent: Option<String>,
+    ) -> Result<PushSubscription, NotificationError> {
+        let push = self.web_push()?;
+        push.check_endpoint(&input.endpoint)?;
+        let p256dh = decode_key(&input.keys.p256dh, "p256dh", 65)?;
+        if p256dh[0] != 4 {
+            return Err(NotificationError::InvalidInput(
+                "keys.p256dh must be an uncompressed P-256 point".to_string(),
+            ));
+        }
+        let auth = decode_key(&input.keys.auth, "auth", 16)?;
+
+        let mut tx = self.pool.begin().await?;
+        // A browser keeps its endpoint across sign-ins, so it moves to
+        // whoever subscribed last
+        let subscription = sqlx::query_as::<_, PushSubscription>(
+            r#"
+            INSERT INTO push_subscriptions (user_id, endpoint, p256dh, auth, user_agent)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (endpoint) DO UPDATE SET
+                user_id = EXCLUDED.user_id,
+                p256dh = EXCLUDED.p256dh,
+                auth = EXCLUDED.auth,
+                user_agent = EXCLUDED.user_agent,
+                failure_count = 0,
+                last_error = NULL
+            RETURNING *
+            "#,
+        )
+        .bind(user_id)
+        .bind(&input.endpoint)
+        .bind(base64::encode_config(&p256dh, base64::URL_SAFE_NO_PAD))
+        .bind(base64::encode_config(&auth, base64::URL_SAFE_NO_PAD))
+        .bind(user_agent)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM push_subscriptions
+            WHERE user_id = $1 AND id IN (
+                SELECT id FROM push_subscriptions WHERE user_id = $1
+                ORDER BY created_at DESC, id OFFSET $2
+            )
+            "#,
+        )
+        .bind(user_id)
+        .bind(push.config.max_subscriptions_per_user.max(1))
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(subscription)
+    }
+
+    pub async fn unsubscribe_push(
+        &self,
+        user_id: Uuid,
+        endpoint: &str,
+    ) -> Result<(), NotificationError> {
+        let removed =
+            sqlx::query("DELETE FROM push_subscriptions WHERE user_id = $1 AND endpoint = $2")
+                .bind(user_id)
+                .bind(endpoint)
+                .execute(&self.pool)
+                .await?
+                .rows_affected();
+        if removed == 0 {
+            return Err(NotificationError::NotFound("Push subscription".to_string()));
+        }
+        Ok(())
+    }
+
+    pub async fn list_push_subscriptions(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Vec<PushSubscription>, NotificationError> {
+        Ok(sqlx::query_as::<_, PushSubscription>(
+            "SELECT * FROM push_subscriptions WHERE user_id = $1 ORDER BY created_at DESC",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    /// Push `payload` to every browser `user_id` subscribed. Returns how many
+    /// accepted it; dead subscriptions are removed along the way.
+    pub async fn send_push(
+        &self,
+        user_id: Uuid,
+        payload: &PushPayload,
+    ) -> Result<usize, NotificationError> {
+        let push = self.web_push()?;
+        let body = serde_json::to_vec(payload).unwrap_or_default();
+        let urgency = match payload.category.priority() {
+            NotificationPriority::High => "high",
+            NotificationPriority::Low => "normal",
+        };
+
+        let mut delivered = 0;
+        for subscription in self.list_push_subscriptions(user_id).await? {
+            match self.push_one(push, &subscription, &body, urgency).await {
+                PushOutcome::Delivered => {
+                    delivered += 1;
+                    sqlx::query(
+                        "UPDATE push_subscriptions SET last_success_at = NOW(), failure_count = 0, last_error = NULL WHERE id = $1",
+                    )
+                    .bind(subscription.id)
+                    .execute(&self.pool)
+                    .await?;
+                }
+                PushOutcome::Gone => {
+                    sqlx::query("DELETE FROM push_subscriptions WHERE id = $1")
+                        .bind(subscription.id)
+                        .execute(&self.pool)
+                        .await?;
+                }
+                PushOutcome::Failed(error) => {
+                    tracing::warn!(
+                        "Web Push to subscription {} failed: {}",
+                        subscription.id,
+                        error
+                    );
+                    sqlx::query(
+                        r#"
+                        UPDATE push_subscriptions
+                        SET failure_count = failure_count + 1, last_error = $2
+                        WHERE id = $1
+                        "#,
+                    )
+                    .bind(subscription.id)
+                    .bind(&error)
+                    .execute(&self.pool)
+                    .await?;
+                    sqlx::query(
+                        "DELETE FROM push_subscriptions WHERE id = $1 AND failure_count >= $2",
+                    )
+                    .bind(subscription.id)
+                    .bind(push.config.max_failures)
+                    .execute(&self.pool)
+                    .await?;
+                }
+            }
+        }
+        Ok(delivered)
+    }
+
+    async fn push_one(
+        &self,
+        push: &WebPush,
+        subscription: &PushSubscription,
+        body: &[u8],
+        urgency: &str,
+    ) -> PushOutcome {
+        // Stored subscriptions were checked on the way in, but the allowed
+        // hosts may have been narrowed since
+        if let Err(e) = push.check_endpoint(&subscription.endpoint) {
+            return PushOutcome::Failed(e.to_string());
+        }
+        let encrypted = match (
+            webpush::b64url_decode(&subscription.p256dh),
+            webpush::b64url_decode(&subscription.auth),
+        ) {
+            (Ok(p256dh), Ok(auth)) => webpush::encrypt(body, &p256dh, &auth),
+            (Err(e), _) | (_, Err(e)) => Err(e),
+        };
+        let encrypted = match encrypted {
+            Ok(encrypted) => encrypted,
+            Err(e) => return PushOutcome::Failed(e),
+        };
+        let authorization = match push
+            .keys
+            .authorization(&subscription.endpoint, &push.config.subject)
+        {
+            Ok(authorization) => authorization,
+            Err(e) => return PushOutcome::Failed(e),
+        };
+
+        let response = push
+            .http
+            .send(|client| {
+                client
+                    .post(&subscription.endpoint)
+                    .header("Authorization", &authorization)
+                    .header("TTL", push.config.ttl_secs.to_string())
+                    .header("Urgency", urgency)
+                    .header("Content-Encoding", "aes128gcm")
+                    .header("Content-Type", "application/octet-stream")
+                    .body(encrypted.clone())
+            })
+            .await;
+        match response {
+            Ok(response) if response.status().is_success() => PushOutcome::Delivered,
+            Ok(response)
+                if matches!(response.status(), StatusCode::NOT_FOUND | StatusCode::GONE) =>
+            {
+                PushOutcome::Gone
+            }
+            Ok(response) => {
+                PushOutcome::Failed(format!("push service returned {}", response.status()))
+            }
+            Err(e) => PushOutcome::Failed(e.to_string()),
+        }
+    }
+
+    /// Push without holding up the caller; failures are only logged
+    pub(super) fn push_in_background(&self, user_id: Uuid, payload: PushPayload) {
+        let service = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = service.send_push(user_id, &payload).await {
+                tracing::error!("Failed to push notification to {}: {}", user_id, e);
+            }
+        });
+    }
+}
@@ -0,0 +1,273 @@
+use crate::features::auth::jwt::Claims;
+use crate::features::notifications::models::{
+    ListNotificationsQuery, Notification, NotificationPage, NotificationStreamQuery,
+    NotificationTemplate, NotificationTemplateVariant, PreferencesView, PushConfigView,
+    PushSubscription, SubscribeInput, UnreadCount, UnsubscribeInput,
+    UpdateNotificationTemplateInput, UpdatePreferencesInput,
+};
+use crate::features::notifications::service::{NotificationError, NotificationService};
+use crate::middleware::abac::{enforce_route_permissions, RoutePermission};
+use crate::utils::api_error::ApiError;
+use crate::utils::validation::ValidatedJson;
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::sse::{Event, KeepAlive, Sse},
+    routing::{delete, get, post, put},
+    Extension, Json, Router,
+};
+use futures::stream::{self, Stream, StreamExt};
+use std::convert::Infallible;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// How long a disconnected browser waits before reconnecting
+const STREAM_RETRY: Duration = Duration::from_secs(3);
+/// Keeps proxies from closing a quiet stream
+const STREAM_HEARTBEAT: Duration = Duration::from_secs(15);
+
+/// Permissions enforced by `enforce_route_permissions` for the routes below
+pub const ROUTE_PERMISSIONS: &[RoutePermission] = &[
+    RoutePermission::new("GET", "/templates", "manage_system"),
+    RoutePermission::new("GET", "/templates/:key", "manage_system"),
+    RoutePermission::new("PUT", "/templates/:key/:locale", "manage_system"),
+    RoutePermission::new("DELETE", "/templates/:key/:locale", "manage_system"),
+];
+
+pub fn notification_routes() -> Router<NotificationService> {
+    Router::new()
+        .route("/", get(list_notifications_handler))
+        .route("/unread-count", get(unread_count_handler))
+        .route("/stream", get(stream_handler))
+        .route("/read-all", post(mark_all_read_handler))
+        .route("/:id", delete(delete_notification_handler))
+        .route("/:id/read", post(mark_read_handler))
+        .route(
+            "/preferences",
+            get(get_preferences_handler).put(update_preferences_handler),
+        )
+        .route("/templates", get(list_templates_handler))
+        .route("/templates/:key", get(get_template_handler))
+        .route(
+            "/templates/:key/:locale",
+            put(set_template_variant_handler).delete(delete_template_variant_handler),
+        )
+        .route("/push", get(push_config_handler))
+        .route(
+            "/push/subscriptions",
+            get(list_push_subscriptions_handler)
+                .post(subscribe_push_handler)
+                .delete(unsubscribe_push_handler),
+        )
+        .route_layer(axum::middleware::from_fn_with_state(
+            ROUTE_PERMISSIONS,
+            enforce_route_permissions,
+        ))
+}
+
+fn user_id(claims: &Claims) -> Result<Uuid, NotificationError> {
+    Uuid::parse_str(&claims.sub)
+        .map_err(|_| NotificationError::InvalidInput("Invalid user id in token".to_string()))
+}
+
+#[axum::debug_handler]
+async fn list_notifications_handler(
+    State(service): State<NotificationService>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<ListNotificationsQuery>,
+) -> Result<Json<NotificationPage>, NotificationError> {
+    Ok(Json(
+        service.list_notifications(user_id(&claims)?, query).await?,
+    ))
+}
+
+#[axum::debug_handler]
+async fn unread_count_handler(
+    State(service): State<NotificationService>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<UnreadCount>, NotificationError> {
+    let unread = service.unread_count(user_id(&claims)?).await?;
+    Ok(Json(UnreadCount { unread }))
+}
+
+#[axum::debug_handler]
+async fn mark_read_handler(
+    State(service): State<NotificationService>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Notification>, NotificationError> {
+    Ok(Json(service.mark_read(user_id(&claims)?, id).await?))
+}
+
+#[axum::debug_handler]
+async fn mark_all_read_handler(
+    State(service): State<NotificationService>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<serde_json::Value>, NotificationError> {
+    let marked = service.mark_all_read(user_id(&claims)?).await?;
+    Ok(Json(serde_json::json!({ "marked": marked })))
+}
+
+#[axum::debug_handler]
+async fn delete_notification_handler(
+    State(service): State<NotificationService>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, NotificationError> {
+    service.delete_notification(user_id(&claims)?, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Server-sent events, one per notification, with the notification id as the
+/// event id so a reconnecting browser resumes from `Last-Event-ID`
+async fn stream_handler(
+    State(service): State<NotificationService>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    Query(query): Query<NotificationStreamQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, NotificationError> {
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| Uuid::parse_str(v.trim()).ok())
+        .or(query.last_event_id);
+    let notifications = service
+        .notification_stream(user_id(&claims)?, last_event_id)
+        .await?;
+
+    let events = stream::once(async { Event::default().retry(STREAM_RETRY).comment("connected") })
+        .chain(notifications.map(|notification| {
+            Event::default()
+                .id(notification.id.to_string())
+                .json_data(&notification)
+                .unwrap_or_default()
+        }))
+        .map(Ok);
+    Ok(Sse::new(events).keep_alive(
+        KeepAlive::new()
+            .interval(STREAM_HEARTBEAT)
+            .text("heartbeat"),
+    ))
+}
+
+#[axum::debug_handler]
+async fn get_preferences_handler(
+    State(service): State<NotificationService>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<PreferencesView>, NotificationError> {
+    let prefs = service.get_preferences(user_id(&claims)?).await?;
+    Ok(Json(PreferencesView::from(&prefs)))
+}
+
+#[axum::debug_handler]
+async fn update_preferences_handler(
+    State(service): State<NotificationService>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(input): ValidatedJson<UpdatePreferencesInput>,
+) -> Result<Json<PreferencesView>, NotificationError> {
+    let prefs = service.update_preferences(user_id(&claims)?, input).await?;
+    Ok(Json(PreferencesView::from(&prefs)))
+}
+
+#[axum::debug_handler]
+async fn list_templates_handler(
+    State(service): State<NotificationService>,
+) -> Result<Json<Vec<NotificationTemplate>>, NotificationError> {
+    Ok(Json(service.list_templates().await?))
+}
+
+#[axum::debug_handler]
+async fn get_template_handler(
+    State(service): State<NotificationService>,
+    Path(key): Path<String>,
+) -> Result<Json<NotificationTemplate>, NotificationError> {
+    Ok(Json(service.get_template(&key).await?))
+}
+
+#[axum::debug_handler]
+async fn set_template_variant_handler(
+    State(service): State<NotificationService>,
+    Extension(claims): Extension<Claims>,
+    Path((key, locale)): Path<(String, String)>,
+    ValidatedJson(input): ValidatedJson<UpdateNotificationTemplateInput>,
+) -> Result<Json<NotificationTemplateVariant>, NotificationError> {
+    let user_id = user_id(&claims)?;
+    Ok(Json(
+        service
+            .set_template_variant(&key, &locale, input, user_id)
+            .await?,
+    ))
+}
+
+#[axum::debug_handler]
+async fn delete_template_variant_handler(
+    State(service): State<NotificationService>,
+    Extension(claims): Extension<Claims>,
+    Path((key, locale)): Path<(String, String)>,
+) -> Result<Json<NotificationTemplate>, NotificationError> {
+    let user_id = user_id(&claims)?;
+    Ok(Json(
+        service
+            .delete_template_variant(&key, &locale, user_id)
+            .await?,
+    ))
+}
+
+#[axum::debug_handler]
+async fn push_config_handler(State(service): State<NotificationService>) -> Json<PushConfigView> {
+    Json(service.push_config())
+}
+
+#[axum::debug_handler]
+async fn list_push_subscriptions_handler(
+    State(service): State<NotificationService>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Vec<PushSubscription>>, NotificationError> {
+    Ok(Json(
+        service.list_push_subscriptions(user_id(&claims)?).await?,
+    ))
+}
+
+#[axum::debug_handler]
+async fn subscribe_push_handler(
+    State(service): State<NotificationService>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    ValidatedJson(input): ValidatedJson<SubscribeInput>,
+) -> Result<(StatusCode, Json<PushSubscription>), NotificationError> {
+    let user_agent = headers
+        .get(header::USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.chars().take(255).collect());
+    let subscription = service
+        .subscribe_push(user_id(&claims)?, input, user_agent)
+        .await?;
+    Ok((StatusCode::CREATED, Json(subscription)))
+}
+
+#[axum::debug_handler]
+async fn unsubscribe_push_handler(
+    State(service): State<NotificationService>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(input): ValidatedJson<UnsubscribeInput>,
+) -> Result<StatusCode, NotificationError> {
+    service
+        .unsubscribe_push(user_id(&claims)?, &input.endpoint)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+impl axum::response::IntoResponse for NotificationError {
+    fn into_response(self) -> axum::response::Response {
+        let (status, code) = match &self {
+            NotificationError::DatabaseError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+            NotificationError::InvalidInput(_) => {
+                (StatusCode::BAD_REQUEST, "notifications.invalid_input")
+            }
+            NotificationError::Forbidden(_) => (StatusCode::FORBIDDEN, "notifications.forbidden"),
+            NotificationError::NotFound(_) => (StatusCode::NOT_FOUND, "notifications.not_found"),
+        };
+
+        ApiError::new(status, code, self.to_string()).into_response()
+    }
+}
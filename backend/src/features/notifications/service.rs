@@ -0,0 +1,290 @@
+use super::models::{
+    DeliveryChannel, DigestFrequency, Notification, NotificationCategory, NotificationEvent,
+    NotificationPreferences, NotificationPriority, PushPayload, SentNotification,
+    UpdatePreferencesInput,
+};
+use super::push::WebPush;
+use super::templates::{normalize_locale, BuiltinNotification};
+use crate::features::email::templates::NOTIFICATION_EMAIL;
+use crate::features::email::EmailService;
+use crate::features::system::AuditService;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use thiserror::Error;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+#[derive(Error, Debug)]
+pub enum NotificationError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+}
+
+/// Creates in-app notifications, streams them to open clients, and emails or
+/// pushes them according to each user's per-category preferences
+#[derive(Clone)]
+pub struct NotificationService {
+    pub(super) pool: PgPool,
+    pub(super) audit_service: AuditService,
+    pub(super) email: Option<EmailService>,
+    pub(super) push: Option<WebPush>,
+    pub(super) events: broadcast::Sender<NotificationEvent>,
+}
+
+impl NotificationService {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            audit_service: AuditService::new(pool.clone()),
+            pool,
+            email: None,
+            push: None,
+            events: broadcast::channel(256).0,
+        }
+    }
+
+    /// Email notifications to users who asked for it. Without this, the
+    /// email channel behaves like in-app.
+    pub fn with_email(mut self, email: EmailService) -> Self {
+        self.email = Some(email);
+        self
+    }
+
+    /// Push notifications to users' subscribed browsers
+    pub fn with_push(mut self, push: WebPush) -> Self {
+        self.push = Some(push);
+        self
+    }
+
+    async fn notification_class_id(&self) -> Result<Uuid, NotificationError> {
+        sqlx::query_scalar::<_, Uuid>(
+            r#"
+            SELECT c.id FROM classes c
+            JOIN ontology_versions v ON v.id = c.version_id
+            WHERE c.name = 'Notification'
+            ORDER BY v.is_system DESC, c.created_at DESC
+            LIMIT 1
+            "#,
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| NotificationError::NotFound("Notification class".to_string()))
+    }
+
+    // ========================================================================
+    // PREFERENCES
+    // ========================================================================
+
+    pub async fn get_preferences(
+        &self,
+        user_id: Uuid,
+    ) -> Result<NotificationPreferences, NotificationError> {
+        let stored = sqlx::query_scalar::<_, Option<serde_json::Value>>(
+            "SELECT notification_preferences FROM unified_users WHERE id = $1",
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| NotificationError::NotFound("User".to_string()))?;
+        Ok(NotificationPreferences::from_value(stored))
+    }
+
+    /// Change the listed categories and, if given, the digest frequency and
+    /// locale
+    pub async fn update_preferences(
+        &self,
+        user_id: Uuid,
+        input: UpdatePreferencesInput,
+    ) -> Result<NotificationPreferences, NotificationError> {
+        if let Some(category) = input
+            .channels
+            .iter()
+            .find(|(category, channel)| {
+                **channel == DeliveryChannel::None && !category.can_disable()
+            })
+            .map(|(category, _)| category)
+        {
+            return Err(NotificationError::InvalidInput(format!(
+                "{} notifications can't be turned off",
+                category.as_str()
+            )));
+        }
+
+        let locale = match input.locale.as_deref().map(str::trim) {
+            None => None,
+            Some("") => Some(None),
+            Some(locale) => Some(Some(normalize_locale(locale).ok_or_else(|| {
+                NotificationError::InvalidInput(format!("'{}' is not a valid locale", locale))
+            })?)),
+        };
+
+        let mut prefs = self.get_preferences(user_id).await?;
+        prefs.channels.extend(input.channels);
+        if let Some(digest) = input.digest {
+            prefs.digest = digest;
+        }
+        if let Some(locale) = locale {
+            prefs.locale = locale;
+        }
+        if let Some(push) = input.push {
+            prefs.push = push;
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE entities
+            SET attributes = attributes || jsonb_build_object('notification_preferences', $1::jsonb),
+                updated_at = NOW()
+            WHERE id = $2
+            "#,
+        )
+        .bind(serde_json::to_value(&prefs).unwrap_or_default())
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(prefs)
+    }
+
+    // ========================================================================
+    // DELIVERY
+    // ========================================================================
+
+    /// The recipient's address and preferences. Unknown users (e.g. removed
+    /// since) get defaults, so they still receive the in-app notification.
+    async fn recipient(
+        &self,
+        user_id: Uuid,
+    ) -> Result<(Option<String>, NotificationPreferences), NotificationError> {
+        let (address, stored) = sqlx::query_as::<_, (Option<String>, Option<serde_json::Value>)>(
+            "SELECT email, notification_preferences FROM unified_users WHERE id = $1",
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .unwrap_or((None, None));
+        Ok((address, NotificationPreferences::from_value(stored)))
+    }
+
+    /// Notify `user_id` through the channel they chose for `category`.
+    /// Returns None if they turned the category off.
+    pub async fn notify(
+        &self,
+        user_id: Uuid,
+        category: NotificationCategory,
+        message: &str,
+    ) -> Result<Option<SentNotification>, NotificationError> {
+        let (address, prefs) = self.recipient(user_id).await?;
+        self.deliver(user_id, category, message.to_string(), address, &prefs)
+            .await
+    }
+
+    /// Notify `user_id` with a built-in notification, worded for their locale
+    pub async fn notify_template(
+        &self,
+        user_id: Uuid,
+        template: &BuiltinNotification,
+        values: &[(&str, &str)],
+    ) -> Result<Option<SentNotification>, NotificationError> {
+        let (address, prefs) = self.recipient(user_id).await?;
+        if prefs.channel(template.category) == DeliveryChannel::None
+            && template.category.can_disable()
+        {
+            return Ok(None);
+        }
+        let message = self
+            .render_message(template, prefs.locale.as_deref(), values)
+            .await?;
+        self.deliver(user_id, template.category, message, address, &prefs)
+            .await
+    }
+
+    async fn deliver(
+        &self,
+        user_id: Uuid,
+        category: NotificationCategory,
+        message: String,
+        address: Option<String>,
+        prefs: &NotificationPreferences,
+    ) -> Result<Option<SentNotification>, NotificationError> {
+        let channel = prefs.channel(category);
+        if channel == DeliveryChannel::None && category.can_disable() {
+            return Ok(None);
+        }
+        let email = match (channel, &self.email, address) {
+            (DeliveryChannel::Email, Some(service), Some(address)) => Some((service, address)),
+            _ => None,
+        };
+        // Low-priority emails wait for the digest job if the user wants one
+        let digest_pending = email.is_some()
+            && category.priority() == NotificationPriority::Low
+            && prefs.digest != DigestFrequency::Off;
+
+        let class_id = self.notification_class_id().await?;
+        let (id, created_at) = sqlx::query_as::<_, (Uuid, DateTime<Utc>)>(
+            "INSERT INTO entities (class_id, display_name, attributes) VALUES ($1, $2, $3) RETURNING id, created_at",
+        )
+        .bind(class_id)
+        .bind(format!(
+            "Notification: {}",
+            message.chars().take(20).collect::<String>()
+        ))
+        .bind(serde_json::json!({
+            "user_id": user_id,
+            "message": message,
+            "read": false,
+            "category": category,
+            "digest_pending": digest_pending,
+        }))
+        .fetch_one(&self.pool)
+        .await?;
+
+        if let (Some((service, address)), false) = (email, digest_pending) {
+            if let Err(e) = service
+                .queue(
+                    &NOTIFICATION_EMAIL,
+                    &address,
+                    &[("message", &message), ("category", category.as_str())],
+                )
+                .await
+            {
+                tracing::error!("Failed to queue notification email for {}: {}", user_id, e);
+            }
+        }
+        // No open streams is not an error
+        let _ = self.events.send(NotificationEvent {
+            user_id,
+            notification: Notification {
+                id,
+                message: message.clone(),
+                category: Some(category),
+                read: false,
+                created_at,
+            },
+        });
+        if self.push.is_some() && prefs.push.includes(category) {
+            self.push_in_background(
+                user_id,
+                PushPayload {
+                    id,
+                    category,
+                    message: message.clone(),
+                    created_at,
+                },
+            );
+        }
+        Ok(Some(SentNotification {
+            id,
+            message,
+            category,
+            created_at,
+        }))
+    }
+}
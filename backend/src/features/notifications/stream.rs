@@ -0,0 +1,81 @@
+use super::inbox::{COLUMNS, INBOX};
+use super::models::Notification;
+use super::service::{NotificationError, NotificationService};
+use futures::stream::{self, Stream, StreamExt};
+use std::collections::HashSet;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use uuid::Uuid;
+
+/// Most notifications replayed to a reconnecting client; older ones are
+/// left to the inbox
+const MAX_REPLAY: i64 = 100;
+
+impl NotificationService {
+    // ========================================================================
+    // STREAM
+    // ========================================================================
+
+    /// `user_id`'s notifications as they are created. A reconnecting client
+    /// passes the id of the last one it saw and first gets those created
+    /// since. The stream ends if the client falls behind the broadcast, so
+    /// it reconnects and catches up from its cursor instead of missing some.
+    pub async fn notification_stream(
+        &self,
+        user_id: Uuid,
+        last_event_id: Option<Uuid>,
+    ) -> Result<impl Stream<Item = Notification> + Send + 'static, NotificationError> {
+        // Subscribe before reading the backlog so nothing falls in between
+        let live = BroadcastStream::new(self.events.subscribe());
+        let missed = match last_event_id {
+            Some(cursor) => self.notifications_since(user_id, cursor).await?,
+            None => Vec::new(),
+        };
+        let replayed: HashSet<Uuid> = missed.iter().map(|n| n.id).collect();
+
+        let live = live
+            .take_while(|event| {
+                futures::future::ready(!matches!(event, Err(BroadcastStreamRecvError::Lagged(_))))
+            })
+            .filter_map(move |event| {
+                futures::future::ready(match event {
+                    Ok(event)
+                        if event.user_id == user_id
+                            && !replayed.contains(&event.notification.id) =>
+                    {
+                        Some(event.notification)
+                    }
+                    _ => None,
+                })
+            });
+        Ok(stream::iter(missed).chain(live))
+    }
+
+    /// Notifications created after `cursor`, oldest first. Nothing if the
+    /// cursor isn't one of the user's notifications.
+    async fn notifications_since(
+        &self,
+        user_id: Uuid,
+        cursor: Uuid,
+    ) -> Result<Vec<Notification>, NotificationError> {
+        let mut missed = sqlx::query_as::<_, Notification>(&format!(
+            r#"
+            {} {}
+              AND (e.created_at, e.id) > (
+                  SELECT created_at, id FROM entities
+                  WHERE id = $2 AND attributes->>'user_id' = $1::text
+              )
+            ORDER BY e.created_at DESC, e.id DESC
+            LIMIT $3
+            "#,
+            COLUMNS, INBOX
+        ))
+        .bind(user_id)
+        .bind(cursor)
+        .bind(MAX_REPLAY)
+        .fetch_all(&self.pool)
+        .await?;
+        missed.reverse();
+        Ok(missed)
+    }
+}
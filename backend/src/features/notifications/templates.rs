@@ -0,0 +1,360 @@
+use super::models::{
+    NotificationCategory, NotificationTemplate, NotificationTemplateVariant,
+    UpdateNotificationTemplateInput,
+};
+use super::service::{NotificationError, NotificationService};
+use crate::features::ai::prompts::{placeholders, render_template};
+use crate::features::email::models::TemplateVariable;
+use uuid::Uuid;
+
+/// Used for recipients without a locale, and as the last fallback
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// A notification the backend sends, with the variables its caller supplies.
+/// Admins can add wording per locale; the built-in English text is the
+/// fallback when no variant matches or one can't be rendered.
+pub struct BuiltinNotification {
+    pub key: &'static str,
+    pub category: NotificationCategory,
+    pub description: &'static str,
+    pub message: &'static str,
+    /// (name, description)
+    pub variables: &'static [(&'static str, &'static str)],
+}
+
+pub const NEW_DEVICE_LOGIN: BuiltinNotification = BuiltinNotification {
+    key: "new_device_login",
+    category: NotificationCategory::Security,
+    description: "Sign-in from a device or address not seen before",
+    message: "New sign-in detected from IP {{ip}} and device/agent {{user_agent}}",
+    variables: &[
+        ("ip", "Client IP address"),
+        ("user_agent", "Client User-Agent header"),
+    ],
+};
+
+pub const PASSWORD_CHANGED: BuiltinNotification = BuiltinNotification {
+    key: "password_changed",
+    category: NotificationCategory::Security,
+    description: "The user changed their password",
+    message: "Your password was successfully changed.",
+    variables: &[],
+};
+
+pub const PASSWORD_RESET: BuiltinNotification = BuiltinNotification {
+    key: "password_reset",
+    category: NotificationCategory::Security,
+    description: "The password was set through a reset link",
+    message: "Your password has been successfully reset.",
+    variables: &[],
+};
+
+pub const PROFILE_UPDATED: BuiltinNotification = BuiltinNotification {
+    key: "profile_updated",
+    category: NotificationCategory::Account,
+    description: "The user's profile was changed",
+    message: "Your profile was updated.",
+    variables: &[],
+};
+
+pub const GRANT_EXPIRING: BuiltinNotification = BuiltinNotification {
+    key: "grant_expiring",
+    category: NotificationCategory::Access,
+    description: "Firefighter access is about to run out",
+    message:
+        "Your firefighter access ends at {{ends_at}}. Request a new session if you still need it.",
+    variables: &[("ends_at", "When access ends (RFC 3339)")],
+};
+
+pub const BUILTIN_NOTIFICATIONS: &[&BuiltinNotification] = &[
+    &NEW_DEVICE_LOGIN,
+    &PASSWORD_CHANGED,
+    &PASSWORD_RESET,
+    &PROFILE_UPDATED,
+    &GRANT_EXPIRING,
+];
+
+pub fn builtin(key: &str) -> Option<&'static BuiltinNotification> {
+    BUILTIN_NOTIFICATIONS.iter().copied().find(|t| t.key == key)
+}
+
+/// Canonical form of a BCP 47 language tag limited to language and region,
+/// e.g. "nb_no" becomes "nb-NO". None if it isn't one.
+pub fn normalize_locale(locale: &str) -> Option<String> {
+    let mut parts = locale.trim().split(['-', '_']);
+    let language = parts.next()?;
+    let region = parts.next();
+    if parts.next().is_some()
+        || !(2..=3).contains(&language.len())
+        || !language.chars().all(|c| c.is_ascii_alphabetic())
+    {
+        return None;
+    }
+    match region {
+        None => Some(language.to_ascii_lowercase()),
+        Some(region) if region.len() == 2 && region.chars().all(|c| c.is_ascii_alphabetic()) => {
+            Some(format!(
+                "{}-{}",
+                language.to_ascii_lowercase(),
+                region.to_ascii_uppercase()
+            ))
+        }
+        Some(_) => None,
+    }
+}
+
+/// Locales to try for a recipient, most specific first: "nb-NO", "nb", "en"
+fn fallback_locales(locale: Option<&str>) -> Vec<String> {
+    let mut locales = Vec::new();
+    if let Some(locale) = locale.and_then(normalize_locale) {
+        if let Some((language, _)) = locale.split_once('-') {
+            let language = language.to_string();
+            locales.push(locale);
+            locales.push(language);
+        } else {
+            locales.push(locale);
+        }
+    }
+    if !locales.iter().any(|l| l == DEFAULT_LOCALE) {
+        locales.push(DEFAULT_LOCALE.to_string());
+    }
+    locales
+}
+
+impl BuiltinNotification {
+    fn declared_variables(&self) -> Vec<TemplateVariable> {
+        self.variables
+            .iter()
+            .map(|(name, description)| TemplateVariable {
+                name: name.to_string(),
+                description: description.to_string(),
+            })
+            .collect()
+    }
+}
+
+fn validate_variant(
+    template: &BuiltinNotification,
+    input: &UpdateNotificationTemplateInput,
+) -> Result<(), NotificationError> {
+    if input.message.trim().is_empty() {
+        return Err(NotificationError::InvalidInput(
+            "message is required".to_string(),
+        ));
+    }
+    if let Some(undeclared) = placeholders(&input.message)
+        .into_iter()
+        .find(|p| !template.variables.iter().any(|(name, _)| name == p))
+    {
+        return Err(NotificationError::InvalidInput(format!(
+            "placeholder '{{{{{}}}}}' is not a variable of the '{}' notification",
+            undeclared, template.key
+        )));
+    }
+    Ok(())
+}
+
+fn describe(
+    template: &BuiltinNotification,
+    variants: Vec<NotificationTemplateVariant>,
+) -> NotificationTemplate {
+    NotificationTemplate {
+        key: template.key.to_string(),
+        category: template.category,
+        description: template.description.to_string(),
+        default_message: template.message.to_string(),
+        variables: template.declared_variables(),
+        variants,
+    }
+}
+
+impl NotificationService {
+    // ========================================================================
+    // TEMPLATES
+    // ========================================================================
+
+    /// The message for `template` in the recipient's locale, falling back to
+    /// the language, then English, then the built-in text
+    pub(super) async fn render_message(
+        &self,
+        template: &BuiltinNotification,
+        locale: Option<&str>,
+        values: &[(&str, &str)],
+    ) -> Result<String, NotificationError> {
+        let locales = fallback_locales(locale);
+        let variants = sqlx::query_as::<_, NotificationTemplateVariant>(
+            "SELECT * FROM notification_templates WHERE key = $1 AND locale = ANY($2)",
+        )
+        .bind(template.key)
+        .bind(&locales)
+        .fetch_all(&self.pool)
+        .await?;
+
+        for locale in &locales {
+            let Some(variant) = variants.iter().find(|v| &v.locale == locale) else {
+                continue;
+            };
+            match render_template(&variant.message, values) {
+                Ok(message) => return Ok(message),
+                Err(e) => tracing::warn!(
+                    "Notification template '{}' ({}) could not be rendered: {}",
+                    template.key,
+                    locale,
+                    e
+                ),
+            }
+        }
+        render_template(template.message, values).map_err(NotificationError::InvalidInput)
+    }
+
+    pub async fn list_templates(&self) -> Result<Vec<NotificationTemplate>, NotificationError> {
+        let variants = sqlx::query_as::<_, NotificationTemplateVariant>(
+            "SELECT * FROM notification_templates ORDER BY key, locale",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(BUILTIN_NOTIFICATIONS
+            .iter()
+            .map(|template| {
+                let own = variants
+                    .iter()
+                    .filter(|v| v.key == template.key)
+                    .cloned()
+                    .collect();
+                describe(template, own)
+            })
+            .collect())
+    }
+
+    pub async fn get_template(&self, key: &str) -> Result<NotificationTemplate, NotificationError> {
+        let template = builtin(key)
+            .ok_or_else(|| NotificationError::NotFound(format!("Notification '{}'", key)))?;
+        let variants = sqlx::query_as::<_, NotificationTemplateVariant>(
+            "SELECT * FROM notification_templates WHERE key = $1 ORDER BY locale",
+        )
+        .bind(key)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(describe(template, variants))
+    }
+
+    /// Add or replace the wording of a built-in notification for `locale`
+    pub async fn set_template_variant(
+        &self,
+        key: &str,
+        locale: &str,
+        input: UpdateNotificationTemplateInput,
+        user_id: Uuid,
+    ) -> Result<NotificationTemplateVariant, NotificationError> {
+        let template = builtin(key)
+            .ok_or_else(|| NotificationError::NotFound(format!("Notification '{}'", key)))?;
+        let locale = normalize_locale(locale).ok_or_else(|| {
+            NotificationError::InvalidInput(format!("'{}' is not a valid locale", locale))
+        })?;
+        validate_variant(template, &input)?;
+
+        let variant = sqlx::query_as::<_, NotificationTemplateVariant>(
+            r#"
+            INSERT INTO notification_templates (key, locale, message, updated_by)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (key, locale) DO UPDATE SET
+                message = EXCLUDED.message,
+                updated_by = EXCLUDED.updated_by,
+                updated_at = NOW()
+            RETURNING *
+            "#,
+        )
+        .bind(key)
+        .bind(&locale)
+        .bind(&input.message)
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let _ = self
+            .audit_service
+            .log(
+                user_id,
+                "notification.template_updated",
+                "notification_template",
+                None,
+                None,
+                Some(serde_json::json!({ "key": key, "locale": locale })),
+                None,
+            )
+            .await;
+        Ok(variant)
+    }
+
+    /// Remove a locale's wording; its recipients fall back to the next locale
+    pub async fn delete_template_variant(
+        &self,
+        key: &str,
+        locale: &str,
+        user_id: Uuid,
+    ) -> Result<NotificationTemplate, NotificationError> {
+        let locale = normalize_locale(locale).ok_or_else(|| {
+            NotificationError::InvalidInput(format!("'{}' is not a valid locale", locale))
+        })?;
+        let removed =
+            sqlx::query("DELETE FROM notification_templates WHERE key = $1 AND locale = $2")
+                .bind(key)
+                .bind(&locale)
+                .execute(&self.pool)
+                .await?
+                .rows_affected();
+        if removed == 0 {
+            return Err(NotificationError::NotFound(format!(
+                "'{}' wording for '{}'",
+                locale, key
+            )));
+        }
+
+        let _ = self
+            .audit_service
+            .log(
+                user_id,
+                "notification.template_reset",
+                "notification_template",
+                None,
+                None,
+                Some(serde_json::json!({ "key": key, "locale": locale })),
+                None,
+            )
+            .await;
+        self.get_template(key).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locales_are_normalized_and_fall_back_to_language_then_english() {
+        assert_eq!(normalize_locale("nb_no").as_deref(), Some("nb-NO"));
+        assert_eq!(normalize_locale(" EN ").as_deref(), Some("en"));
+        assert_eq!(normalize_locale("english"), None);
+        assert_eq!(normalize_locale("nb-NO-x"), None);
+        assert_eq!(normalize_locale("../x"), None);
+
+        assert_eq!(fallback_locales(Some("nb-NO")), vec!["nb-NO", "nb", "en"]);
+        assert_eq!(fallback_locales(Some("en-GB")), vec!["en-GB", "en"]);
+        assert_eq!(fallback_locales(Some("bogus!")), vec!["en"]);
+        assert_eq!(fallback_locales(None), vec!["en"]);
+    }
+
+    #[test]
+    fn builtins_only_use_declared_variables() {
+        for template in BUILTIN_NOTIFICATIONS {
+            let input = UpdateNotificationTemplateInput {
+                message: template.message.to_string(),
+            };
+            assert!(
+                validate_variant(template, &input).is_ok(),
+                "{}",
+                template.key
+            );
+        }
+    }
+}
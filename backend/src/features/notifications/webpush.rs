@@ -0,0 +1,243 @@
+use hmac::{Hmac, Mac};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::{aead, agreement, signature};
+use sha2::Sha256;
+
+/// Record size advertised in the header. Payloads always fit one record.
+const RECORD_SIZE: u32 = 4096;
+
+/// Push services accept at most 4096 bytes of encrypted content; the header,
+/// padding delimiter and tag take 103 of them
+pub const MAX_PAYLOAD_BYTES: usize = 3993;
+
+fn b64url_encode(bytes: &[u8]) -> String {
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+/// Browsers hand out keys in base64url, with or without padding
+pub fn b64url_decode(value: &str) -> Result<Vec<u8>, String> {
+    base64::decode_config(value.trim().trim_end_matches('='), base64::URL_SAFE_NO_PAD)
+        .map_err(|e| format!("invalid base64url: {}", e))
+}
+
+// HKDF-SHA256 (RFC 5869), as Web Push uses it
+fn hkdf_extract(salt: &[u8], ikm: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(salt).expect("HMAC accepts any key length");
+    mac.update(ikm);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Single-block HKDF-Expand; every output here is at most 32 bytes
+fn hkdf_expand(prk: &[u8], info: &[u8], len: usize) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(prk).expect("HMAC accepts any key length");
+    mac.update(info);
+    mac.update(&[1]);
+    mac.finalize().into_bytes()[..len].to_vec()
+}
+
+/// Content key and nonce for one message (RFC 8291 section 3.4)
+fn derive_key_and_nonce(
+    ecdh_secret: &[u8],
+    auth_secret: &[u8],
+    ua_public: &[u8],
+    as_public: &[u8],
+    salt: &[u8],
+) -> (Vec<u8>, Vec<u8>) {
+    let prk_key = hkdf_extract(auth_secret, ecdh_secret);
+    let mut key_info = b"WebPush: info\0".to_vec();
+    key_info.extend_from_slice(ua_public);
+    key_info.extend_from_slice(as_public);
+    let ikm = hkdf_expand(&prk_key, &key_info, 32);
+
+    let prk = hkdf_extract(salt, &ikm);
+    let cek = hkdf_expand(&prk, b"Content-Encoding: aes128gcm\0", 16);
+    let nonce = hkdf_expand(&prk, b"Content-Encoding: nonce\0", 12);
+    (cek, nonce)
+}
+
+/// Encrypt `payload` for a subscription's `p256dh` public key and `auth`
+/// secret (RFC 8291, aes128gcm). Returns the request body, header included.
+pub fn encrypt(payload: &[u8], p256dh: &[u8], auth: &[u8]) -> Result<Vec<u8>, String> {
+    if payload.len() > MAX_PAYLOAD_BYTES {
+        return Err(format!(
+            "payload is {} bytes, the limit is {}",
+            payload.len(),
+            MAX_PAYLOAD_BYTES
+        ));
+    }
+    let rng = SystemRandom::new();
+    let as_private = agreement::EphemeralPrivateKey::generate(&agreement::ECDH_P256, &rng)
+        .map_err(|_| "failed to generate an ECDH key".to_string())?;
+    let as_public = as_private
+        .compute_public_key()
+        .map_err(|_| "failed to compute the ECDH public key".to_string())?
+        .as_ref()
+        .to_vec();
+    let ecdh_secret = agreement::agree_ephemeral(
+        as_private,
+        &agreement::UnparsedPublicKey::new(&agreement::ECDH_P256, p256dh),
+        |secret| secret.to_vec(),
+    )
+    .map_err(|_| "subscription has an invalid p256dh key".to_string())?;
+
+    let mut salt = [0u8; 16];
+    rng.fill(&mut salt)
+        .map_err(|_| "failed to generate a salt".to_string())?;
+    let (cek, nonce) = derive_key_and_nonce(&ecdh_secret, auth, p256dh, &as_public, &salt);
+
+    let key = aead::LessSafeKey::new(
+        aead::UnboundKey::new(&aead::AES_128_GCM, &cek).map_err(|_| "invalid content key")?,
+    );
+    let nonce = aead::Nonce::try_assume_unique_for_key(&nonce).map_err(|_| "invalid nonce")?;
+    // A single record ends with the 0x02 delimiter and no padding
+    let mut record = payload.to_vec();
+    record.push(2);
+    key.seal_in_place_append_tag(nonce, aead::Aad::empty(), &mut record)
+        .map_err(|_| "encryption failed".to_string())?;
+
+    let mut body = Vec::with_capacity(21 + as_public.len() + record.len());
+    body.extend_from_slice(&salt);
+    body.extend_from_slice(&RECORD_SIZE.to_be_bytes());
+    body.push(as_public.len() as u8);
+    body.extend_from_slice(&as_public);
+    body.extend_from_slice(&record);
+    Ok(body)
+}
+
+/// The application server's VAPID key pair (RFC 8292)
+pub struct VapidKeys {
+    key_pair: signature::EcdsaKeyPair,
+    public_key: String,
+}
+
+impl VapidKeys {
+    /// From base64url keys: the 65-byte uncompressed public key and the
+    /// 32-byte private scalar
+    pub fn from_base64(public_key: &str, private_key: &str) -> Result<Self, String> {
+        let public = b64url_decode(public_key)?;
+        let private = b64url_decode(private_key)?;
+        let key_pair = signature::EcdsaKeyPair::from_private_key_and_public_key(
+            &signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+            &private,
+            &public,
+            &SystemRandom::new(),
+        )
+        .map_err(|e| format!("VAPID keys are not a P-256 key pair: {}", e))?;
+        Ok(Self {
+            key_pair,
+            public_key: b64url_encode(&public),
+        })
+    }
+
+    /// For the browser's `applicationServerKey`
+    pub fn public_key(&self) -> &str {
+        &self.public_key
+    }
+
+    /// `Authorization` header value for a request to `endpoint`
+    pub fn authorization(&self, endpoint: &str, subject: &str) -> Result<String, String> {
+        let url = reqwest::Url::parse(endpoint).map_err(|e| e.to_string())?;
+        let audience = url.origin().ascii_serialization();
+        // Push services reject tokens valid for more than 24 hours
+        let expires = chrono::Utc::now().timestamp() + 12 * 3600;
+
+        let header = b64url_encode(br#"{"typ":"JWT","alg":"ES256"}"#);
+        let claims = b64url_encode(
+            serde_json::json!({ "aud": audience, "exp": expires, "sub": subject })
+                .to_string()
+                .as_bytes(),
+        );
+        let signing_input = format!("{}.{}", header, claims);
+        let sig = self
+            .key_pair
+            .sign(&SystemRandom::new(), signing_input.as_bytes())
+            .map_err(|_| "failed to sign VAPID token".to_string())?;
+        Ok(format!(
+            "vapid t={}.{}, k={}",
+            signing_input,
+            b64url_encode(sig.as_ref()),
+            self.public_key
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUBLIC_KEY: &str =
+        "BHwnUGOalhc4FPYxrY8RA0U-neolNJOe64T_to8WimDQNCMAhNQh2UBaBMrEcAMYYWZPHbfD6hpPtVvK2-MdcqQ";
+    const PRIVATE_KEY: &str = "1Lq140CmM32WA2WGrp2TKGbR2tTL2myQtUfSh-OAo64";
+
+    #[test]
+    fn encrypted_payload_decrypts_with_subscription_keys() {
+        let rng = SystemRandom::new();
+        let ua_private =
+            agreement::EphemeralPrivateKey::generate(&agreement::ECDH_P256, &rng).unwrap();
+        let ua_public = ua_private.compute_public_key().unwrap().as_ref().to_vec();
+        let auth = [7u8; 16];
+
+        let body = encrypt(b"{\"message\":\"hi\"}", &ua_public, &auth).unwrap();
+
+        // What the browser does on receipt
+        let (salt, rest) = body.split_at(16);
+        assert_eq!(&rest[..4], &RECORD_SIZE.to_be_bytes());
+        let id_len = rest[4] as usize;
+        let (as_public, ciphertext) = rest[5..].split_at(id_len);
+        let ecdh_secret = agreement::agree_ephemeral(
+            ua_private,
+            &agreement::UnparsedPublicKey::new(&agreement::ECDH_P256, as_public),
+            |secret| secret.to_vec(),
+        )
+        .unwrap();
+        let (cek, nonce) = derive_key_and_nonce(&ecdh_secret, &auth, &ua_public, as_public, salt);
+        let key = aead::LessSafeKey::new(aead::UnboundKey::new(&aead::AES_128_GCM, &cek).unwrap());
+        let mut record = ciphertext.to_vec();
+        let plaintext = key
+            .open_in_place(
+                aead::Nonce::try_assume_unique_for_key(&nonce).unwrap(),
+                aead::Aad::empty(),
+                &mut record,
+            )
+            .unwrap();
+        assert_eq!(plaintext, b"{\"message\":\"hi\"}\x02");
+
+        assert!(encrypt(&[0; MAX_PAYLOAD_BYTES + 1], &ua_public, &auth).is_err());
+        assert!(encrypt(b"x", &[4; 65], &auth).is_err());
+    }
+
+    #[test]
+    fn vapid_token_is_signed_for_the_endpoint_origin() {
+        let keys = VapidKeys::from_base64(PUBLIC_KEY, PRIVATE_KEY).unwrap();
+        let header = keys
+            .authorization(
+                "https://fcm.googleapis.com/fcm/send/abc",
+                "mailto:ops@example.com",
+            )
+            .unwrap();
+
+        let (token, key) = header
+            .strip_prefix("vapid t=")
+            .unwrap()
+            .split_once(", k=")
+            .unwrap();
+        assert_eq!(key, PUBLIC_KEY);
+        let (signing_input, sig) = token.rsplit_once('.').unwrap();
+        signature::UnparsedPublicKey::new(
+            &signature::ECDSA_P256_SHA256_FIXED,
+            b64url_decode(PUBLIC_KEY).unwrap(),
+        )
+        .verify(signing_input.as_bytes(), &b64url_decode(sig).unwrap())
+        .expect("signature should verify");
+
+        let claims: serde_json::Value = serde_json::from_slice(
+            &b64url_decode(signing_input.split('.').nth(1).unwrap()).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(claims["aud"], "https://fcm.googleapis.com");
+        assert_eq!(claims["sub"], "mailto:ops@example.com");
+
+        // A private key that doesn't match the public key is refused
+        assert!(VapidKeys::from_base64(PUBLIC_KEY, &b64url_encode(&[1; 32])).is_err());
+    }
+}
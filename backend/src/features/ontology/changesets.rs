@@ -0,0 +1,427 @@
+use super::models::*;
+use super::service::{OntologyError, OntologyService};
+use sqlx::types::Json;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+pub(super) const CARDINALITIES: &[&str] = &["one", "many"];
+
+/// Checks that don't need the target version; class names used as parents,
+/// references or relationship endpoints are resolved when applying
+pub fn validate_changes(changes: &SchemaChanges) -> Result<(), OntologyError> {
+    let invalid = |msg: String| Err(OntologyError::InvalidInput(msg));
+
+    if changes.classes.is_empty() && changes.relationship_types.is_empty() {
+        return invalid("changeset has no changes".to_string());
+    }
+
+    let mut class_names = HashSet::new();
+    for class in &changes.classes {
+        if class.name.trim().is_empty() {
+            return invalid("class name is required".to_string());
+        }
+        if !class_names.insert(class.name.as_str()) {
+            return invalid(format!("class '{}' appears more than once", class.name));
+        }
+
+        let mut property_names = HashSet::new();
+        for property in &class.properties {
+            if property.name.trim().is_empty() {
+                return invalid(format!("property of class '{}' has no name", class.name));
+            }
+            if !property_names.insert(property.name.as_str()) {
+                return invalid(format!(
+                    "property '{}.{}' appears more than once",
+                    class.name, property.name
+                ));
+            }
+            if !PROPERTY_DATA_TYPES.contains(&property.data_type.as_str()) {
+                return invalid(format!(
+                    "property '{}.{}' has unknown data type '{}'",
+                    class.name, property.name, property.data_type
+                ));
+            }
+            if property.data_type == "reference" && property.reference_class.is_none() {
+                return invalid(format!(
+                    "reference property '{}.{}' needs a reference_class",
+                    class.name, property.name
+                ));
+            }
+        }
+    }
+
+    let mut type_names = HashSet::new();
+    for rel_type in &changes.relationship_types {
+        if rel_type.name.trim().is_empty() {
+            return invalid("relationship type name is required".to_string());
+        }
+        if !type_names.insert(rel_type.name.as_str()) {
+            return invalid(format!(
+                "relationship type '{}' appears more than once",
+                rel_type.name
+            ));
+        }
+        for cardinality in [&rel_type.source_cardinality, &rel_type.target_cardinality]
+            .into_iter()
+            .flatten()
+        {
+            if !CARDINALITIES.contains(&cardinality.as_str()) {
+                return invalid(format!(
+                    "relationship type '{}' has unknown cardinality '{}'",
+                    rel_type.name, cardinality
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn resolve(classes: &HashMap<String, Uuid>, name: &str) -> Result<Uuid, OntologyError> {
+    classes.get(name).copied().ok_or_else(|| {
+        OntologyError::InvalidInput(format!(
+            "class '{}' is neither in the changeset nor the target version",
+            name
+        ))
+    })
+}
+
+impl OntologyService {
+    // ========================================================================
+    // CHANGESETS
+    // ========================================================================
+
+    pub async fn create_changeset(
+        &self,
+        source: &str,
+        title: &str,
+        rationale: Option<String>,
+        changes: SchemaChanges,
+        created_by: Option<Uuid>,
+    ) -> Result<OntologyChangeset, OntologyError> {
+        validate_changes(&changes)?;
+
+        let changeset = sqlx::query_as::<_, OntologyChangeset>(
+            r#"
+            INSERT INTO ontology_changesets (source, title, rationale, changes, created_by)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+            "#,
+        )
+        .bind(source)
+        .bind(title)
+        .bind(rationale)
+        .bind(Json(&changes))
+        .bind(created_by)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(changeset)
+    }
+
+    pub async fn list_changesets(
+        &self,
+        status: Option<&str>,
+    ) -> Result<Vec<OntologyChangeset>, OntologyError> {
+        let changesets = sqlx::query_as::<_, OntologyChangeset>(
+            r#"
+            SELECT * FROM ontology_changesets
+            WHERE ($1::text IS NULL OR status = $1)
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(status)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(changesets)
+    }
+
+    pub async fn get_changeset(&self, id: Uuid) -> Result<OntologyChangeset, OntologyError> {
+        sqlx::query_as::<_, OntologyChangeset>("SELECT * FROM ontology_changesets WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| OntologyError::NotFound(format!("Changeset {} not found", id)))
+    }
+
+    /// Edit a draft during review
+    pub async fn update_changeset(
+        &self,
+        id: Uuid,
+        input: UpdateChangesetInput,
+    ) -> Result<OntologyChangeset, OntologyError> {
+        if let Some(changes) = &input.changes {
+            validate_changes(changes)?;
+        }
+
+        let changeset = sqlx::query_as::<_, OntologyChangeset>(
+            r#"
+            UPDATE ontology_changesets SET
+                title = COALESCE($2, title),
+                changes = COALESCE($3, changes),
+                updated_at = NOW()
+            WHERE id = $1 AND status = 'draft'
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(&input.title)
+        .bind(input.changes.as_ref().map(Json))
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match changeset {
+            Some(changeset) => Ok(changeset),
+            None => Err(self.not_draft(id).await),
+        }
+    }
+
+    pub async fn discard_changeset(&self, id: Uuid) -> Result<OntologyChangeset, OntologyError> {
+        let changeset = sqlx::query_as::<_, OntologyChangeset>(
+            r#"
+            UPDATE ontology_changesets SET status = 'discarded', updated_at = NOW()
+            WHERE id = $1 AND status = 'draft'
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match changeset {
+            Some(changeset) => Ok(changeset),
+            None => Err(self.not_draft(id).await),
+        }
+    }
+
+    /// Why a draft-only operation matched nothing
+    async fn not_draft(&self, id: Uuid) -> OntologyError {
+        match self.get_changeset(id).await {
+            Ok(changeset) => OntologyError::VersionConflict(format!(
+                "Changeset {} is {} and can no longer be changed",
+                id, changeset.status
+            )),
+            Err(e) => e,
+        }
+    }
+
+    /// Apply a draft changeset to a DRAFT version in one transaction. Classes
+    /// that already exist in the version by name are extended rather than duplicated.
+    pub async fn apply_changeset(
+        &self,
+        id: Uuid,
+        version_id: Uuid,
+        user_id: Option<Uuid>,
+    ) -> Result<ChangesetApplyResult, OntologyError> {
+        self.ensure_version_mutable(version_id).await?;
+
+        let mut tx = self.pool.begin().await?;
+
+        let changeset = sqlx::query_as::<_, OntologyChangeset>(
+            "SELECT * FROM ontology_changesets WHERE id = $1 FOR UPDATE",
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| OntologyError::NotFound(format!("Changeset {} not found", id)))?;
+        if changeset.status != "draft" {
+            return Err(OntologyError::VersionConflict(format!(
+                "Changeset {} is {} and can no longer be applied",
+                id, changeset.status
+            )));
+        }
+        let changes = &changeset.changes.0;
+        validate_changes(changes)?;
+
+        let mut classes: HashMap<String, Uuid> = sqlx::query_as::<_, (String, Uuid)>(
+            "SELECT name, id FROM classes WHERE version_id = $1 AND tenant_id IS NULL",
+        )
+        .bind(version_id)
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .collect();
+
+        let mut classes_created = 0;
+        let mut created = HashSet::new();
+        for class in &changes.classes {
+            if classes.contains_key(&class.name) {
+                continue;
+            }
+            let class_id = sqlx::query_scalar::<_, Uuid>(
+                r#"
+                INSERT INTO classes (name, description, version_id)
+                VALUES ($1, $2, $3)
+                RETURNING id
+                "#,
+            )
+            .bind(&class.name)
+            .bind(&class.description)
+            .bind(version_id)
+            .fetch_one(&mut *tx)
+            .await?;
+            classes.insert(class.name.clone(), class_id);
+            created.insert(class_id);
+            classes_created += 1;
+        }
+
+        let mut properties_created = 0;
+        for class in &changes.classes {
+            let class_id = classes[&class.name];
+
+            // Parents are only set on new classes; existing hierarchies are left alone
+            if let Some(parent) = &class.parent_class {
+                let parent_id = resolve(&classes, parent)?;
+                if created.contains(&class_id) {
+                    sqlx::query("UPDATE classes SET parent_class_id = $2 WHERE id = $1")
+                        .bind(class_id)
+                        .bind(parent_id)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+            }
+
+            for property in &class.properties {
+                let reference_class_id = property
+                    .reference_class
+                    .as_deref()
+                    .map(|name| resolve(&classes, name))
+                    .transpose()?;
+                let result = sqlx::query(
+                    r#"
+                    INSERT INTO properties (name, description, class_id, data_type,
+                                            reference_class_id, is_required, version_id)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7)
+                    ON CONFLICT (name, class_id) DO NOTHING
+                    "#,
+                )
+                .bind(&property.name)
+                .bind(&property.description)
+                .bind(class_id)
+                .bind(&property.data_type)
+                .bind(reference_class_id)
+                .bind(property.is_required)
+                .bind(version_id)
+                .execute(&mut *tx)
+                .await?;
+                properties_created += result.rows_affected() as usize;
+            }
+        }
+
+        let mut relationship_types_created = 0;
+        for rel_type in &changes.relationship_types {
+            let source_id = rel_type
+                .source_class
+                .as_deref()
+                .map(|name| resolve(&classes, name))
+                .transpose()?;
+            let target_id = rel_type
+                .target_class
+                .as_deref()
+                .map(|name| resolve(&classes, name))
+                .transpose()?;
+            let result = sqlx::query(
+                r#"
+                INSERT INTO relationship_types (name, description, source_cardinality, target_cardinality,
+                                                allowed_source_class_id, allowed_target_class_id)
+                VALUES ($1, $2, COALESCE($3, 'many'), COALESCE($4, 'many'), $5, $6)
+                ON CONFLICT (name) DO NOTHING
+                "#,
+            )
+            .bind(&rel_type.name)
+            .bind(&rel_type.description)
+            .bind(&rel_type.source_cardinality)
+            .bind(&rel_type.target_cardinality)
+            .bind(source_id)
+            .bind(target_id)
+            .execute(&mut *tx)
+            .await?;
+            relationship_types_created += result.rows_affected() as usize;
+        }
+
+        let changeset = sqlx::query_as::<_, OntologyChangeset>(
+            r#"
+            UPDATE ontology_changesets SET
+                status = 'applied', applied_version_id = $2, applied_by = $3,
+                applied_at = NOW(), updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(version_id)
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        self.invalidate_cache().await;
+
+        let result = ChangesetApplyResult {
+            changeset,
+            classes_created,
+            properties_created,
+            relationship_types_created,
+        };
+
+        if let Some(uid) = user_id {
+            let _ = self
+                .audit_service
+                .log(
+                    uid,
+                    "ontology.changeset.apply",
+                    "ontology_changeset",
+                    Some(id),
+                    None,
+                    Some(serde_json::to_value(&result).unwrap_or(serde_json::Value::Null)),
+                    None,
+                )
+                .await;
+        }
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn changes(value: serde_json::Value) -> SchemaChanges {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_valid_changes() {
+        let changes = changes(json!({
+            "classes": [{
+                "name": "Mission",
+                "properties": [
+                    { "name": "code", "data_type": "string", "is_required": true },
+                    { "name": "lead", "data_type": "reference", "reference_class": "Person" }
+                ]
+            }],
+            "relationship_types": [{ "name": "assigned_to", "source_class": "Mission", "target_cardinality": "one" }]
+        }));
+        assert!(validate_changes(&changes).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_changes() {
+        for value in [
+            json!({}),
+            json!({ "classes": [{ "name": "A" }, { "name": "A" }] }),
+            json!({ "classes": [{ "name": "A", "properties": [{ "name": "x", "data_type": "varchar" }] }] }),
+            json!({ "classes": [{ "name": "A", "properties": [{ "name": "x", "data_type": "reference" }] }] }),
+            json!({ "relationship_types": [{ "name": "r", "source_cardinality": "several" }] }),
+        ] {
+            assert!(
+                matches!(
+                    validate_changes(&changes(value.clone())),
+                    Err(OntologyError::InvalidInput(_))
+                ),
+                "{value}"
+            );
+        }
+    }
+}
@@ -0,0 +1,1249 @@
+use super::changesets::CARDINALITIES;
+use super::models::*;
+use super::service::{OntologyError, OntologyService};
+use super::transfer::parents_first;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::{FromRow, Postgres, Transaction};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use uuid::Uuid;
+
+/// Serializes applies, so two pipelines can't interleave their changes
+const APPLY_LOCK: &str = "ontology.desired_state";
+
+/// The parts of the ontology a desired state describes, as they are now
+#[derive(Debug, Default)]
+pub(super) struct CurrentState {
+    version: String,
+    version_id: Uuid,
+    classes: BTreeMap<String, CurrentClass>,
+    /// Classes of the system ontology, which parents and references may use
+    system_classes: HashMap<String, Uuid>,
+    relationship_types: BTreeMap<String, CurrentRelationshipType>,
+    permission_types: BTreeMap<String, CurrentAccessEntity>,
+    roles: BTreeMap<String, CurrentAccessEntity>,
+    /// Permission types each role is allowed, by name
+    grants: BTreeMap<String, BTreeSet<String>>,
+}
+
+#[derive(Debug, Clone)]
+struct CurrentClass {
+    id: Uuid,
+    description: Option<String>,
+    parent: Option<String>,
+    is_abstract: bool,
+    is_deprecated: bool,
+    properties: BTreeMap<String, CurrentProperty>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+struct CurrentProperty {
+    id: Uuid,
+    class_id: Uuid,
+    name: String,
+    description: Option<String>,
+    data_type: String,
+    reference_class: Option<String>,
+    is_required: bool,
+    is_unique: bool,
+    is_indexed: bool,
+    is_sensitive: bool,
+    default_value: Option<serde_json::Value>,
+    validation_rules: Option<serde_json::Value>,
+    is_deprecated: bool,
+}
+
+#[derive(Debug, Clone, FromRow)]
+struct CurrentRelationshipType {
+    id: Uuid,
+    name: String,
+    description: Option<String>,
+    source_class: Option<String>,
+    target_class: Option<String>,
+    source_cardinality: Option<String>,
+    target_cardinality: Option<String>,
+    grants_permission_inheritance: bool,
+}
+
+/// A role or permission type, stored as an entity of the system class
+#[derive(Debug, Clone, FromRow)]
+struct CurrentAccessEntity {
+    id: Uuid,
+    name: String,
+    description: Option<String>,
+    level: i32,
+}
+
+impl OntologyService {
+    // ========================================================================
+    // DESIRED STATE
+    // ========================================================================
+
+    /// What applying `desired` would change in the current version, without
+    /// changing anything
+    pub async fn plan_desired_state(
+        &self,
+        desired: &DesiredState,
+        prune: bool,
+    ) -> Result<DesiredStatePlan, OntologyError> {
+        let mut tx = self.pool.begin().await?;
+        let current = load_current_state(&mut tx).await?;
+        tx.rollback().await?;
+        build_plan(desired, &current, prune)
+    }
+
+    /// Plan and apply `desired` in one transaction, returning the plan that
+    /// was applied. With a `fingerprint` from an earlier plan, nothing is
+    /// changed unless the plan is still the same. The current version is
+    /// changed in place; classes and properties are only ever deprecated,
+    /// and relationship types, roles and permission types are never removed.
+    pub async fn apply_desired_state(
+        &self,
+        desired: &DesiredState,
+        prune: bool,
+        fingerprint: Option<&str>,
+        user_id: Option<Uuid>,
+    ) -> Result<DesiredStatePlan, OntologyError> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+            .bind(APPLY_LOCK)
+            .execute(&mut *tx)
+            .await?;
+        let current = load_current_state(&mut tx).await?;
+        let mut plan = build_plan(desired, &current, prune)?;
+        if let Some(expected) = fingerprint {
+            if expected != plan.fingerprint {
+                return Err(OntologyError::VersionConflict(
+                    "The ontology has changed since the plan was made; plan again".to_string(),
+                ));
+            }
+        }
+
+        execute_plan(&mut tx, desired, &current, &plan.changes, user_id).await?;
+        tx.commit().await?;
+        self.invalidate_cache().await;
+        plan.applied = true;
+
+        if let Some(uid) = user_id {
+            let _ = self
+                .audit_service
+                .log(
+                    uid,
+                    "ontology.desired_state.apply",
+                    "ontology_version",
+                    Some(current.version_id),
+                    None,
+                    Some(serde_json::to_value(&plan).unwrap_or(serde_json::Value::Null)),
+                    None,
+                )
+                .await;
+        }
+
+        Ok(plan)
+    }
+}
+
+async fn load_current_state(
+    tx: &mut Transaction<'_, Postgres>,
+) -> Result<CurrentState, OntologyError> {
+    let (version_id, version) = sqlx::query_as::<_, (Uuid, String)>(
+        "SELECT id, version FROM ontology_versions WHERE is_current = TRUE",
+    )
+    .fetch_optional(&mut **tx)
+    .await?
+    .ok_or_else(|| OntologyError::NotFound("No current version found".to_string()))?;
+
+    let mut state = CurrentState {
+        version,
+        version_id,
+        ..Default::default()
+    };
+
+    let classes = sqlx::query_as::<_, (Uuid, String, Option<String>, Option<String>, bool, bool)>(
+        r#"
+        SELECT c.id, c.name, c.description, p.name, c.is_abstract, c.is_deprecated
+        FROM classes c
+        LEFT JOIN classes p ON p.id = c.parent_class_id
+        WHERE c.version_id = $1 AND c.tenant_id IS NULL
+        ORDER BY c.name, c.created_at
+        "#,
+    )
+    .bind(version_id)
+    .fetch_all(&mut **tx)
+    .await?;
+    let mut names_by_id = HashMap::new();
+    for (id, name, description, parent, is_abstract, is_deprecated) in classes {
+        if state.classes.contains_key(&name) {
+            continue;
+        }
+        names_by_id.insert(id, name.clone());
+        state.classes.insert(
+            name,
+            CurrentClass {
+                id,
+                description,
+                parent,
+                is_abstract,
+                is_deprecated,
+                properties: BTreeMap::new(),
+            },
+        );
+    }
+
+    let class_ids: Vec<Uuid> = names_by_id.keys().copied().collect();
+    let properties = sqlx::query_as::<_, CurrentProperty>(
+        r#"
+        SELECT p.id, p.class_id, p.name, p.description, p.data_type, rc.name AS reference_class,
+               p.is_required, p.is_unique, p.is_indexed, p.is_sensitive,
+               p.default_value, p.validation_rules, p.is_deprecated
+        FROM properties p
+        LEFT JOIN classes rc ON rc.id = p.reference_class_id
+        WHERE p.class_id = ANY($1)
+        ORDER BY p.name, p.created_at
+        "#,
+    )
+    .bind(&class_ids)
+    .fetch_all(&mut **tx)
+    .await?;
+    for property in properties {
+        let class = names_by_id
+            .get(&property.class_id)
+            .and_then(|name| state.classes.get_mut(name));
+        if let Some(class) = class {
+            class
+                .properties
+                .entry(property.name.clone())
+                .or_insert(property);
+        }
+    }
+
+    state.system_classes = sqlx::query_as::<_, (String, Uuid)>(
+        r#"
+        SELECT c.name, c.id FROM classes c
+        JOIN ontology_versions ov ON ov.id = c.version_id
+        WHERE ov.is_system AND c.tenant_id IS NULL
+        "#,
+    )
+    .fetch_all(&mut **tx)
+    .await?
+    .into_iter()
+    .collect();
+
+    state.relationship_types = sqlx::query_as::<_, CurrentRelationshipType>(
+        r#"
+        SELECT rt.id, rt.name, rt.description, sc.name AS source_class, tc.name AS target_class,
+               rt.source_cardinality, rt.target_cardinality, rt.grants_permission_inheritance
+        FROM relationship_types rt
+        LEFT JOIN classes sc ON sc.id = rt.allowed_source_class_id
+        LEFT JOIN classes tc ON tc.id = rt.allowed_target_class_id
+        "#,
+    )
+    .fetch_all(&mut **tx)
+    .await?
+    .into_iter()
+    .map(|rt| (rt.name.clone(), rt))
+    .collect();
+
+    state.permission_types = load_access_entities(tx, &state, "Permission").await?;
+    state.roles = load_access_entities(tx, &state, "Role").await?;
+
+    if let (Some(grants_type), Some(permission_class)) = (
+        state.relationship_types.get("grants_permission"),
+        state.system_classes.get("Permission"),
+    ) {
+        let role_ids: Vec<Uuid> = state.roles.values().map(|r| r.id).collect();
+        let grants = sqlx::query_as::<_, (Uuid, String)>(
+            r#"
+            SELECT r.source_entity_id, p.display_name
+            FROM relationships r
+            JOIN entities p ON p.id = r.target_entity_id
+                AND p.class_id = $2 AND p.deleted_at IS NULL
+            WHERE r.relationship_type_id = $1
+              AND r.source_entity_id = ANY($3)
+              AND COALESCE(r.metadata->>'effect', 'ALLOW') = 'ALLOW'
+            "#,
+        )
+        .bind(grants_type.id)
+        .bind(permission_class)
+        .bind(&role_ids)
+        .fetch_all(&mut **tx)
+        .await?;
+        let role_names: HashMap<Uuid, &str> = state
+            .roles
+            .values()
+            .map(|r| (r.id, r.name.as_str()))
+            .collect();
+        for (role_id, permission) in grants {
+            if let Some(role) = role_names.get(&role_id) {
+                state
+                    .grants
+                    .entry(role.to_string())
+                    .or_default()
+                    .insert(permission);
+            }
+        }
+    }
+
+    Ok(state)
+}
+
+/// Shared (non-tenant) live entities of a system class, by name
+async fn load_access_entities(
+    tx: &mut Transaction<'_, Postgres>,
+    state: &CurrentState,
+    class_name: &str,
+) -> Result<BTreeMap<String, CurrentAccessEntity>, OntologyError> {
+    let class_id = system_class(state, class_name)?;
+    let entities = sqlx::query_as::<_, CurrentAccessEntity>(
+        r#"
+        SELECT id, display_name AS name, attributes->>'description' AS description,
+               COALESCE((attributes->>'level')::numeric, 0)::int AS level
+        FROM entities
+        WHERE class_id = $1 AND tenant_id IS NULL AND deleted_at IS NULL
+        ORDER BY created_at
+        "#,
+    )
+    .bind(class_id)
+    .fetch_all(&mut **tx)
+    .await?;
+    let mut by_name = BTreeMap::new();
+    for entity in entities {
+        by_name.entry(entity.name.clone()).or_insert(entity);
+    }
+    Ok(by_name)
+}
+
+fn system_class(state: &CurrentState, name: &str) -> Result<Uuid, OntologyError> {
+    state
+        .system_classes
+        .get(name)
+        .copied()
+        .ok_or_else(|| OntologyError::NotFound(format!("System class '{}' not found", name)))
+}
+
+// ============================================================================
+// PLANNING
+// ============================================================================
+
+fn build_plan(
+    desired: &DesiredState,
+    current: &CurrentState,
+    prune: bool,
+) -> Result<DesiredStatePlan, OntologyError> {
+    let changes = plan_changes(desired, current, prune)?;
+    let mut hasher = Sha256::new();
+    hasher.update(current.version.as_bytes());
+    hasher.update(serde_json::to_vec(&changes).unwrap_or_default());
+    Ok(DesiredStatePlan {
+        version: current.version.clone(),
+        changes,
+        fingerprint: hex::encode(hasher.finalize()),
+        applied: false,
+    })
+}
+
+/// Record `field` if it differs
+fn compare<T: Serialize + PartialEq>(fields: &mut Vec<FieldChange>, field: &str, from: &T, to: &T) {
+    if from != to {
+        fields.push(FieldChange {
+            field: field.to_string(),
+            from: serde_json::to_value(from).unwrap_or_default(),
+            to: serde_json::to_value(to).unwrap_or_default(),
+        });
+    }
+}
+
+fn change(
+    action: PlanAction,
+    object: PlanObject,
+    owner: Option<&str>,
+    name: &str,
+    fields: Vec<FieldChange>,
+) -> PlannedChange {
+    PlannedChange {
+        action,
+        object,
+        owner: owner.map(str::to_string),
+        name: name.to_string(),
+        fields,
+    }
+}
+
+fn invalid<T>(msg: String) -> Result<T, OntologyError> {
+    Err(OntologyError::InvalidInput(msg))
+}
+
+/// Reject names that appear twice or are empty
+fn unique_names<'a>(
+    what: &str,
+    names: impl Iterator<Item = &'a str>,
+) -> Result<HashSet<&'a str>, OntologyError> {
+    let mut seen = HashSet::new();
+    for name in names {
+        if name.trim().is_empty() {
+            return invalid(format!("{} names must not be empty", what));
+        }
+        if !seen.insert(name) {
+            return invalid(format!("{} '{}' is defined more than once", what, name));
+        }
+    }
+    Ok(seen)
+}
+
+/// The changes that make the current version match `desired`, in the order
+/// they are applied: permission types, classes (parents first), properties,
+/// relationship types, roles, grants, then deprecations.
+pub(super) fn plan_changes(
+    desired: &DesiredState,
+    current: &CurrentState,
+    prune: bool,
+) -> Result<Vec<PlannedChange>, OntologyError> {
+    let ordered = parents_first(&desired.classes)?;
+    let class_names: HashSet<&str> = desired.classes.iter().map(|c| c.name.as_str()).collect();
+    let known_class = |name: &str| {
+        class_names.contains(name)
+            || current.classes.contains_key(name)
+            || current.system_classes.contains_key(name)
+    };
+    let check_class = |owner: &str, name: &Option<String>| match name {
+        Some(name) if !known_class(name) => {
+            invalid(format!("{} refers to unknown class '{}'", owner, name))
+        }
+        _ => Ok(()),
+    };
+    unique_names(
+        "Relationship type",
+        desired.relationship_types.iter().map(|t| t.name.as_str()),
+    )?;
+    let permission_names = unique_names(
+        "Permission type",
+        desired.permission_types.iter().map(|p| p.name.as_str()),
+    )?;
+    unique_names("Role", desired.roles.iter().map(|r| r.name.as_str()))?;
+
+    let mut changes = Vec::new();
+
+    for permission in &desired.permission_types {
+        match current.permission_types.get(&permission.name) {
+            None => changes.push(change(
+                PlanAction::Create,
+                PlanObject::PermissionType,
+                None,
+                &permission.name,
+                vec![],
+            )),
+            Some(existing) => {
+                let mut fields = Vec::new();
+                compare(
+                    &mut fields,
+                    "description",
+                    &existing.description,
+                    &permission.description,
+                );
+                compare(&mut fields, "level", &existing.level, &permission.level);
+                if !fields.is_empty() {
+                    changes.push(change(
+                        PlanAction::Update,
+                        PlanObject::PermissionType,
+                        None,
+                        &permission.name,
+                        fields,
+                    ));
+                }
+            }
+        }
+    }
+
+    for class in &ordered {
+        check_class(&format!("Class '{}'", class.name), &class.parent)?;
+        if !current.classes.contains_key(&class.name) {
+            changes.push(change(
+                PlanAction::Create,
+                PlanObject::Class,
+                None,
+                &class.name,
+                vec![],
+            ));
+        }
+    }
+    for class in &ordered {
+        let Some(existing) = current.classes.get(&class.name) else {
+            continue;
+        };
+        let mut fields = Vec::new();
+        compare(
+            &mut fields,
+            "description",
+            &existing.description,
+            &class.description,
+        );
+        compare(&mut fields, "parent", &existing.parent, &class.parent);
+        compare(
+            &mut fields,
+            "is_abstract",
+            &existing.is_abstract,
+            &class.is_abstract,
+        );
+        compare(
+            &mut fields,
+            "is_deprecated",
+            &existing.is_deprecated,
+            &false,
+        );
+        if !fields.is_empty() {
+            changes.push(change(
+                PlanAction::Update,
+                PlanObject::Class,
+                None,
+                &class.name,
+                fields,
+            ));
+        }
+    }
+
+    for class in &ordered {
+        let existing = current.classes.get(&class.name);
+        for property in &class.properties {
+            if !PROPERTY_DATA_TYPES.contains(&property.data_type.as_str()) {
+                return invalid(format!(
+                    "Property '{}.{}' has unknown data type '{}'",
+                    class.name, property.name, property.data_type
+                ));
+            }
+            if property.data_type == "reference" && property.reference_class.is_none() {
+                return invalid(format!(
+                    "Reference property '{}.{}' needs a reference_class",
+                    class.name, property.name
+                ));
+            }
+            check_class(
+                &format!("Property '{}.{}'", class.name, property.name),
+                &property.reference_class,
+            )?;
+
+            match existing.and_then(|c| c.properties.get(&property.name)) {
+                None => changes.push(change(
+                    PlanAction::Create,
+                    PlanObject::Property,
+                    Some(&class.name),
+                    &property.name,
+                    vec![],
+                )),
+                Some(old) => {
+                    let mut fields = Vec::new();
+                    compare(
+                        &mut fields,
+                        "description",
+                        &old.description,
+                        &property.description,
+                    );
+                    compare(
+                        &mut fields,
+                        "data_type",
+                        &old.data_type,
+                        &property.data_type,
+                    );
+                    compare(
+                        &mut fields,
+                        "reference_class",
+                        &old.reference_class,
+                        &property.reference_class,
+                    );
+                    compare(
+                        &mut fields,
+                        "is_required",
+                        &old.is_required,
+                        &property.is_required,
+                    );
+                    compare(
+                        &mut fields,
+                        "is_unique",
+                        &old.is_unique,
+                        &property.is_unique,
+                    );
+                    compare(
+                        &mut fields,
+                        "is_indexed",
+                        &old.is_indexed,
+                        &property.is_indexed,
+                    );
+                    compare(
+                        &mut fields,
+                        "is_sensitive",
+                        &old.is_sensitive,
+                        &property.is_sensitive,
+                    );
+                    compare(
+                        &mut fields,
+                        "default_value",
+                        &old.default_value,
+                        &property.default_value,
+                    );
+                    compare(
+                        &mut fields,
+                        "validation_rules",
+                        &old.validation_rules,
+                        &property.validation_rules,
+                    );
+                    compare(&mut fields, "is_deprecated", &old.is_deprecated, &false);
+                    if !fields.is_empty() {
+                        changes.push(change(
+                            PlanAction::Update,
+                            PlanObject::Property,
+                            Some(&class.name),
+                            &property.name,
+                            fields,
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    for rel_type in &desired.relationship_types {
+        let owner = format!("Relationship type '{}'", rel_type.name);
+        check_class(&owner, &rel_type.source_class)?;
+        check_class(&owner, &rel_type.target_class)?;
+        let source_cardinality = cardinality(&owner, &rel_type.source_cardinality)?;
+        let target_cardinality = cardinality(&owner, &rel_type.target_cardinality)?;
+
+        match current.relationship_types.get(&rel_type.name) {
+            None => changes.push(change(
+                PlanAction::Create,
+                PlanObject::RelationshipType,
+                None,
+                &rel_type.name,
+                vec![],
+            )),
+            Some(old) => {
+                let mut fields = Vec::new();
+                compare(
+                    &mut fields,
+                    "description",
+                    &old.description,
+                    &rel_type.description,
+                );
+                compare(
+                    &mut fields,
+                    "source_class",
+                    &old.source_class,
+                    &rel_type.source_class,
+                );
+                compare(
+                    &mut fields,
+                    "target_class",
+                    &old.target_class,
+                    &rel_type.target_class,
+                );
+                compare(
+                    &mut fields,
+                    "source_cardinality",
+                    &old.source_cardinality.as_deref().unwrap_or("many"),
+                    &source_cardinality,
+                );
+                compare(
+                    &mut fields,
+                    "target_cardinality",
+                    &old.target_cardinality.as_deref().unwrap_or("many"),
+                    &target_cardinality,
+                );
+                compare(
+                    &mut fields,
+                    "grants_permission_inheritance",
+                    &old.grants_permission_inheritance,
+                    &rel_type.grants_permission_inheritance,
+                );
+                if !fields.is_empty() {
+                    changes.push(change(
+                        PlanAction::Update,
+                        PlanObject::RelationshipType,
+                        None,
+                        &rel_type.name,
+                        fields,
+                    ));
+                }
+            }
+        }
+    }
+
+    for role in &desired.roles {
+        match current.roles.get(&role.name) {
+            None => changes.push(change(
+                PlanAction::Create,
+                PlanObject::Role,
+                None,
+                &role.name,
+                vec![],
+            )),
+            Some(existing) => {
+                let mut fields = Vec::new();
+                compare(
+                    &mut fields,
+                    "description",
+                    &existing.description,
+                    &role.description,
+                );
+                compare(&mut fields, "level", &existing.level, &role.level);
+                if !fields.is_empty() {
+                    changes.push(change(
+                        PlanAction::Update,
+                        PlanObject::Role,
+                        None,
+                        &role.name,
+                        fields,
+                    ));
+                }
+            }
+        }
+    }
+
+    for role in &desired.roles {
+        let Some(permissions) = &role.permissions else {
+            continue;
+        };
+        let wanted = unique_names(
+            &format!("Permission of role '{}'", role.name),
+            permissions.iter().map(String::as_str),
+        )?;
+        let granted = current.grants.get(&role.name);
+        for permission in permissions {
+            if !permission_names.contains(permission.as_str())
+                && !current.permission_types.contains_key(permission)
+            {
+                return invalid(format!(
+                    "Role '{}' refers to unknown permission type '{}'",
+                    role.name, permission
+                ));
+            }
+            if !granted.is_some_and(|g| g.contains(permission)) {
+                changes.push(change(
+                    PlanAction::Create,
+                    PlanObject::RolePermission,
+                    Some(&role.name),
+                    permission,
+                    vec![],
+                ));
+            }
+        }
+        for permission in granted.into_iter().flatten() {
+            if !wanted.contains(permission.as_str()) {
+                changes.push(change(
+                    PlanAction::Remove,
+                    PlanObject::RolePermission,
+                    Some(&role.name),
+                    permission,
+                    vec![],
+                ));
+            }
+        }
+    }
+
+    if prune {
+        let desired_classes: HashMap<&str, &ExportedClass> = desired
+            .classes
+            .iter()
+            .map(|c| (c.name.as_str(), c))
+            .collect();
+        for (name, class) in &current.classes {
+            match desired_classes.get(name.as_str()) {
+                // A deprecated class takes its properties with it
+                None if !class.is_deprecated => changes.push(change(
+                    PlanAction::Deprecate,
+                    PlanObject::Class,
+                    None,
+                    name,
+                    vec![],
+                )),
+                None => {}
+                Some(wanted) => {
+                    for (property_name, property) in &class.properties {
+                        let kept = wanted.properties.iter().any(|p| &p.name == property_name);
+                        if !kept && !property.is_deprecated {
+                            changes.push(change(
+                                PlanAction::Deprecate,
+                                PlanObject::Property,
+                                Some(name),
+                                property_name,
+                                vec![],
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(changes)
+}
+
+fn cardinality<'a>(owner: &str, value: &'a Option<String>) -> Result<&'a str, OntologyError> {
+    let value = value.as_deref().unwrap_or("many");
+    if CARDINALITIES.contains(&value) {
+        Ok(value)
+    } else {
+        invalid(format!("{} has unknown cardinality '{}'", owner, value))
+    }
+}
+
+// ============================================================================
+// APPLYING
+// ============================================================================
+
+async fn execute_plan(
+    tx: &mut Transaction<'_, Postgres>,
+    desired: &DesiredState,
+    current: &CurrentState,
+    changes: &[PlannedChange],
+    user_id: Option<Uuid>,
+) -> Result<(), OntologyError> {
+    let classes: HashMap<&str, &ExportedClass> = desired
+        .classes
+        .iter()
+        .map(|c| (c.name.as_str(), c))
+        .collect();
+    let rel_types: HashMap<&str, &DesiredRelationshipType> = desired
+        .relationship_types
+        .iter()
+        .map(|t| (t.name.as_str(), t))
+        .collect();
+    let permissions: HashMap<&str, &DesiredPermissionType> = desired
+        .permission_types
+        .iter()
+        .map(|p| (p.name.as_str(), p))
+        .collect();
+    let roles: HashMap<&str, &DesiredRole> =
+        desired.roles.iter().map(|r| (r.name.as_str(), r)).collect();
+
+    // Ids by name, growing as things are created
+    let mut class_ids: HashMap<String, Uuid> = current.system_classes.clone();
+    class_ids.extend(current.classes.iter().map(|(n, c)| (n.clone(), c.id)));
+    let mut permission_ids: HashMap<String, Uuid> = current
+        .permission_types
+        .iter()
+        .map(|(n, p)| (n.clone(), p.id))
+        .collect();
+    let mut role_ids: HashMap<String, Uuid> = current
+        .roles
+        .iter()
+        .map(|(n, r)| (n.clone(), r.id))
+        .collect();
+    let class_id = |ids: &HashMap<String, Uuid>, name: &Option<String>| -> Option<Uuid> {
+        name.as_ref().and_then(|n| ids.get(n).copied())
+    };
+
+    for planned in changes {
+        let name = planned.name.as_str();
+        match (planned.object, planned.action) {
+            (PlanObject::PermissionType, action) | (PlanObject::Role, action) => {
+                let (class_name, description, level, ids) = match planned.object {
+                    PlanObject::Role => {
+                        let role = roles[name];
+                        ("Role", &role.description, role.level, &mut role_ids)
+                    }
+                    _ => {
+                        let permission = permissions[name];
+                        (
+                            "Permission",
+                            &permission.description,
+                            permission.level,
+                            &mut permission_ids,
+                        )
+                    }
+                };
+                let attributes = serde_json::json!({
+                    "name": name,
+                    "description": description,
+                    "level": level,
+                });
+                if action == PlanAction::Create {
+                    let id = sqlx::query_scalar::<_, Uuid>(
+                        r#"
+                        INSERT INTO entities (class_id, display_name, attributes, approval_status,
+                                              created_by, updated_by)
+                        VALUES ($1, $2, $3, 'APPROVED', $4, $4)
+                        RETURNING id
+                        "#,
+                    )
+                    .bind(system_class(current, class_name)?)
+                    .bind(name)
+                    .bind(attributes)
+                    .bind(user_id)
+                    .fetch_one(&mut **tx)
+                    .await?;
+                    ids.insert(name.to_string(), id);
+                } else {
+                    sqlx::query(
+                        r#"
+                        UPDATE entities SET attributes = attributes || $2, updated_by = $3,
+                                            updated_at = NOW()
+                        WHERE id = $1
+                        "#,
+                    )
+                    .bind(ids[name])
+                    .bind(attributes)
+                    .bind(user_id)
+                    .execute(&mut **tx)
+                    .await?;
+                }
+            }
+            (PlanObject::Class, PlanAction::Create) => {
+                let class = classes[name];
+                let id = sqlx::query_scalar::<_, Uuid>(
+                    r#"
+                    INSERT INTO classes (name, description, parent_class_id, version_id, is_abstract)
+                    VALUES ($1, $2, $3, $4, $5)
+                    RETURNING id
+                    "#,
+                )
+                .bind(name)
+                .bind(&class.description)
+                .bind(class_id(&class_ids, &class.parent))
+                .bind(current.version_id)
+                .bind(class.is_abstract)
+                .fetch_one(&mut **tx)
+                .await?;
+                class_ids.insert(name.to_string(), id);
+            }
+            (PlanObject::Class, PlanAction::Update) => {
+                let class = classes[name];
+                sqlx::query(
+                    r#"
+                    UPDATE classes SET
+                        description = $2,
+                        parent_class_id = $3,
+                        is_abstract = $4,
+                        is_deprecated = FALSE,
+                        deprecated_at = NULL,
+                        updated_at = NOW()
+                    WHERE id = $1
+                    "#,
+                )
+                .bind(class_ids[name])
+                .bind(&class.description)
+                .bind(class_id(&class_ids, &class.parent))
+                .bind(class.is_abstract)
+                .execute(&mut **tx)
+                .await?;
+            }
+            (PlanObject::Class, _) => {
+                sqlx::query(
+                    r#"
+                    UPDATE classes SET is_deprecated = TRUE, deprecated_at = NOW(), updated_at = NOW()
+                    WHERE id = $1
+                    "#,
+                )
+                .bind(class_ids[name])
+                .execute(&mut **tx)
+                .await?;
+            }
+            (PlanObject::Property, action) => {
+                let owner = planned.owner.as_deref().unwrap_or_default();
+                let existing = current
+                    .classes
+                    .get(owner)
+                    .and_then(|c| c.properties.get(name));
+                if action == PlanAction::Deprecate {
+                    sqlx::query(
+                        r#"
+                        UPDATE properties SET is_deprecated = TRUE, deprecated_at = NOW(),
+                                              updated_at = NOW()
+                        WHERE id = $1
+                        "#,
+                    )
+                    .bind(existing.map(|p| p.id))
+                    .execute(&mut **tx)
+                    .await?;
+                    continue;
+                }
+
+                let property = classes[owner]
+                    .properties
+                    .iter()
+                    .find(|p| p.name == name)
+                    .expect("planned from the document");
+                sqlx::query(
+                    r#"
+                    INSERT INTO properties (name, description, class_id, data_type, reference_class_id,
+                                            is_required, is_unique, is_indexed, is_sensitive,
+                                            default_value, validation_rules, version_id)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+                    ON CONFLICT (name, class_id) DO UPDATE SET
+                        description = EXCLUDED.description,
+                        data_type = EXCLUDED.data_type,
+                        reference_class_id = EXCLUDED.reference_class_id,
+                        is_required = EXCLUDED.is_required,
+                        is_unique = EXCLUDED.is_unique,
+                        is_indexed = EXCLUDED.is_indexed,
+                        is_sensitive = EXCLUDED.is_sensitive,
+                        default_value = EXCLUDED.default_value,
+                        validation_rules = EXCLUDED.validation_rules,
+                        is_deprecated = FALSE,
+                        deprecated_at = NULL,
+                        updated_at = NOW()
+                    "#,
+                )
+                .bind(name)
+                .bind(&property.description)
+                .bind(class_ids[owner])
+                .bind(&property.data_type)
+                .bind(class_id(&class_ids, &property.reference_class))
+                .bind(property.is_required)
+                .bind(property.is_unique)
+                .bind(property.is_indexed)
+                .bind(property.is_sensitive)
+                .bind(&property.default_value)
+                .bind(&property.validation_rules)
+                .bind(current.version_id)
+                .execute(&mut **tx)
+                .await?;
+            }
+            (PlanObject::RelationshipType, _) => {
+                let rel_type = rel_types[name];
+                sqlx::query(
+                    r#"
+                    INSERT INTO relationship_types (name, description, source_cardinality,
+                                                    target_cardinality, allowed_source_class_id,
+                                                    allowed_target_class_id,
+                                                    grants_permission_inheritance)
+                    VALUES ($1, $2, COALESCE($3, 'many'), COALESCE($4, 'many'), $5, $6, $7)
+                    ON CONFLICT (name) DO UPDATE SET
+                        description = EXCLUDED.description,
+                        source_cardinality = EXCLUDED.source_cardinality,
+                        target_cardinality = EXCLUDED.target_cardinality,
+                        allowed_source_class_id = EXCLUDED.allowed_source_class_id,
+                        allowed_target_class_id = EXCLUDED.allowed_target_class_id,
+                        grants_permission_inheritance = EXCLUDED.grants_permission_inheritance
+                    "#,
+                )
+                .bind(name)
+                .bind(&rel_type.description)
+                .bind(&rel_type.source_cardinality)
+                .bind(&rel_type.target_cardinality)
+                .bind(class_id(&class_ids, &rel_type.source_class))
+                .bind(class_id(&class_ids, &rel_type.target_class))
+                .bind(rel_type.grants_permission_inheritance)
+                .execute(&mut **tx)
+                .await?;
+            }
+            (PlanObject::RolePermission, action) => {
+                let grants_type = current
+                    .relationship_types
+                    .get("grants_permission")
+                    .ok_or_else(|| {
+                        OntologyError::NotFound(
+                            "Relationship type 'grants_permission' not found".to_string(),
+                        )
+                    })?;
+                let role = planned.owner.as_deref().unwrap_or_default();
+                if action == PlanAction::Create {
+                    sqlx::query(
+                        r#"
+                        INSERT INTO relationships (source_entity_id, target_entity_id,
+                                                   relationship_type_id, metadata, created_by)
+                        VALUES ($1, $2, $3, $4, $5)
+                        ON CONFLICT (source_entity_id, target_entity_id, relationship_type_id)
+                        DO UPDATE SET metadata = EXCLUDED.metadata
+                        "#,
+                    )
+                    .bind(role_ids[role])
+                    .bind(permission_ids[name])
+                    .bind(grants_type.id)
+                    .bind(serde_json::json!({ "effect": "ALLOW" }))
+                    .bind(user_id)
+                    .execute(&mut **tx)
+                    .await?;
+                } else {
+                    sqlx::query(
+                        r#"
+                        DELETE FROM relationships
+                        WHERE source_entity_id = $1 AND target_entity_id = $2
+                          AND relationship_type_id = $3
+                        "#,
+                    )
+                    .bind(role_ids[role])
+                    .bind(permission_ids[name])
+                    .bind(grants_type.id)
+                    .execute(&mut **tx)
+                    .await?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn desired(value: serde_json::Value) -> DesiredState {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn current() -> CurrentState {
+        let mut state = CurrentState {
+            version: "1.0.0".to_string(),
+            ..Default::default()
+        };
+        let property = |name: &str, data_type: &str| CurrentProperty {
+            id: Uuid::new_v4(),
+            class_id: Uuid::nil(),
+            name: name.to_string(),
+            description: None,
+            data_type: data_type.to_string(),
+            reference_class: None,
+            is_required: false,
+            is_unique: false,
+            is_indexed: false,
+            is_sensitive: false,
+            default_value: None,
+            validation_rules: None,
+            is_deprecated: false,
+        };
+        state.classes.insert(
+            "Vehicle".to_string(),
+            CurrentClass {
+                id: Uuid::new_v4(),
+                description: Some("Anything with wheels".to_string()),
+                parent: None,
+                is_abstract: false,
+                is_deprecated: false,
+                properties: [
+                    ("plate".to_string(), property("plate", "string")),
+                    ("seats".to_string(), property("seats", "integer")),
+                ]
+                .into(),
+            },
+        );
+        state
+            .system_classes
+            .insert("Resource".to_string(), Uuid::new_v4());
+        let entity = |name: &str, level: i32| CurrentAccessEntity {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            description: None,
+            level,
+        };
+        state
+            .permission_types
+            .insert("read".to_string(), entity("read", 20));
+        state
+            .permission_types
+            .insert("update".to_string(), entity("update", 40));
+        state
+            .roles
+            .insert("viewer".to_string(), entity("viewer", 10));
+        state.grants.insert(
+            "viewer".to_string(),
+            ["read".to_string(), "update".to_string()].into(),
+        );
+        state
+    }
+
+    fn summary(changes: &[PlannedChange]) -> Vec<String> {
+        changes
+            .iter()
+            .map(|c| {
+                let action = serde_json::to_value(c.action).unwrap();
+                let object = serde_json::to_value(c.object).unwrap();
+                match &c.owner {
+                    Some(owner) => format!(
+                        "{} {} {}.{}",
+                        action.as_str().unwrap(),
+                        object.as_str().unwrap(),
+                        owner,
+                        c.name
+                    ),
+                    None => format!(
+                        "{} {} {}",
+                        action.as_str().unwrap(),
+                        object.as_str().unwrap(),
+                        c.name
+                    ),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_plan_orders_and_diffs_changes() {
+        let doc = desired(json!({
+            "classes": [
+                { "name": "Truck", "parent": "Vehicle", "properties": [
+                    { "name": "payload", "data_type": "float" }
+                ]},
+                { "name": "Vehicle", "description": "Anything with wheels", "parent": "Resource",
+                  "properties": [
+                    { "name": "plate", "data_type": "string", "is_unique": true },
+                    { "name": "seats", "data_type": "integer" }
+                ]}
+            ],
+            "relationship_types": [{ "name": "tows", "source_class": "Truck", "target_cardinality": "one" }],
+            "permission_types": [{ "name": "read", "level": 20 }, { "name": "tow", "level": 45 }],
+            "roles": [{ "name": "viewer", "level": 10, "permissions": ["read", "tow"] }]
+        }));
+        let changes = plan_changes(&doc, &current(), false).unwrap();
+        assert_eq!(
+            summary(&changes),
+            vec![
+                "create permission_type tow",
+                "create class Truck",
+                "update class Vehicle",
+                "update property Vehicle.plate",
+                "create property Truck.payload",
+                "create relationship_type tows",
+                "create role_permission viewer.tow",
+                "remove role_permission viewer.update",
+            ]
+        );
+        assert_eq!(
+            changes[2].fields,
+            vec![FieldChange {
+                field: "parent".to_string(),
+                from: json!(null),
+                to: json!("Resource"),
+            }]
+        );
+
+        // Nothing left to do once applied, apart from what pruning removes
+        let unchanged = desired(json!({
+            "classes": [{ "name": "Vehicle", "description": "Anything with wheels",
+                          "properties": [{ "name": "seats", "data_type": "integer" }] }],
+            "roles": [{ "name": "viewer", "level": 10 }]
+        }));
+        assert!(plan_changes(&unchanged, &current(), false)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            summary(&plan_changes(&unchanged, &current(), true).unwrap()),
+            vec!["deprecate property Vehicle.plate"]
+        );
+        assert_eq!(
+            summary(&plan_changes(&DesiredState::default(), &current(), true).unwrap()),
+            vec!["deprecate class Vehicle"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_documents_are_rejected() {
+        for value in [
+            json!({ "classes": [{ "name": "A", "parent": "Missing" }] }),
+            json!({ "classes": [{ "name": "A", "properties": [{ "name": "x", "data_type": "varchar" }] }] }),
+            json!({ "classes": [{ "name": "A", "properties": [{ "name": "x", "data_type": "reference" }] }] }),
+            json!({ "relationship_types": [{ "name": "r", "target_class": "Missing" }] }),
+            json!({ "relationship_types": [{ "name": "r", "source_cardinality": "several" }] }),
+            json!({ "roles": [{ "name": "viewer", "permissions": ["fly"] }] }),
+            json!({ "roles": [{ "name": "a" }, { "name": "a" }] }),
+            json!({ "permission_types": [{ "name": "" }] }),
+        ] {
+            assert!(
+                matches!(
+                    plan_changes(&desired(value.clone()), &current(), false),
+                    Err(OntologyError::InvalidInput(_))
+                ),
+                "{value}"
+            );
+        }
+    }
+}
@@ -0,0 +1,546 @@
+use super::models::*;
+use super::service::{OntologyError, OntologyService};
+use crate::config::DuplicateDetectionConfig;
+use crate::utils::shutdown::Shutdown;
+use chrono::{DateTime, Utc};
+use sqlx::types::Json;
+use sqlx::FromRow;
+use std::collections::HashSet;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Each entity is compared with this many neighbours in each sort order, so
+/// detection stays linear in class size
+const COMPARISON_WINDOW: usize = 10;
+
+/// Share of the score from name similarity when the pair has attributes in common
+const NAME_WEIGHT: f64 = 0.7;
+
+const SUGGESTION_COLUMNS: &str = r#"
+    s.id, s.class_id,
+    s.primary_entity_id, pe.display_name AS primary_entity_name,
+    s.duplicate_entity_id, de.display_name AS duplicate_entity_name,
+    s.score, s.reasons, s.status, s.reviewed_by, s.reviewed_at, s.created_at
+"#;
+
+#[derive(Debug, FromRow)]
+struct Candidate {
+    id: Uuid,
+    display_name: String,
+    attributes: serde_json::Value,
+    created_at: DateTime<Utc>,
+}
+
+/// Lowercased alphanumeric words, so punctuation and spacing don't hide duplicates
+fn normalize_name(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn trigrams(name: &str) -> HashSet<[char; 3]> {
+    let padded: Vec<char> = format!("  {} ", name).chars().collect();
+    padded.windows(3).map(|w| [w[0], w[1], w[2]]).collect()
+}
+
+/// Dice coefficient over character trigrams of the normalized names, 0-1
+pub fn name_similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (normalize_name(a), normalize_name(b));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    if a == b {
+        return 1.0;
+    }
+    let (ta, tb) = (trigrams(&a), trigrams(&b));
+    2.0 * ta.intersection(&tb).count() as f64 / (ta.len() + tb.len()) as f64
+}
+
+/// (matching, shared) counts over scalar attributes both entities have set
+fn attribute_agreement(a: &serde_json::Value, b: &serde_json::Value) -> (usize, usize) {
+    let (Some(a), Some(b)) = (a.as_object(), b.as_object()) else {
+        return (0, 0);
+    };
+    let mut matching = 0;
+    let mut shared = 0;
+    for (key, va) in a {
+        let Some(vb) = b.get(key) else { continue };
+        if va.is_null() || vb.is_null() || va.is_object() || va.is_array() {
+            continue;
+        }
+        shared += 1;
+        let equal = match (va.as_str(), vb.as_str()) {
+            (Some(sa), Some(sb)) => normalize_name(sa) == normalize_name(sb),
+            _ => va == vb,
+        };
+        if equal {
+            matching += 1;
+        }
+    }
+    (matching, shared)
+}
+
+/// Likelihood (0-1) that two entities of the same class are the same thing,
+/// with the reasons shown to reviewers
+pub fn duplicate_score(
+    name_a: &str,
+    attributes_a: &serde_json::Value,
+    name_b: &str,
+    attributes_b: &serde_json::Value,
+) -> (f64, Vec<String>) {
+    let name = name_similarity(name_a, name_b);
+    let mut reasons = vec![format!("name similarity {:.2}", name)];
+    let (matching, shared) = attribute_agreement(attributes_a, attributes_b);
+    if shared == 0 {
+        return (name, reasons);
+    }
+    reasons.push(format!(
+        "{} of {} shared attributes match",
+        matching, shared
+    ));
+    let score = name * NAME_WEIGHT + (matching as f64 / shared as f64) * (1.0 - NAME_WEIGHT);
+    (score, reasons)
+}
+
+/// Index pairs worth scoring: neighbours when sorted by normalized name, and
+/// again by sorted words so reordered names ("Smith, John") meet too
+fn candidate_pairs(names: &[&str]) -> HashSet<(usize, usize)> {
+    let normalized: Vec<String> = names.iter().map(|n| normalize_name(n)).collect();
+    let word_sorted: Vec<String> = normalized
+        .iter()
+        .map(|n| {
+            let mut words: Vec<&str> = n.split(' ').collect();
+            words.sort_unstable();
+            words.join(" ")
+        })
+        .collect();
+
+    let mut pairs = HashSet::new();
+    for keys in [&normalized, &word_sorted] {
+        let mut order: Vec<usize> = (0..names.len()).collect();
+        order.sort_by(|&a, &b| keys[a].cmp(&keys[b]));
+        for (pos, &i) in order.iter().enumerate() {
+            for &j in order.iter().skip(pos + 1).take(COMPARISON_WINDOW) {
+                pairs.insert((i.min(j), i.max(j)));
+            }
+        }
+    }
+    pairs
+}
+
+impl OntologyService {
+    // ========================================================================
+    // DUPLICATE DETECTION
+    // ========================================================================
+
+    /// Queue merge suggestions for likely duplicates within each class (and
+    /// tenant). Pairs already suggested, including rejected ones, are skipped.
+    /// Returns how many new suggestions were queued.
+    pub async fn detect_duplicates(&self, min_score: f64) -> Result<usize, OntologyError> {
+        let groups = sqlx::query_as::<_, (Uuid, Option<Uuid>)>(
+            "SELECT DISTINCT class_id, tenant_id FROM entities WHERE deleted_at IS NULL",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut created = 0;
+        for (class_id, tenant_id) in groups {
+            let entities = sqlx::query_as::<_, Candidate>(
+                r#"
+                SELECT id, display_name, attributes, created_at FROM entities
+                WHERE class_id = $1 AND tenant_id IS NOT DISTINCT FROM $2 AND deleted_at IS NULL
+                "#,
+            )
+            .bind(class_id)
+            .bind(tenant_id)
+            .fetch_all(&self.pool)
+            .await?;
+            if entities.len() < 2 {
+                continue;
+            }
+
+            let names: Vec<&str> = entities.iter().map(|e| e.display_name.as_str()).collect();
+            for (i, j) in candidate_pairs(&names) {
+                let (a, b) = (&entities[i], &entities[j]);
+                let (score, reasons) = duplicate_score(
+                    &a.display_name,
+                    &a.attributes,
+                    &b.display_name,
+                    &b.attributes,
+                );
+                if score < min_score {
+                    continue;
+                }
+                // The older entity is kept by default; reviewers can choose otherwise
+                let (primary, duplicate) = if (a.created_at, a.id) <= (b.created_at, b.id) {
+                    (a, b)
+                } else {
+                    (b, a)
+                };
+                let result = sqlx::query(
+                    r#"
+                    INSERT INTO entity_merge_suggestions
+                        (class_id, primary_entity_id, duplicate_entity_id, score, reasons)
+                    VALUES ($1, $2, $3, $4, $5)
+                    ON CONFLICT DO NOTHING
+                    "#,
+                )
+                .bind(class_id)
+                .bind(primary.id)
+                .bind(duplicate.id)
+                .bind(score)
+                .bind(Json(&reasons))
+                .execute(&self.pool)
+                .await?;
+                created += result.rows_affected() as usize;
+            }
+        }
+
+        Ok(created)
+    }
+
+    pub async fn list_merge_suggestions(
+        &self,
+        status: Option<&str>,
+    ) -> Result<Vec<EntityMergeSuggestion>, OntologyError> {
+        let suggestions = sqlx::query_as::<_, EntityMergeSuggestion>(&format!(
+            r#"
+            SELECT {}
+            FROM entity_merge_suggestions s
+            JOIN entities pe ON pe.id = s.primary_entity_id
+            JOIN entities de ON de.id = s.duplicate_entity_id
+            WHERE ($1::text IS NULL OR s.status = $1)
+            ORDER BY s.score DESC, s.created_at
+            "#,
+            SUGGESTION_COLUMNS
+        ))
+        .bind(status)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(suggestions)
+    }
+
+    pub async fn get_merge_suggestion(
+        &self,
+        id: Uuid,
+    ) -> Result<EntityMergeSuggestion, OntologyError> {
+        sqlx::query_as::<_, EntityMergeSuggestion>(&format!(
+            r#"
+            SELECT {}
+            FROM entity_merge_suggestions s
+            JOIN entities pe ON pe.id = s.primary_entity_id
+            JOIN entities de ON de.id = s.duplicate_entity_id
+            WHERE s.id = $1
+            "#,
+            SUGGESTION_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| OntologyError::NotFound(format!("Merge suggestion {} not found", id)))
+    }
+
+    pub async fn reject_merge_suggestion(
+        &self,
+        id: Uuid,
+        user_id: Option<Uuid>,
+    ) -> Result<EntityMergeSuggestion, OntologyError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE entity_merge_suggestions
+            SET status = 'rejected', reviewed_by = $2, reviewed_at = NOW()
+            WHERE id = $1 AND status = 'pending'
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+
+        let suggestion = self.get_merge_suggestion(id).await?;
+        if result.rows_affected() == 0 {
+            return Err(OntologyError::VersionConflict(format!(
+                "Merge suggestion {} is already {}",
+                id, suggestion.status
+            )));
+        }
+        Ok(suggestion)
+    }
+
+    /// Merge the pair in one transaction: relationships and children of the
+    /// merged entity move to the kept one, attributes the kept entity lacks are
+    /// copied over, and the merged entity is soft-deleted.
+    pub async fn approve_merge_suggestion(
+        &self,
+        id: Uuid,
+        input: ApproveMergeInput,
+        user_id: Option<Uuid>,
+    ) -> Result<EntityMergeResult, OntologyError> {
+        let mut tx = self.pool.begin().await?;
+
+        let (status, primary_id, duplicate_id) = sqlx::query_as::<_, (String, Uuid, Uuid)>(
+            r#"
+            SELECT status, primary_entity_id, duplicate_entity_id
+            FROM entity_merge_suggestions WHERE id = $1 FOR UPDATE
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| OntologyError::NotFound(format!("Merge suggestion {} not found", id)))?;
+        if status != "pending" {
+            return Err(OntologyError::VersionConflict(format!(
+                "Merge suggestion {} is already {}",
+                id, status
+            )));
+        }
+
+        let (keep, merge) = match input.keep_entity_id {
+            None => (primary_id, duplicate_id),
+            Some(keep) if keep == primary_id => (primary_id, duplicate_id),
+            Some(keep) if keep == duplicate_id => (duplicate_id, primary_id),
+            Some(keep) => {
+                return Err(OntologyError::InvalidInput(format!(
+                    "Entity {} is not part of merge suggestion {}",
+                    keep, id
+                )))
+            }
+        };
+
+        let live = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM entities WHERE id IN ($1, $2) AND deleted_at IS NULL",
+        )
+        .bind(keep)
+        .bind(merge)
+        .fetch_one(&mut *tx)
+        .await?;
+        if live != 2 {
+            return Err(OntologyError::VersionConflict(
+                "One of the entities has been deleted since the suggestion was made".to_string(),
+            ));
+        }
+
+        // Moving the merged entity's children under its own descendant would form a cycle
+        let keep_is_descendant = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM get_entity_ancestors($1) WHERE ancestor_id = $2)",
+        )
+        .bind(keep)
+        .bind(merge)
+        .fetch_one(&mut *tx)
+        .await?;
+        if keep_is_descendant {
+            return Err(OntologyError::InvalidInput(format!(
+                "Entity {} is a descendant of {} and cannot absorb it",
+                keep, merge
+            )));
+        }
+
+        // Links between the pair would become self-links, and links the kept
+        // entity already has would violate unique_relationship
+        sqlx::query(
+            r#"
+            DELETE FROM relationships r
+            WHERE (r.source_entity_id = $2 AND r.target_entity_id = $1)
+               OR (r.source_entity_id = $1 AND r.target_entity_id = $2)
+               OR (r.source_entity_id = $2 AND EXISTS (
+                    SELECT 1 FROM relationships x
+                    WHERE x.source_entity_id = $1 AND x.target_entity_id = r.target_entity_id
+                      AND x.relationship_type_id = r.relationship_type_id))
+               OR (r.target_entity_id = $2 AND EXISTS (
+                    SELECT 1 FROM relationships x
+                    WHERE x.target_entity_id = $1 AND x.source_entity_id = r.source_entity_id
+                      AND x.relationship_type_id = r.relationship_type_id))
+            "#,
+        )
+        .bind(keep)
+        .bind(merge)
+        .execute(&mut *tx)
+        .await?;
+
+        let mut relationships_moved = sqlx::query(
+            "UPDATE relationships SET source_entity_id = $1 WHERE source_entity_id = $2",
+        )
+        .bind(keep)
+        .bind(merge)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        relationships_moved += sqlx::query(
+            "UPDATE relationships SET target_entity_id = $1 WHERE target_entity_id = $2",
+        )
+        .bind(keep)
+        .bind(merge)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        let children_moved = sqlx::query(
+            r#"
+            UPDATE entities SET parent_entity_id = $1, updated_by = $3, updated_at = NOW()
+            WHERE parent_entity_id = $2 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(keep)
+        .bind(merge)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        sqlx::query(
+            r#"
+            UPDATE entities SET
+                attributes = COALESCE((SELECT attributes FROM entities WHERE id = $2), '{}') || attributes,
+                updated_by = $3,
+                updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(keep)
+        .bind(merge)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("UPDATE entities SET deleted_at = NOW(), deleted_by = $2 WHERE id = $1")
+            .bind(merge)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        // Other suggestions involving the merged entity are moot; detection
+        // will re-pair the kept entity where it still looks like a duplicate
+        sqlx::query(
+            r#"
+            DELETE FROM entity_merge_suggestions
+            WHERE id <> $1 AND status = 'pending'
+              AND (primary_entity_id = $2 OR duplicate_entity_id = $2)
+            "#,
+        )
+        .bind(id)
+        .bind(merge)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE entity_merge_suggestions
+            SET status = 'merged', primary_entity_id = $2, duplicate_entity_id = $3,
+                reviewed_by = $4, reviewed_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(keep)
+        .bind(merge)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        let result = EntityMergeResult {
+            suggestion: self.get_merge_suggestion(id).await?,
+            entity_id: keep,
+            merged_entity_id: merge,
+            relationships_moved,
+            children_moved,
+        };
+
+        if let Some(uid) = user_id {
+            let _ = self
+                .audit_service
+                .log(
+                    uid,
+                    "ontology.entity.merge",
+                    "entity",
+                    Some(keep),
+                    None,
+                    Some(serde_json::to_value(&result).unwrap_or(serde_json::Value::Null)),
+                    None,
+                )
+                .await;
+        }
+
+        Ok(result)
+    }
+
+    pub async fn start_duplicate_detection(
+        self,
+        config: DuplicateDetectionConfig,
+        shutdown: Shutdown,
+    ) {
+        if !config.enabled {
+            tracing::info!("Duplicate entity detection disabled");
+            return;
+        }
+        shutdown.clone().spawn(async move {
+            let mut interval =
+                tokio::time::interval(Duration::from_secs(config.interval_secs.max(60)));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = shutdown.cancelled() => break,
+                }
+                match self.detect_duplicates(config.min_score).await {
+                    Ok(0) => {}
+                    Ok(n) => tracing::info!("Queued {} entity merge suggestions", n),
+                    Err(e) => tracing::warn!("Duplicate entity detection failed: {}", e),
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_name_similarity() {
+        assert_eq!(name_similarity("ACME Corp.", "acme  corp"), 1.0);
+        assert!(name_similarity("Operation Alpha", "Operation Alfa") > 0.6);
+        assert!(name_similarity("Operation Alpha", "Logistics Hub") < 0.3);
+        assert_eq!(name_similarity("", "anything"), 0.0);
+    }
+
+    #[test]
+    fn test_duplicate_score_uses_shared_attributes() {
+        let (score, reasons) = duplicate_score(
+            "Acme Corp",
+            &json!({ "country": "NO", "org_no": 123 }),
+            "ACME Corp.",
+            &json!({ "country": "no", "org_no": 123, "notes": "x" }),
+        );
+        assert_eq!(score, 1.0);
+        assert_eq!(reasons[1], "2 of 2 shared attributes match");
+
+        // Same name, conflicting identifiers
+        let (score, _) = duplicate_score(
+            "Acme Corp",
+            &json!({ "org_no": 123 }),
+            "Acme Corp",
+            &json!({ "org_no": 456 }),
+        );
+        assert!((score - NAME_WEIGHT).abs() < 1e-9);
+
+        // Nothing shared: name alone decides
+        let (score, reasons) = duplicate_score("Acme", &json!({}), "Acme", &json!(null));
+        assert_eq!(score, 1.0);
+        assert_eq!(reasons.len(), 1);
+    }
+
+    #[test]
+    fn test_candidate_pairs_include_reordered_names() {
+        let mut names = vec!["John Smith", "Smith John"];
+        // Enough unrelated names between them to push the pair out of one window
+        let fillers: Vec<String> = (0..30).map(|i| format!("Kim {:02}", i)).collect();
+        names.extend(fillers.iter().map(String::as_str));
+        let pairs = candidate_pairs(&names);
+        assert!(pairs.contains(&(0, 1)));
+        assert!(pairs.len() < names.len() * names.len() / 2);
+    }
+}
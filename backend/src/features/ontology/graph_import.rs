@@ -0,0 +1,129 @@
+use super::models::*;
+use super::service::{OntologyError, OntologyService};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+impl OntologyService {
+    // ========================================================================
+    // GRAPH IMPORT
+    // ========================================================================
+
+    /// Create a batch of entities and the relationships between them in one
+    /// transaction. Every entity starts PENDING, whatever its parent, since
+    /// imported data hasn't been reviewed yet.
+    pub async fn import_pending_graph(
+        &self,
+        import: GraphImport,
+        user_id: Option<Uuid>,
+    ) -> Result<ImportedGraph, OntologyError> {
+        if import.entities.is_empty() {
+            return Err(OntologyError::InvalidInput(
+                "import has no entities".to_string(),
+            ));
+        }
+        for input in &import.entities {
+            if input.display_name.trim().is_empty() {
+                return Err(OntologyError::InvalidInput(
+                    "entity display_name is required".to_string(),
+                ));
+            }
+            let attributes = input
+                .attributes
+                .clone()
+                .unwrap_or_else(|| serde_json::json!({}));
+            self.validate_entity_attributes(input.class_id, &attributes, false)
+                .await?;
+        }
+
+        let mut relationship_types: HashMap<String, Uuid> = HashMap::new();
+        for rel in &import.relationships {
+            if rel.source >= import.entities.len() || rel.target >= import.entities.len() {
+                return Err(OntologyError::InvalidInput(format!(
+                    "relationship '{}' refers to an entity outside the import",
+                    rel.relationship_type
+                )));
+            }
+            if !relationship_types.contains_key(&rel.relationship_type) {
+                let id = sqlx::query_scalar::<_, Uuid>(
+                    "SELECT id FROM relationship_types WHERE name = $1",
+                )
+                .bind(&rel.relationship_type)
+                .fetch_optional(&self.pool)
+                .await?
+                .ok_or_else(|| {
+                    OntologyError::InvalidInput(format!(
+                        "Relationship type '{}' not found",
+                        rel.relationship_type
+                    ))
+                })?;
+                relationship_types.insert(rel.relationship_type.clone(), id);
+            }
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        let mut entities = Vec::with_capacity(import.entities.len());
+        for input in import.entities {
+            let entity = sqlx::query_as::<_, Entity>(
+                r#"
+                INSERT INTO entities (class_id, display_name, parent_entity_id, attributes, approval_status, created_by, updated_by)
+                VALUES ($1, $2, $3, $4, $5, $6, $6)
+                RETURNING *
+                "#,
+            )
+            .bind(input.class_id)
+            .bind(input.display_name.trim())
+            .bind(input.parent_entity_id)
+            .bind(input.attributes.unwrap_or_else(|| serde_json::json!({})))
+            .bind(ApprovalStatus::PENDING)
+            .bind(user_id)
+            .fetch_one(&mut *tx)
+            .await?;
+            entities.push(entity);
+        }
+
+        let mut relationships = Vec::with_capacity(import.relationships.len());
+        for rel in &import.relationships {
+            // The same link proposed twice is created once
+            let relationship = sqlx::query_as::<_, Relationship>(
+                r#"
+                INSERT INTO relationships (source_entity_id, target_entity_id, relationship_type_id, created_by)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (source_entity_id, target_entity_id, relationship_type_id) DO NOTHING
+                RETURNING *
+                "#,
+            )
+            .bind(entities[rel.source].id)
+            .bind(entities[rel.target].id)
+            .bind(relationship_types[&rel.relationship_type])
+            .bind(user_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+            relationships.extend(relationship);
+        }
+
+        tx.commit().await?;
+
+        if let Some(uid) = user_id {
+            for entity in &entities {
+                let _ = self
+                    .audit_service
+                    .log(
+                        uid,
+                        "entity.create",
+                        "entity",
+                        Some(entity.id),
+                        None,
+                        Some(serde_json::to_value(entity).unwrap_or(serde_json::Value::Null)),
+                        None,
+                    )
+                    .await;
+            }
+        }
+
+        Ok(ImportedGraph {
+            entities,
+            relationships,
+        })
+    }
+}
@@ -0,0 +1,208 @@
+//! Per-locale display names and descriptions for classes, properties and
+//! entities.
+//!
+//! Labels are stored next to the untranslated `name`/`display_name` and
+//! `description`, which stay what the rest of the API matches on. List and
+//! get endpoints add a `localized` field resolved from the request's
+//! `Accept-Language`; other responses carry `labels` only.
+
+use super::models::{
+    Class, ClassWithParent, Entity, EntityWithDetails, Labels, LocalizedLabel, Property,
+};
+use crate::features::notifications::templates::normalize_locale;
+use crate::utils::i18n::language_ranges;
+
+/// What a label belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LabelTarget {
+    Class,
+    Property,
+    Entity,
+}
+
+impl LabelTarget {
+    /// `UPDATE` of the target's row `$1` that sets `assignment` and returns
+    /// its labels
+    pub(crate) fn update_sql(self, assignment: &str) -> String {
+        let (table, live) = match self {
+            Self::Class => ("classes", ""),
+            Self::Property => ("properties", ""),
+            Self::Entity => ("entities", " AND deleted_at IS NULL"),
+        };
+        format!(
+            "UPDATE {table} SET {assignment}, updated_at = NOW() WHERE id = $1{live} RETURNING labels"
+        )
+    }
+
+    pub fn noun(self) -> &'static str {
+        match self {
+            Self::Class => "Class",
+            Self::Property => "Property",
+            Self::Entity => "Entity",
+        }
+    }
+}
+
+/// Locales to look labels up in, most preferred first. A regional tag is
+/// followed by its language: "nb-NO,en" gives "nb-NO", "nb", "en".
+pub fn requested_locales(accept_language: Option<&str>) -> Vec<String> {
+    let mut locales: Vec<String> = Vec::new();
+    for tag in accept_language.map(language_ranges).unwrap_or_default() {
+        let Some(locale) = normalize_locale(&tag) else {
+            continue;
+        };
+        let language = locale
+            .split_once('-')
+            .map(|(language, _)| language.to_string());
+        for locale in std::iter::once(locale).chain(language) {
+            if !locales.contains(&locale) {
+                locales.push(locale);
+            }
+        }
+    }
+    locales
+}
+
+/// The display name and description to show in `locales`
+pub fn localize(
+    labels: &Labels,
+    locales: &[String],
+    name: &str,
+    description: Option<&str>,
+) -> LocalizedLabel {
+    let labels_in_order = || {
+        locales
+            .iter()
+            .filter_map(|locale| Some((locale, labels.get(locale)?)))
+    };
+    let display_name = labels_in_order()
+        .find_map(|(locale, label)| Some((locale, label.display_name.as_deref()?)));
+    let translated_description =
+        labels_in_order().find_map(|(_, label)| label.description.as_deref());
+
+    LocalizedLabel {
+        locale: display_name.map(|(locale, _)| locale.clone()),
+        display_name: display_name.map_or(name, |(_, name)| name).to_string(),
+        description: translated_description.or(description).map(str::to_string),
+    }
+}
+
+pub trait Localize {
+    /// Fill in `localized` for `locales`
+    fn localize(&mut self, locales: &[String]);
+}
+
+impl<T: Localize> Localize for Vec<T> {
+    fn localize(&mut self, locales: &[String]) {
+        for item in self {
+            item.localize(locales);
+        }
+    }
+}
+
+impl Localize for Class {
+    fn localize(&mut self, locales: &[String]) {
+        self.localized = Some(localize(
+            &self.labels,
+            locales,
+            &self.name,
+            self.description.as_deref(),
+        ));
+    }
+}
+
+impl Localize for ClassWithParent {
+    fn localize(&mut self, locales: &[String]) {
+        self.localized = Some(localize(
+            &self.labels,
+            locales,
+            &self.name,
+            self.description.as_deref(),
+        ));
+    }
+}
+
+impl Localize for Property {
+    fn localize(&mut self, locales: &[String]) {
+        self.localized = Some(localize(
+            &self.labels,
+            locales,
+            &self.name,
+            self.description.as_deref(),
+        ));
+    }
+}
+
+impl Localize for Entity {
+    fn localize(&mut self, locales: &[String]) {
+        self.localized = Some(localize(&self.labels, locales, &self.display_name, None));
+    }
+}
+
+impl Localize for EntityWithDetails {
+    fn localize(&mut self, locales: &[String]) {
+        self.localized = Some(localize(&self.labels, locales, &self.display_name, None));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::ontology::models::Label;
+
+    fn label(display_name: Option<&str>, description: Option<&str>) -> Label {
+        Label {
+            display_name: display_name.map(str::to_string),
+            description: description.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_requested_locales() {
+        assert_eq!(
+            requested_locales(Some("nb-NO,nb;q=0.9,en;q=0.8")),
+            vec!["nb-NO", "nb", "en"]
+        );
+        assert_eq!(
+            requested_locales(Some("en-gb, fr;q=0.5, bogus!")),
+            vec!["en-GB", "en", "fr"]
+        );
+        assert!(requested_locales(Some("*")).is_empty());
+        assert!(requested_locales(None).is_empty());
+    }
+
+    #[test]
+    fn test_each_field_falls_back_on_its_own() {
+        let labels = Labels::from([
+            ("nb".to_string(), label(Some("Oppdrag"), None)),
+            ("nb-NO".to_string(), label(None, Some("Et oppdrag"))),
+            (
+                "sv".to_string(),
+                label(Some("Uppdrag"), Some("Ett uppdrag")),
+            ),
+        ]);
+        let locales = requested_locales(Some("nb-NO, en;q=0.5"));
+
+        assert_eq!(
+            localize(&labels, &locales, "Mission", Some("A mission")),
+            LocalizedLabel {
+                locale: Some("nb".to_string()),
+                display_name: "Oppdrag".to_string(),
+                description: Some("Et oppdrag".to_string()),
+            }
+        );
+        assert_eq!(
+            localize(
+                &labels,
+                &requested_locales(Some("de")),
+                "Mission",
+                Some("A mission")
+            ),
+            LocalizedLabel {
+                locale: None,
+                display_name: "Mission".to_string(),
+                description: Some("A mission".to_string()),
+            }
+        );
+    }
+}
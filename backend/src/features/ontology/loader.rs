@@ -0,0 +1,195 @@
+//! Request-scoped batching of ontology lookups
+//!
+//! Read-only requests run with an `OntologyLoader` in scope (installed by
+//! `ontology_loader_middleware`). While it is, `OntologyService::get_entity`
+//! calls that are awaited together are gathered into one `id = ANY(...)`
+//! query, an entity is read at most once per request, and system classes and
+//! relationship types are each read in a single query the first time one is
+//! needed. Requests that write don't get a loader, so they always see their
+//! own changes.
+
+use super::models::{Class, Entity, RelationshipType};
+use super::service::OntologyError;
+use axum::{extract::Request, http::Method, middleware::Next, response::Response};
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{Notify, OnceCell};
+use uuid::Uuid;
+
+tokio::task_local! {
+    static LOADER: OntologyLoader;
+}
+
+#[derive(Default)]
+struct EntityState {
+    /// Entities read so far; None for ids that don't exist
+    loaded: HashMap<Uuid, Option<Entity>>,
+    /// Ids asked for but not yet fetched
+    pending: HashSet<Uuid>,
+    /// Ids in a batch being fetched
+    in_flight: HashSet<Uuid>,
+}
+
+#[derive(Default)]
+struct Inner {
+    entities: Mutex<EntityState>,
+    /// Woken whenever a batch finishes
+    batch_done: Notify,
+    batches: AtomicU64,
+    system_classes: OnceCell<HashMap<String, Class>>,
+    relationship_types: OnceCell<HashMap<String, RelationshipType>>,
+}
+
+/// Per-request memo and batcher for ontology lookups. Cheap to clone.
+#[derive(Clone, Default)]
+pub struct OntologyLoader {
+    inner: Arc<Inner>,
+}
+
+impl OntologyLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The loader of the request being handled, if any
+    pub fn current() -> Option<Self> {
+        LOADER.try_with(|loader| loader.clone()).ok()
+    }
+
+    /// Run `future` with this loader in scope
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        LOADER.scope(self, future).await
+    }
+
+    /// Entity queries run so far
+    pub fn batches(&self) -> u64 {
+        self.inner.batches.load(Ordering::Relaxed)
+    }
+
+    /// The entities with these ids that exist. Ids asked for by other
+    /// lookups of the request awaited at the same time go in the same query.
+    pub async fn entities(
+        &self,
+        pool: &PgPool,
+        ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, Entity>, OntologyError> {
+        {
+            let mut state = self.lock();
+            let missing: Vec<_> = ids
+                .iter()
+                .filter(|id| !state.loaded.contains_key(id))
+                .copied()
+                .collect();
+            if missing.is_empty() {
+                return Ok(collect(&state, ids));
+            }
+            state.pending.extend(missing);
+        }
+        // Let the other lookups awaited alongside this one queue their ids
+        tokio::task::yield_now().await;
+
+        loop {
+            let notified = self.inner.batch_done.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            let batch = {
+                let mut state = self.lock();
+                if ids.iter().all(|id| state.loaded.contains_key(id)) {
+                    return Ok(collect(&state, ids));
+                }
+                let mut batch: Vec<Uuid> = state.pending.drain().collect();
+                // Ours that a failed batch left behind
+                for id in ids {
+                    if !state.loaded.contains_key(id)
+                        && !state.in_flight.contains(id)
+                        && !batch.contains(id)
+                    {
+                        batch.push(*id);
+                    }
+                }
+                state.in_flight.extend(batch.iter().copied());
+                batch
+            };
+            if batch.is_empty() {
+                // Someone else is fetching what we need
+                notified.await;
+                continue;
+            }
+
+            self.inner.batches.fetch_add(1, Ordering::Relaxed);
+            let result = sqlx::query_as::<_, Entity>(
+                "SELECT * FROM entities WHERE id = ANY($1) AND deleted_at IS NULL",
+            )
+            .bind(&batch)
+            .fetch_all(pool)
+            .await;
+            {
+                let mut state = self.lock();
+                for id in &batch {
+                    state.in_flight.remove(id);
+                }
+                if let Ok(rows) = &result {
+                    for id in &batch {
+                        state.loaded.insert(*id, None);
+                    }
+                    for entity in rows {
+                        state.loaded.insert(entity.id, Some(entity.clone()));
+                    }
+                }
+            }
+            self.inner.batch_done.notify_waiters();
+            result?;
+        }
+    }
+
+    /// All system classes by name, read with `load` the first time
+    pub async fn system_classes<F, Fut>(
+        &self,
+        load: F,
+    ) -> Result<&HashMap<String, Class>, OntologyError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<HashMap<String, Class>, OntologyError>>,
+    {
+        self.inner.system_classes.get_or_try_init(load).await
+    }
+
+    /// All relationship types by name, read with `load` the first time
+    pub async fn relationship_types<F, Fut>(
+        &self,
+        load: F,
+    ) -> Result<&HashMap<String, RelationshipType>, OntologyError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<HashMap<String, RelationshipType>, OntologyError>>,
+    {
+        self.inner.relationship_types.get_or_try_init(load).await
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, EntityState> {
+        self.inner
+            .entities
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn collect(state: &EntityState, ids: &[Uuid]) -> HashMap<Uuid, Entity> {
+    ids.iter()
+        .filter_map(|id| state.loaded.get(id).cloned().flatten())
+        .map(|entity| (entity.id, entity))
+        .collect()
+}
+
+/// Give each GET and HEAD request its own loader
+pub async fn ontology_loader_middleware(request: Request, next: Next) -> Response {
+    if matches!(*request.method(), Method::GET | Method::HEAD) {
+        OntologyLoader::new().scope(next.run(request)).await
+    } else {
+        next.run(request).await
+    }
+}
@@ -1,6 +1,15 @@
+pub mod changesets;
+pub mod desired_state;
+pub mod duplicates;
+pub mod graph_import;
+pub mod labels;
+pub mod loader;
 pub mod models;
 pub mod routes;
+pub mod schema;
 pub mod service;
+pub mod sitemap;
+pub mod transfer;
 
 pub use models::*;
 pub use service::OntologyService;
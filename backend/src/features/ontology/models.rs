@@ -1,7 +1,11 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use std::collections::BTreeMap;
 use uuid::Uuid;
+use validator::Validate;
+
+use crate::utils::validation::not_blank;
 
 // ============================================================================
 // SCHEMA VERSIONING
@@ -37,8 +41,9 @@ pub struct OntologyVersion {
     pub created_by: Option<Uuid>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreateVersionInput {
+    #[validate(custom = "not_blank", length(max = 50))]
     pub version: String,
     pub description: Option<String>,
 }
@@ -61,6 +66,17 @@ pub struct Class {
     pub deprecated_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    #[sqlx(default)]
+    #[serde(default)]
+    pub delete_policy: DeletePolicy,
+    /// Display name and description per locale
+    #[sqlx(default)]
+    #[serde(default)]
+    pub labels: sqlx::types::Json<Labels>,
+    /// `labels` resolved for the request's `Accept-Language`
+    #[sqlx(skip)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub localized: Option<LocalizedLabel>,
 }
 
 /// Class with resolved parent name for API responses
@@ -75,23 +91,52 @@ pub struct ClassWithParent {
     pub is_abstract: bool,
     pub is_deprecated: bool,
     pub created_at: DateTime<Utc>,
+    #[sqlx(default)]
+    #[serde(default)]
+    pub delete_policy: DeletePolicy,
+    /// Display name and description per locale
+    #[sqlx(default)]
+    #[serde(default)]
+    pub labels: sqlx::types::Json<Labels>,
+    /// `labels` resolved for the request's `Accept-Language`
+    #[sqlx(skip)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub localized: Option<LocalizedLabel>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreateClassInput {
+    #[validate(custom = "not_blank", length(max = 255))]
     pub name: String,
     pub description: Option<String>,
     pub parent_class_id: Option<Uuid>,
     pub is_abstract: Option<bool>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct UpdateClassInput {
+    #[validate(custom = "not_blank", length(max = 255))]
     pub name: Option<String>,
     pub description: Option<String>,
     pub parent_class_id: Option<Uuid>,
     pub is_abstract: Option<bool>,
     pub is_deprecated: Option<bool>,
+    pub delete_policy: Option<DeletePolicy>,
+}
+
+/// What deleting an entity of a class does to the entity's children.
+/// Relationships touching a deleted entity are removed under every policy.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum DeletePolicy {
+    /// Soft-delete all descendants with the entity
+    Cascade,
+    /// Clear the children's parent
+    #[default]
+    Detach,
+    /// Refuse the delete while the entity has children
+    Block,
 }
 
 // ============================================================================
@@ -118,13 +163,23 @@ pub struct Property {
     pub deprecated_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Display name and description per locale
+    #[sqlx(default)]
+    #[serde(default)]
+    pub labels: sqlx::types::Json<Labels>,
+    /// `labels` resolved for the request's `Accept-Language`
+    #[sqlx(skip)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub localized: Option<LocalizedLabel>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreatePropertyInput {
+    #[validate(custom = "not_blank", length(max = 255))]
     pub name: String,
     pub description: Option<String>,
     pub class_id: Uuid,
+    #[validate(custom = "not_blank", length(max = 50))]
     pub data_type: String,
     pub reference_class_id: Option<Uuid>,
     pub is_required: Option<bool>,
@@ -135,10 +190,12 @@ pub struct CreatePropertyInput {
     pub validation_rules: Option<serde_json::Value>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct UpdatePropertyInput {
+    #[validate(custom = "not_blank", length(max = 255))]
     pub name: Option<String>,
     pub description: Option<String>,
+    #[validate(custom = "not_blank", length(max = 50))]
     pub data_type: Option<String>,
     pub reference_class_id: Option<Uuid>,
     pub is_required: Option<bool>,
@@ -172,6 +229,14 @@ pub struct Entity {
     pub updated_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
     pub deleted_by: Option<Uuid>,
+    /// Display name and description per locale
+    #[sqlx(default)]
+    #[serde(default)]
+    pub labels: sqlx::types::Json<Labels>,
+    /// `labels` resolved for the request's `Accept-Language`
+    #[sqlx(skip)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub localized: Option<LocalizedLabel>,
 }
 
 /// Entity with resolved class and parent names
@@ -188,23 +253,67 @@ pub struct EntityWithDetails {
     pub approval_status: ApprovalStatus,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Display name and description per locale
+    #[sqlx(default)]
+    #[serde(default)]
+    pub labels: sqlx::types::Json<Labels>,
+    /// `labels` resolved for the request's `Accept-Language`
+    #[sqlx(skip)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub localized: Option<LocalizedLabel>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreateEntityInput {
     pub class_id: Uuid,
+    #[validate(custom = "not_blank", length(max = 500))]
     pub display_name: String,
     pub parent_entity_id: Option<Uuid>,
     pub attributes: Option<serde_json::Value>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct UpdateEntityInput {
+    #[validate(custom = "not_blank", length(max = 500))]
     pub display_name: Option<String>,
     pub parent_entity_id: Option<Uuid>,
     pub attributes: Option<serde_json::Value>,
 }
 
+// ============================================================================
+// LABELS
+// ============================================================================
+
+/// Translations of a class, property or entity, keyed by language tag
+/// ("nb", "nb-NO")
+pub type Labels = BTreeMap<String, Label>;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Label {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// The name and description to show for one request. Each falls back to the
+/// next requested locale that has it, then to the untranslated text.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LocalizedLabel {
+    /// Locale the display name came from; None when it is the untranslated
+    /// name
+    pub locale: Option<String>,
+    pub display_name: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct SetLabelInput {
+    #[validate(custom = "not_blank", length(max = 500))]
+    pub display_name: Option<String>,
+    pub description: Option<String>,
+}
+
 // ============================================================================
 // RELATIONSHIPS
 // ============================================================================
@@ -251,10 +360,11 @@ pub struct RelationshipWithDetails {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreateRelationshipInput {
     pub source_entity_id: Uuid,
     pub target_entity_id: Uuid,
+    #[validate(custom = "not_blank", length(max = 100))]
     pub relationship_type: String, // Name of the relationship type
     pub metadata: Option<serde_json::Value>,
 }
@@ -278,6 +388,49 @@ pub struct EntityDescendantNode {
     pub depth: i32,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AffectedEntity {
+    pub id: Uuid,
+    pub class_id: Uuid,
+    pub display_name: String,
+}
+
+/// What deleting an entity does under its class's delete policy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeletePlan {
+    pub entity_id: Uuid,
+    pub policy: DeletePolicy,
+    /// Soft-deleted: the entity, then descendants whose parent's class cascades
+    pub deleted_entities: Vec<AffectedEntity>,
+    /// Children left without a parent
+    pub detached_entities: Vec<AffectedEntity>,
+    /// Relationships archived because an endpoint is deleted
+    pub removed_relationships: Vec<Relationship>,
+    /// Children of a deleted entity whose class uses the `block` policy
+    pub blocking_entities: Vec<AffectedEntity>,
+}
+
+impl DeletePlan {
+    pub fn is_blocked(&self) -> bool {
+        !self.blocking_entities.is_empty()
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct DeleteEntityQuery {
+    /// Report what would be deleted without deleting anything
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// One line of a streamed subgraph export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SubgraphRecord {
+    Entity(Entity),
+    Relationship(Relationship),
+}
+
 /// Result from related entities query
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 #[allow(dead_code)]
@@ -288,3 +441,421 @@ pub struct RelatedEntity {
     pub relationship_type: String,
     pub direction: String,
 }
+
+// ============================================================================
+// CHANGESETS
+// ============================================================================
+
+/// Property data types a changeset may use
+pub const PROPERTY_DATA_TYPES: &[&str] = &[
+    "string", "text", "integer", "float", "number", "boolean", "date", "datetime", "json", "uuid",
+    "reference",
+];
+
+/// A property to add to a changeset class
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangesetProperty {
+    pub name: String,
+    pub data_type: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub is_required: bool,
+    /// Class name a "reference" property points to
+    pub reference_class: Option<String>,
+}
+
+/// A class to add, or an existing class of the same name to extend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangesetClass {
+    pub name: String,
+    pub description: Option<String>,
+    /// Name of a class in the changeset or the target version
+    pub parent_class: Option<String>,
+    #[serde(default)]
+    pub properties: Vec<ChangesetProperty>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangesetRelationshipType {
+    pub name: String,
+    pub description: Option<String>,
+    /// Class names; omitted means any class
+    pub source_class: Option<String>,
+    pub target_class: Option<String>,
+    pub source_cardinality: Option<String>,
+    pub target_cardinality: Option<String>,
+}
+
+/// Schema changes referenced by name, so they can be reviewed before any ids exist
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SchemaChanges {
+    #[serde(default)]
+    pub classes: Vec<ChangesetClass>,
+    #[serde(default)]
+    pub relationship_types: Vec<ChangesetRelationshipType>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct OntologyChangeset {
+    pub id: Uuid,
+    pub source: String,
+    /// "draft", "applied" or "discarded"
+    pub status: String,
+    pub title: String,
+    pub rationale: Option<String>,
+    pub changes: sqlx::types::Json<SchemaChanges>,
+    pub applied_version_id: Option<Uuid>,
+    pub created_by: Option<Uuid>,
+    pub applied_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub applied_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateChangesetInput {
+    pub title: Option<String>,
+    pub changes: Option<SchemaChanges>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ApplyChangesetInput {
+    /// DRAFT version to apply the changes to
+    pub version_id: Uuid,
+}
+
+/// What applying a changeset created; existing classes, properties and
+/// relationship types with the same names are left as they are
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangesetApplyResult {
+    pub changeset: OntologyChangeset,
+    pub classes_created: usize,
+    pub properties_created: usize,
+    pub relationship_types_created: usize,
+}
+
+/// A likely duplicate pair queued for review, with both entities' names for display
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct EntityMergeSuggestion {
+    pub id: Uuid,
+    pub class_id: Uuid,
+    pub primary_entity_id: Uuid,
+    pub primary_entity_name: String,
+    pub duplicate_entity_id: Uuid,
+    pub duplicate_entity_name: String,
+    pub score: f64,
+    pub reasons: sqlx::types::Json<Vec<String>>,
+    /// "pending", "merged" or "rejected"
+    pub status: String,
+    pub reviewed_by: Option<Uuid>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ApproveMergeInput {
+    /// Keep this entity instead of the suggested primary; must be one of the pair
+    pub keep_entity_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EntityMergeResult {
+    pub suggestion: EntityMergeSuggestion,
+    /// Entity that was kept
+    pub entity_id: Uuid,
+    /// Entity that was folded in and soft-deleted
+    pub merged_entity_id: Uuid,
+    pub relationships_moved: u64,
+    pub children_moved: u64,
+}
+
+/// Relationship between two entities of a `GraphImport`, by index
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImportRelationshipInput {
+    pub source: usize,
+    pub target: usize,
+    /// Name of the relationship type
+    pub relationship_type: String,
+}
+
+/// New entities and the relationships between them, created together
+#[derive(Debug, Default, Deserialize)]
+pub struct GraphImport {
+    pub entities: Vec<CreateEntityInput>,
+    pub relationships: Vec<ImportRelationshipInput>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportedGraph {
+    pub entities: Vec<Entity>,
+    pub relationships: Vec<Relationship>,
+}
+
+// ============================================================================
+// SCHEMA CATALOGUE
+// ============================================================================
+
+/// The published ontology in one document, for code generators and validators.
+/// Holds nothing time-dependent so its ETag only changes with the schema.
+#[derive(Debug, Clone, Serialize)]
+pub struct OntologySchema {
+    /// Version string of the current published ontology, if one is published
+    pub version: Option<String>,
+    pub classes: Vec<SchemaClass>,
+    pub relationship_types: Vec<SchemaRelationshipType>,
+    pub permission_types: Vec<SchemaPermissionType>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct SchemaClass {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub parent_class_id: Option<Uuid>,
+    pub parent_class_name: Option<String>,
+    pub is_abstract: bool,
+    pub is_deprecated: bool,
+    /// Part of the built-in system ontology rather than the published version
+    pub is_system: bool,
+    #[sqlx(skip)]
+    pub properties: Vec<SchemaProperty>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct SchemaProperty {
+    pub id: Uuid,
+    #[serde(skip)]
+    pub class_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub data_type: String,
+    pub reference_class_id: Option<Uuid>,
+    pub reference_class_name: Option<String>,
+    pub is_required: bool,
+    pub is_unique: bool,
+    pub is_sensitive: bool,
+    pub default_value: Option<serde_json::Value>,
+    pub validation_rules: Option<serde_json::Value>,
+    pub is_deprecated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct SchemaRelationshipType {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub source_cardinality: Option<String>,
+    pub target_cardinality: Option<String>,
+    pub allowed_source_class_id: Option<Uuid>,
+    pub allowed_source_class_name: Option<String>,
+    pub allowed_target_class_id: Option<Uuid>,
+    pub allowed_target_class_name: Option<String>,
+    pub grants_permission_inheritance: bool,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct SchemaPermissionType {
+    pub name: String,
+    pub description: Option<String>,
+    pub level: i32,
+}
+
+// ============================================================================
+// SITEMAP
+// ============================================================================
+
+/// One entity as listed for external indexing and sync agents
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct SitemapEntry {
+    pub id: Uuid,
+    pub class_id: Uuid,
+    pub class_name: String,
+    pub display_name: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+// ============================================================================
+// EXPORT / IMPORT
+// ============================================================================
+
+/// The classes of a published version in a form another installation can
+/// import. Classes refer to each other by name, so ids don't carry over;
+/// names not defined in the file are looked up in the system ontology.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OntologyExport {
+    /// Version string the classes were exported from
+    pub version: Option<String>,
+    pub exported_at: DateTime<Utc>,
+    pub classes: Vec<ExportedClass>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedClass {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Name of the parent class
+    #[serde(default)]
+    pub parent: Option<String>,
+    #[serde(default)]
+    pub is_abstract: bool,
+    #[serde(default)]
+    pub properties: Vec<ExportedProperty>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedProperty {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub data_type: String,
+    /// Name of the class a reference property points at
+    #[serde(default)]
+    pub reference_class: Option<String>,
+    #[serde(default)]
+    pub is_required: bool,
+    #[serde(default)]
+    pub is_unique: bool,
+    #[serde(default)]
+    pub is_indexed: bool,
+    #[serde(default)]
+    pub is_sensitive: bool,
+    #[serde(default)]
+    pub default_value: Option<serde_json::Value>,
+    #[serde(default)]
+    pub validation_rules: Option<serde_json::Value>,
+}
+
+/// What an import created; the version is left as a draft to review and publish
+#[derive(Debug, Clone, Serialize)]
+pub struct OntologyImportSummary {
+    pub version: OntologyVersion,
+    pub classes: usize,
+    pub properties: usize,
+}
+
+// ============================================================================
+// DESIRED STATE
+// ============================================================================
+
+/// The whole shared ontology as it should be, e.g. kept in git and applied
+/// from CI. Classes use the export format, so `export-ontology` output is a
+/// valid starting point. Everything is referred to by name.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DesiredState {
+    #[serde(default)]
+    pub classes: Vec<ExportedClass>,
+    #[serde(default)]
+    pub relationship_types: Vec<DesiredRelationshipType>,
+    #[serde(default)]
+    pub permission_types: Vec<DesiredPermissionType>,
+    #[serde(default)]
+    pub roles: Vec<DesiredRole>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DesiredRelationshipType {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Class names; omitted means any class
+    #[serde(default)]
+    pub source_class: Option<String>,
+    #[serde(default)]
+    pub target_class: Option<String>,
+    /// "one" or "many" (the default)
+    #[serde(default)]
+    pub source_cardinality: Option<String>,
+    #[serde(default)]
+    pub target_cardinality: Option<String>,
+    #[serde(default)]
+    pub grants_permission_inheritance: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DesiredPermissionType {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub level: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DesiredRole {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub level: i32,
+    /// Permission types the role is granted. When given, grants not listed
+    /// are revoked; omitted leaves the role's grants alone.
+    #[serde(default)]
+    pub permissions: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanAction {
+    Create,
+    Update,
+    /// Classes and properties missing from the document, when pruning
+    Deprecate,
+    Remove,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanObject {
+    Class,
+    Property,
+    RelationshipType,
+    PermissionType,
+    Role,
+    RolePermission,
+}
+
+/// One step of a plan, in the order it is applied
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlannedChange {
+    pub action: PlanAction,
+    pub object: PlanObject,
+    /// The class of a property, or the role of a permission grant
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    pub name: String,
+    /// What an update changes
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldChange>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub from: serde_json::Value,
+    pub to: serde_json::Value,
+}
+
+/// The difference between a desired state and the current version
+#[derive(Debug, Clone, Serialize)]
+pub struct DesiredStatePlan {
+    /// The version the plan was computed against, and changed when applied
+    pub version: String,
+    pub changes: Vec<PlannedChange>,
+    /// Hash of the changes; pass it to apply to make sure exactly the
+    /// reviewed plan is applied
+    pub fingerprint: String,
+    pub applied: bool,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct DesiredStateInput {
+    pub document: DesiredState,
+    /// Deprecate classes and properties of the current version that the
+    /// document doesn't mention
+    #[serde(default)]
+    pub prune: bool,
+    /// Fingerprint of a reviewed plan; apply fails if the plan has changed
+    #[serde(default)]
+    pub fingerprint: Option<String>,
+}
@@ -1,14 +1,24 @@
+use super::labels::{requested_locales, LabelTarget, Localize};
 use super::models::*;
-use super::service::OntologyService;
+use super::service::{OntologyError, OntologyService};
+use crate::features::abac::AbacService;
 use crate::features::auth::jwt::Claims;
+use crate::middleware::abac::{
+    check_user_permission, enforce_route_permissions, PermissionError, RoutePermission,
+};
+use crate::utils::etag;
+use crate::utils::i18n::{accept_language, vary_on_language};
+use crate::utils::validation::{not_blank, ValidatedJson};
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     routing::{delete, get, post, put},
     Extension, Json, Router,
 };
 use serde::Deserialize;
 use uuid::Uuid;
+use validator::Validate;
 
 #[derive(Debug, Deserialize)]
 pub struct ListEntitiesQuery {
@@ -23,10 +33,36 @@ pub struct RelationshipsQuery {
 }
 
 #[derive(Debug, Deserialize)]
+pub struct ListChangesetsQuery {
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListMergeSuggestionsQuery {
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DetectDuplicatesInput {
+    /// Overrides the configured threshold for this run
+    pub min_score: Option<f64>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
 pub struct CloneVersionInput {
+    #[validate(custom = "not_blank", length(max = 50))]
     pub name: String,
 }
 
+/// Permissions enforced by `enforce_route_permissions` for the routes below.
+/// Everything else is checked per entity in the handlers.
+pub const ROUTE_PERMISSIONS: &[RoutePermission] = &[
+    // Deleted entities have no grants left to check against
+    RoutePermission::new("POST", "/entities/:id/restore", "manage_system"),
+    RoutePermission::new("POST", "/desired-state/plan", "manage_system"),
+    RoutePermission::new("POST", "/desired-state/apply", "manage_system"),
+];
+
 pub fn ontology_routes() -> Router<OntologyService> {
     Router::new()
         // Schema versions
@@ -41,27 +77,63 @@ pub fn ontology_routes() -> Router<OntologyService> {
             get(get_class).put(update_class).delete(delete_class),
         )
         .route("/classes/:id/properties", get(list_properties))
+        .route(
+            "/classes/:id/labels/:locale",
+            put(set_class_label).delete(remove_class_label),
+        )
         // Properties
         .route("/properties", post(create_property))
         .route(
             "/properties/:id",
             put(update_property).delete(delete_property),
         )
+        .route(
+            "/properties/:id/labels/:locale",
+            put(set_property_label).delete(remove_property_label),
+        )
         // Entities
         .route("/entities", get(list_entities).post(create_entity))
         .route(
             "/entities/:id",
             get(get_entity).put(update_entity).delete(delete_entity),
         )
+        .route(
+            "/entities/:id/labels/:locale",
+            put(set_entity_label).delete(remove_entity_label),
+        )
+        .route("/entities/:id/restore", post(restore_entity))
         .route("/entities/:id/approve", post(approve_entity))
         .route("/entities/:id/reject", post(reject_entity))
         .route("/entities/:id/ancestors", get(get_entity_ancestors))
         .route("/entities/:id/descendants", get(get_entity_descendants))
+        .route("/entities/:id/subgraph/export", get(export_entity_subgraph))
         .route("/entities/:id/relationships", get(get_entity_relationships))
         // Relationships
         .route("/relationship-types", get(list_relationship_types))
         .route("/relationships", post(create_relationship))
         .route("/relationships/:id", delete(delete_relationship))
+        // Changesets (reviewed before being applied to a DRAFT version)
+        .route("/changesets", get(list_changesets))
+        .route(
+            "/changesets/:id",
+            get(get_changeset)
+                .put(update_changeset)
+                .delete(discard_changeset),
+        )
+        .route("/changesets/:id/apply", post(apply_changeset))
+        // Declarative desired state: plan the diff, then apply it
+        .route("/desired-state/plan", post(plan_desired_state))
+        .route("/desired-state/apply", post(apply_desired_state))
+        // Duplicate entities queued for merge review
+        .route("/merge-suggestions", get(list_merge_suggestions))
+        .route("/merge-suggestions/detect", post(detect_duplicates))
+        .route("/merge-suggestions/:id", get(get_merge_suggestion))
+        .route("/merge-suggestions/:id/approve", post(approve_merge_suggestion))
+        .route("/merge-suggestions/:id/reject", post(reject_merge_suggestion))
+        .route_layer(axum::middleware::from_fn_with_state(
+            ROUTE_PERMISSIONS,
+            enforce_route_permissions,
+        ))
 }
 
 // ============================================================================
@@ -88,7 +160,7 @@ async fn get_current_version(
 
 async fn create_version(
     State(svc): State<OntologyService>,
-    Json(input): Json<CreateVersionInput>,
+    ValidatedJson(input): ValidatedJson<CreateVersionInput>,
 ) -> Result<Json<OntologyVersion>, StatusCode> {
     svc.create_version(input, None)
         .await
@@ -100,7 +172,7 @@ async fn clone_version(
     State(svc): State<OntologyService>,
     Extension(claims): Extension<Claims>,
     Path(id): Path<Uuid>,
-    Json(input): Json<CloneVersionInput>,
+    ValidatedJson(input): ValidatedJson<CloneVersionInput>,
 ) -> Result<Json<OntologyVersion>, StatusCode> {
     let user_id = Uuid::parse_str(&claims.sub).ok();
     svc.clone_version(id, input.name, user_id)
@@ -133,26 +205,35 @@ async fn publish_version(
 
 async fn list_classes(
     State(svc): State<OntologyService>,
-) -> Result<Json<Vec<ClassWithParent>>, StatusCode> {
-    svc.list_classes(None)
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let mut classes = svc
+        .list_classes(None)
         .await
-        .map(Json)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    classes.localize(&requested_locales(accept_language(&headers)));
+    let etag = etag::payload_etag(&classes);
+    Ok(vary_on_language(etag::conditional_json(
+        &headers, etag, classes,
+    )))
 }
 
 async fn get_class(
     State(svc): State<OntologyService>,
     Path(id): Path<Uuid>,
-) -> Result<Json<Class>, StatusCode> {
-    svc.get_class(id)
-        .await
-        .map(Json)
-        .map_err(|_| StatusCode::NOT_FOUND)
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let mut class = svc.get_class(id).await.map_err(|_| StatusCode::NOT_FOUND)?;
+    class.localize(&requested_locales(accept_language(&headers)));
+    let etag = etag::payload_etag(&class);
+    Ok(vary_on_language(etag::conditional_json(
+        &headers, etag, class,
+    )))
 }
 
 async fn create_class(
     State(svc): State<OntologyService>,
-    Json(input): Json<CreateClassInput>,
+    ValidatedJson(input): ValidatedJson<CreateClassInput>,
 ) -> Result<Json<Class>, StatusCode> {
     svc.create_class(input, None)
         .await
@@ -163,7 +244,7 @@ async fn create_class(
 async fn update_class(
     State(svc): State<OntologyService>,
     Path(id): Path<Uuid>,
-    Json(input): Json<UpdateClassInput>,
+    ValidatedJson(input): ValidatedJson<UpdateClassInput>,
 ) -> Result<Json<Class>, StatusCode> {
     svc.update_class(id, input)
         .await
@@ -188,16 +269,22 @@ async fn delete_class(
 async fn list_properties(
     State(svc): State<OntologyService>,
     Path(class_id): Path<Uuid>,
-) -> Result<Json<Vec<Property>>, StatusCode> {
-    svc.list_properties(class_id)
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let mut properties = svc
+        .list_properties(class_id)
         .await
-        .map(Json)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    properties.localize(&requested_locales(accept_language(&headers)));
+    let etag = etag::payload_etag(&properties);
+    Ok(vary_on_language(etag::conditional_json(
+        &headers, etag, properties,
+    )))
 }
 
 async fn create_property(
     State(svc): State<OntologyService>,
-    Json(input): Json<CreatePropertyInput>,
+    ValidatedJson(input): ValidatedJson<CreatePropertyInput>,
 ) -> Result<Json<Property>, StatusCode> {
     svc.create_property(input)
         .await
@@ -208,7 +295,7 @@ async fn create_property(
 async fn update_property(
     State(svc): State<OntologyService>,
     Path(id): Path<Uuid>,
-    Json(input): Json<UpdatePropertyInput>,
+    ValidatedJson(input): ValidatedJson<UpdatePropertyInput>,
 ) -> Result<Json<Property>, StatusCode> {
     svc.update_property(id, input)
         .await
@@ -233,86 +320,159 @@ async fn delete_property(
 async fn list_entities(
     State(svc): State<OntologyService>,
     Query(query): Query<ListEntitiesQuery>,
-) -> Result<Json<Vec<EntityWithDetails>>, StatusCode> {
-    svc.list_entities(query.class_id, query.tenant_id, query.is_root)
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let mut entities = svc
+        .list_entities(query.class_id, query.tenant_id, query.is_root)
         .await
-        .map(Json)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    entities.localize(&requested_locales(accept_language(&headers)));
+    // Rows include joined class/parent names, so hash the payload rather than updated_at
+    let etag = etag::payload_etag(&entities);
+    Ok(vary_on_language(etag::conditional_json(
+        &headers, etag, entities,
+    )))
 }
 
 async fn get_entity(
     State(svc): State<OntologyService>,
     Path(id): Path<Uuid>,
-) -> Result<Json<Entity>, StatusCode> {
-    svc.get_entity(id)
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let mut entity = svc
+        .get_entity(id)
         .await
-        .map(Json)
-        .map_err(|_| StatusCode::NOT_FOUND)
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    entity.localize(&requested_locales(accept_language(&headers)));
+    // Hash the payload rather than updated_at: it carries the resolved locale
+    // and its labels, which change without touching the entity
+    let etag = etag::payload_etag(&entity);
+    Ok(vary_on_language(etag::conditional_json(
+        &headers, etag, entity,
+    )))
+}
+
+/// Classes whose entities only holders of `manage_system` may create or
+/// change: an `AiProvider` decides where prompts, and the key it names, go
+const SYSTEM_CLASSES: &[&str] = &["AiProvider"];
+
+/// Whether the caller holds `permission`, on `entity_id` or globally
+async fn has_permission(
+    abac: &AbacService,
+    claims: &Claims,
+    permission: &str,
+    entity_id: Option<Uuid>,
+) -> Result<bool, OntologyError> {
+    let entity_id = entity_id.map(|id| id.to_string());
+    check_user_permission(
+        abac,
+        &claims.sub,
+        permission,
+        entity_id.as_deref(),
+        None,
+        None,
+    )
+    .await
+    .map_err(|e| match e {
+        PermissionError::InternalError(msg) => OntologyError::DatabaseError(msg),
+        _ => OntologyError::Forbidden("Permission check failed".to_string()),
+    })
+}
+
+async fn require_class_write(
+    svc: &OntologyService,
+    abac: &AbacService,
+    claims: &Claims,
+    class_id: Uuid,
+) -> Result<(), OntologyError> {
+    let class = svc.get_class(class_id).await?;
+    if !SYSTEM_CLASSES.contains(&class.name.as_str()) {
+        return Ok(());
+    }
+    if has_permission(abac, claims, "manage_system", None).await? {
+        Ok(())
+    } else {
+        Err(OntologyError::Forbidden(format!(
+            "{} entities can only be changed by system administrators",
+            class.name
+        )))
+    }
 }
 
 async fn create_entity(
     State(svc): State<OntologyService>,
-    Json(input): Json<CreateEntityInput>,
-) -> Result<Json<Entity>, (StatusCode, Json<serde_json::Value>)> {
-    svc.create_entity(input, None, None)
-        .await
-        .map(Json)
-        .map_err(|e| {
-            (
-                e.to_status_code(),
-                Json(serde_json::json!({ "error": e.to_string() })),
-            )
-        })
+    Extension(claims): Extension<Claims>,
+    Extension(abac): Extension<AbacService>,
+    ValidatedJson(input): ValidatedJson<CreateEntityInput>,
+) -> Result<Json<Entity>, OntologyError> {
+    require_class_write(&svc, &abac, &claims, input.class_id).await?;
+    svc.create_entity(input, None, None).await.map(Json)
 }
 
 async fn update_entity(
     State(svc): State<OntologyService>,
+    Extension(claims): Extension<Claims>,
+    Extension(abac): Extension<AbacService>,
     Path(id): Path<Uuid>,
-    Json(input): Json<UpdateEntityInput>,
-) -> Result<Json<Entity>, (StatusCode, Json<serde_json::Value>)> {
-    svc.update_entity(id, input, None)
-        .await
-        .map(Json)
-        .map_err(|e| {
-            (
-                e.to_status_code(),
-                Json(serde_json::json!({ "error": e.to_string() })),
-            )
-        })
+    ValidatedJson(input): ValidatedJson<UpdateEntityInput>,
+) -> Result<Json<Entity>, OntologyError> {
+    let entity = svc.get_entity(id).await?;
+    require_class_write(&svc, &abac, &claims, entity.class_id).await?;
+    svc.update_entity(id, input, None).await.map(Json)
 }
 
 async fn approve_entity(
     State(svc): State<OntologyService>,
     Path(id): Path<Uuid>,
-) -> Result<Json<Entity>, (StatusCode, Json<serde_json::Value>)> {
-    svc.approve_entity(id, None).await.map(Json).map_err(|e| {
-        (
-            e.to_status_code(),
-            Json(serde_json::json!({ "error": e.to_string() })),
-        )
-    })
+) -> Result<Json<Entity>, OntologyError> {
+    svc.approve_entity(id, None).await.map(Json)
 }
 
 async fn reject_entity(
     State(svc): State<OntologyService>,
     Path(id): Path<Uuid>,
-) -> Result<Json<Entity>, (StatusCode, Json<serde_json::Value>)> {
-    svc.reject_entity(id, None).await.map(Json).map_err(|e| {
-        (
-            e.to_status_code(),
-            Json(serde_json::json!({ "error": e.to_string() })),
-        )
-    })
+) -> Result<Json<Entity>, OntologyError> {
+    svc.reject_entity(id, None).await.map(Json)
 }
 
+/// `?dry_run=true` returns what the delete would remove without removing it.
+/// The caller needs `delete` on the entity and on every descendant the
+/// delete cascades to.
 async fn delete_entity(
     State(svc): State<OntologyService>,
+    Extension(claims): Extension<Claims>,
+    Extension(abac): Extension<AbacService>,
     Path(id): Path<Uuid>,
-) -> Result<StatusCode, StatusCode> {
-    svc.delete_entity(id, None)
-        .await
-        .map(|_| StatusCode::NO_CONTENT)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+    Query(query): Query<DeleteEntityQuery>,
+) -> Result<Response, OntologyError> {
+    let plan = svc.preview_entity_delete(id).await?;
+    let mut authorized = Vec::with_capacity(plan.deleted_entities.len());
+    for entity in &plan.deleted_entities {
+        if !has_permission(&abac, &claims, "delete", Some(entity.id)).await? {
+            return Err(OntologyError::Forbidden(format!(
+                "Deleting entity {} needs delete permission on entity {}",
+                id, entity.id
+            )));
+        }
+        authorized.push(entity.id);
+    }
+    if query.dry_run {
+        return Ok(Json(plan).into_response());
+    }
+    let user_id = Uuid::parse_str(&claims.sub).ok();
+    svc.delete_authorized_entity(id, user_id, &authorized)
+        .await?;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Undo a delete, with the relationships it archived
+async fn restore_entity(
+    State(svc): State<OntologyService>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Entity>, OntologyError> {
+    let user_id = Uuid::parse_str(&claims.sub).ok();
+    svc.restore_entity(id, user_id).await.map(Json)
 }
 
 async fn get_entity_ancestors(
@@ -335,6 +495,78 @@ async fn get_entity_descendants(
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
 }
 
+async fn export_entity_subgraph(
+    State(svc): State<OntologyService>,
+    Path(id): Path<Uuid>,
+) -> Result<Response, StatusCode> {
+    svc.get_entity(id).await.map_err(|e| e.to_status_code())?;
+    Ok(crate::utils::streaming::ndjson_response(
+        svc.stream_subgraph(id),
+        &format!("subgraph-{}.ndjson", id),
+    ))
+}
+
+// ============================================================================
+// LABELS
+// ============================================================================
+
+async fn set_class_label(
+    State(svc): State<OntologyService>,
+    Path((id, locale)): Path<(Uuid, String)>,
+    ValidatedJson(input): ValidatedJson<SetLabelInput>,
+) -> Result<Json<Labels>, OntologyError> {
+    svc.set_label(LabelTarget::Class, id, &locale, input)
+        .await
+        .map(Json)
+}
+
+async fn remove_class_label(
+    State(svc): State<OntologyService>,
+    Path((id, locale)): Path<(Uuid, String)>,
+) -> Result<Json<Labels>, OntologyError> {
+    svc.remove_label(LabelTarget::Class, id, &locale)
+        .await
+        .map(Json)
+}
+
+async fn set_property_label(
+    State(svc): State<OntologyService>,
+    Path((id, locale)): Path<(Uuid, String)>,
+    ValidatedJson(input): ValidatedJson<SetLabelInput>,
+) -> Result<Json<Labels>, OntologyError> {
+    svc.set_label(LabelTarget::Property, id, &locale, input)
+        .await
+        .map(Json)
+}
+
+async fn remove_property_label(
+    State(svc): State<OntologyService>,
+    Path((id, locale)): Path<(Uuid, String)>,
+) -> Result<Json<Labels>, OntologyError> {
+    svc.remove_label(LabelTarget::Property, id, &locale)
+        .await
+        .map(Json)
+}
+
+async fn set_entity_label(
+    State(svc): State<OntologyService>,
+    Path((id, locale)): Path<(Uuid, String)>,
+    ValidatedJson(input): ValidatedJson<SetLabelInput>,
+) -> Result<Json<Labels>, OntologyError> {
+    svc.set_label(LabelTarget::Entity, id, &locale, input)
+        .await
+        .map(Json)
+}
+
+async fn remove_entity_label(
+    State(svc): State<OntologyService>,
+    Path((id, locale)): Path<(Uuid, String)>,
+) -> Result<Json<Labels>, OntologyError> {
+    svc.remove_label(LabelTarget::Entity, id, &locale)
+        .await
+        .map(Json)
+}
+
 // ============================================================================
 // RELATIONSHIPS
 // ============================================================================
@@ -361,7 +593,7 @@ async fn get_entity_relationships(
 
 async fn create_relationship(
     State(svc): State<OntologyService>,
-    Json(input): Json<CreateRelationshipInput>,
+    ValidatedJson(input): ValidatedJson<CreateRelationshipInput>,
 ) -> Result<Json<Relationship>, StatusCode> {
     svc.create_relationship(input, None)
         .await
@@ -378,3 +610,168 @@ async fn delete_relationship(
         .map(|_| StatusCode::NO_CONTENT)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
 }
+
+// ============================================================================
+// CHANGESETS
+// ============================================================================
+
+async fn list_changesets(
+    State(svc): State<OntologyService>,
+    Query(query): Query<ListChangesetsQuery>,
+) -> Result<Json<Vec<OntologyChangeset>>, StatusCode> {
+    svc.list_changesets(query.status.as_deref())
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+async fn get_changeset(
+    State(svc): State<OntologyService>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<OntologyChangeset>, StatusCode> {
+    svc.get_changeset(id)
+        .await
+        .map(Json)
+        .map_err(|e| e.to_status_code())
+}
+
+async fn update_changeset(
+    State(svc): State<OntologyService>,
+    Path(id): Path<Uuid>,
+    ValidatedJson(input): ValidatedJson<UpdateChangesetInput>,
+) -> Result<Json<OntologyChangeset>, StatusCode> {
+    svc.update_changeset(id, input)
+        .await
+        .map(Json)
+        .map_err(|e| e.to_status_code())
+}
+
+async fn discard_changeset(
+    State(svc): State<OntologyService>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<OntologyChangeset>, StatusCode> {
+    svc.discard_changeset(id)
+        .await
+        .map(Json)
+        .map_err(|e| e.to_status_code())
+}
+
+async fn apply_changeset(
+    State(svc): State<OntologyService>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+    ValidatedJson(input): ValidatedJson<ApplyChangesetInput>,
+) -> Result<Json<ChangesetApplyResult>, StatusCode> {
+    let user_id = Uuid::parse_str(&claims.sub).ok();
+    svc.apply_changeset(id, input.version_id, user_id)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            tracing::error!(error = ?e, "apply_changeset failed");
+            e.to_status_code()
+        })
+}
+
+// ============================================================================
+// DESIRED STATE
+// ============================================================================
+
+async fn plan_desired_state(
+    State(svc): State<OntologyService>,
+    ValidatedJson(input): ValidatedJson<DesiredStateInput>,
+) -> Result<Json<DesiredStatePlan>, StatusCode> {
+    svc.plan_desired_state(&input.document, input.prune)
+        .await
+        .map(Json)
+        .map_err(|e| e.to_status_code())
+}
+
+async fn apply_desired_state(
+    State(svc): State<OntologyService>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(input): ValidatedJson<DesiredStateInput>,
+) -> Result<Json<DesiredStatePlan>, StatusCode> {
+    let user_id = Uuid::parse_str(&claims.sub).ok();
+    svc.apply_desired_state(
+        &input.document,
+        input.prune,
+        input.fingerprint.as_deref(),
+        user_id,
+    )
+    .await
+    .map(Json)
+    .map_err(|e| {
+        tracing::error!(error = ?e, "apply_desired_state failed");
+        e.to_status_code()
+    })
+}
+
+// ============================================================================
+// MERGE SUGGESTIONS
+// ============================================================================
+
+async fn list_merge_suggestions(
+    State(svc): State<OntologyService>,
+    Query(query): Query<ListMergeSuggestionsQuery>,
+) -> Result<Json<Vec<EntityMergeSuggestion>>, StatusCode> {
+    svc.list_merge_suggestions(query.status.as_deref())
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Run detection now instead of waiting for the background job
+async fn detect_duplicates(
+    State(svc): State<OntologyService>,
+    input: Option<Json<DetectDuplicatesInput>>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let min_score = input
+        .and_then(|Json(i)| i.min_score)
+        .unwrap_or(crate::config::DuplicateDetectionConfig::default().min_score);
+    if !(0.0..=1.0).contains(&min_score) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    svc.detect_duplicates(min_score)
+        .await
+        .map(|created| Json(serde_json::json!({ "created": created })))
+        .map_err(|e| e.to_status_code())
+}
+
+async fn get_merge_suggestion(
+    State(svc): State<OntologyService>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<EntityMergeSuggestion>, StatusCode> {
+    svc.get_merge_suggestion(id)
+        .await
+        .map(Json)
+        .map_err(|e| e.to_status_code())
+}
+
+async fn approve_merge_suggestion(
+    State(svc): State<OntologyService>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+    input: Option<Json<ApproveMergeInput>>,
+) -> Result<Json<EntityMergeResult>, StatusCode> {
+    let user_id = Uuid::parse_str(&claims.sub).ok();
+    let input = input.map(|Json(i)| i).unwrap_or_default();
+    svc.approve_merge_suggestion(id, input, user_id)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            tracing::error!(error = ?e, "approve_merge_suggestion failed");
+            e.to_status_code()
+        })
+}
+
+async fn reject_merge_suggestion(
+    State(svc): State<OntologyService>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<EntityMergeSuggestion>, StatusCode> {
+    let user_id = Uuid::parse_str(&claims.sub).ok();
+    svc.reject_merge_suggestion(id, user_id)
+        .await
+        .map(Json)
+        .map_err(|e| e.to_status_code())
+}
@@ -0,0 +1,103 @@
+use super::models::*;
+use super::service::{OntologyError, OntologyService};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+impl OntologyService {
+    // ========================================================================
+    // SCHEMA CATALOGUE
+    // ========================================================================
+
+    /// Classes of the system ontology and the current published version,
+    /// shared by every tenant, with their properties, plus relationship and
+    /// permission types. Tenant-specific classes and drafts are left out.
+    pub async fn published_schema(&self) -> Result<OntologySchema, OntologyError> {
+        let version = sqlx::query_scalar::<_, String>(
+            "SELECT version FROM ontology_versions WHERE is_current = TRUE",
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let mut classes = sqlx::query_as::<_, SchemaClass>(
+            r#"
+            SELECT c.id, c.name, c.description, c.parent_class_id,
+                   p.name AS parent_class_name, c.is_abstract, c.is_deprecated,
+                   ov.is_system
+            FROM classes c
+            JOIN ontology_versions ov ON ov.id = c.version_id
+            LEFT JOIN classes p ON p.id = c.parent_class_id
+            WHERE (ov.is_current OR ov.is_system) AND c.tenant_id IS NULL
+            ORDER BY c.name, c.id
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let class_ids: Vec<Uuid> = classes.iter().map(|c| c.id).collect();
+        let properties = sqlx::query_as::<_, SchemaProperty>(
+            r#"
+            SELECT pr.id, pr.class_id, pr.name, pr.description, pr.data_type,
+                   pr.reference_class_id, rc.name AS reference_class_name,
+                   pr.is_required, pr.is_unique, pr.is_sensitive,
+                   pr.default_value, pr.validation_rules, pr.is_deprecated
+            FROM properties pr
+            LEFT JOIN classes rc ON rc.id = pr.reference_class_id
+            WHERE pr.class_id = ANY($1)
+            ORDER BY pr.name, pr.id
+            "#,
+        )
+        .bind(&class_ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut by_class: HashMap<Uuid, Vec<SchemaProperty>> = HashMap::new();
+        for property in properties {
+            by_class
+                .entry(property.class_id)
+                .or_default()
+                .push(property);
+        }
+        for class in &mut classes {
+            class.properties = by_class.remove(&class.id).unwrap_or_default();
+        }
+
+        let relationship_types = sqlx::query_as::<_, SchemaRelationshipType>(
+            r#"
+            SELECT rt.id, rt.name, rt.description, rt.source_cardinality,
+                   rt.target_cardinality,
+                   rt.allowed_source_class_id, sc.name AS allowed_source_class_name,
+                   rt.allowed_target_class_id, tc.name AS allowed_target_class_name,
+                   rt.grants_permission_inheritance
+            FROM relationship_types rt
+            LEFT JOIN classes sc ON sc.id = rt.allowed_source_class_id
+            LEFT JOIN classes tc ON tc.id = rt.allowed_target_class_id
+            ORDER BY rt.name
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        // Permission types are entities of the system Permission class
+        let permission_types = sqlx::query_as::<_, SchemaPermissionType>(
+            r#"
+            SELECT e.display_name AS name,
+                   e.attributes->>'description' AS description,
+                   COALESCE((e.attributes->>'level')::int, 0) AS level
+            FROM entities e
+            JOIN classes c ON c.id = e.class_id
+            JOIN ontology_versions ov ON ov.id = c.version_id
+            WHERE c.name = 'Permission' AND ov.is_system AND e.deleted_at IS NULL
+            ORDER BY level, name
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(OntologySchema {
+            version,
+            classes,
+            relationship_types,
+            permission_types,
+        })
+    }
+}
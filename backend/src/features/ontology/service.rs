@@ -1,6 +1,14 @@
+use super::labels::LabelTarget;
+use super::loader::OntologyLoader;
 use super::models::*;
+use crate::features::notifications::templates::normalize_locale;
+use crate::utils::api_error::ApiError;
+use crate::utils::cache::{CacheScope, SharedCache};
 use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
 use sqlx::{Pool, Postgres};
+use std::collections::HashMap;
 use uuid::Uuid;
 
 #[derive(Debug)]
@@ -9,6 +17,8 @@ pub enum OntologyError {
     NotFound(String),
     InvalidInput(String),
     VersionConflict(String),
+    DeleteBlocked(String),
+    Forbidden(String),
 }
 
 impl std::fmt::Display for OntologyError {
@@ -18,6 +28,8 @@ impl std::fmt::Display for OntologyError {
             Self::NotFound(msg) => write!(f, "Not found: {}", msg),
             Self::InvalidInput(msg) => write!(f, "Invalid input: {}", msg),
             Self::VersionConflict(msg) => write!(f, "Version conflict: {}", msg),
+            Self::DeleteBlocked(msg) => write!(f, "Delete blocked: {}", msg),
+            Self::Forbidden(msg) => write!(f, "Forbidden: {}", msg),
         }
     }
 }
@@ -33,16 +45,38 @@ impl OntologyError {
         match self {
             Self::NotFound(_) => StatusCode::NOT_FOUND,
             Self::InvalidInput(_) => StatusCode::BAD_REQUEST,
-            Self::VersionConflict(_) => StatusCode::CONFLICT,
+            Self::VersionConflict(_) | Self::DeleteBlocked(_) => StatusCode::CONFLICT,
+            Self::Forbidden(_) => StatusCode::FORBIDDEN,
             Self::DatabaseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 }
 
+impl From<OntologyError> for ApiError {
+    fn from(err: OntologyError) -> Self {
+        let code = match &err {
+            OntologyError::NotFound(_) => "ontology.not_found",
+            OntologyError::InvalidInput(_) => "ontology.invalid_input",
+            OntologyError::VersionConflict(_) => "ontology.version_conflict",
+            OntologyError::DeleteBlocked(_) => "ontology.delete_blocked",
+            OntologyError::Forbidden(_) => "ontology.forbidden",
+            OntologyError::DatabaseError(_) => "internal",
+        };
+        ApiError::new(err.to_status_code(), code, err.to_string())
+    }
+}
+
+impl IntoResponse for OntologyError {
+    fn into_response(self) -> Response {
+        ApiError::from(self).into_response()
+    }
+}
+
 #[derive(Clone)]
 pub struct OntologyService {
-    pool: Pool<Postgres>,
-    audit_service: crate::features::system::AuditService,
+    pub(super) pool: Pool<Postgres>,
+    pub(super) audit_service: crate::features::system::AuditService,
+    pub(super) cache: Option<SharedCache>,
 }
 
 impl OntologyService {
@@ -50,6 +84,22 @@ impl OntologyService {
         Self {
             pool,
             audit_service,
+            cache: None,
+        }
+    }
+
+    /// Cache the system version, system classes and relationship types
+    pub fn with_cache(mut self, cache: SharedCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Drop cached ontology lookups and the aggregates derived from them.
+    /// Called after every change to classes, versions or relationship types.
+    pub async fn invalidate_cache(&self) {
+        if let Some(cache) = &self.cache {
+            cache.invalidate(CacheScope::Ontology).await;
+            cache.invalidate(CacheScope::Aggregates).await;
         }
     }
 
@@ -81,6 +131,19 @@ impl OntologyService {
 
     /// Get the system ontology version
     pub async fn get_system_version(&self) -> Result<OntologyVersion, OntologyError> {
+        match &self.cache {
+            Some(cache) => {
+                cache
+                    .get_or_load(CacheScope::Ontology, "system_version", || {
+                        self.load_system_version()
+                    })
+                    .await
+            }
+            None => self.load_system_version().await,
+        }
+    }
+
+    async fn load_system_version(&self) -> Result<OntologyVersion, OntologyError> {
         if let Some(version) = sqlx::query_as::<_, OntologyVersion>(
             "SELECT * FROM ontology_versions WHERE is_system = TRUE",
         )
@@ -117,6 +180,39 @@ impl OntologyService {
 
     /// Get a system class by name
     pub async fn get_system_class(&self, class_name: &str) -> Result<Class, OntologyError> {
+        match &self.cache {
+            Some(cache) => {
+                let key = format!("system_class:{}", class_name);
+                cache
+                    .get_or_load(CacheScope::Ontology, &key, || {
+                        self.load_system_class(class_name)
+                    })
+                    .await
+            }
+            None => match OntologyLoader::current() {
+                Some(loader) => loader
+                    .system_classes(|| self.load_system_classes())
+                    .await?
+                    .get(class_name)
+                    .cloned()
+                    .ok_or_else(|| {
+                        OntologyError::NotFound(format!("System class '{}' not found", class_name))
+                    }),
+                None => self.load_system_class(class_name).await,
+            },
+        }
+    }
+
+    async fn load_system_classes(&self) -> Result<HashMap<String, Class>, OntologyError> {
+        let system_version = self.get_system_version().await?;
+        let classes = sqlx::query_as::<_, Class>("SELECT * FROM classes WHERE version_id = $1")
+            .bind(system_version.id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(classes.into_iter().map(|c| (c.name.clone(), c)).collect())
+    }
+
+    async fn load_system_class(&self, class_name: &str) -> Result<Class, OntologyError> {
         let system_version = self.get_system_version().await?;
         sqlx::query_as::<_, Class>("SELECT * FROM classes WHERE name = $1 AND version_id = $2")
             .bind(class_name)
@@ -127,6 +223,7 @@ impl OntologyService {
                 OntologyError::NotFound(format!("System class '{}' not found", class_name))
             })
     }
+
     pub async fn create_version(
         &self,
         input: CreateVersionInput,
@@ -327,6 +424,7 @@ impl OntologyService {
                 .await;
         }
 
+        self.invalidate_cache().await;
         Ok(version)
     }
 
@@ -342,7 +440,7 @@ impl OntologyService {
             r#"
             SELECT c.id, c.name, c.description, c.parent_class_id, 
                    p.name as parent_class_name, c.version_id,
-                   c.is_abstract, c.is_deprecated, c.created_at
+                   c.is_abstract, c.is_deprecated, c.delete_policy, c.labels, c.created_at
             FROM classes c
             LEFT JOIN classes p ON c.parent_class_id = p.id
             WHERE (c.tenant_id IS NULL OR c.tenant_id = $1)
@@ -404,6 +502,7 @@ impl OntologyService {
              // Ah, I need to fix the SERVICE signature to accept user_id properly if I want to audit it.
         }
 
+        self.invalidate_cache().await;
         Ok(class)
     }
 
@@ -424,6 +523,7 @@ impl OntologyService {
                 is_abstract = COALESCE($5, is_abstract),
                 is_deprecated = COALESCE($6, is_deprecated),
                 deprecated_at = CASE WHEN $6 = TRUE AND is_deprecated = FALSE THEN NOW() ELSE deprecated_at END,
+                delete_policy = COALESCE($7, delete_policy),
                 updated_at = NOW()
             WHERE id = $1
             RETURNING *
@@ -435,9 +535,11 @@ impl OntologyService {
         .bind(input.parent_class_id.or(existing.parent_class_id))
         .bind(input.is_abstract)
         .bind(input.is_deprecated)
+        .bind(input.delete_policy)
         .fetch_one(&self.pool)
         .await?;
 
+        self.invalidate_cache().await;
         Ok(class)
     }
 
@@ -453,6 +555,7 @@ impl OntologyService {
         if result.rows_affected() == 0 {
             return Err(OntologyError::NotFound(format!("Class {} not found", id)));
         }
+        self.invalidate_cache().await;
         Ok(())
     }
 
@@ -584,6 +687,74 @@ impl OntologyService {
         Ok(())
     }
 
+    // ========================================================================
+    // LABELS
+    // ========================================================================
+
+    /// Add or replace the `locale` label of a class, property or entity.
+    /// Labels aren't part of the schema, so published versions can be
+    /// translated too.
+    pub async fn set_label(
+        &self,
+        target: LabelTarget,
+        id: Uuid,
+        locale: &str,
+        input: SetLabelInput,
+    ) -> Result<Labels, OntologyError> {
+        let locale = label_locale(locale)?;
+        let label = Label {
+            display_name: input.display_name,
+            description: input.description,
+        };
+        if label.display_name.is_none() && label.description.is_none() {
+            return Err(OntologyError::InvalidInput(
+                "A label needs a display name or a description".to_string(),
+            ));
+        }
+
+        let labels = sqlx::query_scalar::<_, sqlx::types::Json<Labels>>(
+            &target.update_sql("labels = jsonb_set(labels, ARRAY[$2::text], $3)"),
+        )
+        .bind(id)
+        .bind(&locale)
+        .bind(sqlx::types::Json(&label))
+        .fetch_optional(&self.pool)
+        .await?;
+        self.labels_changed(target, id, labels).await
+    }
+
+    pub async fn remove_label(
+        &self,
+        target: LabelTarget,
+        id: Uuid,
+        locale: &str,
+    ) -> Result<Labels, OntologyError> {
+        let locale = label_locale(locale)?;
+        let labels = sqlx::query_scalar::<_, sqlx::types::Json<Labels>>(
+            &target.update_sql("labels = labels - $2::text"),
+        )
+        .bind(id)
+        .bind(&locale)
+        .fetch_optional(&self.pool)
+        .await?;
+        self.labels_changed(target, id, labels).await
+    }
+
+    async fn labels_changed(
+        &self,
+        target: LabelTarget,
+        id: Uuid,
+        labels: Option<sqlx::types::Json<Labels>>,
+    ) -> Result<Labels, OntologyError> {
+        let labels = labels.ok_or_else(|| {
+            OntologyError::NotFound(format!("{} {} not found", target.noun(), id))
+        })?;
+        if target != LabelTarget::Entity {
+            self.invalidate_cache().await;
+        }
+        Ok(labels.0)
+    }
+
     // ========================================================================
     // ENTITIES
     // ========================================================================
@@ -599,7 +770,7 @@ impl OntologyService {
             SELECT e.id, e.class_id, c.name as class_name, e.display_name,
                    e.parent_entity_id, p.display_name as parent_entity_name,
                    e.tenant_id,
-                   e.attributes, e.approval_status, e.created_at, e.updated_at
+                   e.attributes, e.approval_status, e.labels, e.created_at, e.updated_at
             FROM entities e
             JOIN classes c ON e.class_id = c.id
             LEFT JOIN entities p ON e.parent_entity_id = p.id
@@ -621,11 +792,32 @@ impl OntologyService {
     }
 
     pub async fn get_entity(&self, id: Uuid) -> Result<Entity, OntologyError> {
-        sqlx::query_as::<_, Entity>("SELECT * FROM entities WHERE id = $1 AND deleted_at IS NULL")
-            .bind(id)
-            .fetch_optional(&self.pool)
-            .await?
-            .ok_or_else(|| OntologyError::NotFound(format!("Entity {} not found", id)))
+        let entity = match OntologyLoader::current() {
+            Some(loader) => loader.entities(&self.pool, &[id]).await?.remove(&id),
+            None => {
+                sqlx::query_as::<_, Entity>(
+                    "SELECT * FROM entities WHERE id = $1 AND deleted_at IS NULL",
+                )
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await?
+            }
+        };
+        entity.ok_or_else(|| OntologyError::NotFound(format!("Entity {} not found", id)))
+    }
+
+    /// The entities with these ids that exist, in one query
+    pub async fn get_entities(&self, ids: &[Uuid]) -> Result<HashMap<Uuid, Entity>, OntologyError> {
+        if let Some(loader) = OntologyLoader::current() {
+            return loader.entities(&self.pool, ids).await;
+        }
+        let entities = sqlx::query_as::<_, Entity>(
+            "SELECT * FROM entities WHERE id = ANY($1) AND deleted_at IS NULL",
+        )
+        .bind(ids)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(entities.into_iter().map(|e| (e.id, e)).collect())
     }
 
     pub async fn create_entity(
@@ -720,24 +912,192 @@ impl OntologyService {
         Ok(entity)
     }
 
+    /// Soft-delete an entity, applying each deleted entity's class delete
+    /// policy to its children and archiving relationships that touch a
+    /// deleted entity so `restore_entity` can bring them back
     pub async fn delete_entity(
         &self,
         id: Uuid,
         user_id: Option<Uuid>,
-    ) -> Result<(), OntologyError> {
-        // Soft delete
-        let result = sqlx::query(
-            "UPDATE entities SET deleted_at = NOW(), deleted_by = $2 WHERE id = $1 AND deleted_at IS NULL"
+    ) -> Result<DeletePlan, OntologyError> {
+        self.delete_planned(id, user_id, None).await
+    }
+
+    /// `delete_entity` for a caller cleared to delete only `authorized`,
+    /// usually the entities of an earlier `preview_entity_delete`. Fails
+    /// without deleting anything if the tree has since grown past them.
+    pub async fn delete_authorized_entity(
+        &self,
+        id: Uuid,
+        user_id: Option<Uuid>,
+        authorized: &[Uuid],
+    ) -> Result<DeletePlan, OntologyError> {
+        self.delete_planned(id, user_id, Some(authorized)).await
+    }
+
+    async fn delete_planned(
+        &self,
+        id: Uuid,
+        user_id: Option<Uuid>,
+        authorized: Option<&[Uuid]>,
+    ) -> Result<DeletePlan, OntologyError> {
+        let mut tx = self.pool.begin().await?;
+        let plan = plan_entity_delete(&mut tx, id).await?;
+        if plan.is_blocked() {
+            return Err(OntologyError::DeleteBlocked(format!(
+                "Entity {} has {} descendants whose parent's class blocks deleting them",
+                id,
+                plan.blocking_entities.len()
+            )));
+        }
+        if let Some(authorized) = authorized {
+            if let Some(extra) = plan
+                .deleted_entities
+                .iter()
+                .find(|e| !authorized.contains(&e.id))
+            {
+                return Err(OntologyError::VersionConflict(format!(
+                    "Entity {} would now also delete {}, retry the delete",
+                    id, extra.id
+                )));
+            }
+        }
+
+        let deleted: Vec<Uuid> = plan.deleted_entities.iter().map(|e| e.id).collect();
+        sqlx::query(
+            "UPDATE entities SET deleted_at = NOW(), deleted_by = $2 WHERE id = ANY($1) AND deleted_at IS NULL",
         )
-        .bind(id)
+        .bind(&deleted)
         .bind(user_id)
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?;
 
-        if result.rows_affected() == 0 {
-            return Err(OntologyError::NotFound(format!("Entity {} not found", id)));
+        if !plan.detached_entities.is_empty() {
+            let detached: Vec<Uuid> = plan.detached_entities.iter().map(|e| e.id).collect();
+            sqlx::query(
+                "UPDATE entities SET parent_entity_id = NULL, updated_by = $2, updated_at = NOW() WHERE id = ANY($1)",
+            )
+            .bind(&detached)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
         }
-        Ok(())
+
+        if !plan.removed_relationships.is_empty() {
+            let removed: Vec<Uuid> = plan.removed_relationships.iter().map(|r| r.id).collect();
+            sqlx::query(
+                r#"
+                WITH removed AS (
+                    DELETE FROM relationships WHERE id = ANY($1) RETURNING *
+                )
+                INSERT INTO archived_relationships
+                    (id, source_entity_id, target_entity_id, relationship_type_id, metadata,
+                     tenant_id, created_by, created_at, deleted_with_entity_id, archived_by)
+                SELECT id, source_entity_id, target_entity_id, relationship_type_id, metadata,
+                       tenant_id, created_by, created_at, $2, $3
+                FROM removed
+                "#,
+            )
+            .bind(&removed)
+            .bind(id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(plan)
+    }
+
+    /// Undo `delete_entity`: the entity and the descendants deleted with it
+    /// come back, as do the archived relationships whose endpoints are both
+    /// live again. Children the delete detached keep no parent.
+    pub async fn restore_entity(
+        &self,
+        id: Uuid,
+        user_id: Option<Uuid>,
+    ) -> Result<Entity, OntologyError> {
+        let mut tx = self.pool.begin().await?;
+        // Everything one delete removed shares its transaction's NOW()
+        let (deleted_at, parent_deleted) = sqlx::query_as::<_, (DateTime<Utc>, bool)>(
+            r#"
+            SELECT e.deleted_at, COALESCE(p.deleted_at IS NOT NULL, FALSE)
+            FROM entities e
+            LEFT JOIN entities p ON p.id = e.parent_entity_id
+            WHERE e.id = $1 AND e.deleted_at IS NOT NULL
+            FOR UPDATE OF e
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| OntologyError::NotFound(format!("Deleted entity {} not found", id)))?;
+        if parent_deleted {
+            return Err(OntologyError::InvalidInput(format!(
+                "The parent of entity {} is deleted, restore it first",
+                id
+            )));
+        }
+
+        sqlx::query(
+            r#"
+            WITH RECURSIVE restored AS (
+                SELECT id FROM entities WHERE id = $1
+                UNION
+                SELECT e.id FROM entities e
+                JOIN restored r ON e.parent_entity_id = r.id
+                WHERE e.deleted_at = $2
+            )
+            UPDATE entities
+            SET deleted_at = NULL, deleted_by = NULL, updated_by = $3, updated_at = NOW()
+            WHERE id IN (SELECT id FROM restored)
+            "#,
+        )
+        .bind(id)
+        .bind(deleted_at)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+        // Only rows that made it back leave the archive; one that conflicts
+        // with a relationship created since stays archived rather than lost
+        sqlx::query(
+            r#"
+            WITH restored AS (
+                INSERT INTO relationships
+                    (id, source_entity_id, target_entity_id, relationship_type_id, metadata,
+                     tenant_id, created_by, created_at)
+                SELECT a.id, a.source_entity_id, a.target_entity_id, a.relationship_type_id,
+                       a.metadata, a.tenant_id, a.created_by, a.created_at
+                FROM archived_relationships a
+                WHERE a.deleted_with_entity_id = $1
+                  AND EXISTS (SELECT 1 FROM entities s WHERE s.id = a.source_entity_id AND s.deleted_at IS NULL)
+                  AND EXISTS (SELECT 1 FROM entities t WHERE t.id = a.target_entity_id AND t.deleted_at IS NULL)
+                ON CONFLICT DO NOTHING
+                RETURNING id
+            )
+            DELETE FROM archived_relationships
+            WHERE id IN (SELECT id FROM restored)
+            "#,
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        let entity = sqlx::query_as::<_, Entity>("SELECT * FROM entities WHERE id = $1")
+            .bind(id)
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(entity)
+    }
+
+    /// What `delete_entity` would do, without changing anything
+    pub async fn preview_entity_delete(&self, id: Uuid) -> Result<DeletePlan, OntologyError> {
+        let mut tx = self.pool.begin().await?;
+        let plan = plan_entity_delete(&mut tx, id).await?;
+        tx.rollback().await?;
+        Ok(plan)
     }
 
     pub async fn approve_entity(
@@ -815,12 +1175,72 @@ impl OntologyService {
         Ok(descendants)
     }
 
+    /// Stream an entity, all of its descendants and the relationships between them.
+    /// Entities are emitted first so importers can resolve relationship endpoints.
+    pub fn stream_subgraph(
+        &self,
+        root_id: Uuid,
+    ) -> tokio_stream::wrappers::ReceiverStream<Result<SubgraphRecord, sqlx::Error>> {
+        use futures::StreamExt;
+
+        let pool = self.pool.clone();
+        let (tx, rx) =
+            tokio::sync::mpsc::channel(crate::utils::streaming::EXPORT_CHANNEL_CAPACITY);
+
+        tokio::spawn(async move {
+            let mut entities = sqlx::query_as::<_, Entity>(
+                r#"
+                SELECT * FROM entities WHERE id = $1 AND deleted_at IS NULL
+                UNION ALL
+                SELECT e.* FROM entities e
+                JOIN get_entity_descendants($1) d ON d.descendant_id = e.id
+                "#,
+            )
+            .bind(root_id)
+            .fetch(&pool)
+            .map(|row| row.map(SubgraphRecord::Entity));
+
+            while let Some(row) = entities.next().await {
+                let failed = row.is_err();
+                if tx.send(row).await.is_err() || failed {
+                    return;
+                }
+            }
+            drop(entities);
+
+            let mut relationships = sqlx::query_as::<_, Relationship>(
+                r#"
+                WITH subgraph AS (
+                    SELECT $1::uuid AS id
+                    UNION ALL
+                    SELECT descendant_id FROM get_entity_descendants($1)
+                )
+                SELECT r.* FROM relationships r
+                WHERE r.source_entity_id IN (SELECT id FROM subgraph)
+                  AND r.target_entity_id IN (SELECT id FROM subgraph)
+                "#,
+            )
+            .bind(root_id)
+            .fetch(&pool)
+            .map(|row| row.map(SubgraphRecord::Relationship));
+
+            while let Some(row) = relationships.next().await {
+                let failed = row.is_err();
+                if tx.send(row).await.is_err() || failed {
+                    return;
+                }
+            }
+        });
+
+        tokio_stream::wrappers::ReceiverStream::new(rx)
+    }
+
     // ========================================================================
     // RELATIONSHIPS
     // ========================================================================
 
     /// Validates entity attributes against class property definitions and rules
-    async fn validate_entity_attributes(
+    pub(super) async fn validate_entity_attributes(
         &self,
         class_id: Uuid,
         attributes: &serde_json::Value,
@@ -959,24 +1379,59 @@ impl OntologyService {
         Ok(types)
     }
 
+    /// A relationship type by name
+    pub async fn get_relationship_type(
+        &self,
+        name: &str,
+    ) -> Result<RelationshipType, OntologyError> {
+        match &self.cache {
+            Some(cache) => {
+                let key = format!("relationship_type:{}", name);
+                cache
+                    .get_or_load(CacheScope::Ontology, &key, || {
+                        self.load_relationship_type(name)
+                    })
+                    .await
+            }
+            None => match OntologyLoader::current() {
+                Some(loader) => loader
+                    .relationship_types(|| async {
+                        let types = self.list_relationship_types().await?;
+                        Ok(types.into_iter().map(|t| (t.name.clone(), t)).collect())
+                    })
+                    .await?
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| {
+                        OntologyError::NotFound(format!("Relationship type '{}' not found", name))
+                    }),
+                None => self.load_relationship_type(name).await,
+            },
+        }
+    }
+
+    async fn load_relationship_type(&self, name: &str) -> Result<RelationshipType, OntologyError> {
+        sqlx::query_as::<_, RelationshipType>("SELECT * FROM relationship_types WHERE name = $1")
+            .bind(name)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| {
+                OntologyError::NotFound(format!("Relationship type '{}' not found", name))
+            })
+    }
+
     pub async fn create_relationship(
         &self,
         input: CreateRelationshipInput,
         user_id: Option<Uuid>,
     ) -> Result<Relationship, OntologyError> {
-        // Find relationship type by name
-        let rel_type = sqlx::query_as::<_, RelationshipType>(
-            "SELECT * FROM relationship_types WHERE name = $1",
-        )
-        .bind(&input.relationship_type)
-        .fetch_optional(&self.pool)
-        .await?
-        .ok_or_else(|| {
-            OntologyError::InvalidInput(format!(
-                "Relationship type '{}' not found",
-                input.relationship_type
-            ))
-        })?;
+        let rel_type = self
+            .get_relationship_type(&input.relationship_type)
+            .await
+            .map_err(|e| match e {
+                OntologyError::NotFound(msg) => OntologyError::InvalidInput(msg),
+                other => other,
+            })?;
 
         let relationship = sqlx::query_as::<_, Relationship>(
             r#"
@@ -1045,3 +1500,102 @@ impl OntologyService {
         Ok(())
     }
 }
+
+/// Work out what deleting entity `id` touches, walking down from it one
+/// level at a time: each deleted entity's own class policy decides whether
+/// its children are deleted too, detached, or block the delete. Every row
+/// visited is locked, so no child can be added under a deleted entity until
+/// the transaction ends.
+async fn plan_entity_delete(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    id: Uuid,
+) -> Result<DeletePlan, OntologyError> {
+    let (class_id, display_name, policy) = sqlx::query_as::<_, (Uuid, String, DeletePolicy)>(
+        r#"
+        SELECT e.class_id, e.display_name, c.delete_policy
+        FROM entities e
+        JOIN classes c ON c.id = e.class_id
+        WHERE e.id = $1 AND e.deleted_at IS NULL
+        FOR UPDATE OF e
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(&mut **tx)
+    .await?
+    .ok_or_else(|| OntologyError::NotFound(format!("Entity {} not found", id)))?;
+
+    let mut plan = DeletePlan {
+        entity_id: id,
+        policy,
+        deleted_entities: vec![AffectedEntity {
+            id,
+            class_id,
+            display_name,
+        }],
+        detached_entities: Vec::new(),
+        removed_relationships: Vec::new(),
+        blocking_entities: Vec::new(),
+    };
+    // Deleted entities whose children haven't been looked at, with their policy
+    let mut level: HashMap<Uuid, DeletePolicy> = HashMap::from([(id, policy)]);
+    while !level.is_empty() {
+        let parents: Vec<Uuid> = level.keys().copied().collect();
+        let children = sqlx::query_as::<_, (Uuid, Uuid, String, Uuid, DeletePolicy)>(
+            r#"
+            SELECT e.id, e.class_id, e.display_name, e.parent_entity_id, c.delete_policy
+            FROM entities e
+            JOIN classes c ON c.id = e.class_id
+            WHERE e.parent_entity_id = ANY($1) AND e.deleted_at IS NULL
+            ORDER BY e.display_name
+            FOR UPDATE OF e
+            "#,
+        )
+        .bind(&parents)
+        .fetch_all(&mut **tx)
+        .await?;
+
+        let mut next = HashMap::new();
+        for (child_id, class_id, display_name, parent_id, child_policy) in children {
+            let child = AffectedEntity {
+                id: child_id,
+                class_id,
+                display_name,
+            };
+            match level[&parent_id] {
+                DeletePolicy::Block => plan.blocking_entities.push(child),
+                DeletePolicy::Detach => plan.detached_entities.push(child),
+                DeletePolicy::Cascade => {
+                    // A corrupt tree could loop back to an entity already planned
+                    if plan.deleted_entities.iter().any(|e| e.id == child_id) {
+                        continue;
+                    }
+                    plan.deleted_entities.push(child);
+                    next.insert(child_id, child_policy);
+                }
+            }
+        }
+        level = next;
+    }
+    if plan.is_blocked() {
+        return Ok(plan);
+    }
+
+    let deleted: Vec<Uuid> = plan.deleted_entities.iter().map(|e| e.id).collect();
+    plan.removed_relationships = sqlx::query_as::<_, Relationship>(
+        r#"
+        SELECT * FROM relationships
+        WHERE source_entity_id = ANY($1) OR target_entity_id = ANY($1)
+        ORDER BY created_at
+        "#,
+    )
+    .bind(&deleted)
+    .fetch_all(&mut **tx)
+    .await?;
+    Ok(plan)
+}
+
+/// `locale` as labels are keyed
+fn label_locale(locale: &str) -> Result<String, OntologyError> {
+    normalize_locale(locale)
+        .ok_or_else(|| OntologyError::InvalidInput(format!("'{}' is not a valid locale", locale)))
+}
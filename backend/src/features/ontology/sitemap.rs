@@ -0,0 +1,42 @@
+use super::models::SitemapEntry;
+use super::service::{OntologyError, OntologyService};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+impl OntologyService {
+    // ========================================================================
+    // SITEMAP
+    // ========================================================================
+
+    /// Live entities of user-defined classes visible in the tenant, in
+    /// `(updated_at, id)` order starting after the given position, or from
+    /// the start without one. Edits bump `updated_at`, so a changed entity
+    /// moves to the end of the list.
+    pub async fn sitemap_batch(
+        &self,
+        tenant_id: Option<Uuid>,
+        after: Option<(DateTime<Utc>, Uuid)>,
+        limit: i64,
+    ) -> Result<Vec<SitemapEntry>, OntologyError> {
+        let entries = sqlx::query_as::<_, SitemapEntry>(
+            r#"
+            SELECT e.id, e.class_id, c.name AS class_name, e.display_name, e.updated_at
+            FROM entities e
+            JOIN classes c ON c.id = e.class_id
+            JOIN ontology_versions ov ON ov.id = c.version_id
+            WHERE e.deleted_at IS NULL AND NOT ov.is_system
+              AND (e.tenant_id IS NULL OR e.tenant_id = $1)
+              AND ($2::timestamptz IS NULL OR (e.updated_at, e.id) > ($2, $3))
+            ORDER BY e.updated_at, e.id
+            LIMIT $4
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(after.map(|(updated_at, _)| updated_at))
+        .bind(after.map(|(_, id)| id))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(entries)
+    }
+}
@@ -0,0 +1,311 @@
+use super::models::*;
+use super::service::{OntologyError, OntologyService};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+impl OntologyService {
+    // ========================================================================
+    // EXPORT / IMPORT
+    // ========================================================================
+
+    /// The live classes of the current published version with their
+    /// properties. System and tenant classes are left out; every
+    /// installation has the former and the latter aren't shared.
+    pub async fn export_ontology(&self) -> Result<OntologyExport, OntologyError> {
+        let version = self.get_current_version().await?;
+        let classes = sqlx::query_as::<_, (Uuid, String, Option<String>, Option<String>, bool)>(
+            r#"
+            SELECT c.id, c.name, c.description, p.name, c.is_abstract
+            FROM classes c
+            LEFT JOIN classes p ON p.id = c.parent_class_id
+            WHERE c.version_id = $1 AND c.tenant_id IS NULL AND NOT c.is_deprecated
+            ORDER BY c.name, c.id
+            "#,
+        )
+        .bind(version.id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let class_ids: Vec<Uuid> = classes.iter().map(|c| c.0).collect();
+        let properties = sqlx::query_as::<_, Property>(
+            "SELECT * FROM properties WHERE class_id = ANY($1) AND NOT is_deprecated ORDER BY name, id",
+        )
+        .bind(&class_ids)
+        .fetch_all(&self.pool)
+        .await?;
+        let reference_ids: Vec<Uuid> = properties
+            .iter()
+            .filter_map(|p| p.reference_class_id)
+            .collect();
+        let reference_names: HashMap<Uuid, String> =
+            sqlx::query_as::<_, (Uuid, String)>("SELECT id, name FROM classes WHERE id = ANY($1)")
+                .bind(&reference_ids)
+                .fetch_all(&self.pool)
+                .await?
+                .into_iter()
+                .collect();
+
+        let mut by_class: HashMap<Uuid, Vec<ExportedProperty>> = HashMap::new();
+        for property in properties {
+            by_class
+                .entry(property.class_id)
+                .or_default()
+                .push(ExportedProperty {
+                    reference_class: property
+                        .reference_class_id
+                        .and_then(|id| reference_names.get(&id).cloned()),
+                    name: property.name,
+                    description: property.description,
+                    data_type: property.data_type,
+                    is_required: property.is_required,
+                    is_unique: property.is_unique,
+                    is_indexed: property.is_indexed,
+                    is_sensitive: property.is_sensitive,
+                    default_value: property.default_value,
+                    validation_rules: property.validation_rules,
+                });
+        }
+
+        Ok(OntologyExport {
+            version: Some(version.version),
+            exported_at: chrono::Utc::now(),
+            classes: classes
+                .into_iter()
+                .map(
+                    |(id, name, description, parent, is_abstract)| ExportedClass {
+                        name,
+                        description,
+                        parent,
+                        is_abstract,
+                        properties: by_class.remove(&id).unwrap_or_default(),
+                    },
+                )
+                .collect(),
+        })
+    }
+
+    /// Create a new draft version holding the exported classes. Nothing is
+    /// written unless every parent and reference resolves.
+    pub async fn import_ontology(
+        &self,
+        export: &OntologyExport,
+        version: &str,
+        user_id: Option<Uuid>,
+    ) -> Result<OntologyImportSummary, OntologyError> {
+        let ordered = parents_first(&export.classes)?;
+
+        // Names the file doesn't define must be system classes
+        let system_classes: HashMap<String, Uuid> = sqlx::query_as::<_, (String, Uuid)>(
+            r#"
+            SELECT c.name, c.id FROM classes c
+            JOIN ontology_versions ov ON ov.id = c.version_id
+            WHERE ov.is_system AND c.tenant_id IS NULL
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .collect();
+        let defined: HashSet<&str> = export.classes.iter().map(|c| c.name.as_str()).collect();
+        for class in &export.classes {
+            let referenced = class.parent.iter().chain(
+                class
+                    .properties
+                    .iter()
+                    .filter_map(|p| p.reference_class.as_ref()),
+            );
+            for name in referenced {
+                if !defined.contains(name.as_str()) && !system_classes.contains_key(name) {
+                    return Err(OntologyError::InvalidInput(format!(
+                        "Class '{}' refers to unknown class '{}'",
+                        class.name, name
+                    )));
+                }
+            }
+        }
+
+        let mut tx = self.pool.begin().await?;
+        let new_version = sqlx::query_as::<_, OntologyVersion>(
+            r#"
+            INSERT INTO ontology_versions (version, description, status, is_current, created_by)
+            VALUES ($1, $2, 'DRAFT', FALSE, $3)
+            RETURNING *
+            "#,
+        )
+        .bind(version)
+        .bind(format!(
+            "Imported from {}",
+            export.version.as_deref().unwrap_or("an export")
+        ))
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let mut class_ids = system_classes;
+        for class in &ordered {
+            let id = sqlx::query_scalar::<_, Uuid>(
+                r#"
+                INSERT INTO classes (name, description, parent_class_id, version_id, is_abstract)
+                VALUES ($1, $2, $3, $4, $5)
+                RETURNING id
+                "#,
+            )
+            .bind(&class.name)
+            .bind(&class.description)
+            .bind(class.parent.as_ref().map(|p| class_ids[p]))
+            .bind(new_version.id)
+            .bind(class.is_abstract)
+            .fetch_one(&mut *tx)
+            .await?;
+            class_ids.insert(class.name.clone(), id);
+        }
+
+        let mut properties = 0;
+        for class in &ordered {
+            for property in &class.properties {
+                sqlx::query(
+                    r#"
+                    INSERT INTO properties (name, description, class_id, data_type, reference_class_id,
+                                            is_required, is_unique, is_indexed, is_sensitive,
+                                            default_value, validation_rules, version_id)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+                    "#,
+                )
+                .bind(&property.name)
+                .bind(&property.description)
+                .bind(class_ids[&class.name])
+                .bind(&property.data_type)
+                .bind(property.reference_class.as_ref().map(|r| class_ids[r]))
+                .bind(property.is_required)
+                .bind(property.is_unique)
+                .bind(property.is_indexed)
+                .bind(property.is_sensitive)
+                .bind(&property.default_value)
+                .bind(&property.validation_rules)
+                .bind(new_version.id)
+                .execute(&mut *tx)
+                .await?;
+                properties += 1;
+            }
+        }
+        tx.commit().await?;
+        self.invalidate_cache().await;
+
+        if let Some(uid) = user_id {
+            let _ = self
+                .audit_service
+                .log(
+                    uid,
+                    "ontology.version.import",
+                    "ontology_version",
+                    Some(new_version.id),
+                    None,
+                    Some(serde_json::json!({
+                        "source_version": export.version,
+                        "classes": ordered.len(),
+                        "properties": properties,
+                    })),
+                    None,
+                )
+                .await;
+        }
+
+        Ok(OntologyImportSummary {
+            version: new_version,
+            classes: ordered.len(),
+            properties,
+        })
+    }
+}
+
+/// Order classes so each comes after its parent, rejecting duplicate names
+/// and inheritance cycles
+pub(super) fn parents_first(
+    classes: &[ExportedClass],
+) -> Result<Vec<&ExportedClass>, OntologyError> {
+    let mut names = HashSet::new();
+    for class in classes {
+        if class.name.trim().is_empty() {
+            return Err(OntologyError::InvalidInput(
+                "Class names must not be empty".to_string(),
+            ));
+        }
+        if !names.insert(class.name.as_str()) {
+            return Err(OntologyError::InvalidInput(format!(
+                "Class '{}' is defined more than once",
+                class.name
+            )));
+        }
+        let mut properties = HashSet::new();
+        for property in &class.properties {
+            if !properties.insert(property.name.as_str()) {
+                return Err(OntologyError::InvalidInput(format!(
+                    "Property '{}' is defined more than once on class '{}'",
+                    property.name, class.name
+                )));
+            }
+        }
+    }
+
+    let mut ordered: Vec<&ExportedClass> = Vec::with_capacity(classes.len());
+    let mut placed: HashSet<&str> = HashSet::new();
+    while ordered.len() < classes.len() {
+        let before = ordered.len();
+        for class in classes {
+            if placed.contains(class.name.as_str()) {
+                continue;
+            }
+            let ready = match &class.parent {
+                Some(parent) => {
+                    !names.contains(parent.as_str()) || placed.contains(parent.as_str())
+                }
+                None => true,
+            };
+            if ready {
+                placed.insert(&class.name);
+                ordered.push(class);
+            }
+        }
+        if ordered.len() == before {
+            return Err(OntologyError::InvalidInput(
+                "Class inheritance contains a cycle".to_string(),
+            ));
+        }
+    }
+    Ok(ordered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn class(name: &str, parent: Option<&str>) -> ExportedClass {
+        ExportedClass {
+            name: name.to_string(),
+            description: None,
+            parent: parent.map(str::to_string),
+            is_abstract: false,
+            properties: vec![],
+        }
+    }
+
+    #[test]
+    fn test_parents_are_ordered_first() {
+        let classes = vec![
+            class("Platoon", Some("Company")),
+            class("Company", Some("Unit")),
+            class("Unit", Some("Resource")),
+        ];
+        let ordered: Vec<&str> = parents_first(&classes)
+            .unwrap()
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(ordered, vec!["Unit", "Company", "Platoon"]);
+
+        let cycle = vec![class("A", Some("B")), class("B", Some("A"))];
+        assert!(parents_first(&cycle).is_err());
+        let duplicate = vec![class("A", None), class("A", None)];
+        assert!(parents_first(&duplicate).is_err());
+    }
+}
@@ -52,8 +52,9 @@ pub struct CreateProjectInput {
     pub parent_project_id: Option<Uuid>,
 }
 
-#[derive(Debug, Deserialize, Default)]
+#[derive(Debug, Deserialize, Default, Validate)]
 pub struct UpdateProjectInput {
+    #[validate(length(min = 1, max = 200))]
     pub name: Option<String>,
     pub description: Option<String>,
     pub status: Option<String>,
@@ -92,18 +93,21 @@ pub struct CreateTaskInput {
     pub priority: Option<String>,
     pub start_date: Option<NaiveDate>,
     pub due_date: Option<NaiveDate>,
+    #[validate(range(min = 0.0))]
     pub estimated_hours: Option<f64>,
     pub assignee_id: Option<Uuid>,
 }
 
-#[derive(Debug, Deserialize, Default)]
+#[derive(Debug, Deserialize, Default, Validate)]
 pub struct UpdateTaskInput {
+    #[validate(length(min = 1, max = 200))]
     pub title: Option<String>,
     pub description: Option<String>,
     pub status: Option<String>,
     pub priority: Option<String>,
     pub start_date: Option<NaiveDate>,
     pub due_date: Option<NaiveDate>,
+    #[validate(range(min = 0.0))]
     pub estimated_hours: Option<f64>,
     pub assignee_id: Option<Uuid>,
 }
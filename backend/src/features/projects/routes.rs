@@ -8,23 +8,24 @@ use axum::{
 use uuid::Uuid;
 
 use crate::features::auth::jwt::Claims;
+use crate::utils::api_error::ApiError;
 use crate::features::projects::{
     CreateProjectInput, CreateTaskInput, ProjectService, UpdateProjectInput, UpdateTaskInput,
 };
+use crate::utils::validation::ValidatedJson;
 
 use super::service::ProjectError;
 
 impl IntoResponse for ProjectError {
     fn into_response(self) -> axum::response::Response {
-        let (status, message) = match &self {
-            ProjectError::NotFound => (StatusCode::NOT_FOUND, "Project not found"),
-            ProjectError::TaskNotFound => (StatusCode::NOT_FOUND, "Task not found"),
-            ProjectError::ValidationError(_) => (StatusCode::BAD_REQUEST, "Validation error"),
-            ProjectError::DatabaseError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Database error"),
-            ProjectError::OntologyError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Ontology error"),
+        let (status, code) = match &self {
+            ProjectError::NotFound => (StatusCode::NOT_FOUND, "projects.not_found"),
+            ProjectError::TaskNotFound => (StatusCode::NOT_FOUND, "projects.task_not_found"),
+            ProjectError::ValidationError(_) => (StatusCode::BAD_REQUEST, "projects.invalid_input"),
+            ProjectError::DatabaseError(_) | ProjectError::OntologyError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "internal"),
         };
 
-        (status, Json(serde_json::json!({ "error": message, "details": self.to_string() }))).into_response()
+        ApiError::new(status, code, self.to_string()).into_response()
     }
 }
 
@@ -63,7 +64,7 @@ async fn list_projects_handler(
 async fn create_project_handler(
     State(service): State<ProjectService>,
     Extension(claims): Extension<Claims>,
-    Json(input): Json<CreateProjectInput>,
+    ValidatedJson(input): ValidatedJson<CreateProjectInput>,
 ) -> Result<impl IntoResponse, ProjectError> {
     let owner_id = Uuid::parse_str(&claims.sub)
         .map_err(|_| ProjectError::ValidationError("Invalid user ID".to_string()))?;
@@ -88,7 +89,7 @@ async fn update_project_handler(
     State(service): State<ProjectService>,
     Extension(claims): Extension<Claims>,
     Path(id): Path<Uuid>,
-    Json(input): Json<UpdateProjectInput>,
+    ValidatedJson(input): ValidatedJson<UpdateProjectInput>,
 ) -> Result<impl IntoResponse, ProjectError> {
     let user_id = Uuid::parse_str(&claims.sub)
         .map_err(|_| ProjectError::ValidationError("Invalid user ID".to_string()))?;
@@ -142,7 +143,7 @@ async fn create_task_handler(
     State(service): State<ProjectService>,
     Extension(claims): Extension<Claims>,
     Path(project_id): Path<Uuid>,
-    Json(input): Json<CreateTaskInput>,
+    ValidatedJson(input): ValidatedJson<CreateTaskInput>,
 ) -> Result<impl IntoResponse, ProjectError> {
     let user_id = Uuid::parse_str(&claims.sub)
         .map_err(|_| ProjectError::ValidationError("Invalid user ID".to_string()))?;
@@ -162,7 +163,7 @@ async fn update_task_handler(
     State(service): State<ProjectService>,
     Extension(claims): Extension<Claims>,
     Path(params): Path<TaskPathParams>,
-    Json(input): Json<UpdateTaskInput>,
+    ValidatedJson(input): ValidatedJson<UpdateTaskInput>,
 ) -> Result<impl IntoResponse, ProjectError> {
     let user_id = Uuid::parse_str(&claims.sub)
         .map_err(|_| ProjectError::ValidationError("Invalid user ID".to_string()))?;
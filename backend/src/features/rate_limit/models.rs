@@ -1,6 +1,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
+use validator::Validate;
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct RateLimitRule {
@@ -36,15 +37,17 @@ pub struct BypassToken {
     pub created_by: Option<Uuid>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct UpdateRateLimitRule {
     pub name: Option<String>,
+    #[validate(range(min = 1))]
     pub max_requests: Option<i64>,
+    #[validate(range(min = 1))]
     pub window_seconds: Option<i64>,
     pub enabled: Option<bool>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct CreateBypassToken {
     pub description: Option<String>,
     pub expires_at: Option<DateTime<Utc>>,
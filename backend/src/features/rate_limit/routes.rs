@@ -10,6 +10,7 @@ use std::sync::Arc;
 use crate::features::auth::jwt::Claims;
 use crate::features::rate_limit::models::*;
 use crate::features::rate_limit::service::RateLimitService;
+use crate::utils::validation::ValidatedJson;
 use uuid::Uuid;
 
 /// Get all rate limit rules
@@ -48,7 +49,7 @@ pub async fn get_rule_handler(
 pub async fn update_rule_handler(
     State(service): State<Arc<RateLimitService>>,
     Path(rule_id): Path<String>,
-    Json(update): Json<UpdateRateLimitRule>,
+    ValidatedJson(update): ValidatedJson<UpdateRateLimitRule>,
 ) -> impl IntoResponse {
     match service.update_rule(&rule_id, update).await {
         Ok(()) => (
@@ -95,7 +96,7 @@ pub async fn list_bypass_tokens_handler(
 pub async fn create_bypass_token_handler(
     State(service): State<Arc<RateLimitService>>,
     axum::Extension(claims): axum::Extension<Claims>,
-    Json(create): Json<CreateBypassToken>,
+    ValidatedJson(create): ValidatedJson<CreateBypassToken>,
 ) -> impl IntoResponse {
     let user_id = match Uuid::parse_str(&claims.sub) {
         Ok(uid) => uid,
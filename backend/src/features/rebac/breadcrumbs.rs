@@ -0,0 +1,84 @@
+use super::models::{Breadcrumb, Breadcrumbs};
+use super::service::{RebacError, RebacService};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+impl RebacService {
+    // ========================================================================
+    // BREADCRUMBS
+    // ========================================================================
+
+    /// The entity's ancestor chain, root first and ending with the entity,
+    /// with each segment marked by whether the user may read it. Segments
+    /// they can't read carry no id, name or class, so collapsing them in the
+    /// UI reveals nothing. An entity the user can't read itself is reported
+    /// as not found.
+    pub async fn breadcrumbs(
+        &self,
+        user_id: Uuid,
+        entity_id: Uuid,
+        tenant_id: Option<Uuid>,
+    ) -> Result<Breadcrumbs, RebacError> {
+        let chain = sqlx::query_as::<_, (Uuid, String, String, i32)>(
+            r#"
+            SELECT e.id, e.display_name, c.name, chain.depth
+            FROM (
+                SELECT $1::uuid AS id, 0 AS depth
+                UNION ALL
+                SELECT ancestor_id, depth FROM get_entity_ancestors($1)
+            ) chain
+            JOIN entities e ON e.id = chain.id AND e.deleted_at IS NULL
+            JOIN classes c ON c.id = e.class_id
+            ORDER BY chain.depth DESC
+            "#,
+        )
+        .bind(entity_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let not_found = || RebacError::NotFound(format!("Entity {} not found", entity_id));
+        if chain.last().is_none_or(|(id, ..)| *id != entity_id) {
+            return Err(not_found());
+        }
+
+        let ids = chain.iter().map(|(id, ..)| *id).collect();
+        let readable: HashMap<Uuid, bool> = self
+            .check_multiple_permissions(user_id, ids, "read", tenant_id)
+            .await?
+            .into_iter()
+            .map(|(id, allowed, denied)| (id, allowed && !denied))
+            .collect();
+        if !readable.get(&entity_id).copied().unwrap_or(false) {
+            return Err(not_found());
+        }
+
+        let items: Vec<Breadcrumb> = chain
+            .into_iter()
+            .map(|(id, name, class_name, depth)| {
+                if readable.get(&id).copied().unwrap_or(false) {
+                    Breadcrumb {
+                        id: Some(id),
+                        name: Some(name),
+                        class_name: Some(class_name),
+                        depth,
+                        can_view: true,
+                    }
+                } else {
+                    Breadcrumb {
+                        id: None,
+                        name: None,
+                        class_name: None,
+                        depth,
+                        can_view: false,
+                    }
+                }
+            })
+            .collect();
+
+        Ok(Breadcrumbs {
+            entity_id,
+            hidden: items.iter().filter(|b| !b.can_view).count(),
+            items,
+        })
+    }
+}
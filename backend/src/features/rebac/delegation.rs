@@ -10,11 +10,11 @@ impl RebacService {
         &self,
         tenant_id: Option<Uuid>,
     ) -> Result<Vec<crate::features::abac::models::RoleDelegationRule>, RebacError> {
-        let rel_type = sqlx::query_as::<_, crate::features::ontology::models::RelationshipType>(
-            "SELECT * FROM relationship_types WHERE name = 'can_delegate'",
-        )
-        .fetch_one(&self.pool)
-        .await?;
+        let rel_type = self
+            .ontology_service
+            .get_relationship_type("can_delegate")
+            .await
+            .map_err(|e| RebacError::DatabaseError(e.to_string()))?;
 
         let rels = sqlx::query_as::<_, crate::features::ontology::models::Relationship>(
             "SELECT * FROM relationships WHERE relationship_type_id = $1 AND ($2::uuid IS NULL OR tenant_id = $2)"
@@ -62,11 +62,11 @@ impl RebacService {
         can_revoke: bool,
         tenant_id: Option<Uuid>,
     ) -> Result<crate::features::abac::models::RoleDelegationRule, RebacError> {
-        let rel_type = sqlx::query_as::<_, crate::features::ontology::models::RelationshipType>(
-            "SELECT * FROM relationship_types WHERE name = 'can_delegate'",
-        )
-        .fetch_one(&self.pool)
-        .await?;
+        let rel_type = self
+            .ontology_service
+            .get_relationship_type("can_delegate")
+            .await
+            .map_err(|e| RebacError::DatabaseError(e.to_string()))?;
 
         let metadata = serde_json::json!({
             "can_grant": can_grant,
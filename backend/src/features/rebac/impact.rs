@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use sqlx::{Pool, Postgres, Row};
 use uuid::Uuid;
+use validator::Validate;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ImpactReport {
@@ -17,7 +18,7 @@ pub struct UserImpact {
     pub details: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct SimulateRoleChangeInput {
     pub role_id: Uuid,
     pub added_permissions: Vec<String>,
@@ -0,0 +1,106 @@
+use super::service::RebacError;
+use sqlx::{Pool, Postgres};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// What `check_entity_permission` used to look up before every check: the
+/// relationship types that link users to roles and roles to permissions, and
+/// the level of each permission type
+#[derive(Debug)]
+pub struct KernelIds {
+    pub has_role_type_id: Uuid,
+    pub grants_permission_type_id: Uuid,
+    pub permission_class_id: Uuid,
+    levels: HashMap<String, i32>,
+}
+
+impl KernelIds {
+    /// Level of a permission type known when the ids were loaded. Newer
+    /// ones are left to the database to look up.
+    pub fn level(&self, permission: &str) -> Option<i32> {
+        self.levels.get(permission).copied()
+    }
+}
+
+/// The ids and when they were resolved
+type CachedIds = Option<(Instant, Arc<KernelIds>)>;
+
+/// Resolves the kernel ids once and keeps them for `ttl`. Local changes to
+/// permission types call `invalidate`; the TTL bounds how long other
+/// instances keep a changed level.
+#[derive(Clone)]
+pub struct PermissionKernel {
+    ids: Arc<RwLock<CachedIds>>,
+    ttl: Duration,
+}
+
+impl PermissionKernel {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ids: Arc::new(RwLock::new(None)),
+            ttl,
+        }
+    }
+
+    pub async fn ids(&self, pool: &Pool<Postgres>) -> Result<Arc<KernelIds>, RebacError> {
+        if let Some(ids) = self.cached() {
+            return Ok(ids);
+        }
+        let ids = Arc::new(load(pool).await?);
+        if let Ok(mut slot) = self.ids.write() {
+            *slot = Some((Instant::now(), ids.clone()));
+        }
+        Ok(ids)
+    }
+
+    /// Drop the cached ids, e.g. after a permission type changed
+    pub fn invalidate(&self) {
+        if let Ok(mut slot) = self.ids.write() {
+            *slot = None;
+        }
+    }
+
+    fn cached(&self) -> Option<Arc<KernelIds>> {
+        let slot = self.ids.read().ok()?;
+        let (loaded_at, ids) = slot.as_ref()?;
+        (loaded_at.elapsed() < self.ttl).then(|| ids.clone())
+    }
+}
+
+async fn load(pool: &Pool<Postgres>) -> Result<KernelIds, RebacError> {
+    let (has_role, grants_permission, permission_class) =
+        sqlx::query_as::<_, (Option<Uuid>, Option<Uuid>, Option<Uuid>)>(
+            r#"
+            SELECT
+                (SELECT id FROM relationship_types WHERE name = 'has_role' LIMIT 1),
+                (SELECT id FROM relationship_types WHERE name = 'grants_permission' LIMIT 1),
+                (SELECT id FROM classes WHERE name = 'Permission' LIMIT 1)
+            "#,
+        )
+        .fetch_one(pool)
+        .await?;
+    let missing = |what: &str| RebacError::NotFound(format!("{} is not defined", what));
+    let permission_class_id = permission_class.ok_or_else(|| missing("Permission class"))?;
+
+    let levels = sqlx::query_as::<_, (String, i32)>(
+        r#"
+        SELECT DISTINCT ON (display_name) display_name, COALESCE((attributes->>'level')::integer, 0)
+        FROM entities
+        WHERE class_id = $1
+        ORDER BY display_name, created_at
+        "#,
+    )
+    .bind(permission_class_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(KernelIds {
+        has_role_type_id: has_role.ok_or_else(|| missing("Relationship type 'has_role'"))?,
+        grants_permission_type_id: grants_permission
+            .ok_or_else(|| missing("Relationship type 'grants_permission'"))?,
+        permission_class_id,
+        levels: levels.into_iter().collect(),
+    })
+}
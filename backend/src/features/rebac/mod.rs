@@ -1,5 +1,6 @@
 pub mod condition_evaluator;
 pub mod impact;
+pub mod kernel;
 pub mod models;
 pub mod policy_models;
 pub mod policy_routes;
@@ -8,6 +9,7 @@ pub mod routes;
 pub mod service;
 
 // Refactored modules
+pub mod breadcrumbs;
 pub mod delegation;
 pub mod permissions;
 pub mod policy_bridge;
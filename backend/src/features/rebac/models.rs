@@ -2,6 +2,9 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
+use validator::Validate;
+
+use crate::utils::validation::not_blank;
 
 // ============================================================================
 // PERMISSION TYPES
@@ -17,14 +20,15 @@ pub struct PermissionType {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreatePermissionTypeInput {
+    #[validate(custom = "not_blank", length(max = 100))]
     pub name: String,
     pub description: Option<String>,
     pub level: i32,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct UpdatePermissionTypeInput {
     pub description: Option<String>,
     pub level: Option<i32>,
@@ -66,14 +70,15 @@ pub struct RelationshipType {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreateRelationshipTypeInput {
+    #[validate(custom = "not_blank", length(max = 100))]
     pub name: String,
     pub description: Option<String>,
     pub grants_permission_inheritance: bool,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct UpdateRelationshipTypeInput {
     pub description: Option<String>,
     pub grants_permission_inheritance: Option<bool>,
@@ -124,7 +129,7 @@ pub struct AssignScopedRoleInput {
     pub is_deny: Option<bool>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct RevokeRoleInput {
     pub reason: Option<String>,
 }
@@ -182,10 +187,11 @@ pub struct CheckPermissionQuery {
     pub field_name: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct BulkCheckRequest {
     pub user_id: Uuid,
     pub entity_ids: Vec<Uuid>,
+    #[validate(custom = "not_blank")]
     pub permission: String,
     pub tenant_id: Option<Uuid>,
 }
@@ -202,6 +208,27 @@ pub struct EntityPermissionResult {
     pub is_denied: bool,
 }
 
+/// One segment of an entity's breadcrumb trail
+#[derive(Debug, Clone, Serialize)]
+pub struct Breadcrumb {
+    /// Id, name and class are left out of segments the user can't read
+    pub id: Option<Uuid>,
+    pub name: Option<String>,
+    pub class_name: Option<String>,
+    /// Steps up from the entity; the entity itself is 0
+    pub depth: i32,
+    pub can_view: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Breadcrumbs {
+    pub entity_id: Uuid,
+    /// Root first, ending with the entity
+    pub items: Vec<Breadcrumb>,
+    /// How many segments the user can't read
+    pub hidden: usize,
+}
+
 /// Cron schedule preset for easy selection
 #[derive(Debug, Clone, Serialize)]
 pub struct CronPreset {
@@ -211,8 +238,9 @@ pub struct CronPreset {
 }
 
 /// Request to validate a cron expression
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct ValidateCronRequest {
+    #[validate(custom = "not_blank")]
     pub cron: String,
 }
 
@@ -225,7 +253,7 @@ pub struct CronValidationResponse {
 }
 
 /// Request to update role schedule
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct UpdateScheduleRequest {
     pub schedule_cron: Option<String>,
     pub valid_from: Option<DateTime<Utc>>,
@@ -245,7 +273,7 @@ pub struct RolePermissionMatrixEntry {
     pub permissions: Vec<String>, // List of permission names
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct BatchUpdateRolePermissionsInput {
     pub updates: Vec<RolePermissionUpdate>,
 }
@@ -1,10 +1,21 @@
 use super::models::*;
 use super::service::{RebacError, RebacService};
 use crate::features::rebac::policy_models::PolicyResult;
+use crate::features::decision_log::models::{Decision, DecisionReason, DecisionRecord};
+use crate::middleware::trace_context::current_trace_id;
 use chrono::Utc;
 use sqlx::Row;
+use std::time::Instant;
 use uuid::Uuid;
 
+/// How `check_permission_integrated` decided, for the decision log
+struct IntegratedDecision {
+    allowed: bool,
+    reason: DecisionReason,
+    matched_role: Option<String>,
+    matched_policy: Option<String>,
+}
+
 impl RebacService {
     // ========================================================================
     // PERMISSION TYPES
@@ -71,6 +82,7 @@ impl RebacService {
             .create_entity(entity_input, None, None)
             .await
             .map_err(|e| RebacError::DatabaseError(e.to_string()))?;
+        self.kernel.invalidate();
 
         Ok(PermissionType {
             id: entity.id,
@@ -113,6 +125,7 @@ impl RebacService {
             .update_entity(id, entity_input, None)
             .await
             .map_err(|e| RebacError::DatabaseError(e.to_string()))?;
+        self.kernel.invalidate();
 
         Ok(PermissionType {
             id: entity.id,
@@ -135,7 +148,9 @@ impl RebacService {
         self.ontology_service
             .delete_entity(id, None)
             .await
-            .map_err(|e| RebacError::DatabaseError(e.to_string()))
+            .map_err(|e| RebacError::DatabaseError(e.to_string()))?;
+        self.kernel.invalidate();
+        Ok(())
     }
 
     // ========================================================================
@@ -184,20 +199,44 @@ impl RebacService {
         }
 
         if field_name.is_none() {
-            let cache_key = (user_id, entity_id, permission.to_string(), tenant_id);
-            if let Some(cached) = self.permission_cache.get(&cache_key).await {
-                return Ok(cached);
+            // Results under a test mode clock depend on that clock, so they
+            // bypass the cache
+            let cache_key = (self.clock_override(user_id).await?.is_none())
+                .then(|| (user_id, entity_id, permission.to_string(), tenant_id));
+            if let Some(cache_key) = &cache_key {
+                if let Some(cached) = self.permission_cache.get(cache_key).await {
+                    return Ok(cached);
+                }
             }
 
-            let row = sqlx::query(
-                "SELECT has_permission, granted_via_entity_id, granted_via_role, is_inherited, is_denied FROM check_entity_permission($1, $2, $3, $4)",
-            )
-            .bind(user_id)
-            .bind(entity_id)
-            .bind(permission)
-            .bind(tenant_id)
-            .fetch_one(&self.pool)
-            .await?;
+            let ids = self.kernel.ids(&self.pool).await?;
+            let row = if self.denormalized_grants {
+                sqlx::query(
+                    "SELECT has_permission, granted_via_entity_id, granted_via_role, is_inherited, is_denied FROM check_entity_permission_denormalized($1, $2, $3, $4, $5, $6)",
+                )
+                .bind(user_id)
+                .bind(entity_id)
+                .bind(permission)
+                .bind(tenant_id)
+                .bind(ids.permission_class_id)
+                .bind(ids.level(permission))
+                .fetch_one(&self.pool)
+                .await?
+            } else {
+                sqlx::query(
+                    "SELECT has_permission, granted_via_entity_id, granted_via_role, is_inherited, is_denied FROM check_entity_permission_resolved($1, $2, $3, $4, $5, $6, $7, $8)",
+                )
+                .bind(user_id)
+                .bind(entity_id)
+                .bind(permission)
+                .bind(tenant_id)
+                .bind(ids.has_role_type_id)
+                .bind(ids.grants_permission_type_id)
+                .bind(ids.permission_class_id)
+                .bind(ids.level(permission))
+                .fetch_one(&self.pool)
+                .await?
+            };
 
             let result = PermissionCheckResult {
                 has_permission: row.try_get::<Option<bool>, _>("has_permission")?.unwrap_or(false),
@@ -207,9 +246,11 @@ impl RebacService {
                 is_denied: row.try_get::<Option<bool>, _>("is_denied")?,
             };
 
-            self.permission_cache
-                .insert(cache_key, result.clone())
-                .await;
+            if let Some(cache_key) = cache_key {
+                self.permission_cache
+                    .insert(cache_key, result.clone())
+                    .await;
+            }
             return Ok(result);
         }
 
@@ -316,7 +357,8 @@ impl RebacService {
 
     pub async fn has_firefighter_active(&self, user_id: Uuid) -> Result<bool, RebacError> {
         let has_active = sqlx::query_scalar::<_, bool>(
-            "SELECT EXISTS (SELECT 1 FROM firefighter_sessions WHERE user_id = $1 AND deactivated_at IS NULL AND expires_at > NOW())"
+            // Scoped sessions act through their role assignments instead
+            "SELECT EXISTS (SELECT 1 FROM firefighter_sessions WHERE user_id = $1 AND deactivated_at IS NULL AND expires_at > NOW() AND scope IS NULL)"
         )
         .bind(user_id)
         .fetch_one(&self.pool)
@@ -333,9 +375,59 @@ impl RebacService {
         field_name: Option<&str>,
         custom_context: Option<serde_json::Map<String, serde_json::Value>>,
     ) -> Result<bool, RebacError> {
+        let started = Instant::now();
+        let decided = self
+            .decide_integrated(
+                user_id,
+                entity_id,
+                permission,
+                tenant_id,
+                field_name,
+                custom_context,
+            )
+            .await?;
+
+        if let Some(decision_log) = &self.decision_log {
+            decision_log.record(DecisionRecord {
+                timestamp: Utc::now(),
+                trace_id: current_trace_id(),
+                subject_id: user_id,
+                resource_id: entity_id,
+                tenant_id,
+                permission: permission.to_string(),
+                field_name: field_name.map(str::to_string),
+                decision: if decided.allowed {
+                    Decision::Allow
+                } else {
+                    Decision::Deny
+                },
+                reason: decided.reason,
+                matched_role: decided.matched_role,
+                matched_policy: decided.matched_policy,
+                latency_ms: started.elapsed().as_secs_f64() * 1000.0,
+            });
+        }
+
+        Ok(decided.allowed)
+    }
+
+    async fn decide_integrated(
+        &self,
+        user_id: Uuid,
+        entity_id: Uuid,
+        permission: &str,
+        tenant_id: Option<Uuid>,
+        field_name: Option<&str>,
+        custom_context: Option<serde_json::Map<String, serde_json::Value>>,
+    ) -> Result<IntegratedDecision, RebacError> {
         if self.has_firefighter_active(user_id).await? {
             // ... firefighter ...
-            return Ok(true);
+            return Ok(IntegratedDecision {
+                allowed: true,
+                reason: DecisionReason::Firefighter,
+                matched_role: Some("firefighter".to_string()),
+                matched_policy: None,
+            });
         }
 
         let rebac_result = self
@@ -345,13 +437,16 @@ impl RebacService {
         let mut final_has_permission = rebac_result.has_permission;
         let is_rebac_denied = rebac_result.is_denied.unwrap_or(false);
 
+        let mut schedule_inactive = false;
+        let now = self.effective_now(user_id).await?;
         if final_has_permission && !is_rebac_denied {
             let active_roles = self
                 .get_active_grant_roles(user_id, entity_id, permission, tenant_id)
                 .await?;
-            if !active_roles.iter().any(Self::is_role_active) {
+            if !active_roles.iter().any(|role| Self::is_role_active_at(role, now)) {
                 tracing::debug!("Permission check failed cron schedule validation");
                 final_has_permission = false;
+                schedule_inactive = true;
             }
         }
 
@@ -367,16 +462,21 @@ impl RebacService {
 
         let policies = self
             .policy_service
-            .get_applicable_policies(entity_id, permission, Some(entity_class_id))
+            .get_applicable_policies(entity_id, permission, Some(entity_class_id), now)
             .await
             .map_err(|e| RebacError::DatabaseError(e.to_string()))?;
 
-        let policy_decision = self.policy_service.evaluate_policies(&policies, &context);
-
-        let final_result = match policy_decision {
-            PolicyResult::Denied { .. } => false,
-            PolicyResult::Allowed { .. } => true,
-            PolicyResult::NoMatch => final_has_permission && !is_rebac_denied,
+        let policy_decision = self
+            .policy_service
+            .evaluate_policies(&policies, &context, now);
+
+        let (final_result, reason) = match policy_decision {
+            PolicyResult::Denied { .. } => (false, DecisionReason::PolicyDeny),
+            PolicyResult::Allowed { .. } => (true, DecisionReason::PolicyAllow),
+            PolicyResult::NoMatch if is_rebac_denied => (false, DecisionReason::RoleDeny),
+            PolicyResult::NoMatch if schedule_inactive => (false, DecisionReason::Schedule),
+            PolicyResult::NoMatch if final_has_permission => (true, DecisionReason::RoleGrant),
+            PolicyResult::NoMatch => (false, DecisionReason::NoGrant),
         };
 
         let _ = self
@@ -392,7 +492,12 @@ impl RebacService {
             )
             .await;
 
-        Ok(final_result)
+        Ok(IntegratedDecision {
+            allowed: final_result,
+            reason,
+            matched_role: rebac_result.granted_via_role,
+            matched_policy: policy_decision.policy_name().map(str::to_string),
+        })
     }
 
     pub async fn get_active_grant_roles(
@@ -402,25 +507,7 @@ impl RebacService {
         permission: &str,
         tenant_id: Option<Uuid>,
     ) -> Result<Vec<ScopedUserRole>, RebacError> {
-        let perm_class = self
-            .ontology_service
-            .get_system_class("Permission")
-            .await
-            .map_err(|e| RebacError::DatabaseError(e.to_string()))?;
-
-        let has_role_type =
-            sqlx::query_as::<_, crate::features::ontology::models::RelationshipType>(
-                "SELECT * FROM relationship_types WHERE name = 'has_role'",
-            )
-            .fetch_one(&self.pool)
-            .await?;
-
-        let grant_perm_type =
-            sqlx::query_as::<_, crate::features::ontology::models::RelationshipType>(
-                "SELECT * FROM relationship_types WHERE name = 'grants_permission'",
-            )
-            .fetch_one(&self.pool)
-            .await?;
+        let ids = self.kernel.ids(&self.pool).await?;
 
         let rels = sqlx::query_as::<_, crate::features::ontology::models::Relationship>(
             r#"
@@ -445,9 +532,9 @@ impl RebacService {
         .bind(user_id)
         .bind(permission)
         .bind(tenant_id)
-        .bind(has_role_type.id)
-        .bind(grant_perm_type.id)
-        .bind(perm_class.id)
+        .bind(ids.has_role_type_id)
+        .bind(ids.grants_permission_type_id)
+        .bind(ids.permission_class_id)
         .fetch_all(&self.pool)
         .await?;
 
@@ -539,11 +626,12 @@ impl RebacService {
 
         // [UNIFIED] Batch role retrieval now uses ontology relationships exclusively.
         // We query has_role relationships for the given users.
-        let rel_type_id = sqlx::query_scalar::<_, Uuid>(
-            "SELECT id FROM relationship_types WHERE name = 'has_role' LIMIT 1"
-        )
-        .fetch_one(&self.pool)
-        .await?;
+        let rel_type_id = self
+            .ontology_service
+            .get_relationship_type("has_role")
+            .await
+            .map_err(|e| RebacError::DatabaseError(e.to_string()))?
+            .id;
 
         let roles_with_details = sqlx::query!(
             r#"
@@ -574,8 +662,24 @@ impl RebacService {
             result.insert(*uid, Vec::new());
         }
 
-        let now = Utc::now();
+        // Users under a test mode clock are checked against it
+        let clocks: std::collections::HashMap<Uuid, chrono::DateTime<Utc>> =
+            sqlx::query_as::<_, (Uuid, chrono::DateTime<Utc>)>(
+                r#"
+                SELECT user_id, clock FROM (
+                    SELECT user_id, test_mode_clock(user_id) AS clock FROM UNNEST($1::uuid[]) AS t(user_id)
+                ) c
+                WHERE clock IS NOT NULL
+                "#,
+            )
+            .bind(&user_ids)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .collect();
+
         for row in roles_with_details {
+            let now = clocks.get(&row.user_id).copied().unwrap_or_else(Utc::now);
             let mut is_active = true;
 
             // Temporal & Deny Check
@@ -601,7 +705,7 @@ impl RebacService {
             if is_active {
                 if let Some(cron) = row.schedule_cron {
                     if !cron.is_empty() {
-                        if let Ok(cron_active) = Self::is_within_cron_schedule(&cron) {
+                        if let Ok(cron_active) = Self::is_within_cron_schedule_at(&cron, now) {
                             if !cron_active {
                                 is_active = false;
                             }
@@ -1,6 +1,5 @@
 use super::policy_models::EvaluationContext;
 use super::service::{RebacError, RebacService};
-use chrono::Utc;
 use sqlx::Row;
 use uuid::Uuid;
 
@@ -62,7 +61,7 @@ impl RebacService {
         // 3. Environment attributes
         context.env.insert(
             "now".to_string(),
-            serde_json::Value::String(Utc::now().to_rfc3339()),
+            serde_json::Value::String(self.effective_now(user_id).await?.to_rfc3339()),
         );
 
         // 4. Request attributes
@@ -4,6 +4,9 @@ use serde_json::Value as JsonValue;
 use sqlx::FromRow;
 use std::collections::HashMap;
 use uuid::Uuid;
+use validator::Validate;
+
+use crate::utils::validation::not_blank;
 
 // ============================================================================
 // POLICY MODEL
@@ -42,14 +45,17 @@ pub struct Policy {
     pub is_active: bool,
     pub valid_from: Option<DateTime<Utc>>,
     pub valid_until: Option<DateTime<Utc>>,
+    /// Cron schedule the policy is limited to, if any
+    pub schedule_cron: Option<String>,
     pub created_at: DateTime<Utc>,
     pub created_by: Option<Uuid>,
     pub updated_at: Option<DateTime<Utc>>,
     pub updated_by: Option<Uuid>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreatePolicyInput {
+    #[validate(custom = "not_blank", length(max = 255))]
     pub name: String,
     pub description: Option<String>,
     pub effect: String,
@@ -61,10 +67,13 @@ pub struct CreatePolicyInput {
     pub is_active: Option<bool>,
     pub valid_from: Option<DateTime<Utc>>,
     pub valid_until: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub schedule_cron: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct UpdatePolicyInput {
+    #[validate(custom = "not_blank", length(max = 255))]
     pub name: Option<String>,
     pub description: Option<String>,
     pub effect: Option<String>,
@@ -76,6 +85,8 @@ pub struct UpdatePolicyInput {
     pub is_active: Option<bool>,
     pub valid_from: Option<DateTime<Utc>>,
     pub valid_until: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub schedule_cron: Option<String>,
 }
 
 // ============================================================================
@@ -140,10 +151,12 @@ impl EvaluationContext {
 pub struct Condition {
     pub attribute: String,
     pub operator: String,
+    /// Unused by `exists` / `not_exists`, so may be omitted
+    #[serde(default)]
     pub value: JsonValue,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ConditionGroup {
     #[serde(default)]
     pub all: Vec<Condition>,
@@ -192,10 +205,12 @@ pub struct FullPermissionCheckResult {
 // POLICY TESTING
 // ============================================================================
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct TestPolicyRequest {
+    #[validate]
     pub policy: CreatePolicyInput,
     pub context: EvaluationContext,
+    #[validate(custom = "not_blank")]
     pub permission: String,
 }
 
@@ -1,5 +1,6 @@
 use super::policy_models::*;
 use super::policy_service::PolicyService;
+use crate::utils::validation::ValidatedJson;
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
@@ -50,7 +51,7 @@ async fn get_policy(
 
 async fn create_policy(
     State(svc): State<PolicyService>,
-    Json(input): Json<CreatePolicyInput>,
+    ValidatedJson(input): ValidatedJson<CreatePolicyInput>,
 ) -> Result<(StatusCode, Json<Policy>), StatusCode> {
     svc.create_policy(input, None)
         .await
@@ -64,7 +65,7 @@ async fn create_policy(
 async fn update_policy(
     State(svc): State<PolicyService>,
     Path(id): Path<Uuid>,
-    Json(input): Json<UpdatePolicyInput>,
+    ValidatedJson(input): ValidatedJson<UpdatePolicyInput>,
 ) -> Result<Json<Policy>, StatusCode> {
     svc.update_policy(id, input, None)
         .await
@@ -87,7 +88,7 @@ async fn delete_policy(
 
 async fn test_policy(
     State(svc): State<PolicyService>,
-    Json(input): Json<TestPolicyRequest>,
+    ValidatedJson(input): ValidatedJson<TestPolicyRequest>,
 ) -> Json<TestPolicyResponse> {
     Json(svc.test_policy(&input))
 }
@@ -1,6 +1,7 @@
 use super::condition_evaluator::{evaluate_policy_conditions, test_policy_conditions};
 use super::policy_models::*;
-use chrono::Utc;
+use super::service::{RebacError, RebacService};
+use chrono::{DateTime, Utc};
 use serde_json::Value as JsonValue;
 use sqlx::{Pool, Postgres};
 use uuid::Uuid;
@@ -76,13 +77,15 @@ impl PolicyService {
                 "Effect must be ALLOW or DENY".to_string(),
             ));
         }
+        validate_schedule(input.schedule_cron.as_deref())?;
 
         let policy = sqlx::query_as::<_, Policy>(
             r#"
             INSERT INTO policies 
                 (name, description, effect, priority, target_class_id, target_permissions,
-                 conditions, scope_entity_id, is_active, valid_from, valid_until, created_by)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+                 conditions, scope_entity_id, is_active, valid_from, valid_until, created_by,
+                 schedule_cron)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
             RETURNING *
             "#,
         )
@@ -98,6 +101,7 @@ impl PolicyService {
         .bind(input.valid_from)
         .bind(input.valid_until)
         .bind(created_by)
+        .bind(&input.schedule_cron)
         .fetch_one(&self.pool)
         .await?;
 
@@ -111,6 +115,7 @@ impl PolicyService {
         updated_by: Option<Uuid>,
     ) -> Result<Policy, PolicyError> {
         let existing = self.get_policy(id).await?;
+        validate_schedule(input.schedule_cron.as_deref())?;
 
         let policy = sqlx::query_as::<_, Policy>(
             r#"
@@ -126,6 +131,7 @@ impl PolicyService {
                 is_active = COALESCE($10, is_active),
                 valid_from = COALESCE($11, valid_from),
                 valid_until = COALESCE($12, valid_until),
+                schedule_cron = COALESCE($14, schedule_cron),
                 updated_at = NOW(),
                 updated_by = $13
             WHERE id = $1
@@ -145,6 +151,7 @@ impl PolicyService {
         .bind(input.valid_from)
         .bind(input.valid_until)
         .bind(updated_by)
+        .bind(&input.schedule_cron)
         .fetch_one(&self.pool)
         .await?;
 
@@ -173,9 +180,8 @@ impl PolicyService {
         entity_id: Uuid,
         permission: &str,
         entity_class_id: Option<Uuid>,
+        now: DateTime<Utc>,
     ) -> Result<Vec<Policy>, PolicyError> {
-        let now = Utc::now();
-
         // Get policies that:
         // 1. Are active
         // 2. Match the permission (or have empty permissions = all)
@@ -214,8 +220,16 @@ impl PolicyService {
         &self,
         policies: &[Policy],
         context: &EvaluationContext,
+        now: DateTime<Utc>,
     ) -> PolicyResult {
         for policy in policies {
+            // Scheduled policies only apply while their schedule is active
+            if let Some(cron) = &policy.schedule_cron {
+                if !RebacService::is_within_cron_schedule_at(cron, now).unwrap_or(false) {
+                    continue;
+                }
+            }
+
             // Check if conditions match
             if evaluate_policy_conditions(&policy.conditions, context) {
                 return match policy.effect.as_str() {
@@ -291,3 +305,13 @@ impl PolicyService {
         Ok(())
     }
 }
+
+fn validate_schedule(schedule_cron: Option<&str>) -> Result<(), PolicyError> {
+    match schedule_cron {
+        Some(cron) => RebacService::validate_cron(cron).map_err(|e| match e {
+            RebacError::InvalidInput(msg) => PolicyError::InvalidInput(msg),
+            e => PolicyError::InvalidInput(e.to_string()),
+        }),
+        None => Ok(()),
+    }
+}
@@ -27,6 +27,7 @@ impl RebacService {
         .bind(input.grants_permission_inheritance)
         .fetch_one(&self.pool)
         .await?;
+        self.ontology_service.invalidate_cache().await;
         Ok(rt)
     }
 
@@ -49,6 +50,7 @@ impl RebacService {
         .bind(input.grants_permission_inheritance)
         .fetch_one(&self.pool)
         .await?;
+        self.ontology_service.invalidate_cache().await;
         Ok(rt)
     }
 
@@ -57,6 +59,7 @@ impl RebacService {
             .bind(id)
             .execute(&self.pool)
             .await?;
+        self.ontology_service.invalidate_cache().await;
         Ok(())
     }
 
@@ -160,11 +163,11 @@ impl RebacService {
             RebacError::NotFound(format!("Permission '{}' not found", permission_name))
         })?;
 
-        let rel_type = sqlx::query_as::<_, crate::features::ontology::models::RelationshipType>(
-            "SELECT * FROM relationship_types WHERE name = 'grants_permission'",
-        )
-        .fetch_one(&self.pool)
-        .await?;
+        let rel_type = self
+            .ontology_service
+            .get_relationship_type("grants_permission")
+            .await
+            .map_err(|e| RebacError::DatabaseError(e.to_string()))?;
 
         sqlx::query(
             r#"
@@ -206,11 +209,11 @@ impl RebacService {
             RebacError::NotFound(format!("Permission '{}' not found", permission_name))
         })?;
 
-        let rel_type = sqlx::query_as::<_, crate::features::ontology::models::RelationshipType>(
-            "SELECT * FROM relationship_types WHERE name = 'grants_permission'",
-        )
-        .fetch_one(&self.pool)
-        .await?;
+        let rel_type = self
+            .ontology_service
+            .get_relationship_type("grants_permission")
+            .await
+            .map_err(|e| RebacError::DatabaseError(e.to_string()))?;
 
         sqlx::query(
             "DELETE FROM relationships WHERE source_entity_id = $1 AND target_entity_id = $2 AND relationship_type_id = $3"
@@ -1,7 +1,8 @@
 use super::impact::{ImpactReport, ImpactService, SimulateRoleChangeInput};
 use super::models::*;
-use super::service::RebacService;
+use super::service::{RebacError, RebacService};
 use crate::features::auth::jwt::Claims;
+use crate::utils::validation::ValidatedJson;
 use axum::Extension;
 use axum::{
     extract::{Path, Query, State},
@@ -11,6 +12,7 @@ use axum::{
 };
 use serde::Deserialize;
 use uuid::Uuid;
+use validator::Validate;
 
 #[derive(Debug, Deserialize)]
 pub struct UserIdQuery {
@@ -36,6 +38,7 @@ pub fn rebac_routes() -> Router<RebacService> {
         .route("/check", get(check_permission))
         .route("/check/bulk", post(check_bulk_permissions))
         .route("/accessible-entities", get(get_accessible_entities))
+        .route("/entities/:id/breadcrumbs", get(get_breadcrumbs))
         // Permission Types CRUD
         .route(
             "/permission-types",
@@ -84,14 +87,14 @@ pub fn rebac_routes() -> Router<RebacService> {
         .route("/matrix/update", post(batch_update_role_permissions))
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct MatrixRequest {
     pub user_ids: Vec<Uuid>,
 }
 
 async fn get_access_matrix(
     State(svc): State<RebacService>,
-    Json(input): Json<MatrixRequest>,
+    ValidatedJson(input): ValidatedJson<MatrixRequest>,
 ) -> Result<Json<std::collections::HashMap<Uuid, Vec<String>>>, StatusCode> {
     svc.get_active_user_roles_batch(input.user_ids)
         .await
@@ -140,7 +143,7 @@ async fn assign_role(
 async fn revoke_role(
     State(svc): State<RebacService>,
     Path(id): Path<Uuid>,
-    Json(input): Json<RevokeRoleInput>,
+    ValidatedJson(input): ValidatedJson<RevokeRoleInput>,
 ) -> Result<StatusCode, StatusCode> {
     svc.revoke_scoped_role(id, None, input.reason)
         .await
@@ -151,7 +154,7 @@ async fn revoke_role(
 async fn update_role_schedule(
     State(svc): State<RebacService>,
     Path(id): Path<Uuid>,
-    Json(input): Json<UpdateScheduleRequest>,
+    ValidatedJson(input): ValidatedJson<UpdateScheduleRequest>,
 ) -> Result<Json<ScopedUserRole>, StatusCode> {
     svc.update_role_schedule(id, input.schedule_cron, input.valid_from, input.valid_until)
         .await
@@ -206,9 +209,19 @@ async fn check_permission(
     Ok(Json(response))
 }
 
+async fn get_breadcrumbs(
+    State(svc): State<RebacService>,
+    Extension(claims): Extension<Claims>,
+    Path(entity_id): Path<Uuid>,
+) -> Result<Json<Breadcrumbs>, RebacError> {
+    let user_id = Uuid::parse_str(&claims.sub)
+        .map_err(|_| RebacError::InvalidInput("Invalid user id in token".to_string()))?;
+    svc.breadcrumbs(user_id, entity_id, None).await.map(Json)
+}
+
 async fn check_bulk_permissions(
     State(svc): State<RebacService>,
-    Json(payload): Json<BulkCheckRequest>,
+    ValidatedJson(payload): ValidatedJson<BulkCheckRequest>,
 ) -> Result<Json<BulkCheckResponse>, StatusCode> {
     let results = svc
         .check_multiple_permissions(
@@ -310,7 +323,9 @@ async fn remove_role_permission(
 // CRON SCHEDULE MANAGEMENT
 // ============================================================================
 
-async fn validate_cron(Json(input): Json<ValidateCronRequest>) -> Json<CronValidationResponse> {
+async fn validate_cron(
+    ValidatedJson(input): ValidatedJson<ValidateCronRequest>,
+) -> Json<CronValidationResponse> {
     use std::str::FromStr;
 
     match cron::Schedule::from_str(&input.cron) {
@@ -356,7 +371,7 @@ async fn list_permission_types(
 
 async fn create_permission_type(
     State(svc): State<RebacService>,
-    Json(input): Json<CreatePermissionTypeInput>,
+    ValidatedJson(input): ValidatedJson<CreatePermissionTypeInput>,
 ) -> Result<(StatusCode, Json<PermissionType>), StatusCode> {
     svc.create_permission_type(input)
         .await
@@ -367,7 +382,7 @@ async fn create_permission_type(
 async fn update_permission_type(
     State(svc): State<RebacService>,
     Path(id): Path<Uuid>,
-    Json(input): Json<UpdatePermissionTypeInput>,
+    ValidatedJson(input): ValidatedJson<UpdatePermissionTypeInput>,
 ) -> Result<Json<PermissionType>, StatusCode> {
     svc.update_permission_type(id, input)
         .await
@@ -400,7 +415,7 @@ async fn list_relationship_types(
 
 async fn create_relationship_type(
     State(svc): State<RebacService>,
-    Json(input): Json<CreateRelationshipTypeInput>,
+    ValidatedJson(input): ValidatedJson<CreateRelationshipTypeInput>,
 ) -> Result<(StatusCode, Json<RelationshipType>), StatusCode> {
     svc.create_relationship_type(input)
         .await
@@ -411,7 +426,7 @@ async fn create_relationship_type(
 async fn update_relationship_type(
     State(svc): State<RebacService>,
     Path(id): Path<Uuid>,
-    Json(input): Json<UpdateRelationshipTypeInput>,
+    ValidatedJson(input): ValidatedJson<UpdateRelationshipTypeInput>,
 ) -> Result<Json<RelationshipType>, StatusCode> {
     svc.update_relationship_type(id, input)
         .await
@@ -442,7 +457,7 @@ async fn list_all_roles(
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct UpdateRoleLevelInput {
     pub level: i32,
 }
@@ -450,7 +465,7 @@ pub struct UpdateRoleLevelInput {
 async fn update_role_level(
     State(svc): State<RebacService>,
     Path(id): Path<Uuid>,
-    Json(input): Json<UpdateRoleLevelInput>,
+    ValidatedJson(input): ValidatedJson<UpdateRoleLevelInput>,
 ) -> Result<StatusCode, StatusCode> {
     svc.update_role_level(id, input.level)
         .await
@@ -467,7 +482,7 @@ async fn list_delegation_rules(
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct AddDelegationRuleInput {
     pub granter_role_id: Uuid,
     pub grantee_role_id: Uuid,
@@ -479,7 +494,7 @@ pub struct AddDelegationRuleInput {
 
 async fn add_delegation_rule(
     State(svc): State<RebacService>,
-    Json(input): Json<AddDelegationRuleInput>,
+    ValidatedJson(input): ValidatedJson<AddDelegationRuleInput>,
 ) -> Result<Json<crate::features::abac::models::RoleDelegationRule>, StatusCode> {
     svc.add_delegation_rule(
         input.granter_role_id,
@@ -506,7 +521,7 @@ async fn remove_delegation_rule(
 
 async fn simulate_role_change(
     State(svc): State<RebacService>,
-    Json(input): Json<SimulateRoleChangeInput>,
+    ValidatedJson(input): ValidatedJson<SimulateRoleChangeInput>,
 ) -> Result<Json<ImpactReport>, StatusCode> {
     let impact_svc = ImpactService::new(svc.pool.clone());
     impact_svc
@@ -530,7 +545,7 @@ async fn get_role_permission_matrix(
 
 async fn batch_update_role_permissions(
     State(svc): State<RebacService>,
-    Json(input): Json<BatchUpdateRolePermissionsInput>,
+    ValidatedJson(input): ValidatedJson<BatchUpdateRolePermissionsInput>,
 ) -> Result<StatusCode, StatusCode> {
     svc.batch_update_role_permissions(input)
         .await
@@ -1,4 +1,7 @@
 use super::models::*;
+use crate::utils::api_error::ApiError;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
 use moka::future::Cache;
 use sqlx::{Pool, Postgres};
 use std::time::Duration;
@@ -29,8 +32,29 @@ impl From<sqlx::Error> for RebacError {
     }
 }
 
+impl From<RebacError> for ApiError {
+    fn from(err: RebacError) -> Self {
+        let (status, code) = match &err {
+            RebacError::NotFound(_) => (StatusCode::NOT_FOUND, "rebac.not_found"),
+            RebacError::InvalidInput(_) => (StatusCode::BAD_REQUEST, "rebac.invalid_input"),
+            RebacError::PermissionDenied(_) => (StatusCode::FORBIDDEN, "rebac.permission_denied"),
+            RebacError::DatabaseError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+        };
+        ApiError::new(status, code, err.to_string())
+    }
+}
+
+impl IntoResponse for RebacError {
+    fn into_response(self) -> Response {
+        ApiError::from(self).into_response()
+    }
+}
+
+use super::kernel::PermissionKernel;
 use super::policy_service::PolicyService;
 
+use crate::config::PermissionsConfig;
+use crate::features::decision_log::DecisionLog;
 use crate::features::ontology::OntologyService;
 
 #[derive(Clone)]
@@ -41,6 +65,12 @@ pub struct RebacService {
     pub policy_service: PolicyService,
     // Cache for (user_id, entity_id, permission, tenant_id) -> PermissionCheckResult
     pub(crate) permission_cache: Cache<(Uuid, Uuid, String, Option<Uuid>), PermissionCheckResult>,
+    /// Records each integrated permission decision when configured
+    pub(crate) decision_log: Option<DecisionLog>,
+    /// Relationship type ids and permission levels every check needs
+    pub(crate) kernel: PermissionKernel,
+    /// Checks read permission_grants instead of joining roles to permissions
+    pub(crate) denormalized_grants: bool,
 }
 
 impl RebacService {
@@ -61,6 +91,24 @@ impl RebacService {
             audit_service,
             policy_service,
             permission_cache,
+            decision_log: None,
+            kernel: PermissionKernel::new(Duration::from_secs(60)),
+            denormalized_grants: false,
         }
     }
+
+    pub fn with_decision_log(mut self, decision_log: DecisionLog) -> Self {
+        self.decision_log = Some(decision_log);
+        self
+    }
+
+    pub fn with_permissions_config(mut self, config: &PermissionsConfig) -> Self {
+        self.permission_cache = Cache::builder()
+            .max_capacity(config.check_cache_capacity)
+            .time_to_live(Duration::from_secs(config.check_cache_ttl_secs))
+            .build();
+        self.kernel = PermissionKernel::new(Duration::from_secs(config.kernel_cache_ttl_secs));
+        self.denormalized_grants = config.denormalized_grants;
+        self
+    }
 }
@@ -12,11 +12,11 @@ impl RebacService {
         &self,
         user_id: Uuid,
     ) -> Result<Vec<ScopedUserRoleWithDetails>, RebacError> {
-        let rel_type = sqlx::query_as::<_, crate::features::ontology::models::RelationshipType>(
-            "SELECT * FROM relationship_types WHERE name = 'has_role'",
-        )
-        .fetch_one(&self.pool)
-        .await?;
+        let rel_type = self
+            .ontology_service
+            .get_relationship_type("has_role")
+            .await
+            .map_err(|e| RebacError::DatabaseError(e.to_string()))?;
 
         let rels = sqlx::query_as::<_, crate::features::ontology::models::Relationship>(
             "SELECT * FROM relationships WHERE source_entity_id = $1 AND relationship_type_id = $2",
@@ -26,31 +26,44 @@ impl RebacService {
         .fetch_all(&self.pool)
         .await?;
 
+        let scope_id_of = |rel: &crate::features::ontology::models::Relationship| {
+            rel.metadata
+                .as_ref()
+                .and_then(|m| m.get("scope_entity_id"))
+                .and_then(|v| v.as_str())
+                .and_then(|s| Uuid::parse_str(s).ok())
+        };
+        // Roles and scopes of all the assignments in one query
+        let ids: Vec<Uuid> = rels
+            .iter()
+            .flat_map(|rel| std::iter::once(rel.target_entity_id).chain(scope_id_of(rel)))
+            .collect();
+        let entities = self
+            .ontology_service
+            .get_entities(&ids)
+            .await
+            .map_err(|e| RebacError::DatabaseError(e.to_string()))?;
+
         let mut results = Vec::new();
         for rel in rels {
-            let role_entity = self
-                .ontology_service
-                .get_entity(rel.target_entity_id)
-                .await
-                .map_err(|e| RebacError::DatabaseError(e.to_string()))?;
+            let role_entity = entities.get(&rel.target_entity_id).ok_or_else(|| {
+                RebacError::DatabaseError(format!(
+                    "Not found: Entity {} not found",
+                    rel.target_entity_id
+                ))
+            })?;
 
+            let scope_id = scope_id_of(&rel);
+            let scope_name = scope_id
+                .and_then(|sid| entities.get(&sid))
+                .map(|e| e.display_name.clone());
             let metadata = rel.metadata.unwrap_or_default();
-            let scope_id = metadata
-                .get("scope_entity_id")
-                .and_then(|v| v.as_str())
-                .and_then(|s| Uuid::parse_str(s).ok());
-            let mut scope_name = None;
-            if let Some(sid) = scope_id {
-                if let Ok(e) = self.ontology_service.get_entity(sid).await {
-                    scope_name = Some(e.display_name);
-                }
-            }
 
             results.push(ScopedUserRoleWithDetails {
                 id: rel.id,
                 user_id,
                 role_id: rel.target_entity_id,
-                role_name: role_entity.display_name,
+                role_name: role_entity.display_name.clone(),
                 scope_entity_id: scope_id,
                 scope_entity_name: scope_name,
                 valid_from: metadata
@@ -104,19 +117,18 @@ impl RebacService {
         .await?
         .ok_or_else(|| RebacError::NotFound(format!("Role '{}' not found", input.role_name)))?;
 
-        let rel_type = sqlx::query_as::<_, crate::features::ontology::models::RelationshipType>(
-            "SELECT * FROM relationship_types WHERE name = 'has_role'",
-        )
-        .fetch_one(&self.pool)
-        .await?;
+        let rel_type = self
+            .ontology_service
+            .get_relationship_type("has_role")
+            .await
+            .map_err(|e| RebacError::DatabaseError(e.to_string()))?;
 
         if let Some(granter_id) = granted_by {
-            let can_delegate_type =
-                sqlx::query_as::<_, crate::features::ontology::models::RelationshipType>(
-                    "SELECT * FROM relationship_types WHERE name = 'can_delegate'",
-                )
-                .fetch_one(&self.pool)
-                .await?;
+            let can_delegate_type = self
+                .ontology_service
+                .get_relationship_type("can_delegate")
+                .await
+                .map_err(|e| RebacError::DatabaseError(e.to_string()))?;
 
             let is_authorized = sqlx::query_scalar::<_, bool>(
                 r#"
@@ -233,15 +245,34 @@ impl RebacService {
             .map_err(|e| RebacError::InvalidInput(format!("Invalid cron expression: {}", e)))
     }
 
+    /// The clock override of the user's active test mode session, if any
+    pub async fn clock_override(&self, user_id: Uuid) -> Result<Option<DateTime<Utc>>, RebacError> {
+        let clock = sqlx::query_scalar::<_, Option<DateTime<Utc>>>("SELECT test_mode_clock($1)")
+            .bind(user_id)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(clock)
+    }
+
+    /// The time the user's temporal checks run against
+    pub async fn effective_now(&self, user_id: Uuid) -> Result<DateTime<Utc>, RebacError> {
+        Ok(self.clock_override(user_id).await?.unwrap_or_else(Utc::now))
+    }
+
     pub fn is_within_cron_schedule(cron_expression: &str) -> Result<bool, RebacError> {
+        Self::is_within_cron_schedule_at(cron_expression, Utc::now())
+    }
+
+    pub fn is_within_cron_schedule_at(
+        cron_expression: &str,
+        now: DateTime<Utc>,
+    ) -> Result<bool, RebacError> {
         use chrono::Duration;
         use std::str::FromStr;
 
         let schedule = cron::Schedule::from_str(cron_expression)
             .map_err(|e| RebacError::InvalidInput(format!("Invalid cron expression: {}", e)))?;
 
-        let now = Utc::now();
-
         if let Some(prev) = schedule.after(&(now - Duration::minutes(1))).next() {
             if prev <= now && (now - prev).num_seconds() < 60 {
                 return Ok(true);
@@ -264,7 +295,10 @@ impl RebacService {
     }
 
     pub fn is_role_active(role: &ScopedUserRole) -> bool {
-        let now = Utc::now();
+        Self::is_role_active_at(role, Utc::now())
+    }
+
+    pub fn is_role_active_at(role: &ScopedUserRole, now: DateTime<Utc>) -> bool {
         if role.revoked_at.is_some() {
             return false;
         }
@@ -280,7 +314,7 @@ impl RebacService {
         }
         if let Some(ref cron_expr) = role.schedule_cron {
             if !cron_expr.is_empty() {
-                match Self::is_within_cron_schedule(cron_expr) {
+                match Self::is_within_cron_schedule_at(cron_expr, now) {
                     Ok(is_active) => {
                         if !is_active {
                             return false;
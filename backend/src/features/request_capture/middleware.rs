@@ -0,0 +1,149 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::{Request, State},
+    http::{header, HeaderMap},
+    middleware::Next,
+    response::Response,
+};
+use futures::StreamExt;
+use std::time::Instant;
+
+use crate::features::request_capture::models::NewCapture;
+use crate::features::request_capture::service::{
+    sanitize_body, sanitize_headers, sanitize_query, sanitize_request_body, RequestCaptureService,
+    REPLAY_HEADER,
+};
+
+/// Bodies larger than this are not buffered for capture
+const MAX_BUFFERED_BODY_BYTES: usize = 10 * 1024 * 1024;
+
+/// The capture admin API itself is never captured
+const EXCLUDED_PREFIX: &str = "/api/request-capture";
+
+pub async fn capture_middleware(
+    State(service): State<RequestCaptureService>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path().to_string();
+    if path.starts_with(EXCLUDED_PREFIX) || request.headers().contains_key(REPLAY_HEADER) {
+        return next.run(request).await;
+    }
+
+    let api_key = request
+        .headers()
+        .get("x-api-key")
+        .and_then(|v| v.to_str().ok());
+    let Some(rule_id) = service.match_rule(&path, api_key) else {
+        return next.run(request).await;
+    };
+
+    let method = request.method().to_string();
+    let raw_query = request.uri().query();
+    let query = raw_query.map(sanitize_query);
+    let query_altered = query.as_deref() != raw_query;
+    let request_headers = sanitize_headers_map(request.headers());
+
+    // Capturing must never change what the handler or the client sees, so
+    // bodies that can't be buffered are passed on and left out of the capture
+    let (parts, body) = request.into_parts();
+    let (body, request_body, body_altered) = match buffer_body(body).await {
+        BufferedBody::Complete(bytes) => {
+            let (request_body, altered) = sanitize_request_body(&bytes);
+            (Body::from(bytes), request_body, altered)
+        }
+        BufferedBody::Uncaptured(body) => (body, None, true),
+    };
+
+    let started = Instant::now();
+    let response = next.run(Request::from_parts(parts, body)).await;
+
+    // Streaming responses (SSE) never complete, so only their headers are captured
+    let is_stream = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("text/event-stream"));
+
+    let (parts, body) = response.into_parts();
+    let (response, response_body, response_truncated) = if is_stream {
+        (Response::from_parts(parts, body), None, false)
+    } else {
+        match buffer_body(body).await {
+            BufferedBody::Complete(bytes) => {
+                let sanitized = sanitize_body(&bytes);
+                (
+                    Response::from_parts(parts, Body::from(bytes)),
+                    sanitized,
+                    false,
+                )
+            }
+            BufferedBody::Uncaptured(body) => (Response::from_parts(parts, body), None, true),
+        }
+    };
+
+    let capture = NewCapture {
+        rule_id,
+        method,
+        path,
+        query,
+        request_headers,
+        request_body,
+        request_altered: query_altered || body_altered,
+        response_status: response.status().as_u16() as i32,
+        response_headers: sanitize_headers_map(response.headers()),
+        response_body,
+        response_truncated,
+        duration_ms: started.elapsed().as_millis() as i64,
+    };
+
+    // Storing the capture must not delay the response
+    tokio::spawn(async move {
+        if let Err(e) = service.record(capture).await {
+            tracing::error!("Failed to store request capture: {}", e);
+        }
+    });
+
+    response
+}
+
+/// A body read into memory, or handed on without being captured
+enum BufferedBody {
+    Complete(Bytes),
+    /// Larger than `MAX_BUFFERED_BODY_BYTES` or failed part way: what was
+    /// read so far followed by the rest, or by the error
+    Uncaptured(Body),
+}
+
+async fn buffer_body(body: Body) -> BufferedBody {
+    let mut stream = body.into_data_stream();
+    let mut chunks: Vec<Bytes> = Vec::new();
+    let mut len = 0;
+    while let Some(chunk) = stream.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                tracing::warn!("Failed to buffer body for capture: {}", e);
+                let read = futures::stream::iter(chunks.into_iter().map(Ok));
+                return BufferedBody::Uncaptured(Body::from_stream(
+                    read.chain(futures::stream::once(async { Err(e) })),
+                ));
+            }
+        };
+        len += chunk.len();
+        chunks.push(chunk);
+        if len > MAX_BUFFERED_BODY_BYTES {
+            let read = futures::stream::iter(chunks.into_iter().map(Ok));
+            return BufferedBody::Uncaptured(Body::from_stream(read.chain(stream)));
+        }
+    }
+    BufferedBody::Complete(Bytes::from(chunks.concat()))
+}
+
+fn sanitize_headers_map(headers: &HeaderMap) -> serde_json::Value {
+    sanitize_headers(
+        headers
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_bytes())),
+    )
+}
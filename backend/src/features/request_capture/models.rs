@@ -0,0 +1,96 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct CaptureRule {
+    pub id: Uuid,
+    pub api_key_id: Option<Uuid>,
+    pub route_prefix: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// In-memory view of an active rule, joined with the API key prefix so the
+/// middleware can match without touching the database.
+#[derive(Debug, FromRow, Clone)]
+pub struct ActiveCaptureRule {
+    pub id: Uuid,
+    pub api_key_prefix: Option<String>,
+    pub route_prefix: Option<String>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct CapturedRequest {
+    pub id: Uuid,
+    pub rule_id: Option<Uuid>,
+    pub method: String,
+    pub path: String,
+    pub query: Option<String>,
+    pub request_headers: serde_json::Value,
+    pub request_body: Option<String>,
+    /// The stored query or request body was redacted, truncated or not
+    /// valid UTF-8, so replaying it would not send the original request
+    pub request_altered: bool,
+    pub response_status: i32,
+    pub response_headers: serde_json::Value,
+    pub response_body: Option<String>,
+    /// The response was too large to buffer or failed part way, so it was
+    /// passed through without its body
+    pub response_truncated: bool,
+    pub duration_ms: i64,
+    pub captured_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Sanitized capture produced by the middleware, ready to be stored.
+#[derive(Debug, Clone)]
+pub struct NewCapture {
+    pub rule_id: Uuid,
+    pub method: String,
+    pub path: String,
+    pub query: Option<String>,
+    pub request_headers: serde_json::Value,
+    pub request_body: Option<String>,
+    pub request_altered: bool,
+    pub response_status: i32,
+    pub response_headers: serde_json::Value,
+    pub response_body: Option<String>,
+    pub response_truncated: bool,
+    pub duration_ms: i64,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateCaptureRuleRequest {
+    pub api_key_id: Option<Uuid>,
+    pub route_prefix: Option<String>,
+    pub duration_minutes: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListCapturesQuery {
+    pub rule_id: Option<Uuid>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReplayQuery {
+    /// Replay even though the stored request differs from what was sent
+    #[serde(default)]
+    pub allow_altered: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReplayResult {
+    pub capture_id: Uuid,
+    pub original_status: i32,
+    pub replay_status: i32,
+    pub status_matches: bool,
+    pub body_matches: bool,
+    pub replay_body: Option<String>,
+    pub duration_ms: i64,
+}
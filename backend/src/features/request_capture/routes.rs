@@ -0,0 +1,132 @@
+use crate::features::auth::jwt::Claims;
+use crate::features::request_capture::models::{
+    CaptureRule, CapturedRequest, CreateCaptureRuleRequest, ListCapturesQuery, ReplayQuery,
+    ReplayResult,
+};
+use crate::features::request_capture::service::{CaptureError, RequestCaptureService};
+use crate::middleware::abac::{enforce_route_permissions, RoutePermission};
+use crate::utils::api_error::ApiError;
+use crate::utils::validation::ValidatedJson;
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    routing::{delete, get, post},
+    Extension, Json, Router,
+};
+use uuid::Uuid;
+
+/// Headers from the admin's own request that authenticate a replay
+const REPLAY_AUTH_HEADERS: &[&str] = &["authorization", "cookie", "x-csrf-token"];
+
+/// Permissions enforced by `enforce_route_permissions` for the routes below
+pub const ROUTE_PERMISSIONS: &[RoutePermission] = &[
+    RoutePermission::new("GET", "/rules", "manage_system"),
+    RoutePermission::new("POST", "/rules", "manage_system"),
+    RoutePermission::new("DELETE", "/rules/:id", "manage_system"),
+    RoutePermission::new("GET", "/captures", "manage_system"),
+    RoutePermission::new("GET", "/captures/:id", "manage_system"),
+    RoutePermission::new("POST", "/captures/:id/replay", "manage_system"),
+];
+
+pub fn request_capture_routes() -> Router<RequestCaptureService> {
+    Router::new()
+        .route("/rules", get(list_rules_handler).post(create_rule_handler))
+        .route("/rules/:id", delete(delete_rule_handler))
+        .route("/captures", get(list_captures_handler))
+        .route("/captures/:id", get(get_capture_handler))
+        .route("/captures/:id/replay", post(replay_handler))
+        .route_layer(axum::middleware::from_fn_with_state(
+            ROUTE_PERMISSIONS,
+            enforce_route_permissions,
+        ))
+}
+
+#[axum::debug_handler]
+async fn list_rules_handler(
+    State(service): State<RequestCaptureService>,
+) -> Result<Json<Vec<CaptureRule>>, CaptureError> {
+    Ok(Json(service.list_rules().await?))
+}
+
+#[axum::debug_handler]
+async fn create_rule_handler(
+    State(service): State<RequestCaptureService>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(input): ValidatedJson<CreateCaptureRuleRequest>,
+) -> Result<(StatusCode, Json<CaptureRule>), CaptureError> {
+    let created_by = Uuid::parse_str(&claims.sub).ok();
+    let rule = service.create_rule(input, created_by).await?;
+    Ok((StatusCode::CREATED, Json(rule)))
+}
+
+#[axum::debug_handler]
+async fn delete_rule_handler(
+    State(service): State<RequestCaptureService>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, CaptureError> {
+    service.delete_rule(id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[axum::debug_handler]
+async fn list_captures_handler(
+    State(service): State<RequestCaptureService>,
+    Query(query): Query<ListCapturesQuery>,
+) -> Result<Json<Vec<CapturedRequest>>, CaptureError> {
+    let captures = service
+        .list_captures(query.rule_id, query.limit.unwrap_or(50))
+        .await?;
+    Ok(Json(captures))
+}
+
+#[axum::debug_handler]
+async fn get_capture_handler(
+    State(service): State<RequestCaptureService>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<CapturedRequest>, CaptureError> {
+    Ok(Json(service.get_capture(id).await?))
+}
+
+#[axum::debug_handler]
+async fn replay_handler(
+    State(service): State<RequestCaptureService>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<ReplayQuery>,
+    headers: HeaderMap,
+) -> Result<Json<ReplayResult>, CaptureError> {
+    let auth_headers = REPLAY_AUTH_HEADERS
+        .iter()
+        .filter_map(|name| {
+            headers
+                .get(*name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| (name.to_string(), v.to_string()))
+        })
+        .collect();
+
+    let result = service
+        .replay(id, auth_headers, query.allow_altered)
+        .await?;
+    Ok(Json(result))
+}
+
+impl axum::response::IntoResponse for CaptureError {
+    fn into_response(self) -> axum::response::Response {
+        let (status, code) = match &self {
+            CaptureError::DatabaseError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+            CaptureError::Forbidden(_) => (StatusCode::FORBIDDEN, "request_capture.forbidden"),
+            CaptureError::InvalidInput(_) => {
+                (StatusCode::BAD_REQUEST, "request_capture.invalid_input")
+            }
+            CaptureError::NotFound => (StatusCode::NOT_FOUND, "request_capture.not_found"),
+            CaptureError::ReplayFailed(_) => {
+                (StatusCode::BAD_GATEWAY, "request_capture.replay_failed")
+            }
+            CaptureError::NotReplayable(_) => {
+                (StatusCode::CONFLICT, "request_capture.not_replayable")
+            }
+        };
+
+        ApiError::new(status, code, self.to_string()).into_response()
+    }
+}
@@ -0,0 +1,495 @@
+use super::models::{
+    ActiveCaptureRule, CaptureRule, CapturedRequest, CreateCaptureRuleRequest, NewCapture,
+    ReplayResult,
+};
+use crate::utils::shutdown::Shutdown;
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use std::borrow::Cow;
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+use thiserror::Error;
+use uuid::Uuid;
+
+/// Default capture window when a rule is created without a duration
+pub const DEFAULT_CAPTURE_MINUTES: i64 = 60;
+/// Captures are kept for this long after being recorded
+pub const CAPTURE_RETENTION_HOURS: i64 = 72;
+/// Upper bound on stored captures per rule so a busy route cannot fill the table
+pub const MAX_CAPTURES_PER_RULE: i64 = 500;
+/// Stored bodies are truncated to this size
+pub const MAX_STORED_BODY_BYTES: usize = 64 * 1024;
+
+/// Headers that carry credentials and are never stored
+const SENSITIVE_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+    "x-csrf-token",
+    "x-api-key",
+    "x-test-rate-limit-bypass",
+];
+
+/// Headers that describe the original connection and must not be replayed verbatim
+const HOP_BY_HOP_HEADERS: &[&str] = &["host", "content-length", "connection", "transfer-encoding"];
+
+/// Marks requests issued by the replay endpoint so they are not captured again
+pub const REPLAY_HEADER: &str = "x-replayed-capture";
+
+const REDACTED: &str = "[REDACTED]";
+
+#[derive(Error, Debug)]
+pub enum CaptureError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    #[error("Capture not found")]
+    NotFound,
+
+    #[error("Replay failed: {0}")]
+    ReplayFailed(String),
+
+    #[error("Capture cannot be replayed: {0}")]
+    NotReplayable(String),
+}
+
+#[derive(Clone)]
+pub struct RequestCaptureService {
+    pool: PgPool,
+    replay_base_url: String,
+    http: reqwest::Client,
+    active_rules: Arc<RwLock<Vec<ActiveCaptureRule>>>,
+}
+
+impl RequestCaptureService {
+    pub fn new(pool: PgPool, replay_base_url: String) -> Self {
+        Self {
+            pool,
+            replay_base_url,
+            http: reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(30))
+                .build()
+                .unwrap_or_default(),
+            active_rules: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+
+    // ========================================================================
+    // RULES
+    // ========================================================================
+
+    pub async fn create_rule(
+        &self,
+        req: CreateCaptureRuleRequest,
+        created_by: Option<Uuid>,
+    ) -> Result<CaptureRule, CaptureError> {
+        let route_prefix = req
+            .route_prefix
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty());
+
+        if req.api_key_id.is_none() && route_prefix.is_none() {
+            return Err(CaptureError::InvalidInput(
+                "Either api_key_id or route_prefix is required".to_string(),
+            ));
+        }
+        if let Some(prefix) = &route_prefix {
+            if !prefix.starts_with('/') {
+                return Err(CaptureError::InvalidInput(
+                    "route_prefix must start with '/'".to_string(),
+                ));
+            }
+        }
+
+        // Capture windows are kept short (5 min to 24 hours, default 1 hour)
+        let minutes = req.duration_minutes.unwrap_or(DEFAULT_CAPTURE_MINUTES);
+        if !(5..=1440).contains(&minutes) {
+            return Err(CaptureError::InvalidInput(
+                "duration_minutes must be between 5 and 1440".to_string(),
+            ));
+        }
+
+        let rule = sqlx::query_as::<_, CaptureRule>(
+            r#"
+            INSERT INTO capture_rules (api_key_id, route_prefix, expires_at, created_by)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, api_key_id, route_prefix, expires_at, created_by, created_at
+            "#,
+        )
+        .bind(req.api_key_id)
+        .bind(route_prefix)
+        .bind(Utc::now() + Duration::minutes(minutes))
+        .bind(created_by)
+        .fetch_one(&self.pool)
+        .await?;
+
+        self.refresh_rules().await?;
+        Ok(rule)
+    }
+
+    pub async fn list_rules(&self) -> Result<Vec<CaptureRule>, CaptureError> {
+        let rules = sqlx::query_as::<_, CaptureRule>(
+            "SELECT id, api_key_id, route_prefix, expires_at, created_by, created_at FROM capture_rules ORDER BY created_at DESC",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rules)
+    }
+
+    pub async fn delete_rule(&self, id: Uuid) -> Result<(), CaptureError> {
+        let result = sqlx::query("DELETE FROM capture_rules WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(CaptureError::NotFound);
+        }
+        self.refresh_rules().await?;
+        Ok(())
+    }
+
+    /// Reload the active rule set used by the middleware
+    pub async fn refresh_rules(&self) -> Result<(), CaptureError> {
+        let rules = sqlx::query_as::<_, ActiveCaptureRule>(
+            r#"
+            SELECT r.id, k.prefix AS api_key_prefix, r.route_prefix, r.expires_at
+            FROM capture_rules r
+            LEFT JOIN api_keys k ON k.id = r.api_key_id
+            WHERE r.expires_at > NOW()
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        if let Ok(mut guard) = self.active_rules.write() {
+            *guard = rules;
+        }
+        Ok(())
+    }
+
+    /// Find the rule (if any) that selects this request for capture.
+    /// A rule with both an API key and a route prefix requires both to match.
+    pub fn match_rule(&self, path: &str, api_key: Option<&str>) -> Option<Uuid> {
+        let guard = self.active_rules.read().ok()?;
+        if guard.is_empty() {
+            return None;
+        }
+        let now = Utc::now();
+
+        guard
+            .iter()
+            .filter(|rule| rule.expires_at > now)
+            .find(|rule| {
+                let key_ok = match &rule.api_key_prefix {
+                    Some(prefix) => api_key.is_some_and(|k| k.starts_with(prefix.as_str())),
+                    None => true,
+                };
+                let route_ok = match &rule.route_prefix {
+                    Some(prefix) => path.starts_with(prefix.as_str()),
+                    None => true,
+                };
+                key_ok && route_ok
+            })
+            .map(|rule| rule.id)
+    }
+
+    // ========================================================================
+    // CAPTURES
+    // ========================================================================
+
+    /// Store a sanitized capture. Returns false once the rule has reached its capture limit.
+    pub async fn record(&self, capture: NewCapture) -> Result<bool, CaptureError> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO captured_requests
+                (rule_id, method, path, query, request_headers, request_body,
+                 request_altered, response_status, response_headers, response_body,
+                 response_truncated, duration_ms, expires_at)
+            SELECT $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13
+            WHERE (SELECT COUNT(*) FROM captured_requests WHERE rule_id = $1) < $14
+            "#,
+        )
+        .bind(capture.rule_id)
+        .bind(capture.method)
+        .bind(capture.path)
+        .bind(capture.query)
+        .bind(capture.request_headers)
+        .bind(capture.request_body)
+        .bind(capture.request_altered)
+        .bind(capture.response_status)
+        .bind(capture.response_headers)
+        .bind(capture.response_body)
+        .bind(capture.response_truncated)
+        .bind(capture.duration_ms)
+        .bind(Utc::now() + Duration::hours(CAPTURE_RETENTION_HOURS))
+        .bind(MAX_CAPTURES_PER_RULE)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn list_captures(
+        &self,
+        rule_id: Option<Uuid>,
+        limit: i64,
+    ) -> Result<Vec<CapturedRequest>, CaptureError> {
+        let captures = sqlx::query_as::<_, CapturedRequest>(
+            r#"
+            SELECT * FROM captured_requests
+            WHERE ($1::uuid IS NULL OR rule_id = $1)
+            ORDER BY captured_at DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(rule_id)
+        .bind(limit.clamp(1, 500))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(captures)
+    }
+
+    pub async fn get_capture(&self, id: Uuid) -> Result<CapturedRequest, CaptureError> {
+        sqlx::query_as::<_, CapturedRequest>("SELECT * FROM captured_requests WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or(CaptureError::NotFound)
+    }
+
+    /// Re-send a captured request against the running server.
+    ///
+    /// Credentials are never stored, so the caller's own auth headers are
+    /// forwarded instead; the replay therefore runs with the admin's identity.
+    /// A query or body that was redacted or truncated for storage is refused
+    /// unless `allow_altered` is set, since it would send a different request.
+    pub async fn replay(
+        &self,
+        id: Uuid,
+        auth_headers: Vec<(String, String)>,
+        allow_altered: bool,
+    ) -> Result<ReplayResult, CaptureError> {
+        let capture = self.get_capture(id).await?;
+        if capture.request_altered && !allow_altered {
+            return Err(CaptureError::NotReplayable(
+                "the stored request was redacted or truncated; pass allow_altered=true to send it anyway".to_string(),
+            ));
+        }
+
+        let mut url = format!(
+            "{}{}",
+            self.replay_base_url.trim_end_matches('/'),
+            capture.path
+        );
+        if let Some(query) = &capture.query {
+            url.push('?');
+            url.push_str(query);
+        }
+
+        let method = reqwest::Method::from_bytes(capture.method.as_bytes())
+            .map_err(|e| CaptureError::ReplayFailed(e.to_string()))?;
+        let mut request = self.http.request(method, &url);
+
+        if let Some(headers) = capture.request_headers.as_object() {
+            for (name, value) in headers {
+                if HOP_BY_HOP_HEADERS.contains(&name.as_str()) {
+                    continue;
+                }
+                if let Some(value) = value.as_str() {
+                    request = request.header(name.as_str(), value);
+                }
+            }
+        }
+        for (name, value) in auth_headers {
+            request = request.header(name, value);
+        }
+        request = request.header(REPLAY_HEADER, id.to_string());
+        if let Some(body) = capture.request_body.clone() {
+            request = request.body(body);
+        }
+
+        let started = Instant::now();
+        let response = request
+            .send()
+            .await
+            .map_err(|e| CaptureError::ReplayFailed(e.to_string()))?;
+        let replay_status = response.status().as_u16() as i32;
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| CaptureError::ReplayFailed(e.to_string()))?;
+        let duration_ms = started.elapsed().as_millis() as i64;
+
+        let replay_body = sanitize_body(&bytes);
+
+        Ok(ReplayResult {
+            capture_id: capture.id,
+            original_status: capture.response_status,
+            replay_status,
+            status_matches: capture.response_status == replay_status,
+            body_matches: capture.response_body == replay_body,
+            replay_body,
+            duration_ms,
+        })
+    }
+
+    /// Delete expired captures and rules whose captures have all aged out
+    pub async fn purge_expired(&self) -> Result<u64, CaptureError> {
+        let captures = sqlx::query("DELETE FROM captured_requests WHERE expires_at <= NOW()")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(
+            "DELETE FROM capture_rules WHERE expires_at <= NOW() - ($1 || ' hours')::interval",
+        )
+        .bind(CAPTURE_RETENTION_HOURS.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(captures.rows_affected())
+    }
+
+    pub async fn start_cleanup_task(self, shutdown: Shutdown) {
+        if let Err(e) = self.refresh_rules().await {
+            tracing::error!("Failed to load capture rules: {}", e);
+        }
+
+        shutdown.clone().spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(300));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = shutdown.cancelled() => break,
+                }
+                match self.purge_expired().await {
+                    Ok(n) if n > 0 => tracing::info!("Purged {} expired request captures", n),
+                    Ok(_) => {}
+                    Err(e) => tracing::error!("Failed to purge request captures: {}", e),
+                }
+                if let Err(e) = self.refresh_rules().await {
+                    tracing::error!("Failed to refresh capture rules: {}", e);
+                }
+            }
+        });
+    }
+}
+
+/// Convert headers to a JSON object, dropping anything that carries credentials
+pub fn sanitize_headers<'a>(
+    headers: impl Iterator<Item = (&'a str, &'a [u8])>,
+) -> serde_json::Value {
+    let mut map = serde_json::Map::new();
+    for (name, value) in headers {
+        let name = name.to_ascii_lowercase();
+        if SENSITIVE_HEADERS.contains(&name.as_str()) || name == REPLAY_HEADER {
+            continue;
+        }
+        let value = String::from_utf8_lossy(value).to_string();
+        match map.get_mut(&name) {
+            Some(serde_json::Value::String(existing)) => {
+                existing.push_str(", ");
+                existing.push_str(&value);
+            }
+            _ => {
+                map.insert(name, serde_json::Value::String(value));
+            }
+        }
+    }
+    serde_json::Value::Object(map)
+}
+
+/// Redact secrets from a body and truncate it for storage.
+/// JSON bodies have sensitive fields replaced; anything else is stored as lossy UTF-8.
+pub fn sanitize_body(bytes: &[u8]) -> Option<String> {
+    sanitize_request_body(bytes).0
+}
+
+/// [`sanitize_body`], and whether the result lost anything beyond JSON
+/// formatting: a redacted field, the tail past `MAX_STORED_BODY_BYTES`, or
+/// bytes that were not valid UTF-8
+pub fn sanitize_request_body(bytes: &[u8]) -> (Option<String>, bool) {
+    if bytes.is_empty() {
+        return (None, false);
+    }
+
+    let (text, redacted) = match serde_json::from_slice::<serde_json::Value>(bytes) {
+        Ok(mut value) => {
+            let redacted = redact_json(&mut value);
+            (value.to_string(), redacted)
+        }
+        _ => match String::from_utf8_lossy(bytes) {
+            Cow::Borrowed(text) => (text.to_string(), false),
+            Cow::Owned(text) => (text, true),
+        },
+    };
+
+    let length = text.len();
+    let text = truncate(text, MAX_STORED_BODY_BYTES);
+    let truncated = text.len() < length;
+    (Some(text), redacted || truncated)
+}
+
+/// Redact the values of sensitive parameters in a query string
+pub fn sanitize_query(query: &str) -> String {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((key, _)) if is_sensitive_key(key) => format!("{}={}", key, REDACTED),
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn is_sensitive_key(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    ["password", "secret", "token", "api_key", "private_key"]
+        .iter()
+        .any(|s| key.contains(s))
+        || ["code", "otp", "backup_codes"].contains(&key.as_str())
+}
+
+/// Returns whether anything was redacted
+fn redact_json(value: &mut serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut redacted = false;
+            for (key, v) in map.iter_mut() {
+                if is_sensitive_key(key) {
+                    *v = serde_json::Value::String(REDACTED.to_string());
+                    redacted = true;
+                } else {
+                    redacted |= redact_json(v);
+                }
+            }
+            redacted
+        }
+        serde_json::Value::Array(items) => {
+            let mut redacted = false;
+            for v in items.iter_mut() {
+                redacted |= redact_json(v);
+            }
+            redacted
+        }
+        _ => false,
+    }
+}
+
+fn truncate(mut text: String, max: usize) -> String {
+    if text.len() > max {
+        let mut end = max;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+    text
+}
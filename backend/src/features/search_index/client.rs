@@ -0,0 +1,386 @@
+use super::models::{BulkOperation, IndexHit, IndexKind};
+use super::service::SearchIndexError;
+use crate::config::SearchIndexConfig;
+use crate::utils::http_client::OutboundClient;
+use reqwest::{RequestBuilder, StatusCode};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+/// Elasticsearch or OpenSearch, through the REST API both share
+#[derive(Clone)]
+pub struct SearchClient {
+    http: OutboundClient,
+    base_url: String,
+    index_prefix: String,
+    auth: Auth,
+}
+
+#[derive(Clone)]
+enum Auth {
+    None,
+    Basic(String, String),
+    ApiKey(String),
+}
+
+impl SearchClient {
+    pub fn from_config(config: &SearchIndexConfig, http: OutboundClient) -> Self {
+        let auth = if !config.username.trim().is_empty() {
+            Auth::Basic(
+                config.username.clone(),
+                std::env::var(&config.password_env).unwrap_or_default(),
+            )
+        } else {
+            match std::env::var(&config.api_key_env) {
+                Ok(key) if !key.trim().is_empty() => Auth::ApiKey(key.trim().to_string()),
+                _ => Auth::None,
+            }
+        };
+        Self {
+            http,
+            base_url: config.url.trim_end_matches('/').to_string(),
+            index_prefix: config.index_prefix.trim().to_string(),
+            auth,
+        }
+    }
+
+    pub fn index_name(&self, kind: IndexKind) -> String {
+        format!("{}-{}", self.index_prefix, kind.suffix())
+    }
+
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.auth {
+            Auth::None => request,
+            Auth::Basic(user, password) => request.basic_auth(user, Some(password)),
+            Auth::ApiKey(key) => request.header("authorization", format!("ApiKey {}", key)),
+        }
+    }
+
+    /// Create the indexes that don't exist yet
+    pub async fn ensure_indexes(&self) -> Result<(), SearchIndexError> {
+        for kind in [IndexKind::Entities, IndexKind::Audit] {
+            let url = format!("{}/{}", self.base_url, self.index_name(kind));
+            let res = self
+                .http
+                .send(|client| self.authorize(client.head(&url)))
+                .await
+                .map_err(|e| SearchIndexError::Index(e.to_string()))?;
+            match res.status() {
+                status if status.is_success() => continue,
+                StatusCode::NOT_FOUND => {}
+                status => {
+                    return Err(SearchIndexError::Index(format!(
+                        "checking index {} returned {}",
+                        self.index_name(kind),
+                        status
+                    )))
+                }
+            }
+
+            let body = index_definition(kind);
+            let res = self
+                .http
+                .send(|client| self.authorize(client.put(&url).json(&body)))
+                .await
+                .map_err(|e| SearchIndexError::Index(e.to_string()))?;
+            let status = res.status();
+            let text = res.text().await.unwrap_or_default();
+            // Another instance may have created it in the meantime
+            if !status.is_success() && !text.contains("resource_already_exists_exception") {
+                return Err(SearchIndexError::Index(format!(
+                    "creating index {} returned {}: {}",
+                    self.index_name(kind),
+                    status,
+                    text
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Send `operations` in one bulk request. Deleting a document that isn't
+    /// there is not an error.
+    pub async fn bulk(&self, operations: &[BulkOperation]) -> Result<(), SearchIndexError> {
+        if operations.is_empty() {
+            return Ok(());
+        }
+        let body = self.bulk_body(operations);
+        let url = format!("{}/_bulk", self.base_url);
+        let res = self
+            .http
+            .send(|client| {
+                self.authorize(
+                    client
+                        .post(&url)
+                        .header("content-type", "application/x-ndjson")
+                        .body(body.clone()),
+                )
+            })
+            .await
+            .map_err(|e| SearchIndexError::Index(e.to_string()))?;
+        let status = res.status();
+        if !status.is_success() {
+            let text = res.text().await.unwrap_or_default();
+            return Err(SearchIndexError::Index(format!(
+                "bulk request returned {}: {}",
+                status, text
+            )));
+        }
+
+        let response: Value = res
+            .json()
+            .await
+            .map_err(|e| SearchIndexError::Index(e.to_string()))?;
+        if response["errors"].as_bool() != Some(true) {
+            return Ok(());
+        }
+        let failures: Vec<String> = response["items"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|item| item.as_object()?.iter().next())
+            .filter(|(action, result)| {
+                let status = result["status"].as_u64().unwrap_or(0);
+                status >= 300 && !(action.as_str() == "delete" && status == 404)
+            })
+            .map(|(_, result)| {
+                format!(
+                    "{}: {}",
+                    result["_id"].as_str().unwrap_or("?"),
+                    result["error"]["reason"]
+                        .as_str()
+                        .unwrap_or("unknown error")
+                )
+            })
+            .collect();
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(SearchIndexError::Index(format!(
+                "{} documents failed: {}",
+                failures.len(),
+                failures.into_iter().take(5).collect::<Vec<_>>().join("; ")
+            )))
+        }
+    }
+
+    fn bulk_body(&self, operations: &[BulkOperation]) -> String {
+        let mut body = String::new();
+        for operation in operations {
+            let (action, kind, id, document) = match operation {
+                BulkOperation::IndexEntity(doc) => (
+                    "index",
+                    IndexKind::Entities,
+                    doc.id,
+                    serde_json::to_value(doc).ok(),
+                ),
+                BulkOperation::IndexAudit(doc) => (
+                    "index",
+                    IndexKind::Audit,
+                    doc.id,
+                    serde_json::to_value(doc).ok(),
+                ),
+                BulkOperation::Delete(kind, id) => ("delete", *kind, *id, None),
+            };
+            let mut header = serde_json::Map::new();
+            header.insert(
+                action.to_string(),
+                json!({ "_index": self.index_name(kind), "_id": id }),
+            );
+            body.push_str(&Value::Object(header).to_string());
+            body.push('\n');
+            if let Some(document) = document {
+                body.push_str(&document.to_string());
+                body.push('\n');
+            }
+        }
+        body
+    }
+
+    /// Entities matching `text` in their name, class or attribute values that
+    /// are shared or belong to `tenant_id`, leaving out system classes
+    pub async fn search_entities(
+        &self,
+        text: &str,
+        tenant_id: Option<Uuid>,
+        limit: i64,
+    ) -> Result<Vec<IndexHit>, SearchIndexError> {
+        let mut tenant_filter =
+            vec![json!({ "bool": { "must_not": { "exists": { "field": "tenant_id" } } } })];
+        if let Some(tenant_id) = tenant_id {
+            tenant_filter.push(json!({ "term": { "tenant_id": tenant_id } }));
+        }
+        let query = json!({
+            "size": limit,
+            "_source": false,
+            "query": {
+                "bool": {
+                    "must": {
+                        "multi_match": {
+                            "query": text,
+                            "type": "bool_prefix",
+                            "fields": ["display_name^3", "class_name.text", "attributes_text"]
+                        }
+                    },
+                    "filter": [
+                        { "term": { "system": false } },
+                        { "bool": { "should": tenant_filter, "minimum_should_match": 1 } }
+                    ]
+                }
+            }
+        });
+        self.search(IndexKind::Entities, &query).await
+    }
+
+    /// Audit events whose action, target type or details match `text`, newest
+    /// first among equally good matches
+    pub async fn search_audit(
+        &self,
+        text: &str,
+        limit: i64,
+    ) -> Result<Vec<IndexHit>, SearchIndexError> {
+        let query = json!({
+            "size": limit,
+            "_source": false,
+            "query": {
+                "multi_match": {
+                    "query": text,
+                    "fields": ["action.text^2", "target_type.text", "metadata_text"]
+                }
+            },
+            "sort": ["_score", { "created_at": "desc" }]
+        });
+        self.search(IndexKind::Audit, &query).await
+    }
+
+    async fn search(
+        &self,
+        kind: IndexKind,
+        query: &Value,
+    ) -> Result<Vec<IndexHit>, SearchIndexError> {
+        let url = format!("{}/{}/_search", self.base_url, self.index_name(kind));
+        let res = self
+            .http
+            .send(|client| self.authorize(client.post(&url).json(query)))
+            .await
+            .map_err(|e| SearchIndexError::Index(e.to_string()))?;
+        let status = res.status();
+        if !status.is_success() {
+            let text = res.text().await.unwrap_or_default();
+            return Err(SearchIndexError::Index(format!(
+                "search returned {}: {}",
+                status, text
+            )));
+        }
+        let response: Value = res
+            .json()
+            .await
+            .map_err(|e| SearchIndexError::Index(e.to_string()))?;
+        Ok(parse_hits(&response))
+    }
+}
+
+/// Hits with scores scaled to the best one
+fn parse_hits(response: &Value) -> Vec<IndexHit> {
+    let hits: Vec<(Uuid, f64)> = response["hits"]["hits"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|hit| {
+            let id = Uuid::parse_str(hit["_id"].as_str()?).ok()?;
+            Some((id, hit["_score"].as_f64().unwrap_or(0.0)))
+        })
+        .collect();
+    let best = hits.iter().map(|(_, score)| *score).fold(0.0, f64::max);
+    hits.into_iter()
+        .map(|(id, score)| IndexHit {
+            id,
+            score: if best > 0.0 { score / best } else { 1.0 },
+        })
+        .collect()
+}
+
+/// Mappings shared by Elasticsearch and OpenSearch. Raw attributes are kept
+/// unindexed so arbitrary keys can't blow up the mapping.
+fn index_definition(kind: IndexKind) -> Value {
+    let keyword_text = json!({ "type": "keyword", "fields": { "text": { "type": "text" } } });
+    let properties = match kind {
+        IndexKind::Entities => json!({
+            "id": { "type": "keyword" },
+            "class_id": { "type": "keyword" },
+            "class_name": keyword_text.clone(),
+            "display_name": {
+                "type": "text",
+                "fields": { "keyword": { "type": "keyword", "ignore_above": 256 } }
+            },
+            "tenant_id": { "type": "keyword" },
+            "system": { "type": "boolean" },
+            "attributes": { "type": "object", "enabled": false },
+            "attributes_text": { "type": "text" },
+            "updated_at": { "type": "date" }
+        }),
+        IndexKind::Audit => json!({
+            "id": { "type": "keyword" },
+            "user_id": { "type": "keyword" },
+            "action": keyword_text.clone(),
+            "target_type": keyword_text.clone(),
+            "target_id": { "type": "keyword" },
+            "metadata": { "type": "object", "enabled": false },
+            "metadata_text": { "type": "text" },
+            "created_at": { "type": "date" },
+            "firefighter_session_id": { "type": "keyword" }
+        }),
+    };
+    json!({ "mappings": { "dynamic": false, "properties": properties } })
+}
+
+/// String, number and boolean values of a JSON document, space separated,
+/// for full-text matching
+pub fn flatten_text(value: &Value) -> String {
+    fn collect(value: &Value, out: &mut Vec<String>) {
+        match value {
+            Value::String(s) => out.push(s.clone()),
+            Value::Number(n) => out.push(n.to_string()),
+            Value::Bool(b) => out.push(b.to_string()),
+            Value::Array(items) => items.iter().for_each(|item| collect(item, out)),
+            Value::Object(map) => map.values().for_each(|item| collect(item, out)),
+            Value::Null => {}
+        }
+    }
+    let mut parts = Vec::new();
+    collect(value, &mut parts);
+    parts.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flatten_text() {
+        let value = json!({ "code": "OSL", "floors": 3, "tags": ["north", null], "nested": { "open": true } });
+        let text = flatten_text(&value);
+        for part in ["OSL", "3", "north", "true"] {
+            assert!(text.contains(part), "{} missing from {}", part, text);
+        }
+        assert_eq!(flatten_text(&Value::Null), "");
+    }
+
+    #[test]
+    fn test_parse_hits_scales_scores() {
+        let a = Uuid::new_v4();
+        let b = Uuid::new_v4();
+        let response = json!({ "hits": { "hits": [
+            { "_id": a.to_string(), "_score": 4.0 },
+            { "_id": "not-a-uuid", "_score": 3.0 },
+            { "_id": b.to_string(), "_score": 1.0 }
+        ] } });
+        assert_eq!(
+            parse_hits(&response),
+            vec![
+                IndexHit { id: a, score: 1.0 },
+                IndexHit { id: b, score: 0.25 }
+            ]
+        );
+        assert!(parse_hits(&json!({})).is_empty());
+    }
+}
@@ -0,0 +1,84 @@
+use crate::features::ai::index_worker::IndexWorkerState;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// An entity in `<prefix>-entities`
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct EntityDocument {
+    pub id: Uuid,
+    pub class_id: Uuid,
+    pub class_name: String,
+    pub display_name: String,
+    pub tenant_id: Option<Uuid>,
+    /// Entity of a system class (users, roles, events); quick search skips them
+    pub system: bool,
+    /// Stored but not indexed; without sensitive properties unless
+    /// `search_index.include_sensitive` is set
+    pub attributes: serde_json::Value,
+    /// String values of `attributes`, for full-text matching
+    pub attributes_text: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A row of `unified_audit_logs` in `<prefix>-audit`
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AuditDocument {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: Option<Uuid>,
+    /// Stored but not indexed
+    pub metadata: Option<serde_json::Value>,
+    /// String values of `metadata`, for full-text matching
+    pub metadata_text: String,
+    pub created_at: DateTime<Utc>,
+    pub firefighter_session_id: Option<Uuid>,
+}
+
+/// Which index a document lives in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexKind {
+    Entities,
+    Audit,
+}
+
+impl IndexKind {
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            IndexKind::Entities => "entities",
+            IndexKind::Audit => "audit",
+        }
+    }
+}
+
+/// One action of a bulk request
+#[derive(Debug, Clone)]
+pub enum BulkOperation {
+    IndexEntity(EntityDocument),
+    IndexAudit(AuditDocument),
+    Delete(IndexKind, Uuid),
+}
+
+/// A match from the index, best first
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexHit {
+    pub id: Uuid,
+    /// Relevance relative to the best hit, 0-1
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchIndexStatus {
+    /// `entities_indexed` counts documents written or deleted
+    #[serde(flatten)]
+    pub state: IndexWorkerState,
+    pub enabled: bool,
+    /// Change events not yet indexed
+    pub pending_events: i64,
+    pub backfilling: bool,
+    /// Running, with a recent heartbeat and no error since its last success
+    pub healthy: bool,
+}
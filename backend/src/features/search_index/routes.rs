@@ -0,0 +1,52 @@
+use super::models::SearchIndexStatus;
+use super::service::{SearchIndex, SearchIndexError};
+use crate::middleware::abac::{enforce_route_permissions, RoutePermission};
+use crate::utils::api_error::ApiError;
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+
+/// Permissions enforced by `enforce_route_permissions` for the routes below
+pub const ROUTE_PERMISSIONS: &[RoutePermission] =
+    &[RoutePermission::new("POST", "/backfill", "manage_system")];
+
+/// Progress of the search indexer, and admin-only backfills
+pub fn search_index_routes() -> Router<SearchIndex> {
+    Router::new()
+        .route("/status", get(status_handler))
+        .route("/backfill", post(backfill_handler))
+        .route_layer(axum::middleware::from_fn_with_state(
+            ROUTE_PERMISSIONS,
+            enforce_route_permissions,
+        ))
+}
+
+async fn status_handler(
+    State(index): State<SearchIndex>,
+) -> Result<Json<SearchIndexStatus>, SearchIndexError> {
+    index.status().await.map(Json)
+}
+
+async fn backfill_handler(
+    State(index): State<SearchIndex>,
+) -> Result<(StatusCode, Json<SearchIndexStatus>), SearchIndexError> {
+    let status = index.request_backfill().await?;
+    Ok((StatusCode::ACCEPTED, Json(status)))
+}
+
+impl IntoResponse for SearchIndexError {
+    fn into_response(self) -> Response {
+        let (status, code) = match &self {
+            SearchIndexError::DatabaseError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+            SearchIndexError::Index(_) => (StatusCode::BAD_GATEWAY, "search_index.index_failed"),
+            SearchIndexError::Disabled => (StatusCode::NOT_FOUND, "search_index.disabled"),
+            SearchIndexError::Forbidden(_) => (StatusCode::FORBIDDEN, "search_index.forbidden"),
+        };
+
+        ApiError::new(status, code, self.to_string()).into_response()
+    }
+}
@@ -0,0 +1,585 @@
+use super::client::{flatten_text, SearchClient};
+use super::models::{
+    AuditDocument, BulkOperation, EntityDocument, IndexHit, IndexKind, SearchIndexStatus,
+};
+use crate::config::SearchIndexConfig;
+use crate::features::ai::index_worker::{prune_change_events, IndexWorkerState, CHANGES_CHANNEL};
+use crate::utils::http_client::OutboundClient;
+use crate::utils::shutdown::Shutdown;
+use chrono::Utc;
+use sqlx::postgres::PgListener;
+use sqlx::{FromRow, PgPool};
+use std::collections::BTreeSet;
+use std::time::Duration;
+use thiserror::Error;
+use uuid::Uuid;
+
+/// Row in `ai_index_state` for this indexer
+const WORKER: &str = "search_index";
+
+/// Handled change events are kept this long, like the embedding indexer's default
+const EVENT_RETENTION_HOURS: i64 = 72;
+
+/// Entities of this class are audit events and go to the audit index
+const AUDIT_CLASS: &str = "SecurityEvent";
+
+#[derive(Error, Debug)]
+pub enum SearchIndexError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+
+    #[error("Search index error: {0}")]
+    Index(String),
+
+    #[error("Search index is not enabled")]
+    Disabled,
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+}
+
+#[derive(Debug, FromRow)]
+struct ChangeEvent {
+    seq: i64,
+    object_type: String,
+    object_id: Uuid,
+    class_id: Option<Uuid>,
+    source_entity_id: Option<Uuid>,
+}
+
+/// Keeps Elasticsearch/OpenSearch indexes of entities and audit events
+/// current from the ontology change feed, and answers searches from them
+/// when `delegate_search` is set.
+#[derive(Clone)]
+pub struct SearchIndex {
+    pool: PgPool,
+    client: SearchClient,
+    config: SearchIndexConfig,
+}
+
+impl SearchIndex {
+    pub fn new(pool: PgPool, client: SearchClient, config: SearchIndexConfig) -> Self {
+        Self {
+            pool,
+            client,
+            config,
+        }
+    }
+
+    /// The configured indexer, or `None` when disabled. A disabled indexer is
+    /// recorded as stopped, so the change feed can be pruned without it.
+    pub async fn from_config(
+        pool: PgPool,
+        config: &SearchIndexConfig,
+        http: OutboundClient,
+    ) -> Result<Option<Self>, SearchIndexError> {
+        if !config.enabled {
+            sqlx::query("UPDATE ai_index_state SET available = FALSE WHERE worker = $1")
+                .bind(WORKER)
+                .execute(&pool)
+                .await?;
+            return Ok(None);
+        }
+        if config.index_prefix.trim().is_empty() {
+            return Err(SearchIndexError::Index(
+                "search_index.index_prefix must not be empty".to_string(),
+            ));
+        }
+        let client = SearchClient::from_config(config, http);
+        Ok(Some(Self::new(pool, client, config.clone())))
+    }
+
+    /// Whether searches should be answered from the index
+    pub fn delegates_search(&self) -> bool {
+        self.config.delegate_search
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(self.config.sync_interval_secs.max(1))
+    }
+
+    fn batch_size(&self) -> i64 {
+        self.config.batch_size.max(1)
+    }
+
+    pub async fn start(self, shutdown: Shutdown) {
+        shutdown.clone().spawn(async move {
+            // The cluster may come up after us; keep trying on each pass
+            let mut enabled = false;
+
+            let mut listener = match PgListener::connect_with(&self.pool).await {
+                Ok(mut listener) => match listener.listen(CHANGES_CHANNEL).await {
+                    Ok(()) => Some(listener),
+                    Err(e) => {
+                        tracing::warn!("Search indexer can't listen for changes, polling: {}", e);
+                        None
+                    }
+                },
+                Err(e) => {
+                    tracing::warn!("Search indexer can't listen for changes, polling: {}", e);
+                    None
+                }
+            };
+
+            let mut interval = tokio::time::interval(self.interval());
+            loop {
+                let periodic = tokio::select! {
+                    _ = interval.tick() => true,
+                    notification = async {
+                        match listener.as_mut() {
+                            Some(listener) => listener.recv().await,
+                            None => std::future::pending().await,
+                        }
+                    } => {
+                        if let Err(e) = notification {
+                            tracing::warn!("Search indexer lost its change listener, polling: {}", e);
+                            listener = None;
+                        }
+                        false
+                    }
+                    _ = shutdown.cancelled() => break,
+                };
+                if !enabled {
+                    match self.enable().await {
+                        Ok(()) => enabled = true,
+                        Err(e) => {
+                            self.record_error(&e).await;
+                            continue;
+                        }
+                    }
+                }
+                self.run_once(periodic, &shutdown).await;
+            }
+        });
+    }
+
+    /// Create missing indexes and mark the indexer as running. Changes made
+    /// while it was stopped may have been pruned from the feed, so an
+    /// indexer that wasn't running starts from the end of the feed with a
+    /// full backfill, which is also how new indexes get their documents.
+    pub async fn enable(&self) -> Result<(), SearchIndexError> {
+        self.client.ensure_indexes().await?;
+        let running: Option<bool> =
+            sqlx::query_scalar("SELECT available FROM ai_index_state WHERE worker = $1")
+                .bind(WORKER)
+                .fetch_optional(&self.pool)
+                .await?;
+        if running == Some(true) {
+            return Ok(());
+        }
+        sqlx::query(
+            r#"
+            INSERT INTO ai_index_state (worker, last_seq, available, heartbeat_at)
+            SELECT $1, COALESCE(MAX(seq), 0), TRUE, NOW() FROM ontology_change_events
+            ON CONFLICT (worker) DO UPDATE SET
+                last_seq = EXCLUDED.last_seq,
+                available = TRUE,
+                heartbeat_at = NOW()
+            "#,
+        )
+        .bind(WORKER)
+        .execute(&self.pool)
+        .await?;
+        self.request_backfill().await?;
+        Ok(())
+    }
+
+    /// One pass: index pending change events and continue any backfill. The
+    /// `periodic` pass also re-indexes what recent events touched, in case
+    /// some were committed after later ones had been handled, and prunes the
+    /// feed. Errors are recorded in the indexer state and retried on the
+    /// next pass.
+    pub async fn run_once(&self, periodic: bool, shutdown: &Shutdown) {
+        let result = async {
+            sqlx::query("UPDATE ai_index_state SET heartbeat_at = NOW() WHERE worker = $1")
+                .bind(WORKER)
+                .execute(&self.pool)
+                .await?;
+            self.process_events(shutdown).await?;
+            self.continue_backfill(shutdown).await?;
+            if periodic {
+                self.recheck_recent().await?;
+                prune_change_events(&self.pool, EVENT_RETENTION_HOURS).await?;
+            }
+            Ok::<_, SearchIndexError>(())
+        }
+        .await;
+
+        if let Err(e) = result {
+            self.record_error(&e).await;
+        }
+    }
+
+    async fn record_error(&self, error: &SearchIndexError) {
+        tracing::warn!("Search indexer failed: {}", error);
+        let _ = sqlx::query(
+            "UPDATE ai_index_state SET last_error = $2, last_error_at = NOW() WHERE worker = $1",
+        )
+        .bind(WORKER)
+        .bind(error.to_string())
+        .execute(&self.pool)
+        .await;
+    }
+
+    /// Index change events in order. The position only advances past events
+    /// whose documents were written.
+    async fn process_events(&self, shutdown: &Shutdown) -> Result<(), SearchIndexError> {
+        loop {
+            let last_seq = self.state().await?.last_seq;
+            let events = sqlx::query_as::<_, ChangeEvent>(
+                r#"
+                SELECT seq, object_type, object_id, class_id, source_entity_id
+                FROM ontology_change_events
+                WHERE seq > $1
+                ORDER BY seq
+                LIMIT $2
+                "#,
+            )
+            .bind(last_seq)
+            .bind(self.batch_size())
+            .fetch_all(&self.pool)
+            .await?;
+            let Some(last) = events.last().map(|e| e.seq) else {
+                return Ok(());
+            };
+
+            let written = self.index_events(&events).await?;
+            self.record_progress(last, events.len(), written).await?;
+
+            if (events.len() as i64) < self.batch_size() || shutdown.is_triggered() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Re-index what the events of the last two intervals touched. Documents
+    /// are built from the current rows, so repeating them is harmless.
+    async fn recheck_recent(&self) -> Result<(), SearchIndexError> {
+        let window = chrono::Duration::from_std(self.interval() * 2)
+            .unwrap_or_else(|_| chrono::Duration::hours(1));
+        let last_seq = self.state().await?.last_seq;
+        let events = sqlx::query_as::<_, ChangeEvent>(
+            r#"
+            SELECT seq, object_type, object_id, class_id, source_entity_id
+            FROM ontology_change_events
+            WHERE seq <= $1 AND created_at > $2
+            ORDER BY seq
+            "#,
+        )
+        .bind(last_seq)
+        .bind(Utc::now() - window)
+        .fetch_all(&self.pool)
+        .await?;
+        for chunk in events.chunks(self.batch_size() as usize) {
+            self.index_events(chunk).await?;
+        }
+        Ok(())
+    }
+
+    /// Index what the events touched as it is now: changed entities, every
+    /// entity of a class whose name or sensitive properties changed, and
+    /// audit events whose user or target relationship changed
+    async fn index_events(&self, events: &[ChangeEvent]) -> Result<usize, SearchIndexError> {
+        let mut entity_ids = BTreeSet::new();
+        let mut class_ids = BTreeSet::new();
+        let mut relationship_sources = BTreeSet::new();
+        for event in events {
+            match (event.object_type.as_str(), event.class_id) {
+                ("entity", _) => {
+                    entity_ids.insert(event.object_id);
+                }
+                ("relationship", _) => {
+                    relationship_sources.extend(event.source_entity_id);
+                }
+                (_, Some(class_id)) => {
+                    class_ids.insert(class_id);
+                }
+                _ => {}
+            }
+        }
+        if !class_ids.is_empty() {
+            let class_ids: Vec<Uuid> = class_ids.into_iter().collect();
+            let members: Vec<Uuid> = sqlx::query_scalar(
+                "SELECT id FROM entities WHERE class_id = ANY($1) AND deleted_at IS NULL",
+            )
+            .bind(&class_ids)
+            .fetch_all(&self.pool)
+            .await?;
+            entity_ids.extend(members);
+        }
+
+        // Entities that only appear as relationship sources
+        let relationship_only: BTreeSet<Uuid> = relationship_sources
+            .difference(&entity_ids)
+            .copied()
+            .collect();
+        let ids: Vec<Uuid> = entity_ids.union(&relationship_only).copied().collect();
+        let operations = self.operations(&ids, &relationship_only).await?;
+        for chunk in operations.chunks(self.batch_size() as usize) {
+            self.client.bulk(chunk).await?;
+        }
+        Ok(operations.len())
+    }
+
+    /// Bulk operations bringing the documents of `ids` up to date. Missing or
+    /// deleted entities are removed from both indexes. Entities that are only
+    /// relationship sources are indexed if they are audit events and left
+    /// alone otherwise.
+    async fn operations(
+        &self,
+        ids: &[Uuid],
+        relationship_only: &BTreeSet<Uuid>,
+    ) -> Result<Vec<BulkOperation>, SearchIndexError> {
+        let entities = self.entity_documents(ids).await?;
+        let audits = self.audit_documents(ids).await?;
+        let found: BTreeSet<Uuid> = entities
+            .iter()
+            .map(|d| d.id)
+            .chain(audits.iter().map(|d| d.id))
+            .collect();
+
+        let mut operations = Vec::with_capacity(ids.len());
+        for id in ids {
+            if !found.contains(id) && !relationship_only.contains(id) {
+                operations.push(BulkOperation::Delete(IndexKind::Entities, *id));
+                operations.push(BulkOperation::Delete(IndexKind::Audit, *id));
+            }
+        }
+        operations.extend(
+            entities
+                .into_iter()
+                .filter(|d| !relationship_only.contains(&d.id))
+                .map(BulkOperation::IndexEntity),
+        );
+        operations.extend(audits.into_iter().map(BulkOperation::IndexAudit));
+        Ok(operations)
+    }
+
+    /// Live entities among `ids`, other than audit events
+    async fn entity_documents(
+        &self,
+        ids: &[Uuid],
+    ) -> Result<Vec<EntityDocument>, SearchIndexError> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut documents = sqlx::query_as::<_, EntityDocument>(
+            r#"
+            SELECT e.id, e.class_id, c.name AS class_name, e.display_name, e.tenant_id,
+                   ov.is_system AS system, e.updated_at, '' AS attributes_text,
+                   CASE WHEN $2 THEN e.attributes ELSE e.attributes - COALESCE(
+                       (SELECT array_agg(p.name) FROM properties p
+                        WHERE p.class_id = e.class_id AND p.is_sensitive = TRUE),
+                       '{}'
+                   ) END AS attributes
+            FROM entities e
+            JOIN classes c ON c.id = e.class_id
+            JOIN ontology_versions ov ON ov.id = c.version_id
+            WHERE e.id = ANY($1) AND e.deleted_at IS NULL AND c.name <> $3
+            ORDER BY e.id
+            "#,
+        )
+        .bind(ids)
+        .bind(self.config.include_sensitive)
+        .bind(AUDIT_CLASS)
+        .fetch_all(&self.pool)
+        .await?;
+        for document in &mut documents {
+            document.attributes_text = flatten_text(&document.attributes);
+        }
+        Ok(documents)
+    }
+
+    /// Audit events among `ids`
+    async fn audit_documents(&self, ids: &[Uuid]) -> Result<Vec<AuditDocument>, SearchIndexError> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut documents = sqlx::query_as::<_, AuditDocument>(
+            r#"
+            SELECT u.id, u.user_id, COALESCE(u.action, '') AS action,
+                   COALESCE(u.target_type, '') AS target_type, u.target_id, u.metadata,
+                   '' AS metadata_text, u.created_at, u.firefighter_session_id
+            FROM unified_audit_logs u
+            JOIN entities e ON e.id = u.id AND e.deleted_at IS NULL
+            WHERE u.id = ANY($1)
+            ORDER BY u.id
+            "#,
+        )
+        .bind(ids)
+        .fetch_all(&self.pool)
+        .await?;
+        for document in &mut documents {
+            if let Some(metadata) = &document.metadata {
+                document.metadata_text = flatten_text(metadata);
+            }
+        }
+        Ok(documents)
+    }
+
+    /// Index the next page of live entities and audit events in id order,
+    /// resuming after `backfill_cursor`
+    async fn continue_backfill(&self, shutdown: &Shutdown) -> Result<(), SearchIndexError> {
+        loop {
+            let state = self.state().await?;
+            if state.backfill_started_at.is_none() || state.backfill_finished_at.is_some() {
+                return Ok(());
+            }
+            let cursor: Option<String> =
+                sqlx::query_scalar("SELECT backfill_cursor FROM ai_index_state WHERE worker = $1")
+                    .bind(WORKER)
+                    .fetch_one(&self.pool)
+                    .await?;
+            let after = cursor.as_deref().and_then(|c| Uuid::parse_str(c).ok());
+
+            let ids: Vec<Uuid> = sqlx::query_scalar(
+                r#"
+                SELECT id FROM entities
+                WHERE deleted_at IS NULL AND ($1::uuid IS NULL OR id > $1)
+                ORDER BY id
+                LIMIT $2
+                "#,
+            )
+            .bind(after)
+            .bind(self.batch_size())
+            .fetch_all(&self.pool)
+            .await?;
+            let operations = self.operations(&ids, &BTreeSet::new()).await?;
+            self.client.bulk(&operations).await?;
+
+            let finished = (ids.len() as i64) < self.batch_size();
+            sqlx::query(
+                r#"
+                UPDATE ai_index_state SET
+                    backfill_cursor = COALESCE($2, backfill_cursor),
+                    backfill_done = backfill_done + $3,
+                    entities_indexed = entities_indexed + $4,
+                    last_indexed_at = NOW(),
+                    backfill_finished_at = CASE WHEN $5 THEN NOW() ELSE NULL END
+                WHERE worker = $1
+                "#,
+            )
+            .bind(WORKER)
+            .bind(ids.last().map(|id| id.to_string()))
+            .bind(ids.len() as i64)
+            .bind(operations.len() as i64)
+            .bind(finished)
+            .execute(&self.pool)
+            .await?;
+
+            if finished || shutdown.is_triggered() {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn record_progress(
+        &self,
+        last_seq: i64,
+        events: usize,
+        written: usize,
+    ) -> Result<(), SearchIndexError> {
+        sqlx::query(
+            r#"
+            UPDATE ai_index_state SET
+                last_seq = $2,
+                events_processed = events_processed + $3,
+                entities_indexed = entities_indexed + $4,
+                last_indexed_at = NOW()
+            WHERE worker = $1
+            "#,
+        )
+        .bind(WORKER)
+        .bind(last_seq)
+        .bind(events as i64)
+        .bind(written as i64)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn state(&self) -> Result<IndexWorkerState, SearchIndexError> {
+        sqlx::query_as::<_, IndexWorkerState>("SELECT * FROM ai_index_state WHERE worker = $1")
+            .bind(WORKER)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or(SearchIndexError::Disabled)
+    }
+
+    pub async fn status(&self) -> Result<SearchIndexStatus, SearchIndexError> {
+        let state = self.state().await?;
+        let pending_events: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM ontology_change_events WHERE seq > $1")
+                .bind(state.last_seq)
+                .fetch_one(&self.pool)
+                .await?;
+
+        // Missing two passes in a row means the indexer has stalled
+        let stale_after = chrono::Duration::from_std(self.interval() * 2)
+            .unwrap_or_else(|_| chrono::Duration::hours(1));
+        let alive = state
+            .heartbeat_at
+            .is_some_and(|at| Utc::now() - at < stale_after);
+        let failing = match (state.last_error_at, state.last_indexed_at) {
+            (Some(error_at), Some(indexed_at)) => error_at > indexed_at,
+            (Some(_), None) => true,
+            _ => false,
+        };
+
+        Ok(SearchIndexStatus {
+            enabled: self.config.enabled,
+            backfilling: state.backfill_started_at.is_some()
+                && state.backfill_finished_at.is_none(),
+            healthy: state.available && alive && !failing,
+            pending_events,
+            state,
+        })
+    }
+
+    /// Re-index every entity and audit event, e.g. after the indexes were
+    /// recreated. Restarts a backfill already in progress.
+    pub async fn request_backfill(&self) -> Result<SearchIndexStatus, SearchIndexError> {
+        sqlx::query(
+            r#"
+            UPDATE ai_index_state SET
+                backfill_started_at = NOW(),
+                backfill_total = (SELECT COUNT(*) FROM entities WHERE deleted_at IS NULL),
+                backfill_done = 0,
+                backfill_cursor = NULL,
+                backfill_finished_at = NULL
+            WHERE worker = $1
+            "#,
+        )
+        .bind(WORKER)
+        .execute(&self.pool)
+        .await?;
+
+        // Wake the indexer rather than waiting for its next pass
+        sqlx::query("SELECT pg_notify($1, '')")
+            .bind(CHANGES_CHANNEL)
+            .execute(&self.pool)
+            .await?;
+
+        self.status().await
+    }
+
+    /// Entities matching `text` that are shared or belong to `tenant_id`,
+    /// best first. Callers still check the user's permissions on them.
+    pub async fn search_entities(
+        &self,
+        text: &str,
+        tenant_id: Option<Uuid>,
+        limit: i64,
+    ) -> Result<Vec<IndexHit>, SearchIndexError> {
+        self.client.search_entities(text, tenant_id, limit).await
+    }
+
+    /// Audit events matching `text`, best first
+    pub async fn search_audit(
+        &self,
+        text: &str,
+        limit: i64,
+    ) -> Result<Vec<IndexHit>, SearchIndexError> {
+        self.client.search_audit(text, limit).await
+    }
+}
@@ -0,0 +1,62 @@
+use crate::utils::api_error::ApiError;
+use crate::utils::storage::{content_disposition, LocalStorage, SignedDownload, StorageError};
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use tokio_util::io::ReaderStream;
+
+/// Presigned downloads of the local store. The signature in the query is the
+/// only credential, so these routes sit outside auth_middleware.
+pub fn storage_routes() -> Router<LocalStorage> {
+    Router::new().route("/objects/*key", get(download_handler))
+}
+
+#[axum::debug_handler]
+async fn download_handler(
+    State(storage): State<LocalStorage>,
+    Path(key): Path<String>,
+    Query(download): Query<SignedDownload>,
+) -> Result<Response, StorageError> {
+    let file = storage.open_signed(&key, &download).await?;
+    let length = file.metadata().await?.len();
+    Ok((
+        [
+            (header::CONTENT_TYPE, download.content_type.clone()),
+            (header::CONTENT_LENGTH, length.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                content_disposition(&download.filename),
+            ),
+        ],
+        Body::from_stream(ReaderStream::new(file)),
+    )
+        .into_response())
+}
+
+impl IntoResponse for StorageError {
+    fn into_response(self) -> Response {
+        let (status, code) = match &self {
+            StorageError::InvalidSignature => (StatusCode::FORBIDDEN, "storage.invalid_signature"),
+            StorageError::InvalidKey => (StatusCode::BAD_REQUEST, "storage.invalid_key"),
+            StorageError::Io(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                (StatusCode::NOT_FOUND, "storage.not_found")
+            }
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+        };
+
+        let message = match status {
+            StatusCode::NOT_FOUND => "Object not found".to_string(),
+            StatusCode::INTERNAL_SERVER_ERROR => {
+                tracing::error!("Download failed: {}", self);
+                "Download failed".to_string()
+            }
+            _ => self.to_string(),
+        };
+        ApiError::new(status, code, message).into_response()
+    }
+}
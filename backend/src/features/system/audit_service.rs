@@ -1,8 +1,31 @@
-use crate::features::auth::models::AuditLog;
+use crate::features::auth::models::{AuditLog, AuditLogQuery};
 use crate::features::auth::service::AuthError;
+use crate::utils::pagination::{contains_pattern, cursor_timestamp, Page, PageRequest, SortField};
+use crate::utils::streaming::EXPORT_CHANNEL_CAPACITY;
+use futures::StreamExt;
 use sqlx::PgPool;
+use tokio_stream::wrappers::ReceiverStream;
 use uuid::Uuid;
 
+static AUDIT_SORT_FIELDS: &[SortField] = &[
+    SortField { name: "created_at", sql: "created_at", sql_type: "timestamptz" },
+    SortField { name: "action", sql: "action", sql_type: "text" },
+    SortField { name: "target_type", sql: "target_type", sql_type: "text" },
+    SortField { name: "user_id", sql: "user_id", sql_type: "uuid" },
+];
+
+/// Filters of `list_logs`, bound at $1..$8
+const AUDIT_FILTERS: &str = r#"
+    ($1::uuid IS NULL OR user_id = $1)
+    AND ($2::uuid IS NULL OR target_id = $2)
+    AND ($3::text IS NULL OR action = $3 OR starts_with(action, $3 || '.'))
+    AND ($4::text IS NULL OR target_type = $4)
+    AND ($5::text IS NULL OR COALESCE(metadata->>'ip_address', metadata->>'ip') = $5)
+    AND ($6::timestamptz IS NULL OR created_at >= $6)
+    AND ($7::timestamptz IS NULL OR created_at < $7)
+    AND ($8::text IS NULL OR action ILIKE $8 OR target_type ILIKE $8 OR metadata::text ILIKE $8)
+"#;
+
 #[derive(Clone)]
 pub struct AuditService {
     pool: PgPool,
@@ -43,7 +66,7 @@ impl AuditService {
 
         // 2. Create SecurityEvent Entity
         let event_id = Uuid::new_v4();
-        let attributes = serde_json::json!({
+        let mut attributes = serde_json::json!({
             "action": action,
             "target_type": target_type,
             "severity": "MEDIUM",
@@ -54,6 +77,23 @@ impl AuditService {
 
         let mut tx = self.pool.begin().await?;
 
+        // Anything done under emergency access is tagged with the session,
+        // for the post-incident review
+        let firefighter_session_id = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            SELECT id FROM firefighter_sessions
+            WHERE user_id = $1 AND deactivated_at IS NULL AND expires_at > NOW()
+            ORDER BY activated_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if let Some(session_id) = firefighter_session_id {
+            attributes["firefighter_session_id"] = serde_json::json!(session_id);
+        }
+
         sqlx::query!(
             r#"
             INSERT INTO entities (id, class_id, display_name, attributes)
@@ -122,6 +162,20 @@ impl AuditService {
         Ok(log)
     }
 
+    /// Everything recorded under a firefighter session, oldest first
+    pub async fn get_logs_for_firefighter_session(
+        &self,
+        session_id: Uuid,
+    ) -> Result<Vec<AuditLog>, AuthError> {
+        let logs = sqlx::query_as::<_, AuditLog>(
+            "SELECT * FROM unified_audit_logs WHERE firefighter_session_id = $1 ORDER BY created_at ASC",
+        )
+        .bind(session_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(logs)
+    }
+
 
     pub async fn get_logs(&self) -> Result<Vec<AuditLog>, AuthError> {
         let logs = sqlx::query_as::<_, AuditLog>(
@@ -131,4 +185,127 @@ impl AuditService {
         .await?;
         Ok(logs)
     }
+
+    /// One page of the entries matching `query`
+    pub async fn list_logs(&self, query: &AuditLogQuery) -> Result<Page<AuditLog>, AuthError> {
+        let page = PageRequest::new(
+            AUDIT_SORT_FIELDS,
+            query.sort.as_deref(),
+            query.order,
+            query.limit,
+            query.offset,
+            query.cursor.as_deref(),
+        )
+        .map_err(|e| AuthError::ValidationError(e.to_string()))?;
+        let non_empty = |value: &Option<String>| {
+            value.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(str::to_string)
+        };
+        let action = non_empty(&query.action);
+        let target_type = non_empty(&query.target_type);
+        let ip = non_empty(&query.ip);
+        let pattern = non_empty(&query.q).map(|q| contains_pattern(&q));
+
+        let total = sqlx::query_scalar::<_, i64>(&format!(
+            "SELECT COUNT(*) FROM unified_audit_logs WHERE {}",
+            AUDIT_FILTERS
+        ))
+        .bind(query.user_id)
+        .bind(query.target_id)
+        .bind(&action)
+        .bind(&target_type)
+        .bind(&ip)
+        .bind(query.from)
+        .bind(query.to)
+        .bind(&pattern)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let sql = format!(
+            "SELECT * FROM unified_audit_logs WHERE {} AND {} ORDER BY {} LIMIT $11 OFFSET $12",
+            AUDIT_FILTERS,
+            page.keyset("id", "uuid", 9),
+            page.order_by("id"),
+        );
+        let logs = sqlx::query_as::<_, AuditLog>(&sql)
+            .bind(query.user_id)
+            .bind(query.target_id)
+            .bind(&action)
+            .bind(&target_type)
+            .bind(&ip)
+            .bind(query.from)
+            .bind(query.to)
+            .bind(&pattern)
+            .bind(page.cursor_value())
+            .bind(page.cursor_id())
+            .bind(page.fetch_limit())
+            .bind(page.offset)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(page.into_page(logs, total, |log, field| {
+            let value = match field {
+                "action" => log.action.clone(),
+                "target_type" => log.target_type.clone(),
+                "user_id" => log.user_id.to_string(),
+                _ => cursor_timestamp(&log.created_at),
+            };
+            (value, log.id.to_string())
+        }))
+    }
+
+    /// Entries whose action, target type or metadata contain `text`, newest first
+    pub async fn search_logs(&self, text: &str, limit: i64) -> Result<Vec<AuditLog>, AuthError> {
+        let pattern = contains_pattern(text);
+        let logs = sqlx::query_as::<_, AuditLog>(
+            r#"
+            SELECT * FROM unified_audit_logs
+            WHERE action ILIKE $1 OR target_type ILIKE $1 OR metadata::text ILIKE $1
+            ORDER BY created_at DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(pattern)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(logs)
+    }
+
+    /// The entries with these ids, in the order given
+    pub async fn get_logs_by_ids(&self, ids: &[Uuid]) -> Result<Vec<AuditLog>, AuthError> {
+        let logs = sqlx::query_as::<_, AuditLog>(
+            r#"
+            SELECT l.* FROM unified_audit_logs l
+            JOIN UNNEST($1::uuid[]) WITH ORDINALITY AS ids(id, position) ON ids.id = l.id
+            ORDER BY ids.position
+            "#,
+        )
+        .bind(ids)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(logs)
+    }
+
+    /// Stream the full audit log, oldest first, without loading it into memory
+    pub fn stream_logs(&self) -> ReceiverStream<Result<AuditLog, sqlx::Error>> {
+        let pool = self.pool.clone();
+        let (tx, rx) = tokio::sync::mpsc::channel(EXPORT_CHANNEL_CAPACITY);
+
+        tokio::spawn(async move {
+            let mut rows = sqlx::query_as::<_, AuditLog>(
+                "SELECT * FROM unified_audit_logs ORDER BY created_at ASC",
+            )
+            .fetch(&pool);
+
+            while let Some(row) = rows.next().await {
+                let failed = row.is_err();
+                // Receiver dropped means the client went away
+                if tx.send(row).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        ReceiverStream::new(rx)
+    }
 }
@@ -1,6 +1,7 @@
 pub mod audit_service;
 pub mod models;
 pub mod routes;
+pub mod schema;
 pub mod service;
 
 pub use audit_service::AuditService;
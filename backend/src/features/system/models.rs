@@ -1,6 +1,12 @@
+use crate::features::ai::index_worker::IndexWorkerStatus;
+use crate::features::graph_sync::models::GraphSyncStatus;
+use crate::features::search_index::models::SearchIndexStatus;
+use crate::features::system::schema::SchemaReport;
+use crate::utils::validation::not_blank;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
+use validator::Validate;
 
 #[derive(Debug, Serialize, Clone)]
 pub struct SystemMetricsResponse {
@@ -52,6 +58,25 @@ pub struct NetworkMetrics {
     pub transmitted_bytes: u64,
 }
 
+/// Build and runtime details, with the health of background workers
+#[derive(Debug, Serialize)]
+pub struct SystemInfo {
+    pub version: String,
+    pub started_at: DateTime<Utc>,
+    pub uptime_secs: i64,
+    pub workers: BackgroundWorkers,
+    /// None when the check couldn't run
+    pub schema: Option<SchemaReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BackgroundWorkers {
+    /// None when the worker isn't running in this process
+    pub embedding_index: Option<IndexWorkerStatus>,
+    pub graph_sync: Option<GraphSyncStatus>,
+    pub search_index: Option<SearchIndexStatus>,
+}
+
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
 pub struct GeneratedReport {
     pub id: Uuid,
@@ -64,7 +89,8 @@ pub struct GeneratedReport {
     pub created_by: Option<Uuid>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct CreateReportRequest {
+    #[validate(custom = "not_blank")]
     pub report_type: String, // ACCESS_AUDIT, USER_ACTIVITY, etc.
 }
@@ -1,12 +1,24 @@
-use super::models::{CreateReportRequest, GeneratedReport, SystemMetricsResponse};
+use super::models::{CreateReportRequest, GeneratedReport, SystemInfo, SystemMetricsResponse};
 use super::service::SystemService;
-use crate::features::auth::models::AuditLog;
-use axum::{extract::State, http::StatusCode, routing::get, Json, Router};
+use crate::features::auth::models::{AuditLog, AuditLogQuery};
+use crate::features::auth::service::AuthError;
+use crate::utils::pagination::Page;
+use crate::utils::streaming::ndjson_response;
+use crate::utils::validation::ValidatedJson;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Response,
+    routing::get,
+    Json, Router,
+};
 
 pub fn system_routes() -> Router<SystemService> {
     Router::new()
+        .route("/info", get(get_system_info))
         .route("/metrics", get(get_system_metrics))
         .route("/logs", get(get_system_logs))
+        .route("/logs/export", get(export_system_logs))
         .route(
             "/reports",
             get(get_system_reports).post(generate_system_report),
@@ -18,15 +30,25 @@ async fn get_system_metrics(State(service): State<SystemService>) -> Json<System
     Json(metrics)
 }
 
+async fn get_system_info(State(service): State<SystemService>) -> Json<SystemInfo> {
+    Json(service.info().await)
+}
+
 async fn get_system_logs(
     State(service): State<SystemService>,
-) -> Result<Json<Vec<AuditLog>>, (StatusCode, String)> {
-    match service.get_logs().await {
-        Ok(logs) => Ok(Json(logs)),
-        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e)),
+    Query(query): Query<AuditLogQuery>,
+) -> Result<Json<Page<AuditLog>>, (StatusCode, String)> {
+    match service.list_logs(&query).await {
+        Ok(page) => Ok(Json(page)),
+        Err(AuthError::ValidationError(e)) => Err((StatusCode::BAD_REQUEST, e)),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
     }
 }
 
+async fn export_system_logs(State(service): State<SystemService>) -> Response {
+    ndjson_response(service.export_logs(), "audit-logs.ndjson")
+}
+
 async fn get_system_reports(
     State(service): State<SystemService>,
 ) -> Result<Json<Vec<GeneratedReport>>, (StatusCode, String)> {
@@ -38,7 +60,7 @@ async fn get_system_reports(
 
 async fn generate_system_report(
     State(service): State<SystemService>,
-    Json(payload): Json<CreateReportRequest>,
+    ValidatedJson(payload): ValidatedJson<CreateReportRequest>,
 ) -> Result<Json<GeneratedReport>, (StatusCode, String)> {
     match service.generate_report(payload.report_type).await {
         Ok(report) => Ok(Json(report)),
@@ -0,0 +1,216 @@
+//! Checks that the database schema is the one this build expects: every
+//! embedded migration applied unchanged, no migrations from a newer build, and
+//! the SQL functions the services call present.
+
+use serde::Serialize;
+use sqlx::migrate::Migrator;
+use sqlx::PgPool;
+
+/// The migrations compiled into this binary
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+/// Functions called from Rust, or by the functions that are. A partial
+/// restore or a hand-edited database can lose these without touching
+/// `_sqlx_migrations`.
+pub const REQUIRED_FUNCTIONS: &[&str] = &[
+    "check_entity_permission",
+    "check_entity_permission_resolved",
+    "check_entity_permission_denormalized",
+    "check_multiple_entities_permission",
+    "get_accessible_entities",
+    "get_user_entity_permissions",
+    "get_entity_ancestors",
+    "get_entity_descendants",
+    "log_failed_auth",
+    "log_security_event",
+    "check_alert_rules",
+    "is_test_data",
+    "mark_as_test_data",
+    "test_mode_clock",
+    "effective_now",
+];
+
+/// An embedded migration that needs attention
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    /// Applied, but the file no longer matches what ran
+    pub modified: bool,
+}
+
+/// A migration recorded in `_sqlx_migrations`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AppliedMigration {
+    pub version: i64,
+    pub description: String,
+}
+
+/// Where the database differs from this build
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SchemaReport {
+    /// Embedded migrations that are pending or were modified after running
+    pub migrations: Vec<MigrationStatus>,
+    /// Applied migrations missing from this build, usually from a newer one
+    pub unknown_migrations: Vec<AppliedMigration>,
+    /// Entries of [`REQUIRED_FUNCTIONS`] the database doesn't have
+    pub missing_functions: Vec<String>,
+}
+
+impl SchemaReport {
+    pub fn has_drift(&self) -> bool {
+        !self.migrations.is_empty()
+            || !self.unknown_migrations.is_empty()
+            || !self.missing_functions.is_empty()
+    }
+
+    /// One line per problem, for the log and the CLI
+    pub fn problems(&self) -> Vec<String> {
+        let migrations = self.migrations.iter().map(|m| {
+            format!(
+                "migration {} ({}) is {}",
+                m.version,
+                m.description,
+                if m.modified {
+                    "applied but modified since"
+                } else {
+                    "pending"
+                }
+            )
+        });
+        let unknown = self.unknown_migrations.iter().map(|m| {
+            format!(
+                "migration {} ({}) is applied but not part of this build",
+                m.version, m.description
+            )
+        });
+        let functions = self
+            .missing_functions
+            .iter()
+            .map(|f| format!("function {} is missing", f));
+        migrations.chain(unknown).chain(functions).collect()
+    }
+}
+
+/// Compare the database against this build
+pub async fn check_schema(pool: &PgPool) -> Result<SchemaReport, sqlx::Error> {
+    let applied = applied_migrations(pool).await?;
+    let unknown_migrations = applied
+        .iter()
+        .filter(|(m, _)| !MIGRATOR.iter().any(|e| e.version == m.version))
+        .map(|(m, _)| m.clone())
+        .collect();
+
+    let present = sqlx::query_scalar::<_, String>(
+        "SELECT DISTINCT proname::text FROM pg_proc WHERE proname::text = ANY($1) AND pg_function_is_visible(oid)",
+    )
+    .bind(REQUIRED_FUNCTIONS)
+    .fetch_all(pool)
+    .await?;
+    let missing_functions = REQUIRED_FUNCTIONS
+        .iter()
+        .filter(|f| !present.iter().any(|p| p == *f))
+        .map(|f| f.to_string())
+        .collect();
+
+    Ok(SchemaReport {
+        migrations: compare_migrations(&applied),
+        unknown_migrations,
+        missing_functions,
+    })
+}
+
+/// Embedded migrations not yet applied, and applied ones whose checksum no
+/// longer matches the file
+pub async fn migration_status(pool: &PgPool) -> Result<Vec<MigrationStatus>, sqlx::Error> {
+    Ok(compare_migrations(&applied_migrations(pool).await?))
+}
+
+async fn applied_migrations(
+    pool: &PgPool,
+) -> Result<Vec<(AppliedMigration, Vec<u8>)>, sqlx::Error> {
+    let table_exists =
+        sqlx::query_scalar::<_, bool>("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+            .fetch_one(pool)
+            .await?;
+    if !table_exists {
+        return Ok(Vec::new());
+    }
+    let rows: Vec<(i64, String, Vec<u8>)> = sqlx::query_as(
+        "SELECT version, description, checksum FROM _sqlx_migrations WHERE success ORDER BY version",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(version, description, checksum)| {
+            (
+                AppliedMigration {
+                    version,
+                    description,
+                },
+                checksum,
+            )
+        })
+        .collect())
+}
+
+fn compare_migrations(applied: &[(AppliedMigration, Vec<u8>)]) -> Vec<MigrationStatus> {
+    MIGRATOR
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration())
+        .filter_map(|m| {
+            let checksum = applied
+                .iter()
+                .find(|(a, _)| a.version == m.version)
+                .map(|(_, c)| c);
+            match checksum {
+                Some(checksum) if checksum.as_slice() == &*m.checksum => None,
+                applied => Some(MigrationStatus {
+                    version: m.version,
+                    description: m.description.to_string(),
+                    modified: applied.is_some(),
+                }),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_problems_are_listed_one_per_line() {
+        let report = SchemaReport {
+            migrations: vec![
+                MigrationStatus {
+                    version: 2,
+                    description: "add things".to_string(),
+                    modified: false,
+                },
+                MigrationStatus {
+                    version: 1,
+                    description: "init".to_string(),
+                    modified: true,
+                },
+            ],
+            unknown_migrations: vec![AppliedMigration {
+                version: 3,
+                description: "from the future".to_string(),
+            }],
+            missing_functions: vec!["get_accessible_entities".to_string()],
+        };
+        assert!(report.has_drift());
+        assert_eq!(
+            report.problems(),
+            vec![
+                "migration 2 (add things) is pending",
+                "migration 1 (init) is applied but modified since",
+                "migration 3 (from the future) is applied but not part of this build",
+                "function get_accessible_entities is missing",
+            ]
+        );
+        assert!(!SchemaReport::default().has_drift());
+    }
+}
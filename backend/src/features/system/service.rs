@@ -1,4 +1,4 @@
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use sqlx::PgPool;
 use std::sync::{Arc, RwLock};
 use std::time::Duration;
@@ -6,16 +6,26 @@ use sysinfo::{Disks, Networks, System};
 
 use super::audit_service::AuditService;
 use super::models::{
-    CpuMetrics, DiskMetrics, GeneratedReport, LoadAvg, MemoryMetrics, NetworkMetrics,
-    SystemMetricsResponse,
+    BackgroundWorkers, CpuMetrics, DiskMetrics, GeneratedReport, LoadAvg, MemoryMetrics,
+    NetworkMetrics, SystemInfo, SystemMetricsResponse,
 };
-use crate::features::auth::models::AuditLog;
+use super::schema;
+use crate::features::ai::index_worker::IndexWorker;
+use crate::features::auth::models::{AuditLog, AuditLogQuery};
+use crate::features::auth::service::AuthError;
+use crate::features::graph_sync::GraphSync;
+use crate::features::search_index::SearchIndex;
+use crate::utils::pagination::{Page, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
 
 #[derive(Clone)]
 pub struct SystemService {
     metrics_cache: Arc<RwLock<SystemMetricsResponse>>,
     pool: PgPool,
     audit_service: AuditService,
+    started_at: DateTime<Utc>,
+    index_worker: Option<IndexWorker>,
+    graph_sync: Option<GraphSync>,
+    search_index: Option<SearchIndex>,
 }
 
 impl SystemService {
@@ -167,6 +177,80 @@ impl SystemService {
             metrics_cache,
             pool,
             audit_service,
+            started_at: Utc::now(),
+            index_worker: None,
+            graph_sync: None,
+            search_index: None,
+        }
+    }
+
+    /// Report the embedding index worker's progress and health in `info`
+    pub fn with_index_worker(mut self, worker: IndexWorker) -> Self {
+        self.index_worker = Some(worker);
+        self
+    }
+
+    /// Report the graph database mirror's progress and health in `info`
+    pub fn with_graph_sync(mut self, sync: GraphSync) -> Self {
+        self.graph_sync = Some(sync);
+        self
+    }
+
+    /// Report the search indexer in `info`, and answer log searches from the
+    /// index when it delegates
+    pub fn with_search_index(mut self, index: SearchIndex) -> Self {
+        self.search_index = Some(index);
+        self
+    }
+
+    pub async fn info(&self) -> SystemInfo {
+        let embedding_index = match &self.index_worker {
+            Some(worker) => match worker.status().await {
+                Ok(status) => Some(status),
+                Err(e) => {
+                    tracing::warn!("Failed to read index worker status: {}", e);
+                    None
+                }
+            },
+            None => None,
+        };
+        let graph_sync = match &self.graph_sync {
+            Some(sync) => match sync.status().await {
+                Ok(status) => Some(status),
+                Err(e) => {
+                    tracing::warn!("Failed to read graph sync status: {}", e);
+                    None
+                }
+            },
+            None => None,
+        };
+        let search_index = match &self.search_index {
+            Some(index) => match index.status().await {
+                Ok(status) => Some(status),
+                Err(e) => {
+                    tracing::warn!("Failed to read search index status: {}", e);
+                    None
+                }
+            },
+            None => None,
+        };
+        let schema = match schema::check_schema(&self.pool).await {
+            Ok(report) => Some(report),
+            Err(e) => {
+                tracing::warn!("Failed to check the database schema: {}", e);
+                None
+            }
+        };
+        SystemInfo {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            started_at: self.started_at,
+            uptime_secs: (Utc::now() - self.started_at).num_seconds(),
+            workers: BackgroundWorkers {
+                embedding_index,
+                graph_sync,
+                search_index,
+            },
+            schema,
         }
     }
 
@@ -181,6 +265,64 @@ impl SystemService {
             .map_err(|e| e.to_string())
     }
 
+    /// One page of audit entries. A plain text search goes to the search
+    /// index when it delegates, and comes back as a single page.
+    pub async fn list_logs(&self, query: &AuditLogQuery) -> Result<Page<AuditLog>, AuthError> {
+        let index = self.search_index.as_ref().filter(|i| i.delegates_search());
+        if let (Some(index), Some(text)) = (index, query.q.as_deref()) {
+            if query.is_text_search() {
+                let limit = query
+                    .limit
+                    .unwrap_or(DEFAULT_PAGE_SIZE)
+                    .clamp(1, MAX_PAGE_SIZE);
+                match index.search_audit(text.trim(), limit).await {
+                    Ok(hits) => {
+                        let ids: Vec<_> = hits.into_iter().map(|hit| hit.id).collect();
+                        let items = self.audit_service.get_logs_by_ids(&ids).await?;
+                        return Ok(Page {
+                            total: items.len() as i64,
+                            items,
+                            limit,
+                            offset: 0,
+                            next_cursor: None,
+                            has_more: false,
+                        });
+                    }
+                    Err(e) => tracing::warn!("Search index unavailable for log search: {}", e),
+                }
+            }
+        }
+        self.audit_service.list_logs(query).await
+    }
+
+    /// Audit entries matching `text`, from the search index when it delegates
+    /// and from the database otherwise or if the index can't be reached
+    pub async fn search_logs(&self, text: &str, limit: i64) -> Result<Vec<AuditLog>, String> {
+        if let Some(index) = self.search_index.as_ref().filter(|i| i.delegates_search()) {
+            match index.search_audit(text, limit).await {
+                Ok(hits) => {
+                    let ids: Vec<_> = hits.into_iter().map(|hit| hit.id).collect();
+                    return self
+                        .audit_service
+                        .get_logs_by_ids(&ids)
+                        .await
+                        .map_err(|e| e.to_string());
+                }
+                Err(e) => tracing::warn!("Search index unavailable for log search: {}", e),
+            }
+        }
+        self.audit_service
+            .search_logs(text, limit)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn export_logs(
+        &self,
+    ) -> tokio_stream::wrappers::ReceiverStream<Result<AuditLog, sqlx::Error>> {
+        self.audit_service.stream_logs()
+    }
+
     pub async fn get_reports(&self) -> Result<Vec<GeneratedReport>, String> {
         let reports = sqlx::query_as::<_, GeneratedReport>(
             "SELECT * FROM generated_reports ORDER BY generated_at DESC",
@@ -7,9 +7,20 @@ use axum::{
 };
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
+use validator::Validate;
 
 use crate::features::auth::jwt::Claims;
 use crate::features::test_marker::service::{TestMarkerError, TestMarkerService};
+use crate::middleware::abac::{enforce_route_permissions, RoutePermission};
+use crate::utils::api_error::ApiError;
+use crate::utils::validation::ValidatedJson;
+
+/// Permissions enforced by `enforce_route_permissions` for the routes below
+pub const ROUTE_PERMISSIONS: &[RoutePermission] = &[RoutePermission::new(
+    "POST",
+    "/cleanup/:days",
+    "manage_system",
+)];
 
 pub fn create_routes() -> Router<TestMarkerService> {
     Router::new()
@@ -17,16 +28,20 @@ pub fn create_routes() -> Router<TestMarkerService> {
         .route("/mark-current-user", post(mark_current_user_handler))
         .route("/is-test-data/:entity_id", get(is_test_data_handler))
         .route("/cleanup/:days", post(cleanup_test_data_handler))
+        .route_layer(axum::middleware::from_fn_with_state(
+            ROUTE_PERMISSIONS,
+            enforce_route_permissions,
+        ))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Validate)]
 struct MarkTestDataRequest {
     entity_id: Uuid,
     test_suite: Option<String>,
     test_name: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Validate)]
 struct MarkCurrentUserRequest {
     test_suite: Option<String>,
     test_name: Option<String>,
@@ -45,18 +60,20 @@ struct CleanupResponse {
 
 impl IntoResponse for TestMarkerError {
     fn into_response(self) -> axum::response::Response {
-        let status = match self {
-            TestMarkerError::DatabaseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
-            TestMarkerError::InfrastructureNotFound => StatusCode::NOT_FOUND,
+        let (status, code) = match self {
+            TestMarkerError::DatabaseError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+            TestMarkerError::InfrastructureNotFound => {
+                (StatusCode::NOT_FOUND, "test_marker.infrastructure_not_found")
+            }
         };
-        (status, self.to_string()).into_response()
+        ApiError::new(status, code, self.to_string()).into_response()
     }
 }
 
 async fn mark_test_data_handler(
     State(service): State<TestMarkerService>,
     Extension(_claims): Extension<Claims>,
-    Json(input): Json<MarkTestDataRequest>,
+    ValidatedJson(input): ValidatedJson<MarkTestDataRequest>,
 ) -> Result<StatusCode, TestMarkerError> {
     let test_suite = input.test_suite.as_deref().unwrap_or("e2e");
     let test_name = input.test_name.as_deref();
@@ -69,7 +86,7 @@ async fn mark_test_data_handler(
 async fn mark_current_user_handler(
     State(service): State<TestMarkerService>,
     Extension(claims): Extension<Claims>,
-    Json(input): Json<MarkCurrentUserRequest>,
+    ValidatedJson(input): ValidatedJson<MarkCurrentUserRequest>,
 ) -> Result<StatusCode, TestMarkerError> {
     let user_id = Uuid::parse_str(&claims.sub)
         .map_err(|_| TestMarkerError::DatabaseError(sqlx::Error::RowNotFound))?;
@@ -94,14 +111,8 @@ async fn is_test_data_handler(
 
 async fn cleanup_test_data_handler(
     State(service): State<TestMarkerService>,
-    Extension(claims): Extension<Claims>,
     Path(days): Path<i32>,
 ) -> Result<Json<CleanupResponse>, TestMarkerError> {
-    // Only superadmin can cleanup
-    if !claims.roles.iter().any(|r| r.role_name == "superadmin") {
-        return Err(TestMarkerError::DatabaseError(sqlx::Error::RowNotFound));
-    }
-
     let deleted_ids = service.cleanup_expired_test_data(days).await?;
     let deleted_count = deleted_ids.len();
 
@@ -0,0 +1,100 @@
+use super::models::{SetClockInput, TestModeSession};
+use super::service::{TestModeError, TestModeService};
+use chrono::Duration;
+use uuid::Uuid;
+
+impl TestModeService {
+    // ========================================================================
+    // MOCK CLOCK
+    // ========================================================================
+
+    /// Freeze the time the user's temporal permission checks see, for as
+    /// long as their session lasts
+    pub async fn set_clock(
+        &self,
+        user_id: Uuid,
+        input: SetClockInput,
+    ) -> Result<TestModeSession, TestModeError> {
+        if !self.config.mock_clock_enabled {
+            return Err(TestModeError::Forbidden(
+                "The mock clock is disabled in this environment".to_string(),
+            ));
+        }
+        let session = self
+            .get_active_session(user_id)
+            .await?
+            .ok_or(TestModeError::NotActive)?;
+        let clock = match (input.at, input.advance_seconds) {
+            (Some(at), None) => at,
+            (None, Some(seconds)) => {
+                let from = session.clock_override.unwrap_or_else(chrono::Utc::now);
+                Duration::try_seconds(seconds)
+                    .and_then(|offset| from.checked_add_signed(offset))
+                    .ok_or_else(|| {
+                        TestModeError::InvalidInput("Clock offset out of range".to_string())
+                    })?
+            }
+            _ => {
+                return Err(TestModeError::InvalidInput(
+                    "Give either a time or an offset in seconds".to_string(),
+                ))
+            }
+        };
+
+        let session = sqlx::query_as::<_, TestModeSession>(
+            "UPDATE test_mode_sessions SET clock_override = $2 WHERE id = $1 RETURNING *",
+        )
+        .bind(session.id)
+        .bind(clock)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let _ = self
+            .audit_service
+            .log(
+                user_id,
+                "test_mode.clock_set",
+                "test_mode_session",
+                None,
+                None,
+                Some(serde_json::json!({
+                    "session_id": session.id,
+                    "clock": clock,
+                })),
+                None,
+            )
+            .await;
+
+        Ok(session)
+    }
+
+    /// Back to real time
+    pub async fn reset_clock(&self, user_id: Uuid) -> Result<TestModeSession, TestModeError> {
+        let session = sqlx::query_as::<_, TestModeSession>(
+            r#"
+            UPDATE test_mode_sessions SET clock_override = NULL
+            WHERE user_id = $1 AND ended_at IS NULL AND expires_at > NOW()
+            RETURNING *
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(TestModeError::NotActive)?;
+
+        let _ = self
+            .audit_service
+            .log(
+                user_id,
+                "test_mode.clock_reset",
+                "test_mode_session",
+                None,
+                None,
+                Some(serde_json::json!({ "session_id": session.id })),
+                None,
+            )
+            .await;
+
+        Ok(session)
+    }
+}
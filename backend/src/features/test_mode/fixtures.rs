@@ -0,0 +1,682 @@
+use super::models::{Fixture, LoadedFixture, TestFixture};
+use super::service::{TestModeError, TestModeService};
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
+    Argon2,
+};
+use serde_json::Value;
+use sqlx::PgConnection;
+use std::collections::{BTreeMap, HashSet};
+use uuid::Uuid;
+use yaml_rust::{Yaml, YamlLoader};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixtureFormat {
+    Json,
+    Yaml,
+}
+
+impl FixtureFormat {
+    /// YAML when the content type says so, JSON otherwise
+    pub fn from_content_type(content_type: Option<&str>) -> Self {
+        match content_type {
+            Some(ct) if ct.contains("yaml") || ct.contains("yml") => FixtureFormat::Yaml,
+            _ => FixtureFormat::Json,
+        }
+    }
+}
+
+pub fn parse_fixture(body: &str, format: FixtureFormat) -> Result<Fixture, TestModeError> {
+    let value = match format {
+        FixtureFormat::Json => serde_json::from_str(body)
+            .map_err(|e| TestModeError::InvalidFixture(format!("Invalid JSON: {}", e)))?,
+        FixtureFormat::Yaml => {
+            let docs = YamlLoader::load_from_str(body)
+                .map_err(|e| TestModeError::InvalidFixture(format!("Invalid YAML: {}", e)))?;
+            match docs.into_iter().next() {
+                Some(doc) => yaml_to_json(doc)?,
+                None => Value::Object(Default::default()),
+            }
+        }
+    };
+    serde_json::from_value(value).map_err(|e| TestModeError::InvalidFixture(e.to_string()))
+}
+
+fn yaml_to_json(yaml: Yaml) -> Result<Value, TestModeError> {
+    Ok(match yaml {
+        Yaml::Null => Value::Null,
+        Yaml::Boolean(b) => Value::Bool(b),
+        Yaml::Integer(i) => Value::from(i),
+        Yaml::Real(s) => s
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(Value::Number)
+            .unwrap_or(Value::String(s)),
+        Yaml::String(s) => Value::String(s),
+        Yaml::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(yaml_to_json)
+                .collect::<Result<_, _>>()?,
+        ),
+        Yaml::Hash(hash) => {
+            let mut map = serde_json::Map::new();
+            for (key, value) in hash {
+                let key = match key {
+                    Yaml::String(s) | Yaml::Real(s) => s,
+                    Yaml::Integer(i) => i.to_string(),
+                    Yaml::Boolean(b) => b.to_string(),
+                    _ => {
+                        return Err(TestModeError::InvalidFixture(
+                            "YAML mapping keys must be scalars".to_string(),
+                        ))
+                    }
+                };
+                map.insert(key, yaml_to_json(value)?);
+            }
+            Value::Object(map)
+        }
+        Yaml::Alias(_) | Yaml::BadValue => {
+            return Err(TestModeError::InvalidFixture(
+                "Unsupported YAML value".to_string(),
+            ))
+        }
+    })
+}
+
+/// Keys resolved so far while loading a fixture
+#[derive(Default)]
+struct Resolved {
+    classes: BTreeMap<String, Uuid>,
+    roles: BTreeMap<String, Uuid>,
+    users: BTreeMap<String, Uuid>,
+    entities: BTreeMap<String, Uuid>,
+    class_ids: Vec<Uuid>,
+    entity_ids: Vec<Uuid>,
+    relationship_ids: Vec<Uuid>,
+}
+
+impl Resolved {
+    /// Roles, users and entities share one namespace, since any of them
+    /// can be a parent or a relationship end
+    fn entity(&self, key: &str) -> Option<Uuid> {
+        self.entities
+            .get(key)
+            .or_else(|| self.roles.get(key))
+            .or_else(|| self.users.get(key))
+            .copied()
+    }
+}
+
+impl TestModeService {
+    // ========================================================================
+    // FIXTURES
+    // ========================================================================
+
+    pub(super) fn ensure_fixtures_enabled(&self) -> Result<(), TestModeError> {
+        if !self.config.fixtures_enabled {
+            return Err(TestModeError::Forbidden(
+                "Fixtures are disabled in this environment".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Create everything the fixture describes in one transaction, marked as
+    /// test data of the user's active session and recorded for cleanup
+    pub async fn load_fixture(
+        &self,
+        user_id: Uuid,
+        fixture: Fixture,
+    ) -> Result<LoadedFixture, TestModeError> {
+        self.ensure_fixtures_enabled()?;
+        let session = self
+            .get_active_session(user_id)
+            .await?
+            .ok_or(TestModeError::NotActive)?;
+        check_keys(&fixture)?;
+
+        let mut tx = self.pool.begin().await?;
+        // Entities inserted from here on are marked by the auto-mark trigger
+        self.set_test_mode_context(&mut tx, user_id, session.id, &session.test_suite)
+            .await?;
+
+        let mut resolved = Resolved::default();
+        create_classes(&mut tx, &fixture, &mut resolved).await?;
+        create_roles(&mut tx, user_id, &fixture, &mut resolved).await?;
+        create_users(&mut tx, &fixture, &mut resolved).await?;
+        create_entities(&mut tx, user_id, &fixture, &mut resolved).await?;
+        create_relationships(&mut tx, user_id, &fixture, &mut resolved).await?;
+
+        let name = fixture
+            .name
+            .unwrap_or_else(|| format!("fixture-{}", chrono::Utc::now().format("%Y%m%d%H%M%S")));
+        let record = sqlx::query_as::<_, TestFixture>(
+            r#"
+            INSERT INTO test_fixtures (session_id, name, loaded_by, class_ids, entity_ids, relationship_ids)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *
+            "#,
+        )
+        .bind(session.id)
+        .bind(&name)
+        .bind(user_id)
+        .bind(&resolved.class_ids)
+        .bind(&resolved.entity_ids)
+        .bind(&resolved.relationship_ids)
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        let _ = self
+            .audit_service
+            .log(
+                user_id,
+                "test_mode.fixture_loaded",
+                "test_fixture",
+                None,
+                None,
+                Some(serde_json::json!({
+                    "fixture_id": record.id,
+                    "name": name,
+                    "session_id": session.id,
+                    "classes": record.class_ids.len(),
+                    "entities": record.entity_ids.len(),
+                    "relationships": record.relationship_ids.len(),
+                })),
+                None,
+            )
+            .await;
+
+        Ok(LoadedFixture {
+            fixture: record,
+            classes: resolved.classes,
+            roles: resolved.roles,
+            users: resolved.users,
+            entities: resolved.entities,
+        })
+    }
+
+    /// Fixtures the user has loaded and not yet removed, newest first
+    pub async fn list_fixtures(&self, user_id: Uuid) -> Result<Vec<TestFixture>, TestModeError> {
+        let fixtures = sqlx::query_as::<_, TestFixture>(
+            r#"
+            SELECT * FROM test_fixtures
+            WHERE loaded_by = $1 AND removed_at IS NULL
+            ORDER BY loaded_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(fixtures)
+    }
+
+    /// Delete everything a fixture created
+    pub async fn remove_fixture(
+        &self,
+        user_id: Uuid,
+        fixture_id: Uuid,
+    ) -> Result<TestFixture, TestModeError> {
+        let mut tx = self.pool.begin().await?;
+        let fixture = sqlx::query_as::<_, TestFixture>(
+            r#"
+            UPDATE test_fixtures SET removed_at = NOW()
+            WHERE id = $1 AND loaded_by = $2 AND removed_at IS NULL
+            RETURNING *
+            "#,
+        )
+        .bind(fixture_id)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(TestModeError::FixtureNotFound)?;
+
+        sqlx::query("DELETE FROM relationships WHERE id = ANY($1)")
+            .bind(&fixture.relationship_ids)
+            .execute(&mut *tx)
+            .await?;
+        // Removes their remaining relationships (test marks included) too
+        sqlx::query("DELETE FROM entities WHERE id = ANY($1)")
+            .bind(&fixture.entity_ids)
+            .execute(&mut *tx)
+            .await?;
+
+        let in_use: Option<String> = sqlx::query_scalar(
+            r#"
+            SELECT c.name FROM classes c
+            WHERE c.id = ANY($1) AND EXISTS (SELECT 1 FROM entities e WHERE e.class_id = c.id)
+            LIMIT 1
+            "#,
+        )
+        .bind(&fixture.class_ids)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if let Some(class) = in_use {
+            return Err(TestModeError::InvalidFixture(format!(
+                "Class '{}' has entities that weren't created by this fixture",
+                class
+            )));
+        }
+        sqlx::query("DELETE FROM classes WHERE id = ANY($1)")
+            .bind(&fixture.class_ids)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        let _ = self
+            .audit_service
+            .log(
+                user_id,
+                "test_mode.fixture_removed",
+                "test_fixture",
+                None,
+                None,
+                Some(serde_json::json!({
+                    "fixture_id": fixture.id,
+                    "name": fixture.name,
+                })),
+                None,
+            )
+            .await;
+
+        Ok(fixture)
+    }
+}
+
+fn check_keys(fixture: &Fixture) -> Result<(), TestModeError> {
+    let duplicate = |kind: &str, key: &str| {
+        TestModeError::InvalidFixture(format!("Duplicate {} key '{}'", kind, key))
+    };
+
+    let mut classes = HashSet::new();
+    for class in &fixture.classes {
+        let key = class.key.as_deref().unwrap_or(&class.name);
+        if !classes.insert(key) {
+            return Err(duplicate("class", key));
+        }
+    }
+
+    let mut entities = HashSet::new();
+    let keys = fixture
+        .roles
+        .iter()
+        .map(|r| r.key.as_deref().unwrap_or(&r.name))
+        .chain(
+            fixture
+                .users
+                .iter()
+                .map(|u| u.key.as_deref().unwrap_or(&u.username)),
+        )
+        .chain(
+            fixture
+                .entities
+                .iter()
+                .map(|e| e.key.as_deref().unwrap_or(&e.display_name)),
+        );
+    for key in keys {
+        if !entities.insert(key) {
+            return Err(duplicate("entity", key));
+        }
+    }
+    Ok(())
+}
+
+/// A fixture class key, or the name of an existing class
+async fn resolve_class(
+    conn: &mut PgConnection,
+    resolved: &Resolved,
+    reference: &str,
+) -> Result<Uuid, TestModeError> {
+    if let Some(id) = resolved.classes.get(reference) {
+        return Ok(*id);
+    }
+    existing_class(conn, reference).await
+}
+
+/// An existing class by name
+pub(super) async fn existing_class(
+    conn: &mut PgConnection,
+    name: &str,
+) -> Result<Uuid, TestModeError> {
+    // Same name in several versions: prefer the current one, then the system one
+    sqlx::query_scalar(
+        r#"
+        SELECT id FROM classes WHERE name = $1
+        ORDER BY COALESCE(version_id = (SELECT id FROM ontology_versions WHERE is_current LIMIT 1), FALSE) DESC,
+                 COALESCE(version_id = (SELECT id FROM ontology_versions WHERE is_system LIMIT 1), FALSE) DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(name)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or_else(|| TestModeError::InvalidFixture(format!("Unknown class '{}'", name)))
+}
+
+/// A fixture entity key. Existing entities can't be referenced, so a
+/// fixture can't hang anything under them or grant anything on them.
+fn resolve_entity(resolved: &Resolved, reference: &str) -> Result<Uuid, TestModeError> {
+    resolved
+        .entity(reference)
+        .ok_or_else(|| TestModeError::InvalidFixture(format!("Unknown entity '{}'", reference)))
+}
+
+pub(super) async fn relationship_type(conn: &mut PgConnection, name: &str) -> Result<Uuid, TestModeError> {
+    sqlx::query_scalar("SELECT id FROM relationship_types WHERE name = $1")
+        .bind(name)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| {
+            TestModeError::InvalidFixture(format!("Unknown relationship type '{}'", name))
+        })
+}
+
+async fn insert_entity(
+    conn: &mut PgConnection,
+    resolved: &mut Resolved,
+    class_id: Uuid,
+    display_name: &str,
+    parent_id: Option<Uuid>,
+    attributes: Value,
+    created_by: Option<Uuid>,
+) -> Result<Uuid, TestModeError> {
+    let id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO entities (class_id, display_name, parent_entity_id, attributes, created_by)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id
+        "#,
+    )
+    .bind(class_id)
+    .bind(display_name)
+    .bind(parent_id)
+    .bind(attributes)
+    .bind(created_by)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| conflict_or(e, display_name))?;
+    resolved.entity_ids.push(id);
+    Ok(id)
+}
+
+async fn insert_relationship(
+    conn: &mut PgConnection,
+    resolved: &mut Resolved,
+    type_id: Uuid,
+    source: Uuid,
+    target: Uuid,
+    metadata: Value,
+    created_by: Uuid,
+) -> Result<(), TestModeError> {
+    let id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO relationships (relationship_type_id, source_entity_id, target_entity_id, metadata, created_by)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id
+        "#,
+    )
+    .bind(type_id)
+    .bind(source)
+    .bind(target)
+    .bind(metadata)
+    .bind(created_by)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| conflict_or(e, &format!("relationship {} -> {}", source, target)))?;
+    resolved.relationship_ids.push(id);
+    Ok(())
+}
+
+/// Unique violations are the fixture's fault, not the server's
+fn conflict_or(error: sqlx::Error, what: &str) -> TestModeError {
+    match &error {
+        sqlx::Error::Database(db) if db.is_unique_violation() => {
+            TestModeError::InvalidFixture(format!("'{}' already exists", what))
+        }
+        _ => error.into(),
+    }
+}
+
+async fn create_classes(
+    conn: &mut PgConnection,
+    fixture: &Fixture,
+    resolved: &mut Resolved,
+) -> Result<(), TestModeError> {
+    if fixture.classes.is_empty() {
+        return Ok(());
+    }
+    let version_id: Uuid =
+        sqlx::query_scalar("SELECT id FROM ontology_versions WHERE is_current = TRUE")
+            .fetch_optional(&mut *conn)
+            .await?
+            .ok_or_else(|| {
+                TestModeError::InvalidFixture("No current ontology version".to_string())
+            })?;
+
+    for class in &fixture.classes {
+        let parent_id = match &class.parent {
+            Some(parent) => Some(resolve_class(conn, resolved, parent).await?),
+            None => None,
+        };
+        let id: Uuid = sqlx::query_scalar(
+            r#"
+            INSERT INTO classes (name, description, parent_class_id, version_id, is_abstract)
+            VALUES ($1, $2, $3, $4, FALSE)
+            RETURNING id
+            "#,
+        )
+        .bind(&class.name)
+        .bind(&class.description)
+        .bind(parent_id)
+        .bind(version_id)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|e| conflict_or(e, &class.name))?;
+        resolved.class_ids.push(id);
+        resolved
+            .classes
+            .insert(class.key.clone().unwrap_or_else(|| class.name.clone()), id);
+    }
+    Ok(())
+}
+
+async fn create_roles(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    fixture: &Fixture,
+    resolved: &mut Resolved,
+) -> Result<(), TestModeError> {
+    if fixture.roles.is_empty() {
+        return Ok(());
+    }
+    let role_class = resolve_class(conn, resolved, "Role").await?;
+    for role in &fixture.roles {
+        let id = insert_entity(
+            conn,
+            resolved,
+            role_class,
+            &role.name,
+            None,
+            serde_json::json!({
+                "name": role.name,
+                "level": role.level,
+                "permissions": role.permissions,
+            }),
+            Some(user_id),
+        )
+        .await?;
+        resolved
+            .roles
+            .insert(role.key.clone().unwrap_or_else(|| role.name.clone()), id);
+    }
+    Ok(())
+}
+
+async fn create_users(
+    conn: &mut PgConnection,
+    fixture: &Fixture,
+    resolved: &mut Resolved,
+) -> Result<(), TestModeError> {
+    if fixture.users.is_empty() {
+        return Ok(());
+    }
+    let user_class = resolve_class(conn, resolved, "User").await?;
+    let has_role = relationship_type(conn, "has_role").await?;
+    for user in &fixture.users {
+        let password_hash = Argon2::default()
+            .hash_password(user.password.as_bytes(), &SaltString::generate(&mut OsRng))
+            .map_err(|e| TestModeError::InvalidFixture(format!("Invalid password: {}", e)))?
+            .to_string();
+        let email = user
+            .email
+            .clone()
+            .unwrap_or_else(|| format!("{}@example.test", user.username));
+        let id = insert_entity(
+            conn,
+            resolved,
+            user_class,
+            &user.username,
+            None,
+            serde_json::json!({
+                "username": user.username,
+                "email": email,
+                "password_hash": password_hash,
+            }),
+            None,
+        )
+        .await?;
+        resolved.users.insert(
+            user.key.clone().unwrap_or_else(|| user.username.clone()),
+            id,
+        );
+
+        for role in &user.roles {
+            // Only the fixture's own roles, never an existing one like admin
+            let role_id = *resolved.roles.get(role).ok_or_else(|| {
+                TestModeError::InvalidFixture(format!("Unknown role '{}'", role))
+            })?;
+            insert_relationship(
+                conn,
+                resolved,
+                has_role,
+                id,
+                role_id,
+                serde_json::json!({}),
+                id,
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+async fn create_entities(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    fixture: &Fixture,
+    resolved: &mut Resolved,
+) -> Result<(), TestModeError> {
+    for entity in &fixture.entities {
+        let class_id = resolve_class(conn, resolved, &entity.class).await?;
+        let parent_id = match &entity.parent {
+            Some(parent) => Some(resolve_entity(resolved, parent)?),
+            None => None,
+        };
+        let id = insert_entity(
+            conn,
+            resolved,
+            class_id,
+            &entity.display_name,
+            parent_id,
+            Value::Object(entity.attributes.clone()),
+            Some(user_id),
+        )
+        .await?;
+        resolved.entities.insert(
+            entity
+                .key
+                .clone()
+                .unwrap_or_else(|| entity.display_name.clone()),
+            id,
+        );
+    }
+    Ok(())
+}
+
+async fn create_relationships(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    fixture: &Fixture,
+    resolved: &mut Resolved,
+) -> Result<(), TestModeError> {
+    for relationship in &fixture.relationships {
+        let type_id = relationship_type(conn, &relationship.relationship_type).await?;
+        let source = resolve_entity(resolved, &relationship.source)?;
+        let target = resolve_entity(resolved, &relationship.target)?;
+        insert_relationship(
+            conn,
+            resolved,
+            type_id,
+            source,
+            target,
+            relationship
+                .metadata
+                .clone()
+                .unwrap_or_else(|| serde_json::json!({})),
+            user_id,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_yaml_and_json_fixtures() {
+        let yaml = r#"
+name: sites
+classes:
+  - name: Site
+entities:
+  - key: a
+    class: Site
+    display_name: Site A
+    attributes:
+      floors: 3
+      ratio: 0.5
+      tags: [north, "1"]
+relationships:
+  - source: a
+    target: b
+    type: depends_on
+"#;
+        let fixture = parse_fixture(yaml, FixtureFormat::Yaml).unwrap();
+        assert_eq!(fixture.name.as_deref(), Some("sites"));
+        assert_eq!(fixture.classes[0].name, "Site");
+        let attributes = &fixture.entities[0].attributes;
+        assert_eq!(attributes["floors"], serde_json::json!(3));
+        assert_eq!(attributes["ratio"], serde_json::json!(0.5));
+        assert_eq!(attributes["tags"], serde_json::json!(["north", "1"]));
+        assert_eq!(fixture.relationships[0].relationship_type, "depends_on");
+
+        let json = r#"{"entities": [{"class": "Site", "display_name": "Site B"}]}"#;
+        let fixture = parse_fixture(json, FixtureFormat::Json).unwrap();
+        assert!(fixture.classes.is_empty());
+        assert_eq!(fixture.entities[0].display_name, "Site B");
+
+        assert!(matches!(
+            parse_fixture("entities: [{class: Site}]", FixtureFormat::Yaml),
+            Err(TestModeError::InvalidFixture(_))
+        ));
+        assert_eq!(
+            FixtureFormat::from_content_type(Some("application/x-yaml")),
+            FixtureFormat::Yaml
+        );
+        assert_eq!(FixtureFormat::from_content_type(None), FixtureFormat::Json);
+    }
+}
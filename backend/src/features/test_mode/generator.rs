@@ -0,0 +1,497 @@
+use super::fixtures::{existing_class, relationship_type};
+use super::models::{GenerateDataInput, GeneratedData, TestFixture};
+use super::service::{TestModeError, TestModeService};
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
+    Argon2,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sqlx::PgConnection;
+use std::collections::{BTreeMap, HashSet};
+use std::time::Instant;
+use uuid::Uuid;
+
+const MAX_ENTITIES: usize = 100_000;
+const MAX_USERS: usize = 10_000;
+const MAX_RELATIONSHIPS: usize = 500_000;
+/// Rows per INSERT
+const BATCH_SIZE: usize = 5_000;
+
+impl TestModeService {
+    // ========================================================================
+    // SYNTHETIC DATA
+    // ========================================================================
+
+    /// Bulk-create a hierarchy of entities, users holding scoped roles in it
+    /// and random relationships between the entities, for load testing the
+    /// permission checks and list endpoints. Everything is marked as test
+    /// data of the user's active session and recorded as a fixture, so
+    /// removing the fixture removes the lot.
+    pub async fn generate_data(
+        &self,
+        user_id: Uuid,
+        input: GenerateDataInput,
+    ) -> Result<GeneratedData, TestModeError> {
+        self.ensure_fixtures_enabled()?;
+        let started = Instant::now();
+        let session = self
+            .get_active_session(user_id)
+            .await?
+            .ok_or(TestModeError::NotActive)?;
+        check_generate_input(&input)?;
+        let mut rng = match input.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        // Tags the batch in attributes and usernames
+        let batch = Uuid::new_v4().simple().to_string()[..8].to_string();
+
+        let mut tx = self.pool.begin().await?;
+        self.set_test_mode_context(&mut tx, user_id, session.id, &session.test_suite)
+            .await?;
+
+        let entity_ids = generate_entities(&mut tx, user_id, &input, &batch).await?;
+        let mut entities_by_class = BTreeMap::new();
+        if !entity_ids.is_empty() {
+            let weights: Vec<u32> = input.class_distribution.values().copied().collect();
+            for (name, count) in input
+                .class_distribution
+                .keys()
+                .zip(split_by_weight(entity_ids.len(), &weights))
+            {
+                entities_by_class.insert(name.clone(), count);
+            }
+        }
+
+        let user_ids = generate_users(&mut tx, &input, &batch).await?;
+        let mut role_ids = Vec::new();
+        let mut relationship_ids = match &input.role {
+            Some(role) if !user_ids.is_empty() => {
+                let role_id = generate_role(&mut tx, user_id, &input, role, &batch).await?;
+                role_ids.push(role_id);
+                assign_role(&mut tx, user_id, role_id, &user_ids, &entity_ids).await?
+            }
+            _ => Vec::new(),
+        };
+        let role_assignments = relationship_ids.len();
+        relationship_ids
+            .extend(generate_relationships(&mut tx, user_id, &input, &entity_ids, &mut rng).await?);
+
+        let name = input
+            .name
+            .clone()
+            .unwrap_or_else(|| format!("synthetic-{}", batch));
+        let all_entity_ids: Vec<Uuid> = entity_ids
+            .iter()
+            .chain(&user_ids)
+            .chain(&role_ids)
+            .copied()
+            .collect();
+        let fixture = sqlx::query_as::<_, TestFixture>(
+            r#"
+            INSERT INTO test_fixtures (session_id, name, loaded_by, entity_ids, relationship_ids)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+            "#,
+        )
+        .bind(session.id)
+        .bind(&name)
+        .bind(user_id)
+        .bind(&all_entity_ids)
+        .bind(&relationship_ids)
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        let generated = GeneratedData {
+            fixture,
+            entities_by_class,
+            users: user_ids.len(),
+            relationships: relationship_ids.len() - role_assignments,
+            depth: hierarchy_depth(entity_ids.len(), input.fanout),
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        };
+
+        let _ = self
+            .audit_service
+            .log(
+                user_id,
+                "test_mode.data_generated",
+                "test_fixture",
+                None,
+                None,
+                Some(serde_json::json!({
+                    "fixture_id": generated.fixture.id,
+                    "name": name,
+                    "session_id": session.id,
+                    "entities": entity_ids.len(),
+                    "users": generated.users,
+                    "relationships": generated.relationships,
+                    "seed": input.seed,
+                    "elapsed_ms": generated.elapsed_ms,
+                })),
+                None,
+            )
+            .await;
+
+        Ok(generated)
+    }
+}
+
+fn check_generate_input(input: &GenerateDataInput) -> Result<(), TestModeError> {
+    let invalid = |message: String| Err(TestModeError::InvalidInput(message));
+    if input.entities > MAX_ENTITIES {
+        return invalid(format!("At most {} entities per run", MAX_ENTITIES));
+    }
+    if input.users > MAX_USERS {
+        return invalid(format!("At most {} users per run", MAX_USERS));
+    }
+    if input.relationships > MAX_RELATIONSHIPS {
+        return invalid(format!(
+            "At most {} relationships per run",
+            MAX_RELATIONSHIPS
+        ));
+    }
+    if input.entities > 0 && input.class_distribution.values().all(|w| *w == 0) {
+        return invalid("The class distribution needs a class with a weight above 0".to_string());
+    }
+    if input.relationships > 0 {
+        if input.relationship_types.is_empty() {
+            return invalid("Relationships need at least one relationship type".to_string());
+        }
+        let possible = input
+            .entities
+            .saturating_mul(input.entities.saturating_sub(1))
+            * input.relationship_types.len();
+        if input.relationships > possible {
+            return invalid(format!(
+                "{} entities only allow {} distinct relationships",
+                input.entities, possible
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Insert the entities in batches, interleaving the classes and hanging each
+/// one under an earlier one. Returns their ids in insertion order.
+async fn generate_entities(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    input: &GenerateDataInput,
+    batch: &str,
+) -> Result<Vec<Uuid>, TestModeError> {
+    if input.entities == 0 {
+        return Ok(Vec::new());
+    }
+    let mut classes = Vec::new();
+    for name in input.class_distribution.keys() {
+        classes.push((name.as_str(), existing_class(conn, name).await?));
+    }
+    let weights: Vec<u32> = input.class_distribution.values().copied().collect();
+    let layout = interleave(&split_by_weight(input.entities, &weights));
+
+    let ids: Vec<Uuid> = (0..input.entities).map(|_| Uuid::new_v4()).collect();
+    let class_ids: Vec<Uuid> = layout.iter().map(|c| classes[*c].1).collect();
+    let names: Vec<String> = layout
+        .iter()
+        .enumerate()
+        .map(|(i, c)| format!("{} {}", classes[*c].0, i + 1))
+        .collect();
+    let parent_ids: Vec<Option<Uuid>> = (0..input.entities)
+        .map(|i| parent_index(i, input.fanout).map(|p| ids[p]))
+        .collect();
+
+    for start in (0..ids.len()).step_by(BATCH_SIZE) {
+        let end = (start + BATCH_SIZE).min(ids.len());
+        // Parents come earlier in the order, so they exist by the time
+        // the foreign key is checked
+        sqlx::query(
+            r#"
+            INSERT INTO entities (id, class_id, display_name, parent_entity_id, attributes, created_by)
+            SELECT id, class_id, display_name, parent_id,
+                   jsonb_build_object('synthetic', TRUE, 'batch', $5::text), $6
+            FROM UNNEST($1::uuid[], $2::uuid[], $3::text[], $4::uuid[]) AS t(id, class_id, display_name, parent_id)
+            "#,
+        )
+        .bind(&ids[start..end])
+        .bind(&class_ids[start..end])
+        .bind(&names[start..end])
+        .bind(&parent_ids[start..end])
+        .bind(batch)
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(ids)
+}
+
+/// Users share one password hash; hashing each would dominate the run
+async fn generate_users(
+    conn: &mut PgConnection,
+    input: &GenerateDataInput,
+    batch: &str,
+) -> Result<Vec<Uuid>, TestModeError> {
+    if input.users == 0 {
+        return Ok(Vec::new());
+    }
+    let user_class = existing_class(conn, "User").await?;
+    let password = input
+        .user_password
+        .clone()
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let password_hash = Argon2::default()
+        .hash_password(password.as_bytes(), &SaltString::generate(&mut OsRng))
+        .map_err(|e| TestModeError::InvalidInput(format!("Invalid password: {}", e)))?
+        .to_string();
+
+    let ids: Vec<Uuid> = (0..input.users).map(|_| Uuid::new_v4()).collect();
+    let usernames: Vec<String> = (0..input.users)
+        .map(|i| format!("synthetic-{}-{}", batch, i + 1))
+        .collect();
+    for start in (0..ids.len()).step_by(BATCH_SIZE) {
+        let end = (start + BATCH_SIZE).min(ids.len());
+        sqlx::query(
+            r#"
+            INSERT INTO entities (id, class_id, display_name, attributes)
+            SELECT id, $3, username, jsonb_build_object(
+                'username', username,
+                'email', username || '@example.test',
+                'password_hash', $4::text,
+                'synthetic', TRUE,
+                'batch', $5::text
+            )
+            FROM UNNEST($1::uuid[], $2::text[]) AS t(id, username)
+            "#,
+        )
+        .bind(&ids[start..end])
+        .bind(&usernames[start..end])
+        .bind(user_class)
+        .bind(&password_hash)
+        .bind(batch)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(ids)
+}
+
+/// The run's own role. Handing out an existing one by name would let the
+/// generated users, whose password the caller picks, log in as admins.
+async fn generate_role(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    input: &GenerateDataInput,
+    role: &str,
+    batch: &str,
+) -> Result<Uuid, TestModeError> {
+    let role_class = existing_class(conn, "Role").await?;
+    let id = sqlx::query_scalar(
+        r#"
+        INSERT INTO entities (class_id, display_name, attributes, created_by)
+        VALUES ($1, $2, jsonb_build_object(
+            'name', $2::text,
+            'level', 0,
+            'permissions', $3::jsonb,
+            'synthetic', TRUE,
+            'batch', $4::text
+        ), $5)
+        RETURNING id
+        "#,
+    )
+    .bind(role_class)
+    .bind(format!("synthetic-{}-{}", batch, role))
+    .bind(serde_json::json!(input.role_permissions))
+    .bind(batch)
+    .bind(user_id)
+    .fetch_one(&mut *conn)
+    .await?;
+    Ok(id)
+}
+
+/// Give every user the role, scoped to an entity spread evenly across the
+/// generated ones (unscoped when there are none)
+async fn assign_role(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    role_id: Uuid,
+    user_ids: &[Uuid],
+    entity_ids: &[Uuid],
+) -> Result<Vec<Uuid>, TestModeError> {
+    let has_role = relationship_type(conn, "has_role").await?;
+    let scopes: Vec<Option<Uuid>> = (0..user_ids.len())
+        .map(|i| {
+            (!entity_ids.is_empty()).then(|| entity_ids[i * entity_ids.len() / user_ids.len()])
+        })
+        .collect();
+
+    let mut ids = Vec::with_capacity(user_ids.len());
+    for start in (0..user_ids.len()).step_by(BATCH_SIZE) {
+        let end = (start + BATCH_SIZE).min(user_ids.len());
+        let batch_ids: Vec<Uuid> = sqlx::query_scalar(
+            r#"
+            INSERT INTO relationships (relationship_type_id, source_entity_id, target_entity_id, metadata, created_by)
+            SELECT $3, source_id, $4,
+                   CASE WHEN scope_id IS NULL THEN '{}'::jsonb
+                        ELSE jsonb_build_object('scope_entity_id', scope_id) END,
+                   $5
+            FROM UNNEST($1::uuid[], $2::uuid[]) AS t(source_id, scope_id)
+            RETURNING id
+            "#,
+        )
+        .bind(&user_ids[start..end])
+        .bind(&scopes[start..end])
+        .bind(has_role)
+        .bind(role_id)
+        .bind(user_id)
+        .fetch_all(&mut *conn)
+        .await?;
+        ids.extend(batch_ids);
+    }
+    Ok(ids)
+}
+
+/// Distinct random (source, target, type) triples between the entities
+async fn generate_relationships(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    input: &GenerateDataInput,
+    entity_ids: &[Uuid],
+    rng: &mut StdRng,
+) -> Result<Vec<Uuid>, TestModeError> {
+    if input.relationships == 0 {
+        return Ok(Vec::new());
+    }
+    let mut type_ids = Vec::new();
+    for name in &input.relationship_types {
+        type_ids.push(relationship_type(conn, name).await?);
+    }
+
+    let mut seen = HashSet::with_capacity(input.relationships);
+    let (mut sources, mut targets, mut types) = (Vec::new(), Vec::new(), Vec::new());
+    // Dense requests collide a lot; give up rather than spin
+    let mut attempts = input.relationships.saturating_mul(20);
+    while seen.len() < input.relationships && attempts > 0 {
+        attempts -= 1;
+        let source = rng.gen_range(0..entity_ids.len());
+        let target = rng.gen_range(0..entity_ids.len());
+        let kind = rng.gen_range(0..type_ids.len());
+        if source != target && seen.insert((source, target, kind)) {
+            sources.push(entity_ids[source]);
+            targets.push(entity_ids[target]);
+            types.push(type_ids[kind]);
+        }
+    }
+
+    let mut ids = Vec::with_capacity(sources.len());
+    for start in (0..sources.len()).step_by(BATCH_SIZE) {
+        let end = (start + BATCH_SIZE).min(sources.len());
+        let batch_ids: Vec<Uuid> = sqlx::query_scalar(
+            r#"
+            INSERT INTO relationships (relationship_type_id, source_entity_id, target_entity_id, metadata, created_by)
+            SELECT type_id, source_id, target_id, jsonb_build_object('synthetic', TRUE), $4
+            FROM UNNEST($1::uuid[], $2::uuid[], $3::uuid[]) AS t(type_id, source_id, target_id)
+            ON CONFLICT DO NOTHING
+            RETURNING id
+            "#,
+        )
+        .bind(&types[start..end])
+        .bind(&sources[start..end])
+        .bind(&targets[start..end])
+        .bind(user_id)
+        .fetch_all(&mut *conn)
+        .await?;
+        ids.extend(batch_ids);
+    }
+    Ok(ids)
+}
+
+/// Split `total` in proportion to the weights, handing the rounding
+/// leftovers to the largest remainders
+fn split_by_weight(total: usize, weights: &[u32]) -> Vec<usize> {
+    let sum: u64 = weights.iter().map(|w| *w as u64).sum();
+    if sum == 0 {
+        return vec![0; weights.len()];
+    }
+    let mut counts: Vec<usize> = weights
+        .iter()
+        .map(|w| (total as u64 * *w as u64 / sum) as usize)
+        .collect();
+    let mut by_remainder: Vec<usize> = (0..weights.len()).collect();
+    by_remainder.sort_by_key(|i| std::cmp::Reverse(total as u64 * weights[*i] as u64 % sum));
+    let short = total - counts.iter().sum::<usize>();
+    for i in by_remainder.into_iter().take(short) {
+        counts[i] += 1;
+    }
+    counts
+}
+
+/// Spread the classes through the sequence (smooth weighted round robin),
+/// so every level of the hierarchy gets a mix rather than one class each
+fn interleave(counts: &[usize]) -> Vec<usize> {
+    let total: usize = counts.iter().sum();
+    let mut current = vec![0i64; counts.len()];
+    let mut sequence = Vec::with_capacity(total);
+    for _ in 0..total {
+        for (c, count) in current.iter_mut().zip(counts) {
+            *c += *count as i64;
+        }
+        let Some(pick) = (0..counts.len()).max_by_key(|i| (current[*i], std::cmp::Reverse(*i)))
+        else {
+            break;
+        };
+        current[pick] -= total as i64;
+        sequence.push(pick);
+    }
+    sequence
+}
+
+/// Entity `i` hangs under entity `(i - 1) / fanout`, which makes a tree
+/// `fanout` wide
+fn parent_index(i: usize, fanout: usize) -> Option<usize> {
+    (i > 0 && fanout > 0).then(|| (i - 1) / fanout)
+}
+
+fn hierarchy_depth(entities: usize, fanout: usize) -> usize {
+    if entities == 0 {
+        return 0;
+    }
+    let mut depth = 1;
+    let mut i = entities - 1;
+    while let Some(parent) = parent_index(i, fanout) {
+        depth += 1;
+        i = parent;
+    }
+    depth
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_class_split_and_interleave() {
+        assert_eq!(split_by_weight(10, &[1, 1, 1]), vec![4, 3, 3]);
+        assert_eq!(split_by_weight(1000, &[1, 4, 5]), vec![100, 400, 500]);
+        assert_eq!(split_by_weight(7, &[0, 3]), vec![0, 7]);
+        assert_eq!(split_by_weight(5, &[0, 0]), vec![0, 0]);
+
+        let sequence = interleave(&[2, 4]);
+        assert_eq!(sequence.len(), 6);
+        assert_eq!(sequence.iter().filter(|c| **c == 0).count(), 2);
+        // The heavier class never runs more than two in a row
+        assert_eq!(sequence, vec![1, 0, 1, 1, 0, 1]);
+    }
+
+    #[test]
+    fn test_hierarchy_shape() {
+        assert_eq!(parent_index(0, 10), None);
+        assert_eq!(parent_index(10, 10), Some(0));
+        assert_eq!(parent_index(11, 10), Some(1));
+        assert_eq!(parent_index(5, 0), None);
+        assert_eq!(hierarchy_depth(0, 10), 0);
+        assert_eq!(hierarchy_depth(1, 10), 1);
+        assert_eq!(hierarchy_depth(11, 10), 2);
+        assert_eq!(hierarchy_depth(111, 10), 3);
+        assert_eq!(hierarchy_depth(500, 0), 1);
+    }
+}
@@ -1,6 +1,10 @@
+pub mod clock;
+pub mod fixtures;
+pub mod generator;
 pub mod models;
 pub mod routes;
 pub mod service;
+pub mod snapshots;
 
 pub use models::{TestModeSession, TestModeStatus};
 pub use routes::create_test_mode_routes;
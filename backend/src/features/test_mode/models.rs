@@ -1,6 +1,10 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use uuid::Uuid;
+use validator::Validate;
+
+use crate::utils::validation::not_blank;
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct TestModeSession {
@@ -16,6 +20,8 @@ pub struct TestModeSession {
     pub user_agent: Option<String>,
     pub entities_marked: i32,
     pub created_at: DateTime<Utc>,
+    /// Time the user's temporal permission checks see instead of the real one
+    pub clock_override: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,10 +31,11 @@ pub struct TestModeStatus {
     pub minutes_remaining: Option<f64>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Validate)]
 pub struct ActivateTestModeRequest {
     pub test_suite: Option<String>,
     pub test_run_id: Option<String>,
+    #[validate(custom = "not_blank")]
     pub justification: String,
     pub duration_minutes: Option<i32>,
 }
@@ -45,3 +52,209 @@ pub struct DeactivateTestModeResponse {
     pub entities_marked: i32,
     pub duration_minutes: f64,
 }
+
+// ============================================================================
+// FIXTURES
+// ============================================================================
+
+/// A declarative description of test data. Items refer to each other by
+/// `key`. Classes not defined in the fixture are looked up by name among the
+/// existing ones; roles, users and entities must all be defined in it.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Fixture {
+    pub name: Option<String>,
+    pub classes: Vec<FixtureClass>,
+    pub roles: Vec<FixtureRole>,
+    pub users: Vec<FixtureUser>,
+    pub entities: Vec<FixtureEntity>,
+    pub relationships: Vec<FixtureRelationship>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FixtureClass {
+    /// Defaults to the name
+    pub key: Option<String>,
+    pub name: String,
+    pub description: Option<String>,
+    pub parent: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FixtureRole {
+    pub key: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub level: i32,
+    #[serde(default)]
+    pub permissions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FixtureUser {
+    /// Defaults to the username
+    pub key: Option<String>,
+    pub username: String,
+    /// Defaults to `<username>@example.test`
+    pub email: Option<String>,
+    pub password: String,
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FixtureEntity {
+    /// Defaults to the display name
+    pub key: Option<String>,
+    pub class: String,
+    pub display_name: String,
+    pub parent: Option<String>,
+    #[serde(default)]
+    pub attributes: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FixtureRelationship {
+    pub source: String,
+    pub target: String,
+    /// Relationship type name
+    #[serde(rename = "type")]
+    pub relationship_type: String,
+    pub metadata: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct TestFixture {
+    pub id: Uuid,
+    pub session_id: Uuid,
+    pub name: String,
+    pub loaded_by: Option<Uuid>,
+    pub class_ids: Vec<Uuid>,
+    pub entity_ids: Vec<Uuid>,
+    pub relationship_ids: Vec<Uuid>,
+    pub loaded_at: DateTime<Utc>,
+    pub removed_at: Option<DateTime<Utc>>,
+}
+
+/// A loaded fixture and the ids its keys resolved to
+#[derive(Debug, Clone, Serialize)]
+pub struct LoadedFixture {
+    pub fixture: TestFixture,
+    pub classes: BTreeMap<String, Uuid>,
+    pub roles: BTreeMap<String, Uuid>,
+    pub users: BTreeMap<String, Uuid>,
+    pub entities: BTreeMap<String, Uuid>,
+}
+
+// ============================================================================
+// SNAPSHOTS
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct TestSnapshot {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub created_by: Option<Uuid>,
+    pub table_count: i32,
+    pub row_count: i64,
+    pub created_at: DateTime<Utc>,
+    pub restored_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreateSnapshotInput {
+    #[validate(custom = "not_blank", length(max = 255))]
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SnapshotQuery {
+    /// Only snapshots carrying this tag
+    pub tag: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoredSnapshot {
+    pub snapshot: TestSnapshot,
+    pub tables_restored: usize,
+    pub rows_restored: i64,
+    /// Tables in the snapshot that no longer exist
+    pub skipped_tables: Vec<String>,
+}
+
+// ============================================================================
+// SYNTHETIC DATA
+// ============================================================================
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+#[serde(default)]
+pub struct GenerateDataInput {
+    /// Recorded as the fixture name; defaults to a timestamped one
+    pub name: Option<String>,
+    pub entities: usize,
+    pub users: usize,
+    pub relationships: usize,
+    /// Class name to weight; entities are split between the classes in
+    /// proportion to their weights
+    pub class_distribution: BTreeMap<String, u32>,
+    /// Relationship type names the generated relationships are drawn from
+    pub relationship_types: Vec<String>,
+    /// Children per parent in the generated hierarchy; 0 keeps it flat
+    pub fanout: usize,
+    /// Role created for the run that every generated user holds, scoped to
+    /// one of the generated entities. Existing roles are never handed out.
+    pub role: Option<String>,
+    /// Permissions of the generated role
+    pub role_permissions: Vec<String>,
+    /// Lets generated users log in; without it they get an unusable one
+    pub user_password: Option<String>,
+    /// The same seed gives the same shape of data
+    pub seed: Option<u64>,
+}
+
+impl Default for GenerateDataInput {
+    fn default() -> Self {
+        Self {
+            name: None,
+            entities: 1000,
+            users: 100,
+            relationships: 2000,
+            class_distribution: BTreeMap::from([("Resource".to_string(), 1)]),
+            relationship_types: vec!["depends_on".to_string()],
+            fanout: 10,
+            role: Some("viewer".to_string()),
+            role_permissions: vec!["read".to_string()],
+            user_password: None,
+            seed: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GeneratedData {
+    /// Remove the data like any other fixture
+    pub fixture: TestFixture,
+    pub entities_by_class: BTreeMap<String, usize>,
+    pub users: usize,
+    pub relationships: usize,
+    /// Levels in the generated hierarchy
+    pub depth: usize,
+    pub elapsed_ms: u64,
+}
+
+// ============================================================================
+// MOCK CLOCK
+// ============================================================================
+
+/// Either a time to jump to or an offset from the current mock time (the
+/// real time when the clock isn't overridden yet)
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct SetClockInput {
+    pub at: Option<DateTime<Utc>>,
+    pub advance_seconds: Option<i64>,
+}
@@ -1,36 +1,91 @@
 use axum::{
-    extract::State,
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
     response::IntoResponse,
-    routing::{get, post},
+    routing::{delete, get, post, put},
     Extension, Json, Router,
 };
 
 use crate::features::auth::jwt::Claims;
+use crate::middleware::abac::{enforce_route_permissions, RoutePermission};
+use crate::utils::api_error::ApiError;
+use crate::utils::validation::ValidatedJson;
+use super::fixtures::{parse_fixture, FixtureFormat};
 use super::models::{
-    ActivateTestModeRequest, ActivateTestModeResponse, DeactivateTestModeResponse,
-    TestModeStatus,
+    ActivateTestModeRequest, ActivateTestModeResponse, CreateSnapshotInput,
+    DeactivateTestModeResponse, GenerateDataInput, GeneratedData, LoadedFixture,
+    RestoredSnapshot, SetClockInput, SnapshotQuery, TestFixture, TestModeSession, TestModeStatus,
+    TestSnapshot,
 };
 use super::service::{TestModeError, TestModeService};
 use uuid::Uuid;
 
+/// Permissions enforced by `enforce_route_permissions` for the routes below
+pub const ROUTE_PERMISSIONS: &[RoutePermission] = &[
+    RoutePermission::new("GET", "/active-sessions", "manage_system"),
+    RoutePermission::new("PUT", "/clock", "manage_system"),
+    RoutePermission::new("DELETE", "/clock", "manage_system"),
+    RoutePermission::new("GET", "/fixtures", "manage_system"),
+    RoutePermission::new("POST", "/fixtures", "manage_system"),
+    RoutePermission::new("DELETE", "/fixtures/:id", "manage_system"),
+    RoutePermission::new("POST", "/generate", "manage_system"),
+    RoutePermission::new("GET", "/snapshots", "manage_system"),
+    RoutePermission::new("POST", "/snapshots", "manage_system"),
+    RoutePermission::new("DELETE", "/snapshots/:name", "manage_system"),
+    RoutePermission::new("POST", "/snapshots/:name/restore", "manage_system"),
+];
+
 pub fn create_test_mode_routes() -> Router<TestModeService> {
     Router::new()
         .route("/activate", post(activate_handler))
         .route("/deactivate", post(deactivate_handler))
         .route("/status", get(status_handler))
         .route("/active-sessions", get(list_active_sessions_handler))
+        .route("/clock", put(set_clock_handler).delete(reset_clock_handler))
+        .route(
+            "/fixtures",
+            get(list_fixtures_handler).post(load_fixture_handler),
+        )
+        .route("/fixtures/:id", delete(remove_fixture_handler))
+        .route("/generate", post(generate_data_handler))
+        .route(
+            "/snapshots",
+            get(list_snapshots_handler).post(create_snapshot_handler),
+        )
+        .route("/snapshots/:name", delete(delete_snapshot_handler))
+        .route("/snapshots/:name/restore", post(restore_snapshot_handler))
+        .route_layer(axum::middleware::from_fn_with_state(
+            ROUTE_PERMISSIONS,
+            enforce_route_permissions,
+        ))
+}
+
+fn user_id(claims: &Claims) -> Result<Uuid, TestModeError> {
+    Uuid::parse_str(&claims.sub)
+        .map_err(|_| TestModeError::DatabaseError(sqlx::Error::RowNotFound))
 }
 
 impl IntoResponse for TestModeError {
     fn into_response(self) -> axum::response::Response {
-        let status = match self {
-            TestModeError::DatabaseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
-            TestModeError::AlreadyActive => StatusCode::CONFLICT,
-            TestModeError::NotActive => StatusCode::NOT_FOUND,
-            TestModeError::InvalidDuration(_) => StatusCode::BAD_REQUEST,
+        let (status, code) = match self {
+            TestModeError::DatabaseError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+            TestModeError::AlreadyActive => (StatusCode::CONFLICT, "test_mode.already_active"),
+            TestModeError::NotActive => (StatusCode::NOT_FOUND, "test_mode.not_active"),
+            TestModeError::InvalidDuration(_) => {
+                (StatusCode::BAD_REQUEST, "test_mode.invalid_duration")
+            }
+            TestModeError::InvalidFixture(_) => {
+                (StatusCode::BAD_REQUEST, "test_mode.invalid_fixture")
+            }
+            TestModeError::FixtureNotFound => (StatusCode::NOT_FOUND, "test_mode.fixture_not_found"),
+            TestModeError::Forbidden(_) => (StatusCode::FORBIDDEN, "test_mode.forbidden"),
+            TestModeError::InvalidInput(_) => (StatusCode::BAD_REQUEST, "test_mode.invalid_input"),
+            TestModeError::SnapshotNotFound => {
+                (StatusCode::NOT_FOUND, "test_mode.snapshot_not_found")
+            }
+            TestModeError::SnapshotExists => (StatusCode::CONFLICT, "test_mode.snapshot_exists"),
         };
-        (status, self.to_string()).into_response()
+        ApiError::new(status, code, self.to_string()).into_response()
     }
 }
 
@@ -39,7 +94,7 @@ impl IntoResponse for TestModeError {
 async fn activate_handler(
     State(service): State<TestModeService>,
     Extension(claims): Extension<Claims>,
-    Json(request): Json<ActivateTestModeRequest>,
+    ValidatedJson(request): ValidatedJson<ActivateTestModeRequest>,
 ) -> Result<Json<ActivateTestModeResponse>, TestModeError> {
     let user_id = Uuid::parse_str(&claims.sub)
         .map_err(|_| TestModeError::DatabaseError(sqlx::Error::RowNotFound))?;
@@ -109,14 +164,155 @@ async fn status_handler(
 #[axum::debug_handler]
 async fn list_active_sessions_handler(
     State(service): State<TestModeService>,
-    Extension(claims): Extension<Claims>,
 ) -> Result<Json<Vec<super::models::TestModeSession>>, TestModeError> {
-    // Check if user is superadmin
-    if !claims.roles.iter().any(|r| r.role_name == "superadmin") {
-        return Err(TestModeError::DatabaseError(sqlx::Error::RowNotFound));
-    }
-
     let sessions = service.list_active_sessions().await?;
 
     Ok(Json(sessions))
 }
+
+/// Override the time the current user's temporal permission checks see
+#[axum::debug_handler]
+async fn set_clock_handler(
+    State(service): State<TestModeService>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(input): ValidatedJson<SetClockInput>,
+) -> Result<Json<TestModeSession>, TestModeError> {
+    let user_id = Uuid::parse_str(&claims.sub)
+        .map_err(|_| TestModeError::DatabaseError(sqlx::Error::RowNotFound))?;
+
+    let session = service.set_clock(user_id, input).await?;
+
+    Ok(Json(session))
+}
+
+/// Return the current user's temporal permission checks to real time
+#[axum::debug_handler]
+async fn reset_clock_handler(
+    State(service): State<TestModeService>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<TestModeSession>, TestModeError> {
+    let user_id = Uuid::parse_str(&claims.sub)
+        .map_err(|_| TestModeError::DatabaseError(sqlx::Error::RowNotFound))?;
+
+    let session = service.reset_clock(user_id).await?;
+
+    Ok(Json(session))
+}
+
+/// Load a fixture (JSON, or YAML with a yaml content type) into the
+/// current user's test mode session
+#[axum::debug_handler]
+async fn load_fixture_handler(
+    State(service): State<TestModeService>,
+    Extension(claims): Extension<Claims>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<(StatusCode, Json<LoadedFixture>), TestModeError> {
+    let user_id = Uuid::parse_str(&claims.sub)
+        .map_err(|_| TestModeError::DatabaseError(sqlx::Error::RowNotFound))?;
+
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok());
+    let fixture = parse_fixture(&body, FixtureFormat::from_content_type(content_type))?;
+    let loaded = service.load_fixture(user_id, fixture).await?;
+
+    Ok((StatusCode::CREATED, Json(loaded)))
+}
+
+/// Fixtures the current user has loaded and not removed
+#[axum::debug_handler]
+async fn list_fixtures_handler(
+    State(service): State<TestModeService>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Vec<TestFixture>>, TestModeError> {
+    let user_id = Uuid::parse_str(&claims.sub)
+        .map_err(|_| TestModeError::DatabaseError(sqlx::Error::RowNotFound))?;
+
+    let fixtures = service.list_fixtures(user_id).await?;
+
+    Ok(Json(fixtures))
+}
+
+/// Delete everything a fixture created
+#[axum::debug_handler]
+async fn remove_fixture_handler(
+    State(service): State<TestModeService>,
+    Extension(claims): Extension<Claims>,
+    Path(fixture_id): Path<Uuid>,
+) -> Result<Json<TestFixture>, TestModeError> {
+    let user_id = Uuid::parse_str(&claims.sub)
+        .map_err(|_| TestModeError::DatabaseError(sqlx::Error::RowNotFound))?;
+
+    let fixture = service.remove_fixture(user_id, fixture_id).await?;
+
+    Ok(Json(fixture))
+}
+
+/// Generate synthetic data in the current user's test mode session
+#[axum::debug_handler]
+async fn generate_data_handler(
+    State(service): State<TestModeService>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(input): ValidatedJson<GenerateDataInput>,
+) -> Result<(StatusCode, Json<GeneratedData>), TestModeError> {
+    let user_id = Uuid::parse_str(&claims.sub)
+        .map_err(|_| TestModeError::DatabaseError(sqlx::Error::RowNotFound))?;
+
+    let generated = service.generate_data(user_id, input).await?;
+
+    Ok((StatusCode::CREATED, Json(generated)))
+}
+
+/// Snapshots, newest first (superadmin only)
+#[axum::debug_handler]
+async fn list_snapshots_handler(
+    State(service): State<TestModeService>,
+    Query(query): Query<SnapshotQuery>,
+) -> Result<Json<Vec<TestSnapshot>>, TestModeError> {
+    let snapshots = service.list_snapshots(query.tag.as_deref()).await?;
+
+    Ok(Json(snapshots))
+}
+
+/// Snapshot the current database state (superadmin only)
+#[axum::debug_handler]
+async fn create_snapshot_handler(
+    State(service): State<TestModeService>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(input): ValidatedJson<CreateSnapshotInput>,
+) -> Result<(StatusCode, Json<TestSnapshot>), TestModeError> {
+    let user_id = user_id(&claims)?;
+
+    let snapshot = service.create_snapshot(user_id, input).await?;
+
+    Ok((StatusCode::CREATED, Json(snapshot)))
+}
+
+/// Reset the database to a snapshot (superadmin only)
+#[axum::debug_handler]
+async fn restore_snapshot_handler(
+    State(service): State<TestModeService>,
+    Extension(claims): Extension<Claims>,
+    Path(name): Path<String>,
+) -> Result<Json<RestoredSnapshot>, TestModeError> {
+    let user_id = user_id(&claims)?;
+
+    let restored = service.restore_snapshot(user_id, &name).await?;
+
+    Ok(Json(restored))
+}
+
+/// Delete a snapshot (superadmin only)
+#[axum::debug_handler]
+async fn delete_snapshot_handler(
+    State(service): State<TestModeService>,
+    Extension(claims): Extension<Claims>,
+    Path(name): Path<String>,
+) -> Result<Json<TestSnapshot>, TestModeError> {
+    let user_id = user_id(&claims)?;
+
+    let snapshot = service.delete_snapshot(user_id, &name).await?;
+
+    Ok(Json(snapshot))
+}
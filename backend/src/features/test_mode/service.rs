@@ -1,4 +1,5 @@
 use super::models::{TestModeSession, TestModeStatus};
+use crate::config::TestModeConfig;
 use crate::features::system::AuditService;
 use chrono::{Duration, Utc};
 use sqlx::PgPool;
@@ -18,17 +19,40 @@ pub enum TestModeError {
 
     #[error("Invalid duration: {0}")]
     InvalidDuration(String),
+
+    #[error("Invalid fixture: {0}")]
+    InvalidFixture(String),
+
+    #[error("Fixture not found")]
+    FixtureNotFound,
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    #[error("Snapshot not found")]
+    SnapshotNotFound,
+
+    #[error("A snapshot with that name already exists")]
+    SnapshotExists,
 }
 
 #[derive(Clone)]
 pub struct TestModeService {
-    pool: PgPool,
-    audit_service: AuditService,
+    pub(super) pool: PgPool,
+    pub(super) audit_service: AuditService,
+    pub(super) config: TestModeConfig,
 }
 
 impl TestModeService {
-    pub fn new(pool: PgPool, audit_service: AuditService) -> Self {
-        Self { pool, audit_service }
+    pub fn new(pool: PgPool, audit_service: AuditService, config: TestModeConfig) -> Self {
+        Self {
+            pool,
+            audit_service,
+            config,
+        }
     }
 
     /// Activate test mode for a user
@@ -59,7 +83,7 @@ impl TestModeService {
             r#"
             INSERT INTO test_mode_sessions 
                 (user_id, test_suite, test_run_id, justification, activated_at, expires_at, ip_address, user_agent)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            VALUES ($1, $2, $3, $4, $5, $6, $7::inet, $8)
             RETURNING *
             "#
         )
@@ -74,7 +98,23 @@ impl TestModeService {
         .fetch_one(&self.pool)
         .await?;
 
-        // Audit log is handled by trigger
+        let _ = self
+            .audit_service
+            .log(
+                user_id,
+                "test_mode.activated",
+                "test_mode_session",
+                None,
+                None,
+                Some(serde_json::json!({
+                    "session_id": session.id,
+                    "test_suite": session.test_suite,
+                    "duration_minutes": duration,
+                    "justification": session.justification,
+                })),
+                None,
+            )
+            .await;
 
         Ok(session)
     }
@@ -96,7 +136,21 @@ impl TestModeService {
         .await?
         .ok_or(TestModeError::NotActive)?;
 
-        // Audit log is handled by trigger
+        let _ = self
+            .audit_service
+            .log(
+                user_id,
+                "test_mode.ended",
+                "test_mode_session",
+                None,
+                None,
+                Some(serde_json::json!({
+                    "session_id": session.id,
+                    "entities_marked": session.entities_marked,
+                })),
+                None,
+            )
+            .await;
 
         Ok(session)
     }
@@ -220,12 +274,15 @@ impl TestModeService {
         session_id: Uuid,
         test_suite: &str,
     ) -> Result<(), TestModeError> {
-        sqlx::query(&format!(
-            "SET LOCAL app.test_mode_user_id = '{}'; \
-             SET LOCAL app.test_mode_session_id = '{}'; \
-             SET LOCAL app.test_suite = '{}'",
-            user_id, session_id, test_suite
-        ))
+        // set_config(.., true) is SET LOCAL that takes bind parameters
+        sqlx::query(
+            "SELECT set_config('app.test_mode_user_id', $1, true), \
+                    set_config('app.test_mode_session_id', $2, true), \
+                    set_config('app.test_suite', $3, true)",
+        )
+        .bind(user_id.to_string())
+        .bind(session_id.to_string())
+        .bind(test_suite)
         .execute(&mut **transaction)
         .await?;
 
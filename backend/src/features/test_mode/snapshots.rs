@@ -0,0 +1,327 @@
+use super::models::{CreateSnapshotInput, RestoredSnapshot, TestSnapshot};
+use super::service::{TestModeError, TestModeService};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+/// Tables that survive restores: the migration history and the snapshots
+const EXCLUDED_TABLES: &[&str] = &["_sqlx_migrations", "test_snapshots", "test_snapshot_tables"];
+
+impl TestModeService {
+    // ========================================================================
+    // SNAPSHOTS
+    // ========================================================================
+
+    fn ensure_snapshots_enabled(&self) -> Result<(), TestModeError> {
+        if !self.config.snapshots_enabled {
+            return Err(TestModeError::Forbidden(
+                "Snapshots are disabled in this environment".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Copy every table into a named snapshot, read from a single consistent
+    /// view of the database
+    pub async fn create_snapshot(
+        &self,
+        user_id: Uuid,
+        input: CreateSnapshotInput,
+    ) -> Result<TestSnapshot, TestModeError> {
+        self.ensure_snapshots_enabled()?;
+        let name = input.name.trim();
+        if name.is_empty() {
+            return Err(TestModeError::InvalidInput(
+                "Snapshot name is required".to_string(),
+            ));
+        }
+
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ")
+            .execute(&mut *tx)
+            .await?;
+
+        let snapshot_id: Uuid = sqlx::query_scalar(
+            r#"
+            INSERT INTO test_snapshots (name, description, tags, created_by)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (name) DO NOTHING
+            RETURNING id
+            "#,
+        )
+        .bind(name)
+        .bind(&input.description)
+        .bind(&input.tags)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(TestModeError::SnapshotExists)?;
+
+        for table in snapshot_tables(&mut tx).await? {
+            sqlx::query(&format!(
+                r#"
+                INSERT INTO test_snapshot_tables (snapshot_id, table_name, rows, row_count)
+                SELECT $1, $2, COALESCE(jsonb_agg(t), '[]'::jsonb), COUNT(*) FROM {} t
+                "#,
+                quote_ident(&table)
+            ))
+            .bind(snapshot_id)
+            .bind(&table)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let snapshot = sqlx::query_as::<_, TestSnapshot>(
+            r#"
+            UPDATE test_snapshots SET
+                table_count = (SELECT COUNT(*) FROM test_snapshot_tables WHERE snapshot_id = $1),
+                row_count = (SELECT COALESCE(SUM(row_count), 0) FROM test_snapshot_tables WHERE snapshot_id = $1)
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(snapshot_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        let _ = self
+            .audit_service
+            .log(
+                user_id,
+                "test_mode.snapshot_created",
+                "test_snapshot",
+                None,
+                None,
+                Some(serde_json::json!({
+                    "snapshot_id": snapshot.id,
+                    "name": snapshot.name,
+                    "tags": snapshot.tags,
+                    "row_count": snapshot.row_count,
+                })),
+                None,
+            )
+            .await;
+
+        Ok(snapshot)
+    }
+
+    /// Newest first, optionally only those with a tag
+    pub async fn list_snapshots(
+        &self,
+        tag: Option<&str>,
+    ) -> Result<Vec<TestSnapshot>, TestModeError> {
+        self.ensure_snapshots_enabled()?;
+        let snapshots = sqlx::query_as::<_, TestSnapshot>(
+            r#"
+            SELECT * FROM test_snapshots
+            WHERE $1::text IS NULL OR $1 = ANY(tags)
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(tag)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(snapshots)
+    }
+
+    /// Replace the contents of every table with the snapshot's. Tables
+    /// created since the snapshot are left empty. Needs a database role that
+    /// may set session_replication_role, which skips foreign key checks and
+    /// triggers while the rows go back in.
+    pub async fn restore_snapshot(
+        &self,
+        user_id: Uuid,
+        name: &str,
+    ) -> Result<RestoredSnapshot, TestModeError> {
+        self.ensure_snapshots_enabled()?;
+        let mut tx = self.pool.begin().await?;
+        let snapshot =
+            sqlx::query_as::<_, TestSnapshot>("SELECT * FROM test_snapshots WHERE name = $1")
+                .bind(name)
+                .fetch_optional(&mut *tx)
+                .await?
+                .ok_or(TestModeError::SnapshotNotFound)?;
+
+        sqlx::query("SET LOCAL session_replication_role = replica")
+            .execute(&mut *tx)
+            .await?;
+
+        let tables = snapshot_tables(&mut tx).await?;
+        if !tables.is_empty() {
+            let list = tables
+                .iter()
+                .map(|t| quote_ident(t))
+                .collect::<Vec<_>>()
+                .join(", ");
+            sqlx::query(&format!("TRUNCATE {}", list))
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        let saved: Vec<String> = sqlx::query_scalar(
+            "SELECT table_name FROM test_snapshot_tables WHERE snapshot_id = $1 ORDER BY table_name",
+        )
+        .bind(snapshot.id)
+        .fetch_all(&mut *tx)
+        .await?;
+        let mut skipped_tables = Vec::new();
+        let mut tables_restored = 0;
+        let mut rows_restored = 0;
+        for table in saved {
+            if !tables.contains(&table) {
+                skipped_tables.push(table);
+                continue;
+            }
+            // Columns added since the snapshot take their defaults
+            let table_ident = quote_ident(&table);
+            let rows = sqlx::query(&format!(
+                r#"
+                INSERT INTO {0}
+                SELECT * FROM jsonb_populate_recordset(
+                    NULL::{0},
+                    (SELECT rows FROM test_snapshot_tables WHERE snapshot_id = $1 AND table_name = $2)
+                )
+                "#,
+                table_ident
+            ))
+            .bind(snapshot.id)
+            .bind(&table)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            tables_restored += 1;
+            rows_restored += rows as i64;
+        }
+        reset_sequences(&mut tx).await?;
+
+        let snapshot = sqlx::query_as::<_, TestSnapshot>(
+            "UPDATE test_snapshots SET restored_at = NOW() WHERE id = $1 RETURNING *",
+        )
+        .bind(snapshot.id)
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        tracing::warn!(
+            "Database restored to snapshot '{}' by {} ({} rows)",
+            snapshot.name,
+            user_id,
+            rows_restored
+        );
+        // Best effort: the restoring user may not exist in the snapshot
+        let _ = self
+            .audit_service
+            .log(
+                user_id,
+                "test_mode.snapshot_restored",
+                "test_snapshot",
+                None,
+                None,
+                Some(serde_json::json!({
+                    "snapshot_id": snapshot.id,
+                    "name": snapshot.name,
+                    "rows_restored": rows_restored,
+                })),
+                None,
+            )
+            .await;
+
+        Ok(RestoredSnapshot {
+            snapshot,
+            tables_restored,
+            rows_restored,
+            skipped_tables,
+        })
+    }
+
+    pub async fn delete_snapshot(
+        &self,
+        user_id: Uuid,
+        name: &str,
+    ) -> Result<TestSnapshot, TestModeError> {
+        self.ensure_snapshots_enabled()?;
+        let snapshot = sqlx::query_as::<_, TestSnapshot>(
+            "DELETE FROM test_snapshots WHERE name = $1 RETURNING *",
+        )
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(TestModeError::SnapshotNotFound)?;
+
+        let _ = self
+            .audit_service
+            .log(
+                user_id,
+                "test_mode.snapshot_deleted",
+                "test_snapshot",
+                None,
+                None,
+                Some(serde_json::json!({
+                    "snapshot_id": snapshot.id,
+                    "name": snapshot.name,
+                })),
+                None,
+            )
+            .await;
+
+        Ok(snapshot)
+    }
+}
+
+/// Every table in the public schema that snapshots cover
+async fn snapshot_tables(conn: &mut PgConnection) -> Result<Vec<String>, TestModeError> {
+    let tables = sqlx::query_scalar(
+        r#"
+        SELECT tablename::text FROM pg_tables
+        WHERE schemaname = 'public' AND tablename <> ALL($1)
+        ORDER BY tablename
+        "#,
+    )
+    .bind(EXCLUDED_TABLES)
+    .fetch_all(&mut *conn)
+    .await?;
+    Ok(tables)
+}
+
+/// Move each column-owned sequence past the restored rows
+async fn reset_sequences(conn: &mut PgConnection) -> Result<(), TestModeError> {
+    let sequences: Vec<(String, String, String)> = sqlx::query_as(
+        r#"
+        SELECT s.relname::text, t.relname::text, a.attname::text
+        FROM pg_depend d
+        JOIN pg_class s ON s.oid = d.objid AND s.relkind = 'S'
+        JOIN pg_class t ON t.oid = d.refobjid
+        JOIN pg_namespace n ON n.oid = t.relnamespace AND n.nspname = 'public'
+        JOIN pg_attribute a ON a.attrelid = t.oid AND a.attnum = d.refobjsubid
+        WHERE d.deptype IN ('a', 'i')
+        "#,
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    for (sequence, table, column) in sequences {
+        sqlx::query(&format!(
+            "SELECT setval($1::regclass, COALESCE(MAX({0}), 1), MAX({0}) IS NOT NULL) FROM {1}",
+            quote_ident(&column),
+            quote_ident(&table)
+        ))
+        .bind(quote_ident(&sequence))
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_ident() {
+        assert_eq!(quote_ident("entities"), "\"entities\"");
+        assert_eq!(quote_ident("odd\"name"), "\"odd\"\"name\"");
+    }
+}
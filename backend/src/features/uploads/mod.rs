@@ -0,0 +1,5 @@
+pub mod models;
+pub mod routes;
+pub mod service;
+
+pub use service::UploadService;
@@ -0,0 +1,43 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::utils::validation::not_blank;
+
+/// What an upload is for; each consumer (attachments, avatars, bulk import,
+/// AI document extraction) uses the same upload flow and picks the file up by id
+pub const UPLOAD_PURPOSES: &[&str] = &["attachment", "avatar", "import", "extraction"];
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Upload {
+    pub id: Uuid,
+    pub owner_id: Uuid,
+    /// "attachment", "avatar", "import" or "extraction"
+    pub purpose: String,
+    pub filename: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    /// Offset to resume from while pending
+    pub received_bytes: i64,
+    pub sha256: Option<String>,
+    /// "pending" or "complete"
+    pub status: String,
+    #[serde(skip_serializing)]
+    pub storage_key: String,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// Start a resumable upload; chunks are then sent with PATCH
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateUploadSession {
+    pub purpose: String,
+    #[validate(custom = "not_blank", length(max = 255))]
+    pub filename: String,
+    pub content_type: Option<String>,
+    pub size_bytes: i64,
+    /// Hex SHA-256 the completed file must match
+    pub sha256: Option<String>,
+}
@@ -0,0 +1,175 @@
+use crate::features::auth::jwt::Claims;
+use crate::features::uploads::models::{CreateUploadSession, Upload};
+use crate::features::uploads::service::{UploadError, UploadService};
+use crate::utils::api_error::ApiError;
+use crate::utils::storage::{content_disposition, PresignedUrl, StorageError};
+use crate::utils::validation::ValidatedJson;
+use axum::{
+    body::Body,
+    extract::{Multipart, Path, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use uuid::Uuid;
+
+/// Byte offset a chunk starts at (request) or the upload has reached (response)
+pub const UPLOAD_OFFSET_HEADER: &str = "upload-offset";
+
+pub fn upload_routes() -> Router<UploadService> {
+    Router::new()
+        .route("/", post(upload_handler))
+        .route("/sessions", post(create_session_handler))
+        .route(
+            "/:id",
+            get(get_upload_handler)
+                .patch(append_chunk_handler)
+                .delete(delete_upload_handler),
+        )
+        .route("/:id/content", get(download_handler))
+        .route("/:id/download-url", get(download_url_handler))
+}
+
+fn owner_id(claims: &Claims) -> Result<Uuid, UploadError> {
+    Uuid::parse_str(&claims.sub).map_err(|_| UploadError::NotFound)
+}
+
+fn with_offset(upload: Upload, status: StatusCode) -> Response {
+    let offset = HeaderValue::from(upload.received_bytes);
+    (status, [(UPLOAD_OFFSET_HEADER, offset)], Json(upload)).into_response()
+}
+
+#[axum::debug_handler]
+async fn upload_handler(
+    State(service): State<UploadService>,
+    Extension(claims): Extension<Claims>,
+    multipart: Multipart,
+) -> Result<(StatusCode, Json<Upload>), UploadError> {
+    let upload = service
+        .store_multipart(owner_id(&claims)?, multipart)
+        .await?;
+    Ok((StatusCode::CREATED, Json(upload)))
+}
+
+#[axum::debug_handler]
+async fn create_session_handler(
+    State(service): State<UploadService>,
+    Extension(claims): Extension<Claims>,
+    ValidatedJson(input): ValidatedJson<CreateUploadSession>,
+) -> Result<Response, UploadError> {
+    let upload = service.create_session(owner_id(&claims)?, input).await?;
+    Ok(with_offset(upload, StatusCode::CREATED))
+}
+
+/// Upload status; clients resume from `received_bytes` (also in `Upload-Offset`)
+#[axum::debug_handler]
+async fn get_upload_handler(
+    State(service): State<UploadService>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<Response, UploadError> {
+    let upload = service.get(owner_id(&claims)?, id).await?;
+    Ok(with_offset(upload, StatusCode::OK))
+}
+
+/// Append the raw request body at the offset given in `Upload-Offset`
+#[axum::debug_handler]
+async fn append_chunk_handler(
+    State(service): State<UploadService>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Response, UploadError> {
+    let offset = headers
+        .get(UPLOAD_OFFSET_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<i64>().ok())
+        .filter(|offset| *offset >= 0)
+        .ok_or_else(|| UploadError::InvalidInput("Upload-Offset header is required".to_string()))?;
+
+    let upload = service
+        .append_chunk(owner_id(&claims)?, id, offset, body.into_data_stream())
+        .await?;
+    Ok(with_offset(upload, StatusCode::OK))
+}
+
+#[axum::debug_handler]
+async fn download_handler(
+    State(service): State<UploadService>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<Response, UploadError> {
+    let (upload, content) = service.open(owner_id(&claims)?, id).await?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, upload.content_type),
+            (header::CONTENT_LENGTH, upload.size_bytes.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                content_disposition(&upload.filename),
+            ),
+        ],
+        Body::from_stream(content),
+    )
+        .into_response())
+}
+
+/// Presigned link to the content, for handing to clients that can't send credentials
+#[axum::debug_handler]
+async fn download_url_handler(
+    State(service): State<UploadService>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<PresignedUrl>, UploadError> {
+    Ok(Json(service.download_url(owner_id(&claims)?, id).await?))
+}
+
+#[axum::debug_handler]
+async fn delete_upload_handler(
+    State(service): State<UploadService>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, UploadError> {
+    service.delete(owner_id(&claims)?, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+impl IntoResponse for UploadError {
+    fn into_response(self) -> Response {
+        let (status, code) = match &self {
+            UploadError::DatabaseError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+            UploadError::Storage(StorageError::TooLarge { .. }) => {
+                (StatusCode::PAYLOAD_TOO_LARGE, "uploads.too_large")
+            }
+            UploadError::Storage(StorageError::OffsetMismatch { .. }) => {
+                (StatusCode::CONFLICT, "uploads.offset_mismatch")
+            }
+            UploadError::Storage(StorageError::Stream(_)) => {
+                (StatusCode::BAD_REQUEST, "uploads.stream_failed")
+            }
+            UploadError::Storage(_) => (StatusCode::INTERNAL_SERVER_ERROR, "internal"),
+            UploadError::InvalidInput(_) => (StatusCode::BAD_REQUEST, "uploads.invalid_input"),
+            UploadError::ChecksumMismatch { .. } => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "uploads.checksum_mismatch",
+            ),
+            UploadError::Conflict(_) => (StatusCode::CONFLICT, "uploads.conflict"),
+            UploadError::NotFound => (StatusCode::NOT_FOUND, "uploads.not_found"),
+        };
+
+        let message = if status == StatusCode::INTERNAL_SERVER_ERROR {
+            tracing::error!("Upload failed: {}", self);
+            "Upload failed".to_string()
+        } else {
+            self.to_string()
+        };
+        let mut error = ApiError::new(status, code, message);
+        if let UploadError::Storage(StorageError::OffsetMismatch { expected, .. }) = &self {
+            error = error.with_detail("offset", expected);
+        }
+
+        error.into_response()
+    }
+}
@@ -0,0 +1,478 @@
+use super::models::{CreateUploadSession, Upload, UPLOAD_PURPOSES};
+use crate::config::UploadsConfig;
+use crate::utils::shutdown::Shutdown;
+use crate::utils::storage::{
+    ByteStream, Download, LocalStorage, ObjectStorage, PresignedUrl, StorageError,
+};
+use axum::body::Bytes;
+use axum::extract::Multipart;
+use futures::{Stream, StreamExt, TryStreamExt};
+use sqlx::PgPool;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+use tokio_util::io::ReaderStream;
+use uuid::Uuid;
+
+/// How often expired resumable uploads are swept
+const CLEANUP_INTERVAL_SECS: u64 = 3600;
+
+const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+const UPLOAD_COLUMNS: &str = "id, owner_id, purpose, filename, content_type, size_bytes, received_bytes, sha256, status, storage_key, created_at, completed_at";
+
+#[derive(Error, Debug)]
+pub enum UploadError {
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+
+    #[error("{0}")]
+    Storage(#[from] StorageError),
+
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    #[error("Checksum mismatch: expected {expected}, got {actual}")]
+    ChecksumMismatch { expected: String, actual: String },
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Upload not found")]
+    NotFound,
+}
+
+#[derive(Clone)]
+pub struct UploadService {
+    pool: PgPool,
+    /// Where resumable uploads are assembled
+    staging: LocalStorage,
+    /// Where complete files are kept
+    storage: Arc<dyn ObjectStorage>,
+    config: UploadsConfig,
+    /// Sessions with a chunk being written, so concurrent PATCHes can't interleave
+    in_flight: Arc<Mutex<HashSet<Uuid>>>,
+}
+
+/// Marks a session busy until dropped
+struct InFlight<'a> {
+    set: &'a Mutex<HashSet<Uuid>>,
+    id: Uuid,
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.set
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.id);
+    }
+}
+
+fn validate_purpose(purpose: &str) -> Result<(), UploadError> {
+    if UPLOAD_PURPOSES.contains(&purpose) {
+        Ok(())
+    } else {
+        Err(UploadError::InvalidInput(format!(
+            "purpose must be one of: {}",
+            UPLOAD_PURPOSES.join(", ")
+        )))
+    }
+}
+
+fn normalize_sha256(value: &str) -> Result<String, UploadError> {
+    let value = value.trim().to_ascii_lowercase();
+    if value.len() == 64 && value.chars().all(|c| c.is_ascii_hexdigit()) {
+        Ok(value)
+    } else {
+        Err(UploadError::InvalidInput(
+            "sha256 must be 64 hex characters".to_string(),
+        ))
+    }
+}
+
+fn storage_key(id: Uuid) -> String {
+    format!("uploads/{}", id)
+}
+
+fn staging_key(id: Uuid) -> String {
+    format!("resumable/{}", id)
+}
+
+impl UploadService {
+    /// Complete files are kept next to the staging area until
+    /// [`UploadService::with_storage`] points them elsewhere
+    pub fn new(pool: PgPool, config: UploadsConfig) -> Self {
+        let staging = LocalStorage::new(&config.storage_dir);
+        Self {
+            pool,
+            storage: Arc::new(staging.clone()),
+            staging,
+            config,
+            in_flight: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    pub fn with_storage(mut self, storage: Arc<dyn ObjectStorage>) -> Self {
+        self.storage = storage;
+        self
+    }
+
+    pub fn storage(&self) -> &dyn ObjectStorage {
+        self.storage.as_ref()
+    }
+
+    /// Store a `multipart/form-data` upload in one request.
+    ///
+    /// Expects a `purpose` field and optionally a `sha256` field, both before the
+    /// `file` field, which is streamed straight to storage and checked against
+    /// `sha256` if given.
+    pub async fn store_multipart(
+        &self,
+        owner_id: Uuid,
+        mut multipart: Multipart,
+    ) -> Result<Upload, UploadError> {
+        let mut purpose = None;
+        let mut expected_sha256 = None;
+
+        while let Some(field) = multipart
+            .next_field()
+            .await
+            .map_err(|e| UploadError::InvalidInput(e.body_text()))?
+        {
+            match field.name() {
+                Some("purpose") => {
+                    let value = field
+                        .text()
+                        .await
+                        .map_err(|e| UploadError::InvalidInput(e.body_text()))?;
+                    validate_purpose(&value)?;
+                    purpose = Some(value);
+                }
+                Some("sha256") => {
+                    let value = field
+                        .text()
+                        .await
+                        .map_err(|e| UploadError::InvalidInput(e.body_text()))?;
+                    expected_sha256 = Some(normalize_sha256(&value)?);
+                }
+                Some("file") => {
+                    let purpose = purpose.take().ok_or_else(|| {
+                        UploadError::InvalidInput(
+                            "purpose must be sent before the file".to_string(),
+                        )
+                    })?;
+                    let filename = field.file_name().unwrap_or("upload").to_string();
+                    let content_type = field
+                        .content_type()
+                        .unwrap_or(DEFAULT_CONTENT_TYPE)
+                        .to_string();
+
+                    let id = Uuid::new_v4();
+                    let key = storage_key(id);
+                    let body = field
+                        .map_err(|e| StorageError::Stream(e.body_text()))
+                        .boxed();
+                    let stored = self
+                        .storage
+                        .put(&key, body, self.config.max_file_bytes)
+                        .await?;
+                    if let Some(expected) = expected_sha256 {
+                        if expected != stored.sha256 {
+                            self.storage.delete(&key).await?;
+                            return Err(UploadError::ChecksumMismatch {
+                                expected,
+                                actual: stored.sha256,
+                            });
+                        }
+                    }
+
+                    let result = sqlx::query_as::<_, Upload>(&format!(
+                        r#"
+                        INSERT INTO uploads (id, owner_id, purpose, filename, content_type, size_bytes,
+                                             received_bytes, sha256, status, storage_key, completed_at)
+                        VALUES ($1, $2, $3, $4, $5, $6, $6, $7, 'complete', $8, NOW())
+                        RETURNING {}
+                        "#,
+                        UPLOAD_COLUMNS
+                    ))
+                    .bind(id)
+                    .bind(owner_id)
+                    .bind(&purpose)
+                    .bind(&filename)
+                    .bind(&content_type)
+                    .bind(stored.size as i64)
+                    .bind(&stored.sha256)
+                    .bind(&key)
+                    .fetch_one(&self.pool)
+                    .await;
+                    return match result {
+                        Ok(upload) => Ok(upload),
+                        Err(e) => {
+                            self.storage.delete(&key).await?;
+                            Err(e.into())
+                        }
+                    };
+                }
+                // Unknown fields are drained and ignored
+                _ => {}
+            }
+        }
+
+        Err(UploadError::InvalidInput("missing file field".to_string()))
+    }
+
+    /// Start a resumable upload of a known size
+    pub async fn create_session(
+        &self,
+        owner_id: Uuid,
+        input: CreateUploadSession,
+    ) -> Result<Upload, UploadError> {
+        validate_purpose(&input.purpose)?;
+        if input.filename.trim().is_empty() {
+            return Err(UploadError::InvalidInput(
+                "filename is required".to_string(),
+            ));
+        }
+        if input.size_bytes <= 0 || input.size_bytes as u64 > self.config.max_file_bytes {
+            return Err(UploadError::InvalidInput(format!(
+                "size_bytes must be between 1 and {}",
+                self.config.max_file_bytes
+            )));
+        }
+        let sha256 = input.sha256.as_deref().map(normalize_sha256).transpose()?;
+
+        let id = Uuid::new_v4();
+        let upload = sqlx::query_as::<_, Upload>(&format!(
+            r#"
+            INSERT INTO uploads (id, owner_id, purpose, filename, content_type, size_bytes, sha256, storage_key)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING {}
+            "#,
+            UPLOAD_COLUMNS
+        ))
+        .bind(id)
+        .bind(owner_id)
+        .bind(&input.purpose)
+        .bind(input.filename.trim())
+        .bind(input.content_type.as_deref().unwrap_or(DEFAULT_CONTENT_TYPE))
+        .bind(input.size_bytes)
+        .bind(sha256)
+        .bind(storage_key(id))
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(upload)
+    }
+
+    pub async fn get(&self, owner_id: Uuid, id: Uuid) -> Result<Upload, UploadError> {
+        sqlx::query_as::<_, Upload>(&format!(
+            "SELECT {} FROM uploads WHERE id = $1 AND owner_id = $2",
+            UPLOAD_COLUMNS
+        ))
+        .bind(id)
+        .bind(owner_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(UploadError::NotFound)
+    }
+
+    /// Append the chunk starting at `offset` to a pending upload. Once the last
+    /// byte arrives the file is hashed; on a checksum mismatch the received data
+    /// is discarded so the client can start over from offset 0.
+    pub async fn append_chunk<S, E>(
+        &self,
+        owner_id: Uuid,
+        id: Uuid,
+        offset: i64,
+        chunk: S,
+    ) -> Result<Upload, UploadError>
+    where
+        S: Stream<Item = Result<Bytes, E>>,
+        E: std::fmt::Display,
+    {
+        let _guard = {
+            let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+            if !in_flight.insert(id) {
+                return Err(UploadError::Conflict(
+                    "another chunk for this upload is in progress".to_string(),
+                ));
+            }
+            InFlight {
+                set: &self.in_flight,
+                id,
+            }
+        };
+
+        let upload = self.get(owner_id, id).await?;
+        if upload.status != "pending" {
+            return Err(UploadError::Conflict(
+                "upload is already complete".to_string(),
+            ));
+        }
+        if offset != upload.received_bytes {
+            return Err(StorageError::OffsetMismatch {
+                expected: upload.received_bytes as u64,
+                actual: offset.max(0) as u64,
+            }
+            .into());
+        }
+
+        let staged = staging_key(id);
+        let remaining = (upload.size_bytes - upload.received_bytes) as u64;
+        let received = self
+            .staging
+            .append(
+                &staged,
+                offset as u64,
+                chunk,
+                remaining.min(self.config.max_chunk_bytes),
+            )
+            .await?;
+
+        // Recorded before moving the file, so a failed move can be retried
+        // with an empty chunk at the final offset
+        let upload = sqlx::query_as::<_, Upload>(&format!(
+            "UPDATE uploads SET received_bytes = $2 WHERE id = $1 RETURNING {}",
+            UPLOAD_COLUMNS
+        ))
+        .bind(id)
+        .bind(received as i64)
+        .fetch_one(&self.pool)
+        .await?;
+        if received < upload.size_bytes as u64 {
+            return Ok(upload);
+        }
+
+        let actual = self.staging.sha256(&staged).await?;
+        if let Some(expected) = upload.sha256.clone() {
+            if expected != actual {
+                self.staging.delete(&staged).await?;
+                sqlx::query("UPDATE uploads SET received_bytes = 0 WHERE id = $1")
+                    .bind(id)
+                    .execute(&self.pool)
+                    .await?;
+                return Err(UploadError::ChecksumMismatch { expected, actual });
+            }
+        }
+
+        let file = self.staging.open(&staged).await?;
+        self.storage
+            .put(
+                &upload.storage_key,
+                ReaderStream::new(file).map_err(StorageError::from).boxed(),
+                upload.size_bytes as u64,
+            )
+            .await?;
+        self.staging.delete(&staged).await?;
+
+        let upload = sqlx::query_as::<_, Upload>(&format!(
+            r#"
+            UPDATE uploads
+            SET received_bytes = size_bytes, sha256 = $2, status = 'complete', completed_at = NOW()
+            WHERE id = $1
+            RETURNING {}
+            "#,
+            UPLOAD_COLUMNS
+        ))
+        .bind(id)
+        .bind(actual)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(upload)
+    }
+
+    async fn get_complete(&self, owner_id: Uuid, id: Uuid) -> Result<Upload, UploadError> {
+        let upload = self.get(owner_id, id).await?;
+        if upload.status != "complete" {
+            return Err(UploadError::Conflict("upload is not complete".to_string()));
+        }
+        Ok(upload)
+    }
+
+    /// Open a completed upload for reading
+    pub async fn open(
+        &self,
+        owner_id: Uuid,
+        id: Uuid,
+    ) -> Result<(Upload, ByteStream<'static>), UploadError> {
+        let upload = self.get_complete(owner_id, id).await?;
+        let content = self.storage.get(&upload.storage_key).await?;
+        Ok((upload, content))
+    }
+
+    /// A link to a completed upload that works without signing in, until it expires
+    pub async fn download_url(
+        &self,
+        owner_id: Uuid,
+        id: Uuid,
+    ) -> Result<PresignedUrl, UploadError> {
+        let upload = self.get_complete(owner_id, id).await?;
+        Ok(self
+            .storage
+            .presigned_url(
+                &upload.storage_key,
+                Download {
+                    filename: &upload.filename,
+                    content_type: &upload.content_type,
+                },
+            )
+            .await?)
+    }
+
+    pub async fn delete(&self, owner_id: Uuid, id: Uuid) -> Result<(), UploadError> {
+        let (status, key) = sqlx::query_as::<_, (String, String)>(
+            "DELETE FROM uploads WHERE id = $1 AND owner_id = $2 RETURNING status, storage_key",
+        )
+        .bind(id)
+        .bind(owner_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(UploadError::NotFound)?;
+        if status == "complete" {
+            self.storage.delete(&key).await?;
+        } else {
+            self.staging.delete(&staging_key(id)).await?;
+        }
+        Ok(())
+    }
+
+    /// Discard resumable uploads that were not finished within the TTL
+    pub async fn cleanup_expired(&self) -> Result<u64, UploadError> {
+        let ids = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            DELETE FROM uploads
+            WHERE status = 'pending' AND created_at < NOW() - make_interval(hours => $1)
+            RETURNING id
+            "#,
+        )
+        .bind(self.config.session_ttl_hours as i32)
+        .fetch_all(&self.pool)
+        .await?;
+
+        for id in &ids {
+            if let Err(e) = self.staging.delete(&staging_key(*id)).await {
+                tracing::warn!("Failed to delete expired upload {}: {}", id, e);
+            }
+        }
+        Ok(ids.len() as u64)
+    }
+
+    pub async fn start_cleanup_task(self, shutdown: Shutdown) {
+        shutdown.clone().spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(CLEANUP_INTERVAL_SECS));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = shutdown.cancelled() => break,
+                }
+                match self.cleanup_expired().await {
+                    Ok(0) => {}
+                    Ok(n) => tracing::info!("Discarded {} expired uploads", n),
+                    Err(e) => tracing::error!("Failed to clean up expired uploads: {}", e),
+                }
+            }
+        });
+    }
+}
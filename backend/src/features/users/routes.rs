@@ -2,12 +2,14 @@ use crate::features::auth::jwt::Claims;
 use crate::features::auth::models::User;
 use crate::features::auth::service::AuthError;
 use crate::features::users::service::UserService;
+use crate::utils::validation::ValidatedJson;
 use axum::{
     extract::{Json, Path, State},
     routing::{delete, get, post, put},
     Extension, Router,
 };
 use serde::Deserialize;
+use validator::Validate;
 
 pub fn users_routes() -> Router<UserService> {
     Router::new()
@@ -18,16 +20,21 @@ pub fn users_routes() -> Router<UserService> {
         .route("/:id", delete(delete_user))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Validate)]
 pub struct CreateUserRequest {
+    #[validate(length(min = 3, max = 50))]
     pub username: String,
+    #[validate(email)]
     pub email: String,
+    #[validate(length(min = 8, max = 128))]
     pub password: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Validate)]
 pub struct UpdateUserRequest {
+    #[validate(length(min = 3, max = 50))]
     pub username: Option<String>,
+    #[validate(email)]
     pub email: Option<String>,
 }
 
@@ -47,7 +54,7 @@ async fn get_user(
 async fn create_user(
     State(service): State<UserService>,
     Extension(claims): Extension<Claims>,
-    Json(req): Json<CreateUserRequest>,
+    ValidatedJson(req): ValidatedJson<CreateUserRequest>,
 ) -> Result<Json<User>, AuthError> {
     let performing_user_id = uuid::Uuid::parse_str(&claims.sub).ok();
     let user = service
@@ -60,7 +67,7 @@ async fn update_user(
     State(service): State<UserService>,
     Extension(claims): Extension<Claims>,
     Path(id): Path<String>,
-    Json(req): Json<UpdateUserRequest>,
+    ValidatedJson(req): ValidatedJson<UpdateUserRequest>,
 ) -> Result<Json<User>, AuthError> {
     let performing_user_id = uuid::Uuid::parse_str(&claims.sub).ok();
     let user = service
@@ -1,6 +1,8 @@
+pub mod cli;
 pub mod config;
 pub mod features;
 pub mod middleware;
+pub mod perf;
 pub mod utils;
 
 pub use features::auth::service::AuthService as AppState;
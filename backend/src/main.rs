@@ -3,7 +3,7 @@ use sqlx::postgres::PgPoolOptions;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use template_repo_backend::{config, features, middleware, utils};
-use tower_http::cors::CorsLayer;
+use tower_http::compression::CompressionLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 
@@ -85,31 +85,91 @@ async fn main() {
         .await
         .expect("Failed to connect to database (connection timed out or refused)");
 
-    // Run migrations
-    sqlx::migrate!()
-        .run(&pool)
-        .await
-        .expect("Failed to run migrations");
+    // Run migrations, then log how the schema still differs from this build
+    // (also in /system/info) before a failed migration or, unless
+    // schema.fail_on_drift is off, any remaining drift stops startup
+    let migrated = features::system::schema::MIGRATOR.run(&pool).await;
+    let drift = match features::system::schema::check_schema(&pool).await {
+        Ok(report) => {
+            for problem in report.problems() {
+                tracing::error!("Database schema drift: {}", problem);
+            }
+            report.has_drift()
+        }
+        Err(e) => {
+            tracing::error!("Failed to check the database schema: {}", e);
+            true
+        }
+    };
+    migrated.expect("Failed to run migrations");
+    if drift && config.schema.fail_on_drift {
+        panic!(
+            "Database schema does not match this build (set schema.fail_on_drift = false to start anyway)"
+        );
+    }
 
     // Manual table creation removed in favor of sqlx migrations
 
     let config_arc = Arc::new(config.clone());
 
+    // Background jobs register here so shutdown can wait for them
+    let shutdown = utils::shutdown::Shutdown::new();
+
+    let cors_layer =
+        middleware::cors::cors_layer(&config.cors).expect("Invalid CORS configuration");
+    let body_limits = middleware::body_limit::BodyLimits::from_config(&config.body_limits);
+    let route_limits = middleware::route_limits::RouteLimits::from_config(&config.route_limits);
+    let api_versioning = middleware::api_version::ApiVersioning::from_config(&config.api)
+        .expect("Invalid API configuration");
+    let security_headers =
+        middleware::security_headers::SecurityHeaders::from_config(&config.security_headers)
+            .expect("Invalid security header configuration");
+
+    // System classes, relationship types and sidebar/dashboard counts, shared
+    // through Redis when configured and invalidated on ontology changes
+    let cache =
+        utils::cache::SharedCache::from_config(&config.cache).expect("Invalid cache configuration");
+
     // Create services (clonable for router state)
     let audit_service = features::system::AuditService::new(pool.clone());
-    let ontology_service =
+    let mut ontology_service =
         features::ontology::OntologyService::new(pool.clone(), audit_service.clone());
-    let rebac_service = features::rebac::RebacService::new(
+    if let Some(cache) = &cache {
+        ontology_service = ontology_service.with_cache(cache.clone());
+    }
+    ontology_service
+        .clone()
+        .start_duplicate_detection(config.duplicate_detection.clone(), shutdown.clone())
+        .await;
+    // Optional structured log of permission decisions, kept apart from the audit log
+    let decision_log = features::decision_log::DecisionLog::from_config(
+        &config.decision_log,
+        utils::http_client::OutboundClient::from_config(&config.outbound_http),
+    )
+    .expect("Invalid decision log configuration");
+    let mut rebac_service = features::rebac::RebacService::new(
         pool.clone(),
         ontology_service.clone(),
         audit_service.clone(),
-    );
+    )
+    .with_permissions_config(&config.permissions);
+    if let Some(decision_log) = decision_log {
+        decision_log.start(shutdown.clone());
+        rebac_service = rebac_service.with_decision_log(decision_log);
+    }
     let abac_service = features::abac::AbacService::new(
         pool.clone(),
         rebac_service.clone(),
         ontology_service.clone(),
     );
-    let navigation_service = features::navigation::NavigationService::new(abac_service.clone());
+    let mut navigation_service = features::navigation::NavigationService::new(
+        pool.clone(),
+        abac_service.clone(),
+        rebac_service.clone(),
+    );
+    if let Some(cache) = &cache {
+        navigation_service = navigation_service.with_cache(cache.clone());
+    }
     let user_service = features::users::service::UserService::new(
         pool.clone(),
         audit_service.clone(),
@@ -118,6 +178,55 @@ async fn main() {
     // MFA Service
     let mfa_service = features::auth::mfa::MfaService::new(pool.clone(), "OntologyManager".to_string());
 
+    // Outgoing email, queued in the database and sent by a worker that retries failures
+    let email_service = features::email::EmailService::new(
+        pool.clone(),
+        audit_service.clone(),
+        config.email.clone(),
+        utils::http_client::OutboundClient::from_config(&config.outbound_http),
+    )
+    .expect("Invalid email configuration");
+    email_service.clone().start_delivery_task(shutdown.clone()).await;
+
+    // User notifications, delivered per the users' channel preferences;
+    // low-priority emails are batched into daily or weekly digests
+    let mut notification_service = features::notifications::NotificationService::new(pool.clone())
+        .with_email(email_service.clone());
+    if let Some(web_push) = features::notifications::WebPush::from_config(
+        &config.web_push,
+        utils::http_client::OutboundClient::from_config(&config.outbound_http),
+    )
+    .expect("Invalid web push configuration")
+    {
+        notification_service = notification_service.with_push(web_push);
+    }
+    notification_service
+        .clone()
+        .start_digest_task(config.notifications.clone(), shutdown.clone())
+        .await;
+
+    // Break-glass access, revoked by the sweep once it runs out
+    let firefighter_service = features::firefighter::service::FirefighterService::new(
+        pool.clone(),
+        audit_service.clone(),
+        ontology_service.clone(),
+        config.firefighter.clone(),
+    )
+    .with_notifications(notification_service.clone());
+    firefighter_service.clone().start_expiry_task(shutdown.clone()).await;
+    let trusted_proxies = utils::ip::parse_cidrs(&config.ip_access.trusted_proxies)
+        .expect("Invalid ip_access.trusted_proxies configuration");
+
+    // Per-tenant country restrictions, enforced at login and on API calls
+    let geo_access_service = features::geo_access::GeoAccessService::new(
+        pool.clone(),
+        &config.geoip.database_path,
+        trusted_proxies.clone(),
+        firefighter_service.clone(),
+    )
+    .expect("Invalid geoip configuration");
+    geo_access_service.clone().start_refresh_task(shutdown.clone()).await;
+
     let auth_service = features::auth::service::AuthService::new(
         pool.clone(),
         config.clone(),
@@ -126,23 +235,28 @@ async fn main() {
         audit_service.clone(),
         ontology_service.clone(),
         mfa_service.clone(),
-    );
+    )
+    .with_geo_access(geo_access_service.clone())
+    .with_email(email_service.clone())
+    .with_notifications(notification_service.clone());
     let system_service =
         features::system::service::SystemService::new(pool.clone(), audit_service.clone());
-    let discovery_service =
-        features::discovery::service::DiscoveryService::new(ontology_service.clone());
-    let dashboard_service = features::dashboard::service::DashboardService::new(pool.clone());
+    let discovery_service = features::discovery::service::DiscoveryService::new(
+        ontology_service.clone(),
+        rebac_service.clone(),
+    );
+    let mut dashboard_service = features::dashboard::service::DashboardService::new(pool.clone());
+    if let Some(cache) = &cache {
+        dashboard_service = dashboard_service.with_cache(cache.clone());
+    }
     let rate_limit_service = Arc::new(features::rate_limit::RateLimitService::new(
         pool.clone(),
         false,
     ));
     let policy_service = features::rebac::PolicyService::new(pool.clone());
-    let api_management_service = features::api_management::ApiManagementService::new(pool.clone());
-    let firefighter_service = features::firefighter::service::FirefighterService::new(
-        pool.clone(),
-        audit_service.clone(),
-        ontology_service.clone(),
-    );
+    let api_management_service = features::api_management::ApiManagementService::new(pool.clone())
+        .with_notifications(notification_service.clone());
+    api_management_service.clone().start_expiry_notifier(shutdown.clone()).await;
     let project_service = features::projects::ProjectService::new(
         pool.clone(),
         ontology_service.clone(),
@@ -160,6 +274,7 @@ async fn main() {
     let test_mode_service = features::test_mode::TestModeService::new(
         pool.clone(),
         audit_service.clone(),
+        config.test_mode.clone(),
     );
 
     // AI Service - Default to docker host access if not set (Ollama as local native service)
@@ -167,16 +282,210 @@ async fn main() {
         std::env::var("AI_SERVICE_URL").unwrap_or_else(|_| "http://localhost:11434/v1".to_string());
     // Model name - Default to what we set in docker-compose
     let ai_model = std::env::var("AI_MODEL").unwrap_or_else(|_| "gpt-oss:20b".to_string());
-    let ai_service = features::ai::service::AiService::new(pool.clone(), ai_url, ai_model);
-    ai_service.clone().start_background_health_check().await;
+    let ai_service = features::ai::service::AiService::new(pool.clone(), ai_url, ai_model)
+        .with_circuit_breaker(utils::circuit_breaker::CircuitBreaker::from_config(
+            &config.ai_circuit_breaker,
+        ))
+        .with_http_config(&config.outbound_http)
+        .with_redaction_config(&config.ai_redaction)
+        .with_providers_config(&config.ai_providers);
+    ai_service.clone().start_background_health_check(shutdown.clone()).await;
+
+    // Prompt templates, editable at runtime; built-in prompts are seeded on first start
+    let prompt_service = features::ai::prompts::PromptService::new(pool.clone());
+    if let Err(e) = prompt_service.seed_builtins().await {
+        tracing::error!("Failed to seed built-in prompt templates: {}", e);
+    }
+
+    // Natural-language questions answered with the caller's read permissions
+    let nl_query_service = features::ai::nl_query::NlQueryService::new(
+        pool.clone(),
+        ai_service.clone(),
+        rebac_service.clone(),
+        prompt_service.clone(),
+    );
+
+    // Stored AI chats, deleted once idle past the retention period
+    let conversation_service = features::ai::conversations::ConversationService::new(
+        pool.clone(),
+        ai_service.clone(),
+        rebac_service.clone(),
+        prompt_service.clone(),
+        config.ai_conversations.clone(),
+    );
+    conversation_service.clone().start_retention_task(shutdown.clone()).await;
+
+    // Admin questions about the audit log, answered from retrieved events with citations
+    let audit_qa_service = features::ai::audit_qa::AuditQaService::new(
+        pool.clone(),
+        ai_service.clone(),
+        prompt_service.clone(),
+        audit_service.clone(),
+    );
+
+    // Admin questions about who can do what, answered from ReBAC checks
+    let authz_qa_service = features::ai::authz_qa::AuthzQaService::new(
+        pool.clone(),
+        ai_service.clone(),
+        rebac_service.clone(),
+        prompt_service.clone(),
+        audit_service.clone(),
+    );
+
+    // Machine-generated explanations on raised security alerts
+    let alert_explanation_service = features::ai::alert_explanations::AlertExplanationService::new(
+        pool.clone(),
+        ai_service.clone(),
+        prompt_service.clone(),
+        config.ai_alert_explanations.clone(),
+    );
+
+    // ABAC policies drafted from plain-language requirements, enforced only once approved
+    let policy_drafting_service = features::ai::policy_drafting::PolicyDraftingService::new(
+        pool.clone(),
+        ai_service.clone(),
+        prompt_service.clone(),
+        policy_service.clone(),
+    );
+
+    // AI modeling suggestions become ontology changesets for review
+    let modeling_service =
+        features::ai::modeling::ModelingService::new(ai_service.clone(), ontology_service.clone());
+
+    // Entity embeddings for semantic search, kept current in the background
+    let semantic_search_service = features::ai::semantic_search::SemanticSearchService::new(
+        pool.clone(),
+        ai_service.clone(),
+        rebac_service.clone(),
+        config.semantic_search.clone(),
+    );
+    // Re-embeds entities as ontology change events arrive; progress is in /system/info
+    let index_worker = features::ai::index_worker::IndexWorker::new(
+        pool.clone(),
+        semantic_search_service.clone(),
+    );
+    index_worker.clone().start(shutdown.clone()).await;
+    let mut system_service = system_service.with_index_worker(index_worker.clone());
+
+    // Optional mirror of entities and relationships in a graph database
+    let graph_sync = features::graph_sync::GraphSync::from_config(pool.clone(), &config.graph_sync)
+        .await
+        .expect("Invalid graph sync configuration");
+    if let Some(graph_sync) = &graph_sync {
+        graph_sync.clone().start(shutdown.clone()).await;
+        system_service = system_service.with_graph_sync(graph_sync.clone());
+    }
+
+    // Optional Elasticsearch/OpenSearch index of entities and audit events,
+    // which can also answer quick search and audit log search
+    let search_index = features::search_index::SearchIndex::from_config(
+        pool.clone(),
+        &config.search_index,
+        utils::http_client::OutboundClient::from_config(&config.outbound_http),
+    )
+    .await
+    .expect("Invalid search index configuration");
+    if let Some(search_index) = &search_index {
+        search_index.clone().start(shutdown.clone()).await;
+        system_service = system_service.with_search_index(search_index.clone());
+        navigation_service = navigation_service.with_search_index(search_index.clone());
+    }
+
+    // Advertised at /api/discovery/capabilities so clients can adapt to this deployment
+    let api_capabilities = Arc::new(
+        features::discovery::capabilities::ApiCapabilities::from_config(
+            &config,
+            semantic_search_service.is_available().await.unwrap_or(false),
+        ),
+    );
+
+    // Request capture - replays are sent back to this server over loopback
+    let replay_base_url = std::env::var("CAPTURE_REPLAY_BASE_URL")
+        .unwrap_or_else(|_| "http://127.0.0.1:5300".to_string());
+    let request_capture_service =
+        features::request_capture::RequestCaptureService::new(pool.clone(), replay_base_url);
+    request_capture_service.clone().start_cleanup_task(shutdown.clone()).await;
+
+    // Idempotency-Key store for POST/PUT retries
+    let idempotency_store = middleware::idempotency::IdempotencyStore::new(pool.clone());
+    idempotency_store.clone().start_cleanup_task(shutdown.clone()).await;
+
+    // IP allow/deny rules, evaluated before authentication
+    let ip_access_service =
+        features::ip_access::IpAccessService::new(pool.clone(), trusted_proxies);
+    ip_access_service.clone().start_refresh_task(shutdown.clone()).await;
+
+    // Object storage for uploaded files and export bundles, on local disk or in S3
+    let object_storage = utils::storage::build_storage(
+        &config.storage,
+        &utils::http_client::OutboundClient::from_config(&config.outbound_http),
+    )
+    .expect("Invalid storage configuration");
+
+    // Tenant or instance bundles for promoting configuration between environments
+    let environment_service = features::environment::EnvironmentService::new(
+        pool.clone(),
+        ontology_service.clone(),
+        audit_service.clone(),
+    )
+    .with_storage(object_storage.clone());
+
+    // Consumers applying entity upserts from upstream systems of record
+    let ingestion_service = if config.ingestion.enabled {
+        let ingestion_service = features::ingestion::IngestionService::new(
+            pool.clone(),
+            ontology_service.clone(),
+            config.ingestion.clone(),
+        );
+        ingestion_service
+            .clone()
+            .start(
+                utils::http_client::OutboundClient::from_config(&config.outbound_http),
+                shutdown.clone(),
+            )
+            .await
+            .expect("Invalid ingestion configuration");
+        Some(ingestion_service)
+    } else {
+        None
+    };
+
+    // Shared upload flow for attachments, avatars and bulk import
+    let upload_service = features::uploads::UploadService::new(pool.clone(), config.uploads.clone())
+        .with_storage(object_storage.clone());
+    upload_service.clone().start_cleanup_task(shutdown.clone()).await;
+
+    // Entities and relationships the AI finds in uploaded documents, accepted as PENDING
+    let extraction_service = features::ai::extraction::ExtractionService::new(
+        pool.clone(),
+        ai_service.clone(),
+        upload_service.clone(),
+        ontology_service.clone(),
+        prompt_service.clone(),
+    );
+
+    // Health checks report the AI circuit breaker so degraded AI features are visible
+    let health = {
+        let ai_service = ai_service.clone();
+        get(move || health_check(ai_service.clone()))
+    };
 
     // Create router and attach state
     // API router contains feature routes and an API-scoped health check
     let api_router = Router::new()
-        .route("/health", get(health_check))
+        .route("/health", health.clone())
         .nest(
             "/discovery",
-            features::discovery::routes::discovery_routes().with_state(discovery_service.clone()),
+            features::discovery::routes::discovery_routes()
+                .merge(
+                    features::discovery::routes::discovery_admin_routes()
+                        .layer(axum::middleware::from_fn(middleware::auth::auth_middleware)),
+                )
+                .with_state(discovery_service.clone())
+                .merge(
+                    features::discovery::routes::capabilities_routes()
+                        .with_state(api_capabilities),
+                ),
         )
         .nest(
             "/rate-limits",
@@ -234,6 +543,10 @@ async fn main() {
             "/abac",
             features::abac::routes::abac_routes()
                 .with_state(abac_service.clone())
+                .layer(axum::middleware::from_fn_with_state(
+                    middleware::audit::SensitiveRouteAudit::new(audit_service.clone(), "abac"),
+                    middleware::audit::sensitive_route_audit_middleware,
+                ))
                 .layer(axum::middleware::from_fn(middleware::auth::auth_middleware))
                 .layer(axum::middleware::from_fn(middleware::csrf::validate_csrf)),
         )
@@ -255,6 +568,10 @@ async fn main() {
             "/rebac",
             features::rebac::routes::rebac_routes()
                 .with_state(rebac_service)
+                .layer(axum::middleware::from_fn_with_state(
+                    middleware::audit::SensitiveRouteAudit::new(audit_service.clone(), "rebac"),
+                    middleware::audit::sensitive_route_audit_middleware,
+                ))
                 .layer(axum::middleware::from_fn(middleware::auth::auth_middleware))
                 .layer(axum::middleware::from_fn(middleware::csrf::validate_csrf)),
         )
@@ -262,13 +579,17 @@ async fn main() {
             "/rebac/policies",
             features::rebac::policy_routes::policy_routes()
                 .with_state(policy_service)
+                .layer(axum::middleware::from_fn_with_state(
+                    middleware::audit::SensitiveRouteAudit::new(audit_service.clone(), "rebac_policies"),
+                    middleware::audit::sensitive_route_audit_middleware,
+                ))
                 .layer(axum::middleware::from_fn(middleware::auth::auth_middleware))
                 .layer(axum::middleware::from_fn(middleware::csrf::validate_csrf)),
         )
         .nest(
             "/api-management",
             features::api_management::routes::api_management_routes()
-                .with_state(api_management_service)
+                .with_state(api_management_service.clone())
                 .layer(axum::middleware::from_fn(middleware::auth::auth_middleware))
                 .layer(axum::middleware::from_fn(middleware::csrf::validate_csrf)),
         )
@@ -276,6 +597,40 @@ async fn main() {
             "/ai",
             features::ai::routes::ai_routes()
                 .with_state(ai_service)
+                .merge(features::ai::routes::nl_query_routes().with_state(nl_query_service))
+                .merge(features::ai::routes::modeling_routes().with_state(modeling_service))
+                .merge(
+                    features::ai::routes::semantic_search_routes()
+                        .with_state(semantic_search_service),
+                )
+                .merge(features::ai::routes::extraction_routes().with_state(extraction_service))
+                .merge(features::ai::routes::prompt_routes().with_state(prompt_service))
+                .merge(
+                    features::ai::routes::conversation_routes().with_state(conversation_service),
+                )
+                .merge(features::ai::routes::audit_qa_routes().with_state(audit_qa_service))
+                .merge(features::ai::routes::authz_qa_routes().with_state(authz_qa_service))
+                .merge(
+                    features::ai::routes::usage_routes()
+                        .with_state(features::ai::usage::UsageService::new(pool.clone())),
+                )
+                .merge(features::ai::routes::index_routes().with_state(index_worker))
+                .merge(
+                    features::ai::routes::redaction_routes().with_state(
+                        features::ai::redaction::RedactionService::new(
+                            pool.clone(),
+                            config.ai_redaction.clone(),
+                        ),
+                    ),
+                )
+                .merge(
+                    features::ai::routes::alert_explanation_routes()
+                        .with_state(alert_explanation_service),
+                )
+                .merge(
+                    features::ai::routes::policy_draft_routes()
+                        .with_state(policy_drafting_service),
+                )
                 .layer(axum::middleware::from_fn(middleware::auth::auth_middleware))
                 .layer(axum::middleware::from_fn(middleware::csrf::validate_csrf)),
         )
@@ -283,6 +638,24 @@ async fn main() {
             "/firefighter",
             features::firefighter::routes::firefighter_routes()
                 .with_state(firefighter_service)
+                .layer(axum::middleware::from_fn_with_state(
+                    middleware::audit::SensitiveRouteAudit::new(audit_service.clone(), "firefighter"),
+                    middleware::audit::sensitive_route_audit_middleware,
+                ))
+                .layer(axum::middleware::from_fn(middleware::auth::auth_middleware))
+                .layer(axum::middleware::from_fn(middleware::csrf::validate_csrf)),
+        )
+        .nest(
+            "/email",
+            features::email::routes::email_routes()
+                .with_state(email_service)
+                .layer(axum::middleware::from_fn(middleware::auth::auth_middleware))
+                .layer(axum::middleware::from_fn(middleware::csrf::validate_csrf)),
+        )
+        .nest(
+            "/notifications",
+            features::notifications::routes::notification_routes()
+                .with_state(notification_service)
                 .layer(axum::middleware::from_fn(middleware::auth::auth_middleware))
                 .layer(axum::middleware::from_fn(middleware::csrf::validate_csrf)),
         )
@@ -299,67 +672,206 @@ async fn main() {
                 .with_state(project_service)
                 .layer(axum::middleware::from_fn(middleware::auth::auth_middleware))
                 .layer(axum::middleware::from_fn(middleware::csrf::validate_csrf)),
+        )
+        .nest(
+            "/uploads",
+            features::uploads::routes::upload_routes()
+                .with_state(upload_service)
+                .layer(axum::middleware::from_fn(middleware::auth::auth_middleware))
+                .layer(axum::middleware::from_fn(middleware::csrf::validate_csrf)),
+        )
+        .nest(
+            "/request-capture",
+            features::request_capture::routes::request_capture_routes()
+                .with_state(request_capture_service.clone())
+                .layer(axum::middleware::from_fn(middleware::auth::auth_middleware))
+                .layer(axum::middleware::from_fn(middleware::csrf::validate_csrf)),
+        )
+        .nest(
+            "/ip-access",
+            features::ip_access::routes::ip_access_routes()
+                .with_state(ip_access_service.clone())
+                .layer(axum::middleware::from_fn(middleware::auth::auth_middleware))
+                .layer(axum::middleware::from_fn(middleware::csrf::validate_csrf)),
+        )
+        .nest(
+            "/geo-access",
+            features::geo_access::routes::geo_access_routes()
+                .with_state(geo_access_service.clone())
+                .layer(axum::middleware::from_fn(middleware::auth::auth_middleware))
+                .layer(axum::middleware::from_fn(middleware::csrf::validate_csrf)),
+        )
+        .nest(
+            "/environment",
+            features::environment::routes::environment_routes()
+                .with_state(environment_service)
+                .layer(axum::middleware::from_fn(middleware::auth::auth_middleware))
+                .layer(axum::middleware::from_fn(middleware::csrf::validate_csrf)),
         );
 
+    let api_router = match graph_sync {
+        Some(graph_sync) => api_router.nest(
+            "/graph-sync",
+            features::graph_sync::routes::graph_sync_routes()
+                .with_state(graph_sync)
+                .layer(axum::middleware::from_fn(middleware::auth::auth_middleware))
+                .layer(axum::middleware::from_fn(middleware::csrf::validate_csrf)),
+        ),
+        None => api_router,
+    };
+
+    let api_router = match ingestion_service {
+        Some(ingestion_service) => api_router.nest(
+            "/ingestion",
+            features::ingestion::routes::ingestion_routes()
+                .with_state(ingestion_service)
+                .layer(axum::middleware::from_fn(middleware::auth::auth_middleware))
+                .layer(axum::middleware::from_fn(middleware::csrf::validate_csrf)),
+        ),
+        None => api_router,
+    };
+
+    // Presigned downloads of the local store carry their own signature instead of a session
+    let api_router = match object_storage.as_local() {
+        Some(local) => api_router.nest(
+            "/storage",
+            features::storage::routes::storage_routes().with_state(local.clone()),
+        ),
+        None => api_router,
+    };
+
+    let api_router = match search_index {
+        Some(search_index) => api_router.nest(
+            "/search-index",
+            features::search_index::routes::search_index_routes()
+                .with_state(search_index)
+                .layer(axum::middleware::from_fn(middleware::auth::auth_middleware))
+                .layer(axum::middleware::from_fn(middleware::csrf::validate_csrf)),
+        ),
+        None => api_router,
+    };
+
     // CVE-004 Fix: Rate limiting is handled by the database-backed service
     // The simple in-memory rate limiter has been replaced with proper database rules
 
     let app = Router::new()
-        .route("/health", get(health_check))
+        .route("/health", health)
         .nest("/api", api_router)
         .with_state(auth_service.clone())
+        .layer(axum::middleware::from_fn_with_state(
+            idempotency_store,
+            middleware::idempotency::idempotency_middleware,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            request_capture_service,
+            features::request_capture::middleware::capture_middleware,
+        ))
+        // Size limits are enforced per route by body_limit_middleware instead of axum's 2MB default
+        .layer(axum::middleware::from_fn_with_state(
+            body_limits,
+            middleware::body_limit::body_limit_middleware,
+        ))
+        .layer(axum::extract::DefaultBodyLimit::disable())
+        .layer(axum::middleware::from_fn_with_state(
+            geo_access_service,
+            features::geo_access::middleware::geo_access_middleware,
+        ))
+        // Blocked clients are turned away before any body is read or token checked
+        .layer(axum::middleware::from_fn_with_state(
+            ip_access_service,
+            features::ip_access::middleware::ip_access_middleware,
+        ))
+        // Slow or overloaded route groups answer 408/503 instead of holding connections open
+        .layer(axum::middleware::from_fn_with_state(
+            route_limits,
+            middleware::route_limits::route_limits_middleware,
+        ))
+        // Unsupported Accept-Version gets 406; deprecated routes carry Deprecation/Sunset
+        .layer(axum::middleware::from_fn_with_state(
+            api_versioning,
+            middleware::api_version::api_version_middleware,
+        ))
         .layer(tower_cookies::CookieManagerLayer::new())
         .layer(axum::Extension(config_arc))
-        .layer(
-            CorsLayer::new()
-                .allow_origin([
-                    "http://localhost:5373"
-                        .parse::<axum::http::HeaderValue>()
-                        .unwrap(),
-                    "http://localhost:3000"
-                        .parse::<axum::http::HeaderValue>()
-                        .unwrap(),
-                    "http://127.0.0.1:5373"
-                        .parse::<axum::http::HeaderValue>()
-                        .unwrap(),
-                    "http://127.0.0.1:3000"
-                        .parse::<axum::http::HeaderValue>()
-                        .unwrap(),
-                ])
-                .allow_methods([
-                    axum::http::Method::GET,
-                    axum::http::Method::POST,
-                    axum::http::Method::PUT,
-                    axum::http::Method::DELETE,
-                    axum::http::Method::OPTIONS,
-                ])
-                .allow_headers([
-                    axum::http::header::CONTENT_TYPE,
-                    axum::http::header::AUTHORIZATION,
-                    axum::http::header::COOKIE,
-                    axum::http::header::SET_COOKIE,
-                    axum::http::header::ACCEPT,
-                    axum::http::HeaderName::from_static("x-csrf-token"),
-                ])
-                .allow_credentials(true),
-        );
+        // Read by enforce_route_permissions for routes that declare a RoutePermission
+        .layer(axum::Extension(abac_service.clone()))
+        // auth_middleware verifies x-api-key credentials against it
+        .layer(axum::Extension(api_management_service))
+        .layer(axum::middleware::from_fn_with_state(
+            security_headers,
+            middleware::security_headers::security_headers_middleware,
+        ))
+        // Batches and memoizes entity/schema lookups within GET requests
+        .layer(axum::middleware::from_fn(
+            features::ontology::loader::ontology_loader_middleware,
+        ))
+        // Error responses get the shared envelope with a code and the request id
+        .layer(axum::middleware::from_fn(
+            middleware::error_envelope::error_envelope_middleware,
+        ))
+        // Trace id from traceparent (or a new one) for log lines and decision records
+        .layer(axum::middleware::from_fn(
+            middleware::trace_context::trace_context_middleware,
+        ))
+        // gzip/brotli negotiated via Accept-Encoding; SSE and tiny bodies are skipped
+        .layer(CompressionLayer::new())
+        .layer(cors_layer);
 
     // Start server
     let addr = SocketAddr::from(([0, 0, 0, 0], 5300));
-    tracing::info!(%addr, "Server listening");
+    let mut server = if config.tls.enabled {
+        tracing::info!(%addr, "Server listening (TLS)");
+        tokio::spawn(utils::tls::serve(
+            config.tls.clone(),
+            addr,
+            app,
+            shutdown.clone(),
+        ))
+    } else {
+        tracing::info!(%addr, "Server listening");
+        let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
+        let server = axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(shutdown.cancelled());
+        tokio::spawn(async move { server.await })
+    };
 
-    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
-    axum::serve(
-        listener,
-        app.into_make_service_with_connect_info::<SocketAddr>(),
-    )
-    .await
-    .unwrap();
+    // On SIGTERM stop accepting connections, let in-flight requests and running
+    // background jobs finish within their deadlines, then close the pool
+    tokio::select! {
+        result = &mut server => {
+            if let Ok(Err(e)) = result {
+                tracing::error!("Server error: {}", e);
+            }
+        }
+        _ = utils::shutdown::shutdown_signal() => {
+            tracing::info!("Shutdown signal received, draining connections");
+            shutdown.trigger();
+            let drain = std::time::Duration::from_secs(config.shutdown.drain_timeout_secs);
+            if tokio::time::timeout(drain, &mut server).await.is_err() {
+                tracing::warn!("Connections still open after {:?}, closing them", drain);
+                server.abort();
+            }
+        }
+    }
+
+    shutdown.trigger();
+    let tasks = std::time::Duration::from_secs(config.shutdown.task_timeout_secs);
+    if !shutdown.wait_for_tasks(tasks).await {
+        tracing::warn!("Background jobs still running after {:?}", tasks);
+    }
+    pool.close().await;
+    tracing::info!("Shutdown complete");
 }
 
-async fn health_check() -> axum::Json<serde_json::Value> {
+async fn health_check(
+    ai_service: features::ai::service::AiService,
+) -> axum::Json<serde_json::Value> {
     axum::Json(serde_json::json!({
         "status": "OK",
-        "version": env!("CARGO_PKG_VERSION")
+        "version": env!("CARGO_PKG_VERSION"),
+        "ai": ai_service.breaker_status()
     }))
 }
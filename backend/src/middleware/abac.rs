@@ -1,11 +1,14 @@
+use crate::features;
 use crate::features::abac::AbacService;
 use crate::features::auth::jwt::Claims;
+use crate::utils::api_error::ApiError;
 use axum::{
-    extract::State,
+    extract::{MatchedPath, NestedPath, Request, State},
     http::StatusCode,
+    middleware::Next,
     response::{IntoResponse, Response},
-    Json,
 };
+use serde::Serialize;
 use uuid::Uuid;
 
 /// Extractor that requires a specific permission for access
@@ -31,9 +34,13 @@ pub enum PermissionError {
 
 impl IntoResponse for PermissionError {
     fn into_response(self) -> Response {
-        let (status, message) = match self {
-            PermissionError::Unauthorized => (StatusCode::UNAUTHORIZED, "Authentication required"),
-            PermissionError::Forbidden(msg) => (StatusCode::FORBIDDEN, {
+        let (status, code, message) = match self {
+            PermissionError::Unauthorized => (
+                StatusCode::UNAUTHORIZED,
+                "auth.unauthenticated",
+                "Authentication required",
+            ),
+            PermissionError::Forbidden(msg) => (StatusCode::FORBIDDEN, "abac.permission_denied", {
                 // We need to return a static str, so we'll use a generic message
                 // The actual message is logged server-side
                 tracing::warn!("Permission denied: {}", msg);
@@ -43,12 +50,13 @@ impl IntoResponse for PermissionError {
                 tracing::error!("ABAC internal error: {}", msg);
                 (
                     StatusCode::INTERNAL_SERVER_ERROR,
+                    "internal",
                     "Authorization check failed",
                 )
             }
         };
 
-        (status, Json(serde_json::json!({ "error": message }))).into_response()
+        ApiError::new(status, code, message).into_response()
     }
 }
 
@@ -111,3 +119,242 @@ pub async fn require_permission<B>(
         )))
     }
 }
+
+/// A permission a route requires, declared next to the router that serves it.
+///
+/// `path` is the route pattern as passed to `Router::route`, relative to where the
+/// router is nested.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct RoutePermission {
+    pub method: &'static str,
+    pub path: &'static str,
+    pub permission: &'static str,
+}
+
+impl RoutePermission {
+    pub const fn new(method: &'static str, path: &'static str, permission: &'static str) -> Self {
+        Self {
+            method,
+            path,
+            permission,
+        }
+    }
+}
+
+/// Find the declared requirement for a request, given the router's nest prefix
+/// and the matched route pattern
+pub fn required_permission(
+    table: &[RoutePermission],
+    method: &str,
+    nested_path: &str,
+    matched_path: &str,
+) -> Option<&'static str> {
+    let relative = matched_path
+        .strip_prefix(nested_path.trim_end_matches('/'))
+        .unwrap_or(matched_path);
+    table
+        .iter()
+        .find(|r| r.method.eq_ignore_ascii_case(method) && r.path == relative)
+        .map(|r| r.permission)
+}
+
+/// Enforces a router's declared `RoutePermission`s through `AbacService`.
+///
+/// Add with `route_layer(from_fn_with_state(ROUTE_PERMISSIONS, enforce_route_permissions))`
+/// inside `auth_middleware`; routes without a declaration pass through, except
+/// on routers `DECLARED_ROUTES` marks exhaustive, where they are refused. The
+/// `AbacService` is read from request extensions, and its absence fails closed.
+pub async fn enforce_route_permissions(
+    State(table): State<&'static [RoutePermission]>,
+    nested_path: Option<NestedPath>,
+    matched_path: Option<MatchedPath>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(matched_path) = matched_path else {
+        return next.run(request).await;
+    };
+    let nested_path = nested_path.as_ref().map(|p| p.as_str()).unwrap_or("");
+    let Some(permission) = required_permission(
+        table,
+        request.method().as_str(),
+        nested_path,
+        matched_path.as_str(),
+    ) else {
+        if requires_declaration(nested_path) {
+            tracing::error!(
+                method = %request.method(),
+                route = matched_path.as_str(),
+                "Route on an administrative router has no declared permission"
+            );
+            return PermissionError::Forbidden(format!(
+                "{} {} has no declared permission",
+                request.method(),
+                matched_path.as_str()
+            ))
+            .into_response();
+        }
+        return next.run(request).await;
+    };
+
+    let Some(claims) = request.extensions().get::<Claims>() else {
+        return PermissionError::Unauthorized.into_response();
+    };
+    let Some(abac_service) = request.extensions().get::<AbacService>() else {
+        return PermissionError::InternalError("AbacService extension missing".to_string())
+            .into_response();
+    };
+
+    match check_user_permission(abac_service, &claims.sub, permission, None, None, None).await {
+        Ok(true) => next.run(request).await,
+        Ok(false) => PermissionError::Forbidden(format!(
+            "User {} lacks permission '{}' for {} {}",
+            claims.sub,
+            permission,
+            request.method(),
+            matched_path.as_str()
+        ))
+        .into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+/// One row of the route→permission report
+#[derive(Debug, Clone, Serialize)]
+pub struct RoutePermissionEntry {
+    pub method: &'static str,
+    pub path: String,
+    pub permission: &'static str,
+}
+
+/// A router with declared permissions and where main.rs nests it
+struct DeclaredRouter {
+    prefix: &'static str,
+    table: &'static [RoutePermission],
+    /// Every route is declared, so an undeclared one was added by mistake
+    exhaustive: bool,
+}
+
+const fn declared(
+    prefix: &'static str,
+    table: &'static [RoutePermission],
+    exhaustive: bool,
+) -> DeclaredRouter {
+    DeclaredRouter {
+        prefix,
+        table,
+        exhaustive,
+    }
+}
+
+/// Routers with declared permissions. The administrative ones are exhaustive;
+/// the others declare only their administrative routes and check the rest in
+/// their handlers.
+const DECLARED_ROUTES: &[DeclaredRouter] = &[
+    declared("/api/abac", features::abac::routes::ROUTE_PERMISSIONS, true),
+    declared("/api/ai", features::ai::routes::ROUTE_PERMISSIONS, false),
+    declared(
+        "/api/api-management",
+        features::api_management::routes::ROUTE_PERMISSIONS,
+        false,
+    ),
+    declared(
+        "/api/auth",
+        features::auth::routes::ROUTE_PERMISSIONS,
+        false,
+    ),
+    declared(
+        "/api/discovery",
+        features::discovery::routes::ROUTE_PERMISSIONS,
+        false,
+    ),
+    declared(
+        "/api/email",
+        features::email::routes::ROUTE_PERMISSIONS,
+        true,
+    ),
+    declared(
+        "/api/environment",
+        features::environment::routes::ROUTE_PERMISSIONS,
+        true,
+    ),
+    declared(
+        "/api/firefighter",
+        features::firefighter::routes::ROUTE_PERMISSIONS,
+        false,
+    ),
+    declared(
+        "/api/geo-access",
+        features::geo_access::routes::ROUTE_PERMISSIONS,
+        true,
+    ),
+    declared(
+        "/api/graph-sync",
+        features::graph_sync::routes::ROUTE_PERMISSIONS,
+        false,
+    ),
+    declared(
+        "/api/ingestion",
+        features::ingestion::routes::ROUTE_PERMISSIONS,
+        true,
+    ),
+    declared(
+        "/api/ip-access",
+        features::ip_access::routes::ROUTE_PERMISSIONS,
+        true,
+    ),
+    declared(
+        "/api/navigation",
+        features::navigation::routes::ROUTE_PERMISSIONS,
+        false,
+    ),
+    declared(
+        "/api/notifications",
+        features::notifications::routes::ROUTE_PERMISSIONS,
+        false,
+    ),
+    declared(
+        "/api/ontology",
+        features::ontology::routes::ROUTE_PERMISSIONS,
+        false,
+    ),
+    declared(
+        "/api/request-capture",
+        features::request_capture::routes::ROUTE_PERMISSIONS,
+        true,
+    ),
+    declared(
+        "/api/search-index",
+        features::search_index::routes::ROUTE_PERMISSIONS,
+        false,
+    ),
+    declared(
+        "/api/test-mode",
+        features::test_mode::routes::ROUTE_PERMISSIONS,
+        false,
+    ),
+];
+
+/// Whether routes nested at `nested_path` must all declare a permission
+fn requires_declaration(nested_path: &str) -> bool {
+    let nested_path = nested_path.trim_end_matches('/');
+    DECLARED_ROUTES
+        .iter()
+        .any(|r| r.exhaustive && r.prefix == nested_path)
+}
+
+/// Every declared route requirement with its full path, sorted by path
+pub fn route_permission_report() -> Vec<RoutePermissionEntry> {
+    let mut entries: Vec<RoutePermissionEntry> = DECLARED_ROUTES
+        .iter()
+        .flat_map(|router| {
+            router.table.iter().map(move |r| RoutePermissionEntry {
+                method: r.method,
+                path: format!("{}{}", router.prefix, r.path),
+                permission: r.permission,
+            })
+        })
+        .collect();
+    entries.sort_by(|a, b| (&a.path, a.method).cmp(&(&b.path, b.method)));
+    entries
+}
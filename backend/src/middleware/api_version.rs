@@ -0,0 +1,267 @@
+// API version negotiation and deprecation headers
+//
+// Every response names the API version it was served with. A client that
+// requires a version sends `Accept-Version` and gets a 406 listing the
+// supported versions if this deployment can't serve it. Routes deprecated in
+// `Config::api` answer with `Deprecation` (RFC 9745) and `Sunset` (RFC 8594)
+// headers and a successor-version link, and with 410 Gone once sunset.
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{NaiveDate, Utc};
+use serde_json::json;
+use std::sync::Arc;
+
+use crate::config::{ApiConfig, ApiDeprecation};
+
+pub const ACCEPT_VERSION: HeaderName = HeaderName::from_static("accept-version");
+pub const API_VERSION: HeaderName = HeaderName::from_static("api-version");
+const DEPRECATION: HeaderName = HeaderName::from_static("deprecation");
+const SUNSET: HeaderName = HeaderName::from_static("sunset");
+
+#[derive(Clone)]
+pub struct ApiVersioning {
+    current: HeaderValue,
+    supported: Arc<Vec<String>>,
+    /// Sorted longest prefix first so the most specific deprecation wins
+    deprecations: Arc<Vec<ApiDeprecation>>,
+}
+
+impl ApiVersioning {
+    pub fn from_config(config: &ApiConfig) -> Result<Self, String> {
+        let current_version = config.current_version.trim().to_string();
+        let current = HeaderValue::from_str(&current_version)
+            .map_err(|_| format!("Invalid API version: {}", current_version))?;
+        let supported: Vec<String> = config
+            .supported_versions
+            .iter()
+            .map(|v| v.trim().to_string())
+            .collect();
+        if !supported.contains(&current_version) {
+            return Err(format!(
+                "Supported API versions must include the current version {}",
+                current_version
+            ));
+        }
+
+        let mut deprecations = config.deprecations.clone();
+        for deprecation in &deprecations {
+            if !deprecation.prefix.starts_with('/') {
+                return Err(format!(
+                    "Deprecated route prefix must start with '/': {}",
+                    deprecation.prefix
+                ));
+            }
+            if deprecation
+                .sunset_at
+                .is_some_and(|sunset| sunset < deprecation.deprecated_at)
+            {
+                return Err(format!(
+                    "Sunset of {} is before its deprecation",
+                    deprecation.prefix
+                ));
+            }
+        }
+        deprecations.sort_by_key(|d| std::cmp::Reverse(d.prefix.len()));
+
+        Ok(Self {
+            current,
+            supported: Arc::new(supported),
+            deprecations: Arc::new(deprecations),
+        })
+    }
+
+    pub fn supported_versions(&self) -> &[String] {
+        &self.supported
+    }
+
+    /// The deprecation covering a request, if any
+    pub fn deprecation_for(&self, method: &str, path: &str) -> Option<&ApiDeprecation> {
+        self.deprecations.iter().find(|d| {
+            path.starts_with(d.prefix.as_str())
+                && d.method
+                    .as_deref()
+                    .is_none_or(|m| m.eq_ignore_ascii_case(method))
+        })
+    }
+
+    /// Whether `requested` (an `Accept-Version` value, possibly a list) names
+    /// a version this deployment serves
+    fn accepts(&self, requested: &str) -> bool {
+        requested
+            .split(',')
+            .map(str::trim)
+            .any(|v| v == "*" || self.supported.iter().any(|s| s == v))
+    }
+}
+
+/// Midnight UTC at the start of `date`
+fn start_of(date: NaiveDate) -> chrono::DateTime<Utc> {
+    date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc()
+}
+
+fn deprecation_headers(deprecation: &ApiDeprecation) -> Vec<(HeaderName, HeaderValue)> {
+    let mut headers = Vec::new();
+    let deprecated = format!("@{}", start_of(deprecation.deprecated_at).timestamp());
+    if let Ok(value) = HeaderValue::from_str(&deprecated) {
+        headers.push((DEPRECATION, value));
+    }
+    if let Some(sunset) = deprecation.sunset_at {
+        let date = start_of(sunset)
+            .format("%a, %d %b %Y %H:%M:%S GMT")
+            .to_string();
+        if let Ok(value) = HeaderValue::from_str(&date) {
+            headers.push((SUNSET, value));
+        }
+    }
+    if let Some(replacement) = &deprecation.replacement {
+        let link = format!("<{}>; rel=\"successor-version\"", replacement);
+        if let Ok(value) = HeaderValue::from_str(&link) {
+            headers.push((header::LINK, value));
+        }
+    }
+    headers
+}
+
+pub async fn api_version_middleware(
+    State(versioning): State<ApiVersioning>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !request.uri().path().starts_with("/api") {
+        return next.run(request).await;
+    }
+
+    if let Some(requested) = request.headers().get(&ACCEPT_VERSION) {
+        let requested = requested.to_str().unwrap_or_default();
+        if !versioning.accepts(requested) {
+            return (
+                StatusCode::NOT_ACCEPTABLE,
+                [(API_VERSION, versioning.current.clone())],
+                Json(json!({
+                    "error": format!("API version {} is not supported", requested),
+                    "supported_versions": versioning.supported_versions(),
+                })),
+            )
+                .into_response();
+        }
+    }
+
+    let deprecation = versioning
+        .deprecation_for(request.method().as_str(), request.uri().path())
+        .cloned();
+
+    let mut response = match &deprecation {
+        Some(d) if d.sunset_at.is_some_and(|s| s <= Utc::now().date_naive()) => (
+            StatusCode::GONE,
+            Json(json!({
+                "error": "This endpoint has been retired",
+                "replacement": d.replacement,
+            })),
+        )
+            .into_response(),
+        _ => next.run(request).await,
+    };
+
+    let headers = response.headers_mut();
+    headers.insert(API_VERSION, versioning.current.clone());
+    if let Some(deprecation) = &deprecation {
+        for (name, value) in deprecation_headers(deprecation) {
+            headers.insert(name, value);
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deprecation(prefix: &str, method: Option<&str>) -> ApiDeprecation {
+        ApiDeprecation {
+            prefix: prefix.to_string(),
+            method: method.map(str::to_string),
+            deprecated_at: NaiveDate::from_ymd_opt(2027, 3, 1).unwrap(),
+            sunset_at: NaiveDate::from_ymd_opt(2027, 9, 1),
+            replacement: Some("/api/v2/things".to_string()),
+            note: None,
+        }
+    }
+
+    fn versioning(deprecations: Vec<ApiDeprecation>) -> Result<ApiVersioning, String> {
+        ApiVersioning::from_config(&ApiConfig {
+            current_version: "2".to_string(),
+            supported_versions: vec!["1".to_string(), "2".to_string()],
+            deprecations,
+        })
+    }
+
+    #[test]
+    fn test_accepts_supported_versions() {
+        let v = versioning(Vec::new()).unwrap();
+        assert!(v.accepts("2"));
+        assert!(v.accepts("3, 1"));
+        assert!(v.accepts("*"));
+        assert!(!v.accepts("3"));
+    }
+
+    #[test]
+    fn test_deprecation_for_prefers_longest_prefix_and_method() {
+        let v = versioning(vec![
+            deprecation("/api/things", None),
+            deprecation("/api/things/export", Some("POST")),
+        ])
+        .unwrap();
+        assert_eq!(
+            v.deprecation_for("POST", "/api/things/export")
+                .map(|d| d.prefix.as_str()),
+            Some("/api/things/export")
+        );
+        assert_eq!(
+            v.deprecation_for("GET", "/api/things/export")
+                .map(|d| d.prefix.as_str()),
+            Some("/api/things")
+        );
+        assert!(v.deprecation_for("GET", "/api/other").is_none());
+    }
+
+    #[test]
+    fn test_deprecation_headers() {
+        let headers = deprecation_headers(&deprecation("/api/things", None));
+        let value = |name: &HeaderName| {
+            headers
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v.to_str().unwrap().to_string())
+        };
+        assert_eq!(value(&DEPRECATION).as_deref(), Some("@1803859200"));
+        assert_eq!(
+            value(&SUNSET).as_deref(),
+            Some("Wed, 01 Sep 2027 00:00:00 GMT")
+        );
+        assert_eq!(
+            value(&header::LINK).as_deref(),
+            Some("</api/v2/things>; rel=\"successor-version\"")
+        );
+    }
+
+    #[test]
+    fn test_from_config_rejects_inconsistent_settings() {
+        assert!(ApiVersioning::from_config(&ApiConfig {
+            current_version: "2".to_string(),
+            supported_versions: vec!["1".to_string()],
+            deprecations: Vec::new(),
+        })
+        .is_err());
+
+        let mut backwards = deprecation("/api/things", None);
+        backwards.sunset_at = NaiveDate::from_ymd_opt(2027, 1, 1);
+        assert!(versioning(vec![backwards]).is_err());
+        assert!(versioning(vec![deprecation("api/things", None)]).is_err());
+    }
+}
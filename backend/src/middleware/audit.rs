@@ -0,0 +1,119 @@
+// Automatic audit records for sensitive routes
+//
+// Route groups that change who may do what (role assignment, policies,
+// firefighter elevation) are wrapped in this layer inside auth_middleware, so
+// every state-changing request is written to the audit log together with its
+// response status, whether or not the handler logs anything itself.
+//
+// Only sanitized metadata is stored: credentials are dropped from the headers,
+// sensitive query parameters are redacted and bodies are never recorded.
+
+use axum::{
+    extract::{ConnectInfo, OriginalUri, Request, State},
+    http::Method,
+    middleware::Next,
+    response::Response,
+};
+use serde_json::json;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Instant;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::features::auth::jwt::Claims;
+use crate::features::request_capture::service::{sanitize_headers, sanitize_query};
+use crate::features::system::AuditService;
+use crate::utils::ip::{client_ip, parse_cidrs};
+
+/// Audit action recorded for every request through a sensitive route group
+pub const SENSITIVE_REQUEST_ACTION: &str = "sensitive_request";
+
+#[derive(Clone)]
+pub struct SensitiveRouteAudit {
+    audit: AuditService,
+    /// Route group name stored as the audit target type, e.g. "abac"
+    group: &'static str,
+}
+
+impl SensitiveRouteAudit {
+    pub fn new(audit: AuditService, group: &'static str) -> Self {
+        Self { audit, group }
+    }
+}
+
+/// Record non-safe requests (and their outcome) to the audit log.
+/// Must sit inside auth_middleware so the caller's claims are available.
+pub async fn sensitive_route_audit_middleware(
+    State(audit): State<SensitiveRouteAudit>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+        return next.run(req).await;
+    }
+
+    let Some(user_id) = req
+        .extensions()
+        .get::<Claims>()
+        .and_then(|claims| Uuid::parse_str(&claims.sub).ok())
+    else {
+        tracing::warn!(
+            "Sensitive route {} {} reached without claims; not audited",
+            req.method(),
+            req.uri()
+        );
+        return next.run(req).await;
+    };
+
+    let uri = req
+        .extensions()
+        .get::<OriginalUri>()
+        .map(|original| original.0.clone())
+        .unwrap_or_else(|| req.uri().clone());
+    let trusted_proxies = req
+        .extensions()
+        .get::<Arc<Config>>()
+        .and_then(|config| parse_cidrs(&config.ip_access.trusted_proxies).ok())
+        .unwrap_or_default();
+    let ip = client_ip(
+        req.headers(),
+        req.extensions().get::<ConnectInfo<SocketAddr>>(),
+        &trusted_proxies,
+    );
+    let mut metadata = json!({
+        "method": req.method().as_str(),
+        "path": uri.path(),
+        "query": uri.query().map(sanitize_query),
+        "client_ip": ip.map(|ip| ip.to_string()),
+        "headers": sanitize_headers(
+            req.headers()
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_bytes())),
+        ),
+    });
+
+    let started = Instant::now();
+    let response = next.run(req).await;
+    metadata["status"] = json!(response.status().as_u16());
+    metadata["duration_ms"] = json!(started.elapsed().as_millis() as u64);
+
+    // Awaited rather than spawned so the record exists before the caller sees the result
+    if let Err(e) = audit
+        .audit
+        .log(
+            user_id,
+            SENSITIVE_REQUEST_ACTION,
+            audit.group,
+            None,
+            None,
+            None,
+            Some(metadata),
+        )
+        .await
+    {
+        tracing::error!("Failed to audit sensitive request to {}: {}", uri.path(), e);
+    }
+
+    response
+}
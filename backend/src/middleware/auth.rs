@@ -1,12 +1,13 @@
 use crate::config::Config;
-use crate::features::auth::jwt::validate_jwt;
+use crate::features::api_management::ApiManagementService;
+use crate::features::auth::jwt::{validate_jwt, Claims};
+use crate::utils::api_error::ApiError;
 use axum::http::header::AUTHORIZATION;
 use axum::{
     async_trait,
     extract::FromRequestParts,
-    http::{request::Parts, StatusCode},
+    http::{request::Parts, Extensions, HeaderMap, StatusCode},
     response::{IntoResponse, Response},
-    Json,
 };
 use std::sync::Arc;
 
@@ -72,6 +73,16 @@ pub async fn auth_middleware(
 ) -> Result<Response, AuthError> {
     let (mut parts, body) = req.into_parts();
 
+    if !parts.headers.contains_key(AUTHORIZATION) {
+        if let Some(secret) = parts.headers.get(API_KEY_HEADER) {
+            let secret = secret.to_str().map_err(|_| AuthError::InvalidToken)?;
+            let claims = api_key_claims(&parts, secret).await?;
+            parts.extensions.insert(claims);
+            let req = axum::extract::Request::from_parts(parts, body);
+            return Ok(next.run(req).await);
+        }
+    }
+
     // We use the extractor logic here
     let token = if let Some(auth_header) = parts.headers.get(AUTHORIZATION) {
         auth_header
@@ -110,6 +121,41 @@ pub async fn auth_middleware(
     Ok(next.run(req).await)
 }
 
+/// Header carrying an API key secret, checked when no bearer token is sent
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Claims for a request authenticated with an API key. The key acts as its
+/// owner and stops working once expired or revoked. Its scopes are not
+/// carried over: nothing enforces them, and handlers read the permissions
+/// claim as granted.
+async fn api_key_claims(parts: &Parts, secret: &str) -> Result<Claims, AuthError> {
+    let api_keys = parts
+        .extensions
+        .get::<ApiManagementService>()
+        .ok_or(AuthError::MissingConfig)?;
+    let key = api_keys
+        .verify_key(secret)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to verify API key: {}", e);
+            AuthError::MissingConfig
+        })?
+        .ok_or(AuthError::InvalidToken)?;
+    // Keys without an owner have nobody to act as
+    let owner = key.created_by.ok_or(AuthError::InvalidToken)?;
+
+    Ok(Claims {
+        sub: owner.to_string(),
+        username: format!("api-key:{}", key.prefix),
+        email: String::new(),
+        roles: vec![],
+        permissions: vec![],
+        jti: None,
+        exp: key.expires_at.map(|t| t.timestamp()).unwrap_or(i64::MAX),
+        iat: chrono::Utc::now().timestamp(),
+    })
+}
+
 #[allow(dead_code)]
 #[derive(Debug)]
 pub enum AuthError {
@@ -136,18 +182,62 @@ impl serde::Serialize for AuthError {
 
 impl IntoResponse for AuthError {
     fn into_response(self) -> Response {
-        let (status, message) = match self {
-            AuthError::MissingToken => (StatusCode::UNAUTHORIZED, "Missing authorization token"),
-            AuthError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid authorization token"),
-            AuthError::UserNotFound => (StatusCode::NOT_FOUND, "User not found"),
+        let (status, code, message) = match self {
+            AuthError::MissingToken => (
+                StatusCode::UNAUTHORIZED,
+                "auth.missing_token",
+                "Missing authorization token",
+            ),
+            AuthError::InvalidToken => (
+                StatusCode::UNAUTHORIZED,
+                "auth.invalid_token",
+                "Invalid authorization token",
+            ),
+            AuthError::UserNotFound => (
+                StatusCode::NOT_FOUND,
+                "auth.user_not_found",
+                "User not found",
+            ),
             AuthError::MissingConfig => (
                 StatusCode::INTERNAL_SERVER_ERROR,
+                "internal",
                 "Server configuration error",
             ),
         };
 
-        (status, Json(serde_json::json!({ "error": message }))).into_response()
+        ApiError::new(status, code, message).into_response()
+    }
+}
+
+/// Best-effort user id for middleware that runs before `auth_middleware`.
+/// Returns None for missing or invalid credentials instead of rejecting the
+/// request. An API key resolves to its owner, as it does in `auth_middleware`.
+pub async fn peek_user_id(headers: &HeaderMap, extensions: &Extensions) -> Option<uuid::Uuid> {
+    if !headers.contains_key(AUTHORIZATION) {
+        if let Some(secret) = headers.get(API_KEY_HEADER) {
+            let api_keys = extensions.get::<ApiManagementService>()?;
+            return api_keys
+                .peek_key_owner(secret.to_str().ok()?)
+                .await
+                .ok()
+                .flatten();
+        }
     }
+
+    let token = headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|s| s.to_string())
+        .or_else(|| {
+            extensions
+                .get::<tower_cookies::Cookies>()
+                .and_then(|c| c.get("access_token"))
+                .map(|c| c.value().to_string())
+        })?;
+    let config = extensions.get::<Arc<Config>>()?;
+    let claims = validate_jwt(&token, config).ok()?;
+    uuid::Uuid::parse_str(&claims.sub).ok()
 }
 
 // Extract user from request extensions
@@ -0,0 +1,89 @@
+// Per-route request body size limits
+//
+// Limits come from `Config::body_limits`. Requests announcing a larger
+// Content-Length are rejected before anything is read; chunked bodies are
+// wrapped so reading stops at the limit. Either way the client gets a 413
+// with a JSON error body.
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use http_body_util::Limited;
+use serde_json::json;
+use std::sync::Arc;
+
+use crate::config::BodyLimitsConfig;
+
+#[derive(Clone)]
+pub struct BodyLimits {
+    default_bytes: usize,
+    /// Sorted longest prefix first so the most specific rule wins
+    routes: Arc<Vec<(String, usize)>>,
+}
+
+impl BodyLimits {
+    pub fn from_config(config: &BodyLimitsConfig) -> Self {
+        let mut routes: Vec<(String, usize)> = config
+            .routes
+            .iter()
+            .map(|r| (r.prefix.clone(), r.max_bytes))
+            .collect();
+        routes.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+
+        Self {
+            default_bytes: config.default_bytes,
+            routes: Arc::new(routes),
+        }
+    }
+
+    pub fn limit_for(&self, path: &str) -> usize {
+        self.routes
+            .iter()
+            .find(|(prefix, _)| path.starts_with(prefix.as_str()))
+            .map(|(_, max)| *max)
+            .unwrap_or(self.default_bytes)
+    }
+}
+
+pub async fn body_limit_middleware(
+    State(limits): State<BodyLimits>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let limit = limits.limit_for(request.uri().path());
+
+    let declared = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    if declared.is_some_and(|len| len > limit as u64) {
+        return payload_too_large(limit);
+    }
+
+    let (parts, body) = request.into_parts();
+    let request = Request::from_parts(parts, Body::new(Limited::new(body, limit)));
+    let response = next.run(request).await;
+
+    // Extractors report an exceeded limit as a plain-text 413; normalise it
+    if response.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        return payload_too_large(limit);
+    }
+    response
+}
+
+fn payload_too_large(limit: usize) -> Response {
+    (
+        StatusCode::PAYLOAD_TOO_LARGE,
+        Json(json!({
+            "error": "Request body too large",
+            "limit_bytes": limit,
+        })),
+    )
+        .into_response()
+}
@@ -0,0 +1,133 @@
+// CORS layer built from `Config::cors`
+//
+// Browsers reject `Access-Control-Allow-Origin: *` on credentialed requests and
+// tower-http panics when asked to combine them, so wildcard settings are
+// validated up front and reported as a configuration error instead.
+
+use axum::http::{HeaderName, HeaderValue, Method};
+use std::time::Duration;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::config::CorsConfig;
+
+pub fn cors_layer(config: &CorsConfig) -> Result<CorsLayer, String> {
+    let wildcard = |items: &[String]| items.iter().any(|i| i == "*");
+
+    if config.allow_credentials {
+        for (name, items) in [
+            ("allowed_origins", &config.allowed_origins),
+            ("allowed_methods", &config.allowed_methods),
+            ("allowed_headers", &config.allowed_headers),
+            ("exposed_headers", &config.exposed_headers),
+        ] {
+            if wildcard(items) {
+                return Err(format!(
+                    "cors.{} cannot contain '*' when cors.allow_credentials is true",
+                    name
+                ));
+            }
+        }
+    }
+
+    let origin = if wildcard(&config.allowed_origins) {
+        AllowOrigin::any()
+    } else {
+        let origins = config
+            .allowed_origins
+            .iter()
+            .map(|o| {
+                HeaderValue::from_str(o.trim_end_matches('/'))
+                    .map_err(|_| format!("Invalid CORS origin: {}", o))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        AllowOrigin::list(origins)
+    };
+
+    let mut layer = CorsLayer::new()
+        .allow_origin(origin)
+        .allow_credentials(config.allow_credentials);
+
+    layer = if wildcard(&config.allowed_methods) {
+        layer.allow_methods(tower_http::cors::Any)
+    } else {
+        let methods = config
+            .allowed_methods
+            .iter()
+            .map(|m| {
+                Method::from_bytes(m.to_ascii_uppercase().as_bytes())
+                    .map_err(|_| format!("Invalid CORS method: {}", m))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        layer.allow_methods(methods)
+    };
+
+    layer = if wildcard(&config.allowed_headers) {
+        layer.allow_headers(tower_http::cors::Any)
+    } else {
+        layer.allow_headers(parse_headers(&config.allowed_headers)?)
+    };
+
+    layer = if wildcard(&config.exposed_headers) {
+        layer.expose_headers(tower_http::cors::Any)
+    } else {
+        layer.expose_headers(parse_headers(&config.exposed_headers)?)
+    };
+
+    if config.max_age_secs > 0 {
+        layer = layer.max_age(Duration::from_secs(config.max_age_secs));
+    }
+
+    Ok(layer)
+}
+
+fn parse_headers(names: &[String]) -> Result<Vec<HeaderName>, String> {
+    names
+        .iter()
+        .map(|h| {
+            HeaderName::from_bytes(h.to_ascii_lowercase().as_bytes())
+                .map_err(|_| format!("Invalid CORS header name: {}", h))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config_builds() {
+        assert!(cors_layer(&CorsConfig::default()).is_ok());
+    }
+
+    #[test]
+    fn test_wildcard_with_credentials_rejected() {
+        let config = CorsConfig {
+            allowed_origins: vec!["*".to_string()],
+            ..Default::default()
+        };
+        let err = cors_layer(&config).unwrap_err();
+        assert!(err.contains("allowed_origins"));
+
+        let config = CorsConfig {
+            allowed_origins: vec!["*".to_string()],
+            allow_credentials: false,
+            ..Default::default()
+        };
+        assert!(cors_layer(&config).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_values_rejected() {
+        let config = CorsConfig {
+            allowed_methods: vec!["NOT A METHOD".to_string()],
+            ..Default::default()
+        };
+        assert!(cors_layer(&config).is_err());
+
+        let config = CorsConfig {
+            allowed_headers: vec!["bad header".to_string()],
+            ..Default::default()
+        };
+        assert!(cors_layer(&config).is_err());
+    }
+}
@@ -1,34 +1,137 @@
 use axum::{
     body::Body,
-    http::{Method, Request, StatusCode},
+    http::{header, request::Parts, Method, Request, StatusCode},
     middleware::Next,
     response::Response,
 };
 use rand::{distributions::Alphanumeric, Rng};
+use std::sync::Arc;
 use tower_cookies::cookie::time::Duration;
+use tower_cookies::cookie::SameSite;
 use tower_cookies::{Cookie, Cookies}; // Correct Duration type for tower-cookies
 
+use crate::config::{Config, CsrfConfig};
+use crate::features::api_management::ApiManagementService;
+use crate::features::auth::jwt::validate_jwt;
+use crate::middleware::auth::API_KEY_HEADER;
+
 pub const CSRF_COOKIE_NAME: &str = "csrf_token";
 pub const CSRF_HEADER_NAME: &str = "X-CSRF-Token";
 
-// Function to generate and set a new CSRF token cookie
-// This should be called upon successful authentication (login/register)
-pub fn set_csrf_cookie(cookies: &Cookies) {
+fn same_site(config: &CsrfConfig) -> SameSite {
+    match config.same_site.to_ascii_lowercase().as_str() {
+        "strict" => SameSite::Strict,
+        "none" => SameSite::None,
+        _ => SameSite::Lax,
+    }
+}
+
+// Function to generate and set a new CSRF token cookie, returning the token
+// This should be called upon successful authentication (login/register) and
+// whenever the session's privileges change
+pub fn set_csrf_cookie(cookies: &Cookies, config: &CsrfConfig) -> String {
     let token: String = rand::thread_rng()
         .sample_iter(&Alphanumeric)
         .take(32)
         .map(char::from)
         .collect();
 
-    let mut cookie = Cookie::new(CSRF_COOKIE_NAME, token);
+    let same_site = same_site(config);
+    let mut cookie = Cookie::new(CSRF_COOKIE_NAME, token.clone());
     cookie.set_http_only(false); // MUST be readable by frontend JS
     cookie.set_path("/");
-    cookie.set_same_site(tower_cookies::cookie::SameSite::Lax); // Match auth cookies
-    cookie.set_secure(false); // Set to true in production with HTTPS
-                              // Set expiry effectively "session" or match auth token
-    cookie.set_max_age(Duration::seconds(3600)); // 1 hour
+    cookie.set_same_site(same_site);
+    // Browsers reject SameSite=None cookies that are not Secure
+    cookie.set_secure(config.secure || same_site == SameSite::None);
+    cookie.set_max_age(Duration::seconds(config.max_age_secs));
 
     cookies.add(cookie);
+    token
+}
+
+/// The session's current token, issuing one if it has none yet
+pub fn csrf_token(cookies: &Cookies, config: &CsrfConfig) -> String {
+    match cookies.get(CSRF_COOKIE_NAME) {
+        Some(cookie) if !cookie.value().is_empty() => cookie.value().to_string(),
+        _ => set_csrf_cookie(cookies, config),
+    }
+}
+
+/// Replace the token after a privilege change (password change, firefighter
+/// elevation) so a token captured before it cannot be replayed afterwards
+pub fn rotate_csrf_token(cookies: &Cookies, config: &CsrfConfig) -> String {
+    tracing::debug!("Rotating CSRF token");
+    set_csrf_cookie(cookies, config)
+}
+
+/// Cookie `auth_middleware` authenticates from when no bearer token is sent
+const SESSION_COOKIE_NAME: &str = "access_token";
+
+fn has_session_cookie(req: &Parts) -> bool {
+    req.headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .any(|(name, value)| name == SESSION_COOKIE_NAME && !value.is_empty())
+}
+
+/// Whether the request targets an exempt path, or authenticates with a header
+/// browsers can't attach cross-site rather than the session cookie. Nothing
+/// is exempt while the session cookie is sent, and `Authorization` and
+/// `x-api-key` only count when they carry a bearer token or API key that
+/// verifies, since that is the credential `auth_middleware` will use.
+async fn is_exempt(req: &Parts, csrf: &CsrfConfig, config: Option<&Config>) -> bool {
+    let path = req.uri.path();
+    if csrf
+        .exempt_paths
+        .iter()
+        .any(|prefix| path.starts_with(prefix.as_str()))
+    {
+        return true;
+    }
+    if has_session_cookie(req) {
+        return false;
+    }
+    for name in &csrf.exempt_headers {
+        let Some(value) = req.headers.get(name.as_str()).filter(|v| !v.is_empty()) else {
+            continue;
+        };
+        let exempt = if name.eq_ignore_ascii_case(header::AUTHORIZATION.as_str()) {
+            let token = value.to_str().ok().and_then(|v| v.strip_prefix("Bearer "));
+            match (token, config) {
+                (Some(token), Some(config)) => validate_jwt(token, config).is_ok(),
+                _ => false,
+            }
+        } else if name.eq_ignore_ascii_case(API_KEY_HEADER) {
+            // A bearer token takes precedence over the key in auth_middleware
+            let api_keys = req.extensions.get::<ApiManagementService>();
+            match (value.to_str(), api_keys) {
+                (Ok(secret), Some(api_keys))
+                    if !req.headers.contains_key(header::AUTHORIZATION) =>
+                {
+                    matches!(api_keys.verify_key(secret).await, Ok(Some(_)))
+                }
+                _ => false,
+            }
+        } else {
+            true
+        };
+        if exempt {
+            return true;
+        }
+    }
+    false
+}
+
+/// Compare without exiting at the first differing byte
+fn tokens_match(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+            == 0
 }
 
 // Middleware to validate CSRF token on unsafe methods
@@ -44,6 +147,17 @@ pub async fn validate_csrf(
         return Ok(next.run(req).await);
     }
 
+    let config = req.extensions().get::<Arc<Config>>().cloned();
+    let csrf = config.as_ref().map(|c| c.csrf.clone()).unwrap_or_default();
+    // Checked on the parts: a borrowed body isn't Sync, so can't be held
+    // across the API key lookup
+    let (parts, body) = req.into_parts();
+    let exempt = is_exempt(&parts, &csrf, config.as_deref()).await;
+    let req = Request::from_parts(parts, body);
+    if exempt {
+        return Ok(next.run(req).await);
+    }
+
     // Retrieve CSRF cookie
     let cookie_token = cookies.get(CSRF_COOKIE_NAME).map(|c| c.value().to_string());
 
@@ -56,25 +170,101 @@ pub async fn validate_csrf(
 
     // Validate
     match (&cookie_token, &header_token) {
-        (Some(c), Some(h)) if c == h => {
+        (Some(c), Some(h)) if !c.is_empty() && tokens_match(c, h) => {
             // Valid match
             Ok(next.run(req).await)
         }
         _ => {
             // Missing or mismatch
             tracing::warn!(
-                "CSRF validation failed: Method={}, Uri={}, CookieTokenPresent={}, HeaderTokenPresent={}", 
+                "CSRF validation failed: Method={}, Uri={}, CookieTokenPresent={}, HeaderTokenPresent={}",
                 method,
                 req.uri(),
                 cookie_token.is_some(),
                 header_token.is_some()
             );
-            if let (Some(c), Some(h)) = (cookie_token, header_token) {
-                if c != h {
-                    tracing::debug!("CSRF mismatch: Cookie={}, Header={}", c, h);
-                }
-            }
             Err(StatusCode::FORBIDDEN)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(headers: &[(&str, &str)], path: &str) -> Parts {
+        let mut builder = Request::post(path);
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(()).unwrap().into_parts().0
+    }
+
+    #[tokio::test]
+    async fn test_exemptions() {
+        let config = CsrfConfig::default();
+        // Bearer tokens need a Config to validate against and API keys the
+        // ApiManagementService; see tests/csrf_test.rs
+        assert!(
+            !is_exempt(
+                &request(&[("authorization", "Bearer abc")], "/api/ontology"),
+                &config,
+                None
+            )
+            .await
+        );
+        assert!(
+            !is_exempt(
+                &request(&[("x-api-key", "key_123")], "/api/ontology"),
+                &config,
+                None
+            )
+            .await
+        );
+        assert!(
+            !is_exempt(
+                &request(&[("cookie", "access_token=abc")], "/api/ontology"),
+                &config,
+                None
+            )
+            .await
+        );
+
+        let config = CsrfConfig {
+            exempt_headers: vec!["x-client-cert".to_string()],
+            exempt_paths: vec!["/api/hooks".to_string()],
+            ..CsrfConfig::default()
+        };
+        assert!(is_exempt(&request(&[], "/api/hooks/github"), &config, None).await);
+        assert!(
+            is_exempt(
+                &request(&[("x-client-cert", "abc")], "/api/ontology"),
+                &config,
+                None
+            )
+            .await
+        );
+        // Not while the session cookie could be what authenticates
+        assert!(
+            !is_exempt(
+                &request(
+                    &[
+                        ("x-client-cert", "abc"),
+                        ("cookie", "theme=dark; access_token=abc")
+                    ],
+                    "/api/ontology"
+                ),
+                &config,
+                None
+            )
+            .await
+        );
+    }
+
+    #[test]
+    fn test_tokens_match() {
+        assert!(tokens_match("abc123", "abc123"));
+        assert!(!tokens_match("abc123", "abc124"));
+        assert!(!tokens_match("abc", "abc123"));
+    }
+}
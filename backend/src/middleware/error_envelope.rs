@@ -0,0 +1,205 @@
+// Error envelope middleware
+//
+// Feature errors respond with the shared envelope themselves (see
+// utils::api_error). Other error responses, such as bare status codes,
+// axum's extractor rejections and `{"error": ...}` bodies built by hand, are
+// rewritten here into the same shape. Every 4xx/5xx from the API then has a
+// `code` and a `request_id`. Streams, HTML and large bodies pass through
+// untouched.
+
+use axum::{
+    body::{to_bytes, Body, HttpBody},
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use serde_json::Value;
+
+use crate::middleware::trace_context::current_trace_id;
+use crate::utils::api_error::ApiError;
+
+/// Error bodies larger than this are left alone
+const MAX_BODY_BYTES: u64 = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BodyKind {
+    Json,
+    Text,
+}
+
+pub async fn error_envelope_middleware(request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    let status = response.status();
+    if !status.is_client_error() && !status.is_server_error() {
+        return response;
+    }
+    let Some(kind) = body_kind(response.headers()) else {
+        return response;
+    };
+    match response.body().size_hint().upper() {
+        Some(len) if len <= MAX_BODY_BYTES => {}
+        _ => return response,
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let envelope = match to_bytes(body, MAX_BODY_BYTES as usize).await {
+        Ok(bytes) => envelope(status, kind, &bytes),
+        Err(_) => ApiError::from_status(status, reason(status)).body(),
+    };
+    let Ok(bytes) = serde_json::to_vec(&envelope) else {
+        return Response::from_parts(parts, Body::empty());
+    };
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+/// How to read an error body, or None to leave it alone
+fn body_kind(headers: &HeaderMap) -> Option<BodyKind> {
+    let Some(content_type) = headers.get(header::CONTENT_TYPE) else {
+        return Some(BodyKind::Text);
+    };
+    let content_type = content_type.to_str().ok()?.to_ascii_lowercase();
+    if content_type.starts_with("application/json") {
+        Some(BodyKind::Json)
+    } else if content_type.starts_with("text/plain") {
+        Some(BodyKind::Text)
+    } else {
+        None
+    }
+}
+
+/// The envelope for an error response with body `bytes`
+fn envelope(status: StatusCode, kind: BodyKind, bytes: &[u8]) -> Value {
+    let object = match kind {
+        BodyKind::Json => serde_json::from_slice::<Value>(bytes).ok(),
+        BodyKind::Text => None,
+    };
+    let Some(Value::Object(mut fields)) = object else {
+        let text = String::from_utf8_lossy(bytes);
+        let message = match text.trim() {
+            "" => reason(status).to_string(),
+            text => text.to_string(),
+        };
+        return ApiError::from_status(status, message).body();
+    };
+
+    // Already enveloped; only make sure the request id is there
+    if matches!(
+        (fields.get("error"), fields.get("code")),
+        (Some(Value::String(_)), Some(Value::String(_)))
+    ) {
+        if let Some(request_id) = current_trace_id() {
+            fields
+                .entry("request_id")
+                .or_insert(Value::String(request_id));
+        }
+        return Value::Object(fields);
+    }
+
+    let message = match fields.remove("error") {
+        Some(Value::String(message)) => message,
+        Some(other) => {
+            fields.insert("error".to_string(), other);
+            reason(status).to_string()
+        }
+        None => reason(status).to_string(),
+    };
+    let mut error = ApiError::from_status(status, message);
+    error.details = match fields.remove("details") {
+        Some(Value::Object(details)) if fields.is_empty() => details,
+        Some(details) => {
+            fields.insert("details".to_string(), details);
+            fields
+        }
+        None => fields,
+    };
+    error.body()
+}
+
+fn reason(status: StatusCode) -> &'static str {
+    status.canonical_reason().unwrap_or("Error")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_bodies_are_enveloped() {
+        assert_eq!(
+            envelope(StatusCode::NOT_FOUND, BodyKind::Text, b""),
+            json!({ "error": "Not Found", "code": "not_found" })
+        );
+        assert_eq!(
+            envelope(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                BodyKind::Text,
+                b"Failed to deserialize the JSON body\n"
+            ),
+            json!({ "error": "Failed to deserialize the JSON body", "code": "unprocessable" })
+        );
+        assert_eq!(
+            envelope(
+                StatusCode::SERVICE_UNAVAILABLE,
+                BodyKind::Json,
+                br#"{"error":"Busy","retry_after_secs":5}"#
+            ),
+            json!({
+                "error": "Busy",
+                "code": "unavailable",
+                "details": { "retry_after_secs": 5 },
+            })
+        );
+        assert_eq!(
+            envelope(
+                StatusCode::BAD_REQUEST,
+                BodyKind::Json,
+                br#"{"errors":["name is required"]}"#
+            ),
+            json!({
+                "error": "Bad Request",
+                "code": "bad_request",
+                "details": { "errors": ["name is required"] },
+            })
+        );
+    }
+
+    #[test]
+    fn test_enveloped_bodies_are_kept() {
+        let body = json!({ "error": "Stale", "code": "ontology.version_conflict" });
+        assert_eq!(
+            envelope(
+                StatusCode::CONFLICT,
+                BodyKind::Json,
+                body.to_string().as_bytes()
+            ),
+            body
+        );
+    }
+
+    #[test]
+    fn test_only_json_and_text_are_read() {
+        let headers = |value: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(value));
+            headers
+        };
+        assert_eq!(body_kind(&HeaderMap::new()), Some(BodyKind::Text));
+        assert_eq!(
+            body_kind(&headers("application/json; charset=utf-8")),
+            Some(BodyKind::Json)
+        );
+        assert_eq!(
+            body_kind(&headers("text/plain; charset=utf-8")),
+            Some(BodyKind::Text)
+        );
+        assert_eq!(body_kind(&headers("text/html")), None);
+        assert_eq!(body_kind(&headers("text/event-stream")), None);
+    }
+}
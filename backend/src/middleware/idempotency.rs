@@ -0,0 +1,473 @@
+// Idempotency-Key support for write endpoints
+//
+// Clients may send an `Idempotency-Key` header on POST/PUT requests. The first
+// response for a key is stored per principal and returned verbatim for retries
+// within the TTL, so a retried create does not produce a duplicate entity.
+//
+// - Same key, same request, completed   -> stored response (Idempotent-Replayed: true)
+// - Same key, same request, in flight   -> 409 Conflict
+// - Same key, same request, too large   -> 409 Conflict (the response could not be stored)
+// - Same key, different request         -> 422 Unprocessable Entity
+// - Server errors (5xx) are not stored so the client can retry
+
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderName, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{Duration, Utc};
+use futures::StreamExt;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use sqlx::{PgPool, Row};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::features::auth::jwt::validate_jwt;
+use crate::utils::ip::{client_ip, parse_cidrs};
+use crate::utils::shutdown::Shutdown;
+
+pub const IDEMPOTENCY_HEADER: &str = "idempotency-key";
+pub const REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// How long a stored response is returned for retries
+pub const IDEMPOTENCY_TTL_HOURS: i64 = 24;
+/// An in-flight claim older than this is treated as abandoned (e.g. server crash)
+const IN_FLIGHT_TIMEOUT_SECS: i64 = 60;
+const MAX_KEY_LENGTH: usize = 255;
+/// Request and response bodies above this size are not handled idempotently
+const MAX_BODY_BYTES: usize = 10 * 1024 * 1024;
+
+#[derive(Clone)]
+pub struct IdempotencyStore {
+    pool: PgPool,
+}
+
+/// Outcome of trying to claim a key for a new request
+pub enum ClaimResult {
+    /// First use of the key; the request should run and its response be stored
+    Claimed,
+    /// A completed response exists for the same request
+    Completed(Response),
+    /// The original request is still being processed
+    InFlight,
+    /// The key was already used for a different request
+    Mismatch,
+    /// The request completed, but its response was too large to store
+    NotReplayable,
+}
+
+impl IdempotencyStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn claim(
+        &self,
+        principal: &str,
+        key: &str,
+        method: &str,
+        path: &str,
+        request_hash: &str,
+    ) -> Result<ClaimResult, sqlx::Error> {
+        // Take over expired entries and abandoned in-flight claims
+        let claimed = sqlx::query(
+            r#"
+            INSERT INTO idempotency_keys (principal, idempotency_key, method, path, request_hash, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (principal, idempotency_key) DO UPDATE SET
+                method = EXCLUDED.method,
+                path = EXCLUDED.path,
+                request_hash = EXCLUDED.request_hash,
+                response_status = NULL,
+                response_headers = NULL,
+                response_body = NULL,
+                replayable = TRUE,
+                created_at = NOW(),
+                expires_at = EXCLUDED.expires_at
+            WHERE idempotency_keys.expires_at <= NOW()
+               OR (idempotency_keys.response_status IS NULL
+                   AND idempotency_keys.created_at < NOW() - make_interval(secs => $7))
+            RETURNING idempotency_key
+            "#,
+        )
+        .bind(principal)
+        .bind(key)
+        .bind(method)
+        .bind(path)
+        .bind(request_hash)
+        .bind(Utc::now() + Duration::hours(IDEMPOTENCY_TTL_HOURS))
+        .bind(IN_FLIGHT_TIMEOUT_SECS as f64)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        if claimed.is_some() {
+            return Ok(ClaimResult::Claimed);
+        }
+
+        let row = sqlx::query(
+            r#"
+            SELECT request_hash, response_status, response_headers, response_body, replayable
+            FROM idempotency_keys
+            WHERE principal = $1 AND idempotency_key = $2
+            "#,
+        )
+        .bind(principal)
+        .bind(key)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        // Row vanished between the two statements (released after a failure); let the request run
+        let Some(row) = row else {
+            return Ok(ClaimResult::Claimed);
+        };
+
+        if row.get::<String, _>("request_hash") != request_hash {
+            return Ok(ClaimResult::Mismatch);
+        }
+
+        let Some(status) = row.get::<Option<i32>, _>("response_status") else {
+            return Ok(ClaimResult::InFlight);
+        };
+        if !row.get::<bool, _>("replayable") {
+            return Ok(ClaimResult::NotReplayable);
+        }
+
+        let headers: Option<serde_json::Value> = row.get("response_headers");
+        let body: Option<Vec<u8>> = row.get("response_body");
+        Ok(ClaimResult::Completed(build_response(
+            status,
+            headers,
+            body.unwrap_or_default(),
+        )))
+    }
+
+    pub async fn complete(
+        &self,
+        principal: &str,
+        key: &str,
+        status: u16,
+        headers: serde_json::Value,
+        body: &[u8],
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE idempotency_keys
+            SET response_status = $3, response_headers = $4, response_body = $5
+            WHERE principal = $1 AND idempotency_key = $2
+            "#,
+        )
+        .bind(principal)
+        .bind(key)
+        .bind(status as i32)
+        .bind(headers)
+        .bind(body)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Complete a claim without storing the response, so retries are refused
+    /// rather than run again
+    pub async fn complete_unreplayable(
+        &self,
+        principal: &str,
+        key: &str,
+        status: u16,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE idempotency_keys
+            SET response_status = $3, replayable = FALSE
+            WHERE principal = $1 AND idempotency_key = $2
+            "#,
+        )
+        .bind(principal)
+        .bind(key)
+        .bind(status as i32)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Drop an in-flight claim so the client can retry with the same key
+    pub async fn release(&self, principal: &str, key: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "DELETE FROM idempotency_keys WHERE principal = $1 AND idempotency_key = $2 AND response_status IS NULL",
+        )
+        .bind(principal)
+        .bind(key)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn purge_expired(&self) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM idempotency_keys WHERE expires_at <= NOW()")
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn start_cleanup_task(self, shutdown: Shutdown) {
+        shutdown.clone().spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(3600));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = shutdown.cancelled() => break,
+                }
+                if let Err(e) = self.purge_expired().await {
+                    tracing::error!("Failed to purge idempotency keys: {}", e);
+                }
+            }
+        });
+    }
+}
+
+pub async fn idempotency_middleware(
+    State(store): State<IdempotencyStore>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !matches!(*request.method(), Method::POST | Method::PUT) {
+        return next.run(request).await;
+    }
+    let Some(key) = request
+        .headers()
+        .get(IDEMPOTENCY_HEADER)
+        .map(|v| v.to_str().map(|s| s.trim().to_string()))
+    else {
+        return next.run(request).await;
+    };
+    let key = match key {
+        Ok(k) if !k.is_empty() && k.len() <= MAX_KEY_LENGTH => k,
+        _ => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                "Idempotency-Key must be 1-255 visible ASCII characters",
+            )
+        }
+    };
+
+    let principal = resolve_principal(&request);
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+    let path_and_query = request
+        .uri()
+        .path_and_query()
+        .map(|pq| pq.as_str().to_string())
+        .unwrap_or_else(|| path.clone());
+
+    let (parts, body) = request.into_parts();
+    let body = match to_bytes(body, MAX_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(_) => return StatusCode::PAYLOAD_TOO_LARGE.into_response(),
+    };
+    let request_hash = hash_request(&method, &path_and_query, &body);
+
+    match store
+        .claim(&principal, &key, &method, &path, &request_hash)
+        .await
+    {
+        Ok(ClaimResult::Claimed) => {}
+        Ok(ClaimResult::Completed(response)) => return response,
+        Ok(ClaimResult::InFlight) => {
+            return error_response(
+                StatusCode::CONFLICT,
+                "A request with this Idempotency-Key is still being processed",
+            )
+        }
+        Ok(ClaimResult::Mismatch) => {
+            return error_response(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Idempotency-Key was already used for a different request",
+            )
+        }
+        Ok(ClaimResult::NotReplayable) => {
+            return error_response(
+                StatusCode::CONFLICT,
+                "The request with this Idempotency-Key completed, but its response was too large to replay",
+            )
+        }
+        Err(e) => {
+            // Fail open: idempotency is a safety net, not a gate
+            tracing::error!("Idempotency lookup failed: {}", e);
+            return next.run(Request::from_parts(parts, Body::from(body))).await;
+        }
+    }
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+    let status = response.status();
+
+    let is_stream = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("text/event-stream"));
+
+    if status.is_server_error() || is_stream {
+        if let Err(e) = store.release(&principal, &key).await {
+            tracing::error!("Failed to release idempotency key: {}", e);
+        }
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let bytes = match buffer_body(body).await {
+        BufferedBody::Complete(bytes) => bytes,
+        BufferedBody::TooLarge(body) => {
+            // The write happened; send its response and refuse retries
+            if let Err(e) = store
+                .complete_unreplayable(&principal, &key, status.as_u16())
+                .await
+            {
+                tracing::error!("Failed to mark idempotency key as not replayable: {}", e);
+            }
+            return Response::from_parts(parts, body);
+        }
+        BufferedBody::Failed(e) => {
+            tracing::warn!("Failed to buffer response for idempotency: {}", e);
+            let _ = store.release(&principal, &key).await;
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    // Cookies are never replayed; a retry must not re-issue session tokens
+    let headers: Vec<(String, String)> = parts
+        .headers
+        .iter()
+        .filter(|(name, _)| *name != header::SET_COOKIE)
+        .filter_map(|(name, value)| {
+            value
+                .to_str()
+                .ok()
+                .map(|v| (name.as_str().to_string(), v.to_string()))
+        })
+        .collect();
+
+    if let Err(e) = store
+        .complete(&principal, &key, status.as_u16(), json!(headers), &bytes)
+        .await
+    {
+        tracing::error!("Failed to store idempotent response: {}", e);
+    }
+
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+/// Scope keys to the authenticated user, falling back to API key or client IP
+fn resolve_principal(request: &Request) -> String {
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|s| s.to_string())
+        .or_else(|| {
+            request
+                .extensions()
+                .get::<tower_cookies::Cookies>()
+                .and_then(|c| c.get("access_token"))
+                .map(|c| c.value().to_string())
+        });
+
+    if let (Some(token), Some(config)) = (token, request.extensions().get::<Arc<Config>>()) {
+        if let Ok(claims) = validate_jwt(&token, config) {
+            return format!("user:{}", claims.sub);
+        }
+    }
+
+    if let Some(api_key) = request
+        .headers()
+        .get("x-api-key")
+        .and_then(|v| v.to_str().ok())
+    {
+        return format!(
+            "api_key:{}",
+            hex::encode(Sha256::digest(api_key.as_bytes()))
+        );
+    }
+
+    // Forwarding headers only count from trusted proxies, or anyone could
+    // replay another client's responses by claiming its address
+    let trusted_proxies = request
+        .extensions()
+        .get::<Arc<Config>>()
+        .and_then(|config| parse_cidrs(&config.ip_access.trusted_proxies).ok())
+        .unwrap_or_default();
+    let ip = client_ip(
+        request.headers(),
+        request.extensions().get::<ConnectInfo<SocketAddr>>(),
+        &trusted_proxies,
+    )
+    .map(|ip| ip.to_string())
+    .unwrap_or_else(|| "unknown".to_string());
+    format!("ip:{}", ip)
+}
+
+/// A response body read into memory, or passed on unread
+enum BufferedBody {
+    Complete(Bytes),
+    /// Larger than `MAX_BODY_BYTES`: what was read so far followed by the rest
+    TooLarge(Body),
+    Failed(axum::Error),
+}
+
+async fn buffer_body(body: Body) -> BufferedBody {
+    let mut stream = body.into_data_stream();
+    let mut chunks: Vec<Bytes> = Vec::new();
+    let mut len = 0;
+    while let Some(chunk) = stream.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => return BufferedBody::Failed(e),
+        };
+        len += chunk.len();
+        chunks.push(chunk);
+        if len > MAX_BODY_BYTES {
+            let read = futures::stream::iter(chunks.into_iter().map(Ok));
+            return BufferedBody::TooLarge(Body::from_stream(read.chain(stream)));
+        }
+    }
+    BufferedBody::Complete(Bytes::from(chunks.concat()))
+}
+
+fn hash_request(method: &str, path_and_query: &str, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(method.as_bytes());
+    hasher.update(b" ");
+    hasher.update(path_and_query.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(body);
+    hex::encode(hasher.finalize())
+}
+
+fn build_response(status: i32, headers: Option<serde_json::Value>, body: Vec<u8>) -> Response {
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = StatusCode::from_u16(status as u16).unwrap_or(StatusCode::OK);
+
+    let pairs: Vec<(String, String)> = headers
+        .and_then(|h| serde_json::from_value(h).ok())
+        .unwrap_or_default();
+    for (name, value) in pairs {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            response.headers_mut().append(name, value);
+        }
+    }
+    response
+        .headers_mut()
+        .insert(REPLAYED_HEADER, HeaderValue::from_static("true"));
+    response
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
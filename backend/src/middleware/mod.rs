@@ -1,4 +1,13 @@
 pub mod abac;
+pub mod api_version;
+pub mod audit;
 pub mod auth;
+pub mod body_limit;
+pub mod cors;
 pub mod csrf;
+pub mod error_envelope;
+pub mod idempotency;
 pub mod rate_limit;
+pub mod route_limits;
+pub mod security_headers;
+pub mod trace_context;
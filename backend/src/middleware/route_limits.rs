@@ -0,0 +1,124 @@
+// Per-route-group timeouts and concurrency limits
+//
+// Groups come from `Config::route_limits`, matched by longest path prefix like
+// body limits. A request that finds its group at capacity is turned away with a
+// 503 rather than queued, and one whose handler runs past the group timeout gets
+// a 408; both carry a JSON error body. Limits cover the handler up to the
+// response head, so long-lived streams are not cut off once they have started.
+
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+use crate::config::RouteLimitsConfig;
+
+struct RouteGroup {
+    prefix: String,
+    timeout: Option<Duration>,
+    /// None when the group has no concurrency cap
+    permits: Option<Arc<Semaphore>>,
+}
+
+impl RouteGroup {
+    fn new(prefix: String, timeout_secs: u64, max_concurrent: usize) -> Self {
+        Self {
+            prefix,
+            timeout: (timeout_secs > 0).then(|| Duration::from_secs(timeout_secs)),
+            permits: (max_concurrent > 0).then(|| Arc::new(Semaphore::new(max_concurrent))),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RouteLimits {
+    default: Arc<RouteGroup>,
+    /// Sorted longest prefix first so the most specific group wins
+    groups: Arc<Vec<RouteGroup>>,
+}
+
+impl RouteLimits {
+    pub fn from_config(config: &RouteLimitsConfig) -> Self {
+        let mut groups: Vec<RouteGroup> = config
+            .routes
+            .iter()
+            .map(|r| RouteGroup::new(r.prefix.clone(), r.timeout_secs, r.max_concurrent))
+            .collect();
+        groups.sort_by_key(|g| std::cmp::Reverse(g.prefix.len()));
+
+        Self {
+            default: Arc::new(RouteGroup::new(
+                String::new(),
+                config.default_timeout_secs,
+                config.default_max_concurrent,
+            )),
+            groups: Arc::new(groups),
+        }
+    }
+
+    fn group_for(&self, path: &str) -> &RouteGroup {
+        self.groups
+            .iter()
+            .find(|g| path.starts_with(g.prefix.as_str()))
+            .unwrap_or(&self.default)
+    }
+
+    /// Timeout applied to `path`, if any
+    pub fn timeout_for(&self, path: &str) -> Option<Duration> {
+        self.group_for(path).timeout
+    }
+}
+
+pub async fn route_limits_middleware(
+    State(limits): State<RouteLimits>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let group = limits.group_for(request.uri().path());
+
+    // Held until the response head is ready
+    let _permit = match &group.permits {
+        Some(permits) => match permits.clone().try_acquire_owned() {
+            Ok(permit) => Some(permit),
+            Err(_) => return server_busy(),
+        },
+        None => None,
+    };
+
+    match group.timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, next.run(request)).await {
+            Ok(response) => response,
+            Err(_) => request_timeout(timeout),
+        },
+        None => next.run(request).await,
+    }
+}
+
+fn server_busy() -> Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(header::RETRY_AFTER, "1")],
+        Json(json!({
+            "error": "Server is busy, please retry shortly",
+        })),
+    )
+        .into_response()
+}
+
+fn request_timeout(timeout: Duration) -> Response {
+    (
+        StatusCode::REQUEST_TIMEOUT,
+        Json(json!({
+            "error": "Request timed out",
+            "timeout_secs": timeout.as_secs(),
+        })),
+    )
+        .into_response()
+}
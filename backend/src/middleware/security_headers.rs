@@ -0,0 +1,158 @@
+// Security headers middleware
+//
+// The API is consumed by browsers with cookie auth, so responses carry the
+// usual hardening headers. Values come from `Config::security_headers` and are
+// validated once at startup. Headers a handler sets explicitly are left alone.
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+
+use crate::config::SecurityHeadersConfig;
+
+#[derive(Clone)]
+pub struct SecurityHeaders {
+    headers: Arc<Vec<(HeaderName, HeaderValue)>>,
+}
+
+impl SecurityHeaders {
+    pub fn from_config(config: &SecurityHeadersConfig) -> Result<Self, String> {
+        let mut headers = Vec::new();
+        if !config.enabled {
+            return Ok(Self {
+                headers: Arc::new(headers),
+            });
+        }
+
+        let mut csp = config.content_security_policy.trim().to_string();
+        let frame_ancestors = config.frame_ancestors.trim();
+        if !frame_ancestors.is_empty() && !csp.contains("frame-ancestors") {
+            if !csp.is_empty() && !csp.ends_with(';') {
+                csp.push(';');
+            }
+            if !csp.is_empty() {
+                csp.push(' ');
+            }
+            csp.push_str(&format!("frame-ancestors {}", frame_ancestors));
+        }
+        if !csp.is_empty() {
+            headers.push((header::CONTENT_SECURITY_POLICY, parse("CSP", &csp)?));
+        }
+
+        // Legacy equivalent of frame-ancestors 'none' for older browsers
+        if frame_ancestors == "'none'" {
+            headers.push((header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY")));
+        }
+
+        if config.hsts_max_age > 0 {
+            let mut hsts = format!("max-age={}", config.hsts_max_age);
+            if config.hsts_include_subdomains {
+                hsts.push_str("; includeSubDomains");
+            }
+            headers.push((header::STRICT_TRANSPORT_SECURITY, parse("HSTS", &hsts)?));
+        }
+
+        headers.push((
+            header::X_CONTENT_TYPE_OPTIONS,
+            HeaderValue::from_static("nosniff"),
+        ));
+
+        if !config.referrer_policy.trim().is_empty() {
+            headers.push((
+                header::REFERRER_POLICY,
+                parse("Referrer-Policy", config.referrer_policy.trim())?,
+            ));
+        }
+
+        Ok(Self {
+            headers: Arc::new(headers),
+        })
+    }
+}
+
+fn parse(name: &str, value: &str) -> Result<HeaderValue, String> {
+    HeaderValue::from_str(value).map_err(|e| format!("Invalid {} header value: {}", name, e))
+}
+
+pub async fn security_headers_middleware(
+    State(security_headers): State<SecurityHeaders>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    for (name, value) in security_headers.headers.iter() {
+        if !headers.contains_key(name) {
+            headers.insert(name.clone(), value.clone());
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header_value<'a>(h: &'a SecurityHeaders, name: &HeaderName) -> Option<&'a str> {
+        h.headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .and_then(|(_, v)| v.to_str().ok())
+    }
+
+    #[test]
+    fn test_default_headers() {
+        let h = SecurityHeaders::from_config(&SecurityHeadersConfig::default()).unwrap();
+        assert_eq!(
+            header_value(&h, &header::CONTENT_SECURITY_POLICY),
+            Some("default-src 'none'; base-uri 'none'; frame-ancestors 'none'")
+        );
+        assert_eq!(header_value(&h, &header::X_FRAME_OPTIONS), Some("DENY"));
+        assert_eq!(
+            header_value(&h, &header::STRICT_TRANSPORT_SECURITY),
+            Some("max-age=31536000")
+        );
+        assert_eq!(
+            header_value(&h, &header::X_CONTENT_TYPE_OPTIONS),
+            Some("nosniff")
+        );
+        assert_eq!(
+            header_value(&h, &header::REFERRER_POLICY),
+            Some("no-referrer")
+        );
+    }
+
+    #[test]
+    fn test_custom_and_disabled_headers() {
+        let config = SecurityHeadersConfig {
+            frame_ancestors: "https://admin.example.com".to_string(),
+            hsts_max_age: 0,
+            ..Default::default()
+        };
+        let h = SecurityHeaders::from_config(&config).unwrap();
+        assert!(header_value(&h, &header::CONTENT_SECURITY_POLICY)
+            .unwrap()
+            .ends_with("frame-ancestors https://admin.example.com"));
+        assert!(header_value(&h, &header::X_FRAME_OPTIONS).is_none());
+        assert!(header_value(&h, &header::STRICT_TRANSPORT_SECURITY).is_none());
+
+        let disabled = SecurityHeadersConfig {
+            enabled: false,
+            ..Default::default()
+        };
+        assert!(SecurityHeaders::from_config(&disabled)
+            .unwrap()
+            .headers
+            .is_empty());
+
+        let invalid = SecurityHeadersConfig {
+            referrer_policy: "bad\nvalue".to_string(),
+            ..Default::default()
+        };
+        assert!(SecurityHeaders::from_config(&invalid).is_err());
+    }
+}
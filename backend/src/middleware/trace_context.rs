@@ -0,0 +1,125 @@
+// Trace context middleware
+//
+// Every request runs with a trace id: the one in an incoming W3C `traceparent`
+// header, so records can be joined with the caller's traces, or a new one.
+// Code deeper in the request (e.g. permission checks writing decision logs)
+// reads it with `current_trace_id`, and log lines carry it through the
+// request span. The id is returned in `X-Trace-Id`.
+
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use tracing::Instrument;
+
+pub const TRACEPARENT: HeaderName = HeaderName::from_static("traceparent");
+pub const X_TRACE_ID: HeaderName = HeaderName::from_static("x-trace-id");
+
+tokio::task_local! {
+    static TRACE_ID: String;
+}
+
+/// Trace id of the request being handled, if any. Not inherited by tasks
+/// spawned from the request.
+pub fn current_trace_id() -> Option<String> {
+    TRACE_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Run `future` with `trace_id` as the current trace id
+pub async fn with_trace_id<F: std::future::Future>(trace_id: String, future: F) -> F::Output {
+    TRACE_ID.scope(trace_id, future).await
+}
+
+/// The trace id of a `traceparent` header value
+/// (`version-traceid-parentid-flags`), if it is well formed
+pub fn parse_traceparent(value: &str) -> Option<String> {
+    let mut parts = value.trim().split('-');
+    let version = parts.next()?;
+    let trace_id = parts.next()?;
+    let parent_id = parts.next()?;
+    let flags = parts.next()?;
+    let is_hex = |s: &str, len: usize| {
+        s.len() == len && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+    };
+    if !is_hex(version, 2) || version == "ff" || !is_hex(flags, 2) {
+        return None;
+    }
+    // Version 00 has exactly four fields; later versions may append more
+    if version == "00" && parts.next().is_some() {
+        return None;
+    }
+    if !is_hex(trace_id, 32) || trace_id.bytes().all(|b| b == b'0') {
+        return None;
+    }
+    if !is_hex(parent_id, 16) || parent_id.bytes().all(|b| b == b'0') {
+        return None;
+    }
+    Some(trace_id.to_string())
+}
+
+pub fn new_trace_id() -> String {
+    hex::encode(rand::random::<[u8; 16]>())
+}
+
+pub async fn trace_context_middleware(request: Request, next: Next) -> Response {
+    let trace_id = request
+        .headers()
+        .get(TRACEPARENT)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_traceparent)
+        .unwrap_or_else(new_trace_id);
+
+    let span = tracing::info_span!(
+        "request",
+        trace_id = %trace_id,
+        method = %request.method(),
+        path = %request.uri().path(),
+    );
+    let mut response = with_trace_id(trace_id.clone(), next.run(request).instrument(span)).await;
+    if let Ok(value) = HeaderValue::from_str(&trace_id) {
+        response.headers_mut().insert(X_TRACE_ID, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_traceparent() {
+        assert_eq!(
+            parse_traceparent("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01").as_deref(),
+            Some("4bf92f3577b34da6a3ce929d0e0e4736")
+        );
+        // Future versions may carry extra fields
+        assert!(
+            parse_traceparent("01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra")
+                .is_some()
+        );
+        for invalid in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6-00f067aa0ba902b7-01",
+        ] {
+            assert_eq!(parse_traceparent(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_trace_id_is_scoped_to_the_request() {
+        assert_eq!(current_trace_id(), None);
+        let id = new_trace_id();
+        assert_eq!(id.len(), 32);
+        let seen = with_trace_id(id.clone(), async { current_trace_id() }).await;
+        assert_eq!(seen, Some(id));
+        assert_eq!(current_trace_id(), None);
+    }
+}
@@ -0,0 +1,461 @@
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+/// Roles given out by the dataset; user n holds `ROLES[n % 2]`, scoped to
+/// folder `n % folders`
+pub const ROLES: [&str; 2] = ["perf_reader", "perf_editor"];
+
+/// (name, level) of the permission types the roles grant; perf_editor's
+/// perf.update covers perf.read through its higher level
+pub const PERMISSIONS: [(&str, i32); 2] = [("perf.read", 10), ("perf.update", 20)];
+
+/// Class of the entities the benchmarks check and create
+pub const ASSET_CLASS: &str = "PerfAsset";
+const FOLDER_CLASS: &str = "PerfFolder";
+
+/// (name, data type, required, validation rules) of the asset class's
+/// properties; the rules make entity validation do real work
+const ASSET_PROPERTIES: [(&str, &str, bool, &str); 3] = [
+    ("serial", "string", true, r#"{"regex": "^[A-Z0-9]{12}$"}"#),
+    ("weight_kg", "number", false, r#"{"min": 0, "max": 1000}"#),
+    (
+        "status",
+        "string",
+        false,
+        r#"{"options": ["active", "retired"]}"#,
+    ),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatasetSize {
+    pub users: usize,
+    pub folders: usize,
+    pub assets_per_folder: usize,
+}
+
+impl Default for DatasetSize {
+    fn default() -> Self {
+        Self {
+            users: 200,
+            folders: 50,
+            assets_per_folder: 100,
+        }
+    }
+}
+
+impl DatasetSize {
+    /// Defaults, overridden by PERF_USERS, PERF_FOLDERS and
+    /// PERF_ASSETS_PER_FOLDER
+    pub fn from_env() -> Result<Self, String> {
+        let defaults = Self::default();
+        let size = Self {
+            users: env_count("PERF_USERS", defaults.users)?,
+            folders: env_count("PERF_FOLDERS", defaults.folders)?,
+            assets_per_folder: env_count("PERF_ASSETS_PER_FOLDER", defaults.assets_per_folder)?,
+        };
+        if size.users == 0 || size.folders == 0 {
+            return Err("PERF_USERS and PERF_FOLDERS must be at least 1".to_string());
+        }
+        Ok(size)
+    }
+
+    /// Names the dataset; datasets of different sizes live side by side
+    pub fn tag(&self) -> String {
+        format!(
+            "perf-{}u-{}f-{}a",
+            self.users, self.folders, self.assets_per_folder
+        )
+    }
+}
+
+fn env_count(name: &str, default: usize) -> Result<usize, String> {
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .map_err(|_| format!("{} must be a number, got '{}'", name, value)),
+        Err(_) => Ok(default),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PerfUser {
+    pub id: Uuid,
+    pub username: String,
+    pub email: String,
+}
+
+/// A seeded dataset: users holding scoped roles on folders of assets
+#[derive(Debug, Clone)]
+pub struct Dataset {
+    pub tag: String,
+    pub asset_class_id: Uuid,
+    pub users: Vec<PerfUser>,
+    pub folders: Vec<Uuid>,
+    /// Folder by folder, `assets_per_folder` each
+    pub assets: Vec<Uuid>,
+    assets_per_folder: usize,
+}
+
+impl Dataset {
+    /// The dataset of this size, seeded first if the database doesn't have
+    /// it yet. Seeding runs in one transaction, so an interrupted run leaves
+    /// nothing behind.
+    pub async fn ensure(pool: &PgPool, size: &DatasetSize) -> Result<Self, sqlx::Error> {
+        let tag = size.tag();
+        let mut tx = pool.begin().await?;
+        // Another process seeding the same dataset waits for it to finish
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+            .bind(&tag)
+            .execute(&mut *tx)
+            .await?;
+
+        let seed = Seed::load(&mut tx, &tag).await?;
+        let seeded = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM entities WHERE class_id = $1 AND attributes->>'perf_dataset' = $2)",
+        )
+        .bind(seed.folder_class_id)
+        .bind(&tag)
+        .fetch_one(&mut *tx)
+        .await?;
+        if !seeded {
+            seed.run(&mut tx, size).await?;
+        }
+        let dataset = seed.dataset(&mut tx, size).await?;
+        tx.commit().await?;
+        Ok(dataset)
+    }
+
+    /// The folder whose assets user `n` holds a role on
+    pub fn scope_of(&self, user: usize) -> Uuid {
+        self.folders[user % self.folders.len()]
+    }
+
+    /// The role user `n` holds
+    pub fn role_of(&self, user: usize) -> &'static str {
+        ROLES[user % ROLES.len()]
+    }
+
+    /// Assets in the folder user `n` holds a role on
+    pub fn assets_of(&self, user: usize) -> &[Uuid] {
+        let folder = user % self.folders.len();
+        &self.assets[folder * self.assets_per_folder..(folder + 1) * self.assets_per_folder]
+    }
+}
+
+/// Ids looked up or created while seeding
+struct Seed {
+    tag: String,
+    user_class_id: Uuid,
+    folder_class_id: Uuid,
+    asset_class_id: Uuid,
+    has_role_type_id: Uuid,
+    roles: Vec<Uuid>,
+}
+
+impl Seed {
+    /// Classes, permission types and roles shared by every dataset, created
+    /// if missing
+    async fn load(tx: &mut Transaction<'_, Postgres>, tag: &str) -> Result<Self, sqlx::Error> {
+        let system_class = |name: &'static str| {
+            sqlx::query_scalar::<_, Uuid>(
+                r#"
+                SELECT c.id FROM classes c
+                JOIN ontology_versions ov ON ov.id = c.version_id
+                WHERE ov.is_system AND c.name = $1
+                "#,
+            )
+            .bind(name)
+        };
+        let user_class_id = system_class("User").fetch_one(&mut **tx).await?;
+        let role_class_id = system_class("Role").fetch_one(&mut **tx).await?;
+        let permission_class_id = system_class("Permission").fetch_one(&mut **tx).await?;
+        let relationship_type = |name: &'static str| {
+            sqlx::query_scalar::<_, Uuid>("SELECT id FROM relationship_types WHERE name = $1")
+                .bind(name)
+        };
+        let has_role_type_id = relationship_type("has_role").fetch_one(&mut **tx).await?;
+        let grants_type_id = relationship_type("grants_permission")
+            .fetch_one(&mut **tx)
+            .await?;
+
+        let version_id = sqlx::query_scalar::<_, Uuid>(
+            "SELECT id FROM ontology_versions WHERE is_current = TRUE",
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+        let folder_class_id =
+            ensure_class(tx, FOLDER_CLASS, "Groups perf assets", version_id).await?;
+        let asset_class_id =
+            ensure_class(tx, ASSET_CLASS, "Entity used by benchmarks", version_id).await?;
+        for (name, data_type, required, rules) in ASSET_PROPERTIES {
+            sqlx::query(
+                r#"
+                INSERT INTO properties (name, class_id, data_type, is_required,
+                                        validation_rules, version_id)
+                SELECT $1, $2, $3, $4, $5::jsonb, $6
+                WHERE NOT EXISTS (SELECT 1 FROM properties WHERE class_id = $2 AND name = $1)
+                "#,
+            )
+            .bind(name)
+            .bind(asset_class_id)
+            .bind(data_type)
+            .bind(required)
+            .bind(rules)
+            .bind(version_id)
+            .execute(&mut **tx)
+            .await?;
+        }
+
+        let mut roles = Vec::new();
+        for (role, (permission, level)) in ROLES.iter().zip(PERMISSIONS) {
+            let permission_id = ensure_entity(
+                tx,
+                permission_class_id,
+                permission,
+                serde_json::json!({ "name": permission, "level": level }),
+            )
+            .await?;
+            let role_id = ensure_entity(
+                tx,
+                role_class_id,
+                role,
+                serde_json::json!({ "name": role, "level": level }),
+            )
+            .await?;
+            sqlx::query(
+                r#"
+                INSERT INTO relationships (source_entity_id, target_entity_id, relationship_type_id)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (source_entity_id, target_entity_id, relationship_type_id) DO NOTHING
+                "#,
+            )
+            .bind(role_id)
+            .bind(permission_id)
+            .bind(grants_type_id)
+            .execute(&mut **tx)
+            .await?;
+            roles.push(role_id);
+        }
+
+        Ok(Self {
+            tag: tag.to_string(),
+            user_class_id,
+            folder_class_id,
+            asset_class_id,
+            has_role_type_id,
+            roles,
+        })
+    }
+
+    async fn run(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        size: &DatasetSize,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO entities (class_id, display_name, attributes, approval_status)
+            SELECT $1, $2 || '-user-' || n,
+                   jsonb_build_object(
+                       'username', $2 || '-user-' || n,
+                       'email', $2 || '-user-' || n || '@perf.example',
+                       'custom_attributes', '{}'::jsonb,
+                       'perf_dataset', $2,
+                       'n', n
+                   ),
+                   'APPROVED'
+            FROM generate_series(0, $3 - 1) AS n
+            "#,
+        )
+        .bind(self.user_class_id)
+        .bind(&self.tag)
+        .bind(size.users as i32)
+        .execute(&mut **tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO entities (class_id, display_name, attributes, approval_status)
+            SELECT $1, $2 || '-folder-' || n,
+                   jsonb_build_object('perf_dataset', $2, 'n', n),
+                   'APPROVED'
+            FROM generate_series(0, $3 - 1) AS n
+            "#,
+        )
+        .bind(self.folder_class_id)
+        .bind(&self.tag)
+        .bind(size.folders as i32)
+        .execute(&mut **tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO entities (class_id, display_name, attributes, parent_entity_id, approval_status)
+            SELECT $1, $2 || '-asset-' || f.n || '-' || a,
+                   jsonb_build_object(
+                       'serial', upper(substr(md5($2 || f.n || '-' || a), 1, 12)),
+                       'weight_kg', a % 50 + 1,
+                       'status', CASE WHEN a % 3 = 0 THEN 'retired' ELSE 'active' END,
+                       'perf_dataset', $2,
+                       'n', f.n * $3 + a
+                   ),
+                   f.id,
+                   'APPROVED'
+            FROM (
+                SELECT id, (attributes->>'n')::integer AS n FROM entities
+                WHERE class_id = $4 AND attributes->>'perf_dataset' = $2
+            ) f
+            CROSS JOIN generate_series(0, $3 - 1) AS a
+            "#,
+        )
+        .bind(self.asset_class_id)
+        .bind(&self.tag)
+        .bind(size.assets_per_folder as i32)
+        .bind(self.folder_class_id)
+        .execute(&mut **tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO relationships (source_entity_id, target_entity_id, relationship_type_id, metadata)
+            SELECT u.id, ($4::uuid[])[(u.n % 2) + 1], $5,
+                   jsonb_build_object('scope_entity_id', f.id, 'is_deny', false)
+            FROM (
+                SELECT id, (attributes->>'n')::integer AS n FROM entities
+                WHERE class_id = $1 AND attributes->>'perf_dataset' = $2
+            ) u
+            JOIN (
+                SELECT id, (attributes->>'n')::integer AS n FROM entities
+                WHERE class_id = $3 AND attributes->>'perf_dataset' = $2
+            ) f ON f.n = u.n % $6
+            "#,
+        )
+        .bind(self.user_class_id)
+        .bind(&self.tag)
+        .bind(self.folder_class_id)
+        .bind(&self.roles)
+        .bind(self.has_role_type_id)
+        .bind(size.folders as i32)
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    async fn dataset(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        size: &DatasetSize,
+    ) -> Result<Dataset, sqlx::Error> {
+        let users = sqlx::query_as::<_, (Uuid, String, String)>(
+            r#"
+            SELECT id, attributes->>'username', attributes->>'email' FROM entities
+            WHERE class_id = $1 AND attributes->>'perf_dataset' = $2
+            ORDER BY (attributes->>'n')::integer
+            "#,
+        )
+        .bind(self.user_class_id)
+        .bind(&self.tag)
+        .fetch_all(&mut **tx)
+        .await?
+        .into_iter()
+        .map(|(id, username, email)| PerfUser {
+            id,
+            username,
+            email,
+        })
+        .collect();
+        let ids_of = |class_id: Uuid| {
+            sqlx::query_scalar::<_, Uuid>(
+                r#"
+                SELECT id FROM entities
+                WHERE class_id = $1 AND attributes->>'perf_dataset' = $2
+                ORDER BY (attributes->>'n')::integer
+                "#,
+            )
+            .bind(class_id)
+            .bind(&self.tag)
+        };
+        let folders = ids_of(self.folder_class_id).fetch_all(&mut **tx).await?;
+        let assets = ids_of(self.asset_class_id).fetch_all(&mut **tx).await?;
+        Ok(Dataset {
+            tag: self.tag.clone(),
+            asset_class_id: self.asset_class_id,
+            users,
+            folders,
+            assets,
+            assets_per_folder: size.assets_per_folder,
+        })
+    }
+}
+
+async fn ensure_class(
+    tx: &mut Transaction<'_, Postgres>,
+    name: &str,
+    description: &str,
+    version_id: Uuid,
+) -> Result<Uuid, sqlx::Error> {
+    let existing = sqlx::query_scalar::<_, Uuid>(
+        "SELECT id FROM classes WHERE name = $1 AND version_id = $2 AND tenant_id IS NULL",
+    )
+    .bind(name)
+    .bind(version_id)
+    .fetch_optional(&mut **tx)
+    .await?;
+    if let Some(id) = existing {
+        return Ok(id);
+    }
+    sqlx::query_scalar::<_, Uuid>(
+        "INSERT INTO classes (name, description, version_id) VALUES ($1, $2, $3) RETURNING id",
+    )
+    .bind(name)
+    .bind(description)
+    .bind(version_id)
+    .fetch_one(&mut **tx)
+    .await
+}
+
+async fn ensure_entity(
+    tx: &mut Transaction<'_, Postgres>,
+    class_id: Uuid,
+    display_name: &str,
+    attributes: serde_json::Value,
+) -> Result<Uuid, sqlx::Error> {
+    let existing = sqlx::query_scalar::<_, Uuid>(
+        "SELECT id FROM entities WHERE class_id = $1 AND display_name = $2 AND deleted_at IS NULL",
+    )
+    .bind(class_id)
+    .bind(display_name)
+    .fetch_optional(&mut **tx)
+    .await?;
+    if let Some(id) = existing {
+        return Ok(id);
+    }
+    sqlx::query_scalar::<_, Uuid>(
+        r#"
+        INSERT INTO entities (class_id, display_name, attributes, approval_status)
+        VALUES ($1, $2, $3, 'APPROVED')
+        RETURNING id
+        "#,
+    )
+    .bind(class_id)
+    .bind(display_name)
+    .bind(attributes)
+    .fetch_one(&mut **tx)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sizes_get_their_own_tag() {
+        let small = DatasetSize {
+            users: 2,
+            folders: 1,
+            assets_per_folder: 3,
+        };
+        assert_eq!(small.tag(), "perf-2u-1f-3a");
+        assert_ne!(small.tag(), DatasetSize::default().tag());
+    }
+}
@@ -0,0 +1,782 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use reqwest::{Client, Method, RequestBuilder};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::cli::Options;
+
+pub const USAGE: &str = "\
+Usage: ontology-loadgen [options]
+
+Drives mixed traffic against a running instance and reports latency
+percentiles per operation.
+
+Options:
+  --url <url>              Server to load (default http://127.0.0.1:5300)
+  --concurrency <n>        Parallel workers (default 16)
+  --duration <secs>        How long to run (default 60)
+  --mix <op=weight,...>    Relative weight of each operation; operations left
+                           out are not run. Operations: login, create, read,
+                           update, delete, check, search.
+                           (default login=1,create=10,read=40,update=10,
+                           delete=5,check=25,search=10)
+  --user <identifier:password>
+                           Log in as this user. Repeat for more users; workers
+                           share them round robin.
+  --register <n>           Without --user, register this many throwaway users
+                           first (default: one per worker)
+  --class-id <id>          Class of the entities to create. Without it a
+                           LoadgenItem class is found or created.
+  --permission <name>      Permission the check operation asks about
+                           (default read)
+  --json <file>            Also write the report as JSON
+
+Login and registration go through the /api/auth rate limits. Raise them on
+the target, or keep the login weight low, so the run doesn't measure 429s.
+Entities a worker created and didn't delete are removed when the run ends.
+";
+
+const DEFAULT_URL: &str = "http://127.0.0.1:5300";
+const CLASS_NAME: &str = "LoadgenItem";
+const SEARCH_TERM: &str = "loadgen";
+
+/// One kind of request a worker sends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Operation {
+    Login,
+    Create,
+    Read,
+    Update,
+    Delete,
+    Check,
+    Search,
+}
+
+impl Operation {
+    pub const ALL: [Operation; 7] = [
+        Operation::Login,
+        Operation::Create,
+        Operation::Read,
+        Operation::Update,
+        Operation::Delete,
+        Operation::Check,
+        Operation::Search,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Operation::Login => "login",
+            Operation::Create => "create",
+            Operation::Read => "read",
+            Operation::Update => "update",
+            Operation::Delete => "delete",
+            Operation::Check => "check",
+            Operation::Search => "search",
+        }
+    }
+
+    fn parse(name: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|op| op.name() == name)
+            .ok_or_else(|| format!("Unknown operation '{}'", name))
+    }
+}
+
+/// Relative weights of the operations in a run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mix {
+    weights: Vec<(Operation, u32)>,
+}
+
+impl Default for Mix {
+    fn default() -> Self {
+        Self {
+            weights: vec![
+                (Operation::Login, 1),
+                (Operation::Create, 10),
+                (Operation::Read, 40),
+                (Operation::Update, 10),
+                (Operation::Delete, 5),
+                (Operation::Check, 25),
+                (Operation::Search, 10),
+            ],
+        }
+    }
+}
+
+impl Mix {
+    /// Parse `op=weight` pairs separated by commas
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut weights: Vec<(Operation, u32)> = Vec::new();
+        for pair in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (name, weight) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("Expected op=weight, got '{}'", pair))?;
+            let op = Operation::parse(name.trim())?;
+            let weight = weight
+                .trim()
+                .parse()
+                .map_err(|_| format!("Weight of {} must be a whole number", name.trim()))?;
+            if weights.iter().any(|(o, _)| *o == op) {
+                return Err(format!("{} is given twice", op.name()));
+            }
+            weights.push((op, weight));
+        }
+        weights.retain(|(_, w)| *w > 0);
+        if weights.is_empty() {
+            return Err("The mix has no operation with a weight above 0".to_string());
+        }
+        Ok(Self { weights })
+    }
+
+    pub fn pick(&self, rng: &mut impl Rng) -> Operation {
+        let total: u32 = self.weights.iter().map(|(_, w)| w).sum();
+        let mut roll = rng.gen_range(0..total);
+        for (op, weight) in &self.weights {
+            if roll < *weight {
+                return *op;
+            }
+            roll -= weight;
+        }
+        unreachable!("roll is below the total weight")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub identifier: String,
+    pub password: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadConfig {
+    pub base_url: String,
+    pub concurrency: usize,
+    pub duration: Duration,
+    pub mix: Mix,
+    pub users: Vec<Credentials>,
+    pub register: usize,
+    pub class_id: Option<Uuid>,
+    pub permission: String,
+    pub json_output: Option<String>,
+}
+
+impl LoadConfig {
+    /// Parse the arguments after the program name
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Options::parse(args)?;
+        let concurrency = number(&mut options, "concurrency")?.unwrap_or(16);
+        if concurrency == 0 {
+            return Err("--concurrency must be at least 1".to_string());
+        }
+        let mut users = Vec::new();
+        while let Some(user) = options.take("user")? {
+            let (identifier, password) = user
+                .split_once(':')
+                .ok_or_else(|| "--user must be identifier:password".to_string())?;
+            users.push(Credentials {
+                identifier: identifier.to_string(),
+                password: password.to_string(),
+            });
+        }
+        let register = number(&mut options, "register")?;
+        if register.is_some() && !users.is_empty() {
+            return Err("--register and --user can't be combined".to_string());
+        }
+        let config = Self {
+            base_url: options
+                .take("url")?
+                .unwrap_or_else(|| DEFAULT_URL.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            concurrency,
+            duration: Duration::from_secs(number(&mut options, "duration")?.unwrap_or(60) as u64),
+            mix: match options.take("mix")? {
+                Some(spec) => Mix::parse(&spec)?,
+                None => Mix::default(),
+            },
+            register: if users.is_empty() {
+                register.unwrap_or(concurrency).max(1)
+            } else {
+                0
+            },
+            users,
+            class_id: options.uuid("class-id")?,
+            permission: options
+                .take("permission")?
+                .unwrap_or_else(|| "read".to_string()),
+            json_output: options.take("json")?,
+        };
+        options.finish()?;
+        Ok(config)
+    }
+}
+
+fn number(options: &mut Options, name: &str) -> Result<Option<usize>, String> {
+    options
+        .take(name)?
+        .map(|v| {
+            v.parse()
+                .map_err(|_| format!("--{} must be a whole number", name))
+        })
+        .transpose()
+}
+
+/// Outcome of one request. `status` is None when no response came back.
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    op: Operation,
+    status: Option<u16>,
+    micros: u64,
+}
+
+impl Sample {
+    fn is_error(&self) -> bool {
+        !matches!(self.status, Some(s) if s < 400)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Session {
+    credentials: Credentials,
+    user_id: Uuid,
+    token: String,
+}
+
+#[derive(Deserialize)]
+struct LoginResponse {
+    access_token: Option<String>,
+    user_id: Uuid,
+}
+
+#[derive(Deserialize)]
+struct Created {
+    id: Uuid,
+}
+
+#[derive(Deserialize)]
+struct ClassSummary {
+    id: Uuid,
+    name: String,
+}
+
+/// Run the load described by `config` and summarize it
+pub async fn run(config: &LoadConfig) -> Result<Report, String> {
+    let client = Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+    let api = format!("{}/api", config.base_url);
+
+    let credentials = if config.users.is_empty() {
+        register_users(&client, &api, config.register).await?
+    } else {
+        config.users.clone()
+    };
+    let mut sessions = Vec::with_capacity(credentials.len());
+    for credentials in credentials {
+        let (status, session) = login(&client, &api, &credentials).await;
+        let session = session.ok_or_else(|| {
+            format!(
+                "Login as {} failed ({})",
+                credentials.identifier,
+                status.map_or("no response".to_string(), |s| s.to_string())
+            )
+        })?;
+        sessions.push(session);
+    }
+    let class_id = match config.class_id {
+        Some(id) => id,
+        None => loadgen_class(&client, &api, &sessions[0].token).await?,
+    };
+
+    let started = Instant::now();
+    let deadline = started + config.duration;
+    let workers = (0..config.concurrency)
+        .map(|n| {
+            let worker = Worker {
+                client: client.clone(),
+                api: api.clone(),
+                session: sessions[n % sessions.len()].clone(),
+                class_id,
+                permission: config.permission.clone(),
+                owned: Vec::new(),
+                created: 0,
+                rng: StdRng::from_entropy(),
+                samples: Vec::new(),
+            };
+            let mix = config.mix.clone();
+            tokio::spawn(worker.run(mix, deadline))
+        })
+        .collect::<Vec<_>>();
+    let mut samples = Vec::new();
+    for worker in workers {
+        samples.extend(worker.await.map_err(|e| format!("Worker failed: {}", e))?);
+    }
+    Ok(Report::new(&samples, started.elapsed(), config.concurrency))
+}
+
+async fn register_users(
+    client: &Client,
+    api: &str,
+    count: usize,
+) -> Result<Vec<Credentials>, String> {
+    let run = Uuid::new_v4().simple().to_string()[..8].to_string();
+    let mut users = Vec::with_capacity(count);
+    for n in 0..count {
+        let username = format!("loadgen_{}_{}", run, n);
+        let password = format!("Lg-{}!", Uuid::new_v4().simple());
+        let response = client
+            .post(format!("{}/auth/register", api))
+            .json(&json!({
+                "username": username,
+                "email": format!("{}@loadgen.example.com", username),
+                "password": password,
+            }))
+            .send()
+            .await
+            .map_err(|e| format!("Failed to register {}: {}", username, e))?;
+        if !response.status().is_success() {
+            return Err(format!(
+                "Failed to register {}: {}",
+                username,
+                response.status()
+            ));
+        }
+        users.push(Credentials {
+            identifier: username,
+            password,
+        });
+    }
+    Ok(users)
+}
+
+async fn login(
+    client: &Client,
+    api: &str,
+    credentials: &Credentials,
+) -> (Option<u16>, Option<Session>) {
+    let request = client.post(format!("{}/auth/login", api)).json(&json!({
+        "identifier": credentials.identifier,
+        "password": credentials.password,
+        "remember_me": false,
+    }));
+    let Ok(response) = request.send().await else {
+        return (None, None);
+    };
+    let status = response.status();
+    let body = response.json::<LoginResponse>().await.ok();
+    let session = match body {
+        Some(LoginResponse {
+            access_token: Some(token),
+            user_id,
+        }) if status.is_success() => Some(Session {
+            credentials: credentials.clone(),
+            user_id,
+            token,
+        }),
+        _ => None,
+    };
+    (Some(status.as_u16()), session)
+}
+
+/// Find the class load entities are created in, or create it
+async fn loadgen_class(client: &Client, api: &str, token: &str) -> Result<Uuid, String> {
+    let classes: Vec<ClassSummary> = client
+        .get(format!("{}/ontology/classes", api))
+        .bearer_auth(token)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Failed to list classes: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to list classes: {}", e))?;
+    if let Some(class) = classes.into_iter().find(|c| c.name == CLASS_NAME) {
+        return Ok(class.id);
+    }
+    let created: Created = client
+        .post(format!("{}/ontology/classes", api))
+        .bearer_auth(token)
+        .json(&json!({
+            "name": CLASS_NAME,
+            "description": "Entities created by ontology-loadgen",
+        }))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Failed to create the {} class: {}", CLASS_NAME, e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to create the {} class: {}", CLASS_NAME, e))?;
+    Ok(created.id)
+}
+
+struct Worker {
+    client: Client,
+    api: String,
+    session: Session,
+    class_id: Uuid,
+    permission: String,
+    /// Entities this worker created and hasn't deleted
+    owned: Vec<Uuid>,
+    created: usize,
+    rng: StdRng,
+    samples: Vec<Sample>,
+}
+
+impl Worker {
+    async fn run(mut self, mix: Mix, deadline: Instant) -> Vec<Sample> {
+        while Instant::now() < deadline {
+            let op = mix.pick(&mut self.rng);
+            self.step(op).await;
+        }
+        for id in std::mem::take(&mut self.owned) {
+            let _ = self
+                .request(Method::DELETE, &format!("/ontology/entities/{}", id))
+                .send()
+                .await;
+        }
+        self.samples
+    }
+
+    async fn step(&mut self, op: Operation) {
+        // Entity operations need an entity of our own; make one first
+        let op = match op {
+            Operation::Read | Operation::Update | Operation::Delete if self.owned.is_empty() => {
+                Operation::Create
+            }
+            op => op,
+        };
+        match op {
+            Operation::Login => {
+                let start = Instant::now();
+                let (status, session) =
+                    login(&self.client, &self.api, &self.session.credentials).await;
+                self.record(op, status, start);
+                if let Some(session) = session {
+                    self.session = session;
+                }
+            }
+            Operation::Create => {
+                self.created += 1;
+                let body = json!({
+                    "class_id": self.class_id,
+                    "display_name": format!("{} item {}-{}", SEARCH_TERM, self.session.user_id, self.created),
+                    "parent_entity_id": null,
+                    "attributes": { "n": self.created },
+                });
+                let request = self.request(Method::POST, "/ontology/entities").json(&body);
+                if let Some(created) = self.timed::<Created>(op, request).await {
+                    self.owned.push(created.id);
+                }
+            }
+            Operation::Read => {
+                let id = self.any_owned();
+                let request = self.request(Method::GET, &format!("/ontology/entities/{}", id));
+                self.timed::<serde_json::Value>(op, request).await;
+            }
+            Operation::Update => {
+                let id = self.any_owned();
+                let request = self
+                    .request(Method::PUT, &format!("/ontology/entities/{}", id))
+                    .json(&json!({
+                        "attributes": { "touched_at": chrono::Utc::now() },
+                    }));
+                self.timed::<serde_json::Value>(op, request).await;
+            }
+            Operation::Delete => {
+                let index = self.rng.gen_range(0..self.owned.len());
+                let id = self.owned.swap_remove(index);
+                let request = self.request(Method::DELETE, &format!("/ontology/entities/{}", id));
+                self.timed::<serde_json::Value>(op, request).await;
+            }
+            Operation::Check => {
+                // Before the worker owns anything, ask about an entity that
+                // doesn't exist; that still walks the whole check
+                let entity_id = if self.owned.is_empty() {
+                    Uuid::new_v4()
+                } else {
+                    self.any_owned()
+                };
+                let request = self.request(Method::GET, "/rebac/check").query(&[
+                    ("user_id", self.session.user_id.to_string()),
+                    ("entity_id", entity_id.to_string()),
+                    ("permission", self.permission.clone()),
+                ]);
+                self.timed::<serde_json::Value>(op, request).await;
+            }
+            Operation::Search => {
+                let request = self
+                    .request(Method::GET, "/navigation/quick-search")
+                    .query(&[("q", SEARCH_TERM), ("limit", "10")]);
+                self.timed::<serde_json::Value>(op, request).await;
+            }
+        }
+    }
+
+    fn any_owned(&mut self) -> Uuid {
+        self.owned[self.rng.gen_range(0..self.owned.len())]
+    }
+
+    /// A request with the worker's bearer token, which also keeps it clear
+    /// of the CSRF check
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.client
+            .request(method, format!("{}{}", self.api, path))
+            .bearer_auth(&self.session.token)
+    }
+
+    /// Send `request`, time it up to the last byte of the body, and parse a
+    /// successful body as `T`
+    async fn timed<T: serde::de::DeserializeOwned>(
+        &mut self,
+        op: Operation,
+        request: RequestBuilder,
+    ) -> Option<T> {
+        let start = Instant::now();
+        let (status, body) = match request.send().await {
+            Ok(response) => {
+                let status = response.status();
+                (Some(status), response.bytes().await.ok())
+            }
+            Err(_) => (None, None),
+        };
+        self.record(op, status.map(|s| s.as_u16()), start);
+        match (status, body) {
+            (Some(status), Some(body)) if status.is_success() => serde_json::from_slice(&body).ok(),
+            _ => None,
+        }
+    }
+
+    fn record(&mut self, op: Operation, status: Option<u16>, start: Instant) {
+        self.samples.push(Sample {
+            op,
+            status,
+            micros: start.elapsed().as_micros() as u64,
+        });
+    }
+}
+
+/// Latency and outcome of one operation over a run. Latencies are in
+/// milliseconds.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OperationStats {
+    pub operation: String,
+    pub count: usize,
+    pub errors: usize,
+    pub per_second: f64,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+    /// Responses by status code; requests without a response count under
+    /// "no_response"
+    pub statuses: BTreeMap<String, usize>,
+}
+
+impl OperationStats {
+    fn new(operation: &str, samples: &[&Sample], elapsed: Duration) -> Self {
+        let mut micros = samples.iter().map(|s| s.micros).collect::<Vec<_>>();
+        micros.sort_unstable();
+        let mut statuses = BTreeMap::new();
+        for sample in samples {
+            let key = sample
+                .status
+                .map_or("no_response".to_string(), |s| s.to_string());
+            *statuses.entry(key).or_insert(0) += 1;
+        }
+        let ms = |micros: u64| micros as f64 / 1000.0;
+        Self {
+            operation: operation.to_string(),
+            count: samples.len(),
+            errors: samples.iter().filter(|s| s.is_error()).count(),
+            per_second: samples.len() as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+            mean_ms: match micros.len() {
+                0 => 0.0,
+                n => ms(micros.iter().sum::<u64>()) / n as f64,
+            },
+            p50_ms: ms(percentile(&micros, 50.0)),
+            p90_ms: ms(percentile(&micros, 90.0)),
+            p95_ms: ms(percentile(&micros, 95.0)),
+            p99_ms: ms(percentile(&micros, 99.0)),
+            max_ms: ms(micros.last().copied().unwrap_or(0)),
+            statuses,
+        }
+    }
+}
+
+/// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[u64], p: f64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Report {
+    pub duration_secs: f64,
+    pub concurrency: usize,
+    pub operations: Vec<OperationStats>,
+    pub total: OperationStats,
+}
+
+impl Report {
+    fn new(samples: &[Sample], elapsed: Duration, concurrency: usize) -> Self {
+        let operations = Operation::ALL
+            .into_iter()
+            .filter_map(|op| {
+                let of_op = samples.iter().filter(|s| s.op == op).collect::<Vec<_>>();
+                (!of_op.is_empty()).then(|| OperationStats::new(op.name(), &of_op, elapsed))
+            })
+            .collect();
+        Self {
+            duration_secs: elapsed.as_secs_f64(),
+            concurrency,
+            operations,
+            total: OperationStats::new("total", &samples.iter().collect::<Vec<_>>(), elapsed),
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} workers for {:.1}s\n",
+            self.concurrency, self.duration_secs
+        )?;
+        writeln!(
+            f,
+            "{:<10} {:>8} {:>7} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8}",
+            "operation", "count", "errors", "req/s", "mean", "p50", "p90", "p95", "p99", "max"
+        )?;
+        for stats in self.operations.iter().chain([&self.total]) {
+            writeln!(
+                f,
+                "{:<10} {:>8} {:>7} {:>8.1} {:>8.2} {:>8.2} {:>8.2} {:>8.2} {:>8.2} {:>8.2}",
+                stats.operation,
+                stats.count,
+                stats.errors,
+                stats.per_second,
+                stats.mean_ms,
+                stats.p50_ms,
+                stats.p90_ms,
+                stats.p95_ms,
+                stats.p99_ms,
+                stats.max_ms
+            )?;
+        }
+        write!(
+            f,
+            "\nLatencies in ms. Errors are 4xx/5xx and requests with no response."
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_percentiles_use_nearest_rank() {
+        let values = (1..=100).collect::<Vec<u64>>();
+        assert_eq!(percentile(&values, 50.0), 50);
+        assert_eq!(percentile(&values, 99.0), 99);
+        assert_eq!(percentile(&values, 100.0), 100);
+        assert_eq!(percentile(&[7], 1.0), 7);
+        assert_eq!(percentile(&[], 50.0), 0);
+    }
+
+    #[test]
+    fn test_mix_is_parsed_and_picked_by_weight() {
+        let mix = Mix::parse("read=3, check=1,search=0").unwrap();
+        assert_eq!(
+            mix.weights,
+            vec![(Operation::Read, 3), (Operation::Check, 1)]
+        );
+        let mut rng = StdRng::seed_from_u64(7);
+        let reads = (0..4000)
+            .filter(|_| mix.pick(&mut rng) == Operation::Read)
+            .count();
+        assert!((2800..3200).contains(&reads), "{} reads", reads);
+
+        assert!(Mix::parse("read=1,fly=2").is_err());
+        assert!(Mix::parse("read=1,read=2").is_err());
+        assert!(Mix::parse("read=0").is_err());
+        assert!(Mix::parse("read").is_err());
+    }
+
+    #[test]
+    fn test_config_is_parsed() {
+        let config = LoadConfig::parse(&args(&[
+            "--url=http://localhost:5300/",
+            "--concurrency",
+            "4",
+            "--user",
+            "alice:secret:with:colons",
+            "--user",
+            "bob:pw",
+            "--mix",
+            "login=1,read=9",
+        ]))
+        .unwrap();
+        assert_eq!(config.base_url, "http://localhost:5300");
+        assert_eq!(config.concurrency, 4);
+        assert_eq!(config.duration, Duration::from_secs(60));
+        assert_eq!(config.register, 0);
+        assert_eq!(config.users.len(), 2);
+        assert_eq!(config.users[0].password, "secret:with:colons");
+        assert_eq!(config.mix, Mix::parse("login=1,read=9").unwrap());
+
+        let config = LoadConfig::parse(&args(&["--concurrency=8"])).unwrap();
+        assert_eq!(config.register, 8);
+        assert_eq!(config.mix, Mix::default());
+
+        assert!(LoadConfig::parse(&args(&["--concurrency", "0"])).is_err());
+        assert!(LoadConfig::parse(&args(&["--user", "nopassword"])).is_err());
+        assert!(LoadConfig::parse(&args(&["--user", "a:b", "--register", "2"])).is_err());
+        assert!(LoadConfig::parse(&args(&["--bogus"])).is_err());
+    }
+
+    #[test]
+    fn test_report_counts_errors_per_operation() {
+        let sample = |op, status, micros| Sample { op, status, micros };
+        let samples = vec![
+            sample(Operation::Read, Some(200), 1000),
+            sample(Operation::Read, Some(404), 3000),
+            sample(Operation::Login, Some(429), 2000),
+            sample(Operation::Login, None, 30_000_000),
+        ];
+        let report = Report::new(&samples, Duration::from_secs(2), 2);
+        assert_eq!(
+            report
+                .operations
+                .iter()
+                .map(|s| s.operation.as_str())
+                .collect::<Vec<_>>(),
+            vec!["login", "read"]
+        );
+        let read = &report.operations[1];
+        assert_eq!((read.count, read.errors), (2, 1));
+        assert_eq!(read.mean_ms, 2.0);
+        assert_eq!(read.per_second, 1.0);
+        assert_eq!(report.operations[0].statuses["no_response"], 1);
+        assert_eq!((report.total.count, report.total.errors), (4, 3));
+    }
+}
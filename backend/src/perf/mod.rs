@@ -0,0 +1,30 @@
+//! Shared setup for the benchmarks in benches/: a database to run against
+//! and a dataset of a known shape seeded into it. `load` is the HTTP load
+//! generator behind the ontology-loadgen binary.
+
+pub mod dataset;
+pub mod load;
+
+pub use dataset::{Dataset, DatasetSize, PerfUser};
+
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+
+/// Database the benchmarks seed and run against. Never point it at a
+/// database with data you care about.
+pub const DATABASE_URL_ENV: &str = "PERF_DATABASE_URL";
+
+/// Connect to `url` and apply pending migrations
+pub async fn connect(url: &str, max_connections: u32) -> Result<PgPool, String> {
+    let pool = PgPoolOptions::new()
+        .max_connections(max_connections)
+        .acquire_timeout(std::time::Duration::from_secs(10))
+        .connect(url)
+        .await
+        .map_err(|e| format!("Failed to connect to database: {}", e))?;
+    sqlx::migrate!()
+        .run(&pool)
+        .await
+        .map_err(|e| format!("Failed to run migrations: {}", e))?;
+    Ok(pool)
+}
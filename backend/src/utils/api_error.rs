@@ -0,0 +1,152 @@
+//! The error envelope every API error response uses:
+//!
+//! ```json
+//! {
+//!   "error": "Entity not found",
+//!   "code": "ontology.not_found",
+//!   "details": { "entity_id": "..." },
+//!   "request_id": "4bf92f3577b34da6a3ce929d0e0e4736"
+//! }
+//! ```
+//!
+//! `error` is a message for people and may change wording. `code` is what
+//! clients branch on and doesn't change once released: `<feature>.<reason>`
+//! for feature errors, or one of the generic codes of [`generic_code`] when
+//! there is nothing more specific to say. `details` holds structured fields
+//! that go with the code and is left out when there are none. `request_id` is
+//! the trace id also returned in `X-Trace-Id`, for finding the request in the
+//! server logs.
+//!
+//! Feature error types convert into [`ApiError`] and respond through it.
+//! Responses that don't (bare status codes, extractor rejections) are put
+//! into the same shape by `middleware::error_envelope`.
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::middleware::trace_context::current_trace_id;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub code: &'static str,
+    pub message: String,
+    pub details: Map<String, Value>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+            details: Map::new(),
+        }
+    }
+
+    /// An error with the generic code for `status`
+    pub fn from_status(status: StatusCode, message: impl Into<String>) -> Self {
+        Self::new(status, generic_code(status), message)
+    }
+
+    pub fn with_detail(mut self, key: &str, value: impl Serialize) -> Self {
+        self.details.insert(
+            key.to_string(),
+            serde_json::to_value(value).unwrap_or(Value::Null),
+        );
+        self
+    }
+
+    /// The response body, with the current request id
+    pub fn body(&self) -> Value {
+        let mut body = Map::new();
+        body.insert("error".to_string(), Value::String(self.message.clone()));
+        body.insert("code".to_string(), Value::String(self.code.to_string()));
+        if !self.details.is_empty() {
+            body.insert("details".to_string(), Value::Object(self.details.clone()));
+        }
+        if let Some(request_id) = current_trace_id() {
+            body.insert("request_id".to_string(), Value::String(request_id));
+        }
+        Value::Object(body)
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.message, self.code)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        if self.status.is_server_error() {
+            tracing::error!(code = self.code, "{}", self.message);
+        }
+        (self.status, Json(self.body())).into_response()
+    }
+}
+
+/// Code for errors that carry nothing more specific than their status
+pub fn generic_code(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::BAD_REQUEST => "bad_request",
+        StatusCode::UNAUTHORIZED => "unauthenticated",
+        StatusCode::FORBIDDEN => "forbidden",
+        StatusCode::NOT_FOUND => "not_found",
+        StatusCode::METHOD_NOT_ALLOWED => "method_not_allowed",
+        StatusCode::NOT_ACCEPTABLE => "not_acceptable",
+        StatusCode::REQUEST_TIMEOUT | StatusCode::GATEWAY_TIMEOUT => "timeout",
+        StatusCode::CONFLICT => "conflict",
+        StatusCode::GONE => "gone",
+        StatusCode::PRECONDITION_FAILED => "precondition_failed",
+        StatusCode::PAYLOAD_TOO_LARGE => "payload_too_large",
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => "unsupported_media_type",
+        StatusCode::UNPROCESSABLE_ENTITY => "unprocessable",
+        StatusCode::TOO_MANY_REQUESTS => "rate_limited",
+        StatusCode::NOT_IMPLEMENTED => "not_implemented",
+        StatusCode::SERVICE_UNAVAILABLE => "unavailable",
+        status if status.is_server_error() => "internal",
+        _ => "bad_request",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::trace_context::with_trace_id;
+
+    #[tokio::test]
+    async fn test_body_carries_code_details_and_request_id() {
+        let error = ApiError::new(StatusCode::CONFLICT, "ontology.version_conflict", "Stale")
+            .with_detail("current_version", 3);
+        let body = with_trace_id("abc".to_string(), async { error.body() }).await;
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "error": "Stale",
+                "code": "ontology.version_conflict",
+                "details": { "current_version": 3 },
+                "request_id": "abc",
+            })
+        );
+
+        let body = ApiError::from_status(StatusCode::NOT_FOUND, "Nope").body();
+        assert_eq!(
+            body,
+            serde_json::json!({ "error": "Nope", "code": "not_found" })
+        );
+    }
+
+    #[test]
+    fn test_generic_codes() {
+        assert_eq!(generic_code(StatusCode::TOO_MANY_REQUESTS), "rate_limited");
+        assert_eq!(generic_code(StatusCode::BAD_GATEWAY), "internal");
+        assert_eq!(generic_code(StatusCode::IM_A_TEAPOT), "bad_request");
+    }
+}
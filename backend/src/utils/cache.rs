@@ -0,0 +1,319 @@
+use crate::config::CacheConfig;
+use moka::future::Cache;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::sync::OnceCell;
+
+#[derive(Error, Debug)]
+pub enum CacheError {
+    #[error("redis error: {0}")]
+    Redis(#[from] redis::RedisError),
+    #[error("cache call timed out")]
+    Timeout,
+}
+
+/// Groups of entries that are invalidated together
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CacheScope {
+    /// System classes, versions and relationship types
+    Ontology,
+    /// Counts for the sidebar and dashboards
+    Aggregates,
+}
+
+impl CacheScope {
+    fn name(&self) -> &'static str {
+        match self {
+            CacheScope::Ontology => "ontology",
+            CacheScope::Aggregates => "aggregates",
+        }
+    }
+}
+
+enum Backend {
+    /// Entries with their expiry, per process, one cache per scope
+    Local {
+        ontology: Cache<String, (Instant, String)>,
+        aggregates: Cache<String, (Instant, String)>,
+    },
+    /// Connected on first use, and retried on the next call if that fails
+    Redis {
+        client: redis::Client,
+        connection: OnceCell<ConnectionManager>,
+    },
+}
+
+struct Inner {
+    backend: Backend,
+    key_prefix: String,
+    ontology_ttl: Duration,
+    aggregate_ttl: Duration,
+    timeout: Duration,
+}
+
+/// Read-through cache of JSON-encoded values, in Redis or in process.
+///
+/// Failures of the cache never fail a request: the value is loaded instead
+/// and the failure logged.
+#[derive(Clone)]
+pub struct SharedCache {
+    inner: Arc<Inner>,
+}
+
+impl SharedCache {
+    /// The configured cache, or `None` when disabled
+    pub fn from_config(config: &CacheConfig) -> Result<Option<Self>, CacheError> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let backend = if config.redis_url.trim().is_empty() {
+            Backend::Local {
+                ontology: Cache::new(config.local_capacity),
+                aggregates: Cache::new(config.local_capacity),
+            }
+        } else {
+            Backend::Redis {
+                client: redis::Client::open(config.redis_url.trim())?,
+                connection: OnceCell::new(),
+            }
+        };
+        Ok(Some(Self {
+            inner: Arc::new(Inner {
+                backend,
+                key_prefix: config.key_prefix.clone(),
+                ontology_ttl: Duration::from_secs(config.ontology_ttl_secs.max(1)),
+                aggregate_ttl: Duration::from_secs(config.aggregate_ttl_secs.max(1)),
+                timeout: Duration::from_millis(config.timeout_ms.max(1)),
+            }),
+        }))
+    }
+
+    /// An in-process cache with the default lifetimes
+    pub fn local() -> Self {
+        let config = CacheConfig {
+            enabled: true,
+            redis_url: String::new(),
+            ..Default::default()
+        };
+        Self::from_config(&config)
+            .expect("in-process cache needs no connection")
+            .expect("in-process cache is enabled")
+    }
+
+    fn ttl(&self, scope: CacheScope) -> Duration {
+        match scope {
+            CacheScope::Ontology => self.inner.ontology_ttl,
+            CacheScope::Aggregates => self.inner.aggregate_ttl,
+        }
+    }
+
+    fn local_cache(&self, scope: CacheScope) -> Option<&Cache<String, (Instant, String)>> {
+        match (&self.inner.backend, scope) {
+            (Backend::Local { ontology, .. }, CacheScope::Ontology) => Some(ontology),
+            (Backend::Local { aggregates, .. }, CacheScope::Aggregates) => Some(aggregates),
+            (Backend::Redis { .. }, _) => None,
+        }
+    }
+
+    fn redis_key(&self, scope: CacheScope, key: &str) -> String {
+        format!("{}:{}:{}", self.inner.key_prefix, scope.name(), key)
+    }
+
+    /// The cached value of `key`, or the result of `load`, which is cached
+    /// when it succeeds
+    pub async fn get_or_load<T, E, F, Fut>(
+        &self,
+        scope: CacheScope,
+        key: &str,
+        load: F,
+    ) -> Result<T, E>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        match self.get(scope, key).await {
+            Ok(Some(cached)) => match serde_json::from_str(&cached) {
+                Ok(value) => return Ok(value),
+                // Written by a version with a different shape; replaced below
+                Err(e) => tracing::debug!("Discarding cached {}: {}", key, e),
+            },
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Cache read failed for {}: {}", key, e),
+        }
+
+        let value = load().await?;
+        if let Ok(encoded) = serde_json::to_string(&value) {
+            if let Err(e) = self.set(scope, key, encoded).await {
+                tracing::warn!("Cache write failed for {}: {}", key, e);
+            }
+        }
+        Ok(value)
+    }
+
+    /// Drop every entry of `scope`, in all instances sharing the cache
+    pub async fn invalidate(&self, scope: CacheScope) {
+        if let Err(e) = self.try_invalidate(scope).await {
+            tracing::warn!("Cache invalidation of {} failed: {}", scope.name(), e);
+        }
+    }
+
+    async fn get(&self, scope: CacheScope, key: &str) -> Result<Option<String>, CacheError> {
+        if let Some(cache) = self.local_cache(scope) {
+            return match cache.get(key).await {
+                Some((expires_at, value)) if expires_at > Instant::now() => Ok(Some(value)),
+                Some(_) => {
+                    cache.invalidate(key).await;
+                    Ok(None)
+                }
+                None => Ok(None),
+            };
+        }
+        let key = self.redis_key(scope, key);
+        self.with_redis(|mut conn| async move { conn.get::<_, Option<String>>(key).await })
+            .await
+    }
+
+    async fn set(&self, scope: CacheScope, key: &str, value: String) -> Result<(), CacheError> {
+        let ttl = self.ttl(scope);
+        if let Some(cache) = self.local_cache(scope) {
+            cache
+                .insert(key.to_string(), (Instant::now() + ttl, value))
+                .await;
+            return Ok(());
+        }
+        let key = self.redis_key(scope, key);
+        self.with_redis(|mut conn| async move {
+            conn.set_ex::<_, _, ()>(key, value, ttl.as_secs()).await
+        })
+        .await
+    }
+
+    async fn try_invalidate(&self, scope: CacheScope) -> Result<(), CacheError> {
+        if let Some(cache) = self.local_cache(scope) {
+            cache.invalidate_all();
+            return Ok(());
+        }
+        let pattern = format!("{}:{}:*", self.inner.key_prefix, scope.name());
+        self.with_redis(|mut conn| async move {
+            let mut cursor: u64 = 0;
+            loop {
+                let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+                    .arg(cursor)
+                    .arg("MATCH")
+                    .arg(&pattern)
+                    .arg("COUNT")
+                    .arg(500)
+                    .query_async(&mut conn)
+                    .await?;
+                if !keys.is_empty() {
+                    redis::cmd("UNLINK")
+                        .arg(&keys)
+                        .query_async::<_, ()>(&mut conn)
+                        .await?;
+                }
+                if next == 0 {
+                    return Ok(());
+                }
+                cursor = next;
+            }
+        })
+        .await
+    }
+
+    /// Run `call` on the shared connection within the configured timeout
+    async fn with_redis<T, F, Fut>(&self, call: F) -> Result<T, CacheError>
+    where
+        F: FnOnce(ConnectionManager) -> Fut,
+        Fut: Future<Output = redis::RedisResult<T>>,
+    {
+        let Backend::Redis { client, connection } = &self.inner.backend else {
+            unreachable!("only called for the Redis backend");
+        };
+        let work = async {
+            let conn = connection
+                .get_or_try_init(|| ConnectionManager::new(client.clone()))
+                .await?
+                .clone();
+            Ok::<_, CacheError>(call(conn).await?)
+        };
+        tokio::time::timeout(self.inner.timeout, work)
+            .await
+            .map_err(|_| CacheError::Timeout)?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    async fn load_counted(
+        cache: &SharedCache,
+        scope: CacheScope,
+        key: &str,
+        loads: &AtomicUsize,
+    ) -> Result<String, String> {
+        cache
+            .get_or_load(scope, key, || async {
+                loads.fetch_add(1, Ordering::SeqCst);
+                Ok(format!("value of {}", key))
+            })
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_values_are_loaded_once() {
+        let cache = SharedCache::local();
+        let loads = AtomicUsize::new(0);
+        for _ in 0..3 {
+            let value = load_counted(&cache, CacheScope::Ontology, "class:Role", &loads).await;
+            assert_eq!(value.unwrap(), "value of class:Role");
+        }
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_errors_are_not_cached() {
+        let cache = SharedCache::local();
+        let failed: Result<String, &str> = cache
+            .get_or_load(CacheScope::Ontology, "class:Missing", || async {
+                Err("not found")
+            })
+            .await;
+        assert!(failed.is_err());
+        let loaded: Result<String, &str> = cache
+            .get_or_load(CacheScope::Ontology, "class:Missing", || async {
+                Ok("found".to_string())
+            })
+            .await;
+        assert_eq!(loaded.unwrap(), "found");
+    }
+
+    #[tokio::test]
+    async fn test_invalidation_is_per_scope() {
+        let cache = SharedCache::local();
+        let loads = AtomicUsize::new(0);
+        load_counted(&cache, CacheScope::Ontology, "a", &loads)
+            .await
+            .unwrap();
+        load_counted(&cache, CacheScope::Aggregates, "a", &loads)
+            .await
+            .unwrap();
+
+        cache.invalidate(CacheScope::Ontology).await;
+        load_counted(&cache, CacheScope::Ontology, "a", &loads)
+            .await
+            .unwrap();
+        load_counted(&cache, CacheScope::Aggregates, "a", &loads)
+            .await
+            .unwrap();
+        assert_eq!(loads.load(Ordering::SeqCst), 3);
+    }
+}
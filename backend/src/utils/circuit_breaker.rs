@@ -0,0 +1,281 @@
+use serde::Serialize;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::config::CircuitBreakerConfig;
+
+/// Fails fast while a dependency is down instead of letting every request wait
+/// for it to time out.
+///
+/// After `failure_threshold` consecutive failures the breaker opens and calls are
+/// rejected for `open_for`. The first call after that is let through as a probe
+/// (half-open): success closes the breaker, failure opens it again.
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    inner: Arc<Mutex<Inner>>,
+    failure_threshold: u32,
+    open_for: Duration,
+}
+
+struct Inner {
+    state: State,
+    consecutive_failures: u32,
+}
+
+#[derive(Clone, Copy)]
+enum State {
+    Closed,
+    Open { until: Instant },
+    HalfOpen { probe_in_flight: bool },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BreakerStatus {
+    pub state: BreakerState,
+    pub consecutive_failures: u32,
+    /// Seconds until a probe is allowed, while open
+    pub retry_after_secs: Option<u64>,
+}
+
+#[derive(Debug)]
+pub enum BreakerError<E> {
+    /// Rejected without calling the dependency
+    Open {
+        retry_after: Duration,
+    },
+    Inner(E),
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, open_for: Duration) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Inner {
+                state: State::Closed,
+                consecutive_failures: 0,
+            })),
+            failure_threshold: failure_threshold.max(1),
+            open_for,
+        }
+    }
+
+    pub fn from_config(config: &CircuitBreakerConfig) -> Self {
+        Self::new(
+            config.failure_threshold,
+            Duration::from_secs(config.open_secs),
+        )
+    }
+
+    /// A closed breaker with the same settings, e.g. one per instance of a dependency
+    pub fn new_like(&self) -> Self {
+        Self::new(self.failure_threshold, self.open_for)
+    }
+
+    /// Run `call` unless the breaker is open, recording its outcome
+    pub async fn call<T, E, F>(&self, call: F) -> Result<T, BreakerError<E>>
+    where
+        F: Future<Output = Result<T, E>>,
+    {
+        let mut permit = self
+            .acquire()
+            .map_err(|retry_after| BreakerError::Open { retry_after })?;
+        let result = call.await;
+        permit.record(result.is_ok());
+        result.map_err(BreakerError::Inner)
+    }
+
+    pub fn status(&self) -> BreakerStatus {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let (state, retry_after_secs) = match inner.state {
+            State::Closed => (BreakerState::Closed, None),
+            State::Open { until } => {
+                let remaining = until.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    (BreakerState::HalfOpen, None)
+                } else {
+                    (BreakerState::Open, Some(remaining.as_secs().max(1)))
+                }
+            }
+            State::HalfOpen { .. } => (BreakerState::HalfOpen, None),
+        };
+        BreakerStatus {
+            state,
+            consecutive_failures: inner.consecutive_failures,
+            retry_after_secs,
+        }
+    }
+
+    fn acquire(&self) -> Result<Permit<'_>, Duration> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let probe = match inner.state {
+            State::Closed => false,
+            State::Open { until } => {
+                let remaining = until.saturating_duration_since(Instant::now());
+                if !remaining.is_zero() {
+                    return Err(remaining);
+                }
+                inner.state = State::HalfOpen {
+                    probe_in_flight: true,
+                };
+                true
+            }
+            State::HalfOpen { probe_in_flight } => {
+                if probe_in_flight {
+                    return Err(Duration::from_secs(1));
+                }
+                inner.state = State::HalfOpen {
+                    probe_in_flight: true,
+                };
+                true
+            }
+        };
+        Ok(Permit {
+            breaker: self,
+            probe,
+            recorded: false,
+        })
+    }
+
+    fn on_result(&self, success: bool) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        if success {
+            if !matches!(inner.state, State::Closed) {
+                tracing::info!("Circuit breaker closed");
+            }
+            inner.state = State::Closed;
+            inner.consecutive_failures = 0;
+            return;
+        }
+
+        inner.consecutive_failures = inner.consecutive_failures.saturating_add(1);
+        let reopen = matches!(inner.state, State::HalfOpen { .. })
+            || inner.consecutive_failures >= self.failure_threshold;
+        if reopen {
+            tracing::warn!(
+                "Circuit breaker open for {:?} after {} consecutive failures",
+                self.open_for,
+                inner.consecutive_failures
+            );
+            inner.state = State::Open {
+                until: Instant::now() + self.open_for,
+            };
+        }
+    }
+}
+
+/// A call in progress. If it is dropped without an outcome (the caller went
+/// away), a half-open probe slot is released so the next call can probe.
+struct Permit<'a> {
+    breaker: &'a CircuitBreaker,
+    probe: bool,
+    recorded: bool,
+}
+
+impl Permit<'_> {
+    fn record(&mut self, success: bool) {
+        self.recorded = true;
+        self.breaker.on_result(success);
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if self.recorded || !self.probe {
+            return;
+        }
+        let mut inner = self.breaker.inner.lock().unwrap_or_else(|e| e.into_inner());
+        if let State::HalfOpen { .. } = inner.state {
+            inner.state = State::HalfOpen {
+                probe_in_flight: false,
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn fail(breaker: &CircuitBreaker) -> Result<(), BreakerError<&'static str>> {
+        breaker.call(async { Err::<(), _>("down") }).await
+    }
+
+    async fn succeed(breaker: &CircuitBreaker) -> Result<(), BreakerError<&'static str>> {
+        breaker.call(async { Ok::<(), &str>(()) }).await
+    }
+
+    #[tokio::test]
+    async fn test_opens_after_threshold_and_fails_fast() {
+        let breaker = CircuitBreaker::new(3, Duration::from_secs(60));
+        for _ in 0..3 {
+            assert!(matches!(fail(&breaker).await, Err(BreakerError::Inner(_))));
+        }
+        assert_eq!(breaker.status().state, BreakerState::Open);
+        assert!(matches!(
+            succeed(&breaker).await,
+            Err(BreakerError::Open { .. })
+        ));
+        assert!(breaker.status().retry_after_secs.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_success_resets_failure_count() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+        let _ = fail(&breaker).await;
+        assert!(succeed(&breaker).await.is_ok());
+        let _ = fail(&breaker).await;
+        assert_eq!(breaker.status().state, BreakerState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_half_open_probe_closes_or_reopens() {
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(20));
+        let _ = fail(&breaker).await;
+        assert_eq!(breaker.status().state, BreakerState::Open);
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(breaker.status().state, BreakerState::HalfOpen);
+        // A failed probe opens the breaker again straight away
+        assert!(matches!(fail(&breaker).await, Err(BreakerError::Inner(_))));
+        assert_eq!(breaker.status().state, BreakerState::Open);
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(succeed(&breaker).await.is_ok());
+        assert_eq!(breaker.status().state, BreakerState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_only_one_probe_at_a_time() {
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(10));
+        let _ = fail(&breaker).await;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let (release_tx, release_rx) = tokio::sync::oneshot::channel::<()>();
+        let probe_breaker = breaker.clone();
+        let probe = tokio::spawn(async move {
+            probe_breaker
+                .call(async {
+                    let _ = release_rx.await;
+                    Ok::<(), &str>(())
+                })
+                .await
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(matches!(
+            succeed(&breaker).await,
+            Err(BreakerError::Open { .. })
+        ));
+
+        release_tx.send(()).unwrap();
+        assert!(probe.await.unwrap().is_ok());
+        assert_eq!(breaker.status().state, BreakerState::Closed);
+    }
+}
@@ -0,0 +1,128 @@
+use axum::{
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// Clients may cache but must revalidate with If-None-Match before reuse
+const CACHE_CONTROL: &str = "private, no-cache";
+
+/// Weak ETag for a single row, derived from its id and last modification time
+pub fn entity_etag(id: Uuid, updated_at: DateTime<Utc>) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(id.as_bytes());
+    hasher.update(updated_at.timestamp_micros().to_be_bytes());
+    format_weak(&hasher.finalize())
+}
+
+/// Weak ETag for a serialized payload (used for lists whose rows include joined data)
+pub fn payload_etag<T: Serialize>(value: &T) -> Option<String> {
+    let bytes = serde_json::to_vec(value).ok()?;
+    Some(format_weak(&Sha256::digest(&bytes)))
+}
+
+fn format_weak(digest: &[u8]) -> String {
+    format!("W/\"{}\"", hex::encode(&digest[..16]))
+}
+
+/// True if the request's If-None-Match matches `etag` (weak comparison, RFC 9110 13.1.2)
+pub fn if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let target = opaque(etag);
+
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|tag| tag.trim() == "*" || opaque(tag) == target)
+}
+
+/// Respond with 304 when the client already holds `etag`, otherwise with the JSON body.
+/// Both responses carry the ETag so clients can keep revalidating.
+pub fn conditional_json<T: Serialize>(
+    headers: &HeaderMap,
+    etag: Option<String>,
+    value: T,
+) -> Response {
+    let Some(etag) = etag else {
+        return Json(value).into_response();
+    };
+
+    let mut response = if if_none_match(headers, &etag) {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        Json(value).into_response()
+    };
+
+    if let Ok(v) = HeaderValue::from_str(&etag) {
+        response.headers_mut().insert(header::ETAG, v);
+    }
+    response.headers_mut().insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static(CACHE_CONTROL),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entity_etag_changes_with_updated_at() {
+        let id = Uuid::new_v4();
+        let t1 = Utc::now();
+        let t2 = t1 + chrono::Duration::microseconds(1);
+        assert_eq!(entity_etag(id, t1), entity_etag(id, t1));
+        assert_ne!(entity_etag(id, t1), entity_etag(id, t2));
+        assert!(entity_etag(id, t1).starts_with("W/\""));
+    }
+
+    #[test]
+    fn test_if_none_match_weak_comparison() {
+        let etag = entity_etag(Uuid::new_v4(), Utc::now());
+        let strong = etag.trim_start_matches("W/").to_string();
+
+        let mut headers = HeaderMap::new();
+        assert!(!if_none_match(&headers, &etag));
+
+        headers.insert(
+            header::IF_NONE_MATCH,
+            HeaderValue::from_str(&strong).unwrap(),
+        );
+        assert!(if_none_match(&headers, &etag));
+
+        let list = format!("W/\"other\", {}", etag);
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_str(&list).unwrap());
+        assert!(if_none_match(&headers, &etag));
+
+        headers.insert(
+            header::IF_NONE_MATCH,
+            HeaderValue::from_static("W/\"other\""),
+        );
+        assert!(!if_none_match(&headers, &etag));
+
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("*"));
+        assert!(if_none_match(&headers, &etag));
+    }
+
+    #[test]
+    fn test_conditional_json_returns_not_modified() {
+        let etag = payload_etag(&vec![1, 2, 3]).unwrap();
+
+        let fresh = conditional_json(&HeaderMap::new(), Some(etag.clone()), vec![1, 2, 3]);
+        assert_eq!(fresh.status(), StatusCode::OK);
+        assert_eq!(fresh.headers()[header::ETAG], etag.as_str());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_str(&etag).unwrap());
+        let cached = conditional_json(&headers, Some(etag.clone()), vec![1, 2, 3]);
+        assert_eq!(cached.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(cached.headers()[header::ETAG], etag.as_str());
+    }
+}
@@ -0,0 +1,288 @@
+use rand::Rng;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use std::future::Future;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+use crate::config::OutboundHttpConfig;
+
+/// How often and how long to retry a call to another service.
+///
+/// Waits between attempts grow exponentially with full jitter, and no attempt is
+/// started or allowed to run past `total_budget`, so callers get a bounded worst case.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Limit on a single attempt
+    pub attempt_timeout: Duration,
+    /// Limit on all attempts and the waits between them
+    pub total_budget: Duration,
+}
+
+#[derive(Error, Debug)]
+pub enum RetryError<E> {
+    #[error("timed out after {0:?}")]
+    TimedOut(Duration),
+    #[error("{0}")]
+    Failed(E),
+}
+
+impl RetryPolicy {
+    pub fn from_config(config: &OutboundHttpConfig) -> Self {
+        Self {
+            max_retries: config.max_retries,
+            initial_backoff: Duration::from_millis(config.initial_backoff_ms),
+            max_backoff: Duration::from_millis(config.max_backoff_ms),
+            attempt_timeout: Duration::from_secs(config.attempt_timeout_secs),
+            total_budget: Duration::from_secs(config.total_timeout_secs),
+        }
+    }
+
+    /// The same policy with longer limits, e.g. for slow model inference
+    pub fn with_timeouts(mut self, attempt_timeout: Duration, total_budget: Duration) -> Self {
+        self.attempt_timeout = attempt_timeout;
+        self.total_budget = total_budget;
+        self
+    }
+
+    /// Wait before retry number `retry` (starting at 0)
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let ceiling = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff);
+        rand::thread_rng().gen_range(Duration::ZERO..=ceiling)
+    }
+
+    /// Run `op` until it succeeds, fails with an error `retryable` rejects, or
+    /// retries or budget run out. Timed-out attempts are always retried.
+    pub async fn run<T, E, F, Fut>(
+        &self,
+        mut op: F,
+        retryable: impl Fn(&E) -> bool,
+    ) -> Result<T, RetryError<E>>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let started = Instant::now();
+        let mut retry = 0;
+        loop {
+            let remaining = self.total_budget.saturating_sub(started.elapsed());
+            let timeout = self.attempt_timeout.min(remaining);
+            let error = match tokio::time::timeout(timeout, op()).await {
+                Ok(Ok(value)) => return Ok(value),
+                Ok(Err(e)) if !retryable(&e) => return Err(RetryError::Failed(e)),
+                Ok(Err(e)) => RetryError::Failed(e),
+                Err(_) => RetryError::TimedOut(timeout),
+            };
+
+            let delay = self.backoff(retry);
+            if retry >= self.max_retries || started.elapsed() + delay >= self.total_budget {
+                return Err(error);
+            }
+            retry += 1;
+            tracing::debug!("Retrying outbound call in {:?} (retry {})", delay, retry);
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// Responses worth retrying: rate limiting and gateway/availability errors
+fn is_transient_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+#[derive(Error, Debug)]
+pub enum OutboundError {
+    #[error("request timed out after {0:?}")]
+    TimedOut(Duration),
+    #[error("request failed: {0}")]
+    Request(#[from] reqwest::Error),
+}
+
+enum Attempt {
+    Status(Response),
+    Error(reqwest::Error),
+}
+
+/// Shared HTTP client for webhooks, AI providers and other outbound calls.
+#[derive(Clone)]
+pub struct OutboundClient {
+    client: reqwest::Client,
+    policy: RetryPolicy,
+}
+
+impl OutboundClient {
+    pub fn new(policy: RetryPolicy) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            policy,
+        }
+    }
+
+    pub fn from_config(config: &OutboundHttpConfig) -> Self {
+        Self::new(RetryPolicy::from_config(config))
+    }
+
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    /// Return redirects to the caller instead of following them, for calls
+    /// to user-supplied URLs that were checked before sending
+    pub fn without_redirects(mut self) -> Self {
+        self.client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap_or(self.client);
+        self
+    }
+
+    /// Send the request built by `build`, retrying transport errors, timeouts and
+    /// transient statuses. Once retries run out the last response is returned
+    /// as-is, so callers keep handling non-success statuses themselves.
+    pub async fn send<F>(&self, build: F) -> Result<Response, OutboundError>
+    where
+        F: Fn(&reqwest::Client) -> RequestBuilder,
+    {
+        let result = self
+            .policy
+            .run(
+                || async {
+                    match build(&self.client).send().await {
+                        Ok(res) if is_transient_status(res.status()) => Err(Attempt::Status(res)),
+                        Ok(res) => Ok(res),
+                        Err(e) => Err(Attempt::Error(e)),
+                    }
+                },
+                |attempt| match attempt {
+                    Attempt::Status(_) => true,
+                    Attempt::Error(e) => e.is_connect() || e.is_timeout() || e.is_request(),
+                },
+            )
+            .await;
+
+        match result {
+            Ok(res) => Ok(res),
+            Err(RetryError::Failed(Attempt::Status(res))) => Ok(res),
+            Err(RetryError::Failed(Attempt::Error(e))) => Err(OutboundError::Request(e)),
+            Err(RetryError::TimedOut(after)) => Err(OutboundError::TimedOut(after)),
+        }
+    }
+
+    pub async fn get(&self, url: &str) -> Result<Response, OutboundError> {
+        self.send(|client| client.get(url)).await
+    }
+}
+
+impl Default for OutboundClient {
+    fn default() -> Self {
+        Self::from_config(&OutboundHttpConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+            attempt_timeout: Duration::from_millis(50),
+            total_budget: Duration::from_secs(1),
+        }
+    }
+
+    #[test]
+    fn test_backoff_is_capped() {
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(300),
+            ..policy(10)
+        };
+        for retry in 0..10 {
+            assert!(policy.backoff(retry) <= Duration::from_millis(300));
+        }
+        assert!(policy.backoff(0) <= Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn test_transient_errors_are_retried() {
+        let attempts = AtomicU32::new(0);
+        let result = policy(3)
+            .run(
+                || async {
+                    if attempts.fetch_add(1, Ordering::SeqCst) < 2 {
+                        Err("flaky")
+                    } else {
+                        Ok("done")
+                    }
+                },
+                |_| true,
+            )
+            .await;
+        assert_eq!(result.unwrap(), "done");
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_retries_or_permanent_error() {
+        let attempts = AtomicU32::new(0);
+        let result = policy(2)
+            .run(
+                || async {
+                    attempts.fetch_add(1, Ordering::SeqCst);
+                    Err::<(), _>("down")
+                },
+                |_| true,
+            )
+            .await;
+        assert!(matches!(result, Err(RetryError::Failed("down"))));
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+        attempts.store(0, Ordering::SeqCst);
+        let result = policy(2)
+            .run(
+                || async {
+                    attempts.fetch_add(1, Ordering::SeqCst);
+                    Err::<(), _>("bad request")
+                },
+                |_| false,
+            )
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_slow_attempts_time_out_within_budget() {
+        let policy = RetryPolicy {
+            total_budget: Duration::from_millis(120),
+            ..policy(10)
+        };
+        let started = Instant::now();
+        let result = policy
+            .run(
+                || async {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    Ok::<(), &str>(())
+                },
+                |_| true,
+            )
+            .await;
+        assert!(matches!(result, Err(RetryError::TimedOut(_))));
+        assert!(started.elapsed() < Duration::from_millis(500));
+    }
+}
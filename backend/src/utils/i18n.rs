@@ -0,0 +1,213 @@
+//! Locales and message catalogs.
+//!
+//! A request's locale comes from its `Accept-Language` header, narrowed to
+//! the locales we ship catalogs for. Catalogs are JSON files under
+//! `locales/<locale>/`, compiled into the binary. English is complete and is
+//! the fallback for any key another locale doesn't translate yet, so a new
+//! locale can start small. Data that carries its own translations, such as
+//! ontology labels, isn't limited to these locales and reads
+//! [`language_ranges`] instead.
+//!
+//! Messages may contain `{name}` placeholders, filled in from the parameters
+//! passed to [`Catalog::format`].
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use axum::{
+    http::{header, HeaderMap, HeaderValue},
+    response::Response,
+};
+use serde_json::Value;
+
+pub const DEFAULT_LOCALE: &str = "en";
+
+pub const SUPPORTED_LOCALES: &[&str] = &["en", "nb"];
+
+/// Language tags of an `Accept-Language` value, most preferred first. Tags
+/// are lowercased; the wildcard and ranges with q=0 are left out.
+pub fn language_ranges(accept_language: &str) -> Vec<String> {
+    let mut ranges: Vec<(String, f32)> = accept_language
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';');
+            let tag = parts.next()?.trim().to_ascii_lowercase();
+            if tag.is_empty() || tag == "*" {
+                return None;
+            }
+            let quality = parts
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+            (quality > 0.0).then_some((tag, quality))
+        })
+        .collect();
+    // Stable, so equally weighted ranges keep the client's order
+    ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranges.into_iter().map(|(tag, _)| tag).collect()
+}
+
+/// The best supported locale for an `Accept-Language` value
+pub fn negotiate(accept_language: Option<&str>) -> &'static str {
+    let Some(accept_language) = accept_language else {
+        return DEFAULT_LOCALE;
+    };
+    for tag in language_ranges(accept_language) {
+        let primary = match tag.split('-').next().unwrap_or_default() {
+            // Norwegian without a written standard, and Nynorsk, read Bokmål
+            "no" | "nn" => "nb",
+            primary => primary,
+        };
+        if let Some(locale) = SUPPORTED_LOCALES.iter().copied().find(|l| *l == primary) {
+            return locale;
+        }
+    }
+    DEFAULT_LOCALE
+}
+
+/// The request's `Accept-Language`, if it has a readable one
+pub fn accept_language(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+}
+
+/// The locale for a request
+pub fn locale_from_headers(headers: &HeaderMap) -> &'static str {
+    negotiate(accept_language(headers))
+}
+
+/// Marks `response` as depending on the request's `Accept-Language`, so
+/// caches keep one copy per language
+pub fn vary_on_language(mut response: Response) -> Response {
+    response
+        .headers_mut()
+        .append(header::VARY, HeaderValue::from_static("accept-language"));
+    response
+}
+
+#[derive(Debug, Default)]
+pub struct Catalog {
+    messages: HashMap<&'static str, HashMap<String, String>>,
+}
+
+impl Catalog {
+    /// A catalog from `(locale, JSON object of key to message)` pairs
+    pub fn from_sources(sources: &[(&'static str, &str)]) -> Result<Self, serde_json::Error> {
+        let mut messages = HashMap::new();
+        for (locale, source) in sources {
+            messages.insert(*locale, serde_json::from_str(source)?);
+        }
+        Ok(Self { messages })
+    }
+
+    /// The message for `key` in `locale`, falling back to the default locale
+    pub fn get(&self, locale: &str, key: &str) -> Option<&str> {
+        [locale, DEFAULT_LOCALE]
+            .iter()
+            .find_map(|locale| self.messages.get(*locale)?.get(key))
+            .map(String::as_str)
+    }
+
+    /// [`Catalog::get`] with `{name}` placeholders filled in from `params`
+    pub fn format<'a>(
+        &self,
+        locale: &str,
+        key: &str,
+        params: impl IntoIterator<Item = (&'a str, &'a Value)>,
+    ) -> Option<String> {
+        let mut message = self.get(locale, key)?.to_string();
+        for (name, value) in params {
+            let value = match value {
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+            message = message.replace(&format!("{{{name}}}"), &value);
+        }
+        Some(message)
+    }
+
+    /// Keys in `locale` that the default locale doesn't have
+    pub fn unknown_keys(&self, locale: &str) -> Vec<&str> {
+        let Some(messages) = self.messages.get(locale) else {
+            return Vec::new();
+        };
+        let defaults = self.messages.get(DEFAULT_LOCALE);
+        let mut keys: Vec<&str> = messages
+            .keys()
+            .filter(|key| !defaults.is_some_and(|defaults| defaults.contains_key(*key)))
+            .map(String::as_str)
+            .collect();
+        keys.sort_unstable();
+        keys
+    }
+}
+
+/// Messages for request validation errors (see utils::validation)
+pub fn validation_catalog() -> &'static Catalog {
+    static CATALOG: OnceLock<Catalog> = OnceLock::new();
+    CATALOG.get_or_init(|| {
+        Catalog::from_sources(&[
+            ("en", include_str!("../../locales/en/validation.json")),
+            ("nb", include_str!("../../locales/nb/validation.json")),
+        ])
+        .expect("validation catalogs are valid JSON")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(negotiate(None), "en");
+        assert_eq!(negotiate(Some("")), "en");
+        assert_eq!(negotiate(Some("nb-NO,nb;q=0.9,en;q=0.8")), "nb");
+        assert_eq!(negotiate(Some("no")), "nb");
+        assert_eq!(negotiate(Some("de-DE,nn;q=0.5")), "nb");
+        assert_eq!(negotiate(Some("en;q=0.4, nb;q=0.7")), "nb");
+        assert_eq!(negotiate(Some("nb;q=0, en")), "en");
+        assert_eq!(negotiate(Some("fr, *;q=0.1")), "en");
+        assert_eq!(negotiate(Some("sv")), "en");
+    }
+
+    #[test]
+    fn test_language_ranges() {
+        assert_eq!(
+            language_ranges("en;q=0.5, nb-NO, *;q=0.1, sv;q=0, nb;q=0.9"),
+            vec!["nb-no", "nb", "en"]
+        );
+        assert!(language_ranges("").is_empty());
+    }
+
+    #[test]
+    fn test_format_falls_back_to_default_locale() {
+        let catalog = Catalog::from_sources(&[
+            ("en", r#"{"a": "at least {min}", "b": "only english"}"#),
+            ("nb", r#"{"a": "minst {min}"}"#),
+        ])
+        .unwrap();
+        let min = json!(3);
+        assert_eq!(
+            catalog.format("nb", "a", [("min", &min)]).as_deref(),
+            Some("minst 3")
+        );
+        assert_eq!(catalog.get("nb", "b"), Some("only english"));
+        assert_eq!(catalog.get("sv", "a"), Some("at least {min}"));
+        assert_eq!(catalog.get("en", "c"), None);
+    }
+
+    #[test]
+    fn test_shipped_catalogs_are_consistent() {
+        let catalog = validation_catalog();
+        for locale in SUPPORTED_LOCALES {
+            assert!(catalog.messages.contains_key(locale), "{locale}");
+            assert!(
+                catalog.unknown_keys(locale).is_empty(),
+                "{locale}: {:?}",
+                catalog.unknown_keys(locale)
+            );
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use axum::{extract::ConnectInfo, http::HeaderMap};
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+
+/// An IPv4 or IPv6 network in CIDR notation. A bare address is a single host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = mask_v4(self.prefix);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = mask_v6(self.prefix);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+fn mask_v4(prefix: u8) -> u32 {
+    u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0)
+}
+
+fn mask_v6(prefix: u8) -> u128 {
+    u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0)
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    /// Host bits are cleared, so `10.1.2.3/8` is stored as `10.0.0.0/8`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+        let addr = IpAddr::from_str(addr)
+            .map_err(|_| format!("Invalid IP address: {}", addr))?
+            .to_canonical();
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(p) => p
+                .parse::<u8>()
+                .ok()
+                .filter(|p| *p <= max)
+                .ok_or_else(|| format!("Invalid prefix length: {}", p))?,
+            None => max,
+        };
+
+        let network = match addr {
+            IpAddr::V4(v4) => IpAddr::V4((u32::from(v4) & mask_v4(prefix)).into()),
+            IpAddr::V6(v6) => IpAddr::V6((u128::from(v6) & mask_v6(prefix)).into()),
+        };
+        Ok(Self { network, prefix })
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+pub fn parse_cidrs(items: &[String]) -> Result<Vec<Cidr>, String> {
+    items.iter().map(|s| s.parse()).collect()
+}
+
+/// Resolve the client address for access control.
+///
+/// Forwarding headers are only trusted when the TCP peer is one of
+/// `trusted_proxies`; otherwise anyone could claim an allowed address. The
+/// X-Forwarded-For chain is walked right to left past further trusted hops.
+pub fn client_ip(
+    headers: &HeaderMap,
+    connect_info: Option<&ConnectInfo<SocketAddr>>,
+    trusted_proxies: &[Cidr],
+) -> Option<IpAddr> {
+    let peer = connect_info.map(|ci| ci.0.ip().to_canonical())?;
+    let trusted = |ip: IpAddr| trusted_proxies.iter().any(|c| c.contains(ip));
+    if !trusted(peer) {
+        return Some(peer);
+    }
+
+    let forwarded: Vec<IpAddr> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|s| IpAddr::from_str(s.trim()).ok())
+        .collect();
+    if let Some(ip) = forwarded.iter().rev().find(|ip| !trusted(**ip)) {
+        return Some(ip.to_canonical());
+    }
+    if let Some(first) = forwarded.first() {
+        return Some(first.to_canonical());
+    }
+
+    headers
+        .get("x-real-ip")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| IpAddr::from_str(s.trim()).ok())
+        .map(|ip| ip.to_canonical())
+        .or(Some(peer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_cidr_parse_and_contains() {
+        let net: Cidr = "10.1.2.3/8".parse().unwrap();
+        assert_eq!(net.to_string(), "10.0.0.0/8");
+        assert!(net.contains(ip("10.255.0.1")));
+        assert!(!net.contains(ip("11.0.0.1")));
+        assert!(net.contains(ip("::ffff:10.0.0.1")));
+
+        let host: Cidr = "192.168.1.5".parse().unwrap();
+        assert_eq!(host.to_string(), "192.168.1.5/32");
+        assert!(host.contains(ip("192.168.1.5")));
+        assert!(!host.contains(ip("192.168.1.6")));
+
+        let any: Cidr = "0.0.0.0/0".parse().unwrap();
+        assert!(any.contains(ip("8.8.8.8")));
+        assert!(!any.contains(ip("2001:db8::1")));
+
+        let v6: Cidr = "2001:db8::/32".parse().unwrap();
+        assert!(v6.contains(ip("2001:db8:1::1")));
+        assert!(!v6.contains(ip("2001:db9::1")));
+
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+        assert!("not-an-ip".parse::<Cidr>().is_err());
+    }
+
+    #[test]
+    fn test_client_ip_only_trusts_forwarding_from_proxies() {
+        let proxies = parse_cidrs(&["127.0.0.1/32".to_string(), "10.0.0.0/8".to_string()]).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            HeaderValue::from_static("203.0.113.9, 198.51.100.7, 10.0.0.2"),
+        );
+
+        let direct = ConnectInfo(SocketAddr::from(([198, 51, 100, 1], 4000)));
+        assert_eq!(
+            client_ip(&headers, Some(&direct), &proxies),
+            Some(ip("198.51.100.1"))
+        );
+
+        let proxied = ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000)));
+        assert_eq!(
+            client_ip(&headers, Some(&proxied), &proxies),
+            Some(ip("198.51.100.7"))
+        );
+
+        assert_eq!(client_ip(&headers, None, &proxies), None);
+    }
+}
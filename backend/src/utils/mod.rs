@@ -1,3 +1,17 @@
-pub mod email;
+pub mod api_error;
+pub mod cache;
+pub mod circuit_breaker;
+pub mod etag;
+pub mod http_client;
+pub mod i18n;
+pub mod ip;
 pub mod jwt_keys;
 pub mod key_rotation;
+pub mod pagination;
+pub mod pdf;
+pub mod shutdown;
+pub mod sigv4;
+pub mod storage;
+pub mod streaming;
+pub mod tls;
+pub mod validation;
@@ -0,0 +1,340 @@
+//! Paging and sorting for admin listings
+//!
+//! A listing takes `limit` and either `offset` or the `cursor` of the
+//! previous page, plus a `sort` field from a fixed list and an `order`.
+//! Cursors hold the sort value and id of the last row returned, so deep
+//! pages cost the same as the first and don't shift when rows are added.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+pub const DEFAULT_PAGE_SIZE: i64 = 50;
+pub const MAX_PAGE_SIZE: i64 = 1000;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum PageError {
+    #[error("Unknown sort field '{0}', expected one of: {1}")]
+    UnknownSort(String, String),
+
+    #[error("Invalid cursor")]
+    InvalidCursor,
+
+    #[error("Cursor was issued for a different sort; start again without it")]
+    CursorMismatch,
+
+    #[error("offset can't be negative")]
+    NegativeOffset,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl SortOrder {
+    fn keyword(self) -> &'static str {
+        match self {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        }
+    }
+
+    /// Comparison selecting the rows that come after a cursor
+    fn after(self) -> &'static str {
+        match self {
+            SortOrder::Asc => ">",
+            SortOrder::Desc => "<",
+        }
+    }
+}
+
+/// A column a listing can be sorted by
+#[derive(Debug, PartialEq, Eq)]
+pub struct SortField {
+    /// Name accepted in `sort`
+    pub name: &'static str,
+    /// SQL expression sorted on. Must not be NULL, or keyset paging skips rows.
+    pub sql: &'static str,
+    /// Postgres type the cursor value is cast back to
+    pub sql_type: &'static str,
+}
+
+/// One page of a listing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Rows matching the filters, across all pages
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+    /// Pass as `cursor` to get the next page; None on the last one
+    pub next_cursor: Option<String>,
+    pub has_more: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+struct Cursor {
+    sort: String,
+    order: SortOrder,
+    value: String,
+    id: String,
+}
+
+/// Validated paging and sorting for one query
+#[derive(Debug)]
+pub struct PageRequest {
+    pub limit: i64,
+    pub offset: i64,
+    pub sort: &'static SortField,
+    pub order: SortOrder,
+    /// Sort value and id of the row to continue after
+    after: Option<(String, String)>,
+}
+
+impl PageRequest {
+    /// `sort` defaults to the first of `fields`. A cursor takes the place of
+    /// `offset` and must come from a page with the same sort and order.
+    pub fn new(
+        fields: &'static [SortField],
+        sort: Option<&str>,
+        order: Option<SortOrder>,
+        limit: Option<i64>,
+        offset: Option<i64>,
+        cursor: Option<&str>,
+    ) -> Result<Self, PageError> {
+        let sort = match sort.map(str::trim).filter(|s| !s.is_empty()) {
+            None => &fields[0],
+            Some(name) => fields.iter().find(|f| f.name == name).ok_or_else(|| {
+                let names: Vec<_> = fields.iter().map(|f| f.name).collect();
+                PageError::UnknownSort(name.to_string(), names.join(", "))
+            })?,
+        };
+        let order = order.unwrap_or_default();
+        let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+        let after = match cursor.filter(|c| !c.is_empty()) {
+            None => None,
+            Some(cursor) => {
+                let cursor = decode_cursor(cursor)?;
+                if cursor.sort != sort.name || cursor.order != order {
+                    return Err(PageError::CursorMismatch);
+                }
+                Some((cursor.value, cursor.id))
+            }
+        };
+        let offset = match (&after, offset.unwrap_or(0)) {
+            (Some(_), _) => 0,
+            (None, offset) if offset < 0 => return Err(PageError::NegativeOffset),
+            (None, offset) => offset,
+        };
+
+        Ok(Self {
+            limit,
+            offset,
+            sort,
+            order,
+            after,
+        })
+    }
+
+    /// `ORDER BY` list, with `id_sql` breaking ties
+    pub fn order_by(&self, id_sql: &str) -> String {
+        let order = self.order.keyword();
+        format!("{} {}, {} {}", self.sort.sql, order, id_sql, order)
+    }
+
+    /// Condition keeping rows after the cursor, true without one. The cursor
+    /// value and id are bound at `$param` and `$param + 1`.
+    pub fn keyset(&self, id_sql: &str, id_type: &str, param: usize) -> String {
+        format!(
+            "(${p}::text IS NULL OR ({sql}, {id}) {cmp} (${p}::{ty}, ${q}::{id_ty}))",
+            p = param,
+            q = param + 1,
+            sql = self.sort.sql,
+            id = id_sql,
+            cmp = self.order.after(),
+            ty = self.sort.sql_type,
+            id_ty = id_type,
+        )
+    }
+
+    pub fn cursor_value(&self) -> Option<&str> {
+        self.after.as_ref().map(|(value, _)| value.as_str())
+    }
+
+    pub fn cursor_id(&self) -> Option<&str> {
+        self.after.as_ref().map(|(_, id)| id.as_str())
+    }
+
+    /// Rows to fetch: one more than the page, to tell whether more follow
+    pub fn fetch_limit(&self) -> i64 {
+        self.limit + 1
+    }
+
+    /// Build the page from rows fetched with `fetch_limit`. `key` gives the
+    /// sort value (named by the field) and id of a row, as the text the
+    /// keyset condition casts back.
+    pub fn into_page<T>(
+        self,
+        mut rows: Vec<T>,
+        total: i64,
+        key: impl Fn(&T, &str) -> (String, String),
+    ) -> Page<T> {
+        let has_more = rows.len() as i64 > self.limit;
+        rows.truncate(self.limit as usize);
+        let next_cursor = rows.last().filter(|_| has_more).map(|last| {
+            let (value, id) = key(last, self.sort.name);
+            encode_cursor(&Cursor {
+                sort: self.sort.name.to_string(),
+                order: self.order,
+                value,
+                id,
+            })
+        });
+        Page {
+            items: rows,
+            total,
+            limit: self.limit,
+            offset: self.offset,
+            next_cursor,
+            has_more,
+        }
+    }
+}
+
+/// Timestamps in cursors keep microseconds, as Postgres does
+pub fn cursor_timestamp(at: &chrono::DateTime<chrono::Utc>) -> String {
+    at.to_rfc3339_opts(chrono::SecondsFormat::Micros, true)
+}
+
+/// `text` as an ILIKE pattern matching it anywhere
+pub fn contains_pattern(text: &str) -> String {
+    format!(
+        "%{}%",
+        text.replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    )
+}
+
+fn encode_cursor(cursor: &Cursor) -> String {
+    let json = serde_json::to_vec(cursor).expect("cursor serializes");
+    base64::encode_config(json, base64::URL_SAFE_NO_PAD)
+}
+
+fn decode_cursor(cursor: &str) -> Result<Cursor, PageError> {
+    base64::decode_config(cursor.trim(), base64::URL_SAFE_NO_PAD)
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .ok_or(PageError::InvalidCursor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static FIELDS: &[SortField] = &[
+        SortField {
+            name: "created_at",
+            sql: "created_at",
+            sql_type: "timestamptz",
+        },
+        SortField {
+            name: "name",
+            sql: "COALESCE(name, '')",
+            sql_type: "text",
+        },
+    ];
+
+    #[test]
+    fn test_defaults_and_bounds() {
+        let page = PageRequest::new(FIELDS, None, None, None, None, None).unwrap();
+        assert_eq!(page.sort.name, "created_at");
+        assert_eq!(page.order, SortOrder::Desc);
+        assert_eq!((page.limit, page.offset), (DEFAULT_PAGE_SIZE, 0));
+        assert_eq!(page.order_by("id"), "created_at DESC, id DESC");
+
+        let page = PageRequest::new(FIELDS, Some("name"), None, Some(0), Some(20), None).unwrap();
+        assert_eq!((page.limit, page.offset), (1, 20));
+        assert_eq!(
+            PageRequest::new(FIELDS, None, None, Some(1_000_000), None, None)
+                .unwrap()
+                .limit,
+            MAX_PAGE_SIZE
+        );
+
+        assert!(matches!(
+            PageRequest::new(FIELDS, Some("password"), None, None, None, None),
+            Err(PageError::UnknownSort(..))
+        ));
+        assert_eq!(
+            PageRequest::new(FIELDS, None, None, None, Some(-1), None).unwrap_err(),
+            PageError::NegativeOffset
+        );
+    }
+
+    #[test]
+    fn test_cursor_continues_the_same_sort() {
+        let page = PageRequest::new(
+            FIELDS,
+            Some("name"),
+            Some(SortOrder::Asc),
+            Some(2),
+            None,
+            None,
+        )
+        .unwrap();
+        let rows = vec![("a", 1), ("b", 2), ("c", 3)];
+        let page = page.into_page(rows, 3, |row, field| {
+            assert_eq!(field, "name");
+            (row.0.to_string(), row.1.to_string())
+        });
+        assert_eq!(page.items.len(), 2);
+        assert!(page.has_more);
+        let cursor = page.next_cursor.unwrap();
+
+        let next = PageRequest::new(
+            FIELDS,
+            Some("name"),
+            Some(SortOrder::Asc),
+            Some(2),
+            Some(40),
+            Some(&cursor),
+        )
+        .unwrap();
+        assert_eq!(
+            (next.cursor_value(), next.cursor_id()),
+            (Some("b"), Some("2"))
+        );
+        assert_eq!(next.offset, 0);
+        assert_eq!(
+            next.keyset("id", "uuid", 5),
+            "($5::text IS NULL OR (COALESCE(name, ''), id) > ($5::text, $6::uuid))"
+        );
+
+        assert_eq!(
+            PageRequest::new(FIELDS, Some("name"), None, None, None, Some(&cursor)).unwrap_err(),
+            PageError::CursorMismatch
+        );
+        assert_eq!(
+            PageRequest::new(FIELDS, None, None, None, None, Some("not a cursor")).unwrap_err(),
+            PageError::InvalidCursor
+        );
+    }
+
+    #[test]
+    fn test_last_page_has_no_cursor() {
+        let page = PageRequest::new(FIELDS, None, None, Some(5), None, None).unwrap();
+        let page = page.into_page(vec![1, 2], 2, |row, _| (row.to_string(), row.to_string()));
+        assert!(!page.has_more);
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn test_contains_pattern_escapes_wildcards() {
+        assert_eq!(contains_pattern(r"50%_off\"), r"%50\%\_off\\%");
+    }
+}
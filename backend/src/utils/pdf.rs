@@ -0,0 +1,229 @@
+// Plain-text PDF documents
+//
+// Enough of PDF 1.4 to print reports: A4 pages, the built-in Helvetica fonts
+// (so nothing is embedded), headings and word-wrapped lines. Characters
+// outside printable ASCII are written as '?'.
+
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 50.0;
+/// Helvetica averages about half its size per character; this keeps
+/// 10pt body lines inside the margins
+const WRAP_COLUMNS: usize = 95;
+
+#[derive(Clone, Copy)]
+enum Style {
+    Title,
+    Heading,
+    Body,
+}
+
+impl Style {
+    /// (font resource, size, line height)
+    fn metrics(self) -> (&'static str, f32, f32) {
+        match self {
+            Style::Title => ("F2", 16.0, 24.0),
+            Style::Heading => ("F2", 12.0, 20.0),
+            Style::Body => ("F1", 10.0, 14.0),
+        }
+    }
+}
+
+pub struct TextPdf {
+    lines: Vec<(Style, String)>,
+}
+
+impl TextPdf {
+    pub fn new(title: &str) -> Self {
+        Self {
+            lines: vec![(Style::Title, title.to_string())],
+        }
+    }
+
+    pub fn heading(&mut self, text: &str) -> &mut Self {
+        self.lines.push((Style::Body, String::new()));
+        self.lines.push((Style::Heading, text.to_string()));
+        self
+    }
+
+    /// A paragraph, wrapped at word boundaries to fit the page
+    pub fn line(&mut self, text: &str) -> &mut Self {
+        for line in wrap(text, WRAP_COLUMNS) {
+            self.lines.push((Style::Body, line));
+        }
+        self
+    }
+
+    pub fn render(&self) -> Vec<u8> {
+        let mut pages: Vec<String> = Vec::new();
+        let mut content = String::new();
+        let mut y = PAGE_HEIGHT - MARGIN;
+        for (style, text) in &self.lines {
+            let (font, size, leading) = style.metrics();
+            if y - leading < MARGIN {
+                pages.push(std::mem::take(&mut content));
+                y = PAGE_HEIGHT - MARGIN;
+            }
+            y -= leading;
+            if !text.is_empty() {
+                content.push_str(&format!(
+                    "BT /{} {} Tf {} {} Td ({}) Tj ET\n",
+                    font,
+                    size,
+                    MARGIN,
+                    y,
+                    escape(text)
+                ));
+            }
+        }
+        pages.push(content);
+
+        // Objects 1-4 are fixed; each page adds a page and a content object
+        let page_ids: Vec<usize> = (0..pages.len()).map(|i| 5 + i * 2).collect();
+        let mut objects = vec![
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                page_ids
+                    .iter()
+                    .map(|id| format!("{} 0 R", id))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                pages.len()
+            ),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+                .to_string(),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+                .to_string(),
+        ];
+        for (page_id, content) in page_ids.iter().zip(&pages) {
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                PAGE_WIDTH,
+                PAGE_HEIGHT,
+                page_id + 1
+            ));
+            objects.push(format!(
+                "<< /Length {} >>\nstream\n{}endstream",
+                content.len(),
+                content
+            ));
+        }
+
+        let mut out = b"%PDF-1.4\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", i + 1, object).as_bytes());
+        }
+        let xref_at = out.len();
+        out.extend_from_slice(
+            format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes(),
+        );
+        for offset in offsets {
+            out.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        out.extend_from_slice(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+                objects.len() + 1,
+                xref_at
+            )
+            .as_bytes(),
+        );
+        out
+    }
+}
+
+/// PDF string literal contents: printable ASCII, with delimiters escaped
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            ' '..='~' => escaped.push(c),
+            '\t' => escaped.push(' '),
+            _ => escaped.push('?'),
+        }
+    }
+    escaped
+}
+
+fn wrap(text: &str, columns: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let mut word = word;
+            // Words longer than a line are split wherever they run out
+            while word.chars().count() > columns {
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                let split = word
+                    .char_indices()
+                    .nth(columns)
+                    .map(|(i, _)| i)
+                    .unwrap_or(word.len());
+                lines.push(word[..split].to_string());
+                word = &word[split..];
+            }
+            if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > columns {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_and_escape() {
+        assert_eq!(wrap("one two three", 7), vec!["one two", "three"]);
+        assert_eq!(wrap("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+        assert_eq!(wrap("a\nb", 10), vec!["a", "b"]);
+        assert_eq!(escape("f(x) = \\ é"), "f\\(x\\) = \\\\ ?");
+    }
+
+    #[test]
+    fn test_render_paginates_and_indexes_objects() {
+        let mut pdf = TextPdf::new("Report");
+        pdf.heading("Section");
+        for i in 0..100 {
+            pdf.line(&format!("Line {}", i));
+        }
+        let bytes = pdf.render();
+        let text = String::from_utf8(bytes).unwrap();
+
+        assert!(text.starts_with("%PDF-1.4\n"));
+        assert!(text.ends_with("%%EOF\n"));
+        assert!(text.contains("/Count 2"));
+        assert!(text.contains("(Line 99) Tj"));
+
+        // startxref points at the cross-reference table, whose entries point at objects
+        let startxref: usize = text
+            .rsplit("startxref\n")
+            .next()
+            .and_then(|s| s.lines().next())
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(text[startxref..].starts_with("xref\n"));
+        let first_object: usize = text[startxref..].lines().nth(3).unwrap()[..10]
+            .parse()
+            .unwrap();
+        assert!(text[first_object..].starts_with("1 0 obj"));
+    }
+}
@@ -0,0 +1,122 @@
+use std::future::Future;
+use std::time::Duration;
+use tokio_util::sync::{CancellationToken, WaitForCancellationFutureOwned};
+use tokio_util::task::TaskTracker;
+
+/// Coordinates graceful shutdown between the HTTP server and background jobs.
+///
+/// Background loops are spawned through `spawn` and watch `cancelled()` between
+/// runs, so a job that is already running is allowed to finish rather than
+/// being dropped halfway through a write.
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    token: CancellationToken,
+    tracker: TaskTracker,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Spawn a task that is waited for during shutdown
+    pub fn spawn<F>(&self, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.tracker.spawn(task);
+    }
+
+    pub fn trigger(&self) {
+        self.token.cancel();
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Resolves once shutdown has been triggered
+    pub fn cancelled(&self) -> WaitForCancellationFutureOwned {
+        self.token.clone().cancelled_owned()
+    }
+
+    /// Wait for tracked tasks to finish. Returns false if `deadline` passed first.
+    pub async fn wait_for_tasks(&self, deadline: Duration) -> bool {
+        self.tracker.close();
+        tokio::time::timeout(deadline, self.tracker.wait())
+            .await
+            .is_ok()
+    }
+}
+
+/// Resolves on SIGTERM (Kubernetes pod termination) or Ctrl+C
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_running_job_finishes_before_shutdown_completes() {
+        let shutdown = Shutdown::new();
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let (task_shutdown, task_runs) = (shutdown.clone(), runs.clone());
+        shutdown.spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_millis(10));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = task_shutdown.cancelled() => break,
+                }
+                // Simulated job that must not be cut short
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                task_runs.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        shutdown.trigger();
+        assert!(shutdown.is_triggered());
+        assert!(shutdown.wait_for_tasks(Duration::from_secs(1)).await);
+        assert!(runs.load(Ordering::SeqCst) >= 1);
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_tasks_respects_deadline() {
+        let shutdown = Shutdown::new();
+        shutdown.spawn(std::future::pending());
+        shutdown.trigger();
+        assert!(!shutdown.wait_for_tasks(Duration::from_millis(20)).await);
+    }
+}
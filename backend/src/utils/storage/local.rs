@@ -0,0 +1,468 @@
+use super::{
+    validate_key, ByteStream, Download, ObjectStorage, PresignedUrl, StorageError, StoredObject,
+};
+use crate::utils::sigv4::uri_encode;
+use axum::async_trait;
+use axum::body::Bytes;
+use chrono::Utc;
+use futures::{Stream, StreamExt, TryStreamExt};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_util::io::ReaderStream;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Query of a presigned local download URL, checked by [`LocalStorage::open_signed`]
+#[derive(Debug, Clone, Deserialize)]
+pub struct SignedDownload {
+    /// Unix time the link stops working
+    pub expires: i64,
+    pub filename: String,
+    pub content_type: String,
+    /// Hex-encoded HMAC-SHA256 of the key and the fields above
+    pub signature: String,
+}
+
+/// Signs download URLs that point back at this server
+struct UrlSigner {
+    public_url: String,
+    key: Vec<u8>,
+    ttl: Duration,
+}
+
+impl UrlSigner {
+    fn mac(
+        &self,
+        object_key: &str,
+        expires: i64,
+        filename: &str,
+        content_type: &str,
+    ) -> HmacSha256 {
+        let mut mac =
+            HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(
+            format!(
+                "{}\n{}\n{}\n{}",
+                object_key, expires, filename, content_type
+            )
+            .as_bytes(),
+        );
+        mac
+    }
+}
+
+/// Files on local disk under a root directory, addressed by `/`-separated keys.
+///
+/// Content is streamed chunk by chunk, so memory use does not grow with file size.
+/// Presigned URLs are served by this server's /api/storage routes, signed with
+/// a key set by [`LocalStorage::with_signed_urls`].
+#[derive(Clone)]
+pub struct LocalStorage {
+    root: Arc<PathBuf>,
+    signer: Option<Arc<UrlSigner>>,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: Arc::new(root.into()),
+            signer: None,
+        }
+    }
+
+    /// Sign download URLs under `public_url` with `key`, valid for `ttl`
+    pub fn with_signed_urls(mut self, public_url: &str, key: Vec<u8>, ttl: Duration) -> Self {
+        self.signer = Some(Arc::new(UrlSigner {
+            public_url: public_url.trim_end_matches('/').to_string(),
+            key,
+            ttl,
+        }));
+        self
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, StorageError> {
+        validate_key(key)?;
+        Ok(self.root.join(key))
+    }
+
+    /// Stream into `key`, replacing any existing content only once the whole
+    /// stream has been written. Nothing is left behind if the stream fails or
+    /// exceeds `max_bytes`.
+    pub async fn write<S, E>(
+        &self,
+        key: &str,
+        stream: S,
+        max_bytes: u64,
+    ) -> Result<StoredObject, StorageError>
+    where
+        S: Stream<Item = Result<Bytes, E>>,
+        E: std::fmt::Display,
+    {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let partial = path.with_file_name(format!(
+            "{}.partial",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        ));
+
+        let mut file = File::create(&partial).await?;
+        match copy_stream(&mut file, stream, max_bytes).await {
+            Ok((size, hasher)) => {
+                file.sync_all().await?;
+                fs::rename(&partial, &path).await?;
+                Ok(StoredObject {
+                    size,
+                    sha256: hex::encode(hasher.finalize()),
+                })
+            }
+            Err(e) => {
+                drop(file);
+                let _ = fs::remove_file(&partial).await;
+                Err(e)
+            }
+        }
+    }
+
+    /// Append a chunk to `key`, which must currently hold exactly `offset` bytes.
+    /// A chunk is all or nothing: on failure the content is cut back to `offset`.
+    /// Returns the new length.
+    pub async fn append<S, E>(
+        &self,
+        key: &str,
+        offset: u64,
+        stream: S,
+        max_bytes: u64,
+    ) -> Result<u64, StorageError>
+    where
+        S: Stream<Item = Result<Bytes, E>>,
+        E: std::fmt::Display,
+    {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await?;
+        let current = file.metadata().await?.len();
+        if current != offset {
+            return Err(StorageError::OffsetMismatch {
+                expected: current,
+                actual: offset,
+            });
+        }
+
+        match copy_stream(&mut file, stream, max_bytes).await {
+            Ok((written, _)) => {
+                file.sync_all().await?;
+                Ok(offset + written)
+            }
+            Err(e) => {
+                file.set_len(offset).await?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Current length of `key`, or 0 if it doesn't exist
+    pub async fn len(&self, key: &str) -> Result<u64, StorageError> {
+        match fs::metadata(self.path(key)?).await {
+            Ok(meta) => Ok(meta.len()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Hex-encoded SHA-256 of the stored content
+    pub async fn sha256(&self, key: &str) -> Result<String, StorageError> {
+        let mut file = File::open(self.path(key)?).await?;
+        let mut hasher = Sha256::new();
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            let n = file.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+        }
+        Ok(hex::encode(hasher.finalize()))
+    }
+
+    pub async fn open(&self, key: &str) -> Result<File, StorageError> {
+        Ok(File::open(self.path(key)?).await?)
+    }
+
+    /// Remove `key`; missing keys are not an error
+    pub async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        match fs::remove_file(self.path(key)?).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Open `key` for a download link made by [`ObjectStorage::presigned_url`]
+    pub async fn open_signed(
+        &self,
+        key: &str,
+        download: &SignedDownload,
+    ) -> Result<File, StorageError> {
+        let signer = self.signer.as_ref().ok_or(StorageError::InvalidSignature)?;
+        let signature =
+            hex::decode(&download.signature).map_err(|_| StorageError::InvalidSignature)?;
+        signer
+            .mac(
+                key,
+                download.expires,
+                &download.filename,
+                &download.content_type,
+            )
+            .verify_slice(&signature)
+            .map_err(|_| StorageError::InvalidSignature)?;
+        if download.expires < Utc::now().timestamp() {
+            return Err(StorageError::InvalidSignature);
+        }
+        self.open(key).await
+    }
+}
+
+#[async_trait]
+impl ObjectStorage for LocalStorage {
+    fn name(&self) -> &str {
+        "local"
+    }
+
+    async fn put(
+        &self,
+        key: &str,
+        body: ByteStream<'_>,
+        max_bytes: u64,
+    ) -> Result<StoredObject, StorageError> {
+        // Keep stream errors' own message instead of wrapping it twice
+        let body = body.map_err(|e| match e {
+            StorageError::Stream(message) => message,
+            e => e.to_string(),
+        });
+        self.write(key, body, max_bytes).await
+    }
+
+    async fn get(&self, key: &str) -> Result<ByteStream<'static>, StorageError> {
+        let file = self.open(key).await?;
+        Ok(ReaderStream::new(file).map_err(StorageError::from).boxed())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        LocalStorage::delete(self, key).await
+    }
+
+    async fn presigned_url(
+        &self,
+        key: &str,
+        download: Download<'_>,
+    ) -> Result<PresignedUrl, StorageError> {
+        validate_key(key)?;
+        let signer = self.signer.as_ref().ok_or_else(|| {
+            StorageError::Remote("local storage has no URL signing key".to_string())
+        })?;
+        let expires_at = Utc::now() + chrono::Duration::seconds(signer.ttl.as_secs() as i64);
+        let expires = expires_at.timestamp();
+        let signature = signer
+            .mac(key, expires, download.filename, download.content_type)
+            .finalize()
+            .into_bytes();
+        Ok(PresignedUrl {
+            url: format!(
+                "{}/objects/{}?expires={}&filename={}&content_type={}&signature={}",
+                signer.public_url,
+                uri_encode(key, true),
+                expires,
+                uri_encode(download.filename, false),
+                uri_encode(download.content_type, false),
+                hex::encode(signature)
+            ),
+            expires_at,
+        })
+    }
+
+    fn as_local(&self) -> Option<&LocalStorage> {
+        Some(self)
+    }
+}
+
+async fn copy_stream<S, E>(
+    file: &mut File,
+    stream: S,
+    max_bytes: u64,
+) -> Result<(u64, Sha256), StorageError>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+    E: std::fmt::Display,
+{
+    let mut stream = std::pin::pin!(stream);
+    let mut hasher = Sha256::new();
+    let mut written: u64 = 0;
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| StorageError::Stream(e.to_string()))?;
+        written += chunk.len() as u64;
+        if written > max_bytes {
+            return Err(StorageError::TooLarge { limit: max_bytes });
+        }
+        hasher.update(&chunk);
+        file.write_all(&chunk).await?;
+    }
+    file.flush().await?;
+    Ok((written, hasher))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunks(parts: &[&'static str]) -> impl Stream<Item = Result<Bytes, String>> {
+        futures::stream::iter(
+            parts
+                .iter()
+                .map(|p| Ok(Bytes::from_static(p.as_bytes())))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    fn storage() -> (LocalStorage, PathBuf) {
+        let root = std::env::temp_dir().join(format!("storage-test-{}", uuid::Uuid::new_v4()));
+        (LocalStorage::new(&root), root)
+    }
+
+    #[test]
+    fn test_keys_cannot_escape_root() {
+        let (storage, _) = storage();
+        assert!(storage.path("uploads/abc.bin").is_ok());
+        for key in ["", "../etc/passwd", "uploads//x", "/abs", "a/./b", "a b"] {
+            assert!(
+                matches!(storage.path(key), Err(StorageError::InvalidKey)),
+                "{key}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_hashes_and_enforces_limit() {
+        let (storage, root) = storage();
+        let stored = storage
+            .write("a/file", chunks(&["hello ", "world"]), 100)
+            .await
+            .unwrap();
+        assert_eq!(stored.size, 11);
+        assert_eq!(
+            stored.sha256,
+            "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+        );
+        assert_eq!(storage.sha256("a/file").await.unwrap(), stored.sha256);
+
+        let result = storage
+            .write("a/other", chunks(&["hello ", "world"]), 8)
+            .await;
+        assert!(matches!(result, Err(StorageError::TooLarge { limit: 8 })));
+        assert_eq!(storage.len("a/other").await.unwrap(), 0);
+        assert!(!root.join("a/other.partial").exists());
+        let _ = fs::remove_dir_all(root).await;
+    }
+
+    #[tokio::test]
+    async fn test_presigned_urls_are_checked() {
+        let (storage, root) = storage();
+        let storage = storage.with_signed_urls(
+            "http://localhost/api/storage/",
+            b"secret".to_vec(),
+            Duration::from_secs(60),
+        );
+        storage
+            .write("exports/b.json", chunks(&["{}"]), 10)
+            .await
+            .unwrap();
+        let presigned = storage
+            .presigned_url(
+                "exports/b.json",
+                Download {
+                    filename: "b \"1\".json",
+                    content_type: "application/json",
+                },
+            )
+            .await
+            .unwrap();
+        let uri: axum::http::Uri = presigned.url.parse().unwrap();
+        assert_eq!(uri.path(), "/api/storage/objects/exports/b.json");
+        let axum::extract::Query(download) =
+            axum::extract::Query::<SignedDownload>::try_from_uri(&uri).unwrap();
+        assert_eq!(download.filename, "b \"1\".json");
+        assert!(storage
+            .open_signed("exports/b.json", &download)
+            .await
+            .is_ok());
+
+        for tampered in [
+            SignedDownload {
+                content_type: "text/html".to_string(),
+                ..download.clone()
+            },
+            SignedDownload {
+                expires: download.expires + 3600,
+                ..download.clone()
+            },
+        ] {
+            assert!(matches!(
+                storage.open_signed("exports/b.json", &tampered).await,
+                Err(StorageError::InvalidSignature)
+            ));
+        }
+        assert!(matches!(
+            storage.open_signed("exports/other.json", &download).await,
+            Err(StorageError::InvalidSignature)
+        ));
+        let _ = fs::remove_dir_all(root).await;
+    }
+
+    #[tokio::test]
+    async fn test_append_requires_offset_and_rolls_back() {
+        let (storage, root) = storage();
+        assert_eq!(
+            storage.append("f", 0, chunks(&["abc"]), 10).await.unwrap(),
+            3
+        );
+        assert!(matches!(
+            storage.append("f", 0, chunks(&["def"]), 10).await,
+            Err(StorageError::OffsetMismatch {
+                expected: 3,
+                actual: 0
+            })
+        ));
+
+        let failing = futures::stream::iter(vec![
+            Ok(Bytes::from_static(b"de")),
+            Err("connection reset".to_string()),
+        ]);
+        assert!(matches!(
+            storage.append("f", 3, failing, 10).await,
+            Err(StorageError::Stream(_))
+        ));
+        assert_eq!(storage.len("f").await.unwrap(), 3);
+
+        assert_eq!(
+            storage.append("f", 3, chunks(&["def"]), 10).await.unwrap(),
+            6
+        );
+        assert_eq!(
+            storage.sha256("f").await.unwrap(),
+            hex::encode(Sha256::digest(b"abcdef"))
+        );
+        let _ = fs::remove_dir_all(root).await;
+    }
+}
@@ -0,0 +1,160 @@
+//! Object storage for uploaded files and export bundles, on local disk or in an
+//! S3-compatible bucket, chosen by `[storage]` in the configuration.
+
+mod local;
+mod s3;
+
+pub use local::{LocalStorage, SignedDownload};
+pub use s3::{S3Credentials, S3Storage};
+
+use crate::config::StorageConfig;
+use crate::utils::http_client::OutboundClient;
+use axum::async_trait;
+use axum::body::Bytes;
+use chrono::{DateTime, Utc};
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use rand::Rng;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum StorageError {
+    #[error("storage I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid storage key")]
+    InvalidKey,
+    #[error("upload exceeds the {limit} byte limit")]
+    TooLarge { limit: u64 },
+    #[error("upload is at offset {expected}, not {actual}")]
+    OffsetMismatch { expected: u64, actual: u64 },
+    #[error("upload stream failed: {0}")]
+    Stream(String),
+    #[error("object store request failed: {0}")]
+    Remote(String),
+    #[error("download link is invalid or has expired")]
+    InvalidSignature,
+}
+
+/// What was written by [`ObjectStorage::put`]
+#[derive(Debug, Clone)]
+pub struct StoredObject {
+    pub size: u64,
+    /// Hex-encoded SHA-256 of the content
+    pub sha256: String,
+}
+
+/// Content streamed into or out of storage
+pub type ByteStream<'a> = BoxStream<'a, Result<Bytes, StorageError>>;
+
+/// How a presigned download is served
+#[derive(Debug, Clone, Copy)]
+pub struct Download<'a> {
+    pub filename: &'a str,
+    pub content_type: &'a str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PresignedUrl {
+    pub url: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait ObjectStorage: Send + Sync {
+    /// "local" or "s3"
+    fn name(&self) -> &str;
+
+    /// Stream `body` into `key`, replacing any existing object only once the
+    /// whole stream has arrived. Nothing is stored if the stream fails or
+    /// exceeds `max_bytes`.
+    async fn put(
+        &self,
+        key: &str,
+        body: ByteStream<'_>,
+        max_bytes: u64,
+    ) -> Result<StoredObject, StorageError>;
+
+    async fn get(&self, key: &str) -> Result<ByteStream<'static>, StorageError>;
+
+    /// Remove `key`; missing keys are not an error
+    async fn delete(&self, key: &str) -> Result<(), StorageError>;
+
+    /// A URL that downloads `key` without further authentication until it
+    /// expires, as an attachment named `download.filename`
+    async fn presigned_url(
+        &self,
+        key: &str,
+        download: Download<'_>,
+    ) -> Result<PresignedUrl, StorageError>;
+
+    /// The local store, when its presigned URLs are served by this server
+    fn as_local(&self) -> Option<&LocalStorage> {
+        None
+    }
+}
+
+/// Build the configured store. Fails on an unknown backend or missing
+/// settings, so a misconfigured deployment is caught at startup.
+pub fn build_storage(
+    config: &StorageConfig,
+    http: &OutboundClient,
+) -> Result<Arc<dyn ObjectStorage>, String> {
+    let ttl = Duration::from_secs(config.presign_ttl_secs.max(1));
+    match config.backend.as_str() {
+        "local" => {
+            let key = match std::env::var(&config.url_signing_key_env) {
+                Ok(key) if !key.is_empty() => key.into_bytes(),
+                _ => {
+                    tracing::warn!(
+                        "{} is not set; local download links only work until restart",
+                        config.url_signing_key_env
+                    );
+                    rand::thread_rng().gen::<[u8; 32]>().to_vec()
+                }
+            };
+            Ok(Arc::new(
+                LocalStorage::new(&config.local_dir).with_signed_urls(&config.public_url, key, ttl),
+            ))
+        }
+        "s3" => Ok(Arc::new(S3Storage::from_config(config, http.clone())?)),
+        other => Err(format!(
+            "unknown storage backend '{}', expected 'local' or 's3'",
+            other
+        )),
+    }
+}
+
+/// Keys may only contain plain path segments, so they can't escape a root
+/// directory or a bucket prefix
+pub fn validate_key(key: &str) -> Result<(), StorageError> {
+    let valid = !key.is_empty()
+        && key.split('/').all(|segment| {
+            !segment.is_empty()
+                && segment != "."
+                && segment != ".."
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        });
+    if valid {
+        Ok(())
+    } else {
+        Err(StorageError::InvalidKey)
+    }
+}
+
+/// Content already in memory, as a stream for [`ObjectStorage::put`]
+pub fn bytes_stream(data: impl Into<Bytes>) -> ByteStream<'static> {
+    futures::stream::once(futures::future::ready(Ok(data.into()))).boxed()
+}
+
+/// `Content-Disposition` value offering `filename` as a download
+pub fn content_disposition(filename: &str) -> String {
+    format!(
+        "attachment; filename=\"{}\"",
+        filename.replace(['"', '\\', '\r', '\n'], "_")
+    )
+}
@@ -0,0 +1,44 @@
+use axum::{
+    body::{Body, Bytes},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use futures::{Stream, StreamExt};
+use serde::Serialize;
+
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Rows are handed from the database task to the response through a bounded
+/// channel of this size, so a slow client applies backpressure to the cursor
+/// instead of the whole export being buffered in memory.
+pub const EXPORT_CHANNEL_CAPACITY: usize = 256;
+
+/// Stream items as newline-delimited JSON.
+///
+/// An error mid-stream aborts the body, so the client sees a truncated transfer
+/// rather than a silently incomplete export.
+pub fn ndjson_response<T, E, S>(stream: S, filename: &str) -> Response
+where
+    T: Serialize + Send + 'static,
+    E: std::fmt::Display + Send + 'static,
+    S: Stream<Item = Result<T, E>> + Send + 'static,
+{
+    let body = stream.map(|item| {
+        let item = item.map_err(|e| std::io::Error::other(e.to_string()))?;
+        let mut line = serde_json::to_vec(&item).map_err(std::io::Error::other)?;
+        line.push(b'\n');
+        Ok::<_, std::io::Error>(Bytes::from(line))
+    });
+
+    (
+        [
+            (header::CONTENT_TYPE, NDJSON_CONTENT_TYPE.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        Body::from_stream(body),
+    )
+        .into_response()
+}
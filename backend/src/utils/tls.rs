@@ -0,0 +1,131 @@
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use axum_server::Handle;
+use rustls_acme::caches::DirCache;
+use rustls_acme::AcmeConfig;
+use std::io;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use tokio_stream::StreamExt;
+
+use crate::config::TlsConfig;
+use crate::utils::shutdown::Shutdown;
+
+/// Serve `app` over HTTPS until shutdown is triggered.
+///
+/// Certificates come from Let's Encrypt when `acme_domains` is set, otherwise from
+/// the PEM files at `cert_path`/`key_path`, which are re-read when they change.
+pub async fn serve(
+    config: TlsConfig,
+    addr: SocketAddr,
+    app: Router,
+    shutdown: Shutdown,
+) -> io::Result<()> {
+    let handle = Handle::new();
+    let stop = handle.clone();
+    let cancelled = shutdown.cancelled();
+    tokio::spawn(async move {
+        cancelled.await;
+        // Stop accepting; in-flight requests are bounded by the caller's drain deadline
+        stop.graceful_shutdown(None);
+    });
+
+    let make_service = app.into_make_service_with_connect_info::<SocketAddr>();
+    if config.acme_domains.is_empty() {
+        let rustls = load_files(&config).await?;
+        start_reload_task(rustls.clone(), config, shutdown);
+        axum_server::bind_rustls(addr, rustls)
+            .handle(handle)
+            .serve(make_service)
+            .await
+    } else {
+        let mut state = AcmeConfig::new(config.acme_domains.clone())
+            .contact(config.acme_contacts.iter().map(|e| format!("mailto:{}", e)))
+            .cache(DirCache::new(config.acme_cache_dir.clone()))
+            .directory_lets_encrypt(config.acme_production)
+            .state();
+        let acceptor = state.axum_acceptor(state.default_rustls_config());
+
+        // Driving the state performs the initial order and every renewal after it
+        shutdown.clone().spawn(async move {
+            loop {
+                tokio::select! {
+                    event = state.next() => match event {
+                        Some(Ok(event)) => tracing::info!("ACME: {:?}", event),
+                        Some(Err(e)) => tracing::error!("ACME error: {:?}", e),
+                        None => break,
+                    },
+                    _ = shutdown.cancelled() => break,
+                }
+            }
+        });
+
+        axum_server::bind(addr)
+            .acceptor(acceptor)
+            .handle(handle)
+            .serve(make_service)
+            .await
+    }
+}
+
+async fn load_files(config: &TlsConfig) -> io::Result<RustlsConfig> {
+    if config.cert_path.is_empty() || config.key_path.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "TLS is enabled but neither cert_path/key_path nor acme_domains are set",
+        ));
+    }
+    RustlsConfig::from_pem_file(&config.cert_path, &config.key_path)
+        .await
+        .map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!(
+                    "Failed to load TLS certificate {} / key {}: {}",
+                    config.cert_path, config.key_path, e
+                ),
+            )
+        })
+}
+
+/// Latest modification time of the certificate and key files
+fn modified(config: &TlsConfig) -> Option<SystemTime> {
+    let mtime = |path: &str| std::fs::metadata(Path::new(path)).and_then(|m| m.modified());
+    Some(
+        mtime(&config.cert_path)
+            .ok()?
+            .max(mtime(&config.key_path).ok()?),
+    )
+}
+
+/// Pick up rotated certificates (e.g. from certbot or cert-manager) without a
+/// restart. A pair that fails to load is logged and the current one kept.
+fn start_reload_task(rustls: RustlsConfig, config: TlsConfig, shutdown: Shutdown) {
+    let interval_secs = config.reload_interval_secs.max(1);
+    shutdown.clone().spawn(async move {
+        let mut last_modified = modified(&config);
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.cancelled() => break,
+            }
+
+            let current = modified(&config);
+            if current.is_none() || current == last_modified {
+                continue;
+            }
+            match rustls
+                .reload_from_pem_file(&config.cert_path, &config.key_path)
+                .await
+            {
+                Ok(()) => {
+                    tracing::info!("Reloaded TLS certificate from {}", config.cert_path);
+                    last_modified = current;
+                }
+                Err(e) => tracing::error!("Failed to reload TLS certificate: {}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,275 @@
+//! Request body validation.
+//!
+//! Request DTOs derive `validator::Validate` and handlers take them through
+//! [`ValidatedJson`] instead of `Json`. A body that parses but breaks a rule
+//! gets a 400 with code `validation_failed` and one entry per failing field:
+//!
+//! ```json
+//! {
+//!   "error": "password: length must be at least 8",
+//!   "code": "validation_failed",
+//!   "details": {
+//!     "fields": [
+//!       {
+//!         "field": "password",
+//!         "code": "length",
+//!         "message": "length must be at least 8",
+//!         "params": { "min": 8 }
+//!       }
+//!     ]
+//!   }
+//! }
+//! ```
+//!
+//! `field` is the JSON path of the value (`items[2].name` for nested and list
+//! fields), `code` is the validator rule that failed and `params` are its
+//! arguments. Messages come from the `validation` catalog in the request's
+//! locale (see utils::i18n). The rejected value itself is never echoed back.
+//!
+//! Bodies that don't parse are rejected by `Json` as before.
+
+use axum::{
+    async_trait,
+    extract::{FromRequest, Request},
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+use validator::{Validate, ValidationError, ValidationErrors, ValidationErrorsKind};
+
+use crate::utils::api_error::ApiError;
+use crate::utils::i18n::{self, validation_catalog};
+
+/// `Json<T>` that also checks `T`'s validation rules
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValidatedJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let locale = i18n::locale_from_headers(request.headers());
+        let Json(value) = Json::<T>::from_request(request, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        match value.validate() {
+            Ok(()) => Ok(Self(value)),
+            Err(errors) => Err((
+                [(header::CONTENT_LANGUAGE, HeaderValue::from_static(locale))],
+                validation_error(&errors, locale),
+            )
+                .into_response()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub code: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub params: Map<String, Value>,
+}
+
+/// The 400 response for `errors`, with messages in `locale`
+pub fn validation_error(errors: &ValidationErrors, locale: &str) -> ApiError {
+    let fields = field_errors(errors, locale);
+    let message = fields
+        .iter()
+        .map(|error| format!("{}: {}", error.field, error.message))
+        .collect::<Vec<_>>()
+        .join("; ");
+    ApiError::new(StatusCode::BAD_REQUEST, "validation_failed", message)
+        .with_detail("fields", fields)
+}
+
+/// `errors` flattened to one entry per failing rule, ordered by field
+pub fn field_errors(errors: &ValidationErrors, locale: &str) -> Vec<FieldError> {
+    let mut fields = Vec::new();
+    collect(errors, "", locale, &mut fields);
+    fields.sort_by(|a, b| a.field.cmp(&b.field));
+    fields
+}
+
+fn collect(errors: &ValidationErrors, prefix: &str, locale: &str, out: &mut Vec<FieldError>) {
+    for (field, kind) in errors.errors() {
+        let path = if prefix.is_empty() {
+            field.to_string()
+        } else {
+            format!("{prefix}.{field}")
+        };
+        match kind {
+            ValidationErrorsKind::Field(errors) => {
+                out.extend(errors.iter().map(|error| field_error(&path, error, locale)))
+            }
+            ValidationErrorsKind::Struct(errors) => collect(errors, &path, locale, out),
+            ValidationErrorsKind::List(items) => {
+                for (index, errors) in items {
+                    collect(errors, &format!("{path}[{index}]"), locale, out);
+                }
+            }
+        }
+    }
+}
+
+fn field_error(field: &str, error: &ValidationError, locale: &str) -> FieldError {
+    let params: Map<String, Value> = error
+        .params
+        .iter()
+        .filter(|(name, _)| *name != "value")
+        .map(|(name, value)| (name.to_string(), whole_number(value)))
+        .collect();
+    let catalog = validation_catalog();
+    let format = |key: &str| {
+        catalog.format(
+            locale,
+            key,
+            params.iter().map(|(name, value)| (name.as_str(), value)),
+        )
+    };
+    // Catalog first, so rule messages are translated too; a custom rule's own
+    // message is only used when the catalog doesn't know its code
+    let message = format(&message_key(error))
+        .or_else(|| error.message.as_ref().map(|message| message.to_string()))
+        .or_else(|| format("invalid"))
+        .unwrap_or_else(|| error.code.to_string());
+    FieldError {
+        field: field.to_string(),
+        code: error.code.to_string(),
+        message,
+        params,
+    }
+}
+
+/// Catalog key for an error. `length` and `range` take one of `min`, `max`
+/// or both, so each combination has its own message.
+fn message_key(error: &ValidationError) -> String {
+    let has = |param: &str| error.params.contains_key(param);
+    match error.code.as_ref() {
+        code @ ("length" | "range") => match (has("equal"), has("min"), has("max")) {
+            (true, _, _) => format!("{code}.equal"),
+            (_, true, true) => format!("{code}.between"),
+            (_, true, false) => format!("{code}.min"),
+            (_, false, true) => format!("{code}.max"),
+            _ => code.to_string(),
+        },
+        code => code.to_string(),
+    }
+}
+
+/// `range` bounds arrive as floats; show `1` rather than `1.0`
+fn whole_number(value: &Value) -> Value {
+    match value.as_f64() {
+        Some(number) if value.is_f64() && number.fract() == 0.0 && number.abs() < 1e15 => {
+            Value::from(number as i64)
+        }
+        _ => value.clone(),
+    }
+}
+
+/// Custom rule for text that must have something besides whitespace
+pub fn not_blank(value: &str) -> Result<(), ValidationError> {
+    if value.trim().is_empty() {
+        return Err(ValidationError::new("not_blank"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Debug, Validate)]
+    struct Item {
+        #[validate(custom = "not_blank")]
+        name: String,
+    }
+
+    #[derive(Debug, Validate)]
+    struct Input {
+        #[validate(email)]
+        email: String,
+        #[validate(length(min = 8))]
+        password: String,
+        #[validate(range(min = 1, max = 100))]
+        limit: u32,
+        #[validate]
+        items: Vec<Item>,
+    }
+
+    fn invalid() -> ValidationErrors {
+        Input {
+            email: "not-an-email".to_string(),
+            password: "short".to_string(),
+            limit: 0,
+            items: vec![
+                Item {
+                    name: "ok".to_string(),
+                },
+                Item {
+                    name: "  ".to_string(),
+                },
+            ],
+        }
+        .validate()
+        .unwrap_err()
+    }
+
+    #[test]
+    fn test_field_errors_are_flattened_and_localized() {
+        let fields = field_errors(&invalid(), "en");
+        let summary: Vec<_> = fields
+            .iter()
+            .map(|f| (f.field.as_str(), f.code.as_str(), f.message.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("email", "email", "must be a valid email address"),
+                ("items[1].name", "not_blank", "must not be blank"),
+                ("limit", "range", "must be between 1 and 100"),
+                ("password", "length", "length must be at least 8"),
+            ]
+        );
+        // Params go back to the client, the rejected value doesn't
+        assert_eq!(fields[3].params, *json!({ "min": 8 }).as_object().unwrap());
+        assert_eq!(
+            fields[2].params,
+            *json!({ "min": 1, "max": 100 }).as_object().unwrap()
+        );
+
+        let fields = field_errors(&invalid(), "nb");
+        assert_eq!(fields[3].message, "lengden må være minst 8");
+    }
+
+    #[test]
+    fn test_validation_error_envelope() {
+        let error = validation_error(&invalid(), "en");
+        assert_eq!(error.status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.code, "validation_failed");
+        assert!(error
+            .message
+            .starts_with("email: must be a valid email address; items[1].name:"));
+        assert_eq!(error.details["fields"].as_array().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_unknown_codes_use_their_message() {
+        let mut error = ValidationError::new("weekday");
+        let field = field_error("day", &error, "en");
+        assert_eq!(field.message, "is invalid");
+
+        error.message = Some("must be a weekday".into());
+        let field = field_error("day", &error, "nb");
+        assert_eq!(field.message, "must be a weekday");
+    }
+}
@@ -0,0 +1,362 @@
+use sqlx::PgPool;
+use template_repo_backend::cli::seed::DEMO_TENANTS;
+use template_repo_backend::cli::{Admin, RetentionJob};
+use template_repo_backend::features::auth::models::LoginUser;
+use template_repo_backend::features::ontology::models::{
+    CreateClassInput, ExportedClass, OntologyExport, OntologyVersionStatus,
+};
+use uuid::Uuid;
+
+mod common;
+
+fn admin(pool: &PgPool) -> Admin {
+    Admin::new(pool.clone(), common::create_test_config())
+}
+
+#[sqlx::test]
+async fn test_migration_status_reports_pending_and_modified(pool: PgPool) {
+    let admin = admin(&pool);
+    assert!(admin.migration_status().await.unwrap().is_empty());
+
+    let versions = sqlx::query_scalar::<_, i64>(
+        "SELECT version FROM _sqlx_migrations ORDER BY version DESC LIMIT 2",
+    )
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    sqlx::query("DELETE FROM _sqlx_migrations WHERE version = $1")
+        .bind(versions[0])
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("UPDATE _sqlx_migrations SET checksum = '\\x00' WHERE version = $1")
+        .bind(versions[1])
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let status = admin.migration_status().await.unwrap();
+    assert_eq!(status.len(), 2);
+    assert!(status
+        .iter()
+        .any(|m| m.version == versions[0] && !m.modified));
+    assert!(status
+        .iter()
+        .any(|m| m.version == versions[1] && m.modified));
+}
+
+#[sqlx::test]
+async fn test_verify_schema_reports_unknown_migrations_and_missing_functions(pool: PgPool) {
+    let admin = admin(&pool);
+    let report = admin.verify_schema().await.unwrap();
+    assert!(!report.has_drift(), "{:?}", report.problems());
+
+    sqlx::query(
+        "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time) \
+         VALUES (99991231000000, 'from a newer build', TRUE, '\\x00', 0)",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    sqlx::query(
+        r#"
+        DO $$
+        DECLARE f regprocedure;
+        BEGIN
+            FOR f IN SELECT oid::regprocedure FROM pg_proc WHERE proname = 'get_user_entity_permissions' LOOP
+                EXECUTE 'DROP FUNCTION ' || f;
+            END LOOP;
+        END $$
+        "#,
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let report = admin.verify_schema().await.unwrap();
+    assert!(report.migrations.is_empty());
+    assert_eq!(report.unknown_migrations.len(), 1);
+    assert_eq!(report.unknown_migrations[0].version, 99991231000000);
+    assert_eq!(report.missing_functions, vec!["get_user_entity_permissions"]);
+}
+
+#[sqlx::test]
+async fn test_create_superadmin_creates_or_promotes(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let admin = admin(&pool);
+
+    let (user_id, created) = admin
+        .create_superadmin("root@example.com", "root", "Password123!")
+        .await
+        .unwrap();
+    assert!(created);
+    let roles = services
+        .abac_service
+        .get_user_roles(&user_id.to_string())
+        .await
+        .unwrap();
+    assert!(roles.iter().any(|r| r.role_name == "superadmin"));
+
+    // Running it again for the same email only makes sure of the role
+    let (again, created) = admin
+        .create_superadmin("root@example.com", "someone-else", "ignored")
+        .await
+        .unwrap();
+    assert_eq!(again, user_id);
+    assert!(!created);
+}
+
+#[sqlx::test]
+async fn test_ontology_export_imports_as_draft(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let admin = admin(&pool);
+    let unit = services
+        .ontology_service
+        .create_class(
+            CreateClassInput {
+                name: "CliUnit".to_string(),
+                description: Some("A military unit".to_string()),
+                parent_class_id: None,
+                is_abstract: Some(true),
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    let company = services
+        .ontology_service
+        .create_class(
+            CreateClassInput {
+                name: "CliCompany".to_string(),
+                description: None,
+                parent_class_id: Some(unit.id),
+                is_abstract: None,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    // Published versions can't be edited through the service
+    sqlx::query(
+        r#"
+        INSERT INTO properties (name, class_id, data_type, reference_class_id, is_required, version_id)
+        VALUES ('commander', $1, 'reference', $2, TRUE, $3),
+               ('strength', $1, 'integer', NULL, FALSE, $3)
+        "#,
+    )
+    .bind(company.id)
+    .bind(unit.id)
+    .bind(company.version_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let export = admin.export_ontology().await.unwrap();
+    let exported = export
+        .classes
+        .iter()
+        .find(|c| c.name == "CliCompany")
+        .unwrap();
+    assert_eq!(exported.parent.as_deref(), Some("CliUnit"));
+    assert_eq!(exported.properties.len(), 2);
+    assert_eq!(
+        exported.properties[0].reference_class.as_deref(),
+        Some("CliUnit")
+    );
+
+    // A round trip through JSON, as the command does
+    let export: OntologyExport =
+        serde_json::from_str(&serde_json::to_string(&export).unwrap()).unwrap();
+    let summary = admin
+        .import_ontology(&export, "2.0.0-import", false)
+        .await
+        .unwrap();
+    assert_eq!(summary.version.status, OntologyVersionStatus::DRAFT);
+    assert_eq!(summary.classes, export.classes.len());
+    assert_eq!(
+        summary.properties,
+        export
+            .classes
+            .iter()
+            .map(|c| c.properties.len())
+            .sum::<usize>()
+    );
+
+    let (parent_name, reference_name) = sqlx::query_as::<_, (String, String)>(
+        r#"
+        SELECT p.name, rc.name FROM classes c
+        JOIN classes p ON p.id = c.parent_class_id
+        JOIN properties pr ON pr.class_id = c.id AND pr.name = 'commander'
+        JOIN classes rc ON rc.id = pr.reference_class_id
+        WHERE c.name = 'CliCompany' AND c.version_id = $1
+          AND p.version_id = $1 AND rc.version_id = $1
+        "#,
+    )
+    .bind(summary.version.id)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(parent_name, "CliUnit");
+    assert_eq!(reference_name, "CliUnit");
+
+    // Unknown references leave nothing behind
+    let broken = OntologyExport {
+        classes: vec![ExportedClass {
+            parent: Some("NoSuchClass".to_string()),
+            ..export.classes[0].clone()
+        }],
+        ..export.clone()
+    };
+    assert!(admin
+        .import_ontology(&broken, "3.0.0", false)
+        .await
+        .is_err());
+    let versions = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM ontology_versions WHERE version = '3.0.0'",
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(versions, 0);
+
+    // Publishing makes the import the current version
+    let published = admin
+        .import_ontology(&export, "2.1.0-import", true)
+        .await
+        .unwrap();
+    assert!(published.version.is_current);
+}
+
+#[sqlx::test]
+async fn test_break_glass_is_granted_once(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let admin = admin(&pool);
+    let (user_id, _) = admin
+        .create_superadmin("oncall@example.com", "oncall", "Password123!")
+        .await
+        .unwrap();
+
+    assert!(admin
+        .grant_break_glass("oncall@example.com", "  ".to_string(), None)
+        .await
+        .is_err());
+    assert!(admin
+        .grant_break_glass("nobody@example.com", "Outage".to_string(), None)
+        .await
+        .is_err());
+    assert!(admin
+        .grant_break_glass(&Uuid::new_v4().to_string(), "Outage".to_string(), None)
+        .await
+        .is_err());
+
+    let session = admin
+        .grant_break_glass("oncall@example.com", "Outage".to_string(), Some(30))
+        .await
+        .unwrap();
+    assert_eq!(session.user_id, user_id);
+    assert_eq!(session.user_agent.as_deref(), Some("ontology-admin"));
+    assert_eq!(
+        (session.expires_at - session.activated_at).num_minutes(),
+        30
+    );
+    assert!(services
+        .firefighter_service
+        .get_active_session(user_id)
+        .await
+        .unwrap()
+        .is_some());
+
+    // One session at a time, whichever way it was activated
+    assert!(admin
+        .grant_break_glass(&user_id.to_string(), "Outage".to_string(), None)
+        .await
+        .is_err());
+}
+
+#[sqlx::test]
+async fn test_retention_jobs_run(pool: PgPool) {
+    let admin = admin(&pool);
+    sqlx::query(
+        r#"
+        INSERT INTO idempotency_keys (principal, idempotency_key, method, path, request_hash, expires_at)
+        VALUES ('cli', 'expired', 'POST', '/api/x', 'hash', NOW() - INTERVAL '1 hour')
+        "#,
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    for job in RetentionJob::ALL {
+        let removed = admin.run_retention(job).await.unwrap();
+        if job == RetentionJob::Idempotency {
+            assert_eq!(removed, 1);
+        }
+    }
+}
+
+#[sqlx::test]
+async fn test_seed_fills_a_fresh_database_once(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let admin = admin(&pool);
+
+    let summary = admin.seed_demo("Demo-Password-1", false).await.unwrap();
+    assert_eq!(summary.users.len(), 6);
+    assert_eq!(summary.classes, 3);
+    assert_eq!(summary.projects, 2);
+
+    // Demo users sign in with the shared password and hold their roles
+    let (email, role) = &summary.users[1];
+    assert_eq!(role, "editor");
+    let user_id = services
+        .auth_service
+        .login(
+            LoginUser {
+                identifier: email.clone(),
+                password: "Demo-Password-1".to_string(),
+                remember_me: None,
+            },
+            None,
+            None,
+        )
+        .await
+        .unwrap()
+        .user_id;
+    let roles = services
+        .abac_service
+        .get_user_roles(&user_id.to_string())
+        .await
+        .unwrap();
+    assert!(roles.iter().any(|r| r.role_name == "editor"));
+
+    // Each tenant's data stays in its tenant
+    for (tenant_id, _, _) in DEMO_TENANTS {
+        let (users, sites) = sqlx::query_as::<_, (i64, i64)>(
+            r#"
+            SELECT COUNT(*) FILTER (WHERE c.name = 'User'),
+                   COUNT(*) FILTER (WHERE c.name = 'Site')
+            FROM entities e JOIN classes c ON c.id = e.class_id
+            WHERE e.tenant_id = $1
+            "#,
+        )
+        .bind(tenant_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!((users, sites), (3, 2));
+    }
+
+    assert!(admin.seed_demo("Demo-Password-1", true).await.is_err());
+}
+
+#[sqlx::test]
+async fn test_seed_needs_force_when_users_exist(pool: PgPool) {
+    let admin = admin(&pool);
+    admin
+        .create_superadmin("root@example.com", "root", "Password123!")
+        .await
+        .unwrap();
+
+    assert!(admin.seed_demo("Demo-Password-1", false).await.is_err());
+    let summary = admin.seed_demo("Demo-Password-1", true).await.unwrap();
+    assert_eq!(summary.users.len(), 6);
+}
@@ -0,0 +1,234 @@
+use serde_json::json;
+use sqlx::PgPool;
+use template_repo_backend::features::auth::models::{
+    AuditLogQuery, LoginUser, RegisterUser, SessionListQuery,
+};
+use template_repo_backend::features::auth::service::AuthError;
+use template_repo_backend::utils::pagination::SortOrder;
+
+mod common;
+
+#[sqlx::test]
+async fn test_sessions_page_by_cursor_and_filter(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let auth = &services.auth_service;
+    for name in ["alice_sessions", "bob_sessions"] {
+        auth.register(RegisterUser {
+            username: name.to_string(),
+            email: format!("{}@example.com", name),
+            password: "Password123!".to_string(),
+        })
+        .await
+        .unwrap();
+    }
+    for (name, ip) in [
+        ("alice_sessions", "10.20.0.1"),
+        ("alice_sessions", "10.20.0.1"),
+        ("alice_sessions", "10.20.0.9"),
+        ("bob_sessions", "10.20.0.2"),
+    ] {
+        auth.login(
+            LoginUser {
+                identifier: name.to_string(),
+                password: "Password123!".to_string(),
+                remember_me: None,
+            },
+            Some(ip.to_string()),
+            Some("ListingTest/1.0".to_string()),
+        )
+        .await
+        .unwrap();
+    }
+
+    let query = SessionListQuery {
+        ip: Some("10.20.0.1".to_string()),
+        limit: Some(1),
+        ..Default::default()
+    };
+    let first = auth.list_all_sessions(&query).await.unwrap();
+    assert_eq!((first.total, first.items.len()), (2, 1));
+    assert!(first.has_more);
+    let second = auth
+        .list_all_sessions(&SessionListQuery {
+            cursor: first.next_cursor.clone(),
+            ..query
+        })
+        .await
+        .unwrap();
+    assert_eq!(second.items.len(), 1);
+    assert!(!second.has_more);
+    assert_eq!(second.next_cursor, None);
+    assert_ne!(first.items[0].id, second.items[0].id);
+    assert!(first.items[0].created_at >= second.items[0].created_at);
+
+    let bob = auth
+        .list_all_sessions(&SessionListQuery {
+            q: Some("bob_sess".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert!(bob.total >= 1);
+    assert!(bob.items.iter().all(|s| s.username == "bob_sessions"));
+
+    let by_name = auth
+        .list_all_sessions(&SessionListQuery {
+            q: Some("_sessions".to_string()),
+            sort: Some("username".to_string()),
+            order: Some(SortOrder::Asc),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let names: Vec<_> = by_name.items.iter().map(|s| s.username.as_str()).collect();
+    assert!(names.windows(2).all(|w| w[0] <= w[1]), "{:?}", names);
+    assert_eq!(names.first(), Some(&"alice_sessions"));
+    assert_eq!(names.last(), Some(&"bob_sessions"));
+
+    let future = auth
+        .list_all_sessions(&SessionListQuery {
+            from: Some(chrono::Utc::now() + chrono::Duration::hours(1)),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(future.total, 0);
+
+    assert!(matches!(
+        auth.list_all_sessions(&SessionListQuery {
+            sort: Some("password_hash".to_string()),
+            ..Default::default()
+        })
+        .await,
+        Err(AuthError::ValidationError(_))
+    ));
+}
+
+#[sqlx::test]
+async fn test_audit_logs_filter_sort_and_page(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let audit = &services.audit_service;
+    let user_id = services
+        .auth_service
+        .register(RegisterUser {
+            username: "audit_lister".to_string(),
+            email: "audit_lister@example.com".to_string(),
+            password: "Password123!".to_string(),
+        })
+        .await
+        .unwrap()
+        .user_id;
+    for (action, ip) in [
+        ("report.viewed", "192.0.2.10"),
+        ("report.exported", "192.0.2.10"),
+        ("reports.archived", "192.0.2.11"),
+        ("user.updated", "192.0.2.11"),
+    ] {
+        audit
+            .log(
+                user_id,
+                action,
+                "report",
+                None,
+                None,
+                None,
+                Some(json!({ "ip_address": ip })),
+            )
+            .await
+            .unwrap();
+    }
+
+    // A prefix matches whole segments only
+    let reports = audit
+        .list_logs(&AuditLogQuery {
+            user_id: Some(user_id),
+            action: Some("report".to_string()),
+            sort: Some("action".to_string()),
+            order: Some(SortOrder::Asc),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let actions: Vec<_> = reports.items.iter().map(|l| l.action.as_str()).collect();
+    assert_eq!(actions, vec!["report.exported", "report.viewed"]);
+    assert_eq!(reports.total, 2);
+
+    let from_ip = audit
+        .list_logs(&AuditLogQuery {
+            user_id: Some(user_id),
+            ip: Some("192.0.2.11".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(from_ip.total, 2);
+
+    // Cursor pages cover everything once, newest first
+    let mut seen = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = audit
+            .list_logs(&AuditLogQuery {
+                user_id: Some(user_id),
+                limit: Some(3),
+                cursor: cursor.take(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(page.total, 4);
+        seen.extend(page.items.into_iter().map(|l| (l.created_at, l.id)));
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+    assert_eq!(seen.len(), 4);
+    assert!(seen.windows(2).all(|w| w[0] > w[1]));
+
+    let offset = audit
+        .list_logs(&AuditLogQuery {
+            user_id: Some(user_id),
+            limit: Some(2),
+            offset: Some(2),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        offset.items.iter().map(|l| l.id).collect::<Vec<_>>(),
+        seen[2..].iter().map(|(_, id)| *id).collect::<Vec<_>>()
+    );
+
+    let cutoff = seen[1].0;
+    let before = audit
+        .list_logs(&AuditLogQuery {
+            user_id: Some(user_id),
+            to: Some(cutoff),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert!(before.items.iter().all(|l| l.created_at < cutoff));
+
+    // A cursor only continues the sort it came from
+    let by_action = audit
+        .list_logs(&AuditLogQuery {
+            user_id: Some(user_id),
+            sort: Some("action".to_string()),
+            limit: Some(1),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert!(matches!(
+        audit
+            .list_logs(&AuditLogQuery {
+                user_id: Some(user_id),
+                cursor: by_action.next_cursor,
+                ..Default::default()
+            })
+            .await,
+        Err(AuthError::ValidationError(_))
+    ));
+}
@@ -0,0 +1,96 @@
+use axum::{
+    body::Body, extract::Request, http::StatusCode, middleware::Next, response::Response, Router,
+};
+use sqlx::PgPool;
+use template_repo_backend::features::abac::models::AssignRoleInput;
+use template_repo_backend::features::auth::jwt::Claims;
+use template_repo_backend::features::auth::models::RegisterUser;
+use template_repo_backend::features::ontology::routes::ontology_routes;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+mod common;
+
+/// A user holding `role`, and the ontology router as they'd reach it
+async fn app_for(services: &common::TestServices, username: &str, role: &str) -> Router {
+    let user_id = services
+        .auth_service
+        .register(RegisterUser {
+            username: username.to_string(),
+            email: format!("{}@example.com", username),
+            password: "password123".to_string(),
+        })
+        .await
+        .unwrap()
+        .user_id;
+    services
+        .abac_service
+        .assign_role(
+            AssignRoleInput {
+                user_id: user_id.to_string(),
+                role_name: role.to_string(),
+                resource_id: None,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    let claims = Claims {
+        sub: user_id.to_string(),
+        username: username.to_string(),
+        email: format!("{}@example.com", username),
+        roles: vec![],
+        permissions: vec![],
+        jti: None,
+        exp: i64::MAX,
+        iat: 0,
+    };
+    let fake_auth = move |mut request: Request, next: Next| {
+        let claims = claims.clone();
+        async move {
+            request.extensions_mut().insert(claims);
+            Ok::<Response, StatusCode>(next.run(request).await)
+        }
+    };
+    ontology_routes()
+        .with_state(services.ontology_service.clone())
+        .layer(axum::middleware::from_fn(fake_auth))
+        .layer(axum::Extension(services.abac_service.clone()))
+}
+
+async fn rename(app: &Router, id: Uuid, name: &str) -> StatusCode {
+    let request = Request::builder()
+        .method("PUT")
+        .uri(format!("/entities/{}", id))
+        .header("content-type", "application/json")
+        .body(Body::from(
+            serde_json::json!({ "display_name": name }).to_string(),
+        ))
+        .unwrap();
+    app.clone().oneshot(request).await.unwrap().status()
+}
+
+#[sqlx::test]
+async fn test_only_system_admins_change_ai_providers(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let provider_id: Uuid = sqlx::query_scalar(
+        "SELECT e.id FROM entities e JOIN classes c ON c.id = e.class_id WHERE c.name = 'AiProvider' AND e.deleted_at IS NULL LIMIT 1",
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+
+    // Tenant admins can manage users but not where prompts and keys are sent
+    let admin = app_for(&services, "provider_admin", "admin").await;
+    assert_eq!(
+        rename(&admin, provider_id, "Exfiltrator").await,
+        StatusCode::FORBIDDEN
+    );
+
+    let superadmin = app_for(&services, "provider_superadmin", "superadmin").await;
+    assert_eq!(
+        rename(&superadmin, provider_id, "Primary").await,
+        StatusCode::OK
+    );
+}
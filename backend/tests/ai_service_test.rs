@@ -47,3 +47,1203 @@ async fn test_ai_model_listing(pool: PgPool) {
     // We expect it to be callable.
     let _ = services.ai_service.list_models().await;
 }
+
+#[sqlx::test]
+async fn test_ai_circuit_breaker_fails_fast_when_provider_down(pool: PgPool) {
+    use std::time::Duration;
+    use template_repo_backend::features::ai::service::AiError;
+    use template_repo_backend::utils::circuit_breaker::{BreakerState, CircuitBreaker};
+
+    let services = common::setup_services(pool.clone()).await;
+
+    // Point every provider at a closed port
+    sqlx::query(
+        r#"
+        UPDATE entities SET attributes = attributes || '{"api_base": "http://127.0.0.1:1/v1"}'
+        WHERE class_id IN (SELECT id FROM classes WHERE name = 'AiProvider')
+        "#,
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let ai = services
+        .ai_service
+        .clone()
+        .with_circuit_breaker(CircuitBreaker::new(2, Duration::from_secs(60)));
+
+    for _ in 0..2 {
+        assert!(matches!(ai.list_models().await, Err(AiError::Failed(_))));
+    }
+    assert_eq!(ai.breaker_status().state, BreakerState::Open);
+
+    // Further calls are rejected without reaching the provider
+    assert!(matches!(
+        ai.list_models().await,
+        Err(AiError::Unavailable { .. })
+    ));
+    assert!(matches!(
+        ai.generate_ontology_suggestions("logistics").await,
+        Err(AiError::Unavailable { .. })
+    ));
+}
+
+#[sqlx::test]
+async fn test_generate_text_stream_yields_pieces_in_order(pool: PgPool) {
+    use axum::{response::IntoResponse, routing::post, Router};
+    use futures::StreamExt;
+    use template_repo_backend::features::ai::service::GenerateRequest;
+
+    let services = common::setup_services(pool.clone()).await;
+
+    // OpenAI-compatible provider that streams "Hello, world" in three chunks
+    let chunk = |content: &str| {
+        format!(
+            "data: {}\n\n",
+            serde_json::json!({
+                "id": "chunk", "object": "chat.completion.chunk", "created": 0, "model": "test",
+                "choices": [{ "index": 0, "delta": { "content": content }, "finish_reason": null }]
+            })
+        )
+    };
+    let body = format!(
+        "{}{}{}{}data: [DONE]\n\n",
+        chunk("Hello"),
+        chunk(""),
+        chunk(", "),
+        chunk("world")
+    );
+    let app = Router::new().route(
+        "/v1/chat/completions",
+        post(move || {
+            let body = body.clone();
+            async move { ([("content-type", "text/event-stream")], body).into_response() }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+
+    sqlx::query(
+        r#"
+        UPDATE entities SET attributes = attributes || jsonb_build_object('api_base', $1::text, 'is_active', true)
+        WHERE class_id IN (SELECT id FROM classes WHERE name = 'AiProvider')
+        "#,
+    )
+    .bind(format!("http://{}/v1", addr))
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let pieces: Vec<String> = services
+        .ai_service
+        .generate_text_stream(GenerateRequest {
+            prompt: "Say hello".to_string(),
+            temperature: None,
+            max_tokens: None,
+        })
+        .await
+        .expect("stream should start")
+        .map(|piece| piece.expect("stream should not fail"))
+        .collect()
+        .await;
+    assert_eq!(pieces, vec!["Hello", ", ", "world"]);
+}
+
+#[sqlx::test]
+async fn test_generate_text_falls_back_to_next_provider(pool: PgPool) {
+    use axum::{routing::post, Json, Router};
+    use template_repo_backend::features::ai::service::GenerateRequest;
+
+    let services = common::setup_services(pool.clone()).await;
+
+    // Anthropic-style provider answering every message with "from fallback"
+    let app = Router::new().route(
+        "/v1/messages",
+        post(|| async {
+            Json(serde_json::json!({
+                "type": "message", "role": "user",
+                "content": [{ "type": "text", "text": "from fallback" }]
+            }))
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+
+    // Existing providers are down and go first
+    sqlx::query(
+        r#"
+        UPDATE entities SET attributes = attributes || '{"api_base": "http://127.0.0.1:1/v1", "priority": 0}'
+        WHERE class_id IN (SELECT id FROM classes WHERE name = 'AiProvider')
+        "#,
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    sqlx::query(
+        r#"
+        INSERT INTO entities (class_id, display_name, attributes, approval_status)
+        SELECT id, 'Fallback Provider', $1, 'APPROVED' FROM classes WHERE name = 'AiProvider'
+        "#,
+    )
+    .bind(serde_json::json!({
+        "api_base": format!("http://{}", addr),
+        "model_name": "claude-test",
+        "provider_type": "Anthropic",
+        "is_active": true,
+        "priority": 10
+    }))
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let providers = services.ai_service.providers().await;
+    assert_eq!(
+        providers.last().map(|p| p.name.as_str()),
+        Some("Fallback Provider")
+    );
+
+    let response = services
+        .ai_service
+        .generate_text(GenerateRequest {
+            prompt: "Say something".to_string(),
+            temperature: None,
+            max_tokens: None,
+        })
+        .await
+        .expect("fallback provider should answer");
+    assert_eq!(response.text, "from fallback");
+}
+
+#[sqlx::test]
+async fn test_prompt_template_versions_drive_rendering(pool: PgPool) {
+    use template_repo_backend::features::ai::nl_query::NL_QUERY_PROMPT;
+    use template_repo_backend::features::ai::prompts::{
+        CreatePromptTemplateInput, PromptService, PromptVariable, UpdatePromptTemplateInput,
+    };
+    use template_repo_backend::features::ai::service::AiError;
+
+    let user_id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO entities (id, class_id, display_name, attributes) SELECT $1, id, 'prompt_admin', '{}' FROM classes WHERE name = 'User' LIMIT 1",
+    )
+    .bind(user_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let prompts = PromptService::new(pool.clone());
+    prompts.seed_builtins().await.unwrap();
+    // Seeding again leaves existing templates alone
+    prompts.seed_builtins().await.unwrap();
+
+    let values = [
+        ("schema", "- Person: name"),
+        ("entity_fields", "id"),
+        ("operators", "=="),
+        ("question", "Who?"),
+    ];
+    let default = prompts.render(&NL_QUERY_PROMPT, &values).await;
+    assert!(default.contains("Question: Who?"));
+
+    let updated = prompts
+        .update(
+            "nl_query",
+            UpdatePromptTemplateInput {
+                description: None,
+                body: "Q: {{question}} over {{schema}}".to_string(),
+                variables: None,
+                note: Some("shorter".to_string()),
+            },
+            user_id,
+        )
+        .await
+        .unwrap();
+    assert_eq!(updated.template.active_version, 2);
+    assert_eq!(
+        prompts.render(&NL_QUERY_PROMPT, &values).await,
+        "Q: Who? over - Person: name"
+    );
+
+    // Built-in prompts can't declare variables their feature never supplies
+    let invalid = prompts
+        .update(
+            "nl_query",
+            UpdatePromptTemplateInput {
+                description: None,
+                body: "{{question}} {{tone}}".to_string(),
+                variables: Some(vec![
+                    PromptVariable { name: "question".to_string(), description: None },
+                    PromptVariable { name: "tone".to_string(), description: None },
+                ]),
+                note: None,
+            },
+            user_id,
+        )
+        .await;
+    assert!(matches!(invalid, Err(AiError::InvalidInput(_))));
+    assert!(matches!(
+        prompts.delete("nl_query").await,
+        Err(AiError::Conflict(_))
+    ));
+
+    // Rolling back restores the default
+    prompts.activate_version("nl_query", 1).await.unwrap();
+    assert_eq!(prompts.render(&NL_QUERY_PROMPT, &values).await, default);
+    assert_eq!(prompts.list_versions("nl_query").await.unwrap().len(), 2);
+
+    // Custom templates
+    prompts
+        .create(
+            CreatePromptTemplateInput {
+                name: "greeting".to_string(),
+                description: None,
+                body: "Hello {{name}}".to_string(),
+                variables: vec![PromptVariable { name: "name".to_string(), description: None }],
+                note: None,
+            },
+            user_id,
+        )
+        .await
+        .unwrap();
+    assert!(matches!(
+        prompts
+            .create(
+                CreatePromptTemplateInput {
+                    name: "greeting".to_string(),
+                    description: None,
+                    body: "Hi".to_string(),
+                    variables: vec![],
+                    note: None,
+                },
+                user_id,
+            )
+            .await,
+        Err(AiError::Conflict(_))
+    ));
+    prompts.delete("greeting").await.unwrap();
+    assert!(matches!(
+        prompts.get("greeting").await,
+        Err(AiError::NotFound(_))
+    ));
+}
+
+#[sqlx::test]
+async fn test_conversation_history_is_stored_and_sent_as_context(pool: PgPool) {
+    use axum::{routing::post, Json, Router};
+    use std::sync::{Arc, Mutex};
+    use template_repo_backend::config::AiConversationsConfig;
+    use template_repo_backend::features::ai::conversations::{
+        ConversationService, CreateConversationInput, ListConversationsQuery, SendMessageInput,
+    };
+    use template_repo_backend::features::ai::prompts::PromptService;
+    use template_repo_backend::features::ai::service::AiError;
+
+    let services = common::setup_services(pool.clone()).await;
+
+    // OpenAI-compatible provider that records the prompt and numbers its replies
+    let prompts_seen = Arc::new(Mutex::new(Vec::<String>::new()));
+    let seen = prompts_seen.clone();
+    let app = Router::new().route(
+        "/v1/chat/completions",
+        post(move |Json(body): Json<serde_json::Value>| {
+            let seen = seen.clone();
+            async move {
+                let prompt = body["messages"][0]["content"].as_str().unwrap_or("").to_string();
+                let mut seen = seen.lock().unwrap();
+                seen.push(prompt);
+                Json(serde_json::json!({
+                    "id": "c", "object": "chat.completion", "created": 0, "model": "test",
+                    "choices": [{ "index": 0, "finish_reason": "stop",
+                        "message": { "role": "assistant", "content": format!("Reply {}", seen.len()) } }]
+                }))
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    sqlx::query(
+        r#"
+        UPDATE entities SET attributes = attributes || jsonb_build_object('api_base', $1::text, 'is_active', true, 'provider_type', 'OpenAI')
+        WHERE class_id IN (SELECT id FROM classes WHERE name = 'AiProvider')
+        "#,
+    )
+    .bind(format!("http://{}/v1", addr))
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let user_id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO entities (id, class_id, display_name, attributes) SELECT $1, id, 'chat_user', '{}' FROM classes WHERE name = 'User' LIMIT 1",
+    )
+    .bind(user_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let conversations = ConversationService::new(
+        pool.clone(),
+        services.ai_service.clone(),
+        services.rebac_service.clone(),
+        PromptService::new(pool.clone()),
+        AiConversationsConfig::default(),
+    );
+    let conversation = conversations
+        .create(user_id, CreateConversationInput::default())
+        .await
+        .unwrap();
+
+    let send = |content: &str| SendMessageInput {
+        content: content.to_string(),
+        temperature: None,
+        max_tokens: None,
+    };
+    let first = conversations
+        .send(user_id, conversation.id, send("What is a class?\nDetails follow"))
+        .await
+        .unwrap();
+    assert_eq!(first.reply.content, "Reply 1");
+    conversations
+        .send(user_id, conversation.id, send("And an entity?"))
+        .await
+        .unwrap();
+
+    // The second prompt carries the first exchange
+    let last_prompt = prompts_seen.lock().unwrap().last().cloned().unwrap();
+    assert!(last_prompt.contains("User: What is a class?"));
+    assert!(last_prompt.contains("Assistant: Reply 1\nUser: And an entity?"));
+
+    let resumed = conversations.get(user_id, conversation.id).await.unwrap();
+    assert_eq!(resumed.conversation.title, "What is a class?");
+    let roles: Vec<_> = resumed.messages.iter().map(|m| m.role.as_str()).collect();
+    assert_eq!(roles, vec!["user", "assistant", "user", "assistant"]);
+
+    // Other users can't see or delete it
+    let other = Uuid::new_v4();
+    assert!(matches!(
+        conversations.get(other, conversation.id).await,
+        Err(AiError::NotFound(_))
+    ));
+    assert!(conversations
+        .list(other, ListConversationsQuery { project_id: None })
+        .await
+        .unwrap()
+        .is_empty());
+
+    // Idle conversations expire
+    sqlx::query("UPDATE ai_conversations SET updated_at = NOW() - INTERVAL '91 days' WHERE id = $1")
+        .bind(conversation.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert_eq!(conversations.purge_expired().await.unwrap(), 1);
+    assert!(matches!(
+        conversations.delete(user_id, conversation.id).await,
+        Err(AiError::NotFound(_))
+    ));
+}
+
+#[sqlx::test]
+async fn test_audit_questions_are_answered_from_cited_events(pool: PgPool) {
+    use axum::{routing::post, Json, Router};
+    use std::sync::{Arc, Mutex};
+    use template_repo_backend::features::ai::audit_qa::{AuditQaService, AuditQuestionRequest};
+    use template_repo_backend::features::ai::prompts::PromptService;
+
+    let services = common::setup_services(pool.clone()).await;
+
+    // Provider that always cites the first and a non-existent event
+    let prompts_seen = Arc::new(Mutex::new(Vec::<String>::new()));
+    let seen = prompts_seen.clone();
+    let app = Router::new().route(
+        "/v1/chat/completions",
+        post(move |Json(body): Json<serde_json::Value>| {
+            let seen = seen.clone();
+            async move {
+                seen.lock().unwrap().push(body["messages"][0]["content"].as_str().unwrap_or("").to_string());
+                Json(serde_json::json!({
+                    "id": "c", "object": "chat.completion", "created": 0, "model": "test",
+                    "choices": [{ "index": 0, "finish_reason": "stop",
+                        "message": { "role": "assistant", "content": "auditor updated it [E1] [E99]" } }]
+                }))
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    sqlx::query(
+        r#"
+        UPDATE entities SET attributes = attributes || jsonb_build_object('api_base', $1::text, 'is_active', true, 'provider_type', 'OpenAI')
+        WHERE class_id IN (SELECT id FROM classes WHERE name = 'AiProvider')
+        "#,
+    )
+    .bind(format!("http://{}/v1", addr))
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let user_id = Uuid::new_v4();
+    let project_id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO entities (id, class_id, display_name, attributes) SELECT $1, id, 'auditor', '{}' FROM classes WHERE name = 'User' LIMIT 1",
+    )
+    .bind(user_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+    sqlx::query(
+        "INSERT INTO entities (id, class_id, display_name, attributes) SELECT $1, id, 'Project Falcon', '{}' FROM classes WHERE name = 'Project' LIMIT 1",
+    )
+    .bind(project_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let audit = services.audit_service.clone();
+    let relevant = audit
+        .log(
+            user_id,
+            "role.update",
+            "role",
+            Some(project_id),
+            Some(serde_json::json!({ "role": "viewer", "password_hash": "x" })),
+            Some(serde_json::json!({ "role": "editor", "password_hash": "y" })),
+            None,
+        )
+        .await
+        .unwrap();
+
+    let qa = AuditQaService::new(
+        pool.clone(),
+        services.ai_service.clone(),
+        PromptService::new(pool.clone()),
+        audit.clone(),
+    );
+    let answer = qa
+        .ask(
+            user_id,
+            AuditQuestionRequest {
+                question: "Who changed permissions on Project Falcon last week?".to_string(),
+                since: None,
+                until: None,
+                limit: None,
+            },
+        )
+        .await
+        .unwrap();
+
+    assert!(answer.grounded);
+    assert_eq!(answer.citations.len(), 1);
+    assert_eq!(answer.citations[0].event_id, relevant.id);
+    assert_eq!(answer.citations[0].target_name.as_deref(), Some("Project Falcon"));
+    let prompt = prompts_seen.lock().unwrap().last().cloned().unwrap();
+    assert!(prompt.contains("[E1]"));
+    assert!(prompt.contains("role: \"viewer\" -> \"editor\""));
+    assert!(!prompt.contains("\"y\""));
+
+    // Nothing in range means no answer from the model at all
+    let calls = prompts_seen.lock().unwrap().len();
+    let empty = qa
+        .ask(
+            user_id,
+            AuditQuestionRequest {
+                question: "What happened yesterday?".to_string(),
+                since: None,
+                until: None,
+                limit: None,
+            },
+        )
+        .await
+        .unwrap();
+    assert!(!empty.grounded);
+    assert_eq!(empty.events_considered, 0);
+    assert_eq!(prompts_seen.lock().unwrap().len(), calls);
+}
+
+#[sqlx::test]
+async fn test_authorization_questions_are_answered_from_rebac_checks(pool: PgPool) {
+    use axum::{routing::post, Json, Router};
+    use std::sync::{Arc, Mutex};
+    use template_repo_backend::features::ai::authz_qa::{
+        AuthzQaService, AuthzQueryKind, AuthzQuestionRequest,
+    };
+    use template_repo_backend::features::ai::prompts::PromptService;
+    use template_repo_backend::features::ontology::models::{
+        CreateEntityInput, CreateRelationshipInput,
+    };
+
+    let services = common::setup_services(pool.clone()).await;
+    let ontology = &services.ontology_service;
+
+    // Provider that turns any question into a who-can lookup, then cites the first result
+    let prompts_seen = Arc::new(Mutex::new(Vec::<String>::new()));
+    let seen = prompts_seen.clone();
+    let app = Router::new().route(
+        "/v1/chat/completions",
+        post(move |Json(body): Json<serde_json::Value>| {
+            let seen = seen.clone();
+            async move {
+                let prompt = body["messages"][0]["content"]
+                    .as_str()
+                    .unwrap_or("")
+                    .to_string();
+                let content = if prompt.contains("into a JSON lookup") {
+                    r#"{"kind": "who_can", "permission": "delegate", "entity": "Project Alpha"}"#
+                } else {
+                    "alice can delegate on Project Alpha [F1]."
+                };
+                seen.lock().unwrap().push(prompt);
+                Json(serde_json::json!({
+                    "id": "c", "object": "chat.completion", "created": 0, "model": "test",
+                    "choices": [{ "index": 0, "finish_reason": "stop",
+                        "message": { "role": "assistant", "content": content } }]
+                }))
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    sqlx::query(
+        r#"
+        UPDATE entities SET attributes = attributes || jsonb_build_object('api_base', $1::text, 'is_active', true, 'provider_type', 'OpenAI')
+        WHERE class_id IN (SELECT id FROM classes WHERE name = 'AiProvider')
+        "#,
+    )
+    .bind(format!("http://{}/v1", addr))
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let admin_id = Uuid::new_v4();
+    let alice_id = Uuid::new_v4();
+    let bob_id = Uuid::new_v4();
+    for (id, name) in [
+        (admin_id, "authz_admin"),
+        (alice_id, "alice"),
+        (bob_id, "bob"),
+    ] {
+        sqlx::query(
+            "INSERT INTO entities (id, class_id, display_name, attributes) SELECT $1, id, $2, jsonb_build_object('username', $2::text) FROM classes WHERE name = 'User' LIMIT 1",
+        )
+        .bind(id)
+        .bind(name)
+        .execute(&pool)
+        .await
+        .unwrap();
+    }
+    let project_id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO entities (id, class_id, display_name, attributes) SELECT $1, id, 'Project Alpha', '{}' FROM classes WHERE name = 'Project' LIMIT 1",
+    )
+    .bind(project_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    // alice may delegate on the project, bob may only read it
+    let role_class = ontology.get_system_class("Role").await.unwrap();
+    let permission_id = |name: &'static str| {
+        let pool = pool.clone();
+        async move {
+            sqlx::query_scalar::<_, Uuid>(
+                "SELECT e.id FROM entities e JOIN classes c ON c.id = e.class_id WHERE c.name = 'Permission' AND e.display_name = $1",
+            )
+            .bind(name)
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+        }
+    };
+    for (user_id, role_name, permission) in [
+        (alice_id, "AlphaDelegator", "delegate"),
+        (bob_id, "AlphaReader", "read"),
+    ] {
+        let role = ontology
+            .create_entity(
+                CreateEntityInput {
+                    class_id: role_class.id,
+                    display_name: role_name.into(),
+                    parent_entity_id: None,
+                    attributes: Some(serde_json::json!({"name": role_name, "level": 10})),
+                },
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        for (source, target, relationship_type, metadata) in [
+            (
+                role.id,
+                permission_id(permission).await,
+                "grants_permission",
+                serde_json::json!({"effect": "ALLOW"}),
+            ),
+            (
+                user_id,
+                role.id,
+                "has_role",
+                serde_json::json!({"scope_entity_id": project_id.to_string()}),
+            ),
+        ] {
+            ontology
+                .create_relationship(
+                    CreateRelationshipInput {
+                        source_entity_id: source,
+                        target_entity_id: target,
+                        relationship_type: relationship_type.into(),
+                        metadata: Some(metadata),
+                    },
+                    None,
+                )
+                .await
+                .unwrap();
+        }
+    }
+
+    let qa = AuthzQaService::new(
+        pool.clone(),
+        services.ai_service.clone(),
+        services.rebac_service.clone(),
+        PromptService::new(pool.clone()),
+        services.audit_service.clone(),
+    );
+    let answer = qa
+        .ask(
+            admin_id,
+            AuthzQuestionRequest {
+                question: "Who can delegate on Project Alpha?".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(answer.query.kind, AuthzQueryKind::WhoCan);
+    let alice = answer
+        .findings
+        .iter()
+        .find(|f| f.user_id == alice_id)
+        .expect("alice holds delegate");
+    assert_eq!(alice.entity_id, project_id);
+    assert_eq!(alice.granted_via_role.as_deref(), Some("AlphaDelegator"));
+    assert!(answer.findings.iter().all(|f| f.user_id != bob_id));
+    assert_eq!(answer.cited, vec!["F1".to_string()]);
+
+    // The narrative is worded from the checks the model was given
+    let prompt = prompts_seen.lock().unwrap().last().cloned().unwrap();
+    assert!(prompt.contains("alice 'delegate' on Project Alpha: allowed via role AlphaDelegator"));
+    assert!(!prompt.contains("bob"));
+}
+
+#[sqlx::test]
+async fn test_policy_drafts_are_enforced_only_after_approval(pool: PgPool) {
+    use axum::{routing::post, Json, Router};
+    use template_repo_backend::features::ai::policy_drafting::{
+        DraftPolicyRequest, PolicyDraftingService, ReviewPolicyDraftRequest,
+    };
+    use template_repo_backend::features::ai::prompts::PromptService;
+    use template_repo_backend::features::ai::service::AiError;
+    use template_repo_backend::features::rebac::PolicyService;
+
+    let services = common::setup_services(pool.clone()).await;
+
+    let app = Router::new().route(
+        "/v1/chat/completions",
+        post(|| async {
+            let policy = serde_json::json!({
+                "name": "Contractors read project documents in business hours",
+                "effect": "ALLOW",
+                "target_class": "project",
+                "target_permissions": ["read"],
+                "conditions": { "all": [
+                    { "attribute": "user.employment_type", "operator": "==", "value": "contractor" }
+                ] },
+                "schedule_cron": "0 * 9-16 * * Mon-Fri",
+                "rationale": "Own-project scoping needs a scope entity"
+            });
+            Json(serde_json::json!({
+                "id": "c", "object": "chat.completion", "created": 0, "model": "test",
+                "choices": [{ "index": 0, "finish_reason": "stop",
+                    "message": { "role": "assistant", "content": policy.to_string() } }]
+            }))
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    sqlx::query(
+        r#"
+        UPDATE entities SET attributes = attributes || jsonb_build_object('api_base', $1::text, 'is_active', true, 'provider_type', 'OpenAI')
+        WHERE class_id IN (SELECT id FROM classes WHERE name = 'AiProvider')
+        "#,
+    )
+    .bind(format!("http://{}/v1", addr))
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let admin_id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO entities (id, class_id, display_name, attributes) SELECT $1, id, 'policy admin', '{}' FROM classes WHERE name = 'User' LIMIT 1",
+    )
+    .bind(admin_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let policies = PolicyService::new(pool.clone());
+    let drafting = PolicyDraftingService::new(
+        pool.clone(),
+        services.ai_service.clone(),
+        PromptService::new(pool.clone()),
+        policies.clone(),
+    );
+    let drafted_policies = || async {
+        policies
+            .list_policies(false)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|p| p.name == "Contractors read project documents in business hours")
+            .count()
+    };
+    let requirement = || DraftPolicyRequest {
+        requirement: "contractors can read documents in their own project during business hours"
+            .to_string(),
+    };
+
+    let draft = drafting.draft(admin_id, requirement()).await.unwrap();
+    assert_eq!(draft.status, "draft");
+    assert_eq!(draft.proposal.target_class_name.as_deref(), Some("Project"));
+    assert_eq!(draft.proposal.schedule_cron.as_deref(), Some("0 * 9-16 * * Mon-Fri"));
+    assert!(draft.warnings.iter().any(|w| w.starts_with("user.employment_type")));
+    assert_eq!(drafted_policies().await, 0);
+
+    let approved = drafting
+        .approve(admin_id, draft.id, ReviewPolicyDraftRequest::default())
+        .await
+        .unwrap();
+    assert_eq!(approved.draft.status, "approved");
+    assert_eq!(approved.draft.policy_id, Some(approved.policy.id));
+    assert_eq!(approved.draft.reviewed_by, Some(admin_id));
+    assert!(approved.policy.is_active);
+    assert_eq!(approved.policy.effect, "ALLOW");
+    assert_eq!(approved.policy.target_class_id, draft.proposal.target_class_id);
+    assert_eq!(
+        approved.policy.schedule_cron.as_deref(),
+        Some("0 * 9-16 * * Mon-Fri")
+    );
+    assert!(matches!(
+        drafting
+            .approve(admin_id, draft.id, ReviewPolicyDraftRequest::default())
+            .await,
+        Err(AiError::Conflict(_))
+    ));
+
+    // Rejected drafts never become policies
+    let rejected_draft = drafting.draft(admin_id, requirement()).await.unwrap();
+    let rejected = drafting
+        .reject(
+            admin_id,
+            rejected_draft.id,
+            ReviewPolicyDraftRequest {
+                note: Some("scope it to a project first".to_string()),
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(rejected.status, "rejected");
+    assert!(rejected.policy_id.is_none());
+    assert_eq!(drafted_policies().await, 1);
+}
+
+#[sqlx::test]
+async fn test_alert_explanations_are_attached_and_marked_machine_generated(pool: PgPool) {
+    use axum::{routing::post, Json, Router};
+    use std::sync::{Arc, Mutex};
+    use template_repo_backend::config::AiAlertExplanationsConfig;
+    use template_repo_backend::features::ai::alert_explanations::AlertExplanationService;
+    use template_repo_backend::features::ai::prompts::PromptService;
+
+    let services = common::setup_services(pool.clone()).await;
+
+    let prompts_seen = Arc::new(Mutex::new(Vec::<String>::new()));
+    let seen = prompts_seen.clone();
+    let app = Router::new().route(
+        "/v1/chat/completions",
+        post(move |Json(body): Json<serde_json::Value>| {
+            let seen = seen.clone();
+            async move {
+                seen.lock().unwrap().push(body["messages"][0]["content"].as_str().unwrap_or("").to_string());
+                let explanation = serde_json::json!({
+                    "summary": "A user granted themselves superadmin.",
+                    "next_steps": ["Revoke the role", " "]
+                });
+                Json(serde_json::json!({
+                    "id": "c", "object": "chat.completion", "created": 0, "model": "test",
+                    "choices": [{ "index": 0, "finish_reason": "stop",
+                        "message": { "role": "assistant", "content": explanation.to_string() } }]
+                }))
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    sqlx::query(
+        r#"
+        UPDATE entities SET attributes = attributes || jsonb_build_object('api_base', $1::text, 'is_active', true, 'provider_type', 'OpenAI')
+        WHERE class_id IN (SELECT id FROM classes WHERE name = 'AiProvider')
+        "#,
+    )
+    .bind(format!("http://{}/v1", addr))
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    // A critical event the privilege_escalation rule counts, and the alert it raised
+    sqlx::query(
+        r#"
+        INSERT INTO security_events (event_type, severity, ip_address, resource, outcome, details)
+        VALUES ('privilege_escalation', 'critical', '10.0.0.7', 'roles', 'success',
+                '{"role": "superadmin", "session_token": "tok-123"}')
+        "#,
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    let alert_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO security_alerts (rule_id, rule_name, event_count, event_ids, message)
+        SELECT id, rule_name, 1, alert_rule_event_ids(id), 'Alert privilege_escalation'
+        FROM alert_rules WHERE rule_name = 'privilege_escalation'
+        RETURNING id
+        "#,
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+
+    let explanations = AlertExplanationService::new(
+        pool.clone(),
+        services.ai_service.clone(),
+        PromptService::new(pool.clone()),
+        AiAlertExplanationsConfig::default(),
+    );
+    let alert = explanations.explain(alert_id).await.unwrap();
+
+    let explanation = alert.ai_explanation.expect("explanation stored").0;
+    assert!(explanation.machine_generated);
+    assert!(!explanation.notice.is_empty());
+    assert_eq!(explanation.summary, "A user granted themselves superadmin.");
+    assert_eq!(explanation.next_steps, vec!["Revoke the role"]);
+    assert!(alert.ai_error.is_none());
+
+    let prompt = prompts_seen.lock().unwrap().last().cloned().unwrap();
+    assert!(prompt.contains("privilege_escalation: Alert on privilege escalation (1 events)"));
+    assert!(prompt.contains("ip=10.0.0.7"));
+    assert!(!prompt.contains("tok-123"));
+}
+
+#[sqlx::test]
+async fn test_ai_usage_is_recorded_and_budgets_enforced(pool: PgPool) {
+    use axum::{routing::post, Json, Router};
+    use template_repo_backend::features::ai::service::{AiError, GenerateRequest};
+    use template_repo_backend::features::ai::usage::{
+        SetBudgetInput, UsageReportQuery, UsageService,
+    };
+
+    let services = common::setup_services(pool.clone()).await;
+
+    // OpenAI-compatible provider that reports 12 prompt and 5 completion tokens
+    let app = Router::new().route(
+        "/v1/chat/completions",
+        post(|| async {
+            Json(serde_json::json!({
+                "id": "done", "object": "chat.completion", "created": 0, "model": "test",
+                "choices": [{
+                    "index": 0, "finish_reason": "stop",
+                    "message": { "role": "assistant", "content": "counted" }
+                }],
+                "usage": { "prompt_tokens": 12, "completion_tokens": 5, "total_tokens": 17 }
+            }))
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+
+    sqlx::query(
+        r#"
+        UPDATE entities SET attributes = attributes || $1
+        WHERE class_id IN (SELECT id FROM classes WHERE name = 'AiProvider')
+        "#,
+    )
+    .bind(serde_json::json!({
+        "api_base": format!("http://{}/v1", addr),
+        "provider_type": "OpenAI",
+        "input_cost_per_1k": 1.0,
+        "output_cost_per_1k": 2.0
+    }))
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let tenant_id = Uuid::new_v4();
+    let user_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO entities (class_id, display_name, attributes, approval_status, tenant_id)
+        SELECT id, 'Metered User', '{}', 'APPROVED', $1 FROM classes WHERE name = 'User' LIMIT 1
+        RETURNING id
+        "#,
+    )
+    .bind(tenant_id)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+
+    let ai = services.ai_service.for_user(user_id).await;
+    let request = || GenerateRequest {
+        prompt: "Count me".to_string(),
+        temperature: None,
+        max_tokens: None,
+    };
+    let response = ai.generate_text(request()).await.unwrap();
+    assert_eq!(response.usage.prompt_tokens, 12);
+    assert_eq!(response.usage.completion_tokens, 5);
+    assert!(!response.usage.estimated);
+
+    let (recorded_user, tokens, cost): (Option<Uuid>, i64, f64) = sqlx::query_as(
+        "SELECT user_id, prompt_tokens + completion_tokens, cost FROM ai_usage WHERE tenant_id = $1",
+    )
+    .bind(tenant_id)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(recorded_user, Some(user_id));
+    assert_eq!(tokens, 17);
+    assert!((cost - 0.022).abs() < 1e-9);
+
+    let usage = UsageService::new(pool.clone());
+    let report = usage
+        .report(UsageReportQuery {
+            group_by: Some("tenant".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let row = report
+        .rows
+        .iter()
+        .find(|r| r.key.as_deref() == Some(tenant_id.to_string().as_str()))
+        .expect("tenant should be in the report");
+    assert_eq!((row.requests, row.prompt_tokens, row.completion_tokens), (1, 12, 5));
+
+    // Once the month's tokens reach the limit, further calls are refused
+    usage
+        .set_budget(
+            tenant_id,
+            SetBudgetInput {
+                monthly_token_limit: Some(17),
+                monthly_cost_limit: None,
+            },
+            user_id,
+        )
+        .await
+        .unwrap();
+    assert!(matches!(
+        ai.generate_text(request()).await,
+        Err(AiError::BudgetExceeded(_))
+    ));
+    // Other tenants are unaffected
+    assert!(services.ai_service.generate_text(request()).await.is_ok());
+
+    usage.delete_budget(tenant_id).await.unwrap();
+    assert!(ai.generate_text(request()).await.is_ok());
+}
+
+#[sqlx::test]
+async fn test_personal_data_is_redacted_before_provider_calls(pool: PgPool) {
+    use axum::{routing::post, Json, Router};
+    use std::sync::{Arc, Mutex};
+    use template_repo_backend::config::AiRedactionConfig;
+    use template_repo_backend::features::ai::redaction::{ListRedactionsQuery, RedactionService};
+    use template_repo_backend::features::ai::service::GenerateRequest;
+
+    let services = common::setup_services(pool.clone()).await;
+
+    // OpenAI-compatible provider that records the prompt and echoes it back
+    let prompts_seen = Arc::new(Mutex::new(Vec::<String>::new()));
+    let seen = prompts_seen.clone();
+    let app = Router::new().route(
+        "/v1/chat/completions",
+        post(move |Json(body): Json<serde_json::Value>| {
+            let seen = seen.clone();
+            async move {
+                let prompt = body["messages"][0]["content"].as_str().unwrap_or("").to_string();
+                seen.lock().unwrap().push(prompt.clone());
+                Json(serde_json::json!({
+                    "id": "c", "object": "chat.completion", "created": 0, "model": "test",
+                    "choices": [{ "index": 0, "finish_reason": "stop",
+                        "message": { "role": "assistant", "content": format!("Echo: {}", prompt) } }]
+                }))
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    sqlx::query(
+        r#"
+        UPDATE entities SET attributes = attributes || jsonb_build_object('api_base', $1::text, 'is_active', true, 'provider_type', 'OpenAI')
+        WHERE class_id IN (SELECT id FROM classes WHERE name = 'AiProvider')
+        "#,
+    )
+    .bind(format!("http://{}/v1", addr))
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    // A user whose name and stored email (a sensitive property) must not leave
+    let user_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO entities (class_id, display_name, attributes, approval_status)
+        SELECT id, 'Grace Hopper', '{"email": "grace@navy.example", "username": "ghopper"}', 'APPROVED'
+        FROM classes WHERE name = 'User' LIMIT 1
+        RETURNING id
+        "#,
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+
+    let prompt = r#"Summarize: grace hopper (grace@navy.example) wrote to bob@corp.example. {"last_login_ip": "10.1.2.3"}"#;
+    let request = || GenerateRequest {
+        prompt: prompt.to_string(),
+        temperature: None,
+        max_tokens: None,
+    };
+
+    // The mock runs on this machine, which is trusted by default
+    services.ai_service.generate_text(request()).await.unwrap();
+    assert_eq!(prompts_seen.lock().unwrap().last().unwrap(), prompt);
+
+    let config = AiRedactionConfig {
+        redact_local_providers: true,
+        ..Default::default()
+    };
+    let ai = services
+        .ai_service
+        .with_redaction_config(&config)
+        .for_user(user_id)
+        .await;
+    let response = ai.generate_text(request()).await.unwrap();
+
+    let sent = prompts_seen.lock().unwrap().last().unwrap().clone();
+    assert_eq!(
+        sent,
+        r#"Summarize: [NAME_1] ([SENSITIVE_2]) wrote to [EMAIL_1]. {"last_login_ip": "[SENSITIVE_1]"}"#
+    );
+    // Placeholders in the response are mapped back to the original values
+    assert_eq!(response.text, format!("Echo: {}", prompt));
+
+    let audits = RedactionService::new(pool.clone(), config)
+        .list(ListRedactionsQuery {
+            user_id: Some(user_id),
+            tenant_id: None,
+            limit: None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(audits.len(), 1);
+    assert_eq!(audits[0].operation, "generate");
+    assert_eq!(audits[0].counts.0.get("name"), Some(&1));
+    assert_eq!(audits[0].counts.0.get("email"), Some(&1));
+    assert_eq!(audits[0].counts.0.get("sensitive"), Some(&2));
+    assert_eq!(audits[0].fields, vec!["email", "last_login_ip"]);
+}
+
+#[sqlx::test]
+async fn test_index_worker_tracks_ontology_change_events(pool: PgPool) {
+    use template_repo_backend::config::SemanticSearchConfig;
+    use template_repo_backend::features::ai::index_worker::IndexWorker;
+    use template_repo_backend::features::ai::semantic_search::SemanticSearchService;
+    use template_repo_backend::utils::shutdown::Shutdown;
+
+    let services = common::setup_services(pool.clone()).await;
+    let search = SemanticSearchService::new(
+        pool.clone(),
+        services.ai_service.clone(),
+        services.rebac_service.clone(),
+        SemanticSearchConfig::default(),
+    );
+    let worker = IndexWorker::new(pool.clone(), search);
+    let before = worker.status().await.unwrap();
+
+    let entity_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO entities (class_id, display_name, attributes, approval_status)
+        SELECT id, 'Indexed Entity', '{"note": "first"}', 'APPROVED' FROM classes WHERE name = 'User' LIMIT 1
+        RETURNING id
+        "#,
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    // Bookkeeping-only updates are not indexing events
+    sqlx::query("UPDATE entities SET updated_at = NOW() WHERE id = $1")
+        .bind(entity_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query(r#"UPDATE entities SET attributes = '{"note": "second"}' WHERE id = $1"#)
+        .bind(entity_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let operations: Vec<String> = sqlx::query_scalar(
+        "SELECT operation FROM ontology_change_events WHERE object_id = $1 ORDER BY seq",
+    )
+    .bind(entity_id)
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(operations, vec!["INSERT", "UPDATE"]);
+
+    // Both, and the default role assignment new users are given
+    let status = worker.status().await.unwrap();
+    assert_eq!(status.pending_events, before.pending_events + 3);
+    assert!(!status.backfilling);
+
+    // Without pgvector the index can't be built: events stay pending and the
+    // failure is reported rather than skipped
+    let available: bool =
+        sqlx::query_scalar("SELECT to_regclass('entity_embeddings') IS NOT NULL")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    if !available {
+        worker.run_once(false, &Shutdown::new()).await;
+        let status = worker.status().await.unwrap();
+        assert_eq!(status.state.last_seq, before.state.last_seq);
+        assert!(status.state.last_error.is_some());
+        assert!(!status.healthy);
+        assert!(worker.request_backfill().await.is_err());
+    }
+
+    let info = services
+        .system_service
+        .clone()
+        .with_index_worker(worker)
+        .info()
+        .await;
+    assert_eq!(
+        info.workers.embedding_index.map(|s| s.pending_events),
+        Some(status.pending_events)
+    );
+}
@@ -0,0 +1,192 @@
+use axum::{
+    body::Body,
+    extract::Extension,
+    http::{Request, StatusCode},
+    routing::{get, post},
+    Router,
+};
+use sqlx::PgPool;
+use std::sync::Arc;
+use template_repo_backend::features::api_management::ApiManagementService;
+use template_repo_backend::features::auth::jwt::Claims;
+use template_repo_backend::features::auth::models::RegisterUser;
+use template_repo_backend::middleware::{
+    auth::{auth_middleware, peek_user_id},
+    csrf::validate_csrf,
+};
+use tower::ServiceExt;
+use tower_cookies::CookieManagerLayer;
+
+mod common;
+
+fn app(api_keys: ApiManagementService) -> Router {
+    Router::new()
+        .route(
+            "/api/me",
+            get(|Extension(claims): Extension<Claims>| async move { claims.sub }),
+        )
+        .route(
+            "/api/permissions",
+            get(|Extension(claims): Extension<Claims>| async move { claims.permissions.join(",") }),
+        )
+        .route("/api/items", post(|| async { "ok" }))
+        .layer(axum::middleware::from_fn(auth_middleware))
+        .layer(axum::middleware::from_fn(validate_csrf))
+        .layer(Extension(Arc::new(common::create_test_config())))
+        .layer(Extension(api_keys))
+        .layer(CookieManagerLayer::new())
+}
+
+async fn status(app: &Router, method: &str, uri: &str, key: &str) -> StatusCode {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("x-api-key", key)
+        .body(Body::empty())
+        .unwrap();
+    app.clone().oneshot(request).await.unwrap().status()
+}
+
+#[sqlx::test]
+async fn test_api_keys_authenticate_until_expired_or_revoked(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let owner = services
+        .auth_service
+        .register(RegisterUser {
+            username: "key_owner".to_string(),
+            email: "key_owner@example.com".to_string(),
+            password: "password123".to_string(),
+        })
+        .await
+        .unwrap()
+        .user_id;
+    let api_keys = &services.api_management_service;
+    let app = app(api_keys.clone());
+
+    let key = api_keys
+        .create_key("client".to_string(), vec![], Some(30), Some(owner))
+        .await
+        .unwrap();
+    assert_eq!(
+        status(&app, "GET", "/api/me", &key.secret).await,
+        StatusCode::OK
+    );
+    // The key is the credential, so no CSRF token is needed
+    assert_eq!(
+        status(&app, "POST", "/api/items", &key.secret).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        status(&app, "GET", "/api/me", "pk_live_unknown").await,
+        StatusCode::UNAUTHORIZED
+    );
+
+    sqlx::query("UPDATE api_keys SET expires_at = NOW() - INTERVAL '1 minute' WHERE id = $1")
+        .bind(key.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert_eq!(
+        status(&app, "GET", "/api/me", &key.secret).await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        status(&app, "POST", "/api/items", &key.secret).await,
+        StatusCode::FORBIDDEN
+    );
+
+    let revoked = api_keys
+        .create_key("revoked".to_string(), vec![], Some(30), Some(owner))
+        .await
+        .unwrap();
+    api_keys.revoke_key(revoked.id, None).await.unwrap();
+    assert_eq!(
+        status(&app, "GET", "/api/me", &revoked.secret).await,
+        StatusCode::UNAUTHORIZED
+    );
+
+    // Scopes aren't enforced, so they mustn't read as granted permissions
+    let scoped = api_keys
+        .create_key(
+            "scoped".to_string(),
+            vec!["ui.view.roles".to_string()],
+            Some(30),
+            Some(owner),
+        )
+        .await
+        .unwrap();
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get("/api/permissions")
+                .header("x-api-key", &scoped.secret)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert!(body.is_empty());
+
+    // Keys without an owner have nobody to act as
+    let orphan = api_keys
+        .create_key("orphan".to_string(), vec![], Some(30), None)
+        .await
+        .unwrap();
+    assert_eq!(
+        status(&app, "GET", "/api/me", &orphan.secret).await,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[sqlx::test]
+async fn test_peek_user_id_resolves_api_key_owner(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let owner = services
+        .auth_service
+        .register(RegisterUser {
+            username: "peeked_owner".to_string(),
+            email: "peeked_owner@example.com".to_string(),
+            password: "password123".to_string(),
+        })
+        .await
+        .unwrap()
+        .user_id;
+    let api_keys = &services.api_management_service;
+    let key = api_keys
+        .create_key("peeked".to_string(), vec![], Some(30), Some(owner))
+        .await
+        .unwrap();
+
+    // IP and geo rules see the key's owner, as auth_middleware will
+    let request = Request::get("/api/me")
+        .header("x-api-key", &key.secret)
+        .extension(api_keys.clone())
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(
+        peek_user_id(request.headers(), request.extensions()).await,
+        Some(owner)
+    );
+    let unknown = Request::get("/api/me")
+        .header("x-api-key", "pk_live_unknown")
+        .extension(api_keys.clone())
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(
+        peek_user_id(unknown.headers(), unknown.extensions()).await,
+        None
+    );
+
+    // Peeking isn't use
+    let last_used: Option<chrono::DateTime<chrono::Utc>> =
+        sqlx::query_scalar("SELECT last_used_at FROM api_keys WHERE id = $1")
+            .bind(key.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert!(last_used.is_none());
+}
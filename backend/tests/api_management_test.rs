@@ -1,42 +1,58 @@
 use sqlx::PgPool;
+use template_repo_backend::features::api_management::service::ApiManagementError;
+use template_repo_backend::features::auth::models::RegisterUser;
 
 mod common;
 
+async fn register(services: &common::TestServices, username: &str) -> uuid::Uuid {
+    services
+        .auth_service
+        .register(RegisterUser {
+            username: username.to_string(),
+            email: format!("{}@example.com", username),
+            password: "password123".to_string(),
+        })
+        .await
+        .unwrap()
+        .user_id
+}
+
 #[sqlx::test]
 async fn test_api_key_lifecycle(pool: PgPool) {
     let services = common::setup_services(pool.clone()).await;
 
-    // 1. Create API Key - create_key(name, scopes)
+    // 1. Create API Key - create_key(name, scopes, expires_in_days, created_by)
     let key_name = "test_key";
     let scopes = vec!["read".to_string(), "write".to_string()];
     let key_resp = services
         .api_management_service
-        .create_key(key_name.to_string(), scopes.clone())
+        .create_key(key_name.to_string(), scopes.clone(), None, None)
         .await
         .expect("Failed to create API key");
 
     assert_eq!(key_resp.name, key_name);
     assert!(key_resp.secret.starts_with("pk_live_"));
     assert_eq!(key_resp.scopes, scopes);
+    assert!(key_resp.expires_at.is_some(), "Keys must always expire");
 
-    // 2. List API Keys - list_keys() takes no arguments in service
+    // 2. List API Keys - list_keys(owner), None for every key
     let keys = services
         .api_management_service
-        .list_keys()
+        .list_keys(None)
         .await
         .expect("Failed to list keys");
     assert!(keys.iter().any(|k| k.id == key_resp.id));
 
-    // 3. Revoke API Key - revoke_key(id)
+    // 3. Revoke API Key - revoke_key(id, owner)
     services
         .api_management_service
-        .revoke_key(key_resp.id)
+        .revoke_key(key_resp.id, None)
         .await
         .expect("Failed to revoke key");
 
     let keys_after = services
         .api_management_service
-        .list_keys()
+        .list_keys(None)
         .await
         .expect("Failed to list keys again");
     let revoked_key = keys_after
@@ -46,6 +62,139 @@ async fn test_api_key_lifecycle(pool: PgPool) {
     assert_eq!(revoked_key.status, "revoked");
 }
 
+#[sqlx::test]
+async fn test_api_key_rotation_grace_window(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let svc = &services.api_management_service;
+
+    let original = svc
+        .create_key(
+            "rotating_key".to_string(),
+            vec!["read".to_string()],
+            Some(30),
+            None,
+        )
+        .await
+        .expect("Failed to create API key");
+
+    let rotated = svc
+        .rotate_key(original.id, None, Some(1), None)
+        .await
+        .expect("Rotation failed")
+        .expect("Active key should be rotatable");
+
+    assert_ne!(rotated.id, original.id);
+    assert_ne!(rotated.secret, original.secret);
+    assert_eq!(rotated.rotated_from_id, Some(original.id));
+    assert_eq!(rotated.scopes, original.scopes);
+
+    // Both keys are valid during the grace window
+    assert!(svc.verify_key(&original.secret).await.unwrap().is_some());
+    assert!(svc.verify_key(&rotated.secret).await.unwrap().is_some());
+
+    // A superseded key cannot be rotated again
+    assert!(svc
+        .rotate_key(original.id, None, None, None)
+        .await
+        .unwrap()
+        .is_none());
+
+    // Once the grace window closes only the new key works
+    sqlx::query("UPDATE api_keys SET expires_at = NOW() - INTERVAL '1 minute' WHERE id = $1")
+        .bind(original.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert!(svc.verify_key(&original.secret).await.unwrap().is_none());
+    assert!(svc.verify_key(&rotated.secret).await.unwrap().is_some());
+}
+
+#[sqlx::test]
+async fn test_api_key_expiry_notifications(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let svc = &services.api_management_service;
+
+    let expiring = svc
+        .create_key("short_lived".to_string(), vec![], Some(3), None)
+        .await
+        .unwrap();
+    let long_lived = svc
+        .create_key("long_lived".to_string(), vec![], Some(90), None)
+        .await
+        .unwrap();
+
+    let notified = svc.notify_expiring_keys(7).await.unwrap();
+    assert_eq!(notified, 1);
+
+    let keys = svc.list_keys(None).await.unwrap();
+    let find = |id| keys.iter().find(|k| k.id == id).unwrap();
+    assert!(find(expiring.id).expiry_notified_at.is_some());
+    assert!(find(long_lived.id).expiry_notified_at.is_none());
+
+    // Keys are only notified once
+    assert_eq!(svc.notify_expiring_keys(7).await.unwrap(), 0);
+
+    // Out-of-range lifetimes and grace periods are rejected as bad input
+    assert!(matches!(
+        svc.create_key("forever".to_string(), vec![], Some(10_000), None)
+            .await,
+        Err(ApiManagementError::InvalidInput(_))
+    ));
+    assert!(matches!(
+        svc.rotate_key(long_lived.id, None, Some(10_000), None)
+            .await,
+        Err(ApiManagementError::InvalidInput(_))
+    ));
+}
+
+#[sqlx::test]
+async fn test_api_keys_are_scoped_to_their_owner(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let svc = &services.api_management_service;
+    let alice = register(&services, "key_alice").await;
+    let bob = register(&services, "key_bob").await;
+
+    let alices = svc
+        .create_key("alice_key".to_string(), vec![], Some(30), Some(alice))
+        .await
+        .unwrap();
+    let bobs = svc
+        .create_key("bob_key".to_string(), vec![], Some(30), Some(bob))
+        .await
+        .unwrap();
+
+    let listed = svc.list_keys(Some(alice)).await.unwrap();
+    assert!(listed.iter().all(|k| k.created_by == Some(alice)));
+    assert!(listed.iter().any(|k| k.id == alices.id));
+    assert!(svc
+        .list_keys(None)
+        .await
+        .unwrap()
+        .iter()
+        .any(|k| k.id == bobs.id));
+
+    // Someone else's key is treated as missing
+    assert!(svc
+        .rotate_key(bobs.id, Some(alice), None, None)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(matches!(
+        svc.revoke_key(bobs.id, Some(alice)).await,
+        Err(ApiManagementError::KeyNotFound)
+    ));
+    assert!(svc.verify_key(&bobs.secret).await.unwrap().is_some());
+
+    // Owners manage their own
+    assert!(svc
+        .rotate_key(alices.id, Some(alice), None, None)
+        .await
+        .unwrap()
+        .is_some());
+    svc.revoke_key(bobs.id, Some(bob)).await.unwrap();
+    assert!(svc.verify_key(&bobs.secret).await.unwrap().is_none());
+}
+
 #[sqlx::test]
 async fn test_webhook_management(pool: PgPool) {
     let services = common::setup_services(pool.clone()).await;
@@ -27,6 +27,11 @@ async fn create_test_app(pool: PgPool) -> Router {
         .with_state(services.auth_service.clone())
         .layer(from_fn(auth_middleware));
 
+    let notification_routes =
+        template_repo_backend::features::notifications::routes::notification_routes()
+            .with_state(services.notification_service.clone())
+            .layer(from_fn(auth_middleware));
+
     let config = common::create_test_config();
     let config_arc = Arc::new(config);
 
@@ -36,8 +41,10 @@ async fn create_test_app(pool: PgPool) -> Router {
             "/auth",
             Router::new().merge(public_routes).merge(protected_routes),
         )
+        .nest("/notifications", notification_routes)
         .layer(CookieManagerLayer::new())
         .layer(Extension(config_arc))
+        .layer(Extension(services.abac_service.clone()))
 }
 
 #[sqlx::test]
@@ -538,13 +545,12 @@ async fn test_api_notifications(pool: PgPool) {
         .unwrap();
 
     // 3. List Notifications via API
-    let list_uri = format!("/auth/notifications?user_id={}", user_id);
     let list_res = app
         .clone()
         .oneshot(
             Request::builder()
                 .method("GET")
-                .uri(&list_uri)
+                .uri("/notifications")
                 .header("cookie", &cookie_header)
                 .body(Body::empty())
                 .unwrap(),
@@ -566,12 +572,10 @@ async fn test_api_notifications(pool: PgPool) {
         .as_array()
         .expect("Should return notifications array");
     assert!(!notifs.is_empty(), "Should see created notification");
-    let notif_id = notifs[0]["id"]
-        .as_i64()
-        .expect("Notification ID should be i64");
+    assert_eq!(notifs[0]["id"], notif_id.to_string());
 
     // 4. Mark Read via API
-    let read_uri = format!("/auth/notifications/{}/read?user_id={}", notif_id, user_id);
+    let read_uri = format!("/notifications/{}/read", notif_id);
     let read_res = app
         .oneshot(
             Request::builder()
@@ -860,10 +864,10 @@ fn extract_reset_token_from_log(email: &str) -> String {
     let log_content = std::fs::read_to_string("data/emails.log").expect("Failed to read emails.log");
     for line in log_content.lines().rev() {
         if line.contains(email) && line.contains("reset-password/") {
-            // line format: ... Link: http://localhost:5373/reset-password/{token}
+            // line format: [...] To: {email} | Subject: ... | ... http://localhost:5373/reset-password/{token} ...
             let parts: Vec<&str> = line.split("reset-password/").collect();
-            if parts.len() > 1 {
-                return parts[1].trim().to_string();
+            if let Some(token) = parts.get(1).and_then(|rest| rest.split_whitespace().next()) {
+                return token.to_string();
             }
         }
     }
@@ -913,7 +917,7 @@ async fn test_verify_invalid_token(pool: PgPool) {
 
 #[sqlx::test]
 async fn test_password_reset_flow(pool: PgPool) {
-    let app = create_test_app(pool).await;
+    let app = create_test_app(pool.clone()).await;
 
     // 1. Register User
     let register_payload = serde_json::json!({
@@ -955,8 +959,13 @@ async fn test_password_reset_flow(pool: PgPool) {
         .unwrap();
     assert_eq!(forgot_res.status(), StatusCode::OK);
 
-    // 3. Extract Token from Log
-    // Give a small delay to ensure FS write? usually instantaneous for local test
+    // 3. Send the queued email, then extract the token from the log
+    let services = common::setup_services(pool).await;
+    services
+        .email_service
+        .deliver_due()
+        .await
+        .expect("Failed to deliver queued email");
     let token = extract_reset_token_from_log("reset_flow@example.com");
 
     // 4. Verify Token
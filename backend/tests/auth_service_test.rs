@@ -1,6 +1,7 @@
 use chrono::Utc;
 use sqlx::{PgPool, Row};
-use template_repo_backend::features::auth::models::{LoginUser, RegisterUser};
+use template_repo_backend::features::auth::models::{LoginUser, RegisterUser, SessionListQuery};
+use template_repo_backend::features::notifications::models::NotificationCategory;
 use uuid::Uuid;
 use totp_rs::{Algorithm, Secret, TOTP};
 
@@ -126,7 +127,6 @@ async fn test_login_new_device_notification(pool: PgPool) {
         .fetch_one(&pool)
         .await
         .unwrap();
-    let user_id_str = user_id.to_string();
 
     services
         .auth_service
@@ -166,12 +166,13 @@ async fn test_login_new_device_notification(pool: PgPool) {
 
     // 3. Verify notification created
     let notifs = services
-        .auth_service
-        .get_notifications(&user_id_str)
+        .notification_service
+        .list_notifications(user_id, Default::default())
         .await
-        .unwrap();
+        .unwrap()
+        .notifications;
     assert!(!notifs.is_empty());
-    assert!(notifs[0].1.contains("New sign-in detected"));
+    assert!(notifs[0].message.contains("New sign-in detected"));
 }
 
 #[sqlx::test]
@@ -668,13 +669,14 @@ async fn test_change_password_creates_notification(pool: PgPool) {
 
     // Verify notification was created
     let notifs = services
-        .auth_service
-        .get_notifications(&user_id.to_string())
+        .notification_service
+        .list_notifications(user_id, Default::default())
         .await
-        .unwrap();
+        .unwrap()
+        .notifications;
     
     assert!(!notifs.is_empty());
-    assert!(notifs.iter().any(|n| n.1.contains("password was successfully changed")));
+    assert!(notifs.iter().any(|n| n.message.contains("password was successfully changed")));
 }
 
 #[sqlx::test]
@@ -703,37 +705,39 @@ async fn test_mark_notification_read(pool: PgPool) {
     // Create notification
     services
         .auth_service
-        .create_notification(&user_id.to_string(), "Test notification")
+        .create_notification(&user_id.to_string(), NotificationCategory::Account, "Test notification")
         .await
         .expect("Failed to create notification");
 
     let notifs = services
-        .auth_service
-        .get_notifications(&user_id.to_string())
+        .notification_service
+        .list_notifications(user_id, Default::default())
         .await
-        .unwrap();
+        .unwrap()
+        .notifications;
     
     assert_eq!(notifs.len(), 1);
-    assert_eq!(notifs[0].2, 0); // Not read (0)
+    assert!(!notifs[0].read);
 
-    let notif_id = notifs[0].0;
+    let notif_id = notifs[0].id;
 
     // Mark as read
     services
-        .auth_service
-        .mark_notification_read(notif_id, &user_id.to_string())
+        .notification_service
+        .mark_read(user_id, notif_id)
         .await
         .expect("Failed to mark read");
 
     // Verify marked as read
     let notifs_updated = services
-        .auth_service
-        .get_notifications(&user_id.to_string())
+        .notification_service
+        .list_notifications(user_id, Default::default())
         .await
-        .unwrap();
+        .unwrap()
+        .notifications;
     
     assert_eq!(notifs_updated.len(), 1);
-    assert_eq!(notifs_updated[0].2, 1); // Read (1)
+    assert!(notifs_updated[0].read);
 }
 
 #[sqlx::test]
@@ -763,36 +767,38 @@ async fn test_mark_all_notifications_read(pool: PgPool) {
     for i in 1..=3 {
         services
             .auth_service
-            .create_notification(&user_id.to_string(), &format!("Notification {}", i))
+            .create_notification(&user_id.to_string(), NotificationCategory::Account, &format!("Notification {}", i))
             .await
             .expect("Failed to create notification");
     }
 
     let notifs = services
-        .auth_service
-        .get_notifications(&user_id.to_string())
+        .notification_service
+        .list_notifications(user_id, Default::default())
         .await
-        .unwrap();
+        .unwrap()
+        .notifications;
     
     assert_eq!(notifs.len(), 3);
     
     // Mark all as read
     services
-        .auth_service
-        .mark_all_notifications_read(&user_id.to_string())
+        .notification_service
+        .mark_all_read(user_id)
         .await
         .expect("Failed to mark all read");
 
     // Verify all marked as read
     let notifs_updated = services
-        .auth_service
-        .get_notifications(&user_id.to_string())
+        .notification_service
+        .list_notifications(user_id, Default::default())
         .await
-        .unwrap();
+        .unwrap()
+        .notifications;
     
     assert_eq!(notifs_updated.len(), 3);
     for notif in notifs_updated {
-        assert_eq!(notif.2, 1);
+        assert!(notif.read);
     }
 }
 
@@ -883,14 +889,17 @@ async fn test_list_all_sessions_admin(pool: PgPool) {
     // List all sessions
     let sessions = services
         .auth_service
-        .list_all_sessions(100)
+        .list_all_sessions(&SessionListQuery {
+            limit: Some(100),
+            ..Default::default()
+        })
         .await
         .expect("Failed to list all sessions");
 
-    assert!(sessions.len() >= 3);
+    assert!(sessions.items.len() >= 3);
     
     // Verify each session has required fields
-    for session in sessions {
+    for session in sessions.items {
         assert!(!session.id.is_empty());
         assert!(!session.username.is_empty());
         assert!(!session.email.is_empty());
@@ -1184,26 +1193,30 @@ async fn test_create_notification_broadcast(pool: PgPool) {
         .await
         .unwrap();
 
-    // Subscribe to notifications
-    let mut rx = services.auth_service.subscribe_notifications();
+    // Open a notification stream
+    let stream = services
+        .notification_service
+        .notification_stream(user_id, None)
+        .await
+        .unwrap();
+    tokio::pin!(stream);
 
     // Create notification
     services
         .auth_service
-        .create_notification(&user_id.to_string(), "Broadcast test")
+        .create_notification(&user_id.to_string(), NotificationCategory::Account, "Broadcast test")
         .await
         .expect("Failed to create notification");
 
     // Receive broadcast
     let event = tokio::time::timeout(
         tokio::time::Duration::from_millis(500),
-        rx.recv()
+        futures::StreamExt::next(&mut stream)
     ).await;
 
     assert!(event.is_ok());
-    let notification_event = event.unwrap().expect("Failed to receive notification");
-    assert_eq!(notification_event.user_id, user_id.to_string());
-    assert!(notification_event.message.contains("Broadcast test"));
+    let notification = event.unwrap().expect("Stream ended");
+    assert!(notification.message.contains("Broadcast test"));
 }
 
 #[sqlx::test]
@@ -1288,24 +1301,25 @@ async fn test_get_notifications(pool: PgPool) {
     for i in 1..=3 {
         services
             .auth_service
-            .create_notification(&user_id.to_string(), &format!("Test notification {}", i))
+            .create_notification(&user_id.to_string(), NotificationCategory::Account, &format!("Test notification {}", i))
             .await
             .expect("Failed to create notification");
     }
 
     // Get notifications
     let notifications = services
-        .auth_service
-        .get_notifications(&user_id.to_string())
+        .notification_service
+        .list_notifications(user_id, Default::default())
         .await
-        .expect("Failed to get notifications");
+        .expect("Failed to get notifications")
+        .notifications;
 
     assert_eq!(notifications.len(), 3, "Should have 3 notifications");
     
     // Verify ordering (newest first) - messages should be in reverse order
-    assert!(notifications[0].1.contains("notification 3"));
-    assert!(notifications[1].1.contains("notification 2"));
-    assert!(notifications[2].1.contains("notification 1"));
+    assert!(notifications[0].message.contains("notification 3"));
+    assert!(notifications[1].message.contains("notification 2"));
+    assert!(notifications[2].message.contains("notification 1"));
 }
 
 #[sqlx::test]
@@ -1333,10 +1347,11 @@ async fn test_get_notifications_empty(pool: PgPool) {
 
     // Get notifications for user with none
     let notifications = services
-        .auth_service
-        .get_notifications(&user_id.to_string())
+        .notification_service
+        .list_notifications(user_id, Default::default())
         .await
-        .expect("Failed to get notifications");
+        .expect("Failed to get notifications")
+        .notifications;
 
     assert_eq!(notifications.len(), 0, "Should have no notifications");
 }
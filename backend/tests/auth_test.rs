@@ -1,5 +1,6 @@
 use sqlx::PgPool;
 use template_repo_backend::features::auth::models::{LoginUser, RegisterUser};
+use template_repo_backend::features::notifications::models::NotificationCategory;
 use uuid::Uuid;
 
 mod common;
@@ -167,59 +168,62 @@ async fn test_notification_flow(pool: PgPool) {
         .fetch_one(&pool)
         .await
         .unwrap();
-    let user_id: Uuid = user_row.get("id");
-    let user_id = user_id.to_string();
+    let user_uuid: Uuid = user_row.get("id");
+    let user_id = user_uuid.to_string();
 
     // 1. Create Notification
     services
         .auth_service
-        .create_notification(&user_id, "Welcome")
+        .create_notification(&user_id, NotificationCategory::Account, "Welcome")
         .await
         .unwrap();
     services
         .auth_service
-        .create_notification(&user_id, "Alert")
+        .create_notification(&user_id, NotificationCategory::Account, "Alert")
         .await
         .unwrap();
 
     // 2. List Notifications
     let notifs = services
-        .auth_service
-        .get_notifications(&user_id)
+        .notification_service
+        .list_notifications(user_uuid, Default::default())
         .await
-        .unwrap();
+        .unwrap()
+        .notifications;
     assert_eq!(notifs.len(), 2);
-    assert_eq!(notifs[0].1, "Alert"); // Descending order
-    assert_eq!(notifs[0].2, 0); // Not read
+    assert_eq!(notifs[0].message, "Alert"); // Descending order
+    assert!(!notifs[0].read);
 
     // 3. Mark Read
-    let alert_id = notifs[0].0;
+    let alert_id = notifs[0].id;
     services
-        .auth_service
-        .mark_notification_read(alert_id, &user_id)
+        .notification_service
+        .mark_read(user_uuid, alert_id)
         .await
         .unwrap();
 
     let notifs_updated = services
-        .auth_service
-        .get_notifications(&user_id)
+        .notification_service
+        .list_notifications(user_uuid, Default::default())
         .await
-        .unwrap();
-    assert_eq!(notifs_updated[0].2, 1); // Read
+        .unwrap()
+        .notifications;
+    assert!(notifs_updated[0].read);
 
     // 4. Mark All Read
     services
-        .auth_service
-        .mark_all_notifications_read(&user_id)
+        .notification_service
+        .mark_all_read(user_uuid)
         .await
         .unwrap();
     let notifs_all = services
-        .auth_service
-        .get_notifications(&user_id)
+        .notification_service
+        .list_notifications(user_uuid, Default::default())
         .await
-        .unwrap();
+        .unwrap()
+        .notifications;
     for n in notifs_all {
-        assert_eq!(n.2, 1);
+        assert!(n.read);
     }
 }
 
@@ -0,0 +1,108 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    routing::post,
+    Json, Router,
+};
+use template_repo_backend::config::{BodyLimitsConfig, RouteBodyLimit};
+use template_repo_backend::middleware::body_limit::{body_limit_middleware, BodyLimits};
+use tower::ServiceExt;
+
+fn limits() -> BodyLimits {
+    BodyLimits::from_config(&BodyLimitsConfig {
+        default_bytes: 1024,
+        routes: vec![
+            RouteBodyLimit {
+                prefix: "/api/auth".to_string(),
+                max_bytes: 64,
+            },
+            RouteBodyLimit {
+                prefix: "/api/auth/import".to_string(),
+                max_bytes: 4096,
+            },
+        ],
+    })
+}
+
+fn app() -> Router {
+    let echo = post(|Json(v): Json<serde_json::Value>| async move { Json(v) });
+    Router::new()
+        .route("/api/auth/login", echo.clone())
+        .route("/api/auth/import", echo.clone())
+        .route("/api/items", echo)
+        .layer(axum::middleware::from_fn_with_state(
+            limits(),
+            body_limit_middleware,
+        ))
+        .layer(axum::extract::DefaultBodyLimit::disable())
+}
+
+fn json_body(size: usize) -> String {
+    format!("{{\"data\":\"{}\"}}", "x".repeat(size))
+}
+
+#[test]
+fn test_longest_prefix_wins() {
+    let limits = limits();
+    assert_eq!(limits.limit_for("/api/auth/login"), 64);
+    assert_eq!(limits.limit_for("/api/auth/import/entities"), 4096);
+    assert_eq!(limits.limit_for("/api/ontology/entities"), 1024);
+}
+
+#[tokio::test]
+async fn test_declared_length_over_limit_rejected() {
+    let body = json_body(200);
+    let response = app()
+        .oneshot(
+            Request::post("/api/auth/login")
+                .header("content-type", "application/json")
+                .header("content-length", body.len())
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(json["limit_bytes"], 64);
+}
+
+#[tokio::test]
+async fn test_streamed_body_over_limit_rejected() {
+    // No Content-Length: the limit is enforced while reading
+    let chunks = vec![Ok::<_, std::io::Error>(json_body(2000))];
+    let response = app()
+        .oneshot(
+            Request::post("/api/items")
+                .header("content-type", "application/json")
+                .body(Body::from_stream(futures::stream::iter(chunks)))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(json["error"], "Request body too large");
+}
+
+#[tokio::test]
+async fn test_body_within_route_limit_accepted() {
+    let response = app()
+        .oneshot(
+            Request::post("/api/auth/import")
+                .header("content-type", "application/json")
+                .body(Body::from(json_body(2000)))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
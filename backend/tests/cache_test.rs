@@ -0,0 +1,102 @@
+use sqlx::PgPool;
+use template_repo_backend::features::dashboard::DashboardService;
+use template_repo_backend::features::ontology::OntologyService;
+use template_repo_backend::features::rebac::models::{
+    CreateRelationshipTypeInput, UpdateRelationshipTypeInput,
+};
+use template_repo_backend::features::rebac::RebacService;
+use template_repo_backend::features::system::AuditService;
+use template_repo_backend::utils::cache::SharedCache;
+
+mod common;
+
+fn ontology(pool: &PgPool, cache: &SharedCache) -> OntologyService {
+    OntologyService::new(pool.clone(), AuditService::new(pool.clone())).with_cache(cache.clone())
+}
+
+#[sqlx::test]
+async fn system_classes_are_served_from_the_cache(pool: PgPool) {
+    let cache = SharedCache::local();
+    let ontology = ontology(&pool, &cache);
+    let role = ontology.get_system_class("Role").await.unwrap();
+
+    // A change made behind the service's back isn't seen until invalidation
+    sqlx::query("UPDATE classes SET description = 'changed' WHERE id = $1")
+        .bind(role.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let cached = ontology.get_system_class("Role").await.unwrap();
+    assert_eq!(cached.description, role.description);
+
+    ontology.invalidate_cache().await;
+    let fresh = ontology.get_system_class("Role").await.unwrap();
+    assert_eq!(fresh.description.as_deref(), Some("changed"));
+
+    // Misses aren't cached
+    assert!(ontology.get_system_class("NoSuchClass").await.is_err());
+}
+
+#[sqlx::test]
+async fn relationship_type_changes_invalidate_lookups(pool: PgPool) {
+    let cache = SharedCache::local();
+    let ontology = ontology(&pool, &cache);
+    let audit = AuditService::new(pool.clone());
+    let rebac = RebacService::new(pool.clone(), ontology.clone(), audit);
+
+    let created = rebac
+        .create_relationship_type(CreateRelationshipTypeInput {
+            name: "supplies".to_string(),
+            description: Some("before".to_string()),
+            grants_permission_inheritance: false,
+        })
+        .await
+        .unwrap();
+    let looked_up = ontology.get_relationship_type("supplies").await.unwrap();
+    assert_eq!(looked_up.id, created.id);
+
+    rebac
+        .update_relationship_type(
+            created.id,
+            UpdateRelationshipTypeInput {
+                description: Some("after".to_string()),
+                grants_permission_inheritance: None,
+            },
+        )
+        .await
+        .unwrap();
+    let looked_up = ontology.get_relationship_type("supplies").await.unwrap();
+    assert_eq!(looked_up.description.as_deref(), Some("after"));
+
+    rebac.delete_relationship_type(created.id).await.unwrap();
+    assert!(ontology.get_relationship_type("supplies").await.is_err());
+}
+
+#[sqlx::test]
+async fn dashboard_stats_are_reused_until_the_ontology_changes(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let cache = SharedCache::local();
+    let ontology = ontology(&pool, &cache);
+    let dashboard = DashboardService::new(pool.clone()).with_cache(cache.clone());
+
+    let before = dashboard.get_admin_stats().await.unwrap();
+    services
+        .ontology_service
+        .create_class(
+            template_repo_backend::features::ontology::models::CreateClassInput {
+                name: "CachedGadget".to_string(),
+                description: None,
+                parent_class_id: None,
+                is_abstract: None,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    let cached = dashboard.get_admin_stats().await.unwrap();
+    assert_eq!(cached.ontology_classes, before.ontology_classes);
+
+    ontology.invalidate_cache().await;
+    let fresh = dashboard.get_admin_stats().await.unwrap();
+    assert_eq!(fresh.ontology_classes, before.ontology_classes + 1);
+}
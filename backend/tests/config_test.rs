@@ -0,0 +1,26 @@
+use template_repo_backend::config::Config;
+
+#[test]
+fn test_cors_origins_env_override() {
+    // Only test in this binary, so mutating the process environment is safe
+    std::env::set_var(
+        "APP_CORS__ALLOWED_ORIGINS",
+        "https://app.example.com, https://admin.example.com",
+    );
+    std::env::set_var("APP_CORS__MAX_AGE_SECS", "600");
+
+    let config = Config::from_env().expect("Failed to load config");
+
+    assert_eq!(
+        config.cors.allowed_origins,
+        vec!["https://app.example.com", "https://admin.example.com"]
+    );
+    assert_eq!(config.cors.max_age_secs, 600);
+    // Values not overridden still come from config/default.toml
+    assert!(config.cors.allow_credentials);
+    assert!(config
+        .cors
+        .allowed_headers
+        .contains(&"x-csrf-token".to_string()));
+    assert_eq!(config.jwt_expiry, 3600);
+}
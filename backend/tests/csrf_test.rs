@@ -0,0 +1,170 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    routing::post,
+    Router,
+};
+use sqlx::PgPool;
+use std::sync::Arc;
+use template_repo_backend::config::{Config, CsrfConfig};
+use template_repo_backend::features::auth::jwt::create_jwt;
+use template_repo_backend::features::auth::routes::protected_auth_routes;
+use template_repo_backend::middleware::csrf::validate_csrf;
+use tower::ServiceExt;
+use tower_cookies::CookieManagerLayer;
+
+mod common;
+
+fn app(csrf: CsrfConfig) -> Router {
+    let config = Config {
+        csrf,
+        ..common::create_test_config()
+    };
+    Router::new()
+        .route("/api/items", post(|| async { "ok" }))
+        .layer(axum::middleware::from_fn(validate_csrf))
+        .layer(axum::Extension(Arc::new(config)))
+        .layer(CookieManagerLayer::new())
+}
+
+async fn post_status(app: Router, headers: &[(&str, &str)]) -> StatusCode {
+    let mut request = Request::post("/api/items");
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    app.oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+        .status()
+}
+
+#[tokio::test]
+async fn test_cookie_sessions_need_matching_token() {
+    let app = app(CsrfConfig::default());
+    assert_eq!(post_status(app.clone(), &[]).await, StatusCode::FORBIDDEN);
+    assert_eq!(
+        post_status(
+            app.clone(),
+            &[("cookie", "csrf_token=abc"), ("x-csrf-token", "xyz")]
+        )
+        .await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        post_status(
+            app,
+            &[("cookie", "csrf_token=abc"), ("x-csrf-token", "abc")]
+        )
+        .await,
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn test_token_authenticated_clients_skip_check() {
+    let token = create_jwt(
+        &uuid::Uuid::new_v4().to_string(),
+        "api_client",
+        "api@example.com",
+        vec![],
+        vec![],
+        &common::create_test_config(),
+    )
+    .unwrap();
+    let bearer = format!("Bearer {}", token);
+    let app = app(CsrfConfig::default());
+    assert_eq!(
+        post_status(app.clone(), &[("authorization", &bearer)]).await,
+        StatusCode::OK
+    );
+
+    // Tokens that wouldn't authenticate, or a session cookie auth could use
+    // instead, don't skip it
+    assert_eq!(
+        post_status(app.clone(), &[("authorization", "Bearer token")]).await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        post_status(app.clone(), &[("authorization", "Basic dXNlcjpwdw==")]).await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        post_status(
+            app.clone(),
+            &[
+                ("authorization", &bearer),
+                ("cookie", "access_token=session")
+            ]
+        )
+        .await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        post_status(app, &[("x-api-key", "key")]).await,
+        StatusCode::FORBIDDEN
+    );
+
+    // The exemption list is configurable
+    let app = self::app(CsrfConfig {
+        exempt_headers: vec![],
+        ..CsrfConfig::default()
+    });
+    assert_eq!(
+        post_status(app, &[("authorization", &bearer)]).await,
+        StatusCode::FORBIDDEN
+    );
+}
+
+#[sqlx::test]
+async fn test_token_endpoint_issues_and_reuses_token(pool: PgPool) {
+    let services = common::setup_services(pool).await;
+    let app = Router::new()
+        .nest(
+            "/api/auth",
+            protected_auth_routes().with_state(services.auth_service.clone()),
+        )
+        .layer(CookieManagerLayer::new());
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get("/api/auth/csrf-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let cookie = response
+        .headers()
+        .get("set-cookie")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert!(cookie.starts_with("csrf_token="));
+    assert!(cookie.contains("SameSite=Lax"));
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    let token = body["token"].as_str().unwrap().to_string();
+    assert_eq!(token.len(), 32);
+
+    // An existing session token is returned unchanged
+    let response = app
+        .oneshot(
+            Request::get("/api/auth/csrf-token")
+                .header("cookie", format!("csrf_token={}", token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert!(response.headers().get("set-cookie").is_none());
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(body["token"], token);
+}
@@ -0,0 +1,201 @@
+use sqlx::PgPool;
+use std::time::Duration;
+use template_repo_backend::config::DecisionLogConfig;
+use template_repo_backend::features::decision_log::models::{
+    Decision, DecisionReason, DecisionRecord,
+};
+use template_repo_backend::features::decision_log::DecisionLog;
+use template_repo_backend::features::ontology::models::{CreateClassInput, CreateEntityInput};
+use template_repo_backend::features::rebac::policy_models::CreatePolicyInput;
+use template_repo_backend::middleware::trace_context::with_trace_id;
+use template_repo_backend::utils::http_client::OutboundClient;
+use template_repo_backend::utils::shutdown::Shutdown;
+use uuid::Uuid;
+
+mod common;
+
+fn file_log(path: &std::path::Path) -> DecisionLog {
+    let config = DecisionLogConfig {
+        enabled: true,
+        sink: "file".to_string(),
+        path: path.to_string_lossy().to_string(),
+        flush_interval_ms: 50,
+        ..Default::default()
+    };
+    DecisionLog::from_config(&config, OutboundClient::from_config(&Default::default()))
+        .unwrap()
+        .unwrap()
+}
+
+fn read_records(path: &std::path::Path) -> Vec<DecisionRecord> {
+    std::fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[sqlx::test]
+async fn test_decisions_are_logged_with_their_trace(pool: PgPool) {
+    let path = std::env::temp_dir()
+        .join(format!("decision-log-test-{}", Uuid::new_v4()))
+        .join("decisions.jsonl");
+    let decision_log = file_log(&path);
+    let shutdown = Shutdown::new();
+    decision_log.start(shutdown.clone());
+
+    let mut services = common::setup_services(pool.clone()).await;
+    services.rebac_service = services
+        .rebac_service
+        .clone()
+        .with_decision_log(decision_log.clone());
+    let rebac = &services.rebac_service;
+
+    let user_id = Uuid::new_v4();
+    let user_class = services
+        .ontology_service
+        .get_system_class("User")
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO entities (id, class_id, display_name, attributes, approval_status) VALUES ($1, $2, 'auditor', '{}', 'APPROVED')")
+        .bind(user_id)
+        .bind(user_class.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let class = services
+        .ontology_service
+        .create_class(
+            CreateClassInput {
+                name: "DecisionVault".to_string(),
+                description: None,
+                parent_class_id: None,
+                is_abstract: Some(false),
+            },
+            Some(user_id),
+        )
+        .await
+        .unwrap();
+    let vault = services
+        .ontology_service
+        .create_entity(
+            CreateEntityInput {
+                class_id: class.id,
+                display_name: "Vault".to_string(),
+                parent_entity_id: None,
+                attributes: Some(serde_json::json!({ "status": "open" })),
+            },
+            Some(user_id),
+            None,
+        )
+        .await
+        .unwrap();
+
+    let trace_id = "4bf92f3577b34da6a3ce929d0e0e4736".to_string();
+    let denied = with_trace_id(
+        trace_id.clone(),
+        rebac.check_permission_integrated(user_id, vault.id, "open", None, None, None),
+    )
+    .await
+    .unwrap();
+    assert!(!denied);
+
+    rebac
+        .policy_service
+        .create_policy(
+            CreatePolicyInput {
+                name: "Open vaults".to_string(),
+                description: None,
+                effect: "ALLOW".to_string(),
+                priority: Some(100),
+                target_class_id: Some(class.id),
+                target_permissions: vec!["open".to_string()],
+                conditions: serde_json::json!({
+                    "all": [{ "attribute": "entity.status", "operator": "eq", "value": "open" }]
+                }),
+                scope_entity_id: None,
+                is_active: Some(true),
+                valid_from: None,
+                valid_until: None,
+                schedule_cron: None,
+            },
+            Some(user_id),
+        )
+        .await
+        .unwrap();
+    let allowed = rebac
+        .check_permission_integrated(user_id, vault.id, "open", None, None, None)
+        .await
+        .unwrap();
+    assert!(allowed);
+
+    shutdown.trigger();
+    assert!(shutdown.wait_for_tasks(Duration::from_secs(5)).await);
+
+    let records = read_records(&path);
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].trace_id.as_deref(), Some(trace_id.as_str()));
+    assert_eq!(records[0].subject_id, user_id);
+    assert_eq!(records[0].resource_id, vault.id);
+    assert_eq!(records[0].decision, Decision::Deny);
+    assert_eq!(records[0].reason, DecisionReason::NoGrant);
+    assert!(records[0].latency_ms > 0.0);
+
+    assert_eq!(records[1].trace_id, None);
+    assert_eq!(records[1].decision, Decision::Allow);
+    assert_eq!(records[1].reason, DecisionReason::PolicyAllow);
+    assert_eq!(records[1].matched_policy.as_deref(), Some("Open vaults"));
+
+    let stats = decision_log.stats();
+    assert_eq!((stats.recorded, stats.written, stats.dropped), (2, 2, 0));
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
+
+#[tokio::test]
+async fn test_sampled_out_and_overflowing_decisions_are_counted() {
+    let path = std::env::temp_dir().join(format!("decision-log-test-{}.jsonl", Uuid::new_v4()));
+    let record = |decision| DecisionRecord {
+        timestamp: chrono::Utc::now(),
+        trace_id: None,
+        subject_id: Uuid::new_v4(),
+        resource_id: Uuid::new_v4(),
+        tenant_id: None,
+        permission: "read".to_string(),
+        field_name: None,
+        decision,
+        reason: DecisionReason::NoGrant,
+        matched_role: None,
+        matched_policy: None,
+        latency_ms: 0.5,
+    };
+
+    // Not started, so the buffer of one fills up
+    let config = DecisionLogConfig {
+        enabled: true,
+        sink: "file".to_string(),
+        path: path.to_string_lossy().to_string(),
+        deny_sample_rate: 0.0,
+        buffer_size: 1,
+        ..Default::default()
+    };
+    let decision_log =
+        DecisionLog::from_config(&config, OutboundClient::from_config(&Default::default()))
+            .unwrap()
+            .unwrap();
+    decision_log.record(record(Decision::Deny));
+    decision_log.record(record(Decision::Allow));
+    decision_log.record(record(Decision::Allow));
+    let stats = decision_log.stats();
+    assert_eq!(
+        (stats.sampled_out, stats.recorded, stats.dropped),
+        (1, 1, 1)
+    );
+
+    // What was buffered is written on shutdown
+    let shutdown = Shutdown::new();
+    decision_log.start(shutdown.clone());
+    shutdown.trigger();
+    assert!(shutdown.wait_for_tasks(Duration::from_secs(5)).await);
+    assert_eq!(read_records(&path).len(), 1);
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,203 @@
+use serde_json::json;
+use sqlx::PgPool;
+use template_repo_backend::features::ontology::models::{DesiredState, PlanAction, PlanObject};
+use template_repo_backend::features::ontology::service::OntologyError;
+
+mod common;
+
+fn document() -> DesiredState {
+    serde_json::from_value(json!({
+        "classes": [
+            { "name": "DsFleet", "description": "A group of vehicles", "properties": [
+                { "name": "code", "data_type": "string", "is_required": true, "is_unique": true }
+            ]},
+            { "name": "DsTruck", "parent": "DsFleet", "properties": [
+                { "name": "payload", "data_type": "float" },
+                { "name": "fleet", "data_type": "reference", "reference_class": "DsFleet" }
+            ]}
+        ],
+        "relationship_types": [
+            { "name": "ds_tows", "source_class": "DsTruck", "target_class": "DsTruck",
+              "target_cardinality": "one" }
+        ],
+        "permission_types": [
+            { "name": "ds_dispatch", "description": "Send trucks out", "level": 45 }
+        ],
+        "roles": [
+            { "name": "ds_dispatcher", "level": 30, "permissions": ["ds_dispatch"] }
+        ]
+    }))
+    .unwrap()
+}
+
+#[sqlx::test]
+async fn test_plan_then_apply_converges(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let ontology = &services.ontology_service;
+    let desired = document();
+
+    let plan = ontology.plan_desired_state(&desired, false).await.unwrap();
+    assert!(!plan.applied);
+    assert_eq!(plan.changes.len(), 9);
+    assert!(plan.changes.iter().all(|c| c.action == PlanAction::Create));
+
+    // Planning doesn't write anything
+    let classes =
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM classes WHERE name = 'DsFleet'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(classes, 0);
+
+    let applied = ontology
+        .apply_desired_state(&desired, false, Some(&plan.fingerprint), None)
+        .await
+        .unwrap();
+    assert!(applied.applied);
+    assert_eq!(applied.changes, plan.changes);
+
+    let (parent, reference) = sqlx::query_as::<_, (String, String)>(
+        r#"
+        SELECT p.name, rc.name FROM classes c
+        JOIN classes p ON p.id = c.parent_class_id
+        JOIN properties pr ON pr.class_id = c.id AND pr.name = 'fleet'
+        JOIN classes rc ON rc.id = pr.reference_class_id
+        WHERE c.name = 'DsTruck'
+        "#,
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(
+        (parent.as_str(), reference.as_str()),
+        ("DsFleet", "DsFleet")
+    );
+
+    let granted = sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT COUNT(*) FROM relationships r
+        JOIN relationship_types rt ON rt.id = r.relationship_type_id AND rt.name = 'grants_permission'
+        JOIN entities role ON role.id = r.source_entity_id AND role.display_name = 'ds_dispatcher'
+        JOIN entities perm ON perm.id = r.target_entity_id AND perm.display_name = 'ds_dispatch'
+        "#,
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(granted, 1);
+
+    // Once applied there is nothing left to do
+    let again = ontology.plan_desired_state(&desired, false).await.unwrap();
+    assert!(again.changes.is_empty(), "{:?}", again.changes);
+
+    // Edits show up as updates of just what changed
+    let mut edited = desired.clone();
+    edited.classes[1].properties[0].is_indexed = true;
+    edited.roles[0].permissions = Some(Vec::new());
+    let plan = ontology.plan_desired_state(&edited, false).await.unwrap();
+    let summary: Vec<_> = plan
+        .changes
+        .iter()
+        .map(|c| (c.action, c.object, c.name.as_str(), c.fields.len()))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (PlanAction::Update, PlanObject::Property, "payload", 1),
+            (
+                PlanAction::Remove,
+                PlanObject::RolePermission,
+                "ds_dispatch",
+                0
+            ),
+        ]
+    );
+}
+
+#[sqlx::test]
+async fn test_stale_fingerprint_applies_nothing(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let ontology = &services.ontology_service;
+    let desired = document();
+
+    let plan = ontology.plan_desired_state(&desired, false).await.unwrap();
+    let mut smaller = desired.clone();
+    smaller.classes.truncate(1);
+    smaller.relationship_types.clear();
+    ontology
+        .apply_desired_state(&smaller, false, None, None)
+        .await
+        .unwrap();
+
+    let result = ontology
+        .apply_desired_state(&desired, false, Some(&plan.fingerprint), None)
+        .await;
+    assert!(matches!(result, Err(OntologyError::VersionConflict(_))));
+    let trucks =
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM classes WHERE name = 'DsTruck'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(trucks, 0);
+}
+
+#[sqlx::test]
+async fn test_prune_deprecates_what_the_document_drops(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let ontology = &services.ontology_service;
+    let desired = document();
+    ontology
+        .apply_desired_state(&desired, false, None, None)
+        .await
+        .unwrap();
+
+    let mut smaller = desired.clone();
+    smaller.classes[0].properties.clear();
+    let plan = ontology
+        .apply_desired_state(&smaller, true, None, None)
+        .await
+        .unwrap();
+    assert!(plan
+        .changes
+        .iter()
+        .all(|c| c.action == PlanAction::Deprecate));
+    assert!(plan
+        .changes
+        .iter()
+        .any(|c| c.object == PlanObject::Property && c.name == "code"));
+
+    let deprecated = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT p.is_deprecated FROM properties p
+        JOIN classes c ON c.id = p.class_id AND c.name = 'DsFleet'
+        WHERE p.name = 'code'
+        "#,
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert!(deprecated);
+
+    // Listing it again brings it back
+    let plan = ontology
+        .apply_desired_state(&desired, true, None, None)
+        .await
+        .unwrap();
+    assert_eq!(plan.changes.len(), 1);
+    assert_eq!(plan.changes[0].action, PlanAction::Update);
+    assert!(ontology
+        .plan_desired_state(&desired, true)
+        .await
+        .unwrap()
+        .changes
+        .is_empty());
+
+    let invalid: DesiredState = serde_json::from_value(json!({
+        "roles": [{ "name": "ds_dispatcher", "permissions": ["ds_fly"] }]
+    }))
+    .unwrap();
+    assert!(matches!(
+        ontology.plan_desired_state(&invalid, false).await,
+        Err(OntologyError::InvalidInput(_))
+    ));
+}
@@ -0,0 +1,239 @@
+use axum::async_trait;
+use sqlx::PgPool;
+use std::sync::{Arc, Mutex};
+use template_repo_backend::features::auth::models::RegisterUser;
+use template_repo_backend::features::email::models::{
+    ListDeliveriesQuery, OutgoingEmail, UpdateEmailTemplateInput,
+};
+use template_repo_backend::features::email::providers::{EmailProvider, SendFailure};
+use template_repo_backend::features::email::templates::PASSWORD_RESET_EMAIL;
+use template_repo_backend::features::email::EmailError;
+use uuid::Uuid;
+
+mod common;
+
+/// Records what it is asked to send and answers with scripted failures first
+#[derive(Default)]
+struct ScriptedProvider {
+    failures: Mutex<Vec<SendFailure>>,
+    sent: Mutex<Vec<OutgoingEmail>>,
+}
+
+#[async_trait]
+impl EmailProvider for ScriptedProvider {
+    fn name(&self) -> &str {
+        "scripted"
+    }
+
+    async fn send(&self, email: &OutgoingEmail) -> Result<Option<String>, SendFailure> {
+        if let Some(failure) = self.failures.lock().unwrap().pop() {
+            return Err(failure);
+        }
+        self.sent.lock().unwrap().push(email.clone());
+        Ok(Some(format!("msg-{}", self.sent.lock().unwrap().len())))
+    }
+}
+
+async fn make_due(pool: &PgPool) {
+    sqlx::query("UPDATE email_deliveries SET next_attempt_at = NOW() WHERE status = 'pending'")
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+#[sqlx::test]
+async fn test_password_reset_email_is_queued_and_delivered(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let provider = Arc::new(ScriptedProvider::default());
+    let email_service = services
+        .email_service
+        .clone()
+        .with_provider(provider.clone());
+    let admin = Uuid::new_v4();
+
+    services
+        .auth_service
+        .register(RegisterUser {
+            username: "mail_user".to_string(),
+            email: "mail_user@example.com".to_string(),
+            password: "Password123!".to_string(),
+        })
+        .await
+        .expect("Registration failed");
+
+    // Deployment wording replaces the built-in
+    email_service
+        .update_template(
+            "password_reset",
+            UpdateEmailTemplateInput {
+                subject: "Password help for {{recipient}}".to_string(),
+                text_body: "Reset here: {{reset_link}}".to_string(),
+                html_body: Some("<a href=\"{{reset_link}}\">{{app_name}}</a>".to_string()),
+            },
+            admin,
+        )
+        .await
+        .expect("Failed to override template");
+
+    let token = services
+        .auth_service
+        .request_password_reset("mail_user@example.com")
+        .await
+        .unwrap()
+        .expect("Reset token should be issued");
+
+    // Queued, not sent, until the worker runs
+    let queued = email_service
+        .list_deliveries(ListDeliveriesQuery {
+            status: Some("pending".to_string()),
+            recipient: Some("mail_user@example.com".to_string()),
+            template: None,
+            limit: None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(queued.len(), 1);
+    assert!(provider.sent.lock().unwrap().is_empty());
+
+    assert_eq!(email_service.deliver_due().await.unwrap(), 1);
+    {
+        let sent = provider.sent.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].to, "mail_user@example.com");
+        assert_eq!(sent[0].subject, "Password help for mail_user@example.com");
+        assert_eq!(
+            sent[0].text_body,
+            format!("Reset here: http://localhost:5373/reset-password/{}", token)
+        );
+        assert!(sent[0]
+            .html_body
+            .as_deref()
+            .unwrap()
+            .contains(">Ontology Manager</a>"));
+    }
+
+    // The log keeps the outcome but not the single-use link
+    let delivery = email_service.get_delivery(queued[0].id).await.unwrap();
+    assert_eq!(delivery.status, "sent");
+    assert_eq!(delivery.attempts, 1);
+    assert_eq!(delivery.provider.as_deref(), Some("scripted"));
+    assert_eq!(delivery.provider_message_id.as_deref(), Some("msg-1"));
+    assert!(delivery.text_body.is_none() && delivery.html_body.is_none());
+    assert!(!serde_json::to_string(&delivery).unwrap().contains(&token));
+
+    // Nothing left to send
+    assert_eq!(email_service.deliver_due().await.unwrap(), 0);
+
+    // Undeclared variables are rejected; resetting restores the built-in
+    let err = email_service
+        .update_template(
+            "password_reset",
+            UpdateEmailTemplateInput {
+                subject: "{{password}}".to_string(),
+                text_body: "x".to_string(),
+                html_body: None,
+            },
+            admin,
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, EmailError::InvalidInput(_)));
+    let reset = email_service
+        .reset_template("password_reset", admin)
+        .await
+        .unwrap();
+    assert!(!reset.is_overridden);
+    assert_eq!(reset.subject, PASSWORD_RESET_EMAIL.subject);
+}
+
+#[sqlx::test]
+async fn test_failed_deliveries_are_retried_then_given_up(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let provider = Arc::new(ScriptedProvider::default());
+    let email_service = services
+        .email_service
+        .clone()
+        .with_provider(provider.clone());
+    let link = email_service.link("reset-password/abc");
+
+    // Transient failures back off and stay pending until attempts run out
+    provider
+        .failures
+        .lock()
+        .unwrap()
+        .extend((0..5).map(|i| SendFailure::Transient(format!("connection refused {}", i))));
+    let id = email_service
+        .queue(
+            &PASSWORD_RESET_EMAIL,
+            "retry@example.com",
+            &[("reset_link", &link)],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(email_service.deliver_due().await.unwrap(), 1);
+    let delivery = email_service.get_delivery(id).await.unwrap();
+    assert_eq!(delivery.status, "pending");
+    assert_eq!(delivery.attempts, 1);
+    assert_eq!(delivery.last_error.as_deref(), Some("connection refused 4"));
+    assert!(delivery.next_attempt_at > chrono::Utc::now());
+    // Not due yet
+    assert_eq!(email_service.deliver_due().await.unwrap(), 0);
+
+    for _ in 1..5 {
+        make_due(&pool).await;
+        assert_eq!(email_service.deliver_due().await.unwrap(), 1);
+    }
+    let delivery = email_service.get_delivery(id).await.unwrap();
+    assert_eq!(delivery.status, "failed");
+    assert_eq!(delivery.attempts, 5);
+    assert_eq!(email_service.delivery_stats().await.unwrap().failed, 1);
+
+    // An admin retry starts a fresh round, and the kept body is sent
+    let retried = email_service
+        .retry_delivery(id, Uuid::new_v4())
+        .await
+        .unwrap();
+    assert_eq!(retried.status, "pending");
+    assert_eq!(retried.attempts, 0);
+    assert_eq!(email_service.deliver_due().await.unwrap(), 1);
+    assert_eq!(email_service.get_delivery(id).await.unwrap().status, "sent");
+    assert!(provider.sent.lock().unwrap()[0].text_body.contains(&link));
+
+    // Sent messages can't be retried
+    assert!(matches!(
+        email_service.retry_delivery(id, Uuid::new_v4()).await,
+        Err(EmailError::InvalidInput(_))
+    ));
+
+    // Permanent rejections fail on the first attempt
+    provider
+        .failures
+        .lock()
+        .unwrap()
+        .push(SendFailure::Permanent("SMTP 550 no such user".to_string()));
+    let rejected = email_service
+        .queue(
+            &PASSWORD_RESET_EMAIL,
+            "nobody@example.com",
+            &[("reset_link", &link)],
+        )
+        .await
+        .unwrap();
+    email_service.deliver_due().await.unwrap();
+    let delivery = email_service.get_delivery(rejected).await.unwrap();
+    assert_eq!(delivery.status, "failed");
+    assert_eq!(delivery.attempts, 1);
+
+    // Addresses that could inject SMTP commands or headers are refused
+    assert!(matches!(
+        email_service
+            .queue(
+                &PASSWORD_RESET_EMAIL,
+                "a@example.com>\r\nRCPT TO:<b@example.com",
+                &[("reset_link", &link)],
+            )
+            .await,
+        Err(EmailError::InvalidInput(_))
+    ));
+}
@@ -0,0 +1,271 @@
+use sqlx::PgPool;
+use template_repo_backend::features::environment::models::{
+    BundleEntity, BundleRelationship, EnvironmentBundle, ImportOptions,
+};
+use template_repo_backend::features::environment::service::EnvironmentError;
+use template_repo_backend::features::environment::EnvironmentService;
+use template_repo_backend::features::ontology::models::{
+    ApprovalStatus, CreateClassInput, CreateEntityInput, CreateRelationshipInput,
+};
+use uuid::Uuid;
+
+mod common;
+
+/// A tenant with two sites, one inside the other, a depot that depends on
+/// the outer one, a policy and access settings. Returns the service and the
+/// outer site's id.
+async fn staging_tenant(pool: &PgPool, tenant_id: Uuid) -> (EnvironmentService, Uuid) {
+    let services = common::setup_services(pool.clone()).await;
+    let environment = EnvironmentService::new(
+        pool.clone(),
+        services.ontology_service.clone(),
+        services.audit_service.clone(),
+    );
+    let class = services
+        .ontology_service
+        .create_class(
+            CreateClassInput {
+                name: "EnvSite".to_string(),
+                description: None,
+                parent_class_id: None,
+                is_abstract: None,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    let mut ids = Vec::new();
+    for name in ["Oslo", "Oslo Depot"] {
+        let entity = services
+            .ontology_service
+            .create_entity(
+                CreateEntityInput {
+                    class_id: class.id,
+                    display_name: name.to_string(),
+                    parent_entity_id: ids.first().copied(),
+                    attributes: Some(serde_json::json!({ "code": name.to_lowercase() })),
+                },
+                None,
+                Some(tenant_id),
+            )
+            .await
+            .unwrap();
+        ids.push(entity.id);
+    }
+    services
+        .ontology_service
+        .create_relationship(
+            CreateRelationshipInput {
+                source_entity_id: ids[1],
+                target_entity_id: ids[0],
+                relationship_type: "depends_on".to_string(),
+                metadata: None,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    sqlx::query(
+        r#"
+        INSERT INTO policies (name, effect, priority, target_class_id, target_permissions,
+                              conditions, scope_entity_id, tenant_id, is_active)
+        VALUES ('Sites are read-only', 'DENY', 100, $1, ARRAY['update'], '{}', $2, $3, TRUE)
+        "#,
+    )
+    .bind(class.id)
+    .bind(ids[0])
+    .bind(tenant_id)
+    .execute(pool)
+    .await
+    .unwrap();
+    sqlx::query(
+        r#"
+        INSERT INTO ip_access_rules (cidr, action, scope, tenant_id)
+        VALUES ('10.0.0.0/8', 'allow', 'tenant', $1)
+        "#,
+    )
+    .bind(tenant_id)
+    .execute(pool)
+    .await
+    .unwrap();
+
+    (environment, ids[0])
+}
+
+async fn tenant_entities(pool: &PgPool, tenant_id: Uuid) -> Vec<(Uuid, String, Option<Uuid>)> {
+    sqlx::query_as::<_, (Uuid, String, Option<Uuid>)>(
+        r#"
+        SELECT e.id, e.display_name, e.parent_entity_id FROM entities e
+        JOIN classes c ON c.id = e.class_id
+        WHERE e.tenant_id = $1 AND c.name = 'EnvSite' AND e.deleted_at IS NULL
+        ORDER BY e.display_name
+        "#,
+    )
+    .bind(tenant_id)
+    .fetch_all(pool)
+    .await
+    .unwrap()
+}
+
+#[sqlx::test]
+async fn test_tenant_bundle_promotes_to_another_tenant(pool: PgPool) {
+    let staging = Uuid::new_v4();
+    let production = Uuid::new_v4();
+    let (environment, oslo) = staging_tenant(&pool, staging).await;
+
+    let bundle = environment.export_bundle(Some(staging), None).await.unwrap();
+    assert!(bundle.entities.iter().all(|e| e.class != "User"));
+    assert!(bundle.entities.iter().any(|e| e.class == "Role"));
+    let sites: Vec<&BundleEntity> = bundle
+        .entities
+        .iter()
+        .filter(|e| e.class == "EnvSite")
+        .collect();
+    assert_eq!(sites.len(), 2);
+    // Roles come with the permissions they grant
+    assert_eq!(
+        bundle
+            .relationships
+            .iter()
+            .filter(|r| r.relationship_type == "depends_on")
+            .count(),
+        1
+    );
+    assert_eq!(bundle.policies.len(), 1);
+    assert_eq!(bundle.settings.ip_access_rules.len(), 1);
+    // Instance-wide settings stay out of tenant bundles
+    assert!(bundle.settings.notification_templates.is_empty());
+
+    // A round trip through JSON, as the API and CLI do
+    let bundle: EnvironmentBundle =
+        serde_json::from_str(&serde_json::to_string(&bundle).unwrap()).unwrap();
+    let report = environment
+        .import_bundle(
+            &bundle,
+            ImportOptions {
+                tenant_id: Some(production),
+                dry_run: false,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    // The staging entities keep their ids, so the copies get new ones
+    assert_eq!(report.entities_created, 2);
+    assert_eq!(report.entities_remapped, 2);
+    assert_eq!(report.entities_matched, bundle.entities.len() - 2);
+    assert_eq!(report.relationships, bundle.relationships.len());
+    assert_eq!((report.policies, report.settings), (1, 1));
+
+    let copied = tenant_entities(&pool, production).await;
+    assert_eq!(copied.len(), 2);
+    let (copied_oslo, _, _) = copied[0];
+    assert_ne!(copied_oslo, oslo);
+    assert_eq!(copied[1].2, Some(copied_oslo));
+    let (scope, policy_tenant) = sqlx::query_as::<_, (Option<Uuid>, Option<Uuid>)>(
+        "SELECT scope_entity_id, tenant_id FROM policies WHERE tenant_id = $1",
+    )
+    .bind(production)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!((scope, policy_tenant), (Some(copied_oslo), Some(production)));
+    // Staging is untouched
+    assert_eq!(tenant_entities(&pool, staging).await[0].0, oslo);
+
+    // Importing into the source tenant again updates in place
+    let report = environment
+        .import_bundle(&bundle, ImportOptions::default(), None)
+        .await
+        .unwrap();
+    assert_eq!((report.entities_created, report.entities_updated), (0, 2));
+    assert_eq!(report.settings, 0);
+    assert_eq!(tenant_entities(&pool, staging).await.len(), 2);
+}
+
+#[sqlx::test]
+async fn test_dry_run_writes_nothing(pool: PgPool) {
+    let staging = Uuid::new_v4();
+    let production = Uuid::new_v4();
+    let (environment, _) = staging_tenant(&pool, staging).await;
+    let bundle = environment.export_bundle(Some(staging), None).await.unwrap();
+
+    let report = environment
+        .import_bundle(
+            &bundle,
+            ImportOptions {
+                tenant_id: Some(production),
+                dry_run: true,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    assert!(report.dry_run);
+    assert_eq!(report.entities_created, 2);
+    assert!(tenant_entities(&pool, production).await.is_empty());
+}
+
+#[sqlx::test]
+async fn test_incompatible_bundles_are_rejected(pool: PgPool) {
+    let staging = Uuid::new_v4();
+    let (environment, _) = staging_tenant(&pool, staging).await;
+    let mut bundle = environment.export_bundle(Some(staging), None).await.unwrap();
+
+    bundle.schema_version += 1;
+    bundle.entities.push(BundleEntity {
+        id: Uuid::new_v4(),
+        class: "NoSuchClass".to_string(),
+        display_name: "Ghost".to_string(),
+        parent_id: None,
+        tenant_id: Some(staging),
+        attributes: serde_json::json!({}),
+        approval_status: ApprovalStatus::APPROVED,
+    });
+    bundle.relationships.push(BundleRelationship {
+        source_id: bundle.entities[0].id,
+        target_id: bundle.entities[1].id,
+        relationship_type: "haunts".to_string(),
+        metadata: None,
+        tenant_id: Some(staging),
+    });
+
+    let production = Uuid::new_v4();
+    match environment
+        .import_bundle(
+            &bundle,
+            ImportOptions {
+                tenant_id: Some(production),
+                dry_run: false,
+            },
+            None,
+        )
+        .await
+    {
+        Err(EnvironmentError::Incompatible(problems)) => {
+            assert_eq!(problems.len(), 3, "{:?}", problems);
+            assert!(problems.iter().any(|p| p.contains("NoSuchClass")));
+            assert!(problems.iter().any(|p| p.contains("haunts")));
+        }
+        other => panic!("expected an incompatible bundle, got {:?}", other),
+    }
+    assert!(tenant_entities(&pool, production).await.is_empty());
+
+    // An instance bundle can't be narrowed to a tenant
+    let instance = environment.export_bundle(None, None).await.unwrap();
+    assert!(matches!(
+        environment
+            .import_bundle(
+                &instance,
+                ImportOptions {
+                    tenant_id: Some(production),
+                    dry_run: true,
+                },
+                None,
+            )
+            .await,
+        Err(EnvironmentError::InvalidInput(_))
+    ));
+}
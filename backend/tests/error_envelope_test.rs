@@ -0,0 +1,134 @@
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    response::{Html, IntoResponse},
+    routing::{get, post},
+    Json, Router,
+};
+use serde_json::json;
+use template_repo_backend::features::ontology::service::OntologyError;
+use template_repo_backend::features::rebac::RebacError;
+use template_repo_backend::middleware::error_envelope::error_envelope_middleware;
+use template_repo_backend::middleware::trace_context::{trace_context_middleware, X_TRACE_ID};
+use tower::ServiceExt;
+
+fn app() -> Router {
+    Router::new()
+        .route(
+            "/typed",
+            get(|| async { Err::<(), _>(OntologyError::VersionConflict("stale".to_string())) }),
+        )
+        .route(
+            "/rebac",
+            get(|| async { Err::<(), _>(RebacError::PermissionDenied("no".to_string())) }),
+        )
+        .route("/bare", get(|| async { StatusCode::NOT_FOUND }))
+        .route(
+            "/legacy",
+            get(|| async {
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    [(header::RETRY_AFTER, "5")],
+                    Json(json!({ "error": "Busy", "retry_after_secs": 5 })),
+                )
+            }),
+        )
+        .route(
+            "/json",
+            post(|Json(body): Json<serde_json::Value>| async { Json(body) }),
+        )
+        .route(
+            "/html",
+            get(|| async { (StatusCode::BAD_GATEWAY, Html("<h1>down</h1>")).into_response() }),
+        )
+        .layer(axum::middleware::from_fn(error_envelope_middleware))
+        .layer(axum::middleware::from_fn(trace_context_middleware))
+}
+
+async fn send(request: Request<Body>) -> (StatusCode, axum::http::HeaderMap, Vec<u8>) {
+    let response = app().oneshot(request).await.unwrap();
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, headers, body.to_vec())
+}
+
+async fn envelope(request: Request<Body>) -> (StatusCode, serde_json::Value) {
+    let (status, headers, body) = send(request).await;
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    // The request id is the trace id the response carries
+    assert_eq!(
+        body["request_id"],
+        headers[X_TRACE_ID].to_str().unwrap(),
+        "{}",
+        body
+    );
+    (status, body)
+}
+
+fn get_request(uri: &str) -> Request<Body> {
+    Request::get(uri).body(Body::empty()).unwrap()
+}
+
+#[tokio::test]
+async fn test_feature_errors_carry_stable_codes() {
+    let (status, body) = envelope(get_request("/typed")).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "ontology.version_conflict");
+    assert_eq!(body["error"], "Version conflict: stale");
+
+    let (status, body) = envelope(get_request("/rebac")).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["code"], "rebac.permission_denied");
+}
+
+#[tokio::test]
+async fn test_other_errors_are_enveloped() {
+    let (status, body) = envelope(get_request("/bare")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "not_found");
+    assert_eq!(body["error"], "Not Found");
+
+    let (status, headers, _) = send(get_request("/legacy")).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(headers[header::RETRY_AFTER], "5");
+    let (_, body) = envelope(get_request("/legacy")).await;
+    assert_eq!(body["code"], "unavailable");
+    assert_eq!(body["error"], "Busy");
+    assert_eq!(body["details"]["retry_after_secs"], 5);
+
+    // Extractor rejections are plain text from axum
+    let (status, body) = envelope(
+        Request::post("/json")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from("{not json"))
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["code"], "bad_request");
+    assert!(body["error"].as_str().unwrap().contains("JSON"), "{}", body);
+
+    let (status, body) = envelope(Request::post("/json").body(Body::empty()).unwrap()).await;
+    assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert_eq!(body["code"], "unsupported_media_type");
+}
+
+#[tokio::test]
+async fn test_successes_and_html_pass_through() {
+    let (status, _, body) = send(
+        Request::post("/json")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"a":1}"#))
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, br#"{"a":1}"#);
+
+    let (status, _, body) = send(get_request("/html")).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    assert_eq!(body, b"<h1>down</h1>");
+}
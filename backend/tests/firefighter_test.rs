@@ -1,4 +1,11 @@
 use sqlx::PgPool;
+use template_repo_backend::features::auth::models::RegisterUser;
+use template_repo_backend::features::firefighter::models::{
+    ElevationOutcome, FirefighterScope, SaveApprovalPolicyInput,
+};
+use template_repo_backend::features::firefighter::service::FirefighterError;
+use template_repo_backend::features::ontology::models::{CreateClassInput, CreateEntityInput};
+use uuid::Uuid;
 
 mod common;
 
@@ -25,7 +32,9 @@ async fn test_firefighter_activation_lifecycle(pool: PgPool) {
             None,
         )
         .await
-        .expect("Failed to activate firefighter");
+        .expect("Failed to activate firefighter")
+        .into_session()
+        .expect("No approval policy applies");
 
     assert_eq!(session.user_id, user.id);
     assert_eq!(session.justification, justification);
@@ -77,3 +86,583 @@ async fn test_firefighter_invalid_password(pool: PgPool) {
         .await;
     assert!(result.is_err());
 }
+
+async fn register(services: &common::TestServices, username: &str) -> Uuid {
+    services
+        .auth_service
+        .register(RegisterUser {
+            username: username.to_string(),
+            email: format!("{}@example.com", username),
+            password: "password123".to_string(),
+        })
+        .await
+        .unwrap()
+        .user_id
+}
+
+async fn notification_count(pool: &PgPool, user_id: Uuid) -> i64 {
+    sqlx::query_scalar(
+        "SELECT COUNT(*) FROM entities e JOIN classes c ON c.id = e.class_id
+         WHERE c.name = 'Notification' AND e.attributes->>'user_id' = $1::text",
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
+
+#[sqlx::test]
+async fn test_firefighter_two_person_approval(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let firefighter = &services.firefighter_service;
+    let requester = register(&services, "ff_requester").await;
+    let approver = register(&services, "ff_approver").await;
+    let bystander = register(&services, "ff_bystander").await;
+
+    // Approval needs at least one approver
+    let invalid = firefighter
+        .save_approval_policy(
+            SaveApprovalPolicyInput {
+                tenant_id: None,
+                requires_approval: true,
+                approver_ids: vec![],
+                request_ttl_minutes: None,
+            },
+            approver,
+        )
+        .await;
+    assert!(matches!(invalid, Err(FirefighterError::InvalidInput(_))));
+
+    // The requester is listed too, but can never approve their own request
+    firefighter
+        .save_approval_policy(
+            SaveApprovalPolicyInput {
+                tenant_id: None,
+                requires_approval: true,
+                approver_ids: vec![requester, approver],
+                request_ttl_minutes: Some(30),
+            },
+            approver,
+        )
+        .await
+        .unwrap();
+
+    let outcome = firefighter
+        .request_elevation(
+            requester,
+            "password123",
+            "Production outage".to_string(),
+            Some(30),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    let ElevationOutcome::PendingApproval(request) = outcome else {
+        panic!("elevation should wait for approval");
+    };
+    assert_eq!(request.status, "PENDING");
+    assert_eq!(request.approver_ids, vec![approver]);
+
+    // Pending: nothing is elevated yet, and the approver was notified
+    let status = firefighter.get_status(requester).await.unwrap();
+    assert!(!status.is_active);
+    assert_eq!(status.pending_request.unwrap().id, request.id);
+    assert_eq!(notification_count(&pool, approver).await, 1);
+
+    // Asking again returns the same request without notifying again
+    let again = firefighter
+        .request_elevation(
+            requester,
+            "password123",
+            "Still down".to_string(),
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    assert!(matches!(again, ElevationOutcome::PendingApproval(r) if r.id == request.id));
+    assert_eq!(notification_count(&pool, approver).await, 1);
+
+    assert!(matches!(
+        firefighter
+            .approve_request(requester, request.id, None)
+            .await,
+        Err(FirefighterError::Forbidden(_))
+    ));
+    assert!(matches!(
+        firefighter
+            .approve_request(bystander, request.id, None)
+            .await,
+        Err(FirefighterError::Forbidden(_))
+    ));
+    assert!(firefighter
+        .list_pending_approvals(bystander)
+        .await
+        .unwrap()
+        .is_empty());
+    let pending = firefighter.list_pending_approvals(approver).await.unwrap();
+    assert_eq!(pending.len(), 1);
+
+    let session = firefighter
+        .approve_request(approver, request.id, Some("Confirmed by phone".to_string()))
+        .await
+        .unwrap();
+    assert_eq!(session.user_id, requester);
+    let status = firefighter.get_status(requester).await.unwrap();
+    assert!(status.is_active);
+    assert!(status.pending_request.is_none());
+    assert_eq!(notification_count(&pool, requester).await, 1);
+    assert!(matches!(
+        firefighter
+            .approve_request(approver, request.id, None)
+            .await,
+        Err(FirefighterError::Conflict(_))
+    ));
+
+    // A tenant policy overrides the global one for that tenant's users
+    let tenant_user = register(&services, "ff_tenant_user").await;
+    let tenant_id = Uuid::new_v4();
+    sqlx::query("UPDATE entities SET tenant_id = $1 WHERE id = $2")
+        .bind(tenant_id)
+        .bind(tenant_user)
+        .execute(&pool)
+        .await
+        .unwrap();
+    firefighter
+        .save_approval_policy(
+            SaveApprovalPolicyInput {
+                tenant_id: Some(tenant_id),
+                requires_approval: false,
+                approver_ids: vec![],
+                request_ttl_minutes: None,
+            },
+            approver,
+        )
+        .await
+        .unwrap();
+    let outcome = firefighter
+        .request_elevation(
+            tenant_user,
+            "password123",
+            "Tenant incident".to_string(),
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    assert!(matches!(outcome, ElevationOutcome::Activated(_)));
+
+    // Rejection leaves the requester without access
+    let outcome = firefighter
+        .request_elevation(
+            bystander,
+            "password123",
+            "Curious".to_string(),
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    let ElevationOutcome::PendingApproval(request) = outcome else {
+        panic!("elevation should wait for approval");
+    };
+    let rejected = firefighter
+        .reject_request(approver, request.id, Some("Not an incident".to_string()))
+        .await
+        .unwrap();
+    assert_eq!(rejected.status, "REJECTED");
+    assert!(!firefighter.get_status(bystander).await.unwrap().is_active);
+}
+
+#[sqlx::test]
+async fn test_firefighter_sessions_expire_automatically(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let firefighter = &services.firefighter_service;
+    let holder = register(&services, "ff_holder").await;
+    let veteran = register(&services, "ff_veteran").await;
+
+    let activate = |user_id| async move {
+        firefighter
+            .request_elevation(
+                user_id,
+                "password123",
+                "Incident".to_string(),
+                Some(10_000),
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .into_session()
+            .unwrap()
+    };
+    let session = activate(holder).await;
+    // Durations beyond the configured maximum are cut down
+    assert_eq!(
+        (session.expires_at - session.activated_at).num_minutes(),
+        480
+    );
+
+    // Nothing to do while the session has time left
+    assert_eq!(firefighter.warn_expiring_sessions().await.unwrap(), 0);
+    assert_eq!(firefighter.revoke_expired_sessions().await.unwrap(), 0);
+
+    // Close to the end: the holder is warned, once
+    sqlx::query(
+        "UPDATE firefighter_sessions SET expires_at = NOW() + INTERVAL '5 minutes' WHERE id = $1",
+    )
+    .bind(session.id)
+    .execute(&pool)
+    .await
+    .unwrap();
+    assert_eq!(firefighter.warn_expiring_sessions().await.unwrap(), 1);
+    assert_eq!(firefighter.warn_expiring_sessions().await.unwrap(), 0);
+    assert_eq!(notification_count(&pool, holder).await, 1);
+
+    // Past expiry: revoked and recorded
+    sqlx::query(
+        "UPDATE firefighter_sessions SET activated_at = NOW() - INTERVAL '1 hour', expires_at = NOW() - INTERVAL '1 minute' WHERE id = $1",
+    )
+    .bind(session.id)
+    .execute(&pool)
+    .await
+    .unwrap();
+    // A session granted before the maximum was lowered is revoked too
+    let long_session = activate(veteran).await;
+    sqlx::query(
+        "UPDATE firefighter_sessions SET activated_at = NOW() - INTERVAL '10 hours', expires_at = NOW() + INTERVAL '1 hour' WHERE id = $1",
+    )
+    .bind(long_session.id)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    assert_eq!(firefighter.revoke_expired_sessions().await.unwrap(), 2);
+    assert_eq!(firefighter.revoke_expired_sessions().await.unwrap(), 0);
+    assert!(!firefighter.get_status(holder).await.unwrap().is_active);
+    assert!(!firefighter.get_status(veteran).await.unwrap().is_active);
+
+    let reasons: Vec<(Uuid, Option<String>)> = sqlx::query_as(
+        "SELECT id, deactivation_reason FROM firefighter_sessions WHERE deactivated_at IS NOT NULL",
+    )
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert!(reasons.contains(&(session.id, Some("Expired".to_string()))));
+    assert!(reasons.contains(&(
+        long_session.id,
+        Some("Maximum duration reached".to_string())
+    )));
+    assert_eq!(notification_count(&pool, holder).await, 2);
+}
+
+#[sqlx::test]
+async fn test_firefighter_actions_are_recorded(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let firefighter = &services.firefighter_service;
+    let audit = &services.audit_service;
+    let holder = register(&services, "ff_actor").await;
+    let colleague = register(&services, "ff_colleague").await;
+
+    audit
+        .log(holder, "entity.updated", "entity", None, None, None, None)
+        .await
+        .unwrap();
+    let session = firefighter
+        .request_elevation(
+            holder,
+            "password123",
+            "Data repair".to_string(),
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap()
+        .into_session()
+        .unwrap();
+
+    // Only the holder's own events while the session is active are tagged
+    let tagged = audit
+        .log(holder, "entity.deleted", "entity", None, None, None, None)
+        .await
+        .unwrap();
+    assert_eq!(tagged.firefighter_session_id, Some(session.id));
+    audit
+        .log(
+            colleague,
+            "entity.updated",
+            "entity",
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    firefighter
+        .deactivate(holder, Some("Repaired".to_string()))
+        .await
+        .unwrap();
+    audit
+        .log(holder, "entity.updated", "entity", None, None, None, None)
+        .await
+        .unwrap();
+
+    let review = firefighter.session_actions(session.id).await.unwrap();
+    assert_eq!(review.session.id, session.id);
+    let actions: Vec<&str> = review.actions.iter().map(|a| a.action.as_str()).collect();
+    assert_eq!(actions, vec!["firefighter.activated", "entity.deleted"]);
+    assert_eq!(
+        review.session.deactivation_reason.as_deref(),
+        Some("Repaired")
+    );
+    assert!(review.actions.iter().all(|a| a.user_id == holder));
+
+    assert!(matches!(
+        firefighter.session_actions(Uuid::new_v4()).await,
+        Err(FirefighterError::NotFound)
+    ));
+}
+
+#[sqlx::test]
+async fn test_firefighter_review_report_and_sign_off(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let firefighter = &services.firefighter_service;
+    let holder = register(&services, "ff_review_holder").await;
+    let reviewer = register(&services, "ff_reviewer").await;
+
+    let session = firefighter
+        .request_elevation(
+            holder,
+            "password123",
+            "Unlock stuck account".to_string(),
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap()
+        .into_session()
+        .unwrap();
+    // The reviewer's user entity stands in for something the holder changed
+    services
+        .audit_service
+        .log(
+            holder,
+            "entity.updated",
+            "entity",
+            Some(reviewer),
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        firefighter
+            .sign_off_review(reviewer, session.id, "Looks fine")
+            .await,
+        Err(FirefighterError::Conflict(_))
+    ));
+    firefighter.deactivate(holder, None).await.unwrap();
+    assert!(matches!(
+        firefighter
+            .sign_off_review(holder, session.id, "All good")
+            .await,
+        Err(FirefighterError::Forbidden(_))
+    ));
+    assert!(matches!(
+        firefighter
+            .sign_off_review(reviewer, session.id, "  ")
+            .await,
+        Err(FirefighterError::InvalidInput(_))
+    ));
+
+    let report = firefighter.review_report(session.id).await.unwrap();
+    assert_eq!(report.holder_name, "ff_review_holder");
+    assert_eq!(report.elevated_role, "superadmin");
+    assert!(report.ended_at.is_some());
+    assert!(report.approval.is_none());
+    assert!(report.review.is_none());
+    assert!(report.actions.iter().any(|a| a.action == "entity.updated"));
+    assert_eq!(report.entities_touched.len(), 1);
+    assert_eq!(report.entities_touched[0].id, reviewer);
+    assert_eq!(report.entities_touched[0].actions, vec!["entity.updated"]);
+
+    let review = firefighter
+        .sign_off_review(reviewer, session.id, "Change matched the ticket")
+        .await
+        .unwrap();
+    assert_eq!(review.reviewer_id, reviewer);
+    assert!(matches!(
+        firefighter
+            .sign_off_review(reviewer, session.id, "Again")
+            .await,
+        Err(FirefighterError::Conflict(_))
+    ));
+
+    let report = firefighter.review_report(session.id).await.unwrap();
+    let pdf = template_repo_backend::features::firefighter::review::render_review_pdf(&report);
+    let pdf = String::from_utf8_lossy(&pdf);
+    assert!(pdf.starts_with("%PDF-1.4"));
+    assert!(pdf.contains("ff_review_holder"));
+    assert!(pdf.contains("Change matched the ticket"));
+    assert_eq!(
+        report.review.map(|r| r.notes).as_deref(),
+        Some("Change matched the ticket")
+    );
+}
+
+#[sqlx::test]
+async fn test_firefighter_scoped_access(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let firefighter = &services.firefighter_service;
+    let rebac = &services.rebac_service;
+    let holder = register(&services, "ff_scoped").await;
+
+    let class = |name: &str| CreateClassInput {
+        name: name.to_string(),
+        description: None,
+        parent_class_id: None,
+        is_abstract: Some(false),
+    };
+    let site_class = services
+        .ontology_service
+        .create_class(class("FfSite"), Some(holder))
+        .await
+        .unwrap();
+    let asset_class = services
+        .ontology_service
+        .create_class(class("FfAsset"), Some(holder))
+        .await
+        .unwrap();
+    let entity = |class_id: Uuid, name: &str, parent: Option<Uuid>| {
+        let ontology = services.ontology_service.clone();
+        let name = name.to_string();
+        async move {
+            ontology
+                .create_entity(
+                    CreateEntityInput {
+                        class_id,
+                        display_name: name,
+                        parent_entity_id: parent,
+                        attributes: None,
+                    },
+                    Some(holder),
+                    None,
+                )
+                .await
+                .unwrap()
+                .id
+        }
+    };
+    let site_a = entity(site_class.id, "Site A", None).await;
+    let asset_a = entity(asset_class.id, "Pump A", Some(site_a)).await;
+    let vault = entity(site_class.id, "Vault", Some(site_a)).await;
+    let asset_vault = entity(asset_class.id, "Vault Pump", Some(vault)).await;
+    let site_b = entity(site_class.id, "Site B", None).await;
+    let asset_b = entity(asset_class.id, "Pump B", Some(site_b)).await;
+
+    let allowed = |entity_id: Uuid, permission: &'static str| async move {
+        rebac
+            .check_permission(holder, entity_id, permission, None, None)
+            .await
+            .unwrap()
+            .has_permission
+    };
+
+    // A scope must name something that exists
+    for scope in [
+        FirefighterScope::default(),
+        FirefighterScope {
+            entity_ids: vec![Uuid::new_v4()],
+            ..Default::default()
+        },
+    ] {
+        let result = firefighter
+            .request_scoped_elevation(
+                holder,
+                "password123",
+                "Outage".to_string(),
+                None,
+                Some(scope),
+                None,
+                None,
+            )
+            .await;
+        assert!(matches!(result, Err(FirefighterError::InvalidInput(_))));
+    }
+
+    // Subtree scope with an excluded branch
+    let session = firefighter
+        .request_scoped_elevation(
+            holder,
+            "password123",
+            "Pump failure at site A".to_string(),
+            None,
+            Some(FirefighterScope {
+                entity_ids: vec![site_a],
+                exclude_entity_ids: vec![vault],
+                ..Default::default()
+            }),
+            None,
+            None,
+        )
+        .await
+        .unwrap()
+        .into_session()
+        .unwrap();
+    assert_eq!(session.scope.as_deref().unwrap().entity_ids, vec![site_a]);
+    assert!(!rebac.has_firefighter_active(holder).await.unwrap());
+
+    assert!(allowed(site_a, "update").await);
+    assert!(allowed(asset_a, "update").await);
+    assert!(!allowed(vault, "update").await);
+    assert!(!allowed(asset_vault, "update").await);
+    assert!(!allowed(site_b, "update").await);
+    assert!(!allowed(asset_b, "update").await);
+
+    firefighter
+        .deactivate(holder, Some("Pump restarted".to_string()))
+        .await
+        .unwrap();
+    assert!(!allowed(asset_a, "delete").await);
+    let leftover_roles: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM entities e JOIN classes c ON c.id = e.class_id
+         WHERE c.name = 'Role' AND e.attributes->>'firefighter_session_id' = $1::text
+           AND e.deleted_at IS NULL",
+    )
+    .bind(session.id)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(leftover_roles, 0);
+
+    // Class-only scope reaches those classes anywhere, and nothing else
+    firefighter
+        .request_scoped_elevation(
+            holder,
+            "password123",
+            "Pump sweep".to_string(),
+            None,
+            Some(FirefighterScope {
+                class_ids: vec![asset_class.id],
+                ..Default::default()
+            }),
+            None,
+            None,
+        )
+        .await
+        .unwrap()
+        .into_session()
+        .unwrap();
+    assert!(allowed(asset_b, "view").await);
+    assert!(allowed(asset_vault, "view").await);
+    assert!(!allowed(site_b, "view").await);
+}
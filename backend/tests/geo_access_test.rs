@@ -0,0 +1,213 @@
+use sqlx::PgPool;
+use template_repo_backend::features::auth::models::{LoginUser, RegisterUser};
+use template_repo_backend::features::auth::service::AuthError;
+use template_repo_backend::features::geo_access::{
+    models::{AccessKind, GeoDecision, UpsertGeoPolicyRequest},
+    GeoAccessService,
+};
+use uuid::Uuid;
+
+mod common;
+
+fn policy(mode: &str, countries: &[&str]) -> UpsertGeoPolicyRequest {
+    UpsertGeoPolicyRequest {
+        mode: mode.to_string(),
+        countries: countries.iter().map(|c| c.to_string()).collect(),
+        restrict_login: None,
+        restrict_api: None,
+        block_unknown: None,
+        description: None,
+    }
+}
+
+/// Register a user and move them into a fresh tenant
+async fn tenant_user(
+    services: &common::TestServices,
+    pool: &PgPool,
+    username: &str,
+) -> (Uuid, Uuid) {
+    let user_id = services
+        .auth_service
+        .register(RegisterUser {
+            username: username.to_string(),
+            email: format!("{}@example.com", username),
+            password: "password123".to_string(),
+        })
+        .await
+        .unwrap()
+        .user_id;
+    let tenant_id = Uuid::new_v4();
+    sqlx::query("UPDATE entities SET tenant_id = $1 WHERE id = $2")
+        .bind(tenant_id)
+        .bind(user_id)
+        .execute(pool)
+        .await
+        .unwrap();
+    (user_id, tenant_id)
+}
+
+async fn security_event_count(pool: &PgPool, event_type: &str, user_id: Uuid) -> i64 {
+    sqlx::query_scalar(
+        "SELECT COUNT(*) FROM security_events WHERE event_type = $1 AND user_id = $2",
+    )
+    .bind(event_type)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
+
+#[sqlx::test]
+async fn test_geo_policy_enforcement_and_firefighter_override(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let geo = GeoAccessService::new(
+        pool.clone(),
+        "",
+        vec![],
+        services.firefighter_service.clone(),
+    )
+    .unwrap();
+
+    let (user_id, tenant_id) = tenant_user(&services, &pool, "geo_user").await;
+
+    // Invalid policies are rejected
+    assert!(geo
+        .upsert_policy(tenant_id, policy("allow", &["NOR"]), None)
+        .await
+        .is_err());
+    assert!(geo
+        .upsert_policy(tenant_id, policy("allow", &[]), None)
+        .await
+        .is_err());
+
+    let saved = geo
+        .upsert_policy(tenant_id, policy("allow", &["no", "SE", "NO"]), None)
+        .await
+        .unwrap();
+    assert_eq!(saved.countries, vec!["NO".to_string(), "SE".to_string()]);
+
+    let ip = Some("198.51.100.1".parse().unwrap());
+    assert_eq!(
+        geo.check_country(user_id, ip, Some("NO"), AccessKind::Api)
+            .await
+            .unwrap(),
+        GeoDecision::Allowed
+    );
+    assert_eq!(
+        geo.check_country(user_id, ip, Some("US"), AccessKind::Api)
+            .await
+            .unwrap(),
+        GeoDecision::Blocked
+    );
+    // Unknown country passes unless block_unknown is set
+    assert_eq!(
+        geo.check_country(user_id, ip, None, AccessKind::Login)
+            .await
+            .unwrap(),
+        GeoDecision::Allowed
+    );
+    assert_eq!(security_event_count(&pool, "geo_blocked", user_id).await, 1);
+
+    // Users in other tenants are unaffected
+    let (other_id, _) = tenant_user(&services, &pool, "geo_other").await;
+    assert_eq!(
+        geo.check_country(other_id, ip, Some("US"), AccessKind::Api)
+            .await
+            .unwrap(),
+        GeoDecision::Allowed
+    );
+
+    // Firefighter access overrides the policy, and the override is recorded
+    services
+        .firefighter_service
+        .request_elevation(
+            user_id,
+            "password123",
+            "Incident response abroad".to_string(),
+            Some(30),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        geo.check_country(user_id, ip, Some("US"), AccessKind::Api)
+            .await
+            .unwrap(),
+        GeoDecision::Overridden
+    );
+    assert_eq!(
+        security_event_count(&pool, "geo_override", user_id).await,
+        1
+    );
+
+    geo.delete_policy(tenant_id).await.unwrap();
+    assert!(!geo.has_policies());
+}
+
+#[sqlx::test]
+async fn test_geo_policy_blocks_login(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let geo = GeoAccessService::new(
+        pool.clone(),
+        "",
+        vec![],
+        services.firefighter_service.clone(),
+    )
+    .unwrap();
+    let auth_service = services.auth_service.clone().with_geo_access(geo.clone());
+
+    let (_, tenant_id) = tenant_user(&services, &pool, "geo_login").await;
+
+    let login = || LoginUser {
+        identifier: "geo_login".to_string(),
+        password: "password123".to_string(),
+        remember_me: None,
+    };
+
+    // Without a GeoIP database the country is unknown, which this policy blocks
+    geo.upsert_policy(
+        tenant_id,
+        UpsertGeoPolicyRequest {
+            block_unknown: Some(true),
+            ..policy("allow", &["NO"])
+        },
+        None,
+    )
+    .await
+    .unwrap();
+    let result = auth_service
+        .login(login(), Some("198.51.100.1".to_string()), None)
+        .await;
+    assert!(matches!(result, Err(AuthError::GeoRestricted)));
+
+    // Wrong passwords still fail as invalid credentials
+    let result = auth_service
+        .login(
+            LoginUser {
+                password: "wrong_password".to_string(),
+                ..login()
+            },
+            Some("198.51.100.1".to_string()),
+            None,
+        )
+        .await;
+    assert!(matches!(result, Err(AuthError::InvalidCredentials)));
+
+    // The policy can be limited to API calls
+    geo.upsert_policy(
+        tenant_id,
+        UpsertGeoPolicyRequest {
+            block_unknown: Some(true),
+            restrict_login: Some(false),
+            ..policy("allow", &["NO"])
+        },
+        None,
+    )
+    .await
+    .unwrap();
+    assert!(auth_service
+        .login(login(), Some("198.51.100.1".to_string()), None)
+        .await
+        .is_ok());
+}
@@ -0,0 +1,217 @@
+use axum::async_trait;
+use sqlx::PgPool;
+use std::sync::{Arc, Mutex};
+use template_repo_backend::config::GraphSyncConfig;
+use template_repo_backend::features::graph_sync::models::GraphBatch;
+use template_repo_backend::features::graph_sync::neo4j::GraphSink;
+use template_repo_backend::features::graph_sync::{GraphSync, GraphSyncError};
+use template_repo_backend::features::ontology::models::{
+    CreateClassInput, CreateEntityInput, CreatePropertyInput, CreateRelationshipInput,
+};
+use template_repo_backend::utils::shutdown::Shutdown;
+use uuid::Uuid;
+
+mod common;
+
+/// Keeps every batch instead of writing to a graph database
+#[derive(Default)]
+struct RecordingSink {
+    batches: Mutex<Vec<GraphBatch>>,
+}
+
+impl RecordingSink {
+    fn take(&self) -> GraphBatch {
+        let mut merged = GraphBatch::default();
+        for batch in self.batches.lock().unwrap().drain(..) {
+            merged.delete_edges.extend(batch.delete_edges);
+            merged.delete_nodes.extend(batch.delete_nodes);
+            merged.upsert_nodes.extend(batch.upsert_nodes);
+            merged.upsert_edges.extend(batch.upsert_edges);
+        }
+        merged
+    }
+}
+
+#[async_trait]
+impl GraphSink for RecordingSink {
+    async fn prepare(&self) -> Result<(), GraphSyncError> {
+        Ok(())
+    }
+
+    async fn apply(&self, batch: &GraphBatch) -> Result<(), GraphSyncError> {
+        self.batches.lock().unwrap().push(batch.clone());
+        Ok(())
+    }
+}
+
+/// Two depots, the second depending on the first; `secret` is sensitive
+async fn depots(pool: &PgPool) -> (common::TestServices, Vec<Uuid>, Uuid) {
+    let services = common::setup_services(pool.clone()).await;
+    common::reopen_current_version(pool).await;
+    let ontology = &services.ontology_service;
+    let class = ontology
+        .create_class(
+            CreateClassInput {
+                name: "GraphDepot".to_string(),
+                description: None,
+                parent_class_id: None,
+                is_abstract: None,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    ontology
+        .create_property(CreatePropertyInput {
+            name: "secret".to_string(),
+            description: None,
+            class_id: class.id,
+            data_type: "string".to_string(),
+            reference_class_id: None,
+            is_required: None,
+            is_unique: None,
+            is_indexed: None,
+            is_sensitive: Some(true),
+            default_value: None,
+            validation_rules: None,
+        })
+        .await
+        .unwrap();
+
+    let mut ids = Vec::new();
+    for name in ["North", "South"] {
+        let entity = ontology
+            .create_entity(
+                CreateEntityInput {
+                    class_id: class.id,
+                    display_name: name.to_string(),
+                    parent_entity_id: None,
+                    attributes: Some(serde_json::json!({ "code": name, "secret": "x" })),
+                },
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        ids.push(entity.id);
+    }
+    let relationship = ontology
+        .create_relationship(
+            CreateRelationshipInput {
+                source_entity_id: ids[1],
+                target_entity_id: ids[0],
+                relationship_type: "depends_on".to_string(),
+                metadata: None,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    (services, ids, relationship.id)
+}
+
+#[sqlx::test]
+async fn change_events_are_mirrored(pool: PgPool) {
+    let (services, ids, relationship_id) = depots(&pool).await;
+    let sink = Arc::new(RecordingSink::default());
+    let sync = GraphSync::new(pool.clone(), sink.clone(), GraphSyncConfig::default());
+
+    sync.run_once(false, &Shutdown::new()).await;
+    let batch = sink.take();
+    for id in &ids {
+        let node = batch.upsert_nodes.iter().find(|n| n.id == *id).unwrap();
+        assert_eq!(node.class_name, "GraphDepot");
+        assert!(node.attributes.get("code").is_some());
+        assert!(node.attributes.get("secret").is_none());
+    }
+    let edge = batch
+        .upsert_edges
+        .iter()
+        .find(|e| e.id == relationship_id)
+        .unwrap();
+    assert_eq!(edge.relationship_type, "depends_on");
+    assert_eq!(
+        (edge.source_entity_id, edge.target_entity_id),
+        (ids[1], ids[0])
+    );
+
+    // Deleting the source removes its node, and the relationship with it
+    services
+        .ontology_service
+        .delete_entity(ids[1], None)
+        .await
+        .unwrap();
+    sync.run_once(false, &Shutdown::new()).await;
+    let batch = sink.take();
+    assert_eq!(batch.delete_nodes, vec![ids[1]]);
+    assert!(batch.upsert_nodes.is_empty());
+
+    let status = sync.status().await.unwrap();
+    assert_eq!(status.pending_events, 0);
+    assert!(status.state.events_processed > 0);
+}
+
+#[sqlx::test]
+async fn deleted_relationships_are_found_by_source(pool: PgPool) {
+    let (services, ids, relationship_id) = depots(&pool).await;
+    let sink = Arc::new(RecordingSink::default());
+    let sync = GraphSync::new(pool.clone(), sink.clone(), GraphSyncConfig::default());
+    sync.run_once(false, &Shutdown::new()).await;
+    sink.take();
+
+    services
+        .ontology_service
+        .delete_relationship(relationship_id)
+        .await
+        .unwrap();
+    sync.run_once(false, &Shutdown::new()).await;
+    let batch = sink.take();
+    assert_eq!(batch.delete_edges.len(), 1);
+    assert_eq!(batch.delete_edges[0].id, relationship_id);
+    assert_eq!(batch.delete_edges[0].source_entity_id, ids[1]);
+    assert!(batch.upsert_edges.is_empty());
+}
+
+#[sqlx::test]
+async fn backfill_rewrites_everything(pool: PgPool) {
+    let (_services, ids, relationship_id) = depots(&pool).await;
+    let sink = Arc::new(RecordingSink::default());
+    let sync = GraphSync::new(
+        pool.clone(),
+        sink.clone(),
+        GraphSyncConfig {
+            batch_size: 1,
+            ..Default::default()
+        },
+    );
+    sync.run_once(false, &Shutdown::new()).await;
+    sink.take();
+
+    let status = sync.request_backfill().await.unwrap();
+    assert!(status.backfilling);
+    sync.run_once(false, &Shutdown::new()).await;
+
+    let batch = sink.take();
+    for id in &ids {
+        assert!(batch.upsert_nodes.iter().any(|n| n.id == *id));
+    }
+    assert!(batch.upsert_edges.iter().any(|e| e.id == relationship_id));
+    let status = sync.status().await.unwrap();
+    assert!(!status.backfilling);
+    assert!(status.state.backfill_done >= 3);
+}
+
+#[sqlx::test]
+async fn stopped_connector_does_not_hold_back_pruning(pool: PgPool) {
+    depots(&pool).await;
+    GraphSync::from_config(pool.clone(), &GraphSyncConfig::default())
+        .await
+        .unwrap();
+    let running: Vec<String> =
+        sqlx::query_scalar("SELECT worker FROM ai_index_state WHERE available ORDER BY worker")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    assert!(!running.contains(&"graph_sync".to_string()));
+}
@@ -0,0 +1,189 @@
+use axum::{
+    body::Body, extract::ConnectInfo, http::Request, http::StatusCode, routing::post, Router,
+};
+use sqlx::PgPool;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use template_repo_backend::middleware::idempotency::{
+    idempotency_middleware, ClaimResult, IdempotencyStore, REPLAYED_HEADER,
+};
+use tower::ServiceExt;
+
+fn counting_app(pool: PgPool, calls: Arc<AtomicUsize>) -> Router {
+    Router::new()
+        .route(
+            "/api/items",
+            post({
+                let calls = calls.clone();
+                move |body: String| {
+                    let calls = calls.clone();
+                    async move {
+                        let n = calls.fetch_add(1, Ordering::SeqCst) + 1;
+                        (StatusCode::CREATED, format!("created #{} from {}", n, body))
+                    }
+                }
+            }),
+        )
+        .route(
+            "/api/exports",
+            post(move || {
+                let calls = calls.clone();
+                async move {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    vec![b'x'; 11 * 1024 * 1024]
+                }
+            }),
+        )
+        .layer(axum::middleware::from_fn_with_state(
+            IdempotencyStore::new(pool),
+            idempotency_middleware,
+        ))
+}
+
+fn post_with_key(key: Option<&str>, body: &str) -> Request<Body> {
+    post_from("10.0.0.1", "/api/items", key, body)
+}
+
+fn post_from(peer: &str, uri: &str, key: Option<&str>, body: &str) -> Request<Body> {
+    let mut builder = Request::builder().method("POST").uri(uri);
+    if let Some(key) = key {
+        builder = builder.header("idempotency-key", key);
+    }
+    let mut request = builder.body(Body::from(body.to_string())).unwrap();
+    let peer: SocketAddr = format!("{}:40000", peer).parse().unwrap();
+    request.extensions_mut().insert(ConnectInfo(peer));
+    request
+}
+
+async fn body_string(response: axum::response::Response) -> String {
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    String::from_utf8_lossy(&bytes).to_string()
+}
+
+#[sqlx::test]
+async fn test_idempotency_key_replays_first_response(pool: PgPool) {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let app = counting_app(pool, calls.clone());
+
+    let first = app
+        .clone()
+        .oneshot(post_with_key(Some("abc-123"), "payload"))
+        .await
+        .unwrap();
+    assert_eq!(first.status(), StatusCode::CREATED);
+    assert!(first.headers().get(REPLAYED_HEADER).is_none());
+    assert_eq!(body_string(first).await, "created #1 from payload");
+
+    // A retry with the same key returns the stored response without re-running the handler
+    let retry = app
+        .clone()
+        .oneshot(post_with_key(Some("abc-123"), "payload"))
+        .await
+        .unwrap();
+    assert_eq!(retry.status(), StatusCode::CREATED);
+    assert_eq!(retry.headers().get(REPLAYED_HEADER).unwrap(), "true");
+    assert_eq!(body_string(retry).await, "created #1 from payload");
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    // Reusing the key for a different payload is rejected
+    let mismatch = app
+        .clone()
+        .oneshot(post_with_key(Some("abc-123"), "other"))
+        .await
+        .unwrap();
+    assert_eq!(mismatch.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    // Requests without a key are not deduplicated
+    app.clone()
+        .oneshot(post_with_key(None, "payload"))
+        .await
+        .unwrap();
+    app.clone()
+        .oneshot(post_with_key(None, "payload"))
+        .await
+        .unwrap();
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+}
+
+#[sqlx::test]
+async fn test_idempotency_keys_are_scoped_per_principal(pool: PgPool) {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let app = counting_app(pool, calls.clone());
+
+    app.clone()
+        .oneshot(post_with_key(Some("shared"), "payload"))
+        .await
+        .unwrap();
+
+    let other = post_from("10.0.0.2", "/api/items", Some("shared"), "payload");
+    let response = app.clone().oneshot(other).await.unwrap();
+
+    assert!(response.headers().get(REPLAYED_HEADER).is_none());
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+    // Forwarding headers from an untrusted peer don't make it someone else
+    let mut spoofed = post_from("10.0.0.3", "/api/items", Some("shared"), "payload");
+    spoofed
+        .headers_mut()
+        .insert("x-forwarded-for", "10.0.0.1".parse().unwrap());
+    let response = app.clone().oneshot(spoofed).await.unwrap();
+
+    assert!(response.headers().get(REPLAYED_HEADER).is_none());
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+}
+
+#[sqlx::test]
+async fn test_responses_too_large_to_store_are_not_replayed(pool: PgPool) {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let app = counting_app(pool, calls.clone());
+
+    let first = app
+        .clone()
+        .oneshot(post_from("10.0.0.1", "/api/exports", Some("big"), ""))
+        .await
+        .unwrap();
+    assert_eq!(first.status(), StatusCode::OK);
+    assert_eq!(body_string(first).await.len(), 11 * 1024 * 1024);
+
+    // The retry is refused rather than running the handler again
+    let retry = app
+        .clone()
+        .oneshot(post_from("10.0.0.1", "/api/exports", Some("big"), ""))
+        .await
+        .unwrap();
+    assert_eq!(retry.status(), StatusCode::CONFLICT);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[sqlx::test]
+async fn test_idempotency_in_flight_and_expiry(pool: PgPool) {
+    let store = IdempotencyStore::new(pool.clone());
+
+    // An in-flight claim blocks concurrent retries
+    let first = store
+        .claim("user:1", "k1", "POST", "/api/items", "hash")
+        .await
+        .unwrap();
+    assert!(matches!(first, ClaimResult::Claimed));
+    let second = store
+        .claim("user:1", "k1", "POST", "/api/items", "hash")
+        .await
+        .unwrap();
+    assert!(matches!(second, ClaimResult::InFlight));
+
+    // Expired entries can be claimed again and are purged
+    sqlx::query("UPDATE idempotency_keys SET expires_at = NOW() - INTERVAL '1 minute'")
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert_eq!(store.purge_expired().await.unwrap(), 1);
+    let again = store
+        .claim("user:1", "k1", "POST", "/api/items", "hash")
+        .await
+        .unwrap();
+    assert!(matches!(again, ClaimResult::Claimed));
+}
@@ -0,0 +1,289 @@
+use axum::{extract::Query, routing::get, Json, Router};
+use serde_json::json;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use template_repo_backend::config::{IngestionConfig, IngestionSourceConfig, OutboundHttpConfig};
+use template_repo_backend::features::ingestion::models::{ApplyOutcome, EntityUpsert};
+use template_repo_backend::features::ingestion::sources::build_source;
+use template_repo_backend::features::ingestion::{IngestionError, IngestionService};
+use template_repo_backend::features::ontology::models::{CreateClassInput, CreatePropertyInput};
+use template_repo_backend::utils::http_client::OutboundClient;
+use template_repo_backend::utils::shutdown::Shutdown;
+
+mod common;
+
+/// An `IngestedServer` class whose `cores` is a required number
+async fn setup(pool: &PgPool) -> (common::TestServices, IngestionService) {
+    let services = common::setup_services(pool.clone()).await;
+    common::reopen_current_version(pool).await;
+    let class = services
+        .ontology_service
+        .create_class(
+            CreateClassInput {
+                name: "IngestedServer".to_string(),
+                description: None,
+                parent_class_id: None,
+                is_abstract: None,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    services
+        .ontology_service
+        .create_property(CreatePropertyInput {
+            name: "cores".to_string(),
+            description: None,
+            class_id: class.id,
+            data_type: "number".to_string(),
+            reference_class_id: None,
+            is_required: Some(true),
+            is_unique: None,
+            is_indexed: None,
+            is_sensitive: None,
+            default_value: None,
+            validation_rules: None,
+        })
+        .await
+        .unwrap();
+    let ingestion = IngestionService::new(
+        pool.clone(),
+        services.ontology_service.clone(),
+        IngestionConfig::default(),
+    );
+    (services, ingestion)
+}
+
+fn upsert(event_id: &str, external_id: &str, cores: i64, sequence: i64) -> EntityUpsert {
+    EntityUpsert {
+        event_id: event_id.to_string(),
+        external_id: external_id.to_string(),
+        class: "IngestedServer".to_string(),
+        display_name: Some(format!("server {}", external_id)),
+        parent_external_id: None,
+        attributes: Some(json!({ "cores": cores })),
+        deleted: false,
+        sequence: Some(sequence),
+    }
+}
+
+#[sqlx::test]
+async fn upserts_are_applied_once_and_in_order(pool: PgPool) {
+    let (services, ingestion) = setup(&pool).await;
+
+    let created = ingestion
+        .apply("cmdb", None, &upsert("e1", "srv-1", 4, 1))
+        .await
+        .unwrap();
+    let ApplyOutcome::Created(entity_id) = created else {
+        panic!("expected a new entity, got {:?}", created);
+    };
+
+    // Redelivery changes nothing
+    let again = ingestion
+        .apply("cmdb", None, &upsert("e1", "srv-1", 4, 1))
+        .await
+        .unwrap();
+    assert_eq!(again, ApplyOutcome::Duplicate);
+
+    let updated = ingestion
+        .apply("cmdb", None, &upsert("e3", "srv-1", 16, 3))
+        .await
+        .unwrap();
+    assert_eq!(updated, ApplyOutcome::Updated(entity_id));
+
+    // An older version arriving late is skipped
+    let stale = ingestion
+        .apply("cmdb", None, &upsert("e2", "srv-1", 8, 2))
+        .await
+        .unwrap();
+    assert_eq!(stale, ApplyOutcome::Stale);
+
+    let entity = services
+        .ontology_service
+        .get_entity(entity_id)
+        .await
+        .unwrap();
+    assert_eq!(entity.attributes["cores"], 16);
+    assert_eq!(entity.display_name, "server srv-1");
+
+    // The same external id from another source is another record
+    let other = ingestion
+        .apply("hr", None, &upsert("e1", "srv-1", 2, 1))
+        .await
+        .unwrap();
+    assert!(matches!(other, ApplyOutcome::Created(id) if id != entity_id));
+}
+
+#[sqlx::test]
+async fn parents_and_deletes_follow_external_ids(pool: PgPool) {
+    let (services, ingestion) = setup(&pool).await;
+
+    let mut child = upsert("c1", "srv-child", 2, 1);
+    child.parent_external_id = Some("srv-parent".to_string());
+    let early = ingestion.apply("cmdb", None, &child).await;
+    assert!(matches!(early, Err(IngestionError::Rejected(_))));
+
+    let ApplyOutcome::Created(parent_id) = ingestion
+        .apply("cmdb", None, &upsert("p1", "srv-parent", 32, 1))
+        .await
+        .unwrap()
+    else {
+        panic!("expected the parent to be created");
+    };
+    let ApplyOutcome::Created(child_id) = ingestion.apply("cmdb", None, &child).await.unwrap()
+    else {
+        panic!("expected the child to be created");
+    };
+    let entity = services
+        .ontology_service
+        .get_entity(child_id)
+        .await
+        .unwrap();
+    assert_eq!(entity.parent_entity_id, Some(parent_id));
+
+    let mut delete = upsert("c2", "srv-child", 2, 5);
+    delete.deleted = true;
+    assert_eq!(
+        ingestion.apply("cmdb", None, &delete).await.unwrap(),
+        ApplyOutcome::Deleted(child_id)
+    );
+    assert!(services
+        .ontology_service
+        .get_entity(child_id)
+        .await
+        .is_err());
+
+    // An upsert sent before the delete doesn't bring the entity back
+    let late = ingestion
+        .apply("cmdb", None, &upsert("c3", "srv-child", 2, 4))
+        .await
+        .unwrap();
+    assert_eq!(late, ApplyOutcome::Stale);
+
+    let mut unknown = upsert("x1", "never-seen", 1, 1);
+    unknown.deleted = true;
+    assert_eq!(
+        ingestion.apply("cmdb", None, &unknown).await.unwrap(),
+        ApplyOutcome::Ignored
+    );
+}
+
+#[sqlx::test]
+async fn invalid_upserts_are_rejected_by_ontology_validation(pool: PgPool) {
+    let (_services, ingestion) = setup(&pool).await;
+
+    let mut missing = upsert("v1", "srv-1", 0, 1);
+    missing.attributes = Some(json!({}));
+    assert!(matches!(
+        ingestion.apply("cmdb", None, &missing).await,
+        Err(IngestionError::Rejected(msg)) if msg.contains("cores")
+    ));
+
+    let mut unknown_class = upsert("v2", "srv-2", 1, 1);
+    unknown_class.class = "NoSuchClass".to_string();
+    assert!(matches!(
+        ingestion.apply("cmdb", None, &unknown_class).await,
+        Err(IngestionError::Rejected(_))
+    ));
+
+    // Nothing was linked, so a corrected event creates the entity
+    let fixed = ingestion
+        .apply("cmdb", None, &upsert("v3", "srv-1", 4, 2))
+        .await
+        .unwrap();
+    assert!(matches!(fixed, ApplyOutcome::Created(_)));
+}
+
+#[sqlx::test]
+async fn polled_http_sources_resume_from_their_cursor(pool: PgPool) {
+    let (_services, ingestion) = setup(&pool).await;
+
+    let cursors = Arc::new(Mutex::new(Vec::new()));
+    let seen = cursors.clone();
+    let app = Router::new().route(
+        "/feed",
+        get(move |Query(params): Query<HashMap<String, String>>| {
+            let seen = seen.clone();
+            async move {
+                let cursor = params.get("cursor").cloned();
+                seen.lock().unwrap().push(cursor.clone());
+                let events = match cursor.as_deref() {
+                    None => vec![
+                        serde_json::to_value(upsert("h1", "srv-1", 4, 1)).unwrap(),
+                        json!({ "event_id": "h2", "external_id": "srv-2" }),
+                        serde_json::to_value(upsert("h3", "srv-3", 0, 1))
+                            .map(|mut v| {
+                                v["attributes"] = json!({ "cores": "many" });
+                                v
+                            })
+                            .unwrap(),
+                    ],
+                    _ => Vec::new(),
+                };
+                Json(json!({ "events": events, "next_cursor": "page-2" }))
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let config = IngestionSourceConfig {
+        name: "feed".to_string(),
+        kind: "http".to_string(),
+        url: format!("http://{}/feed", addr),
+        stream: String::new(),
+        subject: String::new(),
+        durable_name: String::new(),
+        token_env: String::new(),
+        tenant_id: None,
+        poll_interval_secs: 1,
+        batch_size: 10,
+    };
+    let http = OutboundClient::from_config(&OutboundHttpConfig {
+        max_retries: 0,
+        ..Default::default()
+    });
+    let mut source = build_source(&config, &http).unwrap();
+    let shutdown = Shutdown::new();
+
+    let handled = ingestion
+        .consume_once(&config, source.as_mut(), &shutdown)
+        .await
+        .unwrap();
+    assert_eq!(handled, 3);
+    let handled = ingestion
+        .consume_once(&config, source.as_mut(), &shutdown)
+        .await
+        .unwrap();
+    assert_eq!(handled, 0);
+    assert_eq!(
+        *cursors.lock().unwrap(),
+        vec![None, Some("page-2".to_string())]
+    );
+
+    let statuses: Vec<(String, String)> = sqlx::query_as(
+        "SELECT event_id, status FROM ingestion_events WHERE source = 'feed' ORDER BY event_id",
+    )
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(
+        statuses,
+        vec![
+            ("h1".to_string(), "created".to_string()),
+            ("h2".to_string(), "failed".to_string()),
+            ("h3".to_string(), "failed".to_string()),
+        ]
+    );
+
+    let sources = ingestion.sources().await.unwrap();
+    assert_eq!(sources.len(), 1);
+    assert_eq!(sources[0].events_received, 3);
+    assert_eq!(sources[0].events_failed, 2);
+    assert_eq!(sources[0].cursor.as_deref(), Some("page-2"));
+}
@@ -0,0 +1,215 @@
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+    routing::get,
+    Router,
+};
+use sqlx::PgPool;
+use std::net::SocketAddr;
+use template_repo_backend::features::ip_access::{
+    middleware::ip_access_middleware,
+    models::{AccessDecision, CreateIpRuleRequest, RequestOrigin},
+    IpAccessService,
+};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+mod common;
+
+fn rule(cidr: &str, action: &str, scope: &str) -> CreateIpRuleRequest {
+    CreateIpRuleRequest {
+        cidr: cidr.to_string(),
+        action: action.to_string(),
+        scope: scope.to_string(),
+        tenant_id: None,
+        api_key_id: None,
+        route_prefix: None,
+        description: None,
+    }
+}
+
+#[sqlx::test]
+async fn test_ip_rule_evaluation(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let service = IpAccessService::new(pool, vec![]);
+    let ip = |s: &str| Some(s.parse().unwrap());
+    let anonymous = RequestOrigin::default();
+
+    // Invalid rules are rejected
+    assert!(service
+        .create_rule(rule("10.0.0.0/40", "allow", "global"), None)
+        .await
+        .is_err());
+    assert!(service
+        .create_rule(rule("10.0.0.0/8", "maybe", "global"), None)
+        .await
+        .is_err());
+    assert!(service
+        .create_rule(rule("10.0.0.0/8", "allow", "tenant"), None)
+        .await
+        .is_err());
+
+    // No rules: everything is allowed
+    assert_eq!(
+        service.evaluate(ip("198.51.100.1"), "/api/system", &anonymous),
+        AccessDecision::Allowed
+    );
+
+    // Admin routes restricted to the corporate range
+    let corporate = service
+        .create_rule(
+            CreateIpRuleRequest {
+                route_prefix: Some("/api/system".to_string()),
+                ..rule("10.1.2.3/16", "allow", "global")
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(corporate.cidr, "10.1.0.0/16");
+
+    assert_eq!(
+        service.evaluate(ip("10.1.200.7"), "/api/system/logs", &anonymous),
+        AccessDecision::Allowed
+    );
+    assert_eq!(
+        service.evaluate(ip("198.51.100.1"), "/api/system/logs", &anonymous),
+        AccessDecision::NotAllowed
+    );
+    assert_eq!(
+        service.evaluate(None, "/api/system/logs", &anonymous),
+        AccessDecision::NotAllowed
+    );
+    assert_eq!(
+        service.evaluate(ip("198.51.100.1"), "/api/ontology/classes", &anonymous),
+        AccessDecision::Allowed
+    );
+
+    // A deny wins even inside an allowed range
+    let denied = service
+        .create_rule(rule("10.1.9.9", "deny", "global"), None)
+        .await
+        .unwrap();
+    assert_eq!(
+        service.evaluate(ip("10.1.9.9"), "/api/system/logs", &anonymous),
+        AccessDecision::Denied(denied.id)
+    );
+
+    // API key allowlist only applies to requests using that key
+    let key = services
+        .api_management_service
+        .create_key("ip".to_string(), vec![], None, None)
+        .await
+        .unwrap();
+    service
+        .create_rule(
+            CreateIpRuleRequest {
+                api_key_id: Some(key.id),
+                ..rule("203.0.113.0/24", "allow", "api_key")
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    let with_key = RequestOrigin {
+        api_key: Some(key.secret.clone()),
+        tenant_id: None,
+    };
+    assert_eq!(
+        service.evaluate(ip("203.0.113.5"), "/api/ontology", &with_key),
+        AccessDecision::Allowed
+    );
+    assert_eq!(
+        service.evaluate(ip("198.51.100.1"), "/api/ontology", &with_key),
+        AccessDecision::NotAllowed
+    );
+    assert_eq!(
+        service.evaluate(ip("198.51.100.1"), "/api/ontology", &anonymous),
+        AccessDecision::Allowed
+    );
+
+    // Tenant allowlist
+    let tenant_id = Uuid::new_v4();
+    service
+        .create_rule(
+            CreateIpRuleRequest {
+                tenant_id: Some(tenant_id),
+                ..rule("192.0.2.0/24", "allow", "tenant")
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    let tenant = RequestOrigin {
+        api_key: None,
+        tenant_id: Some(tenant_id),
+    };
+    assert!(service.has_tenant_rules());
+    assert_eq!(
+        service.evaluate(ip("192.0.2.10"), "/api/projects", &tenant),
+        AccessDecision::Allowed
+    );
+    assert_eq!(
+        service.evaluate(ip("198.51.100.1"), "/api/projects", &tenant),
+        AccessDecision::NotAllowed
+    );
+
+    // Deleting a rule takes effect immediately
+    service.delete_rule(denied.id).await.unwrap();
+    assert_eq!(
+        service.evaluate(ip("10.1.9.9"), "/api/system/logs", &anonymous),
+        AccessDecision::Allowed
+    );
+    assert_eq!(service.list_rules().await.unwrap().len(), 3);
+}
+
+#[sqlx::test]
+async fn test_ip_middleware_blocks_and_records_event(pool: PgPool) {
+    let service = IpAccessService::new(pool.clone(), vec![]);
+    service
+        .create_rule(rule("198.51.100.0/24", "deny", "global"), None)
+        .await
+        .unwrap();
+
+    let app = Router::new()
+        .route("/api/ping", get(|| async { "pong" }))
+        .layer(axum::middleware::from_fn_with_state(
+            service,
+            ip_access_middleware,
+        ));
+
+    let request = |peer: [u8; 4]| {
+        let mut req = Request::builder()
+            .uri("/api/ping")
+            // Not from a trusted proxy, so this header must be ignored
+            .header("x-forwarded-for", "10.0.0.1")
+            .body(Body::empty())
+            .unwrap();
+        req.extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from((peer, 4000))));
+        req
+    };
+
+    let allowed = app.clone().oneshot(request([10, 0, 0, 1])).await.unwrap();
+    assert_eq!(allowed.status(), StatusCode::OK);
+
+    let blocked = app.oneshot(request([198, 51, 100, 20])).await.unwrap();
+    assert_eq!(blocked.status(), StatusCode::FORBIDDEN);
+
+    // The security event is written in the background
+    let mut count = 0i64;
+    for _ in 0..50 {
+        count = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM security_events WHERE event_type = 'ip_blocked' AND ip_address = '198.51.100.20'::inet AND outcome = 'blocked'",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        if count > 0 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(count, 1);
+}
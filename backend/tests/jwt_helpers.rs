@@ -45,5 +45,37 @@ xOkT6FXwwZZiKamADXpik1wFJ/K5ZD27pXFusiDZbwrUcGfcguZJehRbwBRRiwZl
 FwIDAQAB
 -----END PUBLIC KEY-----"#
             .to_string(),
+        security_headers: Default::default(),
+        cors: Default::default(),
+        body_limits: Default::default(),
+        ip_access: Default::default(),
+        geoip: Default::default(),
+        shutdown: Default::default(),
+        tls: Default::default(),
+        route_limits: Default::default(),
+        ai_circuit_breaker: Default::default(),
+        outbound_http: Default::default(),
+        csrf: Default::default(),
+        uploads: Default::default(),
+        semantic_search: Default::default(),
+        duplicate_detection: Default::default(),
+        ai_conversations: Default::default(),
+        ai_alert_explanations: Default::default(),
+        ai_redaction: Default::default(),
+        ai_providers: Default::default(),
+        api: Default::default(),
+        firefighter: Default::default(),
+        test_mode: Default::default(),
+        email: Default::default(),
+        notifications: Default::default(),
+        web_push: Default::default(),
+        graph_sync: Default::default(),
+        search_index: Default::default(),
+        cache: Default::default(),
+        storage: Default::default(),
+        ingestion: Default::default(),
+        decision_log: Default::default(),
+        permissions: Default::default(),
+        schema: Default::default(),
     }
 }
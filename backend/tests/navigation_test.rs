@@ -1,8 +1,14 @@
 use std::collections::HashSet;
 
+use sqlx::PgPool;
 use template_repo_backend::features::navigation::models::{
-    default_navigation, evaluate_navigation,
+    apply_preferences, default_navigation, evaluate_navigation, validate_sections, NavPreference,
+    NavSectionDefinition, SaveNavigationMenu, UpdateNavPreference,
 };
+use template_repo_backend::features::navigation::NavigationService;
+use uuid::Uuid;
+
+mod common;
 
 #[test]
 fn test_navigation_visibility_with_single_permission() {
@@ -35,3 +41,525 @@ fn test_navigation_visibility_with_wildcard_permission() {
         "all items should be visible with wildcard permission"
     );
 }
+
+fn menu(section_id: &str, items: serde_json::Value) -> Vec<NavSectionDefinition> {
+    serde_json::from_value(serde_json::json!([
+        { "id": section_id, "label": section_id, "items": items }
+    ]))
+    .unwrap()
+}
+
+#[test]
+fn test_navigation_preferences_pin_and_hide() {
+    let definitions = menu(
+        "main",
+        serde_json::json!([
+            { "id": "a", "label": "A", "href": "/a" },
+            { "id": "b", "label": "B", "href": "/b" },
+            { "id": "c", "label": "C", "href": "/c", "required_permissions": ["ui.view.c"] }
+        ]),
+    );
+    let mut evaluated = evaluate_navigation(&definitions, &HashSet::new());
+    let preference = |item_id: &str, pinned, hidden| NavPreference {
+        item_id: item_id.to_string(),
+        pinned,
+        hidden,
+        updated_at: chrono::Utc::now(),
+    };
+    apply_preferences(
+        &mut evaluated,
+        &[
+            preference("b", true, false),
+            preference("a", false, true),
+            preference("c", true, false),
+        ],
+    );
+
+    let items = &evaluated[0].items;
+    // Pinned first; pinning doesn't reveal an item the user may not see
+    assert_eq!(items[0].id, "b");
+    assert_eq!(items[1].id, "c");
+    assert!(items[1].pinned && !items[1].visible);
+    let hidden = items.iter().find(|i| i.id == "a").unwrap();
+    assert!(hidden.hidden && !hidden.visible);
+    assert!(evaluated[0].visible);
+}
+
+#[test]
+fn test_validate_navigation_sections() {
+    assert!(validate_sections(&default_navigation()).is_ok());
+    assert!(validate_sections(&[]).is_err());
+    assert!(validate_sections(&menu(
+        "main",
+        serde_json::json!([{ "id": "main", "label": "Dup", "href": "/dup" }])
+    ))
+    .is_err());
+    assert!(validate_sections(&menu(
+        "main",
+        serde_json::json!([{ "id": "ext", "label": "External", "href": "https://example.com" }])
+    ))
+    .is_err());
+}
+
+#[sqlx::test]
+async fn test_navigation_menus_by_tenant_and_role(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let navigation = NavigationService::new(
+        pool.clone(),
+        services.abac_service.clone(),
+        services.rebac_service.clone(),
+    );
+
+    let tenant_id = Uuid::new_v4();
+    let user_id = Uuid::new_v4();
+    let user_class = services
+        .ontology_service
+        .get_system_class("User")
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO entities (id, class_id, display_name, attributes, tenant_id) VALUES ($1, $2, $3, $4, $5)")
+        .bind(user_id)
+        .bind(user_class.id)
+        .bind("nav_user")
+        .bind(serde_json::json!({"username": "nav_user"}))
+        .bind(tenant_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let roles = vec!["Analyst".to_string()];
+    let save = |tenant_id, role_name: Option<&str>, section_id: &str| SaveNavigationMenu {
+        tenant_id,
+        role_name: role_name.map(str::to_string),
+        priority: 0,
+        sections: menu(
+            section_id,
+            serde_json::json!([
+                { "id": format!("{}.home", section_id), "label": "Home", "href": "/" },
+                { "id": format!("{}.secret", section_id), "label": "Secret", "href": "/secret",
+                  "required_permissions": ["nav_test.secret"] }
+            ]),
+        ),
+    };
+
+    // Built-in navigation until a menu is stored
+    let sections = navigation.menu_for(user_id, &roles).await.unwrap();
+    assert_eq!(sections[0].id, default_navigation()[0].id);
+
+    navigation
+        .save_menu(save(None, None, "global"), None)
+        .await
+        .unwrap();
+    navigation
+        .save_menu(save(None, Some("Analyst"), "analyst"), None)
+        .await
+        .unwrap();
+    assert_eq!(
+        navigation.menu_for(user_id, &roles).await.unwrap()[0].id,
+        "analyst"
+    );
+    assert_eq!(
+        navigation.menu_for(user_id, &[]).await.unwrap()[0].id,
+        "global"
+    );
+
+    let tenant_menu = navigation
+        .save_menu(save(Some(tenant_id), None, "tenant"), None)
+        .await
+        .unwrap();
+    assert_eq!(
+        navigation.menu_for(user_id, &roles).await.unwrap()[0].id,
+        "tenant"
+    );
+
+    // Saving the same scope replaces the menu
+    let replaced = navigation
+        .save_menu(save(Some(tenant_id), None, "tenant2"), None)
+        .await
+        .unwrap();
+    assert_eq!(replaced.id, tenant_menu.id);
+    assert_eq!(navigation.list_menus().await.unwrap().len(), 3);
+
+    // ABAC filtering and the user's preferences apply on top
+    navigation
+        .set_preference(
+            user_id,
+            "tenant2.secret",
+            UpdateNavPreference {
+                pinned: Some(true),
+                hidden: None,
+            },
+        )
+        .await
+        .unwrap();
+    let evaluated = navigation
+        .evaluate_for_user(&user_id.to_string(), &roles)
+        .await
+        .unwrap();
+    let items = &evaluated[0].items;
+    assert_eq!(items[0].id, "tenant2.secret");
+    assert!(items[0].pinned && !items[0].visible);
+    assert!(items[1].visible);
+
+    navigation
+        .set_preference(
+            user_id,
+            "tenant2.home",
+            UpdateNavPreference {
+                pinned: None,
+                hidden: Some(true),
+            },
+        )
+        .await
+        .unwrap();
+    let evaluated = navigation
+        .evaluate_for_user(&user_id.to_string(), &roles)
+        .await
+        .unwrap();
+    assert!(!evaluated[0].visible);
+
+    navigation
+        .clear_preference(user_id, "tenant2.home")
+        .await
+        .unwrap();
+    assert_eq!(navigation.list_preferences(user_id).await.unwrap().len(), 1);
+
+    navigation.delete_menu(replaced.id).await.unwrap();
+    assert_eq!(
+        navigation.menu_for(user_id, &roles).await.unwrap()[0].id,
+        "analyst"
+    );
+}
+
+#[sqlx::test]
+async fn test_quick_search_ranks_and_filters_by_permission(pool: PgPool) {
+    use template_repo_backend::features::abac::models::AssignRoleInput;
+    use template_repo_backend::features::ontology::models::{
+        CreateClassInput, CreateEntityInput, CreateRelationshipInput,
+    };
+
+    let services = common::setup_services(pool.clone()).await;
+    let ontology = &services.ontology_service;
+    let navigation = NavigationService::new(
+        pool.clone(),
+        services.abac_service.clone(),
+        services.rebac_service.clone(),
+    );
+
+    let user_id = Uuid::new_v4();
+    let user_class = ontology.get_system_class("User").await.unwrap();
+    sqlx::query("INSERT INTO entities (id, class_id, display_name, attributes, approval_status) VALUES ($1, $2, $3, $4, 'APPROVED')")
+        .bind(user_id)
+        .bind(user_class.id)
+        .bind("searcher")
+        .bind(serde_json::json!({"username": "searcher", "email": "alpha.searcher@example.com"}))
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let gadget = ontology
+        .create_class(
+            CreateClassInput {
+                name: "AlphaGadget".into(),
+                description: None,
+                parent_class_id: None,
+                is_abstract: Some(false),
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    let entity = |name: &str, parent_entity_id| CreateEntityInput {
+        class_id: gadget.id,
+        display_name: name.into(),
+        parent_entity_id,
+        attributes: None,
+    };
+    let folder = ontology
+        .create_entity(entity("Alpha", None), None, None)
+        .await
+        .unwrap();
+    for name in ["Beta alpha", "Alpha One"] {
+        ontology
+            .create_entity(entity(name, Some(folder.id)), None, None)
+            .await
+            .unwrap();
+    }
+    ontology
+        .create_entity(entity("Alpha Hidden", None), None, None)
+        .await
+        .unwrap();
+
+    // "read" on the folder, inherited by its children
+    let role_class = ontology.get_system_class("Role").await.unwrap();
+    let perm_class = ontology.get_system_class("Permission").await.unwrap();
+    let role = ontology
+        .create_entity(
+            CreateEntityInput {
+                class_id: role_class.id,
+                display_name: "SearchViewer".into(),
+                parent_entity_id: None,
+                attributes: Some(serde_json::json!({"name": "SearchViewer", "level": 10})),
+            },
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    let read = ontology
+        .create_entity(
+            CreateEntityInput {
+                class_id: perm_class.id,
+                display_name: "read".into(),
+                parent_entity_id: None,
+                attributes: Some(serde_json::json!({"name": "read", "level": 1})),
+            },
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    for (source, target, relationship_type, metadata) in [
+        (
+            role.id,
+            read.id,
+            "grants_permission",
+            serde_json::json!({"effect": "ALLOW"}),
+        ),
+        (
+            user_id,
+            role.id,
+            "has_role",
+            serde_json::json!({"scope_entity_id": folder.id.to_string()}),
+        ),
+    ] {
+        ontology
+            .create_relationship(
+                CreateRelationshipInput {
+                    source_entity_id: source,
+                    target_entity_id: target,
+                    relationship_type: relationship_type.into(),
+                    metadata: Some(metadata),
+                },
+                None,
+            )
+            .await
+            .unwrap();
+    }
+
+    let results = navigation
+        .quick_search(user_id, " alpha ", None)
+        .await
+        .unwrap();
+    let labels: Vec<&str> = results.entities.iter().map(|h| h.label.as_str()).collect();
+    assert_eq!(labels, vec!["Alpha", "Alpha One", "Beta alpha"]);
+    let scores: Vec<f64> = results.entities.iter().map(|h| h.score).collect();
+    assert_eq!(scores, vec![1.0, 0.8, 0.6]);
+    assert_eq!(results.entities[0].detail.as_deref(), Some("AlphaGadget"));
+    // No ABAC permission for the user and class admin pages yet
+    assert!(results.users.is_empty());
+    assert!(results.classes.is_empty());
+
+    let limited = navigation
+        .quick_search(user_id, "alpha", Some(1))
+        .await
+        .unwrap();
+    assert_eq!(limited.entities.len(), 1);
+    assert!(navigation
+        .quick_search(user_id, "a", None)
+        .await
+        .unwrap()
+        .entities
+        .is_empty());
+    assert!(navigation
+        .quick_search(user_id, "%%", None)
+        .await
+        .unwrap()
+        .entities
+        .is_empty());
+
+    let admin_role = services
+        .abac_service
+        .create_role("QuickSearchAdmin", None)
+        .await
+        .unwrap();
+    for permission in ["ui.view.users", "ui.view.ontology"] {
+        ontology
+            .create_entity(
+                CreateEntityInput {
+                    class_id: perm_class.id,
+                    display_name: permission.into(),
+                    parent_entity_id: None,
+                    attributes: Some(serde_json::json!({"name": permission, "level": 0})),
+                },
+                None,
+                None,
+            )
+            .await
+            .ok();
+        services
+            .abac_service
+            .add_permission(&admin_role.id.to_string(), permission)
+            .await
+            .unwrap();
+    }
+    services
+        .abac_service
+        .assign_role(
+            AssignRoleInput {
+                user_id: user_id.to_string(),
+                role_name: admin_role.name.clone(),
+                resource_id: None,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    let results = navigation
+        .quick_search(user_id, "alpha", None)
+        .await
+        .unwrap();
+    assert_eq!(results.users.len(), 1);
+    assert_eq!(results.users[0].label, "searcher");
+    assert_eq!(results.users[0].score, 0.8);
+    assert!(results.classes.iter().any(|h| h.label == "AlphaGadget"));
+}
+
+#[sqlx::test]
+async fn test_badge_counts_follow_permissions(pool: PgPool) {
+    use template_repo_backend::features::abac::models::AssignRoleInput;
+    use template_repo_backend::features::ontology::models::{
+        CreateEntityInput, CreateRelationshipInput,
+    };
+
+    let services = common::setup_services(pool.clone()).await;
+    let ontology = &services.ontology_service;
+    let navigation = NavigationService::new(
+        pool.clone(),
+        services.abac_service.clone(),
+        services.rebac_service.clone(),
+    );
+
+    let tenant_id = Uuid::new_v4();
+    let user_id = Uuid::new_v4();
+    let user_class = ontology.get_system_class("User").await.unwrap();
+    sqlx::query("INSERT INTO entities (id, class_id, display_name, attributes, tenant_id) VALUES ($1, $2, $3, $4, $5)")
+        .bind(user_id)
+        .bind(user_class.id)
+        .bind("badge_user")
+        .bind(serde_json::json!({"username": "badge_user"}))
+        .bind(tenant_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let notification_class = ontology.get_system_class("Notification").await.unwrap();
+    for (message, read) in [("one", false), ("two", false), ("seen", true)] {
+        sqlx::query(
+            "INSERT INTO entities (class_id, display_name, attributes) VALUES ($1, $2, $3)",
+        )
+        .bind(notification_class.id)
+        .bind(message)
+        .bind(serde_json::json!({"user_id": user_id, "message": message, "read": read}))
+        .execute(&pool)
+        .await
+        .unwrap();
+    }
+
+    // Pending in the user's tenant, and in another one
+    for tenant in [tenant_id, Uuid::new_v4()] {
+        sqlx::query("INSERT INTO entities (class_id, display_name, attributes, tenant_id, approval_status) VALUES ($1, 'pending', '{}', $2, 'PENDING')")
+            .bind(user_class.id)
+            .bind(tenant)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    let role_class = ontology.get_system_class("Role").await.unwrap();
+    let mut roles = Vec::new();
+    // Only the grant ending within a week counts as expiring
+    for (name, days) in [("BadgeTemporary", 2), ("BadgeQuarterly", 30)] {
+        let role = ontology
+            .create_entity(
+                CreateEntityInput {
+                    class_id: role_class.id,
+                    display_name: name.into(),
+                    parent_entity_id: None,
+                    attributes: Some(serde_json::json!({"name": name, "level": 1})),
+                },
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        let valid_until = chrono::Utc::now() + chrono::Duration::days(days);
+        ontology
+            .create_relationship(
+                CreateRelationshipInput {
+                    source_entity_id: user_id,
+                    target_entity_id: role.id,
+                    relationship_type: "has_role".into(),
+                    metadata: Some(serde_json::json!({"valid_until": valid_until})),
+                },
+                None,
+            )
+            .await
+            .unwrap();
+        roles.push(role);
+    }
+
+    // Someone else's session: an active one of the user's own bypasses ABAC
+    let responder_id = Uuid::new_v4();
+    sqlx::query("INSERT INTO entities (id, class_id, display_name, attributes) VALUES ($1, $2, 'responder', '{}')")
+        .bind(responder_id)
+        .bind(user_class.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO firefighter_sessions (user_id, elevated_role_id, justification, expires_at) VALUES ($1, $2, 'incident', NOW() + INTERVAL '1 hour')")
+        .bind(responder_id)
+        .bind(roles[0].id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    // Without the admin views only the user's own notifications are counted
+    let badges = navigation.badge_counts(user_id).await.unwrap();
+    assert_eq!(badges.unread_notifications, 2);
+    assert!(badges.items.is_empty());
+
+    let admin_role = services
+        .abac_service
+        .create_role("BadgeAdmin", None)
+        .await
+        .unwrap();
+    for permission in [
+        "ui.view.ontology",
+        "ui.view.schedules",
+        "ui.view.firefighter",
+    ] {
+        services
+            .abac_service
+            .add_permission(&admin_role.id.to_string(), permission)
+            .await
+            .unwrap();
+    }
+    services
+        .abac_service
+        .assign_role(
+            AssignRoleInput {
+                user_id: user_id.to_string(),
+                role_name: admin_role.name.clone(),
+                resource_id: None,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    let badges = navigation.badge_counts(user_id).await.unwrap();
+    assert_eq!(badges.items.get("admin.ontology.designer"), Some(&1));
+    assert_eq!(badges.items.get("admin.schedules"), Some(&1));
+    assert_eq!(badges.items.get("admin.firefighter"), Some(&1));
+}
@@ -0,0 +1,542 @@
+use futures::StreamExt;
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use template_repo_backend::features::auth::models::RegisterUser;
+use template_repo_backend::features::email::models::ListDeliveriesQuery;
+use template_repo_backend::features::notifications::models::{
+    DeliveryChannel, DigestFrequency, ListNotificationsQuery, NotificationCategory,
+    UpdateNotificationTemplateInput, UpdatePreferencesInput,
+};
+use template_repo_backend::features::notifications::templates::{
+    NEW_DEVICE_LOGIN, PASSWORD_CHANGED, PASSWORD_RESET,
+};
+use template_repo_backend::features::notifications::NotificationError;
+use uuid::Uuid;
+
+mod common;
+
+async fn register(services: &common::TestServices, username: &str) -> Uuid {
+    services
+        .auth_service
+        .register(RegisterUser {
+            username: username.to_string(),
+            email: format!("{}@example.com", username),
+            password: "Password123!".to_string(),
+        })
+        .await
+        .expect("Registration failed")
+        .user_id
+}
+
+async fn queued_emails(services: &common::TestServices, template: &str) -> Vec<String> {
+    services
+        .email_service
+        .list_deliveries(ListDeliveriesQuery {
+            status: Some("pending".to_string()),
+            recipient: None,
+            template: Some(template.to_string()),
+            limit: None,
+        })
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|d| d.subject)
+        .collect()
+}
+
+async fn in_app_count(services: &common::TestServices, user: Uuid) -> usize {
+    services
+        .notification_service
+        .unread_count(user)
+        .await
+        .unwrap() as usize
+}
+
+/// The stream's next item, or None if nothing arrives in time
+async fn next_within<S: futures::Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+    tokio::time::timeout(std::time::Duration::from_secs(2), stream.next())
+        .await
+        .ok()
+        .flatten()
+}
+
+fn channels(
+    entries: &[(NotificationCategory, DeliveryChannel)],
+) -> BTreeMap<NotificationCategory, DeliveryChannel> {
+    entries.iter().copied().collect()
+}
+
+#[sqlx::test]
+async fn test_preferences_choose_channel_per_category(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let notifications = &services.notification_service;
+    let user = register(&services, "prefs_user").await;
+
+    // Everything is in-app until the user says otherwise
+    let prefs = notifications.get_preferences(user).await.unwrap();
+    assert_eq!(
+        prefs.channel(NotificationCategory::Account),
+        DeliveryChannel::InApp
+    );
+    assert_eq!(prefs.digest, DigestFrequency::Off);
+
+    // Security notices can't be switched off
+    let err = notifications
+        .update_preferences(
+            user,
+            UpdatePreferencesInput {
+                channels: channels(&[(NotificationCategory::Security, DeliveryChannel::None)]),
+                digest: None,
+                locale: None,
+                push: None,
+            },
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, NotificationError::InvalidInput(_)));
+
+    notifications
+        .update_preferences(
+            user,
+            UpdatePreferencesInput {
+                channels: channels(&[
+                    (NotificationCategory::Account, DeliveryChannel::None),
+                    (NotificationCategory::Security, DeliveryChannel::Email),
+                ]),
+                digest: None,
+                locale: None,
+                push: None,
+            },
+        )
+        .await
+        .unwrap();
+    // Later updates only touch the categories they list
+    let prefs = notifications
+        .update_preferences(
+            user,
+            UpdatePreferencesInput {
+                channels: channels(&[(NotificationCategory::Access, DeliveryChannel::Email)]),
+                digest: Some(DigestFrequency::Weekly),
+                locale: None,
+                push: None,
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        prefs.channel(NotificationCategory::Account),
+        DeliveryChannel::None
+    );
+    assert_eq!(
+        prefs.channel(NotificationCategory::Security),
+        DeliveryChannel::Email
+    );
+    assert_eq!(
+        prefs.channel(NotificationCategory::Access),
+        DeliveryChannel::Email
+    );
+    assert_eq!(prefs.digest, DigestFrequency::Weekly);
+    assert_eq!(notifications.get_preferences(user).await.unwrap(), prefs);
+
+    // Turned-off categories create nothing
+    assert!(notifications
+        .notify(
+            user,
+            NotificationCategory::Account,
+            "Your profile was updated."
+        )
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(in_app_count(&services, user).await, 0);
+
+    // High-priority email notifications are sent right away, even with a digest
+    notifications
+        .notify(
+            user,
+            NotificationCategory::Security,
+            "Your password was changed.",
+        )
+        .await
+        .unwrap()
+        .expect("Security notification should be created");
+    assert_eq!(
+        queued_emails(&services, "notification").await,
+        vec!["Ontology Manager: Your password was changed.".to_string()]
+    );
+    assert_eq!(in_app_count(&services, user).await, 1);
+
+    assert!(matches!(
+        notifications.get_preferences(Uuid::new_v4()).await,
+        Err(NotificationError::NotFound(_))
+    ));
+}
+
+#[sqlx::test]
+async fn test_low_priority_emails_are_batched_into_digest(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let notifications = &services.notification_service;
+    let user = register(&services, "digest_user").await;
+
+    notifications
+        .update_preferences(
+            user,
+            UpdatePreferencesInput {
+                channels: channels(&[
+                    (NotificationCategory::Account, DeliveryChannel::Email),
+                    (NotificationCategory::ApiKeys, DeliveryChannel::Email),
+                ]),
+                digest: Some(DigestFrequency::Daily),
+                locale: None,
+                push: None,
+            },
+        )
+        .await
+        .unwrap();
+
+    for message in ["Your profile was updated.", "API key 'ci' expires soon."] {
+        notifications
+            .notify(
+                user,
+                if message.starts_with("API") {
+                    NotificationCategory::ApiKeys
+                } else {
+                    NotificationCategory::Account
+                },
+                message,
+            )
+            .await
+            .unwrap();
+    }
+
+    // Listed in the app, but no email yet
+    assert_eq!(in_app_count(&services, user).await, 2);
+    assert!(queued_emails(&services, "notification").await.is_empty());
+    assert_eq!(notifications.send_due_digests().await.unwrap(), 0);
+
+    // A day later the digest goes out with both
+    sqlx::query(
+        "UPDATE entities SET created_at = created_at - INTERVAL '25 hours'
+         WHERE attributes->>'user_id' = $1::text AND attributes ? 'digest_pending'",
+    )
+    .bind(user)
+    .execute(&pool)
+    .await
+    .unwrap();
+    assert_eq!(notifications.send_due_digests().await.unwrap(), 1);
+    assert_eq!(
+        queued_emails(&services, "notification_digest").await,
+        vec!["Your daily Ontology Manager digest: 2 notification(s)".to_string()]
+    );
+    services.email_service.deliver_due().await.unwrap();
+
+    // Nothing is sent twice, and the next one waits a full day
+    assert_eq!(notifications.send_due_digests().await.unwrap(), 0);
+    notifications
+        .notify(
+            user,
+            NotificationCategory::Account,
+            "Your profile was updated again.",
+        )
+        .await
+        .unwrap();
+    assert_eq!(notifications.send_due_digests().await.unwrap(), 0);
+
+    // Switching the digest off flushes what is waiting
+    notifications
+        .update_preferences(
+            user,
+            UpdatePreferencesInput {
+                channels: BTreeMap::new(),
+                digest: Some(DigestFrequency::Off),
+                locale: None,
+                push: None,
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(notifications.send_due_digests().await.unwrap(), 1);
+    assert_eq!(
+        queued_emails(&services, "notification_digest").await,
+        vec!["Your daily Ontology Manager digest: 1 notification(s)".to_string()]
+    );
+}
+
+#[sqlx::test]
+async fn test_notifications_are_worded_for_user_locale(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let notifications = &services.notification_service;
+    let user = register(&services, "locale_user").await;
+    let admin = Uuid::new_v4();
+    let wording = |message: &str| UpdateNotificationTemplateInput {
+        message: message.to_string(),
+    };
+
+    notifications
+        .set_template_variant(
+            "password_changed",
+            "nb",
+            wording("Passordet ditt ble endret."),
+            admin,
+        )
+        .await
+        .unwrap();
+    let variant = notifications
+        .set_template_variant(
+            "new_device_login",
+            "nb_no",
+            wording("Ny pålogging fra {{ip}} ({{user_agent}})"),
+            admin,
+        )
+        .await
+        .unwrap();
+    assert_eq!(variant.locale, "nb-NO");
+
+    // Only the notification's own variables, real locales and known keys
+    for (key, locale, message) in [
+        ("password_changed", "nb", "{{password}}"),
+        ("password_changed", "norsk!", "Endret"),
+        ("password_changed", "nb", "  "),
+    ] {
+        assert!(matches!(
+            notifications
+                .set_template_variant(key, locale, wording(message), admin)
+                .await,
+            Err(NotificationError::InvalidInput(_))
+        ));
+    }
+    assert!(matches!(
+        notifications
+            .set_template_variant("no_such_notification", "nb", wording("x"), admin)
+            .await,
+        Err(NotificationError::NotFound(_))
+    ));
+
+    let prefs = notifications
+        .update_preferences(
+            user,
+            UpdatePreferencesInput {
+                channels: BTreeMap::new(),
+                digest: None,
+                locale: Some("nb_no".to_string()),
+                push: None,
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(prefs.locale.as_deref(), Some("nb-NO"));
+
+    // Exact locale, then the language, then the built-in English
+    let sent = notifications
+        .notify_template(
+            user,
+            &NEW_DEVICE_LOGIN,
+            &[("ip", "10.0.0.1"), ("user_agent", "curl")],
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(sent.message, "Ny pålogging fra 10.0.0.1 (curl)");
+    let sent = notifications
+        .notify_template(user, &PASSWORD_CHANGED, &[])
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(sent.message, "Passordet ditt ble endret.");
+    services
+        .auth_service
+        .send_notification(user, &PASSWORD_RESET, &[])
+        .await
+        .unwrap();
+    let messages: Vec<String> = services
+        .notification_service
+        .list_notifications(user, Default::default())
+        .await
+        .unwrap()
+        .notifications
+        .into_iter()
+        .map(|n| n.message)
+        .collect();
+    assert!(messages.contains(&PASSWORD_RESET.message.to_string()));
+
+    // Removing a variant falls back to the next locale
+    let template = notifications
+        .delete_template_variant("password_changed", "nb", admin)
+        .await
+        .unwrap();
+    assert!(template.variants.is_empty());
+    let sent = notifications
+        .notify_template(user, &PASSWORD_CHANGED, &[])
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(sent.message, PASSWORD_CHANGED.message);
+    assert!(matches!(
+        notifications
+            .delete_template_variant("password_changed", "nb", admin)
+            .await,
+        Err(NotificationError::NotFound(_))
+    ));
+
+    let templates = notifications.list_templates().await.unwrap();
+    let new_device = templates
+        .iter()
+        .find(|t| t.key == "new_device_login")
+        .unwrap();
+    assert_eq!(new_device.variants.len(), 1);
+    assert_eq!(new_device.variables.len(), 2);
+}
+
+#[sqlx::test]
+async fn test_inbox_pages_and_marks_single_notifications(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let notifications = &services.notification_service;
+    let user = register(&services, "inbox_user").await;
+    let other = register(&services, "inbox_other").await;
+
+    for i in 1..=5 {
+        notifications
+            .notify(
+                user,
+                NotificationCategory::Account,
+                &format!("Notification {}", i),
+            )
+            .await
+            .unwrap();
+    }
+    let theirs = notifications
+        .notify(other, NotificationCategory::Account, "Not yours")
+        .await
+        .unwrap()
+        .unwrap();
+
+    let page = notifications
+        .list_notifications(
+            user,
+            ListNotificationsQuery {
+                limit: Some(2),
+                offset: Some(1),
+                unread_only: false,
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(page.total, 5);
+    assert_eq!(page.unread, 5);
+    let messages: Vec<&str> = page
+        .notifications
+        .iter()
+        .map(|n| n.message.as_str())
+        .collect();
+    assert_eq!(messages, vec!["Notification 4", "Notification 3"]);
+    assert_eq!(
+        page.notifications[0].category,
+        Some(NotificationCategory::Account)
+    );
+
+    // Only the one notification is marked read
+    let marked = notifications
+        .mark_read(user, page.notifications[0].id)
+        .await
+        .unwrap();
+    assert!(marked.read);
+    assert_eq!(notifications.unread_count(user).await.unwrap(), 4);
+    let unread = notifications
+        .list_notifications(
+            user,
+            ListNotificationsQuery {
+                unread_only: true,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(unread.total, 4);
+    assert!(unread.notifications.iter().all(|n| !n.read));
+
+    // Other users' notifications can't be touched
+    assert!(matches!(
+        notifications.mark_read(user, theirs.id).await,
+        Err(NotificationError::NotFound(_))
+    ));
+    assert!(matches!(
+        notifications.delete_notification(user, theirs.id).await,
+        Err(NotificationError::NotFound(_))
+    ));
+    assert_eq!(notifications.unread_count(other).await.unwrap(), 1);
+
+    notifications
+        .delete_notification(user, marked.id)
+        .await
+        .unwrap();
+    assert!(matches!(
+        notifications.delete_notification(user, marked.id).await,
+        Err(NotificationError::NotFound(_))
+    ));
+    assert_eq!(notifications.mark_all_read(user).await.unwrap(), 4);
+    let page = notifications
+        .list_notifications(user, Default::default())
+        .await
+        .unwrap();
+    assert_eq!((page.total, page.unread), (4, 0));
+}
+
+#[sqlx::test]
+async fn test_stream_filters_by_user_and_resumes_from_cursor(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let notifications = &services.notification_service;
+    let user = register(&services, "stream_user").await;
+    let other = register(&services, "stream_other").await;
+
+    let live = notifications.notification_stream(user, None).await.unwrap();
+    tokio::pin!(live);
+    notifications
+        .notify(other, NotificationCategory::Account, "For someone else")
+        .await
+        .unwrap();
+    let seen = notifications
+        .notify(user, NotificationCategory::Account, "First")
+        .await
+        .unwrap()
+        .unwrap();
+    let received = next_within(&mut live).await.unwrap();
+    assert_eq!(received.id, seen.id);
+    assert_eq!(received.message, "First");
+    assert!(!received.read);
+
+    // Created while the client was away
+    for message in ["Second", "Third"] {
+        notifications
+            .notify(user, NotificationCategory::Account, message)
+            .await
+            .unwrap();
+    }
+
+    // Reconnecting replays what came after the last seen id, then goes live
+    let resumed = notifications
+        .notification_stream(user, Some(seen.id))
+        .await
+        .unwrap();
+    tokio::pin!(resumed);
+    for expected in ["Second", "Third"] {
+        assert_eq!(next_within(&mut resumed).await.unwrap().message, expected);
+    }
+    notifications
+        .notify(user, NotificationCategory::Account, "Fourth")
+        .await
+        .unwrap();
+    assert_eq!(next_within(&mut resumed).await.unwrap().message, "Fourth");
+
+    // Another user's id is not a usable cursor
+    let foreign = notifications
+        .notify(other, NotificationCategory::Account, "Not yours")
+        .await
+        .unwrap()
+        .unwrap();
+    let stream = notifications
+        .notification_stream(user, Some(foreign.id))
+        .await
+        .unwrap();
+    tokio::pin!(stream);
+    assert!(next_within(&mut stream).await.is_none());
+}
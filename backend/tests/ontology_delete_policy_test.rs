@@ -0,0 +1,341 @@
+use axum::{body::Body, extract::Request, http::StatusCode, middleware::Next, response::Response};
+use sqlx::PgPool;
+use template_repo_backend::features::auth::jwt::Claims;
+use template_repo_backend::features::auth::models::RegisterUser;
+use template_repo_backend::features::ontology::models::{
+    CreateClassInput, CreateEntityInput, CreateRelationshipInput, DeletePolicy, UpdateClassInput,
+};
+use template_repo_backend::features::ontology::routes::ontology_routes;
+use template_repo_backend::features::ontology::service::{OntologyError, OntologyService};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+mod common;
+
+async fn class_with(ontology: &OntologyService, name: &str, policy: DeletePolicy) -> Uuid {
+    let class = ontology
+        .create_class(
+            CreateClassInput {
+                name: name.to_string(),
+                description: None,
+                parent_class_id: None,
+                is_abstract: Some(false),
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    let class = ontology
+        .update_class(
+            class.id,
+            UpdateClassInput {
+                name: None,
+                description: None,
+                parent_class_id: None,
+                is_abstract: None,
+                is_deprecated: None,
+                delete_policy: Some(policy),
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(class.delete_policy, policy);
+    class.id
+}
+
+/// A root > child > grandchild tree of entities of `classes` (one per
+/// level), and an outside entity the grandchild has a relationship to
+async fn tree_of(ontology: &OntologyService, classes: [Uuid; 3]) -> (Vec<Uuid>, Uuid) {
+    let mut ids: Vec<Uuid> = Vec::new();
+    for (name, class_id) in [
+        ("Root", classes[0]),
+        ("Child", classes[1]),
+        ("Grandchild", classes[2]),
+        ("Outsider", classes[0]),
+    ] {
+        let parent_entity_id = match name {
+            "Outsider" => None,
+            _ => ids.last().copied(),
+        };
+        let entity = ontology
+            .create_entity(
+                CreateEntityInput {
+                    class_id,
+                    display_name: name.to_string(),
+                    parent_entity_id,
+                    attributes: None,
+                },
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        ids.push(entity.id);
+    }
+    let relationship = ontology
+        .create_relationship(
+            CreateRelationshipInput {
+                source_entity_id: ids[2],
+                target_entity_id: ids[3],
+                relationship_type: "influences".to_string(),
+                metadata: None,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    (ids, relationship.id)
+}
+
+/// A tree whose entities are all of one class with `policy`
+async fn tree(ontology: &OntologyService, policy: DeletePolicy) -> (Vec<Uuid>, Uuid) {
+    let class_id = class_with(ontology, &format!("DeletePolicy{:?}", policy), policy).await;
+    tree_of(ontology, [class_id; 3]).await
+}
+
+async fn live(pool: &PgPool, id: Uuid) -> bool {
+    sqlx::query_scalar("SELECT deleted_at IS NULL FROM entities WHERE id = $1")
+        .bind(id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+async fn relationship_exists(pool: &PgPool, id: Uuid) -> bool {
+    sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM relationships WHERE id = $1)")
+        .bind(id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+async fn relationship_archived(pool: &PgPool, id: Uuid) -> bool {
+    sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM archived_relationships WHERE id = $1)")
+        .bind(id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+#[sqlx::test]
+async fn test_cascade_deletes_descendants_and_their_relationships(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    common::reopen_current_version(&pool).await;
+    let ontology = &services.ontology_service;
+    let (ids, relationship_id) = tree(ontology, DeletePolicy::Cascade).await;
+
+    // The dry run reports the whole subtree and changes nothing
+    let preview = ontology.preview_entity_delete(ids[0]).await.unwrap();
+    let deleted: Vec<Uuid> = preview.deleted_entities.iter().map(|e| e.id).collect();
+    assert_eq!(deleted, ids[..3]);
+    assert_eq!(preview.removed_relationships.len(), 1);
+    assert_eq!(preview.removed_relationships[0].id, relationship_id);
+    assert!(live(&pool, ids[2]).await);
+    assert!(relationship_exists(&pool, relationship_id).await);
+
+    let plan = ontology.delete_entity(ids[0], None).await.unwrap();
+    assert_eq!(plan.deleted_entities.len(), 3);
+    for id in &ids[..3] {
+        assert!(!live(&pool, *id).await);
+    }
+    assert!(live(&pool, ids[3]).await);
+    assert!(!relationship_exists(&pool, relationship_id).await);
+    assert!(relationship_archived(&pool, relationship_id).await);
+
+    // Restoring the deleted root brings back the subtree and the relationship
+    assert!(matches!(
+        ontology.restore_entity(ids[1], None).await,
+        Err(OntologyError::InvalidInput(_))
+    ));
+    let root = ontology.restore_entity(ids[0], None).await.unwrap();
+    assert_eq!(root.deleted_at, None);
+    for id in &ids[..3] {
+        assert!(live(&pool, *id).await);
+    }
+    assert!(relationship_exists(&pool, relationship_id).await);
+    assert!(!relationship_archived(&pool, relationship_id).await);
+}
+
+#[sqlx::test]
+async fn test_restore_keeps_conflicting_relationships_archived(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    common::reopen_current_version(&pool).await;
+    let ontology = &services.ontology_service;
+    let (ids, relationship_id) = tree(ontology, DeletePolicy::Cascade).await;
+    ontology.delete_entity(ids[0], None).await.unwrap();
+
+    // The same relationship is recreated while the original is archived
+    let recreated: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO relationships (source_entity_id, target_entity_id, relationship_type_id)
+        SELECT source_entity_id, target_entity_id, relationship_type_id
+        FROM archived_relationships WHERE id = $1
+        RETURNING id
+        "#,
+    )
+    .bind(relationship_id)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+
+    ontology.restore_entity(ids[0], None).await.unwrap();
+    assert!(relationship_exists(&pool, recreated).await);
+    assert!(!relationship_exists(&pool, relationship_id).await);
+    assert!(relationship_archived(&pool, relationship_id).await);
+}
+
+#[sqlx::test]
+async fn test_descendants_follow_their_own_class_policy(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    common::reopen_current_version(&pool).await;
+    let ontology = &services.ontology_service;
+    let cascade = class_with(ontology, "MixedCascade", DeletePolicy::Cascade).await;
+    let block = class_with(ontology, "MixedBlock", DeletePolicy::Block).await;
+    let detach = class_with(ontology, "MixedDetach", DeletePolicy::Detach).await;
+
+    // The cascade stops at a child whose class blocks deleting its children
+    let (ids, _) = tree_of(ontology, [cascade, block, detach]).await;
+    assert!(matches!(
+        ontology.delete_entity(ids[0], None).await,
+        Err(OntologyError::DeleteBlocked(_))
+    ));
+    assert!(live(&pool, ids[0]).await);
+
+    // and leaves the grandchild of one that detaches them
+    let (ids, relationship_id) = tree_of(ontology, [cascade, detach, block]).await;
+    let plan = ontology.delete_entity(ids[0], None).await.unwrap();
+    let deleted: Vec<Uuid> = plan.deleted_entities.iter().map(|e| e.id).collect();
+    assert_eq!(deleted, ids[..2]);
+    assert_eq!(plan.detached_entities[0].id, ids[2]);
+    assert!(live(&pool, ids[2]).await);
+    assert!(relationship_exists(&pool, relationship_id).await);
+}
+
+#[sqlx::test]
+async fn test_authorized_delete_refuses_unchecked_descendants(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    common::reopen_current_version(&pool).await;
+    let ontology = &services.ontology_service;
+    let (ids, _) = tree(ontology, DeletePolicy::Cascade).await;
+
+    // Checked before the grandchild existed, say
+    assert!(matches!(
+        ontology
+            .delete_authorized_entity(ids[0], None, &ids[..2])
+            .await,
+        Err(OntologyError::VersionConflict(_))
+    ));
+    assert!(live(&pool, ids[0]).await);
+
+    let plan = ontology
+        .delete_authorized_entity(ids[0], None, &ids[..3])
+        .await
+        .unwrap();
+    assert_eq!(plan.deleted_entities.len(), 3);
+}
+
+#[sqlx::test]
+async fn test_detach_keeps_children_without_a_parent(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    common::reopen_current_version(&pool).await;
+    let ontology = &services.ontology_service;
+    let (ids, relationship_id) = tree(ontology, DeletePolicy::Detach).await;
+
+    let plan = ontology.delete_entity(ids[1], None).await.unwrap();
+    assert_eq!(plan.deleted_entities.len(), 1);
+    assert_eq!(plan.detached_entities[0].id, ids[2]);
+    assert!(plan.removed_relationships.is_empty());
+
+    let grandchild = ontology.get_entity(ids[2]).await.unwrap();
+    assert_eq!(grandchild.parent_entity_id, None);
+    assert!(relationship_exists(&pool, relationship_id).await);
+}
+
+#[sqlx::test]
+async fn test_block_refuses_while_children_exist(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    common::reopen_current_version(&pool).await;
+    let ontology = &services.ontology_service;
+    let (ids, relationship_id) = tree(ontology, DeletePolicy::Block).await;
+
+    let preview = ontology.preview_entity_delete(ids[1]).await.unwrap();
+    assert!(preview.is_blocked());
+    assert_eq!(preview.blocking_entities[0].id, ids[2]);
+
+    assert!(matches!(
+        ontology.delete_entity(ids[1], None).await,
+        Err(OntologyError::DeleteBlocked(_))
+    ));
+    assert!(live(&pool, ids[1]).await);
+
+    // Leaves can still be deleted, archiving their relationships
+    ontology.delete_entity(ids[2], None).await.unwrap();
+    assert!(!relationship_exists(&pool, relationship_id).await);
+    assert!(relationship_archived(&pool, relationship_id).await);
+    ontology.delete_entity(ids[1], None).await.unwrap();
+
+    assert!(matches!(
+        ontology.preview_entity_delete(ids[1]).await,
+        Err(OntologyError::NotFound(_))
+    ));
+}
+
+#[sqlx::test]
+async fn test_delete_route_needs_delete_permission(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    common::reopen_current_version(&pool).await;
+    let (ids, _) = tree(&services.ontology_service, DeletePolicy::Cascade).await;
+
+    // No role at all: the seeded `delete` permission has level 0, so any
+    // role granting a permission type would pass it
+    let user_id = services
+        .auth_service
+        .register(RegisterUser {
+            username: "delete_outsider".to_string(),
+            email: "delete_outsider@example.com".to_string(),
+            password: "password123".to_string(),
+        })
+        .await
+        .unwrap()
+        .user_id;
+    let claims = Claims {
+        sub: user_id.to_string(),
+        username: "delete_outsider".to_string(),
+        email: "delete_outsider@example.com".to_string(),
+        roles: vec![],
+        permissions: vec![],
+        jti: None,
+        exp: i64::MAX,
+        iat: 0,
+    };
+    let fake_auth = move |mut request: Request, next: Next| {
+        let claims = claims.clone();
+        async move {
+            request.extensions_mut().insert(claims);
+            Ok::<Response, StatusCode>(next.run(request).await)
+        }
+    };
+    let app = ontology_routes()
+        .with_state(services.ontology_service.clone())
+        .layer(axum::middleware::from_fn(fake_auth))
+        .layer(axum::Extension(services.abac_service.clone()));
+
+    for uri in [
+        format!("/entities/{}?dry_run=true", ids[0]),
+        format!("/entities/{}", ids[0]),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("DELETE")
+                    .uri(uri)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+    assert!(live(&pool, ids[0]).await);
+}
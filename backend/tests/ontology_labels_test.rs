@@ -0,0 +1,216 @@
+use axum::{
+    body::{to_bytes, Body},
+    http::{header, Request, StatusCode},
+    Router,
+};
+use serde_json::json;
+use sqlx::PgPool;
+use template_repo_backend::features::ontology::labels::LabelTarget;
+use template_repo_backend::features::ontology::models::{
+    CreateClassInput, CreateEntityInput, SetLabelInput,
+};
+use template_repo_backend::features::ontology::routes::ontology_routes;
+use template_repo_backend::features::ontology::service::OntologyError;
+use tower::ServiceExt;
+
+mod common;
+
+async fn request(
+    app: &Router,
+    method: &str,
+    uri: &str,
+    accept_language: Option<&str>,
+    body: Option<serde_json::Value>,
+) -> (StatusCode, axum::http::HeaderMap, serde_json::Value) {
+    let mut request = Request::builder().method(method).uri(uri);
+    if let Some(accept_language) = accept_language {
+        request = request.header(header::ACCEPT_LANGUAGE, accept_language);
+    }
+    let body = match body {
+        Some(body) => {
+            request = request.header(header::CONTENT_TYPE, "application/json");
+            Body::from(body.to_string())
+        }
+        None => Body::empty(),
+    };
+    let response = app
+        .clone()
+        .oneshot(request.body(body).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (
+        status,
+        headers,
+        serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null),
+    )
+}
+
+#[sqlx::test]
+async fn test_class_labels_follow_accept_language(pool: PgPool) {
+    let services = common::setup_services(pool).await;
+    let class = services
+        .ontology_service
+        .create_class(
+            CreateClassInput {
+                name: "LabelledMission".to_string(),
+                description: Some("A mission".to_string()),
+                parent_class_id: None,
+                is_abstract: Some(false),
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    let app = ontology_routes().with_state(services.ontology_service.clone());
+    let labels_uri = format!("/classes/{}/labels/nb-no", class.id);
+
+    let (status, _, labels) = request(
+        &app,
+        "PUT",
+        &labels_uri,
+        None,
+        Some(json!({ "display_name": "Merket oppdrag" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        labels,
+        json!({ "nb-NO": { "display_name": "Merket oppdrag" } })
+    );
+
+    let class_uri = format!("/classes/{}", class.id);
+    let (status, headers, body) =
+        request(&app, "GET", &class_uri, Some("nb-NO, en;q=0.5"), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers[header::VARY], "accept-language");
+    // The untranslated name is untouched and the description has no nb text yet
+    assert_eq!(body["name"], "LabelledMission");
+    assert_eq!(
+        body["localized"],
+        json!({ "locale": "nb-NO", "display_name": "Merket oppdrag", "description": "A mission" })
+    );
+
+    let (_, de_headers, body) = request(&app, "GET", &class_uri, Some("de"), None).await;
+    assert_eq!(body["localized"]["display_name"], "LabelledMission");
+    assert_eq!(body["localized"]["locale"], serde_json::Value::Null);
+
+    // A cached Norwegian copy is not revalidated for a German reader
+    let nb_etag = headers[header::ETAG].to_str().unwrap();
+    assert_ne!(de_headers[header::ETAG], nb_etag);
+    let request_with = |accept_language: &str| {
+        Request::builder()
+            .uri(&class_uri)
+            .header(header::ACCEPT_LANGUAGE, accept_language)
+            .header(header::IF_NONE_MATCH, nb_etag)
+            .body(Body::empty())
+            .unwrap()
+    };
+    let response = app.clone().oneshot(request_with("de")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app.clone().oneshot(request_with("nb-NO")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    let (status, _, labels) = request(&app, "DELETE", &labels_uri, None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(labels, json!({}));
+}
+
+#[sqlx::test]
+async fn test_entity_labels(pool: PgPool) {
+    let services = common::setup_services(pool).await;
+    let ontology = &services.ontology_service;
+    let class = ontology
+        .create_class(
+            CreateClassInput {
+                name: "LabelledSite".to_string(),
+                description: None,
+                parent_class_id: None,
+                is_abstract: Some(false),
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    let entity = ontology
+        .create_entity(
+            CreateEntityInput {
+                class_id: class.id,
+                display_name: "North Base".to_string(),
+                parent_entity_id: None,
+                attributes: None,
+            },
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+    let labels = ontology
+        .set_label(
+            LabelTarget::Entity,
+            entity.id,
+            "nb",
+            SetLabelInput {
+                display_name: Some("Nordbasen".to_string()),
+                description: None,
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(labels["nb"].display_name.as_deref(), Some("Nordbasen"));
+
+    let app = ontology_routes().with_state(ontology.clone());
+    let entity_uri = format!("/entities/{}", entity.id);
+    let (_, nb_headers, body) = request(&app, "GET", &entity_uri, Some("nb"), None).await;
+    assert_eq!(body["localized"]["display_name"], "Nordbasen");
+    let (_, en_headers, _) = request(&app, "GET", &entity_uri, Some("en"), None).await;
+    assert_ne!(nb_headers[header::ETAG], en_headers[header::ETAG]);
+
+    let (_, _, entities) = request(
+        &app,
+        "GET",
+        &format!("/entities?class_id={}", class.id),
+        Some("nb-NO"),
+        None,
+    )
+    .await;
+    assert_eq!(entities[0]["display_name"], "North Base");
+    assert_eq!(entities[0]["localized"]["display_name"], "Nordbasen");
+    assert_eq!(entities[0]["localized"]["locale"], "nb");
+
+    // Invalid locales, empty labels and unknown targets are rejected
+    let empty = || SetLabelInput {
+        display_name: None,
+        description: None,
+    };
+    assert!(matches!(
+        ontology
+            .set_label(LabelTarget::Entity, entity.id, "not a locale", empty())
+            .await,
+        Err(OntologyError::InvalidInput(_))
+    ));
+    assert!(matches!(
+        ontology
+            .set_label(LabelTarget::Entity, entity.id, "en", empty())
+            .await,
+        Err(OntologyError::InvalidInput(_))
+    ));
+    assert!(matches!(
+        ontology
+            .remove_label(LabelTarget::Class, uuid::Uuid::new_v4(), "en")
+            .await,
+        Err(OntologyError::NotFound(_))
+    ));
+    let (status, _, _) = request(
+        &app,
+        "PUT",
+        &format!("/entities/{}/labels/nb", entity.id),
+        None,
+        Some(json!({ "display_name": "  " })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
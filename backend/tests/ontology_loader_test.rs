@@ -0,0 +1,90 @@
+use futures::future::join_all;
+use sqlx::PgPool;
+use template_repo_backend::features::ontology::loader::OntologyLoader;
+use template_repo_backend::features::ontology::models::{CreateClassInput, CreateEntityInput};
+use template_repo_backend::features::ontology::service::OntologyError;
+use uuid::Uuid;
+
+mod common;
+
+#[sqlx::test]
+async fn test_lookups_in_a_request_are_batched(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let ontology = &services.ontology_service;
+    let class = ontology
+        .create_class(
+            CreateClassInput {
+                name: "LoaderCrate".to_string(),
+                description: None,
+                parent_class_id: None,
+                is_abstract: Some(false),
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    let mut ids = Vec::new();
+    for n in 0..3 {
+        let entity = ontology
+            .create_entity(
+                CreateEntityInput {
+                    class_id: class.id,
+                    display_name: format!("Crate {}", n),
+                    parent_entity_id: None,
+                    attributes: None,
+                },
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        ids.push(entity.id);
+    }
+    let missing = Uuid::new_v4();
+
+    let loader = OntologyLoader::new();
+    loader
+        .clone()
+        .scope(async {
+            // Awaited together: one query for all of them
+            let lookups = ids
+                .iter()
+                .chain([&ids[0], &missing])
+                .map(|id| ontology.get_entity(*id));
+            let results = join_all(lookups).await;
+            assert_eq!(loader.batches(), 1);
+            for (result, id) in results.iter().zip(&ids) {
+                assert_eq!(result.as_ref().unwrap().id, *id);
+            }
+            assert!(matches!(results[4], Err(OntologyError::NotFound(_))));
+
+            // Already read, so no more queries, and known misses stay missing
+            assert_eq!(
+                ontology.get_entity(ids[1]).await.unwrap().display_name,
+                "Crate 1"
+            );
+            assert!(ontology.get_entity(missing).await.is_err());
+            let entities = ontology.get_entities(&ids).await.unwrap();
+            assert_eq!(entities.len(), 3);
+            assert_eq!(loader.batches(), 1);
+
+            let user = ontology.get_system_class("User").await.unwrap();
+            let role = ontology.get_system_class("Role").await.unwrap();
+            assert_ne!(user.id, role.id);
+            assert!(ontology.get_relationship_type("has_role").await.is_ok());
+            assert!(ontology
+                .get_relationship_type("no_such_type")
+                .await
+                .is_err());
+        })
+        .await;
+
+    // Outside the request nothing is memoized
+    sqlx::query("UPDATE entities SET deleted_at = NOW() WHERE id = $1")
+        .bind(ids[0])
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert!(ontology.get_entity(ids[0]).await.is_err());
+    assert_eq!(ontology.get_entities(&ids).await.unwrap().len(), 2);
+}
@@ -1,6 +1,8 @@
 use sqlx::PgPool;
 use template_repo_backend::features::ontology::models::{
-    CreateClassInput, CreateEntityInput, CreateRelationshipInput,
+    ApprovalStatus, ApproveMergeInput, CreateClassInput, CreateEntityInput,
+    CreateRelationshipInput, CreateVersionInput, GraphImport, ImportRelationshipInput,
+    SchemaChanges,
 };
 
 mod common;
@@ -167,3 +169,526 @@ async fn test_relationship_creation(pool: PgPool) {
     assert_eq!(rel.source_entity_id, source_entity.id);
     assert_eq!(rel.target_entity_id, target_entity.id);
 }
+
+#[sqlx::test]
+async fn test_stream_subgraph_export(pool: PgPool) {
+    use futures::StreamExt;
+    use template_repo_backend::features::ontology::models::SubgraphRecord;
+
+    let services = common::setup_services(pool.clone()).await;
+
+    let class = services
+        .ontology_service
+        .create_class(
+            CreateClassInput {
+                name: "ExportNode".to_string(),
+                description: None,
+                parent_class_id: None,
+                is_abstract: Some(false),
+            },
+            None,
+        )
+        .await
+        .expect("Failed to create class");
+
+    let create = |name: &'static str, parent: Option<uuid::Uuid>| {
+        let svc = services.ontology_service.clone();
+        let class_id = class.id;
+        async move {
+            svc.create_entity(
+                CreateEntityInput {
+                    class_id,
+                    display_name: name.to_string(),
+                    parent_entity_id: parent,
+                    attributes: None,
+                },
+                None,
+                None,
+            )
+            .await
+            .expect("Failed to create entity")
+        }
+    };
+
+    let root = create("Root", None).await;
+    let child = create("Child", Some(root.id)).await;
+    let grandchild = create("Grandchild", Some(child.id)).await;
+    let outsider = create("Outsider", None).await;
+
+    for target in [grandchild.id, outsider.id] {
+        services
+            .ontology_service
+            .create_relationship(
+                CreateRelationshipInput {
+                    source_entity_id: root.id,
+                    target_entity_id: target,
+                    relationship_type: "contains".to_string(),
+                    metadata: None,
+                },
+                None,
+            )
+            .await
+            .expect("Failed to create relationship");
+    }
+
+    let records: Vec<SubgraphRecord> = services
+        .ontology_service
+        .stream_subgraph(root.id)
+        .map(|r| r.expect("stream error"))
+        .collect()
+        .await;
+
+    let entity_ids: Vec<uuid::Uuid> = records
+        .iter()
+        .filter_map(|r| match r {
+            SubgraphRecord::Entity(e) => Some(e.id),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(entity_ids.len(), 3);
+    assert_eq!(entity_ids[0], root.id, "Root is emitted first");
+    assert!(!entity_ids.contains(&outsider.id));
+
+    // Only relationships fully inside the subgraph are exported, after all entities
+    let relationships: Vec<_> = records
+        .iter()
+        .filter(|r| matches!(r, SubgraphRecord::Relationship(_)))
+        .collect();
+    assert_eq!(relationships.len(), 1);
+    assert!(matches!(
+        records.last(),
+        Some(SubgraphRecord::Relationship(r)) if r.target_entity_id == grandchild.id
+    ));
+}
+
+#[sqlx::test]
+async fn test_entity_updated_at_bumped_on_any_update(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+
+    let class = services
+        .ontology_service
+        .create_class(
+            CreateClassInput {
+                name: "EtagNode".to_string(),
+                description: None,
+                parent_class_id: None,
+                is_abstract: Some(false),
+            },
+            None,
+        )
+        .await
+        .expect("Failed to create class");
+
+    let entity = services
+        .ontology_service
+        .create_entity(
+            CreateEntityInput {
+                class_id: class.id,
+                display_name: "Before".to_string(),
+                parent_entity_id: None,
+                attributes: None,
+            },
+            None,
+            None,
+        )
+        .await
+        .expect("Failed to create entity");
+
+    // Updates that don't set updated_at themselves must still invalidate the ETag
+    sqlx::query("UPDATE entities SET display_name = 'After' WHERE id = $1")
+        .bind(entity.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let updated = services.ontology_service.get_entity(entity.id).await.unwrap();
+    assert!(updated.updated_at > entity.updated_at);
+    assert_ne!(
+        template_repo_backend::utils::etag::entity_etag(entity.id, entity.updated_at),
+        template_repo_backend::utils::etag::entity_etag(updated.id, updated.updated_at)
+    );
+}
+
+#[sqlx::test]
+async fn test_apply_changeset_to_draft_version(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let ontology = &services.ontology_service;
+
+    let version = ontology
+        .create_version(
+            CreateVersionInput {
+                version: "changeset-test".to_string(),
+                description: None,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    let changes: SchemaChanges = serde_json::from_value(serde_json::json!({
+        "classes": [
+            {
+                "name": "Mission",
+                "properties": [
+                    { "name": "code", "data_type": "string", "is_required": true },
+                    { "name": "lead", "data_type": "reference", "reference_class": "Officer" }
+                ]
+            },
+            { "name": "Officer", "properties": [{ "name": "rank", "data_type": "string" }] },
+            { "name": "AirMission", "parent_class": "Mission" }
+        ],
+        "relationship_types": [
+            { "name": "changeset_test_led_by", "source_class": "Mission", "target_class": "Officer", "target_cardinality": "one" }
+        ]
+    }))
+    .unwrap();
+    let changeset = ontology
+        .create_changeset("ai", "Missions", None, changes, None)
+        .await
+        .unwrap();
+    assert_eq!(changeset.status, "draft");
+
+    let result = ontology
+        .apply_changeset(changeset.id, version.id, None)
+        .await
+        .expect("Failed to apply changeset");
+    assert_eq!(result.classes_created, 3);
+    assert_eq!(result.properties_created, 3);
+    assert_eq!(result.relationship_types_created, 1);
+    assert_eq!(result.changeset.status, "applied");
+    assert_eq!(result.changeset.applied_version_id, Some(version.id));
+
+    let (mission_id, officer_id): (uuid::Uuid, uuid::Uuid) = sqlx::query_as(
+        r#"
+        SELECT m.id, o.id FROM classes m, classes o
+        WHERE m.name = 'Mission' AND o.name = 'Officer'
+          AND m.version_id = $1 AND o.version_id = $1
+        "#,
+    )
+    .bind(version.id)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    let parent: Option<uuid::Uuid> = sqlx::query_scalar(
+        "SELECT parent_class_id FROM classes WHERE name = 'AirMission' AND version_id = $1",
+    )
+    .bind(version.id)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(parent, Some(mission_id));
+    let reference: Option<uuid::Uuid> = sqlx::query_scalar(
+        "SELECT reference_class_id FROM properties WHERE class_id = $1 AND name = 'lead'",
+    )
+    .bind(mission_id)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(reference, Some(officer_id));
+
+    // Applied changesets are final
+    assert!(ontology
+        .apply_changeset(changeset.id, version.id, None)
+        .await
+        .is_err());
+
+    // Published versions don't accept changesets
+    let other = ontology
+        .create_changeset(
+            "ai",
+            "More",
+            None,
+            serde_json::from_value(serde_json::json!({ "classes": [{ "name": "Unit" }] })).unwrap(),
+            None,
+        )
+        .await
+        .unwrap();
+    ontology.publish_version(version.id, None).await.unwrap();
+    assert!(ontology
+        .apply_changeset(other.id, version.id, None)
+        .await
+        .is_err());
+    assert_eq!(
+        ontology.get_changeset(other.id).await.unwrap().status,
+        "draft"
+    );
+}
+
+
+#[sqlx::test]
+async fn test_detect_and_merge_duplicate_entities(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let ontology = &services.ontology_service;
+
+    let class = ontology
+        .create_class(
+            CreateClassInput {
+                name: "DedupOrg".to_string(),
+                description: None,
+                parent_class_id: None,
+                is_abstract: Some(false),
+            },
+            None,
+        )
+        .await
+        .expect("Failed to create class");
+
+    let create = |name: &'static str, parent: Option<uuid::Uuid>, attributes: serde_json::Value| {
+        let svc = ontology.clone();
+        let class_id = class.id;
+        async move {
+            svc.create_entity(
+                CreateEntityInput {
+                    class_id,
+                    display_name: name.to_string(),
+                    parent_entity_id: parent,
+                    attributes: Some(attributes),
+                },
+                None,
+                None,
+            )
+            .await
+            .expect("Failed to create entity")
+        }
+    };
+    let original = create("Acme Corp", None, serde_json::json!({ "org_no": 42 })).await;
+    let duplicate = create(
+        "ACME Corp.",
+        None,
+        serde_json::json!({ "org_no": 42, "city": "Oslo" }),
+    )
+    .await;
+    let other = create("Globex Industries", None, serde_json::json!({ "org_no": 7 })).await;
+    let branch = create("Acme Bergen Branch", Some(duplicate.id), serde_json::json!({})).await;
+
+    for (source, target) in [(duplicate.id, other.id), (other.id, duplicate.id)] {
+        ontology
+            .create_relationship(
+                CreateRelationshipInput {
+                    source_entity_id: source,
+                    target_entity_id: target,
+                    relationship_type: "contains".to_string(),
+                    metadata: None,
+                },
+                None,
+            )
+            .await
+            .expect("Failed to create relationship");
+    }
+
+    let created = ontology.detect_duplicates(0.85).await.unwrap();
+    assert!(created >= 1);
+    // Pairs already suggested are not queued again
+    assert_eq!(ontology.detect_duplicates(0.85).await.unwrap(), 0);
+
+    let suggestions: Vec<_> = ontology
+        .list_merge_suggestions(Some("pending"))
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|s| s.class_id == class.id)
+        .collect();
+    assert_eq!(suggestions.len(), 1);
+    let suggestion = &suggestions[0];
+    assert_eq!(suggestion.primary_entity_id, original.id);
+    assert_eq!(suggestion.duplicate_entity_id, duplicate.id);
+
+    let result = ontology
+        .approve_merge_suggestion(suggestion.id, ApproveMergeInput::default(), None)
+        .await
+        .expect("Failed to merge");
+    assert_eq!(result.suggestion.status, "merged");
+    assert_eq!(result.relationships_moved, 2);
+    assert_eq!(result.children_moved, 1);
+
+    let kept = ontology.get_entity(original.id).await.unwrap();
+    assert_eq!(kept.attributes["org_no"], 42);
+    assert_eq!(kept.attributes["city"], "Oslo");
+    assert!(ontology.get_entity(duplicate.id).await.is_err());
+    assert_eq!(
+        ontology.get_entity(branch.id).await.unwrap().parent_entity_id,
+        Some(original.id)
+    );
+
+    let moved: i64 = sqlx::query_scalar(
+        r#"
+        SELECT COUNT(*) FROM relationships
+        WHERE (source_entity_id = $1 AND target_entity_id = $2)
+           OR (source_entity_id = $2 AND target_entity_id = $1)
+        "#,
+    )
+    .bind(original.id)
+    .bind(other.id)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(moved, 2);
+
+    // Reviewed suggestions can't be reviewed again
+    assert!(ontology
+        .reject_merge_suggestion(suggestion.id, None)
+        .await
+        .is_err());
+}
+
+#[sqlx::test]
+async fn test_import_pending_graph(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let ontology = &services.ontology_service;
+
+    let class = ontology
+        .create_class(
+            CreateClassInput {
+                name: "ImportedUnit".to_string(),
+                description: None,
+                parent_class_id: None,
+                is_abstract: Some(false),
+            },
+            None,
+        )
+        .await
+        .expect("Failed to create class");
+    let parent = ontology
+        .create_entity(
+            CreateEntityInput {
+                class_id: class.id,
+                display_name: "Brigade".to_string(),
+                parent_entity_id: None,
+                attributes: None,
+            },
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+    let entity = |name: &str, parent_entity_id| CreateEntityInput {
+        class_id: class.id,
+        display_name: name.to_string(),
+        parent_entity_id,
+        attributes: None,
+    };
+    let link = |source, target| ImportRelationshipInput {
+        source,
+        target,
+        relationship_type: "contains".to_string(),
+    };
+    let imported = ontology
+        .import_pending_graph(
+            GraphImport {
+                entities: vec![entity("Battalion", Some(parent.id)), entity("Company", None)],
+                relationships: vec![link(0, 1), link(0, 1)],
+            },
+            None,
+        )
+        .await
+        .expect("Failed to import graph");
+    assert_eq!(imported.entities.len(), 2);
+    // Imported children are still reviewed, unlike children created one by one
+    assert!(imported
+        .entities
+        .iter()
+        .all(|e| e.approval_status == ApprovalStatus::PENDING));
+    assert_eq!(imported.relationships.len(), 1);
+
+    // Nothing is created when part of the import is invalid
+    let before: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM entities WHERE class_id = $1")
+        .bind(class.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    let result = ontology
+        .import_pending_graph(
+            GraphImport {
+                entities: vec![entity("Platoon", None)],
+                relationships: vec![ImportRelationshipInput {
+                    source: 0,
+                    target: 0,
+                    relationship_type: "no_such_type".to_string(),
+                }],
+            },
+            None,
+        )
+        .await;
+    assert!(result.is_err());
+    let after: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM entities WHERE class_id = $1")
+        .bind(class.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(before, after);
+}
+
+#[sqlx::test]
+async fn test_published_schema_catalogue(pool: PgPool) {
+    use template_repo_backend::utils::etag;
+
+    let services = common::setup_services(pool.clone()).await;
+    let ontology = &services.ontology_service;
+
+    let version = ontology
+        .create_version(
+            CreateVersionInput {
+                version: "schema-catalogue-test".to_string(),
+                description: None,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    let changes: SchemaChanges = serde_json::from_value(serde_json::json!({
+        "classes": [
+            {
+                "name": "Mission",
+                "properties": [
+                    { "name": "code", "data_type": "string", "is_required": true },
+                    { "name": "lead", "data_type": "reference", "reference_class": "Officer" }
+                ]
+            },
+            { "name": "Officer" }
+        ],
+        "relationship_types": [
+            { "name": "schema_test_led_by", "source_class": "Mission", "target_class": "Officer" }
+        ]
+    }))
+    .unwrap();
+    let changeset = ontology
+        .create_changeset("ai", "Missions", None, changes, None)
+        .await
+        .unwrap();
+    ontology
+        .apply_changeset(changeset.id, version.id, None)
+        .await
+        .unwrap();
+
+    // Draft classes stay out until the version is published
+    let before = ontology.published_schema().await.unwrap();
+    assert!(before.classes.iter().all(|c| c.name != "Mission"));
+    let user = before.classes.iter().find(|c| c.name == "User").unwrap();
+    assert!(user.is_system);
+    assert!(user.properties.iter().any(|p| p.name == "email"));
+    assert!(before.permission_types.iter().any(|p| p.name == "read"));
+
+    ontology.publish_version(version.id, None).await.unwrap();
+    let after = ontology.published_schema().await.unwrap();
+    assert_eq!(after.version.as_deref(), Some("schema-catalogue-test"));
+
+    let mission = after.classes.iter().find(|c| c.name == "Mission").unwrap();
+    assert!(!mission.is_system);
+    let lead = mission.properties.iter().find(|p| p.name == "lead").unwrap();
+    assert_eq!(lead.reference_class_name.as_deref(), Some("Officer"));
+    assert!(mission.properties.iter().any(|p| p.name == "code" && p.is_required));
+
+    let led_by = after
+        .relationship_types
+        .iter()
+        .find(|t| t.name == "schema_test_led_by")
+        .unwrap();
+    assert_eq!(led_by.allowed_source_class_name.as_deref(), Some("Mission"));
+    assert_eq!(led_by.allowed_target_class_name.as_deref(), Some("Officer"));
+
+    // The ETag is stable for an unchanged schema and moves when it changes
+    let again = ontology.published_schema().await.unwrap();
+    assert_eq!(etag::payload_etag(&after), etag::payload_etag(&again));
+    assert_ne!(etag::payload_etag(&before), etag::payload_etag(&after));
+}
@@ -0,0 +1,69 @@
+use sqlx::PgPool;
+use template_repo_backend::features::ontology::models::UpdateEntityInput;
+use template_repo_backend::perf::{Dataset, DatasetSize};
+
+mod common;
+
+#[sqlx::test]
+async fn test_dataset_is_seeded_once_with_scoped_roles(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let size = DatasetSize {
+        users: 4,
+        folders: 3,
+        assets_per_folder: 2,
+    };
+    let dataset = Dataset::ensure(&pool, &size).await.unwrap();
+    assert_eq!(dataset.users.len(), 4);
+    assert_eq!(dataset.folders.len(), 3);
+    assert_eq!(dataset.assets.len(), 6);
+
+    // A second run finds it instead of seeding again
+    let again = Dataset::ensure(&pool, &size).await.unwrap();
+    assert_eq!(again.assets, dataset.assets);
+    assert_eq!(
+        again.users.iter().map(|u| u.id).collect::<Vec<_>>(),
+        dataset.users.iter().map(|u| u.id).collect::<Vec<_>>()
+    );
+
+    let rebac = &services.rebac_service;
+    for (n, user) in dataset.users.iter().enumerate() {
+        let own = dataset.assets_of(n)[0];
+        let other = dataset.assets_of(n + 1)[0];
+        assert!(rebac
+            .has_permission(user.id, own, "perf.read", None)
+            .await
+            .unwrap());
+        assert_eq!(
+            rebac
+                .has_permission(user.id, own, "perf.update", None)
+                .await
+                .unwrap(),
+            dataset.role_of(n) == "perf_editor"
+        );
+        assert!(!rebac
+            .has_permission(user.id, other, "perf.read", None)
+            .await
+            .unwrap());
+    }
+
+    // Seeded assets pass validation against their class
+    let asset = services
+        .ontology_service
+        .get_entity(dataset.assets[0])
+        .await
+        .unwrap();
+    assert_eq!(asset.parent_entity_id, Some(dataset.scope_of(0)));
+    services
+        .ontology_service
+        .update_entity(
+            asset.id,
+            UpdateEntityInput {
+                display_name: None,
+                parent_entity_id: None,
+                attributes: Some(asset.attributes.clone()),
+            },
+            None,
+        )
+        .await
+        .unwrap();
+}
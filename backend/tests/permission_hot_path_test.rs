@@ -0,0 +1,306 @@
+use sqlx::PgPool;
+use template_repo_backend::config::PermissionsConfig;
+use template_repo_backend::features::ontology::models::{CreateClassInput, CreateEntityInput};
+use template_repo_backend::features::rebac::models::{
+    AssignScopedRoleInput, CreatePermissionTypeInput, PermissionCheckResult,
+    UpdatePermissionTypeInput,
+};
+use template_repo_backend::features::rebac::RebacService;
+use uuid::Uuid;
+
+mod common;
+
+type Answer = (
+    bool,
+    Option<Uuid>,
+    Option<String>,
+    Option<bool>,
+    Option<bool>,
+);
+
+fn answer(result: PermissionCheckResult) -> Answer {
+    (
+        result.has_permission,
+        result.granted_via_entity_id,
+        result.granted_via_role,
+        result.is_inherited,
+        result.is_denied,
+    )
+}
+
+/// Both check paths, each with fresh caches so every call reaches the database
+fn checkers(rebac: &RebacService) -> (RebacService, RebacService) {
+    let joined = rebac.clone().with_permissions_config(&PermissionsConfig {
+        check_cache_capacity: 0,
+        ..Default::default()
+    });
+    let denormalized = rebac.clone().with_permissions_config(&PermissionsConfig {
+        denormalized_grants: true,
+        check_cache_capacity: 0,
+        ..Default::default()
+    });
+    (joined, denormalized)
+}
+
+async fn check(
+    pool: &PgPool,
+    rebac: &RebacService,
+    user_id: Uuid,
+    entity_id: Uuid,
+    permission: &str,
+) -> Answer {
+    let (joined, denormalized) = checkers(rebac);
+    let expected = answer(
+        joined
+            .check_permission(user_id, entity_id, permission, None, None)
+            .await
+            .unwrap(),
+    );
+    let from_grants = answer(
+        denormalized
+            .check_permission(user_id, entity_id, permission, None, None)
+            .await
+            .unwrap(),
+    );
+    assert_eq!(expected, from_grants, "{} on {}", permission, entity_id);
+
+    // SQL callers of the original function get the same answer
+    let legacy: Answer = sqlx::query_as(
+        "SELECT has_permission, granted_via_entity_id, granted_via_role, is_inherited, is_denied FROM check_entity_permission($1, $2, $3)",
+    )
+    .bind(user_id)
+    .bind(entity_id)
+    .bind(permission)
+    .fetch_one(pool)
+    .await
+    .unwrap();
+    assert_eq!(expected, legacy, "{} on {}", permission, entity_id);
+    expected
+}
+
+async fn allowed(
+    pool: &PgPool,
+    rebac: &RebacService,
+    user_id: Uuid,
+    entity_id: Uuid,
+    permission: &str,
+) -> bool {
+    check(pool, rebac, user_id, entity_id, permission).await.0
+}
+
+async fn grant_rows(pool: &PgPool, user_id: Uuid) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM permission_grants WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+#[sqlx::test]
+async fn test_check_paths_agree(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let ontology = &services.ontology_service;
+    let rebac = &services.rebac_service;
+
+    let user_id = Uuid::new_v4();
+    let user_class = ontology.get_system_class("User").await.unwrap();
+    sqlx::query("INSERT INTO entities (id, class_id, display_name, attributes, approval_status) VALUES ($1, $2, 'hot_path_user', '{}', 'APPROVED')")
+        .bind(user_id)
+        .bind(user_class.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    for (name, level) in [("hp_read", 10), ("hp_write", 30), ("hp_purge", 90)] {
+        rebac
+            .create_permission_type(CreatePermissionTypeInput {
+                name: name.to_string(),
+                description: None,
+                level,
+            })
+            .await
+            .unwrap();
+    }
+    let role_class = ontology.get_system_class("Role").await.unwrap();
+    let mut roles = Vec::new();
+    for (name, attributes) in [
+        (
+            "HotPathWriter",
+            serde_json::json!({ "name": "HotPathWriter", "level": 0 }),
+        ),
+        (
+            "HotPathExporter",
+            serde_json::json!({ "name": "HotPathExporter", "level": 0, "permissions": ["hp_export"] }),
+        ),
+        (
+            "HotPathFrozen",
+            serde_json::json!({ "name": "HotPathFrozen", "level": 0 }),
+        ),
+    ] {
+        let role = ontology
+            .create_entity(
+                CreateEntityInput {
+                    class_id: role_class.id,
+                    display_name: name.to_string(),
+                    parent_entity_id: None,
+                    attributes: Some(attributes),
+                },
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        roles.push(role);
+    }
+    for role in [&roles[0], &roles[2]] {
+        rebac
+            .add_permission_to_role(role.id, "hp_write", None)
+            .await
+            .unwrap();
+    }
+
+    let class = ontology
+        .create_class(
+            CreateClassInput {
+                name: "HotPathFolder".to_string(),
+                description: None,
+                parent_class_id: None,
+                is_abstract: Some(false),
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    let mut folders = Vec::new();
+    for (name, parent) in [("Root", None), ("Shared", None), ("Reports", Some(0))] {
+        let folder = ontology
+            .create_entity(
+                CreateEntityInput {
+                    class_id: class.id,
+                    display_name: name.to_string(),
+                    parent_entity_id: parent.map(|i: usize| folders[i]),
+                    attributes: None,
+                },
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        folders.push(folder.id);
+    }
+    let (root, shared, reports) = (folders[0], folders[1], folders[2]);
+
+    assert!(!allowed(&pool, rebac, user_id, reports, "hp_read").await);
+    assert_eq!(grant_rows(&pool, user_id).await, 0);
+
+    // Scoped to the root folder: inherited by its children, and a higher
+    // level grant covers the lower ones
+    rebac
+        .assign_scoped_role(
+            AssignScopedRoleInput {
+                user_id,
+                role_name: "HotPathWriter".to_string(),
+                scope_entity_id: Some(root),
+                valid_from: None,
+                valid_until: None,
+                schedule_cron: None,
+                is_deny: None,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(grant_rows(&pool, user_id).await, 1);
+    let read = check(&pool, rebac, user_id, reports, "hp_read").await;
+    assert_eq!(
+        read,
+        (
+            true,
+            Some(root),
+            Some("HotPathWriter".to_string()),
+            Some(true),
+            Some(false)
+        )
+    );
+    assert!(!allowed(&pool, rebac, user_id, reports, "hp_purge").await);
+    assert!(!allowed(&pool, rebac, user_id, shared, "hp_read").await);
+
+    // Permissions named in a role's attributes match by name only
+    rebac
+        .assign_scoped_role(
+            AssignScopedRoleInput {
+                user_id,
+                role_name: "HotPathExporter".to_string(),
+                scope_entity_id: None,
+                valid_from: None,
+                valid_until: None,
+                schedule_cron: None,
+                is_deny: None,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    assert!(allowed(&pool, rebac, user_id, shared, "hp_export").await);
+    assert!(!allowed(&pool, rebac, user_id, shared, "hp_read").await);
+
+    // Raising a level is picked up by both paths
+    rebac
+        .update_permission_type(
+            permission_id(&pool, "hp_read").await,
+            UpdatePermissionTypeInput {
+                description: None,
+                level: Some(50),
+            },
+        )
+        .await
+        .unwrap();
+    assert!(!allowed(&pool, rebac, user_id, reports, "hp_read").await);
+
+    // A deny closer to the entity wins
+    rebac
+        .assign_scoped_role(
+            AssignScopedRoleInput {
+                user_id,
+                role_name: "HotPathFrozen".to_string(),
+                scope_entity_id: Some(reports),
+                valid_from: None,
+                valid_until: None,
+                schedule_cron: None,
+                is_deny: Some(true),
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    let denied = check(&pool, rebac, user_id, reports, "hp_write").await;
+    assert!(!denied.0);
+    assert_eq!(denied.4, Some(true));
+    assert!(allowed(&pool, rebac, user_id, root, "hp_write").await);
+
+    // Taking the permission off a role, or the roles off the user, updates
+    // the grants table
+    assert_eq!(grant_rows(&pool, user_id).await, 3);
+    rebac
+        .remove_permission_from_role(roles[0].id, "hp_write")
+        .await
+        .unwrap();
+    assert!(!allowed(&pool, rebac, user_id, root, "hp_write").await);
+    sqlx::query("DELETE FROM relationships WHERE source_entity_id = $1")
+        .bind(user_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert_eq!(grant_rows(&pool, user_id).await, 0);
+    assert!(!allowed(&pool, rebac, user_id, shared, "hp_export").await);
+}
+
+async fn permission_id(pool: &PgPool, name: &str) -> Uuid {
+    sqlx::query_scalar(
+        "SELECT id FROM entities WHERE display_name = $1 AND class_id = (SELECT id FROM classes WHERE name = 'Permission')",
+    )
+    .bind(name)
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
@@ -155,6 +155,7 @@ async fn test_policy_engine_integration_flow(pool: PgPool) {
         is_active: Some(true),
         valid_from: None,
         valid_until: None,
+        schedule_cron: None,
     };
 
     let policy = services
@@ -232,6 +233,7 @@ async fn test_policy_engine_integration_flow(pool: PgPool) {
                 is_active: Some(false),
                 valid_from: None,
                 valid_until: None,
+                schedule_cron: None,
             },
             Some(user_id),
         )
@@ -340,6 +342,7 @@ async fn test_policy_dynamic_keyword_access(pool: PgPool) {
                 is_active: Some(true),
                 valid_from: None,
                 valid_until: None,
+                schedule_cron: None,
             },
             Some(user_id),
         )
@@ -0,0 +1,310 @@
+use axum::{extract::State, http::HeaderMap, http::StatusCode, routing::post, Router};
+use ring::agreement;
+use ring::rand::SystemRandom;
+use sqlx::PgPool;
+use std::sync::{Arc, Mutex};
+use template_repo_backend::config::WebPushConfig;
+use template_repo_backend::features::auth::models::RegisterUser;
+use template_repo_backend::features::notifications::models::{
+    NotificationCategory, PushPayload, PushSubscriptionKeys, SubscribeInput,
+};
+use template_repo_backend::features::notifications::webpush::VapidKeys;
+use template_repo_backend::features::notifications::{
+    NotificationError, NotificationService, WebPush,
+};
+use template_repo_backend::utils::http_client::OutboundClient;
+use uuid::Uuid;
+
+mod common;
+
+const PUBLIC_KEY: &str =
+    "BHwnUGOalhc4FPYxrY8RA0U-neolNJOe64T_to8WimDQNCMAhNQh2UBaBMrEcAMYYWZPHbfD6hpPtVvK2-MdcqQ";
+const PRIVATE_KEY: &str = "1Lq140CmM32WA2WGrp2TKGbR2tTL2myQtUfSh-OAo64";
+
+/// Requests a mock push service received, and the status it answers with
+#[derive(Clone, Default)]
+struct PushServer {
+    received: Arc<Mutex<Vec<HeaderMap>>>,
+    status: Arc<Mutex<u16>>,
+}
+
+async fn receive(State(server): State<PushServer>, headers: HeaderMap) -> StatusCode {
+    server.received.lock().unwrap().push(headers);
+    StatusCode::from_u16(*server.status.lock().unwrap()).unwrap()
+}
+
+/// Starts a push service on localhost and returns it with its base URL
+async fn start_push_server() -> (PushServer, String) {
+    let server = PushServer {
+        status: Arc::new(Mutex::new(201)),
+        ..Default::default()
+    };
+    let app = Router::new()
+        .route("/push/:id", post(receive))
+        .with_state(server.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    (server, format!("http://{}", addr))
+}
+
+fn web_push(config: WebPushConfig) -> WebPush {
+    WebPush::new(
+        config,
+        VapidKeys::from_base64(PUBLIC_KEY, PRIVATE_KEY).unwrap(),
+        OutboundClient::from_config(&Default::default()),
+    )
+}
+
+/// Accepts plain-HTTP endpoints on any host, for the local push service
+fn local_config() -> WebPushConfig {
+    WebPushConfig {
+        allowed_hosts: vec![],
+        allow_insecure_endpoints: true,
+        max_subscriptions_per_user: 2,
+        max_failures: 2,
+        ..Default::default()
+    }
+}
+
+/// A subscription as a browser would create it
+fn subscription(endpoint: &str) -> SubscribeInput {
+    let key = agreement::EphemeralPrivateKey::generate(&agreement::ECDH_P256, &SystemRandom::new())
+        .unwrap();
+    let public = key.compute_public_key().unwrap();
+    SubscribeInput {
+        endpoint: endpoint.to_string(),
+        keys: PushSubscriptionKeys {
+            p256dh: base64::encode_config(public.as_ref(), base64::URL_SAFE_NO_PAD),
+            auth: base64::encode_config([9u8; 16], base64::URL_SAFE_NO_PAD),
+        },
+    }
+}
+
+fn payload(category: NotificationCategory) -> PushPayload {
+    PushPayload {
+        id: Uuid::new_v4(),
+        category,
+        message: "Your password was changed.".to_string(),
+        created_at: chrono::Utc::now(),
+    }
+}
+
+#[sqlx::test]
+async fn test_push_subscriptions_are_validated_and_limited(pool: PgPool) {
+    let user = Uuid::new_v4();
+
+    // Without VAPID keys push is off
+    let disabled = NotificationService::new(pool.clone());
+    assert!(!disabled.push_config().enabled);
+    assert!(matches!(
+        disabled
+            .subscribe_push(
+                user,
+                subscription("https://fcm.googleapis.com/fcm/send/a"),
+                None
+            )
+            .await,
+        Err(NotificationError::InvalidInput(_))
+    ));
+
+    // By default only https endpoints of the known push services are accepted
+    let notifications = NotificationService::new(pool.clone()).with_push(web_push(WebPushConfig {
+        max_subscriptions_per_user: 2,
+        ..Default::default()
+    }));
+    let config = notifications.push_config();
+    assert_eq!(config.public_key.as_deref(), Some(PUBLIC_KEY));
+    for endpoint in [
+        "http://fcm.googleapis.com/fcm/send/a",
+        "https://169.254.169.254/latest/meta-data",
+        "https://fcm.googleapis.com.attacker.example/x",
+        "not a url",
+    ] {
+        assert!(
+            matches!(
+                notifications
+                    .subscribe_push(user, subscription(endpoint), None)
+                    .await,
+                Err(NotificationError::InvalidInput(_))
+            ),
+            "{}",
+            endpoint
+        );
+    }
+    let mut bad_keys = subscription("https://fcm.googleapis.com/fcm/send/a");
+    bad_keys.keys.auth = "c2hvcnQ".to_string();
+    assert!(matches!(
+        notifications.subscribe_push(user, bad_keys, None).await,
+        Err(NotificationError::InvalidInput(_))
+    ));
+
+    for endpoint in [
+        "https://fcm.googleapis.com/fcm/send/a",
+        "https://updates.push.services.mozilla.com/wpush/v2/b",
+        "https://web.push.apple.com/c",
+    ] {
+        notifications
+            .subscribe_push(user, subscription(endpoint), Some("Firefox".to_string()))
+            .await
+            .unwrap();
+    }
+    // Resubscribing the same browser doesn't add another subscription
+    notifications
+        .subscribe_push(user, subscription("https://web.push.apple.com/c"), None)
+        .await
+        .unwrap();
+
+    // Only the newest two are kept
+    let endpoints: Vec<String> = notifications
+        .list_push_subscriptions(user)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|s| s.endpoint)
+        .collect();
+    assert_eq!(endpoints.len(), 2);
+    assert!(!endpoints.contains(&"https://fcm.googleapis.com/fcm/send/a".to_string()));
+
+    notifications
+        .unsubscribe_push(user, "https://web.push.apple.com/c")
+        .await
+        .unwrap();
+    assert!(matches!(
+        notifications
+            .unsubscribe_push(user, "https://web.push.apple.com/c")
+            .await,
+        Err(NotificationError::NotFound(_))
+    ));
+    assert_eq!(
+        notifications
+            .list_push_subscriptions(user)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[sqlx::test]
+async fn test_push_delivery_drops_dead_subscriptions(pool: PgPool) {
+    let (server, base) = start_push_server().await;
+    let notifications = NotificationService::new(pool.clone()).with_push(web_push(local_config()));
+    let user = Uuid::new_v4();
+    notifications
+        .subscribe_push(user, subscription(&format!("{}/push/1", base)), None)
+        .await
+        .unwrap();
+
+    let sent = notifications
+        .send_push(user, &payload(NotificationCategory::Security))
+        .await
+        .unwrap();
+    assert_eq!(sent, 1);
+    {
+        let received = server.received.lock().unwrap();
+        let headers = &received[0];
+        assert_eq!(headers["content-encoding"], "aes128gcm");
+        assert_eq!(headers["urgency"], "high");
+        assert_eq!(headers["ttl"], "86400");
+        let authorization = headers["authorization"].to_str().unwrap();
+        assert!(authorization.starts_with("vapid t="));
+        assert!(authorization.ends_with(&format!("k={}", PUBLIC_KEY)));
+    }
+    let stored = notifications.list_push_subscriptions(user).await.unwrap();
+    assert!(stored[0].last_success_at.is_some());
+
+    // Failures are recorded, and the subscription goes after max_failures
+    *server.status.lock().unwrap() = 500;
+    assert_eq!(
+        notifications
+            .send_push(user, &payload(NotificationCategory::Account))
+            .await
+            .unwrap(),
+        0
+    );
+    let stored = notifications.list_push_subscriptions(user).await.unwrap();
+    assert_eq!(stored[0].failure_count, 1);
+    assert!(stored[0].last_error.as_deref().unwrap().contains("500"));
+    notifications
+        .send_push(user, &payload(NotificationCategory::Account))
+        .await
+        .unwrap();
+    assert!(notifications
+        .list_push_subscriptions(user)
+        .await
+        .unwrap()
+        .is_empty());
+
+    // A subscription the push service no longer knows is removed at once
+    notifications
+        .subscribe_push(user, subscription(&format!("{}/push/2", base)), None)
+        .await
+        .unwrap();
+    *server.status.lock().unwrap() = 410;
+    notifications
+        .send_push(user, &payload(NotificationCategory::Security))
+        .await
+        .unwrap();
+    assert!(notifications
+        .list_push_subscriptions(user)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[sqlx::test]
+async fn test_high_priority_notifications_are_pushed(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let (server, base) = start_push_server().await;
+    let notifications = services
+        .notification_service
+        .clone()
+        .with_push(web_push(local_config()));
+    let user = services
+        .auth_service
+        .register(RegisterUser {
+            username: "push_user".to_string(),
+            email: "push_user@example.com".to_string(),
+            password: "Password123!".to_string(),
+        })
+        .await
+        .expect("Registration failed")
+        .user_id;
+    notifications
+        .subscribe_push(user, subscription(&format!("{}/push/1", base)), None)
+        .await
+        .unwrap();
+
+    // Low-priority notifications aren't pushed by default
+    notifications
+        .notify(
+            user,
+            NotificationCategory::Account,
+            "Your profile was updated.",
+        )
+        .await
+        .unwrap();
+    notifications
+        .notify(
+            user,
+            NotificationCategory::Security,
+            "Your password was changed.",
+        )
+        .await
+        .unwrap();
+
+    // Pushing happens in the background
+    for _ in 0..50 {
+        if !server.received.lock().unwrap().is_empty() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    let received = server.received.lock().unwrap();
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0]["urgency"], "high");
+}
@@ -1520,3 +1520,289 @@ async fn test_rebac_get_user_entity_permissions(pool: PgPool) {
     // Should have some permissions (read at minimum if role was granted)
     assert!(!entity_perms.is_empty(), "User should have some permissions on the entity");
 }
+
+#[sqlx::test]
+async fn test_rebac_breadcrumbs_hide_unreadable_ancestors(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let ontology = &services.ontology_service;
+    let user_id = Uuid::new_v4();
+
+    let user_class = ontology.get_system_class("User").await.unwrap();
+    sqlx::query("INSERT INTO entities (id, class_id, display_name, attributes, approval_status, created_by, updated_by) VALUES ($1, $2, $3, $4, 'APPROVED', $5, $5)")
+        .bind(user_id).bind(user_class.id).bind("crumbs").bind(serde_json::json!({"user_id": user_id, "username": "crumbs", "email": "crumbs@e.com"}))
+        .bind(user_id).execute(&pool).await.unwrap();
+
+    let folder_class = ontology
+        .create_class(
+            CreateClassInput {
+                name: "Folder".into(),
+                description: None,
+                parent_class_id: None,
+                is_abstract: Some(false),
+            },
+            Some(user_id),
+        )
+        .await
+        .unwrap();
+    let mut parent_entity_id = None;
+    let mut chain = Vec::new();
+    for name in ["Root", "Team", "Report"] {
+        let entity = ontology
+            .create_entity(
+                CreateEntityInput {
+                    class_id: folder_class.id,
+                    display_name: name.into(),
+                    parent_entity_id,
+                    attributes: None,
+                },
+                Some(user_id),
+                None,
+            )
+            .await
+            .unwrap();
+        parent_entity_id = Some(entity.id);
+        chain.push(entity);
+    }
+    let (root, team, report) = (&chain[0], &chain[1], &chain[2]);
+
+    // "read" granted on Team, inherited by Report but not by Root
+    let role_class = ontology.get_system_class("Role").await.unwrap();
+    let perm_class = ontology.get_system_class("Permission").await.unwrap();
+    let role = ontology
+        .create_entity(
+            CreateEntityInput {
+                class_id: role_class.id,
+                display_name: "CrumbViewer".into(),
+                parent_entity_id: None,
+                attributes: Some(serde_json::json!({"name": "CrumbViewer", "level": 10})),
+            },
+            Some(user_id),
+            None,
+        )
+        .await
+        .unwrap();
+    let perm = ontology
+        .create_entity(
+            CreateEntityInput {
+                class_id: perm_class.id,
+                display_name: "read".into(),
+                parent_entity_id: None,
+                attributes: Some(serde_json::json!({"name": "read", "level": 1})),
+            },
+            Some(user_id),
+            None,
+        )
+        .await
+        .unwrap();
+    for (source, target, relationship_type, metadata) in [
+        (role.id, perm.id, "grants_permission", serde_json::json!({"effect": "ALLOW"})),
+        (user_id, role.id, "has_role", serde_json::json!({"scope_entity_id": team.id.to_string()})),
+    ] {
+        ontology
+            .create_relationship(
+                CreateRelationshipInput {
+                    source_entity_id: source,
+                    target_entity_id: target,
+                    relationship_type: relationship_type.into(),
+                    metadata: Some(metadata),
+                },
+                Some(user_id),
+            )
+            .await
+            .unwrap();
+    }
+
+    let crumbs = services
+        .rebac_service
+        .breadcrumbs(user_id, report.id, None)
+        .await
+        .expect("breadcrumbs");
+    assert_eq!(crumbs.hidden, 1);
+    let depths: Vec<i32> = crumbs.items.iter().map(|b| b.depth).collect();
+    assert_eq!(depths, vec![2, 1, 0]);
+    assert!(!crumbs.items[0].can_view);
+    assert!(crumbs.items[0].id.is_none() && crumbs.items[0].name.is_none());
+    assert_eq!(crumbs.items[1].name.as_deref(), Some("Team"));
+    assert_eq!(crumbs.items[2].id, Some(report.id));
+    assert_eq!(crumbs.items[2].class_name.as_deref(), Some("Folder"));
+
+    // An entity the user can't read is reported as missing
+    let err = services
+        .rebac_service
+        .breadcrumbs(user_id, root.id, None)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        template_repo_backend::features::rebac::RebacError::NotFound(_)
+    ));
+}
+
+#[sqlx::test]
+async fn test_discovery_sitemap_pages_readable_entities(pool: PgPool) {
+    use template_repo_backend::features::discovery::models::SitemapQuery;
+    use template_repo_backend::features::discovery::DiscoveryService;
+    use template_repo_backend::features::ontology::models::UpdateEntityInput;
+
+    let services = common::setup_services(pool.clone()).await;
+    let ontology = &services.ontology_service;
+    let discovery = DiscoveryService::new(ontology.clone(), services.rebac_service.clone());
+    let user_id = Uuid::new_v4();
+
+    let user_class = ontology.get_system_class("User").await.unwrap();
+    sqlx::query("INSERT INTO entities (id, class_id, display_name, attributes, approval_status, created_by, updated_by) VALUES ($1, $2, $3, $4, 'APPROVED', $5, $5)")
+        .bind(user_id).bind(user_class.id).bind("indexer").bind(serde_json::json!({"user_id": user_id, "username": "indexer", "email": "indexer@e.com"}))
+        .bind(user_id).execute(&pool).await.unwrap();
+
+    let folder_class = ontology
+        .create_class(
+            CreateClassInput {
+                name: "SitemapFolder".into(),
+                description: None,
+                parent_class_id: None,
+                is_abstract: Some(false),
+            },
+            Some(user_id),
+        )
+        .await
+        .unwrap();
+    let folder = |name: &str, parent_entity_id| CreateEntityInput {
+        class_id: folder_class.id,
+        display_name: name.into(),
+        parent_entity_id,
+        attributes: None,
+    };
+    let root = ontology
+        .create_entity(folder("Root", None), Some(user_id), None)
+        .await
+        .unwrap();
+    let team = ontology
+        .create_entity(folder("Team", Some(root.id)), Some(user_id), None)
+        .await
+        .unwrap();
+    let mut docs = Vec::new();
+    for name in ["A", "B", "C"] {
+        docs.push(
+            ontology
+                .create_entity(folder(name, Some(team.id)), Some(user_id), None)
+                .await
+                .unwrap(),
+        );
+    }
+
+    // "read" on Team and its children, not on Root
+    let role_class = ontology.get_system_class("Role").await.unwrap();
+    let perm_class = ontology.get_system_class("Permission").await.unwrap();
+    let role = ontology
+        .create_entity(
+            CreateEntityInput {
+                class_id: role_class.id,
+                display_name: "SitemapViewer".into(),
+                parent_entity_id: None,
+                attributes: Some(serde_json::json!({"name": "SitemapViewer", "level": 10})),
+            },
+            Some(user_id),
+            None,
+        )
+        .await
+        .unwrap();
+    let perm = ontology
+        .create_entity(
+            CreateEntityInput {
+                class_id: perm_class.id,
+                display_name: "read".into(),
+                parent_entity_id: None,
+                attributes: Some(serde_json::json!({"name": "read", "level": 1})),
+            },
+            Some(user_id),
+            None,
+        )
+        .await
+        .unwrap();
+    for (source, target, relationship_type, metadata) in [
+        (role.id, perm.id, "grants_permission", serde_json::json!({"effect": "ALLOW"})),
+        (user_id, role.id, "has_role", serde_json::json!({"scope_entity_id": team.id.to_string()})),
+    ] {
+        ontology
+            .create_relationship(
+                CreateRelationshipInput {
+                    source_entity_id: source,
+                    target_entity_id: target,
+                    relationship_type: relationship_type.into(),
+                    metadata: Some(metadata),
+                },
+                Some(user_id),
+            )
+            .await
+            .unwrap();
+    }
+
+    let names = |page: &template_repo_backend::features::discovery::models::SitemapPage| {
+        page.items
+            .iter()
+            .map(|e| e.display_name.clone())
+            .collect::<Vec<_>>()
+    };
+    let query = |cursor: Option<String>| SitemapQuery {
+        cursor,
+        changed_since: None,
+        limit: Some(2),
+    };
+
+    // Root is skipped without leaving a gap in the page
+    let first = discovery.sitemap(user_id, &query(None)).await.unwrap();
+    assert_eq!(names(&first), vec!["Team", "A"]);
+    assert_eq!(first.items[0].class_name, "SitemapFolder");
+    assert!(first.has_more);
+
+    let second = discovery
+        .sitemap(user_id, &query(first.next_cursor.clone()))
+        .await
+        .unwrap();
+    assert_eq!(names(&second), vec!["B", "C"]);
+    assert!(!second.has_more);
+
+    // Polling from the last cursor only returns what changed since
+    let caught_up = discovery
+        .sitemap(user_id, &query(second.next_cursor.clone()))
+        .await
+        .unwrap();
+    assert!(caught_up.items.is_empty());
+    assert_eq!(caught_up.next_cursor, second.next_cursor);
+
+    ontology
+        .update_entity(
+            docs[0].id,
+            UpdateEntityInput {
+                display_name: Some("A (renamed)".into()),
+                parent_entity_id: None,
+                attributes: None,
+            },
+            Some(user_id),
+        )
+        .await
+        .unwrap();
+    let changed = discovery
+        .sitemap(user_id, &query(second.next_cursor.clone()))
+        .await
+        .unwrap();
+    assert_eq!(names(&changed), vec!["A (renamed)"]);
+
+    let since = discovery
+        .sitemap(
+            user_id,
+            &SitemapQuery {
+                cursor: None,
+                changed_since: Some(docs[2].updated_at),
+                limit: None,
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(names(&since), vec!["C", "A (renamed)"]);
+
+    assert!(discovery
+        .sitemap(user_id, &query(Some("garbage".into())))
+        .await
+        .is_err());
+}
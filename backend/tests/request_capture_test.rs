@@ -0,0 +1,318 @@
+use axum::{
+    body::Body,
+    http::Request,
+    routing::{get, post},
+    Json, Router,
+};
+use sqlx::PgPool;
+use template_repo_backend::features::request_capture::{
+    middleware::capture_middleware,
+    models::{CreateCaptureRuleRequest, NewCapture},
+    service::{
+        sanitize_body, sanitize_headers, sanitize_request_body, CaptureError, MAX_STORED_BODY_BYTES,
+    },
+    RequestCaptureService,
+};
+use tower::ServiceExt;
+
+mod common;
+
+#[test]
+fn test_sanitize_strips_credentials() {
+    let headers = [
+        ("Authorization", "Bearer abc".as_bytes()),
+        ("cookie", "access_token=abc".as_bytes()),
+        ("x-api-key", "pk_live_123".as_bytes()),
+        ("content-type", "application/json".as_bytes()),
+    ];
+    let sanitized = sanitize_headers(headers.iter().map(|(n, v)| (*n, *v)));
+    let obj = sanitized.as_object().unwrap();
+    assert_eq!(obj.len(), 1);
+    assert_eq!(obj["content-type"], "application/json");
+
+    let body = br#"{"username":"alice","password":"hunter2","nested":{"refresh_token":"t","code":"123456"}}"#;
+    let sanitized = sanitize_body(body).unwrap();
+    assert!(sanitized.contains("alice"));
+    assert!(!sanitized.contains("hunter2"));
+    assert!(!sanitized.contains("123456"));
+    assert!(sanitized.contains("[REDACTED]"));
+
+    assert_eq!(sanitize_body(b""), None);
+    assert_eq!(sanitize_body(b"plain text").as_deref(), Some("plain text"));
+}
+
+#[test]
+fn test_sanitize_reports_altered_bodies() {
+    // Reformatted JSON is still the same request
+    let (body, altered) = sanitize_request_body(br#"{ "name" : "x" }"#);
+    assert_eq!(body.as_deref(), Some(r#"{"name":"x"}"#));
+    assert!(!altered);
+
+    assert!(sanitize_request_body(br#"[{"token":"t"}]"#).1);
+    assert!(sanitize_request_body(&[b'a'; MAX_STORED_BODY_BYTES + 1]).1);
+    assert!(sanitize_request_body(&[0xff, 0xfe]).1);
+    assert!(!sanitize_request_body(b"").1);
+}
+
+#[sqlx::test]
+async fn test_replay_refuses_altered_bodies(pool: PgPool) {
+    // Nothing listens here, so a replay that gets as far as sending fails
+    let capture_service = RequestCaptureService::new(pool, "http://127.0.0.1:1".to_string());
+    let rule = capture_service
+        .create_rule(
+            CreateCaptureRuleRequest {
+                api_key_id: None,
+                route_prefix: Some("/api/users".to_string()),
+                duration_minutes: None,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    let (request_body, request_altered) =
+        sanitize_request_body(br#"{"username":"bob","password":"hunter2"}"#);
+    assert!(capture_service
+        .record(NewCapture {
+            rule_id: rule.id,
+            method: "POST".to_string(),
+            path: "/api/users".to_string(),
+            query: None,
+            request_headers: serde_json::json!({}),
+            request_body,
+            request_altered,
+            response_status: 201,
+            response_headers: serde_json::json!({}),
+            response_body: None,
+            response_truncated: false,
+            duration_ms: 5,
+        })
+        .await
+        .unwrap());
+    let capture = &capture_service
+        .list_captures(Some(rule.id), 10)
+        .await
+        .unwrap()[0];
+    assert!(capture.request_altered);
+
+    assert!(matches!(
+        capture_service.replay(capture.id, vec![], false).await,
+        Err(CaptureError::NotReplayable(_))
+    ));
+    assert!(matches!(
+        capture_service.replay(capture.id, vec![], true).await,
+        Err(CaptureError::ReplayFailed(_))
+    ));
+}
+
+#[sqlx::test]
+async fn test_capture_rule_matching(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let capture_service = RequestCaptureService::new(pool, "http://127.0.0.1:5300".to_string());
+
+    // A rule needs at least one selector
+    let err = capture_service
+        .create_rule(
+            CreateCaptureRuleRequest {
+                api_key_id: None,
+                route_prefix: None,
+                duration_minutes: None,
+            },
+            None,
+        )
+        .await;
+    assert!(err.is_err());
+
+    let key = services
+        .api_management_service
+        .create_key("capture".to_string(), vec![], None, None)
+        .await
+        .unwrap();
+    let key_rule = capture_service
+        .create_rule(
+            CreateCaptureRuleRequest {
+                api_key_id: Some(key.id),
+                route_prefix: Some("/api/ontology".to_string()),
+                duration_minutes: Some(30),
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    // Both selectors must match
+    assert_eq!(
+        capture_service.match_rule("/api/ontology/classes", Some(&key.secret)),
+        Some(key_rule.id)
+    );
+    assert_eq!(
+        capture_service.match_rule("/api/ontology/classes", None),
+        None
+    );
+    assert_eq!(
+        capture_service.match_rule("/api/users", Some(&key.secret)),
+        None
+    );
+
+    // Deleting the rule stops capture immediately
+    capture_service.delete_rule(key_rule.id).await.unwrap();
+    assert_eq!(
+        capture_service.match_rule("/api/ontology/classes", Some(&key.secret)),
+        None
+    );
+}
+
+#[sqlx::test]
+async fn test_capture_middleware_stores_sanitized_pair(pool: PgPool) {
+    let capture_service = RequestCaptureService::new(pool, "http://127.0.0.1:5300".to_string());
+    let rule = capture_service
+        .create_rule(
+            CreateCaptureRuleRequest {
+                api_key_id: None,
+                route_prefix: Some("/api/echo".to_string()),
+                duration_minutes: None,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    let app = Router::new()
+        .route(
+            "/api/echo",
+            post(|Json(v): Json<serde_json::Value>| async move { Json(v) }),
+        )
+        .layer(axum::middleware::from_fn_with_state(
+            capture_service.clone(),
+            capture_middleware,
+        ));
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/echo?debug=1")
+                .header("content-type", "application/json")
+                .header("cookie", "access_token=secret-cookie")
+                .body(Body::from(r#"{"name":"x","password":"hunter2"}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    // The handler still sees the original body
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert!(String::from_utf8_lossy(&body).contains("hunter2"));
+
+    // Captures are written in the background
+    let mut captures = Vec::new();
+    for _ in 0..50 {
+        captures = capture_service
+            .list_captures(Some(rule.id), 10)
+            .await
+            .unwrap();
+        if !captures.is_empty() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+
+    assert_eq!(captures.len(), 1);
+    let capture = &captures[0];
+    assert_eq!(capture.method, "POST");
+    assert_eq!(capture.path, "/api/echo");
+    assert_eq!(capture.query.as_deref(), Some("debug=1"));
+    assert_eq!(capture.response_status, 200);
+    assert!(capture.request_headers.get("cookie").is_none());
+    assert!(!capture.request_body.as_deref().unwrap().contains("hunter2"));
+    assert!(capture.request_altered);
+    assert!(!capture
+        .response_body
+        .as_deref()
+        .unwrap()
+        .contains("hunter2"));
+
+    // A redacted query alters the request even when the body is untouched
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/echo?debug=1&token=abc")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"name":"y"}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let mut redacted = None;
+    for _ in 0..50 {
+        redacted = capture_service
+            .list_captures(Some(rule.id), 10)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|c| c.request_body.as_deref() == Some(r#"{"name":"y"}"#));
+        if redacted.is_some() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    let redacted = redacted.unwrap();
+    assert_eq!(redacted.query.as_deref(), Some("debug=1&token=[REDACTED]"));
+    assert!(redacted.request_altered);
+}
+
+#[sqlx::test]
+async fn test_capture_middleware_passes_oversized_responses_through(pool: PgPool) {
+    let capture_service = RequestCaptureService::new(pool, "http://127.0.0.1:5300".to_string());
+    let rule = capture_service
+        .create_rule(
+            CreateCaptureRuleRequest {
+                api_key_id: None,
+                route_prefix: Some("/api/export".to_string()),
+                duration_minutes: None,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    const SIZE: usize = 11 * 1024 * 1024;
+    let app = Router::new()
+        .route("/api/export", get(|| async { vec![b'a'; SIZE] }))
+        .layer(axum::middleware::from_fn_with_state(
+            capture_service.clone(),
+            capture_middleware,
+        ));
+
+    // The client gets the whole response, not an error from the capture
+    let response = app
+        .oneshot(Request::get("/api/export").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(body.len(), SIZE);
+
+    let mut captures = Vec::new();
+    for _ in 0..50 {
+        captures = capture_service
+            .list_captures(Some(rule.id), 10)
+            .await
+            .unwrap();
+        if !captures.is_empty() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(captures.len(), 1);
+    assert_eq!(captures[0].response_status, 200);
+    assert!(captures[0].response_truncated);
+    assert!(captures[0].response_body.is_none());
+}
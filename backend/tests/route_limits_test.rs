@@ -0,0 +1,155 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    routing::get,
+    Router,
+};
+use std::time::Duration;
+use template_repo_backend::config::{RouteLimit, RouteLimitsConfig};
+use template_repo_backend::middleware::route_limits::{route_limits_middleware, RouteLimits};
+use tokio::sync::oneshot;
+use tower::ServiceExt;
+
+fn limits() -> RouteLimits {
+    RouteLimits::from_config(&RouteLimitsConfig {
+        default_timeout_secs: 0,
+        default_max_concurrent: 0,
+        routes: vec![
+            RouteLimit {
+                prefix: "/api/auth".to_string(),
+                timeout_secs: 1,
+                max_concurrent: 0,
+            },
+            RouteLimit {
+                prefix: "/api/ai".to_string(),
+                timeout_secs: 0,
+                max_concurrent: 1,
+            },
+            RouteLimit {
+                prefix: "/api/ai/models".to_string(),
+                timeout_secs: 5,
+                max_concurrent: 0,
+            },
+        ],
+    })
+}
+
+fn app() -> Router {
+    let slow = get(|| async {
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        "done"
+    });
+    Router::new()
+        .route("/api/auth/login", slow.clone())
+        .route("/api/items", slow)
+        .layer(axum::middleware::from_fn_with_state(
+            limits(),
+            route_limits_middleware,
+        ))
+}
+
+async fn json_body(response: axum::response::Response) -> serde_json::Value {
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+#[test]
+fn test_longest_prefix_wins() {
+    let limits = limits();
+    assert_eq!(
+        limits.timeout_for("/api/auth/login"),
+        Some(Duration::from_secs(1))
+    );
+    assert_eq!(
+        limits.timeout_for("/api/ai/models/list"),
+        Some(Duration::from_secs(5))
+    );
+    assert_eq!(limits.timeout_for("/api/ai/generate"), None);
+    assert_eq!(limits.timeout_for("/api/ontology/entities"), None);
+}
+
+#[tokio::test]
+async fn test_slow_handler_times_out() {
+    let response = app()
+        .oneshot(Request::get("/api/auth/login").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::REQUEST_TIMEOUT);
+    let body = json_body(response).await;
+    assert_eq!(body["error"], "Request timed out");
+    assert_eq!(body["timeout_secs"], 1);
+
+    // Groups without a timeout let the handler finish
+    let response = app()
+        .oneshot(Request::get("/api/items").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_group_at_capacity_rejected() {
+    let (started_tx, started_rx) = oneshot::channel();
+    let (release_tx, release_rx) = oneshot::channel::<()>();
+    let started_tx = std::sync::Mutex::new(Some(started_tx));
+    let release_rx = tokio::sync::Mutex::new(Some(release_rx));
+    let blocking = std::sync::Arc::new((started_tx, release_rx));
+
+    let app = Router::new()
+        .route(
+            "/api/ai/generate",
+            get(move || {
+                let blocking = blocking.clone();
+                async move {
+                    if let Some(tx) = blocking.0.lock().unwrap().take() {
+                        let _ = tx.send(());
+                    }
+                    if let Some(rx) = blocking.1.lock().await.take() {
+                        let _ = rx.await;
+                    }
+                    "done"
+                }
+            }),
+        )
+        .layer(axum::middleware::from_fn_with_state(
+            limits(),
+            route_limits_middleware,
+        ));
+
+    let first = tokio::spawn(
+        app.clone().oneshot(
+            Request::get("/api/ai/generate")
+                .body(Body::empty())
+                .unwrap(),
+        ),
+    );
+    started_rx.await.unwrap();
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::get("/api/ai/generate")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert!(response.headers().contains_key("retry-after"));
+    assert!(json_body(response).await["error"].is_string());
+
+    // The permit is returned once the first request completes
+    release_tx.send(()).unwrap();
+    assert_eq!(first.await.unwrap().unwrap().status(), StatusCode::OK);
+    let response = app
+        .oneshot(
+            Request::get("/api/ai/generate")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
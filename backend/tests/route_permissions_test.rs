@@ -0,0 +1,189 @@
+use axum::{
+    body::Body, extract::Request, http::StatusCode, middleware::Next, response::Response,
+    routing::get, Router,
+};
+use sqlx::PgPool;
+use template_repo_backend::features::abac::models::AssignRoleInput;
+use template_repo_backend::features::abac::routes::{abac_routes, ROUTE_PERMISSIONS};
+use template_repo_backend::features::auth::jwt::Claims;
+use template_repo_backend::features::auth::models::RegisterUser;
+use template_repo_backend::features::ip_access;
+use template_repo_backend::middleware::abac::{
+    enforce_route_permissions, required_permission, route_permission_report,
+};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+mod common;
+
+#[test]
+fn test_required_permission_matches_method_and_route() {
+    assert_eq!(
+        required_permission(ROUTE_PERMISSIONS, "GET", "/api/abac", "/api/abac/roles"),
+        Some("read")
+    );
+    assert_eq!(
+        required_permission(ROUTE_PERMISSIONS, "POST", "/api/abac", "/api/abac/roles"),
+        Some("manage_users")
+    );
+    // Pattern, not concrete path, and nothing for undeclared routes
+    assert_eq!(
+        required_permission(
+            ROUTE_PERMISSIONS,
+            "GET",
+            "/api/abac",
+            "/api/abac/users/:user_id/roles"
+        ),
+        Some("read")
+    );
+    assert_eq!(
+        required_permission(ROUTE_PERMISSIONS, "PATCH", "/api/abac", "/api/abac/roles"),
+        None
+    );
+}
+
+#[test]
+fn test_report_lists_declared_routes_with_full_paths() {
+    let report = route_permission_report();
+    assert!(report.len() > ROUTE_PERMISSIONS.len());
+    assert!(report.iter().any(|e| e.method == "DELETE"
+        && e.path == "/api/abac/users/roles/:id"
+        && e.permission == "manage_users"));
+    assert!(report.iter().any(|e| e.method == "POST"
+        && e.path == "/api/ip-access/rules"
+        && e.permission == "manage_system"));
+}
+
+#[tokio::test]
+async fn test_undeclared_route_on_admin_router_is_refused() {
+    // As if a route were added to the IP access router without declaring it
+    let app = Router::new().nest(
+        "/api/ip-access",
+        Router::new()
+            .route("/undeclared", get(|| async { "reachable" }))
+            .route_layer(axum::middleware::from_fn_with_state(
+                ip_access::routes::ROUTE_PERMISSIONS,
+                enforce_route_permissions,
+            )),
+    );
+    assert_eq!(
+        status(&app, "GET", "/api/ip-access/undeclared").await,
+        StatusCode::FORBIDDEN
+    );
+}
+
+async fn register(services: &common::TestServices, username: &str) -> Uuid {
+    services
+        .auth_service
+        .register(RegisterUser {
+            username: username.to_string(),
+            email: format!("{}@example.com", username),
+            password: "password123".to_string(),
+        })
+        .await
+        .unwrap()
+        .user_id
+}
+
+/// The ABAC router mounted as in main.rs, with a stand-in for auth_middleware
+fn app(services: &common::TestServices, user_id: Uuid) -> Router {
+    let claims = Claims {
+        sub: user_id.to_string(),
+        username: "test".to_string(),
+        email: "test@example.com".to_string(),
+        roles: vec![],
+        permissions: vec![],
+        jti: None,
+        exp: i64::MAX,
+        iat: 0,
+    };
+    let fake_auth = move |mut request: Request, next: Next| {
+        let claims = claims.clone();
+        async move {
+            request.extensions_mut().insert(claims);
+            Ok::<Response, StatusCode>(next.run(request).await)
+        }
+    };
+
+    Router::new()
+        .nest(
+            "/api/abac",
+            abac_routes()
+                .with_state(services.abac_service.clone())
+                .layer(axum::middleware::from_fn(fake_auth)),
+        )
+        .layer(axum::Extension(services.abac_service.clone()))
+}
+
+async fn status(app: &Router, method: &str, uri: &str) -> StatusCode {
+    let mut request = Request::builder().method(method).uri(uri);
+    let body = if method == "POST" {
+        request = request.header("content-type", "application/json");
+        Body::from(r#"{"name": "route_perm_role", "description": null}"#)
+    } else {
+        Body::empty()
+    };
+    app.clone()
+        .oneshot(request.body(body).unwrap())
+        .await
+        .unwrap()
+        .status()
+}
+
+#[sqlx::test]
+async fn test_declared_permissions_are_enforced(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+
+    let viewer_id = register(&services, "route_perm_viewer").await;
+    services
+        .abac_service
+        .assign_role(
+            AssignRoleInput {
+                user_id: viewer_id.to_string(),
+                role_name: "viewer".to_string(),
+                resource_id: None,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    let viewer = app(&services, viewer_id);
+    assert_eq!(
+        status(&viewer, "GET", "/api/abac/roles").await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        status(&viewer, "POST", "/api/abac/roles").await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        status(&viewer, "GET", "/api/abac/route-permissions").await,
+        StatusCode::OK
+    );
+
+    let admin_id = register(&services, "route_perm_admin").await;
+    services
+        .abac_service
+        .assign_role(
+            AssignRoleInput {
+                user_id: admin_id.to_string(),
+                role_name: "admin".to_string(),
+                resource_id: None,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    let admin = app(&services, admin_id);
+    assert_eq!(
+        status(&admin, "POST", "/api/abac/roles").await,
+        StatusCode::OK
+    );
+
+    // A user without roles cannot even list them
+    let nobody = app(&services, register(&services, "route_perm_nobody").await);
+    assert_eq!(
+        status(&nobody, "GET", "/api/abac/roles").await,
+        StatusCode::FORBIDDEN
+    );
+}
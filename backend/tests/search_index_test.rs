@@ -0,0 +1,399 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{head, post},
+    Json, Router,
+};
+use sqlx::PgPool;
+use std::sync::{Arc, Mutex};
+use template_repo_backend::config::{OutboundHttpConfig, SearchIndexConfig};
+use template_repo_backend::features::navigation::NavigationService;
+use template_repo_backend::features::ontology::models::{
+    CreateClassInput, CreateEntityInput, CreatePropertyInput, CreateRelationshipInput,
+};
+use template_repo_backend::features::search_index::SearchIndex;
+use template_repo_backend::features::system::service::SystemService;
+use template_repo_backend::utils::http_client::OutboundClient;
+use template_repo_backend::utils::shutdown::Shutdown;
+use uuid::Uuid;
+
+mod common;
+
+/// What a mock search cluster received, and the ids its searches return
+#[derive(Clone, Default)]
+struct SearchServer {
+    created_indexes: Arc<Mutex<Vec<String>>>,
+    bulk_bodies: Arc<Mutex<Vec<String>>>,
+    hits: Arc<Mutex<Vec<Uuid>>>,
+    failing: Arc<Mutex<bool>>,
+}
+
+impl SearchServer {
+    /// Bulk request lines as JSON, drained
+    fn take_lines(&self) -> Vec<serde_json::Value> {
+        self.bulk_bodies
+            .lock()
+            .unwrap()
+            .drain(..)
+            .flat_map(|body| {
+                body.lines()
+                    .map(|line| serde_json::from_str(line).unwrap())
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+async fn index_exists(State(server): State<SearchServer>, Path(index): Path<String>) -> StatusCode {
+    if server.created_indexes.lock().unwrap().contains(&index) {
+        StatusCode::OK
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+async fn create_index(
+    State(server): State<SearchServer>,
+    Path(index): Path<String>,
+) -> Json<serde_json::Value> {
+    server.created_indexes.lock().unwrap().push(index);
+    Json(serde_json::json!({ "acknowledged": true }))
+}
+
+async fn bulk(State(server): State<SearchServer>, body: String) -> Json<serde_json::Value> {
+    server.bulk_bodies.lock().unwrap().push(body);
+    Json(serde_json::json!({ "errors": false, "items": [] }))
+}
+
+async fn search(State(server): State<SearchServer>) -> Result<Json<serde_json::Value>, StatusCode> {
+    if *server.failing.lock().unwrap() {
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+    let hits: Vec<serde_json::Value> = server
+        .hits
+        .lock()
+        .unwrap()
+        .iter()
+        .enumerate()
+        .map(|(i, id)| serde_json::json!({ "_id": id, "_score": 10.0 - i as f64 }))
+        .collect();
+    Ok(Json(serde_json::json!({ "hits": { "hits": hits } })))
+}
+
+/// Starts a search cluster on localhost and an indexer pointed at it
+async fn start_search_server(pool: &PgPool) -> (SearchServer, SearchIndex) {
+    let server = SearchServer::default();
+    let app = Router::new()
+        .route("/_bulk", post(bulk))
+        .route("/:index", head(index_exists).put(create_index))
+        .route("/:index/_search", post(search))
+        .with_state(server.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let http = OutboundClient::from_config(&OutboundHttpConfig {
+        max_retries: 0,
+        ..Default::default()
+    });
+    let index = SearchIndex::from_config(
+        pool.clone(),
+        &SearchIndexConfig {
+            enabled: true,
+            url: format!("http://{}", addr),
+            ..Default::default()
+        },
+        http,
+    )
+    .await
+    .unwrap()
+    .unwrap();
+    (server, index)
+}
+
+/// A user entity, for audit events to be initiated by
+async fn user(pool: &PgPool, services: &common::TestServices, name: &str) -> Uuid {
+    let user_id = Uuid::new_v4();
+    let user_class = services
+        .ontology_service
+        .get_system_class("User")
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO entities (id, class_id, display_name, attributes, approval_status) VALUES ($1, $2, $3, '{}', 'APPROVED')")
+        .bind(user_id)
+        .bind(user_class.id)
+        .bind(name)
+        .execute(pool)
+        .await
+        .unwrap();
+    user_id
+}
+
+/// A class with a sensitive `secret` property, and one entity of it
+async fn depot(pool: &PgPool, services: &common::TestServices) -> Uuid {
+    common::reopen_current_version(pool).await;
+    let ontology = &services.ontology_service;
+    let class = ontology
+        .create_class(
+            CreateClassInput {
+                name: "IndexedDepot".to_string(),
+                description: None,
+                parent_class_id: None,
+                is_abstract: None,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    ontology
+        .create_property(CreatePropertyInput {
+            name: "secret".to_string(),
+            description: None,
+            class_id: class.id,
+            data_type: "string".to_string(),
+            reference_class_id: None,
+            is_required: None,
+            is_unique: None,
+            is_indexed: None,
+            is_sensitive: Some(true),
+            default_value: None,
+            validation_rules: None,
+        })
+        .await
+        .unwrap();
+    ontology
+        .create_entity(
+            CreateEntityInput {
+                class_id: class.id,
+                display_name: "Harbour Depot".to_string(),
+                parent_entity_id: None,
+                attributes: Some(serde_json::json!({ "code": "HRB-7", "secret": "x" })),
+            },
+            None,
+            None,
+        )
+        .await
+        .unwrap()
+        .id
+}
+
+#[sqlx::test]
+async fn entities_and_audit_events_are_indexed(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let (server, index) = start_search_server(&pool).await;
+
+    // Enabling creates the indexes and backfills what is already there
+    let depot_id = depot(&pool, &services).await;
+    index.enable().await.unwrap();
+    assert_eq!(
+        *server.created_indexes.lock().unwrap(),
+        vec!["ontology-entities", "ontology-audit"]
+    );
+    index.run_once(false, &Shutdown::new()).await;
+    let lines = server.take_lines();
+    let document = lines
+        .iter()
+        .find(|line| line["id"] == serde_json::json!(depot_id))
+        .unwrap();
+    assert_eq!(document["class_name"], "IndexedDepot");
+    assert!(document["attributes_text"]
+        .as_str()
+        .unwrap()
+        .contains("HRB-7"));
+    assert!(document["attributes"].get("secret").is_none());
+    assert!(!index.status().await.unwrap().backfilling);
+
+    // Changes after that arrive through the change feed
+    let inspector = user(&pool, &services, "inspector").await;
+    let log = services
+        .audit_service
+        .log(
+            inspector,
+            "depot.inspected",
+            "IndexedDepot",
+            Some(depot_id),
+            None,
+            None,
+            Some(serde_json::json!({ "inspector": "Quayside" })),
+        )
+        .await
+        .unwrap();
+    index.run_once(false, &Shutdown::new()).await;
+    let lines = server.take_lines();
+    let position = lines
+        .iter()
+        .position(|line| line["id"] == serde_json::json!(log.id))
+        .unwrap();
+    assert_eq!(lines[position - 1]["index"]["_index"], "ontology-audit");
+    assert_eq!(lines[position]["action"], "depot.inspected");
+    assert!(lines[position]["metadata_text"]
+        .as_str()
+        .unwrap()
+        .contains("Quayside"));
+
+    services
+        .ontology_service
+        .delete_entity(depot_id, None)
+        .await
+        .unwrap();
+    index.run_once(false, &Shutdown::new()).await;
+    let deleted: Vec<serde_json::Value> = server
+        .take_lines()
+        .into_iter()
+        .filter(|line| line["delete"]["_id"] == serde_json::json!(depot_id))
+        .map(|line| line["delete"]["_index"].clone())
+        .collect();
+    assert_eq!(deleted, vec!["ontology-entities", "ontology-audit"]);
+    assert_eq!(index.status().await.unwrap().pending_events, 0);
+}
+
+#[sqlx::test]
+async fn quick_search_is_answered_from_the_index(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let (server, index) = start_search_server(&pool).await;
+    let navigation = NavigationService::new(
+        pool.clone(),
+        services.abac_service.clone(),
+        services.rebac_service.clone(),
+    )
+    .with_search_index(index);
+    let ontology = &services.ontology_service;
+
+    let depot_id = depot(&pool, &services).await;
+    let user_id = user(&pool, &services, "indexer").await;
+
+    // "read" on the depot only
+    let role_class = ontology.get_system_class("Role").await.unwrap();
+    let perm_class = ontology.get_system_class("Permission").await.unwrap();
+    let create = |class_id, name: &str, attributes| CreateEntityInput {
+        class_id,
+        display_name: name.into(),
+        parent_entity_id: None,
+        attributes: Some(attributes),
+    };
+    let role = ontology
+        .create_entity(
+            create(
+                role_class.id,
+                "DepotViewer",
+                serde_json::json!({"name": "DepotViewer", "level": 10}),
+            ),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    let read = ontology
+        .create_entity(
+            create(
+                perm_class.id,
+                "read",
+                serde_json::json!({"name": "read", "level": 1}),
+            ),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    for (source, target, relationship_type, metadata) in [
+        (
+            role.id,
+            read.id,
+            "grants_permission",
+            serde_json::json!({"effect": "ALLOW"}),
+        ),
+        (
+            user_id,
+            role.id,
+            "has_role",
+            serde_json::json!({"scope_entity_id": depot_id.to_string()}),
+        ),
+    ] {
+        ontology
+            .create_relationship(
+                CreateRelationshipInput {
+                    source_entity_id: source,
+                    target_entity_id: target,
+                    relationship_type: relationship_type.into(),
+                    metadata: Some(metadata),
+                },
+                None,
+            )
+            .await
+            .unwrap();
+    }
+
+    // Matched on an attribute value the database search doesn't look at;
+    // the unreadable role and the unknown id are dropped
+    *server.hits.lock().unwrap() = vec![depot_id, role.id, Uuid::new_v4()];
+    let results = navigation.quick_search(user_id, "HRB", None).await.unwrap();
+    assert_eq!(results.entities.len(), 1);
+    assert_eq!(results.entities[0].id, depot_id);
+    assert_eq!(results.entities[0].label, "Harbour Depot");
+    assert_eq!(results.entities[0].detail.as_deref(), Some("IndexedDepot"));
+    assert_eq!(results.entities[0].score, 1.0);
+
+    // An unreachable index falls back to matching names in the database
+    *server.failing.lock().unwrap() = true;
+    assert!(navigation
+        .quick_search(user_id, "HRB", None)
+        .await
+        .unwrap()
+        .entities
+        .is_empty());
+    let results = navigation
+        .quick_search(user_id, "harbour", None)
+        .await
+        .unwrap();
+    assert_eq!(results.entities.len(), 1);
+}
+
+#[sqlx::test]
+async fn log_search_is_answered_from_the_index(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let (server, index) = start_search_server(&pool).await;
+    let system =
+        SystemService::new(pool.clone(), services.audit_service.clone()).with_search_index(index);
+
+    let auditor = user(&pool, &services, "auditor").await;
+    let mut ids = Vec::new();
+    for action in ["depot.opened", "depot.closed"] {
+        let log = services
+            .audit_service
+            .log(auditor, action, "Depot", None, None, None, None)
+            .await
+            .unwrap();
+        ids.push(log.id);
+    }
+
+    // In the order the index ranks them
+    *server.hits.lock().unwrap() = vec![ids[0], ids[1]];
+    let logs = system.search_logs("depot", 10).await.unwrap();
+    let found: Vec<Uuid> = logs.iter().map(|log| log.id).collect();
+    assert_eq!(found, ids);
+
+    // The database fallback lists newest first
+    *server.failing.lock().unwrap() = true;
+    let logs = system.search_logs("depot.clo", 10).await.unwrap();
+    assert_eq!(logs.len(), 1);
+    assert_eq!(logs[0].action, "depot.closed");
+}
+
+#[sqlx::test]
+async fn disabled_indexer_does_not_hold_back_pruning(pool: PgPool) {
+    SearchIndex::from_config(
+        pool.clone(),
+        &SearchIndexConfig::default(),
+        OutboundClient::from_config(&Default::default()),
+    )
+    .await
+    .unwrap();
+    let running: Vec<String> =
+        sqlx::query_scalar("SELECT worker FROM ai_index_state WHERE available ORDER BY worker")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    assert!(!running.contains(&"search_index".to_string()));
+}
@@ -0,0 +1,133 @@
+use axum::{
+    body::Body, extract::Request, http::StatusCode, middleware::Next, response::Response, Router,
+};
+use sqlx::PgPool;
+use template_repo_backend::features::abac::models::AssignRoleInput;
+use template_repo_backend::features::abac::routes::abac_routes;
+use template_repo_backend::features::auth::jwt::Claims;
+use template_repo_backend::features::auth::models::RegisterUser;
+use template_repo_backend::features::request_capture::service::sanitize_query;
+use template_repo_backend::middleware::audit::{
+    sensitive_route_audit_middleware, SensitiveRouteAudit, SENSITIVE_REQUEST_ACTION,
+};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+mod common;
+
+#[test]
+fn test_sensitive_query_parameters_are_redacted() {
+    assert_eq!(
+        sanitize_query("page=2&token=abc&api_key=xyz"),
+        "page=2&token=[REDACTED]&api_key=[REDACTED]"
+    );
+    assert_eq!(sanitize_query("flag"), "flag");
+}
+
+/// The ABAC router mounted as in main.rs, with a stand-in for auth_middleware
+fn app(services: &common::TestServices, user_id: Uuid) -> Router {
+    let claims = Claims {
+        sub: user_id.to_string(),
+        username: "test".to_string(),
+        email: "test@example.com".to_string(),
+        roles: vec![],
+        permissions: vec![],
+        jti: None,
+        exp: i64::MAX,
+        iat: 0,
+    };
+    let fake_auth = move |mut request: Request, next: Next| {
+        let claims = claims.clone();
+        async move {
+            request.extensions_mut().insert(claims);
+            Ok::<Response, StatusCode>(next.run(request).await)
+        }
+    };
+
+    Router::new()
+        .nest(
+            "/api/abac",
+            abac_routes()
+                .with_state(services.abac_service.clone())
+                .layer(axum::middleware::from_fn_with_state(
+                    SensitiveRouteAudit::new(services.audit_service.clone(), "abac"),
+                    sensitive_route_audit_middleware,
+                ))
+                .layer(axum::middleware::from_fn(fake_auth)),
+        )
+        .layer(axum::Extension(services.abac_service.clone()))
+}
+
+async fn audited_requests(pool: &PgPool, user_id: Uuid) -> Vec<serde_json::Value> {
+    sqlx::query_scalar::<_, Option<serde_json::Value>>(
+        "SELECT metadata FROM unified_audit_logs WHERE user_id = $1 AND action = $2 ORDER BY created_at",
+    )
+    .bind(user_id)
+    .bind(SENSITIVE_REQUEST_ACTION)
+    .fetch_all(pool)
+    .await
+    .unwrap()
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+#[sqlx::test]
+async fn test_writes_are_audited_with_outcome(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let user_id = services
+        .auth_service
+        .register(RegisterUser {
+            username: "sensitive_audit_admin".to_string(),
+            email: "sensitive_audit_admin@example.com".to_string(),
+            password: "password123".to_string(),
+        })
+        .await
+        .unwrap()
+        .user_id;
+    services
+        .abac_service
+        .assign_role(
+            AssignRoleInput {
+                user_id: user_id.to_string(),
+                role_name: "admin".to_string(),
+                resource_id: None,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    let app = app(&services, user_id);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::post("/api/abac/roles?token=secret")
+                .header("content-type", "application/json")
+                .header("authorization", "Bearer secret")
+                .body(Body::from(
+                    r#"{"name": "sensitive_audit_role", "description": null}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Reads are not audited
+    let response = app
+        .oneshot(Request::get("/api/abac/roles").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let records = audited_requests(&pool, user_id).await;
+    assert_eq!(records.len(), 1);
+    let record = &records[0];
+    assert_eq!(record["method"], "POST");
+    assert_eq!(record["path"], "/api/abac/roles");
+    assert_eq!(record["status"], 200);
+    assert_eq!(record["query"], "token=[REDACTED]");
+    assert!(record["headers"].get("authorization").is_none());
+    assert_eq!(record["headers"]["content-type"], "application/json");
+}
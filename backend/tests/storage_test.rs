@@ -0,0 +1,391 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::{DefaultBodyLimit, Path, Query, Request, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::put,
+    Router,
+};
+use futures::StreamExt;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use template_repo_backend::config::{OutboundHttpConfig, StorageConfig, UploadsConfig};
+use template_repo_backend::features::auth::models::RegisterUser;
+use template_repo_backend::features::environment::models::EnvironmentBundle;
+use template_repo_backend::features::environment::EnvironmentService;
+use template_repo_backend::features::storage::routes::storage_routes;
+use template_repo_backend::features::uploads::models::CreateUploadSession;
+use template_repo_backend::features::uploads::UploadService;
+use template_repo_backend::utils::http_client::OutboundClient;
+use template_repo_backend::utils::storage::{
+    bytes_stream, ByteStream, LocalStorage, ObjectStorage, S3Credentials, S3Storage, StorageError,
+};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+mod common;
+
+const MIB: usize = 1024 * 1024;
+
+/// Parts of each unfinished multipart upload by upload id
+type Uploads = HashMap<String, BTreeMap<u32, Vec<u8>>>;
+
+/// Objects, unfinished multipart uploads and request headers a mock S3 bucket has seen
+#[derive(Clone, Default)]
+struct S3Server {
+    objects: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+    uploads: Arc<Mutex<Uploads>>,
+    aborted: Arc<Mutex<Vec<String>>>,
+    authorizations: Arc<Mutex<Vec<String>>>,
+}
+
+impl S3Server {
+    fn record(&self, headers: &HeaderMap) {
+        let authorization = headers
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default();
+        self.authorizations
+            .lock()
+            .unwrap()
+            .push(authorization.to_string());
+    }
+}
+
+type Params = Query<HashMap<String, String>>;
+
+async fn put_object(
+    State(server): State<S3Server>,
+    Path((_bucket, key)): Path<(String, String)>,
+    Query(params): Params,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    server.record(&headers);
+    match (params.get("uploadId"), params.get("partNumber")) {
+        (Some(id), Some(part)) => {
+            let part: u32 = part.parse().unwrap();
+            let mut uploads = server.uploads.lock().unwrap();
+            let Some(parts) = uploads.get_mut(id) else {
+                return StatusCode::NOT_FOUND.into_response();
+            };
+            parts.insert(part, body.to_vec());
+            [("etag", format!("\"etag-{}\"", part))].into_response()
+        }
+        _ => {
+            server.objects.lock().unwrap().insert(key, body.to_vec());
+            StatusCode::OK.into_response()
+        }
+    }
+}
+
+async fn post_object(
+    State(server): State<S3Server>,
+    Path((_bucket, key)): Path<(String, String)>,
+    Query(params): Params,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    server.record(&headers);
+    if params.contains_key("uploads") {
+        let id = Uuid::new_v4().to_string();
+        server
+            .uploads
+            .lock()
+            .unwrap()
+            .insert(id.clone(), BTreeMap::new());
+        return format!(
+            "<InitiateMultipartUploadResult><Key>{}</Key><UploadId>{}</UploadId></InitiateMultipartUploadResult>",
+            key, id
+        )
+        .into_response();
+    }
+    let id = params.get("uploadId").unwrap();
+    let Some(parts) = server.uploads.lock().unwrap().remove(id) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    assert_eq!(body.matches("<Part>").count(), parts.len());
+    let content: Vec<u8> = parts.into_values().flatten().collect();
+    server.objects.lock().unwrap().insert(key, content);
+    "<CompleteMultipartUploadResult></CompleteMultipartUploadResult>".into_response()
+}
+
+async fn get_object(
+    State(server): State<S3Server>,
+    Path((_bucket, key)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Response {
+    server.record(&headers);
+    match server.objects.lock().unwrap().get(&key) {
+        Some(content) => content.clone().into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            "<Error><Code>NoSuchKey</Code></Error>",
+        )
+            .into_response(),
+    }
+}
+
+async fn delete_object(
+    State(server): State<S3Server>,
+    Path((_bucket, key)): Path<(String, String)>,
+    Query(params): Params,
+    headers: HeaderMap,
+) -> StatusCode {
+    server.record(&headers);
+    match params.get("uploadId") {
+        Some(id) => {
+            server.uploads.lock().unwrap().remove(id);
+            server.aborted.lock().unwrap().push(id.clone());
+        }
+        None => {
+            server.objects.lock().unwrap().remove(&key);
+        }
+    }
+    StatusCode::NO_CONTENT
+}
+
+/// Starts an S3 bucket on localhost and a store pointed at it
+async fn start_s3_server() -> (S3Server, S3Storage) {
+    let server = S3Server::default();
+    let app = Router::new()
+        .route(
+            "/:bucket/*key",
+            put(put_object)
+                .post(post_object)
+                .get(get_object)
+                .delete(delete_object),
+        )
+        // Parts are at least 5 MiB
+        .layer(DefaultBodyLimit::disable())
+        .with_state(server.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let storage = S3Storage::new(
+        &StorageConfig {
+            backend: "s3".to_string(),
+            s3_bucket: "ontology".to_string(),
+            s3_endpoint: format!("http://{}", addr),
+            s3_path_style: true,
+            // Raised to the 5 MiB minimum
+            s3_part_size_bytes: 1,
+            ..Default::default()
+        },
+        S3Credentials {
+            access_key_id: "test-key".to_string(),
+            secret_access_key: "test-secret".to_string(),
+            session_token: None,
+        },
+        OutboundClient::from_config(&OutboundHttpConfig {
+            max_retries: 0,
+            ..Default::default()
+        }),
+    )
+    .unwrap();
+    (server, storage)
+}
+
+/// `data` in chunks of one MiB
+fn chunked(data: &[u8]) -> ByteStream<'static> {
+    let chunks: Vec<Result<Bytes, StorageError>> = data
+        .chunks(MIB)
+        .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+        .collect();
+    futures::stream::iter(chunks).boxed()
+}
+
+async fn read_all(storage: &dyn ObjectStorage, key: &str) -> Result<Vec<u8>, StorageError> {
+    let mut stream = storage.get(key).await?;
+    let mut content = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        content.extend_from_slice(&chunk?);
+    }
+    Ok(content)
+}
+
+#[tokio::test]
+async fn small_objects_are_put_in_one_request() {
+    let (server, storage) = start_s3_server().await;
+
+    let stored = storage
+        .put("exports/a.json", bytes_stream(&b"{\"a\":1}"[..]), 1024)
+        .await
+        .unwrap();
+    assert_eq!(stored.size, 7);
+    assert_eq!(stored.sha256, hex::encode(Sha256::digest(b"{\"a\":1}")));
+    assert_eq!(
+        read_all(&storage, "exports/a.json").await.unwrap(),
+        b"{\"a\":1}"
+    );
+    assert!(server
+        .authorizations
+        .lock()
+        .unwrap()
+        .iter()
+        .all(|auth| auth.starts_with("AWS4-HMAC-SHA256 Credential=test-key/")));
+
+    storage.delete("exports/a.json").await.unwrap();
+    assert!(matches!(
+        read_all(&storage, "exports/a.json").await,
+        Err(StorageError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound
+    ));
+    assert!(matches!(
+        storage.put("../a.json", bytes_stream(&b"x"[..]), 10).await,
+        Err(StorageError::InvalidKey)
+    ));
+}
+
+#[tokio::test]
+async fn large_objects_are_uploaded_in_parts() {
+    let (server, storage) = start_s3_server().await;
+    let data: Vec<u8> = (0..11 * MIB).map(|i| (i % 251) as u8).collect();
+
+    let stored = storage
+        .put("uploads/big", chunked(&data), data.len() as u64)
+        .await
+        .unwrap();
+    assert_eq!(stored.size, data.len() as u64);
+    assert_eq!(server.objects.lock().unwrap()["uploads/big"], data);
+    assert!(server.uploads.lock().unwrap().is_empty());
+
+    // Going over the limit after the first part abandons the upload
+    let result = storage
+        .put("uploads/too-big", chunked(&data), 6 * MIB as u64)
+        .await;
+    assert!(matches!(result, Err(StorageError::TooLarge { .. })));
+    assert_eq!(server.aborted.lock().unwrap().len(), 1);
+    assert!(server.uploads.lock().unwrap().is_empty());
+    assert!(!server
+        .objects
+        .lock()
+        .unwrap()
+        .contains_key("uploads/too-big"));
+}
+
+#[sqlx::test]
+async fn completed_uploads_move_to_object_storage(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let owner_id = services
+        .auth_service
+        .register(RegisterUser {
+            username: "s3_uploader".to_string(),
+            email: "s3_uploader@example.com".to_string(),
+            password: "password123".to_string(),
+        })
+        .await
+        .unwrap()
+        .user_id;
+    let (server, storage) = start_s3_server().await;
+    let staging_dir = std::env::temp_dir().join(format!("storage-test-{}", Uuid::new_v4()));
+    let uploads = UploadService::new(
+        pool,
+        UploadsConfig {
+            storage_dir: staging_dir.to_string_lossy().to_string(),
+            ..Default::default()
+        },
+    )
+    .with_storage(Arc::new(storage));
+
+    let session = uploads
+        .create_session(
+            owner_id,
+            CreateUploadSession {
+                purpose: "attachment".to_string(),
+                filename: "notes.txt".to_string(),
+                content_type: Some("text/plain".to_string()),
+                size_bytes: 10,
+                sha256: None,
+            },
+        )
+        .await
+        .unwrap();
+    for (offset, chunk) in [(0, "hello "), (6, "s3!\n")] {
+        uploads
+            .append_chunk(owner_id, session.id, offset, bytes_stream(chunk))
+            .await
+            .unwrap();
+    }
+
+    let key = format!("uploads/{}", session.id);
+    assert_eq!(server.objects.lock().unwrap()[&key], b"hello s3!\n");
+    assert!(!staging_dir
+        .join(format!("resumable/{}", session.id))
+        .exists());
+    let (_, content) = uploads.open(owner_id, session.id).await.unwrap();
+    let content: Vec<Bytes> = content.map(Result::unwrap).collect().await;
+    assert_eq!(content.concat(), b"hello s3!\n");
+
+    let link = uploads.download_url(owner_id, session.id).await.unwrap();
+    assert!(link.url.contains(&format!("/ontology/{}?", key)));
+    assert!(link.url.contains("X-Amz-Signature="));
+    assert!(link.url.contains("response-content-type=text%2Fplain"));
+
+    uploads.delete(owner_id, session.id).await.unwrap();
+    assert!(server.objects.lock().unwrap().is_empty());
+    let _ = std::fs::remove_dir_all(staging_dir);
+}
+
+#[sqlx::test]
+async fn stored_exports_download_through_signed_links(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let root = std::env::temp_dir().join(format!("storage-test-{}", Uuid::new_v4()));
+    let local = LocalStorage::new(&root).with_signed_urls(
+        "http://localhost:5300/api/storage",
+        b"signing-key".to_vec(),
+        Duration::from_secs(60),
+    );
+    let environment = EnvironmentService::new(
+        pool.clone(),
+        services.ontology_service.clone(),
+        services.audit_service.clone(),
+    )
+    .with_storage(Arc::new(local.clone()));
+    let router = Router::new().nest("/api/storage", storage_routes().with_state(local));
+
+    let stored = environment.store_bundle(None, None).await.unwrap();
+    assert!(stored.key.starts_with("exports/environment-instance-"));
+    let path = stored
+        .download
+        .url
+        .strip_prefix("http://localhost:5300")
+        .unwrap()
+        .to_string();
+
+    let response = router
+        .clone()
+        .oneshot(Request::get(&path).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers()["content-disposition"]
+        .to_str()
+        .unwrap()
+        .starts_with("attachment; filename=\"environment-instance-"));
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(bytes.len() as u64, stored.size_bytes);
+    let bundle: EnvironmentBundle = serde_json::from_slice(&bytes).unwrap();
+    assert!(bundle.tenant_id.is_none());
+
+    // The link can't be bent to another object or content type
+    for tampered in [
+        path.replace("exports/", "uploads/"),
+        path.replace("application%2Fjson", "text%2Fhtml"),
+    ] {
+        let response = router
+            .clone()
+            .oneshot(Request::get(&tampered).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN, "{}", tampered);
+    }
+    let _ = std::fs::remove_dir_all(root);
+}
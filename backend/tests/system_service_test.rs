@@ -11,6 +11,10 @@ async fn test_system_metrics_and_reporting(pool: PgPool) {
     let metrics = services.system_service.get_metrics();
     assert!(!metrics.hostname.is_empty());
 
+    // A freshly migrated database matches this build
+    let info = services.system_service.info().await;
+    assert!(!info.schema.unwrap().has_drift());
+
     // 2. Generate Report
     let report_type = "security_summary";
     let report = services
@@ -0,0 +1,680 @@
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use template_repo_backend::config::TestModeConfig;
+use template_repo_backend::features::auth::models::{LoginUser, RegisterUser};
+use template_repo_backend::features::rebac::models::AssignScopedRoleInput;
+use template_repo_backend::features::test_mode::fixtures::{parse_fixture, FixtureFormat};
+use template_repo_backend::features::test_mode::models::{
+    CreateSnapshotInput, GenerateDataInput, SetClockInput,
+};
+use template_repo_backend::features::test_mode::service::TestModeError;
+use template_repo_backend::features::test_mode::TestModeService;
+use uuid::Uuid;
+
+mod common;
+
+const FIXTURE: &str = r#"
+name: site-access
+classes:
+  - key: site
+    name: FixtureSite
+  - key: pump
+    name: FixturePump
+    parent: site
+roles:
+  - key: operator
+    name: Fixture Operator
+    level: 10
+    permissions: [read]
+users:
+  - key: olive
+    username: fixture_olive
+    password: password123
+    roles: [operator]
+entities:
+  - key: north
+    class: site
+    display_name: North Site
+  - key: north_pump
+    class: pump
+    display_name: Pump 1
+    parent: north
+    attributes:
+      capacity: 40
+relationships:
+  - source: olive
+    target: north
+    type: belongs_to
+"#;
+
+fn fixtures_enabled(pool: &PgPool, services: &common::TestServices) -> TestModeService {
+    TestModeService::new(
+        pool.clone(),
+        services.audit_service.clone(),
+        TestModeConfig {
+            fixtures_enabled: true,
+            ..Default::default()
+        },
+    )
+}
+
+async fn is_test_data(pool: &PgPool, entity_id: Uuid) -> bool {
+    sqlx::query_scalar("SELECT is_test_data($1)")
+        .bind(entity_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+#[sqlx::test]
+async fn test_fixture_loading_and_cleanup(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let test_mode = &fixtures_enabled(&pool, &services);
+    let user_id = services
+        .auth_service
+        .register(RegisterUser {
+            username: "fixture_loader".to_string(),
+            email: "fixture_loader@example.com".to_string(),
+            password: "password123".to_string(),
+        })
+        .await
+        .unwrap()
+        .user_id;
+    let fixture = parse_fixture(FIXTURE, FixtureFormat::Yaml).unwrap();
+
+    // Off unless the environment opts in
+    assert!(matches!(
+        services
+            .test_mode_service
+            .load_fixture(user_id, fixture.clone())
+            .await,
+        Err(TestModeError::Forbidden(_))
+    ));
+
+    // Fixtures belong to a test mode session
+    assert!(matches!(
+        test_mode.load_fixture(user_id, fixture.clone()).await,
+        Err(TestModeError::NotActive)
+    ));
+    let session = test_mode
+        .activate(
+            user_id,
+            Some("integration".to_string()),
+            None,
+            "Fixture test".to_string(),
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+    let loaded = test_mode
+        .load_fixture(user_id, fixture.clone())
+        .await
+        .unwrap();
+    assert_eq!(loaded.fixture.name, "site-access");
+    assert_eq!(loaded.fixture.session_id, session.id);
+    assert_eq!(loaded.fixture.class_ids.len(), 2);
+    assert_eq!(loaded.fixture.entity_ids.len(), 4);
+
+    let pump = loaded.entities["north_pump"];
+    let (parent, class_id, capacity): (Option<Uuid>, Uuid, serde_json::Value) = sqlx::query_as(
+        "SELECT parent_entity_id, class_id, attributes->'capacity' FROM entities WHERE id = $1",
+    )
+    .bind(pump)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(parent, Some(loaded.entities["north"]));
+    assert_eq!(class_id, loaded.classes["pump"]);
+    assert_eq!(capacity, serde_json::json!(40));
+
+    // The fixture user can log in with its role
+    let olive = services
+        .auth_service
+        .login(
+            LoginUser {
+                identifier: "fixture_olive".to_string(),
+                password: "password123".to_string(),
+                remember_me: None,
+            },
+            None,
+            None,
+        )
+        .await;
+    assert!(olive.is_ok());
+    let roles: Vec<String> = sqlx::query_scalar(
+        r#"
+        SELECT e.display_name FROM relationships r
+        JOIN relationship_types rt ON rt.id = r.relationship_type_id
+        JOIN entities e ON e.id = r.target_entity_id
+        WHERE rt.name = 'has_role' AND r.source_entity_id = $1
+        "#,
+    )
+    .bind(loaded.users["olive"])
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert!(roles.contains(&"Fixture Operator".to_string()));
+
+    // Everything is tagged as test data of the session
+    for id in &loaded.fixture.entity_ids {
+        assert!(is_test_data(&pool, *id).await);
+    }
+    let marked: i32 =
+        sqlx::query_scalar("SELECT entities_marked FROM test_mode_sessions WHERE id = $1")
+            .bind(session.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(marked, 4);
+
+    // Loading is all or nothing: the class names are taken now
+    let classes_before: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM classes")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert!(matches!(
+        test_mode.load_fixture(user_id, fixture).await,
+        Err(TestModeError::InvalidFixture(_))
+    ));
+    let classes_after: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM classes")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(classes_before, classes_after);
+
+    let listed = test_mode.list_fixtures(user_id).await.unwrap();
+    assert_eq!(listed.len(), 1);
+
+    test_mode
+        .remove_fixture(user_id, loaded.fixture.id)
+        .await
+        .unwrap();
+    let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM entities WHERE id = ANY($1)")
+        .bind(&loaded.fixture.entity_ids)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(remaining, 0);
+    let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM classes WHERE id = ANY($1)")
+        .bind(&loaded.fixture.class_ids)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(remaining, 0);
+    assert!(test_mode.list_fixtures(user_id).await.unwrap().is_empty());
+    assert!(matches!(
+        test_mode.remove_fixture(user_id, loaded.fixture.id).await,
+        Err(TestModeError::FixtureNotFound)
+    ));
+}
+
+#[sqlx::test]
+async fn test_fixture_rejects_unknown_references(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let test_mode = &fixtures_enabled(&pool, &services);
+    let user_id = services
+        .auth_service
+        .register(RegisterUser {
+            username: "fixture_refs".to_string(),
+            email: "fixture_refs@example.com".to_string(),
+            password: "password123".to_string(),
+        })
+        .await
+        .unwrap()
+        .user_id;
+    test_mode
+        .activate(
+            user_id,
+            None,
+            None,
+            "Fixture test".to_string(),
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+    // Existing roles and entities are out of reach, so a fixture can't grant
+    // itself a role or attach itself to real data
+    let (role_id, role_name): (Uuid, String) = sqlx::query_as(
+        "SELECT e.id, e.display_name FROM entities e JOIN classes c ON c.id = e.class_id WHERE c.name = 'Role' LIMIT 1",
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    for body in [
+        r#"{"entities": [{"class": "NoSuchClass", "display_name": "X"}]}"#.to_string(),
+        r#"{"entities": [{"class": "Role", "display_name": "X", "parent": "missing"}]}"#.to_string(),
+        r#"{"entities": [{"class": "Role", "display_name": "X"}, {"class": "Role", "display_name": "X"}]}"#.to_string(),
+        format!(
+            r#"{{"users": [{{"username": "fixture_mallory", "password": "password123", "roles": ["{}"]}}]}}"#,
+            role_name
+        ),
+        format!(
+            r#"{{"users": [{{"username": "fixture_mallory", "password": "password123"}}], "relationships": [{{"source": "fixture_mallory", "target": "{}", "type": "has_role"}}]}}"#,
+            role_id
+        ),
+        format!(
+            r#"{{"entities": [{{"class": "Role", "display_name": "X", "parent": "{}"}}]}}"#,
+            user_id
+        ),
+    ] {
+        let fixture = parse_fixture(&body, FixtureFormat::Json).unwrap();
+        let result = test_mode.load_fixture(user_id, fixture).await;
+        assert!(
+            matches!(result, Err(TestModeError::InvalidFixture(_))),
+            "{}: {:?}",
+            body,
+            result
+        );
+    }
+}
+
+#[sqlx::test]
+async fn test_snapshot_and_restore(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let user_id = services
+        .auth_service
+        .register(RegisterUser {
+            username: "snapshot_admin".to_string(),
+            email: "snapshot_admin@example.com".to_string(),
+            password: "password123".to_string(),
+        })
+        .await
+        .unwrap()
+        .user_id;
+    let input = |name: &str| CreateSnapshotInput {
+        name: name.to_string(),
+        description: None,
+        tags: vec!["qa".to_string()],
+    };
+
+    // Off unless the environment opts in
+    assert!(matches!(
+        services
+            .test_mode_service
+            .create_snapshot(user_id, input("baseline"))
+            .await,
+        Err(TestModeError::Forbidden(_))
+    ));
+    let snapshots = TestModeService::new(
+        pool.clone(),
+        services.audit_service.clone(),
+        TestModeConfig {
+            snapshots_enabled: true,
+            ..Default::default()
+        },
+    );
+
+    let entity_count = || async {
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM entities")
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+    };
+    let before = entity_count().await;
+    let baseline = snapshots
+        .create_snapshot(user_id, input("baseline"))
+        .await
+        .unwrap();
+    assert!(baseline.row_count >= before);
+    assert!(matches!(
+        snapshots.create_snapshot(user_id, input("baseline")).await,
+        Err(TestModeError::SnapshotExists)
+    ));
+
+    // Change things: a new user, and the existing one renamed
+    services
+        .auth_service
+        .register(RegisterUser {
+            username: "snapshot_intruder".to_string(),
+            email: "snapshot_intruder@example.com".to_string(),
+            password: "password123".to_string(),
+        })
+        .await
+        .unwrap();
+    sqlx::query("UPDATE entities SET display_name = 'renamed' WHERE id = $1")
+        .bind(user_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert!(entity_count().await > before);
+
+    let restored = snapshots
+        .restore_snapshot(user_id, "baseline")
+        .await
+        .unwrap();
+    assert!(restored.skipped_tables.is_empty());
+    assert!(restored.snapshot.restored_at.is_some());
+
+    // The audit event for the restore itself is the only addition
+    let after: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM entities e JOIN classes c ON c.id = e.class_id WHERE c.name <> 'SecurityEvent'",
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    let before_without_audit: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM jsonb_array_elements((SELECT rows FROM test_snapshot_tables WHERE table_name = 'entities')) r
+         JOIN classes c ON c.id = (r->>'class_id')::uuid WHERE c.name <> 'SecurityEvent'",
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(after, before_without_audit);
+    let name: String = sqlx::query_scalar("SELECT display_name FROM entities WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(name, "snapshot_admin");
+    let intruders: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM unified_users WHERE username = 'snapshot_intruder'",
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(intruders, 0);
+
+    // The restored database still works
+    services
+        .auth_service
+        .register(RegisterUser {
+            username: "snapshot_after".to_string(),
+            email: "snapshot_after@example.com".to_string(),
+            password: "password123".to_string(),
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(snapshots.list_snapshots(Some("qa")).await.unwrap().len(), 1);
+    assert!(snapshots
+        .list_snapshots(Some("prod"))
+        .await
+        .unwrap()
+        .is_empty());
+    snapshots
+        .delete_snapshot(user_id, "baseline")
+        .await
+        .unwrap();
+    assert!(matches!(
+        snapshots.restore_snapshot(user_id, "baseline").await,
+        Err(TestModeError::SnapshotNotFound)
+    ));
+}
+
+#[sqlx::test]
+async fn test_synthetic_data_generation(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let test_mode = &fixtures_enabled(&pool, &services);
+    let user_id = services
+        .auth_service
+        .register(RegisterUser {
+            username: "generator".to_string(),
+            email: "generator@example.com".to_string(),
+            password: "password123".to_string(),
+        })
+        .await
+        .unwrap()
+        .user_id;
+    test_mode
+        .activate(
+            user_id,
+            Some("load".to_string()),
+            None,
+            "Generator test".to_string(),
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+    let input = GenerateDataInput {
+        entities: 120,
+        users: 12,
+        relationships: 200,
+        class_distribution: BTreeMap::from([("Resource".to_string(), 2), ("Task".to_string(), 1)]),
+        fanout: 5,
+        user_password: Some("password123".to_string()),
+        seed: Some(7),
+        ..Default::default()
+    };
+    assert!(matches!(
+        test_mode
+            .generate_data(
+                user_id,
+                GenerateDataInput {
+                    entities: 2,
+                    relationships: 5,
+                    ..Default::default()
+                }
+            )
+            .await,
+        Err(TestModeError::InvalidInput(_))
+    ));
+
+    let generated = test_mode.generate_data(user_id, input).await.unwrap();
+    assert_eq!(generated.entities_by_class["Resource"], 80);
+    assert_eq!(generated.entities_by_class["Task"], 40);
+    assert_eq!(generated.users, 12);
+    assert_eq!(generated.relationships, 200);
+    assert_eq!(generated.depth, 4);
+    // The entities, the users and the run's own role
+    assert_eq!(generated.fixture.entity_ids.len(), 133);
+    assert_eq!(generated.fixture.relationship_ids.len(), 212);
+    let role_targets: Vec<Uuid> = sqlx::query_scalar(
+        "SELECT DISTINCT target_entity_id FROM relationships WHERE id = ANY($1) AND metadata ? 'scope_entity_id'",
+    )
+    .bind(&generated.fixture.relationship_ids)
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(role_targets, vec![generated.fixture.entity_ids[132]]);
+    assert!(is_test_data(&pool, generated.fixture.entity_ids[0]).await);
+    assert!(is_test_data(&pool, generated.fixture.entity_ids[131]).await);
+
+    // Users read down the subtree their viewer role is scoped to, and not
+    // above it. With 12 users over 120 entities, the second user's scope is
+    // entity 10, whose children are 51 to 55.
+    let scope = generated.fixture.entity_ids[10];
+    let member: Uuid = sqlx::query_scalar(
+        "SELECT source_entity_id FROM relationships WHERE id = ANY($1) AND metadata->>'scope_entity_id' = $2::text",
+    )
+    .bind(&generated.fixture.relationship_ids)
+    .bind(scope)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    let child = generated.fixture.entity_ids[51];
+    let root = generated.fixture.entity_ids[0];
+    assert!(
+        services
+            .rebac_service
+            .check_permission(member, child, "read", None, None)
+            .await
+            .unwrap()
+            .has_permission
+    );
+    assert!(
+        !services
+            .rebac_service
+            .check_permission(member, root, "read", None, None)
+            .await
+            .unwrap()
+            .has_permission
+    );
+
+    // Removing the fixture removes the generated data
+    test_mode
+        .remove_fixture(user_id, generated.fixture.id)
+        .await
+        .unwrap();
+    let left: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM entities WHERE id = ANY($1)")
+        .bind(&generated.fixture.entity_ids)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(left, 0);
+}
+
+#[sqlx::test]
+async fn test_mock_clock_drives_temporal_grants(pool: PgPool) {
+    let services = common::setup_services(pool.clone()).await;
+    let rebac = &services.rebac_service;
+    let user_id = services
+        .auth_service
+        .register(RegisterUser {
+            username: "time_traveller".to_string(),
+            email: "time_traveller@example.com".to_string(),
+            password: "password123".to_string(),
+        })
+        .await
+        .unwrap()
+        .user_id;
+    let resource: Uuid = sqlx::query_scalar(
+        "INSERT INTO entities (class_id, display_name) SELECT id, 'Clock Resource' FROM classes WHERE name = 'Resource' LIMIT 1 RETURNING id",
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+
+    // A grant for tomorrow only
+    let now = chrono::Utc::now();
+    rebac
+        .assign_scoped_role(
+            AssignScopedRoleInput {
+                user_id,
+                role_name: "viewer".to_string(),
+                scope_entity_id: Some(resource),
+                valid_from: Some(now + chrono::Duration::days(1)),
+                valid_until: Some(now + chrono::Duration::days(2)),
+                schedule_cron: None,
+                is_deny: None,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    let can_read = || async {
+        rebac
+            .check_permission(user_id, resource, "read", None, None)
+            .await
+            .unwrap()
+            .has_permission
+    };
+    assert!(!can_read().await);
+
+    // Off unless the environment opts in, and only inside a session
+    let at_tomorrow = SetClockInput {
+        at: Some(now + chrono::Duration::hours(30)),
+        advance_seconds: None,
+    };
+    assert!(matches!(
+        services
+            .test_mode_service
+            .set_clock(user_id, at_tomorrow.clone())
+            .await,
+        Err(TestModeError::Forbidden(_))
+    ));
+    let test_mode = TestModeService::new(
+        pool.clone(),
+        services.audit_service.clone(),
+        TestModeConfig {
+            mock_clock_enabled: true,
+            ..Default::default()
+        },
+    );
+    assert!(matches!(
+        test_mode.set_clock(user_id, at_tomorrow.clone()).await,
+        Err(TestModeError::NotActive)
+    ));
+    test_mode
+        .activate(
+            user_id,
+            Some("temporal".to_string()),
+            None,
+            "Clock test".to_string(),
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+    let session = test_mode.set_clock(user_id, at_tomorrow).await.unwrap();
+    assert_eq!(
+        session.clock_override.map(|c| c.timestamp()),
+        Some((now + chrono::Duration::hours(30)).timestamp())
+    );
+    assert_eq!(
+        rebac.clock_override(user_id).await.unwrap(),
+        session.clock_override
+    );
+    assert!(can_read().await);
+    let accessible = rebac
+        .get_accessible_entities(user_id, "read")
+        .await
+        .unwrap();
+    assert!(accessible.iter().any(|e| e.entity_id == resource));
+    let roles = rebac
+        .get_active_user_roles_batch(vec![user_id])
+        .await
+        .unwrap();
+    assert!(roles[&user_id].contains(&"viewer".to_string()));
+
+    // A day later the grant has lapsed
+    test_mode
+        .set_clock(
+            user_id,
+            SetClockInput {
+                at: None,
+                advance_seconds: Some(24 * 3600),
+            },
+        )
+        .await
+        .unwrap();
+    assert!(!can_read().await);
+
+    assert!(matches!(
+        test_mode
+            .set_clock(
+                user_id,
+                SetClockInput {
+                    at: None,
+                    advance_seconds: None,
+                },
+            )
+            .await,
+        Err(TestModeError::InvalidInput(_))
+    ));
+
+    // Back to real time, where the grant hasn't started
+    let session = test_mode.reset_clock(user_id).await.unwrap();
+    assert!(session.clock_override.is_none());
+    assert_eq!(rebac.clock_override(user_id).await.unwrap(), None);
+    assert!(!rebac
+        .get_accessible_entities(user_id, "read")
+        .await
+        .unwrap()
+        .iter()
+        .any(|e| e.entity_id == resource));
+
+    // The clock ends with the session
+    test_mode
+        .set_clock(
+            user_id,
+            SetClockInput {
+                at: Some(now + chrono::Duration::hours(30)),
+                advance_seconds: None,
+            },
+        )
+        .await
+        .unwrap();
+    test_mode.deactivate(user_id).await.unwrap();
+    assert_eq!(rebac.clock_override(user_id).await.unwrap(), None);
+}
@@ -0,0 +1,279 @@
+use axum::{
+    body::Body, extract::Request, http::StatusCode, middleware::Next, response::Response, Router,
+};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use template_repo_backend::config::UploadsConfig;
+use template_repo_backend::features::auth::jwt::Claims;
+use template_repo_backend::features::auth::models::RegisterUser;
+use template_repo_backend::features::uploads::routes::upload_routes;
+use template_repo_backend::features::uploads::UploadService;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+mod common;
+
+const BOUNDARY: &str = "upload-test-boundary";
+
+struct TestApp {
+    router: Router,
+    storage_dir: std::path::PathBuf,
+}
+
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.storage_dir);
+    }
+}
+
+/// The uploads router as mounted in main.rs, with a stand-in for auth_middleware
+async fn app(pool: PgPool, username: &str) -> TestApp {
+    let services = common::setup_services(pool.clone()).await;
+    let user_id = services
+        .auth_service
+        .register(RegisterUser {
+            username: username.to_string(),
+            email: format!("{}@example.com", username),
+            password: "password123".to_string(),
+        })
+        .await
+        .unwrap()
+        .user_id;
+
+    let storage_dir = std::env::temp_dir().join(format!("uploads-test-{}", Uuid::new_v4()));
+    let service = UploadService::new(
+        pool,
+        UploadsConfig {
+            storage_dir: storage_dir.to_string_lossy().to_string(),
+            max_file_bytes: 1024,
+            max_chunk_bytes: 8,
+            session_ttl_hours: 24,
+        },
+    );
+
+    let claims = Claims {
+        sub: user_id.to_string(),
+        username: username.to_string(),
+        email: format!("{}@example.com", username),
+        roles: vec![],
+        permissions: vec![],
+        jti: None,
+        exp: i64::MAX,
+        iat: 0,
+    };
+    let fake_auth = move |mut request: Request, next: Next| {
+        let claims = claims.clone();
+        async move {
+            request.extensions_mut().insert(claims);
+            Ok::<Response, StatusCode>(next.run(request).await)
+        }
+    };
+
+    TestApp {
+        router: Router::new().nest(
+            "/api/uploads",
+            upload_routes()
+                .with_state(service)
+                .layer(axum::middleware::from_fn(fake_auth)),
+        ),
+        storage_dir,
+    }
+}
+
+fn multipart(fields: &[(&str, &str)], file: &[u8]) -> Body {
+    let mut body = Vec::new();
+    for (name, value) in fields {
+        body.extend_from_slice(
+            format!(
+                "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"{name}\"\r\n\r\n{value}\r\n"
+            )
+            .as_bytes(),
+        );
+    }
+    body.extend_from_slice(
+        format!(
+            "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"notes.txt\"\r\nContent-Type: text/plain\r\n\r\n"
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(file);
+    body.extend_from_slice(format!("\r\n--{BOUNDARY}--\r\n").as_bytes());
+    Body::from(body)
+}
+
+async fn send(app: &TestApp, request: Request) -> (StatusCode, serde_json::Value) {
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or_default())
+}
+
+fn post_multipart(body: Body) -> Request {
+    Request::post("/api/uploads")
+        .header(
+            "content-type",
+            format!("multipart/form-data; boundary={BOUNDARY}"),
+        )
+        .body(body)
+        .unwrap()
+}
+
+fn chunk(id: &str, offset: usize, data: &'static [u8]) -> Request {
+    Request::patch(format!("/api/uploads/{}", id))
+        .header("upload-offset", offset.to_string())
+        .body(Body::from(data))
+        .unwrap()
+}
+
+fn sha256(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+#[sqlx::test]
+async fn test_multipart_upload_is_stored_and_verified(pool: PgPool) {
+    let app = app(pool, "upload_multipart").await;
+    let content = b"hello upload";
+
+    let (status, upload) = send(
+        &app,
+        post_multipart(multipart(
+            &[("purpose", "attachment"), ("sha256", &sha256(content))],
+            content,
+        )),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(upload["status"], "complete");
+    assert_eq!(upload["size_bytes"], content.len());
+    assert_eq!(upload["filename"], "notes.txt");
+    assert!(upload.get("storage_key").is_none());
+
+    let response = app
+        .router
+        .clone()
+        .oneshot(
+            Request::get(format!(
+                "/api/uploads/{}/content",
+                upload["id"].as_str().unwrap()
+            ))
+            .body(Body::empty())
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "text/plain");
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(&bytes[..], content);
+
+    // Wrong checksum, unknown purpose and oversized files are rejected
+    let (status, _) = send(
+        &app,
+        post_multipart(multipart(
+            &[("purpose", "avatar"), ("sha256", &sha256(b"other"))],
+            content,
+        )),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = send(
+        &app,
+        post_multipart(multipart(&[("purpose", "profile")], content)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send(
+        &app,
+        post_multipart(multipart(&[("purpose", "import")], &[b'x'; 2048])),
+    )
+    .await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[sqlx::test]
+async fn test_resumable_upload_in_chunks(pool: PgPool) {
+    let app = app(pool, "upload_resumable").await;
+    let content = b"0123456789abcdef";
+
+    let (status, session) = send(
+        &app,
+        Request::post("/api/uploads/sessions")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                serde_json::json!({
+                    "purpose": "import",
+                    "filename": "rows.csv",
+                    "content_type": "text/csv",
+                    "size_bytes": content.len(),
+                    "sha256": sha256(content),
+                })
+                .to_string(),
+            ))
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(session["status"], "pending");
+    let id = session["id"].as_str().unwrap().to_string();
+
+    let (status, upload) = send(&app, chunk(&id, 0, b"01234567")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(upload["received_bytes"], 8);
+
+    // A chunk at the wrong offset reports where to resume
+    let (status, body) = send(&app, chunk(&id, 4, b"4567")).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["code"], "uploads.offset_mismatch");
+    assert_eq!(body["details"]["offset"], 8);
+
+    // Chunks above max_chunk_bytes are refused without advancing the offset
+    let (status, _) = send(&app, chunk(&id, 8, b"89abcdef-too-long")).await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    let (_, upload) = send(
+        &app,
+        Request::get(format!("/api/uploads/{}", id))
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(upload["received_bytes"], 8);
+
+    let (status, upload) = send(&app, chunk(&id, 8, b"89abcdef")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(upload["status"], "complete");
+    assert_eq!(upload["sha256"], sha256(content));
+}
+
+#[sqlx::test]
+async fn test_resumable_checksum_mismatch_restarts_upload(pool: PgPool) {
+    let app = app(pool, "upload_mismatch").await;
+
+    let (_, session) = send(
+        &app,
+        Request::post("/api/uploads/sessions")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                serde_json::json!({
+                    "purpose": "attachment",
+                    "filename": "a.bin",
+                    "size_bytes": 4,
+                    "sha256": sha256(b"abcd"),
+                })
+                .to_string(),
+            ))
+            .unwrap(),
+    )
+    .await;
+    let id = session["id"].as_str().unwrap().to_string();
+
+    let (status, _) = send(&app, chunk(&id, 0, b"abce")).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (status, upload) = send(&app, chunk(&id, 0, b"abcd")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(upload["status"], "complete");
+}
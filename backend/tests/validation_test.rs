@@ -0,0 +1,115 @@
+use axum::{
+    body::{to_bytes, Body},
+    http::{header, Request, StatusCode},
+    Extension, Router,
+};
+use serde_json::json;
+use sqlx::PgPool;
+use std::sync::Arc;
+use template_repo_backend::features::auth::routes::public_auth_routes;
+use tower::ServiceExt;
+use tower_cookies::CookieManagerLayer;
+
+mod common;
+
+async fn app(pool: PgPool) -> Router {
+    let services = common::setup_services(pool).await;
+    Router::new()
+        .nest(
+            "/auth",
+            public_auth_routes().with_state(services.auth_service.clone()),
+        )
+        .layer(CookieManagerLayer::new())
+        .layer(Extension(Arc::new(common::create_test_config())))
+}
+
+async fn register(
+    app: &Router,
+    body: serde_json::Value,
+    accept_language: Option<&str>,
+) -> (StatusCode, axum::http::HeaderMap, serde_json::Value) {
+    let mut request =
+        Request::post("/auth/register").header(header::CONTENT_TYPE, "application/json");
+    if let Some(accept_language) = accept_language {
+        request = request.header(header::ACCEPT_LANGUAGE, accept_language);
+    }
+    let response = app
+        .clone()
+        .oneshot(request.body(Body::from(body.to_string())).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (
+        status,
+        headers,
+        serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null),
+    )
+}
+
+#[sqlx::test]
+async fn test_invalid_fields_are_reported_individually(pool: PgPool) {
+    let app = app(pool).await;
+    let invalid = json!({ "username": "ab", "email": "not-an-email", "password": "short" });
+
+    let (status, headers, body) = register(&app, invalid.clone(), None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(headers[header::CONTENT_LANGUAGE], "en");
+    assert_eq!(body["code"], "validation_failed");
+    assert_eq!(
+        body["details"]["fields"],
+        json!([
+            {
+                "field": "email",
+                "code": "email",
+                "message": "must be a valid email address",
+            },
+            {
+                "field": "password",
+                "code": "length",
+                "message": "length must be between 8 and 128",
+                "params": { "min": 8, "max": 128 },
+            },
+            {
+                "field": "username",
+                "code": "length",
+                "message": "length must be between 3 and 50",
+                "params": { "min": 3, "max": 50 },
+            },
+        ])
+    );
+    // The rejected values are not echoed back
+    assert!(!body.to_string().contains("short"), "{}", body);
+
+    let (status, headers, body) = register(&app, invalid, Some("nb-NO,nb;q=0.9,en;q=0.8")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(headers[header::CONTENT_LANGUAGE], "nb");
+    assert_eq!(
+        body["details"]["fields"][0]["message"],
+        "må være en gyldig e-postadresse"
+    );
+    assert_eq!(
+        body["error"],
+        "email: må være en gyldig e-postadresse; \
+         password: lengden må være mellom 8 og 128; \
+         username: lengden må være mellom 3 og 50"
+    );
+}
+
+#[sqlx::test]
+async fn test_valid_bodies_reach_the_handler(pool: PgPool) {
+    let app = app(pool).await;
+
+    let (status, _, _) = register(
+        &app,
+        json!({ "username": "valid_user", "email": "valid@example.com", "password": "Password123!" }),
+        Some("nb"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Bodies that don't parse are still rejected before validation
+    let (status, _, _) = register(&app, json!({ "username": "valid_user" }), None).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
@@ -3,9 +3,10 @@ import { Bell, Check, X } from 'lucide-react'
 import { getUserInfo } from '@/features/auth/lib/auth'
 
 interface Notification {
-  id: number
+  id: string
   message: string
-  read: number
+  category?: string | null
+  read?: boolean
   created_at: string
 }
 
@@ -22,12 +23,11 @@ export default function Notifications() {
       if (!user) return
 
       try {
-        const res = await fetch(`/api/auth/notifications?user_id=${user.id}`, { credentials: 'include' })
+        const res = await fetch('/api/notifications?limit=20', { credentials: 'include' })
         if (res.ok) {
           const json = await res.json()
-          const notifications = json.notifications || []
-          setItems(notifications)
-          setCount(notifications.filter((n: Notification) => n.read === 0).length)
+          setItems(json.notifications || [])
+          setCount(json.unread || 0)
         }
       } catch (e) {
         console.error('Error loading notifications', e)
@@ -35,7 +35,7 @@ export default function Notifications() {
 
       // Setup SSE
       if (!eventSourceRef.current) {
-        eventSourceRef.current = new EventSource('/api/auth/notifications/stream')
+        eventSourceRef.current = new EventSource('/api/notifications/stream')
         eventSourceRef.current.onmessage = (event) => {
           try {
             const newNotif = JSON.parse(event.data)
@@ -65,22 +65,18 @@ export default function Notifications() {
     }
   }, [])
 
-  async function markRead(id: number) {
-    const user = await getUserInfo()
-    if (!user) return
-    const res = await fetch(`/api/auth/notifications/${id}/read?user_id=${user.id}`, { method: 'POST', credentials: 'include' })
+  async function markRead(id: string) {
+    const res = await fetch(`/api/notifications/${id}/read`, { method: 'POST', credentials: 'include' })
     if (res.ok) {
-      setItems((it) => it.map((i) => (i.id === id ? { ...i, read: 1 } : i)))
+      setItems((it) => it.map((i) => (i.id === id ? { ...i, read: true } : i)))
       setCount((c) => Math.max(0, c - 1))
     }
   }
 
   async function markAllRead() {
-    const user = await getUserInfo()
-    if (!user) return
-    const res = await fetch('/api/auth/notifications/read-all', { method: 'POST', credentials: 'include' })
+    const res = await fetch('/api/notifications/read-all', { method: 'POST', credentials: 'include' })
     if (res.ok) {
-      setItems((it) => it.map((i) => ({ ...i, read: 1 })))
+      setItems((it) => it.map((i) => ({ ...i, read: true })))
       setCount(0)
     }
   }
@@ -145,18 +141,18 @@ export default function Notifications() {
               {items.map((n) => (
                 <div
                   key={n.id}
-                  className={`p-4 border-b border-slate-800/50 last:border-b-0 flex justify-between items-start group transition-all duration-200 ${n.read === 0 ? 'bg-primary/5 hover:bg-primary/10' : 'hover:bg-slate-800/50'}`}
+                  className={`p-4 border-b border-slate-800/50 last:border-b-0 flex justify-between items-start group transition-all duration-200 ${!n.read ? 'bg-primary/5 hover:bg-primary/10' : 'hover:bg-slate-800/50'}`}
                 >
                   <div className="flex-1 min-w-0 pr-2">
-                    <div className={`text-sm leading-snug mb-1.5 ${n.read === 0 ? 'text-slate-100 font-medium' : 'text-slate-400'}`}>
+                    <div className={`text-sm leading-snug mb-1.5 ${!n.read ? 'text-slate-100 font-medium' : 'text-slate-400'}`}>
                       {n.message}
                     </div>
                     <div className="text-[10px] text-slate-500 font-medium flex items-center gap-1">
-                      <div className={`w-1 h-1 rounded-full ${n.read === 0 ? 'bg-primary' : 'bg-slate-700'}`} />
+                      <div className={`w-1 h-1 rounded-full ${!n.read ? 'bg-primary' : 'bg-slate-700'}`} />
                       {new Date(n.created_at).toLocaleTimeString([], { hour: '2-digit', minute: '2-digit' })} • {new Date(n.created_at).toLocaleDateString()}
                     </div>
                   </div>
-                  {n.read === 0 && (
+                  {!n.read && (
                     <button
                       onClick={() => markRead(n.id)}
                       className="p-1.5 text-slate-500 hover:text-primary hover:bg-primary/10 rounded-lg transition-all"
@@ -48,7 +48,16 @@ export function FirefighterDialog({ open, onOpenChange, onActivated }: Firefight
                 parseInt(duration)
             );
 
-            if (result.success) {
+            if (result.success && result.pendingRequest) {
+                toast({
+                    title: "Approval Requested",
+                    description: "Your designated approvers have been notified. Elevated access starts once one of them approves.",
+                    variant: "default",
+                });
+                onOpenChange(false);
+                setPassword("");
+                setJustification("");
+            } else if (result.success) {
                 toast({
                     title: "Firefighter Mode Activated",
                     description: "You now have elevated permissions for the next " + duration + " minutes.",
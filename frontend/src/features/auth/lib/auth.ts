@@ -1,3 +1,4 @@
+import { readErrorMessage } from '@/lib/apiError';
 
 // Define types for our authentication state
 export interface UserRoleClaim {
@@ -100,7 +101,7 @@ export async function login(identifier: string, password: string, rememberMe: bo
     }
 
     if (!response.ok) {
-      const errorText = await response.text();
+      const errorText = await readErrorMessage(response);
       return { success: false, error: `Login failed (${response.status} ${response.statusText}): ${errorText}` };
     }
 
@@ -125,7 +126,7 @@ export async function register(username: string, email: string, password: string
     });
 
     if (!response.ok) {
-      const errorText = await response.text();
+      const errorText = await readErrorMessage(response);
       return { success: false, error: `Registration failed (${response.status} ${response.statusText}): ${errorText}` };
     }
 
@@ -157,7 +158,7 @@ export async function changePassword(email: string, currentPassword: string, new
     })
 
     if (!response.ok) {
-      const err = await response.text()
+      const err = await readErrorMessage(response)
       return { success: false, error: err || 'Change password failed' }
     }
 
@@ -188,7 +189,7 @@ export async function updateProfile(username: string): Promise<{ success: boolea
     })
 
     if (!response.ok) {
-      const err = await response.text()
+      const err = await readErrorMessage(response)
       return { success: false, error: err || 'Update profile failed' }
     }
 
@@ -275,7 +276,7 @@ export async function revokeSession(id: string): Promise<{ success: boolean; err
     });
 
     if (!response.ok) {
-      const err = await response.text();
+      const err = await readErrorMessage(response);
       return { success: false, error: err || 'Revoke session failed' };
     }
 
@@ -299,7 +300,7 @@ export interface AdminSessionResponse {
 export async function listAllSessions(): Promise<AdminSessionResponse[]> {
   try {
     const csrfToken = getCsrfToken();
-    const response = await fetch('/api/auth/sessions/all', {
+    const response = await fetch('/api/auth/sessions/all?limit=100', {
       method: 'GET',
       headers: {
         'X-CSRF-Token': csrfToken || '',
@@ -311,7 +312,8 @@ export async function listAllSessions(): Promise<AdminSessionResponse[]> {
       return [];
     }
 
-    return await response.json();
+    const page = await response.json();
+    return page.items;
   } catch (error) {
     console.error("Failed to list sessions", error);
     return [];
@@ -330,7 +332,7 @@ export async function revokeAdminSession(id: string): Promise<{ success: boolean
     });
 
     if (!response.ok) {
-      const err = await response.text();
+      const err = await readErrorMessage(response);
       return { success: false, error: err || 'Revoke session failed' };
     }
 
@@ -356,7 +358,7 @@ export async function requestPasswordReset(email: string): Promise<{ success: bo
     if (!response.ok) {
       // Even on error (like 404), we might want to be vague, but the backend currently returns 200 for user not found
       // If it's a real error (500), show it.
-      const errorText = await response.text();
+      const errorText = await readErrorMessage(response);
       return { success: false, error: `Request failed: ${errorText}` };
     }
 
@@ -378,7 +380,7 @@ export async function verifyResetToken(token: string): Promise<{ success: boolea
     });
 
     if (!response.ok) {
-      const errorText = await response.text();
+      const errorText = await readErrorMessage(response);
       return { success: false, valid: false, error: errorText };
     }
 
@@ -403,7 +405,7 @@ export async function resetPassword(token: string, newPassword: string): Promise
     });
 
     if (!response.ok) {
-      const errorText = await response.text();
+      const errorText = await readErrorMessage(response);
       return { success: false, error: errorText };
     }
 
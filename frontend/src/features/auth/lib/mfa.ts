@@ -1,5 +1,6 @@
 
 import { type AuthResponse } from './auth';
+import { readErrorMessage } from '@/lib/apiError';
 
 export interface MfaSetupResponse {
     secret: string;
@@ -36,7 +37,7 @@ export async function setupMfa(userId: string, email: string): Promise<MfaSetupR
         credentials: 'include',
     });
     if (!response.ok) {
-        const err = await response.text();
+        const err = await readErrorMessage(response);
         throw new Error(err || 'Failed to setup MFA');
     }
     return await response.json();
@@ -50,7 +51,7 @@ export async function verifyMfaSetup(userId: string, code: string): Promise<void
         credentials: 'include',
     });
     if (!response.ok) {
-        const err = await response.text();
+        const err = await readErrorMessage(response);
         throw new Error(err || 'Failed to verify MFA setup');
     }
 }
@@ -66,7 +67,7 @@ export async function verifyMfaLogin(userId: string, code: string, rememberMe: b
     });
 
     if (!response.ok) {
-        const err = await response.text();
+        const err = await readErrorMessage(response);
         throw new Error(err || 'MFA Verification failed');
     }
 
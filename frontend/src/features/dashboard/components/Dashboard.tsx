@@ -108,7 +108,7 @@ export default function Dashboard() {
         if (statsJson) setStats(statsJson)
         if (activityJson) setActivity(activityJson)
         if (metricsJson) setMetrics(metricsJson)
-        if (logsJson) setLogs(logsJson) // Logs might be empty array
+        if (logsJson) setLogs(logsJson.items) // Logs might be empty array
         if (aiJson) setAiStatus(aiJson)
 
       } catch (err: any) {
@@ -12,14 +12,40 @@ export interface FirefighterSession {
     deactivation_reason: string | null;
     ip_address: string | null;
     user_agent: string | null;
+    expiry_warned_at?: string | null;
+    scope?: FirefighterScope | null;
+}
+
+/** Limits a session to entity subtrees and/or classes; omit for full elevation */
+export interface FirefighterScope {
+    entity_ids?: string[];
+    class_ids?: string[];
+    exclude_entity_ids?: string[];
+}
+
+export interface FirefighterRequest {
+    id: string;
+    user_id: string;
+    justification: string;
+    duration_minutes: number;
+    approver_ids: string[];
+    status: 'PENDING' | 'APPROVED' | 'REJECTED' | 'EXPIRED';
+    decided_by: string | null;
+    decided_at: string | null;
+    decision_reason: string | null;
+    session_id: string | null;
+    scope?: FirefighterScope | null;
+    requested_at: string;
+    expires_at: string;
 }
 
 export interface FirefighterStatus {
     is_active: boolean;
     session: FirefighterSession | null;
+    pending_request?: FirefighterRequest | null;
 }
 
-export async function requestElevation(password: string, justification: string, durationMinutes?: number): Promise<{ success: boolean; session?: FirefighterSession; error?: string }> {
+export async function requestElevation(password: string, justification: string, durationMinutes?: number, scope?: FirefighterScope): Promise<{ success: boolean; session?: FirefighterSession; pendingRequest?: FirefighterRequest; error?: string }> {
     try {
         const csrfToken = getCsrfToken();
         const response = await fetch('/api/firefighter/request', {
@@ -29,7 +55,7 @@ export async function requestElevation(password: string, justification: string,
                 'X-CSRF-Token': csrfToken || '',
             },
             credentials: 'include',
-            body: JSON.stringify({ password, justification, duration_minutes: durationMinutes }),
+            body: JSON.stringify({ password, justification, duration_minutes: durationMinutes, scope }),
         });
 
         if (!response.ok) {
@@ -37,6 +63,12 @@ export async function requestElevation(password: string, justification: string,
             return { success: false, error: err.error || 'Elevation request failed' };
         }
 
+        // 202: the request waits for a second approver
+        if (response.status === 202) {
+            const pendingRequest = await response.json();
+            return { success: true, pendingRequest };
+        }
+
         const session = await response.json();
         return { success: true, session };
     } catch (error: any) {
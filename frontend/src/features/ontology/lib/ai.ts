@@ -1,3 +1,5 @@
+import { getCsrfToken } from '@/features/auth/lib/auth';
+
 export interface GenerateClassDescriptionRequest {
     name: string;
     properties?: string[];
@@ -22,3 +24,66 @@ export async function generateClassDescription(
     const data: GenerateClassDescriptionResponse = await res.json();
     return data.description;
 }
+
+export interface GenerateTextRequest {
+    prompt: string;
+    temperature?: number;
+    max_tokens?: number;
+}
+
+/**
+ * Stream a generation from `/api/ai/generate/stream`, calling `onText` with
+ * each piece as it arrives. Resolves with the full text; abort `signal` to
+ * cancel the generation on the server too.
+ */
+export async function streamGeneratedText(
+    request: GenerateTextRequest,
+    onText: (piece: string) => void,
+    signal?: AbortSignal
+): Promise<string> {
+    const res = await fetch('/api/ai/generate/stream', {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+            'X-CSRF-Token': getCsrfToken() || '',
+        },
+        credentials: 'include',
+        body: JSON.stringify(request),
+        signal,
+    });
+
+    if (!res.ok || !res.body) throw new Error('Failed to generate text');
+
+    const reader = res.body.pipeThrough(new TextDecoderStream()).getReader();
+    let buffer = '';
+    let text = '';
+    for (;;) {
+        const { value, done } = await reader.read();
+        if (done) throw new Error('Generation ended unexpectedly');
+        buffer += value;
+
+        // Events are separated by a blank line
+        let end: number;
+        while ((end = buffer.indexOf('\n\n')) >= 0) {
+            const raw = buffer.slice(0, end);
+            buffer = buffer.slice(end + 2);
+
+            let event = 'message';
+            let data = '';
+            for (const line of raw.split('\n')) {
+                if (line.startsWith('event:')) event = line.slice(6).trim();
+                else if (line.startsWith('data:')) data += line.slice(5).trim();
+            }
+
+            if (event === 'delta') {
+                const piece: string = JSON.parse(data).text;
+                text += piece;
+                onText(piece);
+            } else if (event === 'done') {
+                return text;
+            } else if (event === 'error') {
+                throw new Error('Failed to generate text');
+            }
+        }
+    }
+}
@@ -37,6 +37,22 @@ export interface UpdatePermissionTypeInput {
     level?: number;
 }
 
+// Per-locale display names, keyed by language tag ("nb", "nb-NO")
+export interface Label {
+    display_name?: string;
+    description?: string;
+}
+
+// Label resolved from the request's Accept-Language
+export interface LocalizedLabel {
+    locale: string | null;
+    display_name: string;
+    description?: string;
+}
+
+// What deleting an entity of a class does to its children
+export type DeletePolicy = 'cascade' | 'detach' | 'block';
+
 // Ontology Classes
 export interface Class {
     id: string;
@@ -47,6 +63,9 @@ export interface Class {
     tenant_id?: string;
     is_abstract: boolean;
     attributes: Record<string, any>; // Simplified view of properties for explorer
+    delete_policy?: DeletePolicy;
+    labels?: Record<string, Label>;
+    localized?: LocalizedLabel;
     created_at: string;
 }
 
@@ -62,6 +81,7 @@ export interface UpdateClassInput {
     description?: string;
     parent_class_id?: string;
     is_abstract?: boolean;
+    delete_policy?: DeletePolicy;
 }
 
 export interface Property {
@@ -74,6 +94,8 @@ export interface Property {
     is_unique: boolean;
     version_id: string;
     validation_rules: any;
+    labels?: Record<string, Label>;
+    localized?: LocalizedLabel;
 }
 
 export interface CreatePropertyInput {
@@ -329,6 +351,8 @@ export interface Entity {
     approval_status: ApprovalStatus;
     approved_by?: string;
     approved_at?: string;
+    labels?: Record<string, Label>;
+    localized?: LocalizedLabel;
     created_at: string;
     updated_at: string;
 }
@@ -380,6 +404,39 @@ export async function deleteEntity(id: string): Promise<void> {
     if (!res.ok) throw new Error('Failed to delete entity');
 }
 
+export interface AffectedEntity {
+    id: string;
+    class_id: string;
+    display_name: string;
+}
+
+export interface DeletePlan {
+    entity_id: string;
+    policy: DeletePolicy;
+    deleted_entities: AffectedEntity[];
+    detached_entities: AffectedEntity[];
+    removed_relationships: Relationship[];
+    blocking_entities: AffectedEntity[];
+}
+
+// What deleting the entity would remove, without removing it
+export async function previewEntityDelete(id: string): Promise<DeletePlan> {
+    const res = await fetch(`/api/ontology/entities/${id}?dry_run=true`, {
+        method: 'DELETE'
+    });
+    if (!res.ok) throw new Error('Failed to preview entity delete');
+    return res.json();
+}
+
+// Undo a delete, with the descendants and relationships it removed
+export async function restoreEntity(id: string): Promise<Entity> {
+    const res = await fetch(`/api/ontology/entities/${id}/restore`, {
+        method: 'POST'
+    });
+    if (!res.ok) throw new Error('Failed to restore entity');
+    return res.json();
+}
+
 export async function approveEntity(id: string): Promise<Entity> {
     const res = await fetch(`/api/ontology/entities/${id}/approve`, {
         method: 'POST'
@@ -101,7 +101,7 @@ async function fetchWithAuth(url: string, options: RequestInit = {}) {
 
     if (!response.ok) {
         const error = await response.json().catch(() => ({ error: 'Unknown error' }));
-        throw new Error(error.details || error.error || 'Request failed');
+        throw new Error(error.error || 'Request failed');
     }
 
     return response.json();
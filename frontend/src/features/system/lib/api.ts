@@ -18,9 +18,9 @@ export interface GeneratedReport {
 }
 
 export async function fetchSystemLogs(): Promise<LogEntry[]> {
-    const res = await fetch('/api/system/logs');
+    const res = await fetch('/api/system/logs?limit=200');
     if (!res.ok) throw new Error('Failed to fetch logs');
-    const logs = await res.json();
+    const { items: logs } = await res.json();
 
     // Map backend AuditLog to frontend LogEntry
     return logs.map((log: any) => ({
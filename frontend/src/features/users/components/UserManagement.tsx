@@ -1,5 +1,6 @@
 import React, { useEffect, useState } from 'react'
 import { getPasswordStrength } from '@/lib/password'
+import { readErrorMessage } from '@/lib/apiError'
 import { getCsrfToken } from '@/features/auth/lib/auth'
 import { Button } from '@/components/ui/button'
 import { Input } from '@/components/ui/input'
@@ -115,7 +116,7 @@ export default function UserManagement() {
                 credentials: 'include',
             })
             if (!res.ok) {
-                const text = await res.text()
+                const text = await readErrorMessage(res)
                 throw new Error(text || 'Failed to delete user')
             }
             setSuccessMessage('User deleted successfully')
@@ -141,7 +142,7 @@ export default function UserManagement() {
             })
 
             if (!res.ok) {
-                const text = await res.text()
+                const text = await readErrorMessage(res)
                 throw new Error(text || 'Failed to create user')
             }
 
@@ -229,7 +230,7 @@ export default function UserManagement() {
                 credentials: 'include',
             })
             if (!res.ok) {
-                const text = await res.text()
+                const text = await readErrorMessage(res)
                 throw new Error(text || 'Failed to update user')
             }
 
@@ -0,0 +1,30 @@
+/**
+ * API Error Envelope
+ *
+ * Every error response from the API is JSON of this shape. Branch on `code`;
+ * `error` is for display and its wording may change.
+ */
+
+export interface ApiErrorBody {
+  error: string;
+  code: string;
+  details?: Record<string, unknown>;
+  request_id?: string;
+}
+
+/**
+ * Display message of an error response, falling back to the raw body for
+ * responses that didn't come from the API (e.g. a proxy error page)
+ */
+export async function readErrorMessage(response: Response): Promise<string> {
+  const text = await response.text();
+  try {
+    const body = JSON.parse(text) as Partial<ApiErrorBody>;
+    if (typeof body.error === 'string') {
+      return body.error;
+    }
+  } catch {
+    // Not JSON
+  }
+  return text;
+}